@@ -0,0 +1,133 @@
+-- Profiles
+-- Lets several people share one install with separate libraries, watch/reading
+-- progress, notifications and tags. Downloads and cached media stay shared.
+--
+-- SQLite can't change UNIQUE constraints in place, so library, watch_history,
+-- reading_history and library_tags are recreated with profile-scoped keys.
+-- Existing rows are backfilled into the default profile (id 1).
+
+CREATE TABLE IF NOT EXISTS profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO profiles (id, name) VALUES (1, 'Default');
+
+-- Dropping library/library_tags cascades into the assignment table,
+-- so keep a copy and restore it once both tables are rebuilt
+CREATE TABLE library_tag_assignments_backup AS
+SELECT id, library_entry_id, tag_id, created_at FROM library_tag_assignments;
+
+-- Library
+CREATE TABLE library_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    profile_id INTEGER NOT NULL DEFAULT 1,
+    media_id TEXT NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('watching', 'completed', 'on_hold', 'dropped', 'plan_to_watch', 'reading', 'plan_to_read')) DEFAULT 'plan_to_watch',
+    favorite BOOLEAN NOT NULL DEFAULT 0,
+    score REAL,
+    notes TEXT,
+    added_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    auto_download INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (media_id) REFERENCES media(id) ON DELETE CASCADE,
+    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+    UNIQUE(profile_id, media_id)
+);
+
+INSERT INTO library_new (id, profile_id, media_id, status, favorite, score, notes, added_at, updated_at, auto_download)
+SELECT id, 1, media_id, status, favorite, score, notes, added_at, updated_at, auto_download
+FROM library;
+
+DROP TABLE library;
+ALTER TABLE library_new RENAME TO library;
+
+CREATE INDEX IF NOT EXISTS idx_library_status ON library(profile_id, status, updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_library_favorite ON library(profile_id, favorite, updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_library_auto_download ON library(auto_download) WHERE auto_download = 1;
+
+-- Watch history
+CREATE TABLE watch_history_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    profile_id INTEGER NOT NULL DEFAULT 1,
+    media_id TEXT NOT NULL,
+    episode_id TEXT NOT NULL,
+    episode_number INTEGER NOT NULL,
+    progress_seconds REAL NOT NULL DEFAULT 0,
+    duration REAL,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    last_watched TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (media_id) REFERENCES media(id) ON DELETE CASCADE,
+    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+    UNIQUE(profile_id, media_id, episode_id)
+);
+
+INSERT INTO watch_history_new (id, profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at)
+SELECT id, 1, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
+FROM watch_history;
+
+DROP TABLE watch_history;
+ALTER TABLE watch_history_new RENAME TO watch_history;
+
+CREATE INDEX IF NOT EXISTS idx_watch_history_media ON watch_history(profile_id, media_id);
+CREATE INDEX IF NOT EXISTS idx_watch_history_last_watched ON watch_history(profile_id, last_watched DESC);
+CREATE INDEX IF NOT EXISTS idx_watch_history_episode ON watch_history(episode_id);
+
+-- Reading history
+CREATE TABLE reading_history_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    profile_id INTEGER NOT NULL DEFAULT 1,
+    media_id TEXT NOT NULL,
+    chapter_id TEXT NOT NULL,
+    chapter_number REAL NOT NULL,
+    current_page INTEGER NOT NULL DEFAULT 1,
+    total_pages INTEGER,
+    completed BOOLEAN NOT NULL DEFAULT 0,
+    last_read TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+    UNIQUE(profile_id, media_id, chapter_id)
+);
+
+INSERT INTO reading_history_new (id, profile_id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at)
+SELECT id, 1, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
+FROM reading_history;
+
+DROP TABLE reading_history;
+ALTER TABLE reading_history_new RENAME TO reading_history;
+
+CREATE INDEX IF NOT EXISTS idx_reading_history_media ON reading_history(profile_id, media_id);
+CREATE INDEX IF NOT EXISTS idx_reading_history_last_read ON reading_history(profile_id, last_read DESC);
+CREATE INDEX IF NOT EXISTS idx_reading_history_completed ON reading_history(completed);
+
+-- Library tags (collections)
+CREATE TABLE library_tags_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    profile_id INTEGER NOT NULL DEFAULT 1,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL DEFAULT '#6366f1',
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+    UNIQUE(profile_id, name)
+);
+
+INSERT INTO library_tags_new (id, profile_id, name, color, sort_order, created_at, updated_at)
+SELECT id, 1, name, color, sort_order, created_at, updated_at
+FROM library_tags;
+
+DROP TABLE library_tags;
+ALTER TABLE library_tags_new RENAME TO library_tags;
+
+INSERT OR IGNORE INTO library_tag_assignments (id, library_entry_id, tag_id, created_at)
+SELECT id, library_entry_id, tag_id, created_at FROM library_tag_assignments_backup;
+
+DROP TABLE library_tag_assignments_backup;
+
+-- Notifications only need a column; there is no uniqueness to rescope
+ALTER TABLE notifications ADD COLUMN profile_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_profile ON notifications(profile_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- Cold-storage archiving for episode downloads
+-- Archived downloads live outside the downloads directory (e.g. a NAS mount).
+-- original_path remembers where the file came from so it can be moved back.
+ALTER TABLE downloads ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE downloads ADD COLUMN original_path TEXT;
+CREATE INDEX IF NOT EXISTS idx_downloads_archived ON downloads(archived) WHERE archived = 1;
@@ -0,0 +1,17 @@
+-- Jikan metadata enrichment for sparse media rows
+-- Rows created from extension search results often lack synopsis, genres,
+-- year, studios and source material. Enrichment fills only missing fields
+-- from the matching MAL entry and records which source each field came from.
+ALTER TABLE media ADD COLUMN studios TEXT; -- JSON array of studio names
+ALTER TABLE media ADD COLUMN source_material TEXT; -- Manga, Light novel, Original, etc.
+ALTER TABLE media ADD COLUMN mal_id TEXT;
+ALTER TABLE media ADD COLUMN enriched_at TEXT;
+
+-- One row per field filled from a source other than the row's own extension
+CREATE TABLE IF NOT EXISTS media_field_provenance (
+    media_id TEXT NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    source TEXT NOT NULL,
+    recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (media_id, field)
+);
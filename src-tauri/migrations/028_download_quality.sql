@@ -0,0 +1,9 @@
+-- Record which source an episode was downloaded from
+-- quality is the VideoSource label at queue time (e.g. "1080p"), source_label
+-- the server/variant name (e.g. "HardSub"). Both are NULL for older downloads.
+ALTER TABLE downloads ADD COLUMN quality TEXT;
+ALTER TABLE downloads ADD COLUMN source_label TEXT;
+
+-- Set on a quality-upgrade download; when it completes its file replaces the
+-- referenced download's file and the upgrade row is removed
+ALTER TABLE downloads ADD COLUMN replaces_download_id TEXT;
@@ -0,0 +1,18 @@
+-- Release notification digest
+-- When release_digest_mode is 'hourly' or 'daily', detected releases are
+-- queued here instead of notifying immediately. The background checker
+-- flushes them into one grouped notification at the configured cadence.
+CREATE TABLE IF NOT EXISTS pending_release_digest (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    media_id TEXT NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    media_title TEXT NOT NULL,
+    media_type TEXT NOT NULL,
+    extension_id TEXT NOT NULL,
+    new_releases INTEGER NOT NULL,
+    current_number REAL,
+    current_count INTEGER NOT NULL,
+    cover_url TEXT,
+    detected_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_release_digest_media ON pending_release_digest(media_id);
@@ -0,0 +1,11 @@
+-- Track whether a download's file is on disk separately from its status
+-- status stays the historical outcome (a completed download stays completed);
+-- file_state says where the file is now: present, missing, trashed or archived.
+ALTER TABLE downloads ADD COLUMN file_state TEXT NOT NULL DEFAULT 'present';
+
+UPDATE downloads SET file_state = 'archived' WHERE archived = 1;
+
+-- Completed downloads whose file disappeared used to be rewritten as failed
+UPDATE downloads
+SET status = 'completed', file_state = 'missing', error_message = NULL
+WHERE status = 'failed' AND error_message = 'File not found. Please re-download.';
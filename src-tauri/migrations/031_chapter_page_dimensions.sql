@@ -0,0 +1,5 @@
+-- Page dimensions of downloaded chapters
+-- JSON object mapping each page's file name to {"width", "height"}, recorded
+-- while downloading (or on first offline read) so the reader can place
+-- double-page spreads without probing the images again.
+ALTER TABLE chapter_downloads ADD COLUMN page_dimensions TEXT;
@@ -0,0 +1,19 @@
+-- Season pass
+-- Sequels the season pass rule has already acted on for a profile, so a
+-- sequel is only added or suggested once (even if the user later removes
+-- it), plus when each completed library entry last had its relations checked.
+CREATE TABLE IF NOT EXISTS season_pass_sequels (
+    profile_id INTEGER NOT NULL,
+    sequel_mal_id TEXT NOT NULL,
+    source_media_id TEXT NOT NULL,
+    action TEXT NOT NULL CHECK(action IN ('added', 'suggested', 'in_library', 'finished')),
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (profile_id, sequel_mal_id)
+);
+
+CREATE TABLE IF NOT EXISTS season_pass_checks (
+    profile_id INTEGER NOT NULL,
+    media_id TEXT NOT NULL,
+    checked_at INTEGER NOT NULL,
+    PRIMARY KEY (profile_id, media_id)
+);
@@ -0,0 +1,7 @@
+-- Library cursor pagination
+-- Keyset pages walk the library in (updated_at DESC, id DESC) order. With id
+-- in the index a deep page is a seek plus a short range scan, and the order
+-- needs no temp B-tree sort.
+DROP INDEX IF EXISTS idx_library_status;
+CREATE INDEX IF NOT EXISTS idx_library_status ON library(profile_id, status, updated_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_library_updated ON library(profile_id, updated_at DESC, id DESC);
@@ -0,0 +1,12 @@
+-- Reading speed
+-- Seconds spent per page, aggregated from reading sessions, for "about N
+-- minutes left" estimates. One row per media plus one global row per
+-- profile (media_id = '') used when a title doesn't have enough samples yet.
+CREATE TABLE IF NOT EXISTS reading_speed (
+    profile_id INTEGER NOT NULL,
+    media_id TEXT NOT NULL,
+    total_seconds REAL NOT NULL DEFAULT 0,
+    pages INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (profile_id, media_id)
+);
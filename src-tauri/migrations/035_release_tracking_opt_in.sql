@@ -0,0 +1,16 @@
+-- Release tracking opt-in
+-- Tracking used to be initialized for every library entry. Titles that have
+-- already finished airing/publishing will never get a new release, so switch
+-- their existing tracking rows off. Users can turn them back on per title.
+UPDATE release_tracking_v2
+SET notification_enabled = 0,
+    updated_at = CURRENT_TIMESTAMP
+WHERE COALESCE(notification_enabled, 1) = 1
+  AND (
+    normalized_status = 'completed'
+    OR media_id IN (
+        SELECT id FROM media
+        WHERE LOWER(COALESCE(status, '')) LIKE '%finished%'
+           OR LOWER(COALESCE(status, '')) LIKE '%completed%'
+    )
+  );
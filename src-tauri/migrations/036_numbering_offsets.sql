@@ -0,0 +1,11 @@
+-- Episode numbering offsets
+-- Sources number long-running shows differently: MAL (and so watch history)
+-- counts each season from 1, while a source may number episodes absolutely.
+-- source episode number = canonical (MAL) episode number + episode_offset
+CREATE TABLE IF NOT EXISTS numbering_offsets (
+    media_id TEXT NOT NULL,
+    extension_id TEXT NOT NULL,
+    episode_offset INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (media_id, extension_id)
+);
@@ -0,0 +1,6 @@
+-- Download batches
+-- Episodes queued together (download all / download selected) share a
+-- batch_id, so they're reported with one notification once all of them finish.
+ALTER TABLE downloads ADD COLUMN batch_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_downloads_batch_id ON downloads(batch_id);
@@ -0,0 +1,95 @@
+-- Genre normalization
+-- Extensions name the same genre differently ("Sci-Fi", "SciFi", "Science
+-- Fiction", localized names). Raw genres are looked up by key (lowercase,
+-- letters and digits only) and replaced by the canonical (MAL) name.
+-- Shipped defaults have is_default = 1; users can add or override mappings.
+CREATE TABLE IF NOT EXISTS genre_normalization (
+    raw_key TEXT PRIMARY KEY,
+    canonical TEXT NOT NULL,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO genre_normalization (raw_key, canonical, is_default) VALUES
+    ('action', 'Action', 1),
+    ('acción', 'Action', 1),
+    ('ação', 'Action', 1),
+    ('adventure', 'Adventure', 1),
+    ('aventura', 'Adventure', 1),
+    ('aventure', 'Adventure', 1),
+    ('avantgarde', 'Avant Garde', 1),
+    ('awardwinning', 'Award Winning', 1),
+    ('boyslove', 'Boys Love', 1),
+    ('shounenai', 'Boys Love', 1),
+    ('shonenai', 'Boys Love', 1),
+    ('bl', 'Boys Love', 1),
+    ('yaoi', 'Boys Love', 1),
+    ('comedy', 'Comedy', 1),
+    ('comedia', 'Comedy', 1),
+    ('comédie', 'Comedy', 1),
+    ('comédia', 'Comedy', 1),
+    ('drama', 'Drama', 1),
+    ('drame', 'Drama', 1),
+    ('ecchi', 'Ecchi', 1),
+    ('fantasy', 'Fantasy', 1),
+    ('fantasía', 'Fantasy', 1),
+    ('fantasia', 'Fantasy', 1),
+    ('fantastique', 'Fantasy', 1),
+    ('girlslove', 'Girls Love', 1),
+    ('shoujoai', 'Girls Love', 1),
+    ('shojoai', 'Girls Love', 1),
+    ('gl', 'Girls Love', 1),
+    ('yuri', 'Girls Love', 1),
+    ('gourmet', 'Gourmet', 1),
+    ('harem', 'Harem', 1),
+    ('historical', 'Historical', 1),
+    ('history', 'Historical', 1),
+    ('horror', 'Horror', 1),
+    ('terror', 'Horror', 1),
+    ('isekai', 'Isekai', 1),
+    ('josei', 'Josei', 1),
+    ('kids', 'Kids', 1),
+    ('martialarts', 'Martial Arts', 1),
+    ('mecha', 'Mecha', 1),
+    ('military', 'Military', 1),
+    ('music', 'Music', 1),
+    ('musical', 'Music', 1),
+    ('mystery', 'Mystery', 1),
+    ('misterio', 'Mystery', 1),
+    ('mistério', 'Mystery', 1),
+    ('mystère', 'Mystery', 1),
+    ('parody', 'Parody', 1),
+    ('psychological', 'Psychological', 1),
+    ('psicológico', 'Psychological', 1),
+    ('psychologique', 'Psychological', 1),
+    ('romance', 'Romance', 1),
+    ('school', 'School', 1),
+    ('schoollife', 'School', 1),
+    ('scifi', 'Sci-Fi', 1),
+    ('sciencefiction', 'Sci-Fi', 1),
+    ('cienciaficción', 'Sci-Fi', 1),
+    ('ficçãocientífica', 'Sci-Fi', 1),
+    ('seinen', 'Seinen', 1),
+    ('shoujo', 'Shoujo', 1),
+    ('shojo', 'Shoujo', 1),
+    ('shounen', 'Shounen', 1),
+    ('shonen', 'Shounen', 1),
+    ('sliceoflife', 'Slice of Life', 1),
+    ('recuentosdelavida', 'Slice of Life', 1),
+    ('space', 'Space', 1),
+    ('sports', 'Sports', 1),
+    ('sport', 'Sports', 1),
+    ('deportes', 'Sports', 1),
+    ('esportes', 'Sports', 1),
+    ('superpower', 'Super Power', 1),
+    ('superpowers', 'Super Power', 1),
+    ('supernatural', 'Supernatural', 1),
+    ('sobrenatural', 'Supernatural', 1),
+    ('surnaturel', 'Supernatural', 1),
+    ('suspense', 'Suspense', 1),
+    ('thriller', 'Suspense', 1),
+    ('vampire', 'Vampire', 1),
+    ('vampires', 'Vampire', 1);
+
+-- Genres as the source returned them, before normalization (for debugging)
+ALTER TABLE media ADD COLUMN genres_raw TEXT;
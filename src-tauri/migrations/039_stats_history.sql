@@ -0,0 +1,14 @@
+-- Developer stats history
+-- One row per minute holding the averages of the samples taken in it.
+-- Rows older than 48 hours are pruned as new ones are written.
+CREATE TABLE IF NOT EXISTS stats_history (
+    minute INTEGER PRIMARY KEY, -- unix seconds at the start of the minute
+    samples INTEGER NOT NULL,
+    memory_used REAL NOT NULL,
+    memory_percent REAL NOT NULL,
+    cpu_usage REAL NOT NULL,
+    process_memory REAL NOT NULL,
+    process_cpu REAL NOT NULL,
+    disk_used REAL NOT NULL,
+    disk_percent REAL NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Lazily resolved download sources
+-- Episodes of a batch download are queued without a URL; the extension they
+-- come from is stored so the source can be fetched when the download starts,
+-- also after a restart.
+ALTER TABLE downloads ADD COLUMN source_extension_id TEXT;
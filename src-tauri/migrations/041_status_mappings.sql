@@ -0,0 +1,13 @@
+-- Learned release status mappings
+-- Raw statuses the built-in patterns can't classify are recorded here (by
+-- key: trimmed, lowercase, accents removed) with how often they were seen,
+-- so users can map them from the tracking debug view. A mapping set by the
+-- user takes precedence over the built-in patterns.
+CREATE TABLE IF NOT EXISTS status_mappings (
+    status_key TEXT PRIMARY KEY,
+    raw_status TEXT NOT NULL,        -- as first seen
+    normalized_status TEXT,          -- NULL until the user maps it
+    seen_count INTEGER NOT NULL DEFAULT 0,
+    last_seen_at TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
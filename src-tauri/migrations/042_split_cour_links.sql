@@ -0,0 +1,12 @@
+-- Split-cour links
+-- A split-cour show has two MAL entries (part 1 and part 2) but a source may
+-- list it as one title. Once part 1 is linked to its sequel, release checks
+-- for the local media follow the sequel's MAL entry; its episodes restart at
+-- 1, so episode_offset (part 1's episode count) is added to its numbers.
+CREATE TABLE IF NOT EXISTS split_cour_links (
+    media_id TEXT PRIMARY KEY NOT NULL,
+    sequel_mal_id INTEGER NOT NULL,
+    episode_offset INTEGER NOT NULL DEFAULT 0,
+    linked_at INTEGER NOT NULL,                  -- Unix ms
+    FOREIGN KEY (media_id) REFERENCES media(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Series title stored with each download
+-- Notifications used to recover the title from the filename, which no longer
+-- works once filenames come from a user template (download_filename_template).
+ALTER TABLE downloads ADD COLUMN media_title TEXT;
@@ -0,0 +1,11 @@
+-- Hidden media ("not interested")
+-- Results the user hid from home, discover, search and recommendations.
+-- media_id is the id a listing shows: a media table id or an extension's
+-- source id; extension_id records where it was hidden from.
+CREATE TABLE IF NOT EXISTS hidden_media (
+    media_id TEXT PRIMARY KEY NOT NULL,
+    extension_id TEXT,
+    title TEXT,                                  -- shown in the hidden list
+    hidden_at INTEGER NOT NULL,                  -- Unix ms
+    reason TEXT
+);
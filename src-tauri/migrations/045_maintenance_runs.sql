@@ -0,0 +1,10 @@
+-- Maintenance chores
+-- When each background chore (cache sweep, database optimize, ...) last ran,
+-- so the idle-time scheduler knows what's due across restarts.
+CREATE TABLE IF NOT EXISTS maintenance_runs (
+    chore TEXT PRIMARY KEY NOT NULL,
+    last_run INTEGER NOT NULL,                   -- Unix ms
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    last_result TEXT,                            -- summary, e.g. "12 entries removed"
+    last_error TEXT                              -- NULL when the run succeeded
+);
@@ -0,0 +1,6 @@
+-- Scheduled downloads
+-- scheduled_start holds a queued download until then (Unix ms); start_now
+-- lets one download skip its schedule and the off-peak window
+-- (download_off_peak_window setting).
+ALTER TABLE downloads ADD COLUMN scheduled_start INTEGER;
+ALTER TABLE downloads ADD COLUMN start_now INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Download integrity verification
+-- sha256 is the hex SHA-256 of a completed download's file, recorded when it
+-- finishes (download_checksums setting) or on its first hashed verification.
+-- verified_at is when the file was last verified (Unix ms).
+ALTER TABLE downloads ADD COLUMN sha256 TEXT;
+ALTER TABLE downloads ADD COLUMN verified_at INTEGER;
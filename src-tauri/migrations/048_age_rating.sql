@@ -0,0 +1,8 @@
+-- Age ratings
+-- rating_classification is a media row's age rating (g, pg, pg13, r17,
+-- r_plus, rx), from Jikan's rating or the extension that provided it.
+-- A profile's max_age_rating hides anything rated above it from listings and
+-- the library; hide_unrated also hides media whose rating isn't known.
+ALTER TABLE media ADD COLUMN rating_classification TEXT;
+ALTER TABLE profiles ADD COLUMN max_age_rating TEXT;
+ALTER TABLE profiles ADD COLUMN hide_unrated INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Per-download speed limit
+-- Bytes per second one download may use, on top of the global
+-- download_speed_limit setting. NULL leaves the download unlimited.
+ALTER TABLE downloads ADD COLUMN speed_limit INTEGER;
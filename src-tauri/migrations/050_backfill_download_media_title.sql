@@ -0,0 +1,8 @@
+-- Backfill download titles from the media table
+-- Downloads queued before media_title existed only had their filename to go
+-- on, which notifications and the downloads page split on "_EP" to find the
+-- series title. Rows whose media is still known get the real title.
+UPDATE downloads
+SET media_title = (SELECT m.title FROM media m WHERE m.id = downloads.media_id)
+WHERE media_title IS NULL
+  AND EXISTS (SELECT 1 FROM media m WHERE m.id = downloads.media_id);
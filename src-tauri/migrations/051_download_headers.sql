@@ -0,0 +1,4 @@
+-- Download headers
+-- HTTP headers the extension gave with a download's source (JSON object),
+-- sent with every request of the download. NULL sends the defaults.
+ALTER TABLE downloads ADD COLUMN headers TEXT;
@@ -0,0 +1,20 @@
+-- Download subtitles
+-- Subtitle tracks an extension gave with a download's source. Recorded when
+-- the download is queued; file_path is set once the track was fetched next to
+-- the video after it completed. error_message holds why the last fetch failed.
+-- Rows follow their download when it's renamed (set aside by an overwrite)
+-- and go with it when it's deleted.
+CREATE TABLE IF NOT EXISTS download_subtitles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    download_id TEXT NOT NULL,
+    language TEXT NOT NULL, -- tag used in the file name, unique per download
+    label TEXT NOT NULL DEFAULT '',
+    url TEXT NOT NULL,
+    file_path TEXT,
+    error_message TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (download_id) REFERENCES downloads(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    UNIQUE(download_id, language)
+);
+
+CREATE INDEX IF NOT EXISTS idx_download_subtitles_download ON download_subtitles(download_id);
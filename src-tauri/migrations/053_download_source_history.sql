@@ -0,0 +1,6 @@
+-- Download source history
+-- Sources a download can be fetched from again, as a JSON array of
+-- {url, quality, server, headers}: the one it was downloaded from first,
+-- then alternatives the extension offered when it was queued. Used to
+-- re-download a missing file without resolving the episode's sources again.
+ALTER TABLE downloads ADD COLUMN source_history TEXT;
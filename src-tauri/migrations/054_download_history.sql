@@ -0,0 +1,22 @@
+-- Download history
+-- Append-only record of what was downloaded: a row when a download completes
+-- and one when it leaves the downloads list (deleted with its file, removed,
+-- or cleared), so clearing the list doesn't lose track of it. Rows copy what
+-- they need from the download instead of referencing it.
+CREATE TABLE IF NOT EXISTS download_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    download_id TEXT NOT NULL,
+    media_id TEXT NOT NULL,
+    media_title TEXT,
+    episode_id TEXT NOT NULL,
+    episode_number INTEGER NOT NULL,
+    quality TEXT,
+    file_path TEXT NOT NULL,
+    total_bytes INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL, -- the download's status at the time
+    event TEXT NOT NULL CHECK(event IN ('completed', 'deleted', 'removed', 'cleared')),
+    queued_at TEXT, -- when the download was created
+    recorded_at INTEGER NOT NULL -- Unix ms
+);
+
+CREATE INDEX IF NOT EXISTS idx_download_history_recorded ON download_history(recorded_at DESC, id DESC);
@@ -0,0 +1,16 @@
+-- Download events: integrity checks of completed downloads, one row per
+-- download checked (verified, corrupt or missing), so the downloads page can
+-- show when a file was last found intact and why one was flagged.
+CREATE TABLE IF NOT EXISTS download_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    download_id TEXT NOT NULL,
+    media_id TEXT NOT NULL,
+    episode_number INTEGER NOT NULL,
+    kind TEXT NOT NULL CHECK(kind IN ('verified', 'corrupt', 'missing')),
+    -- Why the file was flagged; NULL for verified
+    detail TEXT,
+    created_at INTEGER NOT NULL -- Unix ms
+);
+
+CREATE INDEX IF NOT EXISTS idx_download_events_download ON download_events(download_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_download_events_created ON download_events(created_at DESC, id DESC);
@@ -0,0 +1,6 @@
+-- Per-media preferred source for release checks: the extension (and the
+-- media's id in it) releases are checked against instead of the extension
+-- the tracking row was created with. NULL falls back to the source priority
+-- setting.
+ALTER TABLE release_tracking_v2 ADD COLUMN preferred_extension_id TEXT;
+ALTER TABLE release_tracking_v2 ADD COLUMN preferred_media_id TEXT;
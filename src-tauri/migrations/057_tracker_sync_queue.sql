@@ -0,0 +1,12 @@
+-- Tracker sync queue: watch progress waiting to be pushed to a connected
+-- tracker, one row per tracker and media (the furthest episode watched).
+-- Filled when an episode is completed for a media mapped to a tracker the
+-- user has an account for; the tracker client drains it.
+CREATE TABLE IF NOT EXISTS tracker_sync_queue (
+    tracker_name TEXT NOT NULL,
+    media_id TEXT NOT NULL,
+    tracker_media_id TEXT NOT NULL,
+    episode_number INTEGER NOT NULL,
+    queued_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (tracker_name, media_id)
+);
@@ -0,0 +1,21 @@
+-- Hidden media per profile
+-- "Not interested" is as personal as the library, so hidden results are
+-- scoped to a profile. SQLite can't change a primary key in place, so the
+-- table is recreated; existing rows go to the default profile (id 1).
+CREATE TABLE hidden_media_new (
+    profile_id INTEGER NOT NULL DEFAULT 1,
+    media_id TEXT NOT NULL,
+    extension_id TEXT,
+    title TEXT,                                  -- shown in the hidden list
+    hidden_at INTEGER NOT NULL,                  -- Unix ms
+    reason TEXT,
+    PRIMARY KEY (profile_id, media_id),
+    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+);
+
+INSERT INTO hidden_media_new (profile_id, media_id, extension_id, title, hidden_at, reason)
+SELECT 1, media_id, extension_id, title, hidden_at, reason
+FROM hidden_media;
+
+DROP TABLE hidden_media;
+ALTER TABLE hidden_media_new RENAME TO hidden_media;
@@ -0,0 +1,16 @@
+-- Broadcast schedule of airing media, from the details sources give (Jikan
+-- combines the last aired date with the broadcast day and time): when the
+-- latest episode aired (Unix ms) and the interval between episodes (ms).
+-- The library's next-airing sort steps forward from it.
+ALTER TABLE media ADD COLUMN last_aired_at INTEGER;
+ALTER TABLE media ADD COLUMN broadcast_interval INTEGER;
+
+-- Tracking rows from before last_episode_date was kept take the latest
+-- check that found new episodes, as those checks now stamp it
+UPDATE release_tracking_v2
+SET last_episode_date = (
+    SELECT MAX(l.check_timestamp) FROM release_check_log l
+    WHERE l.media_id = release_tracking_v2.media_id
+      AND l.result_type = 'new_release'
+)
+WHERE last_episode_date IS NULL;
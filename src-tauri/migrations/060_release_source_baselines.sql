@@ -0,0 +1,15 @@
+-- What each preferred source last answered for a media's release check.
+-- Sources number and count episodes differently, so each is compared only
+-- against its own answers; release_tracking_v2 keeps the tracking source's.
+-- A baseline recorded under another id in the source doesn't apply.
+CREATE TABLE IF NOT EXISTS release_source_baselines (
+    media_id TEXT NOT NULL,
+    extension_id TEXT NOT NULL,
+    source_media_id TEXT NOT NULL,
+    last_known_count INTEGER NOT NULL,
+    last_known_latest_number REAL,
+    last_known_latest_id TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (media_id, extension_id),
+    FOREIGN KEY (media_id) REFERENCES media(id) ON DELETE CASCADE
+);
@@ -0,0 +1,233 @@
+// Application Log Files
+//
+// The log plugin writes otaku.log to the app log directory and rotates it by
+// size: a full file is renamed to otaku_<timestamp>.log and a fresh one is
+// started, keeping only the newest few. The log commands go through this
+// module so they see the rotated set as one log, oldest line first, and only
+// read the tail they need instead of whole files.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Log file name (without extension), as configured on the log plugin
+pub const LOG_FILE_NAME: &str = "otaku";
+
+/// Size at which the log plugin rotates otaku.log
+pub const MAX_LOG_FILE_SIZE: u128 = 5 * 1024 * 1024;
+
+/// Rotated files the log plugin keeps besides the current one
+pub const KEPT_ROTATED_LOGS: usize = 3;
+
+/// Log files this big predate rotation (or it failed) and are deleted on start
+const OVERSIZED_LOG_BYTES: u64 = 4 * MAX_LOG_FILE_SIZE as u64;
+
+/// Bytes read per step when reading a file backwards
+const TAIL_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The file currently written to
+pub fn current_log_file(log_dir: &Path) -> PathBuf {
+    log_dir.join(format!("{}.log", LOG_FILE_NAME))
+}
+
+/// Rotated files are named otaku_<timestamp>.log
+fn is_rotated_log(name: &str) -> bool {
+    name.strip_prefix(LOG_FILE_NAME)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|rest| rest.ends_with(".log"))
+}
+
+/// Every log file, oldest first: rotated files in timestamp order, then the
+/// current one
+pub fn log_files(log_dir: &Path) -> Vec<PathBuf> {
+    let mut rotated: Vec<PathBuf> = std::fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| is_rotated_log(&entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // The timestamp format sorts chronologically as text
+    rotated.sort();
+
+    let current = current_log_file(log_dir);
+    if current.exists() {
+        rotated.push(current);
+    }
+    rotated
+}
+
+/// Combined size of every log file
+pub fn total_size(log_dir: &Path) -> u64 {
+    log_files(log_dir)
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// The last `limit` lines of one file, read backwards from the end
+fn read_last_lines(path: &Path, limit: usize) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    let mut chunk = vec![0u8; TAIL_CHUNK_SIZE];
+
+    // One newline more than needed guarantees `limit` whole lines
+    while pos > 0 && tail.iter().filter(|b| **b == b'\n').count() <= limit {
+        let len = TAIL_CHUNK_SIZE.min(pos as usize);
+        pos -= len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut chunk[..len])?;
+        tail.splice(0..0, chunk[..len].iter().copied());
+    }
+
+    let text = String::from_utf8_lossy(&tail);
+    let mut lines: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+    if pos > 0 && !lines.is_empty() {
+        // Started mid-line
+        lines.remove(0);
+    }
+
+    let start = lines.len().saturating_sub(limit);
+    Ok(lines[start..].iter().map(|line| line.to_string()).collect())
+}
+
+/// The last `limit` lines across `files` (oldest first), in order
+pub fn tail_lines(files: &[PathBuf], limit: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for path in files.iter().rev() {
+        let remaining = limit.saturating_sub(lines.len());
+        if remaining == 0 {
+            break;
+        }
+
+        match read_last_lines(path, remaining) {
+            Ok(mut older) => {
+                older.append(&mut lines);
+                lines = older;
+            }
+            Err(e) => log::debug!("Skipping unreadable log file {:?}: {}", path, e),
+        }
+    }
+
+    lines
+}
+
+/// Empty the current log and delete the rotated ones
+pub fn clear_logs(log_dir: &Path) -> io::Result<()> {
+    for path in log_files(log_dir) {
+        if path == current_log_file(log_dir) {
+            std::fs::write(&path, "")?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete log files far past the rotation size, returning how many were
+/// deleted. Run before the log plugin opens its file.
+pub fn trash_oversized_logs(log_dir: &Path) -> usize {
+    log_files(log_dir)
+        .into_iter()
+        .filter(|path| std::fs::metadata(path).is_ok_and(|meta| meta.len() > OVERSIZED_LOG_BYTES))
+        .filter(|path| std::fs::remove_file(path).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_lines(path: &Path, range: std::ops::Range<usize>) {
+        let text: String = range.map(|n| format!("[2024-06-01][INFO] line {}\n", n)).collect();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn files_are_ordered_oldest_first_with_the_current_last() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in [
+            "otaku.log",
+            "otaku_2024-06-02_09-00-00.log",
+            "otaku_2024-06-01_18-30-00.log",
+            "other.log",
+            "otaku.log.bak",
+        ] {
+            std::fs::write(dir.join(name), "x\n").unwrap();
+        }
+
+        let names: Vec<String> = log_files(dir)
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["otaku_2024-06-01_18-30-00.log", "otaku_2024-06-02_09-00-00.log", "otaku.log"]);
+        assert_eq!(total_size(dir), 6);
+    }
+
+    #[test]
+    fn tail_reads_across_rotated_files_in_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        write_lines(&dir.join("otaku_2024-06-01_00-00-00.log"), 0..10);
+        write_lines(&dir.join("otaku_2024-06-02_00-00-00.log"), 10..20);
+        write_lines(&dir.join("otaku.log"), 20..25);
+
+        let files = log_files(dir);
+        let lines = tail_lines(&files, 12);
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines.first().unwrap(), "[2024-06-01][INFO] line 13");
+        assert_eq!(lines.last().unwrap(), "[2024-06-01][INFO] line 24");
+
+        assert_eq!(tail_lines(&files, 100).len(), 25);
+        assert!(tail_lines(&files, 0).is_empty());
+    }
+
+    #[test]
+    fn tail_of_a_large_file_only_returns_whole_lines() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("otaku.log");
+        // Several chunks' worth, so reads start mid-line
+        write_lines(&path, 0..20_000);
+
+        let lines = read_last_lines(&path, 5_000).unwrap();
+        assert_eq!(lines.len(), 5_000);
+        assert_eq!(lines[0], "[2024-06-01][INFO] line 15000");
+        assert_eq!(lines[4_999], "[2024-06-01][INFO] line 19999");
+
+        // No trailing newline on the last line
+        std::fs::write(&path, "first\nsecond\nthird").unwrap();
+        assert_eq!(read_last_lines(&path, 2).unwrap(), vec!["second", "third"]);
+    }
+
+    #[test]
+    fn clear_empties_current_and_removes_rotated() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        write_lines(&dir.join("otaku_2024-06-01_00-00-00.log"), 0..10);
+        write_lines(&dir.join("otaku.log"), 10..20);
+
+        clear_logs(dir).unwrap();
+        assert_eq!(log_files(dir), vec![current_log_file(dir)]);
+        assert_eq!(total_size(dir), 0);
+    }
+
+    #[test]
+    fn only_oversized_logs_are_trashed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let huge = File::create(dir.join("otaku.log")).unwrap();
+        huge.set_len(OVERSIZED_LOG_BYTES + 1).unwrap();
+        write_lines(&dir.join("otaku_2024-06-01_00-00-00.log"), 0..10);
+
+        assert_eq!(trash_oversized_logs(dir), 1);
+        assert!(!current_log_file(dir).exists());
+        assert_eq!(log_files(dir).len(), 1);
+    }
+}
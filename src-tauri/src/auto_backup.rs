@@ -9,10 +9,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Manager};
 
 use crate::commands::AppState;
-use crate::database::export_import::export_all_data;
+use crate::database::export_import::export_to_file;
+use crate::events::{AUTO_BACKUP_COMPLETED_EVENT, AUTO_BACKUP_FAILED_EVENT};
 
 /// Global flag for backup task control
 static BACKUP_TASK_RUNNING: AtomicBool = AtomicBool::new(false);
@@ -40,7 +41,7 @@ impl Default for AutoBackupSettings {
 }
 
 /// Result of a backup operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct BackupResult {
     pub success: bool,
     pub file_path: Option<String>,
@@ -49,13 +50,19 @@ pub struct BackupResult {
     pub items_backed_up: BackupStats,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct BackupStats {
     pub library_count: usize,
     pub watch_history_count: usize,
     pub reading_history_count: usize,
 }
 
+/// Payload of the auto-backup-failed event
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AutoBackupFailed {
+    pub error: String,
+}
+
 /// Get auto-backup settings from database
 pub async fn get_auto_backup_settings(pool: &SqlitePool) -> Result<AutoBackupSettings> {
     let settings_json: Option<String> = sqlx::query_scalar(
@@ -188,19 +195,16 @@ pub async fn perform_backup(
     // Get app version
     let app_version = env!("CARGO_PKG_VERSION");
 
-    // Export data
-    let export_data = export_all_data(pool, app_version).await?;
+    // Stream every profile's data straight to the file; extension code is
+    // left out to keep backups small
+    let metadata = export_to_file(pool, app_version, None, false, &file_path, None).await?;
 
     let stats = BackupStats {
-        library_count: export_data.metadata.library_count,
-        watch_history_count: export_data.metadata.watch_history_count,
-        reading_history_count: export_data.metadata.reading_history_count,
+        library_count: metadata.library_count,
+        watch_history_count: metadata.watch_history_count,
+        reading_history_count: metadata.reading_history_count,
     };
 
-    // Write to file
-    let json = serde_json::to_string_pretty(&export_data)?;
-    tokio::fs::write(&file_path, json).await?;
-
     log::info!("Auto-backup created: {:?}", file_path);
 
     // Cleanup old backups
@@ -290,14 +294,14 @@ pub async fn start_auto_backup_task(app_handle: AppHandle) {
                                 );
 
                                 // Emit event to notify frontend
-                                let _ = app_handle.emit("auto-backup-completed", &result);
+                                AUTO_BACKUP_COMPLETED_EVENT.emit(&app_handle, &result);
                             }
                             Err(e) => {
                                 log::error!("Auto-backup failed: {}", e);
 
-                                let _ = app_handle.emit("auto-backup-failed", serde_json::json!({
-                                    "error": e.to_string()
-                                }));
+                                AUTO_BACKUP_FAILED_EVENT.emit(&app_handle, &AutoBackupFailed {
+                                    error: e.to_string(),
+                                });
                             }
                         }
                     }
@@ -0,0 +1,239 @@
+// Backup File Association
+//
+// Opening a .otakubak file (double-click, "Open with") starts the import flow.
+// Windows and Linux hand the path over as a command-line argument: to this
+// process on a cold start, or to the running instance through the
+// single-instance plugin. macOS delivers it as RunEvent::Opened. Either way
+// the file is validated here and the frontend is told through
+// BACKUP_FILE_OPENED_EVENT. The result is also kept as pending, since on a
+// cold start the event fires before the window has loaded.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::database::export_import::{ExportData, ExportMetadata};
+use crate::events::BACKUP_FILE_OPENED_EVENT;
+
+/// Extension registered for exported backups (see bundle.fileAssociations)
+pub const BACKUP_FILE_EXTENSION: &str = "otakubak";
+
+/// Why an opened file can't be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupFileError {
+    /// The file couldn't be read
+    Io(String),
+    /// The file is empty
+    Empty,
+    /// Valid JSON up to where the file stops, e.g. an interrupted copy
+    Truncated,
+    /// Not an Otaku export (another JSON document, a binary file, ...)
+    WrongFormat(String),
+}
+
+impl fmt::Display for BackupFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupFileError::Io(e) => write!(f, "Failed to read backup file: {}", e),
+            BackupFileError::Empty => write!(f, "Backup file is empty"),
+            BackupFileError::Truncated => {
+                write!(f, "Backup file is incomplete (it may have been cut off while copying)")
+            }
+            BackupFileError::WrongFormat(e) => write!(f, "Not an Otaku backup file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupFileError {}
+
+/// Sent to the frontend when a backup file is opened with the app
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BackupFileOpened {
+    pub path: String,
+    /// Summary of the backup's contents; None when it failed to parse
+    pub metadata: Option<ExportMetadata>,
+    pub exported_at: Option<String>,
+    pub app_version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Last opened backup file that the frontend hasn't picked up yet
+#[derive(Default)]
+pub struct PendingBackupFile(pub Mutex<Option<BackupFileOpened>>);
+
+/// Parse the contents of a backup file
+pub fn parse_backup(bytes: &[u8]) -> Result<ExportData, BackupFileError> {
+    if bytes.iter().all(|b| b.is_ascii_whitespace()) {
+        return Err(BackupFileError::Empty);
+    }
+
+    serde_json::from_slice::<ExportData>(bytes).map_err(|e| match e.classify() {
+        serde_json::error::Category::Eof => BackupFileError::Truncated,
+        serde_json::error::Category::Io => BackupFileError::Io(e.to_string()),
+        _ => BackupFileError::WrongFormat(e.to_string()),
+    })
+}
+
+/// Read and validate a backup file
+pub fn read_backup_file(path: &Path) -> Result<ExportData, BackupFileError> {
+    let bytes = std::fs::read(path).map_err(|e| BackupFileError::Io(e.to_string()))?;
+    parse_backup(&bytes)
+}
+
+pub fn is_backup_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(BACKUP_FILE_EXTENSION))
+}
+
+/// Backup files among command-line arguments. Relative paths are resolved
+/// against `cwd`, which for a forwarded launch is the second instance's
+/// working directory rather than ours.
+pub fn backup_paths_from_args<I>(args: I, cwd: &Path) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = String>,
+{
+    args.into_iter()
+        // The first argument is the executable itself
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .filter(|path| is_backup_file(path))
+        .map(|path| if path.is_relative() { cwd.join(path) } else { path })
+        .collect()
+}
+
+/// Validate an opened backup file and hand it to the frontend
+pub fn open_backup_file(app: &AppHandle, path: &Path) {
+    log::info!("Opening backup file {:?}", path);
+
+    let payload = match read_backup_file(path) {
+        Ok(data) => BackupFileOpened {
+            path: path.to_string_lossy().to_string(),
+            metadata: Some(data.metadata),
+            exported_at: Some(data.exported_at),
+            app_version: Some(data.app_version),
+            error: None,
+        },
+        Err(e) => {
+            log::warn!("Opened backup file {:?} is not importable: {}", path, e);
+            BackupFileOpened {
+                path: path.to_string_lossy().to_string(),
+                metadata: None,
+                exported_at: None,
+                app_version: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+
+    if let Some(state) = app.try_state::<PendingBackupFile>() {
+        if let Ok(mut pending) = state.0.lock() {
+            *pending = Some(payload.clone());
+        }
+    }
+
+    BACKUP_FILE_OPENED_EVENT.emit(app, &payload);
+
+    #[cfg(desktop)]
+    crate::tray::restore_and_navigate(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_json() -> String {
+        serde_json::json!({
+            "format_version": "1.0",
+            "app_version": "1.5.0",
+            "exported_at": "2024-06-01T12:00:00Z",
+            "data": {
+                "library": [],
+                "watch_history": [],
+                "reading_history": [],
+                "library_tags": [],
+                "tag_assignments": [],
+                "app_settings": [],
+                "media_cache": [],
+                "tracker_mappings": []
+            },
+            "metadata": {
+                "library_count": 12,
+                "watch_history_count": 30,
+                "reading_history_count": 4,
+                "tag_count": 2,
+                "media_cache_count": 16
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_a_valid_export() {
+        let data = parse_backup(export_json().as_bytes()).unwrap();
+        assert_eq!(data.metadata.library_count, 12);
+        assert_eq!(data.app_version, "1.5.0");
+    }
+
+    #[test]
+    fn truncated_file_is_reported_as_truncated() {
+        let json = export_json();
+        for cut in [1, json.len() / 3, json.len() - 1] {
+            assert_eq!(parse_backup(&json.as_bytes()[..cut]).unwrap_err(), BackupFileError::Truncated);
+        }
+    }
+
+    #[test]
+    fn wrong_format_files_are_rejected() {
+        assert_eq!(parse_backup(b"").unwrap_err(), BackupFileError::Empty);
+        assert_eq!(parse_backup(b" \n ").unwrap_err(), BackupFileError::Empty);
+
+        for bytes in [
+            br#"{"name": "not a backup"}"#.as_slice(),
+            b"[1, 2, 3]",
+            b"PK\x03\x04\x14\x00\x00\x00\x08\x00",
+            b"library,watch_history\n1,2\n",
+        ] {
+            assert!(
+                matches!(parse_backup(bytes), Err(BackupFileError::WrongFormat(_))),
+                "{:?}",
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn read_backup_file_reports_missing_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("backup.otakubak");
+        assert!(matches!(read_backup_file(&path), Err(BackupFileError::Io(_))));
+
+        std::fs::write(&path, export_json()).unwrap();
+        assert!(read_backup_file(&path).is_ok());
+    }
+
+    #[test]
+    fn picks_backup_paths_out_of_launch_args() {
+        let cwd = Path::new("/home/user");
+        let args = [
+            "/usr/bin/otaku",
+            "--hidden",
+            "notes.json",
+            "Library.OTAKUBAK",
+            "/tmp/otaku-backup-2024-06-01.otakubak",
+        ]
+        .map(String::from);
+
+        assert_eq!(
+            backup_paths_from_args(args, cwd),
+            vec![
+                PathBuf::from("/home/user/Library.OTAKUBAK"),
+                PathBuf::from("/tmp/otaku-backup-2024-06-01.otakubak"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// Sidebar Badges
+//
+// The sidebar shows four counts: unread notifications, media with a NEW
+// release the user hasn't acknowledged, active downloads and failed
+// downloads. `get_badge_summary` returns all of them from one statement (the
+// active episode downloads come from the download manager, like the tray's
+// count, since rows it restored as paused still read 'downloading').
+//
+// Anything that changes a count calls `invalidate`. The badge task waits for
+// that, recomputes shortly after (so a burst of changes costs one query) and
+// emits badge-summary-changed when the counts differ from the last ones sent,
+// so the frontend never has to poll.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::commands::AppState;
+use crate::downloads::DownloadManager;
+use crate::events::BADGE_SUMMARY_CHANGED_EVENT;
+
+/// How long the badge task waits after a change before recomputing
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Woken by `invalidate`; holds a permit if nothing was waiting
+static CHANGED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Counts behind the sidebar badges
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct BadgeSummary {
+    /// Unread, undismissed notifications of the active profile
+    pub unread_notifications: usize,
+    /// Media showing the NEW badge
+    pub new_releases: usize,
+    /// Episode and chapter downloads in progress
+    pub active_downloads: usize,
+    /// Episode and chapter downloads that failed
+    pub failed_downloads: usize,
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Calls to `invalidate` made on this thread, so tests can tell which
+    /// changes reach the badge task
+    static INVALIDATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// A badge count may have changed; the badge task recomputes them
+pub fn invalidate() {
+    CHANGED.notify_one();
+    #[cfg(test)]
+    INVALIDATIONS.with(|n| n.set(n.get() + 1));
+}
+
+/// Compute the badges. `active_episode_downloads` is the download manager's
+/// count; everything else is one query over indexed columns. New releases
+/// match `release_checker::get_media_release_states`.
+pub async fn get_badge_summary(pool: &SqlitePool, profile_id: i64, active_episode_downloads: usize) -> Result<BadgeSummary> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM notifications
+             WHERE profile_id = ? AND read = 0 AND dismissed = 0) AS unread_notifications,
+            (SELECT COUNT(*) FROM release_tracking_v2
+             WHERE user_acknowledged_at IS NULL
+               AND last_known_latest_number > user_notified_up_to) AS new_releases,
+            (SELECT COUNT(*) FROM chapter_downloads WHERE status = 'downloading') AS active_chapters,
+            (SELECT COUNT(*) FROM downloads WHERE status = 'failed')
+              + (SELECT COUNT(*) FROM chapter_downloads WHERE status = 'failed') AS failed_downloads
+        "#,
+    )
+    .bind(profile_id)
+    .fetch_one(pool)
+    .await?;
+
+    let count = |column: &str| -> Result<usize> { Ok(row.try_get::<i64, _>(column)?.max(0) as usize) };
+    Ok(BadgeSummary {
+        unread_notifications: count("unread_notifications")?,
+        new_releases: count("new_releases")?,
+        active_downloads: active_episode_downloads + count("active_chapters")?,
+        failed_downloads: count("failed_downloads")?,
+    })
+}
+
+/// Current badges, with the active episode downloads from `manager`
+pub async fn current_summary(pool: &SqlitePool, profile_id: i64, manager: &DownloadManager) -> Result<BadgeSummary> {
+    let active = manager.download_stats().await.active;
+    get_badge_summary(pool, profile_id, active).await
+}
+
+/// Remember `summary` as sent, returning whether it differs from the last one
+fn record(last: &mut Option<BadgeSummary>, summary: &BadgeSummary) -> bool {
+    if last.as_ref() == Some(summary) {
+        return false;
+    }
+    *last = Some(summary.clone());
+    true
+}
+
+/// Emit the badges once, then again whenever they change
+pub fn start_badge_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last = None;
+        loop {
+            let summary = {
+                let state = app_handle.state::<AppState>();
+                let manager = app_handle.state::<DownloadManager>();
+                current_summary(state.database.pool(), state.profile_id(), &manager).await
+            };
+            match summary {
+                Ok(summary) => {
+                    if record(&mut last, &summary) {
+                        BADGE_SUMMARY_CHANGED_EVENT.emit(&app_handle, &summary);
+                    }
+                }
+                Err(e) => log::warn!("Failed to compute badge counts: {}", e),
+            }
+
+            CHANGED.notified().await;
+            tokio::time::sleep(DEBOUNCE).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use crate::notifications::{self, NotificationPayload, NotificationType};
+    use crate::release_checker;
+
+    async fn setup() -> (tempfile::TempDir, Database) {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Show', 'anime')",
+        )
+        .execute(database.pool())
+        .await
+        .unwrap();
+        (dir, database)
+    }
+
+    async fn insert_download(pool: &SqlitePool, id: &str, status: &str) {
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status)
+            VALUES (?, 'm1', ?, 1, 'Episode_1.mp4', 'https://example.test/1.mp4', '/tmp/Episode_1.mp4', ?)
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn insert_chapter_download(pool: &SqlitePool, id: &str, status: &str) {
+        sqlx::query(
+            r#"
+            INSERT INTO chapter_downloads (id, media_id, chapter_id, chapter_number, folder_path, total_images, downloaded_images, status)
+            VALUES (?, 'm1', ?, 1, '/tmp/chapter', 10, 0, ?)
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn track_release(pool: &SqlitePool, latest: f32, notified_up_to: f32) {
+        sqlx::query(
+            r#"
+            INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, last_known_count,
+                last_known_latest_number, user_notified_up_to, last_checked_at)
+            VALUES ('m1', 'ext', 'anime', 12, ?, ?, 0)
+            ON CONFLICT(media_id) DO UPDATE SET
+                last_known_latest_number = excluded.last_known_latest_number,
+                user_notified_up_to = excluded.user_notified_up_to,
+                user_acknowledged_at = NULL
+            "#,
+        )
+        .bind(latest)
+        .bind(notified_up_to)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_summary_counts_each_badge() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+
+        for title in ["one", "two", "three"] {
+            notifications::save_notification_public(
+                pool, DEFAULT_PROFILE_ID,
+                &NotificationPayload::new(NotificationType::Info, title, "message"),
+            )
+            .await
+            .unwrap();
+        }
+        let listed = notifications::list_notifications(pool, DEFAULT_PROFILE_ID, 10, false).await.unwrap();
+        notifications::mark_notification_read(pool, &listed[0].id).await.unwrap();
+        notifications::dismiss_notification(pool, &listed[1].id).await.unwrap();
+
+        track_release(pool, 12.0, 11.0).await;
+        insert_download(pool, "failed", "failed").await;
+        insert_download(pool, "done", "completed").await;
+        insert_chapter_download(pool, "reading", "downloading").await;
+        insert_chapter_download(pool, "broken", "failed").await;
+
+        let summary = get_badge_summary(pool, DEFAULT_PROFILE_ID, 2).await.unwrap();
+        assert_eq!(
+            summary,
+            BadgeSummary {
+                unread_notifications: 1,
+                new_releases: 1,
+                active_downloads: 3,
+                failed_downloads: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn changes_are_emitted_once_per_transition() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+        let mut last = None;
+
+        // Whether the badge task would emit after the change just made
+        let mut emits = |summary: BadgeSummary| record(&mut last, &summary);
+
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "the first summary is always sent");
+        assert!(!emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "nothing changed");
+
+        notifications::save_notification_public(pool, DEFAULT_PROFILE_ID, &NotificationPayload::new(NotificationType::Info, "t", "m"))
+            .await
+            .unwrap();
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "notification created");
+        notifications::mark_all_notifications_read(pool, DEFAULT_PROFILE_ID).await.unwrap();
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "notifications read");
+
+        track_release(pool, 12.0, 11.0).await;
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "new release found");
+        release_checker::acknowledge_new_releases(pool, "m1", None).await.unwrap();
+        let summary = get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap();
+        assert_eq!(summary.new_releases, 0);
+        assert!(emits(summary), "NEW badge dismissed");
+
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 1).await.unwrap()), "download started");
+        insert_download(pool, "ep", "failed").await;
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "download failed");
+        sqlx::query("DELETE FROM downloads WHERE id = 'ep'").execute(pool).await.unwrap();
+        assert!(emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()), "failed download cleared");
+
+        // A change that doesn't move a count isn't sent
+        insert_download(pool, "done", "completed").await;
+        assert!(!emits(get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()));
+    }
+
+    /// Whether `change` invalidated the badges
+    async fn invalidates<F: std::future::Future>(change: F) -> bool {
+        let before = INVALIDATIONS.with(|n| n.get());
+        change.await;
+        INVALIDATIONS.with(|n| n.get()) > before
+    }
+
+    #[tokio::test]
+    async fn reading_a_notification_and_download_status_changes_invalidate() {
+        let (dir, database) = setup().await;
+        let pool = database.pool();
+        let mut last = None;
+
+        notifications::save_notification_public(pool, DEFAULT_PROFILE_ID, &NotificationPayload::new(NotificationType::Info, "t", "m"))
+            .await
+            .unwrap();
+        insert_download(pool, "ep", "failed").await;
+        assert!(record(&mut last, &get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap()));
+
+        let id = notifications::list_notifications(pool, DEFAULT_PROFILE_ID, 10, false).await.unwrap()[0].id.clone();
+        assert!(invalidates(notifications::mark_notification_read(pool, &id)).await);
+        let summary = get_badge_summary(pool, DEFAULT_PROFILE_ID, 0).await.unwrap();
+        assert_eq!(summary.unread_notifications, 0);
+        assert!(record(&mut last, &summary), "notification read");
+
+        let manager = DownloadManager::new(dir.path().to_path_buf()).with_database(std::sync::Arc::new(pool.clone()));
+        manager.load_from_database().await.unwrap();
+        assert!(invalidates(manager.cancel_download("ep")).await);
+        let summary = current_summary(pool, DEFAULT_PROFILE_ID, &manager).await.unwrap();
+        assert_eq!(summary.failed_downloads, 0);
+        assert!(record(&mut last, &summary), "failed download cancelled");
+    }
+
+    #[tokio::test]
+    async fn invalidating_wakes_the_badge_task() {
+        invalidate();
+        tokio::time::timeout(Duration::from_secs(1), CHANGED.notified())
+            .await
+            .expect("a change before the task waits isn't lost");
+    }
+}
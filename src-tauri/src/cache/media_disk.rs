@@ -0,0 +1,513 @@
+// Media Disk Cache
+//
+// Reader pages, covers and thumbnails cached on disk share one size budget so
+// they can't quietly grow to gigabytes. Each consumer registers a namespace
+// with its own directory; enforcement walks all of them and deletes the least
+// recently used files (by modification time, which read_image bumps with
+// mark_used when it serves a file, as access times are often not kept) until
+// the total fits under media_cache_max_mb. The image proxy reads and writes
+// through read_image/write_image: covers of a known media item are stored as
+// `<media id>.<ext>` (what library reports embed), everything else under a
+// hash of its URL. Enforcement runs as the media_cache maintenance chore.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use super::CacheError;
+
+/// app_settings key: size cap of the media disk cache in megabytes
+pub const MAX_SIZE_SETTING: &str = "media_cache_max_mb";
+
+/// Cap when the setting isn't set
+pub const DEFAULT_MAX_MB: u64 = 1024;
+
+/// Extensions a cached cover can have, with their MIME types
+pub const COVER_EXTENSIONS: [(&str, &str); 4] = [
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("png", "image/png"),
+    ("webp", "image/webp"),
+];
+
+/// A consumer of the media disk cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaCacheNamespace {
+    /// Manga reader pages
+    Pages,
+    /// Cover images
+    Covers,
+    /// Episode thumbnails
+    Thumbnails,
+}
+
+impl MediaCacheNamespace {
+    pub const ALL: [MediaCacheNamespace; 3] = [
+        MediaCacheNamespace::Pages,
+        MediaCacheNamespace::Covers,
+        MediaCacheNamespace::Thumbnails,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaCacheNamespace::Pages => "pages",
+            MediaCacheNamespace::Covers => "covers",
+            MediaCacheNamespace::Thumbnails => "thumbnails",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, CacheError> {
+        Self::ALL
+            .into_iter()
+            .find(|namespace| namespace.as_str() == name)
+            .ok_or_else(|| CacheError::UnknownCache(name.to_string()))
+    }
+
+    /// Default directory of the namespace inside the app data directory
+    pub fn default_dir(&self, app_dir: &Path) -> PathBuf {
+        match self {
+            MediaCacheNamespace::Pages => app_dir.join("page-cache"),
+            MediaCacheNamespace::Covers => app_dir.join("covers"),
+            MediaCacheNamespace::Thumbnails => app_dir.join("thumbnails"),
+        }
+    }
+}
+
+/// Registered namespace directories
+static NAMESPACES: LazyLock<Mutex<BTreeMap<MediaCacheNamespace, PathBuf>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Register (or move) a namespace's directory
+pub fn register_namespace(namespace: MediaCacheNamespace, dir: PathBuf) {
+    NAMESPACES.lock().unwrap().insert(namespace, dir);
+}
+
+/// Directory of a registered namespace
+pub fn namespace_dir(namespace: MediaCacheNamespace) -> Option<PathBuf> {
+    NAMESPACES.lock().unwrap().get(&namespace).cloned()
+}
+
+fn registered() -> BTreeMap<MediaCacheNamespace, PathBuf> {
+    NAMESPACES.lock().unwrap().clone()
+}
+
+/// Mark a cached file as just used so enforcement deletes it last
+pub fn mark_used(path: &Path) {
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// File name stem of a media item's cached cover
+pub fn cover_stem(media_id: &str) -> String {
+    media_id
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// The cached cover of a media item in `covers_dir`, with its MIME type
+pub fn find_cover(covers_dir: &Path, media_id: &str) -> Option<(PathBuf, &'static str)> {
+    let stem = cover_stem(media_id);
+    COVER_EXTENSIONS
+        .into_iter()
+        .map(|(extension, mime)| (covers_dir.join(format!("{}.{}", stem, extension)), mime))
+        .find(|(path, _)| path.is_file())
+}
+
+/// Extension matching the image format of `bytes` (jpg when unrecognised)
+fn image_extension(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "png"
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "webp"
+    } else {
+        "jpg"
+    }
+}
+
+fn url_key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+/// Where an image is cached in `dir`: covers of a known media item by its id,
+/// anything else by a hash of the URL
+fn image_path(dir: &Path, namespace: MediaCacheNamespace, url: &str, media_id: Option<&str>) -> Option<PathBuf> {
+    match (namespace, media_id) {
+        (MediaCacheNamespace::Covers, Some(media_id)) => find_cover(dir, media_id).map(|(path, _)| path),
+        _ => Some(dir.join(url_key(url))),
+    }
+}
+
+fn read_image_in(dir: &Path, namespace: MediaCacheNamespace, url: &str, media_id: Option<&str>) -> Option<Vec<u8>> {
+    let path = image_path(dir, namespace, url, media_id)?;
+    let bytes = std::fs::read(&path).ok()?;
+    mark_used(&path);
+    Some(bytes)
+}
+
+fn write_image_in(
+    dir: &Path,
+    namespace: MediaCacheNamespace,
+    url: &str,
+    media_id: Option<&str>,
+    bytes: &[u8],
+) -> std::io::Result<()> {
+    let path = match (namespace, media_id) {
+        (MediaCacheNamespace::Covers, Some(media_id)) => {
+            remove_cover(dir, media_id);
+            dir.join(format!("{}.{}", cover_stem(media_id), image_extension(bytes)))
+        }
+        _ => dir.join(url_key(url)),
+    };
+
+    std::fs::create_dir_all(dir)?;
+    let partial = path.with_extension("part");
+    std::fs::write(&partial, bytes)?;
+    std::fs::rename(&partial, &path)
+}
+
+fn remove_cover(dir: &Path, media_id: &str) {
+    while let Some((path, _)) = find_cover(dir, media_id) {
+        if std::fs::remove_file(&path).is_err() {
+            break;
+        }
+    }
+}
+
+/// Cached bytes of an image, marking the file used. Blocking.
+pub fn read_image(namespace: MediaCacheNamespace, url: &str, media_id: Option<&str>) -> Option<Vec<u8>> {
+    read_image_in(&namespace_dir(namespace)?, namespace, url, media_id)
+}
+
+/// Cache an image fetched from `url`. Blocking.
+pub fn write_image(namespace: MediaCacheNamespace, url: &str, media_id: Option<&str>, bytes: &[u8]) {
+    let Some(dir) = namespace_dir(namespace) else {
+        return;
+    };
+    if let Err(e) = write_image_in(&dir, namespace, url, media_id, bytes) {
+        log::warn!("Failed to cache {} image {}: {}", namespace.as_str(), url, e);
+    }
+}
+
+/// Drop the cached cover of a media item, e.g. after its cover URL changed
+pub fn forget_cover(media_id: &str) {
+    if let Some(dir) = namespace_dir(MediaCacheNamespace::Covers) {
+        remove_cover(&dir, media_id);
+    }
+}
+
+/// Disk usage of one namespace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NamespaceUsage {
+    pub namespace: MediaCacheNamespace,
+    pub dir: String,
+    pub size: u64,
+    pub files: u64,
+}
+
+/// Disk usage of the media cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MediaCacheUsage {
+    pub namespaces: Vec<NamespaceUsage>,
+    pub total_size: u64,
+    pub max_size: u64,
+}
+
+/// What an enforcement or purge removed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MediaCacheCleanup {
+    pub removed_files: u64,
+    pub freed_bytes: u64,
+}
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// Every file under `dir`, without following symlinks
+fn cached_files(dir: &Path, files: &mut Vec<CachedFile>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.path().symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            cached_files(&entry.path(), files);
+        } else if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let accessed = metadata.accessed().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push(CachedFile {
+                path: entry.path(),
+                size: metadata.len(),
+                last_used: modified.max(accessed),
+            });
+        }
+    }
+}
+
+fn namespace_usage(namespaces: &BTreeMap<MediaCacheNamespace, PathBuf>, max_size: u64) -> MediaCacheUsage {
+    let namespaces: Vec<NamespaceUsage> = namespaces
+        .iter()
+        .map(|(namespace, dir)| {
+            let mut files = Vec::new();
+            cached_files(dir, &mut files);
+            NamespaceUsage {
+                namespace: *namespace,
+                dir: dir.to_string_lossy().to_string(),
+                size: files.iter().map(|f| f.size).sum(),
+                files: files.len() as u64,
+            }
+        })
+        .collect();
+
+    MediaCacheUsage {
+        total_size: namespaces.iter().map(|n| n.size).sum(),
+        namespaces,
+        max_size,
+    }
+}
+
+/// Delete least recently used files across `dirs` until they total at most
+/// `max_size` bytes
+fn enforce_cap(dirs: &[PathBuf], max_size: u64) -> MediaCacheCleanup {
+    let mut files = Vec::new();
+    for dir in dirs {
+        cached_files(dir, &mut files);
+    }
+
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+    let mut cleanup = MediaCacheCleanup::default();
+    if total <= max_size {
+        return cleanup;
+    }
+
+    files.sort_by_key(|f| f.last_used);
+    for file in files {
+        if total <= max_size {
+            break;
+        }
+        if std::fs::remove_file(&file.path).is_ok() {
+            total -= file.size;
+            cleanup.removed_files += 1;
+            cleanup.freed_bytes += file.size;
+        }
+    }
+    cleanup
+}
+
+fn purge_dirs(dirs: &[PathBuf]) -> MediaCacheCleanup {
+    let mut files = Vec::new();
+    for dir in dirs {
+        cached_files(dir, &mut files);
+    }
+
+    let mut cleanup = MediaCacheCleanup::default();
+    for file in files {
+        if std::fs::remove_file(&file.path).is_ok() {
+            cleanup.removed_files += 1;
+            cleanup.freed_bytes += file.size;
+        }
+    }
+    cleanup
+}
+
+/// The cap in bytes from media_cache_max_mb (default 1 GB)
+pub async fn max_size(pool: &SqlitePool) -> u64 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(MAX_SIZE_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    let mb = value.and_then(|v| v.trim().parse::<u64>().ok()).unwrap_or(DEFAULT_MAX_MB);
+    mb * 1024 * 1024
+}
+
+/// Usage of every registered namespace
+pub async fn usage(pool: &SqlitePool) -> Result<MediaCacheUsage, CacheError> {
+    let max_size = max_size(pool).await;
+    let namespaces = registered();
+    tokio::task::spawn_blocking(move || namespace_usage(&namespaces, max_size))
+        .await
+        .map_err(|e| CacheError::Storage(e.to_string()))
+}
+
+/// Delete least recently used files until the cache fits under its cap
+pub async fn enforce(pool: &SqlitePool) -> Result<MediaCacheCleanup, CacheError> {
+    let max_size = max_size(pool).await;
+    let dirs: Vec<PathBuf> = registered().into_values().collect();
+    let cleanup = tokio::task::spawn_blocking(move || enforce_cap(&dirs, max_size))
+        .await
+        .map_err(|e| CacheError::Storage(e.to_string()))?;
+
+    if cleanup.removed_files > 0 {
+        log::info!(
+            "Media cache over its cap: removed {} files ({} bytes)",
+            cleanup.removed_files, cleanup.freed_bytes
+        );
+    }
+    Ok(cleanup)
+}
+
+/// Delete everything in one namespace, or in all of them when `namespace` is None
+pub async fn purge(namespace: Option<&str>) -> Result<MediaCacheCleanup, CacheError> {
+    let namespaces = registered();
+    let dirs: Vec<PathBuf> = match namespace {
+        Some(name) => {
+            let namespace = MediaCacheNamespace::parse(name)?;
+            namespaces.get(&namespace).cloned().into_iter().collect()
+        }
+        None => namespaces.into_values().collect(),
+    };
+
+    let cleanup = tokio::task::spawn_blocking(move || purge_dirs(&dirs))
+        .await
+        .map_err(|e| CacheError::Storage(e.to_string()))?;
+    log::info!("Purged media cache {}: {} files", namespace.unwrap_or("(all)"), cleanup.removed_files);
+    Ok(cleanup)
+}
+
+/// Register the built-in namespaces. The cap is enforced by the media_cache
+/// maintenance chore.
+pub fn start_media_cache(app_dir: &Path) {
+    for namespace in MediaCacheNamespace::ALL {
+        register_namespace(namespace, namespace.default_dir(app_dir));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_file(path: &Path, size: usize, age_secs: u64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; size]).unwrap();
+        let at = SystemTime::now() - Duration::from_secs(age_secs);
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_times(std::fs::FileTimes::new().set_accessed(at).set_modified(at)).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_files_go_first_across_namespaces() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pages = temp_dir.path().join("page-cache");
+        let covers = temp_dir.path().join("covers");
+
+        write_file(&pages.join("chapter-1/001.jpg"), 100, 400);
+        write_file(&covers.join("a.jpg"), 100, 300);
+        write_file(&pages.join("chapter-2/001.jpg"), 100, 200);
+        write_file(&covers.join("b.jpg"), 100, 100);
+
+        let cleanup = enforce_cap(&[pages.clone(), covers.clone()], 250);
+        assert_eq!(cleanup, MediaCacheCleanup { removed_files: 2, freed_bytes: 200 });
+        assert!(!pages.join("chapter-1/001.jpg").exists());
+        assert!(!covers.join("a.jpg").exists());
+        assert!(pages.join("chapter-2/001.jpg").exists());
+        assert!(covers.join("b.jpg").exists());
+
+        // Under the cap: nothing to do
+        assert_eq!(enforce_cap(&[pages, covers], 250), MediaCacheCleanup::default());
+    }
+
+    #[test]
+    fn marking_a_file_used_protects_it() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("thumbnails");
+        write_file(&dir.join("old.jpg"), 100, 500);
+        write_file(&dir.join("newer.jpg"), 100, 100);
+
+        mark_used(&dir.join("old.jpg"));
+        enforce_cap(std::slice::from_ref(&dir), 100);
+
+        assert!(dir.join("old.jpg").exists());
+        assert!(!dir.join("newer.jpg").exists());
+    }
+
+    #[test]
+    fn usage_is_reported_per_namespace_and_purge_empties_it() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut namespaces = BTreeMap::new();
+        for namespace in MediaCacheNamespace::ALL {
+            namespaces.insert(namespace, namespace.default_dir(temp_dir.path()));
+        }
+        write_file(&temp_dir.path().join("page-cache/1.jpg"), 10, 0);
+        write_file(&temp_dir.path().join("page-cache/2.jpg"), 20, 0);
+        write_file(&temp_dir.path().join("covers/1.jpg"), 5, 0);
+
+        let usage = namespace_usage(&namespaces, 1000);
+        assert_eq!(usage.total_size, 35);
+        let pages = usage.namespaces.iter().find(|n| n.namespace == MediaCacheNamespace::Pages).unwrap();
+        assert_eq!((pages.size, pages.files), (30, 2));
+
+        let cleanup = purge_dirs(&[namespaces[&MediaCacheNamespace::Pages].clone()]);
+        assert_eq!(cleanup.removed_files, 2);
+        assert_eq!(namespace_usage(&namespaces, 1000).total_size, 5);
+    }
+
+    #[test]
+    fn images_are_cached_by_url_and_covers_by_media_id() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pages = temp_dir.path().join("page-cache");
+        let covers = temp_dir.path().join("covers");
+        let url = "https://cdn.example/chapter-1/001.jpg";
+
+        assert_eq!(read_image_in(&pages, MediaCacheNamespace::Pages, url, None), None);
+        write_image_in(&pages, MediaCacheNamespace::Pages, url, None, b"page").unwrap();
+        assert_eq!(read_image_in(&pages, MediaCacheNamespace::Pages, url, None), Some(b"page".to_vec()));
+        assert_eq!(read_image_in(&pages, MediaCacheNamespace::Pages, "https://cdn.example/other.jpg", None), None);
+
+        // A cover of a known media item lands where library reports look,
+        // under the extension of its format, replacing the previous one
+        write_image_in(&covers, MediaCacheNamespace::Covers, "https://cdn.example/a", Some("mal:1"), b"jpeg").unwrap();
+        assert_eq!(find_cover(&covers, "mal:1"), Some((covers.join("mal_1.jpg"), "image/jpeg")));
+
+        let png = b"\x89PNG\r\n\x1a\nrest";
+        write_image_in(&covers, MediaCacheNamespace::Covers, "https://cdn.example/b", Some("mal:1"), png).unwrap();
+        assert_eq!(find_cover(&covers, "mal:1"), Some((covers.join("mal_1.png"), "image/png")));
+        assert!(!covers.join("mal_1.jpg").exists());
+        assert_eq!(
+            read_image_in(&covers, MediaCacheNamespace::Covers, "https://cdn.example/b", Some("mal:1")),
+            Some(png.to_vec())
+        );
+
+        remove_cover(&covers, "mal:1");
+        assert_eq!(find_cover(&covers, "mal:1"), None);
+    }
+
+    #[test]
+    fn reading_an_image_marks_it_used() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("thumbnails");
+        let url = "https://cdn.example/ep1.jpg";
+        write_image_in(&dir, MediaCacheNamespace::Thumbnails, url, None, &[0u8; 100]).unwrap();
+        let path = dir.join(url_key(url));
+        let at = SystemTime::now() - Duration::from_secs(500);
+        std::fs::File::options().write(true).open(&path).unwrap()
+            .set_times(std::fs::FileTimes::new().set_accessed(at).set_modified(at)).unwrap();
+        write_file(&dir.join("newer.jpg"), 100, 100);
+
+        read_image_in(&dir, MediaCacheNamespace::Thumbnails, url, None).unwrap();
+        enforce_cap(std::slice::from_ref(&dir), 100);
+
+        assert!(path.exists());
+        assert!(!dir.join("newer.jpg").exists());
+    }
+
+    #[test]
+    fn unknown_namespaces_are_rejected() {
+        assert_eq!(MediaCacheNamespace::parse("covers"), Ok(MediaCacheNamespace::Covers));
+        assert!(matches!(MediaCacheNamespace::parse("videos"), Err(CacheError::UnknownCache(_))));
+    }
+}
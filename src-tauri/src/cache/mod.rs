@@ -0,0 +1,216 @@
+// Cache Registry
+//
+// Every cache the backend keeps, addressable by name. Lets a single cache be
+// cleared on its own (e.g. the discover rows while iterating on a page)
+// instead of wiping everything, and gives the expiry sweep one place to go
+// through all of them.
+
+pub mod media_disk;
+pub mod warmup;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::fmt;
+
+use crate::database::discover_cache;
+use crate::jikan::bridge;
+use crate::jikan::client::JIKAN;
+
+/// A named cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheName {
+    /// Discover/home page results (discover_cache table)
+    Discover,
+    /// In-memory Jikan responses kept for ETag revalidation
+    Jikan,
+    /// MAL → AllAnime id mappings resolved by the bridge (id_mappings table)
+    IdMappings,
+    /// Video sources prefetched by the startup warm-up
+    VideoSources,
+}
+
+impl CacheName {
+    /// Every registered cache
+    pub const ALL: [CacheName; 4] = [
+        CacheName::Discover,
+        CacheName::Jikan,
+        CacheName::IdMappings,
+        CacheName::VideoSources,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheName::Discover => "discover",
+            CacheName::Jikan => "jikan",
+            CacheName::IdMappings => "id_mappings",
+            CacheName::VideoSources => "video_sources",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, CacheError> {
+        Self::ALL
+            .into_iter()
+            .find(|cache| cache.as_str() == name)
+            .ok_or_else(|| CacheError::UnknownCache(name.to_string()))
+    }
+
+    /// Remove every entry, returning how many were removed
+    pub async fn clear(&self, pool: &SqlitePool) -> Result<u64, CacheError> {
+        let removed = match self {
+            CacheName::Discover => discover_cache::clear_discover_cache(pool)
+                .await
+                .map_err(|e| CacheError::Storage(e.to_string()))?,
+            CacheName::Jikan => JIKAN.clear_cache() as u64,
+            CacheName::IdMappings => bridge::clear_cached_mappings(pool)
+                .await
+                .map_err(CacheError::Storage)?,
+            CacheName::VideoSources => warmup::clear_warmed_sources() as u64,
+        };
+
+        log::info!("Cleared {} entries from the {} cache", removed, self.as_str());
+        Ok(removed)
+    }
+
+    /// Remove entries past their TTL. Caches without a TTL are left alone.
+    pub async fn remove_expired(&self, pool: &SqlitePool) -> Result<u64, CacheError> {
+        match self {
+            CacheName::Discover => discover_cache::clear_expired_cache(pool)
+                .await
+                .map_err(|e| CacheError::Storage(e.to_string())),
+            CacheName::Jikan => Ok(JIKAN.remove_expired() as u64),
+            CacheName::IdMappings => Ok(0),
+            CacheName::VideoSources => Ok(warmup::remove_expired_sources() as u64),
+        }
+    }
+
+    /// Number of entries currently held
+    pub async fn entry_count(&self, pool: &SqlitePool) -> Result<u64, CacheError> {
+        match self {
+            CacheName::Discover => discover_cache::count_discover_cache(pool)
+                .await
+                .map_err(|e| CacheError::Storage(e.to_string())),
+            CacheName::Jikan => Ok(JIKAN.cache_len() as u64),
+            CacheName::IdMappings => bridge::count_cached_mappings(pool)
+                .await
+                .map_err(CacheError::Storage),
+            CacheName::VideoSources => Ok(warmup::warmed_sources_len() as u64),
+        }
+    }
+}
+
+/// Entry count of one cache
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntryCount {
+    pub name: &'static str,
+    pub entries: u64,
+}
+
+/// Cache sizes and how the startup warm-up went
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub caches: Vec<CacheEntryCount>,
+    pub warmup: warmup::WarmupReport,
+}
+
+/// Entry counts for every cache, plus the warm-up report
+pub async fn cache_stats(pool: &SqlitePool) -> CacheStats {
+    let mut caches = Vec::with_capacity(CacheName::ALL.len());
+    for cache in CacheName::ALL {
+        match cache.entry_count(pool).await {
+            Ok(entries) => caches.push(CacheEntryCount { name: cache.as_str(), entries }),
+            Err(e) => log::warn!("Failed to count the {} cache: {}", cache.as_str(), e),
+        }
+    }
+
+    CacheStats {
+        caches,
+        warmup: warmup::warmup_report(),
+    }
+}
+
+/// Errors from clearing a cache by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum CacheError {
+    /// The name isn't a registered cache
+    UnknownCache(String),
+    /// The cache's backing store failed
+    Storage(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::UnknownCache(name) => {
+                let valid: Vec<&str> = CacheName::ALL.iter().map(|c| c.as_str()).collect();
+                write!(f, "Unknown cache '{}'. Valid caches: {}", name, valid.join(", "))
+            }
+            CacheError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+/// Clear a single cache by name, returning the number of entries removed
+pub async fn clear_cache(pool: &SqlitePool, name: &str) -> Result<u64, CacheError> {
+    CacheName::parse(name)?.clear(pool).await
+}
+
+/// Sweep expired entries out of every cache, returning the total removed
+pub async fn remove_expired_entries(pool: &SqlitePool) -> u64 {
+    let mut removed = 0;
+    for cache in CacheName::ALL {
+        match cache.remove_expired(pool).await {
+            Ok(count) => removed += count,
+            Err(e) => log::warn!("Failed to sweep the {} cache: {}", cache.as_str(), e),
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    #[test]
+    fn parse_round_trips_registered_names() {
+        for cache in CacheName::ALL {
+            assert_eq!(CacheName::parse(cache.as_str()), Ok(cache));
+        }
+    }
+
+    #[test]
+    fn unknown_name_lists_valid_caches() {
+        let err = CacheName::parse("sources").unwrap_err();
+        assert_eq!(err, CacheError::UnknownCache("sources".to_string()));
+        assert_eq!(
+            err.to_string(),
+            "Unknown cache 'sources'. Valid caches: discover, jikan, id_mappings, video_sources"
+        );
+    }
+
+    #[tokio::test]
+    async fn clear_cache_only_touches_the_named_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        discover_cache::save_discover_cache(pool, "home:trending", "[]", "anime").await.unwrap();
+        discover_cache::save_discover_cache(pool, "manga:popular:sfw=true", "[]", "manga").await.unwrap();
+        bridge::save_mapping(pool, "1", "abc", "anime", "Cowboy Bebop", Some(1.0)).await.unwrap();
+
+        assert_eq!(clear_cache(pool, "discover").await.unwrap(), 2);
+        assert!(discover_cache::get_discover_cache(pool, "home:trending").await.unwrap().is_none());
+        assert_eq!(bridge::get_cached_mapping(pool, "1").await.unwrap(), Some("abc".to_string()));
+
+        assert_eq!(clear_cache(pool, "id_mappings").await.unwrap(), 1);
+        assert!(bridge::get_cached_mapping(pool, "1").await.unwrap().is_none());
+
+        assert!(matches!(
+            clear_cache(pool, "search").await,
+            Err(CacheError::UnknownCache(_))
+        ));
+    }
+}
@@ -0,0 +1,555 @@
+// Startup Cache Warming
+//
+// Opt-in: once the app has gone quiet after start, prefetch the Jikan details
+// of the library titles watched/read most recently, and for the first few
+// anime the video sources of the next episode, so opening them right after
+// launch doesn't wait on the network.
+//
+// Warming is strictly best effort. It waits for the UI to stop invoking
+// commands, spaces requests out, and stops for good on the first command the
+// user triggers, when the network looks down, or when the extension's circuit
+// breaker is open.
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::extensions::circuit_breaker;
+use crate::commands::{self, AppState};
+use crate::extensions::{adult, VideoSources};
+use crate::jikan::client::JIKAN;
+use crate::downloads::network;
+use crate::jikan::{anime, manga, numbering};
+
+/// Number of library titles to warm on start (0 = off)
+pub const WARMUP_COUNT_SETTING: &str = "cache_warmup_count";
+
+/// Upper bound for the setting, to stay well inside Jikan's per-minute limit
+const MAX_WARMUP_COUNT: i64 = 50;
+
+/// How many of the warmed anime also get next-episode sources
+const SOURCES_WARMUP_COUNT: usize = 3;
+
+/// The UI must be quiet this long before warming starts
+const IDLE_BEFORE_WARMUP: Duration = Duration::from_secs(5);
+
+/// Give up if the UI never goes quiet
+const MAX_IDLE_WAIT: Duration = Duration::from_secs(120);
+
+/// Pause between titles, on top of the Jikan client's own rate limit
+const WARMUP_THROTTLE: Duration = Duration::from_secs(2);
+
+/// Consecutive failed fetches, while the network is up, before giving up
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Jikan's API host, probed before giving up as offline
+const JIKAN_PROBE_ADDRESS: &str = "api.jikan.moe:443";
+
+/// Stream URLs expire, so warmed sources are only handed out for a while
+const SOURCES_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// AllAnime, the extension the bridge maps MAL ids to
+const ALLANIME_EXTENSION_ID: &str = "com.allanime.source";
+
+/// Commands the UI invokes on its own (timers, players) rather than because
+/// the user did something
+const BACKGROUND_COMMANDS: &[&str] = &[
+    "report_playback_heartbeat",
+    "get_storage_usage",
+    "get_cache_stats",
+];
+
+static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Bumped on every user-triggered command
+static ACTIVITY: AtomicU64 = AtomicU64::new(0);
+
+/// When ACTIVITY was last bumped, in ms since STARTED_AT
+static LAST_ACTIVITY_MS: AtomicU64 = AtomicU64::new(0);
+
+static REPORT: LazyLock<Mutex<WarmupReport>> = LazyLock::new(|| Mutex::new(WarmupReport::default()));
+
+/// Prefetched video sources, keyed by (extension id, episode id, allow_adult)
+/// so sources fetched with adult content aren't handed to filtered calls
+static WARMED_SOURCES: LazyLock<Mutex<HashMap<WarmedKey, (VideoSources, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+type WarmedKey = (String, String, bool);
+
+/// Outcome of this run's warm-up
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WarmupReport {
+    /// Titles whose details were prefetched
+    pub warmed: u32,
+    /// Episodes whose video sources were prefetched
+    pub sources_warmed: u32,
+    /// Why warming ended early (user_activity, offline, errors, never_idle, ...)
+    pub stopped_reason: Option<String>,
+    /// Unix timestamp (ms) the warm-up ended
+    pub finished_at: Option<i64>,
+}
+
+/// A library title to warm
+#[derive(Debug, Clone, PartialEq)]
+struct WarmupTarget {
+    media_id: String,
+    mal_id: i64,
+    media_type: String,
+    /// AllAnime show id, when already known
+    allanime_id: Option<String>,
+    /// Episode after the last one finished
+    next_episode: Option<f64>,
+}
+
+/// Whether a command was triggered by the user rather than a plugin or a
+/// background poll
+pub fn is_user_command(command: &str) -> bool {
+    !command.starts_with("plugin:") && !BACKGROUND_COMMANDS.contains(&command)
+}
+
+/// Record a command invocation. Anything but a background command counts as
+/// the user being active, which stops a running warm-up.
+pub fn note_command(command: &str) {
+    if !is_user_command(command) {
+        return;
+    }
+
+    LAST_ACTIVITY_MS.store(STARTED_AT.elapsed().as_millis() as u64, Ordering::Relaxed);
+    ACTIVITY.fetch_add(1, Ordering::Relaxed);
+}
+
+fn idle_for() -> Duration {
+    let last = Duration::from_millis(LAST_ACTIVITY_MS.load(Ordering::Relaxed));
+    STARTED_AT.elapsed().saturating_sub(last)
+}
+
+/// This run's warm-up so far
+pub fn warmup_report() -> WarmupReport {
+    REPORT.lock().unwrap().clone()
+}
+
+/// Prefetched sources for an episode, handed out once while still fresh.
+/// Only sources warmed in the same adult mode are returned.
+pub fn take_warmed_sources(extension_id: &str, episode_id: &str, allow_adult: bool) -> Option<VideoSources> {
+    let mut warmed = WARMED_SOURCES.lock().unwrap();
+    let key = (extension_id.to_string(), episode_id.to_string(), allow_adult);
+    let (sources, fetched_at) = warmed.remove(&key)?;
+    (fetched_at.elapsed() < SOURCES_TTL).then_some(sources)
+}
+
+/// Number of prefetched sources waiting to be used
+pub fn warmed_sources_len() -> usize {
+    WARMED_SOURCES.lock().unwrap().len()
+}
+
+/// Drop every prefetched source, returning how many were dropped
+pub fn clear_warmed_sources() -> usize {
+    let mut warmed = WARMED_SOURCES.lock().unwrap();
+    let count = warmed.len();
+    warmed.clear();
+    count
+}
+
+/// Drop prefetched sources past their TTL
+pub fn remove_expired_sources() -> usize {
+    let mut warmed = WARMED_SOURCES.lock().unwrap();
+    let before = warmed.len();
+    warmed.retain(|_, (_, fetched_at)| fetched_at.elapsed() < SOURCES_TTL);
+    before - warmed.len()
+}
+
+/// Read the warm-up count setting, clamped to 0..=MAX_WARMUP_COUNT
+pub async fn get_warmup_count(pool: &SqlitePool) -> i64 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(WARMUP_COUNT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+
+    value
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(0)
+        .clamp(0, MAX_WARMUP_COUNT)
+}
+
+/// The `limit` library titles with the most recent watch/read activity.
+/// Only titles with a MAL id can be warmed.
+async fn warmup_targets(pool: &SqlitePool, profile_id: i64, limit: i64) -> anyhow::Result<Vec<WarmupTarget>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            m.id AS media_id,
+            m.media_type,
+            m.extension_id,
+            COALESCE(m.mal_id, CASE WHEN m.extension_id = 'jikan' THEN m.id END) AS mal_id,
+            im.allanime_id,
+            (SELECT MAX(wh.episode_number) FROM watch_history wh
+              WHERE wh.profile_id = l.profile_id AND wh.media_id = m.id AND wh.completed = 1) AS last_completed,
+            MAX(
+                COALESCE((SELECT MAX(wh.last_watched) FROM watch_history wh
+                          WHERE wh.profile_id = l.profile_id AND wh.media_id = m.id), ''),
+                COALESCE((SELECT MAX(rh.last_read) FROM reading_history rh
+                          WHERE rh.profile_id = l.profile_id AND rh.media_id = m.id), ''),
+                l.updated_at
+            ) AS last_activity
+        FROM library l
+        JOIN media m ON m.id = l.media_id
+        LEFT JOIN id_mappings im
+            ON im.mal_id = COALESCE(m.mal_id, CASE WHEN m.extension_id = 'jikan' THEN m.id END)
+        WHERE l.profile_id = ?
+          AND COALESCE(m.mal_id, CASE WHEN m.extension_id = 'jikan' THEN m.id END) IS NOT NULL
+        ORDER BY last_activity DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(profile_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let targets = rows
+        .into_iter()
+        .filter_map(|row| {
+            let mal_id = row.get::<String, _>("mal_id").parse::<i64>().ok()?;
+            let extension_id: String = row.get("extension_id");
+            let media_id: String = row.get("media_id");
+            let allanime_id = if extension_id == ALLANIME_EXTENSION_ID {
+                Some(media_id.clone())
+            } else {
+                row.get("allanime_id")
+            };
+
+            Some(WarmupTarget {
+                media_id,
+                mal_id,
+                media_type: row.get("media_type"),
+                allanime_id,
+                next_episode: row.get::<Option<f64>, _>("last_completed").map(|n| n.floor() + 1.0),
+            })
+        })
+        .collect();
+
+    Ok(targets)
+}
+
+fn finish(reason: Option<&str>) {
+    let mut report = REPORT.lock().unwrap();
+    report.stopped_reason = reason.map(str::to_string);
+    report.finished_at = Some(chrono::Utc::now().timestamp_millis());
+
+    match reason {
+        Some(reason) => log::info!(
+            "Cache warm-up stopped ({}): {} title(s), {} episode source(s) warmed",
+            reason, report.warmed, report.sources_warmed
+        ),
+        None => log::info!(
+            "Cache warm-up finished: {} title(s), {} episode source(s) warmed",
+            report.warmed, report.sources_warmed
+        ),
+    }
+}
+
+/// Wait for IDLE_BEFORE_WARMUP without user commands. False if the UI never
+/// went quiet within MAX_IDLE_WAIT.
+async fn wait_for_idle() -> bool {
+    let deadline = Instant::now() + MAX_IDLE_WAIT;
+    loop {
+        let idle = idle_for();
+        if idle >= IDLE_BEFORE_WARMUP {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(IDLE_BEFORE_WARMUP - idle).await;
+    }
+}
+
+/// Prefetch the next episode's sources for an anime whose AllAnime id is known
+async fn warm_sources(app: &AppHandle, pool: &SqlitePool, target: &WarmupTarget) -> Result<bool, String> {
+    let (Some(allanime_id), Some(mut next_episode)) = (target.allanime_id.clone(), target.next_episode) else {
+        return Ok(false);
+    };
+
+    // Progress of a MAL entry is in MAL's numbering; AllAnime may count differently
+    if allanime_id != target.media_id {
+        let offset = numbering::get_numbering_offset(pool, &target.mal_id.to_string(), ALLANIME_EXTENSION_ID).await?;
+        next_episode = numbering::to_source_number(next_episode, offset);
+    }
+
+    circuit_breaker::peek(ALLANIME_EXTENSION_ID).map_err(|e| e.to_string())?;
+
+    let Ok(extension) = app.state::<AppState>().extension(ALLANIME_EXTENSION_ID) else {
+        return Ok(false);
+    };
+    let allow_adult = adult::background_allow_adult(pool).await;
+
+    let fetched = tokio::task::spawn_blocking(move || -> Result<Option<(String, VideoSources)>, String> {
+        let runtime = commands::background_runtime(extension, allow_adult)?;
+
+        let details = circuit_breaker::track(ALLANIME_EXTENSION_ID, runtime.get_details(&allanime_id))
+            .map_err(|e| format!("Failed to get details: {}", e))?;
+        let Some(episode) = details.episodes.iter().find(|ep| (ep.number as f64 - next_episode).abs() < 0.01) else {
+            return Ok(None);
+        };
+
+        let sources = circuit_breaker::track(ALLANIME_EXTENSION_ID, runtime.get_sources(&episode.id))
+            .map_err(|e| format!("Failed to get sources: {}", e))?;
+        Ok(Some((episode.id.clone(), sources)))
+    })
+    .await
+    .map_err(|e| format!("Task error: {}", e))??;
+
+    let Some((episode_id, sources)) = fetched else {
+        return Ok(false);
+    };
+
+    WARMED_SOURCES.lock().unwrap().insert(
+        (ALLANIME_EXTENSION_ID.to_string(), episode_id, allow_adult),
+        (sources, Instant::now()),
+    );
+    Ok(true)
+}
+
+async fn run_warmup(app: &AppHandle, pool: &SqlitePool, count: i64) -> Option<&'static str> {
+    if !wait_for_idle().await {
+        return Some("never_idle");
+    }
+
+    let profile_id = app.state::<AppState>().profile_id();
+    let targets = match warmup_targets(pool, profile_id, count).await {
+        Ok(targets) => targets,
+        Err(e) => {
+            log::warn!("Failed to load cache warm-up targets: {}", e);
+            return Some("error");
+        }
+    };
+
+    log::info!("Warming caches for {} library title(s)", targets.len());
+
+    let activity_at_start = ACTIVITY.load(Ordering::Relaxed);
+    let user_active = || ACTIVITY.load(Ordering::Relaxed) != activity_at_start;
+    let mut failures = 0;
+    let mut sources_attempted = 0;
+
+    for target in targets {
+        if user_active() {
+            return Some("user_activity");
+        }
+
+        let mal_id = target.mal_id;
+        let is_manga = target.media_type == "manga";
+        let fetched = tokio::task::spawn_blocking(move || {
+            JIKAN.prefetch(|| {
+                if is_manga {
+                    manga::manga_details(mal_id).map(|_| ())
+                } else {
+                    anime::anime_details(mal_id).map(|_| ())
+                }
+            })
+        })
+        .await
+        .map_err(|e| format!("Task error: {}", e))
+        .and_then(|result| result);
+
+        match fetched {
+            Ok(()) => {
+                failures = 0;
+                REPORT.lock().unwrap().warmed += 1;
+            }
+            Err(e) => {
+                log::debug!("Cache warm-up failed for {}: {}", target.media_id, e);
+                // A failing API isn't a dead connection; only stop as offline
+                // when neither Jikan nor the probe addresses can be reached
+                let mut addresses = network::probe_addresses(Some(pool)).await;
+                addresses.push(JIKAN_PROBE_ADDRESS.to_string());
+                if !network::probe(&addresses).await {
+                    return Some("offline");
+                }
+                failures += 1;
+                if failures >= MAX_CONSECUTIVE_FAILURES {
+                    return Some("errors");
+                }
+            }
+        }
+
+        if !is_manga && sources_attempted < SOURCES_WARMUP_COUNT && target.next_episode.is_some() && !user_active() {
+            sources_attempted += 1;
+            match warm_sources(app, pool, &target).await {
+                Ok(true) => REPORT.lock().unwrap().sources_warmed += 1,
+                Ok(false) => {}
+                Err(e) => log::debug!("Source warm-up failed for {}: {}", target.media_id, e),
+            }
+        }
+
+        tokio::time::sleep(WARMUP_THROTTLE).await;
+    }
+
+    None
+}
+
+/// Warm caches for recently used library titles, if enabled
+pub fn start_warmup_task(app: AppHandle, pool: std::sync::Arc<SqlitePool>) {
+    // Anchor the activity clock at startup
+    LazyLock::force(&STARTED_AT);
+
+    tauri::async_runtime::spawn(async move {
+        let count = get_warmup_count(&pool).await;
+        if count == 0 {
+            log::debug!("Cache warm-up is disabled");
+            return;
+        }
+
+        let reason = run_warmup(&app, &pool, count).await;
+        finish(reason);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::jikan::bridge;
+
+    async fn add_library_media(pool: &SqlitePool, id: &str, extension_id: &str, mal_id: Option<&str>, updated_at: &str) {
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, mal_id) VALUES (?, ?, ?, 'anime', ?)")
+            .bind(id)
+            .bind(extension_id)
+            .bind(id)
+            .bind(mal_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO library (profile_id, media_id, status, updated_at) VALUES (1, ?, 'watching', ?)")
+            .bind(id)
+            .bind(updated_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn watch(pool: &SqlitePool, media_id: &str, episode_number: f64, completed: bool, at: &str) {
+        sqlx::query(
+            "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed, last_watched)
+             VALUES (1, ?, ?, ?, 0, ?, ?)",
+        )
+        .bind(media_id)
+        .bind(format!("{}-{}", media_id, episode_number))
+        .bind(episode_number)
+        .bind(completed)
+        .bind(at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn targets_follow_recent_activity() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        add_library_media(pool, "21", "jikan", None, "2024-01-01 00:00:00").await;
+        add_library_media(pool, "abc", ALLANIME_EXTENSION_ID, Some("5114"), "2024-01-02 00:00:00").await;
+        add_library_media(pool, "xyz", "other", Some("1"), "2024-01-03 00:00:00").await;
+        add_library_media(pool, "nomal", "other", None, "2024-06-01 00:00:00").await;
+
+        watch(pool, "21", 3.0, true, "2024-05-01 00:00:00").await;
+        watch(pool, "21", 4.0, false, "2024-05-02 00:00:00").await;
+        bridge::save_mapping(pool, "1", "xyz-allanime", "anime", "Cowboy Bebop", Some(1.0)).await.unwrap();
+
+        let targets = warmup_targets(pool, 1, 10).await.unwrap();
+        let ids: Vec<&str> = targets.iter().map(|t| t.media_id.as_str()).collect();
+        assert_eq!(ids, vec!["21", "xyz", "abc"]);
+
+        assert_eq!(targets[0].mal_id, 21);
+        assert_eq!(targets[0].next_episode, Some(4.0));
+        assert_eq!(targets[0].allanime_id, None);
+        assert_eq!(targets[1].allanime_id.as_deref(), Some("xyz-allanime"));
+        assert_eq!(targets[1].next_episode, None);
+        assert_eq!(targets[2].allanime_id.as_deref(), Some("abc"));
+
+        assert_eq!(warmup_targets(pool, 1, 1).await.unwrap().len(), 1);
+        assert!(warmup_targets(pool, 2, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn warmup_count_defaults_to_off_and_is_clamped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(get_warmup_count(pool).await, 0);
+
+        for (value, expected) in [("10", 10), ("500", MAX_WARMUP_COUNT), ("-3", 0), ("lots", 0)] {
+            sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+                .bind(WARMUP_COUNT_SETTING)
+                .bind(value)
+                .execute(pool)
+                .await
+                .unwrap();
+            assert_eq!(get_warmup_count(pool).await, expected, "setting {:?}", value);
+        }
+    }
+
+    #[test]
+    fn background_commands_are_not_user_activity() {
+        let before = ACTIVITY.load(Ordering::Relaxed);
+        note_command("report_playback_heartbeat");
+        note_command("plugin:event|listen");
+        assert_eq!(ACTIVITY.load(Ordering::Relaxed), before);
+
+        note_command("get_media_details");
+        assert!(ACTIVITY.load(Ordering::Relaxed) > before);
+        assert!(idle_for() < IDLE_BEFORE_WARMUP);
+    }
+
+    #[test]
+    fn warmed_sources_are_handed_out_once() {
+        let sources: VideoSources = serde_json::from_value(serde_json::json!({ "sources": [], "subtitles": [] })).unwrap();
+        WARMED_SOURCES.lock().unwrap().insert(
+            ("ext".to_string(), "ep-1".to_string(), false),
+            (sources.clone(), Instant::now()),
+        );
+        WARMED_SOURCES.lock().unwrap().insert(
+            ("ext".to_string(), "ep-old".to_string(), false),
+            (sources, Instant::now() - SOURCES_TTL),
+        );
+
+        assert!(take_warmed_sources("ext", "ep-1", false).is_some());
+        assert!(take_warmed_sources("ext", "ep-1", false).is_none());
+        assert!(take_warmed_sources("ext", "ep-old", false).is_none());
+    }
+
+    #[test]
+    fn warmed_sources_stay_in_their_adult_mode() {
+        let sources = |url: &str| -> VideoSources {
+            serde_json::from_value(serde_json::json!({
+                "sources": [{ "url": url, "quality": "1080p", "type": "mp4", "server": "Default" }],
+                "subtitles": []
+            }))
+            .unwrap()
+        };
+        WARMED_SOURCES.lock().unwrap().insert(
+            ("ext".to_string(), "ep-mode".to_string(), true),
+            (sources("https://cdn.example.com/unfiltered.mp4"), Instant::now()),
+        );
+
+        // A filtered call for the same episode doesn't get the unfiltered sources
+        assert!(take_warmed_sources("ext", "ep-mode", false).is_none());
+
+        WARMED_SOURCES.lock().unwrap().insert(
+            ("ext".to_string(), "ep-mode".to_string(), false),
+            (sources("https://cdn.example.com/filtered.mp4"), Instant::now()),
+        );
+        let filtered = take_warmed_sources("ext", "ep-mode", false).unwrap();
+        assert_eq!(filtered.sources[0].url, "https://cdn.example.com/filtered.mp4");
+        let unfiltered = take_warmed_sources("ext", "ep-mode", true).unwrap();
+        assert_eq!(unfiltered.sources[0].url, "https://cdn.example.com/unfiltered.mp4");
+    }
+}
@@ -7,59 +7,301 @@
 // due to QuickJS's thread-safety limitations. In production, we'd use a thread-local
 // runtime pool.
 
-use crate::extensions::{ChapterImages, Extension, ExtensionMetadata, ExtensionRuntime, HomeCategory, HomeContent, MangaDetails, MediaDetails, SearchResult, SearchResults, TagsResult, VideoSources};
+use crate::extensions::circuit_breaker::{self, BreakerStatus};
+use crate::extensions::ExtensionError;
+use crate::extensions::adult;
+use crate::extensions::{ChapterImage, ChapterImages, Extension, ExtensionMetadata, ExtensionRuntime, HomeCategory, HomeContent, MangaDetails, MediaDetails, SearchResult, SearchResults, TagsResult, VideoSources};
+use crate::extensions::language::{apply_language_preference, PREFERRED_LANGUAGE_SETTING};
+use crate::badges::{self, BadgeSummary};
 use crate::database::Database;
-use crate::downloads::{DownloadManager, DownloadProgress, chapter_downloads};
+use crate::database::age_rating::{self, AgeRating, AgeRatingLimit};
+use crate::database::hidden_media::HiddenSet;
+use crate::database::profiles::{self, CurrentProfile, Profile};
+use crate::downloads::{DownloadManager, DownloadProgress, DownloadStatus, QueueError, chapter_downloads, disk_space, lazy_source, size_estimate};
+use crate::downloads::history::DownloadHistoryEntry;
+use crate::maintenance::ActivityMonitor;
 use crate::request_headers::build_image_request;
+use crate::safe_mode::{self, SafeMode, SafeModeState};
+use crate::video_server::VideoServerHandle;
 use crate::VideoServerInfo;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-use tauri::{AppHandle, Emitter, Manager, State};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tauri::{AppHandle, Manager, State};
 use sqlx;
 
 /// Global state for loaded extensions (stores just the code, not runtimes)
 /// Uses RwLock to allow concurrent reads (most commands) while blocking only for writes (loading extensions)
 pub struct AppState {
-    pub extensions: RwLock<Vec<Extension>>,
+    extensions: RwLock<Vec<Extension>>,
     pub database: Arc<Database>,
+    /// Fed by commands, playback heartbeats and downloads; tells the
+    /// maintenance scheduler when the app is idle
+    pub activity: ActivityMonitor,
+    /// Profile that library, history, tags and notifications are scoped to
+    pub profile: CurrentProfile,
 }
 
 impl AppState {
-    pub fn new(database: Database) -> Self {
+    pub fn new(database: Database, profile_id: i64) -> Self {
         Self {
             extensions: RwLock::new(Vec::new()),
             database: Arc::new(database),
+            activity: ActivityMonitor::default(),
+            profile: CurrentProfile::new(profile_id),
         }
     }
+
+    /// Id of the active profile
+    pub fn profile_id(&self) -> i64 {
+        self.profile.id()
+    }
+
+    /// The loaded extensions. Writers only ever retain or push, so the list
+    /// is intact even if one of them panicked; a poisoned lock is recovered
+    /// instead of failing every command from then on.
+    pub fn extensions(&self) -> RwLockReadGuard<'_, Vec<Extension>> {
+        self.extensions.read().unwrap_or_else(|poisoned| {
+            log::warn!("Extensions lock was poisoned, recovering");
+            poisoned.into_inner()
+        })
+    }
+
+    /// The loaded extensions, for loading or unloading one
+    pub fn extensions_mut(&self) -> RwLockWriteGuard<'_, Vec<Extension>> {
+        self.extensions.write().unwrap_or_else(|poisoned| {
+            log::warn!("Extensions lock was poisoned, recovering");
+            poisoned.into_inner()
+        })
+    }
+
+    /// A copy of a loaded extension, so no lock is held while it runs
+    pub fn extension(&self, extension_id: &str) -> Result<Extension, ExtensionError> {
+        self.extensions()
+            .iter()
+            .find(|ext| ext.metadata.id == extension_id)
+            .cloned()
+            .ok_or_else(|| ExtensionError::NotFound(extension_id.to_string()))
+    }
+}
+
+/// Error of a command that runs an extension. Extension errors keep their
+/// type (`{ kind, detail }`) so the UI can tell a missing extension from a
+/// failed request; anything else is a message, like every other command's.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(untagged)]
+pub enum CommandError {
+    Extension(ExtensionError),
+    Message(String),
+}
+
+impl From<ExtensionError> for CommandError {
+    fn from(error: ExtensionError) -> Self {
+        CommandError::Extension(error)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Message(message)
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Extension(error) => error.fmt(f),
+            CommandError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Read the user's preferred content language (None when unset)
+async fn preferred_content_language(state: &AppState) -> Option<String> {
+    sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+        .bind(PREFERRED_LANGUAGE_SETTING)
+        .fetch_optional(state.database.pool())
+        .await
+        .ok()
+        .flatten()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Create a runtime for an extension unless its circuit breaker is open.
+/// A runtime that fails to initialise counts as a failure of the extension;
+/// one that starts doesn't count as a success, the call made with it does.
+pub(crate) fn guarded_runtime(extension: Extension, allow_adult: bool) -> Result<ExtensionRuntime, String> {
+    let extension_id = extension.metadata.id.clone();
+    circuit_breaker::check(&extension_id).map_err(|e| e.to_string())?;
+
+    ExtensionRuntime::with_options(extension, allow_adult).map_err(|e| {
+        circuit_breaker::record_failure(&extension_id, &e.to_string());
+        format!("Failed to create runtime: {}", e)
+    })
+}
+
+/// guarded_runtime for work no one is waiting on: takes a call from the
+/// extension's background budget first and fails when it's used up, so the
+/// caller defers. Commands the user started use guarded_runtime directly.
+pub(crate) fn background_runtime(extension: Extension, allow_adult: bool) -> Result<ExtensionRuntime, String> {
+    crate::extensions::background_budget::take(&extension.metadata.id).map_err(|e| e.to_string())?;
+    guarded_runtime(extension, allow_adult)
 }
 
 /// Load an extension from JavaScript code
-/// If an extension with the same ID exists, it will be replaced
+/// If an extension with the same ID exists, it will be replaced.
+/// Its icon is cached locally in the background (see extensions::icons).
 #[tauri::command]
 pub async fn load_extension(
     state: State<'_, AppState>,
+    storage_paths: State<'_, StoragePaths>,
     code: String,
 ) -> Result<ExtensionMetadata, String> {
     let extension = Extension::from_code(&code)
         .map_err(|e| format!("Failed to parse extension: {}", e))?;
 
+    register_extension(&state, &storage_paths.app_dir, extension)
+}
+
+/// Add a parsed extension to the loaded ones, replacing any with the same ID
+pub(crate) fn register_extension(
+    state: &AppState,
+    app_dir: &std::path::Path,
+    extension: Extension,
+) -> Result<ExtensionMetadata, String> {
     let metadata = extension.metadata.clone();
 
-    let mut extensions = state.extensions.write()
-        .map_err(|e| format!("Failed to write lock extensions: {}", e))?;
+    let mut extensions = state.extensions_mut();
 
     // Remove any existing extension with the same ID
     extensions.retain(|ext| ext.metadata.id != metadata.id);
 
     // Add the new extension
     extensions.push(extension);
+    drop(extensions);
 
     log::debug!("Loaded extension: {}", metadata.name);
 
+    // Fetch (or refresh after an update) the icon without holding up loading
+    let icons_dir = crate::extensions::icons::icons_dir(app_dir);
+    let icon_metadata = metadata.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = crate::extensions::icons::sync_icon(&icons_dir, &icon_metadata) {
+            log::warn!("Failed to cache icon for extension {}: {}", icon_metadata.id, e);
+        }
+    });
+
     Ok(metadata)
 }
 
+/// Install the extensions bundled with the app. Only does something on first
+/// run (and when a bundled extension has an update); returns what was installed.
+#[tauri::command]
+pub async fn install_bundled_extensions(
+    state: State<'_, AppState>,
+    storage_paths: State<'_, StoragePaths>,
+) -> Result<Vec<ExtensionMetadata>, String> {
+    let installed = crate::extensions::bundled::install_bundled_extensions(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to install bundled extensions: {}", e))?;
+
+    installed
+        .into_iter()
+        .map(|extension| register_extension(&state, &storage_paths.app_dir, extension))
+        .collect()
+}
+
+/// Which first-run setup steps are done, for the onboarding checklist
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OnboardingState {
+    /// At least one extension is loaded
+    pub extension_installed: bool,
+    /// The library has at least one entry
+    pub library_non_empty: bool,
+    /// At least one media is mapped to a tracker
+    pub tracker_connected: bool,
+    /// A file can be created in the downloads directory
+    pub downloads_dir_writable: bool,
+    /// install_bundled_extensions has run
+    pub bundled_extensions_installed: bool,
+}
+
+/// Report which onboarding steps are complete
+#[tauri::command]
+pub async fn get_onboarding_state(
+    state: State<'_, AppState>,
+    storage_paths: State<'_, StoragePaths>,
+) -> Result<OnboardingState, String> {
+    let pool = state.database.pool();
+
+    let extension_installed = !state.extensions().is_empty();
+
+    // The library is per profile; a new profile starts with an empty one
+    let library_non_empty = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM library WHERE profile_id = ?")
+        .bind(state.profile_id())
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to get onboarding state: {}", e))?
+        > 0;
+    let tracker_connected = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tracker_mappings")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to get onboarding state: {}", e))?
+        > 0;
+
+    let bundled_extensions_installed = sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+        .bind(crate::extensions::bundled::INSTALLED_SETTING)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to get onboarding state: {}", e))?
+        .is_some_and(|value| value == "true");
+
+    let downloads_dir = storage_paths.downloads_dir.clone();
+    let downloads_dir_writable = tokio::task::spawn_blocking(move || {
+        let probe = downloads_dir.join(".otaku-write-test");
+        let writable = std::fs::create_dir_all(&downloads_dir).is_ok() && std::fs::write(&probe, b"").is_ok();
+        let _ = std::fs::remove_file(&probe);
+        writable
+    })
+    .await
+    .unwrap_or(false);
+
+    Ok(OnboardingState {
+        extension_installed,
+        library_non_empty,
+        tracker_connected,
+        downloads_dir_writable,
+        bundled_extensions_installed,
+    })
+}
+
+/// Unload an extension, deleting its cached icon and, if it was installed,
+/// its stored copy
+#[tauri::command]
+pub async fn unload_extension(
+    state: State<'_, AppState>,
+    storage_paths: State<'_, StoragePaths>,
+    extension_id: String,
+) -> Result<bool, String> {
+    let removed = {
+        let mut extensions = state.extensions_mut();
+        let before = extensions.len();
+        extensions.retain(|ext| ext.metadata.id != extension_id);
+        extensions.len() < before
+    };
+
+    let icons_dir = crate::extensions::icons::icons_dir(&storage_paths.app_dir);
+    crate::extensions::icons::remove_icon(&icons_dir, &extension_id);
+
+    // Installed extensions (e.g. bundled ones) would otherwise come back on restart
+    crate::extensions::bundled::remove_persisted_extension(state.database.pool(), &extension_id)
+        .await
+        .map_err(|e| format!("Failed to remove extension: {}", e))?;
+
+    log::debug!("Unloaded extension: {}", extension_id);
+    Ok(removed)
+}
+
 /// Search for anime using a specific extension
 #[tauri::command]
 pub async fn search_anime(
@@ -68,27 +310,34 @@ pub async fn search_anime(
     query: String,
     page: u32,
     allow_adult: Option<bool>,
-) -> Result<SearchResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<SearchResults, CommandError> {
+    search_with(&state, &extension_id, &query, page, allow_adult).await
+}
+
+async fn search_with(
+    state: &AppState,
+    extension_id: &str,
+    query: &str,
+    page: u32,
+    allow_adult: Option<bool>,
+) -> Result<SearchResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(extension_id)?;
 
-    // Release lock before creating runtime
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
     // Create runtime on-demand with NSFW setting
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let results = runtime.search(&query, page)
+    let mut results = circuit_breaker::track(extension_id, runtime.search(query, page))
         .map_err(|e| format!("Search failed: {}", e))?;
 
+    apply_language_preference(&mut results.results, preferred_language.as_deref());
+    hidden.filter(&mut results);
+
     Ok(results)
 }
 
@@ -98,22 +347,21 @@ pub async fn get_anime_details(
     state: State<'_, AppState>,
     extension_id: String,
     anime_id: String,
-) -> Result<MediaDetails, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
-
-    drop(extensions);
-
-    let runtime = ExtensionRuntime::new(extension)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-    let details = runtime.get_details(&anime_id)
-        .map_err(|e| format!("Failed to get details: {}", e))?;
+    allow_adult: Option<bool>,
+) -> Result<MediaDetails, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let extension = state.extension(&extension_id)?;
+
+    let details = {
+        let runtime = guarded_runtime(extension, allow_adult)?;
+        circuit_breaker::track(&extension_id, runtime.get_details(&anime_id))
+            .map_err(|e| format!("Failed to get details: {}", e))?
+    };
+    age_rating::record_fetched_rating(state.database.pool(), &anime_id, details.age_rating.as_deref()).await;
+    crate::database::media::record_broadcast_schedule(
+        state.database.pool(), &anime_id, details.last_update_end.as_deref(), details.broadcast_interval,
+    )
+    .await;
 
     Ok(details)
 }
@@ -124,37 +372,34 @@ pub async fn get_video_sources(
     state: State<'_, AppState>,
     extension_id: String,
     episode_id: String,
-) -> Result<VideoSources, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    allow_adult: Option<bool>,
+) -> Result<VideoSources, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
+    circuit_breaker::peek(&extension_id).map_err(|e| e.to_string())?;
 
-    drop(extensions);
+    // Prefetched by the startup warm-up, if it ran in the same mode
+    if let Some(mut sources) = crate::cache::warmup::take_warmed_sources(&extension_id, &episode_id, allow_adult) {
+        apply_language_preference(&mut sources.sources, preferred_language.as_deref());
+        return Ok(sources);
+    }
 
-    let runtime = ExtensionRuntime::new(extension)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let sources = runtime.get_sources(&episode_id)
+    let mut sources = circuit_breaker::track(&extension_id, runtime.get_sources(&episode_id))
         .map_err(|e| format!("Failed to get sources: {}", e))?;
 
+    apply_language_preference(&mut sources.sources, preferred_language.as_deref());
+
     Ok(sources)
 }
 
-/// Event name for anime discover streaming
-pub const ANIME_DISCOVER_EVENT: &str = "anime-discover-results";
-
-/// Event name for manga discover streaming
-pub const MANGA_DISCOVER_EVENT: &str = "manga-discover-results";
-
-/// Event name for season anime streaming
-pub const SEASON_ANIME_DISCOVER_EVENT: &str = "season-anime-discover-results";
+use crate::events::{ANIME_DISCOVER_EVENT, MANGA_DISCOVER_EVENT, SEASON_ANIME_DISCOVER_EVENT};
 
 /// Event payload for streaming discover results
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
 pub struct DiscoverResultsEvent {
     pub results: Vec<SearchResult>,
     pub page: u32,
@@ -164,7 +409,7 @@ pub struct DiscoverResultsEvent {
 }
 
 /// Event payload for streaming season anime results (includes season info)
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
 pub struct SeasonDiscoverResultsEvent {
     pub results: Vec<SearchResult>,
     pub page: u32,
@@ -186,22 +431,16 @@ pub async fn stream_discover_anime(
     genres: Vec<String>,
     allow_adult: Option<bool>,
     pages_to_fetch: Option<u32>,
-) -> Result<(), String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<(), CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
     let pages_to_fetch = pages_to_fetch.unwrap_or(3);
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
-
-    drop(extensions);
+    let extension = state.extension(&extension_id)?;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
     let mut all_results: Vec<SearchResult> = Vec::new();
     let mut seen_ids: HashSet<String> = HashSet::new();
@@ -213,27 +452,29 @@ pub async fn stream_discover_anime(
             break;
         }
 
-        let page_results = runtime.discover(page, sort_type.clone(), genres.clone())
+        let page_results = circuit_breaker::track(&extension_id, discover_genres(&runtime, &extension_id, page, sort_type.clone(), &genres))
             .map_err(|e| format!("Discover failed: {}", e))?;
 
         has_more_pages = page_results.has_next_page;
 
-        // Deduplicate and collect new results
+        // Deduplicate and collect new results; hidden ones aren't counted
         let mut new_results: Vec<SearchResult> = Vec::new();
         for item in page_results.results {
-            if !seen_ids.contains(&item.id) {
-                seen_ids.insert(item.id.clone());
-                new_results.push(item.clone());
-                all_results.push(item);
+            if seen_ids.insert(item.id.clone()) {
+                new_results.push(item);
             }
         }
 
+        apply_language_preference(&mut new_results, preferred_language.as_deref());
+        hidden.filter(&mut new_results);
+        all_results.extend(new_results.iter().cloned());
+
         let is_last = page == pages_to_fetch || !has_more_pages;
         let new_count = new_results.len();
 
         // Emit this page's results
         if !new_results.is_empty() || is_last {
-            let _ = app.emit(ANIME_DISCOVER_EVENT, DiscoverResultsEvent {
+            ANIME_DISCOVER_EVENT.emit(&app, &DiscoverResultsEvent {
                 results: new_results,
                 page,
                 has_next_page: has_more_pages,
@@ -259,22 +500,16 @@ pub async fn stream_discover_manga(
     genres: Vec<String>,
     allow_adult: Option<bool>,
     pages_to_fetch: Option<u32>,
-) -> Result<(), String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<(), CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
     let pages_to_fetch = pages_to_fetch.unwrap_or(3);
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
-
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
     let mut all_results: Vec<SearchResult> = Vec::new();
     let mut seen_ids: HashSet<String> = HashSet::new();
@@ -286,27 +521,29 @@ pub async fn stream_discover_manga(
             break;
         }
 
-        let page_results = runtime.discover(page, sort_type.clone(), genres.clone())
+        let page_results = circuit_breaker::track(&extension_id, discover_genres(&runtime, &extension_id, page, sort_type.clone(), &genres))
             .map_err(|e| format!("Manga discover failed: {}", e))?;
 
         has_more_pages = page_results.has_next_page;
 
-        // Deduplicate and collect new results
+        // Deduplicate and collect new results; hidden ones aren't counted
         let mut new_results: Vec<SearchResult> = Vec::new();
         for item in page_results.results {
-            if !seen_ids.contains(&item.id) {
-                seen_ids.insert(item.id.clone());
-                new_results.push(item.clone());
-                all_results.push(item);
+            if seen_ids.insert(item.id.clone()) {
+                new_results.push(item);
             }
         }
 
+        apply_language_preference(&mut new_results, preferred_language.as_deref());
+        hidden.filter(&mut new_results);
+        all_results.extend(new_results.iter().cloned());
+
         let is_last = page == pages_to_fetch || !has_more_pages;
         let new_count = new_results.len();
 
         // Emit this page's results
         if !new_results.is_empty() || is_last {
-            let _ = app.emit(MANGA_DISCOVER_EVENT, DiscoverResultsEvent {
+            MANGA_DISCOVER_EVENT.emit(&app, &DiscoverResultsEvent {
                 results: new_results,
                 page,
                 has_next_page: has_more_pages,
@@ -330,25 +567,23 @@ pub async fn discover_anime(
     sort_type: Option<String>,
     genres: Vec<String>,
     allow_adult: Option<bool>,
-) -> Result<SearchResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<SearchResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let results = runtime.discover(page, sort_type, genres)
+    let mut results = circuit_breaker::track(&extension_id, discover_genres(&runtime, &extension_id, page, sort_type, &genres))
         .map_err(|e| format!("Discover failed: {}", e))?;
 
+    apply_language_preference(&mut results.results, preferred_language.as_deref());
+    hidden.filter(&mut results);
+
     Ok(results)
 }
 
@@ -359,25 +594,20 @@ pub async fn get_current_season_anime(
     extension_id: String,
     page: u32,
     allow_adult: Option<bool>,
-) -> Result<crate::extensions::types::SeasonResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
-
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+) -> Result<crate::extensions::types::SeasonResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let results = runtime.get_current_season(page)
+    let mut results = circuit_breaker::track(&extension_id, runtime.get_current_season(page))
         .map_err(|e| format!("Get current season failed: {}", e))?;
 
+    hidden.filter(&mut results);
+
     Ok(results)
 }
 
@@ -391,22 +621,27 @@ pub async fn stream_current_season_anime(
     extension_id: String,
     allow_adult: Option<bool>,
     pages_to_fetch: Option<u32>,
-) -> Result<(), String> {
-    let allow_adult = allow_adult.unwrap_or(false);
-    let pages_to_fetch = pages_to_fetch.unwrap_or(3);
-
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+) -> Result<(), CommandError> {
+    stream_current_season_with(&state, &extension_id, allow_adult, pages_to_fetch, |event| {
+        SEASON_ANIME_DISCOVER_EVENT.emit(&app, &event);
+    })
+    .await
+}
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+async fn stream_current_season_with(
+    state: &AppState,
+    extension_id: &str,
+    allow_adult: Option<bool>,
+    pages_to_fetch: Option<u32>,
+    mut emit: impl FnMut(SeasonDiscoverResultsEvent),
+) -> Result<(), CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let pages_to_fetch = pages_to_fetch.unwrap_or(3);
 
-    drop(extensions);
+    let extension = state.extension(extension_id)?;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
     let mut all_results: Vec<SearchResult> = Vec::new();
     let mut seen_ids: HashSet<String> = HashSet::new();
@@ -420,7 +655,7 @@ pub async fn stream_current_season_anime(
             break;
         }
 
-        let page_results = runtime.get_current_season(page)
+        let page_results = circuit_breaker::track(extension_id, runtime.get_current_season(page))
             .map_err(|e| format!("Get current season failed: {}", e))?;
 
         // Capture season info from first page
@@ -431,22 +666,22 @@ pub async fn stream_current_season_anime(
 
         has_more_pages = page_results.has_next_page;
 
-        // Deduplicate and collect new results
+        // Deduplicate and collect new results; hidden ones aren't counted
         let mut new_results: Vec<SearchResult> = Vec::new();
         for item in page_results.results {
-            if !seen_ids.contains(&item.id) {
-                seen_ids.insert(item.id.clone());
-                new_results.push(item.clone());
-                all_results.push(item);
+            if seen_ids.insert(item.id.clone()) {
+                new_results.push(item);
             }
         }
+        hidden.filter(&mut new_results);
+        all_results.extend(new_results.iter().cloned());
 
         let is_last = page == pages_to_fetch || !has_more_pages;
         let new_count = new_results.len();
 
         // Emit this page's results
         if !new_results.is_empty() || is_last {
-            let _ = app.emit(SEASON_ANIME_DISCOVER_EVENT, SeasonDiscoverResultsEvent {
+            emit(SeasonDiscoverResultsEvent {
                 results: new_results,
                 page,
                 has_next_page: has_more_pages,
@@ -471,34 +706,28 @@ pub async fn get_home_content(
     state: State<'_, AppState>,
     extension_id: String,
     allow_adult: Option<bool>,
-) -> Result<HomeContent, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<HomeContent, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let extension = state.extension(&extension_id)?;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
-
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
     // Fetch 5 pages (100 items) and categorize
-    let content = runtime.get_home_content(5)
+    let mut content = circuit_breaker::track(&extension_id, runtime.get_home_content(5))
         .map_err(|e| format!("Failed to get home content: {}", e))?;
 
+    hidden.filter(&mut content);
+
     Ok(content)
 }
 
-/// Event name for home content streaming
-pub const HOME_CONTENT_EVENT: &str = "home-content-category";
+use crate::events::HOME_CONTENT_EVENT;
 
 /// Event payload for streaming home content
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Serialize, schemars::JsonSchema)]
 pub struct HomeCategoryEvent {
     pub category: HomeCategory,
     pub is_last: bool,
@@ -513,21 +742,13 @@ pub async fn stream_home_content(
     state: State<'_, AppState>,
     extension_id: String,
     allow_adult: Option<bool>,
-) -> Result<(), String> {
-    let allow_adult = allow_adult.unwrap_or(false);
-
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+) -> Result<(), CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    drop(extensions);
+    let extension = state.extension(&extension_id)?;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
     // Fetch and emit categories progressively
     let mut all_results: Vec<SearchResult> = Vec::new();
@@ -535,9 +756,9 @@ pub async fn stream_home_content(
     let mut categories_emitted = 0;
 
     // Fetch page 1 - emit Trending Now immediately
-    if let Ok(results) = runtime.discover(1, Some("view".to_string()), vec![]) {
+    if let Ok(results) = circuit_breaker::track(&extension_id, runtime.discover(1, Some("view".to_string()), vec![])) {
         for item in results.results {
-            if !seen_ids.contains(&item.id) {
+            if !seen_ids.contains(&item.id) && !hidden.hides(&item) {
                 seen_ids.insert(item.id.clone());
                 all_results.push(item);
             }
@@ -547,7 +768,7 @@ pub async fn stream_home_content(
         let trending: Vec<SearchResult> = all_results.iter().take(20).cloned().collect();
         if !trending.is_empty() {
             let featured = trending.first().cloned();
-            let _ = app.emit(HOME_CONTENT_EVENT, HomeCategoryEvent {
+            HOME_CONTENT_EVENT.emit(&app, &HomeCategoryEvent {
                 category: HomeCategory {
                     id: "trending".to_string(),
                     title: "Trending Now".to_string(),
@@ -563,9 +784,9 @@ pub async fn stream_home_content(
 
     // Fetch pages 2-3 for more data, then emit Top Rated
     for page in 2..=3 {
-        if let Ok(results) = runtime.discover(page, Some("view".to_string()), vec![]) {
+        if let Ok(results) = circuit_breaker::track(&extension_id, runtime.discover(page, Some("view".to_string()), vec![])) {
             for item in results.results {
-                if !seen_ids.contains(&item.id) {
+                if !seen_ids.contains(&item.id) && !hidden.hides(&item) {
                     seen_ids.insert(item.id.clone());
                     all_results.push(item);
                 }
@@ -582,7 +803,7 @@ pub async fn stream_home_content(
     });
     let top_rated: Vec<SearchResult> = by_rating.into_iter().take(20).collect();
     if !top_rated.is_empty() {
-        let _ = app.emit(HOME_CONTENT_EVENT, HomeCategoryEvent {
+        HOME_CONTENT_EVENT.emit(&app, &HomeCategoryEvent {
             category: HomeCategory {
                 id: "top-rated".to_string(),
                 title: "Top Rated".to_string(),
@@ -597,9 +818,9 @@ pub async fn stream_home_content(
 
     // Fetch pages 4-5 for Recently Updated
     for page in 4..=5 {
-        if let Ok(results) = runtime.discover(page, Some("view".to_string()), vec![]) {
+        if let Ok(results) = circuit_breaker::track(&extension_id, runtime.discover(page, Some("view".to_string()), vec![])) {
             for item in results.results {
-                if !seen_ids.contains(&item.id) {
+                if !seen_ids.contains(&item.id) && !hidden.hides(&item) {
                     seen_ids.insert(item.id.clone());
                     all_results.push(item);
                 }
@@ -614,7 +835,7 @@ pub async fn stream_home_content(
         .cloned()
         .collect();
     if !recently_updated.is_empty() {
-        let _ = app.emit(HOME_CONTENT_EVENT, HomeCategoryEvent {
+        HOME_CONTENT_EVENT.emit(&app, &HomeCategoryEvent {
             category: HomeCategory {
                 id: "recently-updated".to_string(),
                 title: "Recently Updated".to_string(),
@@ -669,25 +890,20 @@ pub async fn get_recommendations(
     state: State<'_, AppState>,
     extension_id: String,
     allow_adult: Option<bool>,
-) -> Result<SearchResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
-
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+) -> Result<SearchResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let results = runtime.get_recommendations()
+    let mut results = circuit_breaker::track(&extension_id, runtime.get_recommendations())
         .map_err(|e| format!("Get recommendations failed: {}", e))?;
 
+    hidden.filter(&mut results);
+
     Ok(results)
 }
 
@@ -697,39 +913,212 @@ pub async fn get_tags(
     state: State<'_, AppState>,
     extension_id: String,
     page: u32,
-) -> Result<TagsResult, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    allow_adult: Option<bool>,
+) -> Result<TagsResult, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let extension = state.extension(&extension_id)?;
+
+    let tags = {
+        let runtime = guarded_runtime(extension, allow_adult)?;
+        circuit_breaker::track(&extension_id, runtime.get_tags(page))
+            .map_err(|e| format!("Get tags failed: {}", e))?
+    };
 
-    drop(extensions);
+    Ok(normalize_genre_tags(state.database.pool(), &extension_id, tags).await)
+}
 
-    let runtime = ExtensionRuntime::new(extension)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+/// Merge an extension's genre tags under their canonical names, so the
+/// discover genre filters don't list "Sci-Fi" and "Science Fiction" separately
+async fn normalize_genre_tags(pool: &sqlx::SqlitePool, extension_id: &str, tags: TagsResult) -> TagsResult {
+    match crate::database::genres::load_genre_map(pool).await {
+        Ok(map) => {
+            let tags = crate::database::genres::normalize_tags(tags, &map);
+            crate::database::genres::remember_merged_slugs(extension_id, &tags);
+            tags
+        }
+        Err(e) => {
+            log::warn!("Failed to load genre mappings: {}", e);
+            tags
+        }
+    }
+}
 
-    let tags = runtime.get_tags(page)
-        .map_err(|e| format!("Get tags failed: {}", e))?;
+/// Discover with a genre filter that may name merged tags: one call per
+/// slug variant (genres::genre_filter_variants), results merged in order
+fn discover_genres(
+    runtime: &ExtensionRuntime,
+    extension_id: &str,
+    page: u32,
+    sort_type: Option<String>,
+    genres: &[String],
+) -> anyhow::Result<SearchResults> {
+    let mut merged = SearchResults { results: Vec::new(), has_next_page: false };
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for variant in crate::database::genres::genre_filter_variants(extension_id, genres) {
+        let results = runtime.discover(page, sort_type.clone(), variant)?;
+        merged.has_next_page |= results.has_next_page;
+        merged
+            .results
+            .extend(results.results.into_iter().filter(|r| seen.insert(r.id.clone())));
+    }
+    Ok(merged)
+}
 
-    Ok(tags)
+/// A loaded extension along with its circuit breaker state
+#[derive(serde::Serialize)]
+pub struct ExtensionListEntry {
+    #[serde(flatten)]
+    pub metadata: ExtensionMetadata,
+    pub breaker: BreakerStatus,
+    /// Local URL of the cached icon; None until it's cached (or when the
+    /// extension has none or the video server isn't running)
+    pub icon: Option<String>,
 }
 
-/// List all loaded extensions
+/// List all loaded extensions.
+/// Each entry carries `languages` so the picker can group sources by language,
+/// `breaker` so sources that keep failing can be shown as paused, and `icon`
+/// pointing at the locally cached icon.
 #[tauri::command]
 pub async fn list_extensions(
     state: State<'_, AppState>,
-) -> Result<Vec<ExtensionMetadata>, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let metadata: Vec<ExtensionMetadata> = extensions.iter()
-        .map(|ext| ext.metadata.clone())
+    storage_paths: State<'_, StoragePaths>,
+    video_server: State<'_, VideoServerHandle>,
+) -> Result<Vec<ExtensionListEntry>, String> {
+    let icons_dir = crate::extensions::icons::icons_dir(&storage_paths.app_dir);
+    let video_server = video_server.info().ok();
+
+    let extensions = state.extensions();
+
+    let entries: Vec<ExtensionListEntry> = extensions.iter()
+        .map(|ext| ExtensionListEntry {
+            metadata: ext.metadata.clone(),
+            breaker: circuit_breaker::status(&ext.metadata.id),
+            icon: video_server
+                .as_ref()
+                .filter(|_| crate::extensions::icons::has_icon(&icons_dir, &ext.metadata.id))
+                .map(|info| info.extension_icon_url(&ext.metadata.id, &ext.metadata.version)),
+        })
         .collect();
 
-    Ok(metadata)
+    Ok(entries)
+}
+
+/// Result of probing an extension with a cheap search
+#[derive(serde::Serialize)]
+pub struct ExtensionHealth {
+    pub extension_id: String,
+    pub healthy: bool,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub breaker: BreakerStatus,
+}
+
+/// Check whether an extension is working. Runs even while the breaker is
+/// open; a successful check closes it so the extension is usable again.
+#[tauri::command]
+pub async fn check_extension_health(
+    state: State<'_, AppState>,
+    extension_id: String,
+) -> Result<ExtensionHealth, CommandError> {
+    let extension = state.extension(&extension_id)?;
+
+    let started = std::time::Instant::now();
+    let result = ExtensionRuntime::new(extension)
+        .and_then(|runtime| runtime.search("a", 1));
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let error = match result {
+        Ok(_) => {
+            circuit_breaker::record_success(&extension_id);
+            None
+        }
+        Err(e) => {
+            let error = e.to_string();
+            circuit_breaker::record_failure(&extension_id, &error);
+            Some(error)
+        }
+    };
+
+    Ok(ExtensionHealth {
+        breaker: circuit_breaker::status(&extension_id),
+        extension_id,
+        healthy: error.is_none(),
+        error,
+        latency_ms,
+    })
+}
+
+/// Recent calls into an extension with their duration and memory, newest first
+#[tauri::command]
+pub async fn get_extension_request_log(
+    extension_id: String,
+) -> Result<Vec<crate::extensions::request_log::ExtensionCall>, String> {
+    Ok(crate::extensions::request_log::calls(&extension_id))
+}
+
+/// What the runtime expects from an extension: the hooks it calls and a JSON
+/// schema of every value they return (see extensions::api_schema)
+#[tauri::command]
+pub async fn get_extension_api_schema() -> Result<serde_json::Value, String> {
+    Ok(crate::extensions::api_schema::api_schema())
+}
+
+/// Get the heap limit of extension runtimes in megabytes
+#[tauri::command]
+pub async fn get_extension_memory_limit() -> Result<usize, String> {
+    Ok(crate::extensions::limits::memory_limit_mb())
+}
+
+/// Set the heap limit of extension runtimes in megabytes, returning the
+/// (clamped) value applied. Runtimes created from now on use it.
+#[tauri::command]
+pub async fn set_extension_memory_limit(
+    state: State<'_, AppState>,
+    megabytes: usize,
+) -> Result<usize, String> {
+    let megabytes = crate::extensions::limits::set_memory_limit_mb(megabytes);
+
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(crate::extensions::limits::MEMORY_LIMIT_SETTING)
+        .bind(megabytes.to_string())
+        .execute(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to save extension memory limit: {}", e))?;
+
+    Ok(megabytes)
+}
+
+/// Remaining background call budget of every loaded extension
+#[tauri::command]
+pub async fn get_extension_background_budget(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::extensions::background_budget::BudgetStatus>, String> {
+    Ok(state
+        .extensions()
+        .iter()
+        .map(|ext| crate::extensions::background_budget::status(&ext.metadata.id))
+        .collect())
+}
+
+/// Set how many background calls each extension may take per hour, returning
+/// the (clamped) value applied
+#[tauri::command]
+pub async fn set_extension_background_budget(
+    state: State<'_, AppState>,
+    calls_per_hour: u32,
+) -> Result<u32, String> {
+    let calls_per_hour = crate::extensions::background_budget::set_calls_per_hour(calls_per_hour);
+
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(crate::extensions::background_budget::CALLS_PER_HOUR_SETTING)
+        .bind(calls_per_hour.to_string())
+        .execute(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to save extension background budget: {}", e))?;
+
+    Ok(calls_per_hour)
 }
 
 // ==================== Manga Commands ====================
@@ -742,25 +1131,23 @@ pub async fn search_manga(
     query: String,
     page: u32,
     allow_adult: Option<bool>,
-) -> Result<SearchResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<SearchResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let results = runtime.search(&query, page)
+    let mut results = circuit_breaker::track(&extension_id, runtime.search(&query, page))
         .map_err(|e| format!("Manga search failed: {}", e))?;
 
+    apply_language_preference(&mut results.results, preferred_language.as_deref());
+    hidden.filter(&mut results);
+
     Ok(results)
 }
 
@@ -771,52 +1158,129 @@ pub async fn get_manga_details(
     extension_id: String,
     manga_id: String,
     allow_adult: Option<bool>,
-) -> Result<MangaDetails, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+) -> Result<MangaDetails, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    fetch_manga_details(&state, &extension_id, &manga_id, allow_adult, guarded_runtime)
+}
 
-    drop(extensions);
+/// A manga's details, with the runtime made by `make_runtime`
+/// (guarded_runtime, or background_runtime for work no one waits on)
+fn fetch_manga_details(
+    state: &AppState,
+    extension_id: &str,
+    manga_id: &str,
+    allow_adult: bool,
+    make_runtime: fn(Extension, bool) -> Result<ExtensionRuntime, String>,
+) -> Result<MangaDetails, CommandError> {
+    let extension = state.extension(extension_id)?;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult.unwrap_or(false))
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = make_runtime(extension, allow_adult)?;
 
-    let details = runtime.get_manga_details(&manga_id)
+    let details = circuit_breaker::track(extension_id, runtime.get_manga_details(manga_id))
         .map_err(|e| format!("Failed to get manga details: {}", e))?;
 
     Ok(details)
 }
 
-/// Get chapter images for reading
+/// Get chapter images for reading. Page sizes the extension didn't report
+/// are probed afterwards and emitted as chapter-image-sizes.
 #[tauri::command]
 pub async fn get_chapter_images(
+    app: AppHandle,
     state: State<'_, AppState>,
     extension_id: String,
     chapter_id: String,
-) -> Result<ChapterImages, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    allow_adult: Option<bool>,
+) -> Result<ChapterImages, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let images = fetch_chapter_images(&state, &extension_id, &chapter_id, allow_adult)?;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    // Page sizes let the reader keep two-page spreads on their own
+    crate::media::image_size::spawn_size_probes(app, chapter_id, images.clone());
 
-    drop(extensions);
+    Ok(images)
+}
+
+fn fetch_chapter_images(
+    state: &AppState,
+    extension_id: &str,
+    chapter_id: &str,
+    allow_adult: bool,
+) -> Result<ChapterImages, CommandError> {
+    let extension = state.extension(extension_id)?;
 
-    let runtime = ExtensionRuntime::new(extension)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let images = runtime.get_chapter_images(&chapter_id)
+    let mut images = circuit_breaker::track(extension_id, runtime.get_chapter_images(chapter_id))
         .map_err(|e| format!("Failed to get chapter images: {}", e))?;
+    crate::media::image_size::mark_spreads(&mut images);
 
     Ok(images)
 }
 
+/// Report a chapter page that failed to load (typically a 403 from an
+/// expired URL). The chapter's images are fetched again and the failed
+/// page's fresh URL is returned along with the rest, so the reader can retry.
+/// Several pages failing at once share one refresh.
+#[tauri::command]
+pub async fn report_chapter_image_failure(
+    state: State<'_, AppState>,
+    extension_id: String,
+    chapter_id: String,
+    page_index: usize,
+    failed_url: Option<String>,
+    allow_adult: Option<bool>,
+) -> Result<crate::media::chapter_refresh::ChapterImageRefresh, String> {
+    use crate::media::chapter_refresh;
+
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+
+    if let Some(url) = &failed_url {
+        chapter_refresh::record_image_failure(url);
+    }
+    log::debug!("Chapter {} page {} failed to load, refreshing", chapter_id, page_index);
+
+    let images = chapter_refresh::refresh_chapter(&extension_id, &chapter_id, allow_adult, || async {
+        fetch_chapter_images(&state, &extension_id, &chapter_id, allow_adult).map_err(|e| e.to_string())
+    })
+    .await?;
+
+    Ok(chapter_refresh::ChapterImageRefresh {
+        page_index,
+        url: images.images.get(page_index).map(|image| image.url.clone()),
+        images,
+    })
+}
+
+/// Chapter image failures by host since the app started, most first
+#[tauri::command]
+pub async fn get_image_host_failures() -> Result<Vec<crate::media::chapter_refresh::ImageHostFailures>, String> {
+    Ok(crate::media::chapter_refresh::image_host_failures())
+}
+
+/// Fill in missing descriptions and covers of library media (anime from
+/// Jikan, manga from their extension). Resumes an interrupted or cancelled
+/// run unless `restart` is set; emits media_hydration_progress.
+#[tauri::command]
+pub async fn hydrate_imported_media(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    restart: Option<bool>,
+) -> Result<crate::media_hydration::MediaHydrationProgress, String> {
+    let state = state.inner();
+    let allow_adult = adult::background_allow_adult(state.database.pool()).await;
+    let fetch_manga = move |extension_id: String, manga_id: String| async move {
+        fetch_manga_details(state, &extension_id, &manga_id, allow_adult, background_runtime).map_err(|e| e.to_string())
+    };
+    crate::media_hydration::hydrate_imported_media(state.database.pool(), &app, restart.unwrap_or(false), fetch_manga).await
+}
+
+/// Stop a running hydration after the current item; returns whether one was running
+#[tauri::command]
+pub async fn cancel_media_hydration() -> Result<bool, String> {
+    Ok(crate::media_hydration::cancel_hydration())
+}
+
 /// Discover manga with filters (trending, top-rated, by genre)
 #[tauri::command]
 pub async fn discover_manga(
@@ -826,27 +1290,25 @@ pub async fn discover_manga(
     sort_type: Option<String>,
     genres: Vec<String>,
     allow_adult: Option<bool>,
-) -> Result<SearchResults, String> {
-    let allow_adult = allow_adult.unwrap_or(false);
+) -> Result<SearchResults, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
 
     log::debug!("[Manga] discover_manga called with genres: {:?}", genres);
 
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
+    let preferred_language = preferred_content_language(&state).await;
 
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
+    let extension = state.extension(&extension_id)?;
 
-    drop(extensions);
+    let hidden = HiddenSet::load(state.database.pool(), state.profile_id()).await;
 
-    let runtime = ExtensionRuntime::with_options(extension, allow_adult)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+    let runtime = guarded_runtime(extension, allow_adult)?;
 
-    let result = runtime.discover(page, sort_type, genres.clone())
+    let mut result = circuit_breaker::track(&extension_id, discover_genres(&runtime, &extension_id, page, sort_type, &genres))
         .map_err(|e| format!("Manga discover failed: {}", e))?;
 
+    apply_language_preference(&mut result.results, preferred_language.as_deref());
+    hidden.filter(&mut result);
+
     log::debug!("[Manga] discover_manga returned {} results for genres {:?}", result.results.len(), genres);
 
     Ok(result)
@@ -858,24 +1320,18 @@ pub async fn get_manga_tags(
     state: State<'_, AppState>,
     extension_id: String,
     page: u32,
-) -> Result<TagsResult, String> {
-    let extensions = state.extensions.read()
-        .map_err(|e| format!("Failed to lock extensions: {}", e))?;
-
-    let extension = extensions.iter()
-        .find(|ext| ext.metadata.id == extension_id)
-        .ok_or_else(|| format!("Extension not found: {}", extension_id))?
-        .clone();
-
-    drop(extensions);
-
-    let runtime = ExtensionRuntime::new(extension)
-        .map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-    let tags = runtime.get_tags(page)
-        .map_err(|e| format!("Get manga tags failed: {}", e))?;
+    allow_adult: Option<bool>,
+) -> Result<TagsResult, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let extension = state.extension(&extension_id)?;
+
+    let tags = {
+        let runtime = guarded_runtime(extension, allow_adult)?;
+        circuit_breaker::track(&extension_id, runtime.get_tags(page))
+            .map_err(|e| format!("Get manga tags failed: {}", e))?
+    };
 
-    Ok(tags)
+    Ok(normalize_genre_tags(state.database.pool(), &extension_id, tags).await)
 }
 
 /// Proxy image request to avoid CORS issues (for manga pages)
@@ -884,37 +1340,58 @@ pub async fn get_manga_tags(
 #[tauri::command]
 pub async fn proxy_image_request(
     url: String,
+    cache: Option<String>,
+    media_id: Option<String>,
 ) -> Result<tauri::ipc::Response, String> {
+    use crate::cache::media_disk::{self, MediaCacheNamespace};
+
     log::debug!("Proxying image request: {}", url);
 
-    use std::io::Read;
+    // Served from the media disk cache when it's there, cached after fetching
+    // otherwise. Reader pages go to the pages namespace unless told otherwise.
+    let namespace = match cache.as_deref() {
+        Some(name) => MediaCacheNamespace::parse(name).map_err(|e| e.to_string())?,
+        None => MediaCacheNamespace::Pages,
+    };
 
-    let request = build_image_request(&url)?;
+    tokio::task::spawn_blocking(move || {
+        use std::io::Read;
 
-    match request.call() {
-        Ok(response) => {
-            let content_length = response.header("Content-Length")
-                .and_then(|v| v.parse::<usize>().ok());
+        if let Some(bytes) = media_disk::read_image(namespace, &url, media_id.as_deref()) {
+            log::debug!("Image cache hit: {} bytes", bytes.len());
+            return Ok(tauri::ipc::Response::new(bytes));
+        }
 
-            // Pre-allocate based on content length, cap at 50MB for images
-            let initial_capacity = content_length
-                .map(|l| l.min(50 * 1024 * 1024))
-                .unwrap_or(1024 * 1024);
+        let request = build_image_request(&url)?;
 
-            let mut bytes = Vec::with_capacity(initial_capacity);
+        match request.call() {
+            Ok(response) => {
+                let content_length = response.header("Content-Length")
+                    .and_then(|v| v.parse::<usize>().ok());
 
-            response.into_reader()
-                .read_to_end(&mut bytes)
-                .map_err(|e| format!("Failed to read image: {}", e))?;
+                // Pre-allocate based on content length, cap at 50MB for images
+                let initial_capacity = content_length
+                    .map(|l| l.min(50 * 1024 * 1024))
+                    .unwrap_or(1024 * 1024);
 
-            log::debug!("Proxied image: {} bytes", bytes.len());
-            Ok(tauri::ipc::Response::new(bytes))
-        }
-        Err(e) => {
-            log::error!("Image proxy error for {}: {:?}", url, e);
-            Err(format!("Image proxy request failed: {}", e))
+                let mut bytes = Vec::with_capacity(initial_capacity);
+
+                response.into_reader()
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| format!("Failed to read image: {}", e))?;
+
+                log::debug!("Proxied image: {} bytes", bytes.len());
+                media_disk::write_image(namespace, &url, media_id.as_deref(), &bytes);
+                Ok(tauri::ipc::Response::new(bytes))
+            }
+            Err(e) => {
+                log::error!("Image proxy error for {}: {:?}", url, e);
+                Err(format!("Image proxy request failed: {}", e))
+            }
         }
-    }
+    })
+    .await
+    .map_err(|e| format!("Image proxy task failed: {}", e))?
 }
 
 /// Proxy video request to avoid CORS issues
@@ -1070,18 +1547,33 @@ fn rewrite_playlist_urls(playlist: &str, base_url: &str) -> String {
     result
 }
 
-/// Start downloading a video
+/// Start downloading a video. With `scheduled_start` (Unix ms) it waits in
+/// the queue until then. `media_title` is the series title for notifications
+/// and the downloads page; without it the media table's is used. `headers`
+/// are the source's HTTP headers (VideoSource headers plus its referrer);
+/// without them the defaults for AllAnime's CDNs are sent.
 #[tauri::command]
 pub async fn start_download(
     download_manager: State<'_, DownloadManager>,
     media_id: String,
     episode_id: String,
     episode_number: i32,
+    media_title: Option<String>,
     url: String,
     filename: String,
     custom_path: Option<String>,
-) -> Result<String, String> {
+    quality: Option<String>,
+    source_label: Option<String>,
+    batch_id: Option<String>,
+    scheduled_start: Option<i64>,
+    overwrite: Option<bool>,
+    headers: Option<HashMap<String, String>>,
+    subtitles: Option<Vec<crate::extensions::types::Subtitle>>,
+    alternatives: Option<Vec<crate::extensions::VideoSource>>,
+) -> Result<String, QueueError> {
     let download_id = format!("{}_{}", media_id, episode_number);
+    // The episode's other sources, kept to download it again later
+    let alternatives = crate::downloads::source_history::alternatives(&alternatives.unwrap_or_default(), &url);
 
     log::debug!("Starting download: {} (custom_path: {:?})", download_id, custom_path);
 
@@ -1091,12 +1583,21 @@ pub async fn start_download(
             media_id,
             episode_id,
             episode_number,
+            media_title,
             url,
+            headers.unwrap_or_default(),
+            subtitles.unwrap_or_default(),
+            alternatives,
             filename,
             custom_path,
+            quality,
+            source_label,
+            batch_id,
+            scheduled_start,
+            overwrite.unwrap_or(false),
         )
         .await
-        .map_err(|e| format!("Failed to queue download: {}", e))?;
+        .map_err(|e| QueueError::from_anyhow(e, "Failed to queue download"))?;
 
     Ok(download_id)
 }
@@ -1121,7 +1622,7 @@ pub async fn list_downloads(
     Ok(download_manager.list_downloads().await)
 }
 
-/// Cancel a download
+/// Cancel a download, keeping what it has fetched so it can be resumed
 #[tauri::command]
 pub async fn cancel_download(
     download_manager: State<'_, DownloadManager>,
@@ -1133,6 +1634,19 @@ pub async fn cancel_download(
         .map_err(|e| format!("Failed to cancel download: {}", e))
 }
 
+/// Abort a download, deleting its partial file unless `delete_partial` is false
+#[tauri::command]
+pub async fn abort_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+    delete_partial: Option<bool>,
+) -> Result<(), String> {
+    download_manager
+        .abort_download(&download_id, delete_partial.unwrap_or(true))
+        .await
+        .map_err(|e| format!("Failed to abort download: {}", e))
+}
+
 /// Pause a download
 #[tauri::command]
 pub async fn pause_download(
@@ -1145,7 +1659,7 @@ pub async fn pause_download(
         .map_err(|e| format!("Failed to pause download: {}", e))
 }
 
-/// Resume a paused download
+/// Resume a paused, cancelled or failed download
 #[tauri::command]
 pub async fn resume_download(
     download_manager: State<'_, DownloadManager>,
@@ -1157,272 +1671,1014 @@ pub async fn resume_download(
         .map_err(|e| format!("Failed to resume download: {}", e))
 }
 
-/// Check if an episode is downloaded
+/// Resume a download whose source URL has expired: the episode's sources are
+/// fetched from the extension again, the one in the download's quality
+/// replaces the stored URL, and the download resumes. It starts over when the
+/// new URL can't continue the bytes fetched so far.
 #[tauri::command]
-pub async fn is_episode_downloaded(
+pub async fn refresh_and_resume_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
     download_manager: State<'_, DownloadManager>,
-    media_id: String,
-    episode_number: i32,
-) -> Result<bool, String> {
-    Ok(download_manager.is_episode_downloaded(&media_id, episode_number).await)
-}
+    download_id: String,
+    extension_id: String,
+    allow_adult: Option<bool>,
+) -> Result<(), String> {
+    use crate::downloads::{lazy_source::ResolvedSource, source_refresh, upgrade};
 
-/// Get the file path for a downloaded episode
-#[tauri::command]
-pub async fn get_episode_file_path(
-    download_manager: State<'_, DownloadManager>,
-    media_id: String,
-    episode_number: i32,
-) -> Result<Option<String>, String> {
-    Ok(download_manager.get_episode_file_path(&media_id, episode_number).await)
+    let progress = download_manager
+        .get_progress(&download_id)
+        .await
+        .ok_or_else(|| format!("Download not found: {}", download_id))?;
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let episode_id = upgrade::source_episode_id(&progress.episode_id);
+
+    let sources = lazy_source::fetch_sources(&app, &extension_id, episode_id, allow_adult)
+        .await
+        .map_err(|e| format!("Failed to get sources: {}", e))?;
+    let source = source_refresh::pick_matching_source(
+        &sources,
+        progress.quality.as_deref(),
+        progress.source_label.as_deref(),
+    )
+    .ok_or_else(|| "No usable sources for this episode".to_string())?;
+
+    download_manager
+        .refresh_source(&download_id, ResolvedSource::from_source(source))
+        .await
+        .map_err(|e| format!("Failed to refresh download source: {}", e))?;
+    download_manager
+        .resume_download(&download_id)
+        .await
+        .map_err(|e| format!("Failed to resume download: {}", e))
 }
 
-/// Get total storage used by downloads
+/// Download a completed episode whose file is missing or corrupt again,
+/// under the same filename. Source URLs stored at download time are tried
+/// first; when none still answers the episode's sources are fetched from its
+/// extension again.
 #[tauri::command]
-pub async fn get_total_storage_used(
+pub async fn redownload_episode(
+    app: AppHandle,
+    state: State<'_, AppState>,
     download_manager: State<'_, DownloadManager>,
-) -> Result<u64, String> {
-    Ok(download_manager.get_total_storage_used().await)
+    download_id: String,
+    allow_adult: Option<bool>,
+) -> Result<crate::downloads::source_history::RedownloadSource, String> {
+    use crate::downloads::{lazy_source::ResolvedSource, source_refresh, upgrade};
+
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let resolve = |extension_id: String, download: DownloadProgress| async move {
+        let episode_id = upgrade::source_episode_id(&download.episode_id);
+        let sources = lazy_source::fetch_sources(&app, &extension_id, episode_id, allow_adult).await?;
+        source_refresh::pick_matching_source(&sources, download.quality.as_deref(), download.source_label.as_deref())
+            .map(ResolvedSource::from_source)
+            .ok_or_else(|| anyhow::anyhow!("No usable sources for this episode"))
+    };
+
+    download_manager
+        .redownload_episode(&download_id, resolve)
+        .await
+        .map_err(|e| format!("Failed to download episode again: {}", e))
 }
 
-/// Get the downloads directory path
+/// Get how many downloads may run at the same time
 #[tauri::command]
-pub async fn get_downloads_directory(
+pub async fn get_max_concurrent_downloads(
     download_manager: State<'_, DownloadManager>,
-) -> Result<String, String> {
-    Ok(download_manager.get_downloads_directory())
+) -> Result<usize, String> {
+    Ok(download_manager.max_concurrent())
 }
 
-/// Open the downloads directory in file explorer
+/// Change how many downloads may run at the same time. Values outside 1-20
+/// are clamped; returns the value applied.
 #[tauri::command]
-pub async fn open_downloads_folder(
+pub async fn set_max_concurrent_downloads(
+    state: State<'_, AppState>,
     download_manager: State<'_, DownloadManager>,
-    custom_path: Option<String>,
-) -> Result<(), String> {
-    // No-op on Android (no file explorer to open)
-    #[cfg(target_os = "android")]
-    {
-        let _ = (download_manager, custom_path);
-        return Ok(());
-    }
-
-    #[cfg(not(target_os = "android"))]
-    {
-        // Use custom path if provided, otherwise use default
-        let path = custom_path.unwrap_or_else(|| download_manager.get_downloads_directory());
+    max_concurrent: usize,
+) -> Result<usize, String> {
+    let applied = download_manager.set_max_concurrent(max_concurrent);
 
-        // Ensure the directory exists
-        std::fs::create_dir_all(&path)
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(crate::downloads::MAX_CONCURRENT_SETTING)
+        .bind(applied.to_string())
+        .execute(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to save max concurrent downloads: {}", e))?;
 
-        // Open the directory based on the platform
-        #[cfg(target_os = "windows")]
-        {
-            std::process::Command::new("explorer")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        }
+    log::info!("Max concurrent downloads set to {}", applied);
+    Ok(applied)
+}
 
-        #[cfg(target_os = "macos")]
-        {
-            std::process::Command::new("open")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        }
+/// Get the global download speed limit in bytes per second (0 = unlimited)
+#[tauri::command]
+pub async fn get_download_speed_limit() -> Result<u64, String> {
+    Ok(crate::downloads::throttle::speed_limit())
+}
 
-        #[cfg(target_os = "linux")]
-        {
-            std::process::Command::new("xdg-open")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        }
+/// Set the global download speed limit in bytes per second (0 = unlimited).
+/// Applies to running downloads straight away.
+#[tauri::command]
+pub async fn set_download_speed_limit(
+    state: State<'_, AppState>,
+    bytes_per_sec: u64,
+) -> Result<(), String> {
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(crate::downloads::throttle::SPEED_LIMIT_SETTING)
+        .bind(bytes_per_sec.to_string())
+        .execute(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to save download speed limit: {}", e))?;
 
-        Ok(())
-    }
+    crate::downloads::throttle::set_speed_limit(bytes_per_sec);
+    Ok(())
 }
 
-/// Remove a download from the list
+/// Set one download's own speed limit in bytes per second, on top of the
+/// global one (None or 0 = unlimited). A running download picks it up
+/// without restarting.
 #[tauri::command]
-pub async fn remove_download(
+pub async fn set_download_speed_limit_for(
     download_manager: State<'_, DownloadManager>,
     download_id: String,
+    limit: Option<u64>,
 ) -> Result<(), String> {
     download_manager
-        .remove_download(&download_id)
+        .set_speed_limit_for(&download_id, limit)
         .await
-        .map_err(|e| format!("Failed to remove download: {}", e))
+        .map_err(|e| format!("Failed to set download speed limit: {}", e))
 }
 
-/// Delete a downloaded file
+/// Get the daily window downloads start in (None = any time)
 #[tauri::command]
-pub async fn delete_download(
+pub async fn get_download_off_peak_window(
     download_manager: State<'_, DownloadManager>,
-    download_id: String,
+) -> Result<Option<crate::downloads::schedule::OffPeakWindow>, String> {
+    Ok(download_manager.off_peak_window())
+}
+
+/// Set the daily window downloads start in, in local time (None = any
+/// time). Queued downloads outside the window wait for it; ones waiting on
+/// the old window start if the new one is open.
+#[tauri::command]
+pub async fn set_download_off_peak_window(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+    window: Option<crate::downloads::schedule::OffPeakWindow>,
 ) -> Result<(), String> {
-    download_manager
-        .delete_download(&download_id)
+    let window = window
+        .map(|w| w.validated())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    crate::downloads::schedule::save_off_peak_window_setting(state.database.pool(), window)
         .await
-        .map_err(|e| format!("Failed to delete download: {}", e))
+        .map_err(|e| format!("Failed to save off-peak window: {}", e))?;
+
+    download_manager.set_off_peak_window(window);
+    download_manager.start_scheduled_downloads().await;
+    Ok(())
 }
 
-/// Delete a downloaded episode by media ID and episode number
+/// Set or clear when a queued download may start (Unix ms)
 #[tauri::command]
-pub async fn delete_episode_download(
+pub async fn schedule_download(
     download_manager: State<'_, DownloadManager>,
-    media_id: String,
-    episode_number: i32,
+    download_id: String,
+    scheduled_start: Option<i64>,
 ) -> Result<(), String> {
     download_manager
-        .delete_episode_download(&media_id, episode_number)
+        .schedule_download(&download_id, scheduled_start)
         .await
-        .map_err(|e| format!("Failed to delete episode download: {}", e))
+        .map_err(|e| format!("Failed to schedule download: {}", e))
 }
 
-/// Clear completed downloads from list
+/// Start a download without waiting for its scheduled start or the off-peak
+/// window
 #[tauri::command]
-pub async fn clear_completed_downloads(
+pub async fn start_download_now(
     download_manager: State<'_, DownloadManager>,
+    download_id: String,
 ) -> Result<(), String> {
     download_manager
-        .clear_completed()
+        .start_download_now(&download_id)
         .await
-        .map_err(|e| format!("Failed to clear completed downloads: {}", e))
+        .map_err(|e| format!("Failed to start download: {}", e))
 }
 
-/// Clear failed downloads from list
+/// Pause every active or queued download, returning how many were paused.
+/// Downloads queued afterwards wait until resume_all_downloads.
 #[tauri::command]
-pub async fn clear_failed_downloads(
+pub async fn pause_all_downloads(
     download_manager: State<'_, DownloadManager>,
-) -> Result<(), String> {
+) -> Result<usize, String> {
     download_manager
-        .clear_failed()
+        .pause_all()
         .await
-        .map_err(|e| format!("Failed to clear failed downloads: {}", e))
+        .map_err(|e| format!("Failed to pause downloads: {}", e))
 }
 
-/// Clear cancelled downloads from list
+/// Resume every paused download and start the ones queued while paused,
+/// returning how many were resumed
 #[tauri::command]
-pub async fn clear_cancelled_downloads(
+pub async fn resume_all_downloads(
     download_manager: State<'_, DownloadManager>,
-) -> Result<(), String> {
+) -> Result<usize, String> {
     download_manager
-        .clear_cancelled()
+        .resume_all()
         .await
-        .map_err(|e| format!("Failed to clear cancelled downloads: {}", e))
+        .map_err(|e| format!("Failed to resume downloads: {}", e))
 }
 
+/// Whether downloads are paused globally (pause_all_downloads)
+#[tauri::command]
+pub async fn get_downloads_paused(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<bool, String> {
+    Ok(download_manager.downloads_paused())
+}
 
-// ==================== Watch History Commands ====================
-
-/// Save or update watch progress for an episode
+/// Totals for the downloads page header. Also emitted as download-stats
+/// whenever they change.
 #[tauri::command]
-pub async fn save_watch_progress(
-    state: State<'_, AppState>,
-    media_id: String,
-    episode_id: String,
-    episode_number: i32,
-    progress_seconds: f64,
-    duration: Option<f64>,
-    completed: bool,
-) -> Result<(), String> {
-    use crate::database::watch_history::{save_watch_progress as save_progress, WatchProgress};
+pub async fn get_download_stats(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<crate::downloads::stats::DownloadStats, String> {
+    Ok(download_manager.download_stats().await)
+}
 
-    let progress = WatchProgress {
-        media_id,
-        episode_id,
-        episode_number,
-        progress_seconds,
-        duration,
-        completed,
-    };
+/// Whether the network is up, as last probed. Downloads paused by an outage
+/// resume on their own once it's back.
+#[tauri::command]
+pub async fn get_network_status(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<crate::downloads::network::NetworkStatus, String> {
+    Ok(download_manager.network_status())
+}
 
-    save_progress(state.database.pool(), &progress)
+/// Check that a completed download's file exists and has its recorded size.
+/// With `checksum` set the file is also hashed and compared with (or stored
+/// as) its recorded SHA-256. A bad file marks the download failed.
+#[tauri::command]
+pub async fn verify_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+    checksum: Option<bool>,
+) -> Result<crate::downloads::verify::VerifyResult, String> {
+    download_manager
+        .verify_download(&download_id, checksum.unwrap_or(false))
         .await
-        .map_err(|e| format!("Failed to save watch progress: {}", e))
+        .map_err(|e| format!("Failed to verify download: {}", e))
 }
 
-/// Get watch progress for a specific episode
+/// Verify every completed download in the background; progress arrives as
+/// download-verify-progress events
 #[tauri::command]
-pub async fn get_watch_progress(
-    state: State<'_, AppState>,
-    episode_id: String,
-) -> Result<Option<crate::database::watch_history::WatchHistory>, String> {
-    use crate::database::watch_history::get_watch_progress as get_progress;
-
-    get_progress(state.database.pool(), &episode_id)
-        .await
-        .map_err(|e| format!("Failed to get watch progress: {}", e))
+pub async fn verify_all_downloads(
+    app: AppHandle,
+    checksum: Option<bool>,
+) -> Result<(), String> {
+    crate::downloads::verify::start_verify_all(app, checksum.unwrap_or(false))
+        .map_err(|e| format!("Failed to verify downloads: {}", e))
 }
 
-/// Get watch progress for all episodes of a media (batch)
+/// Check every completed download of one series now: size, container header
+/// and, with `hash` set, the stored checksum. Bad files are flagged corrupt
+/// (or missing) and logged as download events rather than failed.
 #[tauri::command]
-pub async fn get_batch_watch_progress(
-    state: State<'_, AppState>,
+pub async fn verify_media_downloads(
+    download_manager: State<'_, DownloadManager>,
     media_id: String,
-) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
-    use crate::database::watch_history::get_media_watch_history;
-
-    get_media_watch_history(state.database.pool(), &media_id)
+    hash: Option<bool>,
+) -> Result<Vec<crate::downloads::verify::VerifyResult>, String> {
+    download_manager
+        .verify_media_downloads(&media_id, hash.unwrap_or(false))
         .await
-        .map_err(|e| format!("Failed to get batch watch progress: {}", e))
+        .map_err(|e| format!("Failed to verify downloads: {}", e))
 }
 
-/// Get reading progress for all chapters of a manga (batch)
+/// Get the schedule for verifying downloads during maintenance
 #[tauri::command]
-pub async fn get_batch_reading_progress(
+pub async fn get_verify_schedule(
     state: State<'_, AppState>,
-    media_id: String,
-) -> Result<Vec<crate::database::reading_history::ReadingHistory>, String> {
-    use crate::database::reading_history::get_manga_reading_history;
-
-    get_manga_reading_history(state.database.pool(), &media_id)
+) -> Result<crate::downloads::verify_sweep::VerifySchedule, String> {
+    crate::downloads::verify_sweep::load_schedule(state.database.pool())
         .await
-        .map_err(|e| format!("Failed to get batch reading progress: {}", e))
+        .map_err(|e| format!("Failed to get verify schedule: {}", e))
 }
 
-/// Get the most recent watch progress for a media (for Resume Watching feature)
+/// Update the schedule for verifying downloads during maintenance
 #[tauri::command]
-pub async fn get_latest_watch_progress_for_media(
+pub async fn set_verify_schedule(
     state: State<'_, AppState>,
-    media_id: String,
-) -> Result<Option<crate::database::watch_history::WatchHistory>, String> {
-    use crate::database::watch_history::get_latest_watch_progress_for_media as get_latest;
-
-    get_latest(state.database.pool(), &media_id)
+    schedule: crate::downloads::verify_sweep::VerifySchedule,
+) -> Result<(), String> {
+    crate::downloads::verify_sweep::save_schedule(state.database.pool(), &schedule)
         .await
-        .map_err(|e| format!("Failed to get latest watch progress: {}", e))
+        .map_err(|e| format!("Failed to save verify schedule: {}", e))
 }
 
-/// Get continue watching list (recently watched episodes that aren't completed)
+/// Get integrity events (verified, corrupt, missing), most recent first,
+/// for one download or all of them
 #[tauri::command]
-pub async fn get_continue_watching(
+pub async fn get_download_events(
     state: State<'_, AppState>,
-    limit: i32,
-) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
-    use crate::database::watch_history::get_continue_watching as get_continue;
+    download_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<crate::downloads::verify_sweep::DownloadEvent>, String> {
+    crate::downloads::verify_sweep::list_events(
+        state.database.pool(),
+        download_id.as_deref(),
+        limit.unwrap_or(100).clamp(1, 500),
+    )
+    .await
+    .map_err(|e| format!("Failed to get download events: {}", e))
+}
 
-    get_continue(state.database.pool(), limit)
+/// Retry the failed episodes of a download batch, returning how many were queued
+#[tauri::command]
+pub async fn retry_download_batch(
+    download_manager: State<'_, DownloadManager>,
+    batch_id: String,
+) -> Result<usize, String> {
+    download_manager
+        .retry_batch(&batch_id)
         .await
-        .map_err(|e| format!("Failed to get continue watching: {}", e))
+        .map_err(|e| format!("Failed to retry download batch: {}", e))
 }
 
-/// Remove media from continue watching (deletes all watch history for that media)
+/// Retry every failed download (or those matching `filter`), grouped by why
+/// they failed. Downloads whose source URL has expired get a fresh one from
+/// their extension; disk errors are left for the user. Returns a summary
+/// per group.
 #[tauri::command]
-pub async fn remove_from_continue_watching(
+pub async fn retry_failed_downloads(
+    app: AppHandle,
     state: State<'_, AppState>,
-    media_id: String,
-) -> Result<(), String> {
-    use crate::database::watch_history::delete_media_watch_history;
+    download_manager: State<'_, DownloadManager>,
+    filter: Option<crate::downloads::retry_failed::RetryFailedFilter>,
+    allow_adult: Option<bool>,
+) -> Result<crate::downloads::retry_failed::RetryFailedSummary, String> {
+    use crate::downloads::{lazy_source::ResolvedSource, source_refresh, upgrade};
+
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let resolve = |extension_id: String, download: DownloadProgress| {
+        let app = app.clone();
+        async move {
+            let episode_id = upgrade::source_episode_id(&download.episode_id);
+            let sources = lazy_source::fetch_sources(&app, &extension_id, episode_id, allow_adult).await?;
+            source_refresh::pick_matching_source(&sources, download.quality.as_deref(), download.source_label.as_deref())
+                .map(ResolvedSource::from_source)
+                .ok_or_else(|| anyhow::anyhow!("No usable sources for this episode"))
+        }
+    };
 
-    delete_media_watch_history(state.database.pool(), &media_id)
+    download_manager
+        .retry_failed_downloads(&filter.unwrap_or_default(), resolve)
         .await
-        .map_err(|e| format!("Failed to remove from continue watching: {}", e))?;
+        .map_err(|e| format!("Failed to retry failed downloads: {}", e))
+}
 
-    log::debug!("Removed media {} from continue watching", media_id);
-    Ok(())
+/// Result of starting a batch download
+#[derive(serde::Serialize)]
+pub struct BatchDownloadStarted {
+    pub batch_id: String,
+    pub queued: usize,
+    /// Episode numbers left out because they're downloaded already
+    pub already_downloaded: Vec<i32>,
+    /// Estimated size of the whole batch, None when it couldn't be told
+    pub estimated_bytes: Option<u64>,
+}
+
+/// Estimate the size of a set of downloads before queueing them: each URL is
+/// probed with `headers` (the source's; the defaults when None) for its size
+/// and whether it accepts byte ranges (unknown sizes are reported as such),
+/// and the total is compared with the free space on the download volume
+/// minus the reserve.
+#[tauri::command]
+pub async fn estimate_download_size(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+    urls: Vec<String>,
+    custom_path: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<size_estimate::DownloadSizeEstimate, String> {
+    let items = size_estimate::estimate_sizes(&urls, &headers.unwrap_or_default()).await;
+    let directory = custom_path.unwrap_or_else(|| download_manager.get_downloads_directory());
+    let available = disk_space::available_space(std::path::Path::new(&directory));
+    let reserve = disk_space::reserve_bytes(state.database.pool()).await;
+
+    Ok(size_estimate::summarize(items, available, reserve))
+}
+
+/// Queue a batch of episodes without fetching their sources: each episode's
+/// source is fetched from the extension right before it starts downloading,
+/// so URLs can't expire in the queue. Episodes already queued are skipped, and
+/// so are downloaded ones (listed in `already_downloaded`) unless `overwrite`
+/// is set.
+///
+/// Unless `force` is set, the first episode's source is probed and its size
+/// times the number of episodes is checked against the free disk space; the
+/// batch is refused when it wouldn't fit.
+///
+/// With `scheduled_start` (Unix ms) the episodes wait in the queue until then.
+#[tauri::command]
+pub async fn start_batch_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    extension_id: String,
+    episode_ids: Vec<String>,
+    custom_path: Option<String>,
+    force: Option<bool>,
+    overwrite: Option<bool>,
+    scheduled_start: Option<i64>,
+    allow_adult: Option<bool>,
+) -> Result<BatchDownloadStarted, CommandError> {
+    let allow_adult = adult::allow_adult(state.database.pool(), allow_adult).await;
+    let extension = state.extension(&extension_id)?;
+    let details = {
+        let runtime = guarded_runtime(extension, allow_adult)?;
+        circuit_breaker::track(&extension_id, runtime.get_details(&media_id))
+            .map_err(|e| format!("Failed to get anime details: {}", e))?
+    };
+
+    let mut episodes = Vec::new();
+    for episode_id in &episode_ids {
+        let episode = details
+            .episodes
+            .iter()
+            .find(|ep| &ep.id == episode_id)
+            .ok_or_else(|| format!("Episode not found: {}", episode_id))?;
+        episodes.push((episode_id.clone(), episode.number.round() as i32));
+    }
+    let overwrite = overwrite.unwrap_or(false);
+    let selection = download_manager.select_batch_episodes(&media_id, &episodes, overwrite).await;
+    let to_queue = selection.to_queue;
+
+    // Episodes of a season are about the same size, so one probe stands in
+    // for all of them. HLS playlists have no meaningful Content-Length.
+    let mut estimated_bytes = None;
+    if let Some((_, first_episode_id, _)) = to_queue.first().filter(|_| !force.unwrap_or(false)) {
+        match lazy_source::fetch_source(&app, &extension_id, first_episode_id).await {
+            Ok(source) if !source.is_hls => {
+                let items = size_estimate::estimate_sizes(std::slice::from_ref(&source.url), &source.headers).await;
+                estimated_bytes = items[0].size.map(|size| size * to_queue.len() as u64);
+            }
+            Ok(_) => {}
+            Err(e) => log::debug!("Could not estimate the size of batch for {}: {}", media_id, e),
+        }
+
+        let directory = custom_path.clone().unwrap_or_else(|| download_manager.get_downloads_directory());
+        if let (Some(needed), Some(available)) =
+            (estimated_bytes, disk_space::available_space(std::path::Path::new(&directory)))
+        {
+            let reserve = disk_space::reserve_bytes(state.database.pool()).await;
+            if !disk_space::fits(needed, available, reserve) {
+                let locale = crate::locale::current_locale(state.database.pool()).await;
+                return Err(format!(
+                    "Not enough disk space: this needs about {}, {} free ({} kept in reserve)",
+                    crate::locale::format_bytes(needed, locale),
+                    crate::locale::format_bytes(available, locale),
+                    crate::locale::format_bytes(reserve, locale)
+                )
+                .into());
+            }
+        }
+    }
+
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    let mut queued = 0;
+    let mut already_downloaded = selection.already_downloaded;
+
+    for (download_id, episode_id, episode_number) in to_queue {
+        let result = download_manager
+            .queue_pending_download(
+                download_id,
+                media_id.clone(),
+                episode_id,
+                episode_number,
+                lazy_source::placeholder_filename(&details.title, episode_number),
+                custom_path.clone(),
+                Some(batch_id.clone()),
+                extension_id.clone(),
+                scheduled_start,
+                overwrite,
+            )
+            .await;
+        match result.map_err(|e| QueueError::from_anyhow(e, "Failed to queue download")) {
+            Ok(()) => queued += 1,
+            // Its file is there even though no download says so
+            Err(QueueError::AlreadyDownloaded(_)) => already_downloaded.push(episode_number),
+            Err(e) => {
+                download_manager.seal_batch(&batch_id).await;
+                return Err(e.to_string().into());
+            }
+        }
+    }
+    download_manager.seal_batch(&batch_id).await;
+
+    log::debug!(
+        "Queued batch {} of {} episode(s) for {} ({} already downloaded)",
+        batch_id, queued, media_id, already_downloaded.len()
+    );
+    Ok(BatchDownloadStarted {
+        batch_id,
+        queued,
+        already_downloaded,
+        estimated_bytes,
+    })
+}
+
+/// Mark a batch queued through start_download as complete once its last
+/// episode is queued; its summary can't go out before then
+#[tauri::command]
+pub async fn seal_download_batch(
+    download_manager: State<'_, DownloadManager>,
+    batch_id: String,
+) -> Result<(), String> {
+    download_manager.seal_batch(&batch_id).await;
+    Ok(())
+}
+
+/// Progress of each download batch of a media item
+#[tauri::command]
+pub async fn get_batch_progress(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+) -> Result<Vec<crate::downloads::batch::BatchProgress>, String> {
+    Ok(download_manager.batch_progress(&media_id).await)
+}
+
+/// Downloaded episodes watchable in a row from the watch position, for each
+/// series with a batch download in progress
+#[tauri::command]
+pub async fn get_offline_ready(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::downloads::offline_ready::OfflineReady>, String> {
+    crate::downloads::offline_ready::get_offline_ready(state.database.pool(), state.profile_id())
+        .await
+        .map_err(|e| format!("Failed to get offline-ready episodes: {}", e))
+}
+
+/// Cancel the episodes of a batch that haven't started, returning how many
+#[tauri::command]
+pub async fn cancel_batch_download(
+    download_manager: State<'_, DownloadManager>,
+    batch_id: String,
+) -> Result<usize, String> {
+    download_manager
+        .cancel_batch(&batch_id)
+        .await
+        .map_err(|e| format!("Failed to cancel download batch: {}", e))
+}
+
+/// Check if an episode is downloaded
+#[tauri::command]
+pub async fn is_episode_downloaded(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    episode_number: i32,
+) -> Result<bool, String> {
+    Ok(download_manager.is_episode_downloaded(&media_id, episode_number).await)
+}
+
+/// Get the file path for a downloaded episode
+#[tauri::command]
+pub async fn get_episode_file_path(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    episode_number: i32,
+) -> Result<Option<String>, String> {
+    Ok(download_manager.get_episode_file_path(&media_id, episode_number).await)
+}
+
+/// Subtitle files downloaded with an episode, for offline playback
+#[tauri::command]
+pub async fn get_episode_subtitle_paths(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    episode_number: i32,
+) -> Result<Vec<crate::downloads::subtitles::DownloadSubtitle>, String> {
+    download_manager
+        .get_episode_subtitle_paths(&media_id, episode_number)
+        .await
+        .map_err(|e| format!("Failed to get subtitles: {}", e))
+}
+
+/// Get total storage used by downloads
+#[tauri::command]
+pub async fn get_total_storage_used(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<u64, String> {
+    Ok(download_manager.get_total_storage_used().await)
+}
+
+/// Get the downloads directory path
+#[tauri::command]
+pub async fn get_downloads_directory(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<String, String> {
+    Ok(download_manager.get_downloads_directory())
+}
+
+/// Open the downloads directory in file explorer
+#[tauri::command]
+pub async fn open_downloads_folder(
+    download_manager: State<'_, DownloadManager>,
+    custom_path: Option<String>,
+) -> Result<(), String> {
+    // No-op on Android (no file explorer to open)
+    #[cfg(target_os = "android")]
+    {
+        let _ = (download_manager, custom_path);
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        // Use custom path if provided, otherwise use default
+        let path = custom_path.unwrap_or_else(|| download_manager.get_downloads_directory());
+
+        // Ensure the directory exists
+        std::fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+        // Open the directory based on the platform
+        #[cfg(target_os = "windows")]
+        {
+            std::process::Command::new("explorer")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            std::process::Command::new("open")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            std::process::Command::new("xdg-open")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove a download from the list
+#[tauri::command]
+pub async fn remove_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    download_manager
+        .remove_download(&download_id)
+        .await
+        .map_err(|e| format!("Failed to remove download: {}", e))
+}
+
+/// Delete a downloaded file
+#[tauri::command]
+pub async fn delete_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    download_manager
+        .delete_download(&download_id)
+        .await
+        .map_err(|e| format!("Failed to delete download: {}", e))
+}
+
+/// Move a completed download's file to the trash, keeping the download listed
+#[tauri::command]
+pub async fn trash_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    download_manager
+        .trash_download(&download_id)
+        .await
+        .map_err(|e| format!("Failed to move download to trash: {}", e))
+}
+
+/// Move a trashed download's file back to where it was
+#[tauri::command]
+pub async fn restore_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    download_manager
+        .restore_download(&download_id)
+        .await
+        .map_err(|e| format!("Failed to restore download: {}", e))
+}
+
+/// Delete a downloaded episode by media ID and episode number
+#[tauri::command]
+pub async fn delete_episode_download(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    episode_number: i32,
+) -> Result<(), String> {
+    download_manager
+        .delete_episode_download(&media_id, episode_number)
+        .await
+        .map_err(|e| format!("Failed to delete episode download: {}", e))
+}
+
+/// Clear downloads with a finished status (completed, failed or cancelled)
+/// from the list, returning how many were removed
+#[tauri::command]
+pub async fn clear_downloads_by_status(
+    download_manager: State<'_, DownloadManager>,
+    status: DownloadStatus,
+) -> Result<usize, String> {
+    download_manager
+        .clear_downloads_by_status(status)
+        .await
+        .map_err(|e| format!("Failed to clear downloads: {}", e))
+}
+
+/// Get a page of the download history (completed downloads and those taken
+/// off the list), most recent first
+#[tauri::command]
+pub async fn get_download_history(
+    state: State<'_, AppState>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<DownloadHistoryEntry>, String> {
+    crate::downloads::history::list(state.database.pool(), limit.unwrap_or(100).clamp(1, 500), offset.unwrap_or(0))
+        .await
+        .map_err(|e| format!("Failed to get download history: {}", e))
+}
+
+/// Clear completed downloads from list
+#[tauri::command]
+pub async fn clear_completed_downloads(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<(), String> {
+    download_manager
+        .clear_completed()
+        .await
+        .map_err(|e| format!("Failed to clear completed downloads: {}", e))
+}
+
+/// Clear failed downloads from list
+#[tauri::command]
+pub async fn clear_failed_downloads(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<(), String> {
+    download_manager
+        .clear_failed()
+        .await
+        .map_err(|e| format!("Failed to clear failed downloads: {}", e))
+}
+
+/// Clear cancelled downloads from list
+#[tauri::command]
+pub async fn clear_cancelled_downloads(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<(), String> {
+    download_manager
+        .clear_cancelled()
+        .await
+        .map_err(|e| format!("Failed to clear cancelled downloads: {}", e))
+}
+
+
+// ==================== Watch History Commands ====================
+
+/// Save or update watch progress for an episode.
+/// `completed` is authoritative when given; otherwise the backend decides from
+/// the completion threshold setting.
+#[tauri::command]
+pub async fn save_watch_progress(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    media_id: String,
+    episode_id: String,
+    episode_number: i32,
+    progress_seconds: f64,
+    duration: Option<f64>,
+    completed: Option<bool>,
+    session_token: Option<String>,
+) -> Result<crate::database::watch_history::WatchProgressSaved, crate::playback_sessions::PlaybackSessionError> {
+    use crate::database::watch_history::{save_watch_progress as save_progress, WatchProgress};
+    use crate::episode_completion::{on_episode_completed, EpisodeCompleted};
+    use crate::playback_sessions::{authorize_save, PlaybackSessionError};
+
+    let pool = state.database.pool();
+
+    // Explicit mark watched/unwatched isn't a player write and needs no session
+    if session_token.is_some() || completed.is_none() {
+        authorize_save(pool, session_token.as_deref(), &episode_id).await?;
+    }
+
+    let progress = WatchProgress {
+        media_id,
+        episode_id,
+        episode_number,
+        progress_seconds,
+        duration,
+        completed,
+    };
+
+    let saved = save_progress(pool, state.profile_id(), &progress)
+        .await
+        .map_err(|e| PlaybackSessionError::Storage(format!("Failed to save watch progress: {}", e)))?;
+
+    if saved.newly_completed {
+        on_episode_completed(
+            &app,
+            pool,
+            EpisodeCompleted {
+                media_id: progress.media_id,
+                episode_id: progress.episode_id,
+                episode_number: progress.episode_number,
+            },
+        )
+        .await;
+    }
+
+    Ok(saved)
+}
+
+/// Get watch progress for a specific episode.
+/// Offers the last playback heartbeat instead when it is newer than the saved
+/// progress (e.g. the player crashed before its next save).
+#[tauri::command]
+pub async fn get_watch_progress(
+    state: State<'_, AppState>,
+    episode_id: String,
+) -> Result<Option<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::get_watch_progress as get_progress;
+
+    let progress = get_progress(state.database.pool(), state.profile_id(), &episode_id)
+        .await
+        .map_err(|e| format!("Failed to get watch progress: {}", e))?;
+
+    Ok(progress.map(crate::playback_recovery::apply_recovery))
+}
+
+/// Record the player's current position (kept in memory, flushed to the
+/// crash-recovery file periodically)
+#[tauri::command]
+pub async fn report_playback_heartbeat(
+    state: State<'_, AppState>,
+    media_id: String,
+    episode_id: String,
+    position: f64,
+    session_token: Option<String>,
+) -> Result<(), crate::playback_sessions::PlaybackSessionError> {
+    state.activity.note_playback();
+    if let Some(token) = &session_token {
+        crate::playback_sessions::touch_session(token, &episode_id)?;
+    }
+    crate::playback_recovery::report_heartbeat(&media_id, &episode_id, position);
+    Ok(())
+}
+
+/// Start a playback session for an episode. Any other player's session for
+/// the episode is superseded and its progress saves are rejected from now on.
+#[tauri::command]
+pub async fn start_playback_session(
+    media_id: String,
+    episode_id: String,
+) -> Result<crate::playback_sessions::PlaybackSession, String> {
+    Ok(crate::playback_sessions::start_session(&media_id, &episode_id))
+}
+
+/// End a playback session (player closed)
+#[tauri::command]
+pub async fn end_playback_session(session_token: String) -> Result<(), String> {
+    crate::playback_sessions::end_session(&session_token);
+    Ok(())
+}
+
+/// Playback sessions that haven't timed out, for debugging
+#[tauri::command]
+pub async fn get_active_playback_sessions() -> Result<Vec<crate::playback_sessions::PlaybackSession>, String> {
+    Ok(crate::playback_sessions::active_sessions())
+}
+
+/// Get watch progress for all episodes of a media (batch)
+#[tauri::command]
+pub async fn get_batch_watch_progress(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::get_media_watch_history;
+
+    get_media_watch_history(state.database.pool(), state.profile_id(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to get batch watch progress: {}", e))
+}
+
+/// Get reading progress for all chapters of a manga (batch)
+#[tauri::command]
+pub async fn get_batch_reading_progress(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<Vec<crate::database::reading_history::ReadingHistory>, String> {
+    use crate::database::reading_history::get_manga_reading_history;
+
+    get_manga_reading_history(state.database.pool(), state.profile_id(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to get batch reading progress: {}", e))
+}
+
+/// Get the most recent watch progress for a media (for Resume Watching feature)
+#[tauri::command]
+pub async fn get_latest_watch_progress_for_media(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<Option<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::get_latest_watch_progress_for_media as get_latest;
+
+    get_latest(state.database.pool(), state.profile_id(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to get latest watch progress: {}", e))
+}
+
+/// Get continue watching list (recently watched episodes that aren't completed)
+#[tauri::command]
+pub async fn get_continue_watching(
+    state: State<'_, AppState>,
+    limit: i32,
+) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::get_continue_watching as get_continue;
+
+    get_continue(state.database.pool(), state.profile_id(), limit)
+        .await
+        .map_err(|e| format!("Failed to get continue watching: {}", e))
+}
+
+/// Remove media from continue watching (deletes all watch history for that media)
+#[tauri::command]
+pub async fn remove_from_continue_watching(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<(), String> {
+    use crate::database::watch_history::delete_media_watch_history;
+
+    delete_media_watch_history(state.database.pool(), state.profile_id(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to remove from continue watching: {}", e))?;
+
+    log::debug!("Removed media {} from continue watching", media_id);
+    Ok(())
+}
+
+/// Delete the watch history of a single episode. Returns the deleted rows,
+/// which restore_watch_history takes to undo the deletion.
+#[tauri::command]
+pub async fn delete_episode_watch_history(
+    state: State<'_, AppState>,
+    episode_id: String,
+) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::delete_episode_watch_history as delete_episode;
+
+    delete_episode(state.database.pool(), state.profile_id(), &episode_id)
+        .await
+        .map_err(|e| format!("Failed to delete episode watch history: {}", e))
+}
+
+/// Delete the watch history of episodes from_episode..=to_episode of a media.
+/// Returns the deleted rows.
+#[tauri::command]
+pub async fn delete_watch_history_range(
+    state: State<'_, AppState>,
+    media_id: String,
+    from_episode: i32,
+    to_episode: i32,
+) -> Result<Vec<crate::database::watch_history::WatchHistory>, String> {
+    use crate::database::watch_history::delete_watch_history_range as delete_range;
+
+    delete_range(state.database.pool(), state.profile_id(), &media_id, from_episode, to_episode)
+        .await
+        .map_err(|e| format!("Failed to delete watch history range: {}", e))
+}
+
+/// Undo a watch history deletion, returning the number of rows restored
+#[tauri::command]
+pub async fn restore_watch_history(
+    state: State<'_, AppState>,
+    entries: Vec<crate::database::watch_history::WatchHistory>,
+) -> Result<u64, String> {
+    use crate::database::watch_history::restore_watch_history as restore;
+
+    restore(state.database.pool(), state.profile_id(), &entries)
+        .await
+        .map_err(|e| format!("Failed to restore watch history: {}", e))
 }
 
 // ==================== Reading History Commands ====================
@@ -1449,7 +2705,7 @@ pub async fn save_reading_progress(
         completed,
     };
 
-    save_progress(state.database.pool(), &progress)
+    save_progress(state.database.pool(), state.profile_id(), &progress)
         .await
         .map_err(|e| format!("Failed to save reading progress: {}", e))
 }
@@ -1462,7 +2718,7 @@ pub async fn get_reading_progress(
 ) -> Result<Option<crate::database::reading_history::ReadingHistory>, String> {
     use crate::database::reading_history::get_reading_progress as get_progress;
 
-    get_progress(state.database.pool(), &chapter_id)
+    get_progress(state.database.pool(), state.profile_id(), &chapter_id)
         .await
         .map_err(|e| format!("Failed to get reading progress: {}", e))
 }
@@ -1475,7 +2731,7 @@ pub async fn get_latest_reading_progress_for_media(
 ) -> Result<Option<crate::database::reading_history::ReadingHistory>, String> {
     use crate::database::reading_history::get_latest_reading_progress_for_media as get_latest;
 
-    get_latest(state.database.pool(), &media_id)
+    get_latest(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to get latest reading progress: {}", e))
 }
@@ -1488,7 +2744,7 @@ pub async fn get_continue_reading(
 ) -> Result<Vec<crate::database::reading_history::ReadingHistory>, String> {
     use crate::database::reading_history::get_continue_reading as get_continue;
 
-    get_continue(state.database.pool(), limit)
+    get_continue(state.database.pool(), state.profile_id(), limit)
         .await
         .map_err(|e| format!("Failed to get continue reading: {}", e))
 }
@@ -1501,7 +2757,7 @@ pub async fn remove_from_continue_reading_manga(
 ) -> Result<(), String> {
     use crate::database::reading_history::delete_manga_reading_history;
 
-    delete_manga_reading_history(state.database.pool(), &media_id)
+    delete_manga_reading_history(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to remove from continue reading: {}", e))?;
 
@@ -1509,23 +2765,140 @@ pub async fn remove_from_continue_reading_manga(
     Ok(())
 }
 
+/// Delete the reading history of a single chapter. Returns the deleted rows,
+/// which restore_reading_history takes to undo the deletion.
+#[tauri::command]
+pub async fn delete_chapter_reading_history(
+    state: State<'_, AppState>,
+    chapter_id: String,
+) -> Result<Vec<crate::database::reading_history::ReadingHistory>, String> {
+    use crate::database::reading_history::delete_chapter_reading_history as delete_chapter;
+
+    delete_chapter(state.database.pool(), state.profile_id(), &chapter_id)
+        .await
+        .map_err(|e| format!("Failed to delete chapter reading history: {}", e))
+}
+
+/// Delete the reading history of chapters from_chapter..=to_chapter of a
+/// manga. Returns the deleted rows.
+#[tauri::command]
+pub async fn delete_reading_history_range(
+    state: State<'_, AppState>,
+    media_id: String,
+    from_chapter: f64,
+    to_chapter: f64,
+) -> Result<Vec<crate::database::reading_history::ReadingHistory>, String> {
+    use crate::database::reading_history::delete_reading_history_range as delete_range;
+
+    delete_range(state.database.pool(), state.profile_id(), &media_id, from_chapter, to_chapter)
+        .await
+        .map_err(|e| format!("Failed to delete reading history range: {}", e))
+}
+
+/// Undo a reading history deletion, returning the number of rows restored
+#[tauri::command]
+pub async fn restore_reading_history(
+    state: State<'_, AppState>,
+    entries: Vec<crate::database::reading_history::ReadingHistory>,
+) -> Result<u64, String> {
+    use crate::database::reading_history::restore_reading_history as restore;
+
+    restore(state.database.pool(), state.profile_id(), &entries)
+        .await
+        .map_err(|e| format!("Failed to restore reading history: {}", e))
+}
+
+// ==================== Reading Speed Commands ====================
+
+/// Start timing page turns for a chapter; returns the session id
+#[tauri::command]
+pub async fn start_reading_session(
+    media_id: String,
+    chapter_id: String,
+    page: i32,
+) -> Result<u64, String> {
+    Ok(crate::database::reading_speed::start_reading_session(&media_id, &chapter_id, page))
+}
+
+/// Report a page turn in a reading session
+#[tauri::command]
+pub async fn record_reading_page_turn(
+    session_id: u64,
+    page: i32,
+) -> Result<bool, String> {
+    Ok(crate::database::reading_speed::record_page_turn(session_id, page))
+}
+
+/// End a reading session and save its timings into the reading speed averages
+#[tauri::command]
+pub async fn end_reading_session(
+    state: State<'_, AppState>,
+    session_id: u64,
+) -> Result<Option<crate::database::reading_speed::ReadingSessionSummary>, String> {
+    crate::database::reading_speed::end_reading_session(state.database.pool(), state.profile_id(), session_id)
+        .await
+        .map_err(|e| format!("Failed to end reading session: {}", e))
+}
+
+/// Estimate minutes left in a chapter from saved progress and reading speed
+#[tauri::command]
+pub async fn get_reading_time_estimate(
+    state: State<'_, AppState>,
+    media_id: String,
+    chapter_id: String,
+) -> Result<Option<crate::database::reading_speed::ReadingTimeEstimate>, String> {
+    crate::database::reading_speed::get_reading_time_estimate(state.database.pool(), state.profile_id(), &media_id, &chapter_id)
+        .await
+        .map_err(|e| format!("Failed to estimate reading time: {}", e))
+}
+
 // ==================== Library Commands ====================
 
-/// Add media to library
+/// Add media to library.
+/// `track_releases` opts the title in or out of release checking; when omitted
+/// only ongoing titles (or ones with an unclassifiable status) are tracked.
 #[tauri::command]
 pub async fn add_to_library(
     state: State<'_, AppState>,
     media_id: String,
     status: String,
+    track_releases: Option<bool>,
 ) -> Result<crate::database::library::LibraryEntry, String> {
     use crate::database::library::{add_to_library as add_media, LibraryStatus};
 
     let status = LibraryStatus::from_str(&status)
         .ok_or_else(|| format!("Invalid library status: {}", status))?;
 
-    add_media(state.database.pool(), &media_id, status)
+    let pool = state.database.pool();
+    let entry = add_media(pool, state.profile_id(), &media_id, status)
         .await
-        .map_err(|e| format!("Failed to add to library: {}", e))
+        .map_err(|e| format!("Failed to add to library: {}", e))?;
+
+    let track = match track_releases {
+        Some(track) => track,
+        None => {
+            let raw_status: Option<String> = sqlx::query_scalar("SELECT status FROM media WHERE id = ?")
+                .bind(&media_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| format!("Failed to read media status: {}", e))?
+                .flatten();
+            release_checker::default_track_releases(raw_status.as_deref())
+        }
+    };
+
+    // An unset default leaves any existing tracking row as the user left it
+    let result = match (track, track_releases) {
+        (false, _) => release_checker::disable_release_tracking(pool, &media_id).await,
+        (true, Some(true)) => release_checker::enable_release_tracking(pool, &media_id).await,
+        (true, _) => Ok(()),
+    };
+    if let Err(e) = result {
+        // The entry is in the library either way; don't fail the add
+        log::warn!("Failed to update release tracking for {}: {}", media_id, e);
+    }
+
+    Ok(entry)
 }
 
 /// Remove media from library
@@ -1536,7 +2909,7 @@ pub async fn remove_from_library(
 ) -> Result<(), String> {
     use crate::database::library::remove_from_library as remove_media;
 
-    remove_media(state.database.pool(), &media_id)
+    remove_media(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to remove from library: {}", e))
 }
@@ -1549,7 +2922,7 @@ pub async fn get_library_entry(
 ) -> Result<Option<crate::database::library::LibraryEntry>, String> {
     use crate::database::library::get_library_entry as get_entry;
 
-    get_entry(state.database.pool(), &media_id)
+    get_entry(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to get library entry: {}", e))
 }
@@ -1570,7 +2943,7 @@ pub async fn get_library_by_status(
         None => None,
     };
 
-    get_by_status(state.database.pool(), status)
+    get_by_status(state.database.pool(), state.profile_id(), status)
         .await
         .map_err(|e| format!("Failed to get library: {}", e))
 }
@@ -1591,11 +2964,50 @@ pub async fn get_library_with_media(
         None => None,
     };
 
-    get_library_with_media_by_status(state.database.pool(), status)
+    get_library_with_media_by_status(state.database.pool(), state.profile_id(), status)
         .await
         .map_err(|e| format!("Failed to get library with media: {}", e))
 }
 
+/// Get one page of library entries with media.
+/// Pass the previous page's `next_cursor` to continue; `offset` is only used
+/// when no cursor is given. Sorts other than `updated_at` (next_airing_at,
+/// unwatched_count, last_release_at) page by `offset`.
+#[tauri::command]
+pub async fn get_library_with_media_page(
+    state: State<'_, AppState>,
+    status: Option<String>,
+    sort: Option<String>,
+    limit: Option<u32>,
+    cursor: Option<String>,
+    offset: Option<u32>,
+) -> Result<crate::database::library::LibraryPage, String> {
+    use crate::database::library::{get_library_with_media_page as get_page, LibrarySort, LibraryStatus, DEFAULT_PAGE_SIZE};
+
+    let status = match status {
+        Some(s) => Some(
+            LibraryStatus::from_str(&s)
+                .ok_or_else(|| format!("Invalid library status: {}", s))?
+        ),
+        None => None,
+    };
+    let sort = match sort {
+        Some(s) => LibrarySort::from_str(&s).ok_or_else(|| format!("Invalid library sort: {}", s))?,
+        None => LibrarySort::default(),
+    };
+
+    get_page(
+        state.database.pool(), state.profile_id(),
+        status,
+        sort,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        cursor.as_deref(),
+        offset.unwrap_or(0),
+    )
+    .await
+    .map_err(|e| format!("Failed to get library page: {}", e))
+}
+
 /// Toggle favorite status
 #[tauri::command]
 pub async fn toggle_favorite(
@@ -1604,7 +3016,7 @@ pub async fn toggle_favorite(
 ) -> Result<bool, String> {
     use crate::database::library::toggle_favorite as toggle;
 
-    toggle(state.database.pool(), &media_id)
+    toggle(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to toggle favorite: {}", e))
 }
@@ -1618,7 +3030,7 @@ pub async fn set_auto_download(
 ) -> Result<bool, String> {
     use crate::database::library::set_auto_download as set;
 
-    set(state.database.pool(), &media_id, enabled)
+    set(state.database.pool(), state.profile_id(), &media_id, enabled)
         .await
         .map_err(|e| format!("Failed to update auto-download: {}", e))
 }
@@ -1631,7 +3043,7 @@ pub async fn is_in_library(
 ) -> Result<bool, String> {
     use crate::database::library::is_in_library as check_library;
 
-    check_library(state.database.pool(), &media_id)
+    check_library(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to check library: {}", e))
 }
@@ -1647,7 +3059,7 @@ pub async fn create_library_tag(
 ) -> Result<crate::database::tags::LibraryTag, String> {
     use crate::database::tags::create_tag;
 
-    create_tag(state.database.pool(), &name, &color)
+    create_tag(state.database.pool(), state.profile_id(), &name, &color)
         .await
         .map_err(|e| format!("Failed to create tag: {}", e))
 }
@@ -1659,7 +3071,7 @@ pub async fn get_library_tags(
 ) -> Result<Vec<crate::database::tags::LibraryTag>, String> {
     use crate::database::tags::get_all_tags;
 
-    get_all_tags(state.database.pool())
+    get_all_tags(state.database.pool(), state.profile_id())
         .await
         .map_err(|e| format!("Failed to get tags: {}", e))
 }
@@ -1671,7 +3083,7 @@ pub async fn get_library_tags_with_counts(
 ) -> Result<Vec<crate::database::tags::LibraryTagWithCount>, String> {
     use crate::database::tags::get_tags_with_counts;
 
-    get_tags_with_counts(state.database.pool())
+    get_tags_with_counts(state.database.pool(), state.profile_id())
         .await
         .map_err(|e| format!("Failed to get tags with counts: {}", e))
 }
@@ -1688,6 +3100,7 @@ pub async fn update_library_tag(
 
     update_tag(
         state.database.pool(),
+        state.profile_id(),
         tag_id,
         name.as_deref(),
         color.as_deref(),
@@ -1704,7 +3117,7 @@ pub async fn delete_library_tag(
 ) -> Result<(), String> {
     use crate::database::tags::delete_tag;
 
-    delete_tag(state.database.pool(), tag_id)
+    delete_tag(state.database.pool(), state.profile_id(), tag_id)
         .await
         .map_err(|e| format!("Failed to delete tag: {}", e))
 }
@@ -1718,7 +3131,7 @@ pub async fn assign_library_tag(
 ) -> Result<(), String> {
     use crate::database::tags::assign_tag;
 
-    assign_tag(state.database.pool(), &media_id, tag_id)
+    assign_tag(state.database.pool(), state.profile_id(), &media_id, tag_id)
         .await
         .map_err(|e| format!("Failed to assign tag: {}", e))
 }
@@ -1732,7 +3145,7 @@ pub async fn unassign_library_tag(
 ) -> Result<(), String> {
     use crate::database::tags::unassign_tag;
 
-    unassign_tag(state.database.pool(), &media_id, tag_id)
+    unassign_tag(state.database.pool(), state.profile_id(), &media_id, tag_id)
         .await
         .map_err(|e| format!("Failed to unassign tag: {}", e))
 }
@@ -1745,7 +3158,7 @@ pub async fn get_media_tags(
 ) -> Result<Vec<crate::database::tags::LibraryTag>, String> {
     use crate::database::tags::get_tags_for_media;
 
-    get_tags_for_media(state.database.pool(), &media_id)
+    get_tags_for_media(state.database.pool(), state.profile_id(), &media_id)
         .await
         .map_err(|e| format!("Failed to get media tags: {}", e))
 }
@@ -1772,7 +3185,7 @@ pub async fn bulk_assign_library_tag(
 ) -> Result<(), String> {
     use crate::database::tags::bulk_assign_tag;
 
-    bulk_assign_tag(state.database.pool(), &media_ids, tag_id)
+    bulk_assign_tag(state.database.pool(), state.profile_id(), &media_ids, tag_id)
         .await
         .map_err(|e| format!("Failed to bulk assign tag: {}", e))
 }
@@ -1786,7 +3199,7 @@ pub async fn bulk_unassign_library_tag(
 ) -> Result<(), String> {
     use crate::database::tags::bulk_unassign_tag;
 
-    bulk_unassign_tag(state.database.pool(), &media_ids, tag_id)
+    bulk_unassign_tag(state.database.pool(), state.profile_id(), &media_ids, tag_id)
         .await
         .map_err(|e| format!("Failed to bulk unassign tag: {}", e))
 }
@@ -1803,7 +3216,7 @@ pub async fn bulk_update_library_status(
     let status = LibraryStatus::from_str(&status)
         .ok_or_else(|| format!("Invalid library status: {}", status))?;
 
-    bulk_update(state.database.pool(), &media_ids, status)
+    bulk_update(state.database.pool(), state.profile_id(), &media_ids, status)
         .await
         .map_err(|e| format!("Failed to bulk update status: {}", e))
 }
@@ -1816,24 +3229,78 @@ pub async fn bulk_remove_from_library(
 ) -> Result<(), String> {
     use crate::database::library::bulk_remove_from_library as bulk_remove;
 
-    bulk_remove(state.database.pool(), &media_ids)
+    bulk_remove(state.database.pool(), state.profile_id(), &media_ids)
         .await
         .map_err(|e| format!("Failed to bulk remove from library: {}", e))
 }
 
+/// Watching/reading entries without watch or reading history for
+/// `threshold_days` (default: the stale_library_threshold_days setting)
+#[tauri::command]
+pub async fn get_stale_library_entries(
+    state: State<'_, AppState>,
+    threshold_days: Option<u32>,
+) -> Result<Vec<crate::database::stale_library::StaleEntry>, String> {
+    use crate::database::stale_library::{get_stale_entries, threshold_days_setting};
+
+    let pool = state.database.pool();
+    let threshold_days = match threshold_days {
+        Some(days) => days,
+        None => threshold_days_setting(pool).await,
+    };
+    get_stale_entries(pool, state.profile_id(), threshold_days)
+        .await
+        .map_err(|e| format!("Failed to find stale library entries: {}", e))
+}
+
+/// Move stale entries to on_hold or dropped
+#[tauri::command]
+pub async fn move_stale_library_entries(
+    state: State<'_, AppState>,
+    media_ids: Vec<String>,
+    status: String,
+) -> Result<(), String> {
+    use crate::database::library::LibraryStatus;
+    use crate::database::stale_library::move_stale_entries;
+
+    let status = LibraryStatus::from_str(&status)
+        .ok_or_else(|| format!("Invalid library status: {}", status))?;
+
+    move_stale_entries(state.database.pool(), state.profile_id(), &media_ids, status)
+        .await
+        .map_err(|e| format!("Failed to move stale entries: {}", e))
+}
+
 // ==================== Media Commands ====================
 
-/// Save media details to database
+/// Save media details to database. Only the fields `media` has a value for
+/// are written, so a sparse entry never erases what a richer one stored;
+/// returns which fields were updated and which were kept.
 #[tauri::command]
 pub async fn save_media_details(
     state: State<'_, AppState>,
-    media: crate::database::media::MediaEntry,
+    media: crate::database::media::MediaEntry,
+    age_rating: Option<String>,
+) -> Result<crate::database::media::MediaSaveReport, String> {
+    use crate::database::media::save_media;
+
+    let report = save_media(state.database.pool(), &media)
+        .await
+        .map_err(|e| format!("Failed to save media: {}", e))?;
+    age_rating::record_fetched_rating(state.database.pool(), &media.id, age_rating.as_deref()).await;
+    Ok(report)
+}
+
+/// Empty fields of a stored media, which saving never does
+#[tauri::command]
+pub async fn clear_media_fields(
+    state: State<'_, AppState>,
+    media_id: String,
+    fields: Vec<String>,
 ) -> Result<(), String> {
-    use crate::database::media::save_media;
-
-    save_media(state.database.pool(), &media)
+    crate::database::media::clear_media_fields(state.database.pool(), &media_id, &fields)
         .await
-        .map_err(|e| format!("Failed to save media: {}", e))
+        .map_err(|e| format!("Failed to clear media fields: {}", e))
 }
 
 /// Get continue watching with full media details
@@ -1844,7 +3311,7 @@ pub async fn get_continue_watching_with_details(
 ) -> Result<Vec<crate::database::media::ContinueWatchingEntry>, String> {
     use crate::database::media::get_continue_watching_with_media;
 
-    get_continue_watching_with_media(state.database.pool(), limit)
+    get_continue_watching_with_media(state.database.pool(), state.profile_id(), limit)
         .await
         .map_err(|e| format!("Failed to get continue watching: {}", e))
 }
@@ -1857,7 +3324,7 @@ pub async fn get_continue_reading_with_details(
 ) -> Result<Vec<crate::database::media::ContinueReadingEntry>, String> {
     use crate::database::media::get_continue_reading_with_media;
 
-    get_continue_reading_with_media(state.database.pool(), limit)
+    get_continue_reading_with_media(state.database.pool(), state.profile_id(), limit)
         .await
         .map_err(|e| format!("Failed to get continue reading: {}", e))
 }
@@ -1874,6 +3341,34 @@ pub async fn get_downloads_with_media(
         .map_err(|e| format!("Failed to get downloads with media: {}", e))
 }
 
+/// Recreate the media rows downloads point at when they're missing (after
+/// clearing data, or a failed migration), so their series show up with a
+/// title and cover again. Returns how many were repaired and how.
+#[tauri::command]
+pub async fn repair_download_media_links(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::downloads::media_links::MediaLinkRepair, String> {
+    crate::downloads::media_links::repair(&app, state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to repair download media links: {}", e))
+}
+
+/// Compare the downloads directory with the downloads list: files no
+/// download points at (with sizes) and completed downloads whose file is
+/// gone. `reconcile` imports the orphaned files as completed downloads or
+/// deletes them.
+#[tauri::command]
+pub async fn scan_downloads_directory(
+    download_manager: State<'_, DownloadManager>,
+    reconcile: Option<crate::downloads::orphans::ReconcileAction>,
+) -> Result<crate::downloads::orphans::DownloadDirectoryScan, String> {
+    download_manager
+        .scan_downloads_directory(reconcile)
+        .await
+        .map_err(|e| format!("Failed to scan downloads directory: {}", e))
+}
+
 /// Save episodes to database for caching
 #[tauri::command]
 pub async fn save_episodes(
@@ -1927,9 +3422,14 @@ pub async fn get_discover_cache(
 ) -> Result<Option<crate::database::discover_cache::DiscoverCacheEntry>, String> {
     use crate::database::discover_cache::get_discover_cache as get_cache;
 
-    get_cache(state.database.pool(), &cache_key)
+    let mut entry = get_cache(state.database.pool(), &cache_key)
         .await
-        .map_err(|e| format!("Failed to get discover cache: {}", e))
+        .map_err(|e| format!("Failed to get discover cache: {}", e))?;
+
+    if let Some(entry) = entry.as_mut() {
+        HiddenSet::load(state.database.pool(), state.profile_id()).await.filter_json(&mut entry.data);
+    }
+    Ok(entry)
 }
 
 /// Get cached discover results with freshness metadata (for SWR pattern)
@@ -1940,9 +3440,14 @@ pub async fn get_discover_cache_with_freshness(
 ) -> Result<Option<crate::database::discover_cache::CachedDataWithMeta>, String> {
     use crate::database::discover_cache::get_discover_cache_with_freshness as get_cache;
 
-    get_cache(state.database.pool(), &cache_key)
+    let mut entry = get_cache(state.database.pool(), &cache_key)
         .await
-        .map_err(|e| format!("Failed to get discover cache with freshness: {}", e))
+        .map_err(|e| format!("Failed to get discover cache with freshness: {}", e))?;
+
+    if let Some(entry) = entry.as_mut() {
+        HiddenSet::load(state.database.pool(), state.profile_id()).await.filter_json(&mut entry.data);
+    }
+    Ok(entry)
 }
 
 /// Save discover results to cache with explicit TTL
@@ -1961,6 +3466,64 @@ pub async fn save_discover_cache_with_ttl(
         .map_err(|e| format!("Failed to save discover cache with TTL: {}", e))
 }
 
+/// Clear a single cache by name (see cache::CacheName), returning the number of entries removed
+#[tauri::command]
+pub async fn clear_cache(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<u64, String> {
+    crate::cache::clear_cache(state.database.pool(), &name)
+        .await
+        .map_err(|e| format!("Failed to clear cache: {}", e))
+}
+
+/// Entry counts of every cache, plus how the startup warm-up went
+#[tauri::command]
+pub async fn get_cache_stats(
+    state: State<'_, AppState>,
+) -> Result<crate::cache::CacheStats, String> {
+    Ok(crate::cache::cache_stats(state.database.pool()).await)
+}
+
+/// Disk usage of the media cache (reader pages, covers, thumbnails) per namespace
+#[tauri::command]
+pub async fn get_media_cache_usage(
+    state: State<'_, AppState>,
+) -> Result<crate::cache::media_disk::MediaCacheUsage, String> {
+    crate::cache::media_disk::usage(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get media cache usage: {}", e))
+}
+
+/// Delete the files of one media cache namespace, or of all of them
+#[tauri::command]
+pub async fn purge_media_cache(
+    namespace: Option<String>,
+) -> Result<crate::cache::media_disk::MediaCacheCleanup, String> {
+    crate::cache::media_disk::purge(namespace.as_deref())
+        .await
+        .map_err(|e| format!("Failed to purge media cache: {}", e))
+}
+
+/// Set the media cache cap in megabytes and trim the cache down to it
+#[tauri::command]
+pub async fn set_media_cache_max_size(
+    state: State<'_, AppState>,
+    max_mb: u64,
+) -> Result<crate::cache::media_disk::MediaCacheCleanup, String> {
+    let pool = state.database.pool();
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(crate::cache::media_disk::MAX_SIZE_SETTING)
+        .bind(max_mb.to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save media cache size: {}", e))?;
+
+    crate::cache::media_disk::enforce(pool)
+        .await
+        .map_err(|e| format!("Failed to trim media cache: {}", e))
+}
+
 // ==================== Data Management Commands ====================
 
 /// Clear all watch history
@@ -1968,7 +3531,8 @@ pub async fn save_discover_cache_with_ttl(
 pub async fn clear_all_watch_history(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    sqlx::query("DELETE FROM watch_history")
+    sqlx::query("DELETE FROM watch_history WHERE profile_id = ?")
+        .bind(state.profile_id())
         .execute(state.database.pool())
         .await
         .map_err(|e| format!("Failed to clear watch history: {}", e))?;
@@ -1982,7 +3546,8 @@ pub async fn clear_all_watch_history(
 pub async fn clear_library(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    sqlx::query("DELETE FROM library")
+    sqlx::query("DELETE FROM library WHERE profile_id = ?")
+        .bind(state.profile_id())
         .execute(state.database.pool())
         .await
         .map_err(|e| format!("Failed to clear library: {}", e))?;
@@ -2015,34 +3580,275 @@ pub async fn clear_all_data(
     Ok(())
 }
 
+use crate::storage_usage::{StoragePaths, StorageUsage};
+
+/// Get storage usage per category (database, downloads, chapters, covers, logs, ...).
+/// Cached for a minute; pass force_refresh after clearing data.
+#[tauri::command]
+pub async fn get_storage_usage(
+    app: AppHandle,
+    storage_paths: State<'_, StoragePaths>,
+    force_refresh: Option<bool>,
+) -> Result<StorageUsage, String> {
+    crate::storage_usage::get_storage_usage(&app, storage_paths.inner().clone(), force_refresh.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to get storage usage: {}", e))
+}
+
+// ==================== Genre Normalization Commands ====================
+
+use crate::database::genres::GenreMapping;
+
+/// List genre mappings (raw genre key → canonical genre)
+#[tauri::command]
+pub async fn get_genre_mappings(state: State<'_, AppState>) -> Result<Vec<GenreMapping>, String> {
+    crate::database::genres::list_genre_mappings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get genre mappings: {}", e))
+}
+
+/// Map a raw genre to a canonical one (takes effect for newly saved media;
+/// run renormalize_media_genres to update stored media)
+#[tauri::command]
+pub async fn set_genre_mapping(
+    state: State<'_, AppState>,
+    raw: String,
+    canonical: String,
+) -> Result<GenreMapping, String> {
+    crate::database::genres::set_genre_mapping(state.database.pool(), &raw, &canonical)
+        .await
+        .map_err(|e| format!("Failed to set genre mapping: {}", e))
+}
+
+/// Remove the mapping for a raw genre, returning whether there was one
+#[tauri::command]
+pub async fn delete_genre_mapping(state: State<'_, AppState>, raw: String) -> Result<bool, String> {
+    crate::database::genres::delete_genre_mapping(state.database.pool(), &raw)
+        .await
+        .map_err(|e| format!("Failed to delete genre mapping: {}", e))
+}
+
+/// Re-apply the genre mappings to all stored media, returning how many changed
+#[tauri::command]
+pub async fn renormalize_media_genres(state: State<'_, AppState>) -> Result<u64, String> {
+    crate::database::genres::renormalize_media_genres(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to renormalize genres: {}", e))
+}
+
+use crate::network_diagnostics::NetworkDiagnostics;
+
+/// Time DNS, connect, TTFB and throughput against the test URL and the source
+/// hosts. Finishes within about 30 seconds; the report is also written to the log.
+#[tauri::command]
+pub async fn run_network_diagnostics(
+    state: State<'_, AppState>,
+    test_url: Option<String>,
+    source_url: Option<String>,
+) -> Result<NetworkDiagnostics, String> {
+    let pool = state.database.pool();
+    Ok(crate::network_diagnostics::run_network_diagnostics(pool, test_url, source_url).await)
+}
+
+/// Rate-limit (429/503) retries by host since the app started, most first
+#[tauri::command]
+pub async fn get_http_retry_stats() -> Result<Vec<crate::http_retry::HostRetryStats>, String> {
+    Ok(crate::http_retry::host_retry_stats())
+}
+
+// ==================== Download Archive Commands ====================
+
+use crate::downloads::archive::{ArchiveResult, RescanResult, StorageBreakdown};
+
 #[derive(serde::Serialize)]
-pub struct StorageUsage {
+pub struct StorageBreakdownResponse {
     database_size: u64,
-    downloads_size: u64,
-    total_size: u64,
+    #[serde(flatten)]
+    downloads: StorageBreakdown,
 }
 
-/// Get storage usage information
+/// Move a series' completed downloads to cold storage (e.g. a NAS path)
 #[tauri::command]
-pub async fn get_storage_usage(
+pub async fn archive_downloads(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+    dest_dir: String,
+) -> Result<ArchiveResult, String> {
+    download_manager
+        .archive_downloads(&media_id, std::path::Path::new(&dest_dir))
+        .await
+        .map_err(|e| format!("Failed to archive downloads: {}", e))
+}
+
+/// Move a series' archived downloads back into local storage
+#[tauri::command]
+pub async fn unarchive_downloads(
+    download_manager: State<'_, DownloadManager>,
+    media_id: String,
+) -> Result<ArchiveResult, String> {
+    download_manager
+        .unarchive_downloads(&media_id)
+        .await
+        .map_err(|e| format!("Failed to unarchive downloads: {}", e))
+}
+
+/// Get storage usage split into local and archived downloads
+#[tauri::command]
+pub async fn get_storage_breakdown(
     state: State<'_, AppState>,
     download_manager: State<'_, DownloadManager>,
-) -> Result<StorageUsage, String> {
+) -> Result<StorageBreakdownResponse, String> {
     let database_size = state.database.get_database_size()
         .await
         .map_err(|e| format!("Failed to get database size: {}", e))?;
 
-    let downloads_size = download_manager.get_total_storage_used().await;
-
-    Ok(StorageUsage {
+    Ok(StorageBreakdownResponse {
         database_size,
-        downloads_size,
-        total_size: database_size + downloads_size,
+        downloads: download_manager.get_storage_breakdown().await,
     })
 }
 
+/// Re-check completed downloads against the filesystem
+#[tauri::command]
+pub async fn rescan_downloads(
+    download_manager: State<'_, DownloadManager>,
+) -> Result<RescanResult, String> {
+    download_manager
+        .rescan_downloads()
+        .await
+        .map_err(|e| format!("Failed to rescan downloads: {}", e))
+}
+
+/// List completed downloads below the preferred quality (or the
+/// preferred_quality setting when not given)
+#[tauri::command]
+pub async fn find_upgradeable_downloads(
+    download_manager: State<'_, DownloadManager>,
+    preferred_quality: Option<String>,
+) -> Result<Vec<crate::downloads::upgrade::UpgradeCandidate>, String> {
+    Ok(download_manager.find_upgradeable_downloads(preferred_quality).await)
+}
+
+/// Re-download an episode from a better source. The old file stays in place
+/// until the new one completes, then gets replaced.
+#[tauri::command]
+pub async fn upgrade_download(
+    download_manager: State<'_, DownloadManager>,
+    download_id: String,
+    url: String,
+    filename: String,
+    quality: Option<String>,
+    source_label: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    download_manager
+        .upgrade_download(&download_id, url, headers.unwrap_or_default(), filename, quality, source_label)
+        .await
+        .map_err(|e| format!("Failed to upgrade download: {}", e))
+}
+
+/// Move completed downloads into the organize_downloads folder template.
+/// With `dry_run`, returns the planned moves without touching any files.
+#[tauri::command]
+pub async fn reorganize_existing_downloads(
+    download_manager: State<'_, DownloadManager>,
+    dry_run: bool,
+) -> Result<crate::downloads::organize::ReorganizeResult, String> {
+    download_manager
+        .reorganize_existing_downloads(dry_run)
+        .await
+        .map_err(|e| format!("Failed to reorganize downloads: {}", e))
+}
+
+// ==================== Watch Folder Commands ====================
+
+use crate::downloads::watchfolder::{self, WatchFolderSettings, WatchScanResult};
+
+/// Get the watch folder settings
+#[tauri::command]
+pub async fn get_watch_folder_settings(
+    state: State<'_, AppState>,
+) -> Result<WatchFolderSettings, String> {
+    watchfolder::load_settings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get watch folder settings: {}", e))
+}
+
+/// Update the watch folder settings. Custom filename templates are validated
+/// before anything is saved.
+#[tauri::command]
+pub async fn set_watch_folder_settings(
+    state: State<'_, AppState>,
+    settings: WatchFolderSettings,
+) -> Result<(), String> {
+    watchfolder::save_settings(state.database.pool(), &settings)
+        .await
+        .map_err(|e| format!("Failed to save watch folder settings: {}", e))
+}
+
+/// Scan the watch folder now instead of waiting for the next poll.
+/// Files still being copied are picked up on a later scan.
+#[tauri::command]
+pub async fn scan_watch_folder(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+) -> Result<WatchScanResult, String> {
+    let settings = watchfolder::load_settings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get watch folder settings: {}", e))?;
+
+    download_manager
+        .scan_watch_folder(&settings)
+        .await
+        .map_err(|e| format!("Failed to scan watch folder: {}", e))
+}
+
+// ==================== Auto-Delete Watched Commands ====================
+
+use crate::downloads::auto_delete::{self, AutoDeleteSettings, AutoDeletedEpisode};
+
+/// Get the settings for deleting watched episodes
+#[tauri::command]
+pub async fn get_auto_delete_settings(
+    state: State<'_, AppState>,
+) -> Result<AutoDeleteSettings, String> {
+    auto_delete::load_settings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get auto-delete settings: {}", e))
+}
+
+/// Update the settings for deleting watched episodes
+#[tauri::command]
+pub async fn set_auto_delete_settings(
+    state: State<'_, AppState>,
+    settings: AutoDeleteSettings,
+) -> Result<(), String> {
+    auto_delete::save_settings(state.database.pool(), &settings)
+        .await
+        .map_err(|e| format!("Failed to save auto-delete settings: {}", e))
+}
+
+/// Delete watched episodes that are due now instead of waiting for the next
+/// sweep. Runs with the saved settings even while auto-delete is off.
+#[tauri::command]
+pub async fn sweep_watched_downloads(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+) -> Result<Vec<AutoDeletedEpisode>, String> {
+    let settings = auto_delete::load_settings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get auto-delete settings: {}", e))?;
+
+    auto_delete::sweep_exclusive(&download_manager, &settings)
+        .await
+        .ok_or_else(|| "Watched episodes are already being cleaned up".to_string())?
+        .map_err(|e| format!("Failed to delete watched episodes: {}", e))
+}
+
 // ==================== Video Server Commands ====================
 
+use crate::media::remux::{self, Container};
+
 #[derive(serde::Serialize)]
 pub struct VideoServerUrls {
     pub local_base_url: String,
@@ -2051,26 +3857,90 @@ pub struct VideoServerUrls {
     pub port: u16,
 }
 
-/// Get video server info for streaming
+impl From<VideoServerInfo> for VideoServerUrls {
+    fn from(video_server: VideoServerInfo) -> Self {
+        Self {
+            local_base_url: format!("http://127.0.0.1:{}/local", video_server.port),
+            proxy_base_url: format!("http://127.0.0.1:{}/proxy", video_server.port),
+            token: video_server.access_token,
+            port: video_server.port,
+        }
+    }
+}
+
+/// Get video server info for streaming. Waits for a launch still in
+/// progress at startup.
 #[tauri::command]
 pub async fn get_video_server_info(
-    video_server: State<'_, VideoServerInfo>,
+    video_server: State<'_, VideoServerHandle>,
 ) -> Result<VideoServerUrls, String> {
-    Ok(VideoServerUrls {
-        local_base_url: format!("http://127.0.0.1:{}/local", video_server.port),
-        proxy_base_url: format!("http://127.0.0.1:{}/proxy", video_server.port),
-        token: video_server.access_token.clone(),
-        port: video_server.port,
-    })
+    Ok(video_server.settled_info().await?.into())
+}
+
+/// Start the video server again after it failed to bind a port.
+/// Returns the current info straight away when it's already running.
+#[tauri::command]
+pub async fn retry_video_server(app: AppHandle) -> Result<VideoServerUrls, String> {
+    crate::video_server::launch(&app).await.map(Into::into)
 }
 
 /// Get streaming URL for a local downloaded file
+///
+/// MKV downloads can't be played by the webview, so when the file is
+/// Matroska the remux URL is returned instead (requires ffmpeg).
 #[tauri::command]
 pub async fn get_local_video_url(
-    video_server: State<'_, VideoServerInfo>,
+    video_server: State<'_, VideoServerHandle>,
+    download_manager: State<'_, DownloadManager>,
     filename: String,
 ) -> Result<String, String> {
-    Ok(video_server.local_url(&filename))
+    let video_server = video_server.info()?;
+    let downloads_dir = PathBuf::from(download_manager.get_downloads_directory());
+    let path = std::path::Path::new(&filename);
+    let file_path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        // Organized downloads live in nested folders; never let a relative
+        // path climb out of the downloads directory
+        if !crate::downloads::organize::is_contained_relative(path) {
+            return Err("Invalid file path".to_string());
+        }
+        downloads_dir.join(path)
+    };
+
+    let container = remux::detect_container(&file_path)
+        .await
+        .unwrap_or(Container::Unknown);
+
+    if container.needs_remux() {
+        let file_path_str = file_path.to_string_lossy();
+        let download = download_manager
+            .list_downloads()
+            .await
+            .into_iter()
+            .find(|d| d.file_path == file_path_str || d.filename == filename);
+
+        if let Some(download) = download {
+            let ffmpeg_available = tokio::task::spawn_blocking(remux::ffmpeg_available)
+                .await
+                .unwrap_or(false);
+            if !ffmpeg_available {
+                return Err(remux::FFMPEG_MISSING_ERROR.to_string());
+            }
+
+            return Ok(video_server.remux_url(&download.id));
+        }
+
+        log::warn!("MKV file {:?} is not a tracked download, serving as-is", file_path);
+    }
+
+    // /files serves relative to the downloads directory
+    let relative = file_path
+        .strip_prefix(&downloads_dir)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or(filename);
+
+    Ok(video_server.local_url(&relative))
 }
 
 /// Get file size for a downloaded file path.
@@ -2085,20 +3955,21 @@ pub async fn get_local_file_size(path: String) -> Result<u64, String> {
 /// Get proxy URL for a remote video
 #[tauri::command]
 pub async fn get_proxy_video_url(
-    video_server: State<'_, VideoServerInfo>,
+    video_server: State<'_, VideoServerHandle>,
     url: String,
 ) -> Result<String, String> {
-    Ok(video_server.proxy_url(&url))
+    Ok(video_server.info()?.proxy_url(&url))
 }
 
 // ==================== System Stats Commands ====================
 
 use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_os = "android"))]
+use std::sync::atomic::AtomicU64;
 
-/// Event names for streaming
 #[cfg(not(target_os = "android"))]
-pub const SYSTEM_STATS_EVENT: &str = "system-stats";
-pub const APP_LOGS_EVENT: &str = "app-logs";
+use crate::events::SYSTEM_STATS_EVENT;
+use crate::events::APP_LOGS_EVENT;
 
 /// Global flags for streaming control
 #[cfg(not(target_os = "android"))]
@@ -2106,7 +3977,7 @@ static STATS_STREAMING: AtomicBool = AtomicBool::new(false);
 static LOGS_STREAMING: AtomicBool = AtomicBool::new(false);
 
 /// System statistics for developer debugging
-#[derive(serde::Serialize, Clone)]
+#[derive(serde::Serialize, Clone, schemars::JsonSchema)]
 pub struct SystemStats {
     // Memory (in bytes)
     pub memory_used: u64,
@@ -2128,6 +3999,80 @@ pub struct SystemStats {
     pub disk_percent: f32,
 }
 
+/// Sample system statistics. Reuse `sys` between samples: CPU usage is
+/// measured since the previous refresh, so a fresh System always reads 0.
+#[cfg(not(target_os = "android"))]
+pub(crate) fn collect_system_stats(sys: &mut sysinfo::System) -> SystemStats {
+    use sysinfo::{Disks, Pid, ProcessesToUpdate};
+
+    let current_pid = Pid::from_u32(std::process::id());
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+    sys.refresh_processes(ProcessesToUpdate::Some(&[current_pid]));
+
+    // Get CPU usage (average across all cores)
+    let cpu_usage = sys.cpus().iter()
+        .map(|cpu| cpu.cpu_usage())
+        .sum::<f32>() / sys.cpus().len().max(1) as f32;
+
+    let cpu_count = sys.cpus().len();
+
+    // Get memory stats
+    let memory_total = sys.total_memory();
+    let memory_used = sys.used_memory();
+    let memory_percent = if memory_total > 0 {
+        (memory_used as f32 / memory_total as f32) * 100.0
+    } else {
+        0.0
+    };
+
+    // Get current process stats
+    let (process_memory, process_cpu, thread_count) = if let Some(process) = sys.process(current_pid) {
+        (
+            process.memory(),
+            process.cpu_usage(),
+            std::thread::available_parallelism()
+                .map(|p| p.get())
+                .unwrap_or(1)
+        )
+    } else {
+        (0, 0.0, 1)
+    };
+
+    // Get disk stats (primary disk)
+    let disks = Disks::new_with_refreshed_list();
+    let (disk_used, disk_total) = disks.iter()
+        .find(|d| d.mount_point() == std::path::Path::new("/"))
+        .or_else(|| disks.first())
+        .map(|d| {
+            let total = d.total_space();
+            let available = d.available_space();
+            let used = total.saturating_sub(available);
+            (used, total)
+        })
+        .unwrap_or((0, 0));
+
+    let disk_percent = if disk_total > 0 {
+        (disk_used as f32 / disk_total as f32) * 100.0
+    } else {
+        0.0
+    };
+
+    SystemStats {
+        memory_used,
+        memory_total,
+        memory_percent,
+        cpu_usage,
+        cpu_count,
+        process_memory,
+        process_cpu,
+        thread_count,
+        disk_used,
+        disk_total,
+        disk_percent,
+    }
+}
+
 /// Get real-time system statistics for developer debugging
 #[tauri::command]
 pub async fn get_system_stats() -> Result<SystemStats, String> {
@@ -2144,176 +4089,103 @@ pub async fn get_system_stats() -> Result<SystemStats, String> {
 
     #[cfg(not(target_os = "android"))]
     {
-        use sysinfo::{System, Pid, Disks};
-
-        // Create system info instance and refresh relevant data
-        let mut sys = System::new();
-        sys.refresh_cpu_usage();
-        sys.refresh_memory();
-        sys.refresh_processes(sysinfo::ProcessesToUpdate::All);
-
-        // Get CPU usage (average across all cores)
-        let cpu_usage = sys.cpus().iter()
-            .map(|cpu| cpu.cpu_usage())
-            .sum::<f32>() / sys.cpus().len().max(1) as f32;
-
-        let cpu_count = sys.cpus().len();
-
-        // Get memory stats
-        let memory_total = sys.total_memory();
-        let memory_used = sys.used_memory();
-        let memory_percent = if memory_total > 0 {
-            (memory_used as f32 / memory_total as f32) * 100.0
-        } else {
-            0.0
-        };
-
-        // Get current process stats
-        let current_pid = Pid::from_u32(std::process::id());
-        let (process_memory, process_cpu, thread_count) = if let Some(process) = sys.process(current_pid) {
-            (
-                process.memory(),
-                process.cpu_usage(),
-                std::thread::available_parallelism()
-                    .map(|p| p.get())
-                    .unwrap_or(1)
-            )
-        } else {
-            (0, 0.0, 1)
-        };
-
-        // Get disk stats (primary disk)
-        let disks = Disks::new_with_refreshed_list();
-        let (disk_used, disk_total) = disks.iter()
-            .find(|d| d.mount_point() == std::path::Path::new("/"))
-            .or_else(|| disks.first())
-            .map(|d| {
-                let total = d.total_space();
-                let available = d.available_space();
-                let used = total.saturating_sub(available);
-                (used, total)
-            })
-            .unwrap_or((0, 0));
-
-        let disk_percent = if disk_total > 0 {
-            (disk_used as f32 / disk_total as f32) * 100.0
-        } else {
-            0.0
-        };
-
-        Ok(SystemStats {
-            memory_used,
-            memory_total,
-            memory_percent,
-            cpu_usage,
-            cpu_count,
-            process_memory,
-            process_cpu,
-            thread_count,
-            disk_used,
-            disk_total,
-            disk_percent,
-        })
+        Ok(collect_system_stats(&mut sysinfo::System::new()))
     }
 }
 
-/// Start streaming system stats via events (emits every second)
+/// Intervals start_stats_stream accepts, in seconds (slower ones for when the
+/// panel is in the background)
+#[cfg(not(target_os = "android"))]
+const STATS_STREAM_INTERVALS: &[u64] = &[1, 5, 30];
+
+/// Seconds between streamed samples
+#[cfg(not(target_os = "android"))]
+static STATS_STREAM_INTERVAL_SECS: AtomicU64 = AtomicU64::new(1);
+
+/// Start streaming system stats via events, every `interval_secs` seconds
+/// (1, 5 or 30; default 1). Calling it while streaming changes the interval.
 #[tauri::command]
-pub async fn start_stats_stream(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn start_stats_stream(app: tauri::AppHandle, interval_secs: Option<u64>) -> Result<(), String> {
     // No-op on Android (sysinfo not available)
     #[cfg(target_os = "android")]
     {
-        let _ = app;
+        let _ = (app, interval_secs);
         return Ok(());
     }
 
     #[cfg(not(target_os = "android"))]
     {
+        let interval_secs = interval_secs.unwrap_or(1);
+        if !STATS_STREAM_INTERVALS.contains(&interval_secs) {
+            return Err(format!(
+                "Unsupported stats interval {}s (expected one of {:?})",
+                interval_secs, STATS_STREAM_INTERVALS
+            ));
+        }
+        STATS_STREAM_INTERVAL_SECS.store(interval_secs, Ordering::SeqCst);
+
         // Check if already streaming
         if STATS_STREAMING.swap(true, Ordering::SeqCst) {
             return Ok(()); // Already streaming
         }
 
         tokio::spawn(async move {
-            use sysinfo::{System, Pid, Disks};
+            let mut sys = sysinfo::System::new();
 
             while STATS_STREAMING.load(Ordering::SeqCst) {
-                // Collect stats
-                let mut sys = System::new();
-                sys.refresh_cpu_usage();
-                sys.refresh_memory();
-                sys.refresh_processes(sysinfo::ProcessesToUpdate::All);
-
-                let cpu_usage = sys.cpus().iter()
-                    .map(|cpu| cpu.cpu_usage())
-                    .sum::<f32>() / sys.cpus().len().max(1) as f32;
-
-                let cpu_count = sys.cpus().len();
-                let memory_total = sys.total_memory();
-                let memory_used = sys.used_memory();
-                let memory_percent = if memory_total > 0 {
-                    (memory_used as f32 / memory_total as f32) * 100.0
-                } else {
-                    0.0
-                };
-
-                let current_pid = Pid::from_u32(std::process::id());
-                let (process_memory, process_cpu, thread_count) = if let Some(process) = sys.process(current_pid) {
-                    (
-                        process.memory(),
-                        process.cpu_usage(),
-                        std::thread::available_parallelism()
-                            .map(|p| p.get())
-                            .unwrap_or(1)
-                    )
-                } else {
-                    (0, 0.0, 1)
-                };
-
-                let disks = Disks::new_with_refreshed_list();
-                let (disk_used, disk_total) = disks.iter()
-                    .find(|d| d.mount_point() == std::path::Path::new("/"))
-                    .or_else(|| disks.first())
-                    .map(|d| {
-                        let total = d.total_space();
-                        let available = d.available_space();
-                        let used = total.saturating_sub(available);
-                        (used, total)
-                    })
-                    .unwrap_or((0, 0));
-
-                let disk_percent = if disk_total > 0 {
-                    (disk_used as f32 / disk_total as f32) * 100.0
-                } else {
-                    0.0
-                };
-
-                let stats = SystemStats {
-                    memory_used,
-                    memory_total,
-                    memory_percent,
-                    cpu_usage,
-                    cpu_count,
-                    process_memory,
-                    process_cpu,
-                    thread_count,
-                    disk_used,
-                    disk_total,
-                    disk_percent,
-                };
+                let stats = collect_system_stats(&mut sys);
 
                 // Emit event
-                if let Err(e) = app.emit(SYSTEM_STATS_EVENT, &stats) {
-                    log::error!("Failed to emit stats event: {}", e);
-                }
+                SYSTEM_STATS_EVENT.emit(&app, &stats);
 
-                // Wait 1 second before next update
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                let interval = STATS_STREAM_INTERVAL_SECS.load(Ordering::SeqCst);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
             }
         });
 
-        Ok(())
-    }
+        Ok(())
+    }
+}
+
+/// Per-minute history of one stats metric over a period (e.g. "1h", "48h")
+#[tauri::command]
+pub async fn get_stats_history(
+    state: State<'_, AppState>,
+    metric: String,
+    period: String,
+) -> Result<crate::stats_history::StatsHistory, String> {
+    crate::stats_history::get_stats_history(state.database.pool(), &metric, &period)
+        .await
+        .map_err(|e| format!("Failed to get stats history: {}", e))
+}
+
+/// Whether the app is idle and when each maintenance chore last ran
+#[tauri::command]
+pub async fn get_maintenance_report(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+) -> Result<crate::maintenance::MaintenanceReport, String> {
+    state.activity.set_active_downloads(download_manager.active_download_count().await);
+    crate::maintenance::report(&state.database, &state.activity)
+        .await
+        .map_err(|e| format!("Failed to get maintenance report: {}", e))
+}
+
+/// Run one maintenance chore (or every chore that's on) now, idle or not
+#[tauri::command]
+pub async fn run_maintenance_now(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+    chore: Option<String>,
+) -> Result<Vec<crate::maintenance::ChoreReport>, String> {
+    let chore = chore
+        .as_deref()
+        .map(crate::maintenance::Chore::parse)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    crate::maintenance::run_now(&state.database, Some(&download_manager), chore)
+        .await
+        .map_err(|e| format!("Failed to run maintenance: {}", e))
 }
 
 /// Stop streaming system stats
@@ -2329,115 +4201,89 @@ pub async fn stop_stats_stream() -> Result<(), String> {
 // ==================== Log Commands ====================
 
 /// Log entry structure
-#[derive(serde::Serialize, Clone)]
+#[derive(serde::Serialize, Clone, schemars::JsonSchema)]
 pub struct LogEntry {
     pub timestamp: String,
     pub level: String,
     pub message: String,
 }
 
-/// Get application logs for debugging
+/// Parse a log line in the plugin's format: [TIMESTAMP][LEVEL] message.
+/// Anything else is kept whole as an INFO message.
+fn parse_log_line(line: &str) -> LogEntry {
+    if let Some(bracket_end) = line.find(']') {
+        let timestamp = line[1..bracket_end].to_string();
+        let rest = &line[bracket_end + 1..];
+
+        if let Some(level_end) = rest.find(']') {
+            let level = rest[1..level_end].to_string();
+            let message = rest[level_end + 1..].trim().to_string();
+            return LogEntry { timestamp, level, message };
+        }
+    }
+
+    LogEntry {
+        timestamp: String::new(),
+        level: "INFO".to_string(),
+        message: line.to_string(),
+    }
+}
+
+/// Get application logs for debugging (the tail of the current and rotated log files)
 #[tauri::command]
 pub async fn get_app_logs(
     app: tauri::AppHandle,
     lines: Option<usize>,
 ) -> Result<Vec<LogEntry>, String> {
-    use std::io::{BufRead, BufReader};
-    use std::fs::File;
-
     // Get log directory
     let log_dir = app.path().app_log_dir()
         .map_err(|e| format!("Failed to get log directory: {}", e))?;
 
-    let log_file = log_dir.join("otaku.log");
-
-    if !log_file.exists() {
-        return Ok(vec![]);
-    }
-
-    let file = File::open(&log_file)
-        .map_err(|e| format!("Failed to open log file: {}", e))?;
-
-    let reader = BufReader::new(file);
-    let all_lines: Vec<String> = reader.lines()
-        .filter_map(|l| l.ok())
-        .collect();
-
     // Take last N lines (default 100)
     let limit = lines.unwrap_or(100);
-    let start = all_lines.len().saturating_sub(limit);
-    let recent_lines = &all_lines[start..];
-
-    // Parse log entries
-    let entries: Vec<LogEntry> = recent_lines.iter()
-        .map(|line| {
-            // Try to parse structured log format: [TIMESTAMP][LEVEL] message
-            // Or just treat as message if unparseable
-            if let Some(bracket_end) = line.find(']') {
-                let timestamp = line[1..bracket_end].to_string();
-                let rest = &line[bracket_end + 1..];
-
-                if let Some(level_end) = rest.find(']') {
-                    let level = rest[1..level_end].to_string();
-                    let message = rest[level_end + 1..].trim().to_string();
-                    return LogEntry { timestamp, level, message };
-                }
-            }
-
-            LogEntry {
-                timestamp: String::new(),
-                level: "INFO".to_string(),
-                message: line.clone(),
-            }
-        })
-        .collect();
+    let recent_lines = tokio::task::spawn_blocking(move || {
+        crate::app_logs::tail_lines(&crate::app_logs::log_files(&log_dir), limit)
+    })
+    .await
+    .map_err(|e| format!("Task error: {}", e))?;
 
-    Ok(entries)
+    Ok(recent_lines.iter().map(|line| parse_log_line(line)).collect())
 }
 
-/// Clear application logs
+/// Clear application logs (empties the current log and deletes rotated ones)
 #[tauri::command]
 pub async fn clear_app_logs(app: tauri::AppHandle) -> Result<(), String> {
     let log_dir = app.path().app_log_dir()
         .map_err(|e| format!("Failed to get log directory: {}", e))?;
 
-    let log_file = log_dir.join("otaku.log");
-
-    if log_file.exists() {
-        std::fs::write(&log_file, "")
-            .map_err(|e| format!("Failed to clear log file: {}", e))?;
-    }
-
-    Ok(())
+    crate::app_logs::clear_logs(&log_dir)
+        .map_err(|e| format!("Failed to clear log files: {}", e))
 }
 
-/// Get log file path
+/// Get log file path (the file currently written to; rotated files sit next to it)
 #[tauri::command]
 pub async fn get_log_file_path(app: tauri::AppHandle) -> Result<String, String> {
     let log_dir = app.path().app_log_dir()
         .map_err(|e| format!("Failed to get log directory: {}", e))?;
 
-    let log_file = log_dir.join("otaku.log");
+    let log_file = crate::app_logs::current_log_file(&log_dir);
     Ok(log_file.to_string_lossy().to_string())
 }
 
 /// Start streaming logs via events (emits every 2 seconds)
 #[tauri::command]
 pub async fn start_logs_stream(app: tauri::AppHandle) -> Result<(), String> {
-    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::AtomicU64;
 
     // Check if already streaming
     if LOGS_STREAMING.swap(true, Ordering::SeqCst) {
         return Ok(()); // Already streaming
     }
 
-    // Track last known line count to detect new logs
-    static LAST_LINE_COUNT: AtomicUsize = AtomicUsize::new(0);
+    // Track the last seen log size to detect new logs
+    static LAST_LOG_SIZE: AtomicU64 = AtomicU64::new(0);
 
     tokio::spawn(async move {
-        use std::io::{BufRead, BufReader};
-        use std::fs::File;
-
         let log_dir = match app.path().app_log_dir() {
             Ok(dir) => dir,
             Err(e) => {
@@ -2446,51 +4292,24 @@ pub async fn start_logs_stream(app: tauri::AppHandle) -> Result<(), String> {
                 return;
             }
         };
-        let log_file = log_dir.join("otaku.log");
 
         while LOGS_STREAMING.load(Ordering::SeqCst) {
-            if log_file.exists() {
-                if let Ok(file) = File::open(&log_file) {
-                    let reader = BufReader::new(file);
-                    let all_lines: Vec<String> = reader.lines()
-                        .filter_map(|l| l.ok())
+            let files = crate::app_logs::log_files(&log_dir);
+            if !files.is_empty() {
+                let current_size = crate::app_logs::total_size(&log_dir);
+                let last_size = LAST_LOG_SIZE.load(Ordering::SeqCst);
+
+                // Only emit if there are new logs or first run
+                if current_size != last_size || last_size == 0 {
+                    LAST_LOG_SIZE.store(current_size, Ordering::SeqCst);
+
+                    // Get last 100 lines
+                    let entries: Vec<LogEntry> = crate::app_logs::tail_lines(&files, 100)
+                        .iter()
+                        .map(|line| parse_log_line(line))
                         .collect();
 
-                    let current_count = all_lines.len();
-                    let last_count = LAST_LINE_COUNT.load(Ordering::SeqCst);
-
-                    // Only emit if there are new logs or first run
-                    if current_count != last_count || last_count == 0 {
-                        LAST_LINE_COUNT.store(current_count, Ordering::SeqCst);
-
-                        // Get last 100 lines
-                        let start = current_count.saturating_sub(100);
-                        let recent_lines = &all_lines[start..];
-
-                        let entries: Vec<LogEntry> = recent_lines.iter()
-                            .map(|line| {
-                                if let Some(bracket_end) = line.find(']') {
-                                    let timestamp = line[1..bracket_end].to_string();
-                                    let rest = &line[bracket_end + 1..];
-
-                                    if let Some(level_end) = rest.find(']') {
-                                        let level = rest[1..level_end].to_string();
-                                        let message = rest[level_end + 1..].trim().to_string();
-                                        return LogEntry { timestamp, level, message };
-                                    }
-                                }
-                                LogEntry {
-                                    timestamp: String::new(),
-                                    level: "INFO".to_string(),
-                                    message: line.clone(),
-                                }
-                            })
-                            .collect();
-
-                        if let Err(e) = app.emit(APP_LOGS_EVENT, &entries) {
-                            log::error!("Failed to emit logs event: {}", e);
-                        }
-                    }
+                    APP_LOGS_EVENT.emit(&app, &entries);
                 }
             }
 
@@ -2572,7 +4391,7 @@ pub async fn get_downloaded_chapter_images(
     state: State<'_, AppState>,
     media_id: String,
     chapter_id: String,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<ChapterImage>, String> {
     chapter_downloads::get_downloaded_chapter_images(state.database.pool(), &media_id, &chapter_id)
         .await
         .map_err(|e| format!("Failed to get downloaded chapter images: {}", e))
@@ -2695,7 +4514,7 @@ pub async fn create_notification(
 
     let notification_id = notification.id.clone();
 
-    notifications::save_notification_public(state.database.pool(), &notification)
+    notifications::save_notification_public(state.database.pool(), state.profile_id(), &notification)
         .await
         .map_err(|e| format!("Failed to save notification: {}", e))?;
 
@@ -2712,7 +4531,7 @@ pub async fn list_notifications(
     let limit = limit.unwrap_or(50);
     let include_dismissed = include_dismissed.unwrap_or(false);
 
-    notifications::list_notifications(state.database.pool(), limit, include_dismissed)
+    notifications::list_notifications(state.database.pool(), state.profile_id(), limit, include_dismissed)
         .await
         .map_err(|e| format!("Failed to list notifications: {}", e))
 }
@@ -2733,7 +4552,7 @@ pub async fn mark_notification_read(
 pub async fn mark_all_notifications_read(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    notifications::mark_all_notifications_read(state.database.pool())
+    notifications::mark_all_notifications_read(state.database.pool(), state.profile_id())
         .await
         .map_err(|e| format!("Failed to mark all notifications as read: {}", e))
 }
@@ -2754,7 +4573,7 @@ pub async fn dismiss_notification(
 pub async fn clear_all_notifications(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    notifications::clear_all_notifications(state.database.pool())
+    notifications::clear_all_notifications(state.database.pool(), state.profile_id())
         .await
         .map_err(|e| format!("Failed to clear notifications: {}", e))
 }
@@ -2764,11 +4583,22 @@ pub async fn clear_all_notifications(
 pub async fn get_unread_notification_count(
     state: State<'_, AppState>,
 ) -> Result<i32, String> {
-    notifications::get_unread_count(state.database.pool())
+    notifications::get_unread_count(state.database.pool(), state.profile_id())
         .await
         .map_err(|e| format!("Failed to get unread count: {}", e))
 }
 
+/// Get every sidebar badge count at once (also the badge-summary-changed event)
+#[tauri::command]
+pub async fn get_badge_summary(
+    state: State<'_, AppState>,
+    download_manager: State<'_, DownloadManager>,
+) -> Result<BadgeSummary, String> {
+    badges::current_summary(state.database.pool(), state.profile_id(), &download_manager)
+        .await
+        .map_err(|e| format!("Failed to get badge counts: {}", e))
+}
+
 // ============================================================================
 // App Settings Commands
 // ============================================================================
@@ -2879,8 +4709,8 @@ pub async fn notify_update_available(
 // ============================================================================
 
 use crate::release_checker::{
-    self, CheckLogEntry, MediaReleaseState, ReleaseCheckResult, ReleaseCheckSettings,
-    ReleaseCheckStatus, TrackingDebugInfo,
+    self, CheckLogEntry, DigestMode, MediaReleaseState, ReleaseCheckResult, ReleaseCheckSettings,
+    ReleaseCheckStatus, ReleaseSource, TrackingDebugInfo,
 };
 
 /// Get release check settings
@@ -2901,12 +4731,31 @@ pub async fn update_release_check_settings(
     enabled: bool,
     interval_hours: Option<u32>,
     interval_minutes: Option<u32>,
+    digest_mode: Option<String>,
+    release_source: Option<String>,
+    source_priority: Option<Vec<String>>,
+    notify_when_watchable: Option<bool>,
 ) -> Result<(), String> {
     // Support both legacy interval_hours and new interval_minutes
     let interval = interval_minutes
         .or_else(|| interval_hours.map(|h| h * 60))
         .unwrap_or(120);
 
+    // Keep the stored values of whatever the caller doesn't send
+    let stored = release_checker::get_release_settings(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to get release settings: {}", e))?;
+    let digest_mode = match digest_mode {
+        Some(mode) => DigestMode::parse(&mode)
+            .ok_or_else(|| format!("Invalid digest mode: {}", mode))?,
+        None => stored.digest_mode,
+    };
+    let release_source = match release_source {
+        Some(source) => ReleaseSource::parse(&source)
+            .ok_or_else(|| format!("Invalid release source: {}", source))?,
+        None => stored.release_source,
+    };
+
     let settings = ReleaseCheckSettings {
         enabled,
         interval_minutes: interval,
@@ -2914,7 +4763,11 @@ pub async fn update_release_check_settings(
         retry_delay_minutes: 5,
         max_retries: 3,
         last_full_check: None,
+        digest_mode,
         interval_hours: None,
+        release_source,
+        source_priority: source_priority.unwrap_or(stored.source_priority),
+        notify_when_watchable: notify_when_watchable.unwrap_or(stored.notify_when_watchable),
     };
 
     release_checker::update_release_settings(state.database.pool(), &settings)
@@ -2956,6 +4809,25 @@ pub async fn stop_release_check() -> Result<(), String> {
     Ok(())
 }
 
+/// Set the source a media's releases are checked against in preferred mode;
+/// no extension_id goes back to the source priority
+#[tauri::command]
+pub async fn set_release_source(
+    state: State<'_, AppState>,
+    media_id: String,
+    extension_id: Option<String>,
+    source_media_id: Option<String>,
+) -> Result<(), String> {
+    release_checker::set_preferred_source(
+        state.database.pool(),
+        &media_id,
+        extension_id.as_deref(),
+        source_media_id.as_deref(),
+    )
+    .await
+    .map_err(|e| format!("Failed to set release source: {}", e))
+}
+
 /// Get release check status
 #[tauri::command]
 pub async fn get_release_check_status(
@@ -2986,6 +4858,28 @@ pub async fn initialize_release_tracking(
     .map_err(|e| format!("Failed to initialize tracking: {}", e))
 }
 
+/// Opt a media item into release checking
+#[tauri::command]
+pub async fn enable_release_tracking(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<(), String> {
+    release_checker::enable_release_tracking(state.database.pool(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to enable release tracking: {}", e))
+}
+
+/// Opt a media item out of release checking (keeps its tracking history)
+#[tauri::command]
+pub async fn disable_release_tracking(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<(), String> {
+    release_checker::disable_release_tracking(state.database.pool(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to disable release tracking: {}", e))
+}
+
 /// Get release tracking status for multiple media items
 #[tauri::command]
 pub async fn get_release_tracking_status(
@@ -3001,7 +4895,9 @@ pub async fn get_release_tracking_status(
     // Build placeholders for the IN clause
     let placeholders = media_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
     let query = format!(
-        "SELECT media_id FROM release_tracking WHERE media_id IN ({})",
+        "SELECT media_id FROM release_tracking
+         WHERE media_id IN ({})
+           AND media_id NOT IN (SELECT media_id FROM release_tracking_v2 WHERE notification_enabled = 0)",
         placeholders
     );
 
@@ -3072,6 +4968,31 @@ pub async fn get_release_tracking_debug(
         .map_err(|e| format!("Failed to get tracking debug: {}", e))
 }
 
+/// Raw release statuses the normalizer couldn't classify, most seen first
+/// (with the ones the user already mapped when `include_mapped` is set)
+#[tauri::command]
+pub async fn list_unknown_statuses(
+    state: State<'_, AppState>,
+    include_mapped: Option<bool>,
+) -> Result<Vec<crate::status_normalizer::LearnedStatus>, String> {
+    crate::status_normalizer::list_unknown_statuses(state.database.pool(), include_mapped.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to list unknown statuses: {}", e))
+}
+
+/// Map a raw release status to ongoing/completed/hiatus; None removes the
+/// mapping. Returns how many tracked media changed status.
+#[tauri::command]
+pub async fn set_status_mapping(
+    state: State<'_, AppState>,
+    raw_status: String,
+    normalized_status: Option<crate::status_normalizer::NormalizedStatus>,
+) -> Result<u64, String> {
+    crate::status_normalizer::set_status_mapping(state.database.pool(), &raw_status, normalized_status)
+        .await
+        .map_err(|e| format!("Failed to set status mapping: {}", e))
+}
+
 /// Initialize release tracking with V2 fields (includes episode number and status)
 #[tauri::command]
 pub async fn initialize_release_tracking_v2(
@@ -3167,12 +5088,20 @@ pub async fn delete_app_setting(
     Ok(())
 }
 
+/// Locales the backend formats messages and reports in. The choice is
+/// saved as the `locale` app setting.
+#[tauri::command]
+pub async fn get_supported_locales() -> Result<Vec<crate::locale::LocaleInfo>, String> {
+    Ok(crate::locale::supported_locales())
+}
+
 /// Get YouTube video URL using Invidious API (simple, no authentication needed)
 #[tauri::command]
 pub async fn get_youtube_video_url(
     video_id: String,
-    video_server: State<'_, VideoServerInfo>
+    video_server: State<'_, VideoServerHandle>
 ) -> Result<String, String> {
+    let video_server = video_server.info()?;
     log::info!("[YouTube] Fetching video URL for ID: {}", video_id);
 
     // Try popular Invidious instances
@@ -3232,37 +5161,219 @@ pub async fn get_youtube_video_url(
     Err("All Invidious instances failed".to_string())
 }
 
+// ============================================================================
+// Profile Commands
+// ============================================================================
+
+/// List all profiles
+#[tauri::command]
+pub async fn list_profiles(
+    state: State<'_, AppState>,
+) -> Result<Vec<Profile>, String> {
+    profiles::list_profiles(state.database.pool())
+        .await
+        .map_err(|e| format!("Failed to list profiles: {}", e))
+}
+
+/// Get the active profile
+#[tauri::command]
+pub async fn get_current_profile(
+    state: State<'_, AppState>,
+) -> Result<Profile, String> {
+    profiles::get_profile(state.database.pool(), state.profile_id())
+        .await
+        .map_err(|e| format!("Failed to get current profile: {}", e))?
+        .ok_or_else(|| "Active profile not found".to_string())
+}
+
+/// Create a new, empty profile
+#[tauri::command]
+pub async fn create_profile(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<Profile, String> {
+    profiles::create_profile(state.database.pool(), &name)
+        .await
+        .map_err(|e| format!("Failed to create profile: {}", e))
+}
+
+/// Switch the active profile
+#[tauri::command]
+pub async fn switch_profile(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> Result<Profile, String> {
+    profiles::switch_profile(state.database.pool(), &state.profile, profile_id)
+        .await
+        .map_err(|e| format!("Failed to switch profile: {}", e))
+}
+
+/// Delete a profile. With `reassign_to`, its data moves to that profile;
+/// otherwise the profile's library, history, tags and notifications are deleted.
+#[tauri::command]
+pub async fn delete_profile(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    reassign_to: Option<i64>,
+) -> Result<(), String> {
+    profiles::delete_profile(state.database.pool(), &state.profile, profile_id, reassign_to)
+        .await
+        .map_err(|e| format!("Failed to delete profile: {}", e))
+}
+
+/// Get a profile's age rating limit (the active profile's by default)
+#[tauri::command]
+pub async fn get_age_rating_limit(
+    state: State<'_, AppState>,
+    profile_id: Option<i64>,
+) -> Result<AgeRatingLimit, String> {
+    age_rating::get_age_rating_limit(state.database.pool(), profile_id.unwrap_or_else(|| state.profile_id()))
+        .await
+        .map_err(|e| format!("Failed to get age rating limit: {}", e))
+}
+
+/// Set a profile's age rating limit (the active profile's by default).
+/// Without `max_rating` everything is shown; `hide_unrated` also hides
+/// media whose rating isn't known while a limit is set. The NSFW filter
+/// being on keeps it from being loosened.
+#[tauri::command]
+pub async fn set_age_rating_limit(
+    state: State<'_, AppState>,
+    profile_id: Option<i64>,
+    max_rating: Option<AgeRating>,
+    hide_unrated: bool,
+) -> Result<(), String> {
+    let limit = AgeRatingLimit { max_rating, hide_unrated };
+    age_rating::change_age_rating_limit(state.database.pool(), profile_id.unwrap_or_else(|| state.profile_id()), limit)
+        .await
+        .map_err(|e| format!("Failed to set age rating limit: {}", e))
+}
+
 // ============================================================================
 // Export/Import Commands
 // ============================================================================
 
 use crate::database::export_import::{
-    ExportData, ImportOptions, ImportResult, export_all_data, import_data,
+    ExportData, ExportMetadata, ImportOptions, ImportResult, export_all_data, export_to_file, import_data,
 };
 
-/// Export all user data to JSON
+/// Export user data to JSON.
+/// Pass a profile id to export just that profile; omit it to export every profile.
+/// Installed extensions are listed; their code is embedded only with
+/// `include_extension_code`.
 #[tauri::command]
 pub async fn export_user_data(
+    app: AppHandle,
     state: State<'_, AppState>,
+    profile_id: Option<i64>,
+    include_extension_code: Option<bool>,
 ) -> Result<ExportData, String> {
     // Get app version from Cargo.toml
     let app_version = env!("CARGO_PKG_VERSION");
 
-    export_all_data(state.database.pool(), app_version)
+    export_all_data(state.database.pool(), app_version, profile_id, include_extension_code.unwrap_or(false), Some(&app))
+        .await
+        .map_err(|e| format!("Failed to export data: {}", e))
+}
+
+/// Export user data straight to a file, streaming it table by table so large
+/// libraries never have to fit in memory.
+/// Progress is reported through "data-transfer-progress" events.
+#[tauri::command]
+pub async fn export_user_data_to_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    profile_id: Option<i64>,
+    include_extension_code: Option<bool>,
+) -> Result<ExportMetadata, String> {
+    let app_version = env!("CARGO_PKG_VERSION");
+
+    export_to_file(
+        state.database.pool(),
+        app_version,
+        profile_id,
+        include_extension_code.unwrap_or(false),
+        std::path::Path::new(&path),
+        Some(&app),
+    )
         .await
         .map_err(|e| format!("Failed to export data: {}", e))
 }
 
-/// Import user data from JSON
+/// Export a readable library report (Markdown or HTML) grouped by status.
+/// HTML reports can embed covers from the local cover cache.
+#[tauri::command]
+pub async fn export_library_report(
+    state: State<'_, AppState>,
+    path: String,
+    format: crate::database::library_report::ReportFormat,
+    options: Option<crate::database::library_report::ReportOptions>,
+) -> Result<crate::database::library_report::ReportSummary, String> {
+    let covers_dir = crate::cache::media_disk::namespace_dir(crate::cache::media_disk::MediaCacheNamespace::Covers);
+
+    crate::database::library_report::export_library_report(
+        state.database.pool(),
+        state.profile_id(),
+        std::path::Path::new(&path),
+        format,
+        &options.unwrap_or_default(),
+        covers_dir.as_deref(),
+    )
+    .await
+    .map_err(|e| format!("Failed to export library report: {}", e))
+}
+
+/// Import user data from JSON.
+/// Progress is reported through "data-transfer-progress" events.
+/// Extensions reinstalled with `import_extensions` are loaded right away.
 #[tauri::command]
 pub async fn import_user_data(
+    app: AppHandle,
     state: State<'_, AppState>,
+    storage_paths: State<'_, StoragePaths>,
     data: ExportData,
     options: ImportOptions,
 ) -> Result<ImportResult, String> {
-    import_data(state.database.pool(), data, options)
+    let result = import_data(state.database.pool(), state.profile_id(), data, options, Some(&app))
+        .await
+        .map_err(|e| format!("Failed to import data: {}", e))?;
+
+    if result.extensions_imported > 0 {
+        let installed = crate::extensions::bundled::restore_extensions(state.database.pool())
+            .await
+            .map_err(|e| format!("Failed to load imported extensions: {}", e))?;
+        for extension in installed {
+            register_extension(&state, &storage_paths.app_dir, extension)?;
+        }
+        state
+            .extensions_mut()
+            .retain(|ext| !result.extensions_disabled.contains(&ext.metadata.id));
+    }
+
+    Ok(result)
+}
+
+/// Read and validate a backup file, e.g. one opened through the .otakubak
+/// file association
+#[tauri::command]
+pub async fn read_backup_file(path: String) -> Result<ExportData, String> {
+    tokio::task::spawn_blocking(move || crate::backup_file::read_backup_file(std::path::Path::new(&path)))
         .await
-        .map_err(|e| format!("Failed to import data: {}", e))
+        .map_err(|e| format!("Failed to read backup file: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// Take the backup file the app was opened with, if the frontend hasn't
+/// handled it yet (the "backup-file-opened" event can fire before the
+/// window has loaded)
+#[tauri::command]
+pub async fn take_pending_backup_file(
+    pending: State<'_, crate::backup_file::PendingBackupFile>,
+) -> Result<Option<crate::backup_file::BackupFileOpened>, String> {
+    let mut pending = pending.0.lock()
+        .map_err(|e| format!("Failed to lock pending backup file: {}", e))?;
+    Ok(pending.take())
 }
 
 // ============================================================================
@@ -3396,7 +5507,12 @@ use crate::database::migration_runner::{self, MigrationProgress};
 #[tauri::command]
 pub async fn check_migration_needed(
     state: State<'_, AppState>,
+    safe_mode: State<'_, SafeMode>,
 ) -> Result<bool, String> {
+    // Safe mode leaves the data as it is
+    if safe_mode.is_active() {
+        return Ok(false);
+    }
     migration_runner::needs_migration(state.database.pool()).await
 }
 
@@ -3405,8 +5521,12 @@ pub async fn check_migration_needed(
 #[tauri::command]
 pub async fn start_migration(
     state: State<'_, AppState>,
+    safe_mode: State<'_, SafeMode>,
     app: AppHandle,
 ) -> Result<(), String> {
+    if safe_mode.is_active() {
+        return Err("The migration doesn't run in safe mode".to_string());
+    }
     let pool = state.database.pool().clone();
     tokio::spawn(async move {
         if let Err(e) = migration_runner::run_migration(pool, app).await {
@@ -3426,6 +5546,72 @@ pub async fn get_migration_progress() -> Result<MigrationProgress, String> {
     Ok(progress)
 }
 
+// --- Safe Mode Commands ---
+
+/// Whether this run is in safe mode, what it skipped and how the database
+/// was opened
+#[tauri::command]
+pub async fn get_safe_mode_state(
+    safe_mode: State<'_, SafeMode>,
+) -> Result<SafeModeState, String> {
+    Ok(safe_mode.state())
+}
+
+/// Run SQLite's integrity check; returns the problems found, or ["ok"]
+#[tauri::command]
+pub async fn run_database_integrity_check(
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    state
+        .database
+        .integrity_check()
+        .await
+        .map_err(|e| format!("Failed to check database: {:#}", e))
+}
+
+/// Replace the database with a backup file (safe mode only). The database
+/// is closed afterwards, so the app has to be restarted.
+#[tauri::command]
+pub async fn restore_database_backup(
+    state: State<'_, AppState>,
+    safe_mode: State<'_, SafeMode>,
+    file_path: String,
+) -> Result<ImportResult, String> {
+    if !safe_mode.is_active() {
+        return Err("Restoring the database is only available in safe mode".to_string());
+    }
+
+    safe_mode::restore_backup(safe_mode.app_dir(), state.database.pool(), &PathBuf::from(file_path))
+        .await
+        .map_err(|e| format!("Failed to restore backup: {:#}", e))
+}
+
+/// Uninstall all extensions and delete their directory (safe mode only);
+/// returns how many were removed
+#[tauri::command]
+pub async fn clear_installed_extensions(
+    state: State<'_, AppState>,
+    safe_mode: State<'_, SafeMode>,
+) -> Result<u64, String> {
+    if !safe_mode.is_active() {
+        return Err("Clearing extensions is only available in safe mode".to_string());
+    }
+
+    safe_mode::clear_extensions(state.database.pool(), safe_mode.app_dir())
+        .await
+        .map_err(|e| format!("Failed to clear extensions: {:#}", e))
+}
+
+/// Start normally on the next launch
+#[tauri::command]
+pub async fn leave_safe_mode(
+    safe_mode: State<'_, SafeMode>,
+) -> Result<(), String> {
+    safe_mode
+        .leave()
+        .map_err(|e| format!("Failed to leave safe mode: {}", e))
+}
+
 // --- History Commands ---
 
 #[tauri::command]
@@ -3438,7 +5624,7 @@ pub async fn get_all_history(
 ) -> Result<Vec<crate::database::history::HistoryEntry>, String> {
     let pool = state.database.pool();
     crate::database::history::get_all_history(
-        pool, page, limit,
+        pool, state.profile_id(), page, limit,
         media_type.as_deref(),
         search.as_deref(),
     ).await.map_err(|e| e.to_string())
@@ -3454,7 +5640,7 @@ pub async fn get_history_grouped_by_media(
 ) -> Result<Vec<crate::database::history::MediaHistorySummary>, String> {
     let pool = state.database.pool();
     crate::database::history::get_history_grouped_by_media(
-        pool, page, limit,
+        pool, state.profile_id(), page, limit,
         media_type.as_deref(),
         search.as_deref(),
     ).await.map_err(|e| e.to_string())
@@ -3467,7 +5653,7 @@ pub async fn remove_watch_history_entry(
     episode_id: String,
 ) -> Result<(), String> {
     let pool = state.database.pool();
-    crate::database::history::remove_watch_history_entry(pool, &media_id, &episode_id)
+    crate::database::history::remove_watch_history_entry(pool, state.profile_id(), &media_id, &episode_id)
         .await.map_err(|e| e.to_string())
 }
 
@@ -3478,7 +5664,7 @@ pub async fn remove_reading_history_entry(
     chapter_id: String,
 ) -> Result<(), String> {
     let pool = state.database.pool();
-    crate::database::history::remove_reading_history_entry(pool, &media_id, &chapter_id)
+    crate::database::history::remove_reading_history_entry(pool, state.profile_id(), &media_id, &chapter_id)
         .await.map_err(|e| e.to_string())
 }
 
@@ -3487,7 +5673,7 @@ pub async fn clear_all_reading_history(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let pool = state.database.pool();
-    crate::database::history::clear_all_reading_history(pool)
+    crate::database::history::clear_all_reading_history(pool, state.profile_id())
         .await.map_err(|e| e.to_string())
 }
 
@@ -3498,7 +5684,7 @@ pub async fn get_watch_stats_summary(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::WatchStatsSummary, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_watch_stats_summary(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_watch_stats_summary(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3506,7 +5692,7 @@ pub async fn get_reading_stats_summary(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::ReadingStatsSummary, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_reading_stats_summary(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_reading_stats_summary(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3516,7 +5702,7 @@ pub async fn get_daily_activity(
 ) -> Result<Vec<crate::database::stats::DailyActivity>, String> {
     log::info!("Command get_daily_activity invoked with days={}", days);
     let pool = state.database.pool();
-    match crate::database::stats::get_daily_activity(pool, days).await {
+    match crate::database::stats::get_daily_activity(pool, state.profile_id(), days).await {
         Ok(data) => {
             log::info!("Command get_daily_activity success: {} entries", data.len());
             Ok(data)
@@ -3534,7 +5720,7 @@ pub async fn get_genre_stats(
     media_type: Option<String>,
 ) -> Result<Vec<crate::database::stats::GenreStat>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_genre_stats(pool, media_type.as_deref()).await.map_err(|e| e.to_string())
+    crate::database::stats::get_genre_stats(pool, state.profile_id(), media_type.as_deref()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3542,7 +5728,7 @@ pub async fn get_completion_stats(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::CompletionStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_completion_stats(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_completion_stats(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3551,7 +5737,7 @@ pub async fn get_top_watched_anime(
     limit: i32,
 ) -> Result<Vec<crate::database::stats::TopWatchedEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_top_watched_anime(pool, limit).await.map_err(|e| e.to_string())
+    crate::database::stats::get_top_watched_anime(pool, state.profile_id(), limit).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3560,7 +5746,7 @@ pub async fn get_top_read_manga(
     limit: i32,
 ) -> Result<Vec<crate::database::stats::TopReadEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_top_read_manga(pool, limit).await.map_err(|e| e.to_string())
+    crate::database::stats::get_top_read_manga(pool, state.profile_id(), limit).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3568,7 +5754,7 @@ pub async fn get_streak_stats(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::StreakStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_streak_stats(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_streak_stats(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3576,7 +5762,7 @@ pub async fn get_activity_patterns(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::ActivityPatterns, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_activity_patterns(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_activity_patterns(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3584,7 +5770,7 @@ pub async fn get_binge_stats(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::BingeStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_binge_stats(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_binge_stats(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3592,7 +5778,7 @@ pub async fn get_peak_hours(
     state: State<'_, AppState>,
 ) -> Result<Vec<crate::database::stats::HourlyActivity>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_peak_hours(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_peak_hours(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3600,7 +5786,7 @@ pub async fn get_completion_rate(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::CompletionRateStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_completion_rate(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_completion_rate(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3608,7 +5794,7 @@ pub async fn get_score_distribution(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::ScoreDistribution, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_score_distribution(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_score_distribution(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3616,7 +5802,7 @@ pub async fn get_content_type_breakdown(
     state: State<'_, AppState>,
 ) -> Result<Vec<crate::database::stats::ContentTypeEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_content_type_breakdown(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_content_type_breakdown(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3624,7 +5810,7 @@ pub async fn get_seasonal_trends(
     state: State<'_, AppState>,
 ) -> Result<Vec<crate::database::stats::SeasonEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_seasonal_trends(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_seasonal_trends(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3632,7 +5818,7 @@ pub async fn get_watch_completion_rate(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::WatchCompletionRateStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_watch_completion_rate(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_watch_completion_rate(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3640,7 +5826,7 @@ pub async fn get_favorites_stats(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::FavoritesStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_favorites_stats(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_favorites_stats(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3648,7 +5834,7 @@ pub async fn get_time_to_completion(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::TimeToCompletion, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_time_to_completion(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_time_to_completion(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3656,7 +5842,7 @@ pub async fn get_year_distribution(
     state: State<'_, AppState>,
 ) -> Result<Vec<crate::database::stats::YearDistEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_year_distribution(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_year_distribution(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3664,7 +5850,7 @@ pub async fn get_milestones(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::MilestoneStats, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_milestones(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_milestones(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3672,7 +5858,7 @@ pub async fn get_monthly_recap(
     state: State<'_, AppState>,
 ) -> Result<crate::database::stats::MonthlyRecap, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_monthly_recap(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_monthly_recap(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3680,7 +5866,7 @@ pub async fn get_rating_comparison(
     state: State<'_, AppState>,
 ) -> Result<Vec<crate::database::stats::RatingComparisonEntry>, String> {
     let pool = state.database.pool();
-    crate::database::stats::get_rating_comparison(pool).await.map_err(|e| e.to_string())
+    crate::database::stats::get_rating_comparison(pool, state.profile_id()).await.map_err(|e| e.to_string())
 }
 
 // Recommendations
@@ -3690,7 +5876,7 @@ pub async fn get_content_recommendations(
     limit: i32,
 ) -> Result<Vec<crate::database::recommendations::RecommendationEntry>, String> {
     let pool = state.database.pool();
-    crate::database::recommendations::get_content_recommendations(pool, limit).await.map_err(|e| e.to_string())
+    crate::database::recommendations::get_content_recommendations(pool, state.profile_id(), limit).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3699,7 +5885,7 @@ pub async fn get_similar_to_watched(
     limit_per_series: i32,
 ) -> Result<Vec<crate::database::recommendations::SimilarToGroup>, String> {
     let pool = state.database.pool();
-    crate::database::recommendations::get_similar_to_watched(pool, limit_per_series).await.map_err(|e| e.to_string())
+    crate::database::recommendations::get_similar_to_watched(pool, state.profile_id(), limit_per_series).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -3708,7 +5894,7 @@ pub async fn get_user_top_genres(
     limit: i32,
 ) -> Result<crate::database::recommendations::UserGenreProfile, String> {
     let pool = state.database.pool();
-    crate::database::recommendations::get_user_top_genres(pool, limit).await.map_err(|e| e.to_string())
+    crate::database::recommendations::get_user_top_genres(pool, state.profile_id(), limit).await.map_err(|e| e.to_string())
 }
 
 // Feedback
@@ -3740,6 +5926,43 @@ pub async fn remove_media_feedback(
     crate::database::feedback::remove_feedback(pool, &media_id).await.map_err(|e| e.to_string())
 }
 
+// Hidden media ("not interested")
+#[tauri::command]
+pub async fn hide_media(
+    state: State<'_, AppState>,
+    media_id: String,
+    extension_id: Option<String>,
+    title: Option<String>,
+    reason: Option<String>,
+) -> Result<(), String> {
+    let pool = state.database.pool();
+    crate::database::hidden_media::hide_media(pool, state.profile_id(), &media_id, extension_id.as_deref(), title.as_deref(), reason.as_deref())
+        .await
+        .map_err(|e| format!("Failed to hide media: {}", e))
+}
+
+/// Show a hidden result again; false if it wasn't hidden
+#[tauri::command]
+pub async fn unhide_media(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<bool, String> {
+    let pool = state.database.pool();
+    crate::database::hidden_media::unhide_media(pool, state.profile_id(), &media_id)
+        .await
+        .map_err(|e| format!("Failed to unhide media: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_hidden_media(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::database::hidden_media::HiddenMedia>, String> {
+    let pool = state.database.pool();
+    crate::database::hidden_media::list_hidden_media(pool, state.profile_id())
+        .await
+        .map_err(|e| format!("Failed to list hidden media: {}", e))
+}
+
 // ==================== Autostart ====================
 
 #[tauri::command]
@@ -3790,3 +6013,181 @@ pub async fn get_desktop_notifications_enabled(
         None => Ok(true),
     }
 }
+
+// ==================== Event Schemas ====================
+
+/// JSON schema of every backend event payload, keyed by event name.
+/// Used to keep src/types/events.ts in sync with the Rust types.
+#[tauri::command]
+pub async fn generate_event_schema() -> Result<serde_json::Value, String> {
+    Ok(crate::events::schema_document())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn state_with_extensions(ids: &[&str]) -> (tempfile::TempDir, AppState) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let state = AppState::new(database, profiles::DEFAULT_PROFILE_ID);
+
+        let template = crate::extensions::bundled::bundled_extensions().remove(0);
+        for id in ids {
+            let mut extension = template.clone();
+            extension.metadata.id = id.to_string();
+            state.extensions_mut().push(extension);
+        }
+        (temp_dir, state)
+    }
+
+    /// An extension whose search keeps the CPU busy for `millis` and returns
+    /// one result named after the extension
+    fn slow_extension(id: &str, millis: u64) -> Extension {
+        Extension::from_code(&format!(
+            r#"
+            const extensionObject = {{
+                id: "{id}",
+                name: "{id}",
+                version: "1.0.0",
+                type: "anime",
+                language: "en",
+                baseUrl: "https://example.com",
+
+                search: (query, page) => {{
+                    const until = Date.now() + {millis};
+                    while (Date.now() < until) {{}}
+                    return {{ results: [{{ id: "{id}", title: query }}], hasNextPage: false }};
+                }}
+            }};
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 3)]
+    async fn searches_on_different_extensions_run_in_parallel() {
+        const SEARCH_MS: u64 = 400;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let state = Arc::new(AppState::new(database, profiles::DEFAULT_PROFILE_ID));
+        let ids = ["test.parallel-a", "test.parallel-b", "test.parallel-c"];
+        for id in ids {
+            state.extensions_mut().push(slow_extension(id, SEARCH_MS));
+        }
+
+        let started = std::time::Instant::now();
+        let searches: Vec<_> = ids
+            .into_iter()
+            .map(|id| {
+                let state = state.clone();
+                tokio::spawn(async move { search_with(&state, id, "frieren", 1, Some(false)).await })
+            })
+            .collect();
+
+        let mut found = Vec::new();
+        for search in searches {
+            let results = search.await.unwrap().unwrap();
+            found.push(results.results[0].id.clone());
+        }
+        let elapsed = started.elapsed();
+
+        found.sort();
+        assert_eq!(found, ids);
+        // One after the other they'd take three times as long
+        assert!(elapsed < Duration::from_millis(2 * SEARCH_MS), "searches were serialized: {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn a_missing_extension_keeps_its_error_type() {
+        let (_temp_dir, state) = state_with_extensions(&[]).await;
+
+        let err = search_with(&state, "test.missing", "frieren", 1, None).await.unwrap_err();
+        assert_eq!(err, CommandError::Extension(ExtensionError::NotFound("test.missing".to_string())));
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({ "kind": "not_found", "detail": "test.missing" })
+        );
+        assert_eq!(
+            serde_json::to_value(CommandError::from("Search failed".to_string())).unwrap(),
+            serde_json::json!("Search failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn hidden_results_are_left_out_of_search_and_season_listings() {
+        let (_temp_dir, state) = state_with_extensions(&[]).await;
+        state.extensions_mut().push(
+            Extension::from_code(
+                r#"
+                const extensionObject = {
+                    id: "test.hidden-listings",
+                    name: "Hidden Listings",
+                    version: "1.0.0",
+                    type: "anime",
+                    language: "en",
+                    baseUrl: "https://example.com",
+
+                    search: (query, page) => ({
+                        results: [{ id: "hidden", title: "Hidden" }, { id: "kept", title: "Kept" }],
+                        hasNextPage: false
+                    }),
+                    getCurrentSeason: (page) => ({
+                        results: page === 1
+                            ? [{ id: "hidden", title: "Hidden" }, { id: "kept", title: "Kept" }]
+                            : [{ id: "kept", title: "Kept" }, { id: "later", title: "Later" }],
+                        hasNextPage: page < 2,
+                        season: "fall",
+                        year: 2026
+                    })
+                };
+                "#,
+            )
+            .unwrap(),
+        );
+        let pool = state.database.pool();
+        crate::database::hidden_media::hide_media(pool, state.profile_id(), "hidden", None, None, None).await.unwrap();
+
+        let search = search_with(&state, "test.hidden-listings", "anything", 1, Some(false)).await.unwrap();
+        assert_eq!(search.results.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["kept"]);
+
+        let mut events = Vec::new();
+        stream_current_season_with(&state, "test.hidden-listings", Some(false), Some(3), |event| events.push(event))
+            .await
+            .unwrap();
+        let emitted: Vec<(Vec<String>, usize)> = events
+            .iter()
+            .map(|e| (e.results.iter().map(|r| r.id.clone()).collect(), e.total_results))
+            .collect();
+        assert_eq!(
+            emitted,
+            [(vec!["kept".to_string()], 1), (vec!["later".to_string()], 2)]
+        );
+        assert!(events.last().unwrap().is_last);
+    }
+
+    #[tokio::test]
+    async fn a_poisoned_lock_is_recovered() {
+        let (_temp_dir, state) = state_with_extensions(&["source-a"]).await;
+
+        let panicked = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _extensions = state.extensions_mut();
+                    panic!("extension load failed");
+                })
+                .join()
+        });
+        assert!(panicked.is_err());
+        assert!(state.extensions.is_poisoned());
+
+        assert_eq!(state.extension("source-a").unwrap().metadata.id, "source-a");
+        assert_eq!(
+            state.extension("missing").unwrap_err(),
+            ExtensionError::NotFound("missing".to_string())
+        );
+        state.extensions_mut().clear();
+        assert!(state.extensions().is_empty());
+    }
+}
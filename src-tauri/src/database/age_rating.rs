@@ -0,0 +1,310 @@
+// Age Rating Module
+//
+// Age ratings beyond the binary adult flag, so a shared family profile can
+// be limited to, say, PG-13. Media rows get a rating_classification from
+// Jikan's rating (on details fetch and enrichment) or from extensions that
+// provide one. Each profile can set a max_age_rating: results rated above
+// it are left out of search, discover, home and recommendations (through
+// HiddenSet) and out of the library queries. Whether media with no known
+// rating is shown under a limit is the profile's hide_unrated choice. While
+// parental controls (the NSFW filter) are on, a limit can be tightened but
+// not loosened.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+
+
+/// Age ratings, least restricted first (MAL's scale)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeRating {
+    /// All ages
+    G,
+    /// Children
+    Pg,
+    /// Teens 13 or older
+    Pg13,
+    /// 17+ (violence and profanity)
+    R17,
+    /// Mild nudity
+    RPlus,
+    /// Hentai
+    Rx,
+}
+
+impl AgeRating {
+    pub const ALL: [AgeRating; 6] = [Self::G, Self::Pg, Self::Pg13, Self::R17, Self::RPlus, Self::Rx];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::G => "g",
+            Self::Pg => "pg",
+            Self::Pg13 => "pg13",
+            Self::R17 => "r17",
+            Self::RPlus => "r_plus",
+            Self::Rx => "rx",
+        }
+    }
+
+    /// Parse a stored code or a rating as sources write it, e.g. Jikan's
+    /// "PG-13 - Teens 13 or older" or "R - 17+ (violence & profanity)"
+    pub fn classify(raw: &str) -> Option<Self> {
+        let label = raw.split(" - ").next().unwrap_or(raw).trim().to_ascii_lowercase();
+        match label.as_str() {
+            "g" => Some(Self::G),
+            "pg" => Some(Self::Pg),
+            "pg13" | "pg-13" => Some(Self::Pg13),
+            "r" | "r17" | "r-17" | "r17+" | "r-17+" => Some(Self::R17),
+            "r+" | "r_plus" | "rplus" => Some(Self::RPlus),
+            "rx" => Some(Self::Rx),
+            _ => None,
+        }
+    }
+}
+
+/// A profile's age rating limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgeRatingLimit {
+    /// Highest rating shown; None shows everything
+    pub max_rating: Option<AgeRating>,
+    /// Under a limit, also hide media whose rating isn't known
+    pub hide_unrated: bool,
+}
+
+impl AgeRatingLimit {
+    pub fn is_active(&self) -> bool {
+        self.max_rating.is_some()
+    }
+
+    /// Whether this limit shows anything `other` keeps out
+    pub fn is_looser_than(&self, other: &AgeRatingLimit) -> bool {
+        match (self.max_rating, other.max_rating) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(mine), Some(theirs)) => mine > theirs || (!self.hide_unrated && other.hide_unrated),
+        }
+    }
+
+    /// Whether media with this rating (code or source label) may be shown
+    pub fn allows(&self, rating: Option<&str>) -> bool {
+        let Some(max) = self.max_rating else {
+            return true;
+        };
+        match rating.and_then(AgeRating::classify) {
+            Some(rating) => rating <= max,
+            None => !self.hide_unrated,
+        }
+    }
+
+    /// SQL condition on a rating_classification column keeping what the
+    /// limit allows; None when there's no limit. Only enum codes are
+    /// interpolated.
+    pub fn sql_condition(&self, column: &str) -> Option<String> {
+        let max = self.max_rating?;
+        let allowed = AgeRating::ALL
+            .iter()
+            .filter(|rating| **rating <= max)
+            .map(|rating| format!("'{}'", rating.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Some(if self.hide_unrated {
+            format!("{} IN ({})", column, allowed)
+        } else {
+            format!("({0} IS NULL OR {0} IN ({1}))", column, allowed)
+        })
+    }
+}
+
+/// A profile's limit
+pub async fn get_age_rating_limit(pool: &SqlitePool, profile_id: i64) -> Result<AgeRatingLimit> {
+    let row = sqlx::query("SELECT max_age_rating, hide_unrated FROM profiles WHERE id = ?")
+        .bind(profile_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", profile_id))?;
+
+    let max_rating: Option<String> = row.try_get("max_age_rating")?;
+    Ok(AgeRatingLimit {
+        max_rating: max_rating.as_deref().and_then(AgeRating::classify),
+        hide_unrated: row.try_get::<i64, _>("hide_unrated")? != 0,
+    })
+}
+
+/// Change a profile's limit
+pub async fn set_age_rating_limit(pool: &SqlitePool, profile_id: i64, limit: AgeRatingLimit) -> Result<()> {
+    let updated = sqlx::query("UPDATE profiles SET max_age_rating = ?, hide_unrated = ? WHERE id = ?")
+        .bind(limit.max_rating.map(|r| r.as_str()))
+        .bind(limit.hide_unrated)
+        .bind(profile_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    if updated == 0 {
+        return Err(anyhow::anyhow!("Profile not found: {}", profile_id));
+    }
+    log::debug!("Age rating limit of profile {}: {:?}", profile_id, limit);
+    Ok(())
+}
+
+/// Change a profile's limit from the settings. While the NSFW filter is
+/// on, loosening it is refused.
+pub async fn change_age_rating_limit(pool: &SqlitePool, profile_id: i64, limit: AgeRatingLimit) -> Result<()> {
+    if crate::extensions::adult::nsfw_filter_enabled(pool).await
+        && limit.is_looser_than(&get_age_rating_limit(pool, profile_id).await?)
+    {
+        anyhow::bail!("Turn off the NSFW filter to loosen the age rating limit");
+    }
+    set_age_rating_limit(pool, profile_id, limit).await
+}
+
+/// The active profile's limit. A listing is never failed over this: on a
+/// database error nothing is limited.
+pub async fn current_limit(pool: &SqlitePool, profile_id: i64) -> AgeRatingLimit {
+    get_age_rating_limit(pool, profile_id).await.unwrap_or_else(|e| {
+        log::warn!("Failed to load age rating limit: {}", e);
+        AgeRatingLimit::default()
+    })
+}
+
+/// Store a media row's rating from a source's label. Returns false when the
+/// label isn't a rating or there's no such row (yet).
+pub async fn record_rating(pool: &SqlitePool, media_id: &str, raw: &str) -> Result<bool> {
+    let Some(rating) = AgeRating::classify(raw) else {
+        return Ok(false);
+    };
+    let updated = sqlx::query("UPDATE media SET rating_classification = ? WHERE id = ?")
+        .bind(rating.as_str())
+        .bind(media_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Record the rating of details a command just fetched. Fetching isn't
+/// failed over this, so errors are only logged.
+pub async fn record_fetched_rating(pool: &SqlitePool, media_id: &str, raw: Option<&str>) {
+    let Some(raw) = raw else {
+        return;
+    };
+    if let Err(e) = record_rating(pool, media_id, raw).await {
+        log::warn!("Failed to record the age rating of {}: {}", media_id, e);
+    }
+}
+
+/// Ids of media rows the limit doesn't allow
+pub async fn blocked_media_ids(pool: &SqlitePool, limit: &AgeRatingLimit) -> Result<HashSet<String>> {
+    let Some(condition) = limit.sql_condition("rating_classification") else {
+        return Ok(HashSet::new());
+    };
+    let ids: Vec<String> = sqlx::query_scalar(&format!("SELECT id FROM media WHERE NOT ({})", condition))
+        .fetch_all(pool)
+        .await?;
+    Ok(ids.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+
+    #[test]
+    fn classifies_source_labels() {
+        assert_eq!(AgeRating::classify("G - All Ages"), Some(AgeRating::G));
+        assert_eq!(AgeRating::classify("PG-13 - Teens 13 or older"), Some(AgeRating::Pg13));
+        assert_eq!(AgeRating::classify("R - 17+ (violence & profanity)"), Some(AgeRating::R17));
+        assert_eq!(AgeRating::classify("R+ - Mild Nudity"), Some(AgeRating::RPlus));
+        assert_eq!(AgeRating::classify("r_plus"), Some(AgeRating::RPlus));
+        assert_eq!(AgeRating::classify("Rx - Hentai"), Some(AgeRating::Rx));
+        assert_eq!(AgeRating::classify("None"), None);
+    }
+
+    #[test]
+    fn limit_allows_up_to_its_max() {
+        let limit = AgeRatingLimit { max_rating: Some(AgeRating::Pg13), hide_unrated: false };
+        assert!(limit.allows(Some("PG - Children")));
+        assert!(limit.allows(Some("pg13")));
+        assert!(!limit.allows(Some("R - 17+ (violence & profanity)")));
+        assert!(limit.allows(None));
+        assert!(limit.allows(Some("unknown")));
+
+        let strict = AgeRatingLimit { hide_unrated: true, ..limit };
+        assert!(!strict.allows(None));
+        assert!(strict.allows(Some("g")));
+
+        let unlimited = AgeRatingLimit { max_rating: None, hide_unrated: true };
+        assert!(unlimited.allows(Some("rx")));
+        assert!(unlimited.allows(None));
+
+        assert!(unlimited.is_looser_than(&limit));
+        assert!(limit.is_looser_than(&strict));
+        assert!(!strict.is_looser_than(&limit));
+        assert!(!limit.is_looser_than(&unlimited));
+        let mature = AgeRatingLimit { max_rating: Some(AgeRating::R17), hide_unrated: true };
+        assert!(mature.is_looser_than(&strict));
+        assert!(!strict.is_looser_than(&mature));
+    }
+
+    #[tokio::test]
+    async fn the_nsfw_filter_keeps_limits_from_being_loosened() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        let teen = AgeRatingLimit { max_rating: Some(AgeRating::Pg13), hide_unrated: false };
+        let kids = AgeRatingLimit { max_rating: Some(AgeRating::Pg), hide_unrated: false };
+
+        change_age_rating_limit(pool, DEFAULT_PROFILE_ID, teen).await.unwrap();
+        sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, '1')")
+            .bind(crate::extensions::adult::NSFW_FILTER_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        change_age_rating_limit(pool, DEFAULT_PROFILE_ID, kids).await.unwrap();
+        assert!(change_age_rating_limit(pool, DEFAULT_PROFILE_ID, teen).await.is_err());
+        assert!(change_age_rating_limit(pool, DEFAULT_PROFILE_ID, AgeRatingLimit::default()).await.is_err());
+        assert_eq!(get_age_rating_limit(pool, DEFAULT_PROFILE_ID).await.unwrap(), kids);
+    }
+
+    #[tokio::test]
+    async fn limits_are_per_profile_and_filter_media_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        for (id, rating) in [("family", "G - All Ages"), ("teen", "PG-13 - Teens 13 or older"), ("mature", "R+ - Mild Nudity")] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'jikan', ?, 'anime')")
+                .bind(id)
+                .bind(id)
+                .execute(pool)
+                .await
+                .unwrap();
+            assert!(record_rating(pool, id, rating).await.unwrap());
+        }
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('unrated', 'jikan', 'unrated', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        assert!(!record_rating(pool, "missing", "G - All Ages").await.unwrap());
+
+        let kids = crate::database::profiles::create_profile(pool, "Kids").await.unwrap();
+        let limit = AgeRatingLimit { max_rating: Some(AgeRating::Pg13), hide_unrated: true };
+        set_age_rating_limit(pool, kids.id, limit).await.unwrap();
+
+        assert_eq!(get_age_rating_limit(pool, kids.id).await.unwrap(), limit);
+        assert_eq!(get_age_rating_limit(pool, DEFAULT_PROFILE_ID).await.unwrap(), AgeRatingLimit::default());
+        assert!(set_age_rating_limit(pool, 999, limit).await.is_err());
+
+        let blocked = blocked_media_ids(pool, &limit).await.unwrap();
+        assert_eq!(blocked, HashSet::from(["mature".to_string(), "unrated".to_string()]));
+
+        let lenient = AgeRatingLimit { hide_unrated: false, ..limit };
+        assert_eq!(blocked_media_ids(pool, &lenient).await.unwrap(), HashSet::from(["mature".to_string()]));
+        assert!(blocked_media_ids(pool, &AgeRatingLimit::default()).await.unwrap().is_empty());
+    }
+}
@@ -63,16 +63,24 @@ pub async fn get_discover_cache(
     Ok(entry)
 }
 
-/// Clear all discover cache (for refresh/reset)
-#[allow(dead_code)]
-pub async fn clear_discover_cache(pool: &SqlitePool) -> Result<()> {
-    sqlx::query("DELETE FROM discover_cache")
+/// Clear all discover cache (for refresh/reset), returning the number of entries removed
+pub async fn clear_discover_cache(pool: &SqlitePool) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM discover_cache")
         .execute(pool)
         .await?;
 
     log::debug!("Cleared all discover cache");
 
-    Ok(())
+    Ok(result.rows_affected())
+}
+
+/// Number of cached discover results
+pub async fn count_discover_cache(pool: &SqlitePool) -> Result<u64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM discover_cache")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count as u64)
 }
 
 /// Clear discover cache by media type
@@ -179,7 +187,6 @@ pub async fn save_discover_cache_with_ttl(
 }
 
 /// Delete cache entries older than 3x their TTL (garbage collection)
-#[allow(dead_code)]
 pub async fn clear_expired_cache(pool: &SqlitePool) -> Result<u64> {
     let result = sqlx::query(
         r#"
@@ -1,21 +1,102 @@
 // Export/Import Module
 //
 // Handles exporting all user data to JSON and importing it back
-// Enables users to transfer their data between devices
+// Enables users to transfer their data between devices, including the
+// installed extensions (their code only on request)
 
-use sqlx::{SqlitePool, Row};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{FromRow, Row, SqliteConnection, SqlitePool};
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::Utc;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::events::DATA_TRANSFER_PROGRESS_EVENT;
+use crate::extensions::bundled::{bundled_extensions, is_newer_version, persist_extension};
+use crate::extensions::Extension;
 
 use super::library::{LibraryEntry, LibraryStatus};
 use super::watch_history::WatchHistory;
 use super::reading_history::ReadingHistory;
+use super::hidden_media::HiddenMedia;
 use super::media::MediaEntry;
 use super::tags::LibraryTag;
+use super::profiles::Profile;
+
+/// Format version for the export file. Minor versions only add tables, which
+/// older files simply don't have; a different major version may not import.
+/// 1.1.0: id_mappings, migration_archive
+/// 1.2.0: hidden_media
+/// 1.3.0: extensions
+pub const EXPORT_FORMAT_VERSION: &str = "1.3.0";
+
+/// First format version with id_mappings and migration_archive
+const MIGRATION_TABLES_SINCE: &str = "1.1.0";
+
+/// First format version with hidden_media
+const HIDDEN_MEDIA_SINCE: &str = "1.2.0";
 
-/// Format version for the export file
-pub const EXPORT_FORMAT_VERSION: &str = "1.0.0";
+/// Rows written per import transaction
+pub const IMPORT_CHUNK_SIZE: usize = 500;
+
+/// Which operation a progress event belongs to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DataTransferPhase {
+    Export,
+    Import,
+    Complete,
+}
+
+/// Payload of the data-transfer-progress event
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DataTransferProgress {
+    pub phase: DataTransferPhase,
+    /// Table currently being processed (empty once complete)
+    pub table: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Emits progress events when an app handle is available; a no-op otherwise
+/// (auto-backup and tests run without one)
+struct ProgressReporter<'a> {
+    app_handle: Option<&'a AppHandle>,
+    phase: DataTransferPhase,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(app_handle: Option<&'a AppHandle>, phase: DataTransferPhase) -> Self {
+        Self { app_handle, phase }
+    }
+
+    fn emit(&self, table: &str, processed: usize, total: usize) {
+        self.send(DataTransferProgress {
+            phase: self.phase,
+            table: table.to_string(),
+            processed,
+            total,
+        });
+    }
+
+    fn complete(&self) {
+        self.send(DataTransferProgress {
+            phase: DataTransferPhase::Complete,
+            table: String::new(),
+            processed: 0,
+            total: 0,
+        });
+    }
+
+    fn send(&self, progress: DataTransferProgress) {
+        if let Some(handle) = self.app_handle {
+            DATA_TRANSFER_PROGRESS_EVENT.emit(handle, &progress);
+        }
+    }
+}
 
 /// Top-level export data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +119,16 @@ pub struct ExportedTables {
     pub app_settings: Vec<AppSetting>,
     pub media_cache: Vec<MediaEntry>,
     pub tracker_mappings: Vec<TrackerMapping>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub id_mappings: Vec<IdMapping>,
+    #[serde(default)]
+    pub migration_archive: Vec<MigrationArchiveEntry>,
+    #[serde(default)]
+    pub hidden_media: Vec<HiddenMedia>,
+    #[serde(default)]
+    pub extensions: Vec<ExportedExtension>,
 }
 
 /// Tag assignment record (library_tag_assignments table)
@@ -65,15 +156,74 @@ pub struct TrackerMapping {
     pub created_at: String,
 }
 
+/// MAL-to-AllAnime mapping (id_mappings table), so migrated titles resolve
+/// their sources without searching again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdMapping {
+    pub mal_id: String,
+    pub allanime_id: String,
+    pub media_type: String,
+    pub title: String,
+    pub match_score: Option<f64>,
+    pub created_at: Option<String>,
+}
 
-/// Export metadata for summary
+/// AllAnime-to-Jikan migration record (migration_archive table), kept for
+/// recovering entries the migration couldn't match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationArchiveEntry {
+    pub original_id: String,
+    pub original_extension_id: String,
+    pub media_type: String,
+    pub title: String,
+    pub english_name: Option<String>,
+    pub new_mal_id: Option<String>,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub original_media_json: Option<String>,
+    pub original_children_json: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// Installed extension (extensions table). Its code is only included when
+/// the export was asked to embed it; extensions bundled with the app are
+/// reinstalled from the app itself.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedExtension {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub extension_type: String,
+    pub language: String,
+    pub enabled: bool,
+    /// Extensions load (and are listed) in install order
+    pub created_at: String,
+    /// Position in the release source priority; None when it isn't listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// Export metadata for summary
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ExportMetadata {
     pub library_count: usize,
     pub watch_history_count: usize,
     pub reading_history_count: usize,
     pub tag_count: usize,
     pub media_cache_count: usize,
+    /// Profile the export was taken from; None means every profile
+    #[serde(default)]
+    pub profile_id: Option<i64>,
+    #[serde(default)]
+    pub id_mapping_count: usize,
+    #[serde(default)]
+    pub migration_archive_count: usize,
+    #[serde(default)]
+    pub hidden_media_count: usize,
+    #[serde(default)]
+    pub extension_count: usize,
 }
 
 /// Import strategy options
@@ -99,6 +249,20 @@ pub struct ImportOptions {
     pub import_settings: bool,
     pub import_media_cache: bool,
     pub import_tracker_mappings: bool,
+    #[serde(default = "default_true")]
+    pub import_id_mappings: bool,
+    #[serde(default = "default_true")]
+    pub import_migration_archive: bool,
+    #[serde(default = "default_true")]
+    pub import_hidden_media: bool,
+    /// Reinstall the exported extensions. Off unless asked for, since it
+    /// runs code from the file.
+    #[serde(default)]
+    pub import_extensions: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for ImportOptions {
@@ -112,6 +276,10 @@ impl Default for ImportOptions {
             import_settings: true,
             import_media_cache: true,
             import_tracker_mappings: true,
+            import_id_mappings: true,
+            import_migration_archive: true,
+            import_hidden_media: true,
+            import_extensions: false,
         }
     }
 }
@@ -132,6 +300,18 @@ pub struct ImportResult {
     pub settings_imported: usize,
     pub media_cache_imported: usize,
     pub tracker_mappings_imported: usize,
+    pub id_mappings_imported: usize,
+    pub id_mappings_skipped: usize,
+    pub migration_archive_imported: usize,
+    pub migration_archive_skipped: usize,
+    pub hidden_media_imported: usize,
+    pub extensions_imported: usize,
+    pub extensions_skipped: usize,
+    /// Extensions the import left disabled, for the caller to unload
+    #[serde(default)]
+    pub extensions_disabled: Vec<String>,
+    /// Number of import transactions committed
+    pub chunks_committed: usize,
     pub warnings: Vec<String>,
 }
 
@@ -151,32 +331,53 @@ impl Default for ImportResult {
             settings_imported: 0,
             media_cache_imported: 0,
             tracker_mappings_imported: 0,
+            id_mappings_imported: 0,
+            id_mappings_skipped: 0,
+            migration_archive_imported: 0,
+            migration_archive_skipped: 0,
+            hidden_media_imported: 0,
+            extensions_imported: 0,
+            extensions_skipped: 0,
+            extensions_disabled: Vec::new(),
+            chunks_committed: 0,
             warnings: Vec::new(),
         }
     }
 }
 
-/// Export all user data to a structured format
-pub async fn export_all_data(
-    pool: &SqlitePool,
-    app_version: &str,
-) -> Result<ExportData> {
-    log::info!("Starting data export");
+/// Rows fetched per export query
+pub const EXPORT_CHUNK_SIZE: i64 = 500;
+
+/// A table as it appears in the export file. Rows are read in chunks of
+/// `EXPORT_CHUNK_SIZE` so a large table never has to be held in memory.
+trait ExportTable: Serialize + Sized {
+    /// Key under "data" in the export file
+    const NAME: &'static str;
+    /// Whether the queries filter by profile (bound as ?1)
+    const PROFILE_SCOPED: bool;
+    /// Row count; binds ?1 only when profile scoped
+    const COUNT_SQL: &'static str;
+    /// One chunk of rows: ?1 profile id (may be unused), ?2 limit, ?3 offset
+    const SELECT_SQL: &'static str;
+
+    fn read_row(row: &SqliteRow) -> Result<Self>;
+}
 
-    // Export library entries
-    let library = sqlx::query(
-        r#"
+impl ExportTable for LibraryEntry {
+    const NAME: &'static str = "library";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM library WHERE ?1 IS NULL OR profile_id = ?1";
+    const SELECT_SQL: &'static str = r#"
         SELECT id, media_id, status, favorite, score, notes, added_at, updated_at
         FROM library
-        ORDER BY added_at ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|row| {
+        WHERE ?1 IS NULL OR profile_id = ?1
+        ORDER BY added_at ASC, id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
         let status_str: String = row.try_get("status").unwrap_or_default();
-        LibraryEntry {
+        Ok(LibraryEntry {
             id: row.try_get("id").unwrap_or_default(),
             media_id: row.try_get("media_id").unwrap_or_default(),
             status: LibraryStatus::from_str(&status_str).unwrap_or(LibraryStatus::PlanToWatch),
@@ -186,93 +387,113 @@ pub async fn export_all_data(
             added_at: row.try_get("added_at").unwrap_or_default(),
             updated_at: row.try_get("updated_at").unwrap_or_default(),
             auto_download: row.try_get("auto_download").unwrap_or_default(),
-        }
-    })
-    .collect::<Vec<_>>();
-
-    log::debug!("Exported {} library entries", library.len());
+        })
+    }
+}
 
-    // Export watch history
-    let watch_history = sqlx::query_as::<_, WatchHistory>(
-        r#"
+impl ExportTable for WatchHistory {
+    const NAME: &'static str = "watch_history";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM watch_history WHERE ?1 IS NULL OR profile_id = ?1";
+    const SELECT_SQL: &'static str = r#"
         SELECT id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
         FROM watch_history
-        ORDER BY created_at ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await?;
+        WHERE ?1 IS NULL OR profile_id = ?1
+        ORDER BY id ASC -- insertion order; walks the rowid instead of sorting every chunk
+        LIMIT ?2 OFFSET ?3
+    "#;
 
-    log::debug!("Exported {} watch history entries", watch_history.len());
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(WatchHistory::from_row(row)?)
+    }
+}
 
-    // Export reading history
-    let reading_history = sqlx::query_as::<_, ReadingHistory>(
-        r#"
+impl ExportTable for ReadingHistory {
+    const NAME: &'static str = "reading_history";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM reading_history WHERE ?1 IS NULL OR profile_id = ?1";
+    const SELECT_SQL: &'static str = r#"
         SELECT id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
         FROM reading_history
-        ORDER BY created_at ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await?;
+        WHERE ?1 IS NULL OR profile_id = ?1
+        ORDER BY id ASC -- insertion order; walks the rowid instead of sorting every chunk
+        LIMIT ?2 OFFSET ?3
+    "#;
 
-    log::debug!("Exported {} reading history entries", reading_history.len());
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(ReadingHistory::from_row(row)?)
+    }
+}
 
-    // Export library tags
-    let library_tags = sqlx::query_as::<_, LibraryTag>(
-        r#"
+impl ExportTable for LibraryTag {
+    const NAME: &'static str = "library_tags";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM library_tags WHERE ?1 IS NULL OR profile_id = ?1";
+    const SELECT_SQL: &'static str = r#"
         SELECT id, name, color, sort_order, created_at, updated_at
         FROM library_tags
-        ORDER BY sort_order ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await?;
+        WHERE ?1 IS NULL OR profile_id = ?1
+        ORDER BY sort_order ASC, id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
 
-    log::debug!("Exported {} library tags", library_tags.len());
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(LibraryTag::from_row(row)?)
+    }
+}
 
-    // Export tag assignments with media_id for easier import
-    let tag_assignments = sqlx::query(
-        r#"
+impl ExportTable for TagAssignment {
+    const NAME: &'static str = "tag_assignments";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = r#"
+        SELECT COUNT(*)
+        FROM library_tag_assignments a
+        INNER JOIN library l ON a.library_entry_id = l.id
+        WHERE ?1 IS NULL OR l.profile_id = ?1
+    "#;
+    // media_id is included for easier import
+    const SELECT_SQL: &'static str = r#"
         SELECT a.library_entry_id, a.tag_id, l.media_id, a.created_at
         FROM library_tag_assignments a
         INNER JOIN library l ON a.library_entry_id = l.id
-        ORDER BY a.created_at ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|row| TagAssignment {
-        library_entry_id: row.try_get("library_entry_id").unwrap_or_default(),
-        tag_id: row.try_get("tag_id").unwrap_or_default(),
-        media_id: row.try_get("media_id").unwrap_or_default(),
-        created_at: row.try_get("created_at").unwrap_or_default(),
-    })
-    .collect::<Vec<_>>();
-
-    log::debug!("Exported {} tag assignments", tag_assignments.len());
+        WHERE ?1 IS NULL OR l.profile_id = ?1
+        ORDER BY a.created_at ASC, a.library_entry_id ASC, a.tag_id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(TagAssignment {
+            library_entry_id: row.try_get("library_entry_id").unwrap_or_default(),
+            tag_id: row.try_get("tag_id").unwrap_or_default(),
+            media_id: row.try_get("media_id").unwrap_or_default(),
+            created_at: row.try_get("created_at").unwrap_or_default(),
+        })
+    }
+}
 
-    // Export app settings
-    let app_settings = sqlx::query(
-        r#"
+impl ExportTable for AppSetting {
+    const NAME: &'static str = "app_settings";
+    const PROFILE_SCOPED: bool = false;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM app_settings";
+    const SELECT_SQL: &'static str = r#"
         SELECT key, value FROM app_settings
-        "#
-    )
-    .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|row| AppSetting {
-        key: row.try_get("key").unwrap_or_default(),
-        value: row.try_get("value").unwrap_or_default(),
-    })
-    .collect::<Vec<_>>();
-
-    log::debug!("Exported {} app settings", app_settings.len());
+        ORDER BY key ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(AppSetting {
+            key: row.try_get("key").unwrap_or_default(),
+            value: row.try_get("value").unwrap_or_default(),
+        })
+    }
+}
 
-    // Export media cache
-    let media_cache = sqlx::query_as::<_, MediaEntry>(
-        r#"
+impl ExportTable for MediaEntry {
+    const NAME: &'static str = "media_cache";
+    const PROFILE_SCOPED: bool = false;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM media";
+    const SELECT_SQL: &'static str = r#"
         SELECT
             id, extension_id, title, english_name, native_name, description,
             cover_url, banner_url, trailer_url, media_type, content_type, status,
@@ -281,78 +502,565 @@ pub async fn export_all_data(
             aired_start_year, aired_start_month, aired_start_date,
             genres, created_at, updated_at
         FROM media
-        ORDER BY created_at ASC
+        ORDER BY created_at ASC, id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(MediaEntry::from_row(row)?)
+    }
+}
+
+impl ExportTable for TrackerMapping {
+    const NAME: &'static str = "tracker_mappings";
+    const PROFILE_SCOPED: bool = false;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM tracker_mappings";
+    const SELECT_SQL: &'static str = r#"
+        SELECT media_id, tracker_type, tracker_id, created_at
+        FROM tracker_mappings
+        ORDER BY created_at ASC, media_id ASC, tracker_type ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(TrackerMapping {
+            media_id: row.try_get("media_id").unwrap_or_default(),
+            tracker_type: row.try_get("tracker_type").unwrap_or_default(),
+            tracker_id: row.try_get("tracker_id").unwrap_or_default(),
+            created_at: row.try_get("created_at").unwrap_or_default(),
+        })
+    }
+}
+
+impl ExportTable for Profile {
+    const NAME: &'static str = "profiles";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM profiles WHERE ?1 IS NULL OR id = ?1";
+    const SELECT_SQL: &'static str = r#"
+        SELECT id, name, created_at
+        FROM profiles
+        WHERE ?1 IS NULL OR id = ?1
+        ORDER BY id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(Profile::from_row(row)?)
+    }
+}
+
+impl ExportTable for IdMapping {
+    const NAME: &'static str = "id_mappings";
+    const PROFILE_SCOPED: bool = false;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM id_mappings";
+    const SELECT_SQL: &'static str = r#"
+        SELECT mal_id, allanime_id, media_type, title, match_score, created_at
+        FROM id_mappings
+        ORDER BY mal_id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(IdMapping {
+            mal_id: row.try_get("mal_id").unwrap_or_default(),
+            allanime_id: row.try_get("allanime_id").unwrap_or_default(),
+            media_type: row.try_get("media_type").unwrap_or_default(),
+            title: row.try_get("title").unwrap_or_default(),
+            match_score: row.try_get("match_score").ok().flatten(),
+            created_at: row.try_get("created_at").ok().flatten(),
+        })
+    }
+}
+
+impl ExportTable for MigrationArchiveEntry {
+    const NAME: &'static str = "migration_archive";
+    const PROFILE_SCOPED: bool = false;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM migration_archive";
+    const SELECT_SQL: &'static str = r#"
+        SELECT original_id, original_extension_id, media_type, title, english_name, new_mal_id,
+               status, error_message, original_media_json, original_children_json, created_at
+        FROM migration_archive
+        ORDER BY original_id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(MigrationArchiveEntry {
+            original_id: row.try_get("original_id").unwrap_or_default(),
+            original_extension_id: row.try_get("original_extension_id").unwrap_or_default(),
+            media_type: row.try_get("media_type").unwrap_or_default(),
+            title: row.try_get("title").unwrap_or_default(),
+            english_name: row.try_get("english_name").ok().flatten(),
+            new_mal_id: row.try_get("new_mal_id").ok().flatten(),
+            status: row.try_get("status").unwrap_or_default(),
+            error_message: row.try_get("error_message").ok().flatten(),
+            original_media_json: row.try_get("original_media_json").ok().flatten(),
+            original_children_json: row.try_get("original_children_json").ok().flatten(),
+            created_at: row.try_get("created_at").ok().flatten(),
+        })
+    }
+}
+
+impl ExportTable for HiddenMedia {
+    const NAME: &'static str = "hidden_media";
+    const PROFILE_SCOPED: bool = true;
+    const COUNT_SQL: &'static str = "SELECT COUNT(*) FROM hidden_media WHERE ?1 IS NULL OR profile_id = ?1";
+    const SELECT_SQL: &'static str = r#"
+        SELECT media_id, extension_id, title, hidden_at, reason
+        FROM hidden_media
+        WHERE ?1 IS NULL OR profile_id = ?1
+        ORDER BY hidden_at ASC, media_id ASC
+        LIMIT ?2 OFFSET ?3
+    "#;
+
+    fn read_row(row: &SqliteRow) -> Result<Self> {
+        Ok(HiddenMedia {
+            media_id: row.try_get("media_id").unwrap_or_default(),
+            extension_id: row.try_get("extension_id").ok().flatten(),
+            title: row.try_get("title").ok().flatten(),
+            hidden_at: row.try_get("hidden_at").unwrap_or_default(),
+            reason: row.try_get("reason").ok().flatten(),
+        })
+    }
+}
+
+async fn count_rows<T: ExportTable>(conn: &mut SqliteConnection, profile_id: Option<i64>) -> Result<usize> {
+    let mut query = sqlx::query_scalar::<_, i64>(T::COUNT_SQL);
+    if T::PROFILE_SCOPED {
+        query = query.bind(profile_id);
+    }
+    Ok(query.fetch_one(&mut *conn).await? as usize)
+}
+
+async fn fetch_chunk<T: ExportTable>(
+    conn: &mut SqliteConnection,
+    profile_id: Option<i64>,
+    offset: usize,
+) -> Result<Vec<T>> {
+    sqlx::query(T::SELECT_SQL)
+        .bind(profile_id)
+        .bind(EXPORT_CHUNK_SIZE)
+        .bind(offset as i64)
+        .fetch_all(&mut *conn)
+        .await?
+        .iter()
+        .map(T::read_row)
+        .collect()
+}
+
+/// Read a whole table, chunk by chunk
+async fn fetch_table<T: ExportTable>(
+    conn: &mut SqliteConnection,
+    profile_id: Option<i64>,
+    progress: &ProgressReporter<'_>,
+) -> Result<Vec<T>> {
+    let total = count_rows::<T>(conn, profile_id).await?;
+    let mut rows = Vec::with_capacity(total);
+
+    loop {
+        let chunk = fetch_chunk::<T>(conn, profile_id, rows.len()).await?;
+        let done = (chunk.len() as i64) < EXPORT_CHUNK_SIZE;
+        rows.extend(chunk);
+        progress.emit(T::NAME, rows.len(), total.max(rows.len()));
+        if done {
+            break;
+        }
+    }
+
+    log::debug!("Exported {} {} rows", rows.len(), T::NAME);
+    Ok(rows)
+}
+
+/// Tracker mappings are optional; a database without the table exports none
+async fn fetch_tracker_mappings(
+    conn: &mut SqliteConnection,
+    progress: &ProgressReporter<'_>,
+) -> Vec<TrackerMapping> {
+    fetch_table::<TrackerMapping>(conn, None, progress).await.unwrap_or_default()
+}
+
+/// Installed extensions in install order, with their code when
+/// `include_code`. There are only ever a few, so they're read in one go.
+async fn fetch_extensions(conn: &mut SqliteConnection, include_code: bool) -> Result<Vec<ExportedExtension>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, version, extension_type, language, enabled, created_at,
+               CASE WHEN ?1 THEN code END AS code
+        FROM extensions
+        ORDER BY created_at ASC, rowid ASC
         "#
     )
-    .fetch_all(pool)
+    .bind(include_code)
+    .fetch_all(&mut *conn)
+    .await?;
+    let source_priority = fetch_source_priority(&mut *conn).await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| ExportedExtension {
+            id: row.try_get("id").unwrap_or_default(),
+            name: row.try_get("name").unwrap_or_default(),
+            version: row.try_get("version").unwrap_or_default(),
+            extension_type: row.try_get("extension_type").unwrap_or_default(),
+            language: row.try_get("language").unwrap_or_default(),
+            enabled: row.try_get("enabled").unwrap_or(true),
+            created_at: row.try_get("created_at").unwrap_or_default(),
+            priority: row
+                .try_get::<String, _>("id")
+                .ok()
+                .and_then(|id| source_priority.iter().position(|p| *p == id)),
+            code: row.try_get("code").ok().flatten(),
+        })
+        .collect())
+}
+
+/// The release source priority setting: extension ids, most preferred first
+async fn fetch_source_priority(conn: &mut SqliteConnection) -> Result<Vec<String>> {
+    let value: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = 'release_source_priority'"
+    )
+    .fetch_optional(&mut *conn)
     .await?;
+    Ok(value.and_then(|v| serde_json::from_str(&v).ok()).unwrap_or_default())
+}
 
-    log::debug!("Exported {} media cache entries", media_cache.len());
+/// Put the imported extensions back in the release source priority in their
+/// exported order, ahead of the ones the file doesn't rank
+async fn restore_source_priority(pool: &SqlitePool, ranked: &[(usize, String)]) -> Result<()> {
+    let mut ranked = ranked.to_vec();
+    ranked.sort();
+    let mut conn = pool.acquire().await?;
+    let current = fetch_source_priority(&mut conn).await?;
 
-    // Export tracker mappings
-    let tracker_mappings = sqlx::query(
+    let mut priority: Vec<String> = ranked.into_iter().map(|(_, id)| id).collect();
+    let unranked: Vec<String> = current.into_iter().filter(|id| !priority.contains(id)).collect();
+    priority.extend(unranked);
+
+    sqlx::query(
         r#"
-        SELECT media_id, tracker_type, tracker_id, created_at
-        FROM tracker_mappings
-        ORDER BY created_at ASC
-        "#
+        INSERT INTO app_settings (key, value, updated_at)
+        VALUES ('release_source_priority', ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
     )
-    .fetch_all(pool)
-    .await
-    .unwrap_or_default()
-    .into_iter()
-    .map(|row| TrackerMapping {
-        media_id: row.try_get("media_id").unwrap_or_default(),
-        tracker_type: row.try_get("tracker_type").unwrap_or_default(),
-        tracker_id: row.try_get("tracker_id").unwrap_or_default(),
-        created_at: row.try_get("created_at").unwrap_or_default(),
-    })
-    .collect::<Vec<_>>();
+    .bind(serde_json::to_string(&priority)?)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
 
-    log::debug!("Exported {} tracker mappings", tracker_mappings.len());
+/// Export all user data to a structured format.
+/// `profile_id` limits the per-profile tables to one profile; None exports every profile.
+/// Extension code is embedded only with `include_extension_code`, as it can
+/// make up most of the file.
+/// Emits a progress event after each chunk when `app_handle` is given.
+/// For large libraries prefer `export_to_file`, which never holds every row at once.
+pub async fn export_all_data(
+    pool: &SqlitePool,
+    app_version: &str,
+    profile_id: Option<i64>,
+    include_extension_code: bool,
+    app_handle: Option<&AppHandle>,
+) -> Result<ExportData> {
+    log::info!("Starting data export (profile: {:?})", profile_id);
+
+    let progress = ProgressReporter::new(app_handle, DataTransferPhase::Export);
+    let exported_at = Utc::now().to_rfc3339();
+
+    // One read transaction so the chunks see a single snapshot
+    let mut tx = pool.begin().await?;
+
+    let data = ExportedTables {
+        library: fetch_table(&mut tx, profile_id, &progress).await?,
+        watch_history: fetch_table(&mut tx, profile_id, &progress).await?,
+        reading_history: fetch_table(&mut tx, profile_id, &progress).await?,
+        library_tags: fetch_table(&mut tx, profile_id, &progress).await?,
+        tag_assignments: fetch_table(&mut tx, profile_id, &progress).await?,
+        app_settings: fetch_table(&mut tx, profile_id, &progress).await?,
+        media_cache: fetch_table(&mut tx, profile_id, &progress).await?,
+        tracker_mappings: fetch_tracker_mappings(&mut tx, &progress).await,
+        profiles: fetch_table(&mut tx, profile_id, &progress).await?,
+        id_mappings: fetch_table(&mut tx, profile_id, &progress).await?,
+        migration_archive: fetch_table(&mut tx, profile_id, &progress).await?,
+        hidden_media: fetch_table(&mut tx, profile_id, &progress).await?,
+        extensions: fetch_extensions(&mut tx, include_extension_code).await?,
+    };
+
+    tx.commit().await?;
+    progress.complete();
 
     let metadata = ExportMetadata {
-        library_count: library.len(),
-        watch_history_count: watch_history.len(),
-        reading_history_count: reading_history.len(),
-        tag_count: library_tags.len(),
-        media_cache_count: media_cache.len(),
+        library_count: data.library.len(),
+        watch_history_count: data.watch_history.len(),
+        reading_history_count: data.reading_history.len(),
+        tag_count: data.library_tags.len(),
+        media_cache_count: data.media_cache.len(),
+        profile_id,
+        id_mapping_count: data.id_mappings.len(),
+        migration_archive_count: data.migration_archive.len(),
+        hidden_media_count: data.hidden_media.len(),
+        extension_count: data.extensions.len(),
     };
 
-    let export_data = ExportData {
+    log::info!("Data export completed successfully");
+
+    Ok(ExportData {
         format_version: EXPORT_FORMAT_VERSION.to_string(),
         app_version: app_version.to_string(),
-        exported_at: Utc::now().to_rfc3339(),
-        data: ExportedTables {
-            library,
-            watch_history,
-            reading_history,
-            library_tags,
-            tag_assignments,
-            app_settings,
-            media_cache,
-            tracker_mappings,
-        },
+        exported_at,
+        data,
         metadata,
+    })
+}
+
+/// Indents everything written through it by `indent` spaces after each
+/// newline, so serde_json's pretty output lines up when nested in a document
+/// that is written by hand. JSON strings never contain raw newlines.
+struct IndentWriter<'a, W: Write> {
+    inner: &'a mut W,
+    indent: usize,
+}
+
+impl<W: Write> Write for IndentWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = buf.split(|b| *b == b'\n');
+        if let Some(first) = lines.next() {
+            self.inner.write_all(first)?;
+        }
+        for line in lines {
+            self.inner.write_all(b"\n")?;
+            for _ in 0..self.indent {
+                self.inner.write_all(b" ")?;
+            }
+            self.inner.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes an export document piece by piece, producing exactly what
+/// `serde_json::to_writer_pretty(&ExportData)` would. Output is staged in
+/// `buf` and handed to `out` after every chunk of rows, so at most one
+/// chunk is held and the async writer never blocks the runtime.
+struct ExportWriter<W: AsyncWrite + Unpin> {
+    out: W,
+    buf: Vec<u8>,
+}
+
+impl<W: AsyncWrite + Unpin> ExportWriter<W> {
+    /// Serialize a value nested `depth` levels deep
+    fn value<T: Serialize + ?Sized>(&mut self, value: &T, depth: usize) -> Result<()> {
+        let writer = IndentWriter { inner: &mut self.buf, indent: depth * 2 };
+        let mut serializer = serde_json::Serializer::pretty(writer);
+        value.serialize(&mut serializer)?;
+        Ok(())
+    }
+
+    fn raw(&mut self, s: &str) -> Result<()> {
+        self.buf.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+
+    /// Hand what's been staged so far to the writer
+    async fn drain(&mut self) -> Result<()> {
+        self.out.write_all(&self.buf).await?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    fn header(&mut self, app_version: &str, exported_at: &str) -> Result<()> {
+        self.raw("{\n  \"format_version\": ")?;
+        self.value(EXPORT_FORMAT_VERSION, 1)?;
+        self.raw(",\n  \"app_version\": ")?;
+        self.value(app_version, 1)?;
+        self.raw(",\n  \"exported_at\": ")?;
+        self.value(exported_at, 1)?;
+        self.raw(",\n  \"data\": {")
+    }
+
+    /// Stream one table as a `"name": [...]` entry of "data"
+    async fn table<T: ExportTable>(
+        &mut self,
+        conn: &mut SqliteConnection,
+        profile_id: Option<i64>,
+        progress: &ProgressReporter<'_>,
+        first: bool,
+    ) -> Result<usize> {
+        let total = count_rows::<T>(conn, profile_id).await?;
+
+        self.raw(if first { "\n    \"" } else { ",\n    \"" })?;
+        self.raw(T::NAME)?;
+        self.raw("\": [")?;
+
+        let mut written = 0;
+        loop {
+            let chunk = fetch_chunk::<T>(conn, profile_id, written).await?;
+            let done = (chunk.len() as i64) < EXPORT_CHUNK_SIZE;
+
+            for row in &chunk {
+                self.raw(if written == 0 { "\n      " } else { ",\n      " })?;
+                self.value(row, 3)?;
+                written += 1;
+            }
+            self.drain().await?;
+
+            progress.emit(T::NAME, written, total.max(written));
+            if done {
+                break;
+            }
+        }
+
+        if written > 0 {
+            self.raw("\n    ")?;
+        }
+        self.raw("]")?;
+
+        log::debug!("Exported {} {} rows", written, T::NAME);
+        Ok(written)
+    }
+
+    async fn footer(&mut self, metadata: &ExportMetadata) -> Result<()> {
+        self.raw("\n  },\n  \"metadata\": ")?;
+        self.value(metadata, 1)?;
+        self.raw("\n}")?;
+        self.drain().await?;
+        self.out.flush().await?;
+        Ok(())
+    }
+}
+
+/// Stream an export straight to `writer`, one chunk of rows at a time.
+/// The output is byte-for-byte what pretty-printing `export_all_data` gives.
+async fn write_export<W: AsyncWrite + Unpin>(
+    pool: &SqlitePool,
+    writer: W,
+    app_version: &str,
+    exported_at: &str,
+    profile_id: Option<i64>,
+    include_extension_code: bool,
+    progress: &ProgressReporter<'_>,
+) -> Result<ExportMetadata> {
+    let mut out = ExportWriter { out: writer, buf: Vec::new() };
+    let mut tx = pool.begin().await?;
+
+    out.header(app_version, exported_at)?;
+    let library_count = out.table::<LibraryEntry>(&mut tx, profile_id, progress, true).await?;
+    let watch_history_count = out.table::<WatchHistory>(&mut tx, profile_id, progress, false).await?;
+    let reading_history_count = out.table::<ReadingHistory>(&mut tx, profile_id, progress, false).await?;
+    let tag_count = out.table::<LibraryTag>(&mut tx, profile_id, progress, false).await?;
+    out.table::<TagAssignment>(&mut tx, profile_id, progress, false).await?;
+    out.table::<AppSetting>(&mut tx, profile_id, progress, false).await?;
+    let media_cache_count = out.table::<MediaEntry>(&mut tx, profile_id, progress, false).await?;
+    if count_rows::<TrackerMapping>(&mut tx, None).await.is_ok() {
+        out.table::<TrackerMapping>(&mut tx, None, progress, false).await?;
+    } else {
+        out.raw(",\n    \"tracker_mappings\": []")?;
+    }
+    out.table::<Profile>(&mut tx, profile_id, progress, false).await?;
+    let id_mapping_count = out.table::<IdMapping>(&mut tx, profile_id, progress, false).await?;
+    let migration_archive_count = out.table::<MigrationArchiveEntry>(&mut tx, profile_id, progress, false).await?;
+    let hidden_media_count = out.table::<HiddenMedia>(&mut tx, profile_id, progress, false).await?;
+    let extensions = fetch_extensions(&mut tx, include_extension_code).await?;
+    out.raw(",\n    \"extensions\": ")?;
+    out.value(&extensions, 2)?;
+
+    tx.commit().await?;
+
+    let metadata = ExportMetadata {
+        library_count,
+        watch_history_count,
+        reading_history_count,
+        tag_count,
+        media_cache_count,
+        profile_id,
+        id_mapping_count,
+        migration_archive_count,
+        hidden_media_count,
+        extension_count: extensions.len(),
     };
+    out.footer(&metadata).await?;
 
-    log::info!("Data export completed successfully");
+    Ok(metadata)
+}
 
-    Ok(export_data)
+/// Export all user data straight to a file without building the document in
+/// memory. Written to a temporary file first and renamed into place, so a
+/// failed export never leaves a truncated backup behind.
+pub async fn export_to_file(
+    pool: &SqlitePool,
+    app_version: &str,
+    profile_id: Option<i64>,
+    include_extension_code: bool,
+    path: &Path,
+    app_handle: Option<&AppHandle>,
+) -> Result<ExportMetadata> {
+    log::info!("Starting data export to {:?} (profile: {:?})", path, profile_id);
+
+    let progress = ProgressReporter::new(app_handle, DataTransferPhase::Export);
+    let exported_at = Utc::now().to_rfc3339();
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let file = tokio::fs::File::create(&partial)
+        .await
+        .with_context(|| format!("Failed to create {:?}", partial))?;
+    let result = write_export(pool, file, app_version, &exported_at, profile_id, include_extension_code, &progress).await;
+    let metadata = match result {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+
+    tokio::fs::rename(&partial, path)
+        .await
+        .with_context(|| format!("Failed to move export into place at {:?}", path))?;
+
+    progress.complete();
+    log::info!("Data export to {:?} completed successfully", path);
+
+    Ok(metadata)
+}
+
+/// Files from the same major format version import cleanly: tables a file
+/// doesn't have are simply empty
+fn is_compatible_format(version: &str) -> bool {
+    let major = |v: &str| v.split('.').next().map(str::to_string);
+    major(version) == major(EXPORT_FORMAT_VERSION)
 }
 
-/// Import data from an export file
+/// Whether a file of format `version` has the tables added in `since`. An
+/// older file's missing table says nothing about what should be there.
+fn has_tables_since(version: &str, since: &str) -> bool {
+    !is_newer_version(since, version)
+}
+
+/// Import data from an export file into the active profile.
+///
+/// Each table is written in chunks of `IMPORT_CHUNK_SIZE` rows, one
+/// transaction per chunk, so a crash loses at most one chunk and progress
+/// events can be emitted between chunks.
 pub async fn import_data(
     pool: &SqlitePool,
+    profile_id: i64,
     data: ExportData,
     options: ImportOptions,
+    app_handle: Option<&AppHandle>,
 ) -> Result<ImportResult> {
     log::info!("Starting data import with strategy: {:?}", options.strategy);
 
     let mut result = ImportResult::default();
+    let progress = ProgressReporter::new(app_handle, DataTransferPhase::Import);
 
     // Validate format version
-    if data.format_version != EXPORT_FORMAT_VERSION {
+    if !is_compatible_format(&data.format_version) {
         result.warnings.push(format!(
             "Export file version {} differs from current version {}. Some data may not import correctly.",
             data.format_version, EXPORT_FORMAT_VERSION
@@ -363,246 +1071,316 @@ pub async fn import_data(
     if matches!(options.strategy, ImportStrategy::ReplaceAll) {
         log::info!("Clearing existing data for ReplaceAll strategy");
 
+        let mut tx = pool.begin().await?;
+
+        // Per-profile tables only lose the active profile's rows;
+        // assignments go with their tags through ON DELETE CASCADE
         if options.import_tags {
-            sqlx::query("DELETE FROM library_tag_assignments").execute(pool).await?;
-            sqlx::query("DELETE FROM library_tags").execute(pool).await?;
+            sqlx::query("DELETE FROM library_tags WHERE profile_id = ?").bind(profile_id).execute(&mut *tx).await?;
         }
         if options.import_library {
-            sqlx::query("DELETE FROM library").execute(pool).await?;
+            sqlx::query("DELETE FROM library WHERE profile_id = ?").bind(profile_id).execute(&mut *tx).await?;
         }
         if options.import_watch_history {
-            sqlx::query("DELETE FROM watch_history").execute(pool).await?;
+            sqlx::query("DELETE FROM watch_history WHERE profile_id = ?").bind(profile_id).execute(&mut *tx).await?;
         }
         if options.import_reading_history {
-            sqlx::query("DELETE FROM reading_history").execute(pool).await?;
+            sqlx::query("DELETE FROM reading_history WHERE profile_id = ?").bind(profile_id).execute(&mut *tx).await?;
         }
         if options.import_settings {
-            sqlx::query("DELETE FROM app_settings").execute(pool).await?;
+            sqlx::query("DELETE FROM app_settings").execute(&mut *tx).await?;
         }
         if options.import_media_cache {
-            sqlx::query("DELETE FROM media").execute(pool).await?;
+            sqlx::query("DELETE FROM media").execute(&mut *tx).await?;
         }
         if options.import_tracker_mappings {
-            let _ = sqlx::query("DELETE FROM tracker_mappings").execute(pool).await;
+            let _ = sqlx::query("DELETE FROM tracker_mappings").execute(&mut *tx).await;
+        }
+        // Files from before these tables existed leave them alone
+        let has_migration_tables = has_tables_since(&data.format_version, MIGRATION_TABLES_SINCE);
+        if options.import_id_mappings && has_migration_tables {
+            sqlx::query("DELETE FROM id_mappings").execute(&mut *tx).await?;
+        }
+        if options.import_migration_archive && has_migration_tables {
+            sqlx::query("DELETE FROM migration_archive").execute(&mut *tx).await?;
+        }
+        if options.import_hidden_media && has_tables_since(&data.format_version, HIDDEN_MEDIA_SINCE) {
+            sqlx::query("DELETE FROM hidden_media WHERE profile_id = ?").bind(profile_id).execute(&mut *tx).await?;
         }
+
+        tx.commit().await?;
     }
 
     // Import media cache first (other tables reference it)
     if options.import_media_cache {
-        for media in &data.data.media_cache {
-            let exists: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM media WHERE id = ?)"
-            )
-            .bind(&media.id)
-            .fetch_one(pool)
-            .await?;
-
-            let should_import = match options.strategy {
-                ImportStrategy::ReplaceAll => true,
-                ImportStrategy::MergeKeepExisting => !exists,
-                ImportStrategy::MergePreferImport => true,
-            };
+        let total = data.data.media_cache.len();
+        let mut processed = 0;
 
-            if should_import {
-                sqlx::query(
-                    r#"
-                    INSERT INTO media (
-                        id, extension_id, title, english_name, native_name, description,
-                        cover_url, banner_url, trailer_url, media_type, content_type, status,
-                        year, rating, episode_count, episode_duration,
-                        season_quarter, season_year,
-                        aired_start_year, aired_start_month, aired_start_date,
-                        genres, created_at, updated_at
-                    )
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                    ON CONFLICT(id) DO UPDATE SET
-                        title = excluded.title,
-                        english_name = excluded.english_name,
-                        native_name = excluded.native_name,
-                        description = excluded.description,
-                        cover_url = excluded.cover_url,
-                        banner_url = excluded.banner_url,
-                        trailer_url = excluded.trailer_url,
-                        status = excluded.status,
-                        year = excluded.year,
-                        rating = excluded.rating,
-                        episode_count = excluded.episode_count,
-                        genres = excluded.genres,
-                        updated_at = excluded.updated_at
-                    "#
+        for chunk in data.data.media_cache.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for media in chunk {
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM media WHERE id = ?)"
                 )
                 .bind(&media.id)
-                .bind(&media.extension_id)
-                .bind(&media.title)
-                .bind(&media.english_name)
-                .bind(&media.native_name)
-                .bind(&media.description)
-                .bind(&media.cover_url)
-                .bind(&media.banner_url)
-                .bind(&media.trailer_url)
-                .bind(&media.media_type)
-                .bind(&media.content_type)
-                .bind(&media.status)
-                .bind(media.year)
-                .bind(media.rating)
-                .bind(media.episode_count)
-                .bind(media.episode_duration)
-                .bind(&media.season_quarter)
-                .bind(media.season_year)
-                .bind(media.aired_start_year)
-                .bind(media.aired_start_month)
-                .bind(media.aired_start_date)
-                .bind(&media.genres)
-                .bind(&media.created_at)
-                .bind(&media.updated_at)
-                .execute(pool)
+                .fetch_one(&mut *tx)
                 .await?;
 
-                result.media_cache_imported += 1;
+                let should_import = match options.strategy {
+                    ImportStrategy::ReplaceAll => true,
+                    ImportStrategy::MergeKeepExisting => !exists,
+                    ImportStrategy::MergePreferImport => true,
+                };
+
+                if should_import {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO media (
+                            id, extension_id, title, english_name, native_name, description,
+                            cover_url, banner_url, trailer_url, media_type, content_type, status,
+                            year, rating, episode_count, episode_duration,
+                            season_quarter, season_year,
+                            aired_start_year, aired_start_month, aired_start_date,
+                            genres, created_at, updated_at
+                        )
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        ON CONFLICT(id) DO UPDATE SET
+                            title = excluded.title,
+                            english_name = excluded.english_name,
+                            native_name = excluded.native_name,
+                            description = excluded.description,
+                            cover_url = excluded.cover_url,
+                            banner_url = excluded.banner_url,
+                            trailer_url = excluded.trailer_url,
+                            status = excluded.status,
+                            year = excluded.year,
+                            rating = excluded.rating,
+                            episode_count = excluded.episode_count,
+                            genres = excluded.genres,
+                            updated_at = excluded.updated_at
+                        "#
+                    )
+                    .bind(&media.id)
+                    .bind(&media.extension_id)
+                    .bind(&media.title)
+                    .bind(&media.english_name)
+                    .bind(&media.native_name)
+                    .bind(&media.description)
+                    .bind(&media.cover_url)
+                    .bind(&media.banner_url)
+                    .bind(&media.trailer_url)
+                    .bind(&media.media_type)
+                    .bind(&media.content_type)
+                    .bind(&media.status)
+                    .bind(media.year)
+                    .bind(media.rating)
+                    .bind(media.episode_count)
+                    .bind(media.episode_duration)
+                    .bind(&media.season_quarter)
+                    .bind(media.season_year)
+                    .bind(media.aired_start_year)
+                    .bind(media.aired_start_month)
+                    .bind(media.aired_start_date)
+                    .bind(&media.genres)
+                    .bind(&media.created_at)
+                    .bind(&media.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    result.media_cache_imported += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("media_cache", processed, total);
         }
         log::debug!("Imported {} media cache entries", result.media_cache_imported);
     }
 
     // Import library entries
     if options.import_library {
-        for entry in &data.data.library {
-            let exists: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM library WHERE media_id = ?)"
-            )
-            .bind(&entry.media_id)
-            .fetch_one(pool)
-            .await?;
-
-            let should_import = match options.strategy {
-                ImportStrategy::ReplaceAll => true,
-                ImportStrategy::MergeKeepExisting => !exists,
-                ImportStrategy::MergePreferImport => true,
-            };
+        let total = data.data.library.len();
+        let mut processed = 0;
 
-            if should_import {
-                sqlx::query(
-                    r#"
-                    INSERT INTO library (media_id, status, favorite, score, notes, added_at, updated_at)
-                    VALUES (?, ?, ?, ?, ?, ?, ?)
-                    ON CONFLICT(media_id) DO UPDATE SET
-                        status = excluded.status,
-                        favorite = excluded.favorite,
-                        score = excluded.score,
-                        notes = excluded.notes,
-                        updated_at = excluded.updated_at
-                    "#
+        for chunk in data.data.library.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for entry in chunk {
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM library WHERE profile_id = ? AND media_id = ?)"
                 )
+                .bind(profile_id)
                 .bind(&entry.media_id)
-                .bind(entry.status.as_str())
-                .bind(entry.favorite)
-                .bind(entry.score)
-                .bind(&entry.notes)
-                .bind(&entry.added_at)
-                .bind(&entry.updated_at)
-                .execute(pool)
+                .fetch_one(&mut *tx)
                 .await?;
 
-                result.library_imported += 1;
-            } else {
-                result.library_skipped += 1;
+                let should_import = match options.strategy {
+                    ImportStrategy::ReplaceAll => true,
+                    ImportStrategy::MergeKeepExisting => !exists,
+                    ImportStrategy::MergePreferImport => true,
+                };
+
+                if should_import {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO library (profile_id, media_id, status, favorite, score, notes, added_at, updated_at)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                        ON CONFLICT(profile_id, media_id) DO UPDATE SET
+                            status = excluded.status,
+                            favorite = excluded.favorite,
+                            score = excluded.score,
+                            notes = excluded.notes,
+                            updated_at = excluded.updated_at
+                        "#
+                    )
+                    .bind(profile_id)
+                    .bind(&entry.media_id)
+                    .bind(entry.status.as_str())
+                    .bind(entry.favorite)
+                    .bind(entry.score)
+                    .bind(&entry.notes)
+                    .bind(&entry.added_at)
+                    .bind(&entry.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    result.library_imported += 1;
+                } else {
+                    result.library_skipped += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("library", processed, total);
         }
         log::debug!("Imported {} library entries, skipped {}", result.library_imported, result.library_skipped);
     }
 
     // Import watch history
     if options.import_watch_history {
-        for entry in &data.data.watch_history {
-            let exists: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM watch_history WHERE media_id = ? AND episode_id = ?)"
-            )
-            .bind(&entry.media_id)
-            .bind(&entry.episode_id)
-            .fetch_one(pool)
-            .await?;
-
-            let should_import = match options.strategy {
-                ImportStrategy::ReplaceAll => true,
-                ImportStrategy::MergeKeepExisting => !exists,
-                ImportStrategy::MergePreferImport => true,
-            };
+        let total = data.data.watch_history.len();
+        let mut processed = 0;
 
-            if should_import {
-                sqlx::query(
-                    r#"
-                    INSERT INTO watch_history (media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-                    ON CONFLICT(media_id, episode_id) DO UPDATE SET
-                        progress_seconds = excluded.progress_seconds,
-                        duration = excluded.duration,
-                        completed = excluded.completed,
-                        last_watched = excluded.last_watched
-                    "#
+        for chunk in data.data.watch_history.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for entry in chunk {
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM watch_history WHERE profile_id = ? AND media_id = ? AND episode_id = ?)"
                 )
+                .bind(profile_id)
                 .bind(&entry.media_id)
                 .bind(&entry.episode_id)
-                .bind(entry.episode_number)
-                .bind(entry.progress_seconds)
-                .bind(entry.duration)
-                .bind(entry.completed)
-                .bind(&entry.last_watched)
-                .bind(&entry.created_at)
-                .execute(pool)
+                .fetch_one(&mut *tx)
                 .await?;
 
-                result.watch_history_imported += 1;
-            } else {
-                result.watch_history_skipped += 1;
+                let should_import = match options.strategy {
+                    ImportStrategy::ReplaceAll => true,
+                    ImportStrategy::MergeKeepExisting => !exists,
+                    ImportStrategy::MergePreferImport => true,
+                };
+
+                if should_import {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        ON CONFLICT(profile_id, media_id, episode_id) DO UPDATE SET
+                            progress_seconds = excluded.progress_seconds,
+                            duration = excluded.duration,
+                            completed = excluded.completed,
+                            last_watched = excluded.last_watched
+                        "#
+                    )
+                    .bind(profile_id)
+                    .bind(&entry.media_id)
+                    .bind(&entry.episode_id)
+                    .bind(entry.episode_number)
+                    .bind(entry.progress_seconds)
+                    .bind(entry.duration)
+                    .bind(entry.completed)
+                    .bind(&entry.last_watched)
+                    .bind(&entry.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    result.watch_history_imported += 1;
+                } else {
+                    result.watch_history_skipped += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("watch_history", processed, total);
         }
         log::debug!("Imported {} watch history entries, skipped {}", result.watch_history_imported, result.watch_history_skipped);
     }
 
     // Import reading history
     if options.import_reading_history {
-        for entry in &data.data.reading_history {
-            let exists: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM reading_history WHERE media_id = ? AND chapter_id = ?)"
-            )
-            .bind(&entry.media_id)
-            .bind(&entry.chapter_id)
-            .fetch_one(pool)
-            .await?;
-
-            let should_import = match options.strategy {
-                ImportStrategy::ReplaceAll => true,
-                ImportStrategy::MergeKeepExisting => !exists,
-                ImportStrategy::MergePreferImport => true,
-            };
+        let total = data.data.reading_history.len();
+        let mut processed = 0;
 
-            if should_import {
-                sqlx::query(
-                    r#"
-                    INSERT INTO reading_history (media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-                    ON CONFLICT(media_id, chapter_id) DO UPDATE SET
-                        current_page = excluded.current_page,
-                        total_pages = excluded.total_pages,
-                        completed = excluded.completed,
-                        last_read = excluded.last_read
-                    "#
+        for chunk in data.data.reading_history.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for entry in chunk {
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM reading_history WHERE profile_id = ? AND media_id = ? AND chapter_id = ?)"
                 )
+                .bind(profile_id)
                 .bind(&entry.media_id)
                 .bind(&entry.chapter_id)
-                .bind(entry.chapter_number)
-                .bind(entry.current_page)
-                .bind(entry.total_pages)
-                .bind(entry.completed)
-                .bind(&entry.last_read)
-                .bind(&entry.created_at)
-                .execute(pool)
+                .fetch_one(&mut *tx)
                 .await?;
 
-                result.reading_history_imported += 1;
-            } else {
-                result.reading_history_skipped += 1;
+                let should_import = match options.strategy {
+                    ImportStrategy::ReplaceAll => true,
+                    ImportStrategy::MergeKeepExisting => !exists,
+                    ImportStrategy::MergePreferImport => true,
+                };
+
+                if should_import {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO reading_history (profile_id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        ON CONFLICT(profile_id, media_id, chapter_id) DO UPDATE SET
+                            current_page = excluded.current_page,
+                            total_pages = excluded.total_pages,
+                            completed = excluded.completed,
+                            last_read = excluded.last_read
+                        "#
+                    )
+                    .bind(profile_id)
+                    .bind(&entry.media_id)
+                    .bind(&entry.chapter_id)
+                    .bind(entry.chapter_number)
+                    .bind(entry.current_page)
+                    .bind(entry.total_pages)
+                    .bind(entry.completed)
+                    .bind(&entry.last_read)
+                    .bind(&entry.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    result.reading_history_imported += 1;
+                } else {
+                    result.reading_history_skipped += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("reading_history", processed, total);
         }
         log::debug!("Imported {} reading history entries, skipped {}", result.reading_history_imported, result.reading_history_skipped);
     }
@@ -612,152 +1390,969 @@ pub async fn import_data(
     let mut tag_id_map: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
 
     if options.import_tags {
-        for tag in &data.data.library_tags {
-            let existing_id: Option<i64> = sqlx::query_scalar(
-                "SELECT id FROM library_tags WHERE name = ?"
-            )
-            .bind(&tag.name)
-            .fetch_optional(pool)
-            .await?;
-
-            let should_import = match options.strategy {
-                ImportStrategy::ReplaceAll => true,
-                ImportStrategy::MergeKeepExisting => existing_id.is_none(),
-                ImportStrategy::MergePreferImport => true,
-            };
+        let total = data.data.library_tags.len();
+        let mut processed = 0;
 
-            if should_import {
-                sqlx::query(
-                    r#"
-                    INSERT INTO library_tags (name, color, sort_order, created_at, updated_at)
-                    VALUES (?, ?, ?, ?, ?)
-                    ON CONFLICT(name) DO UPDATE SET
-                        color = excluded.color,
-                        sort_order = excluded.sort_order,
-                        updated_at = excluded.updated_at
-                    "#
-                )
-                .bind(&tag.name)
-                .bind(&tag.color)
-                .bind(tag.sort_order)
-                .bind(&tag.created_at)
-                .bind(&tag.updated_at)
-                .execute(pool)
-                .await?;
+        for chunk in data.data.library_tags.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
 
-                // Get the new ID for this tag
-                let new_id: i64 = sqlx::query_scalar(
-                    "SELECT id FROM library_tags WHERE name = ?"
+            for tag in chunk {
+                let existing_id: Option<i64> = sqlx::query_scalar(
+                    "SELECT id FROM library_tags WHERE profile_id = ? AND name = ?"
                 )
+                .bind(profile_id)
                 .bind(&tag.name)
-                .fetch_one(pool)
+                .fetch_optional(&mut *tx)
                 .await?;
 
-                tag_id_map.insert(tag.id, new_id);
-                result.tags_imported += 1;
-            } else if let Some(existing) = existing_id {
-                tag_id_map.insert(tag.id, existing);
-                result.tags_skipped += 1;
+                let should_import = match options.strategy {
+                    ImportStrategy::ReplaceAll => true,
+                    ImportStrategy::MergeKeepExisting => existing_id.is_none(),
+                    ImportStrategy::MergePreferImport => true,
+                };
+
+                if should_import {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO library_tags (profile_id, name, color, sort_order, created_at, updated_at)
+                        VALUES (?, ?, ?, ?, ?, ?)
+                        ON CONFLICT(profile_id, name) DO UPDATE SET
+                            color = excluded.color,
+                            sort_order = excluded.sort_order,
+                            updated_at = excluded.updated_at
+                        "#
+                    )
+                    .bind(profile_id)
+                    .bind(&tag.name)
+                    .bind(&tag.color)
+                    .bind(tag.sort_order)
+                    .bind(&tag.created_at)
+                    .bind(&tag.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    // Get the new ID for this tag
+                    let new_id: i64 = sqlx::query_scalar(
+                        "SELECT id FROM library_tags WHERE profile_id = ? AND name = ?"
+                    )
+                    .bind(profile_id)
+                    .bind(&tag.name)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                    tag_id_map.insert(tag.id, new_id);
+                    result.tags_imported += 1;
+                } else if let Some(existing) = existing_id {
+                    tag_id_map.insert(tag.id, existing);
+                    result.tags_skipped += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("library_tags", processed, total);
         }
         log::debug!("Imported {} tags, skipped {}", result.tags_imported, result.tags_skipped);
 
         // Import tag assignments
-        for assignment in &data.data.tag_assignments {
-            // Get the new tag ID from our mapping
-            let new_tag_id = match tag_id_map.get(&assignment.tag_id) {
-                Some(id) => *id,
-                None => {
-                    result.warnings.push(format!(
-                        "Tag assignment skipped: tag ID {} not found in import",
-                        assignment.tag_id
-                    ));
-                    continue;
-                }
-            };
+        let total = data.data.tag_assignments.len();
+        let mut processed = 0;
+
+        for chunk in data.data.tag_assignments.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for assignment in chunk {
+                // Get the new tag ID from our mapping
+                let new_tag_id = match tag_id_map.get(&assignment.tag_id) {
+                    Some(id) => *id,
+                    None => {
+                        result.warnings.push(format!(
+                            "Tag assignment skipped: tag ID {} not found in import",
+                            assignment.tag_id
+                        ));
+                        continue;
+                    }
+                };
+
+                // Get the library entry ID for this media_id
+                let library_entry_id: Option<i64> = sqlx::query_scalar(
+                    "SELECT id FROM library WHERE profile_id = ? AND media_id = ?"
+                )
+                .bind(profile_id)
+                .bind(&assignment.media_id)
+                .fetch_optional(&mut *tx)
+                .await?;
 
-            // Get the library entry ID for this media_id
-            let library_entry_id: Option<i64> = sqlx::query_scalar(
-                "SELECT id FROM library WHERE media_id = ?"
-            )
-            .bind(&assignment.media_id)
-            .fetch_optional(pool)
-            .await?;
-
-            let library_entry_id = match library_entry_id {
-                Some(id) => id,
-                None => {
-                    // Library entry doesn't exist, skip this assignment
-                    continue;
-                }
-            };
+                let library_entry_id = match library_entry_id {
+                    Some(id) => id,
+                    None => {
+                        // Library entry doesn't exist, skip this assignment
+                        continue;
+                    }
+                };
 
-            // Insert assignment (ignore if already exists)
-            let insert_result = sqlx::query(
-                r#"
-                INSERT OR IGNORE INTO library_tag_assignments (library_entry_id, tag_id, created_at)
-                VALUES (?, ?, ?)
-                "#
-            )
-            .bind(library_entry_id)
-            .bind(new_tag_id)
-            .bind(&assignment.created_at)
-            .execute(pool)
-            .await?;
+                // Insert assignment (ignore if already exists)
+                let insert_result = sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO library_tag_assignments (library_entry_id, tag_id, created_at)
+                    VALUES (?, ?, ?)
+                    "#
+                )
+                .bind(library_entry_id)
+                .bind(new_tag_id)
+                .bind(&assignment.created_at)
+                .execute(&mut *tx)
+                .await?;
 
-            if insert_result.rows_affected() > 0 {
-                result.tag_assignments_imported += 1;
+                if insert_result.rows_affected() > 0 {
+                    result.tag_assignments_imported += 1;
+                }
             }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("tag_assignments", processed, total);
         }
         log::debug!("Imported {} tag assignments", result.tag_assignments_imported);
     }
 
     // Import app settings
     if options.import_settings {
-        for setting in &data.data.app_settings {
-            sqlx::query(
-                r#"
-                INSERT INTO app_settings (key, value, updated_at)
-                VALUES (?, ?, strftime('%s', 'now') * 1000)
-                ON CONFLICT(key) DO UPDATE SET
-                    value = excluded.value,
-                    updated_at = strftime('%s', 'now') * 1000
-                "#
-            )
-            .bind(&setting.key)
-            .bind(&setting.value)
-            .execute(pool)
-            .await?;
+        let total = data.data.app_settings.len();
+        let mut processed = 0;
+
+        for chunk in data.data.app_settings.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for setting in chunk {
+                sqlx::query(
+                    r#"
+                    INSERT INTO app_settings (key, value, updated_at)
+                    VALUES (?, ?, strftime('%s', 'now') * 1000)
+                    ON CONFLICT(key) DO UPDATE SET
+                        value = excluded.value,
+                        updated_at = strftime('%s', 'now') * 1000
+                    "#
+                )
+                .bind(&setting.key)
+                .bind(&setting.value)
+                .execute(&mut *tx)
+                .await?;
 
-            result.settings_imported += 1;
+                result.settings_imported += 1;
+            }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("app_settings", processed, total);
         }
         log::debug!("Imported {} app settings", result.settings_imported);
     }
 
     // Import tracker mappings
     if options.import_tracker_mappings {
-        for mapping in &data.data.tracker_mappings {
-            let _ = sqlx::query(
-                r#"
-                INSERT INTO tracker_mappings (media_id, tracker_type, tracker_id, created_at)
-                VALUES (?, ?, ?, ?)
-                ON CONFLICT(media_id, tracker_type) DO UPDATE SET
-                    tracker_id = excluded.tracker_id
-                "#
-            )
-            .bind(&mapping.media_id)
-            .bind(&mapping.tracker_type)
-            .bind(&mapping.tracker_id)
-            .bind(&mapping.created_at)
-            .execute(pool)
-            .await;
+        let total = data.data.tracker_mappings.len();
+        let mut processed = 0;
+
+        for chunk in data.data.tracker_mappings.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
 
-            result.tracker_mappings_imported += 1;
+            for mapping in chunk {
+                let _ = sqlx::query(
+                    r#"
+                    INSERT INTO tracker_mappings (media_id, tracker_type, tracker_id, created_at)
+                    VALUES (?, ?, ?, ?)
+                    ON CONFLICT(media_id, tracker_type) DO UPDATE SET
+                        tracker_id = excluded.tracker_id
+                    "#
+                )
+                .bind(&mapping.media_id)
+                .bind(&mapping.tracker_type)
+                .bind(&mapping.tracker_id)
+                .bind(&mapping.created_at)
+                .execute(&mut *tx)
+                .await;
+
+                result.tracker_mappings_imported += 1;
+            }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("tracker_mappings", processed, total);
         }
         log::debug!("Imported {} tracker mappings", result.tracker_mappings_imported);
     }
 
-    log::info!("Data import completed successfully");
+    // Import id mappings. A mapping this machine already has was resolved
+    // here, so it's kept whatever the strategy.
+    if options.import_id_mappings {
+        let total = data.data.id_mappings.len();
+        let mut processed = 0;
+
+        for chunk in data.data.id_mappings.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for mapping in chunk {
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT INTO id_mappings (mal_id, allanime_id, media_type, title, match_score, created_at)
+                    VALUES (?, ?, ?, ?, ?, COALESCE(?, datetime('now')))
+                    ON CONFLICT(mal_id) DO NOTHING
+                    "#
+                )
+                .bind(&mapping.mal_id)
+                .bind(&mapping.allanime_id)
+                .bind(&mapping.media_type)
+                .bind(&mapping.title)
+                .bind(mapping.match_score)
+                .bind(&mapping.created_at)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                if inserted > 0 {
+                    result.id_mappings_imported += 1;
+                } else {
+                    result.id_mappings_skipped += 1;
+                }
+            }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("id_mappings", processed, total);
+        }
+        log::debug!("Imported {} id mappings, skipped {}", result.id_mappings_imported, result.id_mappings_skipped);
+    }
+
+    // Import the migration archive. Records are never rewritten once made.
+    if options.import_migration_archive {
+        let total = data.data.migration_archive.len();
+        let mut processed = 0;
+
+        for chunk in data.data.migration_archive.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for entry in chunk {
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO migration_archive (
+                        original_id, original_extension_id, media_type, title, english_name, new_mal_id,
+                        status, error_message, original_media_json, original_children_json, created_at
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP))
+                    "#
+                )
+                .bind(&entry.original_id)
+                .bind(&entry.original_extension_id)
+                .bind(&entry.media_type)
+                .bind(&entry.title)
+                .bind(&entry.english_name)
+                .bind(&entry.new_mal_id)
+                .bind(&entry.status)
+                .bind(&entry.error_message)
+                .bind(&entry.original_media_json)
+                .bind(&entry.original_children_json)
+                .bind(&entry.created_at)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                if inserted > 0 {
+                    result.migration_archive_imported += 1;
+                } else {
+                    result.migration_archive_skipped += 1;
+                }
+            }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("migration_archive", processed, total);
+        }
+        log::debug!(
+            "Imported {} migration archive entries, skipped {}",
+            result.migration_archive_imported, result.migration_archive_skipped
+        );
+    }
+
+    // Import hidden media. Hiding is a flag, so a result already hidden here
+    // stays as it is.
+    if options.import_hidden_media {
+        let total = data.data.hidden_media.len();
+        let mut processed = 0;
+
+        for chunk in data.data.hidden_media.chunks(IMPORT_CHUNK_SIZE) {
+            let mut tx = pool.begin().await?;
+
+            for hidden in chunk {
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO hidden_media (profile_id, media_id, extension_id, title, hidden_at, reason)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#
+                )
+                .bind(profile_id)
+                .bind(&hidden.media_id)
+                .bind(&hidden.extension_id)
+                .bind(&hidden.title)
+                .bind(hidden.hidden_at)
+                .bind(&hidden.reason)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                result.hidden_media_imported += inserted as usize;
+            }
+
+            tx.commit().await?;
+            result.chunks_committed += 1;
+            processed += chunk.len();
+            progress.emit("hidden_media", processed, total);
+        }
+        log::debug!("Imported {} hidden media", result.hidden_media_imported);
+    }
+
+    // Reinstall extensions: from the copy bundled with the app when there is
+    // one (unless the file embeds newer code), otherwise from the embedded
+    // code. Installed extensions the file doesn't have are left alone, even
+    // for ReplaceAll. The caller loads the enabled ones and unloads the ones
+    // left disabled.
+    if options.import_extensions {
+        let total = data.data.extensions.len();
+        let bundled = bundled_extensions();
+        let mut ranked = Vec::new();
+
+        for (processed, exported) in data.data.extensions.iter().enumerate() {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM extensions WHERE id = ?)")
+                .bind(&exported.id)
+                .fetch_one(pool)
+                .await?;
+            if exists && matches!(options.strategy, ImportStrategy::MergeKeepExisting) {
+                result.extensions_skipped += 1;
+                continue;
+            }
+
+            let embedded = exported.code.as_deref().map(Extension::from_code);
+            let extension = match (bundled.iter().find(|e| e.metadata.id == exported.id), embedded) {
+                (Some(bundled), Some(Ok(embedded)))
+                    if is_newer_version(&embedded.metadata.version, &bundled.metadata.version) => Ok(embedded),
+                (Some(bundled), _) => Ok(bundled.clone()),
+                (None, Some(embedded)) => embedded,
+                (None, None) => Err(anyhow::anyhow!("the export doesn't include its code")),
+            };
+            let extension = match extension {
+                Ok(extension) if extension.metadata.id == exported.id => extension,
+                Ok(extension) => {
+                    result.warnings.push(format!(
+                        "Extension {} not restored: its code is for {}",
+                        exported.name, extension.metadata.id
+                    ));
+                    result.extensions_skipped += 1;
+                    continue;
+                }
+                Err(e) => {
+                    result.warnings.push(format!("Extension {} not restored: {}", exported.name, e));
+                    result.extensions_skipped += 1;
+                    continue;
+                }
+            };
+
+            persist_extension(pool, &extension).await?;
+            sqlx::query("UPDATE extensions SET enabled = ?, created_at = ? WHERE id = ?")
+                .bind(exported.enabled)
+                .bind(&exported.created_at)
+                .bind(&exported.id)
+                .execute(pool)
+                .await?;
+            if !exported.enabled {
+                result.extensions_disabled.push(exported.id.clone());
+            }
+            if let Some(priority) = exported.priority {
+                ranked.push((priority, exported.id.clone()));
+            }
+            result.extensions_imported += 1;
+            progress.emit("extensions", processed + 1, total);
+        }
+        if !ranked.is_empty() {
+            restore_source_priority(pool, &ranked).await?;
+        }
+        log::debug!(
+            "Imported {} extensions, skipped {}",
+            result.extensions_imported, result.extensions_skipped
+        );
+    }
+
+    progress.complete();
+
+    log::info!("Data import completed successfully ({} chunks committed)", result.chunks_committed);
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use tempfile::tempdir;
+
+    /// Tracks live heap bytes and their high-water mark per thread for the
+    /// memory test, so tests running in parallel don't count against it.
+    /// `#[tokio::test]` runs the export on the test's own thread.
+    struct CountingAllocator;
+
+    thread_local! {
+        static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+        static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+    }
+
+    fn adjust_live_bytes(delta: isize) {
+        let _ = LIVE_BYTES.try_with(|live| {
+            live.set(live.get() + delta);
+            let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+        });
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                adjust_live_bytes(layout.size() as isize);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            adjust_live_bytes(-(layout.size() as isize));
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    const MEDIA_COUNT: usize = 1_200;
+    const EPISODES_PER_MEDIA: usize = 5;
+
+    /// Populate a database with enough rows to span several import chunks
+    async fn seed_large_fixture(pool: &SqlitePool) {
+        let mut tx = pool.begin().await.unwrap();
+
+        sqlx::query("INSERT INTO library_tags (profile_id, name, color, sort_order) VALUES (1, 'Backlog', '#6366f1', 0)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        for i in 0..MEDIA_COUNT {
+            let media_id = format!("media-{}", i);
+
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'ext', ?, 'anime')")
+                .bind(&media_id)
+                .bind(format!("Title {}", i))
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+
+            let library_id = sqlx::query("INSERT INTO library (profile_id, media_id, status) VALUES (1, ?, 'watching')")
+                .bind(&media_id)
+                .execute(&mut *tx)
+                .await
+                .unwrap()
+                .last_insert_rowid();
+
+            sqlx::query("INSERT INTO library_tag_assignments (library_entry_id, tag_id) SELECT ?, id FROM library_tags WHERE name = 'Backlog'")
+                .bind(library_id)
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+
+            for ep in 1..=EPISODES_PER_MEDIA {
+                sqlx::query(
+                    "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed) VALUES (1, ?, ?, ?, 1440, 1)"
+                )
+                .bind(&media_id)
+                .bind(format!("{}-{}", media_id, ep))
+                .bind(ep as i32)
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+            }
+        }
+
+        tx.commit().await.unwrap();
+    }
+
+    fn expected_chunks(lens: &[usize]) -> usize {
+        lens.iter().map(|len| len.div_ceil(IMPORT_CHUNK_SIZE)).sum()
+    }
+
+    #[tokio::test]
+    async fn test_large_import_is_chunked() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+
+        seed_large_fixture(source.pool()).await;
+
+        let export = export_all_data(source.pool(), "test", None, false, None).await.unwrap();
+        assert_eq!(export.metadata.library_count, MEDIA_COUNT);
+        assert_eq!(export.metadata.watch_history_count, MEDIA_COUNT * EPISODES_PER_MEDIA);
+
+        let tables = &export.data;
+        let chunks = expected_chunks(&[
+            tables.media_cache.len(),
+            tables.library.len(),
+            tables.watch_history.len(),
+            tables.reading_history.len(),
+            tables.library_tags.len(),
+            tables.tag_assignments.len(),
+            tables.app_settings.len(),
+            tables.tracker_mappings.len(),
+            tables.id_mappings.len(),
+            tables.migration_archive.len(),
+            tables.hidden_media.len(),
+        ]);
+
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, export, ImportOptions::default(), None).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.media_cache_imported, MEDIA_COUNT);
+        assert_eq!(result.library_imported, MEDIA_COUNT);
+        assert_eq!(result.watch_history_imported, MEDIA_COUNT * EPISODES_PER_MEDIA);
+        assert_eq!(result.tag_assignments_imported, MEDIA_COUNT);
+        assert_eq!(result.chunks_committed, chunks);
+        assert!(result.chunks_committed >= 12 + 3 + 3 + 3);
+
+        let watch_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM watch_history")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(watch_rows as usize, MEDIA_COUNT * EPISODES_PER_MEDIA);
+    }
+
+    #[tokio::test]
+    async fn test_large_merge_skips_existing_rows() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+
+        seed_large_fixture(db.pool()).await;
+
+        let export = export_all_data(db.pool(), "test", None, false, None).await.unwrap();
+        let result = import_data(db.pool(), DEFAULT_PROFILE_ID, export, ImportOptions::default(), None).await.unwrap();
+
+        assert_eq!(result.library_imported, 0);
+        assert_eq!(result.library_skipped, MEDIA_COUNT);
+        assert_eq!(result.watch_history_skipped, MEDIA_COUNT * EPISODES_PER_MEDIA);
+        assert_eq!(result.tags_skipped, 1);
+    }
+
+    async fn seed_migration_tables(pool: &SqlitePool) {
+        sqlx::query(
+            "INSERT INTO id_mappings (mal_id, allanime_id, media_type, title, match_score) VALUES ('52991', 'aa-frieren', 'anime', 'Frieren', 0.97), ('5114', 'aa-fma', 'anime', 'Fullmetal Alchemist', NULL)"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO migration_archive (original_id, original_extension_id, media_type, title, new_mal_id, status, original_media_json)
+            VALUES ('aa-frieren', 'com.allanime.source', 'anime', 'Frieren', '52991', 'matched', '{"id":"aa-frieren"}'),
+                   ('aa-obscure', 'com.allanime.source', 'anime', 'Obscure OVA', NULL, 'archived', '{"id":"aa-obscure"}')
+            "#
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_id_mappings_and_migration_archive_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        seed_migration_tables(source.pool()).await;
+
+        let path = temp_dir.path().join("backup.otakubak");
+        let metadata = export_to_file(source.pool(), "test", None, false, &path, None).await.unwrap();
+        assert_eq!(metadata.id_mapping_count, 2);
+        assert_eq!(metadata.migration_archive_count, 2);
+
+        // The target already resolved one title differently; that mapping stays
+        sqlx::query("INSERT INTO id_mappings (mal_id, allanime_id, media_type, title) VALUES ('52991', 'aa-frieren-local', 'anime', 'Frieren')")
+            .execute(target.pool())
+            .await
+            .unwrap();
+
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        let options = ImportOptions { strategy: ImportStrategy::MergePreferImport, ..ImportOptions::default() };
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), options, None).await.unwrap();
+        assert_eq!((result.id_mappings_imported, result.id_mappings_skipped), (1, 1));
+        assert_eq!((result.migration_archive_imported, result.migration_archive_skipped), (2, 0));
+
+        let local: String = sqlx::query_scalar("SELECT allanime_id FROM id_mappings WHERE mal_id = '52991'")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(local, "aa-frieren-local");
+        let score: Option<f64> = sqlx::query_scalar("SELECT match_score FROM id_mappings WHERE mal_id = '5114'")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(score, None);
+
+        let archived: (String, Option<String>) = sqlx::query_as(
+            "SELECT status, original_media_json FROM migration_archive WHERE original_id = 'aa-obscure'"
+        )
+        .fetch_one(target.pool())
+        .await
+        .unwrap();
+        assert_eq!(archived, ("archived".to_string(), Some("{\"id\":\"aa-obscure\"}".to_string())));
+
+        // Importing again leaves the archive as it is
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data, ImportOptions::default(), None).await.unwrap();
+        assert_eq!((result.migration_archive_imported, result.migration_archive_skipped), (0, 2));
+
+        // Both tables can be left out
+        let fresh = Database::new(temp_dir.path().join("fresh.db")).await.unwrap();
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        let options = ImportOptions { import_id_mappings: false, import_migration_archive: false, ..ImportOptions::default() };
+        let result = import_data(fresh.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert_eq!(result.id_mappings_imported + result.migration_archive_imported, 0);
+    }
+
+    #[tokio::test]
+    async fn test_replacing_from_a_file_without_migration_tables_keeps_them() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        seed_migration_tables(target.pool()).await;
+
+        let mut data = export_all_data(source.pool(), "test", None, false, None).await.unwrap();
+        data.format_version = "1.0.0".to_string();
+        let options = ImportOptions { strategy: ImportStrategy::ReplaceAll, ..ImportOptions::default() };
+        import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), options.clone(), None).await.unwrap();
+
+        let count = |table: &str| {
+            let sql = format!("SELECT COUNT(*) FROM {}", table);
+            let pool = target.pool().clone();
+            async move { sqlx::query_scalar::<_, i64>(&sql).fetch_one(&pool).await.unwrap() }
+        };
+        assert_eq!(count("id_mappings").await, 2);
+        assert_eq!(count("migration_archive").await, 2);
+
+        // A file that has the tables replaces them, even when they're empty
+        data.format_version = MIGRATION_TABLES_SINCE.to_string();
+        import_data(target.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert_eq!(count("id_mappings").await, 0);
+        assert_eq!(count("migration_archive").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_media_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        crate::database::hidden_media::hide_media(source.pool(), DEFAULT_PROFILE_ID, "52991", None, Some("Frieren"), Some("seen it"))
+            .await
+            .unwrap();
+        crate::database::hidden_media::hide_media(target.pool(), DEFAULT_PROFILE_ID, "52991", None, None, None).await.unwrap();
+
+        let path = temp_dir.path().join("backup.otakubak");
+        let metadata = export_to_file(source.pool(), "test", None, false, &path, None).await.unwrap();
+        assert_eq!(metadata.hidden_media_count, 1);
+
+        // Merging keeps what the target has
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), ImportOptions::default(), None).await.unwrap();
+        assert_eq!(result.hidden_media_imported, 0);
+
+        let options = ImportOptions { strategy: ImportStrategy::ReplaceAll, ..ImportOptions::default() };
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert_eq!(result.hidden_media_imported, 1);
+        let hidden = crate::database::hidden_media::list_hidden_media(target.pool(), DEFAULT_PROFILE_ID).await.unwrap();
+        assert_eq!(hidden[0].reason.as_deref(), Some("seen it"));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_media_is_kept_per_profile_and_by_older_files() {
+        use crate::database::hidden_media::{hide_media, list_hidden_media};
+
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        let other = crate::database::profiles::create_profile(target.pool(), "Other").await.unwrap().id;
+        hide_media(target.pool(), DEFAULT_PROFILE_ID, "52991", None, None, None).await.unwrap();
+        hide_media(target.pool(), other, "21", None, None, None).await.unwrap();
+
+        // A file from before hidden_media existed doesn't clear it
+        let mut data = export_all_data(source.pool(), "test", None, false, None).await.unwrap();
+        data.format_version = "1.1.0".to_string();
+        let options = ImportOptions { strategy: ImportStrategy::ReplaceAll, ..ImportOptions::default() };
+        import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), options.clone(), None).await.unwrap();
+        assert_eq!(list_hidden_media(target.pool(), DEFAULT_PROFILE_ID).await.unwrap().len(), 1);
+
+        // A file that has it replaces the active profile's, not the others'
+        data.format_version = HIDDEN_MEDIA_SINCE.to_string();
+        import_data(target.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert!(list_hidden_media(target.pool(), DEFAULT_PROFILE_ID).await.unwrap().is_empty());
+        assert_eq!(list_hidden_media(target.pool(), other).await.unwrap()[0].media_id, "21");
+    }
+
+    fn fixture_extension(id: &str, name: &str, extension_type: &str) -> Extension {
+        Extension::from_code(&format!(
+            r#"const extensionObject = {{ id: "{}", name: "{}", version: "1.2.0", type: "{}", baseUrl: "https://{}.example.com" }};"#,
+            id, name, extension_type, name.to_lowercase()
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extensions_round_trip_into_a_clean_install() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let anime = fixture_extension("com.example.anime", "Anime", "anime");
+        let manga = fixture_extension("com.example.manga", "Manga", "manga");
+        persist_extension(source.pool(), &anime).await.unwrap();
+        persist_extension(source.pool(), &manga).await.unwrap();
+        sqlx::query("UPDATE extensions SET created_at = '2024-01-01 00:00:00' WHERE id = 'com.example.manga'")
+            .execute(source.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE extensions SET created_at = '2024-02-01 00:00:00', enabled = 0 WHERE id = 'com.example.anime'")
+            .execute(source.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES ('release_source_priority', '[\"com.example.anime\",\"com.example.manga\"]')")
+            .execute(source.pool())
+            .await
+            .unwrap();
+
+        // Without code the export only lists them, and they can't be restored
+        let path = temp_dir.path().join("backup.otakubak");
+        let metadata = export_to_file(source.pool(), "test", None, false, &path, None).await.unwrap();
+        assert_eq!(metadata.extension_count, 2);
+        let listed = crate::backup_file::read_backup_file(&path).unwrap();
+        assert!(listed.data.extensions.iter().all(|e| e.code.is_none()));
+
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        let options = ImportOptions { import_extensions: true, ..ImportOptions::default() };
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, listed, options.clone(), None).await.unwrap();
+        assert_eq!((result.extensions_imported, result.extensions_skipped), (0, 2));
+        assert_eq!(result.warnings.len(), 2);
+
+        // Left out unless asked for
+        export_to_file(source.pool(), "test", None, true, &path, None).await.unwrap();
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), ImportOptions::default(), None).await.unwrap();
+        assert_eq!(result.extensions_imported, 0);
+
+        sqlx::query("UPDATE app_settings SET value = '[\"com.example.other\",\"com.example.manga\"]' WHERE key = 'release_source_priority'")
+            .execute(target.pool())
+            .await
+            .unwrap();
+        let extensions_only = ImportOptions { import_settings: false, ..options.clone() };
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data.clone(), extensions_only, None).await.unwrap();
+        assert_eq!(result.extensions_imported, 2);
+        assert!(result.warnings.is_empty(), "unexpected warnings: {:?}", result.warnings);
+        assert_eq!(result.extensions_disabled, vec!["com.example.anime".to_string()]);
+
+        // Their source priority comes along, ahead of what the file doesn't rank
+        let mut conn = target.pool().acquire().await.unwrap();
+        assert_eq!(
+            fetch_source_priority(&mut conn).await.unwrap(),
+            vec!["com.example.anime", "com.example.manga", "com.example.other"]
+        );
+        drop(conn);
+
+        // Install order and enabled state come along; disabled ones don't load
+        let rows: Vec<(String, bool, String)> = sqlx::query_as("SELECT id, enabled, code FROM extensions ORDER BY created_at")
+            .fetch_all(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("com.example.manga".to_string(), true, manga.code.clone()),
+                ("com.example.anime".to_string(), false, anime.code.clone()),
+            ]
+        );
+        let loaded = crate::extensions::bundled::restore_extensions(target.pool()).await.unwrap();
+        assert_eq!(loaded.iter().map(|e| e.metadata.id.as_str()).collect::<Vec<_>>(), vec!["com.example.manga"]);
+
+        // A merge leaves installed extensions as they are
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert_eq!((result.extensions_imported, result.extensions_skipped), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_bundled_extensions_are_restored_without_their_code() {
+        let temp_dir = tempdir().unwrap();
+        let source = Database::new(temp_dir.path().join("source.db")).await.unwrap();
+        let bundled = bundled_extensions().remove(0);
+        persist_extension(source.pool(), &bundled).await.unwrap();
+
+        let data = export_all_data(source.pool(), "test", None, false, None).await.unwrap();
+        assert!(data.data.extensions[0].code.is_none());
+
+        let target = Database::new(temp_dir.path().join("target.db")).await.unwrap();
+        let options = ImportOptions { import_extensions: true, ..ImportOptions::default() };
+        let result = import_data(target.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert_eq!(result.extensions_imported, 1);
+
+        let code: String = sqlx::query_scalar("SELECT code FROM extensions WHERE id = ?")
+            .bind(&bundled.metadata.id)
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(code, bundled.code);
+    }
+
+    #[tokio::test]
+    async fn test_older_export_without_migration_tables_imports_cleanly() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        seed_migration_tables(db.pool()).await;
+
+        let json = serde_json::json!({
+            "format_version": "1.0.0",
+            "app_version": "1.4.0",
+            "exported_at": "2024-06-01T12:00:00Z",
+            "data": {
+                "library": [],
+                "watch_history": [],
+                "reading_history": [],
+                "library_tags": [],
+                "tag_assignments": [],
+                "app_settings": [],
+                "media_cache": [],
+                "tracker_mappings": []
+            },
+            "metadata": {
+                "library_count": 0,
+                "watch_history_count": 0,
+                "reading_history_count": 0,
+                "tag_count": 0,
+                "media_cache_count": 0
+            }
+        });
+        let data: ExportData = serde_json::from_value(json).unwrap();
+        assert!(data.data.id_mappings.is_empty());
+        assert_eq!(data.metadata.migration_archive_count, 0);
+
+        // Older options payloads don't have the new flags either
+        let options: ImportOptions = serde_json::from_value(serde_json::json!({
+            "strategy": "merge_keep_existing",
+            "import_library": true,
+            "import_watch_history": true,
+            "import_reading_history": true,
+            "import_tags": true,
+            "import_settings": true,
+            "import_media_cache": true,
+            "import_tracker_mappings": true
+        }))
+        .unwrap();
+        assert!(options.import_id_mappings && options.import_migration_archive);
+
+        let result = import_data(db.pool(), DEFAULT_PROFILE_ID, data, options, None).await.unwrap();
+        assert!(result.warnings.is_empty(), "unexpected warnings: {:?}", result.warnings);
+
+        // A merge of a file without the tables keeps what's here
+        let mappings: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM id_mappings").fetch_one(db.pool()).await.unwrap();
+        let archive: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM migration_archive").fetch_one(db.pool()).await.unwrap();
+        assert_eq!((mappings, archive), (2, 2));
+
+        assert!(!is_compatible_format("2.0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_export_matches_in_memory_export() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        seed_large_fixture(db.pool()).await;
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES ('theme', 'dark \"oled\"')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let mut export = export_all_data(db.pool(), "test", None, false, None).await.unwrap();
+        export.exported_at = "2024-06-01T12:00:00+00:00".to_string();
+        let expected = serde_json::to_vec_pretty(&export).unwrap();
+
+        let progress = ProgressReporter::new(None, DataTransferPhase::Export);
+        let mut streamed = Vec::new();
+        let metadata = write_export(db.pool(), &mut streamed, "test", &export.exported_at, None, false, &progress)
+            .await
+            .unwrap();
+
+        assert_eq!(String::from_utf8(streamed).unwrap(), String::from_utf8(expected).unwrap());
+        assert_eq!(metadata.library_count, MEDIA_COUNT);
+        assert_eq!(metadata.watch_history_count, MEDIA_COUNT * EPISODES_PER_MEDIA);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_export_of_empty_profile_round_trips() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let path = temp_dir.path().join("backup.otakubak");
+
+        let metadata = export_to_file(db.pool(), "test", Some(1), false, &path, None).await.unwrap();
+        assert_eq!(metadata.library_count, 0);
+
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        assert_eq!(data.metadata.profile_id, Some(1));
+        assert!(data.data.library.is_empty());
+        assert!(!temp_dir.path().join("backup.otakubak.partial").exists());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_export_memory_stays_bounded() {
+        const HISTORY_ROWS: usize = 100_000;
+        // Holding 100k watch history rows takes well over 30 MB; a streamed
+        // export only ever holds one chunk
+        const ALLOCATION_BUDGET: usize = 8 * 1024 * 1024;
+
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Long Runner', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < ?)
+            INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed)
+            SELECT 1, 'm1', 'episode-' || n, n, 1380.5, 1440, 1 FROM seq
+            "#
+        )
+        .bind(HISTORY_ROWS as i64)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let path = temp_dir.path().join("backup.otakubak");
+        let baseline = LIVE_BYTES.with(Cell::get);
+        PEAK_BYTES.with(|peak| peak.set(baseline));
+
+        let metadata = export_to_file(pool, "test", None, false, &path, None).await.unwrap();
+
+        let peak = PEAK_BYTES.with(Cell::get).saturating_sub(baseline).max(0) as usize;
+        assert_eq!(metadata.watch_history_count, HISTORY_ROWS);
+        assert!(
+            peak < ALLOCATION_BUDGET,
+            "streamed export peaked at {} bytes above baseline",
+            peak
+        );
+
+        let data = crate::backup_file::read_backup_file(&path).unwrap();
+        assert_eq!(data.data.watch_history.len(), HISTORY_ROWS);
+    }
+}
@@ -0,0 +1,475 @@
+// Genre Normalization
+//
+// Extensions return the same genre under different names ("Sci-Fi", "SciFi",
+// "Science Fiction", localized names), which splits library filters and
+// recommendations. genre_normalization maps a raw genre's key (lowercase,
+// letters and digits only) to a canonical name. media.genres holds canonical
+// genres; media.genres_raw keeps what the source returned.
+//
+// The map is loaded once per database and dropped whenever a mapping changes.
+// Discover tags merged under one canonical genre remember every slug they
+// stand for, and a genre filter on a merged tag asks the extension for each
+// of them (genre_filter_variants).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::extensions::types::{Tag, TagsResult};
+
+/// A raw genre key and the canonical genre it maps to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenreMapping {
+    pub raw_key: String,
+    pub canonical: String,
+    /// Shipped with the app rather than added by the user
+    pub is_default: bool,
+}
+
+/// Lookup table from raw key to canonical genre
+pub type GenreMap = HashMap<String, String>;
+
+/// Most variants of one genre filter sent to an extension; filters combining
+/// several merged genres beyond this only use each tag's own slug
+const MAX_FILTER_VARIANTS: usize = 4;
+
+/// Loaded genre maps by database file
+static GENRE_MAPS: LazyLock<Mutex<HashMap<PathBuf, Arc<GenreMap>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Per extension, the slugs merged into each discover tag's slug
+static MERGED_SLUGS: LazyLock<Mutex<HashMap<String, HashMap<String, Vec<String>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Lookup key for a raw genre: lowercase, letters and digits only, so
+/// "Sci-Fi", "sci fi" and "SCIFI" share one key
+pub fn genre_key(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Canonical name for one genre; unmapped genres keep their own (trimmed) name
+pub fn normalize_genre(raw: &str, map: &GenreMap) -> String {
+    map.get(&genre_key(raw))
+        .cloned()
+        .unwrap_or_else(|| raw.trim().to_string())
+}
+
+/// Canonical genres in their original order, without duplicates or blanks
+pub fn normalize_genres(raw: &[String], map: &GenreMap) -> Vec<String> {
+    let mut genres: Vec<String> = Vec::new();
+    for genre in raw.iter().filter(|g| !g.trim().is_empty()) {
+        let canonical = normalize_genre(genre, map);
+        if !genres.contains(&canonical) {
+            genres.push(canonical);
+        }
+    }
+    genres
+}
+
+/// Normalize a JSON array of genres (media.genres format). Values that
+/// aren't a JSON string array are returned unchanged.
+pub fn normalize_genres_json(raw_json: &str, map: &GenreMap) -> String {
+    match serde_json::from_str::<Vec<String>>(raw_json) {
+        Ok(raw) => serde_json::to_string(&normalize_genres(&raw, map)).unwrap_or_else(|_| raw_json.to_string()),
+        Err(_) => raw_json.to_string(),
+    }
+}
+
+pub(super) fn is_empty_list(json: &str) -> bool {
+    serde_json::from_str::<Vec<String>>(json).is_ok_and(|list| list.is_empty())
+}
+
+/// Normalize an extension's genre tags for the discover filters. Tags that
+/// normalize to the same genre are merged: the first one's slug is kept,
+/// the other slugs go to merged_slugs (the extension only understands its
+/// own slugs) and the counts are added up.
+pub fn normalize_tags(mut tags: TagsResult, map: &GenreMap) -> TagsResult {
+    let mut merged: Vec<Tag> = Vec::new();
+    for tag in tags.genres {
+        let canonical = normalize_genre(&tag.name, map);
+        match merged.iter_mut().find(|t| t.name == canonical) {
+            Some(existing) => {
+                existing.count += tag.count;
+                for slug in std::iter::once(tag.slug).chain(tag.merged_slugs) {
+                    if slug != existing.slug && !existing.merged_slugs.contains(&slug) {
+                        existing.merged_slugs.push(slug);
+                    }
+                }
+            }
+            None => merged.push(Tag { name: canonical, ..tag }),
+        }
+    }
+    tags.genres = merged;
+    tags
+}
+
+/// Remember which slugs an extension's normalized tags stand for, for
+/// genre_filter_variants
+pub fn remember_merged_slugs(extension_id: &str, tags: &TagsResult) {
+    let mut merged = MERGED_SLUGS.lock().unwrap();
+    let slugs = merged.entry(extension_id.to_string()).or_default();
+    for tag in tags.genres.iter().filter(|t| !t.merged_slugs.is_empty()) {
+        slugs.insert(tag.slug.clone(), tag.merged_slugs.clone());
+    }
+}
+
+/// The genre filters to send to an extension for `genres`: the filter as
+/// given, plus one with each slug merged into a tag swapped in. Results of
+/// all variants together are what the merged genre covers.
+pub fn genre_filter_variants(extension_id: &str, genres: &[String]) -> Vec<Vec<String>> {
+    let merged = MERGED_SLUGS.lock().unwrap();
+    let Some(slugs) = merged.get(extension_id) else {
+        return vec![genres.to_vec()];
+    };
+
+    let mut variants = vec![Vec::new()];
+    for genre in genres {
+        let alternatives: Vec<&String> = std::iter::once(genre)
+            .chain(slugs.get(genre).into_iter().flatten())
+            .collect();
+        if variants.len() * alternatives.len() > MAX_FILTER_VARIANTS {
+            return vec![genres.to_vec()];
+        }
+        variants = variants
+            .iter()
+            .flat_map(|variant| {
+                alternatives.iter().map(move |slug| {
+                    let mut variant = variant.clone();
+                    variant.push((*slug).clone());
+                    variant
+                })
+            })
+            .collect();
+    }
+    variants
+}
+
+/// Every mapping as a lookup table, loaded once per database
+pub async fn load_genre_map(pool: &SqlitePool) -> Result<Arc<GenreMap>> {
+    let db_file = pool.connect_options().get_filename().to_path_buf();
+    if let Some(map) = GENRE_MAPS.lock().unwrap().get(&db_file) {
+        return Ok(map.clone());
+    }
+
+    let rows = sqlx::query("SELECT raw_key, canonical FROM genre_normalization")
+        .fetch_all(pool)
+        .await?;
+    let map: Arc<GenreMap> = Arc::new(
+        rows.iter()
+            .map(|row| (row.get("raw_key"), row.get("canonical")))
+            .collect(),
+    );
+
+    GENRE_MAPS.lock().unwrap().insert(db_file, map.clone());
+    Ok(map)
+}
+
+/// Drop the loaded map after the mappings changed
+fn invalidate_genre_map(pool: &SqlitePool) {
+    GENRE_MAPS
+        .lock()
+        .unwrap()
+        .remove(pool.connect_options().get_filename());
+}
+
+/// Every mapping, grouped by canonical genre
+pub async fn list_genre_mappings(pool: &SqlitePool) -> Result<Vec<GenreMapping>> {
+    let rows = sqlx::query(
+        "SELECT raw_key, canonical, is_default FROM genre_normalization ORDER BY canonical, raw_key",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| GenreMapping {
+            raw_key: row.get("raw_key"),
+            canonical: row.get("canonical"),
+            is_default: row.get::<i64, _>("is_default") != 0,
+        })
+        .collect())
+}
+
+/// Map a raw genre to a canonical one, replacing any existing mapping for it.
+/// Run renormalize_media_genres afterwards to apply it to stored media.
+pub async fn set_genre_mapping(pool: &SqlitePool, raw: &str, canonical: &str) -> Result<GenreMapping> {
+    let raw_key = genre_key(raw);
+    let canonical = canonical.trim();
+    if raw_key.is_empty() || canonical.is_empty() {
+        anyhow::bail!("Genre names can't be empty");
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO genre_normalization (raw_key, canonical, is_default, updated_at)
+        VALUES (?, ?, 0, CURRENT_TIMESTAMP)
+        ON CONFLICT(raw_key) DO UPDATE SET
+            canonical = excluded.canonical,
+            is_default = 0,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(&raw_key)
+    .bind(canonical)
+    .execute(pool)
+    .await?;
+    invalidate_genre_map(pool);
+
+    Ok(GenreMapping {
+        raw_key,
+        canonical: canonical.to_string(),
+        is_default: false,
+    })
+}
+
+/// Remove the mapping for a raw genre, returning whether there was one
+pub async fn delete_genre_mapping(pool: &SqlitePool, raw: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM genre_normalization WHERE raw_key = ?")
+        .bind(genre_key(raw))
+        .execute(pool)
+        .await?;
+    invalidate_genre_map(pool);
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Re-apply the current mappings to every stored media row, returning how
+/// many rows changed. genres_raw is filled from genres for rows saved before
+/// normalization existed, and is what gets normalized, so changing a mapping
+/// and running this again gives the same result as saving the rows afresh.
+pub async fn renormalize_media_genres(pool: &SqlitePool) -> Result<u64> {
+    let map = load_genre_map(pool).await?;
+
+    let rows = sqlx::query("SELECT id, genres, genres_raw FROM media WHERE genres IS NOT NULL OR genres_raw IS NOT NULL")
+        .fetch_all(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    let mut changed = 0;
+    for row in rows {
+        let id: String = row.get("id");
+        let genres: Option<String> = row.get("genres");
+        let genres_raw: Option<String> = row.get("genres_raw");
+
+        // Genres filled in later (e.g. by Jikan enrichment) leave an empty
+        // genres_raw behind; those rows are normalized from genres instead
+        let raw = match (&genres_raw, &genres) {
+            (Some(raw), _) if !is_empty_list(raw) => raw.clone(),
+            (_, Some(genres)) => genres.clone(),
+            (Some(raw), None) => raw.clone(),
+            (None, None) => continue,
+        };
+        let normalized = normalize_genres_json(&raw, &map);
+
+        if genres.as_deref() != Some(normalized.as_str()) || genres_raw.as_deref() != Some(raw.as_str()) {
+            sqlx::query("UPDATE media SET genres = ?, genres_raw = ? WHERE id = ?")
+                .bind(&normalized)
+                .bind(&raw)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            changed += 1;
+        }
+    }
+    tx.commit().await?;
+
+    if changed > 0 {
+        log::info!("Renormalized genres of {} media", changed);
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::media::{save_media, MediaEntry};
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    fn map(pairs: &[(&str, &str)]) -> GenreMap {
+        pairs.iter().map(|(k, v)| (genre_key(k), v.to_string())).collect()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn tag(name: &str, slug: &str, count: u32) -> Tag {
+        Tag {
+            id: None,
+            name: name.to_string(),
+            slug: slug.to_string(),
+            count,
+            thumbnail: None,
+            merged_slugs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn variants_share_a_key() {
+        assert_eq!(genre_key("Sci-Fi"), "scifi");
+        assert_eq!(genre_key(" sci fi "), "scifi");
+        assert_eq!(genre_key("Slice of Life"), "sliceoflife");
+        assert_eq!(genre_key("Ciencia Ficción"), "cienciaficción");
+    }
+
+    #[test]
+    fn genres_are_mapped_deduplicated_and_kept_in_order() {
+        let map = map(&[("SciFi", "Sci-Fi"), ("Science Fiction", "Sci-Fi"), ("Shonen", "Shounen")]);
+
+        let raw = strings(&["Science Fiction", "Action", "SciFi", "shonen", " ", "Unmapped Genre "]);
+        assert_eq!(normalize_genres(&raw, &map), strings(&["Sci-Fi", "Action", "Shounen", "Unmapped Genre"]));
+
+        assert_eq!(normalize_genres_json(r#"["Sci Fi","Drama"]"#, &map), r#"["Sci-Fi","Drama"]"#);
+        // Not a genre list: left alone
+        assert_eq!(normalize_genres_json("not json", &map), "not json");
+    }
+
+    #[test]
+    fn discover_tags_are_merged_under_the_canonical_name() {
+        let map = map(&[("SciFi", "Sci-Fi"), ("Science Fiction", "Sci-Fi")]);
+        let tags = TagsResult {
+            genres: vec![tag("SciFi", "scifi", 10), tag("Action", "action", 7), tag("Science Fiction", "science-fiction", 5)],
+            studios: vec![tag("Bones", "bones", 3)],
+            has_next_page: false,
+        };
+
+        let normalized = normalize_tags(tags, &map);
+        assert_eq!(normalized.genres.len(), 2);
+        assert_eq!(normalized.genres[0].name, "Sci-Fi");
+        assert_eq!(normalized.genres[0].slug, "scifi");
+        assert_eq!(normalized.genres[0].count, 15);
+        assert_eq!(normalized.genres[0].merged_slugs, strings(&["science-fiction"]));
+        assert!(normalized.genres[1].merged_slugs.is_empty());
+        assert_eq!(normalized.studios[0].name, "Bones");
+    }
+
+    #[test]
+    fn filters_on_merged_tags_ask_for_every_slug() {
+        let extension_id = "test.genres.variants";
+        let map = map(&[("SciFi", "Sci-Fi"), ("Science Fiction", "Sci-Fi"), ("Mecha", "Mecha"), ("Robots", "Mecha")]);
+        let tags = TagsResult {
+            genres: vec![
+                tag("SciFi", "scifi", 10),
+                tag("Science Fiction", "science-fiction", 5),
+                tag("Mecha", "mecha", 4),
+                tag("Robots", "robots", 2),
+                tag("Action", "action", 7),
+            ],
+            studios: vec![],
+            has_next_page: false,
+        };
+        remember_merged_slugs(extension_id, &normalize_tags(tags, &map));
+
+        assert_eq!(genre_filter_variants(extension_id, &strings(&["action"])), vec![strings(&["action"])]);
+        assert_eq!(
+            genre_filter_variants(extension_id, &strings(&["scifi", "action"])),
+            vec![strings(&["scifi", "action"]), strings(&["science-fiction", "action"])]
+        );
+        assert_eq!(genre_filter_variants(extension_id, &strings(&["scifi", "mecha"])).len(), 4);
+        // Other extensions' slugs mean nothing here
+        assert_eq!(genre_filter_variants("test.genres.other", &strings(&["scifi"])), vec![strings(&["scifi"])]);
+    }
+
+    fn media(id: &str, genres: &str) -> MediaEntry {
+        MediaEntry {
+            id: id.to_string(),
+            extension_id: "ext".to_string(),
+            title: id.to_string(),
+            english_name: None,
+            native_name: None,
+            description: None,
+            cover_url: None,
+            banner_url: None,
+            trailer_url: None,
+            media_type: "anime".to_string(),
+            content_type: None,
+            status: None,
+            year: None,
+            rating: None,
+            episode_count: None,
+            episode_duration: None,
+            season_quarter: None,
+            season_year: None,
+            aired_start_year: None,
+            aired_start_month: None,
+            aired_start_date: None,
+            genres: Some(genres.to_string()),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    async fn stored_genres(pool: &SqlitePool, id: &str) -> (Option<String>, Option<String>) {
+        let row = sqlx::query("SELECT genres, genres_raw FROM media WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (row.get("genres"), row.get("genres_raw"))
+    }
+
+    #[tokio::test]
+    async fn saved_media_gets_canonical_genres_and_keeps_the_raw_ones() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        // Shipped defaults
+        save_media(pool, &media("m1", r#"["Science Fiction","Shonen","Slice-of-Life"]"#)).await.unwrap();
+        let (genres, raw) = stored_genres(pool, "m1").await;
+        assert_eq!(genres.as_deref(), Some(r#"["Sci-Fi","Shounen","Slice of Life"]"#));
+        assert_eq!(raw.as_deref(), Some(r#"["Science Fiction","Shonen","Slice-of-Life"]"#));
+    }
+
+    #[tokio::test]
+    async fn backfill_applies_new_mappings_to_existing_rows() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        // Saved before normalization existed: no genres_raw
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, genres) VALUES ('old', 'ext', 'Old', 'anime', ?)")
+            .bind(r#"["SciFi","Mecha"]"#)
+            .execute(pool)
+            .await
+            .unwrap();
+        save_media(pool, &media("new", r#"["Robots"]"#)).await.unwrap();
+
+        // Loaded (and cached) before the mapping exists
+        assert!(!load_genre_map(pool).await.unwrap().contains_key("robots"));
+        set_genre_mapping(pool, "robots", "Mecha").await.unwrap();
+        assert_eq!(renormalize_media_genres(pool).await.unwrap(), 2);
+
+        let (genres, raw) = stored_genres(pool, "old").await;
+        assert_eq!(genres.as_deref(), Some(r#"["Sci-Fi","Mecha"]"#));
+        assert_eq!(raw.as_deref(), Some(r#"["SciFi","Mecha"]"#));
+        assert_eq!(stored_genres(pool, "new").await.0.as_deref(), Some(r#"["Mecha"]"#));
+
+        // Saved without genres, then filled in by enrichment
+        save_media(pool, &media("enriched", "[]")).await.unwrap();
+        sqlx::query("UPDATE media SET genres = ? WHERE id = 'enriched'")
+            .bind(r#"["Action","Science Fiction"]"#)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(renormalize_media_genres(pool).await.unwrap(), 1);
+        assert_eq!(stored_genres(pool, "enriched").await.0.as_deref(), Some(r#"["Action","Sci-Fi"]"#));
+
+        // Nothing left to change
+        assert_eq!(renormalize_media_genres(pool).await.unwrap(), 0);
+
+        // Removing the mapping brings the raw genre back
+        assert!(delete_genre_mapping(pool, "Robots").await.unwrap());
+        assert_eq!(renormalize_media_genres(pool).await.unwrap(), 1);
+        assert_eq!(stored_genres(pool, "new").await.0.as_deref(), Some(r#"["Robots"]"#));
+
+        let mappings = list_genre_mappings(pool).await.unwrap();
+        assert!(mappings.iter().any(|m| m.raw_key == "sciencefiction" && m.canonical == "Sci-Fi" && m.is_default));
+    }
+}
@@ -0,0 +1,415 @@
+// Hidden Media Module
+//
+// "Not interested": results a profile hid from home, discover, search and
+// recommendations. Like the library, hidden results belong to a profile.
+// Listings are filtered in the command layer after they
+// come back from the extension, Jikan or a cache, so cached listings stay
+// the same for everyone and unhiding shows an item again straight away.
+//
+// The same filtering keeps out results rated above the active profile's
+// age rating limit (see age_rating.rs).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+
+use super::age_rating::{self, AgeRatingLimit};
+use crate::extensions::types::{HomeContent, SearchResult, SearchResults, SeasonResults};
+
+/// A hidden result (hidden_media table)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HiddenMedia {
+    /// Id the listings show: a media table id or an extension's source id
+    pub media_id: String,
+    pub extension_id: Option<String>,
+    pub title: Option<String>,
+    /// Unix timestamp (ms)
+    pub hidden_at: i64,
+    pub reason: Option<String>,
+}
+
+/// Hide a result for a profile; hiding it again updates the reason
+pub async fn hide_media(
+    pool: &SqlitePool,
+    profile_id: i64,
+    media_id: &str,
+    extension_id: Option<&str>,
+    title: Option<&str>,
+    reason: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO hidden_media (profile_id, media_id, extension_id, title, hidden_at, reason)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(profile_id, media_id) DO UPDATE SET
+            extension_id = COALESCE(excluded.extension_id, extension_id),
+            title = COALESCE(excluded.title, title),
+            reason = excluded.reason
+        "#
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .bind(extension_id)
+    .bind(title)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Show a result again. Returns false if it wasn't hidden.
+pub async fn unhide_media(pool: &SqlitePool, profile_id: i64, media_id: &str) -> Result<bool> {
+    let removed = sqlx::query("DELETE FROM hidden_media WHERE profile_id = ? AND media_id = ?")
+        .bind(profile_id)
+        .bind(media_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(removed > 0)
+}
+
+/// Every result a profile hid, most recently hidden first
+pub async fn list_hidden_media(pool: &SqlitePool, profile_id: i64) -> Result<Vec<HiddenMedia>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT media_id, extension_id, title, hidden_at, reason FROM hidden_media
+        WHERE profile_id = ?
+        ORDER BY hidden_at DESC, media_id ASC
+        "#
+    )
+    .bind(profile_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|r| HiddenMedia {
+        media_id: r.get("media_id"),
+        extension_id: r.get("extension_id"),
+        title: r.get("title"),
+        hidden_at: r.get("hidden_at"),
+        reason: r.get("reason"),
+    }).collect())
+}
+
+/// Ids of hidden results and the active profile's age rating limit, for
+/// filtering listings
+#[derive(Debug, Default)]
+pub struct HiddenSet {
+    ids: HashSet<String>,
+    age_limit: AgeRatingLimit,
+    /// Media rows whose stored rating the limit doesn't allow, for results
+    /// that come without a rating of their own
+    blocked: HashSet<String>,
+}
+
+impl HiddenSet {
+    /// Load the profile's hidden ids. A listing is never failed over this:
+    /// on a database error nothing is hidden.
+    pub async fn load(pool: &SqlitePool, profile_id: i64) -> Self {
+        let ids = sqlx::query_scalar::<_, String>("SELECT media_id FROM hidden_media WHERE profile_id = ?")
+            .bind(profile_id)
+            .fetch_all(pool)
+            .await;
+        let ids = match ids {
+            Ok(ids) => ids.into_iter().collect(),
+            Err(e) => {
+                log::warn!("Failed to load hidden media: {}", e);
+                HashSet::new()
+            }
+        };
+        let age_limit = age_rating::current_limit(pool, profile_id).await;
+        let blocked = age_rating::blocked_media_ids(pool, &age_limit).await.unwrap_or_else(|e| {
+            log::warn!("Failed to load media above the age rating limit: {}", e);
+            HashSet::new()
+        });
+        Self { ids, age_limit, blocked }
+    }
+
+    pub fn contains(&self, media_id: &str) -> bool {
+        self.ids.contains(media_id)
+    }
+
+    /// Whether a result is kept out: hidden, or rated above the limit by
+    /// the source or by its media row
+    pub fn hides(&self, item: &SearchResult) -> bool {
+        self.contains(&item.id) || self.blocked.contains(&item.id) || !self.age_limit.allows(item.age_rating.as_deref())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && !self.age_limit.is_active()
+    }
+
+    /// Remove hidden results from a listing
+    pub fn filter<L: Listing + ?Sized>(&self, listing: &mut L) {
+        if !self.is_empty() {
+            listing.remove_hidden(self);
+        }
+    }
+
+    /// Remove hidden results from a cached listing (JSON written by the
+    /// frontend): objects with a hidden "id" and a "title" are dropped from
+    /// arrays and nulled elsewhere. Data that isn't JSON is left as is.
+    pub fn filter_json(&self, data: &mut String) {
+        if self.is_empty() {
+            return;
+        }
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(data) else {
+            return;
+        };
+        if self.remove_from_value(&mut value) {
+            if let Ok(filtered) = serde_json::to_string(&value) {
+                *data = filtered;
+            }
+        }
+    }
+
+    /// Returns whether anything was removed
+    fn remove_from_value(&self, value: &mut serde_json::Value) -> bool {
+        use serde_json::Value;
+
+        let is_hidden = |item: &Value| {
+            let rating = item.get("age_rating").or_else(|| item.get("ageRating")).and_then(Value::as_str);
+            item.get("title").is_some_and(Value::is_string)
+                && item.get("id").and_then(Value::as_str).is_some_and(|id| {
+                    self.contains(id) || self.blocked.contains(id) || !self.age_limit.allows(rating)
+                })
+        };
+
+        match value {
+            Value::Array(items) => {
+                let before = items.len();
+                items.retain(|item| !is_hidden(item));
+                let mut removed = items.len() != before;
+                for item in items {
+                    removed |= self.remove_from_value(item);
+                }
+                removed
+            }
+            Value::Object(fields) => {
+                let mut removed = false;
+                for field in fields.values_mut() {
+                    if is_hidden(field) {
+                        *field = Value::Null;
+                        removed = true;
+                    } else {
+                        removed |= self.remove_from_value(field);
+                    }
+                }
+                removed
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Something a listing command returns or emits
+pub trait Listing {
+    fn remove_hidden(&mut self, hidden: &HiddenSet);
+}
+
+impl Listing for Vec<SearchResult> {
+    fn remove_hidden(&mut self, hidden: &HiddenSet) {
+        self.retain(|item| !hidden.hides(item));
+    }
+}
+
+impl Listing for SearchResults {
+    fn remove_hidden(&mut self, hidden: &HiddenSet) {
+        self.results.remove_hidden(hidden);
+    }
+}
+
+impl Listing for SeasonResults {
+    fn remove_hidden(&mut self, hidden: &HiddenSet) {
+        self.results.remove_hidden(hidden);
+    }
+}
+
+impl Listing for HomeContent {
+    fn remove_hidden(&mut self, hidden: &HiddenSet) {
+        for category in &mut self.categories {
+            category.items.remove_hidden(hidden);
+        }
+        // A hidden featured item gives way to the first one left
+        if self.featured.as_ref().is_some_and(|f| hidden.hides(f)) {
+            self.featured = self.categories.iter().find_map(|c| c.items.first().cloned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::recommendations::{get_content_recommendations, get_similar_to_watched};
+    use crate::database::Database;
+    use crate::extensions::types::HomeCategory;
+
+    fn result(id: &str) -> SearchResult {
+        serde_json::from_value(serde_json::json!({ "id": id, "title": id })).unwrap()
+    }
+
+    fn ids(items: &[SearchResult]) -> Vec<&str> {
+        items.iter().map(|i| i.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn hide_unhide_and_list() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        hide_media(pool, DEFAULT_PROFILE_ID, "a1", Some("com.allanime.source"), Some("Show A"), None).await.unwrap();
+        hide_media(pool, DEFAULT_PROFILE_ID, "a1", None, None, Some("seen it")).await.unwrap();
+
+        let hidden = list_hidden_media(pool, DEFAULT_PROFILE_ID).await.unwrap();
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].extension_id.as_deref(), Some("com.allanime.source"));
+        assert_eq!(hidden[0].title.as_deref(), Some("Show A"));
+        assert_eq!(hidden[0].reason.as_deref(), Some("seen it"));
+
+        // Other profiles still see it
+        let other = crate::database::profiles::create_profile(pool, "Other").await.unwrap().id;
+        assert!(HiddenSet::load(pool, DEFAULT_PROFILE_ID).await.contains("a1"));
+        assert!(!HiddenSet::load(pool, other).await.contains("a1"));
+        assert!(list_hidden_media(pool, other).await.unwrap().is_empty());
+        assert!(!unhide_media(pool, other, "a1").await.unwrap());
+
+        assert!(unhide_media(pool, DEFAULT_PROFILE_ID, "a1").await.unwrap());
+        assert!(!unhide_media(pool, DEFAULT_PROFILE_ID, "a1").await.unwrap());
+        assert!(!HiddenSet::load(pool, DEFAULT_PROFILE_ID).await.contains("a1"));
+    }
+
+    #[tokio::test]
+    async fn hidden_ids_never_appear_in_any_listing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        // Listings from extensions, Jikan and caches
+        let mut search = SearchResults { results: vec![result("keep"), result("hide")], has_next_page: false };
+        let mut season = SeasonResults {
+            results: vec![result("hide"), result("keep")],
+            has_next_page: true,
+            season: "fall".to_string(),
+            year: 2026,
+        };
+        let mut home = HomeContent {
+            featured: Some(result("hide")),
+            categories: vec![
+                HomeCategory { id: "trending".to_string(), title: "Trending Now".to_string(), items: vec![result("hide")] },
+                HomeCategory { id: "top-rated".to_string(), title: "Top Rated".to_string(), items: vec![result("keep"), result("hide")] },
+            ],
+        };
+        let mut streamed = vec![result("hide"), result("keep")];
+        let mut cached = serde_json::to_string(&vec![result("keep"), result("hide")]).unwrap();
+        let mut cached_home = serde_json::to_string(&home).unwrap();
+
+        // The local recommendation engine: a watched favourite, and two
+        // candidates in the media cache sharing its genres
+        sqlx::query(
+            r#"
+            INSERT INTO media (id, extension_id, title, media_type, genres, rating) VALUES
+                ('fav', 'ext', 'Favourite', 'anime', '["Action","Drama","Fantasy"]', 9.0),
+                ('keep', 'ext', 'Keep', 'anime', '["Action","Drama","Fantasy"]', 8.0),
+                ('hide', 'ext', 'Hide', 'anime', '["Action","Drama","Fantasy"]', 8.5),
+                ('other', 'ext', 'Other', 'anime', '["Comedy"]', 7.0)
+            "#
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO library (media_id, status, score, profile_id) VALUES ('fav', 'completed', 10, 1)")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed) VALUES (1, 'fav', 'fav-1', 1, 1440, 1)"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        hide_media(pool, DEFAULT_PROFILE_ID, "hide", None, Some("Hide"), None).await.unwrap();
+        let hidden = HiddenSet::load(pool, DEFAULT_PROFILE_ID).await;
+
+        hidden.filter(&mut search);
+        hidden.filter(&mut season);
+        hidden.filter(&mut home);
+        hidden.filter(&mut streamed);
+        hidden.filter_json(&mut cached);
+        hidden.filter_json(&mut cached_home);
+        assert_eq!(ids(&search.results), vec!["keep"]);
+        assert_eq!(ids(&season.results), vec!["keep"]);
+        assert_eq!(ids(&streamed), vec!["keep"]);
+        assert_eq!(home.featured.as_ref().map(|f| f.id.as_str()), Some("keep"));
+        assert!(home.categories.iter().all(|c| !c.items.iter().any(|i| i.id == "hide")));
+        let cached: Vec<SearchResult> = serde_json::from_str(&cached).unwrap();
+        assert_eq!(ids(&cached), vec!["keep"]);
+        let cached_home: HomeContent = serde_json::from_str(&cached_home).unwrap();
+        assert!(cached_home.featured.is_none());
+        assert_eq!(cached_home.categories.len(), 2);
+        assert!(cached_home.categories.iter().all(|c| !c.items.iter().any(|i| i.id == "hide")));
+
+        let recommended = get_content_recommendations(pool, DEFAULT_PROFILE_ID, 10).await.unwrap();
+        assert_eq!(recommended.iter().map(|r| r.media.id.as_str()).collect::<Vec<_>>(), vec!["keep"]);
+        let similar = get_similar_to_watched(pool, DEFAULT_PROFILE_ID, 10).await.unwrap();
+        assert!(!similar.is_empty());
+        assert!(similar.iter().flat_map(|g| &g.recommendations).all(|r| r.media.id != "hide"));
+
+        // Unhiding shows it again
+        unhide_media(pool, DEFAULT_PROFILE_ID, "hide").await.unwrap();
+        let recommended = get_content_recommendations(pool, DEFAULT_PROFILE_ID, 10).await.unwrap();
+        assert!(recommended.iter().any(|r| r.media.id == "hide"));
+    }
+
+    #[tokio::test]
+    async fn results_above_the_age_rating_limit_are_filtered() {
+        use crate::database::age_rating::{set_age_rating_limit, AgeRating};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let rated = |id: &str, rating: Option<&str>| -> SearchResult {
+            serde_json::from_value(serde_json::json!({ "id": id, "title": id, "age_rating": rating })).unwrap()
+        };
+        let listing = || {
+            vec![
+                rated("family", Some("G - All Ages")),
+                rated("teen", Some("PG-13 - Teens 13 or older")),
+                rated("mature", Some("R+ - Mild Nudity")),
+                rated("unrated", None),
+                rated("known", None),
+            ]
+        };
+        // The source gives no rating, but the media row knows it
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('known', 'jikan', 'known', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        assert!(crate::database::age_rating::record_rating(pool, "known", "Rx - Hentai").await.unwrap());
+
+        // No limit: nothing is filtered
+        let mut all = listing();
+        HiddenSet::load(pool, DEFAULT_PROFILE_ID).await.filter(&mut all);
+        assert_eq!(all.len(), 5);
+
+        let limit = AgeRatingLimit { max_rating: Some(AgeRating::Pg13), hide_unrated: false };
+        set_age_rating_limit(pool, DEFAULT_PROFILE_ID, limit).await.unwrap();
+        let hidden = HiddenSet::load(pool, DEFAULT_PROFILE_ID).await;
+        let mut results = listing();
+        let mut cached = serde_json::to_string(&listing()).unwrap();
+        hidden.filter(&mut results);
+        hidden.filter_json(&mut cached);
+        assert_eq!(ids(&results), vec!["family", "teen", "unrated"]);
+        let cached: Vec<SearchResult> = serde_json::from_str(&cached).unwrap();
+        assert_eq!(ids(&cached), vec!["family", "teen", "unrated"]);
+
+        set_age_rating_limit(pool, DEFAULT_PROFILE_ID, AgeRatingLimit { hide_unrated: true, ..limit }).await.unwrap();
+        let mut results = listing();
+        HiddenSet::load(pool, DEFAULT_PROFILE_ID).await.filter(&mut results);
+        assert_eq!(ids(&results), vec!["family", "teen"]);
+    }
+}
@@ -44,6 +44,7 @@ pub struct MediaHistorySummary {
 /// When "anime", queries only watch_history. When "manga", only reading_history.
 pub async fn get_all_history(
     pool: &SqlitePool,
+    profile_id: i64,
     page: i32,
     limit: i32,
     media_type: Option<&str>,
@@ -76,7 +77,7 @@ pub async fn get_all_history(
                 w.last_watched as timestamp
             FROM watch_history w
             JOIN media m ON w.media_id = m.id
-            WHERE 1=1 {}",
+            WHERE w.profile_id = ? {}",
             if search_pattern.is_some() { "AND m.title LIKE ?" } else { "" }
         ));
     }
@@ -99,7 +100,7 @@ pub async fn get_all_history(
                 r.last_read as timestamp
             FROM reading_history r
             JOIN media m ON r.media_id = m.id
-            WHERE 1=1 {}",
+            WHERE r.profile_id = ? {}",
             if search_pattern.is_some() { "AND m.title LIKE ?" } else { "" }
         ));
     }
@@ -113,11 +114,13 @@ pub async fn get_all_history(
 
     // Bind search patterns
     if include_watch {
+        query = query.bind(profile_id);
         if let Some(ref pattern) = search_pattern {
             query = query.bind(pattern.clone());
         }
     }
     if include_read {
+        query = query.bind(profile_id);
         if let Some(ref pattern) = search_pattern {
             query = query.bind(pattern.clone());
         }
@@ -178,6 +181,7 @@ pub async fn get_all_history(
 /// Returns history aggregated per anime/manga, paginated.
 pub async fn get_history_grouped_by_media(
     pool: &SqlitePool,
+    profile_id: i64,
     page: i32,
     limit: i32,
     media_type: Option<&str>,
@@ -206,7 +210,7 @@ pub async fn get_history_grouped_by_media(
                 MAX(w.last_watched) as last_activity
             FROM watch_history w
             JOIN media m ON w.media_id = m.id
-            WHERE 1=1 {}
+            WHERE w.profile_id = ? {}
             GROUP BY m.id",
             if search_pattern.is_some() { "AND m.title LIKE ?" } else { "" }
         ));
@@ -227,7 +231,7 @@ pub async fn get_history_grouped_by_media(
                 MAX(r.last_read) as last_activity
             FROM reading_history r
             JOIN media m ON r.media_id = m.id
-            WHERE 1=1 {}
+            WHERE r.profile_id = ? {}
             GROUP BY m.id",
             if search_pattern.is_some() { "AND m.title LIKE ?" } else { "" }
         ));
@@ -241,11 +245,13 @@ pub async fn get_history_grouped_by_media(
     let mut query = sqlx::query(&query_str);
 
     if include_watch {
+        query = query.bind(profile_id);
         if let Some(ref pattern) = search_pattern {
             query = query.bind(pattern.clone());
         }
     }
     if include_read {
+        query = query.bind(profile_id);
         if let Some(ref pattern) = search_pattern {
             query = query.bind(pattern.clone());
         }
@@ -300,10 +306,12 @@ pub async fn get_history_grouped_by_media(
 /// Remove a single watch history entry.
 pub async fn remove_watch_history_entry(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     episode_id: &str,
 ) -> Result<()> {
-    sqlx::query("DELETE FROM watch_history WHERE media_id = ? AND episode_id = ?")
+    sqlx::query("DELETE FROM watch_history WHERE profile_id = ? AND media_id = ? AND episode_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .bind(episode_id)
         .execute(pool)
@@ -314,10 +322,12 @@ pub async fn remove_watch_history_entry(
 /// Remove a single reading history entry.
 pub async fn remove_reading_history_entry(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     chapter_id: &str,
 ) -> Result<()> {
-    sqlx::query("DELETE FROM reading_history WHERE media_id = ? AND chapter_id = ?")
+    sqlx::query("DELETE FROM reading_history WHERE profile_id = ? AND media_id = ? AND chapter_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .bind(chapter_id)
         .execute(pool)
@@ -325,9 +335,10 @@ pub async fn remove_reading_history_entry(
     Ok(())
 }
 
-/// Clear all reading history.
-pub async fn clear_all_reading_history(pool: &SqlitePool) -> Result<()> {
-    sqlx::query("DELETE FROM reading_history")
+/// Clear all reading history for the active profile.
+pub async fn clear_all_reading_history(pool: &SqlitePool, profile_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM reading_history WHERE profile_id = ?")
+        .bind(profile_id)
         .execute(pool)
         .await?;
     Ok(())
@@ -5,6 +5,8 @@
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use super::age_rating;
 use super::media::MediaEntry;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,18 +82,20 @@ impl LibraryStatus {
 /// Add media to library
 pub async fn add_to_library(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     status: LibraryStatus,
 ) -> Result<LibraryEntry> {
     sqlx::query(
         r#"
-        INSERT INTO library (media_id, status, favorite, added_at, updated_at)
-        VALUES (?, ?, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-        ON CONFLICT(media_id) DO UPDATE SET
+        INSERT INTO library (profile_id, media_id, status, favorite, added_at, updated_at)
+        VALUES (?, ?, ?, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        ON CONFLICT(profile_id, media_id) DO UPDATE SET
             status = ?,
             updated_at = CURRENT_TIMESTAMP
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .bind(status.as_str())
     .bind(status.as_str()) // for UPDATE
@@ -101,7 +105,7 @@ pub async fn add_to_library(
     log::debug!("Added media {} to library with status {:?}", media_id, status);
 
     // Return the created/updated entry
-    get_library_entry(pool, media_id)
+    get_library_entry(pool, profile_id, media_id)
         .await?
         .ok_or_else(|| anyhow::anyhow!("Failed to retrieve library entry"))
 }
@@ -109,6 +113,7 @@ pub async fn add_to_library(
 /// Get library entry for a specific media
 pub async fn get_library_entry(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Option<LibraryEntry>> {
     let entry = if has_auto_download_column(pool).await? {
@@ -116,9 +121,10 @@ pub async fn get_library_entry(
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at, auto_download
             FROM library
-            WHERE media_id = ?
+            WHERE profile_id = ? AND media_id = ?
             "#
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_optional(pool)
         .await?
@@ -127,9 +133,10 @@ pub async fn get_library_entry(
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at
             FROM library
-            WHERE media_id = ?
+            WHERE profile_id = ? AND media_id = ?
             "#
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_optional(pool)
         .await?
@@ -141,6 +148,7 @@ pub async fn get_library_entry(
 /// Get all library entries by status
 pub async fn get_library_by_status(
     pool: &SqlitePool,
+    profile_id: i64,
     status: Option<LibraryStatus>,
 ) -> Result<Vec<LibraryEntry>> {
     let has_auto = has_auto_download_column(pool).await?;
@@ -149,18 +157,19 @@ pub async fn get_library_by_status(
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at, auto_download
             FROM library
-            WHERE status = ?
+            WHERE profile_id = ? AND status = ?
             ORDER BY updated_at DESC
             "#
         } else {
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at
             FROM library
-            WHERE status = ?
+            WHERE profile_id = ? AND status = ?
             ORDER BY updated_at DESC
             "#
         };
         sqlx::query_as::<_, LibraryEntry>(sql)
+            .bind(profile_id)
             .bind(status.as_str())
             .fetch_all(pool)
             .await?
@@ -169,16 +178,19 @@ pub async fn get_library_by_status(
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at, auto_download
             FROM library
+            WHERE profile_id = ?
             ORDER BY updated_at DESC
             "#
         } else {
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at
             FROM library
+            WHERE profile_id = ?
             ORDER BY updated_at DESC
             "#
         };
         sqlx::query_as::<_, LibraryEntry>(sql)
+            .bind(profile_id)
             .fetch_all(pool)
             .await?
     };
@@ -189,6 +201,7 @@ pub async fn get_library_by_status(
 /// Get library entries with full media details by status
 pub async fn get_library_with_media_by_status(
     pool: &SqlitePool,
+    profile_id: i64,
     status: Option<LibraryStatus>,
 ) -> Result<Vec<LibraryEntryWithMedia>> {
     let has_auto = has_auto_download_column(pool).await?;
@@ -205,7 +218,7 @@ pub async fn get_library_with_media_by_status(
                 m.genres, m.created_at, m.updated_at
             FROM library l
             INNER JOIN media m ON l.media_id = m.id
-            WHERE l.status = ?
+            WHERE l.profile_id = ? AND l.status = ?
             ORDER BY l.updated_at DESC
             "# } else { r#"
             SELECT
@@ -218,10 +231,11 @@ pub async fn get_library_with_media_by_status(
                 m.genres, m.created_at, m.updated_at
             FROM library l
             INNER JOIN media m ON l.media_id = m.id
-            WHERE l.status = ?
+            WHERE l.profile_id = ? AND l.status = ?
             ORDER BY l.updated_at DESC
             "# }
         )
+        .bind(profile_id)
         .bind(status.as_str())
         .fetch_all(pool)
         .await?
@@ -238,6 +252,7 @@ pub async fn get_library_with_media_by_status(
                 m.genres, m.created_at, m.updated_at
             FROM library l
             INNER JOIN media m ON l.media_id = m.id
+            WHERE l.profile_id = ?
             ORDER BY l.updated_at DESC
             "# } else { r#"
             SELECT
@@ -250,96 +265,321 @@ pub async fn get_library_with_media_by_status(
                 m.genres, m.created_at, m.updated_at
             FROM library l
             INNER JOIN media m ON l.media_id = m.id
+            WHERE l.profile_id = ?
             ORDER BY l.updated_at DESC
             "# }
         )
+        .bind(profile_id)
         .fetch_all(pool)
         .await?
     };
 
-    let mut results = Vec::new();
-    for row in query {
-        use sqlx::Row;
+    // Leave out media rated above the profile's age rating limit
+    let blocked = age_rating::blocked_media_ids(pool, &age_rating::current_limit(pool, profile_id).await).await?;
 
-        let library_status_str: String = row.try_get(2)?;
-        let library_status = LibraryStatus::from_str(&library_status_str)
-            .ok_or_else(|| anyhow::anyhow!("Invalid library status: {}", library_status_str))?;
-
-        let library_entry = if has_auto {
-            LibraryEntry {
-                id: row.try_get(0)?,
-                media_id: row.try_get(1)?,
-                status: library_status,
-                favorite: row.try_get(3)?,
-                score: row.try_get(4)?,
-                notes: row.try_get(5)?,
-                added_at: row.try_get(6)?,
-                updated_at: row.try_get(7)?,
-                auto_download: row.try_get(8)?,
-            }
-        } else {
-            LibraryEntry {
-                id: row.try_get(0)?,
-                media_id: row.try_get(1)?,
-                status: library_status,
-                favorite: row.try_get(3)?,
-                score: row.try_get(4)?,
-                notes: row.try_get(5)?,
-                added_at: row.try_get(6)?,
-                updated_at: row.try_get(7)?,
-                auto_download: false,
-            }
-        };
+    query
+        .iter()
+        .map(|row| entry_with_media_from_row(row, has_auto))
+        .filter(|entry| entry.as_ref().map_or(true, |e| !blocked.contains(&e.media.id)))
+        .collect()
+}
 
-        let media_offset = if has_auto { 9 } else { 8 };
-        let media = MediaEntry {
-            id: row.try_get(media_offset)?,
-            extension_id: row.try_get(media_offset + 1)?,
-            title: row.try_get(media_offset + 2)?,
-            english_name: row.try_get(media_offset + 3)?,
-            native_name: row.try_get(media_offset + 4)?,
-            description: row.try_get(media_offset + 5)?,
-            cover_url: row.try_get(media_offset + 6)?,
-            banner_url: row.try_get(media_offset + 7)?,
-            trailer_url: row.try_get(media_offset + 8)?,
-            media_type: row.try_get(media_offset + 9)?,
-            content_type: row.try_get(media_offset + 10)?,
-            status: row.try_get(media_offset + 11)?,
-            year: row.try_get(media_offset + 12)?,
-            rating: row.try_get(media_offset + 13)?,
-            episode_count: row.try_get(media_offset + 14)?,
-            episode_duration: row.try_get(media_offset + 15)?,
-            season_quarter: row.try_get(media_offset + 16)?,
-            season_year: row.try_get(media_offset + 17)?,
-            aired_start_year: row.try_get(media_offset + 18)?,
-            aired_start_month: row.try_get(media_offset + 19)?,
-            aired_start_date: row.try_get(media_offset + 20)?,
-            genres: row.try_get(media_offset + 21)?,
-            created_at: row.try_get(media_offset + 22)?,
-            updated_at: row.try_get(media_offset + 23)?,
-        };
+/// Map a library JOIN media row (columns as selected by the queries above,
+/// with or without `l.auto_download`)
+fn entry_with_media_from_row(row: &sqlx::sqlite::SqliteRow, has_auto: bool) -> Result<LibraryEntryWithMedia> {
+    use sqlx::Row;
+
+    let library_status_str: String = row.try_get(2)?;
+    let library_status = LibraryStatus::from_str(&library_status_str)
+        .ok_or_else(|| anyhow::anyhow!("Invalid library status: {}", library_status_str))?;
+
+    let library_entry = if has_auto {
+        LibraryEntry {
+            id: row.try_get(0)?,
+            media_id: row.try_get(1)?,
+            status: library_status,
+            favorite: row.try_get(3)?,
+            score: row.try_get(4)?,
+            notes: row.try_get(5)?,
+            added_at: row.try_get(6)?,
+            updated_at: row.try_get(7)?,
+            auto_download: row.try_get(8)?,
+        }
+    } else {
+        LibraryEntry {
+            id: row.try_get(0)?,
+            media_id: row.try_get(1)?,
+            status: library_status,
+            favorite: row.try_get(3)?,
+            score: row.try_get(4)?,
+            notes: row.try_get(5)?,
+            added_at: row.try_get(6)?,
+            updated_at: row.try_get(7)?,
+            auto_download: false,
+        }
+    };
+
+    let media_offset = if has_auto { 9 } else { 8 };
+    let media = MediaEntry {
+        id: row.try_get(media_offset)?,
+        extension_id: row.try_get(media_offset + 1)?,
+        title: row.try_get(media_offset + 2)?,
+        english_name: row.try_get(media_offset + 3)?,
+        native_name: row.try_get(media_offset + 4)?,
+        description: row.try_get(media_offset + 5)?,
+        cover_url: row.try_get(media_offset + 6)?,
+        banner_url: row.try_get(media_offset + 7)?,
+        trailer_url: row.try_get(media_offset + 8)?,
+        media_type: row.try_get(media_offset + 9)?,
+        content_type: row.try_get(media_offset + 10)?,
+        status: row.try_get(media_offset + 11)?,
+        year: row.try_get(media_offset + 12)?,
+        rating: row.try_get(media_offset + 13)?,
+        episode_count: row.try_get(media_offset + 14)?,
+        episode_duration: row.try_get(media_offset + 15)?,
+        season_quarter: row.try_get(media_offset + 16)?,
+        season_year: row.try_get(media_offset + 17)?,
+        aired_start_year: row.try_get(media_offset + 18)?,
+        aired_start_month: row.try_get(media_offset + 19)?,
+        aired_start_date: row.try_get(media_offset + 20)?,
+        genres: row.try_get(media_offset + 21)?,
+        created_at: row.try_get(media_offset + 22)?,
+        updated_at: row.try_get(media_offset + 23)?,
+    };
+
+    Ok(LibraryEntryWithMedia {
+        library_entry,
+        media,
+    })
+}
+
+/// Page size used when the caller doesn't pass one
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Upper bound on a single page
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Position in the library's (updated_at DESC, id DESC) order. Handed to the
+/// frontend as an opaque string; the id breaks ties between entries updated
+/// in the same second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryCursor {
+    pub updated_at: String,
+    pub id: i64,
+}
+
+impl LibraryCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.id, self.updated_at))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid library cursor: {}", cursor);
+
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (id, updated_at) = text.split_once(':').ok_or_else(invalid)?;
+
+        Ok(LibraryCursor {
+            updated_at: updated_at.to_string(),
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// One page of library entries
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryPage {
+    pub entries: Vec<LibraryEntryWithMedia>,
+    /// Pass back to fetch the next page; None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Order of a library page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    /// Most recently updated first
+    #[default]
+    UpdatedAt,
+    /// Airs soonest first: from the stored broadcast schedule when there is
+    /// one, else a week after the last release for ongoing series; rolled
+    /// forward past now
+    NextAiringAt,
+    /// Most episodes left first: episode count minus completed episodes
+    UnwatchedCount,
+    /// Most recent release first
+    LastReleaseAt,
+}
+
+/// A week in milliseconds, the cadence assumed for ongoing series
+const WEEK_MS: i64 = 7 * 24 * 60 * 60 * 1000;
 
-        results.push(LibraryEntryWithMedia {
-            library_entry,
-            media,
-        });
+/// SQL for the first of `last` + n * `interval` (Unix ms) after now
+fn next_after_now(last: &str, interval: &str) -> String {
+    format!(
+        "{last} + {interval} * (MAX(CAST(strftime('%s', 'now') AS INTEGER) * 1000 - {last}, 0) / {interval} + 1)",
+        last = last,
+        interval = interval
+    )
+}
+
+impl LibrarySort {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "updated_at" => Some(LibrarySort::UpdatedAt),
+            "next_airing_at" => Some(LibrarySort::NextAiringAt),
+            "unwatched_count" => Some(LibrarySort::UnwatchedCount),
+            "last_release_at" => Some(LibrarySort::LastReleaseAt),
+            _ => None,
+        }
     }
 
-    Ok(results)
+    /// The sort key as SQL over `l`, `m` and `rt` (release_tracking_v2),
+    /// with its direction. NULL where the data is missing.
+    fn key(&self) -> Option<(String, &'static str)> {
+        match self {
+            LibrarySort::UpdatedAt => None,
+            LibrarySort::NextAiringAt => Some((
+                format!(
+                    "CASE \
+                     WHEN m.last_aired_at IS NOT NULL AND m.broadcast_interval > 0 \
+                          AND COALESCE(rt.normalized_status, 'ongoing') NOT IN ('completed', 'hiatus') \
+                     THEN {broadcast} \
+                     WHEN rt.normalized_status = 'ongoing' THEN {weekly} \
+                     END",
+                    broadcast = next_after_now("m.last_aired_at", "m.broadcast_interval"),
+                    weekly = next_after_now("rt.last_episode_date", &WEEK_MS.to_string()),
+                ),
+                "ASC",
+            )),
+            LibrarySort::UnwatchedCount => Some((
+                "MAX(m.episode_count - (SELECT COUNT(DISTINCT wh.episode_number) FROM watch_history wh \
+                 WHERE wh.profile_id = l.profile_id AND wh.media_id = l.media_id AND wh.completed = 1), 0)"
+                    .to_string(),
+                "DESC",
+            )),
+            LibrarySort::LastReleaseAt => Some(("rt.last_episode_date".to_string(), "DESC")),
+        }
+    }
+}
+
+/// Get a page of library entries with media, newest update first unless
+/// `sort` says otherwise.
+///
+/// With a cursor this is a keyset query: it seeks straight to the cursor in
+/// idx_library_status/idx_library_updated, so a deep page costs the same as
+/// the first, and entries added while scrolling (which sort before the
+/// cursor) can't shift later pages and cause repeats or gaps. Without a
+/// cursor, `offset` pages the old way, which is fine for small libraries.
+///
+/// The other orders are computed in the query and page by `offset` only.
+/// Entries without the data (no release tracking, no episode count) come
+/// last, newest added first.
+pub async fn get_library_with_media_page(
+    pool: &SqlitePool,
+    profile_id: i64,
+    status: Option<LibraryStatus>,
+    sort: LibrarySort,
+    limit: u32,
+    cursor: Option<&str>,
+    offset: u32,
+) -> Result<LibraryPage> {
+    let has_auto = has_auto_download_column(pool).await?;
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+    let cursor = cursor.map(LibraryCursor::decode).transpose()?;
+    let sort_key = sort.key();
+    if cursor.is_some() && sort_key.is_some() {
+        anyhow::bail!("Library cursors only page the default order; use an offset");
+    }
+
+    let mut sql = format!(
+        r#"
+        SELECT
+            l.id, l.media_id, l.status, l.favorite, l.score, l.notes, l.added_at, l.updated_at,{}
+            m.id, m.extension_id, m.title, m.english_name, m.native_name, m.description,
+            m.cover_url, m.banner_url, m.trailer_url, m.media_type, m.content_type, m.status,
+            m.year, m.rating, m.episode_count, m.episode_duration,
+            m.season_quarter, m.season_year,
+            m.aired_start_year, m.aired_start_month, m.aired_start_date,
+            m.genres, m.created_at, m.updated_at
+        FROM library l
+        INNER JOIN media m ON l.media_id = m.id{}
+        WHERE l.profile_id = ?
+        "#,
+        if has_auto { " l.auto_download," } else { "" },
+        if sort_key.is_some() { "\n        LEFT JOIN release_tracking_v2 rt ON rt.media_id = m.id" } else { "" }
+    );
+    if status.is_some() {
+        sql.push_str(" AND l.status = ?");
+    }
+    if cursor.is_some() {
+        sql.push_str(" AND (l.updated_at, l.id) < (?, ?)");
+    }
+    if let Some(condition) = age_rating::current_limit(pool, profile_id).await.sql_condition("m.rating_classification") {
+        sql.push_str(" AND ");
+        sql.push_str(&condition);
+    }
+    match &sort_key {
+        Some((key, direction)) => {
+            sql.push_str(&format!(" ORDER BY ({key}) IS NULL, ({key}) {direction}, l.id DESC"));
+        }
+        None => sql.push_str(" ORDER BY l.updated_at DESC, l.id DESC"),
+    }
+    // One extra row tells us whether there is a next page
+    sql.push_str(" LIMIT ?");
+    if cursor.is_none() {
+        sql.push_str(" OFFSET ?");
+    }
+
+    let mut query = sqlx::query(&sql).bind(profile_id);
+    if let Some(status) = &status {
+        query = query.bind(status.as_str());
+    }
+    if let Some(cursor) = &cursor {
+        query = query.bind(&cursor.updated_at).bind(cursor.id);
+    }
+    query = query.bind(limit as i64 + 1);
+    if cursor.is_none() {
+        query = query.bind(offset as i64);
+    }
+
+    let rows = query.fetch_all(pool).await?;
+    let has_more = rows.len() > limit as usize;
+
+    let entries = rows
+        .iter()
+        .take(limit as usize)
+        .map(|row| entry_with_media_from_row(row, has_auto))
+        .collect::<Result<Vec<_>>>()?;
+
+    let next_cursor = match entries.last() {
+        Some(last) if has_more && sort_key.is_none() => Some(
+            LibraryCursor {
+                updated_at: last.library_entry.updated_at.clone(),
+                id: last.library_entry.id,
+            }
+            .encode(),
+        ),
+        _ => None,
+    };
+
+    Ok(LibraryPage { entries, next_cursor })
 }
 
 /// Get favorites
 #[allow(dead_code)]
-pub async fn get_favorites(pool: &SqlitePool) -> Result<Vec<LibraryEntry>> {
+pub async fn get_favorites(pool: &SqlitePool, profile_id: i64) -> Result<Vec<LibraryEntry>> {
     let entries = if has_auto_download_column(pool).await? {
         sqlx::query_as::<_, LibraryEntry>(
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at, auto_download
             FROM library
-            WHERE favorite = 1
+            WHERE profile_id = ? AND favorite = 1
             ORDER BY updated_at DESC
             "#
         )
+        .bind(profile_id)
         .fetch_all(pool)
         .await?
     } else {
@@ -347,10 +587,11 @@ pub async fn get_favorites(pool: &SqlitePool) -> Result<Vec<LibraryEntry>> {
             r#"
             SELECT id, media_id, status, favorite, score, notes, added_at, updated_at
             FROM library
-            WHERE favorite = 1
+            WHERE profile_id = ? AND favorite = 1
             ORDER BY updated_at DESC
             "#
         )
+        .bind(profile_id)
         .fetch_all(pool)
         .await?
     };
@@ -362,6 +603,7 @@ pub async fn get_favorites(pool: &SqlitePool) -> Result<Vec<LibraryEntry>> {
 #[allow(dead_code)]
 pub async fn update_library_status(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     status: LibraryStatus,
 ) -> Result<()> {
@@ -369,10 +611,11 @@ pub async fn update_library_status(
         r#"
         UPDATE library
         SET status = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         "#
     )
     .bind(status.as_str())
+    .bind(profile_id)
     .bind(media_id)
     .execute(pool)
     .await?;
@@ -383,6 +626,7 @@ pub async fn update_library_status(
 /// Set auto-download flag
 pub async fn set_auto_download(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     enabled: bool,
 ) -> Result<bool> {
@@ -394,10 +638,11 @@ pub async fn set_auto_download(
         r#"
         UPDATE library
         SET auto_download = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         "#
     )
     .bind(enabled)
+    .bind(profile_id)
     .bind(media_id)
     .execute(pool)
     .await?;
@@ -408,10 +653,11 @@ pub async fn set_auto_download(
 /// Toggle favorite status
 pub async fn toggle_favorite(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<bool> {
     // Get current favorite status
-    let entry = get_library_entry(pool, media_id).await?
+    let entry = get_library_entry(pool, profile_id, media_id).await?
         .ok_or_else(|| anyhow::anyhow!("Media not in library"))?;
 
     let new_favorite = !entry.favorite;
@@ -420,10 +666,11 @@ pub async fn toggle_favorite(
         r#"
         UPDATE library
         SET favorite = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         "#
     )
     .bind(new_favorite)
+    .bind(profile_id)
     .bind(media_id)
     .execute(pool)
     .await?;
@@ -435,6 +682,7 @@ pub async fn toggle_favorite(
 #[allow(dead_code)]
 pub async fn update_score(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     score: f64,
 ) -> Result<()> {
@@ -442,10 +690,11 @@ pub async fn update_score(
         r#"
         UPDATE library
         SET score = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         "#
     )
     .bind(score)
+    .bind(profile_id)
     .bind(media_id)
     .execute(pool)
     .await?;
@@ -457,6 +706,7 @@ pub async fn update_score(
 #[allow(dead_code)]
 pub async fn update_notes(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     notes: &str,
 ) -> Result<()> {
@@ -464,10 +714,11 @@ pub async fn update_notes(
         r#"
         UPDATE library
         SET notes = ?, updated_at = CURRENT_TIMESTAMP
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         "#
     )
     .bind(notes)
+    .bind(profile_id)
     .bind(media_id)
     .execute(pool)
     .await?;
@@ -478,9 +729,11 @@ pub async fn update_notes(
 /// Remove from library
 pub async fn remove_from_library(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<()> {
-    sqlx::query("DELETE FROM library WHERE media_id = ?")
+    sqlx::query("DELETE FROM library WHERE profile_id = ? AND media_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .execute(pool)
         .await?;
@@ -493,9 +746,11 @@ pub async fn remove_from_library(
 /// Check if media is in library
 pub async fn is_in_library(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<bool> {
-    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM library WHERE media_id = ?")
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM library WHERE profile_id = ? AND media_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .fetch_one(pool)
         .await?;
@@ -506,6 +761,7 @@ pub async fn is_in_library(
 /// Bulk update library status for multiple items
 pub async fn bulk_update_library_status(
     pool: &SqlitePool,
+    profile_id: i64,
     media_ids: &[String],
     status: LibraryStatus,
 ) -> Result<()> {
@@ -514,10 +770,11 @@ pub async fn bulk_update_library_status(
             r#"
             UPDATE library
             SET status = ?, updated_at = CURRENT_TIMESTAMP
-            WHERE media_id = ?
+            WHERE profile_id = ? AND media_id = ?
             "#
         )
         .bind(status.as_str())
+        .bind(profile_id)
         .bind(media_id)
         .execute(pool)
         .await?;
@@ -530,10 +787,12 @@ pub async fn bulk_update_library_status(
 /// Bulk remove from library
 pub async fn bulk_remove_from_library(
     pool: &SqlitePool,
+    profile_id: i64,
     media_ids: &[String],
 ) -> Result<()> {
     for media_id in media_ids {
-        sqlx::query("DELETE FROM library WHERE media_id = ?")
+        sqlx::query("DELETE FROM library WHERE profile_id = ? AND media_id = ?")
+            .bind(profile_id)
             .bind(media_id)
             .execute(pool)
             .await?;
@@ -567,3 +826,344 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for LibraryEntry {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use sqlx::Row;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    const LIBRARY_SIZE: usize = 3_000;
+    const PAGE_SIZE: u32 = 100;
+
+    /// Fill the library with entries spread over a few timestamps, so many
+    /// share an updated_at and the id tie-break matters
+    async fn seed_library(pool: &SqlitePool) {
+        let mut tx = pool.begin().await.unwrap();
+
+        for i in 0..LIBRARY_SIZE {
+            let media_id = format!("media-{}", i);
+            let status = if i % 3 == 0 { "completed" } else { "watching" };
+
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'ext', ?, 'anime')")
+                .bind(&media_id)
+                .bind(format!("Title {}", i))
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+
+            sqlx::query("INSERT INTO library (profile_id, media_id, status, updated_at) VALUES (1, ?, ?, ?)")
+                .bind(&media_id)
+                .bind(status)
+                .bind(format!("2024-01-{:02} 12:00:00", i % 20 + 1))
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+        }
+
+        tx.commit().await.unwrap();
+    }
+
+    async fn add_entry(pool: &SqlitePool, media_id: &str) {
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'ext', ?, 'anime')")
+            .bind(media_id)
+            .bind(media_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        add_to_library(pool, DEFAULT_PROFILE_ID, media_id, LibraryStatus::Watching).await.unwrap();
+    }
+
+    #[test]
+    fn cursor_round_trips_and_rejects_garbage() {
+        let cursor = LibraryCursor { updated_at: "2024-03-01 10:20:30".to_string(), id: 42 };
+        assert_eq!(LibraryCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        assert!(LibraryCursor::decode("not a cursor!").is_err());
+        assert!(LibraryCursor::decode(&URL_SAFE_NO_PAD.encode("abc:2024")).is_err());
+    }
+
+    #[tokio::test]
+    async fn cursor_pages_visit_every_entry_once_despite_concurrent_inserts() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed_library(pool).await;
+
+        let mut seen = HashSet::new();
+        let mut previous: Option<(String, i64)> = None;
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+
+        loop {
+            let page = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, None, LibrarySort::UpdatedAt, PAGE_SIZE, cursor.as_deref(), 0)
+                .await
+                .unwrap();
+            pages += 1;
+
+            for entry in &page.entries {
+                let key = (entry.library_entry.updated_at.clone(), entry.library_entry.id);
+                if let Some(prev) = &previous {
+                    assert!(key < *prev, "entries out of order: {:?} after {:?}", key, prev);
+                }
+                assert!(seen.insert(entry.library_entry.media_id.clone()), "duplicate entry");
+                previous = Some(key);
+            }
+
+            // Entries added mid-scroll sort before the cursor and must not
+            // shift the remaining pages
+            if pages == 3 {
+                for i in 0..25 {
+                    add_entry(pool, &format!("added-{}", i)).await;
+                }
+            }
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen.len(), LIBRARY_SIZE);
+        assert_eq!(pages, LIBRARY_SIZE / PAGE_SIZE as usize);
+        assert!(seen.iter().all(|id| id.starts_with("media-")));
+    }
+
+    #[tokio::test]
+    async fn cursor_and_offset_pages_agree_with_status_filter() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed_library(pool).await;
+
+        let completed = Some(LibraryStatus::Completed);
+        let first = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, completed.clone(), LibrarySort::UpdatedAt, 50, None, 0)
+            .await
+            .unwrap();
+        let by_cursor = get_library_with_media_page(
+            pool, DEFAULT_PROFILE_ID, completed.clone(), LibrarySort::UpdatedAt, 50, first.next_cursor.as_deref(), 0,
+        )
+        .await
+        .unwrap();
+        let by_offset = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, completed, LibrarySort::UpdatedAt, 50, None, 50)
+            .await
+            .unwrap();
+
+        let ids = |page: &LibraryPage| page.entries.iter().map(|e| e.library_entry.id).collect::<Vec<_>>();
+        assert_eq!(ids(&by_cursor), ids(&by_offset));
+        assert!(by_cursor.entries.iter().all(|e| e.library_entry.status == LibraryStatus::Completed));
+
+        let all = get_library_with_media_by_status(pool, DEFAULT_PROFILE_ID, Some(LibraryStatus::Completed)).await.unwrap();
+        assert_eq!(all.len(), LIBRARY_SIZE / 3);
+    }
+
+    #[tokio::test]
+    async fn library_queries_respect_the_age_rating_limit() {
+        use crate::database::age_rating::{record_rating, set_age_rating_limit, AgeRating, AgeRatingLimit};
+
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        for (media_id, rating) in [("family", Some("G - All Ages")), ("mature", Some("Rx - Hentai")), ("unrated", None)] {
+            add_entry(pool, media_id).await;
+            if let Some(rating) = rating {
+                record_rating(pool, media_id, rating).await.unwrap();
+            }
+        }
+
+        let media_ids = |entries: &[LibraryEntryWithMedia]| {
+            let mut ids = entries.iter().map(|e| e.media.id.clone()).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let page = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, None, LibrarySort::UpdatedAt, 10, None, 0).await.unwrap();
+        assert_eq!(page.entries.len(), 3);
+
+        let limit = AgeRatingLimit { max_rating: Some(AgeRating::Pg13), hide_unrated: false };
+        set_age_rating_limit(pool, DEFAULT_PROFILE_ID, limit).await.unwrap();
+        let page = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, None, LibrarySort::UpdatedAt, 10, None, 0).await.unwrap();
+        assert_eq!(media_ids(&page.entries), vec!["family", "unrated"]);
+        let all = get_library_with_media_by_status(pool, DEFAULT_PROFILE_ID, None).await.unwrap();
+        assert_eq!(media_ids(&all), vec!["family", "unrated"]);
+
+        set_age_rating_limit(pool, DEFAULT_PROFILE_ID, AgeRatingLimit { hide_unrated: true, ..limit }).await.unwrap();
+        let page = get_library_with_media_page(pool, DEFAULT_PROFILE_ID, Some(LibraryStatus::Watching), LibrarySort::UpdatedAt, 10, None, 0)
+            .await
+            .unwrap();
+        assert_eq!(media_ids(&page.entries), vec!["family"]);
+        let all = get_library_with_media_by_status(pool, DEFAULT_PROFILE_ID, Some(LibraryStatus::Watching)).await.unwrap();
+        assert_eq!(media_ids(&all), vec!["family"]);
+    }
+
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+    /// A library where only some entries have release tracking, an episode
+    /// count or watch history:
+    /// - airing-soon: ongoing, released 6 days ago, 24 episodes, none watched
+    /// - rolled-over: ongoing, released 10 days ago, 12 episodes, 1 watched
+    /// - recent: ongoing, released 2 days ago, 12 episodes, 3 watched
+    /// - finished: tracked but finished, released yesterday, no episode count
+    /// - untracked: no tracking, 5 episodes, all watched
+    async fn seed_mixed_library(pool: &SqlitePool) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let entries: [(&str, Option<(&str, i64)>, Option<i32>, i32); 5] = [
+            ("untracked", None, Some(5), 5),
+            ("finished", Some(("completed", 1)), None, 0),
+            ("recent", Some(("ongoing", 2)), Some(12), 3),
+            ("rolled-over", Some(("ongoing", 10)), Some(12), 1),
+            ("airing-soon", Some(("ongoing", 6)), Some(24), 0),
+        ];
+
+        for (media_id, tracking, episode_count, watched) in entries {
+            add_entry(pool, media_id).await;
+            sqlx::query("UPDATE media SET episode_count = ? WHERE id = ?")
+                .bind(episode_count)
+                .bind(media_id)
+                .execute(pool)
+                .await
+                .unwrap();
+
+            if let Some((status, days_ago)) = tracking {
+                sqlx::query(
+                    "INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, normalized_status, \
+                     last_episode_date, last_checked_at) VALUES (?, 'ext', 'anime', ?, ?, ?)",
+                )
+                .bind(media_id)
+                .bind(status)
+                .bind(now - days_ago * DAY_MS)
+                .bind(now)
+                .execute(pool)
+                .await
+                .unwrap();
+            }
+
+            for episode in 1..=watched {
+                sqlx::query(
+                    "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, completed) \
+                     VALUES (1, ?, ?, ?, 1)",
+                )
+                .bind(media_id)
+                .bind(format!("{}-{}", media_id, episode))
+                .bind(episode)
+                .execute(pool)
+                .await
+                .unwrap();
+            }
+        }
+
+        // Half-watched episodes don't count as watched
+        sqlx::query(
+            "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, completed) \
+             VALUES (1, 'airing-soon', 'airing-soon-1', 1, 0)",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn sorted_ids(pool: &SqlitePool, sort: LibrarySort) -> Vec<String> {
+        get_library_with_media_page(pool, DEFAULT_PROFILE_ID, None, sort, 10, None, 0)
+            .await
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.media.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn next_airing_sort_puts_the_soonest_first_and_unknown_last() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        seed_mixed_library(db.pool()).await;
+
+        // airing-soon airs tomorrow, rolled-over missed a week and airs in 4
+        // days, recent in 5; the rest have no estimate, newest added first
+        assert_eq!(
+            sorted_ids(db.pool(), LibrarySort::NextAiringAt).await,
+            vec!["airing-soon", "rolled-over", "recent", "finished", "untracked"]
+        );
+
+        // A stored broadcast schedule wins: untracked aired 4 days ago and
+        // airs every 3 days, so in 2; finished ones have no next episode
+        let now = chrono::Utc::now().timestamp_millis();
+        for media_id in ["untracked", "finished"] {
+            sqlx::query("UPDATE media SET last_aired_at = ?, broadcast_interval = ? WHERE id = ?")
+                .bind(now - 4 * DAY_MS)
+                .bind(3 * DAY_MS)
+                .bind(media_id)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            sorted_ids(db.pool(), LibrarySort::NextAiringAt).await,
+            vec!["airing-soon", "untracked", "rolled-over", "recent", "finished"]
+        );
+    }
+
+    #[tokio::test]
+    async fn unwatched_count_sort_puts_the_longest_backlog_first() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        seed_mixed_library(db.pool()).await;
+
+        assert_eq!(
+            sorted_ids(db.pool(), LibrarySort::UnwatchedCount).await,
+            vec!["airing-soon", "rolled-over", "recent", "untracked", "finished"]
+        );
+    }
+
+    #[tokio::test]
+    async fn last_release_sort_puts_the_latest_release_first() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        seed_mixed_library(db.pool()).await;
+
+        assert_eq!(
+            sorted_ids(db.pool(), LibrarySort::LastReleaseAt).await,
+            vec!["finished", "recent", "airing-soon", "rolled-over", "untracked"]
+        );
+
+        // Other orders can't be continued from a cursor
+        let cursor = LibraryCursor { updated_at: "2024-01-01 00:00:00".to_string(), id: 1 }.encode();
+        assert!(
+            get_library_with_media_page(db.pool(), DEFAULT_PROFILE_ID, None, LibrarySort::LastReleaseAt, 10, Some(&cursor), 0)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn deep_cursor_page_seeks_the_index_instead_of_scanning() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed_library(pool).await;
+
+        for (status_filter, index) in [("", "idx_library_updated"), (" AND l.status = 'watching'", "idx_library_status")] {
+            let sql = format!(
+                "EXPLAIN QUERY PLAN SELECT l.id FROM library l INNER JOIN media m ON l.media_id = m.id \
+                 WHERE l.profile_id = 1{} AND (l.updated_at, l.id) < ('2024-01-02 12:00:00', 50) \
+                 ORDER BY l.updated_at DESC, l.id DESC LIMIT 101",
+                status_filter
+            );
+            let plan: Vec<String> = sqlx::query(&sql)
+                .fetch_all(pool)
+                .await
+                .unwrap()
+                .iter()
+                .map(|row| row.get::<String, _>("detail"))
+                .collect();
+            let plan = plan.join("\n");
+
+            // A seek into the index with no sort step: the page only reads
+            // LIMIT rows however deep the cursor is
+            assert!(plan.contains(&format!("SEARCH l USING INDEX {}", index)), "{}", plan);
+            assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+        }
+    }
+}
@@ -0,0 +1,537 @@
+// Library Report
+//
+// A human-readable snapshot of the library (Markdown or HTML), as opposed to
+// the machine-readable JSON export. Entries are grouped by status with their
+// progress (episodes watched or chapters read vs. the known total), score
+// and favorite mark, after a summary of the watch and reading statistics.
+// Rows are streamed from the database straight into the file, so large
+// libraries never have to fit in memory. HTML reports inline a small
+// stylesheet and, when asked to, covers from the local cover cache as data
+// URIs (per cover and in total capped, so the file stays shareable).
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use super::stats::{get_reading_stats_summary, get_watch_stats_summary};
+use crate::cache::media_disk;
+use crate::locale::{self, Locale};
+
+/// Covers larger than this are left out of HTML reports
+const MAX_COVER_BYTES: u64 = 256 * 1024;
+
+/// Embedded covers stop once they add up to this much
+const MAX_TOTAL_COVER_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Statuses in report order, with their section headings
+const STATUS_SECTIONS: &[(&str, &str)] = &[
+    ("watching", "Watching"),
+    ("reading", "Reading"),
+    ("completed", "Completed"),
+    ("on_hold", "On Hold"),
+    ("dropped", "Dropped"),
+    ("plan_to_watch", "Plan to Watch"),
+    ("plan_to_read", "Plan to Read"),
+];
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2rem auto;padding:0 1rem;color:#222}\
+h1{margin-bottom:0}.generated{color:#777}table{border-collapse:collapse;width:100%;margin-bottom:2rem}\
+th,td{text-align:left;padding:.4rem .6rem;border-bottom:1px solid #ddd;vertical-align:middle}\
+th{background:#f4f4f4}img{width:40px;height:56px;object-fit:cover;border-radius:3px}.fav{color:#e0a100}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportOptions {
+    /// Add the user's notes under each entry
+    #[serde(default)]
+    pub include_notes: bool,
+    /// Embed cached covers (HTML only)
+    #[serde(default)]
+    pub include_covers: bool,
+}
+
+/// What ended up in a written report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportSummary {
+    pub entries: usize,
+    pub covers_embedded: usize,
+}
+
+/// One library entry as it appears in the report
+struct ReportEntry {
+    media_id: String,
+    title: String,
+    media_type: String,
+    favorite: bool,
+    score: Option<f64>,
+    notes: Option<String>,
+    progress: i64,
+    total: Option<i64>,
+}
+
+impl ReportEntry {
+    fn progress_label(&self) -> String {
+        match self.total {
+            Some(total) if total > 0 => format!("{}/{}", self.progress, total),
+            _ => format!("{}/?", self.progress),
+        }
+    }
+
+    fn score_label(&self) -> String {
+        match self.score {
+            Some(score) if score.fract() == 0.0 => format!("{}", score as i64),
+            Some(score) => format!("{:.1}", score),
+            None => "-".to_string(),
+        }
+    }
+
+    fn type_label(&self) -> &'static str {
+        if self.media_type == "manga" {
+            "Manga"
+        } else {
+            "Anime"
+        }
+    }
+}
+
+/// Statistics shown at the top of the report
+struct ReportStats {
+    entries: i64,
+    favorites: i64,
+    episodes_watched: i32,
+    hours_watched: f64,
+    anime_completed: i32,
+    chapters_read: i32,
+    manga_completed: i32,
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Data URI of the cached cover of a media item, if there's one small enough
+fn cover_data_uri(covers_dir: &Path, media_id: &str, budget: &mut u64) -> Option<String> {
+    let (path, mime) = media_disk::find_cover(covers_dir, media_id)?;
+    let metadata = std::fs::metadata(&path).ok()?;
+    if metadata.len() > MAX_COVER_BYTES || metadata.len() > *budget {
+        return None;
+    }
+    let bytes = std::fs::read(&path).ok()?;
+    media_disk::mark_used(&path);
+    *budget -= bytes.len() as u64;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Writes one report format
+struct ReportWriter<'a, W: Write> {
+    out: W,
+    format: ReportFormat,
+    options: &'a ReportOptions,
+    locale: Locale,
+    covers_dir: Option<&'a Path>,
+    cover_budget: u64,
+    covers_embedded: usize,
+}
+
+impl<W: Write> ReportWriter<'_, W> {
+    fn header(&mut self, stats: &ReportStats, generated_at: &str) -> Result<()> {
+        let number = |n: i64| locale::format_number(n, self.locale);
+        let lines = [
+            format!("Titles in library: {} ({} favorite{})", number(stats.entries), number(stats.favorites), if stats.favorites == 1 { "" } else { "s" }),
+            format!(
+                "Episodes watched: {} ({} hours)",
+                number(stats.episodes_watched.into()),
+                locale::format_decimal(stats.hours_watched, 1, self.locale)
+            ),
+            format!("Anime completed: {}", number(stats.anime_completed.into())),
+            format!("Chapters read: {}", number(stats.chapters_read.into())),
+            format!("Manga completed: {}", number(stats.manga_completed.into())),
+        ];
+
+        match self.format {
+            ReportFormat::Markdown => {
+                writeln!(self.out, "# Otaku Library Report\n")?;
+                writeln!(self.out, "Generated {}\n", generated_at)?;
+                writeln!(self.out, "## Summary\n")?;
+                for line in lines {
+                    writeln!(self.out, "- {}", line)?;
+                }
+            }
+            ReportFormat::Html => {
+                writeln!(self.out, "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">", self.locale.code())?;
+                writeln!(self.out, "<title>Otaku Library Report</title>\n<style>{}</style>\n</head>\n<body>", HTML_STYLE)?;
+                writeln!(self.out, "<h1>Otaku Library Report</h1>\n<p class=\"generated\">Generated {}</p>", escape_html(generated_at))?;
+                writeln!(self.out, "<h2>Summary</h2>\n<ul>")?;
+                for line in lines {
+                    writeln!(self.out, "<li>{}</li>", escape_html(&line))?;
+                }
+                writeln!(self.out, "</ul>")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn section_start(&mut self, heading: &str, count: i64) -> Result<()> {
+        match self.format {
+            ReportFormat::Markdown => {
+                writeln!(self.out, "\n## {} ({})\n", heading, count)?;
+                writeln!(self.out, "| Title | Type | Progress | Score | Favorite |")?;
+                writeln!(self.out, "|---|---|---|---|---|")?;
+            }
+            ReportFormat::Html => {
+                writeln!(self.out, "<h2>{} ({})</h2>\n<table>", escape_html(heading), count)?;
+                let cover_column = if self.show_covers() { "<th></th>" } else { "" };
+                writeln!(self.out, "<tr>{}<th>Title</th><th>Type</th><th>Progress</th><th>Score</th><th>Favorite</th></tr>", cover_column)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn show_covers(&self) -> bool {
+        self.format == ReportFormat::Html && self.options.include_covers && self.covers_dir.is_some()
+    }
+
+    fn entry(&mut self, entry: &ReportEntry) -> Result<()> {
+        let notes = entry
+            .notes
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| self.options.include_notes && !n.is_empty());
+
+        match self.format {
+            ReportFormat::Markdown => {
+                let title = match notes {
+                    Some(notes) => format!("{}<br>_{}_", escape_markdown(&entry.title), escape_markdown(notes)),
+                    None => escape_markdown(&entry.title),
+                };
+                writeln!(
+                    self.out,
+                    "| {} | {} | {} | {} | {} |",
+                    title,
+                    entry.type_label(),
+                    entry.progress_label(),
+                    entry.score_label(),
+                    if entry.favorite { "★" } else { "" }
+                )?;
+            }
+            ReportFormat::Html => {
+                let mut cover_cell = String::new();
+                if self.show_covers() {
+                    let uri = self
+                        .covers_dir
+                        .and_then(|dir| cover_data_uri(dir, &entry.media_id, &mut self.cover_budget));
+                    if uri.is_some() {
+                        self.covers_embedded += 1;
+                    }
+                    cover_cell = match uri {
+                        Some(uri) => format!("<td><img src=\"{}\" alt=\"\"></td>", uri),
+                        None => "<td></td>".to_string(),
+                    };
+                }
+                let notes = notes
+                    .map(|n| format!("<br><small>{}</small>", escape_html(n)))
+                    .unwrap_or_default();
+                writeln!(
+                    self.out,
+                    "<tr>{}<td>{}{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"fav\">{}</td></tr>",
+                    cover_cell,
+                    escape_html(&entry.title),
+                    notes,
+                    entry.type_label(),
+                    entry.progress_label(),
+                    entry.score_label(),
+                    if entry.favorite { "★" } else { "" }
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn section_end(&mut self) -> Result<()> {
+        if self.format == ReportFormat::Html {
+            writeln!(self.out, "</table>")?;
+        }
+        Ok(())
+    }
+
+    fn footer(&mut self) -> Result<()> {
+        if self.format == ReportFormat::Html {
+            writeln!(self.out, "</body>\n</html>")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+async fn load_stats(pool: &SqlitePool, profile_id: i64) -> Result<ReportStats> {
+    let counts = sqlx::query(
+        "SELECT COUNT(*) AS entries, COALESCE(SUM(favorite), 0) AS favorites FROM library WHERE profile_id = ?",
+    )
+    .bind(profile_id)
+    .fetch_one(pool)
+    .await?;
+    let watch = get_watch_stats_summary(pool, profile_id).await?;
+    let reading = get_reading_stats_summary(pool, profile_id).await?;
+
+    Ok(ReportStats {
+        entries: counts.get("entries"),
+        favorites: counts.get("favorites"),
+        episodes_watched: watch.episodes_completed,
+        hours_watched: watch.total_time_seconds / 3600.0,
+        anime_completed: watch.series_completed,
+        chapters_read: reading.total_chapters_completed,
+        manga_completed: reading.series_completed,
+    })
+}
+
+/// Write the report of the active profile's library to `out`, with numbers
+/// formatted for `locale`
+pub async fn write_report<W: Write>(
+    pool: &SqlitePool,
+    profile_id: i64,
+    out: W,
+    format: ReportFormat,
+    options: &ReportOptions,
+    covers_dir: Option<&Path>,
+    locale: Locale,
+    generated_at: &str,
+) -> Result<ReportSummary> {
+    let mut writer = ReportWriter {
+        out,
+        format,
+        options,
+        locale,
+        covers_dir,
+        cover_budget: MAX_TOTAL_COVER_BYTES,
+        covers_embedded: 0,
+    };
+
+    let stats = load_stats(pool, profile_id).await?;
+    writer.header(&stats, generated_at)?;
+
+    let mut entries = 0;
+    for (status, heading) in STATUS_SECTIONS {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM library WHERE profile_id = ? AND status = ?")
+            .bind(profile_id)
+            .bind(status)
+            .fetch_one(pool)
+            .await?;
+        if count == 0 {
+            continue;
+        }
+
+        writer.section_start(heading, count)?;
+        let mut rows = sqlx::query(
+            r#"
+            SELECT
+                l.media_id, l.favorite, l.score, l.notes,
+                m.title, m.media_type, m.episode_count,
+                CASE WHEN m.media_type = 'manga' THEN
+                    (SELECT COUNT(*) FROM reading_history r
+                     WHERE r.profile_id = l.profile_id AND r.media_id = l.media_id AND r.completed = 1)
+                ELSE
+                    (SELECT COUNT(*) FROM watch_history w
+                     WHERE w.profile_id = l.profile_id AND w.media_id = l.media_id AND w.completed = 1)
+                END AS progress
+            FROM library l
+            INNER JOIN media m ON l.media_id = m.id
+            WHERE l.profile_id = ? AND l.status = ?
+            ORDER BY m.title COLLATE NOCASE, l.media_id
+            "#,
+        )
+        .bind(profile_id)
+        .bind(status)
+        .fetch(pool);
+
+        while let Some(row) = rows.try_next().await? {
+            writer.entry(&ReportEntry {
+                media_id: row.get("media_id"),
+                title: row.get("title"),
+                media_type: row.get("media_type"),
+                favorite: row.get::<bool, _>("favorite"),
+                score: row.get("score"),
+                notes: row.get("notes"),
+                progress: row.get("progress"),
+                total: row.get("episode_count"),
+            })?;
+            entries += 1;
+        }
+        writer.section_end()?;
+    }
+    writer.footer()?;
+
+    Ok(ReportSummary {
+        entries,
+        covers_embedded: writer.covers_embedded,
+    })
+}
+
+/// Write the library report to `path`. Written to a temporary file first and
+/// renamed into place, so a failed export never leaves half a report behind.
+pub async fn export_library_report(
+    pool: &SqlitePool,
+    profile_id: i64,
+    path: &Path,
+    format: ReportFormat,
+    options: &ReportOptions,
+    covers_dir: Option<&Path>,
+) -> Result<ReportSummary> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let file = std::fs::File::create(&partial).with_context(|| format!("Failed to create {:?}", partial))?;
+    let locale = locale::current_locale(pool).await;
+    let generated_at = locale::format_datetime(&chrono::Local::now(), locale);
+
+    let summary = match write_report(pool, profile_id, std::io::BufWriter::new(file), format, options, covers_dir, locale, &generated_at).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    std::fs::rename(&partial, path).with_context(|| format!("Failed to move report into place at {:?}", path))?;
+    log::info!("Exported library report with {} entries to {:?}", summary.entries, path);
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    async fn seed(pool: &SqlitePool) {
+        for (id, title, media_type, episodes) in [
+            ("frieren", "Frieren: Beyond Journey's End", "anime", Some(28)),
+            ("bocchi", "Bocchi the Rock!", "anime", Some(12)),
+            ("berserk", "Berserk | Deluxe", "manga", None),
+        ] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type, episode_count) VALUES (?, 'ext', ?, ?, ?)")
+                .bind(id)
+                .bind(title)
+                .bind(media_type)
+                .bind(episodes)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        for (id, status, favorite, score, notes) in [
+            ("frieren", "watching", 1, Some(9.5), Some("Rewatch with friends")),
+            ("bocchi", "completed", 0, Some(9.0), None),
+            ("berserk", "reading", 1, None, None),
+        ] {
+            sqlx::query("INSERT INTO library (profile_id, media_id, status, favorite, score, notes) VALUES (1, ?, ?, ?, ?, ?)")
+                .bind(id)
+                .bind(status)
+                .bind(favorite)
+                .bind(score)
+                .bind(notes)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        for episode in 1..=3 {
+            sqlx::query(
+                "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed) \
+                 VALUES (1, 'frieren', ?, ?, 1440, 1)",
+            )
+            .bind(format!("frieren-{}", episode))
+            .bind(episode)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO reading_history (profile_id, media_id, chapter_id, chapter_number, current_page, total_pages, completed) \
+             VALUES (1, 'berserk', 'berserk-1', 1, 20, 20, 1)",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn report(pool: &SqlitePool, format: ReportFormat, options: &ReportOptions, covers: Option<&Path>) -> String {
+        let mut out = Vec::new();
+        write_report(pool, DEFAULT_PROFILE_ID, &mut out, format, options, covers, Locale::En, "2026-01-01 12:00").await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn markdown_report_matches_the_golden_file() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed(pool).await;
+
+        let options = ReportOptions { include_notes: true, include_covers: false };
+        let markdown = report(pool, ReportFormat::Markdown, &options, None).await;
+        assert_eq!(markdown, include_str!("testdata/library_report.md"));
+    }
+
+    #[tokio::test]
+    async fn html_report_is_tagged_and_formatted_for_the_locale() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed(pool).await;
+
+        let mut out = Vec::new();
+        write_report(pool, DEFAULT_PROFILE_ID, &mut out, ReportFormat::Html, &ReportOptions::default(), None, Locale::De, "05.01.2026, 15:04")
+            .await
+            .unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("<html lang=\"de\">"));
+        assert!(html.contains("Episodes watched: 3 (1,2 hours)"));
+        assert!(html.contains("Generated 05.01.2026, 15:04"));
+    }
+
+    #[tokio::test]
+    async fn html_report_embeds_small_cached_covers() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed(pool).await;
+
+        let covers = temp_dir.path().join("covers");
+        std::fs::create_dir_all(&covers).unwrap();
+        std::fs::write(covers.join("frieren.jpg"), b"jpeg bytes").unwrap();
+        std::fs::write(covers.join("bocchi.png"), vec![0u8; MAX_COVER_BYTES as usize + 1]).unwrap();
+
+        let options = ReportOptions { include_notes: false, include_covers: true };
+        let html = report(pool, ReportFormat::Html, &options, Some(&covers)).await;
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&format!("data:image/jpeg;base64,{}", STANDARD.encode(b"jpeg bytes"))));
+        // Too large to embed
+        assert!(!html.contains("data:image/png"));
+        assert!(html.contains("<td>Berserk | Deluxe</td>"));
+        assert!(html.trim_end().ends_with("</html>"));
+
+        let path = temp_dir.path().join("library.html");
+        let summary = export_library_report(pool, DEFAULT_PROFILE_ID, &path, ReportFormat::Html, &options, Some(&covers)).await.unwrap();
+        assert_eq!(summary, ReportSummary { entries: 3, covers_embedded: 1 });
+        assert!(path.exists());
+    }
+}
@@ -2,9 +2,13 @@
 //
 // Handles CRUD operations for media (anime/manga) metadata
 
+use std::collections::HashSet;
+
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use super::genres::{is_empty_list, load_genre_map, normalize_genres_json};
+use crate::jikan::covers::USER_SOURCE;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MediaEntry {
@@ -56,81 +60,319 @@ pub struct ContinueReadingEntry {
     pub last_read: String,
 }
 
-/// Save or update media details
+/// What a save_media call did to the stored row
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MediaSaveReport {
+    /// Whether the media wasn't stored before
+    pub created: bool,
+    /// Fields the save changed
+    pub updated: Vec<&'static str>,
+    /// Stored fields kept because the save had no value for them, a smaller
+    /// episode count, or a value the user set
+    pub preserved: Vec<&'static str>,
+}
+
+/// Field-by-field merge of an incoming entry into the stored one
+struct Merge {
+    report: MediaSaveReport,
+    /// Fields whose stored value the user set (media_field_provenance)
+    user_set: HashSet<String>,
+}
+
+impl Merge {
+    /// The value to store for `name`: the incoming one when there is one,
+    /// the stored one otherwise or when the user set it
+    fn field<T: PartialEq>(&mut self, name: &'static str, stored: Option<T>, incoming: Option<T>) -> Option<T> {
+        if self.user_set.contains(name) {
+            if incoming.is_some() && incoming != stored {
+                self.report.preserved.push(name);
+            }
+            return stored;
+        }
+        match (stored, incoming) {
+            (stored, Some(incoming)) => {
+                if stored.as_ref() != Some(&incoming) {
+                    self.report.updated.push(name);
+                }
+                Some(incoming)
+            }
+            (Some(stored), None) => {
+                self.report.preserved.push(name);
+                Some(stored)
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// A text value worth storing: blank strings count as missing
+fn text(value: &Option<String>) -> Option<String> {
+    value.as_ref().filter(|v| !v.trim().is_empty()).cloned()
+}
+
+/// Save media details. Screens save whatever partial entry they have, so a
+/// save only fills in or replaces the fields it has a value for: missing or
+/// blank fields (and an empty genre list) keep what's stored, and the
+/// episode count never goes down (clear_media_fields removes values).
+/// Fields the user set, by clearing them or picking a cover, keep their
+/// value. updated_at is bumped either way. Runs in one transaction and reports
+/// which fields changed and which were kept.
+///
+/// Genres are stored normalized (see database::genres), with the source's
+/// own names kept in genres_raw.
 pub async fn save_media(
     pool: &SqlitePool,
     media: &MediaEntry,
-) -> Result<()> {
-    sqlx::query(
+) -> Result<MediaSaveReport> {
+    use sqlx::Row;
+
+    let genre_map = load_genre_map(pool).await?;
+    let mut tx = pool.begin().await?;
+
+    let stored = sqlx::query(
         r#"
-        INSERT INTO media (
-            id, extension_id, title, english_name, native_name, description,
-            cover_url, banner_url, trailer_url, media_type, content_type, status,
+        SELECT
+            title, english_name, native_name, description,
+            cover_url, banner_url, trailer_url, content_type, status,
             year, rating, episode_count, episode_duration,
             season_quarter, season_year,
             aired_start_year, aired_start_month, aired_start_date,
-            genres, created_at, updated_at
-        )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-        ON CONFLICT(id) DO UPDATE SET
-            title = ?,
-            english_name = ?,
-            native_name = ?,
-            description = ?,
-            cover_url = ?,
-            banner_url = ?,
-            trailer_url = ?,
-            status = ?,
-            year = ?,
-            rating = ?,
-            episode_count = ?,
-            genres = COALESCE(?, genres),
-            updated_at = CURRENT_TIMESTAMP
+            genres, genres_raw
+        FROM media
+        WHERE id = ?
         "#
     )
     .bind(&media.id)
-    .bind(&media.extension_id)
-    .bind(&media.title)
-    .bind(&media.english_name)
-    .bind(&media.native_name)
-    .bind(&media.description)
-    .bind(&media.cover_url)
-    .bind(&media.banner_url)
-    .bind(&media.trailer_url)
-    .bind(&media.media_type)
-    .bind(&media.content_type)
-    .bind(&media.status)
-    .bind(media.year)
-    .bind(media.rating)
-    .bind(media.episode_count)
-    .bind(media.episode_duration)
-    .bind(&media.season_quarter)
-    .bind(media.season_year)
-    .bind(media.aired_start_year)
-    .bind(media.aired_start_month)
-    .bind(media.aired_start_date)
-    .bind(&media.genres)
-    // For UPDATE
-    .bind(&media.title)
-    .bind(&media.english_name)
-    .bind(&media.native_name)
-    .bind(&media.description)
-    .bind(&media.cover_url)
-    .bind(&media.banner_url)
-    .bind(&media.trailer_url)
-    .bind(&media.status)
-    .bind(media.year)
-    .bind(media.rating)
-    .bind(media.episode_count)
-    .bind(&media.genres)
-    .execute(pool)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    log::debug!("Saved media: {}", media.id);
+    let user_set: HashSet<String> = sqlx::query_scalar(
+        "SELECT field FROM media_field_provenance WHERE media_id = ? AND source = ?",
+    )
+    .bind(&media.id)
+    .bind(USER_SOURCE)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut merge = Merge {
+        report: MediaSaveReport { created: stored.is_none(), ..Default::default() },
+        user_set,
+    };
+    // A stored column; None for new media
+    macro_rules! stored {
+        ($column:literal) => {
+            match &stored {
+                Some(row) => row.try_get($column)?,
+                None => None,
+            }
+        };
+    }
+
+    let title = merge
+        .field("title", stored!("title"), Some(media.title.clone()).filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(|| media.title.clone());
+    let english_name = merge.field("english_name", stored!("english_name"), text(&media.english_name));
+    let native_name = merge.field("native_name", stored!("native_name"), text(&media.native_name));
+    let description = merge.field("description", stored!("description"), text(&media.description));
+    let cover_url = merge.field("cover_url", stored!("cover_url"), text(&media.cover_url));
+    let banner_url = merge.field("banner_url", stored!("banner_url"), text(&media.banner_url));
+    let trailer_url = merge.field("trailer_url", stored!("trailer_url"), text(&media.trailer_url));
+    let content_type = merge.field("content_type", stored!("content_type"), text(&media.content_type));
+    let status = merge.field("status", stored!("status"), text(&media.status));
+    let year = merge.field("year", stored!("year"), media.year);
+    let rating = merge.field("rating", stored!("rating"), media.rating);
+
+    // Sources that list only the episodes out so far mustn't shrink a
+    // count another source knew more about
+    let stored_episode_count: Option<i32> = stored!("episode_count");
+    let incoming_episode_count = media
+        .episode_count
+        .filter(|count| stored_episode_count.map_or(true, |stored| *count >= stored));
+    let episode_count = merge.field("episode_count", stored_episode_count, incoming_episode_count);
+
+    let episode_duration = merge.field("episode_duration", stored!("episode_duration"), media.episode_duration);
+    let season_quarter = merge.field("season_quarter", stored!("season_quarter"), text(&media.season_quarter));
+    let season_year = merge.field("season_year", stored!("season_year"), media.season_year);
+    let aired_start_year = merge.field("aired_start_year", stored!("aired_start_year"), media.aired_start_year);
+    let aired_start_month = merge.field("aired_start_month", stored!("aired_start_month"), media.aired_start_month);
+    let aired_start_date = merge.field("aired_start_date", stored!("aired_start_date"), media.aired_start_date);
+
+    // Compared by the source's names, since the stored genres are normalized.
+    // Rows saved before normalization have no genres_raw.
+    let stored_genres: Option<String> = stored!("genres");
+    let stored_genres_raw: Option<String> = stored!("genres_raw");
+    let incoming_genres = text(&media.genres).filter(|raw| !is_empty_list(raw));
+    merge.field("genres", stored_genres_raw.clone().or(stored_genres.clone()), incoming_genres.clone());
+    let incoming_genres = incoming_genres.filter(|_| !merge.user_set.contains("genres"));
+    let (genres, genres_raw) = match incoming_genres {
+        Some(raw) => (Some(normalize_genres_json(&raw, &genre_map)), Some(raw)),
+        None => (stored_genres, stored_genres_raw),
+    };
+
+    let query = if merge.report.created {
+        r#"
+        INSERT INTO media (
+            title, english_name, native_name, description,
+            cover_url, banner_url, trailer_url, content_type, status,
+            year, rating, episode_count, episode_duration,
+            season_quarter, season_year,
+            aired_start_year, aired_start_month, aired_start_date,
+            genres, genres_raw, id, extension_id, media_type, created_at, updated_at
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        "#
+    } else {
+        r#"
+        UPDATE media SET
+            title = ?, english_name = ?, native_name = ?, description = ?,
+            cover_url = ?, banner_url = ?, trailer_url = ?, content_type = ?, status = ?,
+            year = ?, rating = ?, episode_count = ?, episode_duration = ?,
+            season_quarter = ?, season_year = ?,
+            aired_start_year = ?, aired_start_month = ?, aired_start_date = ?,
+            genres = ?, genres_raw = ?,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#
+    };
+    let mut query = sqlx::query(query)
+        .bind(&title)
+        .bind(&english_name)
+        .bind(&native_name)
+        .bind(&description)
+        .bind(&cover_url)
+        .bind(&banner_url)
+        .bind(&trailer_url)
+        .bind(&content_type)
+        .bind(&status)
+        .bind(year)
+        .bind(rating)
+        .bind(episode_count)
+        .bind(episode_duration)
+        .bind(&season_quarter)
+        .bind(season_year)
+        .bind(aired_start_year)
+        .bind(aired_start_month)
+        .bind(aired_start_date)
+        .bind(&genres)
+        .bind(&genres_raw)
+        .bind(&media.id);
+    if merge.report.created {
+        query = query.bind(&media.extension_id).bind(&media.media_type);
+    }
+    query.execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    log::debug!(
+        "Saved media {}: updated {:?}, kept {:?}",
+        media.id, merge.report.updated, merge.report.preserved
+    );
+
+    Ok(merge.report)
+}
 
+/// Columns clear_media_fields can empty. Kept as a fixed list because the
+/// names are interpolated into SQL; the title is required and can't be.
+const CLEARABLE_FIELDS: &[&str] = &[
+    "english_name",
+    "native_name",
+    "description",
+    "cover_url",
+    "banner_url",
+    "trailer_url",
+    "content_type",
+    "status",
+    "year",
+    "rating",
+    "episode_count",
+    "episode_duration",
+    "season_quarter",
+    "season_year",
+    "aired_start_year",
+    "aired_start_month",
+    "aired_start_date",
+    "genres",
+    "studios",
+    "source_material",
+];
+
+/// Clear fields of a stored media. Saves never remove a value, so this is
+/// how a wrong one goes away. Cleared fields are recorded as set by the
+/// user, which keeps enrichment from filling them in again.
+pub async fn clear_media_fields(pool: &SqlitePool, media_id: &str, fields: &[String]) -> Result<()> {
+    if let Some(field) = fields.iter().find(|f| !CLEARABLE_FIELDS.contains(&f.as_str())) {
+        return Err(anyhow::anyhow!("Field can't be cleared: {}", field));
+    }
+
+    let mut tx = pool.begin().await?;
+    for field in fields {
+        // The source's genre names go with the normalized ones
+        let columns = if field == "genres" {
+            "genres = NULL, genres_raw = NULL".to_string()
+        } else {
+            format!("{} = NULL", field)
+        };
+        let updated = sqlx::query(&format!(
+            "UPDATE media SET {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            columns
+        ))
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(anyhow::anyhow!("Media not found: {}", media_id));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO media_field_provenance (media_id, field, source, recorded_at)
+            VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(media_id, field) DO UPDATE SET source = excluded.source, recorded_at = excluded.recorded_at
+            "#,
+        )
+        .bind(media_id)
+        .bind(field)
+        .bind(USER_SOURCE)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    log::debug!("Cleared {:?} of media {}", fields, media_id);
     Ok(())
 }
 
+/// Store the broadcast schedule of details a command just fetched: when the
+/// latest episode aired (an ISO 8601 timestamp) and the interval between
+/// episodes. Details without one leave the stored schedule alone. Fetching
+/// isn't failed over this, so errors are only logged.
+pub async fn record_broadcast_schedule(
+    pool: &SqlitePool,
+    media_id: &str,
+    last_update_end: Option<&str>,
+    broadcast_interval: Option<u64>,
+) {
+    let (Some(last_update_end), Some(interval)) = (last_update_end, broadcast_interval) else {
+        return;
+    };
+    let Ok(last_aired) = chrono::DateTime::parse_from_rfc3339(last_update_end) else {
+        log::debug!("Ignoring unparseable last episode time of {}: {}", media_id, last_update_end);
+        return;
+    };
+
+    if let Err(e) = sqlx::query("UPDATE media SET last_aired_at = ?, broadcast_interval = ? WHERE id = ?")
+        .bind(last_aired.timestamp_millis())
+        .bind(interval as i64)
+        .bind(media_id)
+        .execute(pool)
+        .await
+    {
+        log::warn!("Failed to record the broadcast schedule of {}: {}", media_id, e);
+    }
+}
+
 /// Get media by ID
 #[allow(dead_code)]
 pub async fn get_media(
@@ -165,6 +407,7 @@ pub async fn get_media(
 /// - Anime where the final episode is completed or >= 90% watched
 pub async fn get_continue_watching_with_media(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<Vec<ContinueWatchingEntry>> {
     // Use a CTE to get the most recent watch entry per media, then filter
@@ -175,13 +418,14 @@ pub async fn get_continue_watching_with_media(
                 w.*,
                 ROW_NUMBER() OVER (PARTITION BY w.media_id ORDER BY w.last_watched DESC) as rn
             FROM watch_history w
-            WHERE w.progress_seconds > 0
+            WHERE w.profile_id = ?1 AND w.progress_seconds > 0
         ),
         max_completed AS (
             SELECT
                 media_id,
                 MAX(CASE WHEN completed = 1 THEN episode_number ELSE 0 END) as max_completed_ep
             FROM watch_history
+            WHERE profile_id = ?1
             GROUP BY media_id
         )
         SELECT DISTINCT
@@ -217,9 +461,10 @@ pub async fn get_continue_watching_with_media(
             AND (lw.progress_seconds / lw.duration) >= 0.9
           )
         ORDER BY lw.last_watched DESC
-        LIMIT ?
+        LIMIT ?2
         "#
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -277,6 +522,7 @@ pub async fn get_continue_watching_with_media(
 /// - Manga where the final chapter is completed or >= 90% read
 pub async fn get_continue_reading_with_media(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<Vec<ContinueReadingEntry>> {
     // Use a CTE to get the most recent read entry per media, then filter
@@ -287,13 +533,14 @@ pub async fn get_continue_reading_with_media(
                 r.*,
                 ROW_NUMBER() OVER (PARTITION BY r.media_id ORDER BY r.last_read DESC) as rn
             FROM reading_history r
-            WHERE r.current_page > 0
+            WHERE r.profile_id = ?1 AND r.current_page > 0
         ),
         max_completed_chapter AS (
             SELECT
                 media_id,
                 MAX(CASE WHEN completed = 1 THEN chapter_number ELSE 0 END) as max_completed_ch
             FROM reading_history
+            WHERE profile_id = ?1
             GROUP BY media_id
         )
         SELECT DISTINCT
@@ -329,9 +576,10 @@ pub async fn get_continue_reading_with_media(
             AND (CAST(lr.current_page AS REAL) / CAST(lr.total_pages AS REAL)) >= 0.9
           )
         ORDER BY lr.last_read DESC
-        LIMIT ?
+        LIMIT ?2
         "#
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -390,6 +638,11 @@ pub struct DownloadWithMedia {
     pub cover_url: Option<String>,
     pub episode_count: i32,
     pub total_size: i64,
+    /// Distinct source qualities among the downloaded episodes (e.g. ["1080p", "720p"])
+    pub qualities: Vec<String>,
+    /// No media row exists for media_id; title is a placeholder and there's
+    /// no cover (see repair_download_media_links)
+    pub media_missing: bool,
 }
 
 pub async fn get_downloads_with_media(pool: &SqlitePool) -> Result<Vec<DownloadWithMedia>> {
@@ -397,10 +650,13 @@ pub async fn get_downloads_with_media(pool: &SqlitePool) -> Result<Vec<DownloadW
         r#"
         SELECT
             d.media_id,
-            m.title,
+            COALESCE(m.title, MAX(d.media_title)) as title,
+            MIN(d.filename) as filename,
+            m.id IS NULL as media_missing,
             m.cover_url,
             COUNT(DISTINCT d.episode_number) as episode_count,
-            GROUP_CONCAT(d.file_path) as file_paths
+            GROUP_CONCAT(d.file_path) as file_paths,
+            GROUP_CONCAT(DISTINCT d.quality) as qualities
         FROM downloads d
         LEFT JOIN media m ON d.media_id = m.id
         WHERE d.status = 'completed'
@@ -415,7 +671,7 @@ pub async fn get_downloads_with_media(pool: &SqlitePool) -> Result<Vec<DownloadW
     for row in entries {
         use sqlx::Row;
 
-        // If media doesn't exist, extract title from downloads
+        // Without a media row the title stored with the downloads is used
         let title: Option<String> = row.try_get("title").ok();
         let media_id: String = row.try_get("media_id")?;
         let file_paths_str: Option<String> = row.try_get("file_paths").ok();
@@ -433,12 +689,25 @@ pub async fn get_downloads_with_media(pool: &SqlitePool) -> Result<Vec<DownloadW
         result.push(DownloadWithMedia {
             media_id: media_id.clone(),
             title: title.unwrap_or_else(|| {
-                // Fallback: extract from media_id or use placeholder
-                media_id.replace('_', " ")
+                // Downloads queued before titles were stored: the
+                // Title_EP1_quality.mp4 filename convention, or a placeholder
+                let filename: String = row.try_get("filename").unwrap_or_default();
+                if filename.contains("_EP") {
+                    crate::downloads::batch::title_from_filename(&filename)
+                } else {
+                    crate::downloads::media_links::UNKNOWN_SERIES_TITLE.to_string()
+                }
             }),
+            media_missing: row.try_get("media_missing")?,
             cover_url: row.try_get("cover_url").ok().flatten(),
             episode_count: row.try_get("episode_count")?,
             total_size,
+            qualities: row
+                .try_get::<Option<String>, _>("qualities")
+                .ok()
+                .flatten()
+                .map(|q| q.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
         });
     }
 
@@ -597,3 +866,169 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for MediaEntry {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use sqlx::Row;
+    use tempfile::tempdir;
+
+    fn sparse(id: &str) -> MediaEntry {
+        MediaEntry {
+            id: id.to_string(),
+            extension_id: "ext".to_string(),
+            title: "Frieren".to_string(),
+            english_name: None,
+            native_name: None,
+            description: None,
+            cover_url: Some("https://example.test/cover.jpg".to_string()),
+            banner_url: None,
+            trailer_url: None,
+            media_type: "anime".to_string(),
+            content_type: None,
+            status: None,
+            year: None,
+            rating: None,
+            episode_count: Some(10),
+            episode_duration: None,
+            season_quarter: None,
+            season_year: None,
+            aired_start_year: None,
+            aired_start_month: None,
+            aired_start_date: None,
+            genres: Some("[]".to_string()),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn rich(id: &str) -> MediaEntry {
+        MediaEntry {
+            english_name: Some("Frieren: Beyond Journey's End".to_string()),
+            description: Some("An elf mage outlives her party.".to_string()),
+            banner_url: Some("https://example.test/banner.jpg".to_string()),
+            status: Some("Finished Airing".to_string()),
+            year: Some(2023),
+            rating: Some(9.1),
+            episode_count: Some(28),
+            genres: Some(r#"["Adventure","Fantasy"]"#.to_string()),
+            ..sparse(id)
+        }
+    }
+
+    async fn stored(pool: &SqlitePool, id: &str) -> (Option<String>, Option<String>, Option<i32>, Option<String>, Option<String>) {
+        let row = sqlx::query("SELECT english_name, banner_url, episode_count, genres, updated_at FROM media WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (
+            row.get("english_name"),
+            row.get("banner_url"),
+            row.get("episode_count"),
+            row.get("genres"),
+            row.get("updated_at"),
+        )
+    }
+
+    #[tokio::test]
+    async fn a_sparse_save_keeps_what_a_rich_one_stored() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let report = save_media(pool, &rich("m1")).await.unwrap();
+        assert!(report.created);
+        assert!(report.updated.contains(&"genres") && report.preserved.is_empty());
+
+        sqlx::query("UPDATE media SET updated_at = '2000-01-01 00:00:00' WHERE id = 'm1'")
+            .execute(pool)
+            .await
+            .unwrap();
+        let mut update = sparse("m1");
+        update.cover_url = Some("https://example.test/new-cover.jpg".to_string());
+        update.title = "  ".to_string();
+        let report = save_media(pool, &update).await.unwrap();
+
+        assert!(!report.created);
+        assert_eq!(report.updated, vec!["cover_url"]);
+        for field in ["title", "english_name", "description", "banner_url", "status", "year", "rating", "episode_count", "genres"] {
+            assert!(report.preserved.contains(&field), "{} should be kept", field);
+        }
+
+        let (english_name, banner_url, episode_count, genres, updated_at) = stored(pool, "m1").await;
+        assert_eq!(english_name.as_deref(), Some("Frieren: Beyond Journey's End"));
+        assert_eq!(banner_url.as_deref(), Some("https://example.test/banner.jpg"));
+        assert_eq!(episode_count, Some(28), "the episode count never goes down");
+        assert_eq!(genres.as_deref(), Some(r#"["Adventure","Fantasy"]"#));
+        assert_ne!(updated_at.as_deref(), Some("2000-01-01 00:00:00"));
+        let media = get_media(pool, "m1").await.unwrap().unwrap();
+        assert_eq!(media.title, "Frieren");
+        assert_eq!(media.cover_url.as_deref(), Some("https://example.test/new-cover.jpg"));
+    }
+
+    #[tokio::test]
+    async fn a_rich_save_fills_in_a_sparse_row() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let report = save_media(pool, &sparse("m1")).await.unwrap();
+        assert!(report.created);
+        assert!(!report.updated.contains(&"genres"), "an empty genre list isn't a value");
+
+        let report = save_media(pool, &rich("m1")).await.unwrap();
+        assert!(!report.created);
+        let mut updated = report.updated.clone();
+        updated.sort();
+        assert_eq!(
+            updated,
+            vec!["banner_url", "description", "english_name", "episode_count", "genres", "rating", "status", "year"]
+        );
+        assert!(report.preserved.is_empty());
+
+        let (english_name, banner_url, episode_count, genres, _) = stored(pool, "m1").await;
+        assert_eq!(english_name.as_deref(), Some("Frieren: Beyond Journey's End"));
+        assert_eq!(banner_url.as_deref(), Some("https://example.test/banner.jpg"));
+        assert_eq!(episode_count, Some(28));
+        assert_eq!(genres.as_deref(), Some(r#"["Adventure","Fantasy"]"#));
+
+        // Saving the same entry again changes nothing
+        let report = save_media(pool, &rich("m1")).await.unwrap();
+        assert!(report.updated.is_empty() && report.preserved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleared_fields_are_emptied_and_marked_as_user_set() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        save_media(pool, &rich("m1")).await.unwrap();
+        clear_media_fields(pool, "m1", &["banner_url".to_string(), "genres".to_string()]).await.unwrap();
+
+        let (english_name, banner_url, _, genres, _) = stored(pool, "m1").await;
+        assert_eq!(english_name.as_deref(), Some("Frieren: Beyond Journey's End"));
+        assert_eq!(banner_url, None);
+        assert_eq!(genres, None);
+        let sources: Vec<String> = sqlx::query_scalar("SELECT source FROM media_field_provenance WHERE media_id = 'm1' ORDER BY field")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        assert_eq!(sources, vec![USER_SOURCE, USER_SOURCE]);
+
+        // Sources don't fill them in again
+        let report = save_media(pool, &rich("m1")).await.unwrap();
+        let mut preserved = report.preserved.clone();
+        preserved.sort();
+        assert_eq!(preserved, vec!["banner_url", "genres"]);
+        assert!(report.updated.is_empty());
+        let (_, banner_url, _, genres, _) = stored(pool, "m1").await;
+        assert_eq!((banner_url, genres), (None, None));
+
+        assert!(clear_media_fields(pool, "m1", &["title".to_string()]).await.is_err());
+        assert!(clear_media_fields(pool, "m1", &["id = 'x'; --".to_string()]).await.is_err());
+        assert!(clear_media_fields(pool, "missing", &["year".to_string()]).await.is_err());
+    }
+}
@@ -11,10 +11,12 @@ use crate::jikan::types::*;
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
+
+use crate::events::MIGRATION_PROGRESS_EVENT;
 
 /// Progress information emitted to frontend during migration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct MigrationProgress {
     pub total: usize,
     pub processed: usize,
@@ -264,7 +266,7 @@ pub async fn run_migration(pool: SqlitePool, app_handle: AppHandle) -> Result<()
 /// Emit current progress to the frontend
 fn emit_progress(app_handle: &AppHandle) {
     let progress = MIGRATION_PROGRESS.lock().unwrap().clone();
-    let _ = app_handle.emit("migration_progress", &progress);
+    MIGRATION_PROGRESS_EVENT.emit(app_handle, &progress);
 }
 
 /// Search Jikan API for an anime title match, returning (mal_id_string, JikanSearchData)
@@ -576,22 +578,23 @@ async fn merge_into_existing(
         .map_err(|e| format!("Failed to begin merge transaction: {}", e))?;
 
     // Reparent watch history (INSERT OR IGNORE to skip duplicates)
-    let watch_rows: Vec<(i32, f64, Option<f64>, bool, String)> = sqlx::query_as(
-        "SELECT episode_number, progress_seconds, duration, completed, last_watched FROM watch_history WHERE media_id = ?",
+    let watch_rows: Vec<(i64, i32, f64, Option<f64>, bool, String)> = sqlx::query_as(
+        "SELECT profile_id, episode_number, progress_seconds, duration, completed, last_watched FROM watch_history WHERE media_id = ?",
     )
     .bind(old_id)
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| format!("Failed to fetch watch history for merge: {}", e))?;
 
-    for (ep_num, progress, duration, completed, last_watched) in &watch_rows {
+    for (profile_id, ep_num, progress, duration, completed, last_watched) in &watch_rows {
         let new_ep_id = format!("{}-{}", mal_id, ep_num);
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO watch_history (media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT OR IGNORE INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
+        .bind(profile_id)
         .bind(mal_id)
         .bind(&new_ep_id)
         .bind(ep_num)
@@ -608,7 +611,7 @@ async fn merge_into_existing(
 
     // Library: INSERT OR IGNORE (keep existing)
     sqlx::query(
-        "INSERT OR IGNORE INTO library (media_id, status) SELECT ?, status FROM library WHERE media_id = ?",
+        "INSERT OR IGNORE INTO library (profile_id, media_id, status) SELECT profile_id, ?, status FROM library WHERE media_id = ?",
     )
     .bind(mal_id)
     .bind(old_id)
@@ -8,20 +8,28 @@
 
 use sqlx::{sqlite::{SqliteConnectOptions, SqlitePoolOptions}, SqlitePool, Row};
 use std::path::PathBuf;
+use std::str::FromStr;
 use anyhow::{Result, Context};
 
 pub mod watch_history;
 pub mod reading_history;
+pub mod reading_speed;
 pub mod history;
 pub mod stats;
 pub mod library;
 pub mod media;
+pub mod genres;
 pub mod tags;
 pub mod export_import;
 pub mod discover_cache;
 pub mod migration_runner;
 pub mod recommendations;
 pub mod feedback;
+pub mod hidden_media;
+pub mod age_rating;
+pub mod profiles;
+pub mod library_report;
+pub mod stale_library;
 
 /// Database manager with connection pooling
 pub struct Database {
@@ -31,6 +39,19 @@ pub struct Database {
 impl Database {
     /// Initialize database with connection pooling
     pub async fn new(db_path: PathBuf) -> Result<Self> {
+        let db = Self::open_without_migrations(db_path).await?;
+
+        // Run migrations
+        db.run_migrations().await?;
+
+        log::debug!("Database initialized successfully");
+
+        Ok(db)
+    }
+
+    /// Open (or create) the database without running migrations; safe mode
+    /// uses this directly
+    pub async fn open_without_migrations(db_path: PathBuf) -> Result<Self> {
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
             tokio::fs::create_dir_all(parent)
@@ -58,13 +79,42 @@ impl Database {
 
         log::debug!("Database connection pool created");
 
-        let db = Self { pool };
+        Ok(Self { pool })
+    }
 
-        // Run migrations
-        db.run_migrations().await?;
+    /// Open an existing database read-only, for safe mode when it can't be
+    /// opened normally
+    pub async fn open_read_only(db_path: PathBuf) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .read_only(true);
 
-        log::debug!("Database initialized successfully");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .context("Failed to open database read-only")?;
+
+        Ok(Self { pool })
+    }
+
+    /// An empty, migrated database in memory, for safe mode when the file
+    /// can't be opened at all. It lives on a single connection that's never
+    /// closed, since the data goes with it.
+    pub async fn open_in_memory() -> Result<Self> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?.foreign_keys(true);
 
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .context("Failed to create in-memory database")?;
+
+        let db = Self { pool };
+        db.run_migrations().await?;
         Ok(db)
     }
 
@@ -112,6 +162,42 @@ impl Database {
             ("022_clear_mappings_v5.sql", include_str!("../../migrations/022_clear_mappings_v5.sql")),
             ("023_feedback_table.sql", include_str!("../../migrations/023_feedback_table.sql")),
             ("024_library_auto_download.sql", include_str!("../../migrations/024_library_auto_download.sql")),
+            ("025_profiles.sql", include_str!("../../migrations/025_profiles.sql")),
+            ("026_download_archive.sql", include_str!("../../migrations/026_download_archive.sql")),
+            ("027_media_enrichment.sql", include_str!("../../migrations/027_media_enrichment.sql")),
+            ("028_download_quality.sql", include_str!("../../migrations/028_download_quality.sql")),
+            ("029_release_digest.sql", include_str!("../../migrations/029_release_digest.sql")),
+            ("030_download_file_state.sql", include_str!("../../migrations/030_download_file_state.sql")),
+            ("031_chapter_page_dimensions.sql", include_str!("../../migrations/031_chapter_page_dimensions.sql")),
+            ("032_season_pass.sql", include_str!("../../migrations/032_season_pass.sql")),
+            ("033_library_cursor_index.sql", include_str!("../../migrations/033_library_cursor_index.sql")),
+            ("034_reading_speed.sql", include_str!("../../migrations/034_reading_speed.sql")),
+            ("035_release_tracking_opt_in.sql", include_str!("../../migrations/035_release_tracking_opt_in.sql")),
+            ("036_numbering_offsets.sql", include_str!("../../migrations/036_numbering_offsets.sql")),
+            ("037_download_batches.sql", include_str!("../../migrations/037_download_batches.sql")),
+            ("038_genre_normalization.sql", include_str!("../../migrations/038_genre_normalization.sql")),
+            ("039_stats_history.sql", include_str!("../../migrations/039_stats_history.sql")),
+            ("040_download_source_extension.sql", include_str!("../../migrations/040_download_source_extension.sql")),
+            ("041_status_mappings.sql", include_str!("../../migrations/041_status_mappings.sql")),
+            ("042_split_cour_links.sql", include_str!("../../migrations/042_split_cour_links.sql")),
+            ("043_download_media_title.sql", include_str!("../../migrations/043_download_media_title.sql")),
+            ("044_hidden_media.sql", include_str!("../../migrations/044_hidden_media.sql")),
+            ("045_maintenance_runs.sql", include_str!("../../migrations/045_maintenance_runs.sql")),
+            ("046_download_schedule.sql", include_str!("../../migrations/046_download_schedule.sql")),
+            ("047_download_checksums.sql", include_str!("../../migrations/047_download_checksums.sql")),
+            ("048_age_rating.sql", include_str!("../../migrations/048_age_rating.sql")),
+            ("049_download_speed_limit.sql", include_str!("../../migrations/049_download_speed_limit.sql")),
+            ("050_backfill_download_media_title.sql", include_str!("../../migrations/050_backfill_download_media_title.sql")),
+            ("051_download_headers.sql", include_str!("../../migrations/051_download_headers.sql")),
+            ("052_download_subtitles.sql", include_str!("../../migrations/052_download_subtitles.sql")),
+            ("053_download_source_history.sql", include_str!("../../migrations/053_download_source_history.sql")),
+            ("054_download_history.sql", include_str!("../../migrations/054_download_history.sql")),
+            ("055_download_events.sql", include_str!("../../migrations/055_download_events.sql")),
+            ("056_release_preferred_source.sql", include_str!("../../migrations/056_release_preferred_source.sql")),
+            ("057_tracker_sync_queue.sql", include_str!("../../migrations/057_tracker_sync_queue.sql")),
+            ("058_hidden_media_profiles.sql", include_str!("../../migrations/058_hidden_media_profiles.sql")),
+            ("059_media_broadcast_schedule.sql", include_str!("../../migrations/059_media_broadcast_schedule.sql")),
+            ("060_release_source_baselines.sql", include_str!("../../migrations/060_release_source_baselines.sql")),
         ];
 
         for (name, migration_sql) in migrations {
@@ -168,6 +254,17 @@ impl Database {
         Ok(result == 1)
     }
 
+    /// Problems `PRAGMA integrity_check` finds; a single "ok" when there are
+    /// none
+    pub async fn integrity_check(&self) -> Result<Vec<String>> {
+        let rows: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to check database integrity")?;
+
+        Ok(rows)
+    }
+
     /// Get database file size in bytes
     pub async fn get_database_size(&self) -> Result<u64> {
         let size: i64 = sqlx::query_scalar("SELECT page_count * page_size as size FROM pragma_page_count(), pragma_page_size()")
@@ -222,4 +319,48 @@ mod tests {
 
         assert!(size > 0);
     }
+
+    #[tokio::test]
+    async fn download_titles_are_backfilled_from_media() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Steps_EP_Up', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        for (id, media_id, title) in [("d1", "m1", None), ("d2", "m1", Some("Kept")), ("d3", "gone", None)] {
+            sqlx::query(
+                "INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, media_title)
+                 VALUES (?, ?, ?, 1, 'Steps_EP_Up_EP1_1080p.mp4', '', '', ?)",
+            )
+            .bind(id)
+            .bind(media_id)
+            .bind(id)
+            .bind(title)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+
+        // Run again, as it ran on rows saved before the column existed
+        sqlx::raw_sql(include_str!("../../migrations/050_backfill_download_media_title.sql"))
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let titles: Vec<(String, Option<String>)> = sqlx::query_as("SELECT id, media_title FROM downloads ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            titles,
+            vec![
+                ("d1".to_string(), Some("Steps_EP_Up".to_string())),
+                ("d2".to_string(), Some("Kept".to_string())),
+                ("d3".to_string(), None),
+            ]
+        );
+    }
 }
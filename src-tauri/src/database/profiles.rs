@@ -0,0 +1,325 @@
+// Profiles Module
+//
+// Lightweight user profiles so several people can share one install.
+// Library, watch/reading history, notifications and tags are scoped to the
+// active profile; downloads and cached media stay shared.
+//
+// The active profile lives in AppState and is passed to every scoped query
+// helper; it's persisted in app_settings so it survives restarts.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use anyhow::Result;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Profile created by migration 025; owns all pre-profile data
+pub const DEFAULT_PROFILE_ID: i64 = 1;
+
+/// app_settings key holding the active profile id
+const CURRENT_PROFILE_SETTING: &str = "current_profile_id";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+}
+
+/// The active profile, held in AppState
+#[derive(Debug)]
+pub struct CurrentProfile(AtomicI64);
+
+impl CurrentProfile {
+    pub fn new(profile_id: i64) -> Self {
+        Self(AtomicI64::new(profile_id))
+    }
+
+    /// Id of the profile all scoped queries currently target
+    pub fn id(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, profile_id: i64) {
+        self.0.store(profile_id, Ordering::Relaxed);
+    }
+}
+
+impl Default for CurrentProfile {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROFILE_ID)
+    }
+}
+
+/// The persisted active profile, read on startup.
+/// Falls back to the default profile if the stored one no longer exists.
+pub async fn load_current_profile(pool: &SqlitePool) -> Result<i64> {
+    let stored: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = ?"
+    )
+    .bind(CURRENT_PROFILE_SETTING)
+    .fetch_optional(pool)
+    .await?;
+
+    let mut profile_id = stored
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_PROFILE_ID);
+
+    if get_profile(pool, profile_id).await?.is_none() {
+        log::warn!("Stored profile {} no longer exists, using default profile", profile_id);
+        profile_id = DEFAULT_PROFILE_ID;
+    }
+
+    log::debug!("Active profile: {}", profile_id);
+
+    Ok(profile_id)
+}
+
+/// List all profiles, oldest first
+pub async fn list_profiles(pool: &SqlitePool) -> Result<Vec<Profile>> {
+    let profiles = sqlx::query_as::<_, Profile>(
+        "SELECT id, name, created_at FROM profiles ORDER BY id ASC"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(profiles)
+}
+
+/// Get a single profile by id
+pub async fn get_profile(pool: &SqlitePool, profile_id: i64) -> Result<Option<Profile>> {
+    let profile = sqlx::query_as::<_, Profile>(
+        "SELECT id, name, created_at FROM profiles WHERE id = ?"
+    )
+    .bind(profile_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(profile)
+}
+
+/// Create a new, empty profile
+pub async fn create_profile(pool: &SqlitePool, name: &str) -> Result<Profile> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Profile name cannot be empty"));
+    }
+
+    let result = sqlx::query("INSERT INTO profiles (name, created_at) VALUES (?, CURRENT_TIMESTAMP)")
+        .bind(name)
+        .execute(pool)
+        .await?;
+
+    log::debug!("Created profile: {}", name);
+
+    get_profile(pool, result.last_insert_rowid())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created profile"))
+}
+
+/// Make a profile the active one and persist the choice
+pub async fn switch_profile(pool: &SqlitePool, current: &CurrentProfile, profile_id: i64) -> Result<Profile> {
+    let profile = get_profile(pool, profile_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", profile_id))?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    sqlx::query(
+        r#"
+        INSERT INTO app_settings (key, value, updated_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(CURRENT_PROFILE_SETTING)
+    .bind(profile_id.to_string())
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    current.set(profile_id);
+    // Unread notifications are per profile
+    crate::badges::invalidate();
+    log::debug!("Switched to profile {} ({})", profile.id, profile.name);
+
+    Ok(profile)
+}
+
+/// Delete a profile.
+///
+/// With `reassign_to` set, the profile's library, history, tags and
+/// notifications move to that profile first (entries the target already
+/// has are kept as-is). Otherwise everything owned by the profile is
+/// removed through the ON DELETE CASCADE foreign keys.
+///
+/// The default profile can't be deleted. Deleting the active profile
+/// switches back to the default one.
+pub async fn delete_profile(
+    pool: &SqlitePool,
+    current: &CurrentProfile,
+    profile_id: i64,
+    reassign_to: Option<i64>,
+) -> Result<()> {
+    if profile_id == DEFAULT_PROFILE_ID {
+        return Err(anyhow::anyhow!("The default profile cannot be deleted"));
+    }
+
+    if get_profile(pool, profile_id).await?.is_none() {
+        return Err(anyhow::anyhow!("Profile not found: {}", profile_id));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if let Some(target) = reassign_to {
+        if target == profile_id {
+            return Err(anyhow::anyhow!("Cannot reassign a profile's data to itself"));
+        }
+
+        let target_exists: Option<i64> = sqlx::query_scalar("SELECT id FROM profiles WHERE id = ?")
+            .bind(target)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if target_exists.is_none() {
+            return Err(anyhow::anyhow!("Profile not found: {}", target));
+        }
+
+        // OR IGNORE keeps the target's own row when both profiles have one;
+        // the leftovers are removed by the cascade below
+        for table in ["library", "watch_history", "reading_history", "library_tags", "notifications"] {
+            sqlx::query(&format!(
+                "UPDATE OR IGNORE {} SET profile_id = ? WHERE profile_id = ?",
+                table
+            ))
+            .bind(target)
+            .bind(profile_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    // notifications has no foreign key, so clear it explicitly
+    sqlx::query("DELETE FROM notifications WHERE profile_id = ?")
+        .bind(profile_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM profiles WHERE id = ?")
+        .bind(profile_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    log::debug!("Deleted profile {} (reassigned to {:?})", profile_id, reassign_to);
+
+    if current.id() == profile_id {
+        switch_profile(pool, current, DEFAULT_PROFILE_ID).await?;
+    }
+
+    Ok(())
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Profile {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Profile {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::library::{add_to_library, get_library_entry, LibraryStatus};
+    use crate::database::Database;
+
+    async fn setup() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('a', 'ext', 'A', 'anime'), ('b', 'ext', 'B', 'anime')")
+            .execute(database.pool())
+            .await
+            .unwrap();
+        (temp_dir, database)
+    }
+
+    async fn status(pool: &SqlitePool, profile_id: i64, media_id: &str) -> Option<LibraryStatus> {
+        get_library_entry(pool, profile_id, media_id).await.unwrap().map(|entry| entry.status)
+    }
+
+    #[tokio::test]
+    async fn create_profile_trims_and_rejects_bad_names() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+
+        let kids = create_profile(pool, "  Kids ").await.unwrap();
+        assert_eq!(kids.name, "Kids");
+        assert_ne!(kids.id, DEFAULT_PROFILE_ID);
+        assert_eq!(get_profile(pool, kids.id).await.unwrap().unwrap().name, "Kids");
+
+        assert!(create_profile(pool, "   ").await.is_err());
+        assert!(create_profile(pool, "Kids").await.is_err(), "names are unique");
+        assert_eq!(list_profiles(pool).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn switch_profile_updates_state_and_persists() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+        let current = CurrentProfile::default();
+
+        let kids = create_profile(pool, "Kids").await.unwrap();
+        switch_profile(pool, &current, kids.id).await.unwrap();
+        assert_eq!(current.id(), kids.id);
+        assert_eq!(load_current_profile(pool).await.unwrap(), kids.id);
+
+        assert!(switch_profile(pool, &current, 999).await.is_err());
+        assert_eq!(current.id(), kids.id, "a failed switch keeps the active profile");
+    }
+
+    #[tokio::test]
+    async fn delete_profile_reassigns_its_data() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+        let current = CurrentProfile::default();
+
+        let kids = create_profile(pool, "Kids").await.unwrap();
+        add_to_library(pool, DEFAULT_PROFILE_ID, "a", LibraryStatus::Completed).await.unwrap();
+        add_to_library(pool, kids.id, "a", LibraryStatus::Watching).await.unwrap();
+        add_to_library(pool, kids.id, "b", LibraryStatus::PlanToWatch).await.unwrap();
+        switch_profile(pool, &current, kids.id).await.unwrap();
+
+        assert!(delete_profile(pool, &current, kids.id, Some(kids.id)).await.is_err());
+        assert!(delete_profile(pool, &current, kids.id, Some(999)).await.is_err());
+        assert!(delete_profile(pool, &current, DEFAULT_PROFILE_ID, None).await.is_err());
+
+        delete_profile(pool, &current, kids.id, Some(DEFAULT_PROFILE_ID)).await.unwrap();
+
+        assert!(get_profile(pool, kids.id).await.unwrap().is_none());
+        // The target keeps its own entry and gains the ones it didn't have
+        assert_eq!(status(pool, DEFAULT_PROFILE_ID, "a").await, Some(LibraryStatus::Completed));
+        assert_eq!(status(pool, DEFAULT_PROFILE_ID, "b").await, Some(LibraryStatus::PlanToWatch));
+        assert_eq!(status(pool, kids.id, "a").await, None);
+        assert_eq!(current.id(), DEFAULT_PROFILE_ID, "deleting the active profile switches back");
+        assert_eq!(load_current_profile(pool).await.unwrap(), DEFAULT_PROFILE_ID);
+    }
+
+    #[tokio::test]
+    async fn delete_profile_without_reassigning_removes_its_data() {
+        let (_dir, database) = setup().await;
+        let pool = database.pool();
+        let current = CurrentProfile::default();
+
+        let kids = create_profile(pool, "Kids").await.unwrap();
+        add_to_library(pool, kids.id, "b", LibraryStatus::Watching).await.unwrap();
+
+        delete_profile(pool, &current, kids.id, None).await.unwrap();
+
+        assert_eq!(status(pool, kids.id, "b").await, None);
+        assert_eq!(status(pool, DEFAULT_PROFILE_ID, "b").await, None);
+        assert_eq!(current.id(), DEFAULT_PROFILE_ID);
+    }
+}
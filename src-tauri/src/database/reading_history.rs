@@ -32,19 +32,21 @@ pub struct ReadingProgress {
 /// Save or update reading progress
 pub async fn save_reading_progress(
     pool: &SqlitePool,
+    profile_id: i64,
     progress: &ReadingProgress,
 ) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO reading_history (media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read)
-        VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
-        ON CONFLICT(media_id, chapter_id) DO UPDATE SET
+        INSERT INTO reading_history (profile_id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read)
+        VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(profile_id, media_id, chapter_id) DO UPDATE SET
             current_page = ?,
             total_pages = ?,
             completed = ?,
             last_read = CURRENT_TIMESTAMP
         "#
     )
+    .bind(profile_id)
     .bind(&progress.media_id)
     .bind(&progress.chapter_id)
     .bind(progress.chapter_number)
@@ -63,7 +65,7 @@ pub async fn save_reading_progress(
     use super::library::{add_to_library, LibraryStatus};
     let library_status = if progress.completed {
         // Check if all chapters are completed
-        let all_completed = check_all_chapters_completed(pool, &progress.media_id).await?;
+        let all_completed = check_all_chapters_completed(pool, profile_id, &progress.media_id).await?;
         if all_completed {
             LibraryStatus::Completed
         } else {
@@ -74,7 +76,7 @@ pub async fn save_reading_progress(
     };
 
     // Add/update library entry (ON CONFLICT will update if already exists)
-    if let Err(e) = add_to_library(pool, &progress.media_id, library_status).await {
+    if let Err(e) = add_to_library(pool, profile_id, &progress.media_id, library_status).await {
         log::warn!("Failed to add manga to library: {}", e);
         // Don't fail the entire operation if library update fails
     }
@@ -85,6 +87,7 @@ pub async fn save_reading_progress(
 /// Check if all chapters of a manga are completed
 async fn check_all_chapters_completed(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<bool> {
     // Get total chapter count from media table (stored in episode_count for manga)
@@ -98,8 +101,9 @@ async fn check_all_chapters_completed(
     if let Some(total) = chapter_count {
         // Count completed chapters in reading history
         let completed_count: i32 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM reading_history WHERE media_id = ? AND completed = 1"
+            "SELECT COUNT(*) FROM reading_history WHERE profile_id = ? AND media_id = ? AND completed = 1"
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_one(pool)
         .await?;
@@ -114,15 +118,17 @@ async fn check_all_chapters_completed(
 /// Get reading progress for a specific chapter
 pub async fn get_reading_progress(
     pool: &SqlitePool,
+    profile_id: i64,
     chapter_id: &str,
 ) -> Result<Option<ReadingHistory>> {
     let progress = sqlx::query_as::<_, ReadingHistory>(
         r#"
         SELECT id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
         FROM reading_history
-        WHERE chapter_id = ?
+        WHERE profile_id = ? AND chapter_id = ?
         "#
     )
+    .bind(profile_id)
     .bind(chapter_id)
     .fetch_optional(pool)
     .await?;
@@ -134,16 +140,18 @@ pub async fn get_reading_progress(
 #[allow(dead_code)]
 pub async fn get_manga_reading_history(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Vec<ReadingHistory>> {
     let history = sqlx::query_as::<_, ReadingHistory>(
         r#"
         SELECT id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
         FROM reading_history
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         ORDER BY chapter_number ASC
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_all(pool)
     .await?;
@@ -154,17 +162,19 @@ pub async fn get_manga_reading_history(
 /// Get the most recently read chapter for a manga (for Resume Reading)
 pub async fn get_latest_reading_progress_for_media(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Option<ReadingHistory>> {
     let progress = sqlx::query_as::<_, ReadingHistory>(
         r#"
         SELECT id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
         FROM reading_history
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         ORDER BY last_read DESC
         LIMIT 1
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_optional(pool)
     .await?;
@@ -175,18 +185,21 @@ pub async fn get_latest_reading_progress_for_media(
 /// Get continue reading list (recently read, not completed)
 pub async fn get_continue_reading(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<Vec<ReadingHistory>> {
     let history = sqlx::query_as::<_, ReadingHistory>(
         r#"
         SELECT DISTINCT r.id, r.media_id, r.chapter_id, r.chapter_number, r.current_page, r.total_pages, r.completed, r.last_read, r.created_at
         FROM reading_history r
-        WHERE r.completed = 0
+        WHERE r.profile_id = ?
+        AND r.completed = 0
         AND r.current_page > 0
         ORDER BY r.last_read DESC
         LIMIT ?
         "#
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -198,15 +211,17 @@ pub async fn get_continue_reading(
 #[allow(dead_code)]
 pub async fn mark_chapter_completed(
     pool: &SqlitePool,
+    profile_id: i64,
     chapter_id: &str,
 ) -> Result<()> {
     sqlx::query(
         r#"
         UPDATE reading_history
         SET completed = 1, last_read = CURRENT_TIMESTAMP
-        WHERE chapter_id = ?
+        WHERE profile_id = ? AND chapter_id = ?
         "#
     )
+    .bind(profile_id)
     .bind(chapter_id)
     .execute(pool)
     .await?;
@@ -217,9 +232,11 @@ pub async fn mark_chapter_completed(
 /// Delete reading history for a manga
 pub async fn delete_manga_reading_history(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<()> {
-    sqlx::query("DELETE FROM reading_history WHERE media_id = ?")
+    sqlx::query("DELETE FROM reading_history WHERE profile_id = ? AND media_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .execute(pool)
         .await?;
@@ -227,6 +244,94 @@ pub async fn delete_manga_reading_history(
     Ok(())
 }
 
+/// Delete the reading history of a single chapter, returning the deleted
+/// rows so the deletion can be undone with restore_reading_history
+pub async fn delete_chapter_reading_history(
+    pool: &SqlitePool,
+    profile_id: i64,
+    chapter_id: &str,
+) -> Result<Vec<ReadingHistory>> {
+    let deleted = sqlx::query_as::<_, ReadingHistory>(
+        r#"
+        DELETE FROM reading_history
+        WHERE profile_id = ? AND chapter_id = ?
+        RETURNING id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
+        "#
+    )
+    .bind(profile_id)
+    .bind(chapter_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deleted)
+}
+
+/// Delete reading history for chapters from_chapter..=to_chapter of a manga,
+/// returning the deleted rows
+pub async fn delete_reading_history_range(
+    pool: &SqlitePool,
+    profile_id: i64,
+    media_id: &str,
+    from_chapter: f64,
+    to_chapter: f64,
+) -> Result<Vec<ReadingHistory>> {
+    let mut deleted = sqlx::query_as::<_, ReadingHistory>(
+        r#"
+        DELETE FROM reading_history
+        WHERE profile_id = ? AND media_id = ? AND chapter_number BETWEEN ? AND ?
+        RETURNING id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at
+        "#
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .bind(from_chapter.min(to_chapter))
+    .bind(from_chapter.max(to_chapter))
+    .fetch_all(pool)
+    .await?;
+
+    deleted.sort_by(|a, b| a.chapter_number.total_cmp(&b.chapter_number));
+    Ok(deleted)
+}
+
+/// Put deleted reading history rows back as they were (undo). Chapters that
+/// have been read again since keep their newer progress. Returns the number
+/// of rows restored.
+pub async fn restore_reading_history(
+    pool: &SqlitePool,
+    profile_id: i64,
+    entries: &[ReadingHistory],
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut restored = 0;
+
+    for entry in entries {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO reading_history
+                (id, profile_id, media_id, chapter_id, chapter_number, current_page, total_pages, completed, last_read, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(entry.id)
+        .bind(profile_id)
+        .bind(&entry.media_id)
+        .bind(&entry.chapter_id)
+        .bind(entry.chapter_number)
+        .bind(entry.current_page)
+        .bind(entry.total_pages)
+        .bind(entry.completed)
+        .bind(&entry.last_read)
+        .bind(&entry.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        restored += result.rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(restored)
+}
+
 impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ReadingHistory {
     fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
         use sqlx::Row;
@@ -0,0 +1,398 @@
+// Reading Speed Module
+//
+// Learns how long the user spends per page so the reader can show "about N
+// minutes left". The reader opens a session when a chapter is shown, reports
+// every page turn, and closes it when the chapter is left. Sessions live in
+// memory; closing one folds its samples into the reading_speed table, per
+// media and globally.
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+
+/// reading_speed.media_id of the per-profile aggregate over every title
+const GLOBAL_MEDIA_ID: &str = "";
+
+/// Quicker turns are flipping past pages rather than reading them
+const MIN_PAGE_SECONDS: f64 = 1.0;
+
+/// Slower turns mean the user stepped away
+const MAX_PAGE_SECONDS: f64 = 5.0 * 60.0;
+
+/// Largest forward step still counted as reading (2 covers double-page mode);
+/// bigger jumps are the slider or the chapter list
+const MAX_PAGE_STEP: i32 = 2;
+
+/// Pages needed before an average is trusted over the next fallback
+const MIN_SAMPLE_PAGES: i64 = 10;
+
+/// Used until there are enough samples at all
+const DEFAULT_SECONDS_PER_PAGE: f64 = 12.0;
+
+/// Sessions untouched for this long are assumed abandoned (reader crashed or
+/// was closed without ending the session)
+const SESSION_IDLE_MS: i64 = 60 * 60 * 1000;
+
+static SESSIONS: LazyLock<Mutex<HashMap<u64, ReadingSession>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Page-turn timings for one chapter being read
+#[derive(Debug, Clone)]
+pub struct ReadingSession {
+    pub media_id: String,
+    pub chapter_id: String,
+    page: i32,
+    page_shown_at_ms: i64,
+    seconds: f64,
+    pages: i64,
+}
+
+impl ReadingSession {
+    pub fn new(media_id: &str, chapter_id: &str, page: i32, now_ms: i64) -> Self {
+        Self {
+            media_id: media_id.to_string(),
+            chapter_id: chapter_id.to_string(),
+            page,
+            page_shown_at_ms: now_ms,
+            seconds: 0.0,
+            pages: 0,
+        }
+    }
+
+    /// Record moving to `page`. The time since the last turn counts towards
+    /// the pages just read, if it looks like reading.
+    pub fn turn_page(&mut self, page: i32, now_ms: i64) {
+        let step = page - self.page;
+        let elapsed = (now_ms - self.page_shown_at_ms) as f64 / 1000.0;
+
+        if (1..=MAX_PAGE_STEP).contains(&step)
+            && (MIN_PAGE_SECONDS * step as f64..=MAX_PAGE_SECONDS).contains(&elapsed)
+        {
+            self.seconds += elapsed;
+            self.pages += step as i64;
+        }
+
+        self.page = page;
+        self.page_shown_at_ms = now_ms;
+    }
+
+    pub fn summary(&self) -> ReadingSessionSummary {
+        ReadingSessionSummary {
+            media_id: self.media_id.clone(),
+            chapter_id: self.chapter_id.clone(),
+            pages_timed: self.pages,
+            seconds: self.seconds,
+            seconds_per_page: (self.pages > 0).then(|| self.seconds / self.pages as f64),
+        }
+    }
+}
+
+/// What a finished session contributed
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingSessionSummary {
+    pub media_id: String,
+    pub chapter_id: String,
+    pub pages_timed: i64,
+    pub seconds: f64,
+    pub seconds_per_page: Option<f64>,
+}
+
+/// Which average an estimate was based on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedSource {
+    Media,
+    Global,
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingTimeEstimate {
+    pub remaining_pages: i32,
+    pub seconds_per_page: f64,
+    pub source: SpeedSource,
+    pub estimated_minutes: u32,
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Open a session for a chapter, returning its id
+pub fn start_reading_session(media_id: &str, chapter_id: &str, page: i32) -> u64 {
+    let now = now_ms();
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+
+    let mut sessions = SESSIONS.lock().unwrap();
+    sessions.retain(|_, session| now - session.page_shown_at_ms < SESSION_IDLE_MS);
+    sessions.insert(id, ReadingSession::new(media_id, chapter_id, page, now));
+
+    id
+}
+
+/// Record a page turn. Returns false for unknown (ended or expired) sessions.
+pub fn record_page_turn(session_id: u64, page: i32) -> bool {
+    let mut sessions = SESSIONS.lock().unwrap();
+    match sessions.get_mut(&session_id) {
+        Some(session) => {
+            session.turn_page(page, now_ms());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Close a session and fold its timings into the stored averages
+pub async fn end_reading_session(pool: &SqlitePool, profile_id: i64, session_id: u64) -> Result<Option<ReadingSessionSummary>> {
+    let session = SESSIONS.lock().unwrap().remove(&session_id);
+    let Some(session) = session else {
+        return Ok(None);
+    };
+
+    let summary = session.summary();
+    if summary.pages_timed > 0 {
+        record_speed(pool, profile_id, &summary.media_id, summary.seconds, summary.pages_timed).await?;
+        log::debug!(
+            "Reading session for {} timed {} pages at {:.1}s/page",
+            summary.chapter_id,
+            summary.pages_timed,
+            summary.seconds_per_page.unwrap_or_default()
+        );
+    }
+
+    Ok(Some(summary))
+}
+
+/// Add timed pages to a media's average and the global one
+pub async fn record_speed(pool: &SqlitePool, profile_id: i64, media_id: &str, seconds: f64, pages: i64) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for key in [media_id, GLOBAL_MEDIA_ID] {
+        sqlx::query(
+            r#"
+            INSERT INTO reading_speed (profile_id, media_id, total_seconds, pages, updated_at)
+            VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(profile_id, media_id) DO UPDATE SET
+                total_seconds = total_seconds + excluded.total_seconds,
+                pages = pages + excluded.pages,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(profile_id)
+        .bind(key)
+        .bind(seconds)
+        .bind(pages)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn stored_average(pool: &SqlitePool, profile_id: i64, media_id: &str) -> Result<Option<f64>> {
+    let row: Option<(f64, i64)> = sqlx::query_as(
+        "SELECT total_seconds, pages FROM reading_speed WHERE profile_id = ? AND media_id = ?"
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row
+        .filter(|(_, pages)| *pages >= MIN_SAMPLE_PAGES)
+        .map(|(seconds, pages)| seconds / pages as f64))
+}
+
+/// Average seconds per page for a media, falling back to the global average
+/// and then to a default
+pub async fn seconds_per_page(pool: &SqlitePool, profile_id: i64, media_id: &str) -> Result<(f64, SpeedSource)> {
+    if let Some(average) = stored_average(pool, profile_id, media_id).await? {
+        return Ok((average, SpeedSource::Media));
+    }
+    if let Some(average) = stored_average(pool, profile_id, GLOBAL_MEDIA_ID).await? {
+        return Ok((average, SpeedSource::Global));
+    }
+    Ok((DEFAULT_SECONDS_PER_PAGE, SpeedSource::Default))
+}
+
+/// Whole minutes to read the remaining pages, rounded up so a few pages left
+/// still shows as a minute
+pub fn estimate_minutes(remaining_pages: i32, seconds_per_page: f64) -> u32 {
+    if remaining_pages <= 0 {
+        return 0;
+    }
+    (remaining_pages as f64 * seconds_per_page / 60.0).ceil() as u32
+}
+
+/// Estimate the time left in a chapter from its saved reading progress.
+/// None when the chapter hasn't been opened or its page count is unknown.
+pub async fn get_reading_time_estimate(
+    pool: &SqlitePool,
+    profile_id: i64,
+    media_id: &str,
+    chapter_id: &str,
+) -> Result<Option<ReadingTimeEstimate>> {
+    let progress: Option<(i32, Option<i32>)> = sqlx::query_as(
+        "SELECT current_page, total_pages FROM reading_history WHERE profile_id = ? AND media_id = ? AND chapter_id = ?"
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .bind(chapter_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some((current_page, Some(total_pages))) = progress else {
+        return Ok(None);
+    };
+
+    let remaining_pages = (total_pages - current_page).max(0);
+    let (seconds_per_page, source) = seconds_per_page(pool, profile_id, media_id).await?;
+
+    Ok(Some(ReadingTimeEstimate {
+        remaining_pages,
+        seconds_per_page,
+        source,
+        estimated_minutes: estimate_minutes(remaining_pages, seconds_per_page),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::reading_history::{save_reading_progress, ReadingProgress};
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn forward_turns_within_bounds_are_timed() {
+        let mut session = ReadingSession::new("m", "c", 1, 0);
+        session.turn_page(2, 10_000);
+        session.turn_page(3, 30_000);
+
+        let summary = session.summary();
+        assert_eq!(summary.pages_timed, 2);
+        assert_eq!(summary.seconds, 30.0);
+        assert_eq!(summary.seconds_per_page, Some(15.0));
+    }
+
+    #[test]
+    fn skims_breaks_backtracks_and_jumps_are_ignored() {
+        let mut session = ReadingSession::new("m", "c", 1, 0);
+        session.turn_page(2, 500); // flipped past in half a second
+        session.turn_page(3, 600_500); // ten minutes away
+        session.turn_page(2, 610_500); // went back
+        session.turn_page(20, 620_500); // jumped with the slider
+        assert_eq!(session.summary().pages_timed, 0);
+        assert_eq!(session.summary().seconds_per_page, None);
+
+        // Timing restarts from the last turn, whatever it was
+        session.turn_page(21, 628_500);
+        assert_eq!(session.summary().pages_timed, 1);
+        assert_eq!(session.summary().seconds, 8.0);
+    }
+
+    #[test]
+    fn double_page_steps_count_both_pages() {
+        let mut session = ReadingSession::new("m", "c", 1, 0);
+        session.turn_page(3, 24_000);
+        assert_eq!(session.summary().pages_timed, 2);
+        assert_eq!(session.summary().seconds_per_page, Some(12.0));
+
+        // Two pages in a second and a half is still skimming
+        session.turn_page(5, 25_500);
+        assert_eq!(session.summary().pages_timed, 2);
+    }
+
+    #[test]
+    fn minutes_round_up() {
+        assert_eq!(estimate_minutes(0, 20.0), 0);
+        assert_eq!(estimate_minutes(-3, 20.0), 0);
+        assert_eq!(estimate_minutes(1, 5.0), 1);
+        assert_eq!(estimate_minutes(27, 20.0), 9);
+        assert_eq!(estimate_minutes(28, 20.0), 10);
+    }
+
+    #[tokio::test]
+    async fn estimate_prefers_media_average_then_global_then_default() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        for media_id in ["slow-manga", "new-manga"] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'ext', ?, 'manga')")
+                .bind(media_id)
+                .bind(media_id)
+                .execute(pool)
+                .await
+                .unwrap();
+            save_reading_progress(pool, DEFAULT_PROFILE_ID, &ReadingProgress {
+                media_id: media_id.to_string(),
+                chapter_id: format!("{}-ch1", media_id),
+                chapter_number: 1.0,
+                current_page: 13,
+                total_pages: Some(40),
+                completed: false,
+            })
+            .await
+            .unwrap();
+        }
+
+        let estimate = get_reading_time_estimate(pool, DEFAULT_PROFILE_ID, "new-manga", "new-manga-ch1").await.unwrap().unwrap();
+        assert_eq!(estimate.source, SpeedSource::Default);
+        assert_eq!(estimate.remaining_pages, 27);
+
+        // Too few pages to trust yet
+        record_speed(pool, DEFAULT_PROFILE_ID, "slow-manga", 100.0, 5).await.unwrap();
+        let estimate = get_reading_time_estimate(pool, DEFAULT_PROFILE_ID, "slow-manga", "slow-manga-ch1").await.unwrap().unwrap();
+        assert_eq!(estimate.source, SpeedSource::Default);
+
+        record_speed(pool, DEFAULT_PROFILE_ID, "slow-manga", 100.0, 5).await.unwrap();
+        let estimate = get_reading_time_estimate(pool, DEFAULT_PROFILE_ID, "slow-manga", "slow-manga-ch1").await.unwrap().unwrap();
+        assert_eq!(estimate.source, SpeedSource::Media);
+        assert_eq!(estimate.seconds_per_page, 20.0);
+        assert_eq!(estimate.estimated_minutes, 9);
+
+        // A title without its own samples uses the global average
+        let estimate = get_reading_time_estimate(pool, DEFAULT_PROFILE_ID, "new-manga", "new-manga-ch1").await.unwrap().unwrap();
+        assert_eq!(estimate.source, SpeedSource::Global);
+        assert_eq!(estimate.seconds_per_page, 20.0);
+
+        assert!(get_reading_time_estimate(pool, DEFAULT_PROFILE_ID, "new-manga", "unopened").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn ending_a_session_persists_its_samples() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let id = start_reading_session("manga-1", "ch-1", 1);
+        // Backdate the session so the turns look like reading
+        {
+            let mut sessions = SESSIONS.lock().unwrap();
+            let session = sessions.get_mut(&id).unwrap();
+            session.page_shown_at_ms -= 12 * 10_000;
+            for page in 2..=12 {
+                let at = session.page_shown_at_ms + 10_000;
+                session.turn_page(page, at);
+            }
+        }
+
+        let summary = end_reading_session(pool, DEFAULT_PROFILE_ID, id).await.unwrap().unwrap();
+        assert_eq!(summary.pages_timed, 11);
+        assert_eq!(summary.seconds_per_page, Some(10.0));
+
+        assert_eq!(seconds_per_page(pool, DEFAULT_PROFILE_ID, "manga-1").await.unwrap(), (10.0, SpeedSource::Media));
+        assert_eq!(seconds_per_page(pool, DEFAULT_PROFILE_ID, "other").await.unwrap(), (10.0, SpeedSource::Global));
+
+        // Already ended
+        assert!(end_reading_session(pool, DEFAULT_PROFILE_ID, id).await.unwrap().is_none());
+        assert!(!record_page_turn(id, 13));
+    }
+}
@@ -9,6 +9,7 @@ use sqlx::SqlitePool;
 use anyhow::Result;
 use std::collections::HashMap;
 
+use super::age_rating;
 use super::media::MediaEntry;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +54,16 @@ fn parse_genres(genres_json: &Option<String>) -> Vec<String> {
     }
 }
 
+/// "AND ..." keeping candidates the active profile's age rating limit
+/// allows, or nothing without a limit
+async fn age_rating_condition(pool: &SqlitePool, profile_id: i64) -> String {
+    age_rating::current_limit(pool, profile_id)
+        .await
+        .sql_condition("m.rating_classification")
+        .map(|condition| format!("AND {}", condition))
+        .unwrap_or_default()
+}
+
 /// Helper: construct a MediaEntry from a sqlx Row using the `m.*` column pattern.
 fn media_entry_from_row(row: &sqlx::sqlite::SqliteRow) -> MediaEntry {
     use sqlx::Row;
@@ -133,16 +144,17 @@ async fn build_feedback_genre_adjustments(pool: &SqlitePool) -> Result<HashMap<S
 ///   computed from the full media catalogue so niche genres get a boost.
 ///
 /// The final weight is TF*IDF normalized to [0, 1].
-pub async fn build_genre_profile(pool: &SqlitePool) -> Result<UserGenreProfile> {
+pub async fn build_genre_profile(pool: &SqlitePool, profile_id: i64) -> Result<UserGenreProfile> {
     // Step 1: Fetch raw watch data with timestamps and genres
     let rows = sqlx::query(
         r#"
         SELECT w.progress_seconds, w.last_watched, m.genres
         FROM watch_history w
         JOIN media m ON w.media_id = m.id
-        WHERE m.genres IS NOT NULL AND w.progress_seconds > 0
+        WHERE w.profile_id = ? AND m.genres IS NOT NULL AND w.progress_seconds > 0
         "#
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -188,8 +200,9 @@ pub async fn build_genre_profile(pool: &SqlitePool) -> Result<UserGenreProfile>
     // Count unique media in watch history for total_series
     {
         let series_rows = sqlx::query(
-            "SELECT DISTINCT media_id FROM watch_history"
+            "SELECT DISTINCT media_id FROM watch_history WHERE profile_id = ?"
         )
+        .bind(profile_id)
         .fetch_all(pool)
         .await?;
 
@@ -270,14 +283,15 @@ pub async fn build_genre_profile(pool: &SqlitePool) -> Result<UserGenreProfile>
 
 /// Score cached media by TF-IDF genre overlap and return top recommendations.
 ///
-/// Candidates are media NOT already in the user's library, with rating > 6.0.
+/// Candidates are media NOT already in the user's library or hidden, with rating > 6.0.
 /// Each candidate is scored by summing the user's genre weights for every matching
 /// genre, plus a small rating bonus (rating / 100).
 pub async fn get_content_recommendations(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<Vec<RecommendationEntry>> {
-    let profile = build_genre_profile(pool).await?;
+    let profile = build_genre_profile(pool, profile_id).await?;
 
     if profile.top_genres.is_empty() {
         return Ok(Vec::new());
@@ -297,8 +311,9 @@ pub async fn get_content_recommendations(
         }
     }
 
-    // Fetch candidate anime: not in library, has genres, rating > 6.0
-    let candidates = sqlx::query(
+    // Fetch candidate anime: not in library, has genres, rating > 6.0, and
+    // allowed by the profile's age rating limit
+    let candidates = sqlx::query(&format!(
         r#"
         SELECT m.*
         FROM media m
@@ -306,10 +321,15 @@ pub async fn get_content_recommendations(
           AND m.genres != '[]'
           AND m.rating > 6.0
           AND m.media_type = 'anime'
-          AND m.id NOT IN (SELECT media_id FROM library)
+          AND m.id NOT IN (SELECT media_id FROM library WHERE profile_id = ?)
+          AND m.id NOT IN (SELECT media_id FROM hidden_media WHERE profile_id = ?)
+          {}
         LIMIT 500
-        "#
-    )
+        "#,
+        age_rating_condition(pool, profile_id).await
+    ))
+    .bind(profile_id)
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -375,6 +395,7 @@ pub async fn get_content_recommendations(
 /// Results are grouped by source series for the frontend to render as carousels.
 pub async fn get_similar_to_watched(
     pool: &SqlitePool,
+    profile_id: i64,
     limit_per_series: i32,
 ) -> Result<Vec<SimilarToGroup>> {
     use sqlx::Row;
@@ -385,12 +406,13 @@ pub async fn get_similar_to_watched(
         SELECT m.*, l.score as user_score, l.added_at
         FROM library l
         JOIN media m ON l.media_id = m.id
-        WHERE m.genres IS NOT NULL AND m.genres != '[]'
+        WHERE l.profile_id = ? AND m.genres IS NOT NULL AND m.genres != '[]'
           AND m.media_type = 'anime'
         ORDER BY COALESCE(l.score, 0) DESC, l.added_at DESC
         LIMIT 3
         "#
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -399,7 +421,8 @@ pub async fn get_similar_to_watched(
     }
 
     // Collect all library media IDs to exclude
-    let library_ids: Vec<String> = sqlx::query("SELECT media_id FROM library")
+    let library_ids: Vec<String> = sqlx::query("SELECT media_id FROM library WHERE profile_id = ?")
+        .bind(profile_id)
         .fetch_all(pool)
         .await?
         .iter()
@@ -411,6 +434,7 @@ pub async fn get_similar_to_watched(
 
     let library_set: std::collections::HashSet<&str> = library_ids.iter().map(|s| s.as_str()).collect();
 
+    let rating_condition = age_rating_condition(pool, profile_id).await;
     let mut groups: Vec<SimilarToGroup> = Vec::new();
 
     for source_row in &top_series {
@@ -424,7 +448,7 @@ pub async fn get_similar_to_watched(
         let genre_threshold = if source_genres.len() < 4 { 2 } else { 3 };
 
         // Fetch anime candidates with genres (match source media type)
-        let candidates = sqlx::query(
+        let candidates = sqlx::query(&format!(
             r#"
             SELECT m.*
             FROM media m
@@ -432,10 +456,14 @@ pub async fn get_similar_to_watched(
               AND m.genres != '[]'
               AND m.media_type = 'anime'
               AND m.id != ?
+              AND m.id NOT IN (SELECT media_id FROM hidden_media WHERE profile_id = ?)
+              {}
             LIMIT 500
-            "#
-        )
+            "#,
+            rating_condition
+        ))
         .bind(&source_media.id)
+        .bind(profile_id)
         .fetch_all(pool)
         .await?;
 
@@ -500,9 +528,10 @@ pub async fn get_similar_to_watched(
 /// Return a truncated genre profile for the frontend (top N genres).
 pub async fn get_user_top_genres(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<UserGenreProfile> {
-    let mut profile = build_genre_profile(pool).await?;
+    let mut profile = build_genre_profile(pool, profile_id).await?;
     profile.top_genres.truncate(limit as usize);
     Ok(profile)
 }
@@ -0,0 +1,355 @@
+// Stale Library Entries
+//
+// "Drop suggestions": watching/reading entries with no watch or reading
+// history for a while (stale_library_threshold_days, 90 by default). An
+// entry that was never watched counts from when it was added. The user can
+// move the ones they picked to on hold or dropped in one go.
+//
+// With stale_library_reminder on, a reminder notification says how many
+// stale entries there are, at most once a month.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use super::library::{bulk_update_library_status, LibraryStatus};
+use crate::commands::AppState;
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key: days without history before an entry counts as stale
+pub const THRESHOLD_DAYS_SETTING: &str = "stale_library_threshold_days";
+
+/// Threshold when the setting isn't set
+pub const DEFAULT_THRESHOLD_DAYS: u32 = 90;
+
+/// app_settings key: "true" sends the monthly stale entries reminder
+pub const REMINDER_SETTING: &str = "stale_library_reminder";
+
+/// app_settings key: Unix timestamp (ms) of the last reminder
+const LAST_REMINDER_SETTING: &str = "stale_library_last_reminder";
+
+const REMINDER_INTERVAL_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(5 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// A watching/reading entry without recent history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StaleEntry {
+    pub media_id: String,
+    pub title: String,
+    pub cover_url: Option<String>,
+    pub media_type: String,
+    /// "watching" or "reading"
+    pub status: String,
+    /// Latest watch/read ("YYYY-MM-DD HH:MM:SS", UTC), or when the entry was
+    /// added if there's no history
+    pub last_activity: String,
+    pub days_inactive: i64,
+    /// Episodes watched or chapters read to the end
+    pub progress: i64,
+    /// Episode or chapter count, when known
+    pub total: Option<i64>,
+}
+
+async fn setting(pool: &SqlitePool, key: &str) -> Option<String> {
+    sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+}
+
+/// The stored stale_library_threshold_days, or DEFAULT_THRESHOLD_DAYS
+pub async fn threshold_days_setting(pool: &SqlitePool) -> u32 {
+    setting(pool, THRESHOLD_DAYS_SETTING)
+        .await
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_THRESHOLD_DAYS)
+}
+
+/// Stale entries of the current profile as of `now` (UTC,
+/// "YYYY-MM-DD HH:MM:SS"), longest inactive first
+pub async fn get_stale_entries_at(pool: &SqlitePool, profile_id: i64, threshold_days: u32, now: &str) -> Result<Vec<StaleEntry>> {
+    let rows = sqlx::query(
+        r#"
+        WITH activity AS (
+            SELECT l.media_id, l.status, l.added_at,
+                   (SELECT MAX(w.last_watched) FROM watch_history w
+                    WHERE w.profile_id = l.profile_id AND w.media_id = l.media_id) AS last_watched,
+                   (SELECT MAX(r.last_read) FROM reading_history r
+                    WHERE r.profile_id = l.profile_id AND r.media_id = l.media_id) AS last_read,
+                   (SELECT COUNT(DISTINCT w.episode_number) FROM watch_history w
+                    WHERE w.profile_id = l.profile_id AND w.media_id = l.media_id AND w.completed = 1) AS episodes,
+                   (SELECT COUNT(DISTINCT r.chapter_number) FROM reading_history r
+                    WHERE r.profile_id = l.profile_id AND r.media_id = l.media_id AND r.completed = 1) AS chapters
+            FROM library l
+            WHERE l.profile_id = ? AND l.status IN ('watching', 'reading')
+        ),
+        latest AS (
+            SELECT a.*,
+                   COALESCE(
+                       NULLIF(MAX(COALESCE(a.last_watched, ''), COALESCE(a.last_read, '')), ''),
+                       a.added_at
+                   ) AS last_activity
+            FROM activity a
+        )
+        SELECT la.media_id, la.status, la.last_activity,
+               CAST(julianday(?) - julianday(la.last_activity) AS INTEGER) AS days_inactive,
+               CASE WHEN m.media_type = 'manga' THEN la.chapters ELSE la.episodes END AS progress,
+               m.title, m.cover_url, m.media_type, m.episode_count
+        FROM latest la
+        INNER JOIN media m ON m.id = la.media_id
+        WHERE julianday(?) - julianday(la.last_activity) >= ?
+        ORDER BY la.last_activity ASC
+        "#
+    )
+    .bind(profile_id)
+    .bind(now)
+    .bind(now)
+    .bind(threshold_days)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(StaleEntry {
+                media_id: row.try_get("media_id")?,
+                title: row.try_get("title")?,
+                cover_url: row.try_get("cover_url")?,
+                media_type: row.try_get("media_type")?,
+                status: row.try_get("status")?,
+                last_activity: row.try_get("last_activity")?,
+                days_inactive: row.try_get("days_inactive")?,
+                progress: row.try_get("progress")?,
+                total: row.try_get("episode_count")?,
+            })
+        })
+        .collect()
+}
+
+fn now_utc() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Stale entries of the current profile, longest inactive first
+pub async fn get_stale_entries(pool: &SqlitePool, profile_id: i64, threshold_days: u32) -> Result<Vec<StaleEntry>> {
+    get_stale_entries_at(pool, profile_id, threshold_days, &now_utc()).await
+}
+
+/// Move stale entries the user picked to on hold or dropped
+pub async fn move_stale_entries(pool: &SqlitePool, profile_id: i64, media_ids: &[String], status: LibraryStatus) -> Result<()> {
+    if !matches!(status, LibraryStatus::OnHold | LibraryStatus::Dropped) {
+        return Err(anyhow!("Stale entries can only be moved to on_hold or dropped, not {}", status.as_str()));
+    }
+    bulk_update_library_status(pool, profile_id, media_ids, status).await
+}
+
+fn reminder_notification(count: usize, threshold_days: u32) -> Option<NotificationPayload> {
+    if count == 0 {
+        return None;
+    }
+    let entries = if count == 1 {
+        "1 entry in your library hasn't".to_string()
+    } else {
+        format!("{} entries in your library haven't", count)
+    };
+    Some(
+        NotificationPayload::new(
+            NotificationType::Info,
+            "Anything to Drop?",
+            format!("{} been touched in {} days", entries, threshold_days),
+        )
+        .with_source("library")
+        .with_action("Review", Some("/library".to_string()), None)
+        .with_metadata(serde_json::json!({ "count": count, "threshold_days": threshold_days })),
+    )
+}
+
+/// Send the reminder if it's on, a month has passed since the last one and
+/// there's something stale
+async fn check_reminder(app_handle: &AppHandle, pool: &SqlitePool) -> Result<()> {
+    if setting(pool, REMINDER_SETTING).await.as_deref() != Some("true") {
+        return Ok(());
+    }
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let last: i64 = setting(pool, LAST_REMINDER_SETTING)
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if now_ms - last < REMINDER_INTERVAL_MS {
+        return Ok(());
+    }
+
+    let threshold_days = threshold_days_setting(pool).await;
+    let profile_id = app_handle.state::<AppState>().profile_id();
+    let stale = get_stale_entries(pool, profile_id, threshold_days).await?;
+    let Some(notification) = reminder_notification(stale.len(), threshold_days) else {
+        return Ok(());
+    };
+    emit_notification(app_handle, Some(pool), notification).await?;
+
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(LAST_REMINDER_SETTING)
+        .bind(now_ms.to_string())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Periodically check whether the monthly reminder is due (no-op while off)
+pub fn start_reminder_task(app_handle: AppHandle, pool: Arc<SqlitePool>) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            if let Err(e) = check_reminder(&app_handle, &pool).await {
+                log::warn!("Stale library reminder failed: {}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    const NOW: &str = "2026-06-30 12:00:00";
+
+    async fn add_entry(pool: &SqlitePool, media_id: &str, media_type: &str, status: &str, added_at: &str) {
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, episode_count) VALUES (?, 'ext', ?, ?, 12)")
+            .bind(media_id)
+            .bind(format!("Title {}", media_id))
+            .bind(media_type)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO library (profile_id, media_id, status, added_at) VALUES (1, ?, ?, ?)")
+            .bind(media_id)
+            .bind(status)
+            .bind(added_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn watched(pool: &SqlitePool, media_id: &str, episode: i32, completed: bool, at: &str) {
+        sqlx::query(
+            "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, completed, last_watched) \
+             VALUES (1, ?, ?, ?, ?, ?)",
+        )
+        .bind(media_id)
+        .bind(format!("{}-ep{}", media_id, episode))
+        .bind(episode)
+        .bind(completed)
+        .bind(at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn read(pool: &SqlitePool, media_id: &str, chapter: f64, at: &str) {
+        sqlx::query(
+            "INSERT INTO reading_history (profile_id, media_id, chapter_id, chapter_number, completed, last_read) \
+             VALUES (1, ?, ?, ?, 1, ?)",
+        )
+        .bind(media_id)
+        .bind(format!("{}-ch{}", media_id, chapter))
+        .bind(chapter)
+        .bind(at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn staleness_goes_by_the_latest_watch_or_read() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        // Last watched 200 days ago, two episodes finished
+        add_entry(pool, "old-anime", "anime", "watching", "2025-01-01 00:00:00").await;
+        watched(pool, "old-anime", 1, true, "2025-12-01 12:00:00").await;
+        watched(pool, "old-anime", 2, true, "2025-12-11 12:00:00").await;
+        watched(pool, "old-anime", 3, false, "2025-12-12 12:00:00").await;
+        // Watched last week
+        add_entry(pool, "recent-anime", "anime", "watching", "2025-01-01 00:00:00").await;
+        watched(pool, "recent-anime", 1, true, "2026-06-23 12:00:00").await;
+        // Read 100 days ago
+        add_entry(pool, "old-manga", "manga", "reading", "2025-01-01 00:00:00").await;
+        read(pool, "old-manga", 1.0, "2026-03-20 12:00:00").await;
+        read(pool, "old-manga", 1.5, "2026-03-22 12:00:00").await;
+        // Read yesterday
+        add_entry(pool, "recent-manga", "manga", "reading", "2025-01-01 00:00:00").await;
+        read(pool, "recent-manga", 1.0, "2026-06-29 12:00:00").await;
+        // Old watch history but recent reading history: the newest counts
+        add_entry(pool, "both", "anime", "watching", "2025-01-01 00:00:00").await;
+        watched(pool, "both", 1, true, "2025-06-01 12:00:00").await;
+        read(pool, "both", 1.0, "2026-06-01 12:00:00").await;
+        // Never watched: counts from when it was added
+        add_entry(pool, "untouched", "anime", "watching", "2026-01-01 12:00:00").await;
+        add_entry(pool, "just-added", "anime", "watching", "2026-06-01 12:00:00").await;
+        // Not watching/reading
+        add_entry(pool, "completed", "anime", "completed", "2024-01-01 00:00:00").await;
+        add_entry(pool, "on-hold", "anime", "on_hold", "2024-01-01 00:00:00").await;
+
+        let stale = get_stale_entries_at(pool, DEFAULT_PROFILE_ID, 90, NOW).await.unwrap();
+        let summary: Vec<(&str, i64, i64)> = stale
+            .iter()
+            .map(|e| (e.media_id.as_str(), e.days_inactive, e.progress))
+            .collect();
+        assert_eq!(summary, vec![("old-anime", 200, 2), ("untouched", 180, 0), ("old-manga", 100, 2)]);
+
+        let untouched = &stale[1];
+        assert_eq!(untouched.last_activity, "2026-01-01 12:00:00");
+        assert_eq!(untouched.total, Some(12));
+        assert_eq!(stale[2].status, "reading");
+
+        // A longer threshold leaves out the shorter gaps
+        let stale = get_stale_entries_at(pool, DEFAULT_PROFILE_ID, 190, NOW).await.unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].media_id, "old-anime");
+    }
+
+    #[tokio::test]
+    async fn stale_entries_only_move_to_on_hold_or_dropped() {
+        let temp = tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        add_entry(pool, "a", "anime", "watching", "2025-01-01 00:00:00").await;
+        add_entry(pool, "b", "manga", "reading", "2025-01-01 00:00:00").await;
+        let ids = vec!["a".to_string(), "b".to_string()];
+
+        assert!(move_stale_entries(pool, DEFAULT_PROFILE_ID, &ids, LibraryStatus::Completed).await.is_err());
+        move_stale_entries(pool, DEFAULT_PROFILE_ID, &ids, LibraryStatus::Dropped).await.unwrap();
+
+        let statuses: Vec<String> = sqlx::query_scalar("SELECT status FROM library ORDER BY media_id")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        assert_eq!(statuses, vec!["dropped", "dropped"]);
+        assert!(get_stale_entries_at(pool, DEFAULT_PROFILE_ID, 90, NOW).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn reminder_counts_the_stale_entries() {
+        assert!(reminder_notification(0, 90).is_none());
+        assert_eq!(
+            reminder_notification(1, 90).unwrap().message,
+            "1 entry in your library hasn't been touched in 90 days"
+        );
+        assert_eq!(
+            reminder_notification(4, 180).unwrap().message,
+            "4 entries in your library haven't been touched in 180 days"
+        );
+    }
+}
@@ -213,22 +213,25 @@ pub struct RatingComparisonEntry {
     pub difference: f64,
 }
 
-pub async fn get_watch_stats_summary(pool: &SqlitePool) -> Result<WatchStatsSummary> {
+pub async fn get_watch_stats_summary(pool: &SqlitePool, profile_id: i64) -> Result<WatchStatsSummary> {
     let row = sqlx::query(
         "SELECT
             COALESCE(SUM(progress_seconds), 0) as total_time,
             COUNT(CASE WHEN completed = 1 THEN 1 END) as eps_completed,
             COUNT(*) as eps_started
-        FROM watch_history"
+        FROM watch_history
+        WHERE profile_id = ?"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
     let series_row = sqlx::query(
         "SELECT COUNT(*) as cnt FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE l.status = 'completed' AND m.media_type = 'anime'"
+         WHERE l.profile_id = ? AND l.status = 'completed' AND m.media_type = 'anime'"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -241,22 +244,25 @@ pub async fn get_watch_stats_summary(pool: &SqlitePool) -> Result<WatchStatsSumm
     })
 }
 
-pub async fn get_reading_stats_summary(pool: &SqlitePool) -> Result<ReadingStatsSummary> {
+pub async fn get_reading_stats_summary(pool: &SqlitePool, profile_id: i64) -> Result<ReadingStatsSummary> {
     let row = sqlx::query(
         "SELECT
             COUNT(CASE WHEN completed = 1 THEN 1 END) as chapters_completed,
             COALESCE(SUM(CASE WHEN completed = 1 THEN COALESCE(total_pages, 0) ELSE current_page END), 0) as total_pages,
             COUNT(*) as chapters_started
-        FROM reading_history"
+        FROM reading_history
+        WHERE profile_id = ?"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
     let series_row = sqlx::query(
         "SELECT COUNT(*) as cnt FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE l.status = 'completed' AND m.media_type = 'manga'"
+         WHERE l.profile_id = ? AND l.status = 'completed' AND m.media_type = 'manga'"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -269,7 +275,7 @@ pub async fn get_reading_stats_summary(pool: &SqlitePool) -> Result<ReadingStats
     })
 }
 
-pub async fn get_daily_activity(pool: &SqlitePool, days: i32) -> Result<Vec<DailyActivity>> {
+pub async fn get_daily_activity(pool: &SqlitePool, profile_id: i64, days: i32) -> Result<Vec<DailyActivity>> {
     log::info!("get_daily_activity called with days={}", days);
 
     // Single query: UNION ALL watch + read, aggregate per day
@@ -283,11 +289,11 @@ pub async fn get_daily_activity(pool: &SqlitePool, days: i32) -> Result<Vec<Dail
         "SELECT day, SUM(watch_min) as watch_minutes, SUM(read_min) as read_minutes
          FROM (
              SELECT DATE(last_watched) as day, progress_seconds / 60.0 as watch_min, 0.0 as read_min
-             FROM watch_history WHERE last_watched IS NOT NULL
+             FROM watch_history WHERE profile_id = ? AND last_watched IS NOT NULL
              UNION ALL
              SELECT DATE(last_read) as day, 0.0 as watch_min,
                  (CASE WHEN completed = 1 THEN COALESCE(total_pages, 0) ELSE current_page END) * {rpm} as read_min
-             FROM reading_history WHERE last_read IS NOT NULL
+             FROM reading_history WHERE profile_id = ? AND last_read IS NOT NULL
          )
          WHERE day IS NOT NULL
          {filter}
@@ -299,6 +305,8 @@ pub async fn get_daily_activity(pool: &SqlitePool, days: i32) -> Result<Vec<Dail
     log::info!("get_daily_activity query: {}", query);
 
     let rows = sqlx::query(&query)
+        .bind(profile_id)
+        .bind(profile_id)
         .fetch_all(pool)
         .await?;
 
@@ -320,6 +328,7 @@ pub async fn get_daily_activity(pool: &SqlitePool, days: i32) -> Result<Vec<Dail
 
 pub async fn get_genre_stats(
     pool: &SqlitePool,
+    profile_id: i64,
     media_type: Option<&str>,
 ) -> Result<Vec<GenreStat>> {
     let query_str = match media_type {
@@ -327,7 +336,7 @@ pub async fn get_genre_stats(
             "SELECT j.value as genre, SUM(w.progress_seconds) as time_seconds, COUNT(*) as count
              FROM watch_history w
              JOIN media m ON w.media_id = m.id, json_each(m.genres) j
-             WHERE m.genres IS NOT NULL
+             WHERE w.profile_id = ? AND m.genres IS NOT NULL
              GROUP BY j.value ORDER BY time_seconds DESC LIMIT 10"
         }
         Some("manga") => {
@@ -336,7 +345,7 @@ pub async fn get_genre_stats(
                 COUNT(*) as count
              FROM reading_history r
              JOIN media m ON r.media_id = m.id, json_each(m.genres) j
-             WHERE m.genres IS NOT NULL
+             WHERE r.profile_id = ? AND m.genres IS NOT NULL
              GROUP BY j.value ORDER BY time_seconds DESC LIMIT 10"
         }
         _ => {
@@ -344,7 +353,7 @@ pub async fn get_genre_stats(
                 SELECT j.value as genre, SUM(w.progress_seconds) as time_seconds, COUNT(*) as count
                 FROM watch_history w
                 JOIN media m ON w.media_id = m.id, json_each(m.genres) j
-                WHERE m.genres IS NOT NULL
+                WHERE w.profile_id = ? AND m.genres IS NOT NULL
                 GROUP BY j.value
                 UNION ALL
                 SELECT j.value as genre,
@@ -352,13 +361,18 @@ pub async fn get_genre_stats(
                     COUNT(*) as count
                 FROM reading_history r
                 JOIN media m ON r.media_id = m.id, json_each(m.genres) j
-                WHERE m.genres IS NOT NULL
+                WHERE r.profile_id = ? AND m.genres IS NOT NULL
                 GROUP BY j.value
             ) GROUP BY genre ORDER BY time_seconds DESC LIMIT 10"
         }
     };
 
-    let rows = sqlx::query(query_str).fetch_all(pool).await?;
+    // The combined query filters both halves of the UNION
+    let mut query = sqlx::query(query_str).bind(profile_id);
+    if media_type != Some("anime") && media_type != Some("manga") {
+        query = query.bind(profile_id);
+    }
+    let rows = query.fetch_all(pool).await?;
 
     use sqlx::Row;
     Ok(rows.iter().map(|row| GenreStat {
@@ -368,13 +382,15 @@ pub async fn get_genre_stats(
     }).collect())
 }
 
-pub async fn get_completion_stats(pool: &SqlitePool) -> Result<CompletionStats> {
+pub async fn get_completion_stats(pool: &SqlitePool, profile_id: i64) -> Result<CompletionStats> {
     let rows = sqlx::query(
         "SELECT m.media_type, l.status, COUNT(*) as cnt
          FROM library l
          JOIN media m ON l.media_id = m.id
+         WHERE l.profile_id = ?
          GROUP BY m.media_type, l.status"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -401,15 +417,17 @@ pub async fn get_completion_stats(pool: &SqlitePool) -> Result<CompletionStats>
     Ok(CompletionStats { anime, manga })
 }
 
-pub async fn get_top_watched_anime(pool: &SqlitePool, limit: i32) -> Result<Vec<TopWatchedEntry>> {
+pub async fn get_top_watched_anime(pool: &SqlitePool, profile_id: i64, limit: i32) -> Result<Vec<TopWatchedEntry>> {
     let rows = sqlx::query(
         "SELECT m.*, SUM(w.progress_seconds) as total_time, COUNT(*) as eps_watched
          FROM watch_history w
          JOIN media m ON w.media_id = m.id
+         WHERE w.profile_id = ?
          GROUP BY m.id
          ORDER BY total_time DESC
          LIMIT ?"
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -447,15 +465,17 @@ pub async fn get_top_watched_anime(pool: &SqlitePool, limit: i32) -> Result<Vec<
     }).collect())
 }
 
-pub async fn get_top_read_manga(pool: &SqlitePool, limit: i32) -> Result<Vec<TopReadEntry>> {
+pub async fn get_top_read_manga(pool: &SqlitePool, profile_id: i64, limit: i32) -> Result<Vec<TopReadEntry>> {
     let rows = sqlx::query(
         "SELECT m.*, COUNT(CASE WHEN r.completed = 1 THEN 1 END) as chapters_read
          FROM reading_history r
          JOIN media m ON r.media_id = m.id
+         WHERE r.profile_id = ?
          GROUP BY m.id
          ORDER BY chapters_read DESC
          LIMIT ?"
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -492,15 +512,17 @@ pub async fn get_top_read_manga(pool: &SqlitePool, limit: i32) -> Result<Vec<Top
     }).collect())
 }
 
-pub async fn get_streak_stats(pool: &SqlitePool) -> Result<StreakStats> {
+pub async fn get_streak_stats(pool: &SqlitePool, profile_id: i64) -> Result<StreakStats> {
     // Get all unique active dates
     let rows = sqlx::query(
             "SELECT DISTINCT day FROM (
-                SELECT DATE(last_watched) as day FROM watch_history
+                SELECT DATE(last_watched) as day FROM watch_history WHERE profile_id = ?
                 UNION
-                SELECT DATE(last_read) as day FROM reading_history
+                SELECT DATE(last_read) as day FROM reading_history WHERE profile_id = ?
             ) ORDER BY day DESC"
     )
+    .bind(profile_id)
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -584,23 +606,25 @@ pub async fn get_streak_stats(pool: &SqlitePool) -> Result<StreakStats> {
     })
 }
 
-pub async fn get_activity_patterns(pool: &SqlitePool) -> Result<ActivityPatterns> {
+pub async fn get_activity_patterns(pool: &SqlitePool, profile_id: i64) -> Result<ActivityPatterns> {
     // Most active day of week
     let dow_rows = sqlx::query(
         &format!(
             "SELECT day_of_week, AVG(total_minutes) as avg_min FROM (
                 SELECT strftime('%w', day) as day_of_week, SUM(minutes) as total_minutes FROM (
                     SELECT DATE(last_watched) as day, SUM(progress_seconds) / 60.0 as minutes
-                    FROM watch_history GROUP BY day
+                    FROM watch_history WHERE profile_id = ? GROUP BY day
                     UNION ALL
                     SELECT DATE(last_read) as day,
                         SUM(CASE WHEN completed = 1 THEN COALESCE(total_pages, 0) ELSE current_page END) * {} as minutes
-                    FROM reading_history GROUP BY day
+                    FROM reading_history WHERE profile_id = ? GROUP BY day
                 ) GROUP BY day
             ) GROUP BY day_of_week ORDER BY avg_min DESC LIMIT 1",
             READING_MINUTES_PER_PAGE
         )
     )
+    .bind(profile_id)
+    .bind(profile_id)
     .fetch_optional(pool)
     .await?;
 
@@ -624,16 +648,18 @@ pub async fn get_activity_patterns(pool: &SqlitePool) -> Result<ActivityPatterns
             "SELECT AVG(total_minutes) as avg_min FROM (
                 SELECT SUM(minutes) as total_minutes FROM (
                     SELECT DATE(last_watched) as day, SUM(progress_seconds) / 60.0 as minutes
-                    FROM watch_history GROUP BY day
+                    FROM watch_history WHERE profile_id = ? GROUP BY day
                     UNION ALL
                     SELECT DATE(last_read) as day,
                         SUM(CASE WHEN completed = 1 THEN COALESCE(total_pages, 0) ELSE current_page END) * {} as minutes
-                    FROM reading_history GROUP BY day
+                    FROM reading_history WHERE profile_id = ? GROUP BY day
                 ) GROUP BY day
             )",
             READING_MINUTES_PER_PAGE
         )
     )
+    .bind(profile_id)
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -646,15 +672,17 @@ pub async fn get_activity_patterns(pool: &SqlitePool) -> Result<ActivityPatterns
     })
 }
 
-pub async fn get_binge_stats(pool: &SqlitePool) -> Result<BingeStats> {
+pub async fn get_binge_stats(pool: &SqlitePool, profile_id: i64) -> Result<BingeStats> {
     // Most episodes in a single day
     let ep_row = sqlx::query(
             "SELECT m.title, DATE(w.last_watched) as day, COUNT(*) as cnt
             FROM watch_history w
             JOIN media m ON w.media_id = m.id
+            WHERE w.profile_id = ?
             GROUP BY m.id, day
             ORDER BY cnt DESC LIMIT 1"
     )
+    .bind(profile_id)
     .fetch_optional(pool)
     .await?;
 
@@ -663,9 +691,11 @@ pub async fn get_binge_stats(pool: &SqlitePool) -> Result<BingeStats> {
             "SELECT m.title, DATE(r.last_read) as day, COUNT(*) as cnt
             FROM reading_history r
             JOIN media m ON r.media_id = m.id
+            WHERE r.profile_id = ?
             GROUP BY m.id, day
             ORDER BY cnt DESC LIMIT 1"
     )
+    .bind(profile_id)
     .fetch_optional(pool)
     .await?;
 
@@ -682,23 +712,25 @@ pub async fn get_binge_stats(pool: &SqlitePool) -> Result<BingeStats> {
 
 // ==================== New Stats Functions ====================
 
-pub async fn get_peak_hours(pool: &SqlitePool) -> Result<Vec<HourlyActivity>> {
+pub async fn get_peak_hours(pool: &SqlitePool, profile_id: i64) -> Result<Vec<HourlyActivity>> {
     let rows = sqlx::query(
         &format!(
             "SELECT hour, day_of_week, SUM(minutes) as minutes FROM (
                 SELECT CAST(strftime('%H', last_watched) AS INTEGER) as hour,
                     CAST(strftime('%w', last_watched) AS INTEGER) as day_of_week,
                     progress_seconds / 60.0 as minutes
-                FROM watch_history WHERE last_watched IS NOT NULL
+                FROM watch_history WHERE profile_id = ? AND last_watched IS NOT NULL
                 UNION ALL
                 SELECT CAST(strftime('%H', last_read) AS INTEGER) as hour,
                     CAST(strftime('%w', last_read) AS INTEGER) as day_of_week,
                     (CASE WHEN completed = 1 THEN COALESCE(total_pages, 0) ELSE current_page END) * {} as minutes
-                FROM reading_history WHERE last_read IS NOT NULL
+                FROM reading_history WHERE profile_id = ? AND last_read IS NOT NULL
             ) GROUP BY hour, day_of_week",
             READING_MINUTES_PER_PAGE
         )
     )
+    .bind(profile_id)
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -710,16 +742,17 @@ pub async fn get_peak_hours(pool: &SqlitePool) -> Result<Vec<HourlyActivity>> {
     }).collect())
 }
 
-pub async fn get_completion_rate(pool: &SqlitePool) -> Result<CompletionRateStats> {
+pub async fn get_completion_rate(pool: &SqlitePool, profile_id: i64) -> Result<CompletionRateStats> {
     let row = sqlx::query(
         "SELECT
-            (SELECT COUNT(DISTINCT media_id) FROM watch_history) as anime_started,
+            (SELECT COUNT(DISTINCT media_id) FROM watch_history WHERE profile_id = ?1) as anime_started,
             (SELECT COUNT(*) FROM library l JOIN media m ON l.media_id = m.id
-             WHERE l.status = 'completed' AND m.media_type = 'anime') as anime_completed,
-            (SELECT COUNT(DISTINCT media_id) FROM reading_history) as manga_started,
+             WHERE l.profile_id = ?1 AND l.status = 'completed' AND m.media_type = 'anime') as anime_completed,
+            (SELECT COUNT(DISTINCT media_id) FROM reading_history WHERE profile_id = ?1) as manga_started,
             (SELECT COUNT(*) FROM library l JOIN media m ON l.media_id = m.id
-             WHERE l.status = 'completed' AND m.media_type = 'manga') as manga_completed"
+             WHERE l.profile_id = ?1 AND l.status = 'completed' AND m.media_type = 'manga') as manga_completed"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -739,22 +772,24 @@ pub async fn get_completion_rate(pool: &SqlitePool) -> Result<CompletionRateStat
     })
 }
 
-pub async fn get_score_distribution(pool: &SqlitePool) -> Result<ScoreDistribution> {
+pub async fn get_score_distribution(pool: &SqlitePool, profile_id: i64) -> Result<ScoreDistribution> {
     let rows = sqlx::query(
         "SELECT CAST(l.score AS INTEGER) as score, COUNT(*) as count
          FROM library l
-         WHERE l.score > 0
+         WHERE l.profile_id = ? AND l.score > 0
          GROUP BY CAST(l.score AS INTEGER)
          ORDER BY score"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
     let avg_row = sqlx::query(
         "SELECT COALESCE(AVG(CAST(l.score AS REAL)), 0) as avg_score,
                 COUNT(*) as total
-         FROM library l WHERE l.score > 0"
+         FROM library l WHERE l.profile_id = ? AND l.score > 0"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -771,7 +806,7 @@ pub async fn get_score_distribution(pool: &SqlitePool) -> Result<ScoreDistributi
     })
 }
 
-pub async fn get_content_type_breakdown(pool: &SqlitePool) -> Result<Vec<ContentTypeEntry>> {
+pub async fn get_content_type_breakdown(pool: &SqlitePool, profile_id: i64) -> Result<Vec<ContentTypeEntry>> {
     let rows = sqlx::query(
         "SELECT
             COALESCE(m.content_type, 'Unknown') as content_type,
@@ -781,12 +816,13 @@ pub async fn get_content_type_breakdown(pool: &SqlitePool) -> Result<Vec<Content
          JOIN media m ON l.media_id = m.id
          LEFT JOIN (
              SELECT media_id, SUM(progress_seconds) as total_time
-             FROM watch_history GROUP BY media_id
+             FROM watch_history WHERE profile_id = ?1 GROUP BY media_id
          ) w_time ON w_time.media_id = m.id
-         WHERE m.media_type = 'anime'
+         WHERE l.profile_id = ?1 AND m.media_type = 'anime'
          GROUP BY COALESCE(m.content_type, 'Unknown')
          ORDER BY count DESC"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -798,7 +834,7 @@ pub async fn get_content_type_breakdown(pool: &SqlitePool) -> Result<Vec<Content
     }).collect())
 }
 
-pub async fn get_seasonal_trends(pool: &SqlitePool) -> Result<Vec<SeasonEntry>> {
+pub async fn get_seasonal_trends(pool: &SqlitePool, profile_id: i64) -> Result<Vec<SeasonEntry>> {
     let rows = sqlx::query(
         "SELECT
             COALESCE(m.season_quarter, 'unknown') as season,
@@ -806,7 +842,7 @@ pub async fn get_seasonal_trends(pool: &SqlitePool) -> Result<Vec<SeasonEntry>>
             COUNT(*) as count
          FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE m.media_type = 'anime'
+         WHERE l.profile_id = ? AND m.media_type = 'anime'
            AND (m.season_quarter IS NOT NULL OR m.season_year IS NOT NULL)
          GROUP BY season, year
          ORDER BY year DESC,
@@ -819,6 +855,7 @@ pub async fn get_seasonal_trends(pool: &SqlitePool) -> Result<Vec<SeasonEntry>>
             END DESC
          LIMIT 20"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -830,7 +867,7 @@ pub async fn get_seasonal_trends(pool: &SqlitePool) -> Result<Vec<SeasonEntry>>
     }).collect())
 }
 
-pub async fn get_watch_completion_rate(pool: &SqlitePool) -> Result<WatchCompletionRateStats> {
+pub async fn get_watch_completion_rate(pool: &SqlitePool, profile_id: i64) -> Result<WatchCompletionRateStats> {
     let row = sqlx::query(
         "SELECT
             COALESCE(AVG(CASE WHEN duration > 0 THEN (progress_seconds * 100.0 / duration) ELSE NULL END), 0) as avg_pct,
@@ -839,8 +876,10 @@ pub async fn get_watch_completion_rate(pool: &SqlitePool) -> Result<WatchComplet
                 / NULLIF(COUNT(CASE WHEN duration > 0 THEN 1 END), 0),
             0) as fully_pct,
             COUNT(*) as total
-         FROM watch_history"
+         FROM watch_history
+         WHERE profile_id = ?"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -852,7 +891,7 @@ pub async fn get_watch_completion_rate(pool: &SqlitePool) -> Result<WatchComplet
     })
 }
 
-pub async fn get_favorites_stats(pool: &SqlitePool) -> Result<FavoritesStats> {
+pub async fn get_favorites_stats(pool: &SqlitePool, profile_id: i64) -> Result<FavoritesStats> {
     let counts = sqlx::query(
         "SELECT
             COUNT(*) as total,
@@ -860,8 +899,9 @@ pub async fn get_favorites_stats(pool: &SqlitePool) -> Result<FavoritesStats> {
             COUNT(CASE WHEN m.media_type = 'manga' THEN 1 END) as manga_fav
          FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE l.favorite = 1"
+         WHERE l.profile_id = ? AND l.favorite = 1"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -869,20 +909,22 @@ pub async fn get_favorites_stats(pool: &SqlitePool) -> Result<FavoritesStats> {
         "SELECT j.value as genre
          FROM library l
          JOIN media m ON l.media_id = m.id, json_each(m.genres) j
-         WHERE l.favorite = 1 AND m.genres IS NOT NULL
+         WHERE l.profile_id = ? AND l.favorite = 1 AND m.genres IS NOT NULL
          GROUP BY j.value
          ORDER BY COUNT(*) DESC
          LIMIT 5"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
     let recent = sqlx::query(
         "SELECT m.title FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE l.favorite = 1
+         WHERE l.profile_id = ? AND l.favorite = 1
          ORDER BY l.updated_at DESC LIMIT 1"
     )
+    .bind(profile_id)
     .fetch_optional(pool)
     .await?;
 
@@ -896,18 +938,20 @@ pub async fn get_favorites_stats(pool: &SqlitePool) -> Result<FavoritesStats> {
     })
 }
 
-pub async fn get_time_to_completion(pool: &SqlitePool) -> Result<TimeToCompletion> {
+pub async fn get_time_to_completion(pool: &SqlitePool, profile_id: i64) -> Result<TimeToCompletion> {
     let rows = sqlx::query(
         "SELECT
             m.title,
             JULIANDAY(MAX(w.last_watched)) - JULIANDAY(MIN(w.last_watched)) as days_to_complete
          FROM watch_history w
          JOIN media m ON w.media_id = m.id
-         JOIN library l ON l.media_id = m.id AND l.status = 'completed'
+         JOIN library l ON l.media_id = m.id AND l.profile_id = w.profile_id AND l.status = 'completed'
+         WHERE w.profile_id = ?
          GROUP BY m.id
          HAVING COUNT(*) > 1 AND days_to_complete >= 0
          ORDER BY days_to_complete"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -934,7 +978,7 @@ pub async fn get_time_to_completion(pool: &SqlitePool) -> Result<TimeToCompletio
     })
 }
 
-pub async fn get_year_distribution(pool: &SqlitePool) -> Result<Vec<YearDistEntry>> {
+pub async fn get_year_distribution(pool: &SqlitePool, profile_id: i64) -> Result<Vec<YearDistEntry>> {
     let rows = sqlx::query(
         "SELECT
             COALESCE(m.year, m.aired_start_year) as release_year,
@@ -942,10 +986,11 @@ pub async fn get_year_distribution(pool: &SqlitePool) -> Result<Vec<YearDistEntr
             COUNT(CASE WHEN m.media_type = 'manga' THEN 1 END) as manga_count
          FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE COALESCE(m.year, m.aired_start_year) IS NOT NULL
+         WHERE l.profile_id = ? AND COALESCE(m.year, m.aired_start_year) IS NOT NULL
          GROUP BY release_year
          ORDER BY release_year"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -969,20 +1014,23 @@ fn make_milestone(id: &str, title: &str, description: &str, current: i32, target
     }
 }
 
-pub async fn get_milestones(pool: &SqlitePool) -> Result<MilestoneStats> {
+pub async fn get_milestones(pool: &SqlitePool, profile_id: i64) -> Result<MilestoneStats> {
     use sqlx::Row;
 
-    let ep_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM watch_history WHERE completed = 1")
+    let ep_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM watch_history WHERE profile_id = ? AND completed = 1")
+        .bind(profile_id)
         .fetch_one(pool).await?.get("cnt");
-    let ch_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM reading_history WHERE completed = 1")
+    let ch_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM reading_history WHERE profile_id = ? AND completed = 1")
+        .bind(profile_id)
         .fetch_one(pool).await?.get("cnt");
-    let series_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM library WHERE status = 'completed'")
+    let series_count: i32 = sqlx::query("SELECT COUNT(*) as cnt FROM library WHERE profile_id = ? AND status = 'completed'")
+        .bind(profile_id)
         .fetch_one(pool).await?.get("cnt");
     let genre_count: i32 = sqlx::query(
         "SELECT COUNT(DISTINCT j.value) as cnt FROM library l
          JOIN media m ON l.media_id = m.id, json_each(m.genres) j
-         WHERE m.genres IS NOT NULL"
-    ).fetch_one(pool).await?.get("cnt");
+         WHERE l.profile_id = ? AND m.genres IS NOT NULL"
+    ).bind(profile_id).fetch_one(pool).await?.get("cnt");
 
     let milestones = vec![
         make_milestone("ep_10", "First Steps", "Watch 10 episodes", ep_count, 10),
@@ -1001,49 +1049,52 @@ pub async fn get_milestones(pool: &SqlitePool) -> Result<MilestoneStats> {
     Ok(MilestoneStats { milestones, total_achieved })
 }
 
-pub async fn get_monthly_recap(pool: &SqlitePool) -> Result<MonthlyRecap> {
+pub async fn get_monthly_recap(pool: &SqlitePool, profile_id: i64) -> Result<MonthlyRecap> {
     let month_str = Local::now().format("%Y-%m").to_string();
     let month_display = Local::now().format("%B %Y").to_string();
 
     use sqlx::Row;
 
+
     let watch_row = sqlx::query(
         "SELECT COUNT(*) as eps_watched,
                 COALESCE(SUM(progress_seconds), 0) as time_seconds
          FROM watch_history
-         WHERE strftime('%Y-%m', last_watched) = ?"
-    ).bind(&month_str).fetch_one(pool).await?;
+         WHERE profile_id = ? AND strftime('%Y-%m', last_watched) = ?"
+    ).bind(profile_id).bind(&month_str).fetch_one(pool).await?;
 
     let read_row = sqlx::query(
         "SELECT COUNT(*) as chapters_read
          FROM reading_history
-         WHERE strftime('%Y-%m', last_read) = ?"
-    ).bind(&month_str).fetch_one(pool).await?;
+         WHERE profile_id = ? AND strftime('%Y-%m', last_read) = ?"
+    ).bind(profile_id).bind(&month_str).fetch_one(pool).await?;
 
     let new_series_row = sqlx::query(
         "SELECT COUNT(*) as cnt FROM (
             SELECT media_id FROM watch_history
+            WHERE profile_id = ?
             GROUP BY media_id
             HAVING strftime('%Y-%m', MIN(last_watched)) = ?
             UNION
             SELECT media_id FROM reading_history
+            WHERE profile_id = ?
             GROUP BY media_id
             HAVING strftime('%Y-%m', MIN(last_read)) = ?
         )"
-    ).bind(&month_str).bind(&month_str).fetch_one(pool).await?;
+    ).bind(profile_id).bind(&month_str).bind(profile_id).bind(&month_str).fetch_one(pool).await?;
 
     let completed_row = sqlx::query(
         "SELECT COUNT(*) as cnt FROM library l
-         WHERE l.status = 'completed' AND strftime('%Y-%m', l.updated_at) = ?"
-    ).bind(&month_str).fetch_one(pool).await?;
+         WHERE l.profile_id = ? AND l.status = 'completed' AND strftime('%Y-%m', l.updated_at) = ?"
+    ).bind(profile_id).bind(&month_str).fetch_one(pool).await?;
 
     let genre_row = sqlx::query(
         "SELECT j.value as genre, COUNT(*) as cnt
          FROM watch_history w
          JOIN media m ON w.media_id = m.id, json_each(m.genres) j
-         WHERE strftime('%Y-%m', w.last_watched) = ? AND m.genres IS NOT NULL
+         WHERE w.profile_id = ? AND strftime('%Y-%m', w.last_watched) = ? AND m.genres IS NOT NULL
          GROUP BY j.value ORDER BY cnt DESC LIMIT 1"
-    ).bind(&month_str).fetch_optional(pool).await?;
+    ).bind(profile_id).bind(&month_str).fetch_optional(pool).await?;
 
     Ok(MonthlyRecap {
         month: month_display,
@@ -1056,7 +1107,7 @@ pub async fn get_monthly_recap(pool: &SqlitePool) -> Result<MonthlyRecap> {
     })
 }
 
-pub async fn get_rating_comparison(pool: &SqlitePool) -> Result<Vec<RatingComparisonEntry>> {
+pub async fn get_rating_comparison(pool: &SqlitePool, profile_id: i64) -> Result<Vec<RatingComparisonEntry>> {
     let rows = sqlx::query(
         "SELECT m.title, m.cover_url,
                 CAST(l.score AS REAL) as user_score,
@@ -1064,10 +1115,11 @@ pub async fn get_rating_comparison(pool: &SqlitePool) -> Result<Vec<RatingCompar
                 (CAST(l.score AS REAL) - CAST(m.rating AS REAL)) as difference
          FROM library l
          JOIN media m ON l.media_id = m.id
-         WHERE l.score > 0 AND m.rating IS NOT NULL AND CAST(m.rating AS REAL) > 0
+         WHERE l.profile_id = ? AND l.score > 0 AND m.rating IS NOT NULL AND CAST(m.rating AS REAL) > 0
          ORDER BY ABS(CAST(l.score AS REAL) - CAST(m.rating AS REAL)) DESC
          LIMIT 10"
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
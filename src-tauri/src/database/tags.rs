@@ -28,13 +28,15 @@ pub struct LibraryTagWithCount {
 /// Create a new tag
 pub async fn create_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     name: &str,
     color: &str,
 ) -> Result<LibraryTag> {
     // Get the max sort_order to put new tag at the end
     let max_order: Option<i32> = sqlx::query_scalar(
-        "SELECT MAX(sort_order) FROM library_tags"
+        "SELECT MAX(sort_order) FROM library_tags WHERE profile_id = ?"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -42,10 +44,11 @@ pub async fn create_tag(
 
     sqlx::query(
         r#"
-        INSERT INTO library_tags (name, color, sort_order, created_at, updated_at)
-        VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        INSERT INTO library_tags (profile_id, name, color, sort_order, created_at, updated_at)
+        VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
         "#
     )
+    .bind(profile_id)
     .bind(name)
     .bind(color)
     .bind(sort_order)
@@ -59,9 +62,10 @@ pub async fn create_tag(
         r#"
         SELECT id, name, color, sort_order, created_at, updated_at
         FROM library_tags
-        WHERE name = ?
+        WHERE profile_id = ? AND name = ?
         "#
     )
+    .bind(profile_id)
     .bind(name)
     .fetch_one(pool)
     .await?;
@@ -70,14 +74,16 @@ pub async fn create_tag(
 }
 
 /// Get all tags
-pub async fn get_all_tags(pool: &SqlitePool) -> Result<Vec<LibraryTag>> {
+pub async fn get_all_tags(pool: &SqlitePool, profile_id: i64) -> Result<Vec<LibraryTag>> {
     let tags = sqlx::query_as::<_, LibraryTag>(
         r#"
         SELECT id, name, color, sort_order, created_at, updated_at
         FROM library_tags
+        WHERE profile_id = ?
         ORDER BY sort_order ASC, name ASC
         "#
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -85,7 +91,7 @@ pub async fn get_all_tags(pool: &SqlitePool) -> Result<Vec<LibraryTag>> {
 }
 
 /// Get all tags with their item counts
-pub async fn get_tags_with_counts(pool: &SqlitePool) -> Result<Vec<LibraryTagWithCount>> {
+pub async fn get_tags_with_counts(pool: &SqlitePool, profile_id: i64) -> Result<Vec<LibraryTagWithCount>> {
     use sqlx::Row;
 
     let rows = sqlx::query(
@@ -95,10 +101,12 @@ pub async fn get_tags_with_counts(pool: &SqlitePool) -> Result<Vec<LibraryTagWit
             COUNT(a.id) as item_count
         FROM library_tags t
         LEFT JOIN library_tag_assignments a ON t.id = a.tag_id
+        WHERE t.profile_id = ?
         GROUP BY t.id
         ORDER BY t.sort_order ASC, t.name ASC
         "#
     )
+    .bind(profile_id)
     .fetch_all(pool)
     .await?;
 
@@ -123,6 +131,7 @@ pub async fn get_tags_with_counts(pool: &SqlitePool) -> Result<Vec<LibraryTagWit
 /// Update a tag
 pub async fn update_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     tag_id: i64,
     name: Option<&str>,
     color: Option<&str>,
@@ -132,11 +141,12 @@ pub async fn update_tag(
             r#"
             UPDATE library_tags
             SET name = ?, updated_at = CURRENT_TIMESTAMP
-            WHERE id = ?
+            WHERE id = ? AND profile_id = ?
             "#
         )
         .bind(name)
         .bind(tag_id)
+        .bind(profile_id)
         .execute(pool)
         .await?;
     }
@@ -146,11 +156,12 @@ pub async fn update_tag(
             r#"
             UPDATE library_tags
             SET color = ?, updated_at = CURRENT_TIMESTAMP
-            WHERE id = ?
+            WHERE id = ? AND profile_id = ?
             "#
         )
         .bind(color)
         .bind(tag_id)
+        .bind(profile_id)
         .execute(pool)
         .await?;
     }
@@ -161,9 +172,10 @@ pub async fn update_tag(
 }
 
 /// Delete a tag
-pub async fn delete_tag(pool: &SqlitePool, tag_id: i64) -> Result<()> {
-    sqlx::query("DELETE FROM library_tags WHERE id = ?")
+pub async fn delete_tag(pool: &SqlitePool, profile_id: i64, tag_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM library_tags WHERE id = ? AND profile_id = ?")
         .bind(tag_id)
+        .bind(profile_id)
         .execute(pool)
         .await?;
 
@@ -175,13 +187,15 @@ pub async fn delete_tag(pool: &SqlitePool, tag_id: i64) -> Result<()> {
 /// Assign a tag to a media item (by media_id)
 pub async fn assign_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     tag_id: i64,
 ) -> Result<()> {
     // First, get the library entry ID for this media
     let library_entry_id: Option<i64> = sqlx::query_scalar(
-        "SELECT id FROM library WHERE media_id = ?"
+        "SELECT id FROM library WHERE profile_id = ? AND media_id = ?"
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_optional(pool)
     .await?;
@@ -189,15 +203,17 @@ pub async fn assign_tag(
     let library_entry_id = library_entry_id
         .ok_or_else(|| anyhow::anyhow!("Media not found in library"))?;
 
-    // Insert the assignment (ignore if already exists)
+    // Insert the assignment (ignore if already exists). Only the profile's
+    // own tags can be assigned.
     sqlx::query(
         r#"
         INSERT OR IGNORE INTO library_tag_assignments (library_entry_id, tag_id, created_at)
-        VALUES (?, ?, CURRENT_TIMESTAMP)
+        SELECT ?, id, CURRENT_TIMESTAMP FROM library_tags WHERE id = ? AND profile_id = ?
         "#
     )
     .bind(library_entry_id)
     .bind(tag_id)
+    .bind(profile_id)
     .execute(pool)
     .await?;
 
@@ -209,13 +225,15 @@ pub async fn assign_tag(
 /// Unassign a tag from a media item (by media_id)
 pub async fn unassign_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
     tag_id: i64,
 ) -> Result<()> {
     // First, get the library entry ID for this media
     let library_entry_id: Option<i64> = sqlx::query_scalar(
-        "SELECT id FROM library WHERE media_id = ?"
+        "SELECT id FROM library WHERE profile_id = ? AND media_id = ?"
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_optional(pool)
     .await?;
@@ -238,6 +256,7 @@ pub async fn unassign_tag(
 /// Get all tags for a specific media item
 pub async fn get_tags_for_media(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Vec<LibraryTag>> {
     let tags = sqlx::query_as::<_, LibraryTag>(
@@ -246,10 +265,11 @@ pub async fn get_tags_for_media(
         FROM library_tags t
         INNER JOIN library_tag_assignments a ON t.id = a.tag_id
         INNER JOIN library l ON a.library_entry_id = l.id
-        WHERE l.media_id = ?
+        WHERE l.profile_id = ? AND l.media_id = ?
         ORDER BY t.sort_order ASC, t.name ASC
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_all(pool)
     .await?;
@@ -372,14 +392,16 @@ pub async fn get_library_by_tag(
 /// Bulk assign a tag to multiple media items
 pub async fn bulk_assign_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     media_ids: &[String],
     tag_id: i64,
 ) -> Result<()> {
     for media_id in media_ids {
         // Get the library entry ID for this media
         let library_entry_id: Option<i64> = sqlx::query_scalar(
-            "SELECT id FROM library WHERE media_id = ?"
+            "SELECT id FROM library WHERE profile_id = ? AND media_id = ?"
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_optional(pool)
         .await?;
@@ -406,14 +428,16 @@ pub async fn bulk_assign_tag(
 /// Bulk unassign a tag from multiple media items
 pub async fn bulk_unassign_tag(
     pool: &SqlitePool,
+    profile_id: i64,
     media_ids: &[String],
     tag_id: i64,
 ) -> Result<()> {
     for media_id in media_ids {
         // Get the library entry ID for this media
         let library_entry_id: Option<i64> = sqlx::query_scalar(
-            "SELECT id FROM library WHERE media_id = ?"
+            "SELECT id FROM library WHERE profile_id = ? AND media_id = ?"
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_optional(pool)
         .await?;
@@ -26,34 +26,106 @@ pub struct WatchProgress {
     pub episode_number: i32,
     pub progress_seconds: f64,
     pub duration: Option<f64>,
+    /// Explicit watched/unwatched (e.g. "mark as watched"). None lets the
+    /// completion threshold decide.
+    pub completed: Option<bool>,
+}
+
+/// Outcome of saving watch progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WatchProgressSaved {
     pub completed: bool,
+    /// The episode just went from unwatched to watched
+    pub newly_completed: bool,
+}
+
+/// app_settings key: percentage of an episode that counts as watched
+pub const COMPLETION_THRESHOLD_SETTING: &str = "completion_threshold_percent";
+
+pub const DEFAULT_COMPLETION_THRESHOLD_PERCENT: f64 = 90.0;
+
+/// Read the completion threshold (1-100), falling back to the default
+pub async fn get_completion_threshold_percent(pool: &SqlitePool) -> f64 {
+    sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+        .bind(COMPLETION_THRESHOLD_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|percent| percent.is_finite() && *percent > 0.0)
+        .map(|percent| percent.min(100.0))
+        .unwrap_or(DEFAULT_COMPLETION_THRESHOLD_PERCENT)
 }
 
-/// Save or update watch progress
+/// Whether progress counts as watched. An explicit flag always wins; without
+/// one the episode is watched once progress reaches `threshold_percent` of a
+/// known duration. An episode already watched stays watched (rewatching from
+/// the start doesn't undo it), and without a duration nothing is decided.
+pub fn resolve_completed(
+    explicit: Option<bool>,
+    previously_completed: bool,
+    progress_seconds: f64,
+    duration: Option<f64>,
+    threshold_percent: f64,
+) -> bool {
+    if let Some(completed) = explicit {
+        return completed;
+    }
+
+    let crossed = duration
+        .filter(|d| d.is_finite() && *d > 0.0)
+        .is_some_and(|d| progress_seconds / d * 100.0 >= threshold_percent);
+
+    previously_completed || crossed
+}
+
+/// Save or update watch progress, deciding whether the episode is watched
 pub async fn save_watch_progress(
     pool: &SqlitePool,
+    profile_id: i64,
     progress: &WatchProgress,
-) -> Result<()> {
+) -> Result<WatchProgressSaved> {
+    let previously_completed: bool = sqlx::query_scalar(
+        "SELECT completed FROM watch_history WHERE profile_id = ? AND media_id = ? AND episode_id = ?"
+    )
+    .bind(profile_id)
+    .bind(&progress.media_id)
+    .bind(&progress.episode_id)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(false);
+
+    let threshold = get_completion_threshold_percent(pool).await;
+    let completed = resolve_completed(
+        progress.completed,
+        previously_completed,
+        progress.progress_seconds,
+        progress.duration,
+        threshold,
+    );
+
     sqlx::query(
         r#"
-        INSERT INTO watch_history (media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched)
-        VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
-        ON CONFLICT(media_id, episode_id) DO UPDATE SET
+        INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched)
+        VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(profile_id, media_id, episode_id) DO UPDATE SET
             progress_seconds = ?,
             duration = ?,
             completed = ?,
             last_watched = CURRENT_TIMESTAMP
         "#
     )
+    .bind(profile_id)
     .bind(&progress.media_id)
     .bind(&progress.episode_id)
     .bind(progress.episode_number)
     .bind(progress.progress_seconds)
     .bind(progress.duration)
-    .bind(progress.completed)
+    .bind(completed)
     .bind(progress.progress_seconds) // for UPDATE
     .bind(progress.duration) // for UPDATE
-    .bind(progress.completed) // for UPDATE
+    .bind(completed) // for UPDATE
     .execute(pool)
     .await?;
 
@@ -61,9 +133,9 @@ pub async fn save_watch_progress(
 
     // Automatically add to library with appropriate status
     use super::library::{add_to_library, LibraryStatus};
-    let library_status = if progress.completed {
+    let library_status = if completed {
         // Check if all episodes are completed
-        let all_completed = check_all_episodes_completed(pool, &progress.media_id).await?;
+        let all_completed = check_all_episodes_completed(pool, profile_id, &progress.media_id).await?;
         if all_completed {
             LibraryStatus::Completed
         } else {
@@ -74,17 +146,21 @@ pub async fn save_watch_progress(
     };
 
     // Add/update library entry (ON CONFLICT will update if already exists)
-    if let Err(e) = add_to_library(pool, &progress.media_id, library_status).await {
+    if let Err(e) = add_to_library(pool, profile_id, &progress.media_id, library_status).await {
         log::warn!("Failed to add media to library: {}", e);
         // Don't fail the entire operation if library update fails
     }
 
-    Ok(())
+    Ok(WatchProgressSaved {
+        completed,
+        newly_completed: completed && !previously_completed,
+    })
 }
 
 /// Check if all episodes of a media are completed
 async fn check_all_episodes_completed(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<bool> {
     // Get total episode count from media table
@@ -98,8 +174,9 @@ async fn check_all_episodes_completed(
     if let Some(total) = episode_count {
         // Count completed episodes in watch history
         let completed_count: i32 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM watch_history WHERE media_id = ? AND completed = 1"
+            "SELECT COUNT(*) FROM watch_history WHERE profile_id = ? AND media_id = ? AND completed = 1"
         )
+        .bind(profile_id)
         .bind(media_id)
         .fetch_one(pool)
         .await?;
@@ -114,15 +191,17 @@ async fn check_all_episodes_completed(
 /// Get watch progress for a specific episode
 pub async fn get_watch_progress(
     pool: &SqlitePool,
+    profile_id: i64,
     episode_id: &str,
 ) -> Result<Option<WatchHistory>> {
     let progress = sqlx::query_as::<_, WatchHistory>(
         r#"
         SELECT id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
         FROM watch_history
-        WHERE episode_id = ?
+        WHERE profile_id = ? AND episode_id = ?
         "#
     )
+    .bind(profile_id)
     .bind(episode_id)
     .fetch_optional(pool)
     .await?;
@@ -133,16 +212,18 @@ pub async fn get_watch_progress(
 /// Get watch progress for all episodes of a media
 pub async fn get_media_watch_history(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Vec<WatchHistory>> {
     let history = sqlx::query_as::<_, WatchHistory>(
         r#"
         SELECT id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
         FROM watch_history
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         ORDER BY episode_number ASC
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_all(pool)
     .await?;
@@ -153,17 +234,19 @@ pub async fn get_media_watch_history(
 /// Get the most recently watched episode for a media (for Resume Watching)
 pub async fn get_latest_watch_progress_for_media(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<Option<WatchHistory>> {
     let progress = sqlx::query_as::<_, WatchHistory>(
         r#"
         SELECT id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
         FROM watch_history
-        WHERE media_id = ?
+        WHERE profile_id = ? AND media_id = ?
         ORDER BY last_watched DESC
         LIMIT 1
         "#
     )
+    .bind(profile_id)
     .bind(media_id)
     .fetch_optional(pool)
     .await?;
@@ -174,18 +257,21 @@ pub async fn get_latest_watch_progress_for_media(
 /// Get continue watching list (recently watched, not completed)
 pub async fn get_continue_watching(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
 ) -> Result<Vec<WatchHistory>> {
     let history = sqlx::query_as::<_, WatchHistory>(
         r#"
         SELECT DISTINCT w.id, w.media_id, w.episode_id, w.episode_number, w.progress_seconds, w.duration, w.completed, w.last_watched, w.created_at
         FROM watch_history w
-        WHERE w.completed = 0
+        WHERE w.profile_id = ?
+        AND w.completed = 0
         AND w.progress_seconds > 0
         ORDER BY w.last_watched DESC
         LIMIT ?
         "#
     )
+    .bind(profile_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -197,15 +283,17 @@ pub async fn get_continue_watching(
 #[allow(dead_code)]
 pub async fn mark_episode_completed(
     pool: &SqlitePool,
+    profile_id: i64,
     episode_id: &str,
 ) -> Result<()> {
     sqlx::query(
         r#"
         UPDATE watch_history
         SET completed = 1, last_watched = CURRENT_TIMESTAMP
-        WHERE episode_id = ?
+        WHERE profile_id = ? AND episode_id = ?
         "#
     )
+    .bind(profile_id)
     .bind(episode_id)
     .execute(pool)
     .await?;
@@ -216,9 +304,11 @@ pub async fn mark_episode_completed(
 /// Delete watch history for a media
 pub async fn delete_media_watch_history(
     pool: &SqlitePool,
+    profile_id: i64,
     media_id: &str,
 ) -> Result<()> {
-    sqlx::query("DELETE FROM watch_history WHERE media_id = ?")
+    sqlx::query("DELETE FROM watch_history WHERE profile_id = ? AND media_id = ?")
+        .bind(profile_id)
         .bind(media_id)
         .execute(pool)
         .await?;
@@ -226,6 +316,95 @@ pub async fn delete_media_watch_history(
     Ok(())
 }
 
+/// Delete the watch history of a single episode, returning the deleted rows
+/// so the deletion can be undone with restore_watch_history. Continue
+/// watching falls back to the next most recent episode on its own.
+pub async fn delete_episode_watch_history(
+    pool: &SqlitePool,
+    profile_id: i64,
+    episode_id: &str,
+) -> Result<Vec<WatchHistory>> {
+    let deleted = sqlx::query_as::<_, WatchHistory>(
+        r#"
+        DELETE FROM watch_history
+        WHERE profile_id = ? AND episode_id = ?
+        RETURNING id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
+        "#
+    )
+    .bind(profile_id)
+    .bind(episode_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deleted)
+}
+
+/// Delete watch history for episodes from_episode..=to_episode of a media,
+/// returning the deleted rows
+pub async fn delete_watch_history_range(
+    pool: &SqlitePool,
+    profile_id: i64,
+    media_id: &str,
+    from_episode: i32,
+    to_episode: i32,
+) -> Result<Vec<WatchHistory>> {
+    let mut deleted = sqlx::query_as::<_, WatchHistory>(
+        r#"
+        DELETE FROM watch_history
+        WHERE profile_id = ? AND media_id = ? AND episode_number BETWEEN ? AND ?
+        RETURNING id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at
+        "#
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .bind(from_episode.min(to_episode))
+    .bind(from_episode.max(to_episode))
+    .fetch_all(pool)
+    .await?;
+
+    deleted.sort_by_key(|entry| entry.episode_number);
+    Ok(deleted)
+}
+
+/// Put deleted watch history rows back as they were (undo). Episodes that
+/// have been watched again since keep their newer progress. Returns the
+/// number of rows restored.
+pub async fn restore_watch_history(
+    pool: &SqlitePool,
+    profile_id: i64,
+    entries: &[WatchHistory],
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut restored = 0;
+
+    for entry in entries {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO watch_history
+                (id, profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(entry.id)
+        .bind(profile_id)
+        .bind(&entry.media_id)
+        .bind(&entry.episode_id)
+        .bind(entry.episode_number)
+        .bind(entry.progress_seconds)
+        .bind(entry.duration)
+        .bind(entry.completed)
+        .bind(&entry.last_watched)
+        .bind(&entry.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        restored += result.rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(restored)
+}
+
 impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for WatchHistory {
     fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
         use sqlx::Row;
@@ -243,3 +422,177 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for WatchHistory {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    fn progress(episode_id: &str, progress_seconds: f64, duration: Option<f64>, completed: Option<bool>) -> WatchProgress {
+        WatchProgress {
+            media_id: "m1".to_string(),
+            episode_id: episode_id.to_string(),
+            episode_number: 1,
+            progress_seconds,
+            duration,
+            completed,
+        }
+    }
+
+    #[test]
+    fn threshold_is_inclusive() {
+        assert!(resolve_completed(None, false, 90.0, Some(100.0), 90.0));
+        assert!(!resolve_completed(None, false, 89.9, Some(100.0), 90.0));
+        assert!(resolve_completed(None, false, 1440.0, Some(1440.0), 100.0));
+        assert!(!resolve_completed(None, false, 1439.0, Some(1440.0), 100.0));
+    }
+
+    #[test]
+    fn missing_or_bogus_duration_never_completes() {
+        for duration in [None, Some(0.0), Some(-1.0), Some(f64::NAN), Some(f64::INFINITY)] {
+            assert!(!resolve_completed(None, false, 1400.0, duration, 90.0), "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn explicit_flag_is_authoritative() {
+        assert!(resolve_completed(Some(true), false, 0.0, None, 90.0));
+        assert!(!resolve_completed(Some(false), true, 1440.0, Some(1440.0), 90.0));
+    }
+
+    #[test]
+    fn rewatching_keeps_an_episode_watched() {
+        assert!(resolve_completed(None, true, 10.0, Some(1440.0), 90.0));
+    }
+
+    #[tokio::test]
+    async fn save_reports_the_transition_to_watched_once() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Frieren', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES (?, '80')")
+            .bind(COMPLETION_THRESHOLD_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(get_completion_threshold_percent(pool).await, 80.0);
+
+        let saved = save_watch_progress(pool, DEFAULT_PROFILE_ID, &progress("e1", 700.0, Some(1000.0), None)).await.unwrap();
+        assert_eq!(saved, WatchProgressSaved { completed: false, newly_completed: false });
+
+        let saved = save_watch_progress(pool, DEFAULT_PROFILE_ID, &progress("e1", 800.0, Some(1000.0), None)).await.unwrap();
+        assert_eq!(saved, WatchProgressSaved { completed: true, newly_completed: true });
+
+        let saved = save_watch_progress(pool, DEFAULT_PROFILE_ID, &progress("e1", 950.0, Some(1000.0), None)).await.unwrap();
+        assert_eq!(saved, WatchProgressSaved { completed: true, newly_completed: false });
+
+        // Explicitly unmarking wins over the progress already recorded
+        let saved = save_watch_progress(pool, DEFAULT_PROFILE_ID, &progress("e1", 950.0, Some(1000.0), Some(false))).await.unwrap();
+        assert_eq!(saved, WatchProgressSaved { completed: false, newly_completed: false });
+
+        // No duration (e.g. a stream that never reported one) stays undecided
+        let saved = save_watch_progress(pool, DEFAULT_PROFILE_ID, &progress("e2", 5000.0, None, None)).await.unwrap();
+        assert!(!saved.completed);
+    }
+
+    #[tokio::test]
+    async fn unreadable_threshold_falls_back_to_default() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(get_completion_threshold_percent(pool).await, DEFAULT_COMPLETION_THRESHOLD_PERCENT);
+
+        for value in ["abc", "0", "-5"] {
+            sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+                .bind(COMPLETION_THRESHOLD_SETTING)
+                .bind(value)
+                .execute(pool)
+                .await
+                .unwrap();
+            assert_eq!(get_completion_threshold_percent(pool).await, DEFAULT_COMPLETION_THRESHOLD_PERCENT);
+        }
+    }
+
+    async fn seed_history(pool: &SqlitePool) {
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Frieren', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        for (episode, last_watched) in [(1, "2024-06-01 10:00:00"), (2, "2024-06-01 11:00:00"), (3, "2024-06-01 12:00:00")] {
+            sqlx::query(
+                "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, duration, completed, last_watched)
+                 VALUES (1, 'm1', ?, ?, 300, 1400, 0, ?)",
+            )
+            .bind(format!("e{}", episode))
+            .bind(episode)
+            .bind(last_watched)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    async fn continue_watching_episode(pool: &SqlitePool) -> Option<String> {
+        crate::database::media::get_continue_watching_with_media(pool, DEFAULT_PROFILE_ID, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.media.id == "m1")
+            .map(|entry| entry.episode_id)
+    }
+
+    #[tokio::test]
+    async fn deleting_the_latest_episode_falls_back_and_can_be_undone() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed_history(pool).await;
+
+        assert_eq!(continue_watching_episode(pool).await.as_deref(), Some("e3"));
+
+        let deleted = delete_episode_watch_history(pool, DEFAULT_PROFILE_ID, "e3").await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert!(get_watch_progress(pool, DEFAULT_PROFILE_ID, "e3").await.unwrap().is_none());
+        assert_eq!(continue_watching_episode(pool).await.as_deref(), Some("e2"));
+
+        assert_eq!(restore_watch_history(pool, DEFAULT_PROFILE_ID, &deleted).await.unwrap(), 1);
+        let restored = get_watch_progress(pool, DEFAULT_PROFILE_ID, "e3").await.unwrap().unwrap();
+        assert_eq!(restored.id, deleted[0].id);
+        assert_eq!(restored.last_watched, "2024-06-01 12:00:00");
+        assert_eq!(continue_watching_episode(pool).await.as_deref(), Some("e3"));
+
+        assert!(delete_episode_watch_history(pool, DEFAULT_PROFILE_ID, "missing").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn range_deletion_is_inclusive_and_restore_keeps_newer_progress() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        seed_history(pool).await;
+
+        // Reversed bounds are accepted
+        let deleted = delete_watch_history_range(pool, DEFAULT_PROFILE_ID, "m1", 3, 2).await.unwrap();
+        let numbers: Vec<i32> = deleted.iter().map(|entry| entry.episode_number).collect();
+        assert_eq!(numbers, vec![2, 3]);
+        assert_eq!(get_media_watch_history(pool, DEFAULT_PROFILE_ID, "m1").await.unwrap().len(), 1);
+
+        // Episode 2 gets rewatched before the undo
+        save_watch_progress(pool, DEFAULT_PROFILE_ID, &WatchProgress { episode_number: 2, ..progress("e2", 600.0, Some(1400.0), None) })
+            .await
+            .unwrap();
+
+        assert_eq!(restore_watch_history(pool, DEFAULT_PROFILE_ID, &deleted).await.unwrap(), 1);
+        assert_eq!(get_watch_progress(pool, DEFAULT_PROFILE_ID, "e2").await.unwrap().unwrap().progress_seconds, 600.0);
+        assert_eq!(get_media_watch_history(pool, DEFAULT_PROFILE_ID, "m1").await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,507 @@
+// Download Archiving
+//
+// Moves a finished series' episode files to cold storage (e.g. a NAS path)
+// while keeping them in the downloads list. Archived rows keep pointing at
+// the new location, so playback works whenever that storage is reachable;
+// when it isn't, the download shows as Offline rather than Failed.
+//
+// Subtitle files fetched with a download move along with its video.
+//
+// Moves try a plain rename first and fall back to copy + verify + delete when
+// the destination is on another filesystem.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use super::{DownloadManager, DownloadStatus, FileState};
+
+/// Result of an archive or unarchive run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveResult {
+    pub moved: usize,
+    pub bytes_moved: u64,
+    /// Per-file failures ("filename: reason"); the rest of the batch still runs
+    pub failed: Vec<String>,
+}
+
+/// Local vs archived download storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageBreakdown {
+    pub local_bytes: u64,
+    pub local_count: usize,
+    pub archived_bytes: u64,
+    pub archived_count: usize,
+    /// Archived downloads whose storage isn't reachable right now
+    pub offline_count: usize,
+}
+
+/// Result of re-checking completed downloads against the filesystem
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RescanResult {
+    pub checked: usize,
+    /// Local files that are missing (the download stays Completed)
+    pub missing: usize,
+    /// Archived files on unreachable storage (shown as Offline)
+    pub offline: usize,
+    /// Missing or unreachable files that are back
+    pub restored: usize,
+}
+
+impl DownloadManager {
+    /// Move every completed, local download of a media to `dest_dir`.
+    pub async fn archive_downloads(&self, media_id: &str, dest_dir: &Path) -> Result<ArchiveResult> {
+        tokio::fs::create_dir_all(dest_dir)
+            .await
+            .with_context(|| format!("Failed to create archive directory: {}", dest_dir.display()))?;
+
+        let candidates: Vec<(String, String, String)> = {
+            let downloads = self.downloads.read().await;
+            downloads
+                .values()
+                .filter(|d| d.media_id == media_id && d.status == DownloadStatus::Completed && d.file_state == FileState::Present)
+                .map(|d| (d.id.clone(), d.filename.clone(), d.file_path.clone()))
+                .collect()
+        };
+
+        let mut result = ArchiveResult::default();
+
+        for (id, filename, source) in candidates {
+            let source = PathBuf::from(source);
+            let dest = dest_dir.join(source.file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from(&filename)));
+
+            let moved = match move_file(&source, &dest).await {
+                Ok(bytes) => self
+                    .set_archive_location(&id, &dest, true, Some(&source))
+                    .await
+                    .map(|()| bytes),
+                Err(e) => Err(e),
+            };
+            if moved.is_ok() {
+                if let Some(pool) = &self.db_pool {
+                    super::subtitles::move_with_video(pool, &id, &dest).await;
+                }
+            }
+            match moved {
+                Ok(bytes) => {
+                    result.moved += 1;
+                    result.bytes_moved += bytes;
+                }
+                Err(e) => {
+                    log::error!("Failed to archive {}: {:#}", filename, e);
+                    result.failed.push(format!("{}: {:#}", filename, e));
+                }
+            }
+        }
+
+        log::info!(
+            "Archived {} downloads for {} ({} bytes, {} failed)",
+            result.moved, media_id, result.bytes_moved, result.failed.len()
+        );
+
+        Ok(result)
+    }
+
+    /// Move a media's archived downloads back to where they came from
+    /// (or into the downloads directory if the original location is unknown).
+    pub async fn unarchive_downloads(&self, media_id: &str) -> Result<ArchiveResult> {
+        let candidates: Vec<(String, String, String)> = {
+            let downloads = self.downloads.read().await;
+            downloads
+                .values()
+                .filter(|d| d.media_id == media_id && d.archived)
+                .map(|d| (d.id.clone(), d.filename.clone(), d.file_path.clone()))
+                .collect()
+        };
+
+        let mut result = ArchiveResult::default();
+
+        for (id, filename, source) in candidates {
+            let source = PathBuf::from(source);
+            let original = match self.original_path(&id).await {
+                Ok(original) => original,
+                Err(e) => {
+                    log::error!("Failed to look up the original location of {}: {:#}", filename, e);
+                    result.failed.push(format!("{}: {:#}", filename, e));
+                    continue;
+                }
+            };
+            let dest = original.unwrap_or_else(|| self.download_dir.join(&filename));
+
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await.ok();
+            }
+
+            let moved = match move_file(&source, &dest).await {
+                Ok(bytes) => self
+                    .set_archive_location(&id, &dest, false, None)
+                    .await
+                    .map(|()| bytes),
+                Err(e) => Err(e),
+            };
+            if moved.is_ok() {
+                if let Some(pool) = &self.db_pool {
+                    super::subtitles::move_with_video(pool, &id, &dest).await;
+                }
+            }
+            match moved {
+                Ok(bytes) => {
+                    result.moved += 1;
+                    result.bytes_moved += bytes;
+                }
+                Err(e) => {
+                    log::error!("Failed to unarchive {}: {:#}", filename, e);
+                    result.failed.push(format!("{}: {:#}", filename, e));
+                }
+            }
+        }
+
+        log::info!(
+            "Unarchived {} downloads for {} ({} bytes, {} failed)",
+            result.moved, media_id, result.bytes_moved, result.failed.len()
+        );
+
+        Ok(result)
+    }
+
+    /// Break download storage down into local and archived bytes
+    pub async fn get_storage_breakdown(&self) -> StorageBreakdown {
+        let downloads = self.downloads.read().await;
+        let mut breakdown = StorageBreakdown::default();
+
+        for d in downloads.values() {
+            match (&d.status, d.file_state) {
+                (DownloadStatus::Completed, FileState::Present) => {
+                    breakdown.local_bytes += d.total_bytes;
+                    breakdown.local_count += 1;
+                }
+                (DownloadStatus::Completed, FileState::Archived) => {
+                    breakdown.archived_bytes += d.total_bytes;
+                    breakdown.archived_count += 1;
+                }
+                (DownloadStatus::Offline, _) => {
+                    breakdown.archived_bytes += d.total_bytes;
+                    breakdown.archived_count += 1;
+                    breakdown.offline_count += 1;
+                }
+                _ => {}
+            }
+        }
+
+        breakdown
+    }
+
+    /// Re-check completed downloads against the filesystem.
+    ///
+    /// Missing files only change file_state, so the download stays Completed
+    /// and can be re-downloaded. Archived files on unreachable storage also
+    /// show as Offline until the storage is mounted again.
+    pub async fn rescan_downloads(&self) -> Result<RescanResult> {
+        let mut result = RescanResult::default();
+        let mut to_persist = Vec::new();
+
+        // Stat without holding the lock: archived files may sit on slow or
+        // unreachable network storage
+        let to_check: Vec<(String, String)> = {
+            let downloads = self.downloads.read().await;
+            downloads
+                .values()
+                .filter(|d| d.status == DownloadStatus::Completed || d.status == DownloadStatus::Offline)
+                .map(|d| (d.id.clone(), d.file_path.clone()))
+                .collect()
+        };
+        let mut observed = Vec::with_capacity(to_check.len());
+        for (id, file_path) in to_check {
+            let exists = tokio::fs::metadata(&file_path).await.is_ok();
+            observed.push((id, file_path, exists));
+        }
+
+        {
+            let mut downloads = self.downloads.write().await;
+            for (id, file_path, exists) in observed {
+                // Skip downloads that changed while their file was checked
+                let Some(d) = downloads.get_mut(&id) else {
+                    continue;
+                };
+                if d.file_path != file_path
+                    || (d.status != DownloadStatus::Completed && d.status != DownloadStatus::Offline)
+                {
+                    continue;
+                }
+                result.checked += 1;
+
+                let file_state = FileState::observe(d.file_state, d.archived, exists);
+                let status = if d.archived && !exists {
+                    DownloadStatus::Offline
+                } else {
+                    DownloadStatus::Completed
+                };
+
+                match (exists, d.archived) {
+                    (false, true) => result.offline += 1,
+                    (false, false) => result.missing += 1,
+                    (true, _) if d.file_state == FileState::Missing => result.restored += 1,
+                    _ => {}
+                }
+
+                if file_state != d.file_state || status != d.status {
+                    d.file_state = file_state;
+                    d.status = status;
+                    self.emit_progress(d);
+                    to_persist.push(d.clone());
+                }
+            }
+        }
+
+        for progress in &to_persist {
+            self.save_to_database(progress).await.ok();
+        }
+
+        log::debug!(
+            "Rescanned {} downloads ({} missing, {} offline, {} restored)",
+            result.checked, result.missing, result.offline, result.restored
+        );
+
+        Ok(result)
+    }
+
+    pub(super) async fn original_path(&self, download_id: &str) -> Result<Option<PathBuf>> {
+        let Some(pool) = &self.db_pool else {
+            return Ok(None);
+        };
+
+        let row = sqlx::query("SELECT original_path FROM downloads WHERE id = ?")
+            .bind(download_id)
+            .fetch_optional(pool.as_ref())
+            .await?;
+
+        Ok(row
+            .and_then(|r| r.try_get::<Option<String>, _>("original_path").ok().flatten())
+            .map(PathBuf::from))
+    }
+
+    /// Record a download's new location in memory and in the database
+    async fn set_archive_location(
+        &self,
+        download_id: &str,
+        path: &Path,
+        archived: bool,
+        original_path: Option<&Path>,
+    ) -> Result<()> {
+        let path_str = path.to_string_lossy().to_string();
+        let file_state = if archived { FileState::Archived } else { FileState::Present };
+
+        if let Some(pool) = &self.db_pool {
+            sqlx::query(
+                r#"
+                UPDATE downloads
+                SET file_path = ?, archived = ?, original_path = ?, file_state = ?, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#
+            )
+            .bind(&path_str)
+            .bind(archived)
+            .bind(original_path.map(|p| p.to_string_lossy().to_string()))
+            .bind(file_state.as_db_str())
+            .bind(download_id)
+            .execute(pool.as_ref())
+            .await?;
+        }
+
+        let mut downloads = self.downloads.write().await;
+        if let Some(d) = downloads.get_mut(download_id) {
+            d.file_path = path_str;
+            d.archived = archived;
+            d.file_state = file_state;
+            d.status = DownloadStatus::Completed;
+            self.emit_progress(d);
+        }
+
+        Ok(())
+    }
+}
+
+/// Move a file, falling back to copy + verify + delete across filesystems.
+///
+/// The copy goes to a `.partial` sibling first and is renamed into place only
+/// after its size matches the source, so an interrupted move never leaves a
+/// truncated file at the destination. Returns the number of bytes moved.
+pub async fn move_file(source: &Path, dest: &Path) -> Result<u64> {
+    let size = tokio::fs::metadata(source)
+        .await
+        .with_context(|| format!("Source file not found: {}", source.display()))?
+        .len();
+
+    if tokio::fs::metadata(dest).await.is_ok() {
+        anyhow::bail!("Destination already exists: {}", dest.display());
+    }
+
+    // Same filesystem: atomic and instant
+    if tokio::fs::rename(source, dest).await.is_ok() {
+        return Ok(size);
+    }
+
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let copied = match copy_and_sync(source, &partial).await {
+        Ok(n) => n,
+        Err(e) => {
+            tokio::fs::remove_file(&partial).await.ok();
+            return Err(e);
+        }
+    };
+
+    if copied != size {
+        tokio::fs::remove_file(&partial).await.ok();
+        anyhow::bail!("Copy verification failed: wrote {} of {} bytes", copied, size);
+    }
+
+    tokio::fs::rename(&partial, dest)
+        .await
+        .with_context(|| format!("Failed to finalize {}", dest.display()))?;
+
+    // Only drop the source once the destination is complete
+    if let Err(e) = tokio::fs::remove_file(source).await {
+        log::warn!("Moved {} but could not remove the original: {}", source.display(), e);
+    }
+
+    Ok(size)
+}
+
+async fn copy_and_sync(source: &Path, dest: &Path) -> Result<u64> {
+    let copied = tokio::fs::copy(source, dest)
+        .await
+        .with_context(|| format!("Failed to copy to {}", dest.display()))?;
+
+    // Make sure the data actually reached the (possibly network) disk
+    let file = tokio::fs::File::open(dest).await?;
+    file.sync_all().await?;
+
+    let written = tokio::fs::metadata(dest).await?.len();
+    if written != copied {
+        anyhow::bail!("Copy verification failed: {} bytes on disk, {} copied", written, copied);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::DownloadProgress;
+
+    fn completed_download(id: &str, file_path: &Path) -> DownloadProgress {
+        DownloadProgress {
+            id: id.to_string(),
+            media_id: "media-1".to_string(),
+            episode_id: format!("{}-ep", id),
+            episode_number: 1,
+            media_title: None,
+            filename: file_path.file_name().unwrap().to_string_lossy().to_string(),
+            url: "https://example.test/video.mp4".to_string(),
+            file_path: file_path.to_string_lossy().to_string(),
+            total_bytes: 5,
+            downloaded_bytes: 5,
+            percentage: 100.0,
+            speed: 0,
+            status: DownloadStatus::Completed,
+            error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality: None,
+            source_label: None,
+            replaces_download_id: None,
+            batch_id: None,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn move_file_refuses_to_overwrite() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("a.otaku");
+        let dest = temp_dir.path().join("b.otaku");
+        tokio::fs::write(&source, b"hello").await.unwrap();
+        tokio::fs::write(&dest, b"other").await.unwrap();
+
+        assert!(move_file(&source, &dest).await.is_err());
+        assert!(source.exists());
+    }
+
+    #[tokio::test]
+    async fn copy_fallback_verifies_and_cleans_up() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("a.otaku");
+        let dest = temp_dir.path().join("b.otaku");
+        tokio::fs::write(&source, b"hello world").await.unwrap();
+
+        let copied = copy_and_sync(&source, &dest).await.unwrap();
+
+        assert_eq!(copied, 11);
+        assert_eq!(tokio::fs::read(&dest).await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn archive_and_unarchive_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let downloads_dir = temp_dir.path().join("downloads");
+        let nas_dir = temp_dir.path().join("nas");
+        tokio::fs::create_dir_all(&downloads_dir).await.unwrap();
+
+        let local = downloads_dir.join("Episode_1.otaku");
+        tokio::fs::write(&local, b"video").await.unwrap();
+
+        let manager = DownloadManager::new(downloads_dir.clone());
+        manager
+            .downloads
+            .write()
+            .await
+            .insert("download-1".to_string(), completed_download("download-1", &local));
+
+        let archived = manager.archive_downloads("media-1", &nas_dir).await.unwrap();
+        assert_eq!(archived.moved, 1);
+        assert_eq!(archived.bytes_moved, 5);
+        assert!(!local.exists());
+
+        let progress = manager.get_progress("download-1").await.unwrap();
+        assert!(progress.archived);
+        assert_eq!(PathBuf::from(&progress.file_path), nas_dir.join("Episode_1.otaku"));
+
+        let breakdown = manager.get_storage_breakdown().await;
+        assert_eq!(breakdown.local_bytes, 0);
+        assert_eq!(breakdown.archived_bytes, 5);
+
+        // Storage goes away: Offline, not Failed
+        tokio::fs::remove_dir_all(&nas_dir).await.unwrap();
+        let rescan = manager.rescan_downloads().await.unwrap();
+        assert_eq!(rescan.offline, 1);
+        assert_eq!(rescan.missing, 0);
+        let offline = manager.get_progress("download-1").await.unwrap();
+        assert_eq!(offline.status, DownloadStatus::Offline);
+        assert_eq!(offline.file_state, FileState::Missing);
+
+        // Storage comes back
+        tokio::fs::create_dir_all(&nas_dir).await.unwrap();
+        tokio::fs::write(nas_dir.join("Episode_1.otaku"), b"video").await.unwrap();
+        let rescan = manager.rescan_downloads().await.unwrap();
+        assert_eq!(rescan.restored, 1);
+
+        // Without a database the original path is unknown, so it lands in the downloads dir
+        let unarchived = manager.unarchive_downloads("media-1").await.unwrap();
+        assert_eq!(unarchived.moved, 1);
+        assert!(local.exists());
+        assert!(!manager.get_progress("download-1").await.unwrap().archived);
+    }
+}
@@ -0,0 +1,476 @@
+// Auto-Delete Watched Episodes
+//
+// Opt-in: downloaded episodes are deleted (file and download row) once every
+// profile following the series has finished them and a grace period has
+// passed since the last of them did. Downloads are shared, so a series counts
+// as followed by each profile with it in their library or any history of it.
+// The last few watched episodes of each series can be kept, and series any
+// profile marked as favorite are never touched.
+//
+// A sweep runs when an episode is marked watched and periodically in the
+// background, so episodes still inside the grace period are picked up later.
+// Each sweep that deletes something sends one notification listing it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use super::DownloadManager;
+use crate::locale::{self, Locale};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key: "true" to delete watched downloads
+pub const AUTO_DELETE_ENABLED_SETTING: &str = "auto_delete_watched_enabled";
+
+/// app_settings key: watched episodes of each series to keep
+pub const AUTO_DELETE_KEEP_LAST_SETTING: &str = "auto_delete_watched_keep_last";
+
+/// app_settings key: hours after watching before an episode is deleted
+pub const AUTO_DELETE_GRACE_HOURS_SETTING: &str = "auto_delete_watched_grace_hours";
+
+const DEFAULT_GRACE_HOURS: u32 = 24;
+
+/// How often the background task sweeps
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Guards against the completion hook, the background task and the sweep
+/// command overlapping
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoDeleteSettings {
+    pub enabled: bool,
+    /// Most recently watched episodes of each series that are kept
+    #[serde(default)]
+    pub keep_last: u32,
+    /// Hours after an episode was watched before it is deleted
+    #[serde(default = "default_grace_hours")]
+    pub grace_hours: u32,
+}
+
+fn default_grace_hours() -> u32 {
+    DEFAULT_GRACE_HOURS
+}
+
+impl Default for AutoDeleteSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_last: 0,
+            grace_hours: DEFAULT_GRACE_HOURS,
+        }
+    }
+}
+
+/// A watched episode whose download was deleted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoDeletedEpisode {
+    pub media_id: String,
+    pub title: String,
+    pub episode_number: i32,
+    pub bytes: u64,
+}
+
+/// Read the auto-delete settings, falling back to defaults
+pub async fn load_settings(pool: &SqlitePool) -> Result<AutoDeleteSettings> {
+    let rows = sqlx::query("SELECT key, value FROM app_settings WHERE key IN (?, ?, ?)")
+        .bind(AUTO_DELETE_ENABLED_SETTING)
+        .bind(AUTO_DELETE_KEEP_LAST_SETTING)
+        .bind(AUTO_DELETE_GRACE_HOURS_SETTING)
+        .fetch_all(pool)
+        .await?;
+
+    let mut settings = AutoDeleteSettings::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            AUTO_DELETE_ENABLED_SETTING => settings.enabled = value == "true",
+            AUTO_DELETE_KEEP_LAST_SETTING => settings.keep_last = value.parse().unwrap_or(0),
+            AUTO_DELETE_GRACE_HOURS_SETTING => {
+                settings.grace_hours = value.parse().unwrap_or(DEFAULT_GRACE_HOURS);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Store the auto-delete settings
+pub async fn save_settings(pool: &SqlitePool, settings: &AutoDeleteSettings) -> Result<()> {
+    let values = [
+        (AUTO_DELETE_ENABLED_SETTING, settings.enabled.to_string()),
+        (AUTO_DELETE_KEEP_LAST_SETTING, settings.keep_last.to_string()),
+        (AUTO_DELETE_GRACE_HOURS_SETTING, settings.grace_hours.to_string()),
+    ];
+
+    for (key, value) in values {
+        sqlx::query(
+            r#"
+            INSERT INTO app_settings (key, value, updated_at)
+            VALUES (?, ?, strftime('%s', 'now') * 1000)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// An episode every profile following its series finished, in a series no
+/// profile marked as favorite
+pub(super) struct WatchedEpisode {
+    pub(super) media_id: String,
+    pub(super) episode_number: i32,
+    /// Unix timestamp (seconds) the last of the profiles finished it
+    pub(super) watched_at: i64,
+}
+
+/// Episodes finished by every profile following their series, outside any
+/// profile's favorites, most recently watched first within each series
+pub(super) async fn watched_episodes(pool: &SqlitePool) -> Result<Vec<WatchedEpisode>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT wh.media_id, wh.episode_number,
+               MAX(CAST(strftime('%s', wh.last_watched) AS INTEGER)) AS watched_at
+        FROM watch_history wh
+        WHERE wh.completed = 1
+          AND NOT EXISTS (
+              SELECT 1 FROM library l
+              WHERE l.media_id = wh.media_id AND l.favorite = 1
+          )
+        GROUP BY wh.media_id, wh.episode_number
+        HAVING COUNT(DISTINCT wh.profile_id) = (
+            SELECT COUNT(*) FROM (
+                SELECT profile_id FROM library WHERE media_id = wh.media_id
+                UNION
+                SELECT profile_id FROM watch_history WHERE media_id = wh.media_id
+            )
+        )
+        ORDER BY wh.media_id, watched_at DESC, wh.episode_number DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| WatchedEpisode {
+            media_id: r.get("media_id"),
+            episode_number: r.get("episode_number"),
+            watched_at: r.get::<Option<i64>, _>("watched_at").unwrap_or(0),
+        })
+        .collect())
+}
+
+impl DownloadManager {
+    /// Delete the downloads of watched episodes that are past the grace
+    /// period, keeping the last `keep_last` of each series. `now` is a Unix
+    /// timestamp in seconds. Returns what was deleted.
+    pub async fn sweep_watched_downloads(
+        &self,
+        settings: &AutoDeleteSettings,
+        now: i64,
+    ) -> Result<Vec<AutoDeletedEpisode>> {
+        let pool = self.db_pool.clone().context("Database not available")?;
+        let cutoff = now - i64::from(settings.grace_hours) * 60 * 60;
+
+        let mut kept: HashMap<String, u32> = HashMap::new();
+        let mut deleted = Vec::new();
+        for episode in watched_episodes(&pool).await? {
+            if self.get_episode_file_path(&episode.media_id, episode.episode_number).await.is_none() {
+                continue;
+            }
+
+            // The most recently watched downloads of each series stay
+            let kept_for_series = kept.entry(episode.media_id.clone()).or_default();
+            if *kept_for_series < settings.keep_last {
+                *kept_for_series += 1;
+                continue;
+            }
+            if episode.watched_at > cutoff {
+                continue;
+            }
+
+            let download = {
+                let downloads = self.downloads.read().await;
+                downloads
+                    .values()
+                    .find(|d| d.media_id == episode.media_id && d.episode_number == episode.episode_number)
+                    .cloned()
+            };
+            if let Err(e) = self.delete_episode_download(&episode.media_id, episode.episode_number).await {
+                log::warn!(
+                    "Failed to auto-delete {} episode {}: {}",
+                    episode.media_id, episode.episode_number, e
+                );
+                continue;
+            }
+
+            deleted.push(AutoDeletedEpisode {
+                title: download.as_ref().map(|d| d.display_title()).unwrap_or_else(|| episode.media_id.clone()),
+                bytes: download.map(|d| d.total_bytes).unwrap_or(0),
+                media_id: episode.media_id,
+                episode_number: episode.episode_number,
+            });
+        }
+
+        if !deleted.is_empty() {
+            log::info!("Auto-deleted {} watched episode download(s)", deleted.len());
+            if let Some(handle) = &self.app_handle {
+                let locale = locale::current_locale(pool.as_ref()).await;
+                let _ = emit_notification(handle, Some(pool.as_ref()), sweep_notification(&deleted, locale)).await;
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// Summary notification for a sweep that deleted something
+fn sweep_notification(deleted: &[AutoDeletedEpisode], locale: Locale) -> NotificationPayload {
+    let episodes: Vec<String> = deleted
+        .iter()
+        .map(|e| format!("{} {}", e.title, locale::episode_label(e.episode_number, locale)))
+        .collect();
+    let freed: u64 = deleted.iter().map(|e| e.bytes).sum();
+
+    NotificationPayload::new(
+        NotificationType::Info,
+        "Watched Episodes Cleaned Up",
+        format!(
+            "Deleted {} ({})",
+            episodes.join(", "),
+            locale::format_bytes(freed, locale)
+        ),
+    )
+    .with_source("download")
+    .with_action("Open Downloads", Some("/downloads".to_string()), None)
+    .with_metadata(serde_json::json!({ "deleted": deleted }))
+}
+
+/// Sweep once if enabled. Skipped while another sweep is running.
+async fn run_sweep(app_handle: &AppHandle) {
+    let manager = app_handle.state::<DownloadManager>();
+    let Some(pool) = manager.db_pool.clone() else {
+        return;
+    };
+
+    let settings = match load_settings(&pool).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::warn!("Failed to load auto-delete settings: {}", e);
+            return;
+        }
+    };
+    if !settings.enabled {
+        return;
+    }
+
+    if let Some(Err(e)) = sweep_exclusive(&manager, &settings).await {
+        log::warn!("Auto-delete sweep failed: {:#}", e);
+    }
+}
+
+/// Sweep now with `settings`, or `None` without sweeping while another sweep
+/// is running
+pub async fn sweep_exclusive(
+    manager: &DownloadManager,
+    settings: &AutoDeleteSettings,
+) -> Option<Result<Vec<AutoDeletedEpisode>>> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    let result = manager.sweep_watched_downloads(settings, chrono::Utc::now().timestamp()).await;
+    RUNNING.store(false, Ordering::SeqCst);
+    Some(result)
+}
+
+/// Called when an episode is marked watched: with no grace period it can go
+/// right away
+pub fn on_episode_watched(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        run_sweep(&app_handle).await;
+    });
+}
+
+/// Sweep in the background. Settings are re-read every cycle, so enabling
+/// needs no restart.
+pub fn start_auto_delete_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            run_sweep(&app_handle).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::Arc;
+
+    const HOUR: i64 = 60 * 60;
+
+    #[tokio::test]
+    async fn sweep_deletes_watched_downloads_past_the_grace_period() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let now = chrono::Utc::now().timestamp();
+
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type) VALUES ('show', 'ext', 'Show', 'anime'), ('fav', 'ext', 'Fav', 'anime')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO library (media_id, status, favorite, profile_id) VALUES ('fav', 'watching', 1, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Episodes 1-3 watched two days ago, 4 an hour ago, 5 not finished
+        for (media_id, episode, completed, hours_ago) in [
+            ("show", 1, 1, 48),
+            ("show", 2, 1, 47),
+            ("show", 3, 1, 46),
+            ("show", 4, 1, 1),
+            ("show", 5, 0, 0),
+            ("fav", 1, 1, 48),
+        ] {
+            sqlx::query(
+                r#"
+                INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed, last_watched)
+                VALUES (1, ?, ?, ?, 100, ?, datetime(?, 'unixepoch'))
+                "#,
+            )
+            .bind(media_id)
+            .bind(format!("{}-{}", media_id, episode))
+            .bind(episode)
+            .bind(completed)
+            .bind(now - hours_ago * HOUR)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool));
+        let mut files = HashMap::new();
+        for (media_id, episode) in [("show", 1), ("show", 2), ("show", 3), ("show", 4), ("show", 5), ("fav", 1)] {
+            let path = temp_dir.path().join(format!("{}-{}.mp4", media_id, episode));
+            std::fs::write(&path, vec![0u8; 1024]).unwrap();
+            manager.adopt_file(media_id, episode, &path, None).await.unwrap();
+            files.insert((media_id, episode), path);
+        }
+
+        // Keeping the last one spares episode 4 regardless of the grace period
+        let settings = AutoDeleteSettings { enabled: true, keep_last: 1, grace_hours: 24 };
+        let deleted = manager.sweep_watched_downloads(&settings, now).await.unwrap();
+
+        let mut numbers: Vec<i32> = deleted.iter().map(|e| e.episode_number).collect();
+        numbers.sort();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert!(deleted.iter().all(|e| e.media_id == "show" && e.bytes == 1024));
+        for episode in [1, 2, 3] {
+            assert!(!files[&("show", episode)].exists());
+            assert!(!manager.is_episode_downloaded("show", episode).await);
+        }
+        for key in [("show", 4), ("show", 5), ("fav", 1)] {
+            assert!(files[&key].exists());
+        }
+
+        // Without a kept episode, 4 still waits out its grace period.
+        // Favorites and unfinished episodes are never deleted.
+        let settings = AutoDeleteSettings { enabled: true, keep_last: 0, grace_hours: 24 };
+        assert!(manager.sweep_watched_downloads(&settings, now).await.unwrap().is_empty());
+        let settings = AutoDeleteSettings { enabled: true, keep_last: 0, grace_hours: 0 };
+        let deleted = manager.sweep_watched_downloads(&settings, now).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].episode_number, 4);
+        assert!(files[&("fav", 1)].exists());
+    }
+
+    #[tokio::test]
+    async fn episodes_other_profiles_still_follow_are_kept() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let now = chrono::Utc::now().timestamp();
+
+        sqlx::query("INSERT INTO profiles (id, name, created_at) VALUES (2, 'Second', CURRENT_TIMESTAMP), (3, 'Third', CURRENT_TIMESTAMP)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type) VALUES ('show', 'ext', 'Show', 'anime'), ('fav', 'ext', 'Fav', 'anime')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // Profile 2 follows the show without having watched it yet, profile 3
+        // made the other series a favorite
+        sqlx::query(
+            "INSERT INTO library (media_id, status, favorite, profile_id) VALUES ('show', 'plan_to_watch', 0, 2), ('fav', 'watching', 1, 3)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for (profile_id, media_id, episode) in [(1, "show", 1), (1, "show", 2), (2, "show", 1), (1, "fav", 1)] {
+            sqlx::query(
+                r#"
+                INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed, last_watched)
+                VALUES (?, ?, ?, ?, 100, 1, datetime(?, 'unixepoch'))
+                "#,
+            )
+            .bind(profile_id)
+            .bind(media_id)
+            .bind(format!("{}-{}", media_id, episode))
+            .bind(episode)
+            .bind(now - 48 * HOUR)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool));
+        for (media_id, episode) in [("show", 1), ("show", 2), ("fav", 1)] {
+            let path = temp_dir.path().join(format!("{}-{}.mp4", media_id, episode));
+            std::fs::write(&path, vec![0u8; 1024]).unwrap();
+            manager.adopt_file(media_id, episode, &path, None).await.unwrap();
+        }
+
+        let settings = AutoDeleteSettings { enabled: true, keep_last: 0, grace_hours: 24 };
+        let deleted = manager.sweep_watched_downloads(&settings, now).await.unwrap();
+        let deleted: Vec<(&str, i32)> = deleted.iter().map(|e| (e.media_id.as_str(), e.episode_number)).collect();
+        assert_eq!(deleted, vec![("show", 1)]);
+        assert!(manager.is_episode_downloaded("show", 2).await);
+        assert!(manager.is_episode_downloaded("fav", 1).await);
+    }
+
+    #[tokio::test]
+    async fn settings_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool();
+
+        assert_eq!(load_settings(pool).await.unwrap(), AutoDeleteSettings::default());
+
+        let settings = AutoDeleteSettings { enabled: true, keep_last: 2, grace_hours: 6 };
+        save_settings(pool, &settings).await.unwrap();
+        assert_eq!(load_settings(pool).await.unwrap(), settings);
+    }
+}
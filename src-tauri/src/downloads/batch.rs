@@ -0,0 +1,504 @@
+// Download Batches
+//
+// Episodes queued together (download all / download selected) share a
+// batch_id. Batch members don't get their own complete/failed notifications;
+// once the last member reaches a terminal state (completed, failed or
+// cancelled) the batch gets one summary notification listing the failures,
+// with an action to retry them. Progress events are emitted as usual.
+//
+// A batch is open while its episodes are still being queued: a member that
+// finishes before the rest are queued doesn't end it. Whoever queues the
+// batch seals it after the last episode, and the summary goes out then if
+// everything queued so far has already finished.
+//
+// Episodes that are already downloaded are left out of a new batch unless it
+// overwrites them, and reported back to the caller.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+use tokio::sync::RwLock;
+
+use super::{DownloadProgress, DownloadStatus};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// Notification action callback that retries a batch's failed members
+pub const RETRY_BATCH_CALLBACK: &str = "retry_download_batch";
+
+/// Batches whose summary has been sent. Two members finishing at once can
+/// both see the batch done; only the first to claim it notifies.
+static NOTIFIED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Batches still having episodes queued into them
+static OPEN: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// How one episode of a batch ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchOutcome {
+    pub download_id: String,
+    pub episode_number: i32,
+    pub status: DownloadStatus,
+    pub error_message: Option<String>,
+}
+
+/// A batch whose members have all finished
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub batch_id: String,
+    pub media_id: String,
+    pub title: String,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Ordered by episode number
+    pub outcomes: Vec<BatchOutcome>,
+}
+
+/// Where a batch stands while it runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub media_id: String,
+    pub total: usize,
+    pub queued: usize,
+    pub downloading: usize,
+    pub paused: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub downloaded_bytes: u64,
+    /// Sum of the known sizes; members that haven't started yet add nothing
+    pub total_bytes: u64,
+}
+
+fn is_terminal(status: &DownloadStatus) -> bool {
+    matches!(
+        status,
+        DownloadStatus::Completed | DownloadStatus::Offline | DownloadStatus::Failed | DownloadStatus::Cancelled
+    )
+}
+
+/// Series title from a download filename (format: Title_EP1_quality.mp4),
+/// for downloads saved without media_title. Cut at the last "_EP" before an
+/// episode number, so titles containing "_EP" come through whole.
+pub(crate) fn title_from_filename(filename: &str) -> String {
+    let end = filename
+        .match_indices("_EP")
+        .filter(|(i, _)| filename[i + 3..].starts_with(|c: char| c.is_ascii_digit()))
+        .last()
+        .map_or(filename.len(), |(i, _)| i);
+    filename[..end].replace('_', " ")
+}
+
+/// Summary of a batch, or None while any member is still queued, running or
+/// paused
+pub fn batch_summary(batch_id: &str, downloads: &HashMap<String, DownloadProgress>) -> Option<BatchSummary> {
+    let mut members: Vec<&DownloadProgress> = downloads
+        .values()
+        .filter(|d| d.batch_id.as_deref() == Some(batch_id))
+        .collect();
+
+    if members.is_empty() || !members.iter().all(|d| is_terminal(&d.status)) {
+        return None;
+    }
+    members.sort_by_key(|d| d.episode_number);
+
+    let count = |wanted: &[DownloadStatus]| members.iter().filter(|d| wanted.contains(&d.status)).count();
+
+    Some(BatchSummary {
+        batch_id: batch_id.to_string(),
+        media_id: members[0].media_id.clone(),
+        title: members[0].display_title(),
+        completed: count(&[DownloadStatus::Completed, DownloadStatus::Offline]),
+        failed: count(&[DownloadStatus::Failed]),
+        cancelled: count(&[DownloadStatus::Cancelled]),
+        outcomes: members
+            .iter()
+            .map(|d| BatchOutcome {
+                download_id: d.id.clone(),
+                episode_number: d.episode_number,
+                status: d.status.clone(),
+                error_message: d.error_message.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// Progress of each batch of a media item, ordered by batch id
+pub fn batch_progress(media_id: &str, downloads: &HashMap<String, DownloadProgress>) -> Vec<BatchProgress> {
+    let mut batches: HashMap<&str, BatchProgress> = HashMap::new();
+
+    for download in downloads.values().filter(|d| d.media_id == media_id) {
+        let Some(batch_id) = download.batch_id.as_deref() else {
+            continue;
+        };
+        let batch = batches.entry(batch_id).or_insert_with(|| BatchProgress {
+            batch_id: batch_id.to_string(),
+            media_id: media_id.to_string(),
+            total: 0,
+            queued: 0,
+            downloading: 0,
+            paused: 0,
+            completed: 0,
+            failed: 0,
+            cancelled: 0,
+            downloaded_bytes: 0,
+            total_bytes: 0,
+        });
+
+        batch.total += 1;
+        match download.status {
+            DownloadStatus::Queued => batch.queued += 1,
+            DownloadStatus::Downloading => batch.downloading += 1,
+            DownloadStatus::Paused => batch.paused += 1,
+            DownloadStatus::Completed | DownloadStatus::Offline => batch.completed += 1,
+            DownloadStatus::Failed => batch.failed += 1,
+            DownloadStatus::Cancelled => batch.cancelled += 1,
+        }
+        batch.downloaded_bytes += download.downloaded_bytes;
+        batch.total_bytes += download.total_bytes;
+    }
+
+    let mut batches: Vec<BatchProgress> = batches.into_values().collect();
+    batches.sort_by(|a, b| a.batch_id.cmp(&b.batch_id));
+    batches
+}
+
+/// Summary notification for a finished batch; None when every member was
+/// cancelled, since there's nothing to report
+pub fn summary_notification(summary: &BatchSummary) -> Option<NotificationPayload> {
+    if summary.completed == 0 && summary.failed == 0 {
+        return None;
+    }
+
+    let attempted = summary.completed + summary.failed;
+    let (notification_type, title) = match (summary.completed, summary.failed) {
+        (_, 0) => (NotificationType::Success, "Downloads Complete"),
+        (0, _) => (NotificationType::Error, "Downloads Failed"),
+        _ => (NotificationType::Warning, "Downloads Finished With Errors"),
+    };
+
+    let mut message = format!(
+        "{}: {} of {} episodes downloaded",
+        summary.title, summary.completed, attempted
+    );
+    if summary.failed > 0 {
+        let failed: Vec<String> = summary
+            .outcomes
+            .iter()
+            .filter(|o| o.status == DownloadStatus::Failed)
+            .map(|o| o.episode_number.to_string())
+            .collect();
+        message.push_str(&format!(". Failed: episode {}", failed.join(", ")));
+    }
+
+    let notification = NotificationPayload::new(notification_type, title, message)
+        .with_source("download")
+        .with_metadata(serde_json::json!({
+            "batch_id": summary.batch_id,
+            "media_id": summary.media_id,
+            "title": summary.title,
+            "completed": summary.completed,
+            "failed": summary.failed,
+            "cancelled": summary.cancelled,
+            "outcomes": summary.outcomes,
+        }));
+
+    Some(if summary.failed > 0 {
+        notification.with_action("Retry Failed", Some("/downloads".to_string()), Some(RETRY_BATCH_CALLBACK.to_string()))
+    } else {
+        notification.with_action("Open Downloads", Some("/downloads".to_string()), None)
+    })
+}
+
+/// The episodes of a new batch, split by what happens to them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchSelection {
+    /// (download id, episode id, episode number) of the episodes to queue
+    pub to_queue: Vec<(String, String, i32)>,
+    /// Episode numbers left out because they're downloaded already
+    pub already_downloaded: Vec<i32>,
+}
+
+/// Pick which of `episodes` ((episode id, episode number) pairs) a new batch
+/// queues. Episodes waiting or downloading already are left alone, and
+/// downloaded ones are skipped unless `overwrite` is set. Failed and
+/// cancelled downloads are queued again.
+pub fn select_episodes(
+    media_id: &str,
+    episodes: &[(String, i32)],
+    downloads: &HashMap<String, DownloadProgress>,
+    overwrite: bool,
+) -> BatchSelection {
+    let mut selection = BatchSelection::default();
+
+    for (episode_id, episode_number) in episodes {
+        let downloaded = downloads.values().any(|d| {
+            d.media_id == media_id
+                && d.episode_number == *episode_number
+                && d.status == DownloadStatus::Completed
+                && d.file_state.is_playable()
+        });
+        if downloaded && !overwrite {
+            selection.already_downloaded.push(*episode_number);
+            continue;
+        }
+
+        let download_id = format!("{}_{}", media_id, episode_number);
+        let in_progress = downloads.get(&download_id).is_some_and(|d| {
+            matches!(
+                d.status,
+                DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused | DownloadStatus::Offline
+            )
+        });
+        if !in_progress {
+            selection.to_queue.push((download_id, episode_id.clone(), *episode_number));
+        }
+    }
+
+    selection
+}
+
+/// Mark a batch's summary as sent; false if it already was
+fn claim(batch_id: &str) -> bool {
+    NOTIFIED.lock().unwrap().insert(batch_id.to_string())
+}
+
+/// Mark a batch as still being queued into
+pub(super) fn open(batch_id: &str) {
+    OPEN.lock().unwrap().insert(batch_id.to_string());
+}
+
+/// Mark a batch as complete; false if it wasn't open
+pub(super) fn seal(batch_id: &str) -> bool {
+    OPEN.lock().unwrap().remove(batch_id)
+}
+
+fn is_open(batch_id: &str) -> bool {
+    OPEN.lock().unwrap().contains(batch_id)
+}
+
+/// Allow another summary for a batch, e.g. after retrying its failures
+pub(super) fn reset(batch_id: &str) {
+    NOTIFIED.lock().unwrap().remove(batch_id);
+}
+
+/// Send the batch's summary if it's sealed and its last member just finished
+pub(super) async fn notify_if_finished(
+    downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+    batch_id: &str,
+    app_handle: Option<&AppHandle>,
+    db_pool: Option<&Arc<SqlitePool>>,
+) {
+    if is_open(batch_id) {
+        return;
+    }
+    let Some(summary) = batch_summary(batch_id, &*downloads.read().await) else {
+        return;
+    };
+    if !claim(batch_id) {
+        return;
+    }
+
+    log::debug!(
+        "Download batch {} finished: {} completed, {} failed, {} cancelled",
+        batch_id, summary.completed, summary.failed, summary.cancelled
+    );
+
+    if let (Some(handle), Some(notification)) = (app_handle, summary_notification(&summary)) {
+        let _ = emit_notification(handle, db_pool.map(|p| p.as_ref()), notification).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::FileState;
+
+    fn member(episode_number: i32, status: DownloadStatus, batch_id: Option<&str>) -> DownloadProgress {
+        DownloadProgress {
+            id: format!("media-1_{}", episode_number),
+            media_id: "media-1".to_string(),
+            episode_id: format!("episode-{}", episode_number),
+            episode_number,
+            media_title: None,
+            filename: format!("Frieren_EP{}_1080p.mp4", episode_number),
+            url: "https://example.test/video.mp4".to_string(),
+            file_path: format!("/downloads/Frieren_EP{}_1080p.mp4", episode_number),
+            total_bytes: 0,
+            downloaded_bytes: 0,
+            percentage: 0.0,
+            speed: 0,
+            error_message: (status == DownloadStatus::Failed).then(|| "HTTP 403".to_string()),
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            status,
+            archived: false,
+            quality: None,
+            source_label: None,
+            replaces_download_id: None,
+            batch_id: batch_id.map(str::to_string),
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: Default::default(),
+        }
+    }
+
+    fn downloads(members: Vec<DownloadProgress>) -> HashMap<String, DownloadProgress> {
+        members.into_iter().map(|d| (d.id.clone(), d)).collect()
+    }
+
+    #[test]
+    fn titles_from_filenames_keep_ep_in_the_title() {
+        assert_eq!(title_from_filename("Frieren_EP3_1080p.mp4"), "Frieren");
+        assert_eq!(title_from_filename("Steps_EP_Up_EP12_720p.mp4"), "Steps EP Up");
+        assert_eq!(title_from_filename("DEEP_Insanity_EP1.otaku"), "DEEP Insanity");
+        assert_eq!(title_from_filename("NoNumber.mp4"), "NoNumber.mp4");
+    }
+
+    #[test]
+    fn mixed_batch_reports_once_every_member_is_done() {
+        let mut map = downloads(vec![
+            member(3, DownloadStatus::Failed, Some("b1")),
+            member(1, DownloadStatus::Completed, Some("b1")),
+            member(2, DownloadStatus::Downloading, Some("b1")),
+            member(4, DownloadStatus::Cancelled, Some("b1")),
+            // Not part of the batch
+            member(9, DownloadStatus::Queued, None),
+        ]);
+
+        assert_eq!(batch_summary("b1", &map), None);
+
+        map.get_mut("media-1_2").unwrap().status = DownloadStatus::Completed;
+        let summary = batch_summary("b1", &map).unwrap();
+        assert_eq!((summary.completed, summary.failed, summary.cancelled), (2, 1, 1));
+        assert_eq!(summary.title, "Frieren");
+        let episodes: Vec<i32> = summary.outcomes.iter().map(|o| o.episode_number).collect();
+        assert_eq!(episodes, vec![1, 2, 3, 4]);
+        assert_eq!(summary.outcomes[2].error_message.as_deref(), Some("HTTP 403"));
+
+        let notification = summary_notification(&summary).unwrap();
+        assert_eq!(notification.notification_type, NotificationType::Warning);
+        assert_eq!(notification.message, "Frieren: 2 of 3 episodes downloaded. Failed: episode 3");
+        let action = notification.action.unwrap();
+        assert_eq!(action.callback.as_deref(), Some(RETRY_BATCH_CALLBACK));
+        let metadata = notification.metadata.unwrap();
+        assert_eq!(metadata["batch_id"], "b1");
+        assert_eq!(metadata["outcomes"].as_array().unwrap().len(), 4);
+        assert_eq!(metadata["outcomes"][2]["status"], "failed");
+    }
+
+    #[test]
+    fn paused_members_keep_the_batch_open() {
+        let map = downloads(vec![
+            member(1, DownloadStatus::Completed, Some("b1")),
+            member(2, DownloadStatus::Paused, Some("b1")),
+        ]);
+        assert_eq!(batch_summary("b1", &map), None);
+        assert_eq!(batch_summary("unknown", &map), None);
+    }
+
+    #[tokio::test]
+    async fn an_open_batch_is_summarised_only_once_sealed() {
+        let batch_id = "test.batch.sealing";
+        let map = Arc::new(RwLock::new(downloads(vec![member(1, DownloadStatus::Completed, Some(batch_id))])));
+
+        // The first episode finished while the rest were still being queued
+        open(batch_id);
+        notify_if_finished(&map, batch_id, None, None).await;
+        assert!(!NOTIFIED.lock().unwrap().contains(batch_id));
+
+        assert!(seal(batch_id));
+        assert!(!seal(batch_id));
+        notify_if_finished(&map, batch_id, None, None).await;
+        assert!(NOTIFIED.lock().unwrap().contains(batch_id));
+    }
+
+    #[test]
+    fn notification_type_follows_the_outcome() {
+        let all_done = downloads(vec![
+            member(1, DownloadStatus::Completed, Some("b1")),
+            member(2, DownloadStatus::Completed, Some("b1")),
+        ]);
+        let notification = summary_notification(&batch_summary("b1", &all_done).unwrap()).unwrap();
+        assert_eq!(notification.notification_type, NotificationType::Success);
+        assert_eq!(notification.action.unwrap().callback, None);
+
+        let all_failed = downloads(vec![
+            member(1, DownloadStatus::Failed, Some("b1")),
+            member(2, DownloadStatus::Failed, Some("b1")),
+        ]);
+        let notification = summary_notification(&batch_summary("b1", &all_failed).unwrap()).unwrap();
+        assert_eq!(notification.notification_type, NotificationType::Error);
+        assert_eq!(notification.message, "Frieren: 0 of 2 episodes downloaded. Failed: episode 1, 2");
+
+        let all_cancelled = downloads(vec![member(1, DownloadStatus::Cancelled, Some("b1"))]);
+        assert!(summary_notification(&batch_summary("b1", &all_cancelled).unwrap()).is_none());
+    }
+
+    #[test]
+    fn progress_is_grouped_per_batch_of_the_media() {
+        let mut running = member(2, DownloadStatus::Downloading, Some("b1"));
+        running.downloaded_bytes = 40;
+        running.total_bytes = 100;
+        let mut other_media = member(5, DownloadStatus::Queued, Some("b1"));
+        other_media.media_id = "media-2".to_string();
+
+        let map = downloads(vec![
+            member(1, DownloadStatus::Completed, Some("b1")),
+            running,
+            member(3, DownloadStatus::Queued, Some("b1")),
+            member(4, DownloadStatus::Queued, Some("b2")),
+            member(9, DownloadStatus::Queued, None),
+            other_media,
+        ]);
+
+        let batches = batch_progress("media-1", &map);
+        assert_eq!(batches.len(), 2);
+        let b1 = &batches[0];
+        assert_eq!(b1.batch_id, "b1");
+        assert_eq!((b1.total, b1.completed, b1.downloading, b1.queued), (3, 1, 1, 1));
+        assert_eq!((b1.downloaded_bytes, b1.total_bytes), (40, 100));
+        assert_eq!((batches[1].batch_id.as_str(), batches[1].queued), ("b2", 1));
+    }
+
+    #[test]
+    fn a_batch_is_claimed_once_until_reset() {
+        assert!(claim("claim-test"));
+        assert!(!claim("claim-test"));
+        reset("claim-test");
+        assert!(claim("claim-test"));
+    }
+
+    #[test]
+    fn batches_skip_downloaded_episodes_unless_overwriting() {
+        let mut gone = member(5, DownloadStatus::Completed, None);
+        gone.file_state = FileState::Missing;
+        let map = downloads(vec![
+            member(1, DownloadStatus::Completed, None),
+            member(2, DownloadStatus::Downloading, None),
+            member(3, DownloadStatus::Failed, None),
+            member(4, DownloadStatus::Cancelled, None),
+            gone,
+        ]);
+        let episodes: Vec<(String, i32)> = (1..=6).map(|n| (format!("episode-{}", n), n)).collect();
+        let queued = |selection: &BatchSelection| selection.to_queue.iter().map(|(_, _, n)| *n).collect::<Vec<_>>();
+
+        let selection = select_episodes("media-1", &episodes, &map, false);
+        assert_eq!(queued(&selection), vec![3, 4, 5, 6]);
+        assert_eq!(selection.already_downloaded, vec![1]);
+        assert_eq!(selection.to_queue[0], ("media-1_3".to_string(), "episode-3".to_string(), 3));
+
+        let selection = select_episodes("media-1", &episodes, &map, true);
+        assert_eq!(queued(&selection), vec![1, 3, 4, 5, 6]);
+        assert!(selection.already_downloaded.is_empty());
+    }
+}
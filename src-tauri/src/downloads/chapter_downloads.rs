@@ -5,15 +5,17 @@
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tokio::fs;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Manager};
 use crate::downloads::DownloadManager;
+use crate::extensions::ChapterImage;
+use crate::media::image_size::{self, ImageSize};
 use crate::notifications;
 use crate::request_headers::build_image_request;
 
-/// Event name for chapter download progress updates
-pub const CHAPTER_DOWNLOAD_PROGRESS_EVENT: &str = "chapter-download-progress";
+use crate::events::CHAPTER_DOWNLOAD_PROGRESS_EVENT;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChapterDownload {
@@ -29,7 +31,7 @@ pub struct ChapterDownload {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ChapterDownloadProgress {
     pub id: String,
     pub media_id: String,
@@ -44,9 +46,7 @@ pub struct ChapterDownloadProgress {
 
 /// Emit chapter download progress event
 fn emit_chapter_progress(app_handle: &AppHandle, progress: &ChapterDownloadProgress) {
-    if let Err(e) = app_handle.emit(CHAPTER_DOWNLOAD_PROGRESS_EVENT, progress) {
-        log::error!("Failed to emit chapter download progress: {}", e);
-    }
+    CHAPTER_DOWNLOAD_PROGRESS_EVENT.emit(app_handle, progress);
 }
 
 /// Start downloading a chapter
@@ -148,6 +148,7 @@ pub async fn start_chapter_download(
         let mut downloaded = 0;
         let mut last_emit_time = std::time::Instant::now();
         let mut cancelled = false;
+        let mut page_sizes = BTreeMap::new();
 
         for (index, url) in image_urls.iter().enumerate() {
             // Check for cancellation every 5 images
@@ -174,8 +175,11 @@ pub async fn start_chapter_download(
 
             // Download image
             match download_image(url, &file_path).await {
-                Ok(_) => {
+                Ok(size) => {
                     downloaded += 1;
+                    if let Some(size) = size {
+                        page_sizes.insert(filename, size);
+                    }
 
                     // Update progress in database
                     let result = sqlx::query(
@@ -236,10 +240,11 @@ pub async fn start_chapter_download(
         };
 
         let result = sqlx::query(
-            "UPDATE chapter_downloads SET status = ?, error_message = ? WHERE id = ?"
+            "UPDATE chapter_downloads SET status = ?, error_message = ?, page_dimensions = ? WHERE id = ?"
         )
         .bind(status)
         .bind(&error_message_str)
+        .bind(serde_json::to_string(&page_sizes).ok())
         .bind(&download_id_clone)
         .execute(&pool_clone)
         .await;
@@ -294,8 +299,8 @@ pub async fn start_chapter_download(
     Ok(download_id)
 }
 
-/// Download a single image
-async fn download_image(url: &str, path: &PathBuf) -> Result<()> {
+/// Download a single image, returning its dimensions when the header is readable
+async fn download_image(url: &str, path: &PathBuf) -> Result<Option<ImageSize>> {
     use std::io::Read;
 
     let request = build_image_request(url).map_err(anyhow::Error::msg)?;
@@ -307,9 +312,10 @@ async fn download_image(url: &str, path: &PathBuf) -> Result<()> {
         .take(50 * 1024 * 1024) // 50MB limit per image
         .read_to_end(&mut bytes)?;
 
+    let size = image_size::read_image_size(&bytes);
     fs::write(path, bytes).await?;
 
-    Ok(())
+    Ok(size)
 }
 
 /// Get image extension from URL
@@ -375,12 +381,13 @@ pub async fn is_chapter_downloaded(
     Ok(count > 0)
 }
 
-/// Get downloaded chapter images (local paths)
+/// Get downloaded chapter images (local paths) with their page dimensions.
+/// Pages without recorded dimensions are probed once and the result saved.
 pub async fn get_downloaded_chapter_images(
     pool: &SqlitePool,
     media_id: &str,
     chapter_id: &str,
-) -> Result<Vec<String>> {
+) -> Result<Vec<ChapterImage>> {
     let download = sqlx::query_as::<_, ChapterDownload>(
         r#"
         SELECT id, media_id, chapter_id, chapter_number, folder_path, total_images, downloaded_images, status, error_message, created_at
@@ -411,18 +418,92 @@ pub async fn get_downloaded_chapter_images(
                 let path = entry.path();
                 if let Some(ext) = path.extension() {
                     if ["jpg", "jpeg", "png", "webp", "gif"].contains(&ext.to_string_lossy().to_lowercase().as_str()) {
-                        images.push(path.to_string_lossy().to_string());
+                        images.push(path);
                     }
                 }
             }
 
-            return Ok(images);
+            let page_sizes = load_page_sizes(pool, &download.id, images).await?;
+
+            return Ok(page_sizes
+                .into_iter()
+                .enumerate()
+                .map(|(index, (path, size))| ChapterImage {
+                    url: path.to_string_lossy().to_string(),
+                    page: index as u32 + 1,
+                    width: size.map(|s| s.width),
+                    height: size.map(|s| s.height),
+                    is_spread: size.is_some_and(|s| s.is_spread()),
+                })
+                .collect());
         }
     }
 
     Ok(vec![])
 }
 
+/// Look up the recorded dimensions of each page, probing (and saving) any
+/// that weren't recorded when the chapter was downloaded
+async fn load_page_sizes(
+    pool: &SqlitePool,
+    download_id: &str,
+    paths: Vec<PathBuf>,
+) -> Result<Vec<(PathBuf, Option<ImageSize>)>> {
+    let stored: Option<String> = sqlx::query_scalar(
+        "SELECT page_dimensions FROM chapter_downloads WHERE id = ?"
+    )
+    .bind(download_id)
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    let mut known: BTreeMap<String, ImageSize> = stored
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+
+    let unknown: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| !known.contains_key(&file_name(path)))
+        .cloned()
+        .collect();
+
+    if !unknown.is_empty() {
+        let probed = tokio::task::spawn_blocking(move || {
+            unknown
+                .into_iter()
+                .filter_map(|path| image_size::probe_file(&path).map(|size| (path, size)))
+                .collect::<Vec<_>>()
+        })
+        .await?;
+
+        if !probed.is_empty() {
+            for (path, size) in probed {
+                known.insert(file_name(&path), size);
+            }
+
+            sqlx::query("UPDATE chapter_downloads SET page_dimensions = ? WHERE id = ?")
+                .bind(serde_json::to_string(&known)?)
+                .bind(download_id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let size = known.get(&file_name(&path)).copied();
+            (path, size)
+        })
+        .collect())
+}
+
 /// Cancel an ongoing chapter download
 pub async fn cancel_chapter_download(
     pool: &SqlitePool,
@@ -529,6 +610,7 @@ pub async fn delete_chapter_download(
         .bind(chapter_id)
         .execute(pool)
         .await?;
+    crate::badges::invalidate();
 
     Ok(())
 }
@@ -538,6 +620,7 @@ async fn clear_chapter_downloads_by_status(pool: &SqlitePool, status: &str) -> R
         .bind(status)
         .execute(pool)
         .await?;
+    crate::badges::invalidate();
 
     Ok(result.rows_affected())
 }
@@ -802,6 +885,7 @@ mod tests {
                 status TEXT NOT NULL DEFAULT 'queued',
                 error_message TEXT,
                 created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                page_dimensions TEXT,
                 UNIQUE(media_id, chapter_id)
             )
             "#,
@@ -864,4 +948,53 @@ mod tests {
 
         assert_eq!(remaining_statuses(&pool).await, vec!["completed", "queued"]);
     }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes
+    }
+
+    #[tokio::test]
+    async fn downloaded_chapter_images_report_and_persist_page_sizes() {
+        let pool = setup_pool().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("Title_Ch1");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("page_0001.png"), png(800, 1200)).unwrap();
+        std::fs::write(folder.join("page_0002.png"), png(1600, 1200)).unwrap();
+        std::fs::write(folder.join("page_0003.png"), png(800, 1200)).unwrap();
+
+        sqlx::query(
+            r#"
+            INSERT INTO chapter_downloads (
+                id, media_id, chapter_id, chapter_number, folder_path,
+                total_images, downloaded_images, status, page_dimensions
+            )
+            VALUES ('a', 'media-a', 'chapter-a', 1.0, ?, 3, 3, 'completed', ?)
+            "#,
+        )
+        .bind(folder.to_string_lossy().to_string())
+        // Only page 1 was recorded at download time
+        .bind(r#"{"page_0001.png":{"width":800,"height":1200}}"#)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let images = get_downloaded_chapter_images(&pool, "media-a", "chapter-a").await.unwrap();
+
+        assert_eq!(images.len(), 3);
+        assert_eq!(images.iter().map(|i| i.page).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(images.iter().map(|i| i.is_spread).collect::<Vec<_>>(), vec![false, true, false]);
+        assert_eq!((images[1].width, images[1].height), (Some(1600), Some(1200)));
+
+        let stored: String = sqlx::query_scalar("SELECT page_dimensions FROM chapter_downloads WHERE id = 'a'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let stored: BTreeMap<String, ImageSize> = serde_json::from_str(&stored).unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored["page_0002.png"], ImageSize { width: 1600, height: 1200 });
+    }
 }
@@ -0,0 +1,66 @@
+// Disk Space
+//
+// Free space on the volume a download is written to, and the reserve that
+// downloads leave free (download_disk_reserve_mb) so they never fill the
+// disk up completely.
+
+use sqlx::SqlitePool;
+use std::path::Path;
+
+/// app_settings key: megabytes downloads must leave free on the volume
+pub const RESERVE_SETTING: &str = "download_disk_reserve_mb";
+
+/// Reserve when the setting isn't set
+pub const DEFAULT_RESERVE_MB: u64 = 1024;
+
+/// The reserve in bytes
+pub async fn reserve_bytes(pool: &SqlitePool) -> u64 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(RESERVE_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    let mb = value.and_then(|v| v.trim().parse::<u64>().ok()).unwrap_or(DEFAULT_RESERVE_MB);
+    mb * 1024 * 1024
+}
+
+/// Free bytes on the volume holding `path`, or None when it can't be told
+#[cfg(not(target_os = "android"))]
+pub fn available_space(path: &Path) -> Option<u64> {
+    // The download directory may not exist yet; its closest existing
+    // ancestor is on the same volume
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let path = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+/// Free bytes on the volume holding `path` (not available on Android)
+#[cfg(target_os = "android")]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether `needed` bytes fit into `available` while leaving `reserve` free
+pub fn fits(needed: u64, available: u64, reserve: u64) -> bool {
+    needed <= available.saturating_sub(reserve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_reserve_is_kept_free() {
+        assert!(fits(500, 2000, 1000));
+        assert!(fits(1000, 2000, 1000));
+        assert!(!fits(1001, 2000, 1000));
+        assert!(!fits(1, 500, 1000));
+    }
+}
@@ -0,0 +1,169 @@
+// Download Filename Templates
+//
+// By default a download keeps the filename the frontend passed in
+// (Title_EP3_1080p.mp4). With download_filename_template set, queue_download
+// renders the name from the media's details instead, e.g.
+// "{title} - S{season}E{episode} [{quality}]". The file extension always
+// comes from the original filename, since it decides how the file is
+// handled (.m3u8 playlists, .otaku obfuscation).
+
+use std::path::Path;
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+use super::organize::{sanitize_segment, template_context, TemplateContext};
+
+/// app_settings key holding the filename template (unset or empty: keep the given name)
+pub const FILENAME_TEMPLATE_SETTING: &str = "download_filename_template";
+
+/// Read the filename template, if one is set
+pub async fn load_template(pool: &SqlitePool) -> Result<Option<String>> {
+    let template: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(FILENAME_TEMPLATE_SETTING)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(template.filter(|t| !t.trim().is_empty()))
+}
+
+/// Title of a media as stored in the media table
+pub async fn media_title(pool: &SqlitePool, media_id: &str) -> Result<Option<String>> {
+    Ok(sqlx::query_scalar("SELECT title FROM media WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Extension of a filename including the dot (".mp4"), or "" when it has none
+fn extension_of(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+/// Render a filename from the template. {season} and {episode} are
+/// zero-padded to two digits; {quality}, {year} and {type} may be empty, in
+/// which case brackets left empty around them are dropped.
+pub fn render_filename(
+    template: &str,
+    ctx: &TemplateContext,
+    episode_number: i32,
+    quality: Option<&str>,
+    original: &str,
+) -> String {
+    // The template's own extension (if it has one) is replaced by the original's
+    let template_extension = extension_of(template);
+    let template = if !template_extension.contains('{') && !template_extension.contains('}') {
+        template.strip_suffix(template_extension.as_str()).unwrap_or(template)
+    } else {
+        template
+    };
+
+    let rendered = template
+        .replace("{title}", &ctx.title)
+        .replace("{season}", &format!("{:02}", ctx.season))
+        .replace("{episode}", &format!("{:02}", episode_number))
+        .replace("{quality}", quality.unwrap_or(""))
+        .replace("{year}", &ctx.year.map(|y| y.to_string()).unwrap_or_default())
+        .replace("{type}", ctx.content_type.as_deref().unwrap_or(""))
+        .replace("[]", "")
+        .replace("()", "");
+    let collapsed = rendered.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let stem = sanitize_segment(collapsed.trim_end_matches(['-', '_', ' ']));
+    if stem.is_empty() {
+        return original.to_string();
+    }
+    format!("{}{}", stem, extension_of(original))
+}
+
+/// Filename for a new download: the template applied to the media's details,
+/// or `filename` unchanged when no template is set
+pub async fn apply_template(
+    pool: &SqlitePool,
+    media_id: &str,
+    episode_number: i32,
+    quality: Option<&str>,
+    filename: &str,
+) -> Result<String> {
+    let Some(template) = load_template(pool).await? else {
+        return Ok(filename.to_string());
+    };
+
+    let ctx = template_context(pool, media_id, filename).await?;
+    Ok(render_filename(&template, &ctx, episode_number, quality, filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::downloads::organize::season_from_title;
+
+    fn title_context(title: &str) -> TemplateContext {
+        TemplateContext {
+            title: title.to_string(),
+            season: season_from_title(title),
+            year: None,
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn renders_fields_and_keeps_the_original_extension() {
+        let ctx = title_context("Frieren Season 2");
+        assert_eq!(
+            render_filename("{title} - S{season}E{episode} [{quality}].mp4", &ctx, 3, Some("1080p"), "x_EP3.otaku"),
+            "Frieren Season 2 - S02E03 [1080p].otaku"
+        );
+        assert_eq!(
+            render_filename("{title} - {episode}", &ctx, 112, None, "x.m3u8"),
+            "Frieren Season 2 - 112.m3u8"
+        );
+    }
+
+    #[test]
+    fn missing_fields_leave_no_empty_brackets() {
+        let ctx = title_context("Bocchi the Rock!");
+        assert_eq!(
+            render_filename("{title} E{episode} [{quality}] ({year})", &ctx, 1, None, "a.mp4"),
+            "Bocchi the Rock! E01.mp4"
+        );
+    }
+
+    #[test]
+    fn characters_illegal_on_windows_and_macos_are_replaced() {
+        let ctx = title_context("Re:Zero? <Part/2> \"Director's\" Cut*");
+        let name = render_filename("{title} - E{episode}", &ctx, 5, None, "a.mp4");
+        assert_eq!(name, "Re_Zero_ _Part_2_ _Director's_ Cut_ - E05.mp4");
+        assert!(!name.contains(['<', '>', ':', '"', '/', '\\', '|', '?', '*']));
+
+        // Nothing usable left: keep what the frontend asked for
+        assert_eq!(render_filename("...", &ctx, 5, None, "Show_EP5.mp4"), "Show_EP5.mp4");
+    }
+
+    #[tokio::test]
+    async fn template_is_only_applied_when_set() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'Frieren', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let name = apply_template(pool, "m1", 7, Some("720p"), "Frieren_EP7_720p.mp4").await.unwrap();
+        assert_eq!(name, "Frieren_EP7_720p.mp4");
+
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES (?, '{title} - S{season}E{episode} [{quality}]')")
+            .bind(FILENAME_TEMPLATE_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        let name = apply_template(pool, "m1", 7, Some("720p"), "Frieren_EP7_720p.mp4").await.unwrap();
+        assert_eq!(name, "Frieren - S01E07 [720p].mp4");
+        assert_eq!(media_title(pool, "m1").await.unwrap().as_deref(), Some("Frieren"));
+    }
+}
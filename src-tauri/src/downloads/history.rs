@@ -0,0 +1,236 @@
+// Download History
+//
+// Append-only log of downloads (download_history): a row when a download
+// completes, and one when it leaves the downloads list, whether it was
+// deleted with its file, removed, or cleared with the rest of its status.
+// Clearing the list used to drop the record entirely; now it moves it here.
+// Rows are copied from the downloads table just before the row goes, so the
+// history doesn't depend on the download still existing.
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::query::Query;
+use sqlx::sqlite::SqliteArguments;
+use sqlx::{Row, Sqlite, SqlitePool};
+
+/// Why a history row was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryEvent {
+    Completed,
+    /// Deleted together with its file
+    Deleted,
+    /// Removed from the list, file kept
+    Removed,
+    /// Cleared from the list with every download of its status
+    Cleared,
+}
+
+impl HistoryEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HistoryEvent::Completed => "completed",
+            HistoryEvent::Deleted => "deleted",
+            HistoryEvent::Removed => "removed",
+            HistoryEvent::Cleared => "cleared",
+        }
+    }
+}
+
+/// One row of the download history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DownloadHistoryEntry {
+    pub id: i64,
+    pub download_id: String,
+    pub media_id: String,
+    pub media_title: Option<String>,
+    pub episode_id: String,
+    pub episode_number: i32,
+    pub quality: Option<String>,
+    pub file_path: String,
+    pub total_bytes: u64,
+    /// The download's status when the row was written
+    pub status: String,
+    /// completed, deleted, removed or cleared
+    pub event: String,
+    pub queued_at: Option<String>,
+    /// Unix ms
+    pub recorded_at: i64,
+}
+
+/// Insert copying the downloads matching `$condition` into the history; binds
+/// the event, the time and then the condition's value
+macro_rules! record_downloads_where {
+    ($condition:literal) => {
+        concat!(
+            "INSERT INTO download_history (
+                download_id, media_id, media_title, episode_id, episode_number, quality,
+                file_path, total_bytes, status, event, queued_at, recorded_at
+            )
+            SELECT
+                d.id, d.media_id, COALESCE(d.media_title, m.title), d.episode_id, d.episode_number, d.quality,
+                d.file_path, d.total_bytes, d.status, ?, d.created_at, ?
+            FROM downloads d
+            LEFT JOIN media m ON m.id = d.media_id
+            WHERE ",
+            $condition
+        )
+    };
+}
+
+/// Record one download, as it's stored now
+pub(super) async fn record(pool: &SqlitePool, download_id: &str, event: HistoryEvent) {
+    let result = sqlx::query(record_downloads_where!("d.id = ?"))
+        .bind(event.as_str())
+        .bind(chrono::Utc::now().timestamp_millis())
+        .bind(download_id)
+        .execute(pool)
+        .await;
+    if let Err(e) = result {
+        log::warn!("Failed to record {} in the download history: {}", download_id, e);
+    }
+}
+
+/// Record every download stored with `status` (a downloads.status value);
+/// run it in the transaction that deletes them
+pub(super) fn record_status(status: &str, event: HistoryEvent) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+    sqlx::query(record_downloads_where!("d.status = ?"))
+        .bind(event.as_str())
+        .bind(chrono::Utc::now().timestamp_millis())
+        .bind(status)
+}
+
+/// A page of the history, most recent first
+pub async fn list(pool: &SqlitePool, limit: u32, offset: u32) -> Result<Vec<DownloadHistoryEntry>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, download_id, media_id, media_title, episode_id, episode_number, quality,
+               file_path, total_bytes, status, event, queued_at, recorded_at
+        FROM download_history
+        ORDER BY recorded_at DESC, id DESC
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(DownloadHistoryEntry {
+                id: row.try_get("id")?,
+                download_id: row.try_get("download_id")?,
+                media_id: row.try_get("media_id")?,
+                media_title: row.try_get("media_title")?,
+                episode_id: row.try_get("episode_id")?,
+                episode_number: row.try_get("episode_number")?,
+                quality: row.try_get("quality")?,
+                file_path: row.try_get("file_path")?,
+                total_bytes: row.try_get::<i64, _>("total_bytes")?.max(0) as u64,
+                status: row.try_get("status")?,
+                event: row.try_get("event")?,
+                queued_at: row.try_get("queued_at")?,
+                recorded_at: row.try_get("recorded_at")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::downloads::{DownloadManager, DownloadProgress};
+    use std::sync::Arc;
+
+    fn download(id: &str, episode_number: i32, status: &str) -> DownloadProgress {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "media_id": "media-1",
+            "episode_id": format!("episode-{}", episode_number),
+            "episode_number": episode_number,
+            "media_title": "Frieren",
+            "filename": "Episode.mp4",
+            "url": "https://example.test/video.mp4",
+            "file_path": format!("/tmp/{}.mp4", id),
+            "total_bytes": 1000,
+            "downloaded_bytes": 1000,
+            "percentage": 100.0,
+            "speed": 0,
+            "status": status,
+            "error_message": null,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn clearing_and_deleting_move_downloads_into_the_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = Arc::new(database.pool().clone());
+        let manager = DownloadManager::new(dir.path().to_path_buf()).with_database(pool.clone());
+
+        for (id, episode, status) in [("ep1", 1, "completed"), ("ep2", 2, "completed"), ("ep3", 3, "failed")] {
+            let progress = download(id, episode, status);
+            manager.save_to_database(&progress).await.unwrap();
+            manager.downloads.write().await.insert(id.to_string(), progress);
+        }
+        record(&pool, "ep1", HistoryEvent::Completed).await;
+
+        manager.clear_completed().await.unwrap();
+        manager.delete_download("ep3").await.unwrap();
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM downloads").fetch_one(pool.as_ref()).await.unwrap();
+        assert_eq!(remaining, 0);
+
+        let history = list(&pool, 10, 0).await.unwrap();
+        let mut events: Vec<(String, String, String)> = history
+            .iter()
+            .map(|e| (e.download_id.clone(), e.event.clone(), e.status.clone()))
+            .collect();
+        events.sort();
+        assert_eq!(
+            events,
+            vec![
+                ("ep1".to_string(), "cleared".to_string(), "completed".to_string()),
+                ("ep1".to_string(), "completed".to_string(), "completed".to_string()),
+                ("ep2".to_string(), "cleared".to_string(), "completed".to_string()),
+                ("ep3".to_string(), "deleted".to_string(), "failed".to_string()),
+            ]
+        );
+        let cleared = history.iter().find(|e| e.download_id == "ep2").unwrap();
+        assert_eq!(cleared.media_title.as_deref(), Some("Frieren"));
+        assert_eq!(cleared.episode_number, 2);
+        assert_eq!(cleared.total_bytes, 1000);
+        assert!(cleared.queued_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn the_history_pages_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::new(dir.path().join("otaku.db")).await.unwrap();
+        let pool = database.pool();
+
+        for episode in 1..=5 {
+            let id = format!("ep{}", episode);
+            sqlx::query(
+                "INSERT INTO downloads (id, media_id, episode_id, episode_number, file_path, status) \
+                 VALUES (?, 'media-1', ?, ?, '/tmp/ep.mp4', 'completed')",
+            )
+            .bind(&id)
+            .bind(&id)
+            .bind(episode)
+            .execute(pool)
+            .await
+            .unwrap();
+            record(pool, &id, HistoryEvent::Completed).await;
+        }
+
+        let first = list(pool, 2, 0).await.unwrap();
+        let second = list(pool, 2, 2).await.unwrap();
+        let last = list(pool, 2, 4).await.unwrap();
+        let ids: Vec<&str> = first.iter().chain(&second).chain(&last).map(|e| e.download_id.as_str()).collect();
+        assert_eq!(ids, vec!["ep5", "ep4", "ep3", "ep2", "ep1"]);
+    }
+}
@@ -0,0 +1,457 @@
+// HLS Downloads
+//
+// Many extensions only return HLS playlists, which can't be downloaded as one
+// file. The best variant of a master playlist is picked, every media segment
+// is fetched in order and the segments are joined into a single file. They
+// are kept in a `.parts` folder next to the download until all of them are
+// in, so a paused or failed download resumes with the segments it's missing.
+// The joined MPEG-TS is remuxed into MP4 when ffmpeg is available and kept as
+// .ts otherwise; fragmented-MP4 streams (EXT-X-MAP) are MP4 once joined.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use super::{obfuscation, speed, stats, throttle, DownloadManager, DownloadProgress, STALL_TIMEOUT};
+use crate::events::DOWNLOAD_PROGRESS_EVENT;
+use crate::http_retry::{send_with_retry, RetryPolicy};
+
+/// Segments fetched between progress saves to the database
+const DB_SAVE_EVERY_SEGMENTS: u32 = 10;
+
+/// Emit progress events at most this often
+const EVENT_THROTTLE_MS: u128 = 500;
+
+/// Segments done out of the playlist's total
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SegmentProgress {
+    pub completed: u32,
+    pub total: u32,
+}
+
+/// A variant stream of a master playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub url: Url,
+    pub bandwidth: u64,
+}
+
+/// What a playlist lists
+#[derive(Debug, Clone, PartialEq)]
+pub enum Playlist {
+    /// Variant streams of different qualities
+    Master(Vec<Variant>),
+    /// Segments, preceded by the fMP4 init segment if there is one
+    Media { init: Option<Url>, segments: Vec<Url> },
+}
+
+/// Whether a response is an HLS playlist, by content type or by its first bytes
+pub fn is_playlist(content_type: Option<&str>, first_bytes: &[u8]) -> bool {
+    let by_type = content_type.is_some_and(|t| t.to_ascii_lowercase().contains("mpegurl"));
+    let trimmed = first_bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(first_bytes);
+    by_type || trimmed.starts_with(b"#EXTM3U")
+}
+
+/// Value of an attribute in a tag's attribute list (BANDWIDTH=..., URI="...")
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let (_, attributes) = tag.split_once(':')?;
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value_and_rest) = rest.split_once('=')?;
+        let (value, remaining) = match value_and_rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], quoted[end + 1..].trim_start_matches(','))
+            }
+            None => match value_and_rest.split_once(',') {
+                Some((value, remaining)) => (value, remaining),
+                None => (value_and_rest, ""),
+            },
+        };
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = remaining;
+    }
+    None
+}
+
+/// Parse a playlist, resolving its URIs against the playlist's own URL
+pub fn parse_playlist(text: &str, base: &Url) -> Result<Playlist> {
+    let mut variants = Vec::new();
+    let mut init = None;
+    let mut segments = Vec::new();
+    let mut pending_bandwidth: Option<u64> = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with("#EXT-X-STREAM-INF") {
+            pending_bandwidth = Some(attribute(line, "BANDWIDTH").and_then(|b| b.parse().ok()).unwrap_or(0));
+        } else if line.starts_with("#EXT-X-KEY") {
+            let method = attribute(line, "METHOD").unwrap_or("NONE");
+            if method != "NONE" {
+                anyhow::bail!("Encrypted HLS streams ({}) can't be downloaded", method);
+            }
+        } else if line.starts_with("#EXT-X-MAP") {
+            let uri = attribute(line, "URI").context("EXT-X-MAP without a URI")?;
+            init = Some(base.join(uri).context("Invalid init segment URL")?);
+        } else if !line.starts_with('#') {
+            let url = base.join(line).context("Invalid playlist URL")?;
+            match pending_bandwidth.take() {
+                Some(bandwidth) => variants.push(Variant { url, bandwidth }),
+                None => segments.push(url),
+            }
+        }
+    }
+
+    if !variants.is_empty() {
+        Ok(Playlist::Master(variants))
+    } else if !segments.is_empty() {
+        Ok(Playlist::Media { init, segments })
+    } else {
+        anyhow::bail!("Playlist has no segments")
+    }
+}
+
+/// The download an HLS stream is fetched for, and where its progress goes
+pub(super) struct Reporter<'a> {
+    pub download_id: &'a str,
+    pub downloads: &'a Arc<RwLock<HashMap<String, DownloadProgress>>>,
+    pub db_pool: Option<&'a Arc<SqlitePool>>,
+    pub app_handle: Option<&'a AppHandle>,
+    /// Cancelled when the download is paused, cancelled or removed
+    pub cancel: &'a CancellationToken,
+}
+
+impl Reporter<'_> {
+    async fn update(&self, segments: SegmentProgress, downloaded: u64, speed: u64, emit: bool, save: bool) {
+        let mut downloads_map = self.downloads.write().await;
+        let Some(progress) = downloads_map.get_mut(self.download_id) else {
+            return;
+        };
+        progress.segments = Some(segments);
+        progress.downloaded_bytes = downloaded;
+        progress.speed = speed;
+        progress.retry_count = 0;
+        progress.percentage = segments.completed as f32 / segments.total.max(1) as f32 * 100.0;
+
+        if emit {
+            if let Some(handle) = self.app_handle {
+                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+            }
+        }
+        if save {
+            if let Some(pool) = self.db_pool {
+                DownloadManager::save_progress_to_db(pool, progress).await.ok();
+            }
+        }
+    }
+}
+
+/// Folder the segments of a download are collected in
+pub fn parts_dir(file_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.parts", file_path))
+}
+
+/// Download an HLS stream whose playlist was fetched from `playlist_url`.
+/// On success the download's file_path (and filename) point at the joined
+/// file, whose extension depends on the container it ended up in. `client`
+/// carries the download's headers (download_headers).
+pub(super) async fn download(
+    client: &reqwest::Client,
+    playlist_url: &str,
+    playlist_text: &str,
+    file_path: &str,
+    reporter: Reporter<'_>,
+) -> Result<()> {
+    let download_id = reporter.download_id;
+
+    let mut base = Url::parse(playlist_url).context("Invalid playlist URL")?;
+    let mut playlist = parse_playlist(playlist_text, &base)?;
+    if let Playlist::Master(variants) = &playlist {
+        let best = variants.iter().max_by_key(|v| v.bandwidth).context("Playlist has no variants")?;
+        log::debug!("HLS download {}: picked variant {} ({} bps)", download_id, best.url, best.bandwidth);
+        base = best.url.clone();
+        let fetch = async {
+            send_with_retry(RetryPolicy::BACKGROUND, client.get(base.clone()))
+                .await
+                .and_then(|r| r.error_for_status())
+                .context("Failed to fetch variant playlist")?
+                .text()
+                .await
+                .context("Failed to read variant playlist")
+        };
+        let text = tokio::select! {
+            biased;
+            _ = reporter.cancel.cancelled() => {
+                return Err(super::interrupted(reporter.downloads, download_id).await);
+            }
+            text = tokio::time::timeout(STALL_TIMEOUT, fetch) => text.unwrap_or_else(|_| {
+                Err(anyhow::anyhow!(
+                    "Failed to fetch variant playlist: connection timed out after {} seconds",
+                    STALL_TIMEOUT.as_secs()
+                ))
+            })?,
+        };
+        playlist = parse_playlist(&text, &base)?;
+    }
+    let Playlist::Media { init, segments } = playlist else {
+        anyhow::bail!("Variant playlist lists other playlists instead of segments");
+    };
+
+    let is_fmp4 = init.is_some();
+    let urls: Vec<Url> = init.into_iter().chain(segments).collect();
+    let total = urls.len() as u32;
+
+    let parts = parts_dir(file_path);
+    tokio::fs::create_dir_all(&parts).await.context("Failed to create segment folder")?;
+
+    let mut rolling_speed = speed::RollingSpeed::new(std::time::Instant::now(), 0);
+    let mut last_event_time = std::time::Instant::now();
+    let mut downloaded: u64 = 0;
+    let mut session_bytes: u64 = 0;
+
+    for (index, url) in urls.iter().enumerate() {
+        let part = parts.join(format!("{:05}.seg", index));
+
+        // Fetched before a pause or restart
+        if let Ok(metadata) = tokio::fs::metadata(&part).await {
+            downloaded += metadata.len();
+            continue;
+        }
+
+        // Pause and cancel interrupt the segment being fetched. Both keep
+        // the segments fetched so far; an abort deletes them afterwards. A
+        // segment that doesn't arrive within STALL_TIMEOUT fails the download
+        // as a network error, like a stalled direct download.
+        let fetch = async {
+            let fetched = async {
+                send_with_retry(RetryPolicy::BACKGROUND, client.get(url.clone()))
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .with_context(|| format!("Failed to fetch segment {} of {}", index + 1, total))?
+                    .bytes()
+                    .await
+                    .with_context(|| format!("Failed to read segment {} of {}", index + 1, total))
+            };
+            tokio::time::timeout(STALL_TIMEOUT, fetched).await.unwrap_or_else(|_| {
+                Err(anyhow::anyhow!(
+                    "Failed to fetch segment {} of {}: connection timed out after {} seconds",
+                    index + 1,
+                    total,
+                    STALL_TIMEOUT.as_secs()
+                ))
+            })
+        };
+        let bytes = tokio::select! {
+            biased;
+            _ = reporter.cancel.cancelled() => {
+                log::debug!("HLS download stopped at segment {} of {}", index + 1, total);
+                return Err(super::interrupted(reporter.downloads, download_id).await);
+            }
+            bytes = fetch => bytes?,
+        };
+
+        throttle::throttle(bytes.len() as u64).await;
+        stats::record_bytes(bytes.len() as u64);
+
+        // Written under a temporary name so an interrupted write isn't
+        // mistaken for a finished segment on resume
+        let partial = part.with_extension("tmp");
+        tokio::fs::write(&partial, &bytes).await.context("Failed to write segment")?;
+        tokio::fs::rename(&partial, &part).await.context("Failed to write segment")?;
+
+        downloaded += bytes.len() as u64;
+        session_bytes += bytes.len() as u64;
+        // Segments already on disk don't count towards the speed
+        let speed = rolling_speed.record(std::time::Instant::now(), session_bytes);
+
+        let completed = index as u32 + 1;
+        let emit = last_event_time.elapsed().as_millis() >= EVENT_THROTTLE_MS || completed == total;
+        if emit {
+            last_event_time = std::time::Instant::now();
+        }
+        reporter
+            .update(SegmentProgress { completed, total }, downloaded, speed, emit, completed % DB_SAVE_EVERY_SEGMENTS == 0)
+            .await;
+    }
+    reporter
+        .update(SegmentProgress { completed: total, total }, downloaded, 0, false, false)
+        .await;
+
+    let final_path = join_segments(&parts, total, is_fmp4, file_path).await?;
+    tokio::fs::remove_dir_all(&parts).await.ok();
+
+    let mut downloads_map = reporter.downloads.write().await;
+    if let Some(progress) = downloads_map.get_mut(download_id) {
+        let final_path = final_path.to_string_lossy().to_string();
+        if final_path != progress.file_path {
+            progress.filename = Path::new(&final_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| progress.filename.clone());
+            progress.file_path = final_path;
+        }
+        progress.segments = None;
+    }
+
+    Ok(())
+}
+
+/// Join the segments into the download's file and return where it ended up
+async fn join_segments(parts: &Path, total: u32, is_fmp4: bool, file_path: &str) -> Result<PathBuf> {
+    let joined = parts.join("joined");
+    {
+        let mut output = tokio::fs::File::create(&joined).await.context("Failed to create joined file")?;
+        for index in 0..total {
+            let mut segment = tokio::fs::File::open(parts.join(format!("{:05}.seg", index)))
+                .await
+                .context("Segment missing while joining")?;
+            tokio::io::copy(&mut segment, &mut output).await.context("Failed to join segments")?;
+        }
+        output.flush().await.context("Failed to join segments")?;
+    }
+
+    let (joined, extension) = if is_fmp4 {
+        (joined, "mp4")
+    } else {
+        match remux_to_mp4(&joined, &parts.join("remuxed.mp4")).await {
+            Ok(remuxed) => (remuxed, "mp4"),
+            Err(e) => {
+                log::debug!("Keeping HLS download as MPEG-TS: {}", e);
+                (joined, "ts")
+            }
+        }
+    };
+
+    // Obfuscated downloads keep their .otaku name whatever the container
+    let target = Path::new(file_path);
+    if crate::media::remux::is_obfuscated(target) {
+        obfuscate_copy(&joined, target).await?;
+        return Ok(target.to_path_buf());
+    }
+
+    let target = target.with_extension(extension);
+    tokio::fs::rename(&joined, &target).await.context("Failed to move joined file into place")?;
+    Ok(target)
+}
+
+/// Remux MPEG-TS into MP4 with ffmpeg (no re-encoding)
+async fn remux_to_mp4(input: &Path, output: &Path) -> Result<PathBuf> {
+    let ffmpeg = crate::media::remux::ffmpeg_path().context("ffmpeg not available")?;
+    let status = tokio::process::Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-nostdin", "-y", "-i"])
+        .arg(input)
+        .args(["-map", "0:v?", "-map", "0:a?", "-c", "copy", "-bsf:a", "aac_adtstoasc", "-movflags", "+faststart"])
+        .arg(output)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .context("Failed to run ffmpeg")?;
+
+    if !status.success() {
+        anyhow::bail!("ffmpeg exited with {}", status);
+    }
+    Ok(output.to_path_buf())
+}
+
+/// Copy a file, XOR-obfuscating it on the way
+async fn obfuscate_copy(input: &Path, output: &Path) -> Result<()> {
+    let mut reader = tokio::fs::File::open(input).await.context("Failed to open joined file")?;
+    let mut writer = tokio::fs::File::create(output).await.context("Failed to create file")?;
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut offset = 0u64;
+    loop {
+        let n = reader.read(&mut buf).await.context("Failed to read joined file")?;
+        if n == 0 {
+            break;
+        }
+        obfuscation::xor_transform(&mut buf[..n], offset);
+        writer.write_all(&buf[..n]).await.context("Failed to write file")?;
+        offset += n as u64;
+    }
+    writer.flush().await.context("Failed to write file")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://cdn.example.com/show/ep1/master.m3u8").unwrap()
+    }
+
+    #[test]
+    fn playlists_are_recognized_by_type_or_header() {
+        assert!(is_playlist(Some("application/vnd.apple.mpegurl"), b""));
+        assert!(is_playlist(Some("audio/x-mpegURL; charset=utf-8"), b""));
+        assert!(is_playlist(Some("application/octet-stream"), b"#EXTM3U\n#EXT-X-VERSION:3"));
+        assert!(is_playlist(None, b"\xEF\xBB\xBF#EXTM3U\n"));
+        assert!(!is_playlist(Some("video/mp4"), b"\x00\x00\x00\x18ftypmp42"));
+    }
+
+    #[test]
+    fn master_playlists_list_their_variants() {
+        let text = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\
+            360p/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080\n\
+            https://other.example.com/1080p.m3u8\n";
+
+        let Playlist::Master(variants) = parse_playlist(text, &base()).unwrap() else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].url.as_str(), "https://cdn.example.com/show/ep1/360p/index.m3u8");
+        assert_eq!(variants[0].bandwidth, 800_000);
+        assert_eq!(variants[1].url.as_str(), "https://other.example.com/1080p.m3u8");
+        assert_eq!(variants[1].bandwidth, 5_000_000);
+    }
+
+    #[test]
+    fn media_playlists_list_segments_in_order() {
+        let text = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-KEY:METHOD=NONE\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:10.0,\nseg-1.m4s\n#EXTINF:10.0,\n/abs/seg-2.m4s\n#EXT-X-ENDLIST\n";
+
+        let Playlist::Media { init, segments } = parse_playlist(text, &base()).unwrap() else {
+            panic!("expected a media playlist");
+        };
+        assert_eq!(init.unwrap().as_str(), "https://cdn.example.com/show/ep1/init.mp4");
+        let segments: Vec<&str> = segments.iter().map(Url::as_str).collect();
+        assert_eq!(segments, vec!["https://cdn.example.com/show/ep1/seg-1.m4s", "https://cdn.example.com/abs/seg-2.m4s"]);
+    }
+
+    #[test]
+    fn encrypted_and_empty_playlists_are_refused() {
+        let encrypted = "#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n#EXTINF:10,\nseg.ts\n";
+        assert!(parse_playlist(encrypted, &base()).unwrap_err().to_string().contains("AES-128"));
+        assert!(parse_playlist("#EXTM3U\n#EXT-X-ENDLIST\n", &base()).is_err());
+    }
+
+    #[tokio::test]
+    async fn segments_are_joined_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("Show_EP1_1080p.m3u8");
+        let parts = parts_dir(&file_path.to_string_lossy());
+        tokio::fs::create_dir_all(&parts).await.unwrap();
+        for (index, data) in [b"aaa".as_slice(), b"bb", b"c"].iter().enumerate() {
+            tokio::fs::write(parts.join(format!("{:05}.seg", index)), data).await.unwrap();
+        }
+
+        // fMP4 segments don't need ffmpeg to end up as MP4
+        let joined = join_segments(&parts, 3, true, &file_path.to_string_lossy()).await.unwrap();
+        assert_eq!(joined, dir.path().join("Show_EP1_1080p.mp4"));
+        assert_eq!(tokio::fs::read(&joined).await.unwrap(), b"aaabbc");
+    }
+}
@@ -0,0 +1,188 @@
+// Lazily Resolved Download Sources
+//
+// Video URLs expire, so a whole season can't have its sources fetched up
+// front and then wait in the queue. Batch downloads queue each episode with
+// an empty URL and the extension it comes from; once the episode gets a
+// download slot its sources are fetched, the best one is picked (as for
+// auto-downloads) and the URL, filename and quality are filled in. Those
+// fetches run without anyone waiting on them, so they draw on the
+// extension's background budget.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use tauri::{AppHandle, Manager};
+
+use super::DownloadProgress;
+use crate::commands::{background_runtime, guarded_runtime, AppState};
+use crate::extensions::{adult, circuit_breaker, Extension, ExtensionRuntime, VideoSource, VideoSources};
+use crate::release_checker::{pick_auto_download_source, sanitize_filename};
+
+/// The source picked for a lazily queued download
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedSource {
+    pub url: String,
+    pub quality: String,
+    pub server: String,
+    pub is_hls: bool,
+    /// HTTP headers the source's CDN needs; empty for the defaults
+    pub headers: HashMap<String, String>,
+}
+
+/// HTTP headers to download `source` with: its headers, plus its referrer
+/// as Referer unless the headers already have one
+pub fn source_headers(source: &VideoSource) -> HashMap<String, String> {
+    let mut headers = source.headers.clone();
+    if let Some(referrer) = source.referrer.as_ref().filter(|r| !r.is_empty()) {
+        if !headers.keys().any(|name| name.eq_ignore_ascii_case("referer")) {
+            headers.insert("Referer".to_string(), referrer.clone());
+        }
+    }
+    headers
+}
+
+/// Filename of a download whose source isn't known yet (Title_EP3.mp4).
+/// The quality is appended once the source is picked.
+pub fn placeholder_filename(title: &str, episode_number: i32) -> String {
+    format!("{}_EP{}.mp4", sanitize_filename(title), episode_number)
+}
+
+/// Final filename for a placeholder (Title_EP3_1080p.mp4)
+pub fn resolved_filename(placeholder: &str, source: &ResolvedSource) -> String {
+    let stem = placeholder.strip_suffix(".mp4").unwrap_or(placeholder);
+    let extension = if source.is_hls { "m3u8" } else { "mp4" };
+    format!("{}_{}.{}", stem, sanitize_filename(&source.quality), extension)
+}
+
+impl ResolvedSource {
+    pub fn from_source(source: &VideoSource) -> Self {
+        Self {
+            url: source.url.clone(),
+            quality: source
+                .resolution
+                .map(|r| format!("{}p", r))
+                .unwrap_or_else(|| source.quality.clone()),
+            server: source.server.clone(),
+            is_hls: source.source_type == "hls",
+            headers: source_headers(source),
+        }
+    }
+}
+
+/// Fetch an episode's sources from the extension, for a command the user
+/// started
+pub async fn fetch_sources(
+    app_handle: &AppHandle,
+    extension_id: &str,
+    episode_id: &str,
+    allow_adult: bool,
+) -> Result<VideoSources> {
+    fetch_sources_using(app_handle, extension_id, episode_id, allow_adult, guarded_runtime).await
+}
+
+/// fetch_sources for work no one is waiting on: fails while the extension's
+/// background budget is used up
+pub async fn fetch_background_sources(
+    app_handle: &AppHandle,
+    extension_id: &str,
+    episode_id: &str,
+    allow_adult: bool,
+) -> Result<VideoSources> {
+    fetch_sources_using(app_handle, extension_id, episode_id, allow_adult, background_runtime).await
+}
+
+async fn fetch_sources_using(
+    app_handle: &AppHandle,
+    extension_id: &str,
+    episode_id: &str,
+    allow_adult: bool,
+    make_runtime: fn(Extension, bool) -> Result<ExtensionRuntime, String>,
+) -> Result<VideoSources> {
+    let state = app_handle.state::<AppState>();
+    let extension = state.extension(extension_id)?;
+    let extension_id = extension_id.to_string();
+    let episode_id = episode_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let runtime = make_runtime(extension, allow_adult).map_err(|e| anyhow::anyhow!(e))?;
+        circuit_breaker::track(&extension_id, runtime.get_sources(&episode_id))
+    })
+    .await?
+}
+
+/// Fetch an episode's sources from the extension and pick one. Queued
+/// episodes have no caller to ask, so adult content follows the NSFW filter.
+pub async fn fetch_source(app_handle: &AppHandle, extension_id: &str, episode_id: &str) -> Result<ResolvedSource> {
+    let allow_adult = adult::background_allow_adult(app_handle.state::<AppState>().database.pool()).await;
+    let sources = fetch_background_sources(app_handle, extension_id, episode_id, allow_adult).await?;
+
+    let source = pick_auto_download_source(&sources)
+        .ok_or_else(|| anyhow::anyhow!("No usable sources for this episode"))?;
+
+    Ok(ResolvedSource::from_source(source))
+}
+
+/// Fill a placeholder download in with its source
+pub fn apply_source(progress: &mut DownloadProgress, source: ResolvedSource) {
+    let filename = resolved_filename(&progress.filename, &source);
+    let file_path = Path::new(&progress.file_path).with_file_name(&filename);
+
+    progress.file_path = file_path.to_string_lossy().to_string();
+    progress.filename = filename;
+    progress.url = source.url;
+    progress.quality = Some(source.quality);
+    progress.source_label = Some(source.server);
+    progress.headers = source.headers;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_get_the_picked_quality_in_their_name() {
+        let placeholder = placeholder_filename("Frieren: Beyond Journey's End", 3);
+        assert_eq!(placeholder, "Frieren__Beyond_Journey_s_End_EP3.mp4");
+
+        let source = ResolvedSource {
+            url: "https://cdn.example.com/ep3.m3u8".to_string(),
+            quality: "1080p".to_string(),
+            server: "Default".to_string(),
+            is_hls: true,
+            headers: HashMap::new(),
+        };
+        assert_eq!(
+            resolved_filename(&placeholder, &source),
+            "Frieren__Beyond_Journey_s_End_EP3_1080p.m3u8"
+        );
+    }
+
+    #[test]
+    fn source_headers_add_the_referrer_unless_given() {
+        let mut source: VideoSource = serde_json::from_value(serde_json::json!({
+            "url": "https://cdn.example.com/ep3.mp4",
+            "quality": "1080p",
+            "type": "mp4",
+            "server": "Default",
+            "referrer": "https://site.example.com/",
+            "headers": { "Cookie": "token=abc" },
+        }))
+        .unwrap();
+        assert_eq!(
+            source_headers(&source),
+            HashMap::from([
+                ("Cookie".to_string(), "token=abc".to_string()),
+                ("Referer".to_string(), "https://site.example.com/".to_string()),
+            ])
+        );
+
+        source.headers.insert("referer".to_string(), "https://other.example.com/".to_string());
+        assert_eq!(source_headers(&source)["referer"], "https://other.example.com/");
+        assert_eq!(source_headers(&source).len(), 2);
+
+        source.referrer = None;
+        source.headers.clear();
+        assert!(source_headers(&source).is_empty());
+    }
+}
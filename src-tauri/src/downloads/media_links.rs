@@ -0,0 +1,346 @@
+// Download Media Links
+//
+// Downloads get their series title and cover from the media row they point
+// at. After clear_all_data or a failed migration that row can be gone while
+// the files are still there. get_downloads_with_media shows those with
+// placeholder metadata, and repair_download_media_links recreates the
+// missing rows: from the extension the download came from, from Jikan for
+// MAL ids, or as a placeholder titled from what the download stored
+// ("Unknown series" when it stored nothing).
+
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use crate::commands::{self, AppState};
+use crate::database::media::{save_media, MediaEntry};
+use crate::extensions::{adult, circuit_breaker};
+use crate::extensions::MediaDetails;
+
+/// Title of placeholder media rows when a download stored no title
+pub const UNKNOWN_SERIES_TITLE: &str = "Unknown series";
+
+/// extension_id of placeholder media rows whose source isn't known
+const UNKNOWN_EXTENSION_ID: &str = "unknown";
+
+/// A media id that downloads point at but no media row has
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanedMedia {
+    pub media_id: String,
+    /// Series title stored with the downloads
+    pub media_title: Option<String>,
+    /// Extension the downloads' sources came from
+    pub extension_id: Option<String>,
+    /// One of the downloads' filenames, for a title when none was stored
+    pub filename: String,
+    pub downloads: i64,
+}
+
+impl OrphanedMedia {
+    /// Title for a placeholder row
+    fn placeholder_title(&self) -> String {
+        self.media_title
+            .clone()
+            .filter(|t| !t.trim().is_empty())
+            .or_else(|| {
+                // Filenames that follow the Title_EP1_quality.mp4 convention
+                self.filename
+                    .contains("_EP")
+                    .then(|| super::batch::title_from_filename(&self.filename))
+                    .filter(|t| !t.trim().is_empty())
+            })
+            .unwrap_or_else(|| UNKNOWN_SERIES_TITLE.to_string())
+    }
+
+    fn placeholder_entry(&self) -> MediaEntry {
+        let now = chrono::Utc::now().to_rfc3339();
+        MediaEntry {
+            id: self.media_id.clone(),
+            extension_id: self.extension_id.clone().unwrap_or_else(|| UNKNOWN_EXTENSION_ID.to_string()),
+            title: self.placeholder_title(),
+            english_name: None,
+            native_name: None,
+            description: None,
+            cover_url: None,
+            banner_url: None,
+            trailer_url: None,
+            media_type: "anime".to_string(),
+            content_type: None,
+            status: None,
+            year: None,
+            rating: None,
+            episode_count: None,
+            episode_duration: None,
+            season_quarter: None,
+            season_year: None,
+            aired_start_year: None,
+            aired_start_month: None,
+            aired_start_date: None,
+            genres: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+}
+
+/// Outcome of repair_download_media_links
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MediaLinkRepair {
+    /// Media ids downloads pointed at without a media row
+    pub orphaned: usize,
+    /// Rows recreated from the extension's or Jikan's details
+    pub fetched: usize,
+    /// Rows recreated from what the downloads stored
+    pub placeholders: usize,
+    /// Orphans whose details fetch failed (they got a placeholder)
+    pub fetch_failures: usize,
+}
+
+impl MediaLinkRepair {
+    /// Media ids that point at a media row again
+    pub fn repaired(&self) -> usize {
+        self.fetched + self.placeholders
+    }
+}
+
+/// Media ids downloads point at that have no media row
+pub async fn orphaned_media(pool: &SqlitePool) -> Result<Vec<OrphanedMedia>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            d.media_id,
+            MAX(d.media_title) AS media_title,
+            MAX(d.source_extension_id) AS extension_id,
+            MIN(d.filename) AS filename,
+            COUNT(*) AS downloads
+        FROM downloads d
+        WHERE NOT EXISTS (SELECT 1 FROM media m WHERE m.id = d.media_id)
+        GROUP BY d.media_id
+        ORDER BY d.media_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| OrphanedMedia {
+            media_id: row.get("media_id"),
+            media_title: row.get("media_title"),
+            extension_id: row.get("extension_id"),
+            filename: row.get("filename"),
+            downloads: row.get("downloads"),
+        })
+        .collect())
+}
+
+/// Recreate the media rows of orphaned downloads. `fetch` looks up an
+/// orphan's details; when it finds nothing or fails, a placeholder is saved.
+pub async fn repair_media_links<F, Fut>(pool: &SqlitePool, fetch: F) -> Result<MediaLinkRepair>
+where
+    F: Fn(OrphanedMedia) -> Fut,
+    Fut: Future<Output = Result<Option<MediaEntry>>>,
+{
+    let orphans = orphaned_media(pool).await?;
+    let mut report = MediaLinkRepair {
+        orphaned: orphans.len(),
+        ..Default::default()
+    };
+
+    for orphan in orphans {
+        let fetched = match fetch(orphan.clone()).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Failed to fetch details of {}: {:#}", orphan.media_id, e);
+                report.fetch_failures += 1;
+                None
+            }
+        };
+
+        match fetched {
+            Some(mut entry) => {
+                // The downloads keep pointing at the id they have
+                entry.id = orphan.media_id.clone();
+                save_media(pool, &entry).await?;
+                report.fetched += 1;
+            }
+            None => {
+                save_media(pool, &orphan.placeholder_entry()).await?;
+                report.placeholders += 1;
+            }
+        }
+    }
+
+    log::info!(
+        "Repaired {} of {} download media link(s) ({} fetched, {} placeholders)",
+        report.repaired(), report.orphaned, report.fetched, report.placeholders
+    );
+    Ok(report)
+}
+
+/// Media row for details an extension returned
+fn media_entry_from_details(details: &MediaDetails, extension_id: &str) -> MediaEntry {
+    let now = chrono::Utc::now().to_rfc3339();
+    MediaEntry {
+        id: details.id.clone(),
+        extension_id: extension_id.to_string(),
+        title: details.title.clone(),
+        english_name: details.english_name.clone(),
+        native_name: details.native_name.clone(),
+        description: details.description.clone(),
+        cover_url: details.cover_url.clone(),
+        banner_url: None,
+        trailer_url: details.trailer_url.clone(),
+        media_type: "anime".to_string(),
+        content_type: details.media_type.clone(),
+        status: details.status.clone(),
+        year: details.year.map(|y| y as i32),
+        rating: details.rating.map(|r| r as f64),
+        episode_count: details.episode_count.map(|c| c as i32),
+        episode_duration: details.episode_duration.map(|d| d as i64),
+        season_quarter: details.season.as_ref().and_then(|s| s.quarter.clone()),
+        season_year: details.season.as_ref().and_then(|s| s.year).map(|y| y as i32),
+        aired_start_year: details.aired_start.as_ref().map(|a| a.year as i32),
+        aired_start_month: details.aired_start.as_ref().and_then(|a| a.month).map(|m| m as i32),
+        aired_start_date: details.aired_start.as_ref().and_then(|a| a.date).map(|d| d as i32),
+        genres: serde_json::to_string(&details.genres).ok(),
+        created_at: now.clone(),
+        updated_at: now,
+    }
+}
+
+/// Look up an orphan's details: from the extension its downloads came from
+/// if it's installed, otherwise from Jikan when the id is a MAL id
+async fn fetch_media_entry(app: &AppHandle, orphan: OrphanedMedia) -> Result<Option<MediaEntry>> {
+    let extension = orphan
+        .extension_id
+        .as_deref()
+        .and_then(|id| app.state::<AppState>().extension(id).ok());
+
+    if let Some(extension) = extension {
+        let allow_adult = adult::background_allow_adult(app.state::<AppState>().database.pool()).await;
+        let extension_id = extension.metadata.id.clone();
+        let media_id = orphan.media_id.clone();
+        return tokio::task::spawn_blocking(move || -> Result<Option<MediaEntry>> {
+            let runtime = commands::background_runtime(extension, allow_adult).map_err(|e| anyhow!(e))?;
+            let details = circuit_breaker::track(&extension_id, runtime.get_details(&media_id))
+                .map_err(|e| anyhow!("Failed to get details: {}", e))?;
+            Ok(Some(media_entry_from_details(&details, &extension_id)))
+        })
+        .await?;
+    }
+
+    if let Ok(mal_id) = orphan.media_id.parse::<i64>() {
+        let anime = tokio::task::spawn_blocking(move || crate::jikan::anime::anime_full(mal_id))
+            .await?
+            .map_err(|e| anyhow!(e))?;
+        return Ok(Some(crate::jikan::anime::jikan_anime_to_media_entry(&anime)));
+    }
+
+    Ok(None)
+}
+
+/// Recreate the media rows of every orphaned download
+pub async fn repair(app: &AppHandle, pool: &SqlitePool) -> Result<MediaLinkRepair> {
+    repair_media_links(pool, |orphan| fetch_media_entry(app, orphan)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::media::get_downloads_with_media;
+    use crate::database::Database;
+
+    async fn insert_download(pool: &SqlitePool, id: &str, media_id: &str, filename: &str, media_title: Option<&str>, extension_id: Option<&str>) {
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status, media_title, source_extension_id)
+            VALUES (?, ?, ?, 1, ?, '', ?, 'completed', ?, ?)
+            "#,
+        )
+        .bind(id)
+        .bind(media_id)
+        .bind(format!("{}-ep", id))
+        .bind(filename)
+        .bind(format!("/nonexistent/{}", filename))
+        .bind(media_title)
+        .bind(extension_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn orphaned_downloads_show_up_and_get_repaired() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('kept', 'ext', 'Kept Show', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        insert_download(pool, "kept_1", "kept", "Kept_Show_EP1_1080p.mp4", None, None).await;
+        // Orphans: one the extension knows, one with a stored title, one
+        // named by the filename convention and one with nothing to go on
+        insert_download(pool, "fetch_1", "fetch", "ep1.mp4", None, Some("com.example.source")).await;
+        insert_download(pool, "titled_1", "titled", "ep1.mp4", Some("Stored Title"), None).await;
+        insert_download(pool, "named_1", "named", "Named_Show_EP1_720p.mp4", None, None).await;
+        insert_download(pool, "bare_1", "bare", "video.mp4", None, None).await;
+
+        // Every download is listed, the orphans with placeholder metadata
+        let listed = get_downloads_with_media(pool).await.unwrap();
+        assert_eq!(listed.len(), 5);
+        let titled = listed.iter().find(|d| d.media_id == "titled").unwrap();
+        assert_eq!(titled.title, "Stored Title");
+        assert!(titled.media_missing);
+        assert!(!listed.iter().find(|d| d.media_id == "kept").unwrap().media_missing);
+
+        let orphans = orphaned_media(pool).await.unwrap();
+        assert_eq!(
+            orphans.iter().map(|o| o.media_id.as_str()).collect::<Vec<_>>(),
+            vec!["bare", "fetch", "named", "titled"]
+        );
+
+        let report = repair_media_links(pool, |orphan| async move {
+            match orphan.extension_id.as_deref() {
+                Some(extension_id) => {
+                    let mut entry = orphan.placeholder_entry();
+                    entry.id = "details-id".to_string();
+                    entry.extension_id = extension_id.to_string();
+                    entry.title = "Fetched Show".to_string();
+                    entry.cover_url = Some("https://example.test/cover.jpg".to_string());
+                    Ok(Some(entry))
+                }
+                None if orphan.media_id == "named" => Err(anyhow!("offline")),
+                None => Ok(None),
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            report,
+            MediaLinkRepair { orphaned: 4, fetched: 1, placeholders: 3, fetch_failures: 1 }
+        );
+        assert_eq!(report.repaired(), 4);
+        assert!(orphaned_media(pool).await.unwrap().is_empty());
+
+        let listed = get_downloads_with_media(pool).await.unwrap();
+        let title_of = |id: &str| listed.iter().find(|d| d.media_id == id).unwrap().title.clone();
+        assert_eq!(title_of("fetch"), "Fetched Show");
+        assert_eq!(title_of("titled"), "Stored Title");
+        assert_eq!(title_of("named"), "Named Show");
+        assert_eq!(title_of("bare"), UNKNOWN_SERIES_TITLE);
+        assert!(listed.iter().all(|d| !d.media_missing));
+        let fetched = listed.iter().find(|d| d.media_id == "fetch").unwrap();
+        assert_eq!(fetched.cover_url.as_deref(), Some("https://example.test/cover.jpg"));
+
+        // Nothing left to repair
+        let report = repair_media_links(pool, |_| async { Ok(None) }).await.unwrap();
+        assert_eq!(report, MediaLinkRepair::default());
+    }
+}
@@ -3,27 +3,197 @@
 // Handles:
 // - Download queue with Tokio tasks
 // - Progress tracking with database persistence
-// - Pause/resume/cancel operations
-// - Concurrent downloads (max 3)
-// - File integrity verification
+// - Pause/resume/cancel operations, which interrupt a running transfer through
+//   its cancellation token even while the connection is idle
+// - Stalled connections (no response or data for STALL_TIMEOUT) failed as network errors
+// - Pausing all downloads at once, which also holds back downloads queued
+//   until everything is resumed (downloads_paused)
+// - Downloads interrupted by closing the app come back paused, resumed on
+//   launch when download_auto_resume is on; quitting stops running ones
+//   first so their saved progress matches their files (shutdown)
+// - Automatic retries of failed downloads with backoff (download_max_retries)
+// - Concurrent downloads (max_concurrent_downloads setting, 10 by default)
+// - Global download speed limit shared by all downloads (throttle.rs)
+// - Scheduled start times and an off-peak window for queued downloads (schedule.rs)
+// - Speed and time remaining measured over the last few seconds (speed.rs)
+// - Totals for the downloads page header, including bytes downloaded today (stats.rs)
+// - Free disk space checked before a download starts (disk_space.rs)
+// - File size and checksum verification of completed downloads (verify.rs),
+//   and a few re-checked per maintenance run to catch files that rot (verify_sweep.rs)
+// - HLS (m3u8) downloads joined into a single file (hls.rs)
+// - Large files fetched over several connections at once (segmented.rs)
+// - Filenames rendered from a user template (filename.rs)
+// - HTTP headers an extension gives with a source, sent with every request
+//   of the download and kept across restarts (download_headers)
+// - Subtitle tracks fetched next to the video once it completes (subtitles.rs)
 // - Chapter downloads for manga
-
+// - Cold-storage archiving of finished series
+// - Deleting watched episodes after a grace period (auto_delete.rs)
+// - A storage quota that evicts the least recently watched episodes to make
+//   room for new downloads (quota.rs)
+// - Organizing completed files into per-series folders
+// - Recreating missing media rows of downloaded series (media_links.rs)
+// - Finding files no download points at, and downloads whose file is gone (orphans.rs)
+// - Quality upgrades that replace an existing download's file
+// - One summary notification per batch of episodes queued together
+// - Episodes of a batch ready to watch offline from the watch position
+//   (offline_ready.rs)
+// - Batch downloads whose sources are fetched as each episode starts
+// - Refreshing the expired source URL of a stopped download, or of a running
+//   one the server refuses (source_refresh.rs)
+// - Sources kept with each download to fetch a missing file again without
+//   resolving the episode (source_history.rs)
+// - Retrying every failed download at once, grouped by cause (retry_failed.rs)
+// - A history of completed downloads and of those removed from the list,
+//   which clearing moves downloads into (history.rs)
+// - Pausing downloads while the network is down and resuming them after (network.rs)
+// - Size estimates checked against free disk space before queueing, and
+//   filled in on queued downloads before they start (size_estimate.rs)
+
+pub mod archive;
+pub mod auto_delete;
+pub mod batch;
 pub mod chapter_downloads;
+pub mod disk_space;
+pub mod filename;
+pub mod history;
+pub mod hls;
+pub mod lazy_source;
+pub mod media_links;
+pub mod network;
 pub mod obfuscation;
+pub mod offline_ready;
+pub mod organize;
+pub mod orphans;
+pub mod quota;
+pub mod retry_failed;
+pub mod schedule;
+pub mod segmented;
+pub mod size_estimate;
+pub mod source_history;
+pub mod source_refresh;
+pub mod speed;
+pub mod stats;
+pub mod subtitles;
+pub mod throttle;
+pub mod trash;
+pub mod upgrade;
+pub mod verify;
+pub mod verify_sweep;
+pub mod watchfolder;
 
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use sqlx::{SqlitePool, Row};
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
+
+use crate::events::DOWNLOAD_PROGRESS_EVENT;
+use crate::extensions::types::Subtitle;
+use history::HistoryEvent;
+use lazy_source::ResolvedSource;
+use crate::http_retry::{send_with_retry, RetryPolicy};
 use crate::notifications;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Delete what an unfinished download has fetched so far (the partial file,
+/// or an HLS download's segments) and reset its progress
+async fn discard_partial(progress: &mut DownloadProgress) {
+    tokio::fs::remove_file(&progress.file_path).await.ok();
+    tokio::fs::remove_dir_all(hls::parts_dir(&progress.file_path)).await.ok();
+    segmented::remove_state(&progress.file_path).await;
+    progress.downloaded_bytes = 0;
+    progress.percentage = 0.0;
+    progress.speed = 0;
+    progress.eta_seconds = None;
+    progress.segments = None;
+    log::debug!("Discarded partial download: {}", progress.id);
+}
+
+/// Bytes an unfinished download has on disk: its file's length, or for a
+/// segmented download (whose file is allocated at full size) its record of
+/// the ranges. None for HLS downloads, whose segments are kept apart, and
+/// when there's no file yet.
+fn bytes_on_disk(file_path: &str) -> Option<u64> {
+    if hls::parts_dir(file_path).exists() {
+        return None;
+    }
+    segmented::recorded_bytes(file_path).or_else(|| std::fs::metadata(file_path).ok().map(|m| m.len()))
+}
+
+/// How long shutdown waits for running downloads to stop writing
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// A connection that sends nothing for this long is given up on
+const STALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Cancellation token of each download whose transfer is running, by id
+type CancelTokens = Arc<std::sync::Mutex<HashMap<String, CancellationToken>>>;
+
+/// The error a transfer stopped through its cancellation token ends with,
+/// by what its status now says
+async fn interrupted(downloads: &RwLock<HashMap<String, DownloadProgress>>, download_id: &str) -> anyhow::Error {
+    let paused = downloads.read().await.get(download_id).is_some_and(|p| p.status == DownloadStatus::Paused);
+    anyhow::anyhow!("Download {}", if paused { "paused" } else { "cancelled" })
+}
+
+/// Referer sent when a download's extension gave no headers; AllAnime's
+/// CDNs refuse requests without it
+const DEFAULT_REFERER: &str = "https://allmanga.to";
+
+/// Headers sent with every request of a download: a browser User-Agent plus
+/// the headers its extension gave with the source, or the default Referer
+/// when it gave none. Headers that aren't valid HTTP are skipped.
+pub(crate) fn download_headers(extra: &HashMap<String, String>) -> reqwest::header::HeaderMap {
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue, REFERER, USER_AGENT};
+
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0"));
+    if extra.is_empty() {
+        headers.insert(REFERER, HeaderValue::from_static(DEFAULT_REFERER));
+    }
+    for (name, value) in extra {
+        match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => log::warn!("Skipping invalid download header: {}", name),
+        }
+    }
+    headers
+}
+
+/// Headers column of the downloads table: JSON, NULL when there are none
+fn headers_to_db(headers: &HashMap<String, String>) -> Option<String> {
+    if headers.is_empty() {
+        return None;
+    }
+    serde_json::to_string(headers).ok()
+}
+
+fn headers_from_db(value: Option<String>) -> HashMap<String, String> {
+    value
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// Status of an episode download.
+///
+/// Transitions, as seen in download-progress events:
+/// - queued → downloading → completed | failed (failures may go back to
+///   queued for an automatic retry)
+/// - queued | downloading → paused → queued on resume
+/// - any unfinished status → cancelled. `cancel_download` keeps the bytes
+///   fetched so far (`downloaded_bytes` says how many) and `resume_download`
+///   picks up from there; `abort_download` deletes them and reports
+///   cancelled with `downloaded_bytes` 0.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum DownloadStatus {
     Queued,
@@ -31,15 +201,95 @@ pub enum DownloadStatus {
     Paused,
     Completed,
     Failed,
+    /// Stopped by the user; `resume_download` continues from `downloaded_bytes`
     Cancelled,
+    /// Archived download whose storage isn't reachable right now (e.g. the
+    /// NAS is unmounted). Presentation-only: persisted as "completed".
+    Offline,
+}
+
+impl DownloadStatus {
+    /// Status string stored in the downloads table
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            DownloadStatus::Queued => "queued",
+            DownloadStatus::Downloading => "downloading",
+            DownloadStatus::Paused => "paused",
+            DownloadStatus::Completed | DownloadStatus::Offline => "completed",
+            DownloadStatus::Failed => "failed",
+            DownloadStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Where a download's file is right now. Only meaningful once a download has
+/// completed; `status` stays the historical outcome either way.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileState {
+    #[default]
+    Present,
+    /// Not found where the download points (deleted outside the app, or on
+    /// archive storage that isn't mounted)
+    Missing,
+    /// Moved to the downloads trash; can be restored
+    Trashed,
+    /// On cold storage outside the downloads directory
+    Archived,
+    /// There, but failed a background integrity check (verify_sweep.rs);
+    /// downloading it again replaces it
+    Corrupt,
+}
+
+impl FileState {
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            FileState::Present => "present",
+            FileState::Missing => "missing",
+            FileState::Trashed => "trashed",
+            FileState::Archived => "archived",
+            FileState::Corrupt => "corrupt",
+        }
+    }
+
+    fn from_db_str(value: &str) -> Self {
+        match value {
+            "missing" => FileState::Missing,
+            "trashed" => FileState::Trashed,
+            "archived" => FileState::Archived,
+            "corrupt" => FileState::Corrupt,
+            _ => FileState::Present,
+        }
+    }
+
+    /// The file can be played from where the download points
+    pub fn is_playable(&self) -> bool {
+        matches!(self, FileState::Present | FileState::Archived)
+    }
+
+    /// State of a completed download's file given what's on disk.
+    /// A trashed file stays trashed while it's still in the trash, and a
+    /// corrupt one stays flagged until it's replaced.
+    fn observe(stored: FileState, archived: bool, exists: bool) -> Self {
+        match (exists, stored, archived) {
+            (false, _, _) => FileState::Missing,
+            (true, FileState::Trashed, _) => FileState::Trashed,
+            (true, FileState::Corrupt, _) => FileState::Corrupt,
+            (true, _, true) => FileState::Archived,
+            (true, _, false) => FileState::Present,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct DownloadProgress {
     pub id: String,
     pub media_id: String,
     pub episode_id: String,
     pub episode_number: i32,
+    /// Series title, so notifications don't have to recover it from the filename
+    #[serde(default)]
+    pub media_title: Option<String>,
     pub filename: String,
     pub url: String,
     pub file_path: String,
@@ -47,17 +297,230 @@ pub struct DownloadProgress {
     pub downloaded_bytes: u64,
     pub percentage: f32,
     pub speed: u64, // bytes per second
+    /// Seconds left at the current speed; None when the size is unknown or
+    /// the download isn't running
+    #[serde(default)]
+    pub eta_seconds: Option<u64>,
     pub status: DownloadStatus,
     pub error_message: Option<String>,
+    /// Automatic retries since the download last made progress
+    #[serde(default)]
+    pub retry_count: u32,
+    /// Segment progress of an HLS download, whose byte total isn't known
+    /// until every segment is in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments: Option<hls::SegmentProgress>,
+    /// File has been moved to cold storage outside the downloads directory
+    #[serde(default)]
+    pub archived: bool,
+    /// Quality label of the source this was downloaded from (e.g. "1080p")
+    #[serde(default)]
+    pub quality: Option<String>,
+    /// Server/variant name of the source (e.g. "HardSub")
+    #[serde(default)]
+    pub source_label: Option<String>,
+    /// Set on quality-upgrade downloads: the download whose file this replaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces_download_id: Option<String>,
+    /// Shared by episodes queued together; the batch gets one notification
+    /// once every member has finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    /// Set while the source is still to be fetched from this extension; the
+    /// url is empty until then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_extension_id: Option<String>,
+    /// Whether the completed file is still on disk
+    #[serde(default)]
+    pub file_state: FileState,
+    /// Unix timestamp (ms) before which the download doesn't start; takes
+    /// the place of the off-peak window for this download
+    #[serde(default)]
+    pub scheduled_start: Option<i64>,
+    /// "Start now": ignore the scheduled start and the off-peak window
+    #[serde(default)]
+    pub start_now: bool,
+    /// Bytes per second this download may use, on top of the global limit;
+    /// None leaves it unlimited
+    #[serde(default)]
+    pub speed_limit: Option<u64>,
+    /// Waiting in the queue because all downloads are paused (pause_all);
+    /// starts once they're resumed. Not stored.
+    #[serde(default)]
+    pub paused_globally: bool,
+    /// HTTP headers the source's CDN needs, from the extension; empty for
+    /// the defaults (see download_headers)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+}
+
+impl DownloadProgress {
+    /// Series title for notifications. Downloads queued before titles were
+    /// stored fall back to the Title_EP1_quality.mp4 filename convention.
+    pub fn display_title(&self) -> String {
+        self.media_title
+            .clone()
+            .unwrap_or_else(|| batch::title_from_filename(&self.filename))
+    }
+}
+
+/// What a new download would overwrite
+#[derive(Debug, Clone, Serialize)]
+pub struct ExistingDownload {
+    /// The completed download of the episode (or owning the file); None for
+    /// a file no download tracks
+    pub download: Option<DownloadProgress>,
+    pub file_path: String,
+}
+
+/// Why a download wasn't queued
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum QueueError {
+    /// The episode is already downloaded, or its file exists; queue it again
+    /// with overwrite to replace it
+    AlreadyDownloaded(Box<ExistingDownload>),
+    /// The download doesn't fit under the storage quota even after evicting
+    /// every watched episode that may go (quota.rs)
+    QuotaExceeded(quota::QuotaExceeded),
+    /// Queueing failed for any other reason
+    Failed(String),
+}
+
+impl std::fmt::Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueError::AlreadyDownloaded(existing) => {
+                write!(f, "Episode is already downloaded: {}", existing.file_path)
+            }
+            QueueError::QuotaExceeded(exceeded) => write!(f, "{}", exceeded),
+            QueueError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+impl QueueError {
+    /// The typed error behind an anyhow error from queueing, or Failed with
+    /// its message
+    pub fn from_anyhow(error: anyhow::Error, context: &str) -> Self {
+        match error.downcast::<QueueError>() {
+            Ok(e) => e,
+            Err(e) => QueueError::Failed(format!("{}: {}", context, e)),
+        }
+    }
+}
+
+/// app_settings key: how many episodes download at the same time
+pub const MAX_CONCURRENT_SETTING: &str = "max_concurrent_downloads";
+
+/// Concurrent downloads when the setting isn't set
+pub const DEFAULT_MAX_CONCURRENT: usize = 10;
+
+/// Highest accepted max_concurrent_downloads
+pub const MAX_CONCURRENT_LIMIT: usize = 20;
+
+/// Bring a concurrency limit into 1..=MAX_CONCURRENT_LIMIT
+pub fn clamp_max_concurrent(value: usize) -> usize {
+    let clamped = value.clamp(1, MAX_CONCURRENT_LIMIT);
+    if clamped != value {
+        log::warn!(
+            "Max concurrent downloads {} is out of range, using {} (allowed: 1-{})",
+            value, clamped, MAX_CONCURRENT_LIMIT
+        );
+    }
+    clamped
+}
+
+/// app_settings key: automatic retries of a failed download before giving up
+pub const MAX_RETRIES_SETTING: &str = "download_max_retries";
+
+/// Automatic retries when the setting isn't set
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Wait before each automatic retry; later retries reuse the last delay
+const RETRY_DELAYS_SECS: &[u64] = &[5, 15, 60, 300];
+
+/// How long to wait before automatic retry number `attempt` (1-based)
+pub fn retry_delay(attempt: u32) -> std::time::Duration {
+    let index = (attempt.max(1) as usize - 1).min(RETRY_DELAYS_SECS.len() - 1);
+    std::time::Duration::from_secs(RETRY_DELAYS_SECS[index])
+}
+
+/// The stored download_max_retries setting, or DEFAULT_MAX_RETRIES
+pub async fn load_max_retries_setting(pool: &SqlitePool) -> u32 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(MAX_RETRIES_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.and_then(|v| v.trim().parse().ok()).unwrap_or(DEFAULT_MAX_RETRIES)
+}
+
+/// app_settings key: "true" resumes downloads the app was closed in the
+/// middle of as soon as it starts again
+pub const AUTO_RESUME_SETTING: &str = "download_auto_resume";
+
+/// Whether interrupted downloads resume on launch. Off when unset.
+pub async fn auto_resume_enabled(pool: &SqlitePool) -> bool {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(AUTO_RESUME_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.as_deref() == Some("true")
+}
+
+/// app_settings key: "true" while downloads are paused globally by
+/// pause_all, so they stay paused across restarts
+pub const DOWNLOADS_PAUSED_SETTING: &str = "downloads_paused";
+
+/// Whether downloads were left paused globally. Off when unset.
+pub async fn downloads_paused_setting(pool: &SqlitePool) -> bool {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(DOWNLOADS_PAUSED_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.as_deref() == Some("true")
 }
 
-/// Event name for download progress updates
-pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+/// The stored max_concurrent_downloads setting, if any
+pub async fn load_max_concurrent_setting(pool: &SqlitePool) -> Option<usize> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(MAX_CONCURRENT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.and_then(|v| v.trim().parse().ok())
+}
 
+/// Clones share the downloads and all state with the original
+#[derive(Clone)]
 pub struct DownloadManager {
     downloads: Arc<RwLock<HashMap<String, DownloadProgress>>>,
     active_downloads: Arc<Mutex<usize>>,
-    max_concurrent: usize,
+    /// Shared with queued download tasks, which re-read it while they wait
+    max_concurrent: Arc<AtomicUsize>,
+    /// Set by pause_all until resume_all: queued downloads don't start
+    downloads_paused: Arc<AtomicBool>,
+    /// Set by shutdown: downloads it paused are saved as interrupted
+    exiting: Arc<AtomicBool>,
+    /// Cancelled (and removed) by whatever stops a running transfer, after
+    /// it set the status saying why
+    cancel_tokens: CancelTokens,
+    /// Downloads aborted while their transfer was running. The task deletes
+    /// the partial file once it has stopped writing to it.
+    pending_aborts: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Off-peak window and the queued downloads parked until they may start
+    schedule: Arc<schedule::Schedule>,
+    /// Whether the network is up, and the downloads paused while it wasn't
+    network: Arc<network::NetworkState>,
     download_dir: PathBuf,
     db_pool: Option<Arc<SqlitePool>>,
     app_handle: Option<AppHandle>,
@@ -68,7 +531,13 @@ impl DownloadManager {
         Self {
             downloads: Arc::new(RwLock::new(HashMap::new())),
             active_downloads: Arc::new(Mutex::new(0)),
-            max_concurrent: 10,
+            max_concurrent: Arc::new(AtomicUsize::new(DEFAULT_MAX_CONCURRENT)),
+            downloads_paused: Arc::new(AtomicBool::new(false)),
+            exiting: Arc::new(AtomicBool::new(false)),
+            cancel_tokens: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            pending_aborts: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            schedule: Arc::new(schedule::Schedule::default()),
+            network: Arc::new(network::NetworkState::default()),
             download_dir,
             db_pool: None,
             app_handle: None,
@@ -87,22 +556,57 @@ impl DownloadManager {
         self
     }
 
+    /// Set the concurrency limit (clamped to 1..=MAX_CONCURRENT_LIMIT)
+    pub fn with_max_concurrent(self, max_concurrent: usize) -> Self {
+        self.set_max_concurrent(max_concurrent);
+        self
+    }
+
+    /// Start with downloads paused globally (the saved downloads_paused
+    /// setting), before loading them from the database
+    pub fn with_downloads_paused(self, paused: bool) -> Self {
+        self.downloads_paused.store(paused, Ordering::SeqCst);
+        self
+    }
+
+    /// Whether downloads are paused globally: queued ones wait until resume_all
+    pub fn downloads_paused(&self) -> bool {
+        self.downloads_paused.load(Ordering::SeqCst)
+    }
+
+    /// How many downloads may run at the same time
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent.load(Ordering::SeqCst)
+    }
+
+    /// Change the concurrency limit, returning the (clamped) value applied.
+    /// Queued downloads pick it up within a second; downloads already running
+    /// over a lowered limit finish normally.
+    pub fn set_max_concurrent(&self, max_concurrent: usize) -> usize {
+        let max_concurrent = clamp_max_concurrent(max_concurrent);
+        self.max_concurrent.store(max_concurrent, Ordering::SeqCst);
+        max_concurrent
+    }
+
     /// Emit a download progress event to the frontend
     fn emit_progress(&self, progress: &DownloadProgress) {
         if let Some(ref handle) = self.app_handle {
-            if let Err(e) = handle.emit(DOWNLOAD_PROGRESS_EVENT, progress) {
-                log::error!("Failed to emit download progress event: {}", e);
-            }
+            DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
         }
     }
 
-    /// Load downloads from database on startup
-    pub async fn load_from_database(&self) -> Result<()> {
+    /// Load downloads saved by earlier runs. Downloads the app was closed in
+    /// the middle of come back paused with the bytes they already have; their
+    /// ids are returned so they can be resumed.
+    pub async fn load_from_database(&self) -> Result<Vec<String>> {
+        let mut interrupted = Vec::new();
         if let Some(pool) = &self.db_pool {
             let rows = sqlx::query(
                 r#"
                 SELECT id, media_id, episode_id, episode_number, filename, url, file_path,
-                       total_bytes, downloaded_bytes, percentage, speed, status, error_message
+                       total_bytes, downloaded_bytes, percentage, speed, status, error_message,
+                       archived, quality, source_label, replaces_download_id, file_state, batch_id,
+                       source_extension_id, media_title, scheduled_start, start_now, speed_limit, headers
                 FROM downloads
                 "#
             )
@@ -116,20 +620,28 @@ impl DownloadManager {
                 let file_metadata = tokio::fs::metadata(&file_path).await;
                 let file_exists = file_metadata.is_ok();
 
+                let archived = row.try_get::<i64, _>("archived")? != 0;
+
                 let original_status_str: String = row.try_get("status")?;
-                let completed_file_missing = original_status_str == "completed" && !file_exists;
-                let mut status_str = original_status_str.clone();
-                // If completed but file missing, mark as failed
-                if completed_file_missing {
-                    status_str = "failed".to_string();
-                }
+                let stored_file_state = FileState::from_db_str(&row.try_get::<String, _>("file_state")?);
+
+                // A completed download stays completed when its file goes
+                // missing; only file_state records that
+                let file_state = if original_status_str == "completed" {
+                    FileState::observe(stored_file_state, archived, file_exists)
+                } else {
+                    stored_file_state
+                };
+                // Archived files may just be on unmounted storage; those show as
+                // Offline until the storage is back
+                let archive_offline = archived && original_status_str == "completed" && !file_exists;
 
-                let status = match status_str.as_str() {
+                let status = match original_status_str.as_str() {
                     "queued" => DownloadStatus::Queued,
-                    "downloading" => DownloadStatus::Failed, // Mark in-progress as failed on restart
+                    "downloading" => DownloadStatus::Paused, // Interrupted; resumable from its partial file
                     "paused" => DownloadStatus::Paused,
-                    "completed" if file_exists => DownloadStatus::Completed,
-                    "completed" => DownloadStatus::Failed,
+                    "completed" if archive_offline => DownloadStatus::Offline,
+                    "completed" => DownloadStatus::Completed,
                     "failed" => DownloadStatus::Failed,
                     "cancelled" => DownloadStatus::Cancelled,
                     _ => DownloadStatus::Failed,
@@ -138,6 +650,29 @@ impl DownloadManager {
                 // Get total_bytes from database, but update with actual file size if it's 0
                 let mut total_bytes = row.try_get::<i64, _>("total_bytes")? as u64;
                 let mut downloaded_bytes = row.try_get::<i64, _>("downloaded_bytes")? as u64;
+                let mut percentage = row.try_get::<f32, _>("percentage")?;
+
+                // A cancelled download resumes from its partial file; with that
+                // gone it starts over, so don't show progress it no longer has
+                if status == DownloadStatus::Cancelled
+                    && !file_exists
+                    && !hls::parts_dir(&file_path).exists()
+                {
+                    downloaded_bytes = 0;
+                    percentage = 0.0;
+                }
+
+                // Progress is saved every few seconds, so an interrupted
+                // download's file is usually ahead of downloaded_bytes. Resuming
+                // only picks up from bytes that match the file, so go by the file.
+                if original_status_str == "downloading" {
+                    if let Some(bytes) = bytes_on_disk(&file_path) {
+                        downloaded_bytes = bytes;
+                        if total_bytes > 0 {
+                            percentage = (downloaded_bytes as f64 / total_bytes as f64 * 100.0).min(100.0) as f32;
+                        }
+                    }
+                }
 
                 // Fix total_bytes for completed downloads where it's 0 (Content-Length was missing)
                 if status == DownloadStatus::Completed && total_bytes == 0 && file_exists {
@@ -153,6 +688,7 @@ impl DownloadManager {
                             media_id: row.try_get("media_id")?,
                             episode_id: row.try_get("episode_id")?,
                             episode_number: row.try_get("episode_number")?,
+                            media_title: row.try_get("media_title")?,
                             filename: row.try_get("filename")?,
                             url: row.try_get("url")?,
                             file_path: file_path.clone(),
@@ -162,85 +698,83 @@ impl DownloadManager {
                             speed: 0,
                             status: DownloadStatus::Completed,
                             error_message: None,
+                            retry_count: 0,
+                            segments: None,
+                            eta_seconds: None,
+                            archived,
+                            quality: row.try_get("quality")?,
+                            source_label: row.try_get("source_label")?,
+                            replaces_download_id: row.try_get("replaces_download_id")?,
+                            batch_id: row.try_get("batch_id")?,
+                            source_extension_id: row.try_get("source_extension_id")?,
+                            file_state,
+                            scheduled_start: row.try_get("scheduled_start")?,
+                            start_now: row.try_get::<i64, _>("start_now")? != 0,
+                            speed_limit: row.try_get::<Option<i64>, _>("speed_limit")?.map(|l| l as u64),
+                            paused_globally: false,
+                            headers: headers_from_db(row.try_get("headers")?),
                         };
                         Self::save_progress_to_db(pool, &updated_progress).await.ok();
                     }
                 }
 
-                let progress = DownloadProgress {
+                let mut progress = DownloadProgress {
                     id: row.try_get("id")?,
                     media_id: row.try_get("media_id")?,
                     episode_id: row.try_get("episode_id")?,
                     episode_number: row.try_get("episode_number")?,
+                    media_title: row.try_get("media_title")?,
                     filename: row.try_get("filename")?,
                     url: row.try_get("url")?,
                     file_path,
                     total_bytes,
                     downloaded_bytes,
-                    percentage: row.try_get::<f32, _>("percentage")?,
-                    speed: row.try_get::<i64, _>("speed")? as u64,
+                    percentage,
+                    // Nothing is running yet, whatever was saved last
+                    speed: if original_status_str == "downloading" { 0 } else { row.try_get::<i64, _>("speed")? as u64 },
                     status,
-                    error_message: if completed_file_missing {
-                        Some("File not found. Please re-download.".to_string())
-                    } else {
-                        row.try_get("error_message")?
-                    },
+                    error_message: row.try_get("error_message")?,
+                    retry_count: 0,
+                    segments: None,
+                    eta_seconds: None,
+                    archived,
+                    quality: row.try_get("quality")?,
+                    source_label: row.try_get("source_label")?,
+                    replaces_download_id: row.try_get("replaces_download_id")?,
+                    batch_id: row.try_get("batch_id")?,
+                    source_extension_id: row.try_get("source_extension_id")?,
+                    file_state,
+                    scheduled_start: row.try_get("scheduled_start")?,
+                    start_now: row.try_get::<i64, _>("start_now")? != 0,
+                    speed_limit: row.try_get::<Option<i64>, _>("speed_limit")?.map(|l| l as u64),
+                    paused_globally: false,
+                    headers: headers_from_db(row.try_get("headers")?),
                 };
 
-                if completed_file_missing || original_status_str == "downloading" {
+                if file_state != stored_file_state || original_status_str == "downloading" {
                     Self::save_progress_to_db(pool, &progress).await.ok();
                 }
+                if original_status_str == "downloading" {
+                    interrupted.push(progress.id.clone());
+                }
+                self.schedule.park_restored(&mut progress, self.downloads_paused());
 
                 downloads.insert(progress.id.clone(), progress);
             }
 
-            log::debug!("Loaded {} downloads from database", downloads.len());
+            log::debug!(
+                "Loaded {} downloads from database ({} interrupted)",
+                downloads.len(),
+                interrupted.len()
+            );
         }
-        Ok(())
+        Ok(interrupted)
     }
 
     /// Save download to database
     async fn save_to_database(&self, download: &DownloadProgress) -> Result<()> {
         if let Some(pool) = &self.db_pool {
-            let status_str = format!("{:?}", download.status).to_lowercase();
-            sqlx::query(
-                r#"
-                INSERT INTO downloads (
-                    id, media_id, episode_id, episode_number, filename, url, file_path,
-                    total_bytes, downloaded_bytes, percentage, speed, status, error_message,
-                    created_at, updated_at
-                )
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-                ON CONFLICT(id) DO UPDATE SET
-                    downloaded_bytes = ?,
-                    percentage = ?,
-                    speed = ?,
-                    status = ?,
-                    error_message = ?,
-                    updated_at = CURRENT_TIMESTAMP
-                "#
-            )
-            .bind(&download.id)
-            .bind(&download.media_id)
-            .bind(&download.episode_id)
-            .bind(download.episode_number)
-            .bind(&download.filename)
-            .bind(&download.url)
-            .bind(&download.file_path)
-            .bind(download.total_bytes as i64)
-            .bind(download.downloaded_bytes as i64)
-            .bind(download.percentage)
-            .bind(download.speed as i64)
-            .bind(&status_str)
-            .bind(&download.error_message)
-            // For UPDATE
-            .bind(download.downloaded_bytes as i64)
-            .bind(download.percentage)
-            .bind(download.speed as i64)
-            .bind(&status_str)
-            .bind(&download.error_message)
-            .execute(pool.as_ref())
-            .await?;
+            Self::save_progress_to_db(pool, download).await?;
         }
         Ok(())
     }
@@ -252,38 +786,100 @@ impl DownloadManager {
                 .bind(download_id)
                 .execute(pool.as_ref())
                 .await?;
+            crate::badges::invalidate();
         }
         Ok(())
     }
 
-    /// Queue a new download
+    /// Queue a new download. Without `media_title` the title is looked up in
+    /// the media table.
     pub async fn queue_download(
         &self,
         id: String,
         media_id: String,
         episode_id: String,
         episode_number: i32,
+        media_title: Option<String>,
         url: String,
+        headers: HashMap<String, String>,
+        subtitles: Vec<Subtitle>,
+        alternatives: Vec<ResolvedSource>,
         filename: String,
         custom_path: Option<String>,
+        quality: Option<String>,
+        source_label: Option<String>,
+        batch_id: Option<String>,
+        scheduled_start: Option<i64>,
+        overwrite: bool,
     ) -> Result<()> {
-        // Use custom path if provided, otherwise use default download_dir
-        let download_dir = custom_path
-            .map(PathBuf::from)
-            .unwrap_or_else(|| self.download_dir.clone());
+        let (media_title, filename) = self
+            .title_and_filename(&media_id, media_title, episode_number, quality.as_deref(), filename)
+            .await;
+        let file_path = self.prepare_file_path(custom_path, &filename).await;
 
-        // Ensure the directory exists
-        tokio::fs::create_dir_all(&download_dir).await.ok();
+        let progress = DownloadProgress {
+            id,
+            media_id,
+            episode_id,
+            episode_number,
+            media_title,
+            filename,
+            url,
+            file_path: file_path.to_string_lossy().to_string(),
+            total_bytes: 0,
+            downloaded_bytes: 0,
+            percentage: 0.0,
+            speed: 0,
+            status: DownloadStatus::Queued,
+            error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality,
+            source_label,
+            replaces_download_id: None,
+            batch_id,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers,
+        };
+
+        self.enqueue(progress, &subtitles, &alternatives, overwrite).await
+    }
 
-        let file_path = download_dir.join(&filename);
+    /// Queue a download whose source isn't known yet. Its video source is
+    /// fetched from `extension_id` once it gets a download slot, so the URL
+    /// can't expire while it waits (see lazy_source.rs).
+    pub async fn queue_pending_download(
+        &self,
+        id: String,
+        media_id: String,
+        episode_id: String,
+        episode_number: i32,
+        filename: String,
+        custom_path: Option<String>,
+        batch_id: Option<String>,
+        extension_id: String,
+        scheduled_start: Option<i64>,
+        overwrite: bool,
+    ) -> Result<()> {
+        // The quality isn't known until the source resolves
+        let (media_title, filename) = self.title_and_filename(&media_id, None, episode_number, None, filename).await;
+        let file_path = self.prepare_file_path(custom_path, &filename).await;
 
         let progress = DownloadProgress {
-            id: id.clone(),
+            id,
             media_id,
             episode_id,
             episode_number,
+            media_title,
             filename,
-            url,
+            url: String::new(),
             file_path: file_path.to_string_lossy().to_string(),
             total_bytes: 0,
             downloaded_bytes: 0,
@@ -291,10 +887,143 @@ impl DownloadManager {
             speed: 0,
             status: DownloadStatus::Queued,
             error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality: None,
+            source_label: None,
+            replaces_download_id: None,
+            batch_id,
+            source_extension_id: Some(extension_id),
+            file_state: FileState::Present,
+            scheduled_start,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: HashMap::new(),
+        };
+
+        self.enqueue(progress, &[], &[], overwrite).await
+    }
+
+    /// Title and filename of a new download: the given title or the stored
+    /// one, and the filename template applied to `filename`
+    async fn title_and_filename(
+        &self,
+        media_id: &str,
+        media_title: Option<String>,
+        episode_number: i32,
+        quality: Option<&str>,
+        filename: String,
+    ) -> (Option<String>, String) {
+        let media_title = media_title.filter(|t| !t.trim().is_empty());
+        let Some(pool) = &self.db_pool else {
+            return (media_title, filename);
+        };
+
+        let title = match media_title {
+            Some(title) => Some(title),
+            None => filename::media_title(pool, media_id).await.unwrap_or_else(|e| {
+                log::warn!("Failed to look up title of {}: {}", media_id, e);
+                None
+            }),
         };
+        let rendered = filename::apply_template(pool, media_id, episode_number, quality, &filename)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to apply filename template: {}", e);
+                filename.clone()
+            });
+        (title, rendered)
+    }
+
+    /// Where a new download's file goes: the custom path if provided,
+    /// otherwise the default download_dir, created if needed
+    async fn prepare_file_path(&self, custom_path: Option<String>, filename: &str) -> PathBuf {
+        let download_dir = custom_path
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.download_dir.clone());
+
+        // Ensure the directory exists
+        tokio::fs::create_dir_all(&download_dir).await.ok();
+
+        download_dir.join(filename)
+    }
+
+    /// What queueing `progress` would overwrite: a completed download of the
+    /// same episode whose file is still there, or an existing file at its
+    /// path. An unfinished download's partial file isn't a conflict; the
+    /// download simply starts over or resumes into it.
+    pub async fn find_conflict(&self, progress: &DownloadProgress) -> Option<ExistingDownload> {
+        let downloads = self.downloads.read().await;
+
+        let completed = downloads.values().find(|d| {
+            d.media_id == progress.media_id
+                && d.episode_number == progress.episode_number
+                && d.status == DownloadStatus::Completed
+                && d.file_state.is_playable()
+        });
+        if let Some(d) = completed {
+            return Some(ExistingDownload {
+                download: Some(d.clone()),
+                file_path: d.file_path.clone(),
+            });
+        }
+
+        if tokio::fs::metadata(&progress.file_path).await.is_err() {
+            return None;
+        }
+        match downloads.values().find(|d| d.file_path == progress.file_path) {
+            Some(owner) if owner.status != DownloadStatus::Completed => None,
+            owner => Some(ExistingDownload {
+                download: owner.cloned(),
+                file_path: progress.file_path.clone(),
+            }),
+        }
+    }
+
+    /// Queue `progress`, refusing with QueueError::AlreadyDownloaded when it
+    /// would overwrite something unless `overwrite` is set, and with
+    /// QueueError::QuotaExceeded when it can't fit under the storage quota
+    async fn enqueue(
+        &self,
+        mut progress: DownloadProgress,
+        subtitle_tracks: &[Subtitle],
+        alternatives: &[ResolvedSource],
+        overwrite: bool,
+    ) -> Result<()> {
+        let conflict = self.find_conflict(&progress).await;
+        if let Some(existing) = conflict.as_ref().filter(|_| !overwrite) {
+            return Err(QueueError::AlreadyDownloaded(Box::new(existing.clone())).into());
+        }
+
+        // Lazily resolved downloads are checked once their source is
+        self.reserve_quota(&mut progress).await?;
+
+        if let Some(existing) = conflict {
+            self.set_aside_for_overwrite(&existing).await?;
+        }
+
+        let id = progress.id.clone();
+        if let Some(batch_id) = &progress.batch_id {
+            batch::open(batch_id);
+        }
 
         // Save to database
         self.save_to_database(&progress).await.ok();
+        if let Some(pool) = self.db_pool.as_ref() {
+            // Subtitle tracks are fetched once the video completes
+            if !subtitle_tracks.is_empty() {
+                if let Err(e) = subtitles::record(pool, &id, subtitle_tracks).await {
+                    log::warn!("Failed to record subtitle tracks of {}: {}", id, e);
+                }
+            }
+            // Lazily resolved downloads get theirs once they complete
+            if !progress.url.is_empty() {
+                source_history::record_queued(pool, &progress, alternatives).await;
+            }
+        }
 
         let mut downloads = self.downloads.write().await;
         downloads.insert(id.clone(), progress.clone());
@@ -315,103 +1044,196 @@ impl DownloadManager {
     async fn start_download_task(&self, download_id: String) -> Result<()> {
         let downloads = self.downloads.clone();
         let active_downloads = self.active_downloads.clone();
-        let max_concurrent = self.max_concurrent;
+        let max_concurrent = self.max_concurrent.clone();
+        let downloads_paused = self.downloads_paused.clone();
+        let exiting = self.exiting.clone();
+        let cancel_tokens = self.cancel_tokens.clone();
+        let pending_aborts = self.pending_aborts.clone();
+        let schedule = self.schedule.clone();
+        let network = self.network.clone();
         let db_pool = self.db_pool.clone();
         let app_handle = self.app_handle.clone();
+        let download_dir = self.download_dir.clone();
+        let manager = self.clone();
 
         tokio::spawn(async move {
-            // Wait for available slot
-            loop {
-                let active = active_downloads.lock().await;
-                if *active < max_concurrent {
-                    break;
-                }
-                drop(active);
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            // Fail right away, without taking a slot, when the file won't fit
+            if !Self::check_disk_space(&downloads, &download_id, db_pool.as_ref(), app_handle.as_ref()).await {
+                return;
             }
 
-            // Check if download was cancelled while waiting in queue
-            {
-                let downloads_map = downloads.read().await;
-                if let Some(progress) = downloads_map.get(&download_id) {
-                    if progress.status == DownloadStatus::Cancelled {
-                        log::debug!("Download was cancelled while queued: {}", download_id);
+            // A URL refused as expired gets one fresh source per task
+            let mut refreshed_source = false;
+
+            // Runs again after a failure that gets retried automatically
+            let result = loop {
+                // Wait for a slot and take it in one step, so many downloads
+                // resumed at once can't all slip past the limit together
+                loop {
+                    // While downloads are paused globally, or outside its
+                    // scheduled start or the off-peak window, the download is
+                    // parked; resume_all or the schedule task starts it again
+                    let parked = {
+                        let mut downloads_map = downloads.write().await;
+                        match downloads_map.get_mut(&download_id) {
+                            Some(progress) => {
+                                let parked = schedule.park_if_waiting(progress, downloads_paused.load(Ordering::SeqCst));
+                                if parked && progress.paused_globally {
+                                    if let Some(ref handle) = app_handle {
+                                        DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+                                    }
+                                }
+                                parked
+                            }
+                            None => false,
+                        }
+                    };
+                    if parked {
                         return;
                     }
-                }
-            }
 
-            // Acquire slot
-            {
-                let mut active = active_downloads.lock().await;
-                *active += 1;
-            }
+                    let mut active = active_downloads.lock().await;
+                    if *active < max_concurrent.load(Ordering::SeqCst) {
+                        *active += 1;
+                        break;
+                    }
+                    drop(active);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                }
 
-            // Update status to downloading and emit event
-            let should_proceed = {
-                let mut downloads_map = downloads.write().await;
-                if let Some(progress) = downloads_map.get_mut(&download_id) {
-                    // Check again if cancelled (could have been cancelled between the read and write)
-                    if progress.status == DownloadStatus::Cancelled {
-                        log::debug!("Download was cancelled before starting: {}", download_id);
-                        false
-                    } else {
-                        progress.status = DownloadStatus::Downloading;
+                // Update status to downloading and emit event. The token is
+                // registered under the same lock, so anything that stops the
+                // download afterwards finds it.
+                let cancel = CancellationToken::new();
+                let should_proceed = {
+                    let mut downloads_map = downloads.write().await;
+                    if let Some(progress) = downloads_map.get_mut(&download_id) {
+                        // Cancelled or paused while waiting in the queue, or already
+                        // picked up by a task started by an earlier resume
+                        if progress.status != DownloadStatus::Queued {
+                            log::debug!("Download is {:?}, not starting it: {}", progress.status, download_id);
+                            false
+                        } else {
+                            progress.status = DownloadStatus::Downloading;
+                            cancel_tokens.lock().unwrap().insert(download_id.clone(), cancel.clone());
+
+                            // Emit event
+                            if let Some(ref handle) = app_handle {
+                                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+                            }
 
-                        // Emit event
-                        if let Some(ref handle) = app_handle {
-                            let _ = handle.emit(DOWNLOAD_PROGRESS_EVENT, progress.clone());
+                            // Save to database
+                            if let Some(pool) = &db_pool {
+                                Self::save_progress_to_db(pool, progress).await.ok();
+                            }
+                            true
                         }
+                    } else {
+                        false
+                    }
+                };
 
-                        // Save to database
-                        if let Some(pool) = &db_pool {
-                            Self::save_progress_to_db(pool, progress).await.ok();
-                        }
-                        true
+                // Update tray downloads count after transitioning to Downloading
+                if should_proceed {
+                    if let (Some(ref handle), Some(ref pool)) = (&app_handle, &db_pool) {
+                        let active = total_active_downloads(&downloads, pool.as_ref()).await;
+                        crate::tray::update_downloads_count(handle, active);
                     }
-                } else {
-                    false
                 }
-            };
 
-            // Update tray downloads count after transitioning to Downloading
-            if should_proceed {
-                if let (Some(ref handle), Some(ref pool)) = (&app_handle, &db_pool) {
-                    let active = total_active_downloads(&downloads, pool.as_ref()).await;
-                    crate::tray::update_downloads_count(handle, active);
+                // If cancelled or not found, release slot and return
+                if !should_proceed {
+                    let mut active = active_downloads.lock().await;
+                    *active -= 1;
+                    return;
                 }
-            }
 
-            // If cancelled or not found, release slot and return
-            if !should_proceed {
-                let mut active = active_downloads.lock().await;
-                *active -= 1;
-                return;
-            }
+                // Fetch the source of a lazily queued download and make room
+                // for it under the quota, then perform it
+                let resolved = match Self::resolve_pending_source(&downloads, &download_id, db_pool.as_ref(), app_handle.as_ref()).await {
+                    Ok(true) => manager.reserve_quota_for_resolved(&download_id).await,
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                let result = match resolved {
+                    Ok(()) => Self::perform_download(
+                        download_id.clone(),
+                        downloads.clone(),
+                        db_pool.clone(),
+                        app_handle.clone(),
+                        cancel.clone(),
+                    ).await,
+                    Err(e) => Err(e),
+                };
 
-            // Perform download
-            let result = Self::perform_download(
-                download_id.clone(),
-                downloads.clone(),
-                db_pool.clone(),
-                app_handle.clone(),
-            ).await;
+                // A cancelled token was already taken out, and a resume may
+                // have registered the next task's in its place
+                if !cancel.is_cancelled() {
+                    cancel_tokens.lock().unwrap().remove(&download_id);
+                }
 
-            // Release slot
-            {
-                let mut active = active_downloads.lock().await;
-                *active -= 1;
-            }
+                // Release slot
+                {
+                    let mut active = active_downloads.lock().await;
+                    *active -= 1;
+                }
+
+                // Transient failures go back in the queue for another attempt,
+                // unless the network is down: then the download waits for it
+                if let Err(ref e) = result {
+                    if !refreshed_source
+                        && Self::refresh_expired_source(&downloads, &download_id, db_pool.as_ref(), app_handle.as_ref(), e).await
+                    {
+                        refreshed_source = true;
+                        continue;
+                    }
+                    if network::pause_if_offline(&downloads, &network, &download_id, db_pool.as_ref(), app_handle.as_ref(), e).await {
+                        break result;
+                    }
+                    if let Some(delay) = Self::schedule_retry(&downloads, &download_id, db_pool.as_ref(), app_handle.as_ref(), e).await {
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                }
+                break result;
+            };
+            // Keep the refresh in the message of a download that failed anyway
+            let result = match result {
+                Err(e) if refreshed_source => Err(anyhow::anyhow!("{} (after fetching a fresh source URL)", e)),
+                result => result,
+            };
+
+            // Move the finished file into its series folder before anyone
+            // sees the Completed event, so file_path is final when it's emitted
+            let organized_path = match (&result, &db_pool) {
+                (Ok(_), Some(pool)) => {
+                    let snapshot = downloads.read().await.get(&download_id).cloned();
+                    match snapshot {
+                        // Upgrades end up next to the file they replace instead
+                        Some(progress) if progress.replaces_download_id.is_none() => {
+                            Self::organize_completed(pool, &download_dir, &progress).await
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
 
             // Update final status and emit event
             {
                 let mut downloads_map = downloads.write().await;
+                let aborted = pending_aborts.lock().unwrap().remove(&download_id);
                 if let Some(progress) = downloads_map.get_mut(&download_id) {
-                    match result {
+                    match &result {
                         Ok(_) => {
                             progress.status = DownloadStatus::Completed;
+                            progress.file_state = FileState::Present;
                             progress.percentage = 100.0;
 
+                            if let Some(path) = organized_path {
+                                progress.file_path = path.to_string_lossy().to_string();
+                            }
+
                             // Set total_bytes to actual file size if it wasn't set (Content-Length missing)
                             if progress.total_bytes == 0 || progress.total_bytes < progress.downloaded_bytes {
                                 // Get actual file size from disk
@@ -425,60 +1247,72 @@ impl DownloadManager {
 
                             log::debug!("Download completed: {} ({} bytes)", download_id, progress.total_bytes);
 
-                            // Emit notification for completed download
-                            if let Some(ref handle) = app_handle {
-                                // Extract title from filename (format: Title_EP1_quality.mp4)
-                                let title = progress.filename
-                                    .split("_EP")
-                                    .next()
-                                    .unwrap_or(&progress.filename)
-                                    .replace('_', " ");
+                            // Emit notification for completed download (batches get one summary instead)
+                            if let (Some(ref handle), None) = (&app_handle, &progress.batch_id) {
+                                let title = progress.display_title();
 
                                 let _ = notifications::notify_download_complete(
                                     handle,
                                     db_pool.as_ref().map(|p| p.as_ref()),
                                     &title,
                                     progress.episode_number,
+                                    progress.quality.as_deref(),
                                     &progress.media_id,
                                 ).await;
                             }
                         }
                         Err(e) => {
-                            // Don't overwrite Cancelled or Paused status - they were intentional
-                            if progress.status != DownloadStatus::Cancelled && progress.status != DownloadStatus::Paused {
+                            // Don't overwrite Cancelled or Paused status - they were intentional.
+                            // Queued means it was paused and resumed before this task noticed;
+                            // the task started by the resume owns it now.
+                            if !matches!(progress.status, DownloadStatus::Cancelled | DownloadStatus::Paused | DownloadStatus::Queued) {
                                 progress.status = DownloadStatus::Failed;
                                 progress.error_message = Some(e.to_string());
                                 log::error!("Download failed: {} - {}", download_id, e);
 
-                                // Emit notification for failed download
-                                if let Some(ref handle) = app_handle {
-                                    // Extract title from filename
-                                    let title = progress.filename
-                                        .split("_EP")
-                                        .next()
-                                        .unwrap_or(&progress.filename)
-                                        .replace('_', " ");
+                                // Emit notification for failed download (batches get one summary instead)
+                                if let (Some(ref handle), None) = (&app_handle, &progress.batch_id) {
+                                    let title = progress.display_title();
 
                                     let _ = notifications::notify_download_failed(
                                         handle,
                                         db_pool.as_ref().map(|p| p.as_ref()),
                                         &title,
                                         progress.episode_number,
+                                        progress.quality.as_deref(),
                                         &e.to_string(),
                                         &progress.media_id,
                                     ).await;
                                 }
                             } else if progress.status == DownloadStatus::Cancelled {
+                                if aborted {
+                                    discard_partial(progress).await;
+                                }
                                 log::debug!("Download was cancelled: {}", download_id);
+                            } else if progress.status == DownloadStatus::Paused && exiting.load(Ordering::SeqCst) {
+                                // Stopped by shutdown: saved as interrupted, like
+                                // a download the app was closed in the middle of
+                                progress.status = DownloadStatus::Downloading;
+                                log::debug!("Download was stopped for exit: {}", download_id);
                             } else {
                                 log::debug!("Download was paused: {}", download_id);
                             }
                         }
                     }
+                    // Nothing is left to estimate once the task has ended
+                    progress.eta_seconds = None;
+                    // Start now covers one run: once it has finished, failed or
+                    // been stopped, the next one waits for the schedule again
+                    if matches!(
+                        progress.status,
+                        DownloadStatus::Completed | DownloadStatus::Failed | DownloadStatus::Cancelled | DownloadStatus::Paused
+                    ) {
+                        progress.start_now = false;
+                    }
 
                     // Emit final status event
                     if let Some(ref handle) = app_handle {
-                        let _ = handle.emit(DOWNLOAD_PROGRESS_EVENT, progress.clone());
+                        DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
                     }
 
                     // Save final status to database
@@ -488,33 +1322,304 @@ impl DownloadManager {
                 }
             }
 
-            // Update tray downloads count after final status transition
-            if let (Some(ref handle), Some(ref pool)) = (&app_handle, &db_pool) {
-                let active = total_active_downloads(&downloads, pool.as_ref()).await;
-                crate::tray::update_downloads_count(handle, active);
+            let batch_id = downloads.read().await.get(&download_id).and_then(|d| d.batch_id.clone());
+            if let Some(batch_id) = batch_id {
+                batch::notify_if_finished(&downloads, &batch_id, app_handle.as_ref(), db_pool.as_ref()).await;
             }
-        });
+
+            // A finished quality upgrade swaps its file in for the old download's
+            let replaces = downloads
+                .read()
+                .await
+                .get(&download_id)
+                .and_then(|d| d.replaces_download_id.clone());
+            if result.is_ok() {
+                if let Some(old_id) = &replaces {
+                    if let Err(e) = Self::finish_upgrade(&downloads, db_pool.as_ref(), app_handle.as_ref(), &download_id, old_id).await {
+                        log::error!("Failed to replace {} with upgraded download {}: {}", old_id, download_id, e);
+                    }
+                }
+            }
+
+            // Update tray downloads count after final status transition
+            if let (Some(ref handle), Some(ref pool)) = (&app_handle, &db_pool) {
+                let active = total_active_downloads(&downloads, pool.as_ref()).await;
+                crate::tray::update_downloads_count(handle, active);
+            }
+
+            // Record the finished file's checksum (download_checksums) and
+            // fetch its subtitles; an upgrade's file now belongs to the
+            // download it replaced
+            if let (Ok(_), Some(pool)) = (&result, &db_pool) {
+                let finished = {
+                    let map = downloads.read().await;
+                    replaces
+                        .and_then(|old_id| map.get(&old_id).cloned())
+                        .or_else(|| map.get(&download_id).cloned())
+                };
+                if let Some(progress) = finished.filter(|d| d.status == DownloadStatus::Completed) {
+                    verify::record_on_completion(pool, &progress).await;
+                    subtitles::fetch_on_completion(pool, &progress).await;
+                    source_history::record_on_completion(pool, &progress).await;
+                    history::record(pool, &progress.id, HistoryEvent::Completed).await;
+                    offline_ready::emit_if_grown(pool, app_handle.as_ref(), &progress.media_id).await;
+                }
+            }
+        });
 
         Ok(())
     }
 
+    /// Compare the size of a queued download (from a HEAD request) with the
+    /// free space on the volume it's saved to. A download that doesn't fit
+    /// while leaving the reserve (download_disk_reserve_mb) free is marked
+    /// failed and the user is warned; returns whether it may start. Downloads
+    /// of unknown size, or whose source isn't fetched yet, are let through.
+    /// The size becomes the download's total_bytes right away, so it shows
+    /// while the download waits for a slot.
+    async fn check_disk_space(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        download_id: &str,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+    ) -> bool {
+        let snapshot = downloads.read().await.get(download_id).cloned();
+        let Some(download) = snapshot.filter(|d| d.status == DownloadStatus::Queued && !d.url.is_empty()) else {
+            return true;
+        };
+
+        let Some(size) = size_estimate::estimate_sizes(std::slice::from_ref(&download.url), &download.headers).await[0].size
+        else {
+            return true;
+        };
+        if download.total_bytes == 0 {
+            let mut downloads_map = downloads.write().await;
+            if let Some(progress) = downloads_map.get_mut(download_id).filter(|p| p.total_bytes == 0) {
+                progress.total_bytes = size;
+                if let Some(handle) = app_handle {
+                    DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+                }
+                if let Some(pool) = db_pool {
+                    Self::save_progress_to_db(pool, progress).await.ok();
+                }
+            }
+        }
+        let needed = size.saturating_sub(download.downloaded_bytes);
+        let directory = std::path::Path::new(&download.file_path).parent().unwrap_or(std::path::Path::new("."));
+        let Some(available) = disk_space::available_space(directory) else {
+            return true;
+        };
+        let reserve = match db_pool {
+            Some(pool) => disk_space::reserve_bytes(pool).await,
+            None => disk_space::DEFAULT_RESERVE_MB * 1024 * 1024,
+        };
+        if disk_space::fits(needed, available, reserve) {
+            return true;
+        }
+
+        let locale = match db_pool {
+            Some(pool) => crate::locale::current_locale(pool).await,
+            None => crate::locale::Locale::default(),
+        };
+        let message = format!(
+            "Insufficient disk space: need {}, have {}",
+            crate::locale::format_bytes(needed, locale),
+            crate::locale::format_bytes(available, locale)
+        );
+        log::warn!("Not starting download {}: {}", download_id, message);
+
+        let batch_id = {
+            let mut downloads_map = downloads.write().await;
+            let Some(progress) = downloads_map.get_mut(download_id) else {
+                return false;
+            };
+            progress.status = DownloadStatus::Failed;
+            progress.error_message = Some(message.clone());
+            if let Some(handle) = app_handle {
+                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+            }
+            if let Some(pool) = db_pool {
+                Self::save_progress_to_db(pool, progress).await.ok();
+            }
+            progress.batch_id.clone()
+        };
+
+        if let Some(handle) = app_handle {
+            let title = download.display_title();
+            let _ = notifications::notify_low_disk_space(
+                handle,
+                db_pool.map(|p| p.as_ref()),
+                &title,
+                download.episode_number,
+                &message,
+                &download.media_id,
+            )
+            .await;
+        }
+        if let Some(batch_id) = batch_id {
+            batch::notify_if_finished(downloads, &batch_id, app_handle, db_pool).await;
+        }
+
+        false
+    }
+
+    /// Fill in the URL of a download queued without one by fetching its
+    /// source from the extension, returning whether it did. Does nothing for
+    /// downloads that have a URL.
+    async fn resolve_pending_source(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        download_id: &str,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+    ) -> Result<bool> {
+        let pending = {
+            let downloads_map = downloads.read().await;
+            downloads_map
+                .get(download_id)
+                .filter(|d| d.url.is_empty())
+                .map(|d| (d.source_extension_id.clone(), d.episode_id.clone()))
+        };
+        let Some((extension_id, episode_id)) = pending else {
+            return Ok(false);
+        };
+        let extension_id = extension_id.ok_or_else(|| anyhow::anyhow!("Download has no source URL"))?;
+        let app_handle = app_handle.ok_or_else(|| anyhow::anyhow!("Can't fetch the source without the app"))?;
+
+        let source = lazy_source::fetch_source(app_handle, &extension_id, &episode_id).await?;
+
+        let mut downloads_map = downloads.write().await;
+        let progress = downloads_map
+            .get_mut(download_id)
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+        lazy_source::apply_source(progress, source);
+        log::debug!("Resolved source for {}: {}", download_id, progress.filename);
+
+        if let Some(pool) = db_pool {
+            Self::save_progress_to_db(pool, progress).await.ok();
+        }
+        Ok(true)
+    }
+
+    /// Put a failed download back in the queue if it has retries left.
+    /// Returns how long to wait before the next attempt, or None when the
+    /// failure is final (or the download was paused or cancelled on purpose).
+    async fn schedule_retry(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        download_id: &str,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+        error: &anyhow::Error,
+    ) -> Option<std::time::Duration> {
+        let max_retries = match db_pool {
+            Some(pool) => load_max_retries_setting(pool).await,
+            None => DEFAULT_MAX_RETRIES,
+        };
+
+        let mut downloads_map = downloads.write().await;
+        let progress = downloads_map.get_mut(download_id)?;
+
+        // Paused, cancelled, or already re-queued by a manual resume
+        if matches!(progress.status, DownloadStatus::Cancelled | DownloadStatus::Paused | DownloadStatus::Queued) {
+            return None;
+        }
+        if progress.retry_count >= max_retries {
+            return None;
+        }
+        // Waiting doesn't make room under the quota
+        if matches!(error.downcast_ref::<QueueError>(), Some(QueueError::QuotaExceeded(_))) {
+            return None;
+        }
+
+        progress.retry_count += 1;
+        progress.status = DownloadStatus::Queued;
+        progress.speed = 0;
+        progress.eta_seconds = None;
+        progress.error_message = Some(error.to_string());
+        let delay = retry_delay(progress.retry_count);
+        log::warn!(
+            "Download {} failed ({}), retry {} of {} in {}s",
+            download_id, error, progress.retry_count, max_retries, delay.as_secs()
+        );
+
+        if let Some(handle) = app_handle {
+            DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+        }
+        if let Some(pool) = db_pool {
+            Self::save_progress_to_db(pool, progress).await.ok();
+        }
+
+        Some(delay)
+    }
+
     /// Helper to save progress to database (for use in spawned tasks)
     async fn save_progress_to_db(pool: &Arc<SqlitePool>, progress: &DownloadProgress) -> Result<()> {
-        let status_str = format!("{:?}", progress.status).to_lowercase();
+        Self::upsert_progress(progress).execute(pool.as_ref()).await?;
+        // Status changes move the active and failed badges
+        crate::badges::invalidate();
+        Ok(())
+    }
+
+    /// Save the downloads pause_all/resume_all changed together with the
+    /// downloads_paused setting, in one transaction
+    async fn save_global_pause(pool: &Arc<SqlitePool>, paused: bool, changed: &[DownloadProgress]) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        if paused {
+            sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, 'true')")
+                .bind(DOWNLOADS_PAUSED_SETTING)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query("DELETE FROM app_settings WHERE key = ?")
+                .bind(DOWNLOADS_PAUSED_SETTING)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for progress in changed {
+            Self::upsert_progress(progress).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        crate::badges::invalidate();
+        Ok(())
+    }
+
+    /// Insert-or-update of a download's row
+    fn upsert_progress(progress: &DownloadProgress) -> sqlx::query::Query<'_, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'_>> {
+        let status_str = progress.status.as_db_str();
         sqlx::query(
             r#"
             INSERT INTO downloads (
                 id, media_id, episode_id, episode_number, filename, url, file_path,
                 total_bytes, downloaded_bytes, percentage, speed, status, error_message,
+                quality, source_label, replaces_download_id, file_state, batch_id,
+                source_extension_id, media_title, scheduled_start, start_now, speed_limit, headers,
                 created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
             ON CONFLICT(id) DO UPDATE SET
+                filename = ?,
+                url = ?,
+                headers = ?,
+                quality = ?,
+                source_label = ?,
+                file_path = ?,
                 downloaded_bytes = ?,
                 percentage = ?,
                 speed = ?,
                 status = ?,
                 error_message = ?,
+                file_state = ?,
+                total_bytes = ?,
+                scheduled_start = ?,
+                start_now = ?,
+                speed_limit = ?,
+                -- Set when a lazily resolved placeholder gets its source, or
+                -- when a download moves to another batch
+                episode_id = excluded.episode_id,
+                batch_id = excluded.batch_id,
+                source_extension_id = excluded.source_extension_id,
+                media_title = excluded.media_title,
+                -- A checksum only describes the file of a finished download
+                sha256 = CASE WHEN excluded.status = 'completed' THEN sha256 ELSE NULL END,
                 updated_at = CURRENT_TIMESTAMP
             "#
         )
@@ -529,17 +1634,36 @@ impl DownloadManager {
         .bind(progress.downloaded_bytes as i64)
         .bind(progress.percentage)
         .bind(progress.speed as i64)
-        .bind(&status_str)
+        .bind(status_str)
         .bind(&progress.error_message)
+        .bind(&progress.quality)
+        .bind(&progress.source_label)
+        .bind(&progress.replaces_download_id)
+        .bind(progress.file_state.as_db_str())
+        .bind(&progress.batch_id)
+        .bind(&progress.source_extension_id)
+        .bind(&progress.media_title)
+        .bind(progress.scheduled_start)
+        .bind(progress.start_now)
+        .bind(progress.speed_limit.map(|l| l as i64))
+        .bind(headers_to_db(&progress.headers))
         // For UPDATE
+        .bind(&progress.filename)
+        .bind(&progress.url)
+        .bind(headers_to_db(&progress.headers))
+        .bind(&progress.quality)
+        .bind(&progress.source_label)
+        .bind(&progress.file_path)
         .bind(progress.downloaded_bytes as i64)
         .bind(progress.percentage)
         .bind(progress.speed as i64)
-        .bind(&status_str)
+        .bind(status_str)
         .bind(&progress.error_message)
-        .execute(pool.as_ref())
-        .await?;
-        Ok(())
+        .bind(progress.file_state.as_db_str())
+        .bind(progress.total_bytes as i64)
+        .bind(progress.scheduled_start)
+        .bind(progress.start_now)
+        .bind(progress.speed_limit.map(|l| l as i64))
     }
 
     /// Perform the actual download
@@ -548,9 +1672,10 @@ impl DownloadManager {
         downloads: Arc<RwLock<HashMap<String, DownloadProgress>>>,
         db_pool: Option<Arc<SqlitePool>>,
         app_handle: Option<AppHandle>,
+        cancel: CancellationToken,
     ) -> Result<()> {
         // Get download info, check if cancelled, and get resume offset
-        let (url, file_path, resume_from, existing_total) = {
+        let (url, headers, file_path, resume_from, existing_total) = {
             let downloads_map = downloads.read().await;
             let progress = downloads_map
                 .get(&download_id)
@@ -563,6 +1688,7 @@ impl DownloadManager {
 
             (
                 progress.url.clone(),
+                progress.headers.clone(),
                 progress.file_path.clone(),
                 progress.downloaded_bytes,
                 progress.total_bytes,
@@ -583,17 +1709,50 @@ impl DownloadManager {
         };
 
         // Make HTTP request with appropriate timeouts for large files
+        // Every request of the download (ranges, HLS segments) goes through
+        // this client, so they all carry the source's headers
         let client = reqwest::Client::builder()
             .connect_timeout(std::time::Duration::from_secs(30))
-            // No read timeout - large files can take a long time to download
-            // Progress tracking handles stalls via cancellation
+            // No read timeout - large files can take a long time to download.
+            // The chunk loop gives up on a connection idle for STALL_TIMEOUT.
+            .default_headers(download_headers(&headers))
             .build()
             .context("Failed to create HTTP client")?;
 
-        let mut request = client
-            .get(&url)
-            .header("User-Agent", "Mozilla/5.0")
-            .header("Referer", "https://allmanga.to");
+        // A segmented download picks up each of its ranges where it stopped
+        if let Some(state) = segmented::resume_state(&file_path).await {
+            let reporter = hls::Reporter {
+                download_id: &download_id,
+                downloads: &downloads,
+                db_pool: db_pool.as_ref(),
+                app_handle: app_handle.as_ref(),
+                cancel: &cancel,
+            };
+            return segmented::download(&client, &url, &file_path, state, reporter).await;
+        }
+
+        let send = |request: reqwest::RequestBuilder| {
+            let (downloads, download_id, cancel) = (&downloads, &download_id, &cancel);
+            async move {
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => Err(interrupted(downloads, download_id).await),
+                    // A server that accepts the connection but never answers
+                    // stalls the download like one that stops sending data
+                    response = tokio::time::timeout(STALL_TIMEOUT, send_with_retry(RetryPolicy::BACKGROUND, request)) => {
+                        match response {
+                            Ok(response) => response.context("Failed to initiate download"),
+                            Err(_) => Err(anyhow::anyhow!(
+                                "Failed to initiate download: connection timed out after {} seconds without a response",
+                                STALL_TIMEOUT.as_secs()
+                            )),
+                        }
+                    }
+                }
+            }
+        };
+
+        let mut request = client.get(&url);
 
         // Add Range header for resume
         if resume_offset > 0 {
@@ -601,13 +1760,28 @@ impl DownloadManager {
             log::debug!("Resuming download from byte {}", resume_offset);
         }
 
-        let response = request
-            .send()
-            .await
-            .context("Failed to initiate download")?;
+        let mut response = send(request).await?;
+
+        // The partial file doesn't fit the source any more (it changed, or
+        // the offset is past its end): start over instead of failing
+        if resume_offset > 0 && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            log::warn!("Server can't resume {} at byte {}, starting over", download_id, resume_offset);
+            response = send(client.get(&url)).await?;
+        }
+
+        // An error page isn't the episode. The status stays in the message,
+        // which is what retry_failed groups failures by.
+        if !response.status().is_success() {
+            anyhow::bail!("Server returned HTTP {}", response.status().as_u16());
+        }
 
         // Check response status - 206 Partial Content for resume, 200 for fresh start
         let is_resume = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
 
         // Get total bytes from Content-Length or Content-Range
         let total_bytes = if is_resume {
@@ -623,6 +1797,49 @@ impl DownloadManager {
         } else {
             response.content_length().unwrap_or(0)
         };
+        let splittable = !is_resume && segmented::supports_ranges(&response, total_bytes);
+
+        use futures_util::StreamExt;
+
+        // An HLS playlist (by content type, or by its #EXTM3U header when the
+        // server doesn't say) is downloaded segment by segment instead
+        let mut stream = Box::pin(response.bytes_stream().peekable());
+        let first_bytes = match stream.as_mut().peek().await {
+            Some(Ok(chunk)) => chunk.clone(),
+            _ => Default::default(),
+        };
+        if !is_resume && hls::is_playlist(content_type.as_deref(), &first_bytes) {
+            let mut playlist = Vec::new();
+            while let Some(chunk) = stream.next().await {
+                playlist.extend_from_slice(&chunk.context("Failed to read playlist")?);
+            }
+            let reporter = hls::Reporter {
+                download_id: &download_id,
+                downloads: &downloads,
+                db_pool: db_pool.as_ref(),
+                app_handle: app_handle.as_ref(),
+                cancel: &cancel,
+            };
+            return hls::download(&client, &url, &String::from_utf8_lossy(&playlist), &file_path, reporter).await;
+        }
+
+        // Large files go over several connections when that's turned on
+        // and the server takes range requests
+        if splittable {
+            let connections = segmented::connections(db_pool.as_ref()).await;
+            if connections > 1 {
+                drop(stream);
+                let reporter = hls::Reporter {
+                    download_id: &download_id,
+                    downloads: &downloads,
+                    db_pool: db_pool.as_ref(),
+                    app_handle: app_handle.as_ref(),
+                    cancel: &cancel,
+                };
+                let state = segmented::RangeState::split(total_bytes, connections);
+                return segmented::download(&client, &url, &file_path, state, reporter).await;
+            }
+        }
 
         // Update total bytes
         {
@@ -658,37 +1875,42 @@ impl DownloadManager {
         let is_obfuscated = file_path.ends_with(".otaku");
 
         // Download in chunks
-        let mut stream = response.bytes_stream();
         let mut downloaded: u64 = if is_resume { resume_offset } else { 0 };
-        let start_time = std::time::Instant::now();
-        let session_downloaded: u64 = 0; // Track bytes downloaded this session for speed calc
+        let mut pacer = throttle::DownloadPacer::new();
+        let mut speed_limit = downloads.read().await.get(&download_id).and_then(|p| p.speed_limit);
+        let mut rolling_speed = speed::RollingSpeed::new(std::time::Instant::now(), downloaded);
         let mut last_db_save: u64 = downloaded;
         let mut last_event_time = std::time::Instant::now();
         const DB_SAVE_INTERVAL: u64 = 5 * 1024 * 1024; // Save to DB every 5MB
         const EVENT_THROTTLE_MS: u128 = 500; // Emit events at most every 500ms
 
-        use futures_util::StreamExt;
-
-        while let Some(chunk) = stream.next().await {
-            // Check if cancelled or paused
-            {
-                let downloads_map = downloads.read().await;
-                if let Some(progress) = downloads_map.get(&download_id) {
-                    if progress.status == DownloadStatus::Cancelled {
-                        // Delete partial file
-                        tokio::fs::remove_file(&file_path).await.ok();
-                        return Err(anyhow::anyhow!("Download cancelled"));
-                    }
-                    if progress.status == DownloadStatus::Paused {
-                        // Flush and return - don't delete file, keep progress
-                        file.flush().await.ok();
-                        log::debug!("Download paused at {} bytes", downloaded);
-                        return Err(anyhow::anyhow!("Download paused"));
-                    }
+        loop {
+            // Cancel and pause interrupt the wait for the next chunk, so a
+            // connection that went quiet doesn't hold them up
+            let next = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    // Both keep the file and progress so the download can be
+                    // resumed; an abort deletes the file after this returns
+                    file.flush().await.ok();
+                    log::debug!("Download stopped at {} bytes", downloaded);
+                    return Err(interrupted(&downloads, &download_id).await);
                 }
-            }
+                next = tokio::time::timeout(STALL_TIMEOUT, stream.next()) => next,
+            };
+            let chunk = match next {
+                Ok(Some(chunk)) => chunk.context("Failed to read chunk")?,
+                Ok(None) => break,
+                Err(_) => {
+                    file.flush().await.ok();
+                    anyhow::bail!("Failed to read chunk: connection timed out after {} seconds without data", STALL_TIMEOUT.as_secs());
+                }
+            };
 
-            let chunk = chunk.context("Failed to read chunk")?;
+            // Stay under the global speed limit and this download's own; the
+            // speed below then shows the throttled rate
+            throttle::throttle(chunk.len() as u64).await;
+            pacer.pace(chunk.len() as u64, speed_limit).await;
 
             // XOR-obfuscate the chunk before writing to disk
             if is_obfuscated {
@@ -699,15 +1921,10 @@ impl DownloadManager {
                 file.write_all(&chunk).await.context("Failed to write chunk")?;
             }
             downloaded += chunk.len() as u64;
+            stats::record_bytes(chunk.len() as u64);
 
-            // Calculate speed based on this session's download
-            let elapsed = start_time.elapsed().as_secs();
-            let session_bytes = downloaded - (if is_resume { resume_offset } else { 0 });
-            let speed = if elapsed > 0 {
-                session_bytes / elapsed
-            } else {
-                session_downloaded
-            };
+            // Speed over the last few seconds, so the ETA follows the current rate
+            let speed = rolling_speed.record(std::time::Instant::now(), downloaded);
 
             // Update progress
             let should_save_db = downloaded - last_db_save >= DB_SAVE_INTERVAL;
@@ -715,8 +1932,12 @@ impl DownloadManager {
             {
                 let mut downloads_map = downloads.write().await;
                 if let Some(progress) = downloads_map.get_mut(&download_id) {
+                    // Applies from the next chunk on
+                    speed_limit = progress.speed_limit;
                     progress.downloaded_bytes = downloaded;
                     progress.speed = speed;
+                    progress.eta_seconds = speed::eta_seconds(total_bytes, downloaded, speed);
+                    progress.retry_count = 0;
                     if total_bytes > 0 {
                         progress.percentage = (downloaded as f32 / total_bytes as f32) * 100.0;
                     }
@@ -724,7 +1945,7 @@ impl DownloadManager {
                     // Emit progress event (throttled)
                     if should_emit_event {
                         if let Some(ref handle) = app_handle {
-                            let _ = handle.emit(DOWNLOAD_PROGRESS_EVENT, progress.clone());
+                            DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
                         }
                         last_event_time = std::time::Instant::now();
                     }
@@ -757,20 +1978,71 @@ impl DownloadManager {
         downloads.values().cloned().collect()
     }
 
-    /// Cancel a download
+    /// Episode and chapter downloads currently transferring
+    pub async fn active_download_count(&self) -> usize {
+        match &self.db_pool {
+            Some(pool) => total_active_downloads(&self.downloads, pool.as_ref()).await,
+            None => {
+                let downloads = self.downloads.read().await;
+                downloads.values().filter(|d| d.status == DownloadStatus::Downloading).count()
+            }
+        }
+    }
+
+    /// Stop the running transfer of a download right away, even while it
+    /// waits on an idle connection. Its status must already say why.
+    fn interrupt(&self, download_id: &str) {
+        if let Some(token) = self.cancel_tokens.lock().unwrap().remove(download_id) {
+            token.cancel();
+        }
+    }
+
+    /// Cancel a download. The transfer stops but the bytes fetched so far are
+    /// kept, so `resume_download` can continue it later.
     pub async fn cancel_download(&self, download_id: &str) -> Result<()> {
-        {
+        self.stop_download(download_id, false).await
+    }
+
+    /// Abort a download. With `delete_partial` the bytes fetched so far are
+    /// deleted as well; without it this is the same as cancelling.
+    pub async fn abort_download(&self, download_id: &str, delete_partial: bool) -> Result<()> {
+        self.stop_download(download_id, delete_partial).await
+    }
+
+    async fn stop_download(&self, download_id: &str, delete_partial: bool) -> Result<()> {
+        let batch_id = {
             let mut downloads = self.downloads.write().await;
             if let Some(progress) = downloads.get_mut(download_id) {
+                // A finished download's file is never partial
+                let finished = matches!(progress.status, DownloadStatus::Completed | DownloadStatus::Offline);
+                if delete_partial && !finished {
+                    if progress.status == DownloadStatus::Downloading {
+                        // Still writing: its task deletes the file once it stops
+                        self.pending_aborts.lock().unwrap().insert(download_id.to_string());
+                    } else {
+                        discard_partial(progress).await;
+                    }
+                }
                 progress.status = DownloadStatus::Cancelled;
-                log::debug!("Cancelled download: {}", download_id);
+                progress.speed = 0;
+                progress.eta_seconds = None;
+                self.interrupt(download_id);
+                log::debug!("Cancelled download: {} (partial {})", download_id, if delete_partial { "deleted" } else { "kept" });
 
                 // Emit event
                 self.emit_progress(progress);
 
                 // Save to database
                 self.save_to_database(progress).await.ok();
+                progress.batch_id.clone()
+            } else {
+                None
             }
+        };
+
+        // Cancelling a queued batch member may leave nothing else running
+        if let Some(batch_id) = batch_id {
+            batch::notify_if_finished(&self.downloads, &batch_id, self.app_handle.as_ref(), self.db_pool.as_ref()).await;
         }
 
         // Update tray count after cancellation
@@ -784,13 +2056,23 @@ impl DownloadManager {
 
     /// Pause a download
     pub async fn pause_download(&self, download_id: &str) -> Result<()> {
-        {
+        self.pause_active(download_id).await;
+        Ok(())
+    }
+
+    /// Pause a download if it's downloading or queued, returning whether it
+    /// was
+    async fn pause_active(&self, download_id: &str) -> bool {
+        let paused = {
             let mut downloads = self.downloads.write().await;
-            if let Some(progress) = downloads.get_mut(download_id) {
+            match downloads.get_mut(download_id) {
                 // Only pause if currently downloading or queued
-                if progress.status == DownloadStatus::Downloading || progress.status == DownloadStatus::Queued {
+                Some(progress) if progress.status == DownloadStatus::Downloading || progress.status == DownloadStatus::Queued => {
                     progress.status = DownloadStatus::Paused;
                     progress.speed = 0; // Reset speed since we're paused
+                    progress.eta_seconds = None;
+                    progress.paused_globally = false;
+                    self.interrupt(download_id);
                     log::debug!("Paused download: {} at {} bytes", download_id, progress.downloaded_bytes);
 
                     // Emit event
@@ -798,9 +2080,11 @@ impl DownloadManager {
 
                     // Save to database
                     self.save_to_database(progress).await.ok();
+                    true
                 }
+                _ => false,
             }
-        }
+        };
 
         // Update tray count after pause (Downloading → Paused decreases active count)
         if let (Some(ref handle), Some(ref pool)) = (&self.app_handle, &self.db_pool) {
@@ -808,10 +2092,10 @@ impl DownloadManager {
             crate::tray::update_downloads_count(handle, active);
         }
 
-        Ok(())
+        paused
     }
 
-    /// Resume a paused download
+    /// Resume a paused, cancelled or failed download
     pub async fn resume_download(&self, download_id: &str) -> Result<()> {
         // Get the download info
         let download_info = {
@@ -820,14 +2104,28 @@ impl DownloadManager {
         };
 
         if let Some(progress) = download_info {
-            // Only resume if paused or failed
-            if progress.status == DownloadStatus::Paused || progress.status == DownloadStatus::Failed {
+            // A completed download whose file went missing is downloaded again from scratch
+            let redownload = progress.status == DownloadStatus::Completed
+                && progress.file_state == FileState::Missing;
+
+            // Cancelled downloads pick up from the bytes cancelling kept
+            let resumable = matches!(
+                progress.status,
+                DownloadStatus::Paused | DownloadStatus::Cancelled | DownloadStatus::Failed
+            );
+            if resumable || redownload {
                 // Update status to queued
                 {
                     let mut downloads = self.downloads.write().await;
                     if let Some(p) = downloads.get_mut(download_id) {
                         p.status = DownloadStatus::Queued;
                         p.error_message = None; // Clear any previous error
+                        p.retry_count = 0;
+                        if redownload {
+                            p.downloaded_bytes = 0;
+                            p.percentage = 0.0;
+                            p.file_state = FileState::Present;
+                        }
                         self.emit_progress(p);
                         self.save_to_database(p).await.ok();
                     }
@@ -842,18 +2140,295 @@ impl DownloadManager {
         Ok(())
     }
 
+    /// Resume downloads the last run was closed in the middle of (see
+    /// load_from_database), returning how many were resumed. Ones paused or
+    /// removed since are left alone; the rest queue up behind the
+    /// concurrency limit like any resumed download. One that fails to
+    /// resume is logged and stays paused without holding up the others.
+    pub async fn resume_interrupted(&self, download_ids: &[String]) -> usize {
+        let mut interrupted: Vec<(String, i32, String)> = {
+            let downloads = self.downloads.read().await;
+            download_ids
+                .iter()
+                .filter_map(|id| downloads.get(id))
+                .filter(|d| d.status == DownloadStatus::Paused)
+                .map(|d| (d.media_id.clone(), d.episode_number, d.id.clone()))
+                .collect()
+        };
+        interrupted.sort();
+
+        let mut resumed = 0;
+        for (_, _, download_id) in &interrupted {
+            match self.resume_download(download_id).await {
+                Ok(()) => resumed += 1,
+                Err(e) => log::warn!("Failed to resume interrupted download {}: {}", download_id, e),
+            }
+        }
+
+        log::info!("Resumed {} of {} interrupted download(s)", resumed, interrupted.len());
+        resumed
+    }
+
+    /// Pause every downloading or queued download and keep downloads queued
+    /// from now on from starting until resume_all, returning how many were
+    /// paused. The paused downloads and the global pause are saved in one
+    /// transaction; nothing changes if that fails.
+    pub async fn pause_all(&self) -> Result<usize> {
+        let paused: Vec<DownloadProgress> = {
+            let mut downloads = self.downloads.write().await;
+            let paused: Vec<DownloadProgress> = downloads
+                .values()
+                .filter(|d| matches!(d.status, DownloadStatus::Downloading | DownloadStatus::Queued))
+                .map(|d| DownloadProgress {
+                    status: DownloadStatus::Paused,
+                    speed: 0,
+                    eta_seconds: None,
+                    paused_globally: false,
+                    ..d.clone()
+                })
+                .collect();
+
+            if let Some(pool) = &self.db_pool {
+                Self::save_global_pause(pool, true, &paused).await?;
+            }
+            // Set while holding the lock, so a task deciding whether to start
+            // sees either the flag or its download paused
+            self.downloads_paused.store(true, Ordering::SeqCst);
+            for progress in &paused {
+                downloads.insert(progress.id.clone(), progress.clone());
+                self.interrupt(&progress.id);
+            }
+            paused
+        };
+
+        for progress in &paused {
+            self.emit_progress(progress);
+        }
+        log::info!("Paused {} download(s); new downloads wait until resumed", paused.len());
+
+        if let (Some(ref handle), Some(ref pool)) = (&self.app_handle, &self.db_pool) {
+            let active = total_active_downloads(&self.downloads, pool.as_ref()).await;
+            crate::tray::update_downloads_count(handle, active);
+        }
+
+        Ok(paused.len())
+    }
+
+    /// Lift the global pause and resume every paused download, returning how
+    /// many were resumed. They're all queued, along with the downloads queued
+    /// during the pause; only max_concurrent of them start downloading right
+    /// away. Saved in one transaction like pause_all.
+    pub async fn resume_all(&self) -> Result<usize> {
+        let (mut resumed, waiting) = {
+            let mut downloads = self.downloads.write().await;
+            let resumed: Vec<DownloadProgress> = downloads
+                .values()
+                .filter(|d| d.status == DownloadStatus::Paused)
+                .map(|d| DownloadProgress {
+                    status: DownloadStatus::Queued,
+                    error_message: None,
+                    retry_count: 0,
+                    paused_globally: false,
+                    ..d.clone()
+                })
+                .collect();
+            let waiting: Vec<DownloadProgress> = downloads
+                .values()
+                .filter(|d| d.paused_globally && d.status == DownloadStatus::Queued)
+                .map(|d| DownloadProgress { paused_globally: false, ..d.clone() })
+                .collect();
+
+            if let Some(pool) = &self.db_pool {
+                Self::save_global_pause(pool, false, &resumed).await?;
+            }
+            self.downloads_paused.store(false, Ordering::SeqCst);
+            for progress in resumed.iter().chain(&waiting) {
+                downloads.insert(progress.id.clone(), progress.clone());
+            }
+            (resumed, waiting)
+        };
+        // Episodes of a series go back into the queue in order
+        resumed.sort_by(|a, b| (&a.media_id, a.episode_number, &a.id).cmp(&(&b.media_id, b.episode_number, &b.id)));
+
+        for progress in resumed.iter().chain(&waiting) {
+            self.emit_progress(progress);
+        }
+        for progress in &resumed {
+            self.start_download_task(progress.id.clone()).await?;
+        }
+        // Downloads queued during the pause were parked; this starts the ones
+        // whose own schedule doesn't hold them back
+        self.start_scheduled_downloads().await;
+
+        log::info!("Resumed {} download(s), {} queued while paused", resumed.len(), waiting.len());
+        Ok(resumed.len())
+    }
+
+    /// Stop running downloads before the app exits, so their progress
+    /// matches their files. Each is paused, which makes its task flush the
+    /// file and stop; once they have (or SHUTDOWN_TIMEOUT passed) the bytes
+    /// on disk are saved as the download's progress. The rows stay
+    /// 'downloading', which the next launch reads as interrupted (see
+    /// load_from_database), and the tasks save them the same way. Returns
+    /// how many downloads were stopped.
+    pub async fn shutdown(&self) -> usize {
+        // Nothing queued starts from here on
+        self.max_concurrent.store(0, Ordering::SeqCst);
+        self.exiting.store(true, Ordering::SeqCst);
+
+        let running: Vec<String> = {
+            let mut downloads = self.downloads.write().await;
+            downloads
+                .values_mut()
+                .filter(|d| d.status == DownloadStatus::Downloading)
+                .map(|d| {
+                    d.status = DownloadStatus::Paused;
+                    d.speed = 0;
+                    d.eta_seconds = None;
+                    d.id.clone()
+                })
+                .collect()
+        };
+        for id in &running {
+            self.interrupt(id);
+        }
+        if running.is_empty() {
+            return 0;
+        }
+
+        let deadline = tokio::time::Instant::now() + SHUTDOWN_TIMEOUT;
+        while *self.active_downloads.lock().await > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                log::warn!("Downloads still running at exit; saving their progress as it is");
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+
+        let downloads = self.downloads.read().await;
+        for id in &running {
+            let Some(progress) = downloads.get(id) else {
+                continue;
+            };
+            let mut last = DownloadProgress {
+                status: DownloadStatus::Downloading,
+                ..progress.clone()
+            };
+            if let Some(bytes) = bytes_on_disk(&last.file_path) {
+                last.downloaded_bytes = bytes;
+                if last.total_bytes > 0 {
+                    last.percentage = (bytes as f64 / last.total_bytes as f64 * 100.0).min(100.0) as f32;
+                }
+            }
+            if let Some(pool) = &self.db_pool {
+                if let Err(e) = Self::save_progress_to_db(pool, &last).await {
+                    log::error!("Failed to save progress of {} at exit: {}", id, e);
+                }
+            }
+            log::debug!("Stopped {} at {} bytes for exit", id, last.downloaded_bytes);
+        }
+
+        log::info!("Stopped {} running download(s) for exit", running.len());
+        running.len()
+    }
+
+    /// Retry the failed members of a download batch, returning how many were
+    /// queued again. The batch gets a new summary once they finish.
+    pub async fn retry_batch(&self, batch_id: &str) -> Result<usize> {
+        let failed: Vec<String> = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .filter(|d| d.batch_id.as_deref() == Some(batch_id) && d.status == DownloadStatus::Failed)
+            .map(|d| d.id.clone())
+            .collect();
+
+        if !failed.is_empty() {
+            batch::reset(batch_id);
+        }
+        for download_id in &failed {
+            self.resume_download(download_id).await?;
+        }
+
+        log::debug!("Retrying {} failed download(s) of batch {}", failed.len(), batch_id);
+        Ok(failed.len())
+    }
+
+    /// Mark a batch as fully queued. Its summary is sent now if every member
+    /// has finished already, otherwise when the last one does.
+    pub async fn seal_batch(&self, batch_id: &str) {
+        if batch::seal(batch_id) {
+            batch::notify_if_finished(&self.downloads, batch_id, self.app_handle.as_ref(), self.db_pool.as_ref()).await;
+        }
+    }
+
+    /// Cancel the members of a batch that haven't started yet, returning how
+    /// many were cancelled. Members already downloading keep going.
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<usize> {
+        let mut cancelled = 0;
+        {
+            let mut downloads = self.downloads.write().await;
+            for progress in downloads.values_mut() {
+                if progress.batch_id.as_deref() != Some(batch_id) || progress.status != DownloadStatus::Queued {
+                    continue;
+                }
+                progress.status = DownloadStatus::Cancelled;
+                self.emit_progress(progress);
+                self.save_to_database(progress).await.ok();
+                cancelled += 1;
+            }
+        }
+
+        batch::notify_if_finished(&self.downloads, batch_id, self.app_handle.as_ref(), self.db_pool.as_ref()).await;
+
+        if let (Some(ref handle), Some(ref pool)) = (&self.app_handle, &self.db_pool) {
+            let active = total_active_downloads(&self.downloads, pool.as_ref()).await;
+            crate::tray::update_downloads_count(handle, active);
+        }
+
+        log::debug!("Cancelled {} queued download(s) of batch {}", cancelled, batch_id);
+        Ok(cancelled)
+    }
+
+    /// Progress of every batch of a media item
+    pub async fn batch_progress(&self, media_id: &str) -> Vec<batch::BatchProgress> {
+        batch::batch_progress(media_id, &*self.downloads.read().await)
+    }
+
+    /// Split the episodes of a new batch of `media_id` into those to queue
+    /// and those skipped for being downloaded already
+    pub async fn select_batch_episodes(
+        &self,
+        media_id: &str,
+        episodes: &[(String, i32)],
+        overwrite: bool,
+    ) -> batch::BatchSelection {
+        batch::select_episodes(media_id, episodes, &*self.downloads.read().await, overwrite)
+    }
+
     /// Remove completed/failed download from list
     pub async fn remove_download(&self, download_id: &str) -> Result<()> {
-        // Delete from database
+        self.forget_download(download_id, HistoryEvent::Removed).await
+    }
+
+    /// Take a download off the list and out of the database, leaving a
+    /// history row
+    async fn forget_download(&self, download_id: &str, event: HistoryEvent) -> Result<()> {
+        if let Some(pool) = &self.db_pool {
+            history::record(pool, download_id, event).await;
+        }
         self.delete_from_database(download_id).await.ok();
 
         let mut downloads = self.downloads.write().await;
         downloads.remove(download_id);
+        // A transfer still running would keep writing a file nobody tracks
+        self.interrupt(download_id);
         log::debug!("Removed download from list: {}", download_id);
         Ok(())
     }
 
-    /// Check if an episode is downloaded and completed
+    /// Check if an episode is downloaded and its file is available
     pub async fn is_episode_downloaded(&self, media_id: &str, episode_number: i32) -> bool {
         let downloads = self.downloads.read().await;
 
@@ -862,6 +2437,7 @@ impl DownloadManager {
             d.media_id == media_id
                 && d.episode_number == episode_number
                 && d.status == DownloadStatus::Completed
+                && d.file_state.is_playable()
         })
     }
 
@@ -874,108 +2450,83 @@ impl DownloadManager {
                 d.media_id == media_id
                     && d.episode_number == episode_number
                     && d.status == DownloadStatus::Completed
+                    && d.file_state.is_playable()
             })
             .map(|d| d.file_path.clone())
     }
 
-    /// Get total storage used by downloads in bytes
+    /// Get total local storage used by downloads in bytes (archived files excluded)
     pub async fn get_total_storage_used(&self) -> u64 {
         let downloads = self.downloads.read().await;
 
         downloads.values()
-            .filter(|d| d.status == DownloadStatus::Completed)
+            .filter(|d| d.status == DownloadStatus::Completed && d.file_state == FileState::Present)
             .map(|d| d.total_bytes)
             .sum()
     }
 
-    /// Clear completed downloads from list (doesn't delete files)
-    pub async fn clear_completed(&self) -> Result<()> {
-        // Get IDs of completed downloads
-        let completed_ids: Vec<String> = {
-            let downloads = self.downloads.read().await;
-            downloads
-                .iter()
-                .filter(|(_, d)| d.status == DownloadStatus::Completed)
-                .map(|(id, _)| id.clone())
-                .collect()
-        };
+    /// Remove every download with `status` from the list and the database
+    /// with one query, returning how many left the list. They're moved into
+    /// the download history and their files stay where they are, except the
+    /// partial files of cancelled downloads, which are deleted. Only finished
+    /// statuses can be cleared; offline downloads are stored as completed and
+    /// go with them.
+    pub async fn clear_downloads_by_status(&self, status: DownloadStatus) -> Result<usize> {
+        if !matches!(status, DownloadStatus::Completed | DownloadStatus::Failed | DownloadStatus::Cancelled) {
+            anyhow::bail!("Only completed, failed or cancelled downloads can be cleared");
+        }
+        let stored = status.as_db_str();
 
-        // Delete from database
         if let Some(pool) = &self.db_pool {
-            for id in &completed_ids {
-                sqlx::query("DELETE FROM downloads WHERE id = ?")
-                    .bind(id)
-                    .execute(pool.as_ref())
-                    .await
-                    .ok();
-            }
+            let mut tx = pool.begin().await?;
+            history::record_status(stored, HistoryEvent::Cleared).execute(&mut *tx).await?;
+            sqlx::query("DELETE FROM downloads WHERE status = ?")
+                .bind(stored)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
         }
 
-        // Remove from memory
-        let mut downloads = self.downloads.write().await;
-        downloads.retain(|_, d| d.status != DownloadStatus::Completed);
-        log::debug!("Cleared {} completed downloads from list", completed_ids.len());
-        Ok(())
-    }
-
-    /// Clear failed downloads from list
-    pub async fn clear_failed(&self) -> Result<()> {
-        // Get IDs of failed downloads
-        let failed_ids: Vec<String> = {
-            let downloads = self.downloads.read().await;
-            downloads
-                .iter()
-                .filter(|(_, d)| d.status == DownloadStatus::Failed)
-                .map(|(id, _)| id.clone())
-                .collect()
+        let removed: Vec<DownloadProgress> = {
+            let mut downloads = self.downloads.write().await;
+            let ids: Vec<String> = downloads
+                .values()
+                .filter(|d| d.status.as_db_str() == stored)
+                .map(|d| d.id.clone())
+                .collect();
+            ids.iter().filter_map(|id| downloads.remove(id)).collect()
         };
-
-        // Delete from database
-        if let Some(pool) = &self.db_pool {
-            for id in &failed_ids {
-                sqlx::query("DELETE FROM downloads WHERE id = ?")
-                    .bind(id)
-                    .execute(pool.as_ref())
-                    .await
-                    .ok();
+        let cleared = removed.len();
+
+        // Cancelled downloads keep what they fetched for a resume; with the
+        // row gone nothing would track it. A download cancelled once all of
+        // it was in has no partial file.
+        if status == DownloadStatus::Cancelled {
+            for mut progress in removed {
+                let finished = progress.total_bytes > 0 && progress.downloaded_bytes >= progress.total_bytes;
+                if !finished {
+                    discard_partial(&mut progress).await;
+                }
             }
         }
-
-        // Remove from memory
-        let mut downloads = self.downloads.write().await;
-        downloads.retain(|_, d| d.status != DownloadStatus::Failed);
-        log::debug!("Cleared {} failed downloads from list", failed_ids.len());
-        Ok(())
+        crate::badges::invalidate();
+        log::debug!("Cleared {} {} downloads from list", cleared, stored);
+        Ok(cleared)
     }
 
-    /// Clear cancelled downloads from list
-    pub async fn clear_cancelled(&self) -> Result<()> {
-        // Get IDs of cancelled downloads
-        let cancelled_ids: Vec<String> = {
-            let downloads = self.downloads.read().await;
-            downloads
-                .iter()
-                .filter(|(_, d)| d.status == DownloadStatus::Cancelled)
-                .map(|(id, _)| id.clone())
-                .collect()
-        };
+    /// Clear completed downloads from list (doesn't delete files)
+    pub async fn clear_completed(&self) -> Result<()> {
+        self.clear_downloads_by_status(DownloadStatus::Completed).await.map(|_| ())
+    }
 
-        // Delete from database
-        if let Some(pool) = &self.db_pool {
-            for id in &cancelled_ids {
-                sqlx::query("DELETE FROM downloads WHERE id = ?")
-                    .bind(id)
-                    .execute(pool.as_ref())
-                    .await
-                    .ok();
-            }
-        }
+    /// Clear failed downloads from list
+    pub async fn clear_failed(&self) -> Result<()> {
+        self.clear_downloads_by_status(DownloadStatus::Failed).await.map(|_| ())
+    }
 
-        // Remove from memory
-        let mut downloads = self.downloads.write().await;
-        downloads.retain(|_, d| d.status != DownloadStatus::Cancelled);
-        log::debug!("Cleared {} cancelled downloads from list", cancelled_ids.len());
-        Ok(())
+    /// Clear cancelled downloads from list, deleting their partial files
+    pub async fn clear_cancelled(&self) -> Result<()> {
+        self.clear_downloads_by_status(DownloadStatus::Cancelled).await.map(|_| ())
     }
 
     /// Delete a downloaded file and remove from list
@@ -998,10 +2549,17 @@ impl DownloadManager {
                     return Err(e).with_context(|| format!("Failed to delete file: {}", path));
                 }
             }
+
+            // Segments of an unfinished HLS download, ranges of a segmented one
+            tokio::fs::remove_dir_all(hls::parts_dir(&path)).await.ok();
+            segmented::remove_state(&path).await;
+        }
+        if let Some(pool) = &self.db_pool {
+            subtitles::remove_files(pool, download_id).await;
         }
 
         // Remove from list and database
-        self.remove_download(download_id).await?;
+        self.forget_download(download_id, HistoryEvent::Deleted).await?;
 
         Ok(())
     }
@@ -1047,6 +2605,7 @@ impl DownloadManager {
                 }
 
                 // Remove from database
+                history::record(pool, &id, HistoryEvent::Deleted).await;
                 sqlx::query("DELETE FROM downloads WHERE id = ?")
                     .bind(&id)
                     .execute(pool.as_ref())
@@ -1054,6 +2613,7 @@ impl DownloadManager {
 
                 // Remove from in-memory map
                 self.downloads.write().await.remove(&id);
+                crate::badges::invalidate();
 
                 log::debug!("Deleted episode download from DB fallback: {} episode {}", media_id, episode_number);
                 return Ok(());
@@ -1073,6 +2633,7 @@ impl DownloadManager {
     /// that a chapter status transition updates the tray correctly even when
     /// there are concurrent episode downloads (and vice-versa).
     pub(crate) async fn refresh_tray_downloads_count(&self, chapter_pool: &SqlitePool) {
+        crate::badges::invalidate();
         if let Some(ref handle) = self.app_handle {
             let active = total_active_downloads(&self.downloads, chapter_pool).await;
             crate::tray::update_downloads_count(handle, active);
@@ -1118,6 +2679,7 @@ mod tests {
             media_id: "media-1".to_string(),
             episode_id: "episode-1".to_string(),
             episode_number: 1,
+            media_title: None,
             filename: "Episode_1.otaku".to_string(),
             url: "https://example.test/video.mp4".to_string(),
             file_path: file_path.to_string_lossy().to_string(),
@@ -1127,6 +2689,21 @@ mod tests {
             speed: 0,
             status,
             error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality: None,
+            source_label: None,
+            replaces_download_id: None,
+            batch_id: None,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: HashMap::new(),
         }
     }
 
@@ -1147,6 +2724,643 @@ mod tests {
         assert!(manager.get_progress("download-1").await.is_none());
     }
 
+    #[tokio::test]
+    async fn failed_downloads_are_requeued_until_retries_run_out() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let error = anyhow::anyhow!("connection reset");
+
+        manager.downloads.write().await.insert(
+            "flaky".to_string(),
+            download_with_path("flaky", temp_dir.path().join("flaky"), DownloadStatus::Downloading),
+        );
+
+        let expected = [5, 15, 60];
+        for (attempt, secs) in expected.iter().enumerate() {
+            let delay = DownloadManager::schedule_retry(&manager.downloads, "flaky", None, None, &error).await;
+            assert_eq!(delay, Some(std::time::Duration::from_secs(*secs)));
+
+            let progress = manager.get_progress("flaky").await.unwrap();
+            assert_eq!(progress.status, DownloadStatus::Queued);
+            assert_eq!(progress.retry_count, attempt as u32 + 1);
+
+            // The next attempt starts and fails again
+            manager.downloads.write().await.get_mut("flaky").unwrap().status = DownloadStatus::Downloading;
+        }
+
+        // Out of retries: the caller marks it failed
+        assert_eq!(DownloadManager::schedule_retry(&manager.downloads, "flaky", None, None, &error).await, None);
+
+        // Paused and cancelled downloads are never retried
+        for status in [DownloadStatus::Paused, DownloadStatus::Cancelled] {
+            manager.downloads.write().await.insert(
+                "stopped".to_string(),
+                download_with_path("stopped", temp_dir.path().join("stopped"), status.clone()),
+            );
+            assert_eq!(DownloadManager::schedule_retry(&manager.downloads, "stopped", None, None, &error).await, None);
+            assert_eq!(manager.get_progress("stopped").await.unwrap().status, status);
+        }
+    }
+
+    #[tokio::test]
+    async fn downloads_that_dont_fit_on_disk_fail_before_starting() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers every request with a Content-Length no disk has room for
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000000000000\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let mut download = download_with_path("huge", temp_dir.path().join("huge.mp4"), DownloadStatus::Queued);
+        download.url = format!("http://{}/huge.mp4", addr);
+        manager.downloads.write().await.insert("huge".to_string(), download);
+
+        assert!(!DownloadManager::check_disk_space(&manager.downloads, "huge", None, None).await);
+        let progress = manager.get_progress("huge").await.unwrap();
+        assert_eq!(progress.status, DownloadStatus::Failed);
+        assert!(progress.error_message.unwrap().starts_with("Insufficient disk space: need "));
+
+        // Not known yet: let through
+        let mut pending = download_with_path("pending", temp_dir.path().join("pending.mp4"), DownloadStatus::Queued);
+        pending.url = String::new();
+        manager.downloads.write().await.insert("pending".to_string(), pending);
+        assert!(DownloadManager::check_disk_space(&manager.downloads, "pending", None, None).await);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_batch_leaves_started_members_running() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+
+        for (id, status) in [
+            ("ep1", DownloadStatus::Downloading),
+            ("ep2", DownloadStatus::Queued),
+            ("ep3", DownloadStatus::Queued),
+            ("other", DownloadStatus::Queued),
+        ] {
+            let mut download = download_with_path(id, temp_dir.path().join(id), status);
+            if id != "other" {
+                download.batch_id = Some("season-1".to_string());
+            }
+            manager.downloads.write().await.insert(id.to_string(), download);
+        }
+
+        assert_eq!(manager.cancel_batch("season-1").await.unwrap(), 2);
+        assert_eq!(manager.get_progress("ep1").await.unwrap().status, DownloadStatus::Downloading);
+        assert_eq!(manager.get_progress("ep2").await.unwrap().status, DownloadStatus::Cancelled);
+        assert_eq!(manager.get_progress("ep3").await.unwrap().status, DownloadStatus::Cancelled);
+        assert_eq!(manager.get_progress("other").await.unwrap().status, DownloadStatus::Queued);
+    }
+
+    /// Serves `body`, slowly unless a Range header asks for the rest of it
+    async fn serve_with_ranges(body: Vec<u8>) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = Arc::new(body);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                    let start = request
+                        .lines()
+                        .find_map(|l| l.strip_prefix("range: bytes="))
+                        .and_then(|r| r.trim().trim_end_matches('-').parse::<usize>().ok());
+
+                    let head = match start {
+                        Some(start) => format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                            body.len() - start, start, body.len() - 1, body.len()
+                        ),
+                        None => format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()),
+                    };
+                    if socket.write_all(head.as_bytes()).await.is_err() || request.starts_with("head") {
+                        return;
+                    }
+                    match start {
+                        Some(start) => {
+                            let _ = socket.write_all(&body[start..]).await;
+                        }
+                        None => {
+                            for chunk in body.chunks(4096) {
+                                if socket.write_all(chunk).await.is_err() {
+                                    return;
+                                }
+                                tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    async fn wait_until<F: Fn(&DownloadProgress) -> bool>(manager: &DownloadManager, id: &str, done: F) {
+        for _ in 0..500 {
+            if manager.get_progress(id).await.is_some_and(|p| done(&p)) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("download {} never got there: {:?}", id, manager.get_progress(id).await);
+    }
+
+    #[tokio::test]
+    async fn cancelled_downloads_resume_into_a_complete_file() {
+        let body: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let addr = serve_with_ranges(body.clone()).await;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let file_path = temp_dir.path().join("episode.mp4");
+        let mut download = download_with_path("ep", file_path.clone(), DownloadStatus::Queued);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.downloaded_bytes = 0;
+        download.percentage = 0.0;
+        manager.downloads.write().await.insert("ep".to_string(), download);
+
+        manager.start_download_task("ep".to_string()).await.unwrap();
+        wait_until(&manager, "ep", |p| p.downloaded_bytes > 0).await;
+        manager.cancel_download("ep").await.unwrap();
+        // The transfer notices at its next chunk
+        while *manager.active_downloads.lock().await > 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // Cancelling stopped the transfer part-way and kept what it had
+        let cancelled = manager.get_progress("ep").await.unwrap();
+        assert_eq!(cancelled.status, DownloadStatus::Cancelled);
+        assert!(cancelled.downloaded_bytes > 0 && cancelled.downloaded_bytes < body.len() as u64);
+        assert_eq!(std::fs::metadata(&file_path).unwrap().len(), cancelled.downloaded_bytes);
+
+        manager.resume_download("ep").await.unwrap();
+        wait_until(&manager, "ep", |p| p.status == DownloadStatus::Completed).await;
+        assert_eq!(std::fs::read(&file_path).unwrap(), body);
+        assert_eq!(manager.get_progress("ep").await.unwrap().downloaded_bytes, body.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn unresumable_partial_files_start_over() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Refuses every range, serves the whole file otherwise
+        let body: Vec<u8> = (0..8 * 1024).map(|i| (i % 251) as u8).collect();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = body.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                if request.contains("range: bytes=") {
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .await;
+                } else {
+                    let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", served.len());
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&served).await;
+                }
+            }
+        });
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let file_path = temp_dir.path().join("episode.mp4");
+        std::fs::write(&file_path, b"stale part").unwrap();
+        let mut download = download_with_path("ep", file_path.clone(), DownloadStatus::Queued);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.downloaded_bytes = 10;
+        download.total_bytes = body.len() as u64;
+        manager.downloads.write().await.insert("ep".to_string(), download);
+
+        manager.start_download_task("ep".to_string()).await.unwrap();
+        wait_until(&manager, "ep", |p| matches!(p.status, DownloadStatus::Completed | DownloadStatus::Failed)).await;
+
+        let progress = manager.get_progress("ep").await.unwrap();
+        assert_eq!(progress.status, DownloadStatus::Completed, "{:?}", progress.error_message);
+        assert_eq!(std::fs::read(&file_path).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn shutdown_saves_the_bytes_running_downloads_wrote() {
+        let body: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        let addr = serve_with_ranges(body.clone()).await;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let pool = Arc::new(setup_downloads_pool().await);
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool.clone());
+        let file_path = temp_dir.path().join("episode.mp4");
+        let mut download = download_with_path("ep", file_path.clone(), DownloadStatus::Queued);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.total_bytes = body.len() as u64;
+        download.downloaded_bytes = 0;
+        download.percentage = 0.0;
+        manager.save_to_database(&download).await.unwrap();
+        manager.downloads.write().await.insert("ep".to_string(), download);
+
+        // A queued one that must not start while the app exits
+        let mut waiting = download_with_path("waiting", temp_dir.path().join("waiting.mp4"), DownloadStatus::Queued);
+        waiting.episode_id = "episode-2".to_string();
+        waiting.url = format!("http://{}/waiting.mp4", addr);
+        manager.save_to_database(&waiting).await.unwrap();
+        manager.downloads.write().await.insert("waiting".to_string(), waiting);
+        manager.max_concurrent.store(1, Ordering::SeqCst);
+
+        manager.start_download_task("ep".to_string()).await.unwrap();
+        manager.start_download_task("waiting".to_string()).await.unwrap();
+        wait_until(&manager, "ep", |p| p.downloaded_bytes > 8 * 1024).await;
+
+        assert_eq!(manager.shutdown().await, 1);
+        assert_eq!(*manager.active_downloads.lock().await, 0);
+
+        // Far below the 5 MB between periodic saves, yet the row matches the file
+        let on_disk = std::fs::metadata(&file_path).unwrap().len();
+        assert!(on_disk > 0 && on_disk < body.len() as u64);
+        let (status, saved): (String, i64) =
+            sqlx::query_as("SELECT status, downloaded_bytes FROM downloads WHERE id = 'ep'")
+                .fetch_one(pool.as_ref())
+                .await
+                .unwrap();
+        assert_eq!(status, "downloading");
+        assert_eq!(saved as u64, on_disk);
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert_eq!(manager.get_progress("waiting").await.unwrap().status, DownloadStatus::Queued);
+
+        // The next launch picks it up as interrupted and resumes into the whole file
+        let relaunched = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool.clone());
+        assert_eq!(relaunched.load_from_database().await.unwrap(), vec!["ep".to_string()]);
+        assert_eq!(relaunched.resume_interrupted(&["ep".to_string()]).await, 1);
+        wait_until(&relaunched, "ep", |p| p.status == DownloadStatus::Completed).await;
+        assert_eq!(std::fs::read(&file_path).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn queued_downloads_know_their_size_before_they_start() {
+        let addr = serve_with_ranges(vec![7u8; 32 * 1024]).await;
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        // No slot: the download stays queued
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        let mut download = download_with_path("ep", temp_dir.path().join("episode.mp4"), DownloadStatus::Queued);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.total_bytes = 0;
+        download.downloaded_bytes = 0;
+        download.percentage = 0.0;
+        manager.downloads.write().await.insert("ep".to_string(), download);
+
+        manager.start_download_task("ep".to_string()).await.unwrap();
+        wait_until(&manager, "ep", |p| p.total_bytes == 32 * 1024).await;
+        let queued = manager.get_progress("ep").await.unwrap();
+        assert_eq!((queued.status, queued.downloaded_bytes), (DownloadStatus::Queued, 0));
+    }
+
+    #[tokio::test]
+    async fn aborting_deletes_the_partial_download() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let file_path = temp_dir.path().join("partial.mp4");
+        std::fs::write(&file_path, [0u8; 50]).unwrap();
+        std::fs::create_dir(hls::parts_dir(&file_path.to_string_lossy())).unwrap();
+        manager.downloads.write().await.insert(
+            "partial".to_string(),
+            download_with_path("partial", file_path.clone(), DownloadStatus::Paused),
+        );
+
+        // Keeping the partial file is just a cancel
+        manager.abort_download("partial", false).await.unwrap();
+        assert!(file_path.exists());
+        assert_eq!(manager.get_progress("partial").await.unwrap().downloaded_bytes, 50);
+
+        manager.abort_download("partial", true).await.unwrap();
+        let progress = manager.get_progress("partial").await.unwrap();
+        assert_eq!(progress.status, DownloadStatus::Cancelled);
+        assert_eq!(progress.downloaded_bytes, 0);
+        assert!(!file_path.exists());
+        assert!(!hls::parts_dir(&file_path.to_string_lossy()).exists());
+    }
+
+    #[tokio::test]
+    async fn queueing_over_a_completed_download_needs_overwrite() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        // Keep queued downloads waiting
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        let file_path = temp_dir.path().join("Episode_1.mp4");
+        std::fs::write(&file_path, b"working copy").unwrap();
+        let mut done = download_with_path("media-1_1", file_path.clone(), DownloadStatus::Completed);
+        done.episode_id = "episode-1".to_string();
+        manager.downloads.write().await.insert("media-1_1".to_string(), done);
+
+        async fn queue(manager: &DownloadManager, overwrite: bool) -> Result<()> {
+            manager
+                .queue_download(
+                    "media-1_1".to_string(),
+                    "media-1".to_string(),
+                    "episode-1".to_string(),
+                    1,
+                    None,
+                    "https://example.test/video.mp4".to_string(),
+                    HashMap::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    "Episode_1.mp4".to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    overwrite,
+                )
+                .await
+        }
+
+        let error = QueueError::from_anyhow(queue(&manager, false).await.unwrap_err(), "Failed to queue download");
+        let QueueError::AlreadyDownloaded(existing) = error else {
+            panic!("expected AlreadyDownloaded, got {:?}", error);
+        };
+        assert_eq!(existing.download.unwrap().id, "media-1_1");
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"working copy");
+        assert_eq!(manager.get_progress("media-1_1").await.unwrap().status, DownloadStatus::Completed);
+
+        // Overwriting trashes the old file first and keeps it restorable
+        queue(&manager, true).await.unwrap();
+        assert!(!file_path.exists());
+        assert_eq!(manager.get_progress("media-1_1").await.unwrap().status, DownloadStatus::Queued);
+        let set_aside: Vec<DownloadProgress> = manager
+            .list_downloads()
+            .await
+            .into_iter()
+            .filter(|d| d.id != "media-1_1")
+            .collect();
+        assert_eq!(set_aside.len(), 1);
+        assert_eq!(set_aside[0].file_state, FileState::Trashed);
+        assert_ne!(set_aside[0].episode_id, "episode-1");
+        assert_eq!(std::fs::read(&set_aside[0].file_path).unwrap(), b"working copy");
+
+        manager.restore_download(&set_aside[0].id).await.unwrap();
+        assert_eq!(std::fs::read(temp_dir.path().join("Episode_1.mp4")).unwrap(), b"working copy");
+    }
+
+    #[tokio::test]
+    async fn queueing_onto_an_existing_file_needs_overwrite() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        // Put there by hand; no download tracks it
+        let file_path = temp_dir.path().join("Episode_2.mp4");
+        std::fs::write(&file_path, b"copied in").unwrap();
+
+        async fn queue(manager: &DownloadManager, overwrite: bool) -> Result<()> {
+            manager
+                .queue_pending_download(
+                    "media-1_2".to_string(),
+                    "media-1".to_string(),
+                    "episode-2".to_string(),
+                    2,
+                    "Episode_2.mp4".to_string(),
+                    None,
+                    Some("batch-1".to_string()),
+                    "com.allanime.source".to_string(),
+                    None,
+                    overwrite,
+                )
+                .await
+        }
+
+        let error = QueueError::from_anyhow(queue(&manager, false).await.unwrap_err(), "Failed to queue download");
+        assert!(matches!(&error, QueueError::AlreadyDownloaded(e) if e.download.is_none()));
+        assert!(manager.get_progress("media-1_2").await.is_none());
+
+        queue(&manager, true).await.unwrap();
+        assert!(!file_path.exists());
+        assert!(temp_dir.path().join(trash::TRASH_DIR).join("Episode_2.mp4").exists());
+
+        // A download's own partial file is no conflict: re-queueing resumes it
+        std::fs::write(&file_path, b"partial").unwrap();
+        manager.cancel_download("media-1_2").await.unwrap();
+        queue(&manager, false).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pending_downloads_are_named_by_the_filename_template() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let database = crate::database::Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = Arc::new(database.pool().clone());
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('media-1', 'ext', 'Frieren', 'anime')")
+            .execute(pool.as_ref())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES (?, '{title} - E{episode}')")
+            .bind(filename::FILENAME_TEMPLATE_SETTING)
+            .execute(pool.as_ref())
+            .await
+            .unwrap();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(pool);
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        manager
+            .queue_pending_download(
+                "media-1_2".to_string(),
+                "media-1".to_string(),
+                "episode-2".to_string(),
+                2,
+                "Episode_2.mp4".to_string(),
+                None,
+                None,
+                "com.allanime.source".to_string(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let progress = manager.get_progress("media-1_2").await.unwrap();
+        assert_eq!(progress.filename, "Frieren - E02.mp4");
+        assert_eq!(progress.file_path, temp_dir.path().join("downloads/Frieren - E02.mp4").to_string_lossy());
+        assert_eq!(progress.media_title.as_deref(), Some("Frieren"));
+    }
+
+    #[test]
+    fn retry_delays_back_off_and_level_out() {
+        assert_eq!(retry_delay(1).as_secs(), 5);
+        assert_eq!(retry_delay(2).as_secs(), 15);
+        assert_eq!(retry_delay(3).as_secs(), 60);
+        assert_eq!(retry_delay(4).as_secs(), 300);
+        assert_eq!(retry_delay(10).as_secs(), 300);
+    }
+
+    #[tokio::test]
+    async fn pause_all_and_resume_all_only_touch_matching_downloads() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        // Keep resumed downloads waiting in the queue
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        for (id, status) in [
+            ("downloading", DownloadStatus::Downloading),
+            ("queued", DownloadStatus::Queued),
+            ("paused", DownloadStatus::Paused),
+            ("completed", DownloadStatus::Completed),
+            ("failed", DownloadStatus::Failed),
+        ] {
+            manager.downloads.write().await.insert(
+                id.to_string(),
+                download_with_path(id, temp_dir.path().join(id), status),
+            );
+        }
+
+        assert_eq!(manager.pause_all().await.unwrap(), 2);
+        for id in ["downloading", "queued", "paused"] {
+            assert_eq!(manager.get_progress(id).await.unwrap().status, DownloadStatus::Paused);
+        }
+
+        assert_eq!(manager.resume_all().await.unwrap(), 3);
+        for id in ["downloading", "queued", "paused"] {
+            assert_eq!(manager.get_progress(id).await.unwrap().status, DownloadStatus::Queued);
+        }
+        assert_eq!(manager.get_progress("completed").await.unwrap().status, DownloadStatus::Completed);
+        assert_eq!(manager.get_progress("failed").await.unwrap().status, DownloadStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn downloads_queued_while_paused_start_only_after_resume_all() {
+        let body: Vec<u8> = (0..8 * 1024).map(|i| (i % 251) as u8).collect();
+        let addr = serve_with_ranges(body.clone()).await;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        assert_eq!(manager.pause_all().await.unwrap(), 0);
+        assert!(manager.downloads_paused());
+
+        let file_path = temp_dir.path().join("queued-while-paused.mp4");
+        let mut download = download_with_path("queued-while-paused", file_path.clone(), DownloadStatus::Queued);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.downloaded_bytes = 0;
+        download.percentage = 0.0;
+        manager.downloads.write().await.insert("queued-while-paused".to_string(), download);
+        manager.start_download_task("queued-while-paused".to_string()).await.unwrap();
+
+        // It waits in the queue, and the schedule task leaves it there too
+        wait_until(&manager, "queued-while-paused", |p| p.paused_globally).await;
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(manager.start_scheduled_downloads().await, 0);
+        let waiting = manager.get_progress("queued-while-paused").await.unwrap();
+        assert_eq!(waiting.status, DownloadStatus::Queued);
+        assert_eq!(*manager.active_downloads.lock().await, 0);
+        assert!(!file_path.exists());
+
+        assert_eq!(manager.resume_all().await.unwrap(), 0);
+        assert!(!manager.downloads_paused());
+        wait_until(&manager, "queued-while-paused", |p| p.status == DownloadStatus::Completed).await;
+        assert!(!manager.get_progress("queued-while-paused").await.unwrap().paused_globally);
+        assert_eq!(std::fs::read(&file_path).unwrap(), body);
+    }
+
+    #[test]
+    fn max_concurrent_is_clamped() {
+        let manager = DownloadManager::new(PathBuf::from("downloads"));
+        assert_eq!(manager.max_concurrent(), DEFAULT_MAX_CONCURRENT);
+
+        assert_eq!(manager.set_max_concurrent(3), 3);
+        assert_eq!(manager.max_concurrent(), 3);
+        assert_eq!(manager.set_max_concurrent(0), 1);
+        assert_eq!(manager.set_max_concurrent(50), MAX_CONCURRENT_LIMIT);
+        assert_eq!(manager.max_concurrent(), MAX_CONCURRENT_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn clearing_by_status_removes_only_that_status() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let pool = Arc::new(setup_downloads_pool().await);
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool.clone());
+
+        for (id, status) in [
+            ("failed-1", DownloadStatus::Failed),
+            ("failed-2", DownloadStatus::Failed),
+            ("done", DownloadStatus::Completed),
+            ("stopped", DownloadStatus::Cancelled),
+            ("running", DownloadStatus::Downloading),
+        ] {
+            let mut download = download_with_path(id, temp_dir.path().join(id), status);
+            download.episode_id = id.to_string();
+            std::fs::write(temp_dir.path().join(id), [0u8; 50]).unwrap();
+            manager.save_to_database(&download).await.expect("save download");
+            manager.downloads.write().await.insert(id.to_string(), download);
+        }
+        let stopped_parts = hls::parts_dir(&temp_dir.path().join("stopped").to_string_lossy());
+        std::fs::create_dir(&stopped_parts).unwrap();
+
+        assert_eq!(manager.clear_downloads_by_status(DownloadStatus::Failed).await.unwrap(), 2);
+        assert!(temp_dir.path().join("failed-1").exists());
+        manager.clear_cancelled().await.unwrap();
+        assert!(!temp_dir.path().join("stopped").exists(), "nothing tracks a cleared partial");
+        assert!(!stopped_parts.exists());
+        assert!(manager.clear_downloads_by_status(DownloadStatus::Downloading).await.is_err());
+
+        let mut remaining: Vec<String> = manager.downloads.read().await.keys().cloned().collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["done".to_string(), "running".to_string()]);
+        let stored: Vec<String> = sqlx::query_scalar("SELECT id FROM downloads ORDER BY id")
+            .fetch_all(pool.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(stored, remaining);
+    }
+
+    #[tokio::test]
+    async fn saving_again_updates_the_source_and_batch_columns() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let pool = Arc::new(setup_downloads_pool().await);
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool.clone());
+
+        let mut download = download_with_path("lazy", temp_dir.path().join("lazy"), DownloadStatus::Queued);
+        download.episode_id = String::new();
+        manager.save_to_database(&download).await.expect("save placeholder");
+
+        download.episode_id = "episode-7".to_string();
+        download.batch_id = Some("batch-1".to_string());
+        download.source_extension_id = Some("allanime".to_string());
+        download.media_title = Some("Frieren".to_string());
+        manager.save_to_database(&download).await.expect("save resolved");
+
+        let stored: (String, Option<String>, Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT episode_id, batch_id, source_extension_id, media_title FROM downloads WHERE id = 'lazy'",
+        )
+        .fetch_one(pool.as_ref())
+        .await
+        .unwrap();
+        assert_eq!(
+            stored,
+            (
+                "episode-7".to_string(),
+                Some("batch-1".to_string()),
+                Some("allanime".to_string()),
+                Some("Frieren".to_string())
+            )
+        );
+    }
+
     async fn setup_downloads_pool() -> SqlitePool {
         let pool = SqlitePoolOptions::new()
             .max_connections(1)
@@ -1172,6 +3386,22 @@ mod tests {
                 error_message TEXT,
                 created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                archived INTEGER NOT NULL DEFAULT 0,
+                original_path TEXT,
+                quality TEXT,
+                source_label TEXT,
+                replaces_download_id TEXT,
+                file_state TEXT NOT NULL DEFAULT 'present',
+                batch_id TEXT,
+                source_extension_id TEXT,
+                media_title TEXT,
+                scheduled_start INTEGER,
+                start_now INTEGER NOT NULL DEFAULT 0,
+                speed_limit INTEGER,
+                headers TEXT,
+                sha256 TEXT,
+                verified_at INTEGER,
+                source_history TEXT,
                 UNIQUE(media_id, episode_id)
             )
             "#,
@@ -1184,7 +3414,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn load_from_database_persists_missing_completed_file_as_failed() {
+    async fn load_from_database_keeps_missing_completed_file_as_completed() {
         let temp_dir = tempfile::tempdir().expect("temp dir");
         let missing_file = temp_dir.path().join("missing-completed.otaku");
         let pool = setup_downloads_pool().await;
@@ -1209,6 +3439,212 @@ mod tests {
 
         manager.load_from_database().await.expect("load downloads");
 
+        let progress = manager.get_progress("download-1").await.expect("download loaded");
+        let (persisted_status, persisted_file_state): (String, String) = sqlx::query_as(
+            "SELECT status, file_state FROM downloads WHERE id = 'download-1'",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("persisted status");
+
+        assert_eq!(progress.status, DownloadStatus::Completed);
+        assert_eq!(progress.file_state, FileState::Missing);
+        assert_eq!(persisted_status, "completed");
+        assert_eq!(persisted_file_state, "missing");
+        assert!(!manager.is_episode_downloaded("media-1", 1).await);
+    }
+
+    #[test]
+    fn download_headers_fall_back_to_the_default_referer() {
+        let defaults = download_headers(&HashMap::new());
+        assert_eq!(defaults["user-agent"], "Mozilla/5.0");
+        assert_eq!(defaults["referer"], DEFAULT_REFERER);
+
+        let custom = download_headers(&HashMap::from([
+            ("Origin".to_string(), "https://cdn.example.com".to_string()),
+            ("User-Agent".to_string(), "Custom/1.0".to_string()),
+            ("Bad Header".to_string(), "x".to_string()),
+        ]));
+        assert_eq!(custom["origin"], "https://cdn.example.com");
+        assert_eq!(custom["user-agent"], "Custom/1.0");
+        assert!(custom.get("referer").is_none());
+        assert_eq!(custom.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn downloads_send_their_sources_headers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Refuses requests without the token, or with AllAnime's Referer
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let allowed = request.contains("x-token: abc") && !request.contains("allmanga");
+                let response: &[u8] = if allowed {
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nvideo"
+                } else {
+                    b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                let _ = socket.write_all(response).await;
+            }
+        });
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let mut download = download_with_path("ep", temp_dir.path().join("episode.mp4"), DownloadStatus::Downloading);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.downloaded_bytes = 0;
+        manager.downloads.write().await.insert("ep".to_string(), download);
+
+        let refused =
+            DownloadManager::perform_download("ep".to_string(), manager.downloads.clone(), None, None, CancellationToken::new()).await;
+        assert!(refused.unwrap_err().to_string().contains("403"));
+
+        manager.downloads.write().await.get_mut("ep").unwrap().headers =
+            HashMap::from([("X-Token".to_string(), "abc".to_string())]);
+        DownloadManager::perform_download("ep".to_string(), manager.downloads.clone(), None, None, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read(temp_dir.path().join("episode.mp4")).await.unwrap(), b"video");
+    }
+
+    #[tokio::test]
+    async fn pausing_interrupts_a_download_whose_connection_went_quiet() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Sends 5 of the 100 bytes it promises, then nothing
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nvideo").await;
+            tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+            drop(socket);
+        });
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let mut download = download_with_path("ep", temp_dir.path().join("episode.mp4"), DownloadStatus::Downloading);
+        download.url = format!("http://{}/episode.mp4", addr);
+        download.downloaded_bytes = 0;
+        manager.downloads.write().await.insert("ep".to_string(), download);
+        let cancel = CancellationToken::new();
+        manager.cancel_tokens.lock().unwrap().insert("ep".to_string(), cancel.clone());
+
+        let task = tokio::spawn(DownloadManager::perform_download(
+            "ep".to_string(),
+            manager.downloads.clone(),
+            None,
+            None,
+            cancel,
+        ));
+        while manager.get_progress("ep").await.unwrap().downloaded_bytes < 5 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        manager.pause_download("ep").await.unwrap();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), task)
+            .await
+            .expect("the stalled read was interrupted")
+            .unwrap();
+        assert_eq!(result.unwrap_err().to_string(), "Download paused");
+        assert!(manager.cancel_tokens.lock().unwrap().is_empty());
+        assert_eq!(tokio::fs::read(temp_dir.path().join("episode.mp4")).await.unwrap(), b"video");
+    }
+
+    #[tokio::test]
+    async fn download_headers_survive_a_restart() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let pool = Arc::new(setup_downloads_pool().await);
+        let headers = HashMap::from([("Referer".to_string(), "https://site.example.com/".to_string())]);
+
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool.clone());
+        let mut download = download_with_path("ep", temp_dir.path().join("episode.mp4"), DownloadStatus::Paused);
+        download.headers = headers.clone();
+        manager.save_to_database(&download).await.unwrap();
+        let mut plain = download_with_path("plain", temp_dir.path().join("plain.mp4"), DownloadStatus::Paused);
+        plain.episode_id = "episode-2".to_string();
+        manager.save_to_database(&plain).await.unwrap();
+
+        let restarted = DownloadManager::new(temp_dir.path().to_path_buf()).with_database(pool);
+        restarted.load_from_database().await.unwrap();
+        assert_eq!(restarted.get_progress("ep").await.unwrap().headers, headers);
+        assert!(restarted.get_progress("plain").await.unwrap().headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn load_from_database_pauses_interrupted_downloads() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let partial_file = temp_dir.path().join("Episode_2.mp4");
+        std::fs::write(&partial_file, vec![0u8; 60]).expect("write partial file");
+        let pool = setup_downloads_pool().await;
+
+        // Progress was last saved at 40 bytes; the file got to 60
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (
+                id, media_id, episode_id, episode_number, filename, url, file_path,
+                total_bytes, downloaded_bytes, percentage, speed, status
+            )
+            VALUES ('download-2', 'media-1', 'episode-2', 2, 'Episode_2.mp4',
+                'https://example.test/video.mp4', ?, 100, 40, 40.0, 500, 'downloading')
+            "#,
+        )
+        .bind(partial_file.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .expect("insert interrupted download");
+
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf())
+            .with_database(Arc::new(pool.clone()));
+
+        let interrupted = manager.load_from_database().await.expect("load downloads");
+        assert_eq!(interrupted, vec!["download-2".to_string()]);
+
+        let progress = manager.get_progress("download-2").await.expect("download loaded");
+        assert_eq!(progress.status, DownloadStatus::Paused);
+        assert_eq!(progress.downloaded_bytes, 60);
+        assert_eq!(progress.percentage, 60.0);
+
+        let persisted_status: String = sqlx::query_scalar("SELECT status FROM downloads WHERE id = 'download-2'")
+            .fetch_one(&pool)
+            .await
+            .expect("persisted status");
+        assert_eq!(persisted_status, "paused");
+    }
+
+    #[tokio::test]
+    async fn load_from_database_shows_unreachable_archive_as_offline() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let unreachable = temp_dir.path().join("nas").join("Episode_1.otaku");
+        let pool = setup_downloads_pool().await;
+
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (
+                id, media_id, episode_id, episode_number, filename, url, file_path,
+                total_bytes, downloaded_bytes, percentage, speed, status, archived
+            )
+            VALUES ('download-1', 'media-1', 'episode-1', 1, 'Episode_1.otaku',
+                'https://example.test/video.mp4', ?, 100, 100, 100.0, 0, 'completed', 1)
+            "#,
+        )
+        .bind(unreachable.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .expect("insert archived download");
+
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf())
+            .with_database(Arc::new(pool.clone()));
+
+        manager.load_from_database().await.expect("load downloads");
+
         let progress = manager.get_progress("download-1").await.expect("download loaded");
         let persisted_status: String = sqlx::query_scalar(
             "SELECT status FROM downloads WHERE id = 'download-1'",
@@ -1217,7 +3653,9 @@ mod tests {
         .await
         .expect("persisted status");
 
-        assert_eq!(progress.status, DownloadStatus::Failed);
-        assert_eq!(persisted_status, "failed");
+        assert_eq!(progress.status, DownloadStatus::Offline);
+        assert_eq!(progress.file_state, FileState::Missing);
+        assert!(progress.archived);
+        assert_eq!(persisted_status, "completed");
     }
 }
@@ -0,0 +1,379 @@
+// Network Monitor
+//
+// When the connection drops, every running download errors out and burns
+// through its retries, and servers that don't honor Range on the retry make
+// it start over. Instead, a download that fails while the network is down is
+// paused, and the monitor task probes connectivity every few seconds: on
+// losing it, running and queued downloads are paused as well; once it's
+// back, the downloads it paused are resumed. Downloads the user paused are
+// left alone.
+//
+// The probe, run only while downloads are running, queued or waiting for
+// the network, opens TCP connections to the hosts the downloads use and to a
+// few well-known addresses (network_probe_addresses, public resolvers by
+// default); any of them answering counts as online. A network that blocks
+// the resolvers but reaches the download servers is therefore never taken
+// for offline. The current state is reported through get_network_status
+// and the network-status event (for the offline banner).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+use tokio::sync::RwLock;
+
+use super::retry_failed::{classify_failure, FailureCause};
+use super::{DownloadManager, DownloadProgress, DownloadStatus};
+use crate::events::{DOWNLOAD_PROGRESS_EVENT, NETWORK_STATUS_EVENT};
+
+/// app_settings key: addresses probed besides the download hosts,
+/// comma-separated host:port (unset = DEFAULT_PROBE_ADDRESSES)
+pub const PROBE_ADDRESSES_SETTING: &str = "network_probe_addresses";
+
+/// How often connectivity is probed
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout of one probe connection
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Probed when no addresses are configured
+const DEFAULT_PROBE_ADDRESSES: [&str; 3] = ["1.1.1.1:443", "8.8.8.8:443", "9.9.9.9:443"];
+
+/// Network state for the offline banner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct NetworkStatus {
+    pub online: bool,
+    /// Unix timestamp (ms) of the last change; None while it never changed
+    pub changed_at: Option<i64>,
+    /// Downloads waiting for the network to come back
+    pub paused_downloads: usize,
+}
+
+/// What a download manager last saw of the network
+pub(super) struct NetworkState {
+    online: AtomicBool,
+    /// Unix timestamp (ms) of the last change; 0 before the first
+    changed_at: AtomicI64,
+    /// Downloads paused because the network went down
+    paused_offline: Mutex<HashSet<String>>,
+}
+
+impl Default for NetworkState {
+    fn default() -> Self {
+        Self {
+            online: AtomicBool::new(true),
+            changed_at: AtomicI64::new(0),
+            paused_offline: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl NetworkState {
+    fn status(&self) -> NetworkStatus {
+        let changed_at = self.changed_at.load(Ordering::SeqCst);
+        NetworkStatus {
+            online: self.online.load(Ordering::SeqCst),
+            changed_at: (changed_at > 0).then_some(changed_at),
+            paused_downloads: self.paused_offline.lock().unwrap().len(),
+        }
+    }
+
+    /// Record the network state, returning whether it changed
+    fn set_online(&self, online: bool, app_handle: Option<&AppHandle>) -> bool {
+        if self.online.swap(online, Ordering::SeqCst) == online {
+            return false;
+        }
+        self.changed_at.store(chrono::Utc::now().timestamp_millis(), Ordering::SeqCst);
+        log::info!("Network is {}", if online { "back online" } else { "offline" });
+        self.emit_status(app_handle);
+        true
+    }
+
+    fn emit_status(&self, app_handle: Option<&AppHandle>) {
+        if let Some(handle) = app_handle {
+            NETWORK_STATUS_EVENT.emit(handle, &self.status());
+        }
+    }
+}
+
+/// The configured probe addresses, or DEFAULT_PROBE_ADDRESSES when unset
+pub async fn probe_addresses(pool: Option<&SqlitePool>) -> Vec<String> {
+    let value: Option<String> = match pool {
+        Some(pool) => sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+            .bind(PROBE_ADDRESSES_SETTING)
+            .fetch_optional(pool)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    let configured: Vec<String> = value
+        .iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_PROBE_ADDRESSES.iter().map(|a| a.to_string()).collect()
+    } else {
+        configured
+    }
+}
+
+/// host:port a URL connects to
+pub fn url_address(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+/// Whether any of `addresses` (host:port) accepts a connection
+pub async fn probe(addresses: &[String]) -> bool {
+    let attempts = addresses.iter().map(|address| async move {
+        matches!(
+            tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(address.as_str())).await,
+            Ok(Ok(_))
+        )
+    });
+    futures_util::future::join_all(attempts).await.into_iter().any(|online| online)
+}
+
+/// Probe the hosts of running and queued downloads along with the
+/// configured addresses
+async fn probe_for_downloads(
+    downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+    db_pool: Option<&Arc<SqlitePool>>,
+) -> bool {
+    let mut addresses: Vec<String> = downloads
+        .read()
+        .await
+        .values()
+        .filter(|d| matches!(d.status, DownloadStatus::Downloading | DownloadStatus::Queued))
+        .filter_map(|d| url_address(&d.url))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    addresses.extend(probe_addresses(db_pool.map(|p| p.as_ref())).await);
+    probe(&addresses).await
+}
+
+/// Pause a download whose transfer just failed with a network error while
+/// the network is down, instead of failing or retrying it. Returns whether
+/// it was paused; it resumes once the monitor sees the network again.
+pub(super) async fn pause_if_offline(
+    downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+    network: &NetworkState,
+    download_id: &str,
+    db_pool: Option<&Arc<SqlitePool>>,
+    app_handle: Option<&AppHandle>,
+    error: &anyhow::Error,
+) -> bool {
+    if classify_failure(Some(&error.to_string())) != FailureCause::Network || probe_for_downloads(downloads, db_pool).await {
+        return false;
+    }
+
+    {
+        let mut downloads_map = downloads.write().await;
+        let Some(progress) = downloads_map.get_mut(download_id) else {
+            return false;
+        };
+        // Paused, cancelled or resumed on purpose meanwhile
+        if progress.status != DownloadStatus::Downloading {
+            return false;
+        }
+        progress.status = DownloadStatus::Paused;
+        progress.speed = 0;
+        progress.eta_seconds = None;
+        network.paused_offline.lock().unwrap().insert(download_id.to_string());
+        log::info!("Network is down, pausing download {} at {} bytes", download_id, progress.downloaded_bytes);
+
+        if let Some(handle) = app_handle {
+            DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+        }
+        if let Some(pool) = db_pool {
+            DownloadManager::save_progress_to_db(pool, progress).await.ok();
+        }
+    }
+
+    if !network.set_online(false, app_handle) {
+        network.emit_status(app_handle);
+    }
+    true
+}
+
+impl DownloadManager {
+    /// Current network state
+    pub fn network_status(&self) -> NetworkStatus {
+        self.network.status()
+    }
+
+    /// Probe the network for the downloads
+    pub async fn probe_network(&self) -> bool {
+        probe_for_downloads(&self.downloads, self.db_pool.as_ref()).await
+    }
+
+    /// Act on a probe result: going offline pauses running and queued
+    /// downloads, coming back resumes the ones paused for it. Returns how
+    /// many downloads were paused or resumed.
+    pub async fn apply_network_status(&self, online: bool) -> usize {
+        if !self.network.set_online(online, self.app_handle.as_ref()) {
+            return 0;
+        }
+
+        if !online {
+            let ids: Vec<String> = self
+                .downloads
+                .read()
+                .await
+                .values()
+                .filter(|d| matches!(d.status, DownloadStatus::Downloading | DownloadStatus::Queued))
+                .map(|d| d.id.clone())
+                .collect();
+            // Only the ones still running when their turn comes are the
+            // monitor's to resume
+            let mut paused = 0;
+            for id in &ids {
+                if self.pause_active(id).await {
+                    self.network.paused_offline.lock().unwrap().insert(id.clone());
+                    paused += 1;
+                }
+            }
+            self.network.emit_status(self.app_handle.as_ref());
+            return paused;
+        }
+
+        let paused: Vec<String> = self.network.paused_offline.lock().unwrap().drain().collect();
+        let resumed = self.resume_interrupted(&paused).await;
+        self.network.emit_status(self.app_handle.as_ref());
+        resumed
+    }
+
+    /// Whether the network is worth probing: downloads are running or
+    /// queued, some wait for the network, or it was last seen down
+    async fn network_needed(&self) -> bool {
+        !self.network.online.load(Ordering::SeqCst)
+            || !self.network.paused_offline.lock().unwrap().is_empty()
+            || self
+                .downloads
+                .read()
+                .await
+                .values()
+                .any(|d| matches!(d.status, DownloadStatus::Downloading | DownloadStatus::Queued))
+    }
+}
+
+/// Probe connectivity periodically and pause or resume downloads with it.
+/// Without downloads that need the network nothing is probed.
+pub fn start_network_monitor(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let manager = app_handle.state::<DownloadManager>();
+            if !manager.network_needed().await {
+                continue;
+            }
+            let online = manager.probe_network().await;
+            manager.apply_network_status(online).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(id: &str, status: &str) -> DownloadProgress {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "media_id": "media-1",
+            "episode_id": format!("episode-{}", id),
+            "episode_number": 1,
+            "filename": format!("{}.mp4", id),
+            // Parked below until its scheduled start, so resuming it doesn't
+            // need a server
+            "url": "https://example.test/video.mp4",
+            "file_path": format!("/tmp/{}.mp4", id),
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": status,
+            "error_message": null,
+            "scheduled_start": i64::MAX,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn going_offline_pauses_and_coming_back_resumes_only_those() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        manager.downloads.write().await.insert("done".to_string(), download("done", "completed"));
+        assert!(!manager.network_needed().await, "nothing to probe for");
+        for (id, status) in [("running", "downloading"), ("waiting", "queued"), ("held", "paused")] {
+            manager.downloads.write().await.insert(id.to_string(), download(id, status));
+        }
+        assert!(manager.network_needed().await);
+
+        assert_eq!(manager.apply_network_status(true).await, 0);
+        assert_eq!(manager.apply_network_status(false).await, 2);
+        let status = manager.network_status();
+        assert!(!status.online && status.changed_at.is_some());
+        assert_eq!(status.paused_downloads, 2);
+        for id in ["running", "waiting", "held"] {
+            assert_eq!(manager.get_progress(id).await.unwrap().status, DownloadStatus::Paused);
+        }
+        // Still offline: nothing more happens
+        assert_eq!(manager.apply_network_status(false).await, 0);
+
+        assert_eq!(manager.apply_network_status(true).await, 2);
+        assert!(manager.network_status().online);
+        assert_eq!(manager.network_status().paused_downloads, 0);
+        assert_eq!(manager.get_progress("running").await.unwrap().status, DownloadStatus::Queued);
+        assert_eq!(manager.get_progress("waiting").await.unwrap().status, DownloadStatus::Queued);
+        // The user paused this one
+        assert_eq!(manager.get_progress("held").await.unwrap().status, DownloadStatus::Paused);
+        assert_eq!(manager.get_progress("done").await.unwrap().status, DownloadStatus::Completed);
+
+        // Only downloads the monitor paused itself are resumed
+        manager.pause_download("running").await.unwrap();
+        manager.pause_download("waiting").await.unwrap();
+        assert!(!manager.network_needed().await);
+        assert_eq!(manager.apply_network_status(false).await, 0);
+        assert_eq!(manager.network_status().paused_downloads, 0);
+        assert_eq!(manager.apply_network_status(true).await, 0);
+        assert_eq!(manager.get_progress("running").await.unwrap().status, DownloadStatus::Paused);
+    }
+
+    #[tokio::test]
+    async fn probes_the_configured_addresses_and_download_hosts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = crate::database::Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(probe_addresses(Some(pool)).await, DEFAULT_PROBE_ADDRESSES.map(String::from).to_vec());
+        sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ' gateway.lan:80, ,10.0.0.1:53')")
+            .bind(PROBE_ADDRESSES_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(probe_addresses(Some(pool)).await, vec!["gateway.lan:80".to_string(), "10.0.0.1:53".to_string()]);
+
+        assert_eq!(url_address("https://cdn.example.test/v.mp4").as_deref(), Some("cdn.example.test:443"));
+        assert_eq!(url_address("http://127.0.0.1:8080/v.mp4").as_deref(), Some("127.0.0.1:8080"));
+        assert_eq!(url_address(""), None);
+
+        // Only the download's own server answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reachable = listener.local_addr().unwrap().to_string();
+        let closed = {
+            let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            unused.local_addr().unwrap().to_string()
+        };
+        assert!(probe(&[closed.clone(), reachable]).await);
+        assert!(!probe(&[closed]).await);
+    }
+}
@@ -0,0 +1,318 @@
+// Ready To Watch Offline
+//
+// While a batch of episodes downloads, the series' continue-watching entry
+// can say how far the user can get without a connection: the run of
+// downloaded episodes that starts at their watch position ("Episodes 5–8
+// ready offline"). The watch position is the most recently watched episode
+// of the current profile, or the one after it once it was finished; episode
+// 1 without any history. Any downloaded episode counts, not only the batch's,
+// and a missing episode ends the run.
+//
+// When a download completes the run of its series is recomputed and the
+// offline-ready event is emitted if it grew, including for the episode that
+// finishes a batch.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+use super::batch::title_from_filename;
+use crate::commands::AppState;
+use crate::database::profiles::DEFAULT_PROFILE_ID;
+use crate::events::OFFLINE_READY_EVENT;
+
+/// Last run reported per media id as (from, to), so the event is only sent
+/// when a run grows
+static REPORTED: LazyLock<Mutex<HashMap<String, (i32, i32)>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Downloaded episodes of a series that can be watched in a row from the
+/// user's watch position
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct OfflineReady {
+    pub media_id: String,
+    pub title: String,
+    /// The watch position
+    pub from_episode: i32,
+    /// Last episode before the first one that isn't downloaded
+    pub to_episode: i32,
+}
+
+/// Batch membership, completed downloads and watch progress in one pass.
+/// Ready episodes are numbered in order; those contiguous with the watch
+/// position are the ones whose number minus their row number equals the
+/// position minus one (gaps-and-islands).
+///
+/// ?1 is the profile. With ?2 NULL every media with a batch still queued,
+/// running or paused is listed; otherwise only media ?2, if it has any batch.
+const OFFLINE_READY_QUERY: &str = r#"
+    WITH batch_media AS (
+        SELECT DISTINCT media_id FROM downloads
+        WHERE batch_id IS NOT NULL
+          AND CASE WHEN ?2 IS NULL
+                   THEN status IN ('queued', 'downloading', 'paused')
+                   ELSE media_id = ?2 END
+    ),
+    latest_watch AS (
+        SELECT media_id, episode_number, completed,
+               ROW_NUMBER() OVER (PARTITION BY media_id ORDER BY last_watched DESC, episode_number DESC) AS recency
+        FROM watch_history
+        WHERE profile_id = ?1 AND media_id IN (SELECT media_id FROM batch_media)
+    ),
+    position AS (
+        SELECT b.media_id,
+               COALESCE(
+                   (SELECT CASE WHEN w.completed THEN w.episode_number + 1 ELSE w.episode_number END
+                    FROM latest_watch w WHERE w.media_id = b.media_id AND w.recency = 1),
+                   1) AS next_episode
+        FROM batch_media b
+    ),
+    ready AS (
+        SELECT d.media_id, d.episode_number, p.next_episode,
+               MAX(d.media_title) AS media_title, MAX(d.filename) AS filename
+        FROM downloads d
+        JOIN position p ON p.media_id = d.media_id
+        WHERE d.status = 'completed' AND d.file_state = 'present' AND d.episode_number >= p.next_episode
+        GROUP BY d.media_id, d.episode_number
+    ),
+    islands AS (
+        SELECT *, episode_number - ROW_NUMBER() OVER (PARTITION BY media_id ORDER BY episode_number) AS island
+        FROM ready
+    )
+    SELECT i.media_id, MIN(i.episode_number), MAX(i.episode_number),
+           COALESCE(m.title, MAX(i.media_title)), MAX(i.filename)
+    FROM islands i
+    LEFT JOIN media m ON m.id = i.media_id
+    WHERE i.island = i.next_episode - 1
+    GROUP BY i.media_id
+    ORDER BY i.media_id
+"#;
+
+async fn query(pool: &SqlitePool, profile_id: i64, media_id: Option<&str>) -> Result<Vec<OfflineReady>> {
+    let rows: Vec<(String, i32, i32, Option<String>, Option<String>)> = sqlx::query_as(OFFLINE_READY_QUERY)
+        .bind(profile_id)
+        .bind(media_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(media_id, from_episode, to_episode, title, filename)| OfflineReady {
+            title: title.unwrap_or_else(|| title_from_filename(&filename.unwrap_or_default())),
+            media_id,
+            from_episode,
+            to_episode,
+        })
+        .collect())
+}
+
+/// Episodes ready offline for every series with a batch download in
+/// progress, for the current profile. Series whose next episode isn't
+/// downloaded yet are left out.
+pub async fn get_offline_ready(pool: &SqlitePool, profile_id: i64) -> Result<Vec<OfflineReady>> {
+    query(pool, profile_id, None).await
+}
+
+/// Remember `ready` as reported; true if it's a new run or extends the one
+/// reported before
+fn record(ready: &OfflineReady) -> bool {
+    let mut reported = REPORTED.lock().unwrap();
+    let range = (ready.from_episode, ready.to_episode);
+    let grew = reported
+        .get(&ready.media_id)
+        .map_or(true, |&(from, to)| from != ready.from_episode || to < ready.to_episode);
+    reported.insert(ready.media_id.clone(), range);
+    grew
+}
+
+/// Recompute the run of a series after one of its downloads completed and
+/// emit offline-ready if it grew
+pub(super) async fn emit_if_grown(pool: &SqlitePool, app_handle: Option<&AppHandle>, media_id: &str) {
+    let profile_id = app_handle
+        .and_then(|handle| handle.try_state::<AppState>())
+        .map_or(DEFAULT_PROFILE_ID, |state| state.profile_id());
+    let ready = match query(pool, profile_id, Some(media_id)).await {
+        Ok(ready) => ready,
+        Err(e) => {
+            log::warn!("Failed to compute offline-ready episodes of {}: {}", media_id, e);
+            return;
+        }
+    };
+
+    for ready in ready.iter().filter(|r| record(r)) {
+        log::debug!(
+            "{}: episodes {}-{} ready offline",
+            ready.title, ready.from_episode, ready.to_episode
+        );
+        if let Some(handle) = app_handle {
+            OFFLINE_READY_EVENT.emit(handle, ready);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    async fn insert_download(pool: &SqlitePool, media_id: &str, episode: i32, status: &str, file_state: &str, batch_id: Option<&str>) {
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status, file_state, batch_id)
+            VALUES (?, ?, ?, ?, ?, '', ?, ?, ?, ?)
+            "#,
+        )
+        .bind(format!("{}_{}", media_id, episode))
+        .bind(media_id)
+        .bind(format!("{}-ep-{}", media_id, episode))
+        .bind(episode)
+        .bind(format!("Batch_Show_EP{}_1080p.mp4", episode))
+        .bind(format!("/nonexistent/{}_{}.mp4", media_id, episode))
+        .bind(status)
+        .bind(file_state)
+        .bind(batch_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn insert_watch(pool: &SqlitePool, profile_id: i64, media_id: &str, episode: i32, completed: bool, last_watched: &str) {
+        sqlx::query(
+            "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed, last_watched) \
+             VALUES (?, ?, ?, ?, 600, ?, ?)",
+        )
+        .bind(profile_id)
+        .bind(media_id)
+        .bind(format!("{}-ep-{}", media_id, episode))
+        .bind(episode)
+        .bind(completed)
+        .bind(last_watched)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn seeded() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('show', 'ext', 'Batch Show', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        // Episodes 1-4 were watched, 4 only halfway through
+        for episode in 1..=3 {
+            insert_watch(pool, 1, "show", episode, true, &format!("2026-01-0{} 20:00:00", episode)).await;
+        }
+        insert_watch(pool, 1, "show", 4, false, "2026-01-04 20:00:00").await;
+
+        // A batch of 4-10: 4-6 done, 7 downloaded before as a single
+        // episode, 8 failed, 9 downloading and 10 queued
+        for episode in 4..=6 {
+            insert_download(pool, "show", episode, "completed", "present", Some("batch-1")).await;
+        }
+        insert_download(pool, "show", 7, "completed", "present", None).await;
+        insert_download(pool, "show", 8, "failed", "present", Some("batch-1")).await;
+        insert_download(pool, "show", 9, "downloading", "present", Some("batch-1")).await;
+        insert_download(pool, "show", 10, "queued", "present", Some("batch-1")).await;
+        // An episode downloaded long ago, past the gap
+        insert_download(pool, "show", 12, "completed", "present", None).await;
+
+        (temp_dir, db)
+    }
+
+    #[tokio::test]
+    async fn the_run_starts_at_the_watch_position_and_ends_at_the_first_gap() {
+        let (_dir, db) = seeded().await;
+
+        let ready = query(db.pool(), 1, None).await.unwrap();
+        assert_eq!(
+            ready,
+            vec![OfflineReady {
+                media_id: "show".to_string(),
+                title: "Batch Show".to_string(),
+                from_episode: 4,
+                to_episode: 7,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_finished_episode_moves_the_position_past_it() {
+        let (_dir, db) = seeded().await;
+        let pool = db.pool();
+        sqlx::query("UPDATE watch_history SET completed = 1, last_watched = '2026-01-05 20:00:00' WHERE episode_number = 4")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let ready = query(pool, 1, None).await.unwrap();
+        assert_eq!((ready[0].from_episode, ready[0].to_episode), (5, 7));
+
+        // Another profile that hasn't watched anything starts at episode 1,
+        // which isn't downloaded
+        assert!(query(pool, 2, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_files_and_other_downloads_end_the_run() {
+        let (_dir, db) = seeded().await;
+        let pool = db.pool();
+        sqlx::query("UPDATE downloads SET file_state = 'trashed' WHERE id = 'show_6'")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let ready = query(pool, 1, None).await.unwrap();
+        assert_eq!((ready[0].from_episode, ready[0].to_episode), (4, 5));
+    }
+
+    #[tokio::test]
+    async fn only_active_batches_are_listed_unless_a_media_is_asked_for() {
+        let (_dir, db) = seeded().await;
+        let pool = db.pool();
+        // The batch finishes: 9 completes, 10 fails
+        sqlx::query("UPDATE downloads SET status = 'completed' WHERE id = 'show_9'")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE downloads SET status = 'failed' WHERE id = 'show_10'")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        assert!(query(pool, 1, None).await.unwrap().is_empty());
+
+        // The completion hook still sees the series whose batch just ended
+        let ready = query(pool, 1, Some("show")).await.unwrap();
+        assert_eq!((ready[0].from_episode, ready[0].to_episode), (4, 7));
+        // Series without any batch never come up
+        insert_download(pool, "single", 1, "completed", "present", None).await;
+        assert!(query(pool, 1, Some("single")).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_growing_runs_are_reported() {
+        let ready = |from_episode, to_episode| OfflineReady {
+            media_id: "record-test".to_string(),
+            title: "Record Test".to_string(),
+            from_episode,
+            to_episode,
+        };
+
+        assert!(record(&ready(4, 5)));
+        assert!(!record(&ready(4, 5)));
+        assert!(record(&ready(4, 7)));
+        // A shorter run from the same position (a file was deleted) is noted
+        // quietly, so growing back to 7 is reported again
+        assert!(!record(&ready(4, 6)));
+        assert!(record(&ready(4, 7)));
+        // The user watched on
+        assert!(record(&ready(6, 7)));
+    }
+}
@@ -0,0 +1,494 @@
+// Download Folder Organization
+//
+// With organize_downloads enabled, completed episodes are moved out of the
+// flat downloads directory into a per-series structure rendered from a folder
+// template (default "{title}/Season {season}"). Each path segment is
+// sanitized separately, so titles can't introduce separators or `..`.
+//
+// reorganize_existing_downloads applies the same template to files that were
+// downloaded before the setting was turned on, with an optional dry run.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use super::archive::move_file;
+use super::{DownloadManager, DownloadProgress, DownloadStatus, FileState};
+
+/// app_settings key: "true" to organize completed downloads into folders
+pub const ORGANIZE_DOWNLOADS_SETTING: &str = "organize_downloads";
+
+/// app_settings key holding the folder template
+pub const FOLDER_TEMPLATE_SETTING: &str = "download_folder_template";
+
+/// Supports {title}, {season}, {year} and {type}; `/` separates folders
+pub const DEFAULT_FOLDER_TEMPLATE: &str = "{title}/Season {season}";
+
+/// Longest allowed folder name; keeps full paths well under OS limits
+const MAX_SEGMENT_LEN: usize = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizeSettings {
+    pub enabled: bool,
+    pub template: String,
+}
+
+/// Values a folder template can reference
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    pub title: String,
+    pub season: u32,
+    pub year: Option<i32>,
+    pub content_type: Option<String>,
+}
+
+/// One planned (or performed) move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizeMove {
+    pub download_id: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReorganizeResult {
+    pub dry_run: bool,
+    pub moves: Vec<OrganizeMove>,
+    /// Completed downloads already at their templated location
+    pub unchanged: usize,
+    /// Per-file failures ("filename: reason")
+    pub failed: Vec<String>,
+}
+
+/// Read the organize settings, falling back to defaults
+pub async fn load_settings(pool: &SqlitePool) -> Result<OrganizeSettings> {
+    let enabled: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(ORGANIZE_DOWNLOADS_SETTING)
+        .fetch_optional(pool)
+        .await?;
+
+    let template: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(FOLDER_TEMPLATE_SETTING)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(OrganizeSettings {
+        enabled: enabled.map(|v| v == "true").unwrap_or(false),
+        template: template
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_FOLDER_TEMPLATE.to_string()),
+    })
+}
+
+/// Build the template context for a media entry.
+/// Falls back to the title embedded in the download filename when the media
+/// row is missing (e.g. downloads started before details were cached).
+pub async fn template_context(
+    pool: &SqlitePool,
+    media_id: &str,
+    filename: &str,
+) -> Result<TemplateContext> {
+    let row = sqlx::query("SELECT title, year, season_year, content_type FROM media WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(row) = row else {
+        let title = filename
+            .split("_EP")
+            .next()
+            .unwrap_or(filename)
+            .replace('_', " ");
+        return Ok(TemplateContext {
+            season: season_from_title(&title),
+            title,
+            year: None,
+            content_type: None,
+        });
+    };
+
+    let title: String = row.get("title");
+    let year: Option<i32> = row
+        .try_get::<Option<i32>, _>("year")
+        .ok()
+        .flatten()
+        .or_else(|| row.try_get::<Option<i32>, _>("season_year").ok().flatten());
+
+    Ok(TemplateContext {
+        season: season_from_title(&title),
+        title,
+        year,
+        content_type: row.try_get("content_type").ok().flatten(),
+    })
+}
+
+/// Guess the season number from a title ("Season 2", "2nd Season", "S3"),
+/// defaulting to 1
+pub fn season_from_title(title: &str) -> u32 {
+    let lower = title.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    for (i, word) in words.iter().enumerate() {
+        // "Season 2"
+        if *word == "season" {
+            if let Some(n) = words.get(i + 1).and_then(|w| w.parse::<u32>().ok()) {
+                return n.max(1);
+            }
+        }
+
+        // "2nd Season"
+        if words.get(i + 1) == Some(&"season") {
+            let digits: String = word.chars().take_while(|c| c.is_ascii_digit()).collect();
+            if let Ok(n) = digits.parse::<u32>() {
+                return n.max(1);
+            }
+        }
+
+        // "S2"
+        if let Some(rest) = word.strip_prefix('s') {
+            if !rest.is_empty() && rest.len() <= 2 {
+                if let Ok(n) = rest.parse::<u32>() {
+                    return n.max(1);
+                }
+            }
+        }
+    }
+
+    1
+}
+
+/// Make a single folder name safe on every platform
+pub fn sanitize_segment(segment: &str) -> String {
+    let cleaned: String = segment
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows rejects trailing dots/spaces; leading dots would hide the folder
+    let trimmed = cleaned.trim().trim_matches('.').trim();
+    let mut name: String = trimmed.chars().take(MAX_SEGMENT_LEN).collect();
+    name = name.trim_end().to_string();
+
+    const RESERVED: &[&str] = &[
+        "con", "prn", "aux", "nul",
+        "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+        "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+    ];
+    if RESERVED.contains(&name.to_lowercase().as_str()) {
+        name.push('_');
+    }
+
+    name
+}
+
+/// Render the template into a relative directory. Empty segments are dropped,
+/// so a missing {year} doesn't leave an empty folder level.
+pub fn render_template(template: &str, ctx: &TemplateContext) -> PathBuf {
+    let mut dir = PathBuf::new();
+
+    for raw in template.split(|c: char| c == '/' || c == '\\') {
+        let rendered = raw
+            .replace("{title}", &ctx.title)
+            .replace("{season}", &ctx.season.to_string())
+            .replace("{year}", &ctx.year.map(|y| y.to_string()).unwrap_or_default())
+            .replace("{type}", ctx.content_type.as_deref().unwrap_or(""));
+
+        let segment = sanitize_segment(&rendered);
+        if !segment.is_empty() {
+            dir.push(segment);
+        }
+    }
+
+    dir
+}
+
+/// Whether a relative path stays inside its base (no `..`, root or prefix)
+pub fn is_contained_relative(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Pick a free path for `filename` in `dir`, adding " (2)", " (3)"... on collision
+pub async fn unique_destination(dir: &Path, filename: &str) -> PathBuf {
+    unique_destination_excluding(dir, filename, &HashSet::new()).await
+}
+
+/// Like unique_destination, also treating the `reserved` paths as taken
+async fn unique_destination_excluding(dir: &Path, filename: &str, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let candidate = dir.join(filename);
+    if is_free(&candidate, reserved).await {
+        return candidate;
+    }
+
+    let path = Path::new(filename);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let mut n = 2;
+    loop {
+        let candidate = dir.join(format!("{} ({}){}", stem, n, ext));
+        if is_free(&candidate, reserved).await {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+async fn is_free(candidate: &Path, reserved: &HashSet<PathBuf>) -> bool {
+    !reserved.contains(candidate) && tokio::fs::metadata(candidate).await.is_err()
+}
+
+impl DownloadManager {
+    /// Move a just-completed download into its templated folder.
+    /// Returns the new path, or None when organizing is off or not applicable.
+    pub(super) async fn organize_completed(
+        pool: &SqlitePool,
+        download_dir: &Path,
+        progress: &DownloadProgress,
+    ) -> Option<PathBuf> {
+        let settings = match load_settings(pool).await {
+            Ok(s) if s.enabled => s,
+            Ok(_) => return None,
+            Err(e) => {
+                log::warn!("Failed to read organize settings: {}", e);
+                return None;
+            }
+        };
+
+        let current = PathBuf::from(&progress.file_path);
+
+        // Downloads saved to a custom location are left where the user put them
+        if current.parent() != Some(download_dir) {
+            return None;
+        }
+
+        match plan_destination(pool, download_dir, &settings.template, progress, &HashSet::new()).await {
+            Ok(Some(dest)) => match create_parent_and_move(&current, &dest).await {
+                Ok(_) => {
+                    log::debug!("Organized {} into {:?}", progress.filename, dest);
+                    Some(dest)
+                }
+                Err(e) => {
+                    log::warn!("Failed to organize {}: {:#}", progress.filename, e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!("Failed to plan folder for {}: {}", progress.filename, e);
+                None
+            }
+        }
+    }
+
+    /// Apply the folder template to already-completed downloads in the
+    /// downloads directory. With `dry_run`, only report what would move.
+    pub async fn reorganize_existing_downloads(&self, dry_run: bool) -> Result<ReorganizeResult> {
+        let pool = self
+            .db_pool
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Database not available"))?;
+        let settings = load_settings(&pool).await?;
+
+        let candidates: Vec<DownloadProgress> = {
+            let downloads = self.downloads.read().await;
+            downloads
+                .values()
+                .filter(|d| d.status == DownloadStatus::Completed && d.file_state == FileState::Present)
+                .filter(|d| Path::new(&d.file_path).starts_with(&self.download_dir))
+                .cloned()
+                .collect()
+        };
+
+        let mut result = ReorganizeResult {
+            dry_run,
+            ..Default::default()
+        };
+        // Destinations already handed out, so two downloads with the same
+        // filename don't get the same one when a dry run moves nothing
+        let mut reserved = HashSet::new();
+
+        for download in candidates {
+            let dest = match plan_destination(&pool, &self.download_dir, &settings.template, &download, &reserved).await {
+                Ok(Some(dest)) => dest,
+                Ok(None) => {
+                    result.unchanged += 1;
+                    continue;
+                }
+                Err(e) => {
+                    result.failed.push(format!("{}: {}", download.filename, e));
+                    continue;
+                }
+            };
+
+            let planned = OrganizeMove {
+                download_id: download.id.clone(),
+                from: download.file_path.clone(),
+                to: dest.to_string_lossy().to_string(),
+            };
+
+            if !dry_run {
+                if let Err(e) = create_parent_and_move(Path::new(&download.file_path), &dest).await {
+                    result.failed.push(format!("{}: {:#}", download.filename, e));
+                    continue;
+                }
+                if let Err(e) = self.set_file_path(&download.id, &dest).await {
+                    log::error!("Moved {} but failed to record its new location: {:#}", download.filename, e);
+                    result.failed.push(format!("{}: {:#}", download.filename, e));
+                    continue;
+                }
+                if let Some(pool) = &self.db_pool {
+                    super::subtitles::move_with_video(pool, &download.id, &dest).await;
+                }
+            }
+
+            reserved.insert(dest);
+            result.moves.push(planned);
+        }
+
+        log::info!(
+            "Reorganize{}: {} moves, {} unchanged, {} failed",
+            if dry_run { " (dry run)" } else { "" },
+            result.moves.len(), result.unchanged, result.failed.len()
+        );
+
+        Ok(result)
+    }
+
+    /// Point a download at a new file location (memory + database)
+    async fn set_file_path(&self, download_id: &str, path: &Path) -> Result<()> {
+        let path_str = path.to_string_lossy().to_string();
+
+        if let Some(pool) = &self.db_pool {
+            sqlx::query("UPDATE downloads SET file_path = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&path_str)
+                .bind(download_id)
+                .execute(pool.as_ref())
+                .await?;
+        }
+
+        let mut downloads = self.downloads.write().await;
+        if let Some(d) = downloads.get_mut(download_id) {
+            d.file_path = path_str;
+            self.emit_progress(d);
+        }
+
+        Ok(())
+    }
+}
+
+async fn create_parent_and_move(source: &Path, dest: &Path) -> Result<u64> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    move_file(source, dest).await
+}
+
+/// Work out where a download should live. None if it's already there.
+/// Nothing is touched on disk, so this is also what dry runs report.
+async fn plan_destination(
+    pool: &SqlitePool,
+    download_dir: &Path,
+    template: &str,
+    progress: &DownloadProgress,
+    reserved: &HashSet<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let ctx = template_context(pool, &progress.media_id, &progress.filename).await?;
+    let relative = render_template(template, &ctx);
+
+    if !is_contained_relative(&relative) {
+        anyhow::bail!("Folder template escapes the downloads directory");
+    }
+
+    let target_dir = download_dir.join(&relative);
+    let current = PathBuf::from(&progress.file_path);
+
+    if current.parent() == Some(target_dir.as_path()) {
+        return Ok(None);
+    }
+
+    let filename = current
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| progress.filename.clone());
+
+    Ok(Some(unique_destination_excluding(&target_dir, &filename, reserved).await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(title: &str) -> TemplateContext {
+        TemplateContext {
+            title: title.to_string(),
+            season: season_from_title(title),
+            year: Some(2024),
+            content_type: Some("TV".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_season_from_title() {
+        assert_eq!(season_from_title("Frieren"), 1);
+        assert_eq!(season_from_title("Mushoku Tensei Season 2"), 2);
+        assert_eq!(season_from_title("Oshi no Ko 3rd Season"), 3);
+        assert_eq!(season_from_title("Vinland Saga S2"), 2);
+        assert_eq!(season_from_title("Steins;Gate 0"), 1);
+    }
+
+    #[test]
+    fn test_render_template_sanitizes_segments() {
+        let dir = render_template(DEFAULT_FOLDER_TEMPLATE, &ctx("Re:Zero Season 3"));
+        assert_eq!(dir, PathBuf::from("Re_Zero Season 3").join("Season 3"));
+
+        // A title can't add folder levels or climb out of the downloads dir
+        let dir = render_template("{title}", &ctx("../../etc/passwd"));
+        assert_eq!(dir.components().count(), 1);
+        assert!(is_contained_relative(&dir));
+
+        // Empty placeholders don't leave empty folders behind
+        let mut no_year = ctx("Frieren");
+        no_year.year = None;
+        assert_eq!(render_template("{year}/{title}", &no_year), PathBuf::from("Frieren"));
+
+        assert_eq!(sanitize_segment("CON"), "CON_");
+        assert_eq!(sanitize_segment("  ...hidden. "), "hidden");
+    }
+
+    #[tokio::test]
+    async fn test_unique_destination_adds_numbered_suffix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(temp_dir.path().join("Episode_1.otaku"), b"a").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("Episode_1 (2).otaku"), b"b").await.unwrap();
+
+        let dest = unique_destination(temp_dir.path(), "Episode_1.otaku").await;
+        assert_eq!(dest, temp_dir.path().join("Episode_1 (3).otaku"));
+
+        let dest = unique_destination(temp_dir.path(), "Episode_2.otaku").await;
+        assert_eq!(dest, temp_dir.path().join("Episode_2.otaku"));
+    }
+
+    #[tokio::test]
+    async fn test_reserved_destinations_count_as_taken() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(temp_dir.path().join("Episode_1.otaku"), b"a").await.unwrap();
+
+        let mut reserved = HashSet::new();
+        let first = unique_destination_excluding(temp_dir.path(), "Episode_1.otaku", &reserved).await;
+        assert_eq!(first, temp_dir.path().join("Episode_1 (2).otaku"));
+        reserved.insert(first);
+
+        let second = unique_destination_excluding(temp_dir.path(), "Episode_1.otaku", &reserved).await;
+        assert_eq!(second, temp_dir.path().join("Episode_1 (3).otaku"));
+    }
+}
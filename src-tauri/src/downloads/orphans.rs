@@ -0,0 +1,383 @@
+// Orphaned Download Files
+//
+// The downloads table and the downloads directory can drift apart: after
+// clear_all_data or a database reset the files are still on disk but no row
+// points at them, and files deleted outside the app leave rows behind.
+// scan_downloads_directory walks the directory and reports both. With a
+// reconcile action, the orphaned files are either adopted as completed
+// downloads (series and episode parsed from the name, e.g.
+// Title_EP3.otaku) or deleted.
+//
+// The trash, manga chapters and unfinished HLS segment folders have their
+// own bookkeeping and are left out of the walk, as is the watch folder when
+// it lies inside the downloads directory: files waiting there aren't
+// orphans. Paths are compared canonicalized, so a download saved under
+// another spelling of its path ("downloads/./x", a symlinked folder) is
+// never mistaken for an orphan.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::batch::title_from_filename;
+use super::trash::TRASH_DIR;
+use super::watchfolder::{
+    best_match, compile_patterns, file_name_of, load_candidates, normalize_title, parse_filename,
+    MatchCandidate, ParsedFilename, VIDEO_EXTENSIONS,
+};
+use super::{DownloadManager, DownloadStatus, FileState};
+
+/// Folder chapter downloads are kept in
+const MANGA_DIR: &str = "Manga";
+
+/// How deep to look into series folders ("Title/Season 1/file")
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// Shown as the source of downloads imported by a reconcile
+const RECONCILE_SOURCE_LABEL: &str = "Downloads folder";
+
+/// Prefix of the media id given to imported files that match no media row
+const UNMATCHED_MEDIA_PREFIX: &str = "unmatched:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileAction {
+    /// Adopt orphaned files as completed downloads
+    Import,
+    /// Delete orphaned files
+    Delete,
+}
+
+/// A file in the downloads directory no download points at
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanFile {
+    pub path: String,
+    pub size: u64,
+    /// Series title and episode parsed from the name, when it could be
+    pub title: Option<String>,
+    pub episode_number: Option<i32>,
+    /// Media row the title matched
+    pub media_id: Option<String>,
+}
+
+/// A completed download whose file isn't there
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingFile {
+    pub download_id: String,
+    pub media_id: String,
+    pub episode_number: i32,
+    pub filename: String,
+    pub file_path: String,
+    /// On archive storage, which may just not be mounted
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadDirectoryScan {
+    pub orphan_files: Vec<OrphanFile>,
+    /// Total size of orphan_files
+    pub orphan_bytes: u64,
+    pub missing_files: Vec<MissingFile>,
+    pub reconcile: Option<ReconcileAction>,
+    /// Paths adopted as downloads
+    pub imported: Vec<String>,
+    /// Paths deleted
+    pub deleted: Vec<String>,
+    /// Orphans the reconcile left alone ("path: reason")
+    pub failed: Vec<String>,
+}
+
+/// Video files under `dir` with their sizes, skipping folders other parts
+/// of the app manage
+fn list_files(dir: &Path, depth: usize, out: &mut Vec<(PathBuf, u64)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if metadata.is_dir() {
+            let managed = (depth == 0 && (name == TRASH_DIR || name == MANGA_DIR)) || name.ends_with(".parts");
+            if !managed && depth < MAX_SCAN_DEPTH {
+                list_files(&path, depth + 1, out);
+            }
+            continue;
+        }
+
+        let is_video = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("otaku") || VIDEO_EXTENSIONS.iter().any(|v| e.eq_ignore_ascii_case(v)))
+            .unwrap_or(false);
+        if is_video {
+            out.push((path, metadata.len()));
+        }
+    }
+}
+
+/// `path` resolved through symlinks and `.`/`..`, or as given when it
+/// can't be (e.g. it doesn't exist)
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Series title and episode from a download's filename: the app's own
+/// Title_EP3_1080p.otaku names first, then the watch folder templates
+pub fn parse_download_filename(file_name: &str, patterns: &[Regex]) -> Option<ParsedFilename> {
+    if let Some((_, rest)) = file_name.split_once("_EP") {
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let title = title_from_filename(file_name).trim().to_string();
+        if let (Ok(episode), false) = (digits.parse::<i32>(), title.is_empty()) {
+            return Some(ParsedFilename {
+                season: super::organize::season_from_title(&title),
+                title,
+                episode,
+            });
+        }
+    }
+    parse_filename(file_name, patterns)
+}
+
+/// Media id for an imported file: the matching media row's, or one derived
+/// from the title that repair_download_media_links can give a placeholder
+fn import_media_id(parsed: &ParsedFilename, candidates: &[MatchCandidate]) -> String {
+    match best_match(parsed, candidates) {
+        Some(candidate) => candidate.media_id.clone(),
+        None => format!("{}{}", UNMATCHED_MEDIA_PREFIX, normalize_title(&parsed.title).replace(' ', "-")),
+    }
+}
+
+impl DownloadManager {
+    /// Compare the downloads directory with the downloads table, reporting
+    /// files no download points at and completed downloads whose file is
+    /// gone. With `reconcile`, the orphaned files are imported or deleted.
+    pub async fn scan_downloads_directory(&self, reconcile: Option<ReconcileAction>) -> Result<DownloadDirectoryScan> {
+        let watch_folder = match &self.db_pool {
+            Some(pool) => super::watchfolder::load_settings(pool).await?.path.map(PathBuf::from),
+            None => None,
+        };
+        let dir = self.download_dir.clone();
+        let tracked: Vec<PathBuf> = self.downloads.read().await.values().map(|d| PathBuf::from(&d.file_path)).collect();
+        let (files, known) = tokio::task::spawn_blocking(move || {
+            let mut files = Vec::new();
+            list_files(&dir, 0, &mut files);
+            let watch_folder = watch_folder.map(|w| canonical(&w));
+            let files: Vec<(PathBuf, u64)> = files
+                .into_iter()
+                .map(|(path, size)| (canonical(&path), size))
+                .filter(|(path, _)| !watch_folder.as_ref().is_some_and(|w| path.starts_with(w)))
+                .collect();
+            let known: HashSet<PathBuf> = tracked.iter().map(|path| canonical(path)).collect();
+            (files, known)
+        })
+        .await?;
+
+        let mut report = DownloadDirectoryScan { reconcile, ..Default::default() };
+        {
+            let downloads = self.downloads.read().await;
+            for d in downloads.values() {
+                let finished = matches!(d.status, DownloadStatus::Completed | DownloadStatus::Offline);
+                if finished && d.file_state != FileState::Trashed && !Path::new(&d.file_path).exists() {
+                    report.missing_files.push(MissingFile {
+                        download_id: d.id.clone(),
+                        media_id: d.media_id.clone(),
+                        episode_number: d.episode_number,
+                        filename: d.filename.clone(),
+                        file_path: d.file_path.clone(),
+                        archived: d.archived,
+                    });
+                }
+            }
+        }
+        report.missing_files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+        let patterns = compile_patterns(&[])?;
+        let candidates = match &self.db_pool {
+            Some(pool) => load_candidates(pool).await?,
+            None => Vec::new(),
+        };
+
+        let mut orphans: Vec<(PathBuf, u64)> = files.into_iter().filter(|(path, _)| !known.contains(path)).collect();
+        orphans.sort();
+
+        for (path, size) in orphans {
+            let parsed = parse_download_filename(&file_name_of(&path), &patterns);
+            let path_str = path.to_string_lossy().to_string();
+            report.orphan_bytes += size;
+            report.orphan_files.push(OrphanFile {
+                path: path_str.clone(),
+                size,
+                title: parsed.as_ref().map(|p| p.title.clone()),
+                episode_number: parsed.as_ref().map(|p| p.episode),
+                media_id: parsed
+                    .as_ref()
+                    .and_then(|p| best_match(p, &candidates))
+                    .map(|c| c.media_id.clone()),
+            });
+
+            match reconcile {
+                None => {}
+                Some(ReconcileAction::Delete) => match tokio::fs::remove_file(&path).await {
+                    Ok(()) => report.deleted.push(path_str),
+                    Err(e) => report.failed.push(format!("{}: {}", path_str, e)),
+                },
+                Some(ReconcileAction::Import) => {
+                    let Some(parsed) = parsed else {
+                        report.failed.push(format!("{}: couldn't tell the series and episode from the name", path_str));
+                        continue;
+                    };
+                    let media_id = import_media_id(&parsed, &candidates);
+                    if self.is_episode_downloaded(&media_id, parsed.episode).await {
+                        report.failed.push(format!("{}: episode {} is already downloaded", path_str, parsed.episode));
+                        continue;
+                    }
+                    let result = self
+                        .adopt_titled_file(
+                            &media_id,
+                            Some(parsed.title.clone()),
+                            parsed.episode,
+                            &path,
+                            Some(RECONCILE_SOURCE_LABEL.to_string()),
+                        )
+                        .await;
+                    match result {
+                        Ok(_) => report.imported.push(path_str),
+                        Err(e) => report.failed.push(format!("{}: {}", path_str, e)),
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "Downloads directory scan: {} orphaned file(s) ({} bytes), {} missing file(s), {} imported, {} deleted",
+            report.orphan_files.len(),
+            report.orphan_bytes,
+            report.missing_files.len(),
+            report.imported.len(),
+            report.deleted.len()
+        );
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::Arc;
+
+    #[test]
+    fn parses_app_and_release_style_names() {
+        let patterns = compile_patterns(&[]).unwrap();
+
+        let parsed = parse_download_filename("Sousou_no_Frieren_EP12_1080p.otaku", &patterns).unwrap();
+        assert_eq!(parsed.title, "Sousou no Frieren");
+        assert_eq!(parsed.episode, 12);
+
+        let parsed = parse_download_filename("Show Name - 05.mkv", &patterns).unwrap();
+        assert_eq!((parsed.title.as_str(), parsed.episode), ("Show Name", 5));
+
+        assert!(parse_download_filename("trailer.mp4", &patterns).is_none());
+    }
+
+    #[tokio::test]
+    async fn scan_reports_and_imports_orphans() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('frieren', 'ext', 'Sousou no Frieren', 'anime')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let downloads_dir = temp_dir.path().join("downloads");
+        std::fs::create_dir_all(downloads_dir.join(TRASH_DIR)).unwrap();
+        std::fs::create_dir_all(downloads_dir.join(MANGA_DIR)).unwrap();
+        let manager = DownloadManager::new(downloads_dir.clone()).with_database(Arc::new(pool));
+
+        // Tracked, orphaned, unparseable, and files in managed folders
+        let tracked = downloads_dir.join("Tracked_EP1.otaku");
+        std::fs::write(&tracked, vec![0u8; 10]).unwrap();
+        manager.adopt_file("tracked", 1, &tracked, None).await.unwrap();
+        std::fs::write(downloads_dir.join("Sousou_no_Frieren_EP3.otaku"), vec![0u8; 30]).unwrap();
+        std::fs::write(downloads_dir.join("Unknown_Show_EP2.mp4"), vec![0u8; 20]).unwrap();
+        std::fs::write(downloads_dir.join("clip.mp4"), vec![0u8; 5]).unwrap();
+        std::fs::write(downloads_dir.join(TRASH_DIR).join("Old_EP1.otaku"), vec![0u8; 5]).unwrap();
+        std::fs::write(downloads_dir.join(MANGA_DIR).join("page.mp4"), vec![0u8; 5]).unwrap();
+
+        // A completed download whose file was deleted
+        let gone = downloads_dir.join("Gone_EP1.otaku");
+        std::fs::write(&gone, vec![0u8; 10]).unwrap();
+        let gone_id = manager.adopt_file("gone", 1, &gone, None).await.unwrap().id;
+        std::fs::remove_file(&gone).unwrap();
+
+        let report = manager.scan_downloads_directory(None).await.unwrap();
+        assert_eq!(report.orphan_files.len(), 3);
+        assert_eq!(report.orphan_bytes, 55);
+        let frieren = report.orphan_files.iter().find(|f| f.path.ends_with("Sousou_no_Frieren_EP3.otaku")).unwrap();
+        assert_eq!(frieren.media_id.as_deref(), Some("frieren"));
+        assert_eq!(frieren.episode_number, Some(3));
+        assert_eq!(report.missing_files.len(), 1);
+        assert_eq!(report.missing_files[0].download_id, gone_id);
+
+        let report = manager.scan_downloads_directory(Some(ReconcileAction::Import)).await.unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].contains("clip.mp4"));
+        assert!(manager.is_episode_downloaded("frieren", 3).await);
+        assert!(manager.is_episode_downloaded("unmatched:unknown-show", 2).await);
+
+        // Only the unparseable file is left to delete
+        let report = manager.scan_downloads_directory(Some(ReconcileAction::Delete)).await.unwrap();
+        assert_eq!(report.deleted.len(), 1);
+        assert!(!downloads_dir.join("clip.mp4").exists());
+        assert!(downloads_dir.join(TRASH_DIR).join("Old_EP1.otaku").exists());
+    }
+
+    #[tokio::test]
+    async fn delete_spares_tracked_files_under_other_paths_and_the_watch_folder() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+
+        let downloads_dir = temp_dir.path().join("downloads");
+        let incoming = downloads_dir.join("Incoming");
+        std::fs::create_dir_all(downloads_dir.join("Series")).unwrap();
+        std::fs::create_dir_all(&incoming).unwrap();
+        crate::downloads::watchfolder::save_settings(
+            &pool,
+            &crate::downloads::watchfolder::WatchFolderSettings {
+                enabled: true,
+                path: Some(incoming.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let manager = DownloadManager::new(downloads_dir.join(".")).with_database(Arc::new(pool));
+
+        // Saved through a path spelled differently from the one the walk finds
+        let tracked = downloads_dir.join("Series").join("Tracked_EP1.otaku");
+        std::fs::write(&tracked, vec![0u8; 10]).unwrap();
+        let roundabout = downloads_dir.join("Series").join("..").join("Series").join("Tracked_EP1.otaku");
+        manager.adopt_file("tracked", 1, &roundabout, None).await.unwrap();
+        std::fs::write(incoming.join("New Show - 01.mkv"), vec![0u8; 10]).unwrap();
+        std::fs::write(downloads_dir.join("Stray_EP4.otaku"), vec![0u8; 10]).unwrap();
+
+        let report = manager.scan_downloads_directory(Some(ReconcileAction::Delete)).await.unwrap();
+        assert_eq!(report.orphan_files.len(), 1);
+        assert_eq!(report.deleted.len(), 1);
+        assert!(report.deleted[0].ends_with("Stray_EP4.otaku"));
+        assert!(tracked.exists());
+        assert!(incoming.join("New Show - 01.mkv").exists());
+    }
+}
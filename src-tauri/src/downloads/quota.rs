@@ -0,0 +1,437 @@
+// Download Storage Quota
+//
+// Opt-in cap on the space downloads take (download_storage_quota_gb, unset or
+// 0 for none). When a new download wouldn't fit under it, downloads of
+// watched episodes are deleted, least recently watched first, until it does.
+// Which episodes count as watched is auto-delete's rule: every profile
+// following the series finished them and no profile favorited it. When
+// evicting everything allowed still isn't enough, nothing is deleted and
+// queueing fails with QueueError::QuotaExceeded. Downloads queued without a
+// source are checked once it's resolved, and fail the same way.
+//
+// The space counted is that of completed downloads (get_total_storage_used)
+// plus the size of downloads still queued or running, whose estimate is
+// stored on them, so queueing several episodes in a row can't each slip
+// under the same total. A download whose size the server doesn't tell (HLS
+// playlists among them) counts as 0.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::auto_delete::{watched_episodes, AutoDeletedEpisode};
+use super::{size_estimate, DownloadManager, DownloadProgress, DownloadStatus, FileState, QueueError};
+use crate::locale::{self, Locale};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key: gigabytes downloads may take up; unset or 0 for no quota
+pub const QUOTA_SETTING: &str = "download_storage_quota_gb";
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// A download that doesn't fit under the quota even after evicting every
+/// watched episode that may go
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuotaExceeded {
+    pub quota_bytes: u64,
+    /// Completed downloads plus those still queued or running
+    pub used_bytes: u64,
+    /// Size of the new download; 0 when unknown
+    pub needed_bytes: u64,
+    /// What evicting every watched episode outside favorites would free
+    pub evictable_bytes: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Download storage quota of {} reached: {} used, this download needs {} and watched episodes only free {}",
+            locale::format_bytes(self.quota_bytes, Locale::En),
+            locale::format_bytes(self.used_bytes, Locale::En),
+            locale::format_bytes(self.needed_bytes, Locale::En),
+            locale::format_bytes(self.evictable_bytes, Locale::En)
+        )
+    }
+}
+
+/// The quota in bytes, None when there is none
+pub async fn quota_bytes(pool: &SqlitePool) -> Option<u64> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(QUOTA_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    let gb = value.and_then(|v| v.trim().parse::<f64>().ok()).filter(|gb| *gb > 0.0)?;
+    Some((gb * GB) as u64)
+}
+
+/// How many of `sizes` (in eviction order) have to go for `needed` more
+/// bytes to fit next to `used` under `quota`; None when even all of them
+/// aren't enough
+fn evictions_needed(used: u64, needed: u64, quota: u64, sizes: &[u64]) -> Option<usize> {
+    let mut total = used + needed;
+    let mut count = 0;
+    for size in sizes {
+        if total <= quota {
+            break;
+        }
+        total = total.saturating_sub(*size);
+        count += 1;
+    }
+    (total <= quota).then_some(count)
+}
+
+impl DownloadManager {
+    /// Bytes the quota counts: completed downloads whose file is in place
+    /// and the known size of downloads that haven't finished
+    async fn quota_usage(&self) -> u64 {
+        let pending: u64 = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .filter(|d| matches!(d.status, DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused))
+            .map(|d| d.total_bytes)
+            .sum();
+        self.get_total_storage_used().await + pending
+    }
+
+    /// Make room under the storage quota for a download of `needed` bytes of
+    /// episode `episode_number` of `media_id` (whose own download is never
+    /// evicted), returning the episodes deleted for it. Fails with
+    /// QueueError::QuotaExceeded, deleting nothing, when it can't fit.
+    pub async fn make_room_under_quota(
+        &self,
+        media_id: &str,
+        episode_number: i32,
+        needed: u64,
+    ) -> Result<Vec<AutoDeletedEpisode>> {
+        let Some(pool) = self.db_pool.clone() else {
+            return Ok(Vec::new());
+        };
+        let Some(quota) = quota_bytes(&pool).await else {
+            return Ok(Vec::new());
+        };
+        let used = self.quota_usage().await;
+        if used + needed <= quota {
+            return Ok(Vec::new());
+        }
+
+        let mut watched = watched_episodes(&pool).await.context("Failed to load watched episodes")?;
+        watched.sort_by_key(|e| e.watched_at);
+        let candidates: Vec<AutoDeletedEpisode> = {
+            let downloads = self.downloads.read().await;
+            let mut seen = HashSet::new();
+            watched
+                .into_iter()
+                .filter(|e| !(e.media_id == media_id && e.episode_number == episode_number))
+                .filter_map(|e| {
+                    let download = downloads.values().find(|d| {
+                        d.media_id == e.media_id
+                            && d.episode_number == e.episode_number
+                            && d.status == DownloadStatus::Completed
+                            && d.file_state == FileState::Present
+                    })?;
+                    seen.insert(download.id.clone()).then(|| AutoDeletedEpisode {
+                        media_id: e.media_id,
+                        title: download.display_title(),
+                        episode_number: e.episode_number,
+                        bytes: download.total_bytes,
+                    })
+                })
+                .collect()
+        };
+
+        let sizes: Vec<u64> = candidates.iter().map(|e| e.bytes).collect();
+        let Some(count) = evictions_needed(used, needed, quota, &sizes) else {
+            let exceeded = QuotaExceeded {
+                quota_bytes: quota,
+                used_bytes: used,
+                needed_bytes: needed,
+                evictable_bytes: sizes.iter().sum(),
+            };
+            log::warn!("Not queueing {} episode {}: {}", media_id, episode_number, exceeded);
+            return Err(QueueError::QuotaExceeded(exceeded).into());
+        };
+
+        let mut evicted = Vec::new();
+        for episode in candidates.into_iter().take(count) {
+            if let Err(e) = self.delete_episode_download(&episode.media_id, episode.episode_number).await {
+                log::warn!(
+                    "Failed to evict {} episode {}: {}",
+                    episode.media_id, episode.episode_number, e
+                );
+                continue;
+            }
+            evicted.push(episode);
+        }
+
+        if !evicted.is_empty() {
+            log::info!("Evicted {} watched episode download(s) to stay under the storage quota", evicted.len());
+            if let Some(handle) = &self.app_handle {
+                let locale = locale::current_locale(pool.as_ref()).await;
+                let _ = emit_notification(handle, Some(pool.as_ref()), eviction_notification(&evicted, quota, locale)).await;
+            }
+        }
+
+        Ok(evicted)
+    }
+
+    /// Make room under the quota for `progress` before it's queued, storing
+    /// its estimated size on total_bytes so later checks count it. Downloads
+    /// without a URL yet are left for reserve_quota_for_resolved.
+    pub(super) async fn reserve_quota(&self, progress: &mut DownloadProgress) -> Result<()> {
+        let Some(pool) = self.db_pool.as_ref().filter(|_| !progress.url.is_empty()) else {
+            return Ok(());
+        };
+        if quota_bytes(pool).await.is_none() {
+            return Ok(());
+        }
+
+        // The probe is cached, so check_disk_space doesn't repeat it
+        let size = size_estimate::estimate_sizes(std::slice::from_ref(&progress.url), &progress.headers).await[0].size;
+        self.make_room_under_quota(&progress.media_id, progress.episode_number, size.unwrap_or(0))
+            .await?;
+        if let Some(size) = size.filter(|_| progress.total_bytes == 0) {
+            progress.total_bytes = size;
+        }
+        Ok(())
+    }
+
+    /// reserve_quota for a queued download whose source was just resolved
+    pub(super) async fn reserve_quota_for_resolved(&self, download_id: &str) -> Result<()> {
+        let Some(mut progress) = self.downloads.read().await.get(download_id).cloned() else {
+            return Ok(());
+        };
+        // Its own (unknown) size isn't part of the usage yet
+        self.reserve_quota(&mut progress).await?;
+
+        if let Some(current) = self.downloads.write().await.get_mut(download_id) {
+            if current.total_bytes == 0 {
+                current.total_bytes = progress.total_bytes;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Summary notification for downloads evicted to make room
+fn eviction_notification(evicted: &[AutoDeletedEpisode], quota: u64, locale: Locale) -> NotificationPayload {
+    let episodes: Vec<String> = evicted
+        .iter()
+        .map(|e| format!("{} {}", e.title, locale::episode_label(e.episode_number, locale)))
+        .collect();
+    let freed: u64 = evicted.iter().map(|e| e.bytes).sum();
+
+    NotificationPayload::new(
+        NotificationType::Info,
+        "Download Storage Quota",
+        format!(
+            "Deleted {} ({}) to stay under the {} quota",
+            episodes.join(", "),
+            locale::format_bytes(freed, locale),
+            locale::format_bytes(quota, locale)
+        ),
+    )
+    .with_source("download")
+    .with_action("Open Downloads", Some("/downloads".to_string()), None)
+    .with_metadata(serde_json::json!({ "evicted": evicted }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn evicts_only_as_many_as_needed() {
+        assert_eq!(evictions_needed(500, 400, 1000, &[300, 300]), Some(0));
+        assert_eq!(evictions_needed(900, 400, 1000, &[200, 300, 300]), Some(2));
+        assert_eq!(evictions_needed(900, 400, 1000, &[100, 100]), None);
+        assert_eq!(evictions_needed(1200, 0, 1000, &[]), None);
+    }
+
+    #[tokio::test]
+    async fn the_least_recently_watched_episodes_make_room() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let now = chrono::Utc::now().timestamp();
+
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type) VALUES ('show', 'ext', 'Show', 'anime'), ('fav', 'ext', 'Fav', 'anime')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO library (media_id, status, favorite, profile_id) VALUES ('fav', 'watching', 1, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Episode 2 was watched longest ago, then 1, then 3; 4 isn't finished
+        for (media_id, episode, completed, hours_ago) in
+            [("show", 1, 1, 10), ("show", 2, 1, 20), ("show", 3, 1, 5), ("show", 4, 0, 1), ("fav", 1, 1, 30)]
+        {
+            sqlx::query(
+                r#"
+                INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed, last_watched)
+                VALUES (1, ?, ?, ?, 100, ?, datetime(?, 'unixepoch'))
+                "#,
+            )
+            .bind(media_id)
+            .bind(format!("{}-{}", media_id, episode))
+            .bind(episode)
+            .bind(completed)
+            .bind(now - hours_ago * 60 * 60)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool.clone()));
+        let mut files = HashMap::new();
+        for (media_id, episode) in [("show", 1), ("show", 2), ("show", 3), ("show", 4), ("fav", 1)] {
+            let path = temp_dir.path().join(format!("{}-{}.mp4", media_id, episode));
+            std::fs::write(&path, vec![0u8; 1024]).unwrap();
+            manager.adopt_file(media_id, episode, &path, None).await.unwrap();
+            files.insert((media_id, episode), path);
+        }
+
+        // No quota, nothing to do
+        assert!(manager.make_room_under_quota("show", 5, 1 << 40).await.unwrap().is_empty());
+
+        // 5 KB used of a 5.5 KB quota: a 2 KB download needs two episodes gone
+        sqlx::query("INSERT INTO app_settings (key, value, updated_at) VALUES (?, ?, 0)")
+            .bind(QUOTA_SETTING)
+            .bind((5.5 * 1024.0 / GB).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let evicted = manager.make_room_under_quota("show", 5, 2048).await.unwrap();
+        let numbers: Vec<i32> = evicted.iter().map(|e| e.episode_number).collect();
+        assert_eq!(numbers, vec![2, 1]);
+        assert!(!files[&("show", 2)].exists());
+        assert!(!files[&("show", 1)].exists());
+        for key in [("show", 3), ("show", 4), ("fav", 1)] {
+            assert!(files[&key].exists());
+        }
+
+        // Only episode 3 may still go, which isn't enough: nothing is deleted
+        let error = manager.make_room_under_quota("show", 5, 4096).await.unwrap_err();
+        match error.downcast::<QueueError>().unwrap() {
+            QueueError::QuotaExceeded(exceeded) => {
+                assert_eq!(exceeded.used_bytes, 3072);
+                assert_eq!(exceeded.evictable_bytes, 1024);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(files[&("show", 3)].exists());
+    }
+
+    #[tokio::test]
+    async fn episodes_another_profile_still_follows_or_favorited_stay() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let other = crate::database::profiles::create_profile(&pool, "Other").await.unwrap().id;
+
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type) VALUES ('shared', 'ext', 'Shared', 'anime'), ('liked', 'ext', 'Liked', 'anime')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // The active profile finished both; the other one follows shared
+        // without having finished it and favorited liked
+        for (media_id, favorite) in [("shared", 0), ("liked", 1)] {
+            sqlx::query("INSERT INTO library (media_id, status, favorite, profile_id) VALUES (?, 'watching', ?, ?)")
+                .bind(media_id)
+                .bind(favorite)
+                .bind(other)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                "INSERT INTO watch_history (profile_id, media_id, episode_id, episode_number, progress_seconds, completed) \
+                 VALUES (1, ?, ?, 1, 100, 1)",
+            )
+            .bind(media_id)
+            .bind(format!("{}-1", media_id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool.clone()));
+        for media_id in ["shared", "liked"] {
+            let path = temp_dir.path().join(format!("{}-1.mp4", media_id));
+            std::fs::write(&path, vec![0u8; 1024]).unwrap();
+            manager.adopt_file(media_id, 1, &path, None).await.unwrap();
+        }
+        sqlx::query("INSERT INTO app_settings (key, value, updated_at) VALUES (?, ?, 0)")
+            .bind(QUOTA_SETTING)
+            .bind((2048.0 / GB).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let error = manager.make_room_under_quota("new", 1, 1024).await.unwrap_err();
+        match error.downcast::<QueueError>().unwrap() {
+            QueueError::QuotaExceeded(exceeded) => assert_eq!(exceeded.evictable_bytes, 0),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_downloads_keep_their_estimated_size() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1024\r\nAccept-Ranges: bytes\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        sqlx::query("INSERT INTO app_settings (key, value, updated_at) VALUES (?, '1', 0)")
+            .bind(QUOTA_SETTING)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool));
+
+        let mut progress: DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": "show_1",
+            "media_id": "show",
+            "episode_id": "show-1",
+            "episode_number": 1,
+            "filename": "Show_EP1.mp4",
+            "url": format!("http://{}/show-1.mp4", addr),
+            "file_path": temp_dir.path().join("Show_EP1.mp4").to_string_lossy(),
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": "queued",
+            "error_message": null,
+        }))
+        .unwrap();
+        manager.reserve_quota(&mut progress).await.unwrap();
+        assert_eq!(progress.total_bytes, 1024);
+    }
+}
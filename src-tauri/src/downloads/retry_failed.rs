@@ -0,0 +1,537 @@
+// Bulk Retry of Failed Downloads
+//
+// After a network outage a whole queue can end up failed. retry_failed
+// groups the failed downloads by what went wrong (from their error message)
+// and retries the ones worth retrying in one go:
+// - network errors, interrupted transfers and unknown errors are queued
+//   again, a moment apart so they don't all hit the server at once. Their
+//   URL is checked with a quick HEAD first; an expired one is treated like
+//   the next group.
+// - expired (401/403) and missing (404/410) source URLs get fresh sources
+//   from the extension the episode came from, when that's known
+// - disk errors are left alone until space is freed
+// A retry that errors part-way is recorded in its group and the rest carry
+// on. The caller gets a summary per group, and one notification sums it up.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::lazy_source::ResolvedSource;
+use super::{batch, download_headers, DownloadManager, DownloadProgress, DownloadStatus};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// Pause between requeued downloads
+const RETRY_STAGGER: Duration = Duration::from_millis(250);
+
+/// Timeout of the HEAD request checking a URL
+const URL_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// URLs checked at the same time
+const URL_CHECK_CONCURRENCY: usize = 4;
+
+/// Why a download failed, from its error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCause {
+    /// Connection, timeout or server errors
+    Network,
+    /// Out of space or the file couldn't be written
+    Disk,
+    /// The source URL is gone (404/410)
+    NotFound,
+    /// The source URL expired (401/403)
+    Expired,
+    /// The transfer was stopped part-way, e.g. by closing the app
+    Interrupted,
+    Other,
+}
+
+impl FailureCause {
+    /// Order groups are reported in
+    const ALL: [FailureCause; 6] = [
+        Self::Network,
+        Self::Interrupted,
+        Self::Expired,
+        Self::NotFound,
+        Self::Disk,
+        Self::Other,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Disk => "disk",
+            Self::NotFound => "not found",
+            Self::Expired => "expired",
+            Self::Interrupted => "interrupted",
+            Self::Other => "other",
+        }
+    }
+
+    /// The stored URL is known to be dead; only fresh sources help
+    fn needs_new_source(&self) -> bool {
+        matches!(self, Self::NotFound | Self::Expired)
+    }
+}
+
+/// Whether a lowercased error message names an HTTP status, as reqwest
+/// ("(403 forbidden)") or perform_download ("http 403") words it
+pub(super) fn has_status(message: &str, code: u16) -> bool {
+    message.contains(&format!("http {}", code)) || message.contains(&format!("({} ", code))
+}
+
+/// Group a failure by its error message
+pub fn classify_failure(error_message: Option<&str>) -> FailureCause {
+    let Some(message) = error_message else {
+        return FailureCause::Other;
+    };
+    let message = message.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+
+    if has_status(&message, 404) || has_status(&message, 410) {
+        FailureCause::NotFound
+    } else if has_status(&message, 401) || has_status(&message, 403) {
+        FailureCause::Expired
+    } else if mentions(&["disk space", "no space", "os error 28", "read-only", "failed to create file", "failed to write"]) {
+        FailureCause::Disk
+    } else if mentions(&["cancelled", "interrupted", "paused"]) {
+        FailureCause::Interrupted
+    } else if mentions(&[
+        "failed to initiate",
+        "failed to read",
+        "failed to fetch",
+        "error sending request",
+        "timed out",
+        "timeout",
+        "connection",
+        "dns",
+        "network",
+        "http 5",
+    ]) {
+        FailureCause::Network
+    } else {
+        FailureCause::Other
+    }
+}
+
+/// Which failed downloads to retry; everything by default
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RetryFailedFilter {
+    pub media_id: Option<String>,
+    /// Only failures of these causes
+    pub causes: Option<Vec<FailureCause>>,
+}
+
+/// What happened to the failed downloads of one cause
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetryGroup {
+    pub cause: FailureCause,
+    pub failed: usize,
+    /// Queued again (including re-resolved ones)
+    pub requeued: usize,
+    /// Queued again with a fresh source URL from the extension
+    pub re_resolved: usize,
+    /// Left failed: disk errors, or dead URLs with no extension to ask
+    pub skipped: usize,
+    /// Retries that went wrong part-way; the rest carried on
+    pub errors: Vec<RetryError>,
+    pub download_ids: Vec<String>,
+}
+
+/// A download whose retry couldn't be carried out
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetryError {
+    pub download_id: String,
+    pub error: String,
+}
+
+impl RetryError {
+    fn new(download_id: &str, error: &anyhow::Error) -> Self {
+        Self {
+            download_id: download_id.to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetryFailedSummary {
+    pub groups: Vec<RetryGroup>,
+    pub requeued: usize,
+    pub skipped: usize,
+    /// Retries that errored, listed per group
+    pub errored: usize,
+}
+
+/// Whether a URL answers a HEAD as expired or gone. Anything else (network
+/// errors, servers that don't allow HEAD) gets the benefit of the doubt.
+async fn url_is_stale(client: &reqwest::Client, url: &str, headers: &HashMap<String, String>) -> bool {
+    match client.head(url).headers(download_headers(headers)).send().await {
+        Ok(response) => matches!(response.status().as_u16(), 401 | 403 | 404 | 410),
+        Err(_) => false,
+    }
+}
+
+/// The notification summing up a bulk retry
+fn summary_notification(summary: &RetryFailedSummary) -> NotificationPayload {
+    let failed: usize = summary.groups.iter().map(|g| g.failed).sum();
+    let mut message = format!("Retrying {} of {} failed downloads", summary.requeued, failed);
+    let skipped: Vec<String> = summary
+        .groups
+        .iter()
+        .filter(|g| g.skipped > 0)
+        .map(|g| format!("{} {}", g.skipped, g.cause.label()))
+        .collect();
+    if !skipped.is_empty() {
+        message.push_str(&format!(". Skipped: {}", skipped.join(", ")));
+    }
+    if summary.errored > 0 {
+        message.push_str(&format!(". {} could not be retried", summary.errored));
+    }
+
+    let notification_type = if summary.skipped > 0 || summary.errored > 0 { NotificationType::Warning } else { NotificationType::Info };
+    NotificationPayload::new(notification_type, "Retrying Failed Downloads", message)
+        .with_source("download")
+        .with_action("Open Downloads", Some("/downloads".to_string()), None)
+        .with_metadata(serde_json::json!({
+            "requeued": summary.requeued,
+            "skipped": summary.skipped,
+            "errored": summary.errored,
+            "groups": summary.groups,
+        }))
+}
+
+impl DownloadManager {
+    /// Extension a download's episode can be re-resolved from: the one it
+    /// was queued from, or the extension of its media row
+    pub(super) async fn source_extension(db_pool: Option<&Arc<SqlitePool>>, download: &DownloadProgress) -> Option<String> {
+        if let Some(extension_id) = &download.source_extension_id {
+            return Some(extension_id.clone());
+        }
+        let pool = db_pool?;
+        sqlx::query_scalar::<_, String>("SELECT extension_id FROM media WHERE id = ?")
+            .bind(&download.media_id)
+            .fetch_optional(pool.as_ref())
+            .await
+            .ok()
+            .flatten()
+            .filter(|extension_id| extension_id != "jikan")
+    }
+
+    /// Retry failed downloads matching `filter`, grouped by cause. Dead
+    /// source URLs are re-resolved with `resolve(extension_id, download)`.
+    pub async fn retry_failed_downloads<R, Fut>(&self, filter: &RetryFailedFilter, resolve: R) -> Result<RetryFailedSummary>
+    where
+        R: Fn(String, DownloadProgress) -> Fut,
+        Fut: Future<Output = Result<ResolvedSource>>,
+    {
+        let mut failed: Vec<(DownloadProgress, FailureCause)> = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .filter(|d| d.status == DownloadStatus::Failed)
+            .filter(|d| filter.media_id.as_ref().map_or(true, |id| &d.media_id == id))
+            .map(|d| (d.clone(), classify_failure(d.error_message.as_deref())))
+            .filter(|(_, cause)| filter.causes.as_ref().map_or(true, |causes| causes.contains(cause)))
+            .collect();
+        failed.sort_by(|(a, _), (b, _)| (&a.media_id, a.episode_number).cmp(&(&b.media_id, b.episode_number)));
+
+        // URLs that look fine from the error may have expired since
+        let client = reqwest::Client::builder().timeout(URL_CHECK_TIMEOUT).build()?;
+        let to_check: Vec<(String, String, HashMap<String, String>)> = failed
+            .iter()
+            .filter(|(d, cause)| *cause != FailureCause::Disk && !cause.needs_new_source() && !d.url.is_empty())
+            .map(|(d, _)| (d.id.clone(), d.url.clone(), d.headers.clone()))
+            .collect();
+        let stale: HashSet<String> = futures_util::stream::iter(to_check)
+        .map(|(id, url, headers)| {
+            let client = &client;
+            async move { url_is_stale(client, &url, &headers).await.then_some(id) }
+        })
+        .buffer_unordered(URL_CHECK_CONCURRENCY)
+        .filter_map(|id| async move { id })
+        .collect()
+        .await;
+
+        let mut groups: Vec<RetryGroup> = Vec::new();
+        let mut batches = HashSet::new();
+        let mut requeued_any = false;
+
+        for (download, cause) in failed {
+            let index = match groups.iter().position(|g| g.cause == cause) {
+                Some(index) => index,
+                None => {
+                    groups.push(RetryGroup {
+                        cause,
+                        failed: 0,
+                        requeued: 0,
+                        re_resolved: 0,
+                        skipped: 0,
+                        errors: Vec::new(),
+                        download_ids: Vec::new(),
+                    });
+                    groups.len() - 1
+                }
+            };
+            groups[index].failed += 1;
+            groups[index].download_ids.push(download.id.clone());
+
+            if cause == FailureCause::Disk {
+                groups[index].skipped += 1;
+                continue;
+            }
+
+            let mut re_resolved = false;
+            if cause.needs_new_source() || stale.contains(&download.id) {
+                let Some(extension_id) = Self::source_extension(self.db_pool.as_ref(), &download).await else {
+                    log::debug!("Not retrying {}: its source URL is dead and no extension is known", download.id);
+                    groups[index].skipped += 1;
+                    continue;
+                };
+                let source = match resolve(extension_id, download.clone()).await {
+                    Ok(source) => source,
+                    Err(e) => {
+                        log::warn!("Could not re-resolve the source of {}: {}", download.id, e);
+                        groups[index].skipped += 1;
+                        continue;
+                    }
+                };
+                if let Err(e) = self.refresh_source(&download.id, source).await {
+                    log::warn!("Could not update the source of {}: {}", download.id, e);
+                    groups[index].errors.push(RetryError::new(&download.id, &e));
+                    continue;
+                }
+                re_resolved = true;
+            }
+
+            if requeued_any {
+                tokio::time::sleep(RETRY_STAGGER).await;
+            }
+            if let Some(batch_id) = &download.batch_id {
+                if batches.insert(batch_id.clone()) {
+                    batch::reset(batch_id);
+                }
+            }
+            if let Err(e) = self.resume_download(&download.id).await {
+                log::warn!("Could not requeue {}: {}", download.id, e);
+                groups[index].errors.push(RetryError::new(&download.id, &e));
+                continue;
+            }
+            requeued_any = true;
+            groups[index].requeued += 1;
+            if re_resolved {
+                groups[index].re_resolved += 1;
+            }
+        }
+
+        groups.sort_by_key(|g| FailureCause::ALL.iter().position(|c| *c == g.cause));
+        let summary = RetryFailedSummary {
+            requeued: groups.iter().map(|g| g.requeued).sum(),
+            skipped: groups.iter().map(|g| g.skipped).sum(),
+            errored: groups.iter().map(|g| g.errors.len()).sum(),
+            groups,
+        };
+        log::info!(
+            "Retried failed downloads: {} requeued, {} skipped, {} errored",
+            summary.requeued, summary.skipped, summary.errored
+        );
+
+        if let Some(handle) = self.app_handle.as_ref().filter(|_| !summary.groups.is_empty()) {
+            let pool = self.db_pool.as_ref().map(|p| p.as_ref());
+            let _ = emit_notification(handle, pool, summary_notification(&summary)).await;
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn failures_are_grouped_by_cause() {
+        let cause = |message: &str| classify_failure(Some(message));
+        assert_eq!(cause("Failed to initiate download"), FailureCause::Network);
+        assert_eq!(cause("Failed to read chunk"), FailureCause::Network);
+        assert_eq!(cause("Server returned HTTP 503"), FailureCause::Network);
+        assert_eq!(cause("Server returned HTTP 404"), FailureCause::NotFound);
+        assert_eq!(
+            cause("HTTP status client error (410 Gone) for url (https://cdn.example.com/ep.mp4)"),
+            FailureCause::NotFound
+        );
+        assert_eq!(cause("Server returned HTTP 403"), FailureCause::Expired);
+        assert_eq!(cause("Insufficient disk space: need 1.2 GB, have 300 MB"), FailureCause::Disk);
+        assert_eq!(cause("No space left on device (os error 28)"), FailureCause::Disk);
+        assert_eq!(cause("Download cancelled"), FailureCause::Interrupted);
+        assert_eq!(cause("Extension returned garbage"), FailureCause::Other);
+        assert_eq!(classify_failure(None), FailureCause::Other);
+    }
+
+    /// Answers 403 under /expired/ and serves a small file elsewhere
+    async fn serve() -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let response = if request.contains(" /expired/") {
+                    "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nepis".to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    fn failed(manager_dir: &std::path::Path, id: &str, url: String, error: &str) -> DownloadProgress {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "media_id": "show",
+            "episode_id": format!("{}-episode", id),
+            "episode_number": 1,
+            "filename": format!("{}.mp4", id),
+            "url": url,
+            "file_path": manager_dir.join(format!("{}.mp4", id)).to_string_lossy(),
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": "failed",
+            "error_message": error,
+            "quality": "1080p",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_by_group_and_re_resolves_stale_urls() {
+        let addr = serve().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let manager = DownloadManager::new(dir.to_path_buf());
+
+        let mut stale_with_source = failed(dir, "stale", format!("http://{}/expired/a.mp4", addr), "Failed to read chunk");
+        stale_with_source.source_extension_id = Some("com.example.source".to_string());
+        let mut gone_with_source = failed(dir, "gone", format!("http://{}/expired/b.mp4", addr), "Server returned HTTP 404");
+        gone_with_source.source_extension_id = Some("com.example.source".to_string());
+        for download in [
+            failed(dir, "flaky", format!("http://{}/ok/c.mp4", addr), "Failed to initiate download"),
+            stale_with_source,
+            gone_with_source,
+            failed(dir, "orphan", format!("http://{}/expired/d.mp4", addr), "Server returned HTTP 403"),
+            failed(dir, "full", format!("http://{}/ok/e.mp4", addr), "Insufficient disk space: need 2 GB, have 1 GB"),
+        ] {
+            manager.downloads.write().await.insert(download.id.clone(), download);
+        }
+
+        let fresh_url = format!("http://{}/ok/fresh.mp4", addr);
+        let summary = manager
+            .retry_failed_downloads(&RetryFailedFilter::default(), |extension_id, download| {
+                let url = fresh_url.clone();
+                async move {
+                    assert_eq!(extension_id, "com.example.source");
+                    assert!(download.id == "stale" || download.id == "gone");
+                    Ok::<_, anyhow::Error>(ResolvedSource {
+                        url,
+                        quality: "1080p".to_string(),
+                        server: "Default".to_string(),
+                        is_hls: false,
+                        headers: Default::default(),
+                    })
+                }
+            })
+            .await
+            .unwrap();
+
+        let group = |cause| summary.groups.iter().find(|g| g.cause == cause).cloned().unwrap();
+        assert_eq!(summary.groups.iter().map(|g| g.cause).collect::<Vec<_>>(), vec![
+            FailureCause::Network,
+            FailureCause::Expired,
+            FailureCause::NotFound,
+            FailureCause::Disk,
+        ]);
+        // The network failure whose URL had expired in the meantime got a new one
+        let network = group(FailureCause::Network);
+        assert_eq!((network.failed, network.requeued, network.re_resolved, network.skipped), (2, 2, 1, 0));
+        let not_found = group(FailureCause::NotFound);
+        assert_eq!((not_found.requeued, not_found.re_resolved), (1, 1));
+        // No extension to ask for the orphan, and disk errors wait for space
+        assert_eq!(group(FailureCause::Expired).skipped, 1);
+        assert_eq!(group(FailureCause::Disk).skipped, 1);
+        assert_eq!((summary.requeued, summary.skipped), (3, 2));
+
+        assert_eq!(manager.get_progress("stale").await.unwrap().url, fresh_url);
+        assert_eq!(manager.get_progress("gone").await.unwrap().url, fresh_url);
+        assert_eq!(manager.get_progress("orphan").await.unwrap().status, DownloadStatus::Failed);
+        assert_eq!(manager.get_progress("full").await.unwrap().status, DownloadStatus::Failed);
+        assert_ne!(manager.get_progress("flaky").await.unwrap().status, DownloadStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn a_retry_that_errors_does_not_stop_the_rest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let manager = DownloadManager::new(dir.to_path_buf());
+        for (id, episode) in [("first", 1), ("second", 2)] {
+            let mut download = failed(dir, id, String::new(), "Server returned HTTP 404");
+            download.episode_number = episode;
+            download.source_extension_id = Some("com.example.source".to_string());
+            manager.downloads.write().await.insert(download.id.clone(), download);
+        }
+
+        // The first download is deleted while its source is being resolved
+        let summary = manager
+            .retry_failed_downloads(&RetryFailedFilter::default(), |_, download| {
+                let manager = &manager;
+                async move {
+                    if download.id == "first" {
+                        manager.downloads.write().await.remove("first");
+                    }
+                    Ok::<_, anyhow::Error>(ResolvedSource {
+                        url: "https://example.test/fresh.mp4".to_string(),
+                        quality: "1080p".to_string(),
+                        server: "Default".to_string(),
+                        is_hls: false,
+                        headers: Default::default(),
+                    })
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!((summary.requeued, summary.skipped, summary.errored), (1, 0, 1));
+        let errors = &summary.groups[0].errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].download_id, "first");
+        assert_ne!(manager.get_progress("second").await.unwrap().status, DownloadStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn filter_limits_what_is_retried() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let download = failed(temp_dir.path(), "full", String::new(), "Insufficient disk space");
+        manager.downloads.write().await.insert(download.id.clone(), download);
+
+        let filter = RetryFailedFilter { media_id: None, causes: Some(vec![FailureCause::Network]) };
+        let summary = manager
+            .retry_failed_downloads(&filter, |_, _| async { Err::<ResolvedSource, _>(anyhow::anyhow!("not called")) })
+            .await
+            .unwrap();
+        assert!(summary.groups.is_empty());
+    }
+}
@@ -0,0 +1,503 @@
+// Scheduled Downloads
+//
+// Queued downloads can wait for a start time of their own (scheduled_start)
+// or, without one, for the off-peak window shared by all downloads
+// (download_off_peak_window, e.g. "01:00-07:00" in local time). A download
+// whose time hasn't come gives up its place in the slot queue and is parked;
+// the schedule task starts parked downloads again once they may run.
+// "Start now" (start_now) lets one download skip both. While downloads are
+// paused globally (pause_all), every queued download is parked until
+// resume_all, whatever its schedule.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+use super::{DownloadManager, DownloadProgress, DownloadStatus};
+
+/// app_settings key: daily window downloads start in, "HH:MM-HH:MM" local
+/// time (unset = any time)
+pub const OFF_PEAK_WINDOW_SETTING: &str = "download_off_peak_window";
+
+/// How often the schedule task looks for parked downloads that may start
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Daily window in local time, as minutes since midnight. A window whose
+/// end is before its start runs past midnight (e.g. 23:00-06:00).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffPeakWindow {
+    pub start_minute: u32,
+    pub end_minute: u32,
+}
+
+impl OffPeakWindow {
+    /// Parse "HH:MM-HH:MM"
+    pub fn parse(value: &str) -> Result<Self> {
+        let (start, end) = value
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Invalid off-peak window '{}' (expected e.g. 01:00-07:00)", value))?;
+        Self {
+            start_minute: parse_time(start)?,
+            end_minute: parse_time(end)?,
+        }
+        .validated()
+    }
+
+    /// The window, or an error if a minute is past the end of the day
+    pub fn validated(self) -> Result<Self> {
+        if self.start_minute >= MINUTES_PER_DAY || self.end_minute >= MINUTES_PER_DAY {
+            bail!("Off-peak window times must be between 00:00 and 23:59");
+        }
+        Ok(self)
+    }
+
+    /// Whether `minute` (since local midnight) is inside the window. A
+    /// window that starts and ends at the same time is open all day.
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start_minute <= self.end_minute {
+            self.start_minute == self.end_minute || (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+}
+
+impl std::fmt::Display for OffPeakWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start_minute / 60,
+            self.start_minute % 60,
+            self.end_minute / 60,
+            self.end_minute % 60
+        )
+    }
+}
+
+fn parse_time(value: &str) -> Result<u32> {
+    let invalid = || anyhow!("Invalid time '{}' (expected HH:MM)", value.trim());
+    let (hours, minutes) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours >= 24 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// The stored window, or None when unset or invalid
+pub async fn load_off_peak_window_setting(pool: &SqlitePool) -> Option<OffPeakWindow> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(OFF_PEAK_WINDOW_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+
+    value.and_then(|v| match OffPeakWindow::parse(&v) {
+        Ok(window) => Some(window),
+        Err(e) => {
+            log::warn!("Ignoring off-peak window setting: {}", e);
+            None
+        }
+    })
+}
+
+/// Store the window (None clears it)
+pub async fn save_off_peak_window_setting(pool: &SqlitePool, window: Option<OffPeakWindow>) -> Result<()> {
+    match window {
+        Some(window) => {
+            sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+                .bind(OFF_PEAK_WINDOW_SETTING)
+                .bind(window.to_string())
+                .execute(pool)
+                .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM app_settings WHERE key = ?")
+                .bind(OFF_PEAK_WINDOW_SETTING)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a queued download may start at `now`. Its own scheduled start
+/// wins over the off-peak window; start_now skips both.
+pub fn may_start<Tz: TimeZone>(progress: &DownloadProgress, window: Option<OffPeakWindow>, now: &DateTime<Tz>) -> bool {
+    if progress.start_now {
+        return true;
+    }
+    match progress.scheduled_start {
+        Some(at) => now.timestamp_millis() >= at,
+        None => window.map_or(true, |w| w.contains(now.hour() * 60 + now.minute())),
+    }
+}
+
+/// A download manager's off-peak window and parked downloads
+#[derive(Default)]
+pub(super) struct Schedule {
+    /// None when downloads may start any time
+    window: Mutex<Option<OffPeakWindow>>,
+    /// Queued downloads waiting for their schedule, with no task running
+    parked: Mutex<HashSet<String>>,
+}
+
+impl Schedule {
+    pub(super) fn window(&self) -> Option<OffPeakWindow> {
+        *self.window.lock().unwrap()
+    }
+
+    pub(super) fn set_window(&self, window: Option<OffPeakWindow>) {
+        let previous = std::mem::replace(&mut *self.window.lock().unwrap(), window);
+        if previous != window {
+            match window {
+                Some(window) => log::info!("Downloads start between {}", window),
+                None => log::info!("Downloads start any time"),
+            }
+        }
+    }
+
+    /// Park the download if it may not start yet, marking it paused_globally
+    /// when that's why. Returns whether it was parked.
+    pub(super) fn park_if_waiting(&self, progress: &mut DownloadProgress, paused_globally: bool) -> bool {
+        if progress.status != DownloadStatus::Queued {
+            return false;
+        }
+        progress.paused_globally = paused_globally;
+        if !paused_globally && may_start(progress, self.window(), &chrono::Local::now()) {
+            return false;
+        }
+        if self.parked.lock().unwrap().insert(progress.id.clone()) {
+            log::debug!("Download waits for its schedule: {}", progress.id);
+        }
+        true
+    }
+
+    /// Park a download restored from the database whose schedule applies.
+    /// Those have no task, so this is what starts them when their time comes
+    /// (or when downloads are resumed, if they were left paused globally).
+    pub(super) fn park_restored(&self, progress: &mut DownloadProgress, paused_globally: bool) {
+        if progress.status != DownloadStatus::Queued {
+            return;
+        }
+        let scheduled = !progress.start_now && (progress.scheduled_start.is_some() || self.window().is_some());
+        if paused_globally || scheduled {
+            progress.paused_globally = paused_globally;
+            self.parked.lock().unwrap().insert(progress.id.clone());
+        }
+    }
+
+    fn parked(&self) -> Vec<String> {
+        self.parked.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn unpark(&self, download_id: &str) -> bool {
+        self.parked.lock().unwrap().remove(download_id)
+    }
+}
+
+impl DownloadManager {
+    /// Set the off-peak window (the saved setting), before loading downloads
+    /// from the database
+    pub fn with_off_peak_window(self, window: Option<OffPeakWindow>) -> Self {
+        self.schedule.set_window(window);
+        self
+    }
+
+    /// Current off-peak window (None = any time)
+    pub fn off_peak_window(&self) -> Option<OffPeakWindow> {
+        self.schedule.window()
+    }
+
+    /// Change the window; parked downloads are checked against it on the
+    /// schedule task's next pass
+    pub fn set_off_peak_window(&self, window: Option<OffPeakWindow>) {
+        self.schedule.set_window(window);
+    }
+
+    /// Start the parked downloads that may run now, returning how many
+    /// started. Parked downloads that are no longer queued are forgotten.
+    /// Nothing starts while downloads are paused globally.
+    pub async fn start_scheduled_downloads(&self) -> usize {
+        if self.downloads_paused() {
+            return 0;
+        }
+        let parked = self.schedule.parked();
+        let window = self.schedule.window();
+        let now = chrono::Local::now();
+
+        let mut ready = Vec::new();
+        {
+            let downloads = self.downloads.read().await;
+            for id in parked {
+                match downloads.get(&id) {
+                    Some(progress) if progress.status == DownloadStatus::Queued => {
+                        if may_start(progress, window, &now) {
+                            ready.push(id);
+                        }
+                    }
+                    _ => {
+                        self.schedule.unpark(&id);
+                    }
+                }
+            }
+        }
+
+        let mut started = 0;
+        for id in ready {
+            if !self.schedule.unpark(&id) {
+                continue;
+            }
+            match self.start_download_task(id.clone()).await {
+                Ok(()) => started += 1,
+                Err(e) => log::warn!("Failed to start scheduled download {}: {}", id, e),
+            }
+        }
+        if started > 0 {
+            log::info!("Started {} scheduled download(s)", started);
+        }
+        started
+    }
+
+    /// Set or clear when an unfinished download may start. Clears "start now".
+    pub async fn schedule_download(&self, download_id: &str, scheduled_start: Option<i64>) -> Result<()> {
+        let progress = {
+            let mut downloads = self.downloads.write().await;
+            let progress = downloads
+                .get_mut(download_id)
+                .ok_or_else(|| anyhow!("Download not found: {}", download_id))?;
+            if matches!(progress.status, DownloadStatus::Downloading | DownloadStatus::Completed | DownloadStatus::Offline) {
+                bail!("Download has already started: {}", download_id);
+            }
+            progress.scheduled_start = scheduled_start;
+            progress.start_now = false;
+            progress.clone()
+        };
+
+        self.save_to_database(&progress).await?;
+        self.emit_progress(&progress);
+
+        // A parked download whose new time has come starts straight away; a
+        // waiting one parks when it next checks
+        self.start_scheduled_downloads().await;
+        Ok(())
+    }
+
+    /// Start a download now, ignoring its scheduled start and the off-peak
+    /// window. It still waits for a free slot.
+    pub async fn start_download_now(&self, download_id: &str) -> Result<()> {
+        let progress = {
+            let mut downloads = self.downloads.write().await;
+            let progress = downloads
+                .get_mut(download_id)
+                .ok_or_else(|| anyhow!("Download not found: {}", download_id))?;
+            if matches!(progress.status, DownloadStatus::Completed | DownloadStatus::Offline) {
+                bail!("Download has already finished: {}", download_id);
+            }
+            progress.start_now = true;
+            progress.clone()
+        };
+
+        self.save_to_database(&progress).await?;
+        self.emit_progress(&progress);
+
+        match progress.status {
+            DownloadStatus::Queued if self.schedule.unpark(download_id) => self.start_download_task(download_id.to_string()).await,
+            DownloadStatus::Paused | DownloadStatus::Cancelled | DownloadStatus::Failed => {
+                self.resume_download(download_id).await
+            }
+            // Running, or already waiting for a slot
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Start parked downloads when their scheduled start or the off-peak window
+/// comes around
+pub fn start_schedule_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            app_handle.state::<DownloadManager>().start_scheduled_downloads().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn queued(scheduled_start: Option<i64>, start_now: bool) -> DownloadProgress {
+        let mut progress: DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": "media-1_1",
+            "media_id": "media-1",
+            "episode_id": "episode-1",
+            "episode_number": 1,
+            "filename": "Episode_1.mp4",
+            "url": "https://example.test/video.mp4",
+            "file_path": "/tmp/Episode_1.mp4",
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": "queued",
+            "error_message": null,
+        }))
+        .unwrap();
+        progress.scheduled_start = scheduled_start;
+        progress.start_now = start_now;
+        progress
+    }
+
+    #[test]
+    fn windows_parse_and_wrap_past_midnight() {
+        let night = OffPeakWindow::parse("01:00-07:00").unwrap();
+        assert_eq!(night, OffPeakWindow { start_minute: 60, end_minute: 420 });
+        assert_eq!(night.to_string(), "01:00-07:00");
+        assert!(night.contains(60));
+        assert!(night.contains(419));
+        assert!(!night.contains(420));
+        assert!(!night.contains(0));
+
+        let late = OffPeakWindow::parse(" 23:30 - 06:00 ").unwrap();
+        assert!(late.contains(23 * 60 + 45));
+        assert!(late.contains(0));
+        assert!(!late.contains(6 * 60));
+        assert!(!late.contains(12 * 60));
+
+        assert!(OffPeakWindow::parse("03:00-03:00").unwrap().contains(12 * 60));
+        assert!(OffPeakWindow::parse("25:00-07:00").is_err());
+        assert!(OffPeakWindow::parse("01:00").is_err());
+        assert!(OffPeakWindow { start_minute: 0, end_minute: MINUTES_PER_DAY }.validated().is_err());
+    }
+
+    #[test]
+    fn downloads_wait_for_their_schedule() {
+        let window = Some(OffPeakWindow::parse("01:00-07:00").unwrap());
+        let afternoon = Utc.with_ymd_and_hms(2026, 10, 16, 14, 0, 0).unwrap();
+        let night = Utc.with_ymd_and_hms(2026, 10, 17, 2, 0, 0).unwrap();
+
+        // The off-peak window
+        assert!(!may_start(&queued(None, false), window, &afternoon));
+        assert!(may_start(&queued(None, false), window, &night));
+        assert!(may_start(&queued(None, false), None, &afternoon));
+
+        // A scheduled start wins over the window, both ways
+        let at_four = Utc.with_ymd_and_hms(2026, 10, 16, 16, 0, 0).unwrap().timestamp_millis();
+        assert!(!may_start(&queued(Some(at_four), false), None, &afternoon));
+        assert!(may_start(&queued(Some(at_four), false), window, &(afternoon + chrono::Duration::hours(3))));
+        assert!(!may_start(&queued(Some(night.timestamp_millis() + 1), false), window, &night));
+
+        // Start now skips everything
+        assert!(may_start(&queued(Some(at_four), true), window, &afternoon));
+    }
+
+    /// A manager with no free slot, so started downloads wait in the queue
+    fn manager(dir: &std::path::Path) -> DownloadManager {
+        let manager = DownloadManager::new(dir.to_path_buf());
+        manager.max_concurrent.store(0, std::sync::atomic::Ordering::SeqCst);
+        manager
+    }
+
+    /// A queued download with no source yet, so starting it needs no network
+    fn pending(id: &str, scheduled_start: Option<i64>) -> DownloadProgress {
+        let mut progress = queued(scheduled_start, false);
+        progress.id = id.to_string();
+        progress.url = String::new();
+        progress
+    }
+
+    #[tokio::test]
+    async fn parked_downloads_start_when_their_time_comes() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = manager(temp_dir.path());
+        let hour = 60 * 60 * 1000;
+        let now = Utc::now().timestamp_millis();
+
+        let mut later = pending("later", Some(now + hour));
+        assert!(manager.schedule.park_if_waiting(&mut later, false));
+        let mut due = pending("due", Some(now - hour));
+        assert!(!manager.schedule.park_if_waiting(&mut due, false));
+        manager.downloads.write().await.insert(later.id.clone(), later);
+
+        assert_eq!(manager.start_scheduled_downloads().await, 0);
+        assert_eq!(manager.schedule.parked(), vec!["later".to_string()]);
+
+        manager.downloads.write().await.get_mut("later").unwrap().scheduled_start = Some(now - hour);
+        assert_eq!(manager.start_scheduled_downloads().await, 1);
+        assert!(manager.schedule.parked().is_empty());
+
+        // A parked download that stopped being queued is forgotten
+        let mut cancelled = pending("cancelled", Some(now + hour));
+        assert!(manager.schedule.park_if_waiting(&mut cancelled, false));
+        cancelled.status = DownloadStatus::Cancelled;
+        manager.downloads.write().await.insert(cancelled.id.clone(), cancelled);
+        assert_eq!(manager.start_scheduled_downloads().await, 0);
+        assert!(manager.schedule.parked().is_empty());
+    }
+
+    #[tokio::test]
+    async fn nothing_parked_starts_while_downloads_are_paused() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = manager(temp_dir.path()).with_downloads_paused(true);
+
+        let mut download = pending("ep", None);
+        assert!(manager.schedule.park_if_waiting(&mut download, true));
+        assert!(download.paused_globally);
+        manager.downloads.write().await.insert(download.id.clone(), download);
+
+        assert_eq!(manager.start_scheduled_downloads().await, 0);
+        assert_eq!(manager.schedule.parked(), vec!["ep".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn start_now_unparks_a_download() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let manager = manager(temp_dir.path()).with_off_peak_window(Some(OffPeakWindow::parse("03:00-03:01").unwrap()));
+        let far_off = Utc::now().timestamp_millis() + 24 * 60 * 60 * 1000;
+
+        let mut download = pending("ep", Some(far_off));
+        assert!(manager.schedule.park_if_waiting(&mut download, false));
+        manager.downloads.write().await.insert(download.id.clone(), download);
+
+        manager.start_download_now("ep").await.unwrap();
+        assert!(manager.schedule.parked().is_empty());
+        assert!(manager.get_progress("ep").await.unwrap().start_now);
+
+        // Restored downloads marked start now aren't held for the window
+        let mut restored = pending("restored", None);
+        restored.start_now = true;
+        manager.schedule.park_restored(&mut restored, false);
+        assert!(manager.schedule.parked().is_empty());
+        restored.start_now = false;
+        manager.schedule.park_restored(&mut restored, false);
+        assert_eq!(manager.schedule.parked(), vec!["restored".to_string()]);
+    }
+
+    #[test]
+    fn each_manager_has_its_own_schedule() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let night = OffPeakWindow::parse("01:00-07:00").unwrap();
+        let first = DownloadManager::new(temp_dir.path().to_path_buf()).with_off_peak_window(Some(night));
+        let second = DownloadManager::new(temp_dir.path().to_path_buf());
+
+        let mut download = pending("ep", None);
+        first.schedule.park_if_waiting(&mut download, true);
+
+        assert_eq!(first.off_peak_window(), Some(night));
+        assert_eq!(second.off_peak_window(), None);
+        assert!(second.schedule.parked().is_empty());
+    }
+}
@@ -0,0 +1,537 @@
+// Segmented Downloads
+//
+// Some CDNs cap a single connection at a couple of MB/s but serve parallel
+// Range requests at full speed. With download_connections set above 1, a
+// download whose server advertises `Accept-Ranges: bytes` and a known size
+// is split into that many byte ranges, fetched concurrently into a file
+// allocated at its full size up front, each range writing at its own offset.
+//
+// How far each range got is kept next to the file (<file>.ranges), so a
+// paused, failed or interrupted download picks up every range where it
+// stopped. A range only counts bytes once they're written, so the record
+// never claims more than the file has. Pause and cancel stop all ranges
+// right away through the download's cancellation token; a failing or stalled
+// range fails the download, which the automatic retry then resumes from the
+// record.
+
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::hls::Reporter;
+use super::{obfuscation, speed, stats, throttle, DownloadManager, DownloadStatus, STALL_TIMEOUT};
+use crate::events::DOWNLOAD_PROGRESS_EVENT;
+use crate::http_retry::{send_with_retry, RetryPolicy};
+
+/// app_settings key: connections per download (unset, 0 or 1 = one)
+pub const CONNECTIONS_SETTING: &str = "download_connections";
+
+/// More connections than this stop helping and start looking like abuse
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// Files smaller than this aren't worth splitting
+pub const MIN_SEGMENTED_SIZE: u64 = 16 * 1024 * 1024;
+
+/// How often progress is merged, pause/cancel checked and the record saved
+const TICK: Duration = Duration::from_millis(500);
+
+/// The record is saved every this many ticks (and when the ranges stop)
+const SAVE_EVERY_TICKS: u32 = 4;
+
+/// Connections per download from the setting, at least 1
+pub async fn connections(pool: Option<&Arc<SqlitePool>>) -> usize {
+    let Some(pool) = pool else {
+        return 1;
+    };
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(CONNECTIONS_SETTING)
+        .fetch_optional(pool.as_ref())
+        .await
+        .ok()
+        .flatten();
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_CONNECTIONS)
+}
+
+/// Whether a response allows splitting the file
+pub fn supports_ranges(response: &reqwest::Response, total_bytes: u64) -> bool {
+    let accepts = response
+        .headers()
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+    accepts && total_bytes >= MIN_SEGMENTED_SIZE
+}
+
+/// One byte range of the file; `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+    /// Bytes written from `start` on
+    pub done: u64,
+}
+
+impl ByteRange {
+    fn is_complete(&self) -> bool {
+        self.start + self.done >= self.end
+    }
+}
+
+/// How far each range of a segmented download got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeState {
+    pub total_bytes: u64,
+    pub ranges: Vec<ByteRange>,
+}
+
+impl RangeState {
+    /// `total_bytes` split into `count` nearly equal ranges
+    pub fn split(total_bytes: u64, count: usize) -> Self {
+        let count = (count.max(1) as u64).min(total_bytes.max(1));
+        let size = total_bytes / count;
+        let ranges = (0..count)
+            .map(|i| ByteRange {
+                start: i * size,
+                end: if i + 1 == count { total_bytes } else { (i + 1) * size },
+                done: 0,
+            })
+            .collect();
+        Self { total_bytes, ranges }
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.ranges.iter().map(|r| r.done).sum()
+    }
+}
+
+/// Where the record of a download's ranges is kept
+pub fn state_path(file_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.ranges", file_path))
+}
+
+fn read_state(file_path: &str) -> Option<RangeState> {
+    let text = std::fs::read_to_string(state_path(file_path)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// Bytes a segmented partial download has, or None when it isn't one
+pub fn recorded_bytes(file_path: &str) -> Option<u64> {
+    read_state(file_path).map(|state| state.downloaded())
+}
+
+/// The record of a partial segmented download to resume, if there's one
+/// that still matches its file
+pub async fn resume_state(file_path: &str) -> Option<RangeState> {
+    let state = read_state(file_path)?;
+    let size = tokio::fs::metadata(file_path).await.ok()?.len();
+    (size == state.total_bytes).then_some(state)
+}
+
+async fn save_state(file_path: &str, state: &RangeState) -> Result<()> {
+    let path = state_path(file_path);
+    let partial = path.with_extension("ranges.tmp");
+    tokio::fs::write(&partial, serde_json::to_vec(state)?).await?;
+    tokio::fs::rename(&partial, &path).await?;
+    Ok(())
+}
+
+/// Delete the record of a download's ranges
+pub async fn remove_state(file_path: &str) {
+    tokio::fs::remove_file(state_path(file_path)).await.ok();
+}
+
+/// What the range fetches share with the loop watching them
+struct Shared {
+    /// Bytes written per range
+    done: Vec<AtomicU64>,
+    /// Cancelled on pause or cancel, with the download's token, or when its
+    /// status says it shouldn't run
+    stop: CancellationToken,
+    /// The download's own limit in bytes per second (0 = none)
+    speed_limit: AtomicU64,
+    pacer: Mutex<throttle::DownloadPacer>,
+}
+
+/// Fetch what's missing of one range into the file
+async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    file_path: &str,
+    range: ByteRange,
+    index: usize,
+    shared: &Shared,
+) -> Result<()> {
+    let from = range.start + range.done;
+    if from >= range.end {
+        return Ok(());
+    }
+
+    let request = client
+        .get(url)
+        .header("Range", format!("bytes={}-{}", from, range.end - 1));
+    // Pause and cancel stop a range still waiting for its response too, and
+    // a server that never answers stalls it like one that stops sending
+    let response = tokio::select! {
+        biased;
+        _ = shared.stop.cancelled() => return Ok(()),
+        response = tokio::time::timeout(STALL_TIMEOUT, send_with_retry(RetryPolicy::BACKGROUND, request)) => match response {
+            Ok(response) => response.with_context(|| format!("Failed to fetch range {}", index + 1))?,
+            Err(_) => anyhow::bail!(
+                "Failed to fetch range {}: connection timed out after {} seconds without a response",
+                index + 1,
+                STALL_TIMEOUT.as_secs()
+            ),
+        },
+    };
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        anyhow::bail!("Server returned HTTP {} for range {}", response.status().as_u16(), index + 1);
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(file_path)
+        .await
+        .context("Failed to open file for writing")?;
+    file.seek(SeekFrom::Start(from)).await.context("Failed to write chunk")?;
+
+    let is_obfuscated = file_path.ends_with(".otaku");
+    let mut position = from;
+    let mut stream = response.bytes_stream();
+    loop {
+        let next = tokio::select! {
+            biased;
+            _ = shared.stop.cancelled() => break,
+            next = tokio::time::timeout(STALL_TIMEOUT, stream.next()) => next,
+        };
+        let chunk = match next {
+            Ok(Some(chunk)) => chunk.context("Failed to read chunk")?,
+            Ok(None) => break,
+            Err(_) => anyhow::bail!(
+                "Failed to read chunk: range {} timed out after {} seconds without data",
+                index + 1,
+                STALL_TIMEOUT.as_secs()
+            ),
+        };
+        // Never past the range, whatever the server sends
+        let take = chunk.len().min((range.end - position) as usize);
+        if take == 0 {
+            break;
+        }
+
+        throttle::throttle(take as u64).await;
+        stats::record_bytes(take as u64);
+        let limit = Some(shared.speed_limit.load(Ordering::SeqCst));
+        let wait = shared.pacer.lock().await.reserve(take as u64, limit);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        if is_obfuscated {
+            let mut data = chunk[..take].to_vec();
+            obfuscation::xor_transform(&mut data, position);
+            file.write_all(&data).await.context("Failed to write chunk")?;
+        } else {
+            file.write_all(&chunk[..take]).await.context("Failed to write chunk")?;
+        }
+        // Only counted once it's in the file
+        file.flush().await.context("Failed to write chunk")?;
+        position += take as u64;
+        shared.done[index].fetch_add(take as u64, Ordering::SeqCst);
+    }
+    file.flush().await.ok();
+
+    if position < range.end && !shared.stop.is_cancelled() {
+        anyhow::bail!("Failed to read chunk: range {} ended early", index + 1);
+    }
+    Ok(())
+}
+
+/// Merge the ranges' progress into the download; returns its status
+async fn report(
+    reporter: &Reporter<'_>,
+    downloaded: u64,
+    total_bytes: u64,
+    speed: u64,
+    save: bool,
+) -> Option<(DownloadStatus, Option<u64>)> {
+    let mut downloads_map = reporter.downloads.write().await;
+    let progress = downloads_map.get_mut(reporter.download_id)?;
+    // A pause or cancel keeps what the ranges have, like the single-connection loop
+    if progress.status == DownloadStatus::Downloading {
+        progress.downloaded_bytes = downloaded;
+        progress.total_bytes = total_bytes;
+        progress.speed = speed;
+        progress.eta_seconds = speed::eta_seconds(total_bytes, downloaded, speed);
+        progress.retry_count = 0;
+        progress.percentage = (downloaded as f32 / total_bytes.max(1) as f32) * 100.0;
+
+        if let Some(handle) = reporter.app_handle {
+            DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+        }
+    } else {
+        progress.downloaded_bytes = downloaded;
+    }
+    if save {
+        if let Some(pool) = reporter.db_pool {
+            DownloadManager::save_progress_to_db(pool, progress).await.ok();
+        }
+    }
+    Some((progress.status.clone(), progress.speed_limit))
+}
+
+/// Download `url` over several connections, continuing `state` (a fresh
+/// split or the record of an earlier attempt). `client` carries the
+/// download's headers (download_headers).
+pub(super) async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    file_path: &str,
+    mut state: RangeState,
+    reporter: Reporter<'_>,
+) -> Result<()> {
+    let downloads = reporter.downloads;
+    let total_bytes = state.total_bytes;
+
+    // Allocated at full size up front, so every range can write at its offset
+    if tokio::fs::metadata(file_path).await.map(|m| m.len()).ok() != Some(total_bytes) {
+        let file = tokio::fs::File::create(file_path).await.context("Failed to create file")?;
+        file.set_len(total_bytes).await.context("Failed to create file")?;
+        for range in &mut state.ranges {
+            range.done = 0;
+        }
+    }
+    save_state(file_path, &state).await.context("Failed to save download ranges")?;
+
+    let initial = state.downloaded();
+    log::debug!(
+        "Segmented download {}: {} ranges, {} of {} bytes done",
+        reporter.download_id,
+        state.ranges.len(),
+        initial,
+        total_bytes
+    );
+
+    let speed_limit = downloads.read().await.get(reporter.download_id).and_then(|p| p.speed_limit);
+    let shared = Shared {
+        done: state.ranges.iter().map(|r| AtomicU64::new(r.done)).collect(),
+        stop: reporter.cancel.child_token(),
+        speed_limit: AtomicU64::new(speed_limit.unwrap_or(0)),
+        pacer: Mutex::new(throttle::DownloadPacer::new()),
+    };
+    let snapshot = |state: &RangeState| RangeState {
+        total_bytes,
+        ranges: state
+            .ranges
+            .iter()
+            .zip(&shared.done)
+            .map(|(range, done)| ByteRange { done: done.load(Ordering::SeqCst), ..*range })
+            .collect(),
+    };
+
+    let fetches = futures_util::future::try_join_all(
+        state
+            .ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| !range.is_complete())
+            .map(|(index, range)| fetch_range(client, url, file_path, *range, index, &shared)),
+    );
+    tokio::pin!(fetches);
+
+    let mut rolling_speed = speed::RollingSpeed::new(std::time::Instant::now(), initial);
+    let mut ticker = tokio::time::interval(TICK);
+    let mut ticks: u32 = 0;
+    let mut stopped_as = None;
+
+    let result = loop {
+        tokio::select! {
+            result = &mut fetches => break result.map(|_| ()),
+            _ = ticker.tick() => {
+                ticks += 1;
+                let current = snapshot(&state);
+                let downloaded = current.downloaded();
+                let speed = rolling_speed.record(std::time::Instant::now(), downloaded);
+                let save = ticks % SAVE_EVERY_TICKS == 0;
+                if save {
+                    save_state(file_path, &current).await.ok();
+                }
+
+                match report(&reporter, downloaded, total_bytes, speed, save).await {
+                    Some((DownloadStatus::Downloading, limit)) => {
+                        shared.speed_limit.store(limit.unwrap_or(0), Ordering::SeqCst);
+                    }
+                    Some((status @ (DownloadStatus::Paused | DownloadStatus::Cancelled), _)) => {
+                        // Normally the token stopped the ranges already
+                        shared.stop.cancel();
+                        stopped_as = Some(status);
+                    }
+                    _ => shared.stop.cancel(),
+                }
+            }
+        }
+    };
+
+    let current = snapshot(&state);
+    save_state(file_path, &current).await.ok();
+    report(&reporter, current.downloaded(), total_bytes, 0, false).await;
+    result?;
+
+    // Interrupted before a tick saw why
+    if stopped_as.is_none() && reporter.cancel.is_cancelled() {
+        log::debug!("Segmented download stopped at {} bytes", current.downloaded());
+        return Err(super::interrupted(downloads, reporter.download_id).await);
+    }
+    match stopped_as {
+        Some(DownloadStatus::Cancelled) => {
+            log::debug!("Segmented download cancelled at {} bytes", current.downloaded());
+            anyhow::bail!("Download cancelled");
+        }
+        Some(_) => {
+            log::debug!("Segmented download paused at {} bytes", current.downloaded());
+            anyhow::bail!("Download paused");
+        }
+        // Removed, or paused and resumed: the task started by the resume
+        // owns it now
+        None if !current.ranges.iter().all(ByteRange::is_complete) => {
+            anyhow::bail!("Download stopped");
+        }
+        None => {}
+    }
+
+    remove_state(file_path).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::DownloadProgress;
+    use std::collections::HashMap;
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn ranges_cover_the_file_exactly() {
+        let state = RangeState::split(1003, 4);
+        assert_eq!(state.ranges.len(), 4);
+        assert_eq!(state.ranges[0], ByteRange { start: 0, end: 250, done: 0 });
+        assert_eq!(state.ranges[3], ByteRange { start: 750, end: 1003, done: 0 });
+        for pair in state.ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(RangeState::split(3, 8).ranges.len(), 3);
+    }
+
+    /// Serves `body` answering Range requests with 206
+    async fn serve(body: Arc<Vec<u8>>) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                    let (start, end) = request
+                        .lines()
+                        .find_map(|l| l.strip_prefix("range: bytes="))
+                        .and_then(|r| r.trim().split_once('-'))
+                        .map(|(s, e)| (s.parse::<usize>().unwrap(), e.parse::<usize>().unwrap() + 1))
+                        .unwrap();
+                    let header = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                        end - start, start, end - 1, body.len()
+                    );
+                    let _ = socket.write_all(header.as_bytes()).await;
+                    let _ = socket.write_all(&body[start..end]).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn downloading(id: &str, file_path: &std::path::Path) -> DownloadProgress {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "media_id": "movie",
+            "episode_id": "movie-1",
+            "episode_number": 1,
+            "filename": "movie.mp4",
+            "url": "",
+            "file_path": file_path.to_string_lossy(),
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": "downloading",
+            "error_message": null,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn ranges_are_fetched_into_one_file_and_resumed_from_the_record() {
+        let body: Arc<Vec<u8>> = Arc::new((0..200_000u32).map(|i| (i % 251) as u8).collect());
+        let addr = serve(body.clone()).await;
+        let url = format!("http://{}/movie.mp4", addr);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("movie.mp4");
+        let file = file_path.to_string_lossy().to_string();
+        let downloads = Arc::new(RwLock::new(HashMap::new()));
+        downloads.write().await.insert("movie".to_string(), downloading("movie", &file_path));
+        let cancel = CancellationToken::new();
+        let reporter = || Reporter { download_id: "movie", downloads: &downloads, db_pool: None, app_handle: None, cancel: &cancel };
+
+        // An earlier attempt got the first range entirely and half of the third
+        let mut state = RangeState::split(body.len() as u64, 4);
+        std::fs::write(&file_path, vec![0u8; body.len()]).unwrap();
+        {
+            let mut written = std::fs::read(&file_path).unwrap();
+            written[..50_000].copy_from_slice(&body[..50_000]);
+            written[100_000..125_000].copy_from_slice(&body[100_000..125_000]);
+            std::fs::write(&file_path, written).unwrap();
+        }
+        state.ranges[0].done = 50_000;
+        state.ranges[2].done = 25_000;
+        save_state(&file, &state).await.unwrap();
+        assert_eq!(recorded_bytes(&file), Some(75_000));
+
+        let resumed = resume_state(&file).await.unwrap();
+        let client = reqwest::Client::new();
+        download(&client, &url, &file, resumed, reporter()).await.unwrap();
+
+        assert_eq!(std::fs::read(&file_path).unwrap(), *body);
+        assert!(!state_path(&file).exists());
+        let progress = downloads.read().await["movie"].clone();
+        assert_eq!(progress.downloaded_bytes, body.len() as u64);
+        assert_eq!(progress.total_bytes, body.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn a_record_that_no_longer_matches_its_file_is_ignored() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("movie.mp4").to_string_lossy().to_string();
+        save_state(&file, &RangeState::split(1000, 2)).await.unwrap();
+
+        assert!(resume_state(&file).await.is_none());
+        std::fs::write(&file, vec![0u8; 999]).unwrap();
+        assert!(resume_state(&file).await.is_none());
+        std::fs::write(&file, vec![0u8; 1000]).unwrap();
+        assert!(resume_state(&file).await.is_some());
+    }
+}
@@ -0,0 +1,296 @@
+// Download Size Estimation
+//
+// Tells the user how big a set of downloads will be before it's queued. Each
+// URL is probed with a HEAD request, falling back to a one-byte ranged GET
+// for servers that don't answer HEAD or leave out Content-Length there; the
+// probe also tells whether the server accepts byte ranges. Probes carry the
+// source's headers, run a few at a time, and sizes are cached briefly per
+// URL so re-opening the download dialog doesn't probe everything again. URLs
+// whose size can't be told, HLS playlists among them, are reported as
+// unknown rather than guessed, and probed again next time (the headers may
+// have been what was missing).
+//
+// Queued downloads are probed the same way as their task starts, so they
+// show their size before the first byte arrives (see check_disk_space).
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use super::{disk_space, download_headers, hls};
+
+/// Probes running at the same time
+const PROBE_CONCURRENCY: usize = 4;
+
+/// How long a probed size is reused
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Timeout of a single probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+static SIZE_CACHE: LazyLock<Mutex<HashMap<String, (Instant, Probe)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What probing one URL told
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Probe {
+    size: Option<u64>,
+    accepts_ranges: bool,
+}
+
+/// Estimated size of one URL; None when the server didn't say
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ItemEstimate {
+    pub url: String,
+    pub size: Option<u64>,
+    /// Whether the server accepts byte ranges, so the download can resume
+    /// (and be split over several connections)
+    pub accepts_ranges: bool,
+}
+
+/// Estimated size of a set of downloads against the space available for them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DownloadSizeEstimate {
+    pub items: Vec<ItemEstimate>,
+    /// Sum of the known sizes
+    pub total_bytes: u64,
+    /// Items whose size is unknown (not part of total_bytes)
+    pub unknown: usize,
+    /// Free space on the download volume, None when it can't be told
+    pub available_bytes: Option<u64>,
+    /// Space downloads leave free (download_disk_reserve_mb)
+    pub reserve_bytes: u64,
+    /// Whether total_bytes fits into the free space minus the reserve
+    pub fits: bool,
+}
+
+fn cached(url: &str) -> Option<Probe> {
+    let mut cache = SIZE_CACHE.lock().unwrap();
+    cache.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+    cache.get(url).map(|(_, probe)| *probe)
+}
+
+fn header_u64(response: &reqwest::Response, name: &str) -> Option<u64> {
+    response.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Total size from a "bytes 0-0/12345" Content-Range header
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get("content-range")?.to_str().ok()?;
+    value.rsplit('/').next()?.trim().parse().ok()
+}
+
+/// Whether Accept-Ranges offers byte ranges; None when the header is absent,
+/// which doesn't mean ranges are refused
+fn accepts_byte_ranges(response: &reqwest::Response) -> Option<bool> {
+    response
+        .headers()
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("bytes"))
+}
+
+/// An HLS playlist, whose length says nothing about the video's
+fn is_playlist(response: &reqwest::Response) -> bool {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    hls::is_playlist(content_type, b"")
+}
+
+/// Size of one URL, and whether it accepts ranges, from its response headers
+async fn probe(client: &reqwest::Client, url: &str) -> Probe {
+    let head = client.head(url).send().await.ok().filter(|r| r.status().is_success());
+    let mut head_size = None;
+    if let Some(head) = &head {
+        if is_playlist(head) {
+            return Probe::default();
+        }
+        head_size = header_u64(head, "content-length").filter(|size| *size > 0);
+        // Without Accept-Ranges only a ranged GET tells
+        if let (Some(size), Some(accepts_ranges)) = (head_size, accepts_byte_ranges(head)) {
+            return Probe { size: Some(size), accepts_ranges };
+        }
+    }
+
+    let Ok(response) = client.get(url).header("Range", "bytes=0-0").send().await else {
+        return Probe { size: head_size, accepts_ranges: false };
+    };
+    if is_playlist(&response) {
+        return Probe::default();
+    }
+    match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => Probe {
+            size: content_range_total(&response).or(head_size),
+            accepts_ranges: true,
+        },
+        // Range ignored: the full body's length is the size. Dropping the
+        // response closes the connection without reading it.
+        status if status.is_success() => Probe {
+            size: header_u64(&response, "content-length").filter(|size| *size > 0).or(head_size),
+            accepts_ranges: false,
+        },
+        _ => Probe { size: head_size, accepts_ranges: false },
+    }
+}
+
+/// Probe every URL with `headers` (as a download sends them; empty for the
+/// defaults), in the order given. Cached sizes are reused; unknown ones are
+/// never cached.
+pub async fn estimate_sizes(urls: &[String], headers: &HashMap<String, String>) -> Vec<ItemEstimate> {
+    let client = reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .default_headers(download_headers(headers))
+        .build();
+    let Ok(client) = client else {
+        return urls
+            .iter()
+            .map(|url| ItemEstimate { url: url.clone(), size: None, accepts_ranges: false })
+            .collect();
+    };
+
+    futures_util::stream::iter(urls.iter().cloned())
+        .map(|url| {
+            let client = &client;
+            async move {
+                let probe = match cached(&url) {
+                    Some(probe) => probe,
+                    None => {
+                        let fresh = probe(client, &url).await;
+                        if fresh.size.is_some() {
+                            SIZE_CACHE.lock().unwrap().insert(url.clone(), (Instant::now(), fresh));
+                        }
+                        fresh
+                    }
+                };
+                ItemEstimate { url, size: probe.size, accepts_ranges: probe.accepts_ranges }
+            }
+        })
+        .buffered(PROBE_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Sum the items up and compare them with the free space
+pub fn summarize(items: Vec<ItemEstimate>, available_bytes: Option<u64>, reserve_bytes: u64) -> DownloadSizeEstimate {
+    let total_bytes = items.iter().filter_map(|i| i.size).sum();
+    let unknown = items.iter().filter(|i| i.size.is_none()).count();
+    // When the free space is unknown there's nothing to refuse on
+    let fits = available_bytes.map_or(true, |available| disk_space::fits(total_bytes, available, reserve_bytes));
+
+    DownloadSizeEstimate {
+        items,
+        total_bytes,
+        unknown,
+        available_bytes,
+        reserve_bytes,
+        fits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal HTTP server: /sized answers HEAD with a Content-Length,
+    /// /ranged only answers ranged GETs, /quiet leaves Accept-Ranges out of
+    /// HEAD but answers ranged GETs, /unsized never tells the size,
+    /// /playlist is an HLS playlist and /private needs an X-Token header
+    async fn mock_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let is_head = request.starts_with("HEAD");
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let has_token = request.to_lowercase().contains("x-token: abc");
+
+                    let response = match (path.as_str(), is_head) {
+                        ("/sized", _) => {
+                            "HTTP/1.1 200 OK\r\nContent-Length: 1234\r\nAccept-Ranges: bytes\r\nConnection: close\r\n\r\n".to_string()
+                        }
+                        ("/playlist", _) => {
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/vnd.apple.mpegurl\r\nContent-Length: 300\r\nConnection: close\r\n\r\n".to_string()
+                        }
+                        ("/private", _) if has_token => {
+                            "HTTP/1.1 200 OK\r\nContent-Length: 777\r\nConnection: close\r\n\r\n".to_string()
+                        }
+                        ("/private", _) => "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                        ("/quiet", true) => "HTTP/1.1 200 OK\r\nContent-Length: 2000\r\nConnection: close\r\n\r\n".to_string(),
+                        ("/quiet", false) => {
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-0/2000\r\nContent-Length: 1\r\nConnection: close\r\n\r\nx".to_string()
+                        }
+                        ("/ranged", true) => "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                        ("/ranged", false) => {
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-0/5000\r\nContent-Length: 1\r\nConnection: close\r\n\r\nx".to_string()
+                        }
+                        _ => "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_string(),
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn sizes_come_from_head_or_a_ranged_get() {
+        let base = mock_server().await;
+        let urls = vec![
+            format!("{}/sized", base),
+            format!("{}/ranged", base),
+            format!("{}/unsized", base),
+            format!("{}/quiet", base),
+        ];
+
+        let items = estimate_sizes(&urls, &HashMap::new()).await;
+        let sizes: Vec<Option<u64>> = items.iter().map(|i| i.size).collect();
+        assert_eq!(sizes, vec![Some(1234), Some(5000), None, Some(2000)]);
+        let ranges: Vec<bool> = items.iter().map(|i| i.accepts_ranges).collect();
+        assert_eq!(ranges, vec![true, true, false, true]);
+
+        let estimate = summarize(items, Some(10_000), 1_000);
+        assert_eq!((estimate.total_bytes, estimate.unknown), (8234, 1));
+        assert!(estimate.fits);
+
+        // Known sizes are cached until they expire; unknown ones are probed again
+        assert_eq!(cached(&urls[0]).unwrap().size, Some(1234));
+        assert_eq!(cached(&urls[2]), None);
+    }
+
+    #[tokio::test]
+    async fn playlists_are_unknown_and_headers_are_sent() {
+        let base = mock_server().await;
+        let playlist = format!("{}/playlist", base);
+        assert_eq!(estimate_sizes(std::slice::from_ref(&playlist), &HashMap::new()).await[0].size, None);
+
+        // A probe without the headers doesn't stick
+        let private = format!("{}/private", base);
+        assert_eq!(estimate_sizes(std::slice::from_ref(&private), &HashMap::new()).await[0].size, None);
+        let headers = HashMap::from([("X-Token".to_string(), "abc".to_string())]);
+        assert_eq!(estimate_sizes(std::slice::from_ref(&private), &headers).await[0].size, Some(777));
+    }
+
+    #[test]
+    fn too_little_free_space_does_not_fit() {
+        let items = vec![ItemEstimate { url: "a".to_string(), size: Some(9_500), accepts_ranges: false }];
+        assert!(!summarize(items.clone(), Some(10_000), 1_000).fits);
+        assert!(summarize(items, None, 1_000).fits);
+    }
+}
@@ -0,0 +1,488 @@
+// Download Source History
+//
+// Downloading an episode again after its file went missing used to mean
+// going back to the episode to resolve its sources. Each download keeps the
+// sources it can be fetched from in downloads.source_history: the one it was
+// downloaded from first, then up to three alternatives the extension offered
+// when it was queued. `redownload_episode` tries them in order, checking
+// each with a one-byte ranged GET since source URLs expire, and only asks
+// the extension for fresh sources (by the stored episode id) when none of
+// them answers. The download is queued again in place: same row, same
+// filename.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::lazy_source::ResolvedSource;
+use super::upgrade::quality_rank;
+use super::{download_headers, DownloadManager, DownloadProgress, DownloadStatus, FileState};
+use crate::extensions::VideoSource;
+
+/// Alternatives kept besides the source a download came from
+const MAX_ALTERNATIVES: usize = 3;
+
+/// Timeout of the ranged request checking a stored URL
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A source a download can be fetched from again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredSource {
+    pub url: String,
+    pub quality: Option<String>,
+    pub server: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl From<&ResolvedSource> for StoredSource {
+    fn from(source: &ResolvedSource) -> Self {
+        Self {
+            url: source.url.clone(),
+            quality: Some(source.quality.clone()),
+            server: Some(source.server.clone()),
+            headers: source.headers.clone(),
+        }
+    }
+}
+
+/// Where `redownload_episode` got the source from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedownloadSource {
+    /// A URL stored when the episode was downloaded
+    Stored,
+    /// Fresh sources from the extension
+    Resolved,
+}
+
+/// The source a download is using
+fn used_source(progress: &DownloadProgress) -> StoredSource {
+    StoredSource {
+        url: progress.url.clone(),
+        quality: progress.quality.clone(),
+        server: progress.source_label.clone(),
+        headers: progress.headers.clone(),
+    }
+}
+
+/// `used` first, then `others` without duplicate URLs, up to the
+/// alternatives kept
+fn merge(used: StoredSource, others: impl IntoIterator<Item = StoredSource>) -> Vec<StoredSource> {
+    let mut history = vec![used];
+    for source in others {
+        if history.len() > MAX_ALTERNATIVES {
+            break;
+        }
+        if !source.url.is_empty() && !history.iter().any(|s| s.url == source.url) {
+            history.push(source);
+        }
+    }
+    history
+}
+
+/// The best of an episode's other sources, to keep with a download of
+/// `used_url`: highest resolution first, in the extension's order otherwise
+pub fn alternatives(sources: &[VideoSource], used_url: &str) -> Vec<ResolvedSource> {
+    let mut alternatives: Vec<ResolvedSource> = sources
+        .iter()
+        .filter(|s| !s.url.trim().is_empty() && s.url != used_url)
+        .map(ResolvedSource::from_source)
+        .collect();
+    alternatives.sort_by_key(|s| std::cmp::Reverse(quality_rank(&s.quality)));
+    alternatives.truncate(MAX_ALTERNATIVES);
+    alternatives
+}
+
+/// Stored sources of a download, the one it came from first
+pub async fn load(pool: &SqlitePool, download_id: &str) -> Result<Vec<StoredSource>> {
+    let json: Option<String> = sqlx::query_scalar("SELECT source_history FROM downloads WHERE id = ?")
+        .bind(download_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    Ok(json
+        .and_then(|json| match serde_json::from_str(&json) {
+            Ok(history) => Some(history),
+            Err(e) => {
+                log::warn!("Ignoring unreadable source history of {}: {}", download_id, e);
+                None
+            }
+        })
+        .unwrap_or_default())
+}
+
+async fn save(pool: &SqlitePool, download_id: &str, history: &[StoredSource]) -> Result<()> {
+    sqlx::query("UPDATE downloads SET source_history = ? WHERE id = ?")
+        .bind(serde_json::to_string(history)?)
+        .bind(download_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Store the source a download was queued with and its alternatives
+pub(super) async fn record_queued(pool: &SqlitePool, progress: &DownloadProgress, alternatives: &[ResolvedSource]) {
+    let history = merge(used_source(progress), alternatives.iter().map(StoredSource::from));
+    if let Err(e) = save(pool, &progress.id, &history).await {
+        log::warn!("Failed to store source history of {}: {}", progress.id, e);
+    }
+}
+
+/// Put the source a download completed from first; it may have been
+/// refreshed or resolved since it was queued
+pub(super) async fn record_on_completion(pool: &SqlitePool, progress: &DownloadProgress) {
+    if progress.url.is_empty() {
+        return;
+    }
+    let result = async {
+        let stored = load(pool, &progress.id).await?;
+        save(pool, &progress.id, &merge(used_source(progress), stored)).await
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Failed to store source history of {}: {}", progress.id, e);
+    }
+}
+
+/// Whether a stored URL still serves its file
+async fn still_served(source: &StoredSource) -> bool {
+    let Ok(client) = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() else {
+        return false;
+    };
+    // Dropping the response closes the connection without reading the body
+    client
+        .get(&source.url)
+        .headers(download_headers(&source.headers))
+        .header("Range", "bytes=0-0")
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+fn is_missing_file(progress: &DownloadProgress) -> bool {
+    progress.status == DownloadStatus::Completed && matches!(progress.file_state, FileState::Missing | FileState::Corrupt)
+}
+
+impl DownloadManager {
+    /// Download a completed episode whose file went missing or was found
+    /// corrupt again, from a stored source that still answers or else from a
+    /// fresh one `resolve(extension_id, download)` gets. The download is
+    /// queued under the same row and filename.
+    pub async fn redownload_episode<R, Fut>(&self, download_id: &str, resolve: R) -> Result<RedownloadSource>
+    where
+        R: FnOnce(String, DownloadProgress) -> Fut,
+        Fut: Future<Output = Result<ResolvedSource>>,
+    {
+        let progress = self
+            .get_progress(download_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+        if !is_missing_file(&progress) {
+            anyhow::bail!("Only completed downloads whose file is missing or corrupt can be downloaded again");
+        }
+
+        let stored = match self.db_pool.as_ref() {
+            Some(pool) => load(pool, download_id).await?,
+            None => Vec::new(),
+        };
+        let mut picked = None;
+        for source in stored {
+            if still_served(&source).await {
+                picked = Some((source, RedownloadSource::Stored));
+                break;
+            }
+            log::debug!("Stored source of {} no longer answers: {}", download_id, source.url);
+        }
+
+        let (source, origin) = match picked {
+            Some(picked) => picked,
+            None => {
+                let extension_id = Self::source_extension(self.db_pool.as_ref(), &progress)
+                    .await
+                    .ok_or_else(|| anyhow::anyhow!("No stored source works and the episode's extension is unknown"))?;
+                let resolved = resolve(extension_id, progress).await?;
+                (StoredSource::from(&resolved), RedownloadSource::Resolved)
+            }
+        };
+
+        {
+            let mut downloads = self.downloads.write().await;
+            let Some(progress) = downloads.get_mut(download_id) else {
+                anyhow::bail!("Download not found: {}", download_id);
+            };
+            // Deleted or downloaded again while the sources were checked
+            if !is_missing_file(progress) {
+                anyhow::bail!("Download changed while its sources were checked");
+            }
+            progress.url = source.url;
+            progress.headers = source.headers;
+            if source.quality.is_some() {
+                progress.quality = source.quality;
+            }
+            if source.server.is_some() {
+                progress.source_label = source.server;
+            }
+            progress.status = DownloadStatus::Queued;
+            progress.file_state = FileState::Present;
+            progress.downloaded_bytes = 0;
+            progress.percentage = 0.0;
+            progress.speed = 0;
+            progress.error_message = None;
+            progress.retry_count = 0;
+            log::info!(
+                "Downloading {} again from {}",
+                download_id,
+                match origin {
+                    RedownloadSource::Stored => "a stored source",
+                    RedownloadSource::Resolved => "a fresh source",
+                }
+            );
+
+            self.emit_progress(progress);
+            self.save_to_database(progress).await.ok();
+        }
+
+        self.start_download_task(download_id.to_string()).await?;
+        Ok(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn stored(url: &str) -> StoredSource {
+        StoredSource {
+            url: url.to_string(),
+            quality: None,
+            server: None,
+            headers: HashMap::new(),
+        }
+    }
+
+    fn video_source(url: &str, quality: &str, resolution: Option<u32>) -> VideoSource {
+        VideoSource {
+            url: url.to_string(),
+            quality: quality.to_string(),
+            source_type: "mp4".to_string(),
+            server: "Default".to_string(),
+            resolution,
+            referrer: None,
+            headers: Default::default(),
+            subtitles: Vec::new(),
+            language: None,
+            language_match: None,
+        }
+    }
+
+    #[test]
+    fn the_used_source_comes_first_without_duplicates() {
+        let history = merge(
+            stored("https://cdn.example.com/new.mp4"),
+            ["old", "new", "a", "", "b", "c"]
+                .iter()
+                .map(|name| stored(&if name.is_empty() { String::new() } else { format!("https://cdn.example.com/{}.mp4", name) })),
+        );
+        let urls: Vec<&str> = history.iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://cdn.example.com/new.mp4",
+                "https://cdn.example.com/old.mp4",
+                "https://cdn.example.com/a.mp4",
+                "https://cdn.example.com/b.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn alternatives_are_the_best_other_sources() {
+        let sources = vec![
+            video_source("https://cdn.example.com/1080.mp4", "1080p", Some(1080)),
+            video_source("https://cdn.example.com/360.mp4", "360p", Some(360)),
+            video_source("https://cdn.example.com/auto.mp4", "auto", None),
+            video_source("https://cdn.example.com/720.mp4", "720p", Some(720)),
+            video_source("https://cdn.example.com/480.mp4", "480p", Some(480)),
+        ];
+
+        let urls: Vec<String> = alternatives(&sources, "https://cdn.example.com/1080.mp4")
+            .into_iter()
+            .map(|s| s.url)
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://cdn.example.com/720.mp4",
+                "https://cdn.example.com/480.mp4",
+                "https://cdn.example.com/360.mp4",
+            ]
+        );
+    }
+
+    /// Answers /live paths with the first byte of a video, anything else
+    /// with 403 like an expired CDN URL
+    async fn serve() -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let response = if request.starts_with("GET /live") {
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: 1\r\nContent-Range: bytes 0-0/4000\r\nConnection: close\r\n\r\nx"
+                } else {
+                    "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    /// A completed download whose file is gone, with `history` stored
+    async fn missing_download(temp_dir: &std::path::Path, history: &[StoredSource]) -> (Database, DownloadManager) {
+        let db = Database::new(temp_dir.join("otaku.db")).await.unwrap();
+        let manager = DownloadManager::new(temp_dir.to_path_buf()).with_database(Arc::new(db.pool().clone()));
+        // Keep the queued download waiting
+        manager.max_concurrent.store(0, Ordering::SeqCst);
+
+        let download: DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": "show_1",
+            "media_id": "show",
+            "episode_id": "show-ep-1",
+            "episode_number": 1,
+            "filename": "Show_EP1_1080p.mp4",
+            "url": history.first().map(|s| s.url.clone()).unwrap_or_default(),
+            "file_path": temp_dir.join("Show_EP1_1080p.mp4").to_string_lossy(),
+            "total_bytes": 4000,
+            "downloaded_bytes": 4000,
+            "percentage": 100.0,
+            "speed": 0,
+            "status": "completed",
+            "file_state": "missing",
+            "quality": "1080p",
+            "source_extension_id": "ext",
+        }))
+        .unwrap();
+        manager.save_to_database(&download).await.unwrap();
+        save(db.pool(), "show_1", history).await.unwrap();
+        manager.downloads.write().await.insert("show_1".to_string(), download);
+        (db, manager)
+    }
+
+    fn fresh(url: String) -> ResolvedSource {
+        ResolvedSource {
+            url,
+            quality: "1080p".to_string(),
+            server: "Fresh".to_string(),
+            is_hls: false,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_stored_url_that_still_answers_is_used() {
+        let addr = serve().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let history = [
+            stored(&format!("http://{}/expired.mp4", addr)),
+            stored(&format!("http://{}/live/720.mp4", addr)),
+        ];
+        let (db, manager) = missing_download(temp_dir.path(), &history).await;
+
+        let origin = manager
+            .redownload_episode("show_1", |_, _| async { Err(anyhow::anyhow!("the extension should not be asked")) })
+            .await
+            .unwrap();
+
+        assert_eq!(origin, RedownloadSource::Stored);
+        let progress = manager.get_progress("show_1").await.unwrap();
+        assert_eq!(progress.url, history[1].url);
+        assert_eq!(progress.status, DownloadStatus::Queued);
+        assert_eq!(progress.file_state, FileState::Present);
+        assert_eq!(progress.filename, "Show_EP1_1080p.mp4");
+        assert_eq!(progress.downloaded_bytes, 0);
+
+        let status: String = sqlx::query_scalar("SELECT status FROM downloads WHERE id = 'show_1'")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(status, "queued");
+    }
+
+    #[tokio::test]
+    async fn expired_stored_urls_fall_back_to_fresh_sources() {
+        let addr = serve().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let history = [
+            stored(&format!("http://{}/expired/1080.mp4", addr)),
+            stored(&format!("http://{}/expired/720.mp4", addr)),
+        ];
+        let (db, manager) = missing_download(temp_dir.path(), &history).await;
+
+        let fresh_url = format!("http://{}/live/fresh.mp4", addr);
+        let resolved_with = Arc::new(std::sync::Mutex::new(None));
+        let origin = manager
+            .redownload_episode("show_1", |extension_id, download| {
+                *resolved_with.lock().unwrap() = Some((extension_id, download.episode_id));
+                let source = fresh(fresh_url.clone());
+                async move { Ok(source) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(origin, RedownloadSource::Resolved);
+        assert_eq!(
+            *resolved_with.lock().unwrap(),
+            Some(("ext".to_string(), "show-ep-1".to_string()))
+        );
+        let progress = manager.get_progress("show_1").await.unwrap();
+        assert_eq!(progress.url, fresh_url);
+        assert_eq!(progress.source_label.as_deref(), Some("Fresh"));
+        assert_eq!(progress.status, DownloadStatus::Queued);
+
+        // Once it completes, the fresh source heads the history
+        record_on_completion(db.pool(), &progress).await;
+        let urls: Vec<String> = load(db.pool(), "show_1").await.unwrap().into_iter().map(|s| s.url).collect();
+        assert_eq!(urls, [fresh_url, history[0].url.clone(), history[1].url.clone()]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_resolution_leaves_the_download_alone() {
+        let addr = serve().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (_db, manager) = missing_download(temp_dir.path(), &[stored(&format!("http://{}/expired.mp4", addr))]).await;
+
+        let result = manager
+            .redownload_episode("show_1", |_, _| async { Err(anyhow::anyhow!("extension unavailable")) })
+            .await;
+
+        assert!(result.is_err());
+        let progress = manager.get_progress("show_1").await.unwrap();
+        assert_eq!(progress.status, DownloadStatus::Completed);
+        assert_eq!(progress.file_state, FileState::Missing);
+    }
+
+    #[tokio::test]
+    async fn only_missing_files_are_downloaded_again() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (_db, manager) = missing_download(temp_dir.path(), &[]).await;
+        manager.downloads.write().await.get_mut("show_1").unwrap().file_state = FileState::Present;
+
+        let result = manager
+            .redownload_episode("show_1", |_, _| async { Err(anyhow::anyhow!("the extension should not be asked")) })
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,401 @@
+// Download Source Refresh
+//
+// Source URLs from extensions expire after a few hours, so resuming an old
+// download against its stored url just gets a 403. The episode's sources
+// are fetched again, the one matching the quality the download was started
+// in is picked, and its URL replaces the stored one before the download is
+// resumed. The download task does the same on its own, once, when the
+// server refuses the stored URL with 401, 403 or 410, and notes it in the
+// download's error message.
+//
+// The bytes fetched so far are only kept when they're sure to belong to the
+// same file: a direct download keeps them when the new URL answers a ranged
+// request with 206 from the right offset, an HLS download keeps its segments
+// when the new source is the same server and quality (segments are stored
+// by their position in the playlist). Otherwise the download starts over
+// rather than producing a corrupt file.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+use tokio::sync::RwLock;
+
+use super::lazy_source::{self, ResolvedSource};
+use super::retry_failed::has_status;
+use super::upgrade::{self, quality_rank};
+use super::{discard_partial, download_headers, hls, DownloadManager, DownloadProgress, DownloadStatus};
+use crate::commands::AppState;
+use crate::events::DOWNLOAD_PROGRESS_EVENT;
+use crate::extensions::{adult, VideoSource, VideoSources};
+use crate::release_checker::pick_auto_download_source;
+
+/// Timeout of the ranged request probing a refreshed URL
+const RANGE_PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Whether two quality labels name the same quality ("1080p" and
+/// "1080p HardSub" do; labels without a resolution have to be equal)
+fn same_quality(a: &str, b: &str) -> bool {
+    match (quality_rank(a), quality_rank(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+/// The source matching a download's quality, from its server when there's
+/// a choice. Without a match (or a recorded quality) the best source is
+/// picked, as for new downloads.
+pub fn pick_matching_source<'a>(
+    sources: &'a VideoSources,
+    quality: Option<&str>,
+    server: Option<&str>,
+) -> Option<&'a VideoSource> {
+    let matching: Vec<&VideoSource> = match quality {
+        Some(quality) => sources
+            .sources
+            .iter()
+            .filter(|s| same_quality(&ResolvedSource::from_source(s).quality, quality))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    matching
+        .iter()
+        .find(|s| server.is_some_and(|server| s.server == server))
+        .or_else(|| matching.first())
+        .copied()
+        .or_else(|| pick_auto_download_source(sources))
+}
+
+/// Whether a download error is the server refusing an expired or revoked
+/// URL (401, 403 or 410)
+pub fn is_expired_url_error(error: &anyhow::Error) -> bool {
+    let message = error.to_string().to_lowercase();
+    [401, 403, 410].iter().any(|&code| has_status(&message, code))
+}
+
+/// Whether `url` serves its content from `offset` on when asked to
+async fn honors_range(url: &str, headers: &HashMap<String, String>, offset: u64) -> bool {
+    let Ok(client) = reqwest::Client::builder().timeout(RANGE_PROBE_TIMEOUT).build() else {
+        return false;
+    };
+    let response = client
+        .get(url)
+        .headers(download_headers(headers))
+        .header("Range", format!("bytes={}-", offset))
+        .send()
+        .await;
+
+    // Dropping the response closes the connection without reading the body
+    match response {
+        Ok(response) if response.status() == reqwest::StatusCode::PARTIAL_CONTENT => response
+            .headers()
+            .get("content-range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().strip_prefix("bytes "))
+            .and_then(|v| v.split('-').next())
+            .and_then(|start| start.trim().parse::<u64>().ok())
+            .is_some_and(|start| start == offset),
+        _ => false,
+    }
+}
+
+impl DownloadManager {
+    /// Put a freshly fetched source on a stopped download, keeping the bytes
+    /// fetched so far only when the new source continues the same file.
+    /// Returns whether they were kept. The download isn't resumed.
+    pub async fn refresh_source(&self, download_id: &str, source: ResolvedSource) -> Result<bool> {
+        let progress = self
+            .get_progress(download_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+
+        match progress.status {
+            DownloadStatus::Queued | DownloadStatus::Downloading => {
+                anyhow::bail!("Download is still running; pause it first")
+            }
+            DownloadStatus::Completed | DownloadStatus::Offline if progress.file_state.is_playable() => {
+                anyhow::bail!("Download has already completed")
+            }
+            _ => {}
+        }
+
+        Self::swap_source(&self.downloads, self.db_pool.as_ref(), self.app_handle.as_ref(), &progress, source).await
+    }
+
+    /// Fetch a fresh source for a download the server just refused with an
+    /// expired-URL status, from the extension the episode came from, and
+    /// queue the download again with it. Returns whether it was refreshed;
+    /// the download task then retries right away.
+    pub(super) async fn refresh_expired_source(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        download_id: &str,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+        error: &anyhow::Error,
+    ) -> bool {
+        if !is_expired_url_error(error) {
+            return false;
+        }
+        let Some(app_handle) = app_handle else {
+            return false;
+        };
+        let Some(progress) = downloads.read().await.get(download_id).cloned() else {
+            return false;
+        };
+        // Paused or cancelled while the request was failing
+        if progress.status != DownloadStatus::Downloading {
+            return false;
+        }
+        let Some(extension_id) = Self::source_extension(db_pool, &progress).await else {
+            return false;
+        };
+
+        let allow_adult = adult::background_allow_adult(app_handle.state::<AppState>().database.pool()).await;
+        let episode_id = upgrade::source_episode_id(&progress.episode_id);
+        let source = match lazy_source::fetch_background_sources(app_handle, &extension_id, episode_id, allow_adult).await {
+            Ok(sources) => pick_matching_source(&sources, progress.quality.as_deref(), progress.source_label.as_deref())
+                .map(ResolvedSource::from_source),
+            Err(e) => {
+                log::warn!("Could not refresh the expired source of {}: {}", download_id, e);
+                return false;
+            }
+        };
+        let Some(source) = source else {
+            log::warn!("Could not refresh the expired source of {}: no usable sources", download_id);
+            return false;
+        };
+
+        let kept = match Self::swap_source(downloads, db_pool, Some(app_handle), &progress, source).await {
+            Ok(kept) => kept,
+            Err(e) => {
+                log::warn!("Failed to refresh the source of {}: {}", download_id, e);
+                return false;
+            }
+        };
+
+        let mut downloads_map = downloads.write().await;
+        let Some(progress) = downloads_map.get_mut(download_id) else {
+            return false;
+        };
+        // Paused or cancelled while the sources were fetched
+        if progress.status != DownloadStatus::Downloading {
+            return false;
+        }
+        progress.status = DownloadStatus::Queued;
+        progress.speed = 0;
+        progress.error_message = Some(format!(
+            "{}; fetched a fresh source URL from {} and retried{}",
+            error,
+            extension_id,
+            if kept { "" } else { " from the start" }
+        ));
+        log::info!("Source URL of {} expired, refreshed it from {}", download_id, extension_id);
+
+        DOWNLOAD_PROGRESS_EVENT.emit(app_handle, progress);
+        if let Some(pool) = db_pool {
+            Self::save_progress_to_db(pool, progress).await.ok();
+        }
+        true
+    }
+
+    /// Put `source` on the download, keeping the bytes fetched so far only
+    /// when it continues the same file. Returns whether they were kept.
+    async fn swap_source(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+        progress: &DownloadProgress,
+        source: ResolvedSource,
+    ) -> Result<bool> {
+        let download_id = progress.id.as_str();
+        let is_hls_partial = tokio::fs::metadata(hls::parts_dir(&progress.file_path)).await.is_ok();
+        let keep = if is_hls_partial {
+            progress.source_label.as_deref() == Some(source.server.as_str())
+                && progress.quality.as_deref().is_some_and(|q| same_quality(q, &source.quality))
+        } else {
+            progress.downloaded_bytes > 0 && honors_range(&source.url, &source.headers, progress.downloaded_bytes).await
+        };
+
+        {
+            let mut downloads = downloads.write().await;
+            let Some(progress) = downloads.get_mut(download_id) else {
+                anyhow::bail!("Download not found: {}", download_id);
+            };
+            if !keep {
+                discard_partial(progress).await;
+            }
+            progress.url = source.url;
+            progress.quality = Some(source.quality);
+            progress.source_label = Some(source.server);
+            progress.headers = source.headers;
+            log::debug!(
+                "Refreshed source of download {} ({})",
+                download_id,
+                if keep { "partial kept" } else { "starting over" }
+            );
+
+            if let Some(handle) = app_handle {
+                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+            }
+            if let Some(pool) = db_pool {
+                Self::save_progress_to_db(pool, progress).await?;
+            }
+        }
+
+        Ok(keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn source(quality: &str, resolution: Option<u32>, server: &str) -> VideoSource {
+        VideoSource {
+            url: format!("https://cdn.example.com/{}/{}.mp4", server, quality),
+            quality: quality.to_string(),
+            source_type: "mp4".to_string(),
+            server: server.to_string(),
+            resolution,
+            referrer: None,
+            headers: Default::default(),
+            subtitles: Vec::new(),
+            language: None,
+            language_match: None,
+        }
+    }
+
+    #[test]
+    fn picks_the_source_in_the_original_quality() {
+        let sources = VideoSources {
+            sources: vec![
+                source("1080p", Some(1080), "Default"),
+                source("720p", Some(720), "Default"),
+                source("720p HardSub", None, "HardSub"),
+            ],
+            subtitles: Vec::new(),
+        };
+
+        let picked = |quality, server| pick_matching_source(&sources, quality, server).map(|s| s.url.as_str());
+        assert_eq!(picked(Some("720p"), None), Some("https://cdn.example.com/Default/720p.mp4"));
+        assert_eq!(picked(Some("720p"), Some("HardSub")), Some("https://cdn.example.com/HardSub/720p HardSub.mp4"));
+        // Gone from the extension, or never recorded: the best one
+        assert_eq!(picked(Some("480p"), None), Some("https://cdn.example.com/Default/1080p.mp4"));
+        assert_eq!(picked(None, None), Some("https://cdn.example.com/Default/1080p.mp4"));
+    }
+
+    #[test]
+    fn only_refused_urls_count_as_expired() {
+        let expired = |message: &str| is_expired_url_error(&anyhow::anyhow!(message.to_string()));
+        assert!(expired("Server returned HTTP 403"));
+        assert!(expired("Server returned HTTP 401 for range 2"));
+        assert!(expired("HTTP status client error (410 Gone) for url (https://cdn.example.com/seg1.ts)"));
+        assert!(!expired("Server returned HTTP 404"));
+        assert!(!expired("Server returned HTTP 503"));
+        assert!(!expired("Failed to initiate download"));
+    }
+
+    /// Serves `body` whole, or from a Range header's offset when `ranges`
+    async fn serve(body: Vec<u8>, ranges: bool) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let start = request
+                    .lines()
+                    .find_map(|l| l.strip_prefix("range: bytes="))
+                    .and_then(|r| r.trim().trim_end_matches('-').parse::<usize>().ok())
+                    .filter(|_| ranges);
+                let response = match start {
+                    Some(start) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                        body.len() - start, start, body.len() - 1, body.len()
+                    ),
+                    None => format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()),
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.write_all(&body[start.unwrap_or(0)..]).await;
+            }
+        });
+        addr
+    }
+
+    async fn stopped_download(manager: &DownloadManager, dir: &std::path::Path, id: &str) -> std::path::PathBuf {
+        let file_path = dir.join(format!("{}.mp4", id));
+        std::fs::write(&file_path, vec![7u8; 1000]).unwrap();
+        let download: DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "media_id": "show",
+            "episode_id": "ep-1",
+            "episode_number": 1,
+            "filename": format!("{}.mp4", id),
+            "url": "https://expired.example.com/ep1.mp4",
+            "file_path": file_path.to_string_lossy(),
+            "total_bytes": 4000,
+            "downloaded_bytes": 1000,
+            "percentage": 25.0,
+            "speed": 0,
+            "status": "failed",
+            "error_message": "HTTP 403",
+            "quality": "1080p",
+        }))
+        .unwrap();
+        manager.downloads.write().await.insert(id.to_string(), download);
+        file_path
+    }
+
+    fn resolved(url: String) -> ResolvedSource {
+        ResolvedSource {
+            url,
+            quality: "1080p".to_string(),
+            server: "Default".to_string(),
+            is_hls: false,
+            headers: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_is_kept_only_when_the_new_url_honors_the_range() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let body = vec![7u8; 4000];
+
+        let ranged = serve(body.clone(), true).await;
+        let kept_path = stopped_download(&manager, temp_dir.path(), "kept").await;
+        let url = format!("http://{}/ep1.mp4", ranged);
+        assert!(manager.refresh_source("kept", resolved(url.clone())).await.unwrap());
+        let kept = manager.get_progress("kept").await.unwrap();
+        assert_eq!(kept.url, url);
+        assert_eq!(kept.downloaded_bytes, 1000);
+        assert_eq!(std::fs::metadata(&kept_path).unwrap().len(), 1000);
+
+        let whole = serve(body, false).await;
+        let restarted_path = stopped_download(&manager, temp_dir.path(), "restarted").await;
+        let url = format!("http://{}/ep1.mp4", whole);
+        assert!(!manager.refresh_source("restarted", resolved(url.clone())).await.unwrap());
+        let restarted = manager.get_progress("restarted").await.unwrap();
+        assert_eq!(restarted.url, url);
+        assert_eq!(restarted.downloaded_bytes, 0);
+        assert!(!restarted_path.exists());
+    }
+
+    #[tokio::test]
+    async fn running_downloads_are_not_refreshed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        stopped_download(&manager, temp_dir.path(), "running").await;
+        manager.downloads.write().await.get_mut("running").unwrap().status = DownloadStatus::Downloading;
+
+        let source = resolved("https://cdn.example.com/ep1.mp4".to_string());
+        assert!(manager.refresh_source("running", source).await.is_err());
+        assert_eq!(manager.get_progress("running").await.unwrap().url, "https://expired.example.com/ep1.mp4");
+    }
+}
@@ -0,0 +1,103 @@
+// Download Speed and ETA
+//
+// The speed shown for a download is measured over the last few seconds
+// rather than averaged over the whole session, so it follows throttling,
+// stalls and network changes, and the time remaining derived from it
+// doesn't drift towards a stale average.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How far back the speed is measured
+const WINDOW: Duration = Duration::from_secs(5);
+
+/// Samples closer together than this are merged
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Download speed over a sliding window of recent progress
+#[derive(Debug)]
+pub struct RollingSpeed {
+    /// (time, downloaded bytes) samples, oldest first; never empty
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RollingSpeed {
+    /// Start measuring from `downloaded` bytes at `now`
+    pub fn new(now: Instant, downloaded: u64) -> Self {
+        Self {
+            samples: VecDeque::from([(now, downloaded)]),
+        }
+    }
+
+    /// Record the downloaded byte count at `now` and return the current
+    /// speed in bytes per second
+    pub fn record(&mut self, now: Instant, downloaded: u64) -> u64 {
+        // Keep one sample at or before the start of the window
+        while self.samples.len() > 1 && now.saturating_duration_since(self.samples[1].0) >= WINDOW {
+            self.samples.pop_front();
+        }
+
+        let (last_at, _) = *self.samples.back().expect("samples are never empty");
+        if now.saturating_duration_since(last_at) >= SAMPLE_INTERVAL {
+            self.samples.push_back((now, downloaded));
+        }
+
+        let (oldest_at, oldest_bytes) = self.samples[0];
+        let elapsed = now.saturating_duration_since(oldest_at).as_secs_f64();
+        if elapsed < SAMPLE_INTERVAL.as_secs_f64() {
+            return 0;
+        }
+        (downloaded.saturating_sub(oldest_bytes) as f64 / elapsed) as u64
+    }
+}
+
+/// Seconds left at `speed`, or None when the total size or speed is unknown
+pub fn eta_seconds(total_bytes: u64, downloaded_bytes: u64, speed: u64) -> Option<u64> {
+    if total_bytes == 0 || speed == 0 {
+        return None;
+    }
+    Some(total_bytes.saturating_sub(downloaded_bytes).div_ceil(speed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_follows_recent_progress_not_the_session_average() {
+        let start = Instant::now();
+        let mut speed = RollingSpeed::new(start, 0);
+
+        // 10 seconds at 1 MB/s
+        let mut downloaded = 0;
+        for tenth in 1..=100 {
+            downloaded += 100_000;
+            speed.record(start + Duration::from_millis(tenth * 100), downloaded);
+        }
+        assert_eq!(speed.record(start + Duration::from_secs(10), downloaded), 1_000_000);
+
+        // Then it slows down to 100 KB/s; after a full window only that counts
+        for tenth in 101..=160 {
+            downloaded += 10_000;
+            speed.record(start + Duration::from_millis(tenth * 100), downloaded);
+        }
+        let current = speed.record(start + Duration::from_secs(16), downloaded);
+        assert!((95_000..=105_000).contains(&current), "speed was {}", current);
+    }
+
+    #[test]
+    fn no_speed_until_there_is_something_to_measure() {
+        let start = Instant::now();
+        let mut speed = RollingSpeed::new(start, 5_000);
+        assert_eq!(speed.record(start + Duration::from_millis(10), 6_000), 0);
+    }
+
+    #[test]
+    fn eta_needs_a_known_size_and_speed() {
+        assert_eq!(eta_seconds(1_000, 400, 100), Some(6));
+        assert_eq!(eta_seconds(1_000, 450, 100), Some(6));
+        assert_eq!(eta_seconds(1_000, 1_000, 100), Some(0));
+        assert_eq!(eta_seconds(0, 400, 100), None);
+        assert_eq!(eta_seconds(1_000, 400, 0), None);
+    }
+}
@@ -0,0 +1,252 @@
+// Download Statistics
+//
+// Totals for the header of the downloads page: combined speed, how many
+// downloads are in each state, bytes downloaded today and the size of the
+// completed downloads. They're aggregated over the in-memory downloads, so
+// the frontend doesn't sum hundreds of entries itself.
+//
+// Bytes downloaded today are counted as chunks arrive (every transfer path
+// calls `record_bytes`) and kept in app_settings, so the count survives a
+// restart; it starts over at local midnight. The stats task emits the
+// download-stats event at the pace of the progress events whenever the
+// totals changed.
+
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+use super::{DownloadManager, DownloadProgress, DownloadStatus};
+use crate::events::DOWNLOAD_STATS_EVENT;
+
+/// app_settings key: bytes downloaded on the current day, as
+/// {"date": "YYYY-MM-DD", "bytes": n}
+pub const BYTES_TODAY_SETTING: &str = "download_bytes_today";
+
+/// How often the stats are recomputed (and emitted when they changed)
+const EMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the day's byte count is saved while it's growing
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Bytes downloaded on one local day
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct DailyBytes {
+    date: String,
+    bytes: u64,
+}
+
+impl DailyBytes {
+    /// Add bytes downloaded on `date`, starting over on a new day
+    fn add(&mut self, date: &str, bytes: u64) {
+        if self.date != date {
+            self.date = date.to_string();
+            self.bytes = 0;
+        }
+        self.bytes += bytes;
+    }
+
+    /// Bytes downloaded on `date`
+    fn on(&self, date: &str) -> u64 {
+        if self.date == date { self.bytes } else { 0 }
+    }
+}
+
+static BYTES_TODAY: LazyLock<Mutex<DailyBytes>> = LazyLock::new(|| Mutex::new(DailyBytes::default()));
+
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Count bytes a download just received
+pub fn record_bytes(bytes: u64) {
+    BYTES_TODAY.lock().unwrap().add(&today(), bytes);
+}
+
+/// Bytes downloaded since local midnight
+pub fn bytes_today() -> u64 {
+    BYTES_TODAY.lock().unwrap().on(&today())
+}
+
+/// Restore the day's byte count saved by an earlier run
+pub async fn load_bytes_today(pool: &SqlitePool) {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(BYTES_TODAY_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    let Some(saved) = value.and_then(|v| serde_json::from_str::<DailyBytes>(&v).ok()) else {
+        return;
+    };
+    // Bytes counted before the load are added to the saved ones
+    let mut current = BYTES_TODAY.lock().unwrap();
+    let bytes = saved.on(&today()) + current.on(&today());
+    *current = DailyBytes { date: today(), bytes };
+}
+
+async fn save_bytes_today(pool: &SqlitePool) -> anyhow::Result<()> {
+    let value = serde_json::to_string(&*BYTES_TODAY.lock().unwrap())?;
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(BYTES_TODAY_SETTING)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Totals over all episode downloads
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct DownloadStats {
+    /// Combined speed of the running downloads, in bytes per second
+    pub speed: u64,
+    pub active: usize,
+    pub queued: usize,
+    pub paused: usize,
+    pub failed: usize,
+    pub completed: usize,
+    /// Bytes received since local midnight, including downloads that were
+    /// removed since
+    pub bytes_today: u64,
+    /// Size of the completed downloads whose file is still there
+    pub completed_bytes: u64,
+}
+
+impl DownloadStats {
+    /// Aggregate `downloads`; `bytes_today` comes from the daily counter
+    fn aggregate<'a>(downloads: impl IntoIterator<Item = &'a DownloadProgress>, bytes_today: u64) -> Self {
+        let mut stats = DownloadStats { bytes_today, ..Default::default() };
+        for download in downloads {
+            match download.status {
+                DownloadStatus::Downloading => {
+                    stats.active += 1;
+                    stats.speed += download.speed;
+                }
+                DownloadStatus::Queued => stats.queued += 1,
+                DownloadStatus::Paused => stats.paused += 1,
+                DownloadStatus::Failed => stats.failed += 1,
+                DownloadStatus::Completed | DownloadStatus::Offline => {
+                    stats.completed += 1;
+                    if download.file_state.is_playable() {
+                        stats.completed_bytes += download.total_bytes;
+                    }
+                }
+                DownloadStatus::Cancelled => {}
+            }
+        }
+        stats
+    }
+}
+
+impl DownloadManager {
+    /// Current totals for the downloads page header
+    pub async fn download_stats(&self) -> DownloadStats {
+        let downloads = self.downloads.read().await;
+        DownloadStats::aggregate(downloads.values(), bytes_today())
+    }
+}
+
+/// Restore today's byte count, then emit the stats whenever they change and
+/// save the count every so often
+pub fn start_stats_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let manager = app_handle.state::<DownloadManager>();
+        if let Some(pool) = &manager.db_pool {
+            load_bytes_today(pool).await;
+        }
+
+        let mut last_emitted = None;
+        let mut saved_bytes = bytes_today();
+        let mut last_save = Instant::now();
+        loop {
+            tokio::time::sleep(EMIT_INTERVAL).await;
+
+            let stats = manager.download_stats().await;
+            if last_emitted.as_ref() != Some(&stats) {
+                DOWNLOAD_STATS_EVENT.emit(&app_handle, &stats);
+                last_emitted = Some(stats.clone());
+            }
+
+            if stats.bytes_today != saved_bytes && last_save.elapsed() >= SAVE_INTERVAL {
+                if let Some(pool) = &manager.db_pool {
+                    match save_bytes_today(pool).await {
+                        Ok(()) => saved_bytes = stats.bytes_today,
+                        Err(e) => log::warn!("Failed to save today's downloaded bytes: {}", e),
+                    }
+                }
+                last_save = Instant::now();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::FileState;
+
+    fn download(status: &str, total_bytes: u64, speed: u64) -> DownloadProgress {
+        serde_json::from_value(serde_json::json!({
+            "id": "media-1_1",
+            "media_id": "media-1",
+            "episode_id": "episode-1",
+            "episode_number": 1,
+            "filename": "Episode_1.mp4",
+            "url": "https://example.test/video.mp4",
+            "file_path": "/tmp/Episode_1.mp4",
+            "total_bytes": total_bytes,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": speed,
+            "status": status,
+            "error_message": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stats_count_each_status_and_sum_speed_and_size() {
+        let mut trashed = download("completed", 700, 0);
+        trashed.file_state = FileState::Trashed;
+        let downloads = [
+            download("downloading", 1000, 300),
+            download("downloading", 1000, 200),
+            download("queued", 0, 0),
+            download("paused", 0, 0),
+            download("failed", 0, 0),
+            download("cancelled", 0, 0),
+            download("completed", 500, 0),
+            trashed,
+        ];
+
+        let stats = DownloadStats::aggregate(&downloads, 42);
+        assert_eq!(
+            stats,
+            DownloadStats {
+                speed: 500,
+                active: 2,
+                queued: 1,
+                paused: 1,
+                failed: 1,
+                completed: 2,
+                bytes_today: 42,
+                completed_bytes: 500,
+            }
+        );
+    }
+
+    #[test]
+    fn daily_bytes_start_over_on_a_new_day() {
+        let mut daily = DailyBytes::default();
+        daily.add("2026-10-15", 100);
+        daily.add("2026-10-15", 50);
+        assert_eq!(daily.on("2026-10-15"), 150);
+        assert_eq!(daily.on("2026-10-16"), 0);
+
+        daily.add("2026-10-16", 10);
+        assert_eq!(daily, DailyBytes { date: "2026-10-16".to_string(), bytes: 10 });
+    }
+}
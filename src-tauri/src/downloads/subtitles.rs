@@ -0,0 +1,520 @@
+// Subtitle Downloads
+//
+// Extensions can give subtitle tracks with a source, which offline playback
+// would otherwise lose. A download queued with tracks records them in
+// download_subtitles; once the video completes each one is fetched next to it
+// as <video name>.<language>.vtt with the download's headers (SRT tracks are
+// converted to WebVTT). A track that can't be fetched is logged and kept
+// without a file; the download itself has still succeeded.
+//
+// The player lists an episode's fetched tracks with get_episode_subtitle_paths
+// and loads them from the video server like the video file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::{download_headers, DownloadManager, DownloadProgress, DownloadStatus};
+use crate::extensions::types::{Subtitle, VideoSource, VideoSources};
+
+/// How long fetching one subtitle file may take
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A fetched subtitle track of a download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DownloadSubtitle {
+    /// Language tag used in the file name
+    pub language: String,
+    pub label: String,
+    pub file_path: String,
+}
+
+/// Subtitle tracks to download with `source`: its own, or the provider-wide
+/// ones when it has none
+pub fn source_subtitles(source: &VideoSource, sources: &VideoSources) -> Vec<Subtitle> {
+    if source.subtitles.is_empty() {
+        sources.subtitles.clone()
+    } else {
+        source.subtitles.clone()
+    }
+}
+
+/// File-name-safe language tag: lowercase letters, digits and dashes, "und"
+/// when nothing is left
+fn language_tag(language: &str) -> String {
+    let tag: String = language
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let tag = tag.trim_matches('-');
+    if tag.is_empty() { "und".to_string() } else { tag.to_string() }
+}
+
+/// Where a track goes: next to the video, named after it
+fn subtitle_path(video_path: &Path, language: &str) -> PathBuf {
+    video_path.with_extension(format!("{}.vtt", language))
+}
+
+/// WebVTT text of a fetched track. WebVTT is kept as it is; anything else is
+/// taken for SRT, whose cues only differ in the decimal comma of their
+/// timestamps.
+fn to_webvtt(text: &str) -> String {
+    let text = text.trim_start_matches('\u{feff}');
+    if text.starts_with("WEBVTT") {
+        return text.to_string();
+    }
+
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in text.lines() {
+        if line.contains("-->") {
+            vtt.push_str(&line.replace(',', "."));
+        } else {
+            vtt.push_str(line);
+        }
+        vtt.push('\n');
+    }
+    vtt
+}
+
+/// Record the tracks to fetch once `download_id` completes, replacing the
+/// ones recorded before. Two tracks of the same language get numbered tags
+/// (en, en-2).
+pub(super) async fn record(pool: &SqlitePool, download_id: &str, tracks: &[Subtitle]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM download_subtitles WHERE download_id = ?")
+        .bind(download_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut used: Vec<String> = Vec::new();
+    for track in tracks {
+        let base = language_tag(&track.language);
+        let mut tag = base.clone();
+        let mut n = 2;
+        while used.contains(&tag) {
+            tag = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        sqlx::query("INSERT INTO download_subtitles (download_id, language, label, url) VALUES (?, ?, ?, ?)")
+            .bind(download_id)
+            .bind(&tag)
+            .bind(&track.label)
+            .bind(&track.url)
+            .execute(&mut *tx)
+            .await?;
+        used.push(tag);
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn fetch_track(client: &reqwest::Client, url: &str, path: &Path) -> Result<()> {
+    let text = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    tokio::fs::write(path, to_webvtt(&text))
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Fetch the tracks of a download that just completed. Failures are logged
+/// and stored with the track; they don't affect the download.
+pub(super) async fn fetch_on_completion(pool: &SqlitePool, progress: &DownloadProgress) {
+    let pending: Vec<(i64, String, String)> = match sqlx::query_as(
+        "SELECT id, language, url FROM download_subtitles WHERE download_id = ? AND file_path IS NULL ORDER BY id",
+    )
+    .bind(&progress.id)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(pending) => pending,
+        Err(e) => {
+            log::warn!("Failed to load subtitle tracks of download {}: {}", progress.id, e);
+            return;
+        }
+    };
+    if pending.is_empty() {
+        return;
+    }
+
+    let client = match reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .default_headers(download_headers(&progress.headers))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("Failed to create HTTP client for subtitles: {}", e);
+            return;
+        }
+    };
+
+    for (id, language, url) in pending {
+        let path = subtitle_path(Path::new(&progress.file_path), &language);
+        let (file_path, error) = match fetch_track(&client, &url, &path).await {
+            Ok(()) => {
+                log::debug!("Downloaded {} subtitles of {}", language, progress.id);
+                (Some(path.to_string_lossy().to_string()), None)
+            }
+            Err(e) => {
+                log::warn!("Failed to download {} subtitles of {}: {}", language, progress.id, e);
+                (None, Some(e.to_string()))
+            }
+        };
+
+        if let Err(e) = sqlx::query("UPDATE download_subtitles SET file_path = ?, error_message = ? WHERE id = ?")
+            .bind(file_path)
+            .bind(error)
+            .bind(id)
+            .execute(pool)
+            .await
+        {
+            log::warn!("Failed to save subtitle track of {}: {}", progress.id, e);
+        }
+    }
+}
+
+/// Fetched tracks of a download, in the order they were given
+pub async fn download_subtitles(pool: &SqlitePool, download_id: &str) -> Result<Vec<DownloadSubtitle>> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT language, label, file_path FROM download_subtitles WHERE download_id = ? AND file_path IS NOT NULL ORDER BY id",
+    )
+    .bind(download_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(language, label, file_path)| DownloadSubtitle { language, label, file_path })
+        .collect())
+}
+
+/// Delete the subtitle files of a download; its rows go with the download's.
+/// Files another download has fetched since (the replacement of a download
+/// set aside by an overwrite) are left alone.
+pub(super) async fn remove_files(pool: &SqlitePool, download_id: &str) {
+    let paths: Vec<String> = match sqlx::query_scalar(
+        r#"
+        SELECT s.file_path FROM download_subtitles s
+        WHERE s.download_id = ? AND s.file_path IS NOT NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM download_subtitles other
+              WHERE other.file_path = s.file_path AND other.download_id != s.download_id
+          )
+        "#,
+    )
+    .bind(download_id)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(paths) => paths,
+        Err(e) => {
+            log::warn!("Failed to load subtitle files of download {}: {}", download_id, e);
+            return;
+        }
+    };
+
+    for path in paths {
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => log::debug!("Deleted subtitles: {}", path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to delete subtitles {}: {}", path, e),
+        }
+    }
+}
+
+/// Move the fetched tracks of a download next to the video's new location,
+/// renamed after it. A track that can't be moved is logged and keeps its
+/// old path.
+pub(super) async fn move_with_video(pool: &SqlitePool, download_id: &str, video_path: &Path) {
+    let tracks: Vec<(i64, String, String)> = match sqlx::query_as(
+        "SELECT id, language, file_path FROM download_subtitles WHERE download_id = ? AND file_path IS NOT NULL ORDER BY id",
+    )
+    .bind(download_id)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(tracks) => tracks,
+        Err(e) => {
+            log::warn!("Failed to load subtitle files of download {}: {}", download_id, e);
+            return;
+        }
+    };
+
+    for (id, language, file_path) in tracks {
+        let dest = subtitle_path(video_path, &language);
+        if Path::new(&file_path) == dest {
+            continue;
+        }
+        if let Err(e) = super::archive::move_file(Path::new(&file_path), &dest).await {
+            log::warn!("Failed to move subtitles {}: {:#}", file_path, e);
+            continue;
+        }
+        if let Err(e) = sqlx::query("UPDATE download_subtitles SET file_path = ? WHERE id = ?")
+            .bind(dest.to_string_lossy().to_string())
+            .bind(id)
+            .execute(pool)
+            .await
+        {
+            log::warn!("Moved subtitles of {} but failed to record their new location: {}", download_id, e);
+        }
+    }
+}
+
+impl DownloadManager {
+    /// Fetched subtitle tracks of a downloaded episode; empty when it isn't
+    /// downloaded or came without subtitles
+    pub async fn get_episode_subtitle_paths(&self, media_id: &str, episode_number: i32) -> Result<Vec<DownloadSubtitle>> {
+        let download_id = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .find(|d| {
+                d.media_id == media_id
+                    && d.episode_number == episode_number
+                    && d.status == DownloadStatus::Completed
+                    && d.file_state.is_playable()
+            })
+            .map(|d| d.id.clone());
+
+        match (download_id, &self.db_pool) {
+            (Some(id), Some(pool)) => download_subtitles(pool, &id).await,
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nOne, two\n";
+
+    fn track(language: &str, url: &str) -> Subtitle {
+        Subtitle {
+            url: url.to_string(),
+            language: language.to_string(),
+            label: format!("{} subs", language),
+        }
+    }
+
+    /// Serves SRT at /en.srt and WebVTT at /ja.vtt; everything else is a 404
+    async fn serve_subtitles() -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let body = if request.starts_with("GET /en.srt ") {
+                        Some(SRT.to_string())
+                    } else if request.starts_with("GET /ja.vtt ") {
+                        Some("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nこんにちは\n".to_string())
+                    } else {
+                        None
+                    };
+                    let response = match body {
+                        Some(body) => format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        ),
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn language_tags_are_safe_in_file_names() {
+        assert_eq!(language_tag("en"), "en");
+        assert_eq!(language_tag(" pt-BR "), "pt-br");
+        assert_eq!(language_tag("English (CC)"), "english--cc");
+        assert_eq!(language_tag("../.."), "und");
+        assert_eq!(language_tag(""), "und");
+    }
+
+    #[test]
+    fn subtitles_are_named_after_the_video() {
+        assert_eq!(
+            subtitle_path(Path::new("/downloads/Frieren_EP5_1080p.mp4"), "en"),
+            PathBuf::from("/downloads/Frieren_EP5_1080p.en.vtt")
+        );
+    }
+
+    #[test]
+    fn srt_is_converted_and_webvtt_kept() {
+        let vtt = to_webvtt(&format!("\u{feff}{}", SRT));
+        assert!(vtt.starts_with("WEBVTT\n\n1\n"));
+        assert!(vtt.contains("00:00:01.000 --> 00:00:02.500\nHello"));
+        // Only timestamps lose their commas
+        assert!(vtt.contains("One, two"));
+
+        let webvtt = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi, there\n";
+        assert_eq!(to_webvtt(webvtt), webvtt);
+    }
+
+    #[test]
+    fn source_tracks_win_over_provider_tracks() {
+        let mut sources: VideoSources = serde_json::from_value(serde_json::json!({
+            "sources": [{ "url": "https://cdn.example.com/ep.mp4", "quality": "1080p", "type": "mp4", "server": "Default" }],
+            "subtitles": [{ "url": "https://example.com/en.vtt", "language": "en", "label": "English" }]
+        }))
+        .unwrap();
+
+        let provider = source_subtitles(&sources.sources[0], &sources);
+        assert_eq!(provider[0].url, "https://example.com/en.vtt");
+
+        sources.sources[0].subtitles = vec![track("de", "https://cdn.example.com/de.vtt")];
+        let own = source_subtitles(&sources.sources[0], &sources);
+        assert_eq!(own.len(), 1);
+        assert_eq!(own[0].language, "de");
+    }
+
+    #[tokio::test]
+    async fn tracks_are_fetched_after_completion_and_failures_are_kept() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        let addr = serve_subtitles().await;
+
+        let video_path = temp_dir.path().join("Frieren_EP5_1080p.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        sqlx::query(
+            "INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status) \
+             VALUES ('frieren_5', 'frieren', 'frieren-5', 5, 'Frieren_EP5_1080p.mp4', '', ?, 'completed')",
+        )
+        .bind(video_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let tracks = vec![
+            track("en", &format!("http://{}/en.srt", addr)),
+            track("EN", &format!("http://{}/gone.srt", addr)),
+            track("ja", &format!("http://{}/ja.vtt", addr)),
+        ];
+        record(pool, "frieren_5", &tracks).await.unwrap();
+
+        let progress: DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": "frieren_5",
+            "media_id": "frieren",
+            "episode_id": "frieren-5",
+            "episode_number": 5,
+            "filename": "Frieren_EP5_1080p.mp4",
+            "url": "",
+            "file_path": video_path.to_string_lossy(),
+            "total_bytes": 5,
+            "downloaded_bytes": 5,
+            "percentage": 100.0,
+            "speed": 0,
+            "status": "completed",
+            "error_message": null,
+        }))
+        .unwrap();
+        fetch_on_completion(pool, &progress).await;
+
+        let fetched = download_subtitles(pool, "frieren_5").await.unwrap();
+        let languages: Vec<&str> = fetched.iter().map(|s| s.language.as_str()).collect();
+        assert_eq!(languages, vec!["en", "ja"]);
+        let english = std::fs::read_to_string(temp_dir.path().join("Frieren_EP5_1080p.en.vtt")).unwrap();
+        assert!(english.starts_with("WEBVTT"));
+        assert!(english.contains("00:00:03.000 --> 00:00:04.000"));
+        assert!(temp_dir.path().join("Frieren_EP5_1080p.ja.vtt").exists());
+
+        // The second English track was numbered and its 404 recorded
+        let (file_path, error): (Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT file_path, error_message FROM download_subtitles WHERE download_id = 'frieren_5' AND language = 'en-2'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert!(file_path.is_none());
+        assert!(error.unwrap().contains("404"));
+
+        // An overwrite sets the download aside under a new id, and its
+        // replacement fetches the same files again
+        let set_aside = "frieren_5:overwritten:1";
+        sqlx::query("UPDATE downloads SET id = ?, episode_id = 'frieren-5:overwritten:1' WHERE id = 'frieren_5'")
+            .bind(set_aside)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(download_subtitles(pool, set_aside).await.unwrap().len(), 2);
+        sqlx::query(
+            "INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status) \
+             VALUES ('frieren_5', 'frieren', 'frieren-5', 5, 'Frieren_EP5_1080p.mp4', '', ?, 'completed')",
+        )
+        .bind(video_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .unwrap();
+        record(pool, "frieren_5", &tracks[..1]).await.unwrap();
+        fetch_on_completion(pool, &progress).await;
+
+        // Deleting the set-aside download keeps the replacement's file
+        remove_files(pool, set_aside).await;
+        sqlx::query("DELETE FROM downloads WHERE id = ?").bind(set_aside).execute(pool).await.unwrap();
+        assert!(temp_dir.path().join("Frieren_EP5_1080p.en.vtt").exists());
+        assert!(!temp_dir.path().join("Frieren_EP5_1080p.ja.vtt").exists());
+        assert!(download_subtitles(pool, set_aside).await.unwrap().is_empty());
+
+        // Deleting the download takes its subtitles along
+        remove_files(pool, "frieren_5").await;
+        sqlx::query("DELETE FROM downloads WHERE id = 'frieren_5'").execute(pool).await.unwrap();
+        assert!(!temp_dir.path().join("Frieren_EP5_1080p.en.vtt").exists());
+        assert!(download_subtitles(pool, "frieren_5").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tracks_follow_their_video() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let video_path = temp_dir.path().join("Frieren_EP5_1080p.mp4");
+        let subtitles_path = temp_dir.path().join("Frieren_EP5_1080p.en.vtt");
+        std::fs::write(&subtitles_path, "WEBVTT\n").unwrap();
+        sqlx::query(
+            "INSERT INTO downloads (id, media_id, episode_id, episode_number, filename, url, file_path, status) \
+             VALUES ('frieren_5', 'frieren', 'frieren-5', 5, 'Frieren_EP5_1080p.mp4', '', ?, 'completed')",
+        )
+        .bind(video_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO download_subtitles (download_id, language, label, url, file_path) VALUES ('frieren_5', 'en', 'English', '', ?)")
+            .bind(subtitles_path.to_string_lossy().to_string())
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let moved_video = temp_dir.path().join("Frieren").join("Season 01").join("Frieren - S01E05 (2).mp4");
+        std::fs::create_dir_all(moved_video.parent().unwrap()).unwrap();
+        move_with_video(pool, "frieren_5", &moved_video).await;
+
+        let moved = moved_video.with_file_name("Frieren - S01E05 (2).en.vtt");
+        assert!(moved.exists());
+        assert!(!subtitles_path.exists());
+        let fetched = download_subtitles(pool, "frieren_5").await.unwrap();
+        assert_eq!(fetched[0].file_path, moved.to_string_lossy());
+    }
+}
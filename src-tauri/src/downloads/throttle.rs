@@ -0,0 +1,237 @@
+// Download Speed Limit
+//
+// Downloads read chunks as fast as the server sends them, which starves
+// streaming in the player. With download_speed_limit set, every download
+// draws from one shared token bucket before writing a chunk, so all running
+// downloads together stay under the limit. 0 means unlimited.
+//
+// A download can also have its own speed_limit (say, a movie trickling in
+// while episodes download at full speed). That one is paced by a bucket of
+// its own, after the shared one; the chunk loop reads the limit for every
+// chunk, so changing it takes effect without restarting the transfer.
+
+use anyhow::{anyhow, Result};
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use super::DownloadManager;
+
+/// app_settings key: global download limit in bytes per second (0 = unlimited)
+pub const SPEED_LIMIT_SETTING: &str = "download_speed_limit";
+
+/// Current limit in bytes per second, 0 when unlimited
+static LIMIT: AtomicU64 = AtomicU64::new(0);
+
+static BUCKET: LazyLock<Mutex<TokenBucket>> = LazyLock::new(|| Mutex::new(TokenBucket::new(Instant::now())));
+
+/// Token bucket holding up to one second of bandwidth. Tokens may go
+/// negative: a chunk larger than what's available is let through and the
+/// caller waits until the debt is paid back.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant) -> Self {
+        Self { tokens: 0.0, refilled_at: now }
+    }
+
+    /// Take `bytes` at `rate` bytes/sec, returning how long to wait before
+    /// using them
+    fn take(&mut self, bytes: u64, rate: u64, now: Instant) -> Duration {
+        let rate = rate as f64;
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.refilled_at = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+/// Current limit in bytes per second (0 = unlimited)
+pub fn speed_limit() -> u64 {
+    LIMIT.load(Ordering::SeqCst)
+}
+
+/// Change the limit; running downloads pick it up with their next chunk
+pub fn set_speed_limit(bytes_per_sec: u64) {
+    let previous = LIMIT.swap(bytes_per_sec, Ordering::SeqCst);
+    if previous != bytes_per_sec {
+        // Start the new rate without a burst saved up under the old one
+        *BUCKET.lock().unwrap() = TokenBucket::new(Instant::now());
+        log::info!("Download speed limit set to {} bytes/s", bytes_per_sec);
+    }
+}
+
+/// Wait until `bytes` may be written under the limit
+pub async fn throttle(bytes: u64) {
+    let rate = speed_limit();
+    if rate == 0 {
+        return;
+    }
+
+    let wait = BUCKET.lock().unwrap().take(bytes, rate, Instant::now());
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Pacing of one download under its own limit
+#[derive(Debug)]
+pub(super) struct DownloadPacer {
+    bucket: TokenBucket,
+    rate: u64,
+}
+
+impl DownloadPacer {
+    pub fn new() -> Self {
+        Self { bucket: TokenBucket::new(Instant::now()), rate: 0 }
+    }
+
+    /// How long to wait before writing `bytes` under `limit` (None or 0 =
+    /// unlimited). A changed limit starts from an empty bucket.
+    fn wait_for(&mut self, bytes: u64, limit: Option<u64>, now: Instant) -> Duration {
+        let rate = limit.unwrap_or(0);
+        if rate != self.rate {
+            self.bucket = TokenBucket::new(now);
+            self.rate = rate;
+        }
+        if rate == 0 {
+            return Duration::ZERO;
+        }
+        self.bucket.take(bytes, rate, now)
+    }
+
+    /// Take `bytes` under `limit`, returning how long to wait before writing
+    /// them (for callers that mustn't hold the pacer while waiting)
+    pub fn reserve(&mut self, bytes: u64, limit: Option<u64>) -> Duration {
+        self.wait_for(bytes, limit, Instant::now())
+    }
+
+    /// Wait until `bytes` may be written under `limit`
+    pub async fn pace(&mut self, bytes: u64, limit: Option<u64>) {
+        let wait = self.reserve(bytes, limit);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl DownloadManager {
+    /// Set or clear (None or 0) one download's own speed limit. A running
+    /// download picks it up with its next chunk.
+    pub async fn set_speed_limit_for(&self, download_id: &str, bytes_per_sec: Option<u64>) -> Result<()> {
+        let progress = {
+            let mut downloads = self.downloads.write().await;
+            let progress = downloads
+                .get_mut(download_id)
+                .ok_or_else(|| anyhow!("Download not found: {}", download_id))?;
+            progress.speed_limit = bytes_per_sec.filter(|limit| *limit > 0);
+            progress.clone()
+        };
+        log::debug!("Speed limit of download {}: {:?} bytes/s", download_id, progress.speed_limit);
+
+        self.save_to_database(&progress).await?;
+        self.emit_progress(&progress);
+        Ok(())
+    }
+}
+
+/// The stored limit, or 0 when unset
+pub async fn load_speed_limit_setting(pool: &SqlitePool) -> u64 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(SPEED_LIMIT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.and_then(|v| v.trim().parse().ok()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_takers_share_the_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(start);
+
+        // Three downloads each take 100 KB at once with a 100 KB/s limit:
+        // together they have to wait three seconds' worth
+        let rate = 100_000;
+        bucket.take(100_000, rate, start);
+        bucket.take(100_000, rate, start);
+        let wait = bucket.take(100_000, rate, start);
+        assert_eq!(wait, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn idle_time_refills_up_to_one_second() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(start);
+        let rate = 1_000;
+
+        // Ten idle seconds only bank one second of bandwidth
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.take(1_000, rate, later), Duration::ZERO);
+        assert_eq!(bucket.take(500, rate, later), Duration::from_millis(500));
+
+        // Paying the debt back takes the waited time
+        let after_wait = later + Duration::from_millis(500);
+        assert_eq!(bucket.take(0, rate, after_wait), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn download_limits_are_set_and_cleared() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let download: super::super::DownloadProgress = serde_json::from_value(serde_json::json!({
+            "id": "movie",
+            "media_id": "media-1",
+            "episode_id": "movie-1",
+            "episode_number": 1,
+            "filename": "movie.mp4",
+            "url": "https://example.test/movie.mp4",
+            "file_path": temp_dir.path().join("movie.mp4").to_string_lossy(),
+            "total_bytes": 0,
+            "downloaded_bytes": 0,
+            "percentage": 0.0,
+            "speed": 0,
+            "status": "downloading",
+            "error_message": null,
+        }))
+        .unwrap();
+        manager.downloads.write().await.insert("movie".to_string(), download);
+
+        manager.set_speed_limit_for("movie", Some(500 * 1024)).await.unwrap();
+        assert_eq!(manager.get_progress("movie").await.unwrap().speed_limit, Some(500 * 1024));
+        manager.set_speed_limit_for("movie", Some(0)).await.unwrap();
+        assert_eq!(manager.get_progress("movie").await.unwrap().speed_limit, None);
+        assert!(manager.set_speed_limit_for("missing", Some(1)).await.is_err());
+    }
+
+    #[test]
+    fn download_pacer_follows_limit_changes() {
+        let start = Instant::now();
+        let mut pacer = DownloadPacer::new();
+
+        assert_eq!(pacer.wait_for(1_000_000, None, start), Duration::ZERO);
+        // 500 KB/s: a 250 KB chunk waits half a second
+        assert_eq!(pacer.wait_for(250_000, Some(500_000), start), Duration::from_millis(500));
+
+        // Raised mid-transfer: the old debt is dropped and the new rate applies
+        let later = start + Duration::from_millis(100);
+        assert_eq!(pacer.wait_for(250_000, Some(1_000_000), later), Duration::from_millis(250));
+        assert_eq!(pacer.wait_for(250_000, Some(0), later), Duration::ZERO);
+    }
+}
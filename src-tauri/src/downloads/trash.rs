@@ -0,0 +1,243 @@
+// Download Trash
+//
+// Deleting a completed episode can be soft: the file moves into a `.trash`
+// folder inside the downloads directory and the download keeps its row with
+// file_state = trashed, so it can be restored to where it was. Deleting a
+// trashed download (delete_download) removes the trashed file for good.
+//
+// Queueing a download with overwrite set also goes through the trash: the
+// existing file is trashed and its row renamed out of the way before the
+// replacement starts, so a re-download that fails leaves it restorable.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use super::archive::move_file;
+use super::organize::unique_destination;
+use super::{DownloadManager, DownloadStatus, ExistingDownload, FileState};
+
+/// Trash folder inside the downloads directory
+pub const TRASH_DIR: &str = ".trash";
+
+/// Appended to the id and episode id of a download set aside by an
+/// overwrite; the downloads table allows one row per (media_id, episode_id)
+const OVERWRITTEN_SUFFIX: &str = ":overwritten";
+
+impl DownloadManager {
+    /// Move a completed download's file to the trash, keeping the download
+    pub async fn trash_download(&self, download_id: &str) -> Result<()> {
+        let download = self
+            .get_progress(download_id)
+            .await
+            .context("Download not found")?;
+
+        if download.status != DownloadStatus::Completed || download.file_state != FileState::Present {
+            bail!("Only downloads with a local file can be moved to the trash");
+        }
+
+        let trash_dir = self.download_dir.join(TRASH_DIR);
+        tokio::fs::create_dir_all(&trash_dir)
+            .await
+            .with_context(|| format!("Failed to create trash directory: {}", trash_dir.display()))?;
+
+        let source = PathBuf::from(&download.file_path);
+        let file_name = source
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| download.filename.clone());
+        let dest = unique_destination(&trash_dir, &file_name).await;
+
+        move_file(&source, &dest).await?;
+        self.set_trash_location(download_id, &dest, Some(&source), FileState::Trashed).await?;
+
+        log::debug!("Moved download {} to trash", download_id);
+        Ok(())
+    }
+
+    /// Make way for a download that overwrites `existing`. A local file is
+    /// moved to the trash and the old download keeps it under a new id, so
+    /// the replacement can take the old id.
+    pub(super) async fn set_aside_for_overwrite(&self, existing: &ExistingDownload) -> Result<()> {
+        let Some(download) = &existing.download else {
+            // A file no download tracks
+            let source = PathBuf::from(&existing.file_path);
+            let file_name = source
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .context("Invalid file path")?;
+            let trash_dir = self.download_dir.join(TRASH_DIR);
+            tokio::fs::create_dir_all(&trash_dir)
+                .await
+                .with_context(|| format!("Failed to create trash directory: {}", trash_dir.display()))?;
+            let dest = unique_destination(&trash_dir, &file_name).await;
+            move_file(&source, &dest).await?;
+            log::debug!("Moved {} to trash before overwriting it", source.display());
+            return Ok(());
+        };
+
+        // Archived files aren't where the replacement goes and stay put
+        if download.file_state == FileState::Present {
+            self.trash_download(&download.id).await?;
+        }
+
+        let stamp = chrono::Utc::now().timestamp_millis();
+        let new_id = format!("{}{}:{}", download.id, OVERWRITTEN_SUFFIX, stamp);
+        let new_episode_id = format!("{}{}:{}", download.episode_id, OVERWRITTEN_SUFFIX, stamp);
+
+        if let Some(pool) = &self.db_pool {
+            sqlx::query("UPDATE downloads SET id = ?, episode_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&new_id)
+                .bind(&new_episode_id)
+                .bind(&download.id)
+                .execute(pool.as_ref())
+                .await?;
+        }
+
+        let mut downloads = self.downloads.write().await;
+        if let Some(mut old) = downloads.remove(&download.id) {
+            old.id = new_id.clone();
+            old.episode_id = new_episode_id;
+            self.emit_progress(&old);
+            downloads.insert(new_id.clone(), old);
+        }
+
+        log::debug!("Set aside download {} as {} to overwrite it", download.id, new_id);
+        Ok(())
+    }
+
+    /// Move a trashed download's file back to where it was (or into the
+    /// downloads directory if that location is unknown)
+    pub async fn restore_download(&self, download_id: &str) -> Result<()> {
+        let download = self
+            .get_progress(download_id)
+            .await
+            .context("Download not found")?;
+
+        if download.file_state != FileState::Trashed {
+            bail!("Download is not in the trash");
+        }
+
+        let original = self.original_path(download_id).await?;
+        let (dir, file_name) = match &original {
+            Some(path) => (
+                path.parent().map(Path::to_path_buf).unwrap_or_else(|| self.download_dir.clone()),
+                path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_else(|| download.filename.clone()),
+            ),
+            None => (self.download_dir.clone(), download.filename.clone()),
+        };
+        tokio::fs::create_dir_all(&dir).await.ok();
+        let dest = unique_destination(&dir, &file_name).await;
+
+        move_file(Path::new(&download.file_path), &dest).await?;
+        self.set_trash_location(download_id, &dest, None, FileState::Present).await?;
+
+        log::debug!("Restored download {} from trash", download_id);
+        Ok(())
+    }
+
+    /// Record a download's location and file state in memory and in the database
+    async fn set_trash_location(
+        &self,
+        download_id: &str,
+        path: &Path,
+        original_path: Option<&Path>,
+        file_state: FileState,
+    ) -> Result<()> {
+        let path_str = path.to_string_lossy().to_string();
+
+        if let Some(pool) = &self.db_pool {
+            sqlx::query(
+                r#"
+                UPDATE downloads
+                SET file_path = ?, original_path = ?, file_state = ?, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#
+            )
+            .bind(&path_str)
+            .bind(original_path.map(|p| p.to_string_lossy().to_string()))
+            .bind(file_state.as_db_str())
+            .bind(download_id)
+            .execute(pool.as_ref())
+            .await?;
+        }
+
+        let mut downloads = self.downloads.write().await;
+        if let Some(d) = downloads.get_mut(download_id) {
+            d.file_path = path_str;
+            d.file_state = file_state;
+            self.emit_progress(d);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::DownloadProgress;
+
+    #[tokio::test]
+    async fn trash_and_restore_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let downloads_dir = temp_dir.path().to_path_buf();
+        let local = downloads_dir.join("Episode_1.otaku");
+        tokio::fs::write(&local, b"video").await.unwrap();
+
+        let manager = DownloadManager::new(downloads_dir.clone());
+        manager.downloads.write().await.insert(
+            "download-1".to_string(),
+            DownloadProgress {
+                id: "download-1".to_string(),
+                media_id: "media-1".to_string(),
+                episode_id: "episode-1".to_string(),
+                episode_number: 1,
+                media_title: None,
+                filename: "Episode_1.otaku".to_string(),
+                url: "https://example.test/video.mp4".to_string(),
+                file_path: local.to_string_lossy().to_string(),
+                total_bytes: 5,
+                downloaded_bytes: 5,
+                percentage: 100.0,
+                speed: 0,
+                status: DownloadStatus::Completed,
+                error_message: None,
+                retry_count: 0,
+                segments: None,
+                eta_seconds: None,
+                archived: false,
+                quality: None,
+                source_label: None,
+                replaces_download_id: None,
+                batch_id: None,
+                source_extension_id: None,
+                file_state: FileState::Present,
+                scheduled_start: None,
+                start_now: false,
+                speed_limit: None,
+                paused_globally: false,
+                headers: Default::default(),
+            },
+        );
+
+        manager.trash_download("download-1").await.unwrap();
+
+        let trashed = manager.get_progress("download-1").await.unwrap();
+        assert_eq!(trashed.status, DownloadStatus::Completed);
+        assert_eq!(trashed.file_state, FileState::Trashed);
+        assert!(!local.exists());
+        assert!(downloads_dir.join(TRASH_DIR).join("Episode_1.otaku").exists());
+        assert!(!manager.is_episode_downloaded("media-1", 1).await);
+
+        // A rescan leaves trashed files alone
+        manager.rescan_downloads().await.unwrap();
+        assert_eq!(manager.get_progress("download-1").await.unwrap().file_state, FileState::Trashed);
+
+        // Without a database the original path is unknown, so it lands in the downloads dir
+        manager.restore_download("download-1").await.unwrap();
+        assert!(local.exists());
+        assert_eq!(manager.get_progress("download-1").await.unwrap().file_state, FileState::Present);
+        assert!(manager.is_episode_downloaded("media-1", 1).await);
+    }
+}
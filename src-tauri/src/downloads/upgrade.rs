@@ -0,0 +1,470 @@
+// Download Quality Upgrades
+//
+// Every download records the quality and source label it was queued with.
+// find_upgradeable_downloads lists completed episodes below the preferred
+// quality, and upgrade_download queues a replacement that downloads alongside
+// the old file. Only once the replacement has fully completed is its file
+// swapped in (a rename onto the old path) and the old row updated, so the
+// episode stays playable throughout and a failed upgrade changes nothing.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+use tokio::sync::RwLock;
+
+use super::organize::unique_destination;
+use super::{DownloadManager, DownloadProgress, DownloadStatus, FileState};
+use crate::events::DOWNLOAD_PROGRESS_EVENT;
+
+/// app_settings key holding the quality downloads should be upgraded to
+pub const PREFERRED_QUALITY_SETTING: &str = "preferred_quality";
+
+pub const DEFAULT_PREFERRED_QUALITY: &str = "1080p";
+
+/// Appended to the episode id of upgrade rows; the downloads table only allows
+/// one row per (media_id, episode_id) and the original row must stay intact
+const UPGRADE_EPISODE_SUFFIX: &str = ":upgrade";
+
+/// Episode id the extension knows a download's episode by; upgrade rows
+/// carry a suffix
+pub fn source_episode_id(episode_id: &str) -> &str {
+    episode_id.strip_suffix(UPGRADE_EPISODE_SUFFIX).unwrap_or(episode_id)
+}
+
+/// A completed download stored below the preferred quality
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeCandidate {
+    pub download_id: String,
+    pub media_id: String,
+    pub episode_id: String,
+    pub episode_number: i32,
+    pub quality: Option<String>,
+    pub source_label: Option<String>,
+    pub preferred_quality: String,
+}
+
+/// Vertical resolution from a quality label ("1080p HardSub" → 1080, "4K" → 2160).
+/// Labels without a resolution ("Auto", "default") give None.
+pub fn quality_rank(label: &str) -> Option<u32> {
+    let lower = label.to_lowercase();
+    if lower.contains("4k") || lower.contains("uhd") {
+        return Some(2160);
+    }
+
+    let chars: Vec<char> = lower.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            if chars.get(i) == Some(&'p') {
+                return number.parse().ok();
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    None
+}
+
+impl DownloadManager {
+    /// Read the preferred quality setting
+    pub async fn preferred_quality(&self) -> String {
+        let Some(pool) = &self.db_pool else {
+            return DEFAULT_PREFERRED_QUALITY.to_string();
+        };
+
+        sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+            .bind(PREFERRED_QUALITY_SETTING)
+            .fetch_optional(pool.as_ref())
+            .await
+            .ok()
+            .flatten()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PREFERRED_QUALITY.to_string())
+    }
+
+    /// List completed downloads whose recorded quality is below `preferred`
+    /// (or the preferred_quality setting). Downloads without a recorded
+    /// quality are skipped since there's nothing to compare.
+    pub async fn find_upgradeable_downloads(&self, preferred: Option<String>) -> Vec<UpgradeCandidate> {
+        let preferred = match preferred {
+            Some(p) => p,
+            None => self.preferred_quality().await,
+        };
+        let Some(target) = quality_rank(&preferred) else {
+            return Vec::new();
+        };
+
+        let downloads = self.downloads.read().await;
+
+        let upgrading: Vec<&str> = downloads
+            .values()
+            .filter(|d| matches!(d.status, DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused))
+            .filter_map(|d| d.replaces_download_id.as_deref())
+            .collect();
+
+        let mut candidates: Vec<UpgradeCandidate> = downloads
+            .values()
+            .filter(|d| d.status == DownloadStatus::Completed && !d.archived)
+            .filter(|d| d.replaces_download_id.is_none() && !upgrading.contains(&d.id.as_str()))
+            .filter(|d| {
+                d.quality
+                    .as_deref()
+                    .and_then(quality_rank)
+                    .map(|rank| rank < target)
+                    .unwrap_or(false)
+            })
+            .map(|d| UpgradeCandidate {
+                download_id: d.id.clone(),
+                media_id: d.media_id.clone(),
+                episode_id: d.episode_id.clone(),
+                episode_number: d.episode_number,
+                quality: d.quality.clone(),
+                source_label: d.source_label.clone(),
+                preferred_quality: preferred.clone(),
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            a.media_id
+                .cmp(&b.media_id)
+                .then(a.episode_number.cmp(&b.episode_number))
+        });
+
+        candidates
+    }
+
+    /// Queue a better-quality replacement for a completed download.
+    /// Returns the id of the upgrade download.
+    pub async fn upgrade_download(
+        &self,
+        download_id: &str,
+        url: String,
+        headers: HashMap<String, String>,
+        filename: String,
+        quality: Option<String>,
+        source_label: Option<String>,
+    ) -> Result<String> {
+        let old = self
+            .get_progress(download_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+
+        if old.status != DownloadStatus::Completed || old.archived {
+            anyhow::bail!("Only completed, local downloads can be upgraded");
+        }
+
+        if let (Some(current), Some(new)) = (
+            old.quality.as_deref().and_then(quality_rank),
+            quality.as_deref().and_then(quality_rank),
+        ) {
+            if new <= current {
+                anyhow::bail!("Selected source is not higher quality than the current download");
+            }
+        }
+
+        let upgrade_id = format!("{}_upgrade", old.id);
+        if let Some(existing) = self.get_progress(&upgrade_id).await {
+            match existing.status {
+                DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused => {
+                    anyhow::bail!("An upgrade for this episode is already in progress");
+                }
+                // Leftover from a failed or cancelled attempt
+                _ => {
+                    tokio::fs::remove_file(&existing.file_path).await.ok();
+                    self.remove_download(&upgrade_id).await?;
+                }
+            }
+        }
+
+        // Download next to the old file so the final swap is a same-filesystem rename
+        let dir = Path::new(&old.file_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.download_dir.clone());
+        let file_path = unique_destination(&dir, &filename).await;
+
+        let progress = DownloadProgress {
+            id: upgrade_id.clone(),
+            media_id: old.media_id.clone(),
+            episode_id: format!("{}{}", old.episode_id, UPGRADE_EPISODE_SUFFIX),
+            episode_number: old.episode_number,
+            media_title: old.media_title.clone(),
+            filename,
+            url,
+            file_path: file_path.to_string_lossy().to_string(),
+            total_bytes: 0,
+            downloaded_bytes: 0,
+            percentage: 0.0,
+            speed: 0,
+            status: DownloadStatus::Queued,
+            error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality,
+            source_label,
+            replaces_download_id: Some(old.id.clone()),
+            batch_id: None,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers,
+        };
+
+        self.save_to_database(&progress).await.ok();
+        self.downloads.write().await.insert(upgrade_id.clone(), progress.clone());
+        self.emit_progress(&progress);
+
+        log::debug!("Queued quality upgrade {} for {}", upgrade_id, old.id);
+
+        self.start_download_task(upgrade_id.clone()).await?;
+
+        Ok(upgrade_id)
+    }
+
+    /// Swap a completed upgrade's file in for the download it replaces.
+    ///
+    /// The new file is renamed onto the old path when the extensions match
+    /// (atomic on the same filesystem), so the episode never disappears.
+    /// The old row keeps its id and takes over the new url, size and quality;
+    /// the upgrade row is removed.
+    pub(super) async fn finish_upgrade(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+        upgrade_id: &str,
+        old_id: &str,
+    ) -> Result<()> {
+        let (upgrade, old) = {
+            let map = downloads.read().await;
+            (map.get(upgrade_id).cloned(), map.get(old_id).cloned())
+        };
+        let upgrade = upgrade.context("Upgrade download not found")?;
+
+        let Some(old) = old else {
+            // The original was deleted meanwhile; keep the upgrade as a normal download
+            return Self::detach_upgrade(downloads, db_pool, app_handle, &upgrade).await;
+        };
+
+        let new_path = PathBuf::from(&upgrade.file_path);
+        let old_path = PathBuf::from(&old.file_path);
+
+        let final_path = if new_path.extension() == old_path.extension() {
+            old_path.clone()
+        } else {
+            let renamed = old_path.with_extension(new_path.extension().unwrap_or_default());
+            if tokio::fs::metadata(&renamed).await.is_ok() {
+                new_path.clone()
+            } else {
+                renamed
+            }
+        };
+
+        if final_path != new_path {
+            tokio::fs::rename(&new_path, &final_path)
+                .await
+                .with_context(|| format!("Failed to move upgraded file to {}", final_path.display()))?;
+        }
+        if final_path != old_path {
+            tokio::fs::remove_file(&old_path).await.ok();
+        }
+
+        let final_filename = final_path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| upgrade.filename.clone());
+        let final_path_str = final_path.to_string_lossy().to_string();
+
+        if let Some(pool) = db_pool {
+            let mut tx = pool.begin().await?;
+            sqlx::query(
+                r#"
+                UPDATE downloads
+                SET url = ?, filename = ?, file_path = ?, total_bytes = ?, downloaded_bytes = ?,
+                    quality = ?, source_label = ?, file_state = 'present', sha256 = NULL, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#
+            )
+            .bind(&upgrade.url)
+            .bind(&final_filename)
+            .bind(&final_path_str)
+            .bind(upgrade.total_bytes as i64)
+            .bind(upgrade.downloaded_bytes as i64)
+            .bind(&upgrade.quality)
+            .bind(&upgrade.source_label)
+            .bind(old_id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM downloads WHERE id = ?")
+                .bind(upgrade_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+
+        let mut map = downloads.write().await;
+        map.remove(upgrade_id);
+        if let Some(progress) = map.get_mut(old_id) {
+            progress.url = upgrade.url.clone();
+            progress.filename = final_filename;
+            progress.file_path = final_path_str;
+            progress.total_bytes = upgrade.total_bytes;
+            progress.downloaded_bytes = upgrade.downloaded_bytes;
+            progress.quality = upgrade.quality.clone();
+            progress.source_label = upgrade.source_label.clone();
+            progress.file_state = FileState::Present;
+
+            if let Some(handle) = app_handle {
+                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+            }
+        }
+
+        log::info!(
+            "Upgraded {} to {} ({:?})",
+            old_id, upgrade.quality.as_deref().unwrap_or("unknown quality"), final_path
+        );
+
+        Ok(())
+    }
+
+    async fn detach_upgrade(
+        downloads: &Arc<RwLock<HashMap<String, DownloadProgress>>>,
+        db_pool: Option<&Arc<SqlitePool>>,
+        app_handle: Option<&AppHandle>,
+        upgrade: &DownloadProgress,
+    ) -> Result<()> {
+        let episode_id = source_episode_id(&upgrade.episode_id).to_string();
+
+        if let Some(pool) = db_pool {
+            sqlx::query("UPDATE downloads SET episode_id = ?, replaces_download_id = NULL WHERE id = ?")
+                .bind(&episode_id)
+                .bind(&upgrade.id)
+                .execute(pool.as_ref())
+                .await?;
+        }
+
+        let mut map = downloads.write().await;
+        if let Some(progress) = map.get_mut(&upgrade.id) {
+            progress.episode_id = episode_id;
+            progress.replaces_download_id = None;
+
+            if let Some(handle) = app_handle {
+                DOWNLOAD_PROGRESS_EVENT.emit(handle, progress);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(id: &str, file_path: &Path, quality: Option<&str>) -> DownloadProgress {
+        DownloadProgress {
+            id: id.to_string(),
+            media_id: "media-1".to_string(),
+            episode_id: format!("{}-ep", id),
+            episode_number: 1,
+            media_title: None,
+            filename: file_path.file_name().unwrap().to_string_lossy().to_string(),
+            url: format!("https://example.test/{}.mp4", id),
+            file_path: file_path.to_string_lossy().to_string(),
+            total_bytes: 3,
+            downloaded_bytes: 3,
+            percentage: 100.0,
+            speed: 0,
+            status: DownloadStatus::Completed,
+            error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality: quality.map(str::to_string),
+            source_label: None,
+            replaces_download_id: None,
+            batch_id: None,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_quality_rank() {
+        assert_eq!(quality_rank("1080p HardSub"), Some(1080));
+        assert_eq!(quality_rank("720p"), Some(720));
+        assert_eq!(quality_rank("4K"), Some(2160));
+        assert_eq!(quality_rank("Auto"), None);
+        assert_eq!(quality_rank("Server 2"), None);
+    }
+
+    #[tokio::test]
+    async fn test_find_upgradeable_downloads() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        {
+            let mut map = manager.downloads.write().await;
+            map.insert("low".into(), download("low", &temp_dir.path().join("a.mp4"), Some("720p")));
+            map.insert("high".into(), download("high", &temp_dir.path().join("b.mp4"), Some("1080p")));
+            map.insert("unknown".into(), download("unknown", &temp_dir.path().join("c.mp4"), None));
+        }
+
+        let candidates = manager.find_upgradeable_downloads(Some("1080p".to_string())).await;
+        let ids: Vec<&str> = candidates.iter().map(|c| c.download_id.as_str()).collect();
+        assert_eq!(ids, vec!["low"]);
+
+        assert!(manager.find_upgradeable_downloads(Some("Auto".to_string())).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finish_upgrade_replaces_old_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old_path = temp_dir.path().join("Show_EP1_720p.otaku");
+        let new_path = temp_dir.path().join("Show_EP1_1080p.otaku");
+        tokio::fs::write(&old_path, b"old").await.unwrap();
+        tokio::fs::write(&new_path, b"new-hd").await.unwrap();
+
+        let manager = DownloadManager::new(temp_dir.path().to_path_buf());
+        let mut upgrade = download("old_upgrade", &new_path, Some("1080p"));
+        upgrade.total_bytes = 6;
+        upgrade.replaces_download_id = Some("old".to_string());
+        {
+            let mut map = manager.downloads.write().await;
+            map.insert("old".into(), download("old", &old_path, Some("720p")));
+            map.insert("old_upgrade".into(), upgrade);
+        }
+
+        DownloadManager::finish_upgrade(&manager.downloads, None, None, "old_upgrade", "old")
+            .await
+            .unwrap();
+
+        assert!(manager.get_progress("old_upgrade").await.is_none());
+        let old = manager.get_progress("old").await.unwrap();
+        assert_eq!(old.quality.as_deref(), Some("1080p"));
+        assert_eq!(old.total_bytes, 6);
+        assert_eq!(PathBuf::from(&old.file_path), old_path);
+        assert_eq!(tokio::fs::read(&old_path).await.unwrap(), b"new-hd");
+        assert!(!new_path.exists());
+    }
+}
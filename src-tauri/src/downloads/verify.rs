@@ -0,0 +1,466 @@
+// Download Verification
+//
+// Re-checks completed downloads against what was recorded when they
+// finished: the file has to exist and be total_bytes long. With
+// download_checksums on, the SHA-256 of each file is stored when it
+// completes; otherwise it's stored the first time the file is verified with
+// hashing. Later hashed verifications compare against it, which catches
+// corruption that leaves the size alone.
+//
+// A file of the wrong size or checksum marks its download failed with the
+// reason, so the UI offers to download it again. A missing file only updates
+// file_state, as it does at startup. The background sweep (verify_sweep.rs)
+// also checks that the file starts like a video container, and only flags a
+// bad file as corrupt instead of failing its download.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+use super::{DownloadManager, DownloadProgress, DownloadStatus, FileState};
+use crate::events::DOWNLOAD_VERIFY_PROGRESS_EVENT;
+
+/// app_settings key: "true" to hash each download's file when it completes
+pub const CHECKSUMS_SETTING: &str = "download_checksums";
+
+/// Prevents two verify-all runs from marking the same downloads
+static VERIFY_ALL_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Why a completed download's file can't be trusted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityProblem {
+    Missing,
+    SizeMismatch { expected: u64, actual: u64 },
+    /// The file doesn't start like any video container
+    UnknownContainer,
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl IntegrityProblem {
+    /// Shown as the download's error message
+    pub fn message(&self) -> String {
+        match self {
+            IntegrityProblem::Missing => "File not found".to_string(),
+            IntegrityProblem::SizeMismatch { expected, actual } => format!(
+                "File is {} bytes but the download was {} bytes; download it again",
+                actual, expected
+            ),
+            IntegrityProblem::UnknownContainer => {
+                "File doesn't start like a video file; download it again".to_string()
+            }
+            IntegrityProblem::ChecksumMismatch { .. } => {
+                "File contents changed since it was downloaded (checksum mismatch); download it again".to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    pub download_id: String,
+    pub ok: bool,
+    pub problem: Option<IntegrityProblem>,
+    /// Whether the file was hashed this time
+    pub checksum_checked: bool,
+    /// Whether this verification stored the file's first checksum
+    pub checksum_recorded: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, schemars::JsonSchema)]
+pub struct DownloadVerifyProgress {
+    pub total: usize,
+    pub processed: usize,
+    /// Marked failed for a wrong size or checksum
+    pub failed: usize,
+    pub missing: usize,
+    pub current_title: String,
+    pub status: String, // "running" | "completed"
+}
+
+/// What's wrong with a file of `actual_size` bytes (None when it doesn't
+/// exist). A download of unknown size (0) only has to exist, and checksums
+/// are compared only when both are known.
+pub fn check_file(
+    expected_size: u64,
+    actual_size: Option<u64>,
+    expected_hash: Option<&str>,
+    actual_hash: Option<&str>,
+) -> Option<IntegrityProblem> {
+    let actual_size = match actual_size {
+        Some(size) => size,
+        None => return Some(IntegrityProblem::Missing),
+    };
+    if expected_size > 0 && actual_size != expected_size {
+        return Some(IntegrityProblem::SizeMismatch { expected: expected_size, actual: actual_size });
+    }
+    match (expected_hash, actual_hash) {
+        (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(actual) => {
+            Some(IntegrityProblem::ChecksumMismatch { expected: expected.to_string(), actual: actual.to_string() })
+        }
+        _ => None,
+    }
+}
+
+/// How a verification treats a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum VerifyMode {
+    /// Asked for by the user: a bad file fails its download
+    Manual,
+    /// The background sweep: the container is checked too, a stored
+    /// checksum is always compared, and a bad file is only flagged corrupt
+    Sweep,
+}
+
+/// Whether a file starts like a video container (see remux::detect_container)
+async fn check_container(path: &Path) -> Result<Option<IntegrityProblem>> {
+    let container = crate::media::remux::detect_container(path).await?;
+    Ok((container == crate::media::remux::Container::Unknown).then_some(IntegrityProblem::UnknownContainer))
+}
+
+/// Hex SHA-256 of a file, read on a blocking thread
+pub async fn file_sha256(path: &Path) -> Result<String> {
+    let path: PathBuf = path.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<String> {
+        let mut file = std::fs::File::open(&path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await?
+}
+
+/// Whether files are hashed as they complete (download_checksums)
+pub async fn checksums_enabled(pool: &SqlitePool) -> bool {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(CHECKSUMS_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value.as_deref() == Some("true")
+}
+
+/// The checksum recorded for a download, if any
+pub async fn stored_checksum(pool: &SqlitePool, download_id: &str) -> Result<Option<String>> {
+    let hash: Option<Option<String>> = sqlx::query_scalar("SELECT sha256 FROM downloads WHERE id = ?")
+        .bind(download_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(hash.flatten())
+}
+
+async fn store_checksum(pool: &SqlitePool, download_id: &str, hash: &str) -> Result<()> {
+    sqlx::query("UPDATE downloads SET sha256 = ? WHERE id = ?")
+        .bind(hash)
+        .bind(download_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+async fn mark_verified(pool: &SqlitePool, download_id: &str) -> Result<()> {
+    sqlx::query("UPDATE downloads SET verified_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now().timestamp_millis())
+        .bind(download_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Hash a download's file as it completes, with download_checksums on.
+/// Failures are logged; the download itself has still succeeded.
+pub(super) async fn record_on_completion(pool: &SqlitePool, progress: &DownloadProgress) {
+    if !checksums_enabled(pool).await {
+        return;
+    }
+    let result = match file_sha256(Path::new(&progress.file_path)).await {
+        Ok(hash) => store_checksum(pool, &progress.id, &hash).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to record checksum of download {}: {}", progress.id, e);
+    }
+}
+
+impl DownloadManager {
+    /// Check that a completed download's file exists and has its recorded
+    /// size, and with `hash` set that its checksum still matches (storing it
+    /// if none was recorded yet). Marks the download failed, or its file
+    /// missing, when it doesn't hold up.
+    pub async fn verify_download(&self, download_id: &str, hash: bool) -> Result<VerifyResult> {
+        self.verify_with(download_id, hash, VerifyMode::Manual).await
+    }
+
+    /// Verify a download with the checks and outcome of `mode`
+    pub(super) async fn verify_with(&self, download_id: &str, hash: bool, mode: VerifyMode) -> Result<VerifyResult> {
+        let progress = self
+            .downloads
+            .read()
+            .await
+            .get(download_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Download not found: {}", download_id))?;
+        if progress.status != DownloadStatus::Completed {
+            bail!("Only completed downloads can be verified");
+        }
+        if progress.file_state == FileState::Trashed {
+            bail!("Download is in the trash");
+        }
+
+        let pool = self.db_pool.as_deref();
+        let expected_hash = match pool {
+            Some(pool) => stored_checksum(pool, download_id).await?,
+            None => None,
+        };
+
+        let actual_size = tokio::fs::metadata(&progress.file_path).await.ok().map(|m| m.len());
+        let mut problem = check_file(progress.total_bytes, actual_size, None, None);
+        if mode == VerifyMode::Sweep && problem.is_none() {
+            problem = check_container(Path::new(&progress.file_path)).await?;
+        }
+        let hash = hash || (mode == VerifyMode::Sweep && expected_hash.is_some());
+        let mut actual_hash = None;
+        if hash && problem.is_none() {
+            let computed = file_sha256(Path::new(&progress.file_path)).await?;
+            problem = check_file(progress.total_bytes, actual_size, expected_hash.as_deref(), Some(&computed));
+            actual_hash = Some(computed);
+        }
+
+        let mut checksum_recorded = false;
+        match &problem {
+            None => {
+                if let (Some(pool), Some(hash), None) = (pool, &actual_hash, &expected_hash) {
+                    store_checksum(pool, download_id, hash).await?;
+                    checksum_recorded = true;
+                }
+                if matches!(progress.file_state, FileState::Missing | FileState::Corrupt) {
+                    let state = FileState::observe(FileState::Missing, progress.archived, true);
+                    self.update_verified(download_id, |d| {
+                        d.file_state = state;
+                        d.error_message = None;
+                    })
+                    .await?;
+                }
+            }
+            Some(IntegrityProblem::Missing) => {
+                self.update_verified(download_id, |d| d.file_state = FileState::Missing).await?;
+            }
+            Some(problem) if mode == VerifyMode::Sweep => {
+                log::warn!("Download {} is corrupt: {:?}", download_id, problem);
+                let message = problem.message();
+                self.update_verified(download_id, |d| {
+                    d.file_state = FileState::Corrupt;
+                    d.error_message = Some(message);
+                })
+                .await?;
+            }
+            Some(problem) => {
+                log::warn!("Download {} failed verification: {:?}", download_id, problem);
+                let message = problem.message();
+                self.update_verified(download_id, |d| {
+                    d.status = DownloadStatus::Failed;
+                    d.error_message = Some(message);
+                    // Start over instead of resuming from the bad file
+                    d.downloaded_bytes = 0;
+                    d.percentage = 0.0;
+                })
+                .await?;
+            }
+        }
+        if let Some(pool) = pool {
+            mark_verified(pool, download_id).await?;
+        }
+
+        Ok(VerifyResult {
+            download_id: download_id.to_string(),
+            ok: problem.is_none(),
+            problem,
+            checksum_checked: actual_hash.is_some(),
+            checksum_recorded,
+        })
+    }
+
+    /// Apply a verification outcome to a download, then save and emit it
+    async fn update_verified(&self, download_id: &str, update: impl FnOnce(&mut DownloadProgress)) -> Result<()> {
+        let progress = {
+            let mut downloads = self.downloads.write().await;
+            let Some(progress) = downloads.get_mut(download_id) else {
+                return Ok(());
+            };
+            update(progress);
+            progress.clone()
+        };
+        self.save_to_database(&progress).await?;
+        self.emit_progress(&progress);
+        Ok(())
+    }
+
+    /// Verify every completed download one by one, reporting progress after
+    /// each. Downloads that can't be verified are logged and skipped.
+    pub async fn verify_all(
+        &self,
+        hash: bool,
+        mut on_progress: impl FnMut(&DownloadVerifyProgress),
+    ) -> DownloadVerifyProgress {
+        let mut targets: Vec<(String, String)> = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .filter(|d| d.status == DownloadStatus::Completed && d.file_state != FileState::Trashed)
+            .map(|d| (d.id.clone(), d.display_title()))
+            .collect();
+        targets.sort();
+
+        let mut progress = DownloadVerifyProgress {
+            total: targets.len(),
+            status: "running".to_string(),
+            ..Default::default()
+        };
+
+        for (id, title) in targets {
+            progress.current_title = title;
+            match self.verify_download(&id, hash).await {
+                Ok(VerifyResult { problem: Some(IntegrityProblem::Missing), .. }) => progress.missing += 1,
+                Ok(VerifyResult { problem: Some(_), .. }) => progress.failed += 1,
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to verify download {}: {}", id, e),
+            }
+            progress.processed += 1;
+            on_progress(&progress);
+        }
+
+        progress.status = "completed".to_string();
+        progress.current_title = String::new();
+        on_progress(&progress);
+
+        log::info!(
+            "Download verification complete: {} failed, {} missing of {}",
+            progress.failed, progress.missing, progress.total
+        );
+        progress
+    }
+}
+
+/// Verify every completed download in the background, emitting
+/// download_verify_progress after each one
+pub fn start_verify_all(app_handle: AppHandle, hash: bool) -> Result<()> {
+    if VERIFY_ALL_RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("Download verification is already running");
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let manager = app_handle.state::<DownloadManager>();
+        manager
+            .verify_all(hash, |progress| DOWNLOAD_VERIFY_PROGRESS_EVENT.emit(&app_handle, progress))
+            .await;
+        VERIFY_ALL_RUNNING.store(false, Ordering::SeqCst);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_file_reports_the_first_problem() {
+        assert_eq!(check_file(10, None, None, None), Some(IntegrityProblem::Missing));
+        assert_eq!(
+            check_file(10, Some(4), Some("aa"), Some("bb")),
+            Some(IntegrityProblem::SizeMismatch { expected: 10, actual: 4 })
+        );
+        assert!(matches!(
+            check_file(10, Some(10), Some("aa"), Some("bb")),
+            Some(IntegrityProblem::ChecksumMismatch { .. })
+        ));
+        assert_eq!(check_file(10, Some(10), Some("AA"), Some("aa")), None);
+        // Unknown size and no recorded checksum: existing is enough
+        assert_eq!(check_file(0, Some(3), None, Some("aa")), None);
+    }
+
+    #[tokio::test]
+    async fn file_sha256_matches_known_digest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("abc.txt");
+        std::fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            file_sha256(&path).await.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn verification_records_then_checks_the_checksum() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool.clone()));
+
+        let path = temp_dir.path().join("ep1.mp4");
+        std::fs::write(&path, vec![1u8; 64]).unwrap();
+        let id = manager.adopt_file("show", 1, &path, None).await.unwrap().id;
+
+        // Without hashing only the size is checked and nothing is stored
+        let result = manager.verify_download(&id, false).await.unwrap();
+        assert!(result.ok && !result.checksum_checked);
+        assert_eq!(stored_checksum(&pool, &id).await.unwrap(), None);
+
+        let result = manager.verify_download(&id, true).await.unwrap();
+        assert!(result.ok && result.checksum_recorded);
+        assert!(stored_checksum(&pool, &id).await.unwrap().is_some());
+
+        // Same size, different contents
+        std::fs::write(&path, vec![2u8; 64]).unwrap();
+        let result = manager.verify_download(&id, true).await.unwrap();
+        assert!(matches!(result.problem, Some(IntegrityProblem::ChecksumMismatch { .. })));
+
+        let progress = manager.downloads.read().await[&id].clone();
+        assert_eq!(progress.status, DownloadStatus::Failed);
+        assert!(progress.error_message.unwrap().contains("checksum mismatch"));
+        assert_eq!(progress.downloaded_bytes, 0);
+        // The checksum went with the completed file
+        assert_eq!(stored_checksum(&pool, &id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn verify_all_fails_truncated_files_and_notes_missing_ones() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads"))
+            .with_database(Arc::new(database.pool().clone()));
+
+        let mut ids = Vec::new();
+        for episode in 1..=3 {
+            let path = temp_dir.path().join(format!("ep{}.mp4", episode));
+            std::fs::write(&path, vec![0u8; 100]).unwrap();
+            ids.push((manager.adopt_file("show", episode, &path, None).await.unwrap().id, path));
+        }
+        std::fs::write(&ids[1].1, vec![0u8; 40]).unwrap();
+        std::fs::remove_file(&ids[2].1).unwrap();
+
+        let mut reports = 0;
+        let progress = manager.verify_all(false, |_| reports += 1).await;
+        assert_eq!((progress.total, progress.processed), (3, 3));
+        assert_eq!((progress.failed, progress.missing), (1, 1));
+        assert_eq!(progress.status, "completed");
+        assert_eq!(reports, 4);
+
+        let downloads = manager.downloads.read().await;
+        assert_eq!(downloads[&ids[0].0].status, DownloadStatus::Completed);
+        assert_eq!(downloads[&ids[1].0].status, DownloadStatus::Failed);
+        assert!(downloads[&ids[1].0].error_message.as_deref().unwrap().contains("40 bytes"));
+        // A missing file keeps its download completed, like at startup
+        assert_eq!(downloads[&ids[2].0].status, DownloadStatus::Completed);
+        assert_eq!(downloads[&ids[2].0].file_state, FileState::Missing);
+    }
+}
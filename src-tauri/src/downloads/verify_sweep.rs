@@ -0,0 +1,467 @@
+// Periodic Download Verification
+//
+// Files on flaky storage (SD cards, old USB drives) rot without anyone
+// noticing until playback glitches. With download_verify_enabled on, the
+// maintenance scheduler's verify_downloads chore re-checks a few completed
+// downloads per run, those verified longest ago (or never) first, so every
+// download comes round in turn. Each file has to have its recorded size,
+// start like a video container and, when a checksum was stored for it,
+// still match it.
+//
+// A file that fails is flagged corrupt (file_state) rather than failing its
+// download, and one notification per run lists the affected episodes with
+// an action to download them again. Every check is recorded in
+// download_events for the downloads page.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use super::verify::{IntegrityProblem, VerifyMode, VerifyResult};
+use super::{DownloadManager, DownloadStatus, FileState};
+use crate::locale::{self, Locale};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key: "true" to verify downloads in the background
+pub const VERIFY_ENABLED_SETTING: &str = "download_verify_enabled";
+
+/// app_settings key: downloads checked per run
+pub const VERIFY_ITEMS_SETTING: &str = "download_verify_items_per_run";
+
+/// app_settings key: hours between runs
+pub const VERIFY_INTERVAL_SETTING: &str = "download_verify_interval_hours";
+
+/// Notification action callback that downloads the flagged episodes again
+pub const REDOWNLOAD_CORRUPT_CALLBACK: &str = "redownload_corrupt_downloads";
+
+const DEFAULT_ITEMS_PER_RUN: u32 = 20;
+const DEFAULT_INTERVAL_HOURS: u32 = 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifySchedule {
+    pub enabled: bool,
+    /// Downloads checked per run
+    #[serde(default = "default_items_per_run")]
+    pub items_per_run: u32,
+    /// Hours between runs
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u32,
+}
+
+fn default_items_per_run() -> u32 {
+    DEFAULT_ITEMS_PER_RUN
+}
+
+fn default_interval_hours() -> u32 {
+    DEFAULT_INTERVAL_HOURS
+}
+
+impl Default for VerifySchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            items_per_run: DEFAULT_ITEMS_PER_RUN,
+            interval_hours: DEFAULT_INTERVAL_HOURS,
+        }
+    }
+}
+
+impl VerifySchedule {
+    /// Time between runs; at least an hour
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_hours.max(1)) * 60 * 60)
+    }
+}
+
+/// Read the verification schedule, falling back to defaults
+pub async fn load_schedule(pool: &SqlitePool) -> Result<VerifySchedule> {
+    let rows = sqlx::query("SELECT key, value FROM app_settings WHERE key IN (?, ?, ?)")
+        .bind(VERIFY_ENABLED_SETTING)
+        .bind(VERIFY_ITEMS_SETTING)
+        .bind(VERIFY_INTERVAL_SETTING)
+        .fetch_all(pool)
+        .await?;
+
+    let mut schedule = VerifySchedule::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            VERIFY_ENABLED_SETTING => schedule.enabled = value == "true",
+            VERIFY_ITEMS_SETTING => schedule.items_per_run = value.parse().unwrap_or(DEFAULT_ITEMS_PER_RUN),
+            VERIFY_INTERVAL_SETTING => schedule.interval_hours = value.parse().unwrap_or(DEFAULT_INTERVAL_HOURS),
+            _ => {}
+        }
+    }
+
+    Ok(schedule)
+}
+
+/// Store the verification schedule
+pub async fn save_schedule(pool: &SqlitePool, schedule: &VerifySchedule) -> Result<()> {
+    let values = [
+        (VERIFY_ENABLED_SETTING, schedule.enabled.to_string()),
+        (VERIFY_ITEMS_SETTING, schedule.items_per_run.max(1).to_string()),
+        (VERIFY_INTERVAL_SETTING, schedule.interval_hours.max(1).to_string()),
+    ];
+
+    for (key, value) in values {
+        sqlx::query(
+            r#"
+            INSERT INTO app_settings (key, value, updated_at)
+            VALUES (?, ?, strftime('%s', 'now') * 1000)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// The outcome of one integrity check, from download_events
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DownloadEvent {
+    pub id: i64,
+    pub download_id: String,
+    pub media_id: String,
+    pub episode_number: i32,
+    /// verified, corrupt or missing
+    pub kind: String,
+    /// Why the file was flagged
+    pub detail: Option<String>,
+    /// Unix ms
+    pub created_at: i64,
+}
+
+/// A download whose file was flagged corrupt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorruptDownload {
+    pub download_id: String,
+    pub media_id: String,
+    pub title: String,
+    pub episode_number: i32,
+    pub reason: String,
+}
+
+/// What a verification run found
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VerifySweepSummary {
+    pub checked: usize,
+    pub corrupt: Vec<CorruptDownload>,
+    pub missing: usize,
+}
+
+impl VerifySweepSummary {
+    /// One line for the maintenance report
+    pub fn describe(&self) -> String {
+        format!("{} checked, {} corrupt, {} missing", self.checked, self.corrupt.len(), self.missing)
+    }
+}
+
+async fn record_event(
+    pool: &SqlitePool,
+    download_id: &str,
+    media_id: &str,
+    episode_number: i32,
+    problem: Option<&IntegrityProblem>,
+) -> Result<()> {
+    let kind = match problem {
+        None => "verified",
+        Some(IntegrityProblem::Missing) => "missing",
+        Some(_) => "corrupt",
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO download_events (download_id, media_id, episode_number, kind, detail, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(download_id)
+    .bind(media_id)
+    .bind(episode_number)
+    .bind(kind)
+    .bind(problem.map(|p| p.message()))
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The latest integrity checks, of one download or of all of them
+pub async fn list_events(pool: &SqlitePool, download_id: Option<&str>, limit: u32) -> Result<Vec<DownloadEvent>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, download_id, media_id, episode_number, kind, detail, created_at
+        FROM download_events
+        WHERE ?1 IS NULL OR download_id = ?1
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(download_id)
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(DownloadEvent {
+                id: row.try_get("id")?,
+                download_id: row.try_get("download_id")?,
+                media_id: row.try_get("media_id")?,
+                episode_number: row.try_get("episode_number")?,
+                kind: row.try_get("kind")?,
+                detail: row.try_get("detail")?,
+                created_at: row.try_get("created_at")?,
+            })
+        })
+        .collect()
+}
+
+/// Completed downloads whose file is in place, in the library or archived,
+/// verified longest ago first
+async fn due_for_verification(pool: &SqlitePool, limit: u32) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        r#"
+        SELECT id FROM downloads
+        WHERE status = 'completed' AND file_state IN ('present', 'archived')
+        ORDER BY verified_at IS NOT NULL, verified_at, id
+        LIMIT ?
+        "#,
+    )
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?)
+}
+
+impl DownloadManager {
+    /// Check `ids` the way the sweep does, recording each result, and sum
+    /// them up. `hash` also hashes files that have no checksum yet.
+    async fn check_downloads(&self, ids: Vec<String>, hash: bool) -> Result<(VerifySweepSummary, Vec<VerifyResult>)> {
+        let pool = self.db_pool.clone().context("Database not available")?;
+        let mut summary = VerifySweepSummary::default();
+        let mut results = Vec::new();
+
+        for id in ids {
+            let Some(download) = self.get_progress(&id).await else {
+                continue;
+            };
+            let result = match self.verify_with(&id, hash, VerifyMode::Sweep).await {
+                Ok(result) => result,
+                Err(e) => {
+                    log::warn!("Failed to verify download {}: {}", id, e);
+                    continue;
+                }
+            };
+            if let Err(e) = record_event(&pool, &id, &download.media_id, download.episode_number, result.problem.as_ref()).await {
+                log::warn!("Failed to record verification of {}: {}", id, e);
+            }
+
+            summary.checked += 1;
+            match &result.problem {
+                None => {}
+                Some(IntegrityProblem::Missing) => summary.missing += 1,
+                Some(problem) => summary.corrupt.push(CorruptDownload {
+                    download_id: id.clone(),
+                    media_id: download.media_id.clone(),
+                    title: download.display_title(),
+                    episode_number: download.episode_number,
+                    reason: problem.message(),
+                }),
+            }
+            results.push(result);
+        }
+
+        Ok((summary, results))
+    }
+
+    /// Verify the `items` completed downloads checked longest ago, flagging
+    /// bad files corrupt, and notify about the ones that were
+    pub async fn verify_sweep(&self, items: u32) -> Result<VerifySweepSummary> {
+        let pool = self.db_pool.clone().context("Database not available")?;
+        let ids = due_for_verification(&pool, items.max(1)).await?;
+        let (summary, _) = self.check_downloads(ids, false).await?;
+
+        log::info!("Download verification sweep: {}", summary.describe());
+        if !summary.corrupt.is_empty() {
+            if let Some(handle) = &self.app_handle {
+                let locale = locale::current_locale(pool.as_ref()).await;
+                let _ = emit_notification(handle, Some(pool.as_ref()), corrupt_notification(&summary.corrupt, locale)).await;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Verify every completed download of a series now, the way the sweep
+    /// does. `hash` also hashes files that have no checksum yet.
+    pub async fn verify_media_downloads(&self, media_id: &str, hash: bool) -> Result<Vec<VerifyResult>> {
+        let mut ids: Vec<String> = self
+            .downloads
+            .read()
+            .await
+            .values()
+            .filter(|d| {
+                d.media_id == media_id
+                    && d.status == DownloadStatus::Completed
+                    && !matches!(d.file_state, FileState::Trashed)
+            })
+            .map(|d| d.id.clone())
+            .collect();
+        ids.sort();
+
+        let (_, results) = self.check_downloads(ids, hash).await?;
+        Ok(results)
+    }
+}
+
+/// Notification listing the downloads a run flagged corrupt
+fn corrupt_notification(corrupt: &[CorruptDownload], locale: Locale) -> NotificationPayload {
+    let episodes: Vec<String> = corrupt
+        .iter()
+        .map(|d| format!("{} {}", d.title, locale::episode_label(d.episode_number, locale)))
+        .collect();
+
+    NotificationPayload::new(
+        NotificationType::Warning,
+        "Corrupted Downloads Found",
+        format!("These files are damaged and won't play properly: {}", episodes.join(", ")),
+    )
+    .with_source("download")
+    .with_action(
+        "Download Again",
+        Some("/downloads".to_string()),
+        Some(REDOWNLOAD_CORRUPT_CALLBACK.to_string()),
+    )
+    .with_metadata(serde_json::json!({
+        "download_ids": corrupt.iter().map(|d| d.download_id.clone()).collect::<Vec<_>>(),
+        "corrupt": corrupt,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::Arc;
+
+    /// An MP4 file of `len` bytes
+    fn mp4(len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        bytes[..12].copy_from_slice(b"\x00\x00\x00\x20ftypisom");
+        bytes
+    }
+
+    #[tokio::test]
+    async fn the_sweep_catches_a_truncated_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool.clone()));
+
+        let mut ids = Vec::new();
+        for episode in 1..=3 {
+            let path = temp_dir.path().join(format!("ep{}.mp4", episode));
+            std::fs::write(&path, mp4(1000)).unwrap();
+            ids.push((manager.adopt_file("show", episode, &path, None).await.unwrap().id, path));
+        }
+
+        // The card loses the end of episode 2
+        std::fs::write(&ids[1].1, &mp4(1000)[..600]).unwrap();
+
+        // Episode 2 was never verified, 1 longest ago: two per run check 2
+        // and 1, then 3 and 1 again
+        for (index, verified_at) in [(0, Some(100)), (1, None), (2, Some(200))] {
+            sqlx::query("UPDATE downloads SET verified_at = ? WHERE id = ?")
+                .bind(verified_at)
+                .bind(&ids[index].0)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let summary = manager.verify_sweep(2).await.unwrap();
+        assert_eq!(summary.checked, 2);
+        assert_eq!(summary.corrupt.len(), 1);
+        assert_eq!(summary.corrupt[0].download_id, ids[1].0);
+        assert!(summary.corrupt[0].reason.contains("600 bytes"));
+
+        let flagged = manager.get_progress(&ids[1].0).await.unwrap();
+        assert_eq!(flagged.file_state, FileState::Corrupt);
+        assert_eq!(flagged.status, DownloadStatus::Completed, "the download keeps its history");
+        assert!(!manager.is_episode_downloaded("show", 2).await);
+
+        let summary = manager.verify_sweep(2).await.unwrap();
+        assert_eq!(summary.checked, 2);
+        assert!(summary.corrupt.is_empty(), "flagged files aren't checked again");
+        assert_eq!(due_for_verification(&pool, 10).await.unwrap().len(), 2);
+
+        // Archived files are still checked
+        sqlx::query("UPDATE downloads SET file_state = 'archived' WHERE id = ?")
+            .bind(&ids[2].0)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(due_for_verification(&pool, 10).await.unwrap().contains(&ids[2].0));
+
+        let events = list_events(&pool, None, 10).await.unwrap();
+        assert_eq!(events.len(), 4);
+        let corrupt: Vec<&DownloadEvent> = events.iter().filter(|e| e.kind == "corrupt").collect();
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(corrupt[0].episode_number, 2);
+        assert_eq!(list_events(&pool, Some(&ids[0].0), 10).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn verifying_a_series_checks_the_container_and_the_checksum() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool.clone()));
+
+        let good = temp_dir.path().join("ep1.mp4");
+        let garbage = temp_dir.path().join("ep2.mp4");
+        std::fs::write(&good, mp4(500)).unwrap();
+        std::fs::write(&garbage, vec![0u8; 500]).unwrap();
+        let good_id = manager.adopt_file("show", 1, &good, None).await.unwrap().id;
+        let garbage_id = manager.adopt_file("show", 2, &garbage, None).await.unwrap().id;
+
+        let results = manager.verify_media_downloads("show", true).await.unwrap();
+        assert_eq!(results.len(), 2);
+        let result = |id: &str| results.iter().find(|r| r.download_id == id).unwrap().clone();
+        assert!(result(&good_id).ok && result(&good_id).checksum_recorded);
+        assert_eq!(result(&garbage_id).problem, Some(IntegrityProblem::UnknownContainer));
+
+        // Same size, different contents: caught by the stored checksum
+        // without asking for hashing
+        let mut changed = mp4(500);
+        changed[499] = 1;
+        std::fs::write(&good, changed).unwrap();
+        let summary = manager.verify_sweep(10).await.unwrap();
+        assert_eq!(summary.corrupt.len(), 1);
+        assert_eq!(summary.corrupt[0].download_id, good_id);
+
+        // Downloading again is offered for the flagged files
+        let notification = corrupt_notification(&summary.corrupt, Locale::En);
+        assert_eq!(notification.action.unwrap().callback.as_deref(), Some(REDOWNLOAD_CORRUPT_CALLBACK));
+    }
+
+    #[tokio::test]
+    async fn schedule_round_trips() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool();
+
+        assert_eq!(load_schedule(pool).await.unwrap(), VerifySchedule::default());
+
+        let schedule = VerifySchedule { enabled: true, items_per_run: 5, interval_hours: 6 };
+        save_schedule(pool, &schedule).await.unwrap();
+        assert_eq!(load_schedule(pool).await.unwrap(), schedule);
+        assert_eq!(schedule.interval(), Duration::from_secs(6 * 60 * 60));
+    }
+}
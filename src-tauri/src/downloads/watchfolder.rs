@@ -0,0 +1,879 @@
+// Watch Folder Import
+//
+// Episodes downloaded with other tools can be dropped into a watched folder.
+// A background task polls it, parses each new video's filename with regex
+// templates (named groups `title`, `season`, `episode`), fuzzy-matches the
+// title against the media table (optionally falling back to a Jikan search)
+// and adopts the file in place as a Completed download.
+//
+// Polling is debounced: a file is only picked up once its size is unchanged
+// between two scans, so copies still in progress are left alone.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use super::organize::season_from_title;
+use super::{DownloadManager, DownloadProgress, DownloadStatus, FileState};
+use crate::database::media::save_media;
+use crate::jikan::bridge::title_similarity;
+use crate::locale::{self, Locale};
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key: "true" to watch the folder
+pub const WATCH_FOLDER_ENABLED_SETTING: &str = "watch_folder_enabled";
+
+/// app_settings key holding the watched directory
+pub const WATCH_FOLDER_PATH_SETTING: &str = "watch_folder_path";
+
+/// app_settings key holding custom filename templates (JSON array of regexes)
+pub const WATCH_FOLDER_PATTERNS_SETTING: &str = "watch_folder_patterns";
+
+/// app_settings key: "true" to search Jikan for titles missing from the library
+pub const WATCH_FOLDER_JIKAN_SETTING: &str = "watch_folder_jikan_lookup";
+
+/// Filename templates tried in order when no custom ones are set. They run
+/// against the cleaned name (see `clean_filename`), case-insensitively.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // "Title S01E05", "Title S1 E5"
+    r"^(?P<title>.+?)\s+S(?P<season>\d{1,2})\s?E(?P<episode>\d{1,4})\b",
+    // "Title - 05", "Title - 05v2", "Title - EP05"
+    r"^(?P<title>.+?)\s+-\s+(?:EP?\s?)?(?P<episode>\d{1,4})(?:v\d+)?\b",
+    // "Title Episode 5", "Title Ep 05", "Title E05"
+    r"^(?P<title>.+?)\s+(?:Episode|Ep|E)\s?(?P<episode>\d{1,4})\b",
+    // "Title 05"
+    r"^(?P<title>.+?)\s+(?P<episode>\d{1,4})(?:v\d+)?$",
+];
+
+pub(super) const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "avi", "m4v", "mov"];
+
+/// How often the background task scans the folder
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How deep to look into subfolders (e.g. "Show/Season 1/file.mkv")
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// Minimum similarity for a title to count as a match
+const MATCH_THRESHOLD: f64 = 0.85;
+
+/// Score multiplier when the parsed season differs from the candidate's
+const SEASON_MISMATCH_PENALTY: f64 = 0.7;
+
+/// Shown as the source of adopted downloads
+const WATCH_FOLDER_SOURCE_LABEL: &str = "Watch folder";
+
+/// Prefix for adopted episode ids; keeps them clear of extension episode ids
+/// under the downloads table's (media_id, episode_id) uniqueness
+const ADOPTED_EPISODE_PREFIX: &str = "watchfolder:";
+
+/// File sizes seen in the previous scan, for debouncing
+static PENDING_SIZES: LazyLock<Mutex<HashMap<PathBuf, u64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Files that were skipped for good (episode already downloaded)
+static SKIPPED: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Unmatched files already reported; they're retried locally on every scan
+/// but only notified about (and looked up on Jikan) once per session
+static REPORTED_UNMATCHED: LazyLock<Mutex<HashSet<PathBuf>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchFolderSettings {
+    pub enabled: bool,
+    pub path: Option<String>,
+    /// Custom filename templates; empty means DEFAULT_PATTERNS
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Search Jikan for titles that aren't in the local media table
+    #[serde(default)]
+    pub jikan_lookup: bool,
+}
+
+/// What a filename template extracted
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFilename {
+    pub title: String,
+    pub season: u32,
+    pub episode: i32,
+}
+
+/// A media entry a parsed title can match against
+#[derive(Debug, Clone)]
+pub struct MatchCandidate {
+    pub media_id: String,
+    /// Main title first, then alternatives (english, native)
+    pub titles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedEpisode {
+    pub file_name: String,
+    pub media_id: String,
+    pub media_title: String,
+    pub episode_number: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchScanResult {
+    pub imported: Vec<ImportedEpisode>,
+    /// File names that couldn't be parsed or matched
+    pub unmatched: Vec<String>,
+    /// Files for episodes that are already downloaded
+    pub skipped: usize,
+}
+
+/// Read the watch folder settings, falling back to defaults
+pub async fn load_settings(pool: &SqlitePool) -> Result<WatchFolderSettings> {
+    let rows = sqlx::query("SELECT key, value FROM app_settings WHERE key IN (?, ?, ?, ?)")
+        .bind(WATCH_FOLDER_ENABLED_SETTING)
+        .bind(WATCH_FOLDER_PATH_SETTING)
+        .bind(WATCH_FOLDER_PATTERNS_SETTING)
+        .bind(WATCH_FOLDER_JIKAN_SETTING)
+        .fetch_all(pool)
+        .await?;
+
+    let mut settings = WatchFolderSettings::default();
+    for row in rows {
+        let key: String = row.get("key");
+        let value: String = row.get("value");
+        match key.as_str() {
+            WATCH_FOLDER_ENABLED_SETTING => settings.enabled = value == "true",
+            WATCH_FOLDER_PATH_SETTING => {
+                settings.path = Some(value).filter(|p| !p.trim().is_empty());
+            }
+            WATCH_FOLDER_PATTERNS_SETTING => {
+                settings.patterns = serde_json::from_str(&value).unwrap_or_default();
+            }
+            WATCH_FOLDER_JIKAN_SETTING => settings.jikan_lookup = value == "true",
+            _ => {}
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Validate and store the watch folder settings
+pub async fn save_settings(pool: &SqlitePool, settings: &WatchFolderSettings) -> Result<()> {
+    compile_patterns(&settings.patterns)?;
+
+    if settings.enabled {
+        let Some(path) = settings.path.as_deref() else {
+            bail!("A watch folder path is required");
+        };
+        if !Path::new(path).is_dir() {
+            bail!("Watch folder does not exist: {}", path);
+        }
+    }
+
+    let values = [
+        (WATCH_FOLDER_ENABLED_SETTING, settings.enabled.to_string()),
+        (WATCH_FOLDER_PATH_SETTING, settings.path.clone().unwrap_or_default()),
+        (WATCH_FOLDER_PATTERNS_SETTING, serde_json::to_string(&settings.patterns)?),
+        (WATCH_FOLDER_JIKAN_SETTING, settings.jikan_lookup.to_string()),
+    ];
+
+    for (key, value) in values {
+        sqlx::query(
+            r#"
+            INSERT INTO app_settings (key, value, updated_at)
+            VALUES (?, ?, strftime('%s', 'now') * 1000)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Compile filename templates (DEFAULT_PATTERNS when `patterns` is empty).
+/// Each template needs `title` and `episode` groups; `season` is optional.
+pub fn compile_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    let sources: Vec<&str> = if patterns.is_empty() {
+        DEFAULT_PATTERNS.to_vec()
+    } else {
+        patterns.iter().map(String::as_str).collect()
+    };
+
+    sources
+        .into_iter()
+        .map(|source| {
+            let regex = RegexBuilder::new(source)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("Invalid filename template: {}", source))?;
+
+            let names: Vec<&str> = regex.capture_names().flatten().collect();
+            if !names.contains(&"title") || !names.contains(&"episode") {
+                bail!("Filename template needs (?P<title>...) and (?P<episode>...) groups: {}", source);
+            }
+            Ok(regex)
+        })
+        .collect()
+}
+
+/// Strip the extension and bracketed tags ("[SubsPlease]", "(1080p)"), turn
+/// `_` and `.` separators into spaces and collapse whitespace
+pub fn clean_filename(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_string());
+
+    let mut cleaned = String::with_capacity(stem.len());
+    let mut depth = 0usize;
+    for c in stem.chars() {
+        match c {
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            '_' | '.' => cleaned.push(' '),
+            c => cleaned.push(c),
+        }
+    }
+
+    cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(|c: char| c == '-' || c.is_whitespace())
+        .to_string()
+}
+
+/// Parse a filename with the first template that matches
+pub fn parse_filename(file_name: &str, patterns: &[Regex]) -> Option<ParsedFilename> {
+    let cleaned = clean_filename(file_name);
+
+    for pattern in patterns {
+        let Some(caps) = pattern.captures(&cleaned) else {
+            continue;
+        };
+
+        let title = caps
+            .name("title")
+            .map(|m| m.as_str().trim_matches(|c: char| c == '-' || c.is_whitespace()).to_string())
+            .filter(|t| !t.is_empty());
+        let episode = caps.name("episode").and_then(|m| m.as_str().parse::<i32>().ok());
+
+        let (Some(title), Some(episode)) = (title, episode) else {
+            continue;
+        };
+
+        let season = caps
+            .name("season")
+            .and_then(|m| m.as_str().parse::<u32>().ok())
+            .map(|s| s.max(1))
+            .unwrap_or_else(|| season_from_title(&title));
+
+        return Some(ParsedFilename { title, season, episode });
+    }
+
+    None
+}
+
+/// Lowercase, drop apostrophes and turn other punctuation into spaces, so
+/// "Frieren: Beyond Journey's End" and "Frieren Beyond Journeys End" agree
+pub fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '\'' && *c != '’')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Drop season markers ("season 2", "2nd season", "s2") from a normalized
+/// title; seasons are compared separately in `match_score`
+fn strip_season_markers(normalized: &str) -> String {
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+    let mut kept = Vec::with_capacity(words.len());
+    let mut i = 0;
+
+    while i < words.len() {
+        let word = words[i];
+        let next = words.get(i + 1).copied();
+
+        if word == "season" && next.is_some_and(|n| n.parse::<u32>().is_ok()) {
+            i += 2;
+            continue;
+        }
+
+        let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let suffix = &word[digits.len()..];
+        if next == Some("season")
+            && !digits.is_empty()
+            && digits.parse::<u32>().is_ok()
+            && matches!(suffix, "st" | "nd" | "rd" | "th")
+        {
+            i += 2;
+            continue;
+        }
+
+        if let Some(rest) = word.strip_prefix('s') {
+            if !rest.is_empty() && rest.len() <= 2 && rest.parse::<u32>().is_ok() {
+                i += 1;
+                continue;
+            }
+        }
+
+        kept.push(word);
+        i += 1;
+    }
+
+    kept.join(" ")
+}
+
+/// How well a parsed filename matches a candidate (0.0 – 1.0). Titles are
+/// compared without season markers; a season mismatch lowers the score, so
+/// "Title S2E05" picks "Title 2nd Season" over "Title".
+pub fn match_score(parsed: &ParsedFilename, titles: &[String]) -> f64 {
+    let query = strip_season_markers(&normalize_title(&parsed.title));
+
+    titles
+        .iter()
+        .map(|title| {
+            let candidate = strip_season_markers(&normalize_title(title));
+            let score = title_similarity(&query, &candidate);
+            if season_from_title(title) == parsed.season {
+                score
+            } else {
+                score * SEASON_MISMATCH_PENALTY
+            }
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Best candidate scoring at least MATCH_THRESHOLD
+pub fn best_match<'a>(
+    parsed: &ParsedFilename,
+    candidates: &'a [MatchCandidate],
+) -> Option<&'a MatchCandidate> {
+    candidates
+        .iter()
+        .map(|c| (c, match_score(parsed, &c.titles)))
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, _)| c)
+}
+
+pub(super) async fn load_candidates(pool: &SqlitePool) -> Result<Vec<MatchCandidate>> {
+    let rows = sqlx::query(
+        "SELECT id, title, english_name, native_name FROM media WHERE media_type = 'anime'",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let mut titles = vec![row.get::<String, _>("title")];
+            for column in ["english_name", "native_name"] {
+                if let Some(alt) = row.get::<Option<String>, _>(column).filter(|t| !t.is_empty()) {
+                    titles.push(alt);
+                }
+            }
+            MatchCandidate {
+                media_id: row.get("id"),
+                titles,
+            }
+        })
+        .collect())
+}
+
+/// Search Jikan for a title missing from the library and save the best hit
+/// as a media row
+async fn lookup_on_jikan(pool: &SqlitePool, parsed: &ParsedFilename) -> Result<Option<MatchCandidate>> {
+    let query = if parsed.season > 1 && season_from_title(&parsed.title) != parsed.season {
+        format!("{} season {}", parsed.title, parsed.season)
+    } else {
+        parsed.title.clone()
+    };
+
+    let entries = tokio::task::spawn_blocking(move || crate::jikan::anime::search_anime_entries(&query))
+        .await?
+        .map_err(anyhow::Error::msg)?;
+
+    let best = entries
+        .into_iter()
+        .map(|anime| {
+            let mut titles = vec![anime.title.clone()];
+            titles.extend(anime.title_english.clone());
+            titles.extend(anime.title_synonyms.clone().unwrap_or_default());
+            let score = match_score(parsed, &titles);
+            (anime, titles, score)
+        })
+        .filter(|(_, _, score)| *score >= MATCH_THRESHOLD)
+        .max_by(|a, b| a.2.total_cmp(&b.2));
+
+    let Some((anime, titles, _)) = best else {
+        return Ok(None);
+    };
+
+    let entry = crate::jikan::anime::jikan_anime_to_media_entry(&anime);
+    save_media(pool, &entry).await?;
+
+    Ok(Some(MatchCandidate {
+        media_id: entry.id,
+        titles,
+    }))
+}
+
+/// Video files under `dir` (up to MAX_SCAN_DEPTH levels) with their sizes
+fn list_video_files(dir: &Path, depth: usize, out: &mut Vec<(PathBuf, u64)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                list_video_files(&path, depth + 1, out);
+            }
+            continue;
+        }
+
+        let is_video = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| VIDEO_EXTENSIONS.iter().any(|v| e.eq_ignore_ascii_case(v)))
+            .unwrap_or(false);
+        if is_video {
+            out.push((path, metadata.len()));
+        }
+    }
+}
+
+/// Keep only files whose size matches the previous scan, remembering the
+/// current sizes for the next one
+fn settled_files(files: Vec<(PathBuf, u64)>) -> Vec<PathBuf> {
+    let mut pending = PENDING_SIZES.lock().unwrap();
+    let mut current = HashMap::with_capacity(files.len());
+    let mut settled = Vec::new();
+
+    for (path, size) in files {
+        if size > 0 && pending.get(&path) == Some(&size) {
+            settled.push(path.clone());
+        }
+        current.insert(path, size);
+    }
+
+    *pending = current;
+    settled
+}
+
+pub(super) fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+impl DownloadManager {
+    /// Register an existing video file as a Completed download. The file
+    /// stays where it is; nothing is copied or obfuscated.
+    pub async fn adopt_file(
+        &self,
+        media_id: &str,
+        episode_number: i32,
+        path: &Path,
+        source_label: Option<String>,
+    ) -> Result<DownloadProgress> {
+        self.adopt_titled_file(media_id, None, episode_number, path, source_label).await
+    }
+
+    /// adopt_file, storing a series title with the download for media ids
+    /// that have no media row
+    pub async fn adopt_titled_file(
+        &self,
+        media_id: &str,
+        media_title: Option<String>,
+        episode_number: i32,
+        path: &Path,
+        source_label: Option<String>,
+    ) -> Result<DownloadProgress> {
+        let size = tokio::fs::metadata(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .len();
+
+        let progress = DownloadProgress {
+            id: uuid::Uuid::new_v4().to_string(),
+            media_id: media_id.to_string(),
+            episode_id: format!("{}{}", ADOPTED_EPISODE_PREFIX, episode_number),
+            episode_number,
+            media_title,
+            filename: file_name_of(path),
+            url: String::new(),
+            file_path: path.to_string_lossy().to_string(),
+            total_bytes: size,
+            downloaded_bytes: size,
+            percentage: 100.0,
+            speed: 0,
+            status: DownloadStatus::Completed,
+            error_message: None,
+            retry_count: 0,
+            segments: None,
+            eta_seconds: None,
+            archived: false,
+            quality: None,
+            source_label,
+            replaces_download_id: None,
+            batch_id: None,
+            source_extension_id: None,
+            file_state: FileState::Present,
+            scheduled_start: None,
+            start_now: false,
+            speed_limit: None,
+            paused_globally: false,
+            headers: Default::default(),
+        };
+
+        self.save_to_database(&progress).await?;
+        self.downloads.write().await.insert(progress.id.clone(), progress.clone());
+        self.emit_progress(&progress);
+
+        log::info!("Adopted {} as {} episode {}", progress.filename, media_id, episode_number);
+
+        Ok(progress)
+    }
+
+    /// Scan the watch folder once, adopting every settled file that matches
+    /// a series and notifying about the results
+    pub async fn scan_watch_folder(&self, settings: &WatchFolderSettings) -> Result<WatchScanResult> {
+        let pool = self.db_pool.clone().context("Database not available")?;
+        let Some(folder) = settings.path.as_deref().map(PathBuf::from) else {
+            bail!("No watch folder configured");
+        };
+        if !folder.is_dir() {
+            bail!("Watch folder does not exist: {}", folder.display());
+        }
+
+        let patterns = compile_patterns(&settings.patterns)?;
+
+        let files = tokio::task::spawn_blocking(move || {
+            let mut files = Vec::new();
+            list_video_files(&folder, 0, &mut files);
+            files
+        })
+        .await?;
+
+        let known: HashSet<String> = {
+            let downloads = self.downloads.read().await;
+            downloads.values().map(|d| d.file_path.clone()).collect()
+        };
+        let files: Vec<(PathBuf, u64)> = {
+            let skipped = SKIPPED.lock().unwrap();
+            files
+                .into_iter()
+                .filter(|(path, _)| !known.contains(path.to_string_lossy().as_ref()) && !skipped.contains(path))
+                .collect()
+        };
+
+        let settled = settled_files(files);
+        let mut result = WatchScanResult::default();
+        if settled.is_empty() {
+            return Ok(result);
+        }
+
+        let mut candidates = load_candidates(&pool).await?;
+        let mut newly_unmatched = Vec::new();
+
+        for path in settled {
+            let file_name = file_name_of(&path);
+            let first_report = !REPORTED_UNMATCHED.lock().unwrap().contains(&path);
+
+            let Some(parsed) = parse_filename(&file_name, &patterns) else {
+                log::debug!("Watch folder: couldn't parse {}", file_name);
+                result.unmatched.push(file_name.clone());
+                if first_report {
+                    newly_unmatched.push(path);
+                }
+                continue;
+            };
+
+            let mut matched = best_match(&parsed, &candidates).cloned();
+            if matched.is_none() && settings.jikan_lookup && first_report {
+                match lookup_on_jikan(&pool, &parsed).await {
+                    Ok(Some(candidate)) => {
+                        candidates.push(candidate.clone());
+                        matched = Some(candidate);
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Watch folder: Jikan lookup for {} failed: {}", parsed.title, e),
+                }
+            }
+
+            let Some(candidate) = matched else {
+                result.unmatched.push(file_name);
+                if first_report {
+                    newly_unmatched.push(path);
+                }
+                continue;
+            };
+
+            if self.is_episode_downloaded(&candidate.media_id, parsed.episode).await {
+                log::debug!("Watch folder: {} is already downloaded, skipping", file_name);
+                SKIPPED.lock().unwrap().insert(path);
+                result.skipped += 1;
+                continue;
+            }
+
+            match self
+                .adopt_file(&candidate.media_id, parsed.episode, &path, Some(WATCH_FOLDER_SOURCE_LABEL.to_string()))
+                .await
+            {
+                Ok(_) => {
+                    REPORTED_UNMATCHED.lock().unwrap().remove(&path);
+                    result.imported.push(ImportedEpisode {
+                        file_name,
+                        media_id: candidate.media_id.clone(),
+                        media_title: candidate.titles[0].clone(),
+                        episode_number: parsed.episode,
+                    });
+                }
+                Err(e) => {
+                    log::error!("Watch folder: failed to adopt {}: {:#}", file_name, e);
+                    result.unmatched.push(file_name);
+                }
+            }
+        }
+
+        let reported: Vec<String> = newly_unmatched.iter().map(|p| file_name_of(p)).collect();
+        REPORTED_UNMATCHED.lock().unwrap().extend(newly_unmatched);
+
+        if let Some(handle) = &self.app_handle {
+            let locale = locale::current_locale(pool.as_ref()).await;
+            if let Some(notification) = scan_notification(&result.imported, &reported, locale) {
+                let _ = emit_notification(handle, Some(pool.as_ref()), notification).await;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Summary notification for a scan; None when there's nothing new to report
+fn scan_notification(imported: &[ImportedEpisode], unmatched: &[String], locale: Locale) -> Option<NotificationPayload> {
+    if imported.is_empty() && unmatched.is_empty() {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+    if !imported.is_empty() {
+        let episodes: Vec<String> = imported
+            .iter()
+            .map(|e| format!("{} {}", e.media_title, locale::episode_label(e.episode_number, locale)))
+            .collect();
+        lines.push(format!("Imported {}", episodes.join(", ")));
+    }
+    if !unmatched.is_empty() {
+        lines.push(format!("Couldn't match {}", unmatched.join(", ")));
+    }
+
+    let notification_type = if unmatched.is_empty() {
+        NotificationType::Success
+    } else {
+        NotificationType::Warning
+    };
+
+    Some(
+        NotificationPayload::new(notification_type, "Watch Folder Import", lines.join(". "))
+            .with_source("download")
+            .with_action("Open Downloads", Some("/downloads".to_string()), None)
+            .with_metadata(serde_json::json!({
+                "imported": imported,
+                "unmatched": unmatched,
+            })),
+    )
+}
+
+/// Poll the watch folder in the background. Settings are re-read every
+/// cycle, so enabling or moving the folder needs no restart.
+pub fn start_watch_folder_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let manager = app_handle.state::<DownloadManager>();
+            let Some(pool) = manager.db_pool.clone() else {
+                continue;
+            };
+
+            let settings = match load_settings(&pool).await {
+                Ok(settings) => settings,
+                Err(e) => {
+                    log::warn!("Failed to load watch folder settings: {}", e);
+                    continue;
+                }
+            };
+            if !settings.enabled || settings.path.is_none() {
+                continue;
+            }
+
+            if let Err(e) = manager.scan_watch_folder(&settings).await {
+                log::warn!("Watch folder scan failed: {:#}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::Arc;
+
+    fn parse(file_name: &str) -> Option<ParsedFilename> {
+        parse_filename(file_name, &compile_patterns(&[]).unwrap())
+    }
+
+    fn parsed(title: &str, season: u32, episode: i32) -> Option<ParsedFilename> {
+        Some(ParsedFilename { title: title.to_string(), season, episode })
+    }
+
+    fn candidate(id: &str, titles: &[&str]) -> MatchCandidate {
+        MatchCandidate {
+            media_id: id.to_string(),
+            titles: titles.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_real_world_filenames() {
+        let corpus = [
+            ("[SubsPlease] Sousou no Frieren - 05 (1080p) [A1B2C3D4].mkv", parsed("Sousou no Frieren", 1, 5)),
+            ("[Erai-raws] Spy x Family - 25 [1080p][Multiple Subtitle].mkv", parsed("Spy x Family", 1, 25)),
+            ("[HorribleSubs] One Piece - 1071 [720p].mkv", parsed("One Piece", 1, 1071)),
+            ("Jujutsu.Kaisen.S02E14.1080p.WEB.H264-SKYANiME.mkv", parsed("Jujutsu Kaisen", 2, 14)),
+            ("Attack_on_Titan_S3E01_720p.mp4", parsed("Attack on Titan", 3, 1)),
+            ("Mob Psycho 100 - 03v2.mkv", parsed("Mob Psycho 100", 1, 3)),
+            ("[Judas] Re Zero kara Hajimeru Isekai Seikatsu 2nd Season - 03.mkv", parsed("Re Zero kara Hajimeru Isekai Seikatsu 2nd Season", 2, 3)),
+            ("[SubsPlease] Shingeki no Kyojin - The Final Season - 05 (1080p).mkv", parsed("Shingeki no Kyojin - The Final Season", 1, 5)),
+            ("Naruto Episode 12.mp4", parsed("Naruto", 1, 12)),
+            ("Bleach Ep 366.avi", parsed("Bleach", 1, 366)),
+            ("Kimetsu no Yaiba 26.mp4", parsed("Kimetsu no Yaiba", 1, 26)),
+            ("[ASW] Dr. Stone - New World - 12 [1080p HEVC].mkv", parsed("Dr Stone - New World", 1, 12)),
+            ("86 - Eighty Six - 01.mkv", parsed("86 - Eighty Six", 1, 1)),
+            ("Oshi no Ko S2 - 04.mkv", parsed("Oshi no Ko S2", 2, 4)),
+            ("[Group] Kaguya-sama wa Kokurasetai (2019) - 07 [BD 1080p].mkv", parsed("Kaguya-sama wa Kokurasetai", 1, 7)),
+            ("Bocchi.the.Rock.E08.1080p.mkv", parsed("Bocchi the Rock", 1, 8)),
+            ("random_video_1080p.mkv", None),
+            ("[SubsPlease] Trailer (1080p).mkv", None),
+        ];
+
+        for (file_name, expected) in corpus {
+            assert_eq!(parse(file_name), expected, "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn custom_patterns_require_title_and_episode_groups() {
+        assert!(compile_patterns(&[r"^(?P<title>.+) #(?P<episode>\d+)$".to_string()]).is_ok());
+        assert!(compile_patterns(&[r"^(?P<title>.+) #\d+$".to_string()]).is_err());
+        assert!(compile_patterns(&[r"^(?P<title>.+".to_string()]).is_err());
+
+        let patterns = compile_patterns(&[r"^(?P<title>.+) #(?P<episode>\d+)$".to_string()]).unwrap();
+        assert_eq!(parse_filename("Made in Abyss #4.mkv", &patterns), parsed("Made in Abyss", 1, 4));
+    }
+
+    #[test]
+    fn matches_titles_across_punctuation_and_alternate_names() {
+        let candidates = vec![
+            candidate("52991", &["Sousou no Frieren", "Frieren: Beyond Journey's End"]),
+            candidate("50265", &["Spy x Family"]),
+            candidate("21", &["One Piece"]),
+        ];
+
+        let frieren = parse("Frieren Beyond Journeys End - 03.mkv").unwrap();
+        assert_eq!(best_match(&frieren, &candidates).unwrap().media_id, "52991");
+
+        let spy = parse("[SubsPlease] SPY x FAMILY - 12 (1080p).mkv").unwrap();
+        assert_eq!(best_match(&spy, &candidates).unwrap().media_id, "50265");
+
+        let unknown = parse("Chainsaw Man - 01.mkv").unwrap();
+        assert!(best_match(&unknown, &candidates).is_none());
+    }
+
+    #[test]
+    fn prefers_the_candidate_for_the_parsed_season() {
+        let candidates = vec![
+            candidate("16498", &["Shingeki no Kyojin", "Attack on Titan"]),
+            candidate("25777", &["Shingeki no Kyojin Season 2", "Attack on Titan Season 2"]),
+            candidate("40748", &["Jujutsu Kaisen"]),
+            candidate("51009", &["Jujutsu Kaisen 2nd Season"]),
+        ];
+
+        let s1 = parse("Shingeki.no.Kyojin.S01E05.mkv").unwrap();
+        assert_eq!(best_match(&s1, &candidates).unwrap().media_id, "16498");
+
+        let s2 = parse("Attack.on.Titan.S02E05.mkv").unwrap();
+        assert_eq!(best_match(&s2, &candidates).unwrap().media_id, "25777");
+
+        let jjk = parse("Jujutsu.Kaisen.S02E14.1080p.mkv").unwrap();
+        assert_eq!(best_match(&jjk, &candidates).unwrap().media_id, "51009");
+    }
+
+    #[tokio::test]
+    async fn scan_adopts_settled_files_and_reports_unmatched() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool().clone();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('52991', 'jikan', 'Sousou no Frieren', 'anime')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let watch_dir = temp_dir.path().join("watch");
+        std::fs::create_dir_all(&watch_dir).unwrap();
+        let episode = watch_dir.join("[SubsPlease] Sousou no Frieren - 05 (1080p).mkv");
+        std::fs::write(&episode, vec![1u8; 2048]).unwrap();
+        std::fs::write(watch_dir.join("Some Unknown Show - 01.mkv"), vec![1u8; 16]).unwrap();
+        std::fs::write(watch_dir.join("notes.txt"), b"not a video").unwrap();
+
+        let manager = DownloadManager::new(temp_dir.path().join("downloads")).with_database(Arc::new(pool));
+        let settings = WatchFolderSettings {
+            enabled: true,
+            path: Some(watch_dir.to_string_lossy().to_string()),
+            patterns: Vec::new(),
+            jikan_lookup: false,
+        };
+
+        // First scan only records sizes
+        let first = manager.scan_watch_folder(&settings).await.unwrap();
+        assert!(first.imported.is_empty());
+        assert!(first.unmatched.is_empty());
+
+        let second = manager.scan_watch_folder(&settings).await.unwrap();
+        assert_eq!(second.imported.len(), 1);
+        assert_eq!(second.imported[0].media_id, "52991");
+        assert_eq!(second.imported[0].episode_number, 5);
+        assert_eq!(second.unmatched, vec!["Some Unknown Show - 01.mkv".to_string()]);
+
+        assert!(manager.is_episode_downloaded("52991", 5).await);
+        let adopted = manager.get_episode_file_path("52991", 5).await.unwrap();
+        assert_eq!(PathBuf::from(adopted), episode);
+
+        // Adopted files aren't picked up again
+        let third = manager.scan_watch_folder(&settings).await.unwrap();
+        assert!(third.imported.is_empty());
+    }
+}
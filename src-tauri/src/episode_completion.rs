@@ -0,0 +1,57 @@
+// Episode Completion
+//
+// save_watch_progress decides when an episode counts as watched (explicit
+// flag, or progress past the completion threshold). The moment it flips from
+// unwatched to watched ends up here, once: the frontend is told through
+// EPISODE_COMPLETED_EVENT and backend hooks run in order. Anything else that
+// should react to a finished episode belongs in on_episode_completed.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+use crate::events::EPISODE_COMPLETED_EVENT;
+
+/// An episode that was just marked watched
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct EpisodeCompleted {
+    pub media_id: String,
+    pub episode_id: String,
+    pub episode_number: i32,
+}
+
+/// Run the completion hooks for a newly watched episode
+pub async fn on_episode_completed(app: &AppHandle, pool: &SqlitePool, completed: EpisodeCompleted) {
+    log::debug!(
+        "Episode {} of {} completed",
+        completed.episode_number, completed.media_id
+    );
+
+    // Watching the newest release clears its NEW badge
+    if let Err(e) = crate::release_checker::acknowledge_watched_release(
+        pool,
+        &completed.media_id,
+        completed.episode_number as f32,
+    )
+    .await
+    {
+        log::warn!("Failed to acknowledge release for {}: {}", completed.media_id, e);
+    }
+
+    // Connected trackers get the new progress on their next sync
+    if let Err(e) = crate::trackers::sync_queue::queue_progress(
+        pool,
+        &completed.media_id,
+        completed.episode_number,
+    )
+    .await
+    {
+        log::warn!("Failed to queue tracker sync for {}: {}", completed.media_id, e);
+    }
+
+    // Downloads of watched episodes may be due for deletion (opt-in)
+    crate::downloads::auto_delete::on_episode_watched(app);
+
+    EPISODE_COMPLETED_EVENT.emit(app, &completed);
+}
@@ -0,0 +1,262 @@
+// Typed Events
+//
+// Every event the backend emits to the frontend is declared here together
+// with its payload type. Emit sites go through `Event::emit`, so a payload
+// can't be sent under the wrong name, and `schema_document` exports a JSON
+// schema per event for the frontend (see src/types/events.ts).
+//
+// To add an event: declare it below, add it to `event_schemas`, and add it to
+// src/types/events.ts. The tests fail if any of those steps is missed or if
+// another module emits directly through `tauri::Emitter`.
+
+use std::marker::PhantomData;
+
+use schemars::schema::RootSchema;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::auto_backup::{AutoBackupFailed, BackupResult};
+use crate::backup_file::BackupFileOpened;
+use crate::badges::BadgeSummary;
+use crate::commands::{
+    DiscoverResultsEvent, HomeCategoryEvent, LogEntry, SeasonDiscoverResultsEvent, SystemStats,
+};
+use crate::database::export_import::DataTransferProgress;
+use crate::database::migration_runner::MigrationProgress;
+use crate::downloads::chapter_downloads::ChapterDownloadProgress;
+use crate::downloads::network::NetworkStatus;
+use crate::downloads::offline_ready::OfflineReady;
+use crate::downloads::stats::DownloadStats;
+use crate::downloads::verify::DownloadVerifyProgress;
+use crate::downloads::DownloadProgress;
+use crate::episode_completion::EpisodeCompleted;
+use crate::jikan::covers::CoverRefreshProgress;
+use crate::media::image_size::ChapterImageSizes;
+use crate::media_hydration::MediaHydrationProgress;
+use crate::notifications::NotificationPayload;
+use crate::release_checker::ReleaseCheckProgress;
+use crate::storage_usage::StorageUsage;
+
+/// An event name bound to its payload type
+pub struct Event<P> {
+    pub name: &'static str,
+    payload: PhantomData<fn(&P)>,
+}
+
+impl<P> Event<P> {
+    pub const fn new(name: &'static str) -> Self {
+        Self { name, payload: PhantomData }
+    }
+}
+
+impl<P: Serialize> Event<P> {
+    /// Emit to all windows, logging (not returning) failures
+    pub fn emit(&self, app_handle: &AppHandle, payload: &P) {
+        if let Err(e) = app_handle.emit(self.name, payload) {
+            log::error!("Failed to emit {} event: {}", self.name, e);
+        }
+    }
+}
+
+impl<P: JsonSchema> Event<P> {
+    pub fn schema(&self) -> (&'static str, RootSchema) {
+        (self.name, schemars::schema_for!(P))
+    }
+}
+
+// ==================== Event Declarations ====================
+
+/// Download queue progress (one payload per download)
+pub const DOWNLOAD_PROGRESS_EVENT: Event<DownloadProgress> = Event::new("download-progress");
+
+/// Download totals for the downloads page header, when they change
+pub const DOWNLOAD_STATS_EVENT: Event<DownloadStats> = Event::new("download-stats");
+
+/// Manga chapter download progress
+pub const CHAPTER_DOWNLOAD_PROGRESS_EVENT: Event<ChapterDownloadProgress> =
+    Event::new("chapter-download-progress");
+
+/// Page sizes of a chapter probed after its images were returned
+pub const CHAPTER_IMAGE_SIZES_EVENT: Event<ChapterImageSizes> = Event::new("chapter-image-sizes");
+
+/// Verify-all progress per completed download
+pub const DOWNLOAD_VERIFY_PROGRESS_EVENT: Event<DownloadVerifyProgress> =
+    Event::new("download-verify-progress");
+
+/// The downloaded episodes of a batch's series, watchable in a row from the
+/// watch position, grew
+pub const OFFLINE_READY_EVENT: Event<OfflineReady> = Event::new("offline-ready");
+
+/// Network went offline or came back (for the offline banner)
+pub const NETWORK_STATUS_EVENT: Event<NetworkStatus> = Event::new("network-status");
+
+/// In-app notification (also escalated to a native banner when hidden)
+pub const NOTIFICATION_EVENT: Event<NotificationPayload> = Event::new("notification");
+
+/// Sidebar badge counts, whenever one of them changes
+pub const BADGE_SUMMARY_CHANGED_EVENT: Event<BadgeSummary> = Event::new("badge-summary-changed");
+
+/// Home page categories, streamed as each one loads
+pub const HOME_CONTENT_EVENT: Event<HomeCategoryEvent> = Event::new("home-content-category");
+
+/// Anime discover pages, streamed progressively
+pub const ANIME_DISCOVER_EVENT: Event<DiscoverResultsEvent> = Event::new("anime-discover-results");
+
+/// Manga discover pages, streamed progressively
+pub const MANGA_DISCOVER_EVENT: Event<DiscoverResultsEvent> = Event::new("manga-discover-results");
+
+/// Seasonal anime pages, streamed progressively
+pub const SEASON_ANIME_DISCOVER_EVENT: Event<SeasonDiscoverResultsEvent> =
+    Event::new("season-anime-discover-results");
+
+/// Developer stats, at the interval passed to start_stats_stream while streaming
+#[cfg_attr(target_os = "android", allow(dead_code))]
+pub const SYSTEM_STATS_EVENT: Event<SystemStats> = Event::new("system-stats");
+
+/// New log lines while log streaming is on
+pub const APP_LOGS_EVENT: Event<Vec<LogEntry>> = Event::new("app-logs");
+
+/// Export/import progress per table
+pub const DATA_TRANSFER_PROGRESS_EVENT: Event<DataTransferProgress> = Event::new("data-transfer-progress");
+
+/// AllAnime → Jikan migration progress
+pub const MIGRATION_PROGRESS_EVENT: Event<MigrationProgress> = Event::new("migration_progress");
+
+/// Release check progress per media item
+pub const RELEASE_CHECK_PROGRESS_EVENT: Event<ReleaseCheckProgress> = Event::new("release_check_progress");
+
+/// Cover refresh progress per media item
+pub const COVER_REFRESH_EVENT: Event<CoverRefreshProgress> = Event::new("cover_refresh_progress");
+
+/// Imported media hydration progress per media item
+pub const MEDIA_HYDRATION_EVENT: Event<MediaHydrationProgress> = Event::new("media_hydration_progress");
+
+/// Storage breakdown after a category changed noticeably
+pub const STORAGE_USAGE_CHANGED_EVENT: Event<StorageUsage> = Event::new("storage-usage-changed");
+
+/// Scheduled backup finished
+pub const AUTO_BACKUP_COMPLETED_EVENT: Event<BackupResult> = Event::new("auto-backup-completed");
+
+/// Scheduled backup failed
+pub const AUTO_BACKUP_FAILED_EVENT: Event<AutoBackupFailed> = Event::new("auto-backup-failed");
+
+/// A backup file was opened with the app (file association)
+pub const BACKUP_FILE_OPENED_EVENT: Event<BackupFileOpened> = Event::new("backup-file-opened");
+
+/// An episode crossed the completion threshold or was marked watched
+pub const EPISODE_COMPLETED_EVENT: Event<EpisodeCompleted> = Event::new("episode-completed");
+
+/// Route to open after the window is activated from the tray or a banner
+#[cfg_attr(not(desktop), allow(dead_code))]
+pub const DEEPLINK_EVENT: Event<String> = Event::new("deeplink");
+
+// ==================== Schema Export ====================
+
+/// Every declared event with its payload schema
+pub fn event_schemas() -> Vec<(&'static str, RootSchema)> {
+    vec![
+        DOWNLOAD_PROGRESS_EVENT.schema(),
+        DOWNLOAD_STATS_EVENT.schema(),
+        CHAPTER_DOWNLOAD_PROGRESS_EVENT.schema(),
+        CHAPTER_IMAGE_SIZES_EVENT.schema(),
+        DOWNLOAD_VERIFY_PROGRESS_EVENT.schema(),
+        OFFLINE_READY_EVENT.schema(),
+        NETWORK_STATUS_EVENT.schema(),
+        NOTIFICATION_EVENT.schema(),
+        BADGE_SUMMARY_CHANGED_EVENT.schema(),
+        HOME_CONTENT_EVENT.schema(),
+        ANIME_DISCOVER_EVENT.schema(),
+        MANGA_DISCOVER_EVENT.schema(),
+        SEASON_ANIME_DISCOVER_EVENT.schema(),
+        SYSTEM_STATS_EVENT.schema(),
+        APP_LOGS_EVENT.schema(),
+        DATA_TRANSFER_PROGRESS_EVENT.schema(),
+        MIGRATION_PROGRESS_EVENT.schema(),
+        RELEASE_CHECK_PROGRESS_EVENT.schema(),
+        COVER_REFRESH_EVENT.schema(),
+        MEDIA_HYDRATION_EVENT.schema(),
+        STORAGE_USAGE_CHANGED_EVENT.schema(),
+        AUTO_BACKUP_COMPLETED_EVENT.schema(),
+        AUTO_BACKUP_FAILED_EVENT.schema(),
+        BACKUP_FILE_OPENED_EVENT.schema(),
+        EPISODE_COMPLETED_EVENT.schema(),
+        DEEPLINK_EVENT.schema(),
+    ]
+}
+
+/// `{ "events": { "<name>": <JSON schema of payload> } }`
+pub fn schema_document() -> serde_json::Value {
+    let events: serde_json::Map<String, serde_json::Value> = event_schemas()
+        .into_iter()
+        .map(|(name, schema)| {
+            (
+                name.to_string(),
+                serde_json::to_value(schema).unwrap_or(serde_json::Value::Null),
+            )
+        })
+        .collect();
+
+    serde_json::json!({ "events": events })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    fn rust_sources(dir: &Path, out: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                rust_sources(&path, out);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                out.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn every_declared_event_has_a_schema() {
+        // Split so this line itself isn't counted as a declaration
+        let declaration = concat!("Event", "::new(\"");
+        let declared = include_str!("events.rs").matches(declaration).count();
+        let schemas = event_schemas();
+
+        assert_eq!(declared, schemas.len(), "an event is declared but missing from event_schemas()");
+
+        let names: HashSet<_> = schemas.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names.len(), schemas.len(), "duplicate event name");
+
+        let document = schema_document();
+        for (name, _) in &schemas {
+            assert!(document["events"][name].is_object(), "no schema for {}", name);
+        }
+    }
+
+    #[test]
+    fn only_this_module_emits_events() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::new();
+        rust_sources(&src, &mut files);
+
+        let offenders: Vec<_> = files
+            .iter()
+            .filter(|path| !path.ends_with("events.rs"))
+            .filter(|path| std::fs::read_to_string(path).unwrap().contains("Emitter"))
+            .collect();
+
+        assert!(offenders.is_empty(), "emit through crate::events instead: {:?}", offenders);
+    }
+
+    #[test]
+    fn frontend_declares_every_event() {
+        let ts_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../src/types/events.ts");
+        let ts = std::fs::read_to_string(&ts_path).unwrap();
+
+        for (name, _) in event_schemas() {
+            assert!(ts.contains(&format!("'{}'", name)), "src/types/events.ts is missing '{}'", name);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Adult Content Gate
+//
+// Extensions leave adult titles out unless their runtime is created with
+// allow_adult. Commands take the mode from the frontend, but the nsfw_filter
+// setting (parental controls) overrides it: while the filter is on, no
+// runtime gets adult content whatever the call asked for. Background work
+// with no caller to ask (release checks, cache warm-up) follows the setting.
+//
+// Anything cached from a runtime is keyed by the mode it ran in, so a
+// filtered response is never handed to an unfiltered call or the reverse.
+
+use sqlx::SqlitePool;
+
+/// app_settings key: "1" hides adult content everywhere
+pub const NSFW_FILTER_SETTING: &str = "nsfw_filter";
+
+/// Whether the NSFW filter is on. Off when unset.
+pub async fn nsfw_filter_enabled(pool: &SqlitePool) -> bool {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(NSFW_FILTER_SETTING)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None);
+
+    value.map(|v| v == "1").unwrap_or(false)
+}
+
+/// Mode a runtime should use given what the caller asked for (None meaning
+/// no adult content) and whether the filter is on
+pub fn resolve(requested: Option<bool>, nsfw_filter: bool) -> bool {
+    !nsfw_filter && requested.unwrap_or(false)
+}
+
+/// Mode for a call that asked for `requested`
+pub async fn allow_adult(pool: &SqlitePool, requested: Option<bool>) -> bool {
+    resolve(requested, nsfw_filter_enabled(pool).await)
+}
+
+/// Mode for background work: adult content unless the filter is on
+pub async fn background_allow_adult(pool: &SqlitePool) -> bool {
+    allow_adult(pool, Some(true)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    #[test]
+    fn filter_overrides_the_request() {
+        assert!(resolve(Some(true), false));
+        assert!(!resolve(Some(true), true));
+        assert!(!resolve(None, false));
+        assert!(!resolve(Some(false), false));
+    }
+
+    #[tokio::test]
+    async fn reads_the_filter_setting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert!(allow_adult(pool, Some(true)).await);
+
+        sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, '1')")
+            .bind(NSFW_FILTER_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert!(!allow_adult(pool, Some(true)).await);
+        assert!(!background_allow_adult(pool).await);
+    }
+}
@@ -0,0 +1,459 @@
+// Extension API Schema
+//
+// Describes what the runtime expects from an extension, for extension
+// authors: the hook functions it calls on `extensionObject` (with their
+// arguments, whether they're required and what happens without them) and a
+// JSON schema of every value those hooks return. The schemas are generated
+// from the types in types.rs that the results are deserialized into, so
+// they can't drift from what the runtime accepts.
+//
+// Serde aliases (e.g. `hasNextPage` for `has_next_page`) don't show up in
+// generated schemas, so they're listed in ALIASES and added afterwards; a
+// test keeps that list in step with types.rs.
+//
+// The home page isn't a hook: the app builds it from discover and
+// getRecentlyUpdated.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::types::{
+    ChapterImages, ExtensionMetadata, ExtensionType, MangaDetails, MediaDetails, SearchResults, SeasonResults,
+    TagsResult, VideoSources,
+};
+
+/// Version of the API described here; bumped when a hook or a type changes
+/// in a way existing extensions would notice
+pub const API_VERSION: u32 = 1;
+
+/// An argument the runtime passes to a hook
+#[derive(Debug, Clone, Serialize)]
+pub struct HookParam {
+    pub name: &'static str,
+    /// TypeScript notation ("string", "number | null", "string[]")
+    #[serde(rename = "type")]
+    pub param_type: &'static str,
+}
+
+/// A function the runtime calls on `extensionObject`
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionHook {
+    pub name: &'static str,
+    pub params: &'static [HookParam],
+    /// Name of the returned type, a key of `types`
+    pub returns: &'static str,
+    /// Kind of extension the hook is called on; None for both
+    pub extension_type: Option<ExtensionType>,
+    pub required: bool,
+    /// What the runtime does instead when an optional hook is missing
+    pub fallback: Option<&'static str>,
+}
+
+const PAGE: HookParam = HookParam { name: "page", param_type: "number" };
+
+/// Every hook the runtime calls, in the order of runtime.rs
+pub const HOOKS: &[ExtensionHook] = &[
+    ExtensionHook {
+        name: "search",
+        params: &[HookParam { name: "query", param_type: "string" }, PAGE],
+        returns: "SearchResults",
+        extension_type: None,
+        required: true,
+        fallback: None,
+    },
+    ExtensionHook {
+        name: "discover",
+        params: &[
+            PAGE,
+            HookParam { name: "sortType", param_type: "string | null" },
+            HookParam { name: "genres", param_type: "string[]" },
+        ],
+        returns: "SearchResults",
+        extension_type: None,
+        required: false,
+        fallback: Some("search with an empty query"),
+    },
+    ExtensionHook {
+        name: "getCurrentSeason",
+        params: &[PAGE],
+        returns: "SeasonResults",
+        extension_type: Some(ExtensionType::Anime),
+        required: false,
+        fallback: Some("discover, labelled with the current season"),
+    },
+    ExtensionHook {
+        name: "getRecommendations",
+        params: &[],
+        returns: "SearchResults",
+        extension_type: None,
+        required: false,
+        fallback: Some("discover, or search with an empty query"),
+    },
+    ExtensionHook {
+        name: "getRecentlyUpdated",
+        params: &[PAGE],
+        returns: "SearchResults",
+        extension_type: None,
+        required: false,
+        fallback: Some("discover, or search with an empty query"),
+    },
+    ExtensionHook {
+        name: "getDetails",
+        params: &[HookParam { name: "id", param_type: "string" }],
+        returns: "MediaDetails",
+        extension_type: Some(ExtensionType::Anime),
+        required: true,
+        fallback: None,
+    },
+    ExtensionHook {
+        name: "getSources",
+        params: &[HookParam { name: "episodeId", param_type: "string" }],
+        returns: "VideoSources",
+        extension_type: Some(ExtensionType::Anime),
+        required: true,
+        fallback: None,
+    },
+    ExtensionHook {
+        name: "getTags",
+        params: &[PAGE],
+        returns: "TagsResult",
+        extension_type: None,
+        required: false,
+        fallback: Some("no genres or studios"),
+    },
+    ExtensionHook {
+        name: "getDetails",
+        params: &[HookParam { name: "id", param_type: "string" }],
+        returns: "MangaDetails",
+        extension_type: Some(ExtensionType::Manga),
+        required: true,
+        fallback: None,
+    },
+    ExtensionHook {
+        name: "getChapterImages",
+        params: &[HookParam { name: "chapterId", param_type: "string" }],
+        returns: "ChapterImages",
+        extension_type: Some(ExtensionType::Manga),
+        required: false,
+        fallback: Some("a chapter without pages"),
+    },
+];
+
+/// Serde aliases of the types in types.rs: (type, field, alias)
+const ALIASES: &[(&str, &str, &str)] = &[
+    ("ExtensionMetadata", "base_url", "baseUrl"),
+    ("ExtensionMetadata", "icon_url", "iconUrl"),
+    ("SearchResult", "cover_url", "coverUrl"),
+    ("SearchResult", "trailer_url", "trailerUrl"),
+    ("SearchResult", "latest_episode", "latestEpisode"),
+    ("SearchResult", "latest_episode_date", "latestEpisodeDate"),
+    ("SearchResult", "available_episodes", "availableEpisodes"),
+    ("SearchResult", "media_type", "mediaType"),
+    ("SearchResult", "broadcast_day", "broadcastDay"),
+    ("SearchResult", "broadcast_time", "broadcastTime"),
+    ("SearchResult", "broadcast_timezone", "broadcastTimezone"),
+    ("SearchResult", "language_match", "languageMatch"),
+    ("SearchResult", "age_rating", "ageRating"),
+    ("SearchResults", "has_next_page", "hasNextPage"),
+    ("SeasonResults", "has_next_page", "hasNextPage"),
+    ("MediaDetails", "cover_url", "coverUrl"),
+    ("MediaDetails", "trailer_url", "trailerUrl"),
+    ("MediaDetails", "last_update_end", "lastUpdateEnd"),
+    ("MediaDetails", "broadcast_interval", "broadcastInterval"),
+    ("MediaDetails", "age_rating", "ageRating"),
+    ("VideoSource", "language_match", "languageMatch"),
+    ("TagsResult", "has_next_page", "hasNextPage"),
+    ("Chapter", "release_date", "releaseDate"),
+    ("ChapterImages", "total_pages", "totalPages"),
+    ("MangaDetails", "cover_url", "coverUrl"),
+    ("MangaDetails", "trailer_url", "trailerUrl"),
+    ("MangaDetails", "total_chapters", "totalChapters"),
+];
+
+/// `{ "version", "metadata": <schema>, "hooks": [...], "types": { "<name>": <schema> } }`
+pub fn api_schema() -> Value {
+    let types: serde_json::Map<String, Value> = [
+        ("SearchResults", type_schema::<SearchResults>()),
+        ("SeasonResults", type_schema::<SeasonResults>()),
+        ("MediaDetails", type_schema::<MediaDetails>()),
+        ("VideoSources", type_schema::<VideoSources>()),
+        ("TagsResult", type_schema::<TagsResult>()),
+        ("MangaDetails", type_schema::<MangaDetails>()),
+        ("ChapterImages", type_schema::<ChapterImages>()),
+    ]
+    .into_iter()
+    .map(|(name, schema)| (name.to_string(), schema))
+    .collect();
+
+    json!({
+        "version": API_VERSION,
+        "metadata": type_schema::<ExtensionMetadata>(),
+        "hooks": HOOKS,
+        "types": types,
+    })
+}
+
+/// Schema of `T` as extensions may write it, aliases included
+fn type_schema<T: JsonSchema>() -> Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(T)).unwrap_or(Value::Null);
+    if let Some(title) = schema.get("title").and_then(Value::as_str).map(str::to_string) {
+        add_aliases(&title, &mut schema);
+    }
+    if let Some(Value::Object(definitions)) = schema.get_mut("definitions") {
+        for (name, definition) in definitions.iter_mut() {
+            add_aliases(name, definition);
+        }
+    }
+    schema
+}
+
+/// Accept each alias of `type_name` wherever its field is: as a property of
+/// its own, and as an alternative when the field is required
+fn add_aliases(type_name: &str, schema: &mut Value) {
+    for &(_, field, alias) in ALIASES.iter().filter(|(name, ..)| *name == type_name) {
+        let Some(property) = schema.get("properties").and_then(|p| p.get(field)).cloned() else {
+            continue;
+        };
+        schema["properties"][alias] = property;
+
+        let was_required = match schema.get_mut("required").and_then(Value::as_array_mut) {
+            Some(required) => {
+                let before = required.len();
+                required.retain(|r| r.as_str() != Some(field));
+                required.len() != before
+            }
+            None => false,
+        };
+        if was_required {
+            let either = json!({ "anyOf": [{ "required": [field] }, { "required": [alias] }] });
+            match schema.get_mut("allOf").and_then(Value::as_array_mut) {
+                Some(all_of) => all_of.push(either),
+                None => schema["allOf"] = json!([either]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::bundled::bundled_extensions;
+    use crate::extensions::ExtensionRuntime;
+    use std::collections::HashSet;
+
+    /// Errors of `value` against `schema`, for the subset of JSON schema that
+    /// schemars generates. `root` resolves `#/definitions/...` references.
+    fn validate(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/definitions/");
+            validate(value, &root["definitions"][name], root, path, errors);
+        }
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            for branch in all_of {
+                validate(value, branch, root, path, errors);
+            }
+        }
+        if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+            let matches = any_of.iter().any(|branch| {
+                let mut branch_errors = Vec::new();
+                validate(value, branch, root, path, &mut branch_errors);
+                branch_errors.is_empty()
+            });
+            if !matches {
+                errors.push(format!("{}: matches none of {}", path, Value::Array(any_of.clone())));
+            }
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            let fits = types.iter().any(|t| match *t {
+                "null" => value.is_null(),
+                "boolean" => value.is_boolean(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "number" => value.is_number(),
+                "string" => value.is_string(),
+                "array" => value.is_array(),
+                "object" => value.is_object(),
+                _ => false,
+            });
+            if !fits {
+                errors.push(format!("{}: expected {:?}, got {}", path, types, value));
+                return;
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                errors.push(format!("{}: {} is not one of {:?}", path, value, options));
+            }
+        }
+        if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+            if number < minimum {
+                errors.push(format!("{}: {} is below {}", path, number, minimum));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for field in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                if !object.contains_key(field.as_str().unwrap_or_default()) {
+                    errors.push(format!("{}: missing {}", path, field));
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (key, property) in properties {
+                    if let Some(field) = object.get(key) {
+                        validate(field, property, root, &format!("{}.{}", path, key), errors);
+                    }
+                }
+            }
+        }
+        if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+            for (i, item) in array.iter().enumerate() {
+                validate(item, items, root, &format!("{}[{}]", path, i), errors);
+            }
+        }
+    }
+
+    fn errors_against(type_name: &str, value: &Value) -> Vec<String> {
+        let schema = &api_schema()["types"][type_name];
+        assert!(schema.is_object(), "no schema for {}", type_name);
+        let mut errors = Vec::new();
+        validate(value, schema, schema, type_name, &mut errors);
+        errors
+    }
+
+    #[test]
+    fn aliases_match_the_serde_attributes() {
+        // (type, field, alias) of every `alias = "..."` in types.rs, except
+        // ones that repeat the field name
+        let mut declared = HashSet::new();
+        let mut current_type = "";
+        let mut pending = Vec::new();
+        for line in include_str!("types.rs").lines() {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("pub struct ").or_else(|| line.strip_prefix("pub enum ")) {
+                current_type = rest.split_whitespace().next().unwrap_or_default();
+            } else if line.starts_with("#[serde(") {
+                pending.extend(line.split("alias = \"").skip(1).filter_map(|s| s.split('"').next()));
+            } else if let Some(field) = line.strip_prefix("pub ").and_then(|rest| rest.split(':').next()) {
+                for alias in pending.drain(..) {
+                    if alias != field {
+                        declared.insert((current_type, field, alias));
+                    }
+                }
+            }
+        }
+
+        let listed: HashSet<_> = ALIASES.iter().copied().collect();
+        assert_eq!(listed, declared, "ALIASES is out of step with types.rs");
+    }
+
+    #[test]
+    fn hooks_match_what_the_runtime_calls() {
+        // Split so this line itself isn't counted
+        let lookup = concat!("ext_obj", ".get(\"");
+        let called: HashSet<&str> = include_str!("runtime.rs")
+            .split(lookup)
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .collect();
+        let listed: HashSet<&str> = HOOKS.iter().map(|hook| hook.name).collect();
+        assert_eq!(listed, called);
+
+        let schema = api_schema();
+        for hook in HOOKS {
+            assert!(schema["types"][hook.returns].is_object(), "no schema for {}", hook.returns);
+            assert_eq!(hook.required, hook.fallback.is_none(), "{} needs a fallback or must be required", hook.name);
+        }
+        assert!(schema["metadata"]["properties"]["baseUrl"].is_object());
+    }
+
+    #[test]
+    fn required_fields_and_aliases_are_checked() {
+        assert!(errors_against("SearchResults", &json!({ "results": [], "has_next_page": false })).is_empty());
+        assert!(errors_against("SearchResults", &json!({ "results": [], "hasNextPage": false })).is_empty());
+        assert!(!errors_against("SearchResults", &json!({ "results": [] })).is_empty());
+        assert!(!errors_against("SearchResults", &json!({ "results": [{ "id": "1" }], "hasNextPage": false })).is_empty());
+        assert!(!errors_against("VideoSources", &json!({ "sources": [], "subtitles": "none" })).is_empty());
+    }
+
+    /// What the AllAnime API answers, one body for every query
+    fn canned_response() -> Value {
+        let show = json!({
+            "_id": "show-1",
+            "name": "Sousou no Frieren",
+            "englishName": "Frieren: Beyond Journey's End",
+            "nativeName": "葬送のフリーレン",
+            "thumbnail": "https://img.example.test/frieren.jpg",
+            "description": "An elf mage outlives her party.",
+            "status": "Finished",
+            "score": "9.1",
+            "type": "TV",
+            "genres": ["Adventure", "Drama"],
+            "season": { "quarter": "Fall", "year": 2023 },
+            "airedStart": { "year": 2023, "month": 8, "date": 29 },
+            "availableEpisodes": { "sub": 2 },
+            "availableEpisodesDetail": { "sub": ["1", "2"] },
+            "lastEpisodeInfo": { "sub": { "episodeString": "2" } },
+            "lastEpisodeDate": { "sub": { "year": 2023, "month": 8, "date": 29 } },
+            "episodeDuration": "1440000",
+            "episodeCount": "28",
+            "broadcastInterval": "604800000",
+            "lastUpdateEnd": "2024-03-22T15:00:00Z",
+        });
+        json!({
+            "data": {
+                "shows": { "edges": [show] },
+                "show": show,
+                "queryPopular": { "recommendations": [{ "anyCard": show }] },
+                "queryLatestPageStatus": { "recommendations": [{ "anyCard": show }] },
+                "queryTags": { "edges": [
+                    { "name": "Adventure", "slug": "adventure", "animeCount": 120, "tagType": "genre" },
+                    { "name": "Madhouse", "slug": "madhouse", "animeCount": 40, "tagType": "studio" },
+                ] },
+                "episode": { "sourceUrls": [
+                    { "sourceName": "Default", "sourceUrl": "https://cdn.example.test/frieren/1.m3u8", "priority": 8, "type": "player" },
+                ] },
+            }
+        })
+    }
+
+    #[test]
+    fn bundled_extension_output_matches_the_schema() {
+        let mut extension = bundled_extensions().remove(0);
+        // Answer every request from the canned body instead of the network
+        extension.code.push_str(&format!(
+            "\nglobalThis.__fetch = function () {{ return JSON.stringify({{ status: 200, body: {} }}); }};\n",
+            serde_json::to_string(&canned_response().to_string()).unwrap()
+        ));
+        let runtime = ExtensionRuntime::new(extension).unwrap();
+
+        let calls = [
+            ("search", "'frieren', 1"),
+            ("discover", "1, null, []"),
+            ("getCurrentSeason", "1"),
+            ("getRecommendations", ""),
+            ("getRecentlyUpdated", "1"),
+            ("getDetails", "'show-1'"),
+            ("getSources", "'show-1::1'"),
+            ("getTags", "1"),
+        ];
+        for (name, args) in calls {
+            let hook = HOOKS
+                .iter()
+                .find(|h| h.name == name && h.extension_type != Some(ExtensionType::Manga))
+                .unwrap();
+            let output = runtime.call_json(name, args).unwrap();
+            let errors = errors_against(hook.returns, &output);
+            assert!(errors.is_empty(), "{} output doesn't match {}: {:?}", name, hook.returns, errors);
+        }
+
+        // The canned data actually made it through
+        let results = runtime.search("frieren", 1).unwrap();
+        assert_eq!(results.results[0].cover_url.as_deref(), Some("https://img.example.test/frieren.jpg"));
+        assert_eq!(runtime.get_details("show-1").unwrap().episodes.len(), 2);
+        assert_eq!(runtime.get_sources("show-1::1").unwrap().sources.len(), 1);
+    }
+}
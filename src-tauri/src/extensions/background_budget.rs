@@ -0,0 +1,307 @@
+// Extension Background Budget
+//
+// Release checks, auto-downloads, cache warm-up, the source lookups of
+// queued downloads, numbering detection and media repair and hydration all
+// call into extensions without anyone waiting on them, and together they
+// could hammer a source site with a large library. Each extension gets a
+// token bucket of background calls (extension_background_calls_per_hour, 30
+// by default) that refills continuously; background features take a token
+// before calling in (`take`, or commands::background_runtime) and defer the
+// work when none is left. Commands the user started don't draw from it.
+// Buckets are in memory only and start full on every launch.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// app_settings key: background calls each extension may take per hour
+pub const CALLS_PER_HOUR_SETTING: &str = "extension_background_calls_per_hour";
+
+pub const DEFAULT_CALLS_PER_HOUR: u32 = 30;
+
+/// Smallest and largest budgets accepted from settings
+const MIN_CALLS_PER_HOUR: u32 = 1;
+const MAX_CALLS_PER_HOUR: u32 = 3600;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Current budget per extension and hour
+static CALLS_PER_HOUR: AtomicU32 = AtomicU32::new(DEFAULT_CALLS_PER_HOUR);
+
+static BUCKETS: LazyLock<Budgets> = LazyLock::new(Budgets::default);
+
+/// Returned instead of calling into an extension whose budget is used up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExhausted {
+    pub extension_id: String,
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Background call budget of {} used up, next call in {}s",
+            self.extension_id,
+            self.retry_after.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for BudgetExhausted {}
+
+/// Budget of one extension as reported to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetStatus {
+    pub extension_id: String,
+    /// Whole calls that can be made right now
+    pub remaining: u32,
+    pub calls_per_hour: u32,
+    /// Seconds until the next call is available; 0 while some are left
+    pub next_call_in_secs: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(capacity: u32, now: Instant) -> Self {
+        Bucket { tokens: capacity as f64, updated_at: now }
+    }
+
+    /// Add the tokens earned since the last update, up to `capacity`
+    fn refill(&mut self, capacity: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        let earned = elapsed.as_secs_f64() * capacity as f64 / HOUR.as_secs_f64();
+        self.tokens = (self.tokens + earned).min(capacity as f64);
+        self.updated_at = now;
+    }
+
+    /// Time until a whole token is available
+    fn wait(&self, capacity: u32) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        let missing = 1.0 - self.tokens;
+        Duration::from_secs_f64(missing * HOUR.as_secs_f64() / capacity as f64)
+    }
+}
+
+/// One bucket per extension, shared by every background feature
+#[derive(Debug, Default)]
+struct Budgets {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Budgets {
+    fn take(&self, extension_id: &str, capacity: u32, now: Instant) -> Result<(), BudgetExhausted> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(extension_id.to_string())
+            .or_insert_with(|| Bucket::full(capacity, now));
+        bucket.refill(capacity, now);
+
+        if bucket.tokens < 1.0 {
+            return Err(BudgetExhausted {
+                extension_id: extension_id.to_string(),
+                retry_after: bucket.wait(capacity),
+            });
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn status(&self, extension_id: &str, capacity: u32, now: Instant) -> BudgetStatus {
+        let buckets = self.buckets.lock().unwrap();
+        let mut bucket = buckets
+            .get(extension_id)
+            .cloned()
+            .unwrap_or_else(|| Bucket::full(capacity, now));
+        bucket.refill(capacity, now);
+
+        BudgetStatus {
+            extension_id: extension_id.to_string(),
+            remaining: bucket.tokens.floor() as u32,
+            calls_per_hour: capacity,
+            next_call_in_secs: bucket.wait(capacity).as_secs_f64().ceil() as u64,
+        }
+    }
+}
+
+fn clamp_calls_per_hour(calls: u32) -> u32 {
+    calls.clamp(MIN_CALLS_PER_HOUR, MAX_CALLS_PER_HOUR)
+}
+
+/// Background calls each extension may make per hour
+pub fn calls_per_hour() -> u32 {
+    CALLS_PER_HOUR.load(Ordering::SeqCst)
+}
+
+/// Change the budget, returning the (clamped) value applied. Buckets holding
+/// more than the new budget are cut down on their next use.
+pub fn set_calls_per_hour(calls: u32) -> u32 {
+    let calls = clamp_calls_per_hour(calls);
+    CALLS_PER_HOUR.store(calls, Ordering::SeqCst);
+    calls
+}
+
+/// The stored budget, or the default when unset
+pub async fn load_calls_per_hour_setting(pool: &SqlitePool) -> u32 {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(CALLS_PER_HOUR_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value
+        .and_then(|v| v.trim().parse().ok())
+        .map(clamp_calls_per_hour)
+        .unwrap_or(DEFAULT_CALLS_PER_HOUR)
+}
+
+/// Take one background call from the extension's budget. Callers defer their
+/// work when this fails.
+pub fn take(extension_id: &str) -> Result<(), BudgetExhausted> {
+    let result = BUCKETS.take(extension_id, calls_per_hour(), Instant::now());
+    if let Err(e) = &result {
+        log::info!("Deferring background work: {}", e);
+    }
+    result
+}
+
+/// Remaining budget of an extension (full for extensions not called yet)
+pub fn status(extension_id: &str) -> BudgetStatus {
+    BUCKETS.status(extension_id, calls_per_hour(), Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn a_full_bucket_allows_its_capacity_then_refuses() {
+        let now = Instant::now();
+        let budgets = Budgets::default();
+
+        for _ in 0..5 {
+            assert!(budgets.take("ext", 5, now).is_ok());
+        }
+        let err = budgets.take("ext", 5, now).unwrap_err();
+        assert_eq!(err.extension_id, "ext");
+        // One call every 12 minutes at 5 per hour
+        assert_eq!(err.retry_after, Duration::from_secs(12 * 60));
+
+        let status = budgets.status("ext", 5, now);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.next_call_in_secs, 12 * 60);
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_the_capacity() {
+        let start = Instant::now();
+        let budgets = Budgets::default();
+        for _ in 0..6 {
+            budgets.take("ext", 6, start).unwrap();
+        }
+
+        // 6 per hour: one call every 10 minutes
+        let later = start + Duration::from_secs(25 * 60);
+        assert_eq!(budgets.status("ext", 6, later).remaining, 2);
+        assert!(budgets.take("ext", 6, later).is_ok());
+        assert!(budgets.take("ext", 6, later).is_ok());
+        assert!(budgets.take("ext", 6, later).is_err());
+
+        let much_later = later + 10 * HOUR;
+        assert_eq!(budgets.status("ext", 6, much_later).remaining, 6);
+    }
+
+    #[test]
+    fn extensions_have_separate_buckets() {
+        let now = Instant::now();
+        let budgets = Budgets::default();
+        budgets.take("a", 1, now).unwrap();
+
+        assert!(budgets.take("a", 1, now).is_err());
+        assert!(budgets.take("b", 1, now).is_ok());
+        assert_eq!(budgets.status("c", 1, now).remaining, 1);
+    }
+
+    #[test]
+    fn concurrent_consumers_never_overdraw_a_bucket() {
+        let now = Instant::now();
+        let budgets = Arc::new(Budgets::default());
+        let granted = Arc::new(AtomicUsize::new(0));
+
+        // Eight background features racing for one extension's 30 calls
+        let consumers: Vec<_> = (0..8)
+            .map(|_| {
+                let budgets = budgets.clone();
+                let granted = granted.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        if budgets.take("ext", 30, now).is_ok() {
+                            granted.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for consumer in consumers {
+            consumer.join().unwrap();
+        }
+
+        assert_eq!(granted.load(Ordering::SeqCst), 30);
+        assert_eq!(budgets.status("ext", 30, now).remaining, 0);
+    }
+
+    #[test]
+    fn concurrent_consumers_share_the_refill() {
+        let start = Instant::now();
+        let budgets = Arc::new(Budgets::default());
+        for _ in 0..4 {
+            budgets.take("ext", 4, start).unwrap();
+        }
+
+        // Half an hour later two calls were earned; four consumers want one each
+        let later = start + HOUR / 2;
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let budgets = budgets.clone();
+                std::thread::spawn(move || budgets.take("ext", 4, later).is_ok())
+            })
+            .collect();
+        let granted = consumers
+            .into_iter()
+            .map(|consumer| consumer.join().unwrap())
+            .filter(|ok| *ok)
+            .count();
+
+        assert_eq!(granted, 2);
+    }
+
+    #[test]
+    fn a_lowered_budget_caps_a_full_bucket() {
+        let now = Instant::now();
+        let budgets = Budgets::default();
+        budgets.take("ext", 30, now).unwrap();
+
+        assert_eq!(budgets.status("ext", 10, now).remaining, 10);
+    }
+
+    #[test]
+    fn calls_per_hour_is_clamped() {
+        assert_eq!(clamp_calls_per_hour(0), MIN_CALLS_PER_HOUR);
+        assert_eq!(clamp_calls_per_hour(45), 45);
+        assert_eq!(clamp_calls_per_hour(u32::MAX), MAX_CALLS_PER_HOUR);
+    }
+}
@@ -0,0 +1,217 @@
+// Bundled Extensions
+//
+// A new install would otherwise open to an empty app until the user finds and
+// pastes an extension. The default extension ships inside the binary and is
+// installed on first run: it's stored in the extensions table (which also
+// keeps it across restarts) and bundled_extensions_installed is set so that
+// uninstalling it later sticks. When a release ships a newer version of a
+// bundled extension, installed copies are updated at startup.
+
+use anyhow::Result;
+use sqlx::{Row, SqlitePool};
+
+use super::types::ExtensionType;
+use super::Extension;
+
+/// app_settings key: "true" once the bundled extensions were installed
+pub const INSTALLED_SETTING: &str = "bundled_extensions_installed";
+
+/// Code of the extensions shipped with the app
+const BUNDLED_EXTENSIONS: &[&str] = &[include_str!("../../bundled-extensions/allanime.js")];
+
+/// Parsed bundled extensions
+pub fn bundled_extensions() -> Vec<Extension> {
+    BUNDLED_EXTENSIONS
+        .iter()
+        .filter_map(|code| match Extension::from_code(code) {
+            Ok(extension) => Some(extension),
+            Err(e) => {
+                log::error!("Bundled extension failed to parse: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether version `a` is newer than `b`, comparing dot-separated numbers
+/// ("1.10.0" > "1.9.2"). Parts that aren't numbers count as 0.
+pub fn is_newer_version(a: &str, b: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parse(a), parse(b));
+    let len = a.len().max(b.len());
+    let pad = |v: &[u64]| -> Vec<u64> { (0..len).map(|i| v.get(i).copied().unwrap_or(0)).collect() };
+    pad(&a) > pad(&b)
+}
+
+async fn is_installed_flag_set(pool: &SqlitePool) -> Result<bool> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(INSTALLED_SETTING)
+        .fetch_optional(pool)
+        .await?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// Store an extension so it's loaded again on the next start
+pub async fn persist_extension(pool: &SqlitePool, extension: &Extension) -> Result<()> {
+    let metadata = &extension.metadata;
+    let extension_type = match metadata.extension_type {
+        ExtensionType::Anime => "anime",
+        ExtensionType::Manga => "manga",
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO extensions (id, name, version, extension_type, language, code, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name,
+            version = excluded.version,
+            extension_type = excluded.extension_type,
+            language = excluded.language,
+            code = excluded.code,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(&metadata.id)
+    .bind(&metadata.name)
+    .bind(&metadata.version)
+    .bind(extension_type)
+    .bind(&metadata.language)
+    .bind(&extension.code)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Forget a stored extension, returning whether there was one
+pub async fn remove_persisted_extension(pool: &SqlitePool, extension_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM extensions WHERE id = ?")
+        .bind(extension_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+async fn persisted_version(pool: &SqlitePool, extension_id: &str) -> Result<Option<String>> {
+    Ok(sqlx::query_scalar("SELECT version FROM extensions WHERE id = ?")
+        .bind(extension_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Install the bundled extensions on first run, and on later runs update
+/// installed ones that are older than the bundled version. Returns the
+/// extensions that were installed or updated, to be loaded by the caller.
+pub async fn install_bundled_extensions(pool: &SqlitePool) -> Result<Vec<Extension>> {
+    let first_run = !is_installed_flag_set(pool).await?;
+
+    let mut changed = Vec::new();
+    for extension in bundled_extensions() {
+        let install = match persisted_version(pool, &extension.metadata.id).await? {
+            Some(version) => is_newer_version(&extension.metadata.version, &version),
+            // Removed by the user after the first run
+            None => first_run,
+        };
+
+        if install {
+            persist_extension(pool, &extension).await?;
+            log::info!(
+                "Installed bundled extension {} {}",
+                extension.metadata.id,
+                extension.metadata.version
+            );
+            changed.push(extension);
+        }
+    }
+
+    if first_run {
+        sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, 'true')")
+            .bind(INSTALLED_SETTING)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(changed)
+}
+
+/// Stored extensions, after updating bundled ones to this release's version
+pub async fn restore_extensions(pool: &SqlitePool) -> Result<Vec<Extension>> {
+    if is_installed_flag_set(pool).await? {
+        install_bundled_extensions(pool).await?;
+    }
+
+    let rows = sqlx::query("SELECT id, code FROM extensions WHERE enabled = 1 ORDER BY created_at")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let id: String = row.get("id");
+            let code: String = row.get("code");
+            match Extension::from_code(&code) {
+                Ok(extension) => Some(extension),
+                Err(e) => {
+                    log::warn!("Stored extension {} failed to parse: {}", id, e);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn bundled_extensions_parse() {
+        let bundled = bundled_extensions();
+        assert_eq!(bundled.len(), BUNDLED_EXTENSIONS.len());
+        assert!(bundled.iter().any(|e| e.metadata.id == "com.allanime.source"));
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(is_newer_version("1.10.0", "1.9.2"));
+        assert!(is_newer_version("1.0.1", "1.0"));
+        assert!(is_newer_version("v2", "1.9.9"));
+        assert!(!is_newer_version("1.0.0", "1.0"));
+        assert!(!is_newer_version("1.0.0", "1.0.1"));
+    }
+
+    #[tokio::test]
+    async fn installs_once_and_updates_older_copies() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+        let bundled_count = bundled_extensions().len();
+
+        // Nothing happens before the first run
+        assert!(restore_extensions(pool).await.unwrap().is_empty());
+
+        assert_eq!(install_bundled_extensions(pool).await.unwrap().len(), bundled_count);
+        assert!(install_bundled_extensions(pool).await.unwrap().is_empty());
+        assert_eq!(restore_extensions(pool).await.unwrap().len(), bundled_count);
+
+        // An older stored copy is brought up to date
+        sqlx::query("UPDATE extensions SET version = '0.0.1' WHERE id = 'com.allanime.source'")
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(install_bundled_extensions(pool).await.unwrap().len(), 1);
+
+        // Uninstalling sticks
+        assert!(remove_persisted_extension(pool, "com.allanime.source").await.unwrap());
+        assert!(install_bundled_extensions(pool).await.unwrap().is_empty());
+        assert!(restore_extensions(pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+// Extension Circuit Breaker
+//
+// An extension that throws on every call would otherwise be retried by every
+// search, home load and release check, each paying for a fresh runtime. After
+// FAILURE_THRESHOLD consecutive failures within FAILURE_WINDOW the extension
+// is tripped: calls fail fast with ExtensionUnavailable for COOL_DOWN. Once the
+// cool-down passes, exactly one call is let through as a probe (half-open) and
+// the rest keep failing fast until it reports back; success closes the
+// breaker, another failure trips it again straight away. A probe that never
+// reports back frees the slot after PROBE_TIMEOUT. A passing health check
+// always closes it.
+//
+// Errors that blame the request rather than the extension (not found, bad
+// query) don't count towards tripping: the extension answered fine.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Consecutive failures that trip the breaker
+const FAILURE_THRESHOLD: u32 = 5;
+
+/// Failures further apart than this don't count as consecutive
+const FAILURE_WINDOW: Duration = Duration::from_secs(2 * 60);
+
+/// How long a tripped extension fails fast
+const COOL_DOWN: Duration = Duration::from_secs(5 * 60);
+
+/// How long a half-open probe holds the slot before another call may probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Lowercase fragments of errors caused by the request, not the extension
+const PERMANENT_ERROR_MARKERS: &[&str] = &[
+    "not found",
+    "404",
+    "bad request",
+    "400",
+    "invalid query",
+    "invalid id",
+    "empty query",
+    "no results",
+];
+
+static BREAKERS: LazyLock<Mutex<HashMap<String, Breaker>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls fail fast until the cool-down ends
+    Open,
+    /// Cool-down over; the next call decides whether to close or re-open
+    HalfOpen,
+}
+
+/// Breaker state as reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Unix timestamp (ms) when a tripped breaker lets calls through again
+    pub retry_at: Option<i64>,
+}
+
+/// Returned instead of calling into a tripped extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionUnavailable {
+    pub extension_id: String,
+    pub retry_after: Duration,
+    pub last_error: Option<String>,
+}
+
+impl fmt::Display for ExtensionUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Extension unavailable: {} failed repeatedly and is paused for {}s",
+            self.extension_id,
+            self.retry_after.as_secs().max(1)
+        )?;
+        if let Some(error) = &self.last_error {
+            write!(f, " (last error: {})", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ExtensionUnavailable {}
+
+#[derive(Debug, Clone, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    last_failure_at: Option<Instant>,
+    open_until: Option<Instant>,
+    last_error: Option<String>,
+    /// When the current half-open probe was let through
+    probe_started_at: Option<Instant>,
+}
+
+/// Whether an error says the request was wrong rather than the extension
+/// broken
+fn is_permanent_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    PERMANENT_ERROR_MARKERS.iter().any(|marker| error.contains(marker))
+}
+
+impl Breaker {
+    fn state(&self, now: Instant) -> BreakerState {
+        match self.open_until {
+            Some(until) if now < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+            None => BreakerState::Closed,
+        }
+    }
+
+    /// Fail fast while open; while half-open, claim the probe slot or fail
+    /// fast if another call holds it
+    fn check(&mut self, extension_id: &str, now: Instant) -> Result<(), ExtensionUnavailable> {
+        let retry_at = match self.state(now) {
+            BreakerState::Closed => return Ok(()),
+            BreakerState::Open => self.open_until,
+            BreakerState::HalfOpen => self
+                .probe_started_at
+                .map(|started| started + PROBE_TIMEOUT)
+                .filter(|probe_ends| now < *probe_ends),
+        };
+
+        match retry_at {
+            Some(until) => Err(ExtensionUnavailable {
+                extension_id: extension_id.to_string(),
+                retry_after: until - now,
+                last_error: self.last_error.clone(),
+            }),
+            None => {
+                self.probe_started_at = Some(now);
+                Ok(())
+            }
+        }
+    }
+
+    /// Free the probe slot without deciding the breaker's state
+    fn release_probe(&mut self) {
+        self.probe_started_at = None;
+    }
+
+    fn record_success(&mut self) {
+        *self = Breaker::default();
+    }
+
+    fn record_failure(&mut self, error: &str, now: Instant) {
+        let half_open = self.state(now) == BreakerState::HalfOpen;
+        let within_window = self
+            .last_failure_at
+            .is_some_and(|at| now.duration_since(at) <= FAILURE_WINDOW);
+
+        self.consecutive_failures = if within_window || half_open {
+            self.consecutive_failures + 1
+        } else {
+            1
+        };
+        self.last_failure_at = Some(now);
+        self.last_error = Some(error.to_string());
+        self.probe_started_at = None;
+
+        // A failed probe re-trips immediately; otherwise wait for the threshold
+        if half_open || self.consecutive_failures >= FAILURE_THRESHOLD {
+            self.open_until = Some(now + COOL_DOWN);
+        }
+    }
+
+    fn status(&self, now: Instant) -> BreakerStatus {
+        let retry_at = self.open_until.filter(|until| now < *until).map(|until| {
+            let remaining = until - now;
+            let at = SystemTime::now() + remaining;
+            at.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0)
+        });
+
+        BreakerStatus {
+            state: self.state(now),
+            consecutive_failures: self.consecutive_failures,
+            last_error: self.last_error.clone(),
+            retry_at,
+        }
+    }
+}
+
+/// Fail fast if the extension is tripped, without claiming the half-open
+/// probe slot. For early exits before a call that goes through `check`.
+pub fn peek(extension_id: &str) -> Result<(), ExtensionUnavailable> {
+    let breakers = BREAKERS.lock().unwrap();
+    match breakers.get(extension_id) {
+        Some(breaker) => breaker.clone().check(extension_id, Instant::now()),
+        None => Ok(()),
+    }
+}
+
+/// Fail fast if the extension is tripped, or is half-open with its probe
+/// already in flight
+pub fn check(extension_id: &str) -> Result<(), ExtensionUnavailable> {
+    let mut breakers = BREAKERS.lock().unwrap();
+    match breakers.get_mut(extension_id) {
+        Some(breaker) => breaker.check(extension_id, Instant::now()),
+        None => Ok(()),
+    }
+}
+
+pub fn record_success(extension_id: &str) {
+    let mut breakers = BREAKERS.lock().unwrap();
+    if let Some(breaker) = breakers.get_mut(extension_id) {
+        if breaker.open_until.is_some() {
+            log::info!("Extension {} recovered, closing its circuit breaker", extension_id);
+        }
+        breaker.record_success();
+    }
+}
+
+pub fn record_failure(extension_id: &str, error: &str) {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(extension_id.to_string()).or_default();
+    let now = Instant::now();
+    let was_open = breaker.state(now) == BreakerState::Open;
+
+    breaker.record_failure(error, now);
+
+    if !was_open && breaker.state(now) == BreakerState::Open {
+        log::warn!(
+            "Extension {} tripped its circuit breaker after {} consecutive failures: {}",
+            extension_id, breaker.consecutive_failures, error
+        );
+    }
+}
+
+/// Hand back a half-open probe slot when the call said nothing about the
+/// extension's health
+pub fn release_probe(extension_id: &str) {
+    let mut breakers = BREAKERS.lock().unwrap();
+    if let Some(breaker) = breakers.get_mut(extension_id) {
+        breaker.release_probe();
+    }
+}
+
+/// Record a call's outcome and pass it through. Errors that blame the
+/// request only free the probe slot.
+pub fn track<T, E: fmt::Display>(extension_id: &str, result: Result<T, E>) -> Result<T, E> {
+    match &result {
+        Ok(_) => record_success(extension_id),
+        Err(e) => {
+            let error = e.to_string();
+            if is_permanent_error(&error) {
+                release_probe(extension_id);
+            } else {
+                record_failure(extension_id, &error);
+            }
+        }
+    }
+    result
+}
+
+/// Current breaker state (closed for extensions that never failed)
+pub fn status(extension_id: &str) -> BreakerStatus {
+    let breakers = BREAKERS.lock().unwrap();
+    breakers
+        .get(extension_id)
+        .cloned()
+        .unwrap_or_default()
+        .status(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fail(breaker: &mut Breaker, times: u32, now: Instant) {
+        for _ in 0..times {
+            breaker.record_failure("boom", now);
+        }
+    }
+
+    #[test]
+    fn trips_after_threshold_consecutive_failures() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+
+        fail(&mut breaker, FAILURE_THRESHOLD - 1, now);
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+        assert!(breaker.check("ext", now).is_ok());
+
+        fail(&mut breaker, 1, now);
+        assert_eq!(breaker.state(now), BreakerState::Open);
+
+        let err = breaker.check("ext", now + Duration::from_secs(10)).unwrap_err();
+        assert_eq!(err.extension_id, "ext");
+        assert_eq!(err.retry_after, COOL_DOWN - Duration::from_secs(10));
+        assert_eq!(err.last_error.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+
+        fail(&mut breaker, FAILURE_THRESHOLD - 1, now);
+        breaker.record_success();
+        fail(&mut breaker, FAILURE_THRESHOLD - 1, now);
+
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+        assert_eq!(breaker.consecutive_failures, FAILURE_THRESHOLD - 1);
+    }
+
+    #[test]
+    fn failures_outside_the_window_start_a_new_streak() {
+        let start = Instant::now();
+        let mut breaker = Breaker::default();
+
+        fail(&mut breaker, FAILURE_THRESHOLD - 1, start);
+        let later = start + FAILURE_WINDOW + Duration::from_secs(1);
+        fail(&mut breaker, 1, later);
+
+        assert_eq!(breaker.consecutive_failures, 1);
+        assert_eq!(breaker.state(later), BreakerState::Closed);
+    }
+
+    #[test]
+    fn half_open_probe_closes_on_success_and_reopens_on_failure() {
+        let start = Instant::now();
+        let mut breaker = Breaker::default();
+        fail(&mut breaker, FAILURE_THRESHOLD, start);
+
+        let after_cool_down = start + COOL_DOWN + Duration::from_secs(1);
+        assert_eq!(breaker.state(after_cool_down), BreakerState::HalfOpen);
+        assert!(breaker.check("ext", after_cool_down).is_ok());
+
+        // Failed probe: straight back to open for a full cool-down
+        let mut reopened = breaker.clone();
+        reopened.record_failure("still broken", after_cool_down);
+        assert_eq!(reopened.state(after_cool_down), BreakerState::Open);
+        assert!(reopened.check("ext", after_cool_down + COOL_DOWN - Duration::from_secs(1)).is_err());
+
+        // Successful probe: closed with a clean slate
+        breaker.record_success();
+        assert_eq!(breaker.state(after_cool_down), BreakerState::Closed);
+        assert_eq!(breaker.consecutive_failures, 0);
+        assert!(breaker.last_error.is_none());
+    }
+
+    #[test]
+    fn status_reports_retry_time_only_while_open() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        assert_eq!(breaker.status(now).state, BreakerState::Closed);
+        assert!(breaker.status(now).retry_at.is_none());
+
+        fail(&mut breaker, FAILURE_THRESHOLD, now);
+        let status = breaker.status(now);
+        assert_eq!(status.state, BreakerState::Open);
+        assert_eq!(status.consecutive_failures, FAILURE_THRESHOLD);
+        assert!(status.retry_at.is_some());
+
+        assert!(breaker.status(now + COOL_DOWN).retry_at.is_none());
+    }
+
+    #[test]
+    fn half_open_lets_exactly_one_probe_through() {
+        let start = Instant::now();
+        let mut breaker = Breaker::default();
+        fail(&mut breaker, FAILURE_THRESHOLD, start);
+
+        let after_cool_down = start + COOL_DOWN + Duration::from_secs(1);
+        assert!(breaker.check("ext", after_cool_down).is_ok());
+        let err = breaker.check("ext", after_cool_down).unwrap_err();
+        assert_eq!(err.retry_after, PROBE_TIMEOUT);
+
+        // A probe that never reports back frees the slot eventually
+        let probe_lost = after_cool_down + PROBE_TIMEOUT;
+        assert!(breaker.check("ext", probe_lost).is_ok());
+        assert!(breaker.check("ext", probe_lost).is_err());
+
+        // Releasing without an outcome lets the next call probe
+        breaker.release_probe();
+        assert!(breaker.check("ext", probe_lost).is_ok());
+        assert_eq!(breaker.state(probe_lost), BreakerState::HalfOpen);
+    }
+
+    #[test]
+    fn request_errors_do_not_count_towards_tripping() {
+        assert!(is_permanent_error("Anime Not Found"));
+        assert!(is_permanent_error("HTTP 404 for https://example.test"));
+        assert!(is_permanent_error("Invalid query"));
+        assert!(!is_permanent_error("connection reset by peer"));
+
+        let id = "test.circuit-breaker.permanent";
+        for _ in 0..FAILURE_THRESHOLD * 2 {
+            let _ = track::<(), _>(id, Err("anime not found"));
+        }
+        assert!(check(id).is_ok());
+        assert_eq!(status(id).consecutive_failures, 0);
+    }
+
+    #[test]
+    fn global_track_trips_and_health_success_resets() {
+        let id = "test.circuit-breaker.global";
+        for _ in 0..FAILURE_THRESHOLD {
+            let _ = track::<(), _>(id, Err("network down"));
+        }
+        assert!(check(id).is_err());
+        assert_eq!(status(id).state, BreakerState::Open);
+
+        record_success(id);
+        assert!(check(id).is_ok());
+        assert_eq!(status(id).state, BreakerState::Closed);
+    }
+}
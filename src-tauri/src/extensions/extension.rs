@@ -42,7 +42,10 @@ impl Extension {
         let version_re = Regex::new(r#"version:\s*["']([^"']+)["']"#)?;
         let type_re = Regex::new(r#"type:\s*["']([^"']+)["']"#)?;
         let lang_re = Regex::new(r#"language:\s*["']([^"']+)["']"#)?;
+        let langs_re = Regex::new(r#"languages:\s*\[([^\]]*)\]"#)?;
+        let quoted_re = Regex::new(r#"["']([^"']+)["']"#)?;
         let url_re = Regex::new(r#"baseUrl:\s*["']([^"']+)["']"#)?;
+        let icon_re = Regex::new(r#"\biconUrl:\s*["']([^"']+)["']"#)?;
 
         let id = id_re
             .captures(code)
@@ -79,19 +82,42 @@ impl Extension {
             .map(|m| m.as_str().to_string())
             .unwrap_or_else(|| "en".to_string());
 
+        // Optional list of content languages, e.g. languages: ["en-sub", "en-dub"]
+        let mut languages: Vec<String> = langs_re
+            .captures(code)
+            .and_then(|c| c.get(1))
+            .map(|m| {
+                quoted_re
+                    .captures_iter(m.as_str())
+                    .filter_map(|c| c.get(1))
+                    .map(|l| l.as_str().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if languages.is_empty() {
+            languages.push(language.clone());
+        }
+
         let base_url = url_re
             .captures(code)
             .and_then(|c| c.get(1))
             .map(|m| m.as_str().to_string())
             .ok_or_else(|| anyhow!("Missing baseUrl"))?;
 
+        let icon_url = icon_re
+            .captures(code)
+            .and_then(|c| c.get(1))
+            .map(|m| m.as_str().to_string());
+
         Ok(ExtensionMetadata {
             id,
             name,
             version,
             extension_type,
             language,
+            languages,
             base_url,
+            icon_url,
         })
     }
 
@@ -143,7 +169,9 @@ mod tests {
                 version: "1.0.0".to_string(),
                 extension_type: ExtensionType::Anime,
                 language: "en".to_string(),
+                languages: vec!["en".to_string()],
                 base_url: "https://example.com".to_string(),
+                icon_url: None,
             },
             code: String::new(),
             allowed_domains: vec!["example.com".to_string()],
@@ -153,4 +181,50 @@ mod tests {
         assert!(ext.is_url_allowed("https://www.example.com/data"));
         assert!(!ext.is_url_allowed("https://evil.com/phishing"));
     }
+
+    #[test]
+    fn test_languages_metadata() {
+        let declared = Extension::from_code(r#"
+            const extension = {
+                id: "com.example.multi",
+                name: "Multi",
+                language: "en",
+                languages: ["en-sub", 'en-dub', "ja"],
+                baseUrl: "https://example.com",
+            };
+        "#).unwrap();
+        assert_eq!(declared.metadata.language, "en");
+        assert_eq!(declared.metadata.languages, vec!["en-sub", "en-dub", "ja"]);
+
+        let undeclared = Extension::from_code(r#"
+            const extension = { id: "com.example.single", name: "Single", language: "es", baseUrl: "https://example.com" };
+        "#).unwrap();
+        assert_eq!(undeclared.metadata.languages, vec!["es"]);
+    }
+
+    #[test]
+    fn stored_metadata_without_languages_falls_back_to_language() {
+        let metadata: ExtensionMetadata = serde_json::from_value(serde_json::json!({
+            "id": "com.example.single",
+            "name": "Single",
+            "version": "1.0.0",
+            "type": "anime",
+            "language": "es",
+            "baseUrl": "https://example.com",
+        }))
+        .unwrap();
+        assert_eq!(metadata.languages, vec!["es"]);
+
+        let metadata: ExtensionMetadata = serde_json::from_value(serde_json::json!({
+            "id": "com.example.multi",
+            "name": "Multi",
+            "version": "1.0.0",
+            "type": "anime",
+            "language": "en",
+            "languages": ["en-sub", "ja"],
+            "base_url": "https://example.com",
+        }))
+        .unwrap();
+        assert_eq!(metadata.languages, vec!["en-sub", "ja"]);
+    }
 }
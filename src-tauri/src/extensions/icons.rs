@@ -0,0 +1,249 @@
+// Extension Icons
+//
+// Extensions may declare an iconUrl. Hot-linking it from the webview breaks
+// offline and sends a third-party request every time the extension list is
+// shown, so the icon is downloaded once through the image proxy when the
+// extension is loaded and kept at app_dir/extensions/icons/{id}.png. The video
+// server serves that directory and list_extensions hands out the local URL.
+// A small {id}.json next to the icon records which version and URL it was
+// fetched for, so it's only fetched again when the extension is updated.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::types::ExtensionMetadata;
+use crate::request_headers::build_image_request;
+
+/// Icons larger than this are rejected
+pub const MAX_ICON_BYTES: u64 = 256 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a cached icon was fetched for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IconSource {
+    version: String,
+    icon_url: String,
+}
+
+/// Directory holding the cached icons
+pub fn icons_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("extensions").join("icons")
+}
+
+/// Extension ids are reverse domain names; anything else is replaced so an id
+/// can't point outside the icons directory
+fn file_stem(extension_id: &str) -> String {
+    extension_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+/// File name of an extension's icon inside the icons directory
+pub fn icon_file_name(extension_id: &str) -> String {
+    format!("{}.png", file_stem(extension_id))
+}
+
+fn source_path(dir: &Path, extension_id: &str) -> PathBuf {
+    dir.join(format!("{}.json", file_stem(extension_id)))
+}
+
+/// Whether an extension has a cached icon
+pub fn has_icon(dir: &Path, extension_id: &str) -> bool {
+    dir.join(icon_file_name(extension_id)).is_file()
+}
+
+/// Image type from the file's first bytes. SVG is deliberately not accepted:
+/// it can carry scripts, and icons are served from the local server's origin.
+pub fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else if bytes.starts_with(b"\x00\x00\x01\x00") {
+        Some("ico")
+    } else {
+        None
+    }
+}
+
+/// Check a downloaded icon's size and type
+pub fn validate_icon(bytes: &[u8]) -> Result<&'static str, String> {
+    if bytes.is_empty() {
+        return Err("Icon is empty".to_string());
+    }
+    if bytes.len() as u64 > MAX_ICON_BYTES {
+        return Err(format!("Icon is larger than {} KB", MAX_ICON_BYTES / 1024));
+    }
+    sniff_image_type(bytes).ok_or_else(|| "Icon is not a PNG, JPEG, GIF, WebP or ICO image".to_string())
+}
+
+fn download_icon(url: &str) -> Result<Vec<u8>, String> {
+    let response = build_image_request(url)?
+        .timeout(FETCH_TIMEOUT)
+        .call()
+        .map_err(|e| format!("Failed to fetch icon: {}", e))?;
+
+    if let Some(length) = response.header("Content-Length").and_then(|l| l.parse::<u64>().ok()) {
+        if length > MAX_ICON_BYTES {
+            return Err(format!("Icon is larger than {} KB", MAX_ICON_BYTES / 1024));
+        }
+    }
+
+    // One byte over the limit is enough to tell it's too big
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_ICON_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read icon: {}", e))?;
+
+    Ok(bytes)
+}
+
+/// Write a validated icon and record what it was fetched for
+fn store_icon(dir: &Path, metadata: &ExtensionMetadata, icon_url: &str, bytes: &[u8]) -> Result<(), String> {
+    validate_icon(bytes)?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create icons directory: {}", e))?;
+
+    let path = dir.join(icon_file_name(&metadata.id));
+    let tmp = path.with_extension("png.tmp");
+    std::fs::write(&tmp, bytes)
+        .and_then(|_| std::fs::rename(&tmp, &path))
+        .map_err(|e| format!("Failed to save icon: {}", e))?;
+
+    let source = IconSource {
+        version: metadata.version.clone(),
+        icon_url: icon_url.to_string(),
+    };
+    let json = serde_json::to_vec(&source).map_err(|e| e.to_string())?;
+    std::fs::write(source_path(dir, &metadata.id), json).map_err(|e| format!("Failed to save icon: {}", e))
+}
+
+/// Whether the cached icon is missing or was fetched for another version or URL
+fn needs_refresh(dir: &Path, metadata: &ExtensionMetadata, icon_url: &str) -> bool {
+    if !has_icon(dir, &metadata.id) {
+        return true;
+    }
+
+    let cached: Option<IconSource> = std::fs::read(source_path(dir, &metadata.id))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+    cached.as_ref().map(|c| (c.version.as_str(), c.icon_url.as_str()))
+        != Some((metadata.version.as_str(), icon_url))
+}
+
+/// Bring an extension's cached icon up to date: fetch it when missing or the
+/// extension changed, and drop it when the extension no longer declares one.
+/// Returns whether anything changed. Blocking (network and disk).
+pub fn sync_icon(dir: &Path, metadata: &ExtensionMetadata) -> Result<bool, String> {
+    let Some(icon_url) = metadata.icon_url.as_deref().filter(|url| !url.trim().is_empty()) else {
+        return Ok(remove_icon(dir, &metadata.id));
+    };
+
+    if !needs_refresh(dir, metadata, icon_url) {
+        return Ok(false);
+    }
+
+    let bytes = download_icon(icon_url)?;
+    store_icon(dir, metadata, icon_url, &bytes)?;
+    log::debug!("Cached icon for extension {}", metadata.id);
+    Ok(true)
+}
+
+/// Delete an extension's cached icon, returning whether there was one
+pub fn remove_icon(dir: &Path, extension_id: &str) -> bool {
+    let removed = std::fs::remove_file(dir.join(icon_file_name(extension_id))).is_ok();
+    let _ = std::fs::remove_file(source_path(dir, extension_id));
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::types::ExtensionType;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+
+    fn metadata(version: &str, icon_url: Option<&str>) -> ExtensionMetadata {
+        ExtensionMetadata {
+            id: "com.example.source".to_string(),
+            name: "Example".to_string(),
+            version: version.to_string(),
+            extension_type: ExtensionType::Anime,
+            language: "en".to_string(),
+            languages: vec!["en".to_string()],
+            base_url: "https://example.com".to_string(),
+            icon_url: icon_url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn icon_names_stay_inside_the_directory() {
+        assert_eq!(icon_file_name("com.allanime.source"), "com.allanime.source.png");
+        assert_eq!(icon_file_name("../../etc/passwd"), "_.._etc_passwd.png");
+        assert_eq!(icon_file_name("a/b\\c"), "a_b_c.png");
+    }
+
+    #[test]
+    fn only_small_raster_images_are_accepted() {
+        assert_eq!(validate_icon(PNG), Ok("png"));
+        assert_eq!(validate_icon(b"\xff\xd8\xff\xe0rest"), Ok("jpeg"));
+        assert_eq!(validate_icon(b"RIFF\x00\x00\x00\x00WEBPVP8 "), Ok("webp"));
+
+        assert!(validate_icon(b"").is_err());
+        assert!(validate_icon(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><script/></svg>").is_err());
+        assert!(validate_icon(b"<!DOCTYPE html><html>").is_err());
+
+        let mut huge = PNG.to_vec();
+        huge.resize(MAX_ICON_BYTES as usize + 1, 0);
+        assert!(validate_icon(&huge).unwrap_err().contains("larger"));
+    }
+
+    #[test]
+    fn icons_are_refetched_only_when_the_extension_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = icons_dir(temp_dir.path());
+        let url = "https://example.com/icon.png";
+        let v1 = metadata("1.0.0", Some(url));
+
+        assert!(needs_refresh(&dir, &v1, url));
+        store_icon(&dir, &v1, url, PNG).unwrap();
+        assert!(has_icon(&dir, &v1.id));
+        assert!(!needs_refresh(&dir, &v1, url));
+
+        // Updated extension, or a new icon URL
+        assert!(needs_refresh(&dir, &metadata("1.1.0", Some(url)), url));
+        assert!(needs_refresh(&dir, &v1, "https://example.com/new-icon.png"));
+
+        // Invalid downloads never replace the cached icon
+        assert!(store_icon(&dir, &v1, url, b"<html>").is_err());
+        assert_eq!(std::fs::read(dir.join(icon_file_name(&v1.id))).unwrap(), PNG);
+    }
+
+    #[test]
+    fn icon_is_dropped_when_undeclared_or_uninstalled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = icons_dir(temp_dir.path());
+        let url = "https://example.com/icon.png";
+
+        store_icon(&dir, &metadata("1.0.0", Some(url)), url, PNG).unwrap();
+        assert_eq!(sync_icon(&dir, &metadata("2.0.0", None)), Ok(true));
+        assert!(!has_icon(&dir, "com.example.source"));
+
+        store_icon(&dir, &metadata("1.0.0", Some(url)), url, PNG).unwrap();
+        assert!(remove_icon(&dir, "com.example.source"));
+        assert!(!remove_icon(&dir, "com.example.source"));
+        assert!(!source_path(&dir, "com.example.source").exists());
+    }
+}
@@ -0,0 +1,138 @@
+// Content Language Preference
+//
+// Multi-language sources mix subbed, dubbed and raw entries. Extensions can tag
+// search results and video sources with a language (e.g. "en", "ja-sub",
+// "en-dub"); when the user has a preferred content language, matching items
+// are moved to the front and every tagged item is annotated with whether it
+// matched. Nothing is dropped, and untagged items are left alone.
+
+use super::types::{SearchResult, VideoSource};
+
+/// app_settings key holding the preferred content language
+pub const PREFERRED_LANGUAGE_SETTING: &str = "preferred_content_language";
+
+/// Items that can carry an extension-provided language tag
+pub trait LanguageTagged {
+    fn language(&self) -> Option<&str>;
+    fn set_language_match(&mut self, matches: bool);
+}
+
+impl LanguageTagged for SearchResult {
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn set_language_match(&mut self, matches: bool) {
+        self.language_match = Some(matches);
+    }
+}
+
+impl LanguageTagged for VideoSource {
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn set_language_match(&mut self, matches: bool) {
+        self.language_match = Some(matches);
+    }
+}
+
+/// Check a language tag against the preference.
+///
+/// Comparison is case-insensitive and prefix-aware on `-` boundaries, so a
+/// preference of "en" matches "en", "en-US" and "en-dub", while "en-dub" only
+/// matches "en-dub".
+pub fn language_matches(tag: &str, preferred: &str) -> bool {
+    let tag = tag.trim().to_lowercase();
+    let preferred = preferred.trim().to_lowercase();
+
+    if preferred.is_empty() {
+        return false;
+    }
+
+    tag == preferred || tag.starts_with(&format!("{}-", preferred))
+}
+
+/// Annotate and reorder items for the preferred language.
+///
+/// Order becomes: matches, then untagged items, then non-matching items,
+/// keeping the extension's original order within each group. With no
+/// preference set the items are returned untouched.
+pub fn apply_language_preference<T: LanguageTagged>(items: &mut Vec<T>, preferred: Option<&str>) {
+    let preferred = match preferred.map(str::trim) {
+        Some(p) if !p.is_empty() => p,
+        _ => return,
+    };
+
+    for item in items.iter_mut() {
+        if let Some(matches) = item.language().map(|tag| language_matches(tag, preferred)) {
+            item.set_language_match(matches);
+        }
+    }
+
+    // sort_by_key is stable, so each group keeps the extension's ranking
+    items.sort_by_key(|item| match item.language() {
+        Some(tag) if language_matches(tag, preferred) => 0,
+        None => 1,
+        Some(_) => 2,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(server: &str, language: Option<&str>) -> VideoSource {
+        VideoSource {
+            url: format!("https://example.com/{}.m3u8", server),
+            quality: "1080p".to_string(),
+            source_type: "hls".to_string(),
+            server: server.to_string(),
+            resolution: None,
+            referrer: None,
+            headers: Default::default(),
+            subtitles: Vec::new(),
+            language: language.map(str::to_string),
+            language_match: None,
+        }
+    }
+
+    #[test]
+    fn test_language_matches() {
+        assert!(language_matches("en", "en"));
+        assert!(language_matches("EN-dub", "en"));
+        assert!(language_matches("en-dub", "en-dub"));
+        assert!(!language_matches("en-sub", "en-dub"));
+        assert!(!language_matches("eng", "en"));
+        assert!(!language_matches("ja", ""));
+    }
+
+    #[test]
+    fn test_preferred_first_and_annotated() {
+        let mut sources = vec![
+            source("raw", Some("ja")),
+            source("untagged", None),
+            source("dub", Some("en-dub")),
+            source("sub", Some("en-sub")),
+        ];
+
+        apply_language_preference(&mut sources, Some("en"));
+
+        let order: Vec<&str> = sources.iter().map(|s| s.server.as_str()).collect();
+        assert_eq!(order, vec!["dub", "sub", "untagged", "raw"]);
+        assert_eq!(sources[0].language_match, Some(true));
+        assert_eq!(sources[2].language_match, None);
+        assert_eq!(sources[3].language_match, Some(false));
+    }
+
+    #[test]
+    fn test_no_preference_keeps_order() {
+        let mut sources = vec![source("raw", Some("ja")), source("dub", Some("en-dub"))];
+
+        apply_language_preference(&mut sources, None);
+        apply_language_preference(&mut sources, Some("  "));
+
+        assert_eq!(sources[0].server, "raw");
+        assert!(sources.iter().all(|s| s.language_match.is_none()));
+    }
+}
@@ -0,0 +1,241 @@
+// Extension Resource Limits
+//
+// A buggy or malicious extension could otherwise allocate until the app runs
+// out of memory long before anything times out. Every runtime gets a heap
+// limit (extension_memory_limit_mb, 64 MB by default) and a fixed stack
+// limit. Running out inside a call surfaces as ExtensionMemoryExceeded, and
+// results are capped in size before they're parsed as JSON. Runtimes
+// allocate through TrackingAllocator, so the request log gets the most heap
+// each call held, not just what was left once it returned.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rquickjs::allocator::{Allocator, RustAllocator};
+use sqlx::SqlitePool;
+
+/// app_settings key: heap limit of each extension runtime, in megabytes
+pub const MEMORY_LIMIT_SETTING: &str = "extension_memory_limit_mb";
+
+pub const DEFAULT_MEMORY_LIMIT_MB: usize = 64;
+
+/// Smallest and largest limits accepted from settings
+const MIN_MEMORY_LIMIT_MB: usize = 16;
+const MAX_MEMORY_LIMIT_MB: usize = 1024;
+
+/// Stack size limit of each runtime
+pub const STACK_SIZE: usize = 1024 * 1024;
+
+/// Longest JSON string an extension call may return
+pub const MAX_RESULT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Current heap limit in megabytes
+static MEMORY_LIMIT_MB: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_LIMIT_MB);
+
+/// An extension call ran out of heap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionMemoryExceeded {
+    pub extension_id: String,
+    pub method: String,
+    pub limit_bytes: usize,
+}
+
+impl fmt::Display for ExtensionMemoryExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Extension {} exceeded its memory limit of {} MB in {}",
+            self.extension_id,
+            self.limit_bytes / (1024 * 1024),
+            self.method
+        )
+    }
+}
+
+impl std::error::Error for ExtensionMemoryExceeded {}
+
+/// An extension call returned more than MAX_RESULT_BYTES of JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionResultTooLarge {
+    pub size: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for ExtensionResultTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extension returned {} bytes, more than the {} byte limit", self.size, self.limit)
+    }
+}
+
+impl std::error::Error for ExtensionResultTooLarge {}
+
+fn clamp_memory_limit_mb(megabytes: usize) -> usize {
+    megabytes.clamp(MIN_MEMORY_LIMIT_MB, MAX_MEMORY_LIMIT_MB)
+}
+
+/// Heap limit of new runtimes in megabytes
+pub fn memory_limit_mb() -> usize {
+    MEMORY_LIMIT_MB.load(Ordering::SeqCst)
+}
+
+/// Heap limit of new runtimes in bytes
+pub fn memory_limit_bytes() -> usize {
+    memory_limit_mb() * 1024 * 1024
+}
+
+/// Change the heap limit of runtimes created from now on, returning the
+/// (clamped) value applied
+pub fn set_memory_limit_mb(megabytes: usize) -> usize {
+    let megabytes = clamp_memory_limit_mb(megabytes);
+    MEMORY_LIMIT_MB.store(megabytes, Ordering::SeqCst);
+    megabytes
+}
+
+/// The stored limit, or the default when unset
+pub async fn load_memory_limit_setting(pool: &SqlitePool) -> usize {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(MEMORY_LIMIT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+    value
+        .and_then(|v| v.trim().parse().ok())
+        .map(clamp_memory_limit_mb)
+        .unwrap_or(DEFAULT_MEMORY_LIMIT_MB)
+}
+
+/// Reject results too large to parse
+pub fn check_result_size(size: usize) -> Result<(), ExtensionResultTooLarge> {
+    if size > MAX_RESULT_BYTES {
+        return Err(ExtensionResultTooLarge { size, limit: MAX_RESULT_BYTES });
+    }
+    Ok(())
+}
+
+/// Heap allocated by one runtime, and the most it has held since the last
+/// reset_peak
+#[derive(Debug, Default)]
+pub struct HeapUsage {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl HeapUsage {
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Start measuring the peak again from what's allocated now
+    pub fn reset_peak(&self) {
+        self.peak.store(self.current(), Ordering::Relaxed);
+    }
+
+    fn add(&self, bytes: usize) {
+        let current = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn remove(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Rust's allocator, counting what a runtime allocates in its HeapUsage.
+/// The heap limit is still enforced by QuickJS before it gets here.
+pub struct TrackingAllocator {
+    usage: Arc<HeapUsage>,
+}
+
+impl TrackingAllocator {
+    pub fn new(usage: Arc<HeapUsage>) -> Self {
+        Self { usage }
+    }
+
+    fn allocated(&self, ptr: *mut u8) -> *mut u8 {
+        if !ptr.is_null() {
+            // SAFETY: ptr was just returned by RustAllocator
+            self.usage.add(unsafe { RustAllocator::usable_size(ptr) });
+        }
+        ptr
+    }
+}
+
+// SAFETY: every call is forwarded to RustAllocator, which upholds the
+// contract; only the bookkeeping is added
+unsafe impl Allocator for TrackingAllocator {
+    fn alloc(&mut self, size: usize) -> *mut u8 {
+        let ptr = RustAllocator.alloc(size);
+        self.allocated(ptr)
+    }
+
+    fn calloc(&mut self, count: usize, size: usize) -> *mut u8 {
+        let ptr = RustAllocator.calloc(count, size);
+        self.allocated(ptr)
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8) {
+        self.usage.remove(RustAllocator::usable_size(ptr));
+        RustAllocator.dealloc(ptr);
+    }
+
+    unsafe fn realloc(&mut self, ptr: *mut u8, new_size: usize) -> *mut u8 {
+        let old_size = RustAllocator::usable_size(ptr);
+        let new_ptr = RustAllocator.realloc(ptr, new_size);
+        // A failed realloc leaves the old block in place
+        if !new_ptr.is_null() {
+            self.usage.remove(old_size);
+        }
+        self.allocated(new_ptr)
+    }
+
+    unsafe fn usable_size(ptr: *mut u8) -> usize {
+        RustAllocator::usable_size(ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_limit_is_clamped() {
+        assert_eq!(clamp_memory_limit_mb(1), MIN_MEMORY_LIMIT_MB);
+        assert_eq!(clamp_memory_limit_mb(128), 128);
+        assert_eq!(clamp_memory_limit_mb(1 << 20), MAX_MEMORY_LIMIT_MB);
+    }
+
+    #[test]
+    fn heap_usage_keeps_the_peak_until_reset() {
+        let usage = Arc::new(HeapUsage::default());
+        let mut allocator = TrackingAllocator::new(usage.clone());
+
+        let small = allocator.alloc(64);
+        let large = allocator.alloc(4096);
+        let held = usage.current();
+        assert!(held >= 64 + 4096);
+        unsafe { allocator.dealloc(large) };
+        assert!(usage.current() < held);
+        assert_eq!(usage.peak(), held);
+
+        usage.reset_peak();
+        assert_eq!(usage.peak(), usage.current());
+        let grown = unsafe { allocator.realloc(small, 1024) };
+        assert!(usage.current() >= 1024);
+        unsafe { allocator.dealloc(grown) };
+        assert_eq!(usage.current(), 0);
+        assert!(usage.peak() >= 1024);
+    }
+
+    #[test]
+    fn oversized_results_are_rejected() {
+        assert!(check_result_size(MAX_RESULT_BYTES).is_ok());
+        let err = check_result_size(MAX_RESULT_BYTES + 1).unwrap_err();
+        assert_eq!(err.size, MAX_RESULT_BYTES + 1);
+    }
+}
@@ -3,10 +3,24 @@
 // Handles:
 // - Extension loading and management
 // - JavaScript sandboxing with QuickJS
+// - Memory, stack and result size limits per runtime (limits.rs)
+// - Log of recent calls per extension (request_log.rs)
 // - Domain whitelisting and URL validation
-// - Extension API interface
+// - Extension API interface, and its schema for extension authors (api_schema.rs)
+// - Extensions bundled with the app (bundled.rs)
+// - Adult content mode and the NSFW filter override (adult.rs)
+// - Hourly budget of background calls per extension (background_budget.rs)
 
+pub mod adult;
+pub mod api_schema;
+pub mod background_budget;
+pub mod bundled;
+pub mod circuit_breaker;
 pub mod extension;
+pub mod icons;
+pub mod language;
+pub mod limits;
+pub mod request_log;
 pub mod runtime;
 pub mod sandbox;
 pub mod types;
@@ -15,3 +29,24 @@ pub mod types;
 pub use extension::Extension;
 pub use runtime::ExtensionRuntime;
 pub use types::*;
+
+use serde::Serialize;
+use std::fmt;
+
+/// Errors from looking up a loaded extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ExtensionError {
+    /// No extension with this id is loaded
+    NotFound(String),
+}
+
+impl fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtensionError::NotFound(id) => write!(f, "Extension not found: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionError {}
@@ -0,0 +1,74 @@
+// Extension Request Log
+//
+// The last calls into each extension with how long they took and how much
+// memory the runtime held, so extension authors can see their footprint.
+// Kept in memory only; the log starts empty on every launch.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Calls kept per extension
+const MAX_ENTRIES: usize = 50;
+
+static LOG: LazyLock<Mutex<HashMap<String, VecDeque<ExtensionCall>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionCall {
+    /// Extension method called, e.g. "search" or "getSources"
+    pub method: String,
+    /// Unix timestamp (ms) when the call finished
+    pub at: i64,
+    pub duration_ms: u64,
+    /// Most heap the runtime held during the call
+    pub peak_memory_bytes: u64,
+    pub error: Option<String>,
+}
+
+/// Add a finished call to the extension's log
+pub fn record(extension_id: &str, method: &str, duration: Duration, peak_memory_bytes: u64, error: Option<String>) {
+    let call = ExtensionCall {
+        method: method.to_string(),
+        at: chrono::Utc::now().timestamp_millis(),
+        duration_ms: duration.as_millis() as u64,
+        peak_memory_bytes,
+        error,
+    };
+
+    let mut log = LOG.lock().unwrap();
+    let calls = log.entry(extension_id.to_string()).or_default();
+    if calls.len() >= MAX_ENTRIES {
+        calls.pop_front();
+    }
+    calls.push_back(call);
+}
+
+/// The extension's logged calls, newest first
+pub fn calls(extension_id: &str) -> Vec<ExtensionCall> {
+    LOG.lock()
+        .unwrap()
+        .get(extension_id)
+        .map(|calls| calls.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_calls_newest_first() {
+        let id = "test.request-log.latest";
+        for i in 0..(MAX_ENTRIES + 5) {
+            record(id, &format!("call{}", i), Duration::from_millis(1), i as u64, None);
+        }
+
+        let calls = calls(id);
+        assert_eq!(calls.len(), MAX_ENTRIES);
+        assert_eq!(calls[0].method, format!("call{}", MAX_ENTRIES + 4));
+        assert_eq!(calls.last().unwrap().method, "call5");
+    }
+}
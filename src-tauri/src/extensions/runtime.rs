@@ -4,8 +4,11 @@
 // - Isolated context per extension
 // - Removed dangerous globals
 // - Safe HTTP fetch wrapper with domain validation
+// - Heap and stack limits, with each call's memory in the request log
 
 use super::extension::Extension;
+use super::limits::{self, ExtensionMemoryExceeded};
+use super::request_log;
 use super::types::{ChapterImages, ExtensionMetadata, HomeCategory, HomeContent, MangaDetails, MediaDetails, SearchResult, SearchResults, SeasonResults, TagsResult, VideoSources};
 use aes_gcm::{aead::Aead, Aes256Gcm, Key, KeyInit, Nonce};
 use anyhow::{anyhow, Result};
@@ -14,6 +17,7 @@ use rquickjs::{CatchResultExt, CaughtError, Context, Runtime};
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Instant;
 
 // AllAnime encryption key seed. SHA-256'd directly (no reversal) to produce
 // the AES-256-GCM key. Extracted from AllAnime's own browser JS bundle.
@@ -137,6 +141,9 @@ pub struct ExtensionRuntime {
     #[allow(dead_code)]
     runtime: Runtime,
     context: Context,
+    memory_limit: usize,
+    /// What the runtime's allocator has handed out
+    heap: Arc<limits::HeapUsage>,
 }
 
 impl ExtensionRuntime {
@@ -147,13 +154,23 @@ impl ExtensionRuntime {
 
     /// Create a new runtime with options
     pub fn with_options(extension: Extension, allow_adult: bool) -> Result<Self> {
-        let runtime = Runtime::new()?;
+        Self::with_memory_limit(extension, allow_adult, limits::memory_limit_bytes())
+    }
+
+    /// Create a new runtime whose heap is capped at `memory_limit` bytes
+    pub fn with_memory_limit(extension: Extension, allow_adult: bool, memory_limit: usize) -> Result<Self> {
+        let heap = Arc::new(limits::HeapUsage::default());
+        let runtime = Runtime::new_with_alloc(limits::TrackingAllocator::new(heap.clone()))?;
+        runtime.set_memory_limit(memory_limit);
+        runtime.set_max_stack_size(limits::STACK_SIZE);
         let context = Context::full(&runtime)?;
 
         let ext_runtime = Self {
             extension: Arc::new(extension),
             runtime,
             context,
+            memory_limit,
+            heap,
         };
 
         // Initialize the sandbox with options
@@ -256,10 +273,12 @@ impl ExtensionRuntime {
 
                 // Execute request (send body for POST, call() for GET)
                 // Use send_bytes to preserve the Content-Type header set by the extension
-                // (send_string overrides Content-Type to text/plain, which breaks JSON APIs)
+                // (send_string overrides Content-Type to text/plain, which breaks JSON APIs).
+                // Only bodiless GETs are retried on 429/503; POSTs may not be idempotent.
                 let result = match effective_body {
                     Some(b) => request.send_bytes(b.as_bytes()),
-                    None => request.call(),
+                    None if effective_method == "POST" => request.call(),
+                    None => crate::http_retry::call_with_retry(crate::http_retry::RetryPolicy::INTERACTIVE, request),
                 };
                 // Extract response from either Ok or Status error
                 // (ureq treats non-2xx as Err, but we want the body for GraphQL error messages)
@@ -335,7 +354,7 @@ impl ExtensionRuntime {
 
     /// Call extension's search method
     pub fn search(&self, query: &str, page: u32) -> Result<SearchResults> {
-        self.context.with(|ctx| {
+        self.tracked("search", || self.context.with(|ctx| {
             // Get the extension object
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
@@ -347,16 +366,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify search result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let search_results: SearchResults = serde_json::from_str(&json_str)?;
 
             Ok(search_results)
-        })
+        }))
     }
 
     /// Call extension's discover method with filters
     pub fn discover(&self, page: u32, sort_type: Option<String>, genres: Vec<String>) -> Result<SearchResults> {
-        self.context.with(|ctx| {
+        self.tracked("discover", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if discover method exists, fallback to search if not
@@ -383,16 +403,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify discover result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let search_results: SearchResults = serde_json::from_str(&json_str)?;
 
             Ok(search_results)
-        })
+        }))
     }
 
     /// Call extension's getCurrentSeason method
     pub fn get_current_season(&self, page: u32) -> Result<SeasonResults> {
-        self.context.with(|ctx| {
+        self.tracked("getCurrentSeason", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if getCurrentSeason method exists
@@ -410,16 +431,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify current season result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let season_results: SeasonResults = serde_json::from_str(&json_str)?;
 
             Ok(season_results)
-        })
+        }))
     }
 
     /// Call extension's getRecommendations method
     pub fn get_recommendations(&self) -> Result<SearchResults> {
-        self.context.with(|ctx| {
+        self.tracked("getRecommendations", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if getRecommendations method exists
@@ -443,16 +465,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify recommendations result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let search_results: SearchResults = serde_json::from_str(&json_str)?;
 
             Ok(search_results)
-        })
+        }))
     }
 
     /// Call extension's getRecentlyUpdated method (anime with new episodes)
     pub fn get_recently_updated(&self, page: u32) -> Result<SearchResults> {
-        self.context.with(|ctx| {
+        self.tracked("getRecentlyUpdated", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if getRecentlyUpdated method exists
@@ -476,16 +499,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify recently updated result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let search_results: SearchResults = serde_json::from_str(&json_str)?;
 
             Ok(search_results)
-        })
+        }))
     }
 
     /// Call extension's getDetails method
     pub fn get_details(&self, id: &str) -> Result<MediaDetails> {
-        self.context.with(|ctx| {
+        self.tracked("getDetails", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
             let fn_obj: rquickjs::Function = ext_obj.get("getDetails")?;
             let result: rquickjs::Value = fn_obj.call((id,))?;
@@ -493,16 +517,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify details"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let details: MediaDetails = serde_json::from_str(&json_str)?;
 
             Ok(details)
-        })
+        }))
     }
 
     /// Call extension's getSources method
     pub fn get_sources(&self, episode_id: &str) -> Result<VideoSources> {
-        self.context.with(|ctx| {
+        self.tracked("getSources", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
             let fn_obj: rquickjs::Function = ext_obj.get("getSources")?;
             let result: rquickjs::Value = fn_obj.call((episode_id,))?;
@@ -510,16 +535,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify sources"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let sources: VideoSources = serde_json::from_str(&json_str)?;
 
             Ok(sources)
-        })
+        }))
     }
 
     /// Call extension's getTags method
     pub fn get_tags(&self, page: u32) -> Result<TagsResult> {
-        self.context.with(|ctx| {
+        self.tracked("getTags", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if getTags method exists
@@ -539,11 +565,59 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify tags result"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let tags_result: TagsResult = serde_json::from_str(&json_str)?;
 
             Ok(tags_result)
-        })
+        }))
+    }
+
+    /// Run one call into the extension, adding it to the request log with
+    /// its duration and memory. Running out of heap becomes
+    /// ExtensionMemoryExceeded.
+    fn tracked<T>(&self, method: &str, call: impl FnOnce() -> Result<T>) -> Result<T> {
+        let started = Instant::now();
+        self.heap.reset_peak();
+        let result = call();
+        let peak = self.heap.peak() as u64;
+
+        let result = result.map_err(|e| {
+            if self.is_out_of_memory(&e) {
+                ExtensionMemoryExceeded {
+                    extension_id: self.extension.metadata.id.clone(),
+                    method: method.to_string(),
+                    limit_bytes: self.memory_limit,
+                }
+                .into()
+            } else {
+                e
+            }
+        });
+
+        request_log::record(
+            &self.extension.metadata.id,
+            method,
+            started.elapsed(),
+            peak,
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        result
+    }
+
+    /// Whether a failed call ran out of heap. QuickJS throws an
+    /// "out of memory" InternalError, which is taken off the context here.
+    fn is_out_of_memory(&self, error: &anyhow::Error) -> bool {
+        match error.downcast_ref::<rquickjs::Error>() {
+            Some(rquickjs::Error::Allocation) => true,
+            Some(rquickjs::Error::Exception) => self.context.with(|ctx| {
+                ctx.catch()
+                    .as_exception()
+                    .and_then(|ex| ex.message())
+                    .is_some_and(|message| message.contains("out of memory"))
+            }),
+            _ => false,
+        }
     }
 
     /// Get extension metadata
@@ -653,7 +727,7 @@ impl ExtensionRuntime {
 
     /// Call extension's getDetails method and return as MangaDetails
     pub fn get_manga_details(&self, id: &str) -> Result<MangaDetails> {
-        self.context.with(|ctx| {
+        self.tracked("getDetails", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
             let fn_obj: rquickjs::Function = ext_obj.get("getDetails")?;
             let result: rquickjs::Value = fn_obj.call((id,))?;
@@ -661,16 +735,17 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify manga details"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let details: MangaDetails = serde_json::from_str(&json_str)?;
 
             Ok(details)
-        })
+        }))
     }
 
     /// Call extension's getChapterImages method
     pub fn get_chapter_images(&self, chapter_id: &str) -> Result<ChapterImages> {
-        self.context.with(|ctx| {
+        self.tracked("getChapterImages", || self.context.with(|ctx| {
             let ext_obj: rquickjs::Object = ctx.eval("extensionObject")?;
 
             // Check if getChapterImages method exists
@@ -690,10 +765,21 @@ impl ExtensionRuntime {
             let json_str: String = ctx.json_stringify(result)?
                 .ok_or_else(|| anyhow!("Failed to stringify chapter images"))?
                 .to_string()?;
+            limits::check_result_size(json_str.len())?;
 
             let chapter_images: ChapterImages = serde_json::from_str(&json_str)?;
 
             Ok(chapter_images)
+        }))
+    }
+
+    /// Call a hook and return its result as plain JSON, for checking it
+    /// against the API schema
+    #[cfg(test)]
+    pub(super) fn call_json(&self, hook: &str, args: &str) -> Result<serde_json::Value> {
+        self.context.with(|ctx| {
+            let json_str: String = ctx.eval(format!("JSON.stringify(extensionObject.{}({}))", hook, args))?;
+            Ok(serde_json::from_str(&json_str)?)
         })
     }
 }
@@ -733,4 +819,67 @@ mod tests {
         // Should not panic - dangerous globals should be removed
         assert!(runtime.is_ok());
     }
+
+    #[test]
+    fn huge_allocation_fails_with_memory_exceeded() {
+        let ext_code = r#"
+            const extensionObject = {
+                id: "test.memory-hog",
+                name: "Memory Hog",
+                version: "1.0.0",
+                type: "anime",
+                language: "en",
+                baseUrl: "https://example.com",
+
+                search: (query, page) => {
+                    const hoard = [];
+                    for (;;) {
+                        hoard.push(new Array(4096).fill(page));
+                    }
+                }
+            };
+        "#;
+
+        let extension = Extension::from_code(ext_code).unwrap();
+        let limit = 16 * 1024 * 1024;
+        let runtime = ExtensionRuntime::with_memory_limit(extension, false, limit).unwrap();
+
+        let err = runtime.search("anything", 1).unwrap_err();
+        let exceeded = err.downcast_ref::<ExtensionMemoryExceeded>().expect("typed memory error");
+        assert_eq!(exceeded.extension_id, "test.memory-hog");
+        assert_eq!(exceeded.method, "search");
+        assert_eq!(exceeded.limit_bytes, limit);
+
+        let calls = request_log::calls("test.memory-hog");
+        assert_eq!(calls[0].method, "search");
+        assert!(calls[0].error.is_some());
+        assert!(calls[0].peak_memory_bytes > 0);
+    }
+
+    #[test]
+    fn calls_log_the_most_heap_they_held() {
+        let ext_code = r#"
+            const extensionObject = {
+                id: "test.heap-peak",
+                name: "Heap Peak",
+                version: "1.0.0",
+                type: "anime",
+                language: "en",
+                search: (query, page) => {
+                    // Held only while the call runs
+                    const scratch = new Array(512 * 1024).fill(page);
+                    return { results: [], hasNextPage: scratch.length === 0 };
+                }
+            };
+        "#;
+
+        let extension = Extension::from_code(ext_code).unwrap();
+        let runtime = ExtensionRuntime::new(extension).unwrap();
+        runtime.search("anything", 1).unwrap();
+
+        let calls = request_log::calls("test.heap-peak");
+        let peak = calls[0].peak_memory_bytes as usize;
+        assert!(peak >= 4 * 1024 * 1024, "peak {peak}");
+        assert!(runtime.heap.current() < peak);
+    }
 }
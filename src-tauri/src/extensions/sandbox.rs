@@ -101,6 +101,8 @@ impl SafeFetch {
         // Execute request
         let response = if let Some(body) = options.body {
             request.send_string(&body)?
+        } else if request.method() == "GET" {
+            crate::http_retry::call_with_retry(crate::http_retry::RetryPolicy::INTERACTIVE, request)?
         } else {
             request.call()?
         };
@@ -3,10 +3,13 @@
 // Defines the core data structures for the extension system including
 // extension metadata, search results, media details, and video sources.
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 /// Extension metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(from = "MetadataFields")]
 pub struct ExtensionMetadata {
     pub id: String,
     pub name: String,
@@ -14,12 +17,58 @@ pub struct ExtensionMetadata {
     #[serde(rename = "type")]
     pub extension_type: ExtensionType,
     pub language: String,
+    /// Content languages the source serves (e.g. ["en-sub", "en-dub", "ja"]).
+    /// Falls back to `[language]` when the extension doesn't declare any.
+    #[serde(default)]
+    pub languages: Vec<String>,
     #[serde(alias = "baseUrl")]
     pub base_url: String,
+    /// Remote icon declared by the extension. The app shows a locally cached
+    /// copy (see extensions::icons), never this URL directly.
+    #[serde(default, alias = "iconUrl", skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+}
+
+/// ExtensionMetadata as stored, before `languages` falls back to `language`
+#[derive(Deserialize)]
+struct MetadataFields {
+    id: String,
+    name: String,
+    version: String,
+    #[serde(rename = "type")]
+    extension_type: ExtensionType,
+    language: String,
+    #[serde(default)]
+    languages: Vec<String>,
+    #[serde(alias = "baseUrl")]
+    base_url: String,
+    #[serde(default, alias = "iconUrl")]
+    icon_url: Option<String>,
+}
+
+impl From<MetadataFields> for ExtensionMetadata {
+    fn from(fields: MetadataFields) -> Self {
+        let languages = if fields.languages.is_empty() {
+            vec![fields.language.clone()]
+        } else {
+            fields.languages
+        };
+
+        Self {
+            id: fields.id,
+            name: fields.name,
+            version: fields.version,
+            extension_type: fields.extension_type,
+            language: fields.language,
+            languages,
+            base_url: fields.base_url,
+            icon_url: fields.icon_url,
+        }
+    }
 }
 
 /// Type of content the extension provides
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ExtensionType {
     Anime,
@@ -27,7 +76,7 @@ pub enum ExtensionType {
 }
 
 /// Episode date information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct EpisodeDate {
     pub year: u32,
     pub month: u32, // 0-indexed (0 = January)
@@ -35,7 +84,7 @@ pub struct EpisodeDate {
 }
 
 /// Search result item
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct SearchResult {
     pub id: String,
     pub title: String,
@@ -80,10 +129,21 @@ pub struct SearchResult {
     /// Broadcast timezone (e.g., "Asia/Tokyo")
     #[serde(default, alias = "broadcastTimezone")]
     pub broadcast_timezone: Option<String>,
+    /// Content language tag set by the extension (e.g., "en-dub", "ja-sub")
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Whether `language` matches the user's preferred content language.
+    /// Only set when a preference exists and the item is tagged.
+    #[serde(default, alias = "languageMatch")]
+    pub language_match: Option<bool>,
+    /// Age rating as the source gives it (e.g. "PG-13 - Teens 13 or older");
+    /// see database::age_rating
+    #[serde(default, alias = "ageRating")]
+    pub age_rating: Option<String>,
 }
 
 /// Paginated search results
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct SearchResults {
     pub results: Vec<SearchResult>,
     #[serde(alias = "hasNextPage")]
@@ -91,7 +151,7 @@ pub struct SearchResults {
 }
 
 /// Season anime results with season info
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct SeasonResults {
     pub results: Vec<SearchResult>,
     #[serde(alias = "hasNextPage")]
@@ -101,7 +161,7 @@ pub struct SeasonResults {
 }
 
 /// Episode information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Episode {
     pub id: String,
     pub number: f32,
@@ -112,14 +172,14 @@ pub struct Episode {
 }
 
 /// Season information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Season {
     pub quarter: Option<String>,
     pub year: Option<u32>,
 }
 
 /// Aired start date
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct AiredStart {
     pub year: u32,
     pub month: Option<u32>,
@@ -127,7 +187,7 @@ pub struct AiredStart {
 }
 
 /// Detailed media information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct MediaDetails {
     pub id: String,
     pub title: String,
@@ -159,6 +219,9 @@ pub struct MediaDetails {
     /// Interval between episodes in milliseconds
     #[serde(alias = "broadcastInterval")]
     pub broadcast_interval: Option<u64>,
+    /// Age rating as the source gives it; see database::age_rating
+    #[serde(default, alias = "ageRating")]
+    pub age_rating: Option<String>,
 }
 
 /// A single playable video source.
@@ -174,9 +237,15 @@ pub struct MediaDetails {
 /// `referrer` is required by some CDNs (wixmp, fast4speed); attach it as
 /// the `Referer` HTTP header when fetching/downloading this source.
 ///
+/// `headers` are any other HTTP headers the source's CDN needs (cookies,
+/// Origin, ...). Downloads store them so they still work after a restart.
+///
 /// `subtitles` are per-source sidecars (distinct from provider-wide
 /// `VideoSources.subtitles`).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `language` is an optional content language tag (e.g. "en-dub"); the
+/// command layer fills `language_match` from the user's preference.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct VideoSource {
     pub url: String,
     pub quality: String,
@@ -187,12 +256,18 @@ pub struct VideoSource {
     pub resolution: Option<u32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub referrer: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub subtitles: Vec<Subtitle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "languageMatch")]
+    pub language_match: Option<bool>,
 }
 
 /// Subtitle track
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Subtitle {
     pub url: String,
     pub language: String,
@@ -200,24 +275,28 @@ pub struct Subtitle {
 }
 
 /// Video sources with subtitles
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct VideoSources {
     pub sources: Vec<VideoSource>,
     pub subtitles: Vec<Subtitle>,
 }
 
 /// Tag/Genre information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Tag {
     pub id: Option<i64>,
     pub name: String,
     pub slug: String,
     pub count: u32,
     pub thumbnail: Option<String>,
+    /// Slugs of other tags of the extension merged into this one by genre
+    /// normalization
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merged_slugs: Vec<String>,
 }
 
 /// Tags result containing genres and studios
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct TagsResult {
     pub genres: Vec<Tag>,
     pub studios: Vec<Tag>,
@@ -228,7 +307,7 @@ pub struct TagsResult {
 // ==================== Home Content Types ====================
 
 /// A category of content for the home page
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct HomeCategory {
     pub id: String,
     pub title: String,
@@ -245,7 +324,7 @@ pub struct HomeContent {
 // ==================== Manga Types ====================
 
 /// Chapter information for manga
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Chapter {
     pub id: String,
     pub number: f32,
@@ -256,16 +335,19 @@ pub struct Chapter {
 }
 
 /// Single page/image in a chapter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ChapterImage {
     pub url: String,
     pub page: u32,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    /// Landscape page spanning two pages (set by the backend from width/height)
+    #[serde(default)]
+    pub is_spread: bool,
 }
 
 /// Collection of images for a chapter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ChapterImages {
     pub images: Vec<ChapterImage>,
     #[serde(alias = "totalPages")]
@@ -274,7 +356,7 @@ pub struct ChapterImages {
 }
 
 /// Manga details with chapters instead of episodes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct MangaDetails {
     pub id: String,
     pub title: String,
@@ -0,0 +1,363 @@
+// Rate-Limit-Aware Retries
+//
+// Source APIs, CDNs and Jikan answer bursts with 429 Too Many Requests or
+// 503 Service Unavailable, usually with a Retry-After header saying when to
+// come back. The app's HTTP clients go through RetryBudget for those
+// two statuses: it waits as long as Retry-After asks (seconds or an HTTP
+// date), or backs off exponentially with jitter when there is no header,
+// and gives up after a bounded number of attempts or total wait.
+//
+// Only idempotent GETs are retried. Each retry is tallied by host, so hosts
+// that keep rate limiting show up in get_http_retry_stats and in the
+// network diagnostics report.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How hard to retry a rate-limited request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubles with each retry
+    pub base_delay: Duration,
+    /// Longest single wait, Retry-After included
+    pub max_delay: Duration,
+    /// Longest total time spent waiting before giving up
+    pub max_total: Duration,
+}
+
+impl RetryPolicy {
+    /// Background requests (downloads, Jikan): patient
+    pub const BACKGROUND: RetryPolicy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+        max_total: Duration::from_secs(90),
+    };
+
+    /// Requests someone is waiting on (player proxy, extension fetches): short
+    pub const INTERACTIVE: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(5),
+        max_total: Duration::from_secs(10),
+    };
+
+    /// Backoff before retry number `retry` (0 for the first), without
+    /// Retry-After: exponential, capped at max_delay, with the upper half
+    /// jittered so clients that were limited together don't return together
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1u32 << retry.min(16)).min(self.max_delay);
+        let half = exponential / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+}
+
+/// Statuses that mean "come back later"
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+/// Parse a Retry-After value: delay seconds or an HTTP date. A date in the
+/// past means "now".
+pub fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Retries left for one request
+#[derive(Debug)]
+pub struct RetryBudget {
+    policy: RetryPolicy,
+    host: Option<String>,
+    started: Instant,
+    attempts: u32,
+    waited: Duration,
+}
+
+impl RetryBudget {
+    pub fn new(policy: RetryPolicy, url: &str) -> Self {
+        Self {
+            policy,
+            host: host_of(url),
+            started: Instant::now(),
+            attempts: 1,
+            waited: Duration::ZERO,
+        }
+    }
+
+    /// How long to wait before retrying a response with `status`, or None
+    /// to give up (not a rate-limit status, or the budget is spent)
+    pub fn next_delay(&mut self, status: u16, retry_after: Option<&str>) -> Option<Duration> {
+        if !is_retryable_status(status) {
+            return None;
+        }
+
+        let delay = retry_after
+            .and_then(|value| parse_retry_after(value, chrono::Utc::now()))
+            .map(|delay| delay.min(self.policy.max_delay))
+            .unwrap_or_else(|| self.policy.backoff(self.attempts - 1));
+
+        let spent = self.waited.max(self.started.elapsed());
+        if self.attempts >= self.policy.max_attempts || spent + delay > self.policy.max_total {
+            if let Some(host) = &self.host {
+                record(host, false);
+            }
+            return None;
+        }
+
+        self.attempts += 1;
+        self.waited += delay;
+        if let Some(host) = &self.host {
+            record(host, true);
+        }
+        Some(delay)
+    }
+}
+
+/// Send a GET built with reqwest, retrying 429/503 within `policy`. A
+/// request that can't be cloned (streamed body) is sent once.
+pub async fn send_with_retry(
+    policy: RetryPolicy,
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    let Some(first) = request.try_clone() else {
+        return request.send().await;
+    };
+    let url = first
+        .build()
+        .map(|r| r.url().to_string())
+        .unwrap_or_default();
+    let mut budget = RetryBudget::new(policy, &url);
+
+    loop {
+        let attempt = request.try_clone().expect("request was cloneable once");
+        let response = attempt.send().await?;
+
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok());
+        let Some(delay) = budget.next_delay(response.status().as_u16(), retry_after) else {
+            return Ok(response);
+        };
+
+        log::warn!("{} returned {}, retrying in {}ms", url, response.status(), delay.as_millis());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Make a GET with ureq (blocking), retrying 429/503 within `policy`
+pub fn call_with_retry(policy: RetryPolicy, request: ureq::Request) -> Result<ureq::Response, ureq::Error> {
+    let url = request.url().to_string();
+    let mut budget = RetryBudget::new(policy, &url);
+
+    loop {
+        match request.clone().call() {
+            Err(ureq::Error::Status(status, response)) => {
+                let Some(delay) = budget.next_delay(status, response.header("retry-after")) else {
+                    return Err(ureq::Error::Status(status, response));
+                };
+                log::warn!("{} returned {}, retrying in {}ms", url, status, delay.as_millis());
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Rate-limit retries seen for one host this run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostRetryStats {
+    pub host: String,
+    /// Requests retried after a 429/503
+    pub retries: u32,
+    /// Requests that were still rate limited when their budget ran out
+    pub gave_up: u32,
+    /// Unix timestamp (ms) of the last retry or give-up
+    pub last_at: i64,
+}
+
+static HOST_RETRIES: LazyLock<Mutex<HashMap<String, HostRetryStats>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn host_of(url: &str) -> Option<String> {
+    url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string))
+}
+
+fn record(host: &str, retried: bool) {
+    let mut hosts = HOST_RETRIES.lock().unwrap();
+    let entry = hosts.entry(host.to_string()).or_insert_with(|| HostRetryStats {
+        host: host.to_string(),
+        retries: 0,
+        gave_up: 0,
+        last_at: 0,
+    });
+    if retried {
+        entry.retries += 1;
+    } else {
+        entry.gave_up += 1;
+    }
+    entry.last_at = chrono::Utc::now().timestamp_millis();
+}
+
+/// Hosts that rate limited us this run, most retries first
+pub fn host_retry_stats() -> Vec<HostRetryStats> {
+    let mut hosts: Vec<HostRetryStats> = HOST_RETRIES.lock().unwrap().values().cloned().collect();
+    hosts.sort_by(|a, b| b.retries.cmp(&a.retries).then_with(|| a.host.cmp(&b.host)));
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const FAST: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(50),
+        max_total: Duration::from_secs(5),
+    };
+
+    /// Serve `script` (status, Retry-After) responses in order, then 200s
+    async fn scripted_server(script: Vec<(u16, Option<&'static str>)>) -> (std::net::SocketAddr, Arc<AtomicU32>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hits = Arc::new(AtomicU32::new(0));
+        let served = hits.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else { return };
+                let mut buf = [0u8; 2048];
+                let _ = socket.read(&mut buf).await;
+
+                let hit = served.fetch_add(1, Ordering::SeqCst) as usize;
+                let (status, retry_after) = script.get(hit).copied().unwrap_or((200, None));
+                let body = if status == 200 { "ok" } else { "slow down" };
+                let retry_after = retry_after.map(|v| format!("Retry-After: {}\r\n", v)).unwrap_or_default();
+                let response = format!(
+                    "HTTP/1.1 {} X\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    retry_after,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (addr, hits)
+    }
+
+    /// Client resolving `host` to the server, so each test's retry stats
+    /// (which are global) land under a host of its own
+    fn client_for(host: &str, addr: std::net::SocketAddr) -> (reqwest::Client, String) {
+        let client = reqwest::Client::builder().resolve(host, addr).build().unwrap();
+        (client, format!("http://{}:{}/resource", host, addr.port()))
+    }
+
+    fn stats_for(url: &str) -> Option<HostRetryStats> {
+        let host = host_of(url).unwrap();
+        host_retry_stats().into_iter().find(|s| s.host == host)
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2026 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2026 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2026 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn backoff_grows_and_stays_within_bounds() {
+        for retry in 0..10 {
+            let cap = FAST.base_delay.saturating_mul(1 << retry).min(FAST.max_delay);
+            let delay = FAST.backoff(retry);
+            assert!(delay >= cap / 2 && delay <= cap, "retry {}: {:?}", retry, delay);
+        }
+    }
+
+    #[test]
+    fn budget_is_bounded_by_attempts_and_total_time() {
+        let mut budget = RetryBudget::new(FAST, "not a url");
+        assert_eq!(budget.next_delay(404, None), None);
+        assert_eq!(budget.next_delay(429, Some("0")), Some(Duration::ZERO));
+        assert_eq!(budget.next_delay(503, Some("0")), Some(Duration::ZERO));
+        assert_eq!(budget.next_delay(429, Some("0")), Some(Duration::ZERO));
+        assert_eq!(budget.next_delay(429, Some("0")), None);
+
+        // Retry-After is capped at max_delay; waits past max_total give up
+        let policy = RetryPolicy {
+            max_total: Duration::from_millis(120),
+            ..FAST
+        };
+        let mut budget = RetryBudget::new(policy, "not a url");
+        assert_eq!(budget.next_delay(429, Some("3600")), Some(Duration::from_millis(50)));
+        assert_eq!(budget.next_delay(429, Some("3600")), Some(Duration::from_millis(50)));
+        assert_eq!(budget.next_delay(429, Some("3600")), None);
+    }
+
+    #[tokio::test]
+    async fn reqwest_requests_retry_429_then_succeed() {
+        let (addr, hits) = scripted_server(vec![(429, Some("0")), (503, None)]).await;
+        let (client, url) = client_for("retry-then-ok.test", addr);
+
+        let response = send_with_retry(FAST, client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        let stats = stats_for(&url).unwrap();
+        assert_eq!((stats.retries, stats.gave_up), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn reqwest_gives_up_with_the_last_response() {
+        let (addr, hits) = scripted_server(vec![(429, Some("0")); 10]).await;
+        let (client, url) = client_for("retry-give-up.test", addr);
+
+        let response = send_with_retry(FAST, client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(hits.load(Ordering::SeqCst), FAST.max_attempts);
+
+        let stats = stats_for(&url).unwrap();
+        assert_eq!((stats.retries, stats.gave_up), (FAST.max_attempts - 1, 1));
+    }
+
+    #[tokio::test]
+    async fn ureq_requests_retry_429_then_succeed() {
+        let (addr, hits) = scripted_server(vec![(429, Some("0"))]).await;
+        let url = format!("http://{}/resource", addr);
+
+        let body = tokio::task::spawn_blocking(move || {
+            call_with_retry(FAST, ureq::get(&url)).unwrap().into_string().unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(body, "ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,5 +1,6 @@
 use super::client::JIKAN;
 use super::types::*;
+use crate::database::media::MediaEntry;
 use crate::extensions::types::{
     AiredStart, Episode, MediaDetails, SearchResult, SearchResults, Season, Tag, TagsResult,
 };
@@ -62,7 +63,7 @@ fn build_trailer_url(trailer: &Option<JikanTrailer>) -> Option<String> {
     None
 }
 
-fn jikan_anime_to_search_result(anime: &JikanAnime) -> SearchResult {
+pub(crate) fn jikan_anime_to_search_result(anime: &JikanAnime) -> SearchResult {
     SearchResult {
         id: anime.mal_id.to_string(),
         title: anime.title.clone(),
@@ -83,6 +84,42 @@ fn jikan_anime_to_search_result(anime: &JikanAnime) -> SearchResult {
         broadcast_day: anime.broadcast.as_ref().and_then(|b| b.day.clone()),
         broadcast_time: anime.broadcast.as_ref().and_then(|b| b.time.clone()),
         broadcast_timezone: anime.broadcast.as_ref().and_then(|b| b.timezone.clone()),
+        language: None,
+        language_match: None,
+        age_rating: anime.rating.clone(),
+    }
+}
+
+/// Media row for a Jikan entry, keyed by its MAL id (as saved for Jikan-sourced anime)
+pub(crate) fn jikan_anime_to_media_entry(anime: &JikanAnime) -> MediaEntry {
+    let result = jikan_anime_to_search_result(anime);
+    let now = chrono::Utc::now().to_rfc3339();
+
+    MediaEntry {
+        id: result.id,
+        extension_id: super::enrichment::JIKAN_SOURCE.to_string(),
+        title: result.title,
+        english_name: anime.title_english.clone(),
+        native_name: anime.title_japanese.clone(),
+        description: result.description,
+        cover_url: result.cover_url,
+        banner_url: None,
+        trailer_url: result.trailer_url,
+        media_type: "anime".to_string(),
+        content_type: result.media_type,
+        status: result.status,
+        year: result.year.map(|y| y as i32),
+        rating: result.rating.map(|r| r as f64),
+        episode_count: anime.episodes,
+        episode_duration: None,
+        season_quarter: anime.season.clone(),
+        season_year: anime.year,
+        aired_start_year: None,
+        aired_start_month: None,
+        aired_start_date: None,
+        genres: result.genres.as_ref().and_then(|g| serde_json::to_string(g).ok()),
+        created_at: now.clone(),
+        updated_at: now,
     }
 }
 
@@ -204,6 +241,7 @@ fn jikan_anime_to_media_details(anime: &JikanAnime, episodes: Vec<Episode>, last
         aired_start,
         last_update_end: compute_last_update_end(anime, &last_aired),
         broadcast_interval: compute_broadcast_interval(anime, &last_aired),
+        age_rating: anime.rating.clone(),
     }
 }
 
@@ -356,6 +394,22 @@ pub fn watch_episodes_popular() -> Result<SearchResults, String> {
     })
 }
 
+/// Fetch the raw Jikan entry for an anime, without episodes.
+/// Used by metadata enrichment, which needs fields MediaDetails doesn't carry
+/// (studios, source material).
+pub fn anime_full(mal_id: i64) -> Result<JikanAnime, String> {
+    let path = format!("/anime/{}/full", mal_id);
+    let response: JikanResponse<JikanAnime> = JIKAN.get_parsed(&path)?;
+    Ok(response.data)
+}
+
+/// Search Jikan and return the raw entries (first page only)
+pub fn search_anime_entries(query: &str) -> Result<Vec<JikanAnime>, String> {
+    let response: JikanPaginatedResponse<JikanAnime> =
+        JIKAN.get_parsed_with_query("/anime", &[("q", query), ("limit", "10")])?;
+    Ok(response.data)
+}
+
 pub fn anime_details(mal_id: i64) -> Result<MediaDetails, String> {
     let path = format!("/anime/{}/full", mal_id);
     let response: JikanResponse<JikanAnime> = JIKAN.get_parsed(&path)?;
@@ -543,6 +597,9 @@ pub fn anime_recommendations(mal_id: i64) -> Result<SearchResults, String> {
                 broadcast_day: None,
                 broadcast_time: None,
                 broadcast_timezone: None,
+                language: None,
+                language_match: None,
+                age_rating: None,
             }
         })
         .collect();
@@ -565,6 +622,7 @@ pub fn genres_anime() -> Result<TagsResult, String> {
             slug: g.name.to_lowercase().replace(' ', "-"),
             count: g.count.unwrap_or(0) as u32,
             thumbnail: None,
+            merged_slugs: Vec::new(),
         })
         .collect();
 
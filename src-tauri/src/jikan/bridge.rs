@@ -125,6 +125,24 @@ pub async fn delete_cached_mapping(pool: &SqlitePool, mal_id: &str) -> Result<()
     Ok(())
 }
 
+/// Delete every cached mapping, returning how many were removed.
+pub async fn clear_cached_mappings(pool: &SqlitePool) -> Result<u64, String> {
+    let result = sqlx::query("DELETE FROM id_mappings")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+/// Number of cached MAL-to-AllAnime mappings.
+pub async fn count_cached_mappings(pool: &SqlitePool) -> Result<u64, String> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM id_mappings")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    Ok(count as u64)
+}
+
 /// Save a MAL-to-AllAnime mapping to the cache.
 pub async fn save_mapping(
     pool: &SqlitePool,
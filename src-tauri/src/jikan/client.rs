@@ -1,19 +1,31 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+use crate::http_retry::{RetryBudget, RetryPolicy};
+
 const JIKAN_BASE_URL: &str = "https://api.jikan.moe/v4";
 const MAX_PER_SECOND: usize = 3;
 const MAX_PER_MINUTE: usize = 60;
 const RETRY_DELAY_MS: u64 = 1000;
 const MAX_RETRIES: u32 = 5;
 const CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+/// How long a prefetched response is served without revalidating
+const PREFETCH_FRESH_SECS: u64 = 10 * 60;
+
+thread_local! {
+    /// Set while a prefetch runs on this thread
+    static PREFETCHING: Cell<bool> = const { Cell::new(false) };
+}
 
 struct CacheEntry {
     etag: String,
     body: String,
     cached_at: Instant,
+    /// Fetched ahead of time; the first regular request uses it as is
+    prefetched: bool,
 }
 
 pub struct JikanClient {
@@ -29,6 +41,49 @@ impl JikanClient {
         }
     }
 
+    /// Drop every cached response, returning how many were removed
+    pub fn clear_cache(&self) -> usize {
+        let mut cache = self.cache.lock().unwrap();
+        let count = cache.len();
+        cache.clear();
+        count
+    }
+
+    /// Drop cached responses past their TTL, returning how many were removed
+    pub fn remove_expired(&self) -> usize {
+        let mut cache = self.cache.lock().unwrap();
+        let before = cache.len();
+        cache.retain(|_, entry| entry.cached_at.elapsed() < Duration::from_secs(CACHE_TTL_SECS));
+        before - cache.len()
+    }
+
+    /// Number of cached responses
+    pub fn cache_len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    /// Run `fetch` (on this thread) with every response it gets marked as
+    /// prefetched: the next regular request for the same URL is answered from
+    /// the cache without a round trip, as long as it comes within
+    /// PREFETCH_FRESH_SECS. Goes through the normal rate limit.
+    pub fn prefetch<T>(&self, fetch: impl FnOnce() -> T) -> T {
+        PREFETCHING.with(|p| p.set(true));
+        let result = fetch();
+        PREFETCHING.with(|p| p.set(false));
+        result
+    }
+
+    /// Hand out a prefetched body once
+    fn take_prefetched(&self, url: &str) -> Option<String> {
+        let mut cache = self.cache.lock().unwrap();
+        let entry = cache.get_mut(url)?;
+        if !entry.prefetched || entry.cached_at.elapsed() >= Duration::from_secs(PREFETCH_FRESH_SECS) {
+            return None;
+        }
+        entry.prefetched = false;
+        Some(entry.body.clone())
+    }
+
     fn wait_for_rate_limit(&self) {
         loop {
             let mut times = self.request_times.lock().unwrap();
@@ -84,6 +139,14 @@ impl JikanClient {
             }
         }
 
+        let prefetching = PREFETCHING.with(Cell::get);
+        if !prefetching {
+            if let Some(body) = self.take_prefetched(&url) {
+                log::debug!("Jikan prefetch hit: {}", url);
+                return Ok(body);
+            }
+        }
+
         // Look up cached ETag for this URL (only if not expired)
         let (cached_etag, cached_body) = {
             let cache = self.cache.lock().unwrap();
@@ -99,6 +162,7 @@ impl JikanClient {
         };
 
         let mut last_error = String::new();
+        let mut rate_limit_budget = RetryBudget::new(RetryPolicy::BACKGROUND, &url);
 
         for attempt in 0..MAX_RETRIES {
             self.wait_for_rate_limit();
@@ -124,6 +188,7 @@ impl JikanClient {
                                 etag,
                                 body: body.clone(),
                                 cached_at: Instant::now(),
+                                prefetched: prefetching,
                             },
                         );
                     }
@@ -132,20 +197,28 @@ impl JikanClient {
                 Err(ureq::Error::Status(304, _)) => {
                     if let Some(ref body) = cached_body {
                         log::debug!("Jikan ETag cache hit: {}", url);
+                        if prefetching {
+                            if let Some(entry) = self.cache.lock().unwrap().get_mut(&url) {
+                                entry.cached_at = Instant::now();
+                                entry.prefetched = true;
+                            }
+                        }
                         return Ok(body.clone());
                     }
                     // No cached body despite 304 — fall through to retry without ETag
                     last_error = "Received 304 but no cached body".to_string();
                 }
-                Err(ureq::Error::Status(429, _)) => {
-                    log::warn!(
-                        "Jikan rate limited, waiting {}ms before retry",
-                        RETRY_DELAY_MS * (attempt as u64 + 1)
-                    );
-                    std::thread::sleep(Duration::from_millis(
-                        RETRY_DELAY_MS * (attempt as u64 + 1),
-                    ));
-                    last_error = "Rate limited by Jikan API".to_string();
+                Err(ureq::Error::Status(code @ (429 | 503), response)) => {
+                    last_error = if code == 429 {
+                        "Rate limited by Jikan API".to_string()
+                    } else {
+                        "Jikan API unavailable (503)".to_string()
+                    };
+                    let Some(delay) = rate_limit_budget.next_delay(code, response.header("retry-after")) else {
+                        return Err(last_error);
+                    };
+                    log::warn!("Jikan returned {}, waiting {}ms before retry", code, delay.as_millis());
+                    std::thread::sleep(delay);
                 }
                 Err(ureq::Error::Status(code, response)) => {
                     let body = response.into_string().unwrap_or_default();
@@ -1,74 +1,102 @@
-use crate::commands::AppState;
+use crate::commands::{AppState, CommandError};
 use crate::extensions::types::*;
 use super::types::*;
-use super::{anime, bridge, manga};
-use tauri::State;
+use super::{anime, bridge, covers, enrichment, manga, numbering, season_pass, split_cour};
+use crate::database::age_rating;
+use crate::database::hidden_media::HiddenSet;
+use tauri::{AppHandle, State};
+
+/// Drop results the user hid from a Jikan listing. Jikan responses are
+/// cached unfiltered, so this runs on every call.
+async fn without_hidden(
+    state: &AppState,
+    results: Result<SearchResults, String>,
+) -> Result<SearchResults, String> {
+    let mut results = results?;
+    HiddenSet::load(state.database.pool(), state.profile_id()).await.filter(&mut results);
+    Ok(results)
+}
 
 // --- Anime Commands ---
 
 #[tauri::command]
 pub async fn jikan_search_anime(
+    state: State<'_, AppState>,
     query: String,
     page: i32,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::search_anime(&query, page, sfw))
+    let results = tokio::task::spawn_blocking(move || anime::search_anime(&query, page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
 pub async fn jikan_top_anime(
+    state: State<'_, AppState>,
     page: i32,
     type_filter: Option<String>,
     filter: Option<String>,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || {
+    let results = tokio::task::spawn_blocking(move || {
         anime::top_anime(page, type_filter.as_deref(), filter.as_deref(), sfw)
     })
     .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
-pub async fn jikan_season_now(page: i32, sfw: bool) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::season_now(page, sfw))
+pub async fn jikan_season_now(state: State<'_, AppState>, page: i32, sfw: bool) -> Result<SearchResults, String> {
+    let results = tokio::task::spawn_blocking(move || anime::season_now(page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
 pub async fn jikan_season(
+    state: State<'_, AppState>,
     year: i32,
     season: String,
     page: i32,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::season(year, &season, page, sfw))
+    let results = tokio::task::spawn_blocking(move || anime::season(year, &season, page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
-pub async fn jikan_season_upcoming(page: i32, sfw: bool) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::season_upcoming(page, sfw))
+pub async fn jikan_season_upcoming(state: State<'_, AppState>, page: i32, sfw: bool) -> Result<SearchResults, String> {
+    let results = tokio::task::spawn_blocking(move || anime::season_upcoming(page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
-pub async fn jikan_watch_episodes_popular() -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::watch_episodes_popular())
+pub async fn jikan_watch_episodes_popular(state: State<'_, AppState>) -> Result<SearchResults, String> {
+    let results = tokio::task::spawn_blocking(move || anime::watch_episodes_popular())
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
-pub async fn jikan_anime_details(mal_id: i64) -> Result<MediaDetails, String> {
-    tokio::task::spawn_blocking(move || anime::anime_details(mal_id))
+pub async fn jikan_anime_details(state: State<'_, AppState>, mal_id: i64) -> Result<MediaDetails, String> {
+    let details = tokio::task::spawn_blocking(move || anime::anime_details(mal_id))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))??;
+    age_rating::record_fetched_rating(state.database.pool(), &details.id, details.age_rating.as_deref()).await;
+    crate::database::media::record_broadcast_schedule(
+        state.database.pool(), &details.id, details.last_update_end.as_deref(), details.broadcast_interval,
+    )
+    .await;
+    Ok(details)
 }
 
 #[tauri::command]
@@ -89,10 +117,11 @@ pub async fn jikan_anime_episode_detail(
 }
 
 #[tauri::command]
-pub async fn jikan_anime_recommendations(mal_id: i64) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::anime_recommendations(mal_id))
+pub async fn jikan_anime_recommendations(state: State<'_, AppState>, mal_id: i64) -> Result<SearchResults, String> {
+    let results = tokio::task::spawn_blocking(move || anime::anime_recommendations(mal_id))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
@@ -104,13 +133,15 @@ pub async fn jikan_genres_anime() -> Result<TagsResult, String> {
 
 #[tauri::command]
 pub async fn jikan_schedules(
+    state: State<'_, AppState>,
     day: Option<String>,
     page: i32,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || anime::schedules(day.as_deref(), page, sfw))
+    let results = tokio::task::spawn_blocking(move || anime::schedules(day.as_deref(), page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
@@ -166,6 +197,7 @@ pub async fn jikan_anime_news(mal_id: i64) -> Result<Vec<JikanNews>, String> {
 
 #[tauri::command]
 pub async fn jikan_search_anime_filtered(
+    state: State<'_, AppState>,
     query: Option<String>,
     page: i32,
     sfw: bool,
@@ -178,7 +210,7 @@ pub async fn jikan_search_anime_filtered(
     max_score: Option<String>,
     rating: Option<String>,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || {
+    let results = tokio::task::spawn_blocking(move || {
         anime::search_anime_filtered(
             query.as_deref(),
             page,
@@ -194,34 +226,39 @@ pub async fn jikan_search_anime_filtered(
         )
     })
     .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 // --- Manga Commands ---
 
 #[tauri::command]
 pub async fn jikan_search_manga(
+    state: State<'_, AppState>,
     query: String,
     page: i32,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || manga::search_manga(&query, page, sfw))
+    let results = tokio::task::spawn_blocking(move || manga::search_manga(&query, page, sfw))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
 pub async fn jikan_top_manga(
+    state: State<'_, AppState>,
     page: i32,
     type_filter: Option<String>,
     filter: Option<String>,
     sfw: bool,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || {
+    let results = tokio::task::spawn_blocking(move || {
         manga::top_manga(page, type_filter.as_deref(), filter.as_deref(), sfw)
     })
     .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
@@ -269,14 +306,16 @@ pub async fn jikan_manga_news(mal_id: i64) -> Result<Vec<JikanNews>, String> {
 }
 
 #[tauri::command]
-pub async fn jikan_manga_recommendations(mal_id: i64) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || manga::manga_recommendations(mal_id))
+pub async fn jikan_manga_recommendations(state: State<'_, AppState>, mal_id: i64) -> Result<SearchResults, String> {
+    let results = tokio::task::spawn_blocking(move || manga::manga_recommendations(mal_id))
         .await
-        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
 pub async fn jikan_search_manga_filtered(
+    state: State<'_, AppState>,
     query: Option<String>,
     page: i32,
     sfw: bool,
@@ -288,7 +327,7 @@ pub async fn jikan_search_manga_filtered(
     min_score: Option<String>,
     max_score: Option<String>,
 ) -> Result<SearchResults, String> {
-    tokio::task::spawn_blocking(move || {
+    let results = tokio::task::spawn_blocking(move || {
         manga::search_manga_filtered(
             query.as_deref(),
             page,
@@ -303,7 +342,8 @@ pub async fn jikan_search_manga_filtered(
         )
     })
     .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .map_err(|e| format!("Task error: {}", e))?;
+    without_hidden(&state, results).await
 }
 
 #[tauri::command]
@@ -380,11 +420,193 @@ pub async fn clear_allanime_mapping(
     bridge::delete_cached_mapping(pool, &mal_id).await
 }
 
+// --- Episode Numbering Commands ---
+
+/// Compare MAL's episode list with a source's and propose a numbering offset.
+/// Nothing is saved; confirm the proposal with set_numbering_offset.
+/// `source_media_id` defaults to the cached AllAnime mapping of the MAL id.
+#[tauri::command]
+pub async fn detect_numbering_offset(
+    state: State<'_, AppState>,
+    media_id: String,
+    extension_id: String,
+    source_media_id: Option<String>,
+) -> Result<numbering::OffsetProposal, CommandError> {
+    let mal_id: i64 = media_id
+        .parse()
+        .map_err(|_| format!("Not a MAL id: {}", media_id))?;
+
+    let source_media_id = match source_media_id {
+        Some(id) => id,
+        None => bridge::get_cached_mapping(state.database.pool(), &media_id)
+            .await?
+            .ok_or_else(|| format!("No source mapping for {}", media_id))?,
+    };
+
+    let allow_adult = crate::extensions::adult::background_allow_adult(state.database.pool()).await;
+    let extension = state.extension(&extension_id)?;
+
+    let proposal = tokio::task::spawn_blocking(move || {
+        let canonical = anime::anime_details(mal_id)?;
+
+        let runtime = crate::commands::background_runtime(extension, allow_adult)?;
+        let source = crate::extensions::circuit_breaker::track(&extension_id, runtime.get_details(&source_media_id))
+            .map_err(|e| format!("Failed to get details: {}", e))?;
+
+        let proposal = numbering::propose_offset(&canonical.episodes, &source.episodes);
+        log::info!(
+            "Proposed numbering offset {} ({}) for {} on {}",
+            proposal.offset, proposal.reason, media_id, extension_id
+        );
+        Ok::<_, String>(proposal)
+    })
+    .await
+    .map_err(|e| format!("Task error: {}", e))??;
+    Ok(proposal)
+}
+
+/// Offset between MAL's episode numbers and a source's (0 when unset)
+#[tauri::command]
+pub async fn get_numbering_offset(
+    state: State<'_, AppState>,
+    media_id: String,
+    extension_id: String,
+) -> Result<i64, String> {
+    numbering::get_numbering_offset(state.database.pool(), &media_id, &extension_id).await
+}
+
+/// Store the offset for a media on a source (a detected proposal or a manual
+/// override). 0 clears it.
+#[tauri::command]
+pub async fn set_numbering_offset(
+    state: State<'_, AppState>,
+    media_id: String,
+    extension_id: String,
+    offset: i64,
+) -> Result<(), String> {
+    numbering::set_numbering_offset(state.database.pool(), &media_id, &extension_id, offset).await
+}
+
+// --- Split-Cour Commands ---
+
+/// Tracked anime whose finished MAL entry has a split-cour sequel (a direct
+/// sequel airing within ~6 months under the same base title). Nothing is linked.
+#[tauri::command]
+pub async fn find_split_cour_candidates(
+    state: State<'_, AppState>,
+) -> Result<Vec<split_cour::SplitCourCandidate>, String> {
+    split_cour::find_split_cour_candidates(state.database.pool(), state.profile_id()).await
+}
+
+/// Make release tracking of a media follow a split-cour sequel's MAL entry,
+/// numbering its episodes after part 1's `episode_offset` episodes
+#[tauri::command]
+pub async fn link_split_cour(
+    state: State<'_, AppState>,
+    media_id: String,
+    sequel_mal_id: i64,
+    episode_offset: i64,
+) -> Result<split_cour::SplitCourLink, String> {
+    split_cour::link_split_cour(state.database.pool(), &media_id, sequel_mal_id, episode_offset).await
+}
+
+/// Remove a media's split-cour link. Returns false when it had none.
+#[tauri::command]
+pub async fn unlink_split_cour(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<bool, String> {
+    split_cour::unlink_split_cour(state.database.pool(), &media_id).await
+}
+
 #[tauri::command]
 pub async fn check_daily_schedule(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let pool = state.database.pool();
-    super::schedule::check_daily_schedule_inner(&app, pool).await
+    super::schedule::check_daily_schedule_inner(&app, pool, state.profile_id()).await
+}
+
+// --- Enrichment Commands ---
+
+/// Fill missing metadata (synopsis, genres, year, studios, source material)
+/// for an anime row from its MAL entry. Present values are never overwritten.
+#[tauri::command]
+pub async fn enrich_media_from_jikan(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<enrichment::EnrichmentResult, String> {
+    let pool = state.database.pool();
+    enrichment::enrich_media(pool, &media_id).await
+}
+
+/// Debug: where each metadata field of a media row came from
+#[tauri::command]
+pub async fn get_media_provenance(
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<enrichment::MediaProvenance, String> {
+    let pool = state.database.pool();
+    enrichment::get_media_provenance(pool, &media_id).await
+}
+
+/// Replace dead AllAnime-CDN (or 404ing) anime covers with their MAL images.
+/// Resumes an interrupted run unless `restart` is set; emits cover_refresh_progress.
+#[tauri::command]
+pub async fn refresh_cover_urls(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    restart: Option<bool>,
+) -> Result<covers::CoverRefreshProgress, String> {
+    let pool = state.database.pool();
+    covers::refresh_cover_urls(pool, &app, restart.unwrap_or(false)).await
+}
+
+/// Set a media's cover or banner (`field` "cover_url" or "banner_url") by
+/// hand, or with no `url` let the cover refresh manage it again
+#[tauri::command]
+pub async fn set_media_image(
+    state: State<'_, AppState>,
+    media_id: String,
+    field: String,
+    url: Option<String>,
+) -> Result<(), String> {
+    covers::set_user_image(state.database.pool(), &media_id, &field, url.as_deref()).await
+}
+
+// --- Season Pass Commands ---
+
+#[tauri::command]
+pub async fn get_season_pass_mode(
+    state: State<'_, AppState>,
+) -> Result<season_pass::SeasonPassMode, String> {
+    season_pass::get_season_pass_mode(state.database.pool()).await
+}
+
+/// Off, suggest-only, or auto-add sequels of completed anime
+#[tauri::command]
+pub async fn set_season_pass_mode(
+    state: State<'_, AppState>,
+    mode: season_pass::SeasonPassMode,
+) -> Result<(), String> {
+    season_pass::set_season_pass_mode(state.database.pool(), mode).await
+}
+
+/// Check every completed anime for airing/upcoming sequels now. Uses the
+/// configured mode, falling back to suggest-only while the rule is off.
+#[tauri::command]
+pub async fn run_season_pass(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<season_pass::SeasonPassResult, String> {
+    let pool = state.database.pool();
+    let mode = match season_pass::get_season_pass_mode(pool).await? {
+        season_pass::SeasonPassMode::Off => season_pass::SeasonPassMode::Suggest,
+        mode => mode,
+    };
+
+    let result = season_pass::run_season_pass(pool, state.profile_id(), mode, true).await?;
+    season_pass::notify_season_pass(&app, pool, &result).await;
+    Ok(result)
 }
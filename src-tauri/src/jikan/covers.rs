@@ -0,0 +1,507 @@
+// Cover Refresh
+//
+// Anime rows created before the Jikan migration still point at AllAnime's
+// image CDN, which is slowly going away. This walks anime rows whose cover is
+// on a known-dead host (or whose cover URL now returns 404/410), fetches the
+// images for the matching MAL entry and rewrites cover_url / banner_url.
+//
+// Rows are processed in id order and the last finished id is stored in
+// app_settings, so an interrupted run continues where it stopped. Covers and
+// banners the user picked themselves (set_user_image, provenance source
+// "user") are never touched. Cover HEAD checks are spaced out so a large
+// library doesn't hammer the image hosts.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::AppHandle;
+
+use super::anime;
+use super::enrichment::{resolve_mal_id, JIKAN_SOURCE};
+use crate::events::COVER_REFRESH_EVENT;
+
+/// Provenance source for values the user set by hand
+pub const USER_SOURCE: &str = "user";
+
+/// app_settings key holding the last processed media id of an unfinished run
+const CURSOR_SETTING: &str = "cover_refresh_cursor";
+
+/// Image hosts that no longer serve (or are about to stop serving) covers
+const DEAD_COVER_HOSTS: &[&str] = &[
+    "youtube-anime.com",
+    "allanime.day",
+    "allanime.to",
+    "allmanga.to",
+];
+
+/// Minimum gap between two cover HEAD checks
+const HEAD_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Image fields the user can set by hand
+const USER_IMAGE_FIELDS: &[&str] = &["cover_url", "banner_url"];
+
+/// Prevents two refresh runs from sharing the cursor
+static REFRESH_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, schemars::JsonSchema)]
+pub struct CoverRefreshProgress {
+    pub total: usize,
+    pub processed: usize,
+    pub fixed: usize,
+    pub failed: usize,
+    pub current_title: String,
+    pub status: String, // "running" | "completed"
+}
+
+struct CoverCandidate {
+    id: String,
+    title: String,
+    extension_id: String,
+    cover_url: Option<String>,
+    banner_url: Option<String>,
+    mal_id: Option<String>,
+}
+
+/// True when the URL's host is (a subdomain of) a known-dead image host
+fn is_dead_cover_host(url: &str) -> bool {
+    let Some(host) = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) else {
+        return false;
+    };
+
+    DEAD_COVER_HOSTS
+        .iter()
+        .any(|dead| host == *dead || host.ends_with(&format!(".{}", dead)))
+}
+
+/// HEAD the cover and report whether the server says it's gone.
+/// Network errors count as alive so a flaky connection doesn't rewrite covers.
+fn cover_is_gone(url: &str) -> bool {
+    match ureq::head(url).timeout(Duration::from_secs(10)).call() {
+        Err(ureq::Error::Status(404, _)) | Err(ureq::Error::Status(410, _)) => true,
+        _ => false,
+    }
+}
+
+/// Anime rows after the cursor whose cover the user hasn't overridden
+async fn load_candidates(pool: &SqlitePool, after: Option<&str>) -> Result<Vec<CoverCandidate>, String> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, title, extension_id, cover_url, banner_url, mal_id
+        FROM media
+        WHERE media_type = 'anime'
+          AND id > ?
+          AND NOT EXISTS (
+              SELECT 1 FROM media_field_provenance p
+              WHERE p.media_id = media.id AND p.field = 'cover_url' AND p.source = ?
+          )
+        ORDER BY id
+        "#,
+    )
+    .bind(after.unwrap_or(""))
+    .bind(USER_SOURCE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| CoverCandidate {
+            id: row.get("id"),
+            title: row.get("title"),
+            extension_id: row.get("extension_id"),
+            cover_url: row.get("cover_url"),
+            banner_url: row.get("banner_url"),
+            mal_id: row.try_get("mal_id").ok().flatten(),
+        })
+        .collect())
+}
+
+/// Write the new cover (and drop a dead banner) unless the user set them.
+/// Jikan has no banner images, so a dead banner is cleared and the UI falls
+/// back to the cover.
+async fn apply_cover_update(
+    pool: &SqlitePool,
+    media_id: &str,
+    cover_url: &str,
+    clear_banner: bool,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| format!("DB error: {}", e))?;
+
+    let not_user_set = |field: &str| {
+        format!(
+            "NOT EXISTS (SELECT 1 FROM media_field_provenance WHERE media_id = ? AND field = '{}' AND source = ?)",
+            field
+        )
+    };
+
+    let updated = sqlx::query(&format!(
+        "UPDATE media SET cover_url = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND {}",
+        not_user_set("cover_url")
+    ))
+    .bind(cover_url)
+    .bind(media_id)
+    .bind(media_id)
+    .bind(USER_SOURCE)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?
+    .rows_affected();
+
+    if updated > 0 {
+        sqlx::query(
+            r#"
+            INSERT INTO media_field_provenance (media_id, field, source, recorded_at)
+            VALUES (?, 'cover_url', ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(media_id, field) DO UPDATE SET source = excluded.source, recorded_at = excluded.recorded_at
+            "#,
+        )
+        .bind(media_id)
+        .bind(JIKAN_SOURCE)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    }
+
+    if clear_banner {
+        sqlx::query(&format!(
+            "UPDATE media SET banner_url = NULL WHERE id = ? AND {}",
+            not_user_set("banner_url")
+        ))
+        .bind(media_id)
+        .bind(media_id)
+        .bind(USER_SOURCE)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| format!("DB error: {}", e))?;
+    if updated > 0 {
+        crate::cache::media_disk::forget_cover(media_id);
+    }
+    Ok(())
+}
+
+/// Set a media's cover or banner by hand, or with `url` None hand it back to
+/// the automatic refresh (the current value stays until then). User-set
+/// images are never replaced by the refresh or by enrichment.
+pub async fn set_user_image(pool: &SqlitePool, media_id: &str, field: &str, url: Option<&str>) -> Result<(), String> {
+    if !USER_IMAGE_FIELDS.contains(&field) {
+        return Err(format!("Not an image field: {}", field));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("DB error: {}", e))?;
+    match url {
+        Some(url) => {
+            let updated = sqlx::query(&format!(
+                "UPDATE media SET {} = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                field
+            ))
+            .bind(url)
+            .bind(media_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("DB error: {}", e))?
+            .rows_affected();
+            if updated == 0 {
+                return Err(format!("Media not found: {}", media_id));
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO media_field_provenance (media_id, field, source, recorded_at)
+                VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+                ON CONFLICT(media_id, field) DO UPDATE SET source = excluded.source, recorded_at = excluded.recorded_at
+                "#,
+            )
+            .bind(media_id)
+            .bind(field)
+            .bind(USER_SOURCE)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("DB error: {}", e))?;
+        }
+        None => {
+            sqlx::query("DELETE FROM media_field_provenance WHERE media_id = ? AND field = ? AND source = ?")
+                .bind(media_id)
+                .bind(field)
+                .bind(USER_SOURCE)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("DB error: {}", e))?;
+        }
+    }
+    tx.commit().await.map_err(|e| format!("DB error: {}", e))?;
+    if field == "cover_url" && url.is_some() {
+        crate::cache::media_disk::forget_cover(media_id);
+    }
+
+    log::debug!("{} of {} set by the user: {:?}", field, media_id, url);
+    Ok(())
+}
+
+async fn read_cursor(pool: &SqlitePool) -> Result<Option<String>, String> {
+    sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(CURSOR_SETTING)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))
+}
+
+async fn write_cursor(pool: &SqlitePool, media_id: Option<&str>) -> Result<(), String> {
+    let query = match media_id {
+        Some(id) => sqlx::query(
+            "INSERT OR REPLACE INTO app_settings (key, value, updated_at) VALUES (?, ?, strftime('%s', 'now') * 1000)",
+        )
+        .bind(CURSOR_SETTING)
+        .bind(id),
+        None => sqlx::query("DELETE FROM app_settings WHERE key = ?").bind(CURSOR_SETTING),
+    };
+
+    query
+        .execute(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    Ok(())
+}
+
+/// Wait until HEAD_INTERVAL has passed since the previous cover check
+async fn pace_head_check(last_head: &mut Option<Instant>) {
+    if let Some(last) = *last_head {
+        let wait = HEAD_INTERVAL.saturating_sub(last.elapsed());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+    *last_head = Some(Instant::now());
+}
+
+/// Fix one row. Ok(true) when the cover was replaced, Ok(false) when it's fine.
+async fn refresh_one(
+    pool: &SqlitePool,
+    candidate: &CoverCandidate,
+    last_head: &mut Option<Instant>,
+) -> Result<bool, String> {
+    let cover_dead = match candidate.cover_url.as_deref().filter(|u| !u.is_empty()) {
+        None => true,
+        Some(url) if is_dead_cover_host(url) => true,
+        Some(url) => {
+            pace_head_check(last_head).await;
+            let url = url.to_string();
+            tokio::task::spawn_blocking(move || cover_is_gone(&url))
+                .await
+                .map_err(|e| format!("Task error: {}", e))?
+        }
+    };
+
+    if !cover_dead {
+        return Ok(false);
+    }
+
+    let mal_id = resolve_mal_id(pool, &candidate.id, &candidate.extension_id, candidate.mal_id.as_deref())
+        .await?
+        .ok_or_else(|| "No MAL id for this entry".to_string())?;
+
+    let entry = tokio::task::spawn_blocking(move || anime::anime_full(mal_id))
+        .await
+        .map_err(|e| format!("Task error: {}", e))??;
+
+    let cover_url = anime::extract_image_url(&entry.images)
+        .ok_or_else(|| format!("MAL {} has no cover image", entry.mal_id))?;
+
+    let clear_banner = candidate.banner_url.as_deref().is_some_and(is_dead_cover_host);
+    apply_cover_update(pool, &candidate.id, &cover_url, clear_banner).await?;
+
+    Ok(true)
+}
+
+/// Replace dead anime covers with their MAL images, resuming from the saved
+/// cursor unless `restart` is set. Emits cover_refresh_progress per row.
+pub async fn refresh_cover_urls(
+    pool: &SqlitePool,
+    app_handle: &AppHandle,
+    restart: bool,
+) -> Result<CoverRefreshProgress, String> {
+    if REFRESH_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("Cover refresh is already running".to_string());
+    }
+
+    let result = run_refresh(pool, app_handle, restart).await;
+    REFRESH_RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn run_refresh(
+    pool: &SqlitePool,
+    app_handle: &AppHandle,
+    restart: bool,
+) -> Result<CoverRefreshProgress, String> {
+    let cursor = if restart { None } else { read_cursor(pool).await? };
+    let candidates = load_candidates(pool, cursor.as_deref()).await?;
+
+    let mut progress = CoverRefreshProgress {
+        total: candidates.len(),
+        status: "running".to_string(),
+        ..Default::default()
+    };
+    let mut last_head = None;
+
+    for candidate in &candidates {
+        progress.current_title = candidate.title.clone();
+
+        match refresh_one(pool, candidate, &mut last_head).await {
+            Ok(true) => progress.fixed += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("Cover refresh failed for {} ({}): {}", candidate.title, candidate.id, e);
+                progress.failed += 1;
+            }
+        }
+
+        progress.processed += 1;
+        write_cursor(pool, Some(&candidate.id)).await?;
+        COVER_REFRESH_EVENT.emit(app_handle, &progress);
+    }
+
+    write_cursor(pool, None).await?;
+
+    progress.status = "completed".to_string();
+    progress.current_title = String::new();
+    COVER_REFRESH_EVENT.emit(app_handle, &progress);
+
+    log::info!(
+        "Cover refresh complete: {} fixed, {} failed of {}",
+        progress.fixed, progress.failed, progress.total
+    );
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    async fn insert_anime(pool: &SqlitePool, id: &str, cover: &str) {
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type, cover_url, banner_url) VALUES (?, 'allanime', ?, 'anime', ?, ?)",
+        )
+        .bind(id)
+        .bind(format!("Show {}", id))
+        .bind(cover)
+        .bind(cover)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn mark_user_set(pool: &SqlitePool, id: &str, field: &str) {
+        sqlx::query("INSERT INTO media_field_provenance (media_id, field, source) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(field)
+            .bind(USER_SOURCE)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn dead_hosts_match_subdomains_only() {
+        assert!(is_dead_cover_host("https://wp.youtube-anime.com/aln.youtube-anime.com/images/x.jpg"));
+        assert!(is_dead_cover_host("https://allanime.day/cover.png"));
+        assert!(!is_dead_cover_host("https://cdn.myanimelist.net/images/anime/1/1.jpg"));
+        assert!(!is_dead_cover_host("https://notyoutube-anime.com/x.jpg"));
+        assert!(!is_dead_cover_host("not a url"));
+    }
+
+    #[tokio::test]
+    async fn candidates_resume_after_cursor_and_skip_user_covers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        for id in ["a", "b", "c"] {
+            insert_anime(pool, id, "https://wp.youtube-anime.com/x.jpg").await;
+        }
+        mark_user_set(pool, "c", "cover_url").await;
+
+        let all: Vec<String> = load_candidates(pool, None).await.unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(all, vec!["a", "b"]);
+
+        write_cursor(pool, Some("a")).await.unwrap();
+        let cursor = read_cursor(pool).await.unwrap();
+        let resumed: Vec<String> = load_candidates(pool, cursor.as_deref())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(resumed, vec!["b"]);
+
+        write_cursor(pool, None).await.unwrap();
+        assert!(read_cursor(pool).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn apply_update_leaves_user_banner_alone() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        insert_anime(pool, "a", "https://wp.youtube-anime.com/x.jpg").await;
+        mark_user_set(pool, "a", "banner_url").await;
+
+        apply_cover_update(pool, "a", "https://cdn.myanimelist.net/images/anime/1/1.jpg", true)
+            .await
+            .unwrap();
+
+        let row = sqlx::query("SELECT cover_url, banner_url FROM media WHERE id = 'a'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>("cover_url"), "https://cdn.myanimelist.net/images/anime/1/1.jpg");
+        assert_eq!(row.get::<String, _>("banner_url"), "https://wp.youtube-anime.com/x.jpg");
+
+        let source: String = sqlx::query_scalar(
+            "SELECT source FROM media_field_provenance WHERE media_id = 'a' AND field = 'cover_url'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(source, JIKAN_SOURCE);
+    }
+
+    #[tokio::test]
+    async fn user_images_are_skipped_until_handed_back() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        insert_anime(pool, "a", "https://wp.youtube-anime.com/x.jpg").await;
+        set_user_image(pool, "a", "cover_url", Some("https://example.test/mine.jpg")).await.unwrap();
+
+        let cover: String = sqlx::query_scalar("SELECT cover_url FROM media WHERE id = 'a'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(cover, "https://example.test/mine.jpg");
+        assert!(load_candidates(pool, None).await.unwrap().is_empty());
+
+        set_user_image(pool, "a", "cover_url", None).await.unwrap();
+        assert_eq!(load_candidates(pool, None).await.unwrap().len(), 1);
+
+        assert!(set_user_image(pool, "a", "title", Some("x")).await.is_err());
+        assert!(set_user_image(pool, "missing", "cover_url", Some("x")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn head_checks_are_spaced_out() {
+        let mut last_head = None;
+        let started = Instant::now();
+        pace_head_check(&mut last_head).await;
+        pace_head_check(&mut last_head).await;
+        assert!(started.elapsed() >= HEAD_INTERVAL);
+    }
+}
@@ -0,0 +1,564 @@
+// Jikan Metadata Enrichment
+//
+// Media rows saved from extension search results are often sparse. For anime
+// missing a description, genres, year or cover, this resolves the matching MAL entry
+// and fills in whatever is missing. Present values are never overwritten, so
+// data from the extension or edited by the user always wins.
+//
+// Every filled field is recorded in media_field_provenance, which is what
+// get_media_provenance reports alongside the row's own extension.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+
+use super::anime;
+use super::bridge::title_similarity;
+use super::covers::USER_SOURCE;
+use crate::database::age_rating::AgeRating;
+use super::types::JikanAnime;
+
+/// Provenance source recorded for fields filled from Jikan
+pub const JIKAN_SOURCE: &str = "jikan";
+
+/// Minimum title similarity to accept a Jikan search hit
+const MIN_MATCH_SCORE: f64 = 0.85;
+
+/// Library items enriched per background run (Jikan allows ~60 requests/minute)
+const BACKGROUND_BATCH_SIZE: i64 = 20;
+
+/// Columns enrichment may fill, in the order they're reported.
+/// Kept as a fixed list because the names are interpolated into SQL.
+const ENRICHABLE_FIELDS: &[&str] = &[
+    "description",
+    "genres",
+    "year",
+    "studios",
+    "source_material",
+    "english_name",
+    "native_name",
+    "content_type",
+    "episode_count",
+    "cover_url",
+    "rating_classification",
+];
+
+/// A row counts as sparse when any of these are missing
+const REQUIRED_FIELDS: &[&str] = &["description", "genres", "year", "cover_url"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrichmentResult {
+    pub media_id: String,
+    pub mal_id: Option<String>,
+    /// Fields that were empty and got filled
+    pub fields_filled: Vec<String>,
+    /// Why nothing was attempted (not anime, already complete, no match)
+    pub skipped_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldProvenance {
+    pub field: String,
+    pub has_value: bool,
+    /// Where the value came from; None when the field is empty
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaProvenance {
+    pub media_id: String,
+    pub extension_id: String,
+    pub mal_id: Option<String>,
+    pub enriched_at: Option<String>,
+    pub fields: Vec<FieldProvenance>,
+}
+
+enum FieldValue {
+    Text(String),
+    Int(i64),
+}
+
+/// SQL expression that is true when `field` holds no usable value
+fn empty_condition(field: &str) -> String {
+    format!("({0} IS NULL OR {0} = '' OR {0} = '[]')", field)
+}
+
+/// Enrich a single media row from Jikan
+pub async fn enrich_media(pool: &SqlitePool, media_id: &str) -> Result<EnrichmentResult, String> {
+    let row = sqlx::query(
+        "SELECT id, extension_id, title, english_name, media_type, year, mal_id FROM media WHERE id = ?",
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?
+    .ok_or_else(|| format!("Media not found: {}", media_id))?;
+
+    let mut result = EnrichmentResult {
+        media_id: media_id.to_string(),
+        mal_id: row.try_get("mal_id").ok().flatten(),
+        fields_filled: Vec::new(),
+        skipped_reason: None,
+    };
+
+    if row.get::<String, _>("media_type") != "anime" {
+        result.skipped_reason = Some("Only anime entries are enriched".to_string());
+        return Ok(result);
+    }
+
+    if !is_sparse(pool, media_id).await? {
+        result.skipped_reason = Some("Nothing to enrich".to_string());
+        return Ok(result);
+    }
+
+    let extension_id: String = row.get("extension_id");
+    let title: String = row.get("title");
+    let english_name: Option<String> = row.try_get("english_name").ok().flatten();
+    let year: Option<i32> = row.try_get("year").ok().flatten();
+
+    let mal_id = match resolve_mal_id(pool, media_id, &extension_id, result.mal_id.as_deref()).await? {
+        Some(id) => Some(id),
+        None => search_mal_id(title, english_name, year).await?,
+    };
+
+    let Some(mal_id) = mal_id else {
+        // Still stamp the row so background runs don't retry it every launch
+        mark_enriched(pool, media_id, None).await?;
+        result.skipped_reason = Some("No matching MAL entry found".to_string());
+        return Ok(result);
+    };
+
+    let entry = tokio::task::spawn_blocking(move || anime::anime_full(mal_id))
+        .await
+        .map_err(|e| format!("Task error: {}", e))??;
+
+    result.fields_filled = merge_missing_fields(pool, media_id, &entry).await?;
+    result.mal_id = Some(entry.mal_id.to_string());
+
+    log::info!(
+        "Enriched {} from MAL {} ({} fields)",
+        media_id, entry.mal_id, result.fields_filled.len()
+    );
+
+    Ok(result)
+}
+
+/// Enrich sparse anime in any profile's library, a small batch per run.
+/// Rows already attempted (enriched_at set) are left to the manual command.
+pub async fn enrich_library_in_background(pool: &SqlitePool) -> Result<usize, String> {
+    let sparse = REQUIRED_FIELDS
+        .iter()
+        .map(|f| empty_condition(&format!("m.{}", f)))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+
+    let media_ids: Vec<String> = sqlx::query_scalar(&format!(
+        r#"
+        SELECT DISTINCT m.id
+        FROM media m
+        JOIN library l ON l.media_id = m.id
+        WHERE m.media_type = 'anime'
+          AND m.enriched_at IS NULL
+          AND ({})
+        LIMIT ?
+        "#,
+        sparse
+    ))
+    .bind(BACKGROUND_BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    let mut enriched = 0;
+    for media_id in &media_ids {
+        match enrich_media(pool, media_id).await {
+            Ok(result) if !result.fields_filled.is_empty() => enriched += 1,
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to enrich {}: {}", media_id, e),
+        }
+    }
+
+    if !media_ids.is_empty() {
+        log::info!("Background enrichment: {} of {} library items updated", enriched, media_ids.len());
+    }
+
+    Ok(enriched)
+}
+
+/// Report where each enrichable field of a media row came from
+pub async fn get_media_provenance(pool: &SqlitePool, media_id: &str) -> Result<MediaProvenance, String> {
+    let has_value_columns = ENRICHABLE_FIELDS
+        .iter()
+        .map(|f| format!("NOT {} AS has_{}", empty_condition(f), f))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let row = sqlx::query(&format!(
+        "SELECT extension_id, mal_id, enriched_at, {} FROM media WHERE id = ?",
+        has_value_columns
+    ))
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?
+    .ok_or_else(|| format!("Media not found: {}", media_id))?;
+
+    let recorded: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT field, source FROM media_field_provenance WHERE media_id = ?",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?
+    .into_iter()
+    .collect();
+
+    let extension_id: String = row.get("extension_id");
+
+    let fields = ENRICHABLE_FIELDS
+        .iter()
+        .map(|field| {
+            let has_value = row
+                .try_get::<Option<bool>, _>(format!("has_{}", field).as_str())
+                .ok()
+                .flatten()
+                .unwrap_or(false);
+            let source = has_value.then(|| {
+                recorded
+                    .get(*field)
+                    .cloned()
+                    .unwrap_or_else(|| extension_id.clone())
+            });
+
+            FieldProvenance {
+                field: field.to_string(),
+                has_value,
+                source,
+            }
+        })
+        .collect();
+
+    Ok(MediaProvenance {
+        media_id: media_id.to_string(),
+        extension_id,
+        mal_id: row.try_get("mal_id").ok().flatten(),
+        enriched_at: row.try_get("enriched_at").ok().flatten(),
+        fields,
+    })
+}
+
+async fn is_sparse(pool: &SqlitePool, media_id: &str) -> Result<bool, String> {
+    let sparse = REQUIRED_FIELDS
+        .iter()
+        .map(|f| empty_condition(f))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+
+    sqlx::query_scalar::<_, bool>(&format!("SELECT ({}) FROM media WHERE id = ?", sparse))
+        .bind(media_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))
+}
+
+/// Resolve the MAL id without searching: a previous match, a Jikan-sourced
+/// row (whose id is the MAL id), or a cached bridge mapping.
+pub(super) async fn resolve_mal_id(
+    pool: &SqlitePool,
+    media_id: &str,
+    extension_id: &str,
+    known_mal_id: Option<&str>,
+) -> Result<Option<i64>, String> {
+    if let Some(id) = known_mal_id.and_then(|id| id.parse().ok()) {
+        return Ok(Some(id));
+    }
+
+    if extension_id == JIKAN_SOURCE {
+        return Ok(media_id.parse().ok());
+    }
+
+    let mapped: Option<String> = sqlx::query_scalar(
+        "SELECT mal_id FROM id_mappings WHERE allanime_id = ? AND media_type = 'anime' LIMIT 1",
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(mapped.and_then(|id| id.parse().ok()))
+}
+
+/// Search Jikan by title and accept the best hit only if it's a close match
+async fn search_mal_id(
+    title: String,
+    english_name: Option<String>,
+    year: Option<i32>,
+) -> Result<Option<i64>, String> {
+    let query = title.clone();
+    let candidates = tokio::task::spawn_blocking(move || anime::search_anime_entries(&query))
+        .await
+        .map_err(|e| format!("Task error: {}", e))??;
+
+    Ok(best_match(&candidates, &title, english_name.as_deref(), year))
+}
+
+fn best_match(
+    candidates: &[JikanAnime],
+    title: &str,
+    english_name: Option<&str>,
+    year: Option<i32>,
+) -> Option<i64> {
+    candidates
+        .iter()
+        .filter(|c| match (year, candidate_year(c)) {
+            (Some(a), Some(b)) => (a - b).abs() <= 1,
+            _ => true,
+        })
+        .map(|c| {
+            let mut names = vec![c.title.as_str()];
+            names.extend(c.title_english.as_deref());
+            names.extend(c.title_synonyms.iter().flatten().map(String::as_str));
+
+            let score = names
+                .iter()
+                .flat_map(|name| {
+                    std::iter::once(title_similarity(title, name))
+                        .chain(english_name.map(|e| title_similarity(e, name)))
+                })
+                .fold(0.0, f64::max);
+
+            (c.mal_id, score)
+        })
+        .filter(|(_, score)| *score >= MIN_MATCH_SCORE)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+fn candidate_year(entry: &JikanAnime) -> Option<i32> {
+    entry.year.or_else(|| {
+        entry
+            .aired
+            .as_ref()
+            .and_then(|a| a.prop.as_ref())
+            .and_then(|p| p.from.as_ref())
+            .and_then(|f| f.year)
+    })
+}
+
+fn enrichment_values(entry: &JikanAnime) -> Vec<(&'static str, FieldValue)> {
+    let mut values = Vec::new();
+
+    let genres = anime::extract_genre_names(&entry.genres);
+    let studios: Vec<String> = entry.studios.iter().flatten().map(|s| s.name.clone()).collect();
+
+    if let Some(synopsis) = entry.synopsis.clone().filter(|s| !s.trim().is_empty()) {
+        values.push(("description", FieldValue::Text(synopsis)));
+    }
+    if !genres.is_empty() {
+        values.push(("genres", FieldValue::Text(serde_json::to_string(&genres).unwrap_or_default())));
+    }
+    if let Some(year) = candidate_year(entry) {
+        values.push(("year", FieldValue::Int(year as i64)));
+    }
+    if !studios.is_empty() {
+        values.push(("studios", FieldValue::Text(serde_json::to_string(&studios).unwrap_or_default())));
+    }
+    if let Some(source) = entry.source.clone().filter(|s| !s.is_empty() && s != "Unknown") {
+        values.push(("source_material", FieldValue::Text(source)));
+    }
+    if let Some(english) = entry.title_english.clone() {
+        values.push(("english_name", FieldValue::Text(english)));
+    }
+    if let Some(native) = entry.title_japanese.clone() {
+        values.push(("native_name", FieldValue::Text(native)));
+    }
+    if let Some(kind) = entry.anime_type.clone() {
+        values.push(("content_type", FieldValue::Text(kind)));
+    }
+    if let Some(episodes) = entry.episodes {
+        values.push(("episode_count", FieldValue::Int(episodes as i64)));
+    }
+    if let Some(cover) = anime::extract_image_url(&entry.images) {
+        values.push(("cover_url", FieldValue::Text(cover)));
+    }
+    if let Some(rating) = entry.rating.as_deref().and_then(AgeRating::classify) {
+        values.push(("rating_classification", FieldValue::Text(rating.as_str().to_string())));
+    }
+
+    values
+}
+
+/// Fill empty columns from the Jikan entry and record their provenance.
+/// Fields the user set or cleared are left alone. Returns the names of the
+/// fields that were filled.
+async fn merge_missing_fields(
+    pool: &SqlitePool,
+    media_id: &str,
+    entry: &JikanAnime,
+) -> Result<Vec<String>, String> {
+    let mut tx = pool.begin().await.map_err(|e| format!("DB error: {}", e))?;
+    let mut filled = Vec::new();
+
+    for (field, value) in enrichment_values(entry) {
+        let sql = format!(
+            "UPDATE media SET {} = ? WHERE id = ? AND {} AND NOT EXISTS (
+                SELECT 1 FROM media_field_provenance p
+                WHERE p.media_id = media.id AND p.field = ? AND p.source = ?
+            )",
+            field,
+            empty_condition(field)
+        );
+        let query = match value {
+            FieldValue::Text(v) => sqlx::query(&sql).bind(v),
+            FieldValue::Int(v) => sqlx::query(&sql).bind(v),
+        };
+
+        let updated = query
+            .bind(media_id)
+            .bind(field)
+            .bind(USER_SOURCE)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("DB error: {}", e))?
+            .rows_affected();
+
+        if updated > 0 {
+            sqlx::query(
+                r#"
+                INSERT INTO media_field_provenance (media_id, field, source, recorded_at)
+                VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+                ON CONFLICT(media_id, field) DO UPDATE SET source = excluded.source, recorded_at = excluded.recorded_at
+                "#,
+            )
+            .bind(media_id)
+            .bind(field)
+            .bind(JIKAN_SOURCE)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("DB error: {}", e))?;
+
+            filled.push(field.to_string());
+        }
+    }
+
+    tx.commit().await.map_err(|e| format!("DB error: {}", e))?;
+
+    mark_enriched(pool, media_id, Some(entry.mal_id)).await?;
+
+    Ok(filled)
+}
+
+async fn mark_enriched(pool: &SqlitePool, media_id: &str, mal_id: Option<i64>) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE media SET mal_id = COALESCE(mal_id, ?), enriched_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(mal_id.map(|id| id.to_string()))
+    .bind(media_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    fn jikan_entry() -> JikanAnime {
+        serde_json::from_value(serde_json::json!({
+            "mal_id": 5114,
+            "images": {},
+            "title": "Fullmetal Alchemist: Brotherhood",
+            "title_english": "Fullmetal Alchemist: Brotherhood",
+            "type": "TV",
+            "source": "Manga",
+            "episodes": 64,
+            "synopsis": "After a horrific alchemy experiment goes wrong...",
+            "year": 2009,
+            "studios": [{ "mal_id": 4, "type": "anime", "name": "Bones", "url": "" }],
+            "genres": [{ "mal_id": 1, "type": "anime", "name": "Action", "url": "" }]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn merge_only_fills_missing_fields() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query(
+            r#"
+            INSERT INTO media (id, extension_id, title, media_type, description, genres)
+            VALUES ('show-1', 'com.example.ext', 'FMA Brotherhood', 'anime', 'Extension synopsis', '[]')
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let filled = merge_missing_fields(pool, "show-1", &jikan_entry()).await.unwrap();
+
+        assert!(!filled.contains(&"description".to_string()));
+        assert!(filled.contains(&"genres".to_string()));
+        assert!(filled.contains(&"studios".to_string()));
+
+        let description: String = sqlx::query_scalar("SELECT description FROM media WHERE id = 'show-1'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(description, "Extension synopsis");
+
+        let provenance = get_media_provenance(pool, "show-1").await.unwrap();
+        assert_eq!(provenance.mal_id.as_deref(), Some("5114"));
+        assert!(provenance.enriched_at.is_some());
+
+        let source_of = |field: &str| {
+            provenance
+                .fields
+                .iter()
+                .find(|f| f.field == field)
+                .and_then(|f| f.source.clone())
+        };
+        assert_eq!(source_of("description").as_deref(), Some("com.example.ext"));
+        assert_eq!(source_of("genres").as_deref(), Some(JIKAN_SOURCE));
+        assert_eq!(source_of("source_material").as_deref(), Some(JIKAN_SOURCE));
+    }
+
+    #[tokio::test]
+    async fn merge_skips_fields_the_user_cleared() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type, description) VALUES ('show-1', 'com.example.ext', 'FMA Brotherhood', 'anime', 'Wrong synopsis')",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        crate::database::media::clear_media_fields(pool, "show-1", &["description".to_string()]).await.unwrap();
+
+        let filled = merge_missing_fields(pool, "show-1", &jikan_entry()).await.unwrap();
+
+        assert!(!filled.contains(&"description".to_string()));
+        assert!(filled.contains(&"genres".to_string()));
+        let description: Option<String> = sqlx::query_scalar("SELECT description FROM media WHERE id = 'show-1'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(description, None);
+    }
+
+    #[test]
+    fn best_match_requires_close_title_and_year() {
+        let candidates = vec![jikan_entry()];
+
+        assert_eq!(best_match(&candidates, "Fullmetal Alchemist: Brotherhood", None, Some(2009)), Some(5114));
+        assert_eq!(best_match(&candidates, "Fullmetal Alchemist: Brotherhood", None, Some(2003)), None);
+        assert_eq!(best_match(&candidates, "Naruto", None, None), None);
+    }
+}
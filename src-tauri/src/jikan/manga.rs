@@ -46,6 +46,9 @@ fn jikan_manga_to_search_result(manga: &JikanManga) -> SearchResult {
         broadcast_day: None,
         broadcast_time: None,
         broadcast_timezone: None,
+        language: None,
+        language_match: None,
+        age_rating: None,
     }
 }
 
@@ -244,6 +247,9 @@ pub fn manga_recommendations(mal_id: i64) -> Result<SearchResults, String> {
                 broadcast_day: None,
                 broadcast_time: None,
                 broadcast_timezone: None,
+                language: None,
+                language_match: None,
+                age_rating: None,
             }
         })
         .collect();
@@ -328,6 +334,7 @@ pub fn genres_manga() -> Result<TagsResult, String> {
             slug: g.name.to_lowercase().replace(' ', "-"),
             count: g.count.unwrap_or(0) as u32,
             thumbnail: None,
+            merged_slugs: Vec::new(),
         })
         .collect();
 
@@ -5,3 +5,8 @@ pub mod manga;
 pub mod commands;
 pub mod bridge;
 pub mod schedule;
+pub mod enrichment;
+pub mod covers;
+pub mod season_pass;
+pub mod numbering;
+pub mod split_cour;
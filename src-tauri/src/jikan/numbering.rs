@@ -0,0 +1,255 @@
+// Cross-Source Episode Numbering
+//
+// MAL numbers every season from 1, and watch history uses MAL episode ids.
+// A source may number the same episodes differently, typically absolutely
+// across seasons (season 2 episode 1 is "episode 13"). numbering_offsets
+// stores, per media and source, how far the source's numbers are ahead of
+// MAL's, so progress and "up next" can be translated between the two.
+//
+// detect_numbering_offset proposes an offset by comparing the episode lists;
+// it never saves one. An offset is stored only through set_numbering_offset.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::extensions::types::Episode;
+
+/// First-aired dates this close count as the same episode
+const SAME_AIR_DATE_TOLERANCE_SECS: i64 = 24 * 60 * 60;
+
+/// A proposed offset and the evidence it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OffsetProposal {
+    /// source episode number = canonical episode number + offset
+    pub offset: i64,
+    /// "first_aired", "first_number", "episode_count" or "aligned"
+    pub reason: String,
+    pub canonical_episodes: usize,
+    pub source_episodes: usize,
+}
+
+/// Stored offset for a media on a source (0 when none is stored)
+pub async fn get_numbering_offset(pool: &SqlitePool, media_id: &str, extension_id: &str) -> Result<i64, String> {
+    let offset = sqlx::query_scalar::<_, i64>(
+        "SELECT episode_offset FROM numbering_offsets WHERE media_id = ? AND extension_id = ?",
+    )
+    .bind(media_id)
+    .bind(extension_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(offset.unwrap_or(0))
+}
+
+/// Store an offset for a media on a source. An offset of 0 removes the entry.
+pub async fn set_numbering_offset(
+    pool: &SqlitePool,
+    media_id: &str,
+    extension_id: &str,
+    offset: i64,
+) -> Result<(), String> {
+    if offset == 0 {
+        sqlx::query("DELETE FROM numbering_offsets WHERE media_id = ? AND extension_id = ?")
+            .bind(media_id)
+            .bind(extension_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("DB error: {}", e))?;
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO numbering_offsets (media_id, extension_id, episode_offset, updated_at)
+        VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(media_id, extension_id) DO UPDATE SET
+            episode_offset = excluded.episode_offset,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(media_id)
+    .bind(extension_id)
+    .bind(offset)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(())
+}
+
+/// Canonical (MAL) episode number to the source's numbering
+pub fn to_source_number(canonical: f64, offset: i64) -> f64 {
+    canonical + offset as f64
+}
+
+/// Source episode number to the canonical (MAL) numbering
+pub fn to_canonical_number(source: f64, offset: i64) -> f64 {
+    source - offset as f64
+}
+
+/// Parse an ISO 8601 timestamp or plain date into a unix timestamp in seconds
+fn parse_aired(aired: &str) -> Option<i64> {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(aired) {
+        return Some(dt.timestamp());
+    }
+    let date = chrono::NaiveDate::parse_from_str(aired.get(..10)?, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+fn first_aired(episodes: &[Episode]) -> Option<(f32, i64)> {
+    episodes
+        .iter()
+        .filter_map(|ep| Some((ep.number, parse_aired(ep.aired.as_deref()?)?)))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+fn number_range(episodes: &[Episode]) -> Option<(f32, f32)> {
+    let min = episodes.iter().map(|ep| ep.number).min_by(f32::total_cmp)?;
+    let max = episodes.iter().map(|ep| ep.number).max_by(f32::total_cmp)?;
+    Some((min, max))
+}
+
+/// Propose how far the source's episode numbers are ahead of the canonical
+/// ones. Checked in order of reliability:
+///
+/// 1. The source episode that aired on the canonical first episode's date
+/// 2. The source list starting past the canonical first number
+/// 3. The source list running longer than the canonical one (absolute
+///    numbering that still includes the earlier seasons)
+pub fn propose_offset(canonical: &[Episode], source: &[Episode]) -> OffsetProposal {
+    let proposal = |offset: i64, reason: &str| OffsetProposal {
+        offset,
+        reason: reason.to_string(),
+        canonical_episodes: canonical.len(),
+        source_episodes: source.len(),
+    };
+
+    let (Some((canonical_min, canonical_max)), Some((source_min, source_max))) =
+        (number_range(canonical), number_range(source))
+    else {
+        return proposal(0, "aligned");
+    };
+
+    if let Some((canonical_number, canonical_aired)) = first_aired(canonical) {
+        let matched = source
+            .iter()
+            .filter_map(|ep| Some((ep.number, parse_aired(ep.aired.as_deref()?)?)))
+            .filter(|(_, aired)| (aired - canonical_aired).abs() <= SAME_AIR_DATE_TOLERANCE_SECS)
+            .min_by_key(|(_, aired)| (aired - canonical_aired).abs());
+
+        if let Some((source_number, _)) = matched {
+            return proposal((source_number - canonical_number).round() as i64, "first_aired");
+        }
+    }
+
+    if source_min > canonical_min {
+        return proposal((source_min - canonical_min).round() as i64, "first_number");
+    }
+
+    if source_max > canonical_max {
+        return proposal((source_max - canonical_max).round() as i64, "episode_count");
+    }
+
+    proposal(0, "aligned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use tempfile::tempdir;
+
+    fn episode(number: f32, aired: Option<&str>) -> Episode {
+        Episode {
+            id: format!("ep-{}", number),
+            number,
+            title: None,
+            thumbnail: None,
+            aired: aired.map(str::to_string),
+        }
+    }
+
+    /// Weekly episodes from `first_day` (days since 2023-01-01)
+    fn weekly(numbers: std::ops::RangeInclusive<u32>, first_day: i64, with_dates: bool) -> Vec<Episode> {
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        numbers
+            .enumerate()
+            .map(|(i, n)| {
+                let day = start + chrono::Duration::days(first_day + i as i64 * 7);
+                let aired = format!("{}T15:00:00+00:00", day);
+                episode(n as f32, with_dates.then_some(aired.as_str()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn absolute_numbering_is_matched_by_first_aired_date() {
+        // Season 2 on MAL: episodes 1-12, starting the week after season 1's 25
+        let seasonal = weekly(1..=12, 25 * 7, true);
+        // The source numbers both seasons together: 1-37
+        let absolute = weekly(1..=37, 0, true);
+
+        let proposal = propose_offset(&seasonal, &absolute);
+        assert_eq!(proposal.offset, 25);
+        assert_eq!(proposal.reason, "first_aired");
+        assert_eq!((proposal.canonical_episodes, proposal.source_episodes), (12, 37));
+
+        // Season 2 episode 3 is the source's episode 28, and back
+        assert_eq!(to_source_number(3.0, proposal.offset), 28.0);
+        assert_eq!(to_canonical_number(28.0, proposal.offset), 3.0);
+    }
+
+    #[test]
+    fn air_dates_a_few_hours_apart_still_match() {
+        let seasonal = vec![episode(1.0, Some("2023-07-01"))];
+        let absolute = vec![
+            episode(12.0, Some("2023-06-24T16:00:00+00:00")),
+            episode(13.0, Some("2023-07-01T16:00:00+00:00")),
+        ];
+        assert_eq!(propose_offset(&seasonal, &absolute).offset, 12);
+    }
+
+    #[test]
+    fn without_dates_the_numbers_decide() {
+        // Source lists only the season, but keeps counting from season 1
+        let seasonal = weekly(1..=12, 0, false);
+        let continued = weekly(26..=37, 0, false);
+        let proposal = propose_offset(&seasonal, &continued);
+        assert_eq!((proposal.offset, proposal.reason.as_str()), (25, "first_number"));
+
+        // Source lists every season from 1
+        let absolute = weekly(1..=37, 0, false);
+        let proposal = propose_offset(&seasonal, &absolute);
+        assert_eq!((proposal.offset, proposal.reason.as_str()), (25, "episode_count"));
+    }
+
+    #[test]
+    fn matching_lists_need_no_offset() {
+        let seasonal = weekly(1..=12, 0, true);
+        assert_eq!(propose_offset(&seasonal, &seasonal.clone()).offset, 0);
+        // Source behind on releases
+        assert_eq!(propose_offset(&seasonal, &seasonal[..8]).offset, 0);
+        assert_eq!(propose_offset(&seasonal, &[]).reason, "aligned");
+    }
+
+    #[tokio::test]
+    async fn offsets_are_stored_per_media_and_source() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(get_numbering_offset(pool, "51009", "com.allanime.source").await.unwrap(), 0);
+
+        set_numbering_offset(pool, "51009", "com.allanime.source", 25).await.unwrap();
+        set_numbering_offset(pool, "51009", "com.other.source", 12).await.unwrap();
+        assert_eq!(get_numbering_offset(pool, "51009", "com.allanime.source").await.unwrap(), 25);
+
+        // Manual override replaces the stored value; 0 clears it
+        set_numbering_offset(pool, "51009", "com.allanime.source", 24).await.unwrap();
+        assert_eq!(get_numbering_offset(pool, "51009", "com.allanime.source").await.unwrap(), 24);
+        set_numbering_offset(pool, "51009", "com.allanime.source", 0).await.unwrap();
+        assert_eq!(get_numbering_offset(pool, "51009", "com.allanime.source").await.unwrap(), 0);
+        assert_eq!(get_numbering_offset(pool, "51009", "com.other.source").await.unwrap(), 12);
+    }
+}
@@ -5,7 +5,7 @@ use sqlx::SqlitePool;
 use tauri::AppHandle;
 
 /// Check today's schedule against user library and emit notifications
-pub async fn check_daily_schedule_inner(app: &AppHandle, pool: &SqlitePool) -> Result<(), String> {
+pub async fn check_daily_schedule_inner(app: &AppHandle, pool: &SqlitePool, profile_id: i64) -> Result<(), String> {
     // 1. Check if we already notified today
     let today = Local::now().format("%Y-%m-%d").to_string();
     let last_check: Option<String> = sqlx::query_scalar(
@@ -34,8 +34,9 @@ pub async fn check_daily_schedule_inner(app: &AppHandle, pool: &SqlitePool) -> R
     let mut matches = Vec::new();
     for result in &schedule.results {
         let in_lib: bool = sqlx::query_scalar(
-            "SELECT COUNT(*) > 0 FROM library WHERE media_id = ? AND status IN ('watching', 'plan_to_watch')",
+            "SELECT COUNT(*) > 0 FROM library WHERE profile_id = ? AND media_id = ? AND status IN ('watching', 'plan_to_watch')",
         )
+        .bind(profile_id)
         .bind(&result.id)
         .fetch_one(pool)
         .await
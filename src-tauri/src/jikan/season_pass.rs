@@ -0,0 +1,613 @@
+// Season Pass
+//
+// When a library anime is completed and MAL lists a direct sequel that is
+// airing or announced, either add the sequel to the library as plan to watch
+// (with release tracking, so new episodes get notified) or just suggest it.
+// Opt-in via the season_pass_mode setting; runs in the background over a few
+// completed entries at a time and on demand across the whole library.
+//
+// Every sequel acted on is recorded in season_pass_sequels, so it is added or
+// suggested at most once per profile, even if the user removes it again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use super::anime;
+use super::enrichment::{resolve_mal_id, JIKAN_SOURCE};
+use super::types::JikanAnime;
+use crate::commands::AppState;
+use crate::database::library::{add_to_library, LibraryStatus};
+use crate::database::media::save_media;
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+use crate::release_checker;
+
+pub const SEASON_PASS_MODE_SETTING: &str = "season_pass_mode";
+
+/// Completed entries checked per background run (each costs 1+ Jikan requests)
+const BACKGROUND_BATCH_SIZE: i64 = 10;
+
+/// How long before a completed entry's relations are checked again, since
+/// sequels often get their MAL entry long after the first season ends
+const RECHECK_INTERVAL_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+const FIRST_RUN_DELAY: Duration = Duration::from_secs(120);
+const RUN_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Guards against the background run and an on-demand run overlapping
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// What to do when a sequel is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeasonPassMode {
+    #[default]
+    Off,
+    /// Notify about the sequel without touching the library
+    Suggest,
+    /// Add the sequel as plan to watch and start tracking its releases
+    AutoAdd,
+}
+
+impl SeasonPassMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeasonPassMode::Off => "off",
+            SeasonPassMode::Suggest => "suggest",
+            SeasonPassMode::AutoAdd => "auto_add",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(SeasonPassMode::Off),
+            "suggest" => Some(SeasonPassMode::Suggest),
+            "auto_add" => Some(SeasonPassMode::AutoAdd),
+            _ => None,
+        }
+    }
+}
+
+/// A sequel that was added or suggested
+#[derive(Debug, Clone, Serialize)]
+pub struct SeasonPassSequel {
+    pub source_media_id: String,
+    pub source_title: String,
+    /// MAL id of the sequel (also its media id once added)
+    pub sequel_id: String,
+    pub sequel_title: String,
+    pub airing_status: Option<String>,
+    pub added: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SeasonPassResult {
+    /// Completed entries whose relations were checked
+    pub checked: usize,
+    pub sequels: Vec<SeasonPassSequel>,
+}
+
+/// Completed library entry the rule runs on
+#[derive(Debug, Clone)]
+struct SourceEntry {
+    media_id: String,
+    extension_id: String,
+    title: String,
+    mal_id: Option<String>,
+}
+
+pub async fn get_season_pass_mode(pool: &SqlitePool) -> Result<SeasonPassMode, String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(SEASON_PASS_MODE_SETTING)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(value.as_deref().and_then(SeasonPassMode::parse).unwrap_or_default())
+}
+
+pub async fn set_season_pass_mode(pool: &SqlitePool, mode: SeasonPassMode) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT INTO app_settings (key, value, updated_at)
+        VALUES (?, ?, strftime('%s', 'now') * 1000)
+        ON CONFLICT(key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(SEASON_PASS_MODE_SETTING)
+    .bind(mode.as_str())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(())
+}
+
+/// MAL ids of an entry's direct anime sequels
+pub(super) fn sequel_ids(anime: &JikanAnime) -> Vec<i64> {
+    anime
+        .relations
+        .iter()
+        .flatten()
+        .filter(|relation| relation.relation == "Sequel")
+        .flat_map(|relation| &relation.entry)
+        .filter(|entry| entry.entry_type.as_deref() == Some("anime"))
+        .map(|entry| entry.mal_id)
+        .collect()
+}
+
+/// Airing now or announced; finished sequels aren't a season pass
+fn is_airing_or_upcoming(anime: &JikanAnime) -> bool {
+    anime.airing == Some(true)
+        || matches!(anime.status.as_deref(), Some("Currently Airing") | Some("Not yet aired"))
+}
+
+/// Whether the sequel is in the library under any id: its MAL id (Jikan-sourced
+/// rows), a row enriched with that MAL id, or an AllAnime id mapped to it
+async fn is_in_library(pool: &SqlitePool, profile_id: i64, mal_id: &str) -> Result<bool, String> {
+    let found: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT 1
+        FROM library l
+        JOIN media m ON m.id = l.media_id
+        WHERE l.profile_id = ?
+          AND (m.id = ? OR m.mal_id = ? OR m.id IN (SELECT allanime_id FROM id_mappings WHERE mal_id = ?))
+        LIMIT 1
+        "#,
+    )
+    .bind(profile_id)
+    .bind(mal_id)
+    .bind(mal_id)
+    .bind(mal_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(found.is_some())
+}
+
+async fn already_handled(pool: &SqlitePool, profile_id: i64, mal_id: &str) -> Result<bool, String> {
+    let found: Option<i64> = sqlx::query_scalar(
+        "SELECT 1 FROM season_pass_sequels WHERE profile_id = ? AND sequel_mal_id = ?",
+    )
+    .bind(profile_id)
+    .bind(mal_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(found.is_some())
+}
+
+async fn record_sequel(pool: &SqlitePool, profile_id: i64, mal_id: &str, source_media_id: &str, action: &str) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO season_pass_sequels (profile_id, sequel_mal_id, source_media_id, action, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(profile_id)
+    .bind(mal_id)
+    .bind(source_media_id)
+    .bind(action)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(())
+}
+
+async fn mark_checked(pool: &SqlitePool, profile_id: i64, media_id: &str) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        INSERT INTO season_pass_checks (profile_id, media_id, checked_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT(profile_id, media_id) DO UPDATE SET checked_at = excluded.checked_at
+        "#,
+    )
+    .bind(profile_id)
+    .bind(media_id)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(())
+}
+
+/// Completed anime in the library, least recently checked first. Unless
+/// `include_recent` is set, entries checked within the recheck interval are skipped.
+async fn completed_entries(pool: &SqlitePool, profile_id: i64, include_recent: bool, limit: i64) -> Result<Vec<SourceEntry>, String> {
+    let recheck_before = chrono::Utc::now().timestamp_millis() - RECHECK_INTERVAL_MS;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT m.id, m.extension_id, m.title, m.mal_id
+        FROM library l
+        JOIN media m ON m.id = l.media_id
+        LEFT JOIN season_pass_checks c ON c.profile_id = l.profile_id AND c.media_id = m.id
+        WHERE l.profile_id = ?
+          AND l.status = 'completed'
+          AND m.media_type = 'anime'
+          AND (? OR c.checked_at IS NULL OR c.checked_at < ?)
+        ORDER BY c.checked_at IS NOT NULL, c.checked_at
+        LIMIT ?
+        "#,
+    )
+    .bind(profile_id)
+    .bind(include_recent)
+    .bind(recheck_before)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SourceEntry {
+            media_id: row.get("id"),
+            extension_id: row.get("extension_id"),
+            title: row.get("title"),
+            mal_id: row.try_get("mal_id").ok().flatten(),
+        })
+        .collect())
+}
+
+/// Act on a sequel that isn't in the library yet. `baseline` is the episode
+/// count and latest episode number used to start release tracking.
+async fn apply_sequel(
+    pool: &SqlitePool,
+    profile_id: i64,
+    mode: SeasonPassMode,
+    source: &SourceEntry,
+    sequel: &JikanAnime,
+    baseline: (i32, Option<f32>),
+) -> Result<Option<SeasonPassSequel>, String> {
+    let sequel_id = sequel.mal_id.to_string();
+
+    if !is_airing_or_upcoming(sequel) {
+        record_sequel(pool, profile_id, &sequel_id, &source.media_id, "finished").await?;
+        return Ok(None);
+    }
+
+    let added = match mode {
+        SeasonPassMode::Off => return Ok(None),
+        SeasonPassMode::Suggest => false,
+        SeasonPassMode::AutoAdd => {
+            let entry = anime::jikan_anime_to_media_entry(sequel);
+            save_media(pool, &entry).await.map_err(|e| format!("Failed to save media: {}", e))?;
+            add_to_library(pool, profile_id, &sequel_id, LibraryStatus::PlanToWatch)
+                .await
+                .map_err(|e| format!("Failed to add to library: {}", e))?;
+
+            let (count, latest_number) = baseline;
+            release_checker::initialize_tracking_v2(
+                pool,
+                &sequel_id,
+                JIKAN_SOURCE,
+                "anime",
+                count,
+                latest_number,
+                None,
+                sequel.status.as_deref(),
+            )
+            .await
+            .map_err(|e| format!("Failed to initialize release tracking: {}", e))?;
+            true
+        }
+    };
+
+    record_sequel(pool, profile_id, &sequel_id, &source.media_id, if added { "added" } else { "suggested" }).await?;
+    log::info!(
+        "Season pass: {} sequel '{}' of '{}'",
+        if added { "added" } else { "suggested" },
+        sequel.title,
+        source.title
+    );
+
+    Ok(Some(SeasonPassSequel {
+        source_media_id: source.media_id.clone(),
+        source_title: source.title.clone(),
+        sequel_id,
+        sequel_title: sequel.title_english.clone().unwrap_or_else(|| sequel.title.clone()),
+        airing_status: sequel.status.clone(),
+        added,
+    }))
+}
+
+/// Episode count and latest episode number currently on Jikan, the same
+/// numbers the release checker compares against
+async fn release_baseline(mal_id: i64) -> (i32, Option<f32>) {
+    match tokio::task::spawn_blocking(move || anime::anime_details(mal_id)).await {
+        Ok(Ok(details)) => (
+            details.episodes.len() as i32,
+            details.episodes.iter().map(|e| e.number).reduce(f32::max),
+        ),
+        Ok(Err(e)) => {
+            log::warn!("Failed to load episodes for MAL {}: {}", mal_id, e);
+            (0, None)
+        }
+        Err(e) => {
+            log::warn!("Jikan task failed: {}", e);
+            (0, None)
+        }
+    }
+}
+
+async fn fetch_anime(mal_id: i64) -> Result<JikanAnime, String> {
+    tokio::task::spawn_blocking(move || anime::anime_full(mal_id))
+        .await
+        .map_err(|e| format!("Task error: {}", e))?
+}
+
+/// Look up a completed entry's sequels and act on each new one
+async fn check_entry(
+    pool: &SqlitePool,
+    profile_id: i64,
+    mode: SeasonPassMode,
+    source: &SourceEntry,
+) -> Result<Vec<SeasonPassSequel>, String> {
+    let mut found = Vec::new();
+
+    let Some(mal_id) = resolve_mal_id(pool, &source.media_id, &source.extension_id, source.mal_id.as_deref()).await? else {
+        mark_checked(pool, profile_id, &source.media_id).await?;
+        return Ok(found);
+    };
+
+    let anime = fetch_anime(mal_id).await?;
+
+    for sequel_id in sequel_ids(&anime) {
+        let sequel_key = sequel_id.to_string();
+        if already_handled(pool, profile_id, &sequel_key).await? {
+            continue;
+        }
+        if is_in_library(pool, profile_id, &sequel_key).await? {
+            record_sequel(pool, profile_id, &sequel_key, &source.media_id, "in_library").await?;
+            continue;
+        }
+
+        let sequel = fetch_anime(sequel_id).await?;
+        let baseline = if mode == SeasonPassMode::AutoAdd && is_airing_or_upcoming(&sequel) {
+            release_baseline(sequel_id).await
+        } else {
+            (0, None)
+        };
+
+        if let Some(result) = apply_sequel(pool, profile_id, mode, source, &sequel, baseline).await? {
+            found.push(result);
+        }
+    }
+
+    mark_checked(pool, profile_id, &source.media_id).await?;
+    Ok(found)
+}
+
+/// Run the rule over completed library entries. Background runs take a small
+/// batch of entries not checked recently; on-demand runs cover everything.
+pub async fn run_season_pass(
+    pool: &SqlitePool,
+    profile_id: i64,
+    mode: SeasonPassMode,
+    whole_library: bool,
+) -> Result<SeasonPassResult, String> {
+    if mode == SeasonPassMode::Off {
+        return Ok(SeasonPassResult::default());
+    }
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("Season pass check is already running".to_string());
+    }
+
+    let limit = if whole_library { -1 } else { BACKGROUND_BATCH_SIZE };
+    let outcome = async {
+        let entries = completed_entries(pool, profile_id, whole_library, limit).await?;
+        let mut result = SeasonPassResult::default();
+
+        for entry in &entries {
+            match check_entry(pool, profile_id, mode, entry).await {
+                Ok(sequels) => result.sequels.extend(sequels),
+                Err(e) => log::warn!("Season pass check failed for {}: {}", entry.media_id, e),
+            }
+            result.checked += 1;
+        }
+
+        Ok(result)
+    }
+    .await;
+
+    RUNNING.store(false, Ordering::SeqCst);
+    outcome
+}
+
+/// Notification for the sequels a run added or suggested
+fn season_pass_notification(sequels: &[SeasonPassSequel]) -> Option<NotificationPayload> {
+    let first = sequels.first()?;
+
+    let (title, message) = if sequels.len() == 1 {
+        let message = if first.added {
+            format!("'{}' was added to your plan to watch list", first.sequel_title)
+        } else {
+            format!("'{}' continues '{}'", first.sequel_title, first.source_title)
+        };
+        ("New Season Available", message)
+    } else {
+        let titles: Vec<&str> = sequels.iter().map(|s| s.sequel_title.as_str()).collect();
+        let verb = if sequels.iter().all(|s| s.added) { "Added to plan to watch" } else { "Sequels found" };
+        ("New Seasons Available", format!("{}: {}", verb, titles.join(", ")))
+    };
+
+    let route = if sequels.len() == 1 {
+        format!("/watch?malId={}", first.sequel_id)
+    } else {
+        "/library".to_string()
+    };
+
+    Some(
+        NotificationPayload::new(NotificationType::Info, title, message)
+            .with_source("library")
+            .with_action("View", Some(route), None)
+            .with_metadata(serde_json::json!({ "sequels": sequels })),
+    )
+}
+
+pub async fn notify_season_pass(app_handle: &AppHandle, pool: &SqlitePool, result: &SeasonPassResult) {
+    if let Some(notification) = season_pass_notification(&result.sequels) {
+        if let Err(e) = emit_notification(app_handle, Some(pool), notification).await {
+            log::warn!("Failed to emit season pass notification: {}", e);
+        }
+    }
+}
+
+/// Periodically run the rule over a batch of completed entries (no-op while off)
+pub fn start_season_pass_task(app_handle: AppHandle, pool: Arc<SqlitePool>) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_RUN_DELAY).await;
+
+        loop {
+            match get_season_pass_mode(&pool).await {
+                Ok(SeasonPassMode::Off) => {}
+                Ok(mode) => match run_season_pass(&pool, app_handle.state::<AppState>().profile_id(), mode, false).await {
+                    Ok(result) => notify_season_pass(&app_handle, &pool, &result).await,
+                    Err(e) => log::warn!("Season pass run failed: {}", e),
+                },
+                Err(e) => log::warn!("Failed to load season pass mode: {}", e),
+            }
+
+            tokio::time::sleep(RUN_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::profiles::DEFAULT_PROFILE_ID;
+    use crate::database::Database;
+
+    fn jikan_anime(mal_id: i64, title: &str, status: &str, sequels: &[i64]) -> JikanAnime {
+        serde_json::from_value(serde_json::json!({
+            "mal_id": mal_id,
+            "images": {},
+            "title": title,
+            "status": status,
+            "airing": status == "Currently Airing",
+            "episodes": 12,
+            "relations": [
+                { "relation": "Prequel", "entry": [{ "mal_id": 1, "type": "anime", "name": "Prequel" }] },
+                { "relation": "Sequel", "entry": sequels.iter().map(|id| serde_json::json!({
+                    "mal_id": id, "type": "anime", "name": "Sequel"
+                })).collect::<Vec<_>>() },
+                { "relation": "Adaptation", "entry": [{ "mal_id": 99, "type": "manga", "name": "Manga" }] },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn source(media_id: &str) -> SourceEntry {
+        SourceEntry {
+            media_id: media_id.to_string(),
+            extension_id: JIKAN_SOURCE.to_string(),
+            title: "Frieren".to_string(),
+            mal_id: None,
+        }
+    }
+
+    async fn library_status(pool: &SqlitePool, media_id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT status FROM library WHERE media_id = ?")
+            .bind(media_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn sequel_ids_only_follow_anime_sequels() {
+        let anime = jikan_anime(52991, "Frieren", "Finished Airing", &[59978, 60000]);
+        assert_eq!(sequel_ids(&anime), vec![59978, 60000]);
+        assert!(sequel_ids(&jikan_anime(1, "Standalone", "Finished Airing", &[])).is_empty());
+    }
+
+    #[test]
+    fn mode_round_trips() {
+        for mode in [SeasonPassMode::Off, SeasonPassMode::Suggest, SeasonPassMode::AutoAdd] {
+            assert_eq!(SeasonPassMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(SeasonPassMode::parse("always"), None);
+    }
+
+    #[tokio::test]
+    async fn auto_add_creates_plan_to_watch_entry_with_tracking() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let sequel = jikan_anime(59978, "Frieren Season 2", "Not yet aired", &[]);
+        let result = apply_sequel(pool, DEFAULT_PROFILE_ID, SeasonPassMode::AutoAdd, &source("52991"), &sequel, (0, None))
+            .await
+            .unwrap()
+            .expect("sequel added");
+
+        assert!(result.added);
+        assert_eq!(library_status(pool, "59978").await.as_deref(), Some("plan_to_watch"));
+        let tracked: Option<String> = sqlx::query_scalar("SELECT extension_id FROM release_tracking_v2 WHERE media_id = '59978'")
+            .fetch_optional(pool)
+            .await
+            .unwrap();
+        assert_eq!(tracked.as_deref(), Some(JIKAN_SOURCE));
+
+        // Recorded, so later runs skip it even if the user removes it
+        assert!(already_handled(pool, DEFAULT_PROFILE_ID, "59978").await.unwrap());
+        assert!(is_in_library(pool, DEFAULT_PROFILE_ID, "59978").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn suggest_and_finished_sequels_leave_library_alone() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let airing = jikan_anime(200, "Airing Sequel", "Currently Airing", &[]);
+        let suggested = apply_sequel(pool, DEFAULT_PROFILE_ID, SeasonPassMode::Suggest, &source("100"), &airing, (0, None))
+            .await
+            .unwrap()
+            .expect("sequel suggested");
+        assert!(!suggested.added);
+        assert_eq!(library_status(pool, "200").await, None);
+        assert!(already_handled(pool, DEFAULT_PROFILE_ID, "200").await.unwrap());
+
+        let finished = jikan_anime(300, "Finished Sequel", "Finished Airing", &[]);
+        let none = apply_sequel(pool, DEFAULT_PROFILE_ID, SeasonPassMode::AutoAdd, &source("100"), &finished, (0, None))
+            .await
+            .unwrap();
+        assert!(none.is_none());
+        assert_eq!(library_status(pool, "300").await, None);
+        assert!(already_handled(pool, DEFAULT_PROFILE_ID, "300").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn sequel_in_library_under_another_id_is_detected() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        // Added earlier from an AllAnime extension, linked to MAL 400 by the bridge
+        let mut entry = anime::jikan_anime_to_media_entry(&jikan_anime(400, "Sequel", "Currently Airing", &[]));
+        entry.id = "allanime-xyz".to_string();
+        entry.extension_id = "com.allanime.source".to_string();
+        save_media(pool, &entry).await.unwrap();
+        add_to_library(pool, DEFAULT_PROFILE_ID, "allanime-xyz", LibraryStatus::Watching).await.unwrap();
+        super::super::bridge::save_mapping(pool, "400", "allanime-xyz", "anime", "Sequel", Some(1.0))
+            .await
+            .unwrap();
+
+        assert!(is_in_library(pool, DEFAULT_PROFILE_ID, "400").await.unwrap());
+        assert!(!is_in_library(pool, DEFAULT_PROFILE_ID, "401").await.unwrap());
+        assert_eq!(library_status(pool, "allanime-xyz").await.as_deref(), Some("watching"));
+    }
+}
@@ -0,0 +1,403 @@
+// Split-Cour Links
+//
+// A split-cour show airs as two MAL entries ("Part 1" and "Part 2") while a
+// source may list it under one id. After migration the local media tracks
+// part 1, which finishes airing, so part 2's episodes are never reported.
+//
+// detect_split_cour spots the pattern in MAL's relations graph: a direct
+// sequel that starts airing within about six months of part 1 ending and
+// shares its base title. Candidates are only proposed; once the user links
+// one, release checks for the local media follow the sequel's MAL entry, with
+// part 1's episode count added to the sequel's numbers (which restart at 1).
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+use super::anime;
+use super::enrichment::{resolve_mal_id, JIKAN_SOURCE};
+use super::numbering;
+use super::season_pass::sequel_ids;
+use super::types::JikanAnime;
+use crate::release_checker;
+
+/// Longest break between the parts of a split-cour show
+const MAX_GAP_DAYS: i64 = 183;
+
+/// Trailing title words that only number a season or part
+const PART_MARKERS: &[&str] = &["part", "cour", "season", "second", "ii", "iii", "iv"];
+
+/// A tracked media whose MAL entry looks like part 1 of a split-cour show
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplitCourCandidate {
+    pub media_id: String,
+    pub title: String,
+    pub part_one_mal_id: i64,
+    pub sequel_mal_id: i64,
+    pub sequel_title: String,
+    pub sequel_status: Option<String>,
+    /// Days between part 1's last and the sequel's first airing
+    pub gap_days: i64,
+    /// Part 1's episode count, added to the sequel's episode numbers
+    pub episode_offset: i64,
+}
+
+/// A local media whose release tracking follows a sequel MAL entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitCourLink {
+    pub media_id: String,
+    pub sequel_mal_id: i64,
+    pub episode_offset: i64,
+    pub linked_at: i64,
+}
+
+fn is_part_marker(word: &str) -> bool {
+    if PART_MARKERS.contains(&word) || word.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    // 2nd, 3rd, 4th...
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && matches!(&word[digits.len()..], "st" | "nd" | "rd" | "th")
+}
+
+/// Title words without the trailing season/part numbering
+fn base_title(title: &str) -> Vec<String> {
+    let mut words: Vec<String> = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect();
+    while words.last().is_some_and(|w| is_part_marker(w)) {
+        words.pop();
+    }
+    words
+}
+
+/// One base title starts with the other ("Spy x Family" / "Spy x Family Part 2")
+fn shares_base_title(a: &str, b: &str) -> bool {
+    let (a, b) = (base_title(a), base_title(b));
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    !shorter.is_empty() && longer.starts_with(&shorter)
+}
+
+fn titles(anime: &JikanAnime) -> Vec<&str> {
+    std::iter::once(anime.title.as_str()).chain(anime.title_english.as_deref()).collect()
+}
+
+fn aired_date(value: Option<&str>) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value?.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Whether `sequel` continues `part_one` as a split cour. Returns the days
+/// between the two parts when it does.
+pub fn detect_split_cour(part_one: &JikanAnime, sequel: &JikanAnime) -> Option<i64> {
+    if !sequel_ids(part_one).contains(&sequel.mal_id) {
+        return None;
+    }
+
+    let part_one_end = aired_date(part_one.aired.as_ref()?.to.as_deref())?;
+    let sequel_start = aired_date(sequel.aired.as_ref()?.from.as_deref())?;
+    let gap_days = (sequel_start - part_one_end).num_days();
+    if !(0..=MAX_GAP_DAYS).contains(&gap_days) {
+        return None;
+    }
+
+    let shared = titles(part_one)
+        .iter()
+        .any(|a| titles(sequel).iter().any(|b| shares_base_title(a, b)));
+    shared.then_some(gap_days)
+}
+
+async fn fetch_anime(mal_id: i64) -> Result<JikanAnime, String> {
+    tokio::task::spawn_blocking(move || anime::anime_full(mal_id))
+        .await
+        .map_err(|e| format!("Task error: {}", e))?
+}
+
+/// Look for split-cour sequels of tracked anime whose MAL entry has finished
+/// airing. Each entry costs a few Jikan requests; nothing is linked.
+pub async fn find_split_cour_candidates(pool: &SqlitePool, profile_id: i64) -> Result<Vec<SplitCourCandidate>, String> {
+    let rows = sqlx::query(
+        r#"
+        SELECT DISTINCT m.id, m.extension_id, m.title, m.mal_id
+        FROM release_tracking_v2 rt
+        JOIN media m ON m.id = rt.media_id
+        JOIN library l ON l.media_id = m.id AND l.profile_id = ?
+        LEFT JOIN split_cour_links s ON s.media_id = m.id
+        WHERE m.media_type = 'anime'
+          AND rt.normalized_status = 'completed'
+          AND l.status != 'dropped'
+          AND s.media_id IS NULL
+        "#,
+    )
+    .bind(profile_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    let mut candidates = Vec::new();
+    for row in rows {
+        let media_id: String = row.get("id");
+        let extension_id: String = row.get("extension_id");
+        let known_mal_id: Option<String> = row.try_get("mal_id").ok().flatten();
+
+        let Some(mal_id) = resolve_mal_id(pool, &media_id, &extension_id, known_mal_id.as_deref()).await? else {
+            continue;
+        };
+        let part_one = match fetch_anime(mal_id).await {
+            Ok(anime) => anime,
+            Err(e) => {
+                log::warn!("Split-cour check failed for {}: {}", media_id, e);
+                continue;
+            }
+        };
+        let Some(episodes) = part_one.episodes.filter(|&n| n > 0) else {
+            continue;
+        };
+
+        for sequel_id in sequel_ids(&part_one) {
+            let sequel = match fetch_anime(sequel_id).await {
+                Ok(anime) => anime,
+                Err(e) => {
+                    log::warn!("Failed to load sequel {} of {}: {}", sequel_id, media_id, e);
+                    continue;
+                }
+            };
+            if let Some(gap_days) = detect_split_cour(&part_one, &sequel) {
+                candidates.push(SplitCourCandidate {
+                    media_id: media_id.clone(),
+                    title: row.get("title"),
+                    part_one_mal_id: mal_id,
+                    sequel_mal_id: sequel.mal_id,
+                    sequel_title: sequel.title_english.clone().unwrap_or_else(|| sequel.title.clone()),
+                    sequel_status: sequel.status.clone(),
+                    gap_days,
+                    episode_offset: episodes as i64,
+                });
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// The split-cour link of a media, if it has one
+pub async fn get_split_cour_link(pool: &SqlitePool, media_id: &str) -> Result<Option<SplitCourLink>, String> {
+    let row = sqlx::query(
+        "SELECT media_id, sequel_mal_id, episode_offset, linked_at FROM split_cour_links WHERE media_id = ?",
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(row.map(|row| SplitCourLink {
+        media_id: row.get("media_id"),
+        sequel_mal_id: row.get("sequel_mal_id"),
+        episode_offset: row.get("episode_offset"),
+        linked_at: row.get("linked_at"),
+    }))
+}
+
+async fn media_extension(pool: &SqlitePool, media_id: &str) -> Result<String, String> {
+    sqlx::query_scalar("SELECT extension_id FROM media WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?
+        .ok_or_else(|| format!("Media not found: {}", media_id))
+}
+
+/// Make release checks for a media follow a sequel MAL entry. Episodes of
+/// the sequel are numbered after part 1's `episode_offset` episodes, and the
+/// same offset is stored for the sequel on the media's source.
+pub async fn link_split_cour(
+    pool: &SqlitePool,
+    media_id: &str,
+    sequel_mal_id: i64,
+    episode_offset: i64,
+) -> Result<SplitCourLink, String> {
+    let extension_id = media_extension(pool, media_id).await?;
+    let link = SplitCourLink {
+        media_id: media_id.to_string(),
+        sequel_mal_id,
+        episode_offset,
+        linked_at: chrono::Utc::now().timestamp_millis(),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO split_cour_links (media_id, sequel_mal_id, episode_offset, linked_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(media_id) DO UPDATE SET
+            sequel_mal_id = excluded.sequel_mal_id,
+            episode_offset = excluded.episode_offset,
+            linked_at = excluded.linked_at
+        "#,
+    )
+    .bind(&link.media_id)
+    .bind(link.sequel_mal_id)
+    .bind(link.episode_offset)
+    .bind(link.linked_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    if extension_id != JIKAN_SOURCE {
+        numbering::set_numbering_offset(pool, &sequel_mal_id.to_string(), &extension_id, episode_offset).await?;
+    }
+
+    // Part 1's "completed" status would keep the media out of release checks;
+    // the next check takes the sequel's status instead
+    sqlx::query(
+        r#"
+        UPDATE release_tracking_v2
+        SET normalized_status = 'ongoing', raw_status = NULL, next_scheduled_check = NULL,
+            consecutive_failures = 0, last_error = NULL
+        WHERE media_id = ?
+        "#,
+    )
+    .bind(media_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    let signal = format!("sequel MAL {} (+{} episodes)", sequel_mal_id, episode_offset);
+    release_checker::log_check_result(pool, media_id, "split_cour_linked", None, None, None, None, Some(&signal), None, None, false)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    log::info!("Linked {} to split-cour sequel MAL {} (offset {})", media_id, sequel_mal_id, episode_offset);
+
+    Ok(link)
+}
+
+/// Remove a media's split-cour link; release checks go back to its own entry
+pub async fn unlink_split_cour(pool: &SqlitePool, media_id: &str) -> Result<bool, String> {
+    let Some(link) = get_split_cour_link(pool, media_id).await? else {
+        return Ok(false);
+    };
+    let extension_id = media_extension(pool, media_id).await?;
+
+    sqlx::query("DELETE FROM split_cour_links WHERE media_id = ?")
+        .bind(media_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+
+    if extension_id != JIKAN_SOURCE {
+        numbering::set_numbering_offset(pool, &link.sequel_mal_id.to_string(), &extension_id, 0).await?;
+    }
+
+    let signal = format!("sequel MAL {}", link.sequel_mal_id);
+    release_checker::log_check_result(pool, media_id, "split_cour_unlinked", None, None, None, None, Some(&signal), None, None, false)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    log::info!("Unlinked {} from split-cour sequel MAL {}", media_id, link.sequel_mal_id);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    /// MAL entry with its relations, as returned by /anime/{id}/full
+    fn entry(mal_id: i64, title: &str, aired: (&str, Option<&str>), sequels: &[i64]) -> JikanAnime {
+        serde_json::from_value(serde_json::json!({
+            "mal_id": mal_id,
+            "images": {},
+            "title": title,
+            "episodes": 12,
+            "aired": { "from": format!("{}T00:00:00+00:00", aired.0), "to": aired.1.map(|to| format!("{}T00:00:00+00:00", to)) },
+            "relations": [
+                { "relation": "Sequel", "entry": sequels.iter().map(|id| serde_json::json!({
+                    "mal_id": id, "type": "anime", "name": "Sequel"
+                })).collect::<Vec<_>>() },
+                { "relation": "Side Story", "entry": [{ "mal_id": 500, "type": "anime", "name": "OVA" }] },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn base_titles_drop_part_and_season_numbering() {
+        assert_eq!(base_title("Spy x Family Part 2"), vec!["spy", "x", "family"]);
+        assert_eq!(base_title("Dr. Stone: New World 2nd Cour"), vec!["dr", "stone", "new", "world"]);
+        assert!(shares_base_title("Attack on Titan: The Final Season", "Attack on Titan: The Final Season Part 2"));
+        assert!(shares_base_title("Vinland Saga", "Vinland Saga Season 2"));
+        assert!(shares_base_title("Kaguya-sama 2nd Cour", "Kaguya-sama"));
+        assert!(!shares_base_title("Spy x Family", "Spy Classroom"));
+    }
+
+    #[test]
+    fn direct_sequel_airing_soon_after_with_the_same_base_title_is_a_split_cour() {
+        let part_one = entry(50265, "Spy x Family", ("2022-04-09", Some("2022-06-25")), &[50602]);
+        let part_two = entry(50602, "Spy x Family Part 2", ("2022-10-01", Some("2022-12-24")), &[]);
+        assert_eq!(detect_split_cour(&part_one, &part_two), Some(98));
+    }
+
+    #[test]
+    fn distant_unrelated_or_retitled_sequels_are_not_split_cours() {
+        let part_one = entry(1, "Spy x Family", ("2022-04-09", Some("2022-06-25")), &[2, 3, 4]);
+
+        // A year and a half later: a new season, not the second half
+        let season_two = entry(2, "Spy x Family Season 2", ("2023-10-07", None), &[]);
+        assert_eq!(detect_split_cour(&part_one, &season_two), None);
+
+        // Airs soon but under another title (a spin-off or movie)
+        let movie = entry(3, "Code: White", ("2022-09-01", None), &[]);
+        assert_eq!(detect_split_cour(&part_one, &movie), None);
+
+        // Same title and timing, but only a side story in the relations graph
+        let side_story = entry(500, "Spy x Family Part 2", ("2022-10-01", None), &[]);
+        assert_eq!(detect_split_cour(&part_one, &side_story), None);
+
+        // Part 1 hasn't finished airing yet
+        let airing = entry(1, "Spy x Family", ("2022-04-09", None), &[4]);
+        let part_two = entry(4, "Spy x Family Part 2", ("2022-10-01", None), &[]);
+        assert_eq!(detect_split_cour(&airing, &part_two), None);
+    }
+
+    #[tokio::test]
+    async fn linking_resumes_tracking_against_the_sequel() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('abc123', 'allanime', 'Spy x Family', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, raw_status, normalized_status, last_checked_at)
+             VALUES ('abc123', 'allanime', 'anime', 'Finished Airing', 'completed', 0)",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let link = link_split_cour(pool, "abc123", 50602, 12).await.unwrap();
+        assert_eq!(get_split_cour_link(pool, "abc123").await.unwrap(), Some(link));
+
+        let status: String = sqlx::query_scalar("SELECT normalized_status FROM release_tracking_v2 WHERE media_id = 'abc123'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(status, "ongoing");
+        assert_eq!(numbering::get_numbering_offset(pool, "50602", "allanime").await.unwrap(), 12);
+
+        let logged: String = sqlx::query_scalar("SELECT result_type FROM release_check_log WHERE media_id = 'abc123'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, "split_cour_linked");
+
+        assert!(unlink_split_cour(pool, "abc123").await.unwrap());
+        assert_eq!(get_split_cour_link(pool, "abc123").await.unwrap(), None);
+        assert_eq!(numbering::get_numbering_offset(pool, "50602", "allanime").await.unwrap(), 0);
+        assert!(!unlink_split_cour(pool, "abc123").await.unwrap());
+    }
+}
@@ -1,15 +1,31 @@
 // Module declarations
+mod app_logs;
 mod auto_backup;
+mod backup_file;
+mod badges;
+mod cache;
 mod commands;
 mod database;
 mod downloads;
+mod episode_completion;
+mod events;
 mod extensions;
+mod http_retry;
 mod jikan;
+mod locale;
+mod maintenance;
 mod media;
+mod media_hydration;
+mod network_diagnostics;
 mod notifications;
+mod playback_recovery;
+mod playback_sessions;
 mod request_headers;
 mod release_checker;
+mod safe_mode;
+mod stats_history;
 mod status_normalizer;
+mod storage_usage;
 mod trackers;
 #[cfg_attr(desktop, path = "tray.rs")]
 #[cfg_attr(not(desktop), path = "tray_stub.rs")]
@@ -19,11 +35,12 @@ mod video_server;
 use commands::AppState;
 use database::Database;
 use downloads::DownloadManager;
-use video_server::VideoServer;
+use video_server::VideoServerHandle;
 use tauri::Manager;
 use std::sync::Arc;
 
 /// Holds video server connection info
+#[derive(Clone)]
 pub struct VideoServerInfo {
     pub port: u16,
     pub access_token: String,
@@ -32,15 +49,44 @@ pub struct VideoServerInfo {
 impl VideoServerInfo {
     /// Get the base URL for local file streaming
     /// Uses tower-http ServeDir which handles Range requests automatically
+    /// `filename` may be a nested path relative to the downloads directory
     pub fn local_url(&self, filename: &str) -> String {
+        let encoded: Vec<String> = filename
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect();
+
         format!(
             "http://127.0.0.1:{}/files/{}?token={}",
             self.port,
-            urlencoding::encode(filename),
+            encoded.join("/"),
+            self.access_token
+        )
+    }
+
+    /// Get the URL that streams an MKV download remuxed to MP4
+    /// Requires ffmpeg; nothing is written to disk
+    pub fn remux_url(&self, download_id: &str) -> String {
+        format!(
+            "http://127.0.0.1:{}/remux/{}?token={}",
+            self.port,
+            urlencoding::encode(download_id),
             self.access_token
         )
     }
 
+    /// Get the URL of an extension's cached icon. The version busts the
+    /// webview's cache when an update brings a new icon.
+    pub fn extension_icon_url(&self, extension_id: &str, version: &str) -> String {
+        format!(
+            "http://127.0.0.1:{}/extension-icons/{}?token={}&v={}",
+            self.port,
+            urlencoding::encode(&extensions::icons::icon_file_name(extension_id)),
+            self.access_token,
+            urlencoding::encode(version)
+        )
+    }
+
     /// Get the proxy URL for remote video streaming
     /// Streams without buffering and forwards Range headers for seeking
     pub fn proxy_url(&self, remote_url: &str) -> String {
@@ -53,11 +99,44 @@ impl VideoServerInfo {
     }
 }
 
+/// Wrap the command handler so every invocation counts as user activity
+/// (lets the startup cache warm-up get out of the way, and holds off
+/// maintenance chores)
+fn track_activity<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        cache::warmup::note_command(invoke.message.command());
+        if let Some(state) = invoke.message.webview_ref().try_state::<AppState>() {
+            state.activity.note_command(invoke.message.command());
+        }
+        handler(invoke)
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   // Database and DownloadManager will be initialized in setup
   #[allow(unused_mut)] // mut needed on desktop for conditional plugin registration
-  let mut builder = tauri::Builder::default()
+  let mut builder = tauri::Builder::default();
+
+  // Single-instance must be registered first. A second launch (e.g. opening
+  // a .otakubak file while the app is running) exits straight away and
+  // forwards its arguments here instead.
+  #[cfg(desktop)]
+  {
+    builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+      let paths = backup_file::backup_paths_from_args(args, std::path::Path::new(&cwd));
+      if paths.is_empty() {
+        tray::restore_and_navigate(app);
+      }
+      for path in paths {
+        backup_file::open_backup_file(app, &path);
+      }
+    }));
+  }
+
+  builder = builder
     .plugin(tauri_plugin_shell::init())
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_process::init())
@@ -217,12 +296,25 @@ pub fn run() {
         log::LevelFilter::Info
       };
 
+      // Logs from before rotation can be huge; drop them before the plugin opens the file
+      let trashed_logs = app
+        .path()
+        .app_log_dir()
+        .map(|log_dir| app_logs::trash_oversized_logs(&log_dir))
+        .unwrap_or(0);
+
       let _ = app.handle().plugin(
         tauri_plugin_log::Builder::default()
           .level(log_level)
+          .max_file_size(app_logs::MAX_LOG_FILE_SIZE)
+          .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepSome(app_logs::KEPT_ROTATED_LOGS))
           .build(),
       );
 
+      if trashed_logs > 0 {
+        log::info!("Deleted {} oversized log file(s)", trashed_logs);
+      }
+
       // Initialize database and download manager
       let app_handle = app.handle();
 
@@ -230,6 +322,14 @@ pub fn run() {
       // targets have no system tray, menubar, or close-to-hide semantics).
       #[cfg(desktop)]
       app_handle.manage(tray::TrayLifecycleState::default());
+      app_handle.manage(backup_file::PendingBackupFile::default());
+
+      // Launched by opening a backup file (Windows/Linux pass it as an argument)
+      if let Ok(cwd) = std::env::current_dir() {
+        for path in backup_file::backup_paths_from_args(std::env::args(), &cwd) {
+          backup_file::open_backup_file(app_handle, &path);
+        }
+      }
 
       // If launched with --hidden (e.g. by the autostart LaunchAgent), start
       // straight into the tray instead of showing the window.
@@ -263,27 +363,82 @@ pub fn run() {
         log::error!("Failed to create app directory: {}", e);
       }
 
+      // Count this startup until it finishes; after two that didn't, start in safe mode
+      let failed_startups = safe_mode::begin_startup(&app_dir);
+      let safe = safe_mode::should_enter(failed_startups);
+      if safe {
+        log::warn!("{} failed startups in a row, starting in safe mode", failed_startups);
+      }
+
+      // Pick up playback positions left behind by a crash
+      playback_recovery::start_recovery_task(&app_dir);
+
       tauri::async_runtime::block_on(async move {
         // Create database path
         let db_path = app_dir.join("otaku.db");
 
         log::info!("Initializing database at {:?}", db_path);
 
-        // Initialize database with proper error handling
-        let database = match Database::new(db_path).await {
-          Ok(db) => db,
-          Err(e) => {
-            log::error!("Failed to initialize database: {}", e);
-            panic!("Database initialization failed: {}", e);
+        // Initialize database with proper error handling. Safe mode skips the
+        // migrations and falls back to read-only or in-memory.
+        let (database, database_mode, database_error) = if safe {
+          match safe_mode::open_database(db_path).await {
+            Ok(opened) => opened,
+            Err(e) => panic!("Database initialization failed in safe mode: {}", e),
+          }
+        } else {
+          match Database::new(db_path).await {
+            Ok(db) => (db, safe_mode::DatabaseMode::Normal, None),
+            Err(e) => {
+              log::error!("Failed to initialize database: {}", e);
+              panic!("Database initialization failed: {}", e);
+            }
           }
         };
+        app_handle.manage(safe_mode::SafeMode::new(app_dir.clone(), failed_startups, database_mode, database_error));
+
+        // Restore the active profile before anything reads per-profile data
+        let profile_id = database::profiles::load_current_profile(database.pool())
+          .await
+          .unwrap_or_else(|e| {
+            log::error!("Failed to load active profile: {}", e);
+            database::profiles::DEFAULT_PROFILE_ID
+          });
 
         let db_pool = Arc::new(database.pool().clone());
         let checker_db_pool = db_pool.clone(); // Clone for release checker before it's moved
         let schedule_db_pool = db_pool.clone(); // Clone for schedule checker before it's moved
+        let enrichment_db_pool = db_pool.clone(); // Clone for background metadata enrichment
+        let season_pass_db_pool = db_pool.clone(); // Clone for the season pass sequel check
+        let stale_library_db_pool = db_pool.clone(); // Clone for the stale library reminder
+        let video_db_pool = db_pool.clone(); // Clone for video server (resolves /remux download ids)
+        let warmup_db_pool = db_pool.clone(); // Clone for the startup cache warm-up
+        let genres_db_pool = db_pool.clone(); // Clone for the genre normalization backfill
+        let stats_db_pool = db_pool.clone(); // Clone for the developer stats history
 
         // Add database to app state
-        app_handle.manage(AppState::new(database));
+        app_handle.manage(AppState::new(database, profile_id));
+
+        // Load installed extensions, updating bundled ones shipped with a newer version
+        {
+          let state = app_handle.state::<AppState>();
+          extensions::limits::set_memory_limit_mb(extensions::limits::load_memory_limit_setting(state.database.pool()).await);
+          extensions::background_budget::set_calls_per_hour(extensions::background_budget::load_calls_per_hour_setting(state.database.pool()).await);
+          if safe {
+            log::warn!("Safe mode: not loading installed extensions");
+          } else {
+            match extensions::bundled::restore_extensions(state.database.pool()).await {
+              Ok(installed) => {
+                for extension in installed {
+                  if let Err(e) = commands::register_extension(&state, &app_dir, extension) {
+                    log::error!("Failed to load installed extension: {}", e);
+                  }
+                }
+              }
+              Err(e) => log::error!("Failed to load installed extensions: {}", e),
+            }
+          }
+        }
 
         // Initialize download manager with database
         let downloads_dir = app_dir.join("downloads");
@@ -291,37 +446,99 @@ pub fn run() {
           log::error!("Failed to create downloads directory: {}", e);
         }
 
+        let max_concurrent = downloads::load_max_concurrent_setting(&db_pool)
+          .await
+          .unwrap_or(downloads::DEFAULT_MAX_CONCURRENT);
+        downloads::throttle::set_speed_limit(downloads::throttle::load_speed_limit_setting(&db_pool).await);
+        let off_peak_window = downloads::schedule::load_off_peak_window_setting(&db_pool).await;
+        let auto_resume = downloads::auto_resume_enabled(&db_pool).await;
+        let downloads_paused = downloads::downloads_paused_setting(&db_pool).await;
+
         let download_manager = DownloadManager::new(downloads_dir.clone())
+          .with_max_concurrent(max_concurrent)
+          .with_downloads_paused(downloads_paused)
+          .with_off_peak_window(off_peak_window)
           .with_database(db_pool)
           .with_app_handle(app_handle.clone());
 
         // Load downloads from database (non-fatal if fails)
-        if let Err(e) = download_manager.load_from_database().await {
+        let interrupted = download_manager.load_from_database().await.unwrap_or_else(|e| {
           log::error!("Failed to load downloads from database: {}", e);
-        }
+          Vec::new()
+        });
 
         app_handle.manage(download_manager);
 
-        // Start video streaming server (workaround for Tauri protocol memory issues)
-        let video_server = VideoServer::new(downloads_dir);
-        let video_server_info = VideoServerInfo {
-            port: video_server.port(),
-            access_token: video_server.access_token().to_string(),
+        // Pick up downloads the app was closed in the middle of (download_auto_resume);
+        // otherwise they wait as paused
+        if auto_resume && !interrupted.is_empty() {
+          let resume_handle = app_handle.clone();
+          tokio::spawn(async move {
+            resume_handle.state::<DownloadManager>().resume_interrupted(&interrupted).await;
+          });
+        }
+
+        // Start queued downloads when their scheduled start or the off-peak window comes
+        downloads::schedule::start_schedule_task(app_handle.clone());
+
+        // Download totals for the downloads page header
+        downloads::stats::start_stats_task(app_handle.clone());
+
+        // Sidebar badge counts, emitted when they change
+        badges::start_badge_task(app_handle.clone());
+
+        // Pause downloads while the network is down, resume them when it's back
+        downloads::network::start_network_monitor(app_handle.clone());
+
+        // Adopt episodes dropped into the watch folder (no-op until enabled)
+        downloads::watchfolder::start_watch_folder_task(app_handle.clone());
+
+        // Delete downloads of watched episodes (no-op until enabled)
+        downloads::auto_delete::start_auto_delete_task(app_handle.clone());
+
+        // Storage usage breakdown (settings page) and its periodic refresh
+        let storage_paths = storage_usage::StoragePaths {
+          app_dir: app_dir.clone(),
+          downloads_dir: downloads_dir.clone(),
+          log_dir: app_handle.path().app_log_dir().ok(),
         };
+        storage_usage::start_storage_monitor(app_handle.clone(), storage_paths.clone());
+        app_handle.manage(storage_paths);
 
-        app_handle.manage(video_server_info);
+        // Start video streaming server (workaround for Tauri protocol memory issues).
+        // Spawned so binding (which retries) doesn't hold up setup; commands
+        // report the server unavailable until it's listening. If no port can
+        // be bound the user is notified and retry_video_server can start it later.
+        app_handle.manage(VideoServerHandle::new(
+          downloads_dir,
+          extensions::icons::icons_dir(&app_dir),
+          video_db_pool,
+        ));
+        {
+          let server_app_handle = app_handle.clone();
+          tokio::spawn(async move {
+            let _ = video_server::launch(&server_app_handle).await;
+          });
+        }
 
-        // Spawn video server in background
-        tokio::spawn(async move {
-            if let Err(e) = video_server.start().await {
-                log::error!("Video server error: {}", e);
-            }
-        });
+        // Probe for ffmpeg off the async runtime so the first MKV playback doesn't block on it
+        tokio::task::spawn_blocking(media::remux::ffmpeg_available);
 
         // Start release checker if enabled
         {
             let checker_app_handle = app_handle.clone();
             tokio::spawn(async move {
+                // The user's status mappings have to be in place before
+                // anything gets normalized
+                if let Err(e) = status_normalizer::load_status_mappings(&checker_db_pool).await {
+                    log::error!("Failed to load release status mappings: {}", e);
+                }
+
+                if safe {
+                    log::warn!("Safe mode: not starting the release checker");
+                    return;
+                }
+
                 // Wait for app to fully initialize
                 tokio::time::sleep(std::time::Duration::from_secs(30)).await;
 
@@ -348,9 +565,11 @@ pub fn run() {
             tokio::spawn(async move {
                 // Small delay to let app fully initialize
                 tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+                let profile_id = schedule_app_handle.state::<AppState>().profile_id();
                 if let Err(e) = jikan::schedule::check_daily_schedule_inner(
                     &schedule_app_handle,
                     &schedule_db_pool,
+                    profile_id,
                 )
                 .await
                 {
@@ -359,10 +578,57 @@ pub fn run() {
             });
         }
 
+        // Fill in sparse library metadata from Jikan, after the schedule check
+        // has had its turn at the rate limit
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            if let Err(e) = jikan::enrichment::enrich_library_in_background(&enrichment_db_pool).await {
+                log::error!("Background metadata enrichment failed: {}", e);
+            }
+        });
+
+        // Directories of cached pages, covers and thumbnails (their shared size
+        // cap is enforced by the media_cache maintenance chore)
+        cache::media_disk::start_media_cache(&app_dir);
+
+        // Per-minute developer stats for the last 48 hours
+        stats_history::start_history_task(stats_db_pool);
+
+        // Normalize genres of media saved before the mappings existed (or since changed)
+        tokio::spawn(async move {
+            if let Err(e) = database::genres::renormalize_media_genres(&genres_db_pool).await {
+                log::error!("Genre normalization backfill failed: {}", e);
+            }
+        });
+
+        // Prefetch recently watched library titles (no-op until opted in)
+        cache::warmup::start_warmup_task(app_handle.clone(), warmup_db_pool);
+
+        // Add or suggest sequels of completed anime (no-op until opted in)
+        jikan::season_pass::start_season_pass_task(app_handle.clone(), season_pass_db_pool);
+
+        // Monthly reminder of watching/reading entries gone stale (no-op until enabled)
+        database::stale_library::start_reminder_task(app_handle.clone(), stale_library_db_pool);
+
+        // Cache sweeps, stats pruning and database optimize, while the app is idle
+        maintenance::start_maintenance_task(app_handle.clone());
+
         // Start auto-backup task
         let backup_app_handle = app_handle.clone();
         auto_backup::start_auto_backup_task(backup_app_handle).await;
 
+        // Explain what safe mode skipped once the frontend is listening
+        if safe {
+          let safe_mode_handle = app_handle.clone();
+          tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            let state = safe_mode_handle.state::<safe_mode::SafeMode>().state();
+            let app_state = safe_mode_handle.state::<AppState>();
+            safe_mode::notify(&safe_mode_handle, app_state.database.pool(), &state).await;
+          });
+        }
+
+        safe_mode::finish_startup(&app_dir, failed_startups);
         log::info!("Backend initialized successfully");
       });
 
@@ -378,7 +644,7 @@ pub fn run() {
 
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
+    .invoke_handler(track_activity(tauri::generate_handler![
       commands::load_extension,
       commands::search_anime,
       commands::discover_anime,
@@ -392,12 +658,26 @@ pub fn run() {
       commands::get_anime_details,
       commands::get_video_sources,
       commands::list_extensions,
+      commands::unload_extension,
+      commands::install_bundled_extensions,
+      commands::get_onboarding_state,
+      commands::check_extension_health,
+      commands::get_extension_request_log,
+      commands::get_extension_api_schema,
+      commands::get_extension_memory_limit,
+      commands::set_extension_memory_limit,
+      commands::get_extension_background_budget,
+      commands::set_extension_background_budget,
       commands::proxy_video_request,
       commands::proxy_hls_playlist,
       // Manga
       commands::search_manga,
       commands::get_manga_details,
       commands::get_chapter_images,
+      commands::report_chapter_image_failure,
+      commands::get_image_host_failures,
+      commands::hydrate_imported_media,
+      commands::cancel_media_hydration,
       commands::discover_manga,
       commands::stream_discover_manga,
       commands::get_manga_tags,
@@ -419,26 +699,69 @@ pub fn run() {
       commands::get_download_progress,
       commands::list_downloads,
       commands::cancel_download,
+      commands::abort_download,
       commands::pause_download,
       commands::resume_download,
+      commands::refresh_and_resume_download,
+      commands::redownload_episode,
+      commands::get_max_concurrent_downloads,
+      commands::set_max_concurrent_downloads,
+      commands::get_download_speed_limit,
+      commands::set_download_speed_limit,
+      commands::set_download_speed_limit_for,
+      commands::get_download_off_peak_window,
+      commands::set_download_off_peak_window,
+      commands::schedule_download,
+      commands::start_download_now,
+      commands::pause_all_downloads,
+      commands::resume_all_downloads,
+      commands::get_downloads_paused,
+      commands::get_download_stats,
+      commands::get_network_status,
+      commands::verify_download,
+      commands::verify_all_downloads,
+      commands::verify_media_downloads,
+      commands::get_verify_schedule,
+      commands::set_verify_schedule,
+      commands::get_download_events,
+      commands::retry_download_batch,
+      commands::retry_failed_downloads,
+      commands::start_batch_download,
+      commands::estimate_download_size,
+      commands::get_batch_progress,
+      commands::get_offline_ready,
+      commands::cancel_batch_download,
+      commands::seal_download_batch,
       commands::is_episode_downloaded,
       commands::get_episode_file_path,
+      commands::get_episode_subtitle_paths,
       commands::get_total_storage_used,
       commands::get_downloads_directory,
       commands::open_downloads_folder,
       commands::remove_download,
       commands::delete_download,
+      commands::trash_download,
+      commands::restore_download,
       commands::delete_episode_download,
+      commands::clear_downloads_by_status,
+      commands::get_download_history,
       commands::clear_completed_downloads,
       commands::clear_failed_downloads,
       commands::clear_cancelled_downloads,
       // Watch History
       commands::save_watch_progress,
       commands::get_watch_progress,
+      commands::report_playback_heartbeat,
+      commands::start_playback_session,
+      commands::end_playback_session,
+      commands::get_active_playback_sessions,
       commands::get_batch_watch_progress,
       commands::get_latest_watch_progress_for_media,
       commands::get_continue_watching,
       commands::remove_from_continue_watching,
+      commands::delete_episode_watch_history,
+      commands::delete_watch_history_range,
+      commands::restore_watch_history,
       // Reading History
       commands::save_reading_progress,
       commands::get_reading_progress,
@@ -446,12 +769,20 @@ pub fn run() {
       commands::get_latest_reading_progress_for_media,
       commands::get_continue_reading,
       commands::remove_from_continue_reading_manga,
+      commands::delete_chapter_reading_history,
+      commands::delete_reading_history_range,
+      commands::restore_reading_history,
+      commands::start_reading_session,
+      commands::record_reading_page_turn,
+      commands::end_reading_session,
+      commands::get_reading_time_estimate,
       // Library
       commands::add_to_library,
       commands::remove_from_library,
       commands::get_library_entry,
       commands::get_library_by_status,
       commands::get_library_with_media,
+      commands::get_library_with_media_page,
       commands::toggle_favorite,
       commands::set_auto_download,
       commands::is_in_library,
@@ -470,25 +801,57 @@ pub fn run() {
       commands::bulk_unassign_library_tag,
       commands::bulk_update_library_status,
       commands::bulk_remove_from_library,
+      commands::get_stale_library_entries,
+      commands::move_stale_library_entries,
       // Media
       commands::save_media_details,
+      commands::clear_media_fields,
       commands::save_episodes,
       commands::get_cached_media_details,
       commands::get_continue_watching_with_details,
       commands::get_continue_reading_with_details,
       commands::get_downloads_with_media,
+      commands::repair_download_media_links,
+      commands::scan_downloads_directory,
       // Discover Cache
       commands::save_discover_cache,
       commands::get_discover_cache,
       commands::get_discover_cache_with_freshness,
       commands::save_discover_cache_with_ttl,
+      commands::clear_cache,
+      commands::get_cache_stats,
+      commands::get_media_cache_usage,
+      commands::purge_media_cache,
+      commands::set_media_cache_max_size,
       // Data Management
       commands::clear_all_watch_history,
       commands::clear_library,
       commands::clear_all_data,
       commands::get_storage_usage,
+      commands::run_network_diagnostics,
+      commands::get_http_retry_stats,
+      // Genre Normalization
+      commands::get_genre_mappings,
+      commands::set_genre_mapping,
+      commands::delete_genre_mapping,
+      commands::renormalize_media_genres,
+      // Download Archive
+      commands::archive_downloads,
+      commands::unarchive_downloads,
+      commands::get_storage_breakdown,
+      commands::rescan_downloads,
+      commands::reorganize_existing_downloads,
+      commands::find_upgradeable_downloads,
+      commands::upgrade_download,
+      commands::get_watch_folder_settings,
+      commands::set_watch_folder_settings,
+      commands::scan_watch_folder,
+      commands::get_auto_delete_settings,
+      commands::set_auto_delete_settings,
+      commands::sweep_watched_downloads,
       // Video Server
       commands::get_video_server_info,
+      commands::retry_video_server,
       commands::get_local_video_url,
       commands::get_local_file_size,
       commands::get_proxy_video_url,
@@ -496,6 +859,10 @@ pub fn run() {
       commands::get_system_stats,
       commands::start_stats_stream,
       commands::stop_stats_stream,
+      commands::get_stats_history,
+      // Maintenance
+      commands::get_maintenance_report,
+      commands::run_maintenance_now,
       // Logs
       commands::get_app_logs,
       commands::clear_app_logs,
@@ -510,6 +877,7 @@ pub fn run() {
       commands::dismiss_notification,
       commands::clear_all_notifications,
       commands::get_unread_notification_count,
+      commands::get_badge_summary,
       // App Settings
       commands::get_update_check_info,
       commands::set_update_check_info,
@@ -517,24 +885,42 @@ pub fn run() {
       commands::get_app_setting,
       commands::set_app_setting,
       commands::delete_app_setting,
+      commands::get_supported_locales,
       commands::get_youtube_video_url,
       // Release Checker
       commands::get_release_check_settings,
       commands::update_release_check_settings,
       commands::check_for_new_releases,
       commands::stop_release_check,
+      commands::set_release_source,
       commands::get_release_check_status,
       commands::initialize_release_tracking,
+      commands::enable_release_tracking,
+      commands::disable_release_tracking,
       commands::get_release_tracking_status,
       // Release Checker V2
       commands::get_media_release_states,
       commands::acknowledge_new_releases,
       commands::get_release_check_history,
       commands::get_release_tracking_debug,
+      commands::list_unknown_statuses,
+      commands::set_status_mapping,
       commands::initialize_release_tracking_v2,
+      // Profiles
+      commands::list_profiles,
+      commands::get_current_profile,
+      commands::create_profile,
+      commands::switch_profile,
+      commands::delete_profile,
+      commands::get_age_rating_limit,
+      commands::set_age_rating_limit,
       // Export/Import
       commands::export_user_data,
+      commands::export_user_data_to_file,
+      commands::export_library_report,
       commands::import_user_data,
+      commands::read_backup_file,
+      commands::take_pending_backup_file,
       // Auto-Backup
       commands::get_auto_backup_config,
       commands::update_auto_backup_config,
@@ -576,10 +962,28 @@ pub fn run() {
       jikan::commands::jikan_genres_manga,
       jikan::commands::resolve_allanime_id,
       jikan::commands::clear_allanime_mapping,
+      jikan::commands::detect_numbering_offset,
+      jikan::commands::get_numbering_offset,
+      jikan::commands::set_numbering_offset,
+      jikan::commands::find_split_cour_candidates,
+      jikan::commands::link_split_cour,
+      jikan::commands::unlink_split_cour,
+      jikan::commands::enrich_media_from_jikan,
+      jikan::commands::get_media_provenance,
+      jikan::commands::refresh_cover_urls,
+      jikan::commands::set_media_image,
       jikan::commands::check_daily_schedule,
+      jikan::commands::get_season_pass_mode,
+      jikan::commands::set_season_pass_mode,
+      jikan::commands::run_season_pass,
       // Migration (AllAnime → Jikan)
       commands::check_migration_needed,
       commands::start_migration,
+      commands::get_safe_mode_state,
+      commands::run_database_integrity_check,
+      commands::restore_database_backup,
+      commands::clear_installed_extensions,
+      commands::leave_safe_mode,
       commands::get_migration_progress,
       // History
       commands::get_all_history,
@@ -619,20 +1023,46 @@ pub fn run() {
       commands::set_media_feedback,
       commands::get_media_feedback,
       commands::remove_media_feedback,
+      commands::hide_media,
+      commands::unhide_media,
+      commands::list_hidden_media,
       // Tray / background settings
       commands::set_autostart,
       commands::get_autostart_status,
       commands::set_desktop_notifications_enabled,
       commands::get_desktop_notifications_enabled,
-    ])
+      commands::generate_event_schema,
+    ]))
     .build(tauri::generate_context!())
     .expect("error while building tauri application")
     .run(|_app_handle, _event| {
+      // Saved progress is current after a clean exit
+      if let tauri::RunEvent::Exit = _event {
+        playback_recovery::discard();
+
+        // Quitting from the tray, the menu or by closing the last window all
+        // end here: stop running downloads so their progress matches their files
+        if let Some(download_manager) = _app_handle.try_state::<DownloadManager>() {
+          tauri::async_runtime::block_on(download_manager.shutdown());
+        }
+      }
+
       #[cfg(target_os = "macos")]
-      if let tauri::RunEvent::Reopen { has_visible_windows, .. } = _event {
-        if !has_visible_windows {
-          tray::restore_and_navigate(_app_handle);
+      match _event {
+        tauri::RunEvent::Reopen { has_visible_windows, .. } => {
+          if !has_visible_windows {
+            tray::restore_and_navigate(_app_handle);
+          }
+        }
+        // Finder hands opened files over as URLs rather than arguments
+        tauri::RunEvent::Opened { urls } => {
+          for path in urls.iter().filter_map(|url| url.to_file_path().ok()) {
+            if backup_file::is_backup_file(&path) {
+              backup_file::open_backup_file(_app_handle, &path);
+            }
+          }
         }
+        _ => {}
       }
     });
 }
@@ -0,0 +1,287 @@
+// Locale Module
+//
+// Formatting of what the backend writes for people to read (notification
+// messages, the library report, the diagnostics summary) in the language
+// picked in settings: byte sizes, numbers, dates and times, and episode and
+// chapter labels. Supports English, Japanese and German; anything unset or
+// unknown falls back to English, which matches how these were formatted
+// before there was a setting.
+
+use chrono::{DateTime, Datelike, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// app_settings key holding the locale code ("en", "ja", "de")
+pub const LOCALE_SETTING: &str = "locale";
+
+const ENGLISH_MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+    De,
+}
+
+/// A supported locale, for the settings UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocaleInfo {
+    pub code: &'static str,
+    /// Name in its own language
+    pub name: &'static str,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Self::En, Self::Ja, Self::De];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Ja => "ja",
+            Self::De => "de",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Ja => "日本語",
+            Self::De => "Deutsch",
+        }
+    }
+
+    /// Parse a locale code, ignoring the region ("de-AT" and "ja_JP" work)
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code.trim().split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        Self::ALL.into_iter().find(|locale| locale.code() == language)
+    }
+
+    fn decimal_separator(&self) -> char {
+        match self {
+            Self::De => ',',
+            Self::En | Self::Ja => '.',
+        }
+    }
+
+    fn group_separator(&self) -> char {
+        match self {
+            Self::De => '.',
+            Self::En | Self::Ja => ',',
+        }
+    }
+
+    /// Episode label with `{n}` for the number
+    fn episode_template(&self) -> &'static str {
+        match self {
+            Self::En => "Episode {n}",
+            Self::Ja => "第{n}話",
+            Self::De => "Folge {n}",
+        }
+    }
+
+    /// Chapter label with `{n}` for the number
+    fn chapter_template(&self) -> &'static str {
+        match self {
+            Self::En => "Chapter {n}",
+            Self::Ja => "第{n}章",
+            Self::De => "Kapitel {n}",
+        }
+    }
+}
+
+/// Every supported locale
+pub fn supported_locales() -> Vec<LocaleInfo> {
+    Locale::ALL
+        .iter()
+        .map(|locale| LocaleInfo { code: locale.code(), name: locale.name() })
+        .collect()
+}
+
+/// The locale picked in settings; English when unset or unknown
+pub async fn current_locale(pool: &SqlitePool) -> Locale {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(LOCALE_SETTING)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None);
+
+    value.as_deref().and_then(Locale::from_code).unwrap_or_default()
+}
+
+/// Integer with thousands separators ("12,345", "12.345")
+pub fn format_number(value: i64, locale: Locale) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        grouped.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(locale.group_separator());
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Number with a fixed number of decimals ("1,234.5", "1.234,5")
+pub fn format_decimal(value: f64, decimals: usize, locale: Locale) -> String {
+    let fixed = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut formatted = format_number(whole.parse().unwrap_or(0), locale);
+    if value.is_sign_negative() && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        formatted.insert(0, '-');
+    }
+    if !fraction.is_empty() {
+        formatted.push(locale.decimal_separator());
+        formatted.push_str(fraction);
+    }
+    formatted
+}
+
+/// Byte count in human units ("1.2 GB", "1,2 GB", "400 MB")
+pub fn format_bytes(bytes: u64, locale: Locale) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{} GB", format_decimal(bytes / GB, 1, locale))
+    } else if bytes >= MB {
+        format!("{} MB", format_decimal(bytes / MB, 0, locale))
+    } else {
+        format!("{} KB", format_decimal(bytes / KB, 0, locale))
+    }
+}
+
+/// Date and time as the locale writes them ("Jan 5, 2026, 3:04 PM",
+/// "2026年1月5日 15:04", "05.01.2026, 15:04")
+pub fn format_datetime<Tz: TimeZone>(time: &DateTime<Tz>, locale: Locale) -> String {
+    match locale {
+        Locale::En => {
+            let (pm, hour) = time.hour12();
+            format!(
+                "{} {}, {}, {}:{:02} {}",
+                ENGLISH_MONTHS[time.month0() as usize],
+                time.day(),
+                time.year(),
+                hour,
+                time.minute(),
+                if pm { "PM" } else { "AM" }
+            )
+        }
+        Locale::Ja => format!(
+            "{}年{}月{}日 {}:{:02}",
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute()
+        ),
+        Locale::De => format!(
+            "{:02}.{:02}.{}, {:02}:{:02}",
+            time.day(),
+            time.month(),
+            time.year(),
+            time.hour(),
+            time.minute()
+        ),
+    }
+}
+
+/// Unix timestamp (ms) in local time; None when it's out of range
+pub fn format_timestamp(timestamp_ms: i64, locale: Locale) -> Option<String> {
+    let time = chrono::Local.timestamp_millis_opt(timestamp_ms).single()?;
+    Some(format_datetime(&time, locale))
+}
+
+/// "Episode 5", "第5話", "Folge 5"
+pub fn episode_label(number: i32, locale: Locale) -> String {
+    locale.episode_template().replace("{n}", &number.to_string())
+}
+
+/// "Chapter 10.5", "第10.5章", "Kapitel 10,5"; whole numbers have no decimals
+pub fn chapter_label(number: f64, locale: Locale) -> String {
+    let number = if number.fract() == 0.0 {
+        format!("{}", number as i64)
+    } else {
+        // Chapter numbers go to one or two decimals (10.5, 10.25)
+        let decimal = format_decimal(number, 2, locale);
+        decimal.trim_end_matches('0').trim_end_matches(locale.decimal_separator()).to_string()
+    };
+    locale.chapter_template().replace("{n}", &number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn afternoon() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 5, 15, 4, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_codes_with_regions() {
+        assert_eq!(Locale::from_code("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::from_code("ja_JP"), Some(Locale::Ja));
+        assert_eq!(Locale::from_code("EN"), Some(Locale::En));
+        assert_eq!(Locale::from_code("fr"), None);
+        assert_eq!(supported_locales().len(), Locale::ALL.len());
+    }
+
+    #[test]
+    fn english_formatting() {
+        let en = Locale::En;
+        assert_eq!(format_number(1234567, en), "1,234,567");
+        assert_eq!(format_number(-999, en), "-999");
+        assert_eq!(format_decimal(1234.56, 1, en), "1,234.6");
+        assert_eq!(format_bytes(1288490188, en), "1.2 GB");
+        assert_eq!(format_bytes(400 * 1024 * 1024, en), "400 MB");
+        assert_eq!(format_datetime(&afternoon(), en), "Jan 5, 2026, 3:04 PM");
+        assert_eq!(episode_label(5, en), "Episode 5");
+        assert_eq!(chapter_label(10.5, en), "Chapter 10.5");
+        assert_eq!(chapter_label(12.0, en), "Chapter 12");
+    }
+
+    #[test]
+    fn japanese_formatting() {
+        let ja = Locale::Ja;
+        assert_eq!(format_number(1234567, ja), "1,234,567");
+        assert_eq!(format_bytes(1288490188, ja), "1.2 GB");
+        assert_eq!(format_datetime(&afternoon(), ja), "2026年1月5日 15:04");
+        assert_eq!(episode_label(5, ja), "第5話");
+        assert_eq!(chapter_label(10.25, ja), "第10.25章");
+    }
+
+    #[test]
+    fn german_formatting() {
+        let de = Locale::De;
+        assert_eq!(format_number(1234567, de), "1.234.567");
+        assert_eq!(format_decimal(-0.04, 1, de), "0,0");
+        assert_eq!(format_decimal(1234.56, 1, de), "1.234,6");
+        assert_eq!(format_bytes(1288490188, de), "1,2 GB");
+        assert_eq!(format_bytes(2048, de), "2 KB");
+        assert_eq!(format_datetime(&afternoon(), de), "05.01.2026, 15:04");
+        assert_eq!(episode_label(5, de), "Folge 5");
+        assert_eq!(chapter_label(10.5, de), "Kapitel 10,5");
+    }
+
+    #[tokio::test]
+    async fn reads_the_locale_setting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = crate::database::Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(current_locale(pool).await, Locale::En);
+        sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, 'de-DE')")
+            .bind(LOCALE_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(current_locale(pool).await, Locale::De);
+    }
+}
@@ -0,0 +1,608 @@
+// Maintenance Scheduler
+//
+// Background chores (expired cache sweep, media cache cap, stats history
+// pruning, database optimize, download verification) run while the app is idle instead of on a
+// fixed timer: no user commands, no playback heartbeats and no active
+// downloads for IDLE_THRESHOLD. Each chore has an interval; a chore that's
+// due waits for the next idle window. When each one last ran is kept in
+// maintenance_runs, so a restart doesn't make everything due again.
+//
+// Download verification is opt-in and takes its interval from its settings
+// (downloads::verify_sweep); while it's off it never comes due.
+//
+// run_maintenance_now runs chores on demand, idle or not.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::{AppHandle, Manager};
+
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::downloads::verify_sweep;
+use crate::downloads::DownloadManager;
+
+/// No activity for this long counts as idle
+pub const IDLE_THRESHOLD: Duration = Duration::from_secs(10 * 60);
+
+/// How often the background task checks for due chores
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Guards against the background task and a manual run overlapping
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Tracks what the user and the app are doing, to tell when it's quiet
+/// enough for maintenance. Held in AppState.
+#[derive(Debug)]
+pub struct ActivityMonitor {
+    /// Unix ms of the last user-triggered command
+    last_command_ms: AtomicI64,
+    /// Unix ms of the last playback heartbeat
+    last_playback_ms: AtomicI64,
+    active_downloads: AtomicUsize,
+}
+
+impl Default for ActivityMonitor {
+    fn default() -> Self {
+        Self::starting_at(now_ms())
+    }
+}
+
+impl ActivityMonitor {
+    /// A monitor that treats `now_ms` (app start) as the last activity
+    pub fn starting_at(now_ms: i64) -> Self {
+        Self {
+            last_command_ms: AtomicI64::new(now_ms),
+            last_playback_ms: AtomicI64::new(now_ms),
+            active_downloads: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record a command invocation. Background polls don't count.
+    pub fn note_command(&self, command: &str) {
+        if crate::cache::warmup::is_user_command(command) {
+            self.note_command_at(now_ms());
+        }
+    }
+
+    pub fn note_command_at(&self, at_ms: i64) {
+        self.last_command_ms.fetch_max(at_ms, Ordering::Relaxed);
+    }
+
+    /// Record a player heartbeat
+    pub fn note_playback(&self) {
+        self.note_playback_at(now_ms());
+    }
+
+    pub fn note_playback_at(&self, at_ms: i64) {
+        self.last_playback_ms.fetch_max(at_ms, Ordering::Relaxed);
+    }
+
+    /// Update the number of downloads in progress
+    pub fn set_active_downloads(&self, count: usize) {
+        self.active_downloads.store(count, Ordering::Relaxed);
+    }
+
+    pub fn active_downloads(&self) -> usize {
+        self.active_downloads.load(Ordering::Relaxed)
+    }
+
+    /// Time since the last command or heartbeat, as of `now_ms`
+    pub fn idle_for_at(&self, now_ms: i64) -> Duration {
+        let last = self
+            .last_command_ms
+            .load(Ordering::Relaxed)
+            .max(self.last_playback_ms.load(Ordering::Relaxed));
+        Duration::from_millis(now_ms.saturating_sub(last).max(0) as u64)
+    }
+
+    /// Whether nothing happened for `threshold` and no download is running
+    pub fn is_idle(&self, threshold: Duration) -> bool {
+        self.is_idle_at(now_ms(), threshold)
+    }
+
+    pub fn is_idle_at(&self, now_ms: i64, threshold: Duration) -> bool {
+        self.active_downloads() == 0 && self.idle_for_at(now_ms) >= threshold
+    }
+}
+
+/// A registered maintenance chore
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Chore {
+    /// Drop expired entries from every cache
+    ExpiredCache,
+    /// Trim the media disk cache to its size cap
+    MediaCache,
+    /// Delete stats history past its retention window
+    StatsHistory,
+    /// VACUUM and ANALYZE the database
+    OptimizeDatabase,
+    /// Re-check a few completed downloads for files that rotted
+    VerifyDownloads,
+}
+
+impl Chore {
+    pub const ALL: [Chore; 5] = [
+        Chore::ExpiredCache,
+        Chore::MediaCache,
+        Chore::StatsHistory,
+        Chore::OptimizeDatabase,
+        Chore::VerifyDownloads,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Chore::ExpiredCache => "expired_cache",
+            Chore::MediaCache => "media_cache",
+            Chore::StatsHistory => "stats_history",
+            Chore::OptimizeDatabase => "optimize_database",
+            Chore::VerifyDownloads => "verify_downloads",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|chore| chore.as_str() == name)
+            .ok_or_else(|| anyhow!("Unknown maintenance chore '{}'", name))
+    }
+
+    /// How long after a run the chore is due again, unless its settings say
+    /// otherwise (see chore_intervals)
+    pub fn interval(&self) -> Duration {
+        match self {
+            Chore::ExpiredCache => Duration::from_secs(6 * 60 * 60),
+            Chore::MediaCache => Duration::from_secs(60 * 60),
+            Chore::StatsHistory => Duration::from_secs(24 * 60 * 60),
+            Chore::OptimizeDatabase => Duration::from_secs(7 * 24 * 60 * 60),
+            Chore::VerifyDownloads => verify_sweep::VerifySchedule::default().interval(),
+        }
+    }
+
+    /// Run the chore, returning a short summary. `downloads` is needed by
+    /// the download chores.
+    async fn run(&self, database: &Database, downloads: Option<&DownloadManager>) -> Result<String> {
+        let pool = database.pool();
+        match self {
+            Chore::ExpiredCache => {
+                let removed = crate::cache::remove_expired_entries(pool).await;
+                Ok(format!("{} expired entries removed", removed))
+            }
+            Chore::MediaCache => {
+                let cleanup = crate::cache::media_disk::enforce(pool).await?;
+                Ok(format!(
+                    "{} files removed ({})",
+                    cleanup.removed_files,
+                    crate::locale::format_bytes(cleanup.freed_bytes, crate::locale::current_locale(pool).await)
+                ))
+            }
+            Chore::StatsHistory => {
+                let removed = crate::stats_history::prune(pool, now_ms() / 1000).await?;
+                Ok(format!("{} old samples removed", removed))
+            }
+            Chore::OptimizeDatabase => {
+                let before = database.get_database_size().await?;
+                database.optimize().await?;
+                let after = database.get_database_size().await?;
+                Ok(format!(
+                    "{} reclaimed",
+                    crate::locale::format_bytes(before.saturating_sub(after), crate::locale::current_locale(pool).await)
+                ))
+            }
+            Chore::VerifyDownloads => {
+                let downloads = downloads.context("Download manager not available")?;
+                let schedule = verify_sweep::load_schedule(pool).await?;
+                let summary = downloads.verify_sweep(schedule.items_per_run).await?;
+                Ok(summary.describe())
+            }
+        }
+    }
+}
+
+/// The interval of every chore that's on. Download verification is left out
+/// unless it's enabled.
+pub async fn chore_intervals(pool: &SqlitePool) -> Result<HashMap<Chore, Duration>> {
+    let mut intervals: HashMap<Chore, Duration> = Chore::ALL.into_iter().map(|chore| (chore, chore.interval())).collect();
+    let schedule = verify_sweep::load_schedule(pool).await?;
+    if schedule.enabled {
+        intervals.insert(Chore::VerifyDownloads, schedule.interval());
+    } else {
+        intervals.remove(&Chore::VerifyDownloads);
+    }
+    Ok(intervals)
+}
+
+/// Chores in `intervals` due at `now_ms`, most overdue first. A chore that
+/// never ran is due.
+pub fn due_chores(last_runs: &HashMap<Chore, i64>, intervals: &HashMap<Chore, Duration>, now_ms: i64) -> Vec<Chore> {
+    let mut due: Vec<(Chore, i64)> = Chore::ALL
+        .into_iter()
+        .filter_map(|chore| {
+            let interval = intervals.get(&chore)?;
+            let overdue = match last_runs.get(&chore) {
+                Some(last_run) => now_ms - (last_run + interval.as_millis() as i64),
+                None => i64::MAX,
+            };
+            (overdue >= 0).then_some((chore, overdue))
+        })
+        .collect();
+    due.sort_by(|a, b| b.1.cmp(&a.1));
+    due.into_iter().map(|(chore, _)| chore).collect()
+}
+
+/// A chore's last run, from maintenance_runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChoreReport {
+    pub chore: Chore,
+    /// Off chores only run on demand
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Unix timestamp (ms), None if it never ran
+    pub last_run: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub last_result: Option<String>,
+    /// Set when the last run failed
+    pub last_error: Option<String>,
+    /// Unix timestamp (ms) the chore is due (again)
+    pub next_due: i64,
+}
+
+/// Idle state and every chore's last run
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub idle: bool,
+    pub idle_for_secs: u64,
+    pub active_downloads: usize,
+    /// Whether chores are running right now
+    pub running: bool,
+    pub chores: Vec<ChoreReport>,
+}
+
+async fn last_runs(pool: &SqlitePool) -> Result<HashMap<Chore, i64>> {
+    let rows = sqlx::query("SELECT chore, last_run FROM maintenance_runs")
+        .fetch_all(pool)
+        .await?;
+
+    // Rows of chores that were since removed are ignored
+    Ok(rows
+        .iter()
+        .filter_map(|row| Some((Chore::parse(row.get("chore")).ok()?, row.get("last_run"))))
+        .collect())
+}
+
+async fn record_run(pool: &SqlitePool, chore: Chore, started_ms: i64, duration_ms: i64, outcome: &Result<String>) -> Result<()> {
+    let (result, error) = match outcome {
+        Ok(summary) => (Some(summary.clone()), None),
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO maintenance_runs (chore, last_run, duration_ms, last_result, last_error)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(chore) DO UPDATE SET
+            last_run = excluded.last_run,
+            duration_ms = excluded.duration_ms,
+            last_result = excluded.last_result,
+            last_error = excluded.last_error
+        "#,
+    )
+    .bind(chore.as_str())
+    .bind(started_ms)
+    .bind(duration_ms)
+    .bind(result)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Run one chore and record it. A failed chore counts as run, so it isn't
+/// retried on every idle check; the error shows in the report.
+async fn run_chore(database: &Database, downloads: Option<&DownloadManager>, chore: Chore, started_ms: i64) -> Result<()> {
+    let started = Instant::now();
+    let outcome = chore.run(database, downloads).await;
+    let duration_ms = started.elapsed().as_millis() as i64;
+
+    match &outcome {
+        Ok(summary) => log::info!("Maintenance: {} done in {} ms ({})", chore.as_str(), duration_ms, summary),
+        Err(e) => log::warn!("Maintenance: {} failed: {:#}", chore.as_str(), e),
+    }
+    record_run(database.pool(), chore, started_ms, duration_ms, &outcome).await
+}
+
+/// Run the chores that are due, most overdue first, for as long as the app
+/// stays idle. `now` is the clock. Returns the chores that ran.
+pub async fn run_due_chores(
+    database: &Database,
+    activity: &ActivityMonitor,
+    downloads: Option<&DownloadManager>,
+    now: impl Fn() -> i64,
+) -> Result<Vec<Chore>> {
+    let intervals = chore_intervals(database.pool()).await?;
+    let due = due_chores(&last_runs(database.pool()).await?, &intervals, now());
+
+    let mut ran = Vec::new();
+    for chore in due {
+        let started_ms = now();
+        if !activity.is_idle_at(started_ms, IDLE_THRESHOLD) {
+            break;
+        }
+        run_chore(database, downloads, chore, started_ms).await?;
+        ran.push(chore);
+    }
+    Ok(ran)
+}
+
+/// Every chore's last run, as of `now_ms`
+pub async fn chore_reports(pool: &SqlitePool, now_ms: i64) -> Result<Vec<ChoreReport>> {
+    let intervals = chore_intervals(pool).await?;
+    let rows = sqlx::query("SELECT chore, last_run, duration_ms, last_result, last_error FROM maintenance_runs")
+        .fetch_all(pool)
+        .await?;
+    let mut runs: HashMap<String, _> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get::<String, _>("chore"),
+                (
+                    row.get::<i64, _>("last_run"),
+                    row.get::<i64, _>("duration_ms"),
+                    row.get::<Option<String>, _>("last_result"),
+                    row.get::<Option<String>, _>("last_error"),
+                ),
+            )
+        })
+        .collect();
+
+    Ok(Chore::ALL
+        .into_iter()
+        .map(|chore| {
+            let enabled = intervals.contains_key(&chore);
+            let interval = intervals.get(&chore).copied().unwrap_or_else(|| chore.interval());
+            match runs.remove(chore.as_str()) {
+                Some((last_run, duration_ms, last_result, last_error)) => ChoreReport {
+                    chore,
+                    enabled,
+                    interval_secs: interval.as_secs(),
+                    last_run: Some(last_run),
+                    duration_ms: Some(duration_ms),
+                    last_result,
+                    last_error,
+                    next_due: last_run + interval.as_millis() as i64,
+                },
+                None => ChoreReport {
+                    chore,
+                    enabled,
+                    interval_secs: interval.as_secs(),
+                    last_run: None,
+                    duration_ms: None,
+                    last_result: None,
+                    last_error: None,
+                    next_due: now_ms,
+                },
+            }
+        })
+        .collect())
+}
+
+/// Idle state and every chore's last run
+pub async fn report(database: &Database, activity: &ActivityMonitor) -> Result<MaintenanceReport> {
+    let now = now_ms();
+    Ok(MaintenanceReport {
+        idle: activity.is_idle_at(now, IDLE_THRESHOLD),
+        idle_for_secs: activity.idle_for_at(now).as_secs(),
+        active_downloads: activity.active_downloads(),
+        running: RUNNING.load(Ordering::SeqCst),
+        chores: chore_reports(database.pool(), now).await?,
+    })
+}
+
+/// Run `chore` (even when it's off), or every chore that's on, right away
+/// whether or not the app is idle
+pub async fn run_now(database: &Database, downloads: Option<&DownloadManager>, chore: Option<Chore>) -> Result<Vec<ChoreReport>> {
+    let chores = match chore {
+        Some(chore) => vec![chore],
+        None => {
+            let intervals = chore_intervals(database.pool()).await?;
+            Chore::ALL.into_iter().filter(|chore| intervals.contains_key(chore)).collect()
+        }
+    };
+
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(anyhow!("Maintenance is already running"));
+    }
+    let mut outcome = Ok(());
+    for chore in chores {
+        outcome = run_chore(database, downloads, chore, now_ms()).await;
+        if outcome.is_err() {
+            break;
+        }
+    }
+    RUNNING.store(false, Ordering::SeqCst);
+
+    outcome?;
+    chore_reports(database.pool(), now_ms()).await
+}
+
+/// Check for due chores in the background and run them while idle
+pub fn start_maintenance_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+
+            let state = app_handle.state::<AppState>();
+            let downloads = app_handle.state::<DownloadManager>();
+            state.activity.set_active_downloads(downloads.active_download_count().await);
+
+            if !state.activity.is_idle(IDLE_THRESHOLD) || RUNNING.swap(true, Ordering::SeqCst) {
+                continue;
+            }
+            if let Err(e) = run_due_chores(&state.database, &state.activity, Some(&downloads), now_ms).await {
+                log::warn!("Maintenance run failed: {:#}", e);
+            }
+            RUNNING.store(false, Ordering::SeqCst);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+    const HOUR: i64 = 60 * MINUTE;
+
+    /// The chores that are on by default
+    const DEFAULT_CHORES: [Chore; 4] =
+        [Chore::ExpiredCache, Chore::MediaCache, Chore::StatsHistory, Chore::OptimizeDatabase];
+
+    fn default_intervals() -> HashMap<Chore, Duration> {
+        DEFAULT_CHORES.into_iter().map(|chore| (chore, chore.interval())).collect()
+    }
+
+    #[test]
+    fn idle_needs_quiet_commands_playback_and_downloads() {
+        let start = 1_000 * HOUR;
+        let activity = ActivityMonitor::starting_at(start);
+        assert!(!activity.is_idle_at(start + 5 * MINUTE, IDLE_THRESHOLD));
+        assert!(activity.is_idle_at(start + 10 * MINUTE, IDLE_THRESHOLD));
+
+        // A command pushes idleness back; an older timestamp doesn't
+        activity.note_command_at(start + 8 * MINUTE);
+        activity.note_command_at(start + 2 * MINUTE);
+        assert!(!activity.is_idle_at(start + 10 * MINUTE, IDLE_THRESHOLD));
+        assert!(activity.is_idle_at(start + 18 * MINUTE, IDLE_THRESHOLD));
+
+        // So does watching something
+        activity.note_playback_at(start + 17 * MINUTE);
+        assert!(!activity.is_idle_at(start + 18 * MINUTE, IDLE_THRESHOLD));
+        assert_eq!(activity.idle_for_at(start + 18 * MINUTE), Duration::from_secs(60));
+
+        // Downloads keep the app busy however long it's been quiet
+        activity.set_active_downloads(2);
+        assert!(!activity.is_idle_at(start + 48 * HOUR, IDLE_THRESHOLD));
+        activity.set_active_downloads(0);
+        assert!(activity.is_idle_at(start + 48 * HOUR, IDLE_THRESHOLD));
+    }
+
+    #[test]
+    fn due_chores_follow_their_intervals() {
+        let now = 1_000 * HOUR;
+        let intervals = default_intervals();
+        assert_eq!(due_chores(&HashMap::new(), &intervals, now), DEFAULT_CHORES.to_vec());
+
+        let last_runs = HashMap::from([
+            (Chore::ExpiredCache, now - 7 * HOUR),
+            (Chore::MediaCache, now - 30 * MINUTE),
+            (Chore::StatsHistory, now - 23 * HOUR),
+            (Chore::OptimizeDatabase, now - 8 * 24 * HOUR),
+        ]);
+        // Most overdue first; the media cache and stats history aren't due yet
+        assert_eq!(due_chores(&last_runs, &intervals, now), vec![Chore::OptimizeDatabase, Chore::ExpiredCache]);
+        assert_eq!(
+            due_chores(&last_runs, &intervals, now + HOUR),
+            vec![Chore::OptimizeDatabase, Chore::ExpiredCache, Chore::MediaCache, Chore::StatsHistory]
+        );
+    }
+
+    #[tokio::test]
+    async fn download_verification_is_due_only_while_enabled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let pool = database.pool();
+        let now = 1_000 * HOUR;
+        let last_runs = HashMap::from([(Chore::VerifyDownloads, now - 7 * HOUR)]);
+
+        let intervals = chore_intervals(pool).await.unwrap();
+        assert_eq!(intervals, default_intervals());
+        assert!(!due_chores(&last_runs, &intervals, now).contains(&Chore::VerifyDownloads));
+
+        let schedule = verify_sweep::VerifySchedule { enabled: true, items_per_run: 5, interval_hours: 6 };
+        verify_sweep::save_schedule(pool, &schedule).await.unwrap();
+        let intervals = chore_intervals(pool).await.unwrap();
+        assert_eq!(intervals[&Chore::VerifyDownloads], Duration::from_secs(6 * 60 * 60));
+        assert_eq!(due_chores(&last_runs, &intervals, now)[0], Chore::VerifyDownloads);
+        assert!(!due_chores(&last_runs, &intervals, now - 2 * HOUR).contains(&Chore::VerifyDownloads));
+    }
+
+    #[tokio::test]
+    async fn the_verify_chore_sweeps_downloads() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let downloads = DownloadManager::new(temp_dir.path().join("downloads"))
+            .with_database(std::sync::Arc::new(database.pool().clone()));
+
+        let path = temp_dir.path().join("ep1.mp4");
+        let mut bytes = vec![0u8; 1000];
+        bytes[..12].copy_from_slice(b"\x00\x00\x00\x20ftypisom");
+        std::fs::write(&path, &bytes).unwrap();
+        downloads.adopt_file("show", 1, &path, None).await.unwrap();
+        std::fs::write(&path, &bytes[..300]).unwrap();
+
+        let reports = run_now(&database, Some(&downloads), Some(Chore::VerifyDownloads)).await.unwrap();
+        let report = reports.iter().find(|r| r.chore == Chore::VerifyDownloads).unwrap();
+        assert!(!report.enabled, "run on demand while off");
+        assert_eq!(report.last_result.as_deref(), Some("1 checked, 1 corrupt, 0 missing"));
+    }
+
+    #[test]
+    fn chore_names_round_trip() {
+        for chore in Chore::ALL {
+            assert_eq!(Chore::parse(chore.as_str()).unwrap(), chore);
+        }
+        assert!(Chore::parse("defragment").is_err());
+    }
+
+    #[tokio::test]
+    async fn chores_only_run_when_idle_and_due() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let start = chrono::Utc::now().timestamp_millis();
+        let clock = AtomicI64::new(start);
+        let now = || clock.load(Ordering::SeqCst);
+        let activity = ActivityMonitor::starting_at(start);
+
+        // Just started: nothing runs
+        assert!(run_due_chores(&database, &activity, None, now).await.unwrap().is_empty());
+
+        // A download is running
+        clock.store(start + 30 * MINUTE, Ordering::SeqCst);
+        activity.set_active_downloads(1);
+        assert!(run_due_chores(&database, &activity, None, now).await.unwrap().is_empty());
+        activity.set_active_downloads(0);
+
+        // Something is playing
+        activity.note_playback_at(start + 25 * MINUTE);
+        assert!(run_due_chores(&database, &activity, None, now).await.unwrap().is_empty());
+
+        // Quiet: every chore that's on is due on a fresh database
+        clock.store(start + 40 * MINUTE, Ordering::SeqCst);
+        assert_eq!(run_due_chores(&database, &activity, None, now).await.unwrap(), DEFAULT_CHORES.to_vec());
+        assert!(run_due_chores(&database, &activity, None, now).await.unwrap().is_empty());
+
+        let reports = chore_reports(database.pool(), now()).await.unwrap();
+        let (on, off): (Vec<&ChoreReport>, Vec<&ChoreReport>) = reports.iter().partition(|r| r.enabled);
+        assert!(on.iter().all(|r| r.last_run == Some(start + 40 * MINUTE) && r.last_error.is_none()));
+        assert_eq!(off.len(), 1);
+        assert_eq!((off[0].chore, off[0].last_run), (Chore::VerifyDownloads, None));
+        let cache = reports.iter().find(|r| r.chore == Chore::ExpiredCache).unwrap();
+        assert_eq!(cache.next_due, start + 40 * MINUTE + 6 * HOUR);
+
+        // Seven hours later only the cache chores are due again
+        clock.store(start + 40 * MINUTE + 7 * HOUR, Ordering::SeqCst);
+        assert_eq!(
+            run_due_chores(&database, &activity, None, now).await.unwrap(),
+            vec![Chore::MediaCache, Chore::ExpiredCache]
+        );
+    }
+}
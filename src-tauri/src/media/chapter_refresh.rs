@@ -0,0 +1,201 @@
+// Chapter Image Refresh
+//
+// Chapter image URLs are signed and expire like video URLs do, so partway
+// through a chapter the remaining pages start failing with 403. The reader
+// reports the failed page and gets the chapter's images fetched afresh from
+// the extension. Pages fail together, so refreshes are coalesced per chapter:
+// the first report fetches, and reports arriving while it runs (or shortly
+// after) get the same result instead of fetching again. Refreshes are kept
+// apart by adult mode, so a filtered reader never gets pages fetched with
+// adult content allowed.
+//
+// Each failure is also tallied by image host, so hosts whose URLs keep
+// expiring show up in get_image_host_failures.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::extensions::types::ChapterImages;
+
+/// A refresh finished this recently is handed to later reports as is
+const REUSE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Result of the last refresh of a chapter
+struct Refresh {
+    at: Instant,
+    images: ChapterImages,
+}
+
+type RefreshSlot = Arc<tokio::sync::Mutex<Option<Refresh>>>;
+
+/// Per-chapter refresh slots, keyed by extension, chapter and adult mode. A
+/// report holds its chapter's slot while fetching, so concurrent reports for
+/// the chapter wait for that fetch.
+static REFRESHES: LazyLock<Mutex<HashMap<String, RefreshSlot>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Failed chapter images seen for one host
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageHostFailures {
+    pub host: String,
+    pub failures: u32,
+    /// Unix timestamp (ms) of the latest failure
+    pub last_failure_at: i64,
+}
+
+static HOST_FAILURES: LazyLock<Mutex<HashMap<String, ImageHostFailures>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What the reader gets back after reporting a failed page
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterImageRefresh {
+    pub page_index: usize,
+    /// Fresh URL for the failed page; None if the chapter no longer has that page
+    pub url: Option<String>,
+    /// All of the chapter's pages with fresh URLs
+    pub images: ChapterImages,
+}
+
+fn slot_for(key: &str) -> RefreshSlot {
+    let mut refreshes = REFRESHES.lock().unwrap();
+
+    // Drop slots nobody is using whose result is too old to be reused
+    refreshes.retain(|_, slot| {
+        Arc::strong_count(slot) > 1
+            || slot
+                .try_lock()
+                .map_or(true, |refresh| refresh.as_ref().is_some_and(|r| r.at.elapsed() < REUSE_WINDOW))
+    });
+
+    refreshes.entry(key.to_string()).or_default().clone()
+}
+
+/// Fetch a chapter's images again with `fetch`, unless another report for
+/// the chapter in the same adult mode is already doing so or just did
+pub async fn refresh_chapter<F, Fut>(
+    extension_id: &str,
+    chapter_id: &str,
+    allow_adult: bool,
+    fetch: F,
+) -> Result<ChapterImages, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<ChapterImages, String>>,
+{
+    let key = format!("{}:{}:{}", extension_id, chapter_id, if allow_adult { "adult" } else { "filtered" });
+    let slot = slot_for(&key);
+    let mut refresh = slot.lock().await;
+
+    if let Some(recent) = refresh.as_ref().filter(|r| r.at.elapsed() < REUSE_WINDOW) {
+        log::debug!("Reusing refreshed images for chapter {}", chapter_id);
+        return Ok(recent.images.clone());
+    }
+
+    let images = fetch().await?;
+    log::debug!("Refreshed images for chapter {} ({} pages)", chapter_id, images.images.len());
+    *refresh = Some(Refresh {
+        at: Instant::now(),
+        images: images.clone(),
+    });
+    Ok(images)
+}
+
+/// Count a failed image against its host
+pub fn record_image_failure(url: &str) {
+    let Some(host) = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)) else {
+        return;
+    };
+
+    let mut failures = HOST_FAILURES.lock().unwrap();
+    let entry = failures.entry(host.clone()).or_insert_with(|| ImageHostFailures {
+        host,
+        failures: 0,
+        last_failure_at: 0,
+    });
+    entry.failures += 1;
+    entry.last_failure_at = chrono::Utc::now().timestamp_millis();
+}
+
+/// Image hosts with failures this run, most failures first
+pub fn image_host_failures() -> Vec<ImageHostFailures> {
+    let mut hosts: Vec<ImageHostFailures> = HOST_FAILURES.lock().unwrap().values().cloned().collect();
+    hosts.sort_by(|a, b| b.failures.cmp(&a.failures).then_with(|| a.host.cmp(&b.host)));
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::types::ChapterImage;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn images(token: &str) -> ChapterImages {
+        ChapterImages {
+            images: (1..=3)
+                .map(|page| ChapterImage {
+                    url: format!("https://cdn.example.com/{}.jpg?token={}", page, token),
+                    page,
+                    width: None,
+                    height: None,
+                    is_spread: false,
+                })
+                .collect(),
+            total_pages: 3,
+            title: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_failures_share_one_refresh() {
+        let fetches = Arc::new(AtomicU32::new(0));
+
+        let reports = (0..10).map(|_| {
+            let fetches = fetches.clone();
+            refresh_chapter("ext", "coalesce-chapter", false, move || async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(images("fresh"))
+            })
+        });
+        let results = futures_util::future::join_all(reports).await;
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| r.as_ref().unwrap().images[0].url.ends_with("token=fresh")));
+    }
+
+    #[tokio::test]
+    async fn failed_refreshes_are_not_reused() {
+        let failed = refresh_chapter("ext", "failing-chapter", false, || async { Err("boom".to_string()) }).await;
+        assert_eq!(failed.unwrap_err(), "boom");
+
+        let retried = refresh_chapter("ext", "failing-chapter", false, || async { Ok(images("second")) }).await;
+        assert!(retried.unwrap().images[0].url.ends_with("token=second"));
+    }
+
+    #[tokio::test]
+    async fn refreshes_are_not_shared_across_adult_modes() {
+        let unfiltered = refresh_chapter("ext", "mode-chapter", true, || async { Ok(images("unfiltered")) }).await;
+        assert!(unfiltered.unwrap().images[0].url.ends_with("token=unfiltered"));
+
+        // Within the reuse window, but a filtered report still fetches its own
+        let filtered = refresh_chapter("ext", "mode-chapter", false, || async { Ok(images("filtered")) }).await;
+        assert!(filtered.unwrap().images[0].url.ends_with("token=filtered"));
+
+        let reused = refresh_chapter("ext", "mode-chapter", true, || async { Ok(images("again")) }).await;
+        assert!(reused.unwrap().images[0].url.ends_with("token=unfiltered"));
+    }
+
+    #[test]
+    fn failures_are_tallied_per_host() {
+        record_image_failure("https://tally.example.org/1.jpg?token=a");
+        record_image_failure("https://tally.example.org/2.jpg?token=a");
+        record_image_failure("not a url");
+
+        let hosts = image_host_failures();
+        let host = hosts.iter().find(|h| h.host == "tally.example.org").unwrap();
+        assert_eq!(host.failures, 2);
+        assert!(host.last_failure_at > 0);
+    }
+}
@@ -0,0 +1,385 @@
+// Image Size Probing
+//
+// Reads page dimensions from the first bytes of an image (PNG, JPEG, GIF,
+// WebP headers) without decoding it, so the manga reader can tell two-page
+// spreads apart from regular portrait pages. Remote pages are probed with a
+// ranged request for just the header; downloaded pages are read from disk.
+// Chapter images are returned right away with the sizes the extension
+// reported; the missing ones are probed a few at a time in the background and
+// sent to the reader afterwards.
+
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::events::CHAPTER_IMAGE_SIZES_EVENT;
+use crate::extensions::ChapterImages;
+use crate::request_headers::build_image_request;
+
+/// Pages wider than this ratio (width / height) are treated as spreads
+pub const SPREAD_ASPECT_RATIO: f64 = 1.2;
+
+/// How much of an image is fetched/read to find its dimensions. JPEGs with
+/// large EXIF blocks can put the frame header past the first few KB.
+const PROBE_BYTES: u64 = 64 * 1024;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pages of one chapter probed at once
+const MAX_CONCURRENT_PROBES: usize = 4;
+
+/// Width and height of an image in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Size of a chapter page found by probing, after the images were returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct PageSize {
+    pub page: u32,
+    pub width: u32,
+    pub height: u32,
+    pub is_spread: bool,
+}
+
+/// Probed page sizes of a chapter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+pub struct ChapterImageSizes {
+    pub chapter_id: String,
+    pub sizes: Vec<PageSize>,
+}
+
+impl ImageSize {
+    pub fn is_spread(&self) -> bool {
+        is_spread(self.width, self.height)
+    }
+}
+
+/// Whether a page of the given size is a double-page spread
+pub fn is_spread(width: u32, height: u32) -> bool {
+    height > 0 && width as f64 / height as f64 > SPREAD_ASPECT_RATIO
+}
+
+/// Read dimensions from the start of an image file. Returns None for
+/// unsupported formats or when the header is cut off.
+pub fn read_image_size(bytes: &[u8]) -> Option<ImageSize> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_size(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_size(bytes)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        gif_size(bytes)
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        webp_size(bytes)
+    } else {
+        None
+    }
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn png_size(bytes: &[u8]) -> Option<ImageSize> {
+    // IHDR is always the first chunk: width and height follow its type
+    if bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    Some(ImageSize { width, height })
+}
+
+fn gif_size(bytes: &[u8]) -> Option<ImageSize> {
+    Some(ImageSize {
+        width: le_u16(bytes, 6)?,
+        height: le_u16(bytes, 8)?,
+    })
+}
+
+fn jpeg_size(bytes: &[u8]) -> Option<ImageSize> {
+    // Walk the marker segments until a start-of-frame (SOF0..SOF15, minus
+    // DHT/JPG/DAC which share the range)
+    let mut pos = 2;
+    loop {
+        while *bytes.get(pos)? != 0xFF {
+            pos += 1;
+        }
+        while *bytes.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = *bytes.get(pos)?;
+        pos += 1;
+
+        match marker {
+            // Standalone markers without a length
+            0x01 | 0xD0..=0xD7 => continue,
+            0xD9 | 0xDA => return None,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some(ImageSize {
+                    height: be_u16(bytes, pos + 3)?,
+                    width: be_u16(bytes, pos + 5)?,
+                });
+            }
+            _ => pos += be_u16(bytes, pos)? as usize,
+        }
+    }
+}
+
+fn webp_size(bytes: &[u8]) -> Option<ImageSize> {
+    match bytes.get(12..16)? {
+        // Lossy: frame header after the 3-byte frame tag and start code
+        b"VP8 " => Some(ImageSize {
+            width: le_u16(bytes, 26)? & 0x3FFF,
+            height: le_u16(bytes, 28)? & 0x3FFF,
+        }),
+        // Lossless: 14-bit width-1 and height-1 packed after the signature byte
+        b"VP8L" => {
+            let b = bytes.get(21..25)?;
+            let bits = u32::from_le_bytes(b.try_into().ok()?);
+            Some(ImageSize {
+                width: (bits & 0x3FFF) + 1,
+                height: ((bits >> 14) & 0x3FFF) + 1,
+            })
+        }
+        // Extended: 24-bit canvas width-1 and height-1
+        b"VP8X" => Some(ImageSize {
+            width: le_u24(bytes, 24)? + 1,
+            height: le_u24(bytes, 27)? + 1,
+        }),
+        _ => None,
+    }
+}
+
+/// Read the dimensions of an image on disk
+pub fn probe_file(path: &Path) -> Option<ImageSize> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bytes = Vec::new();
+    file.take(PROBE_BYTES).read_to_end(&mut bytes).ok()?;
+    read_image_size(&bytes)
+}
+
+/// Fetch just the header of a remote image and read its dimensions
+pub fn probe_url(url: &str) -> Option<ImageSize> {
+    let request = build_image_request(url).ok()?
+        .set("Range", &format!("bytes=0-{}", PROBE_BYTES - 1))
+        .timeout(PROBE_TIMEOUT);
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(e) => {
+            log::debug!("Failed to probe image size for {}: {}", url, e);
+            return None;
+        }
+    };
+
+    // Servers that ignore Range send the whole image; only the start is read
+    let mut bytes = Vec::new();
+    response.into_reader().take(PROBE_BYTES).read_to_end(&mut bytes).ok()?;
+    read_image_size(&bytes)
+}
+
+/// Set is_spread for the pages whose size the extension reported
+pub fn mark_spreads(images: &mut ChapterImages) {
+    for image in &mut images.images {
+        if let (Some(width), Some(height)) = (image.width, image.height) {
+            image.is_spread = is_spread(width, height);
+        }
+    }
+}
+
+/// Probe the pages the extension gave no size for, a few at a time
+pub async fn probe_missing_sizes(images: &ChapterImages) -> Vec<PageSize> {
+    let missing: Vec<(u32, String)> = images
+        .images
+        .iter()
+        .filter(|image| image.width.is_none() || image.height.is_none())
+        .map(|image| (image.page, image.url.clone()))
+        .collect();
+
+    let probes = missing.into_iter().map(|(page, url)| async move {
+        let size = tokio::task::spawn_blocking(move || probe_url(&url))
+            .await
+            .ok()
+            .flatten()?;
+        Some(PageSize {
+            page,
+            width: size.width,
+            height: size.height,
+            is_spread: size.is_spread(),
+        })
+    });
+
+    let mut sizes: Vec<PageSize> = stream::iter(probes)
+        .buffer_unordered(MAX_CONCURRENT_PROBES)
+        .filter_map(|size| async move { size })
+        .collect()
+        .await;
+    sizes.sort_by_key(|size| size.page);
+    sizes
+}
+
+/// Probe a chapter's missing page sizes in the background and emit them as
+/// chapter-image-sizes, so the images can be shown before the probes finish
+pub fn spawn_size_probes(app_handle: AppHandle, chapter_id: String, images: ChapterImages) {
+    if images.images.iter().all(|image| image.width.is_some() && image.height.is_some()) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let sizes = probe_missing_sizes(&images).await;
+        if !sizes.is_empty() {
+            CHAPTER_IMAGE_SIZES_EVENT.emit(&app_handle, &ChapterImageSizes { chapter_id, sizes });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 2, 0, 0, 0]);
+        bytes
+    }
+
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        // APP0 (JFIF) segment ahead of the frame header
+        bytes.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        bytes.extend_from_slice(b"JFIF\x00\x01\x01\x00\x00\x01\x00\x01\x00\x00");
+        // DHT segment, which sits in the SOF marker range but isn't one
+        bytes.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x04, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&[0x03, 0x01, 0x22, 0x00]);
+        bytes
+    }
+
+    fn gif(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes
+    }
+
+    fn webp_lossy(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = b"RIFF\x00\x00\x00\x00WEBPVP8 \x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x9D, 0x01, 0x2A]);
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes
+    }
+
+    fn webp_lossless(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\x00\x00\x00\x00WEBPVP8L\x00\x00\x00\x00\x2F".to_vec();
+        let bits = (width - 1) | ((height - 1) << 14);
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        bytes
+    }
+
+    fn webp_extended(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\x00\x00\x00\x00WEBPVP8X\x0A\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        bytes.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        bytes
+    }
+
+    #[test]
+    fn reads_portrait_and_landscape_pages() {
+        let cases = [
+            (png(800, 1200), 800, 1200, false),
+            (png(1600, 1200), 1600, 1200, true),
+            (jpeg(720, 1024), 720, 1024, false),
+            (jpeg(2048, 1440), 2048, 1440, true),
+            (gif(600, 900), 600, 900, false),
+            (webp_lossy(960, 1400), 960, 1400, false),
+            (webp_lossless(1900, 1300), 1900, 1300, true),
+            (webp_extended(3000, 2000), 3000, 2000, true),
+        ];
+
+        for (bytes, width, height, spread) in cases {
+            let size = read_image_size(&bytes).expect("size");
+            assert_eq!(size, ImageSize { width, height });
+            assert_eq!(size.is_spread(), spread, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn truncated_or_unknown_headers_return_none() {
+        assert_eq!(read_image_size(&png(800, 1200)[..20]), None);
+        assert_eq!(read_image_size(&jpeg(800, 1200)[..30]), None);
+        assert_eq!(read_image_size(b"<html>Forbidden</html>"), None);
+        assert_eq!(read_image_size(&[]), None);
+    }
+
+    #[test]
+    fn square_ish_pages_are_not_spreads() {
+        assert!(!is_spread(1000, 1000));
+        assert!(!is_spread(1200, 1000));
+        assert!(is_spread(1210, 1000));
+        assert!(!is_spread(100, 0));
+    }
+
+    fn page(page: u32, size: Option<(u32, u32)>) -> crate::extensions::ChapterImage {
+        crate::extensions::ChapterImage {
+            url: format!("http://127.0.0.1:9/page_{}.png", page),
+            page,
+            width: size.map(|(w, _)| w),
+            height: size.map(|(_, h)| h),
+            is_spread: false,
+        }
+    }
+
+    #[test]
+    fn mark_spreads_uses_reported_sizes_only() {
+        let mut images = ChapterImages {
+            images: vec![page(1, Some((800, 1200))), page(2, Some((1600, 1200))), page(3, None)],
+            total_pages: 3,
+            title: None,
+        };
+        mark_spreads(&mut images);
+
+        let spreads: Vec<bool> = images.images.iter().map(|image| image.is_spread).collect();
+        assert_eq!(spreads, vec![false, true, false]);
+    }
+
+    #[tokio::test]
+    async fn pages_with_reported_sizes_are_not_probed() {
+        let images = ChapterImages {
+            images: vec![page(1, Some((800, 1200))), page(2, Some((1600, 1200)))],
+            total_pages: 2,
+            title: None,
+        };
+        assert!(probe_missing_sizes(&images).await.is_empty());
+    }
+
+    #[test]
+    fn probe_file_reads_header_from_disk() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("page_0001.png");
+        std::fs::write(&path, png(1800, 1300)).unwrap();
+
+        assert_eq!(probe_file(&path), Some(ImageSize { width: 1800, height: 1300 }));
+        assert_eq!(probe_file(&temp_dir.path().join("missing.png")), None);
+    }
+}
@@ -5,6 +5,13 @@
 // - Image processing and optimization
 // - Thumbnail generation
 // - CORS bypass for media sources
+// - MKV → MP4 remuxing for in-app playback (remux.rs)
+// - Page dimension probing for manga spreads (image_size.rs)
+// - Refreshing expired chapter image URLs (chapter_refresh.rs)
+
+pub mod chapter_refresh;
+pub mod image_size;
+pub mod remux;
 
 // Submodules (to be created in Phase 2, Week 6)
 // pub mod video;
@@ -0,0 +1,285 @@
+// MKV → MP4 Remuxing
+//
+// The webview's media element can't play Matroska, so MKV downloads are
+// streamed through ffmpeg with `-c copy` into fragmented MP4. Nothing is
+// written to disk: ffmpeg's stdout is piped straight into the HTTP response
+// and the process is killed as soon as the client disconnects.
+//
+// ffmpeg is optional. When it isn't installed, MKV downloads stay
+// downloadable but callers get a clear error instead of a broken player.
+
+use anyhow::Result;
+use futures_util::{Stream, StreamExt};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::downloads::obfuscation;
+
+/// Error shown when an MKV needs remuxing but ffmpeg can't be found
+pub const FFMPEG_MISSING_ERROR: &str =
+    "This episode is an MKV file, which can't be played in-app without ffmpeg. Install ffmpeg and restart Otaku, or open the file in an external player.";
+
+/// Bytes in an MPEG-TS packet
+const TS_PACKET_SIZE: usize = 188;
+
+/// Container formats we care about when deciding how to serve a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Mp4,
+    Matroska,
+    WebM,
+    /// HLS downloads kept as joined segments when ffmpeg isn't available
+    MpegTs,
+    /// QuickTime (.mov): ISO BMFF without a leading ftyp box
+    QuickTime,
+    /// AVI: a RIFF file of type "AVI "
+    Avi,
+    Unknown,
+}
+
+impl Container {
+    /// Whether the webview can't play this container directly
+    pub fn needs_remux(&self) -> bool {
+        matches!(self, Container::Matroska)
+    }
+}
+
+/// Check whether a file is XOR-obfuscated (.otaku extension)
+pub fn is_obfuscated(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("otaku"))
+        .unwrap_or(false)
+}
+
+/// Sniff the container from the file's magic bytes.
+/// Obfuscated files are decrypted first, since .otaku can wrap any container.
+pub async fn detect_container(path: &Path) -> Result<Container> {
+    let mut file = tokio::fs::File::open(path).await?;
+    // Long enough to reach the second MPEG-TS packet
+    let mut header = vec![0u8; TS_PACKET_SIZE + 1];
+    let mut read = 0;
+    while read < header.len() {
+        let n = file.read(&mut header[read..]).await?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    header.truncate(read);
+
+    if is_obfuscated(path) {
+        obfuscation::xor_transform(&mut header, 0);
+    }
+
+    Ok(container_from_header(&header))
+}
+
+fn container_from_header(header: &[u8]) -> Container {
+    // EBML magic; Matroska and WebM share it and differ only by DocType
+    if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        if header.windows(4).any(|w| w == b"webm") {
+            return Container::WebM;
+        }
+        return Container::Matroska;
+    }
+
+    // ISO BMFF: box size (4 bytes) followed by "ftyp"
+    if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        return Container::Mp4;
+    }
+    // Older QuickTime files start straight with a movie, media data or
+    // padding box
+    if header.len() >= 8 && [b"moov", b"mdat", b"free", b"wide", b"skip"].iter().any(|b| &header[4..8] == *b) {
+        return Container::QuickTime;
+    }
+
+    if header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"AVI " {
+        return Container::Avi;
+    }
+
+    // A sync byte at the start of each of the first two packets
+    if header.len() > TS_PACKET_SIZE && header[0] == 0x47 && header[TS_PACKET_SIZE] == 0x47 {
+        return Container::MpegTs;
+    }
+
+    Container::Unknown
+}
+
+/// Locate the ffmpeg binary. Checked once and cached for the process lifetime.
+///
+/// GUI apps on macOS don't inherit the shell PATH, so the usual Homebrew
+/// locations are tried after the bare name.
+pub fn ffmpeg_path() -> Option<&'static PathBuf> {
+    static FFMPEG: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    FFMPEG
+        .get_or_init(|| {
+            let candidates = [
+                PathBuf::from("ffmpeg"),
+                PathBuf::from("/opt/homebrew/bin/ffmpeg"),
+                PathBuf::from("/usr/local/bin/ffmpeg"),
+            ];
+
+            let found = candidates.into_iter().find(|candidate| {
+                std::process::Command::new(candidate)
+                    .arg("-version")
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .map(|s| s.success())
+                    .unwrap_or(false)
+            });
+
+            match &found {
+                Some(path) => log::debug!("Found ffmpeg at {:?}", path),
+                None => log::debug!("ffmpeg not found, MKV remuxing disabled"),
+            }
+
+            found
+        })
+        .as_ref()
+}
+
+/// Whether on-the-fly remuxing is available
+pub fn ffmpeg_available() -> bool {
+    ffmpeg_path().is_some()
+}
+
+/// Spawn ffmpeg to remux `input` into fragmented MP4 and return its output
+/// as a byte stream.
+///
+/// Streams are copied as-is (`-c copy`); only the first video track and any
+/// audio tracks are kept because MKV subtitle formats (ASS/PGS) can't go in
+/// MP4 without transcoding. Dropping the stream kills ffmpeg.
+pub fn remux_to_mp4_stream(
+    input: &Path,
+) -> Result<impl Stream<Item = std::io::Result<axum::body::Bytes>> + Send + 'static> {
+    let ffmpeg = ffmpeg_path().ok_or_else(|| anyhow::anyhow!(FFMPEG_MISSING_ERROR))?;
+    let obfuscated = is_obfuscated(input);
+
+    let mut command = Command::new(ffmpeg);
+    command.args(["-hide_banner", "-loglevel", "error"]);
+    if obfuscated {
+        // Decrypted bytes are fed through stdin below
+        command.args(["-i", "pipe:0"]);
+    } else {
+        command.arg("-nostdin").arg("-i").arg(input);
+    }
+    command
+        .args([
+            "-map", "0:v:0",
+            "-map", "0:a?",
+            "-sn",
+            "-dn",
+            "-c", "copy",
+            "-movflags", "frag_keyframe+empty_moov+default_base_moof",
+            "-f", "mp4",
+            "pipe:1",
+        ])
+        .stdin(if obfuscated { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Failed to capture ffmpeg output"))?;
+
+    if obfuscated {
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open ffmpeg input"))?;
+        let input = input.to_path_buf();
+
+        tokio::spawn(async move {
+            let mut file = match tokio::fs::File::open(&input).await {
+                Ok(f) => f,
+                Err(e) => {
+                    log::error!("Failed to open {:?} for remux: {}", input, e);
+                    return;
+                }
+            };
+
+            let mut offset = 0u64;
+            let mut buf = vec![0u8; 256 * 1024];
+            loop {
+                let n = match file.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) => {
+                        log::error!("Failed to read {:?} for remux: {}", input, e);
+                        break;
+                    }
+                };
+                obfuscation::xor_transform(&mut buf[..n], offset);
+                offset += n as u64;
+
+                // A write error means ffmpeg exited (usually the client went away)
+                if stdin.write_all(&buf[..n]).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let stream = async_stream::stream! {
+        // Keep the child alive for as long as the response body is; kill_on_drop
+        // stops ffmpeg when the player disconnects or seeks away
+        let _child = child;
+        let mut output = tokio_util::io::ReaderStream::new(stdout);
+        while let Some(chunk) = output.next().await {
+            yield chunk;
+        }
+    };
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_from_header() {
+        let mut mkv = vec![0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81, 0x01];
+        mkv.extend_from_slice(b"\x42\x82\x88matroska");
+        assert_eq!(container_from_header(&mkv), Container::Matroska);
+
+        let mut webm = vec![0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81, 0x01];
+        webm.extend_from_slice(b"\x42\x82\x84webm");
+        assert_eq!(container_from_header(&webm), Container::WebM);
+
+        assert_eq!(container_from_header(b"\x00\x00\x00\x20ftypisom"), Container::Mp4);
+        assert_eq!(container_from_header(b"\x00\x00\x00\x08wide\x00\x10\x00\x00mdat"), Container::QuickTime);
+        assert_eq!(container_from_header(b"\x00\x00\x6c\x8dmoovlmvhd"), Container::QuickTime);
+        assert_eq!(container_from_header(b"RIFF\x24\x00\x10\x00AVI LIST"), Container::Avi);
+        assert_eq!(container_from_header(b"RIFF\x24\x00\x10\x00WAVEfmt "), Container::Unknown);
+
+        let mut ts = vec![0u8; TS_PACKET_SIZE * 2];
+        ts[0] = 0x47;
+        ts[TS_PACKET_SIZE] = 0x47;
+        assert_eq!(container_from_header(&ts), Container::MpegTs);
+        assert_eq!(container_from_header(&ts[..TS_PACKET_SIZE]), Container::Unknown);
+        assert_eq!(container_from_header(b"not a video"), Container::Unknown);
+        assert_eq!(container_from_header(&[]), Container::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_detect_container_decrypts_obfuscated_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("Episode_1.otaku");
+
+        let mut data = b"\x1A\x45\xDF\xA3\x9F\x42\x82\x88matroska".to_vec();
+        obfuscation::xor_transform(&mut data, 0);
+        tokio::fs::write(&path, &data).await.unwrap();
+
+        assert_eq!(detect_container(&path).await.unwrap(), Container::Matroska);
+    }
+}
@@ -0,0 +1,382 @@
+// Imported Media Hydration
+//
+// Libraries imported from a backup or another app arrive as bare rows: a
+// title and an id, often without a description or cover. This walks library
+// media missing either one and fills them in: anime through Jikan enrichment,
+// manga from the row's own extension. Requests are paced so a large import
+// doesn't hammer either source.
+//
+// Like the cover refresh, rows are processed in id order and the last
+// finished id is kept in app_settings, so a cancelled or interrupted run
+// resumes where it stopped. Only empty fields are ever written, which makes
+// running it again harmless.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::AppHandle;
+
+use crate::events::MEDIA_HYDRATION_EVENT;
+use crate::extensions::types::MangaDetails;
+use crate::jikan::enrichment;
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// app_settings key holding the last processed media id of an unfinished run
+const CURSOR_SETTING: &str = "media_hydration_cursor";
+
+/// Pause between rows; Jikan's client limits itself too, extensions don't
+const REQUEST_DELAY: Duration = Duration::from_millis(1500);
+
+/// Prevents two runs from sharing the cursor
+static HYDRATION_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Set by cancel_hydration, checked between rows
+static HYDRATION_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, schemars::JsonSchema)]
+pub struct MediaHydrationProgress {
+    pub total: usize,
+    pub processed: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub current_title: String,
+    pub status: String, // "running" | "completed" | "cancelled"
+}
+
+struct HydrationCandidate {
+    id: String,
+    title: String,
+    extension_id: String,
+    media_type: String,
+}
+
+/// Library media after the cursor that lack a description or cover
+async fn load_candidates(pool: &SqlitePool, after: Option<&str>) -> Result<Vec<HydrationCandidate>, String> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, title, extension_id, media_type
+        FROM media
+        WHERE id > ?
+          AND (description IS NULL OR description = '' OR cover_url IS NULL OR cover_url = '')
+          AND EXISTS (SELECT 1 FROM library l WHERE l.media_id = media.id)
+        ORDER BY id
+        "#,
+    )
+    .bind(after.unwrap_or(""))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| HydrationCandidate {
+            id: row.get("id"),
+            title: row.get("title"),
+            extension_id: row.get("extension_id"),
+            media_type: row.get("media_type"),
+        })
+        .collect())
+}
+
+async fn read_cursor(pool: &SqlitePool) -> Result<Option<String>, String> {
+    sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(CURSOR_SETTING)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))
+}
+
+async fn write_cursor(pool: &SqlitePool, media_id: Option<&str>) -> Result<(), String> {
+    let query = match media_id {
+        Some(id) => sqlx::query(
+            "INSERT OR REPLACE INTO app_settings (key, value, updated_at) VALUES (?, ?, strftime('%s', 'now') * 1000)",
+        )
+        .bind(CURSOR_SETTING)
+        .bind(id),
+        None => sqlx::query("DELETE FROM app_settings WHERE key = ?").bind(CURSOR_SETTING),
+    };
+
+    query
+        .execute(pool)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+    Ok(())
+}
+
+/// Fill the manga row's empty description and cover from the extension's
+/// details. Returns whether anything was written.
+async fn apply_manga_details(pool: &SqlitePool, media_id: &str, details: &MangaDetails) -> Result<bool, String> {
+    let description = details.description.as_deref().filter(|d| !d.trim().is_empty());
+    let cover_url = details.cover_url.as_deref().filter(|u| !u.is_empty());
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE media SET
+            description = CASE WHEN description IS NULL OR description = '' THEN COALESCE(?, description) ELSE description END,
+            cover_url = CASE WHEN cover_url IS NULL OR cover_url = '' THEN COALESCE(?, cover_url) ELSE cover_url END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+          AND ((? IS NOT NULL AND (description IS NULL OR description = ''))
+            OR (? IS NOT NULL AND (cover_url IS NULL OR cover_url = '')))
+        "#,
+    )
+    .bind(description)
+    .bind(cover_url)
+    .bind(media_id)
+    .bind(description)
+    .bind(cover_url)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("DB error: {}", e))?
+    .rows_affected();
+
+    Ok(updated > 0)
+}
+
+/// Hydrate one row. Ok(true) when something was filled in.
+async fn hydrate_one<F, Fut>(pool: &SqlitePool, candidate: &HydrationCandidate, fetch_manga: &F) -> Result<bool, String>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<MangaDetails, String>>,
+{
+    match candidate.media_type.as_str() {
+        "anime" => {
+            let result = enrichment::enrich_media(pool, &candidate.id).await?;
+            Ok(!result.fields_filled.is_empty())
+        }
+        "manga" => {
+            let details = fetch_manga(candidate.extension_id.clone(), candidate.id.clone()).await?;
+            apply_manga_details(pool, &candidate.id, &details).await
+        }
+        other => Err(format!("Unsupported media type: {}", other)),
+    }
+}
+
+/// Ask a running hydration to stop after the current row. Returns whether one was running.
+pub fn cancel_hydration() -> bool {
+    let running = HYDRATION_RUNNING.load(Ordering::SeqCst);
+    if running {
+        HYDRATION_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    running
+}
+
+/// Fill missing descriptions and covers of library media, resuming from the
+/// saved cursor unless `restart` is set. `fetch_manga` loads a manga's
+/// details from its extension. Emits media_hydration_progress per row and a
+/// notification when the run completes.
+pub async fn hydrate_imported_media<F, Fut>(
+    pool: &SqlitePool,
+    app_handle: &AppHandle,
+    restart: bool,
+    fetch_manga: F,
+) -> Result<MediaHydrationProgress, String>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<MangaDetails, String>>,
+{
+    if HYDRATION_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("Media hydration is already running".to_string());
+    }
+    HYDRATION_CANCELLED.store(false, Ordering::SeqCst);
+
+    let result = run_hydration(pool, restart, REQUEST_DELAY, &fetch_manga, |progress| {
+        MEDIA_HYDRATION_EVENT.emit(app_handle, progress);
+    })
+    .await;
+    HYDRATION_RUNNING.store(false, Ordering::SeqCst);
+
+    let progress = result?;
+    if progress.status == "completed" && progress.total > 0 {
+        let notification = NotificationPayload::new(
+            NotificationType::Success,
+            "Library details updated",
+            format!(
+                "Filled in {} of {} titles ({} failed)",
+                progress.updated, progress.total, progress.failed
+            ),
+        )
+        .with_source("library")
+        .with_action("Open Library", Some("/library".to_string()), None);
+
+        if let Err(e) = emit_notification(app_handle, Some(pool), notification).await {
+            log::warn!("Failed to send hydration notification: {}", e);
+        }
+    }
+
+    Ok(progress)
+}
+
+async fn run_hydration<F, Fut>(
+    pool: &SqlitePool,
+    restart: bool,
+    delay: Duration,
+    fetch_manga: &F,
+    mut on_progress: impl FnMut(&MediaHydrationProgress),
+) -> Result<MediaHydrationProgress, String>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<MangaDetails, String>>,
+{
+    let cursor = if restart { None } else { read_cursor(pool).await? };
+    let candidates = load_candidates(pool, cursor.as_deref()).await?;
+
+    let mut progress = MediaHydrationProgress {
+        total: candidates.len(),
+        status: "running".to_string(),
+        ..Default::default()
+    };
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if HYDRATION_CANCELLED.swap(false, Ordering::SeqCst) {
+            // The cursor stays, so the next run picks up from here
+            progress.status = "cancelled".to_string();
+            progress.current_title = String::new();
+            on_progress(&progress);
+            log::info!("Media hydration cancelled after {} of {}", progress.processed, progress.total);
+            return Ok(progress);
+        }
+
+        if index > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        progress.current_title = candidate.title.clone();
+
+        match hydrate_one(pool, candidate, fetch_manga).await {
+            Ok(true) => progress.updated += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("Media hydration failed for {} ({}): {}", candidate.title, candidate.id, e);
+                progress.failed += 1;
+            }
+        }
+
+        progress.processed += 1;
+        write_cursor(pool, Some(&candidate.id)).await?;
+        on_progress(&progress);
+    }
+
+    write_cursor(pool, None).await?;
+
+    progress.status = "completed".to_string();
+    progress.current_title = String::new();
+    on_progress(&progress);
+
+    log::info!(
+        "Media hydration complete: {} updated, {} failed of {}",
+        progress.updated, progress.failed, progress.total
+    );
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    async fn insert_manga(pool: &SqlitePool, id: &str, description: Option<&str>, cover: Option<&str>) {
+        sqlx::query(
+            "INSERT INTO media (id, extension_id, title, media_type, description, cover_url) VALUES (?, 'ext', ?, 'manga', ?, ?)",
+        )
+        .bind(id)
+        .bind(format!("Manga {}", id))
+        .bind(description)
+        .bind(cover)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO library (profile_id, media_id, status) VALUES (1, ?, 'reading')")
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    fn details(id: &str) -> MangaDetails {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": format!("Manga {}", id),
+            "coverUrl": format!("https://img.example.com/{}.jpg", id),
+            "description": format!("About {}", id),
+            "genres": [],
+            "chapters": [],
+        }))
+        .unwrap()
+    }
+
+    async fn fields(pool: &SqlitePool, id: &str) -> (Option<String>, Option<String>) {
+        let row = sqlx::query("SELECT description, cover_url FROM media WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (row.get("description"), row.get("cover_url"))
+    }
+
+    #[tokio::test]
+    async fn fills_only_missing_fields_and_is_idempotent() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        insert_manga(pool, "a", None, None).await;
+        insert_manga(pool, "b", Some("Kept"), None).await;
+        insert_manga(pool, "c", Some("Done"), Some("https://img.example.com/own.jpg")).await;
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetch = |_ext: String, id: String| {
+            let fetches = fetches.clone();
+            async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                Ok(details(&id))
+            }
+        };
+
+        let progress = run_hydration(pool, false, Duration::ZERO, &fetch, |_| {}).await.unwrap();
+        assert_eq!((progress.total, progress.updated, progress.status.as_str()), (2, 2, "completed"));
+        assert_eq!(
+            fields(pool, "a").await,
+            (Some("About a".to_string()), Some("https://img.example.com/a.jpg".to_string()))
+        );
+        assert_eq!(
+            fields(pool, "b").await,
+            (Some("Kept".to_string()), Some("https://img.example.com/b.jpg".to_string()))
+        );
+
+        // Nothing left to do on a second run
+        let again = run_hydration(pool, false, Duration::ZERO, &fetch, |_| {}).await.unwrap();
+        assert_eq!(again.total, 0);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn resumes_after_the_cursor() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        insert_manga(pool, "a", None, None).await;
+        insert_manga(pool, "b", None, None).await;
+        write_cursor(pool, Some("a")).await.unwrap();
+
+        let fetch = |_ext: String, id: String| async move { Ok(details(&id)) };
+        let progress = run_hydration(pool, false, Duration::ZERO, &fetch, |_| {}).await.unwrap();
+        assert_eq!(progress.total, 1);
+        assert_eq!(fields(pool, "a").await, (None, None));
+        assert!(read_cursor(pool).await.unwrap().is_none());
+
+        // A restart ignores the cursor
+        write_cursor(pool, Some("b")).await.unwrap();
+        let progress = run_hydration(pool, true, Duration::ZERO, &fetch, |_| {}).await.unwrap();
+        assert_eq!(progress.updated, 1);
+        assert!(fields(pool, "a").await.0.is_some());
+    }
+}
@@ -7,16 +7,17 @@
 
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Manager};
 use anyhow::Result;
+use crate::database::profiles::DEFAULT_PROFILE_ID;
+use crate::locale::{self, Locale};
 
 fn default_true() -> bool { true }
 
-/// Event name for notification events (matches frontend listener)
-pub const NOTIFICATION_EVENT: &str = "notification";
+use crate::events::NOTIFICATION_EVENT;
 
 /// Notification types supported by the system
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum NotificationType {
     Success,
@@ -37,7 +38,7 @@ impl NotificationType {
 }
 
 /// Action that can be performed when clicking a notification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct NotificationAction {
     pub label: String,
     pub route: Option<String>,
@@ -45,7 +46,7 @@ pub struct NotificationAction {
 }
 
 /// Notification payload sent to frontend and stored in database
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct NotificationPayload {
     pub id: String,
     #[serde(rename = "type")]
@@ -134,21 +135,16 @@ pub async fn emit_notification(
     notification: NotificationPayload,
 ) -> Result<()> {
     // 1. In-app event (drives the existing toast UI and any other listeners).
-    if let Err(e) = app_handle.emit(NOTIFICATION_EVENT, &notification) {
-        log::error!("Failed to emit notification event: {}", e);
-    } else {
-        log::debug!(
-            "Emitted notification: {} - {}",
-            notification.title,
-            notification.message
-        );
-    }
+    NOTIFICATION_EVENT.emit(app_handle, &notification);
+    log::debug!(
+        "Emitted notification: {} - {}",
+        notification.title,
+        notification.message
+    );
 
     // 2. Desktop: escalate to native banner whenever enabled + flagged.
     #[cfg(desktop)]
     {
-        use tauri::Manager;
-
         let desktop_notifs_enabled = match pool {
             Some(pool) => read_desktop_notifications_setting(pool).await,
             None => true,
@@ -175,7 +171,12 @@ pub async fn emit_notification(
 
     // 4. Persist.
     if let Some(pool) = pool {
-        save_notification(pool, &notification).await?;
+        // Saved for whichever profile is active when it fires
+        let profile_id = app_handle
+            .try_state::<crate::commands::AppState>()
+            .map(|state| state.profile_id())
+            .unwrap_or(DEFAULT_PROFILE_ID);
+        save_notification(pool, profile_id, &notification).await?;
     }
 
     Ok(())
@@ -218,12 +219,12 @@ async fn read_desktop_notifications_setting(pool: &SqlitePool) -> bool {
 }
 
 /// Save a notification to the database (public version for commands)
-pub async fn save_notification_public(pool: &SqlitePool, notification: &NotificationPayload) -> Result<()> {
-    save_notification(pool, notification).await
+pub async fn save_notification_public(pool: &SqlitePool, profile_id: i64, notification: &NotificationPayload) -> Result<()> {
+    save_notification(pool, profile_id, notification).await
 }
 
 /// Save a notification to the database
-async fn save_notification(pool: &SqlitePool, notification: &NotificationPayload) -> Result<()> {
+async fn save_notification(pool: &SqlitePool, profile_id: i64, notification: &NotificationPayload) -> Result<()> {
     let action_label = notification.action.as_ref().map(|a| a.label.clone());
     let action_route = notification.action.as_ref().and_then(|a| a.route.clone());
     let action_callback = notification.action.as_ref().and_then(|a| a.callback.clone());
@@ -234,9 +235,9 @@ async fn save_notification(pool: &SqlitePool, notification: &NotificationPayload
         INSERT INTO notifications (
             id, notification_type, title, message, source,
             action_label, action_route, action_callback, metadata,
-            read, dismissed, created_at
+            read, dismissed, created_at, profile_id
         )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#
     )
     .bind(&notification.id)
@@ -251,15 +252,18 @@ async fn save_notification(pool: &SqlitePool, notification: &NotificationPayload
     .bind(notification.read)
     .bind(notification.dismissed)
     .bind(notification.timestamp)
+    .bind(profile_id)
     .execute(pool)
     .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
 /// List notifications from the database
 pub async fn list_notifications(
     pool: &SqlitePool,
+    profile_id: i64,
     limit: i32,
     include_dismissed: bool,
 ) -> Result<Vec<NotificationPayload>> {
@@ -269,6 +273,7 @@ pub async fn list_notifications(
                action_label, action_route, action_callback, metadata,
                read, dismissed, created_at
         FROM notifications
+        WHERE profile_id = ?
         ORDER BY created_at DESC
         LIMIT ?
         "#
@@ -278,13 +283,14 @@ pub async fn list_notifications(
                action_label, action_route, action_callback, metadata,
                read, dismissed, created_at
         FROM notifications
-        WHERE dismissed = 0
+        WHERE profile_id = ? AND dismissed = 0
         ORDER BY created_at DESC
         LIMIT ?
         "#
     };
 
     let rows = sqlx::query(query)
+        .bind(profile_id)
         .bind(limit)
         .fetch_all(pool)
         .await?;
@@ -340,15 +346,18 @@ pub async fn mark_notification_read(pool: &SqlitePool, notification_id: &str) ->
         .execute(pool)
         .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
 /// Mark all notifications as read
-pub async fn mark_all_notifications_read(pool: &SqlitePool) -> Result<()> {
-    sqlx::query("UPDATE notifications SET read = 1 WHERE read = 0")
+pub async fn mark_all_notifications_read(pool: &SqlitePool, profile_id: i64) -> Result<()> {
+    sqlx::query("UPDATE notifications SET read = 1 WHERE profile_id = ? AND read = 0")
+        .bind(profile_id)
         .execute(pool)
         .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
@@ -359,23 +368,27 @@ pub async fn dismiss_notification(pool: &SqlitePool, notification_id: &str) -> R
         .execute(pool)
         .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
-/// Clear all notifications (hard delete)
-pub async fn clear_all_notifications(pool: &SqlitePool) -> Result<()> {
-    sqlx::query("DELETE FROM notifications")
+/// Clear all notifications for the active profile (hard delete)
+pub async fn clear_all_notifications(pool: &SqlitePool, profile_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM notifications WHERE profile_id = ?")
+        .bind(profile_id)
         .execute(pool)
         .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
 /// Get count of unread notifications
-pub async fn get_unread_count(pool: &SqlitePool) -> Result<i32> {
+pub async fn get_unread_count(pool: &SqlitePool, profile_id: i64) -> Result<i32> {
     let count: i64 = sqlx::query_scalar(
-        "SELECT COUNT(*) FROM notifications WHERE read = 0 AND dismissed = 0"
+        "SELECT COUNT(*) FROM notifications WHERE profile_id = ? AND read = 0 AND dismissed = 0"
     )
+    .bind(profile_id)
     .fetch_one(pool)
     .await?;
 
@@ -399,6 +412,23 @@ pub(crate) fn should_escalate_native(
 // These are utility functions that can be called from anywhere in the backend
 // Some may not be used yet but are available for future use
 
+/// Locale messages are formatted in; English without a database
+async fn message_locale(pool: Option<&SqlitePool>) -> Locale {
+    match pool {
+        Some(pool) => locale::current_locale(pool).await,
+        None => Locale::default(),
+    }
+}
+
+/// "Episode 3 (1080p)", or just the episode label without a quality
+fn episode_with_quality(episode_number: i32, quality: Option<&str>, locale: Locale) -> String {
+    let episode = locale::episode_label(episode_number, locale);
+    match quality.filter(|q| !q.is_empty()) {
+        Some(quality) => format!("{} ({})", episode, quality),
+        None => episode,
+    }
+}
+
 /// Emit a download started notification
 #[allow(dead_code)]
 pub async fn notify_download_started(
@@ -408,10 +438,11 @@ pub async fn notify_download_started(
     episode_number: i32,
     media_id: &str,
 ) -> Result<()> {
+    let episode = locale::episode_label(episode_number, message_locale(pool).await);
     let notification = NotificationPayload::new(
         NotificationType::Info,
         "Download Started",
-        format!("Started downloading {} Episode {}", title, episode_number),
+        format!("Started downloading {} {}", title, episode),
     )
     .with_source("download")
     .with_metadata(serde_json::json!({
@@ -429,18 +460,21 @@ pub async fn notify_download_complete(
     pool: Option<&SqlitePool>,
     title: &str,
     episode_number: i32,
+    quality: Option<&str>,
     media_id: &str,
 ) -> Result<()> {
+    let episode = episode_with_quality(episode_number, quality, message_locale(pool).await);
     let notification = NotificationPayload::new(
         NotificationType::Success,
         "Download Complete",
-        format!("{} Episode {} downloaded successfully", title, episode_number),
+        format!("{} {} downloaded successfully", title, episode),
     )
     .with_source("download")
     .with_action("Open Downloads", Some("/downloads".to_string()), None)
     .with_metadata(serde_json::json!({
         "title": title,
         "episode_number": episode_number,
+        "quality": quality,
         "media_id": media_id
     }));
 
@@ -453,15 +487,45 @@ pub async fn notify_download_failed(
     pool: Option<&SqlitePool>,
     title: &str,
     episode_number: i32,
+    quality: Option<&str>,
     error: &str,
     media_id: &str,
 ) -> Result<()> {
+    let episode = episode_with_quality(episode_number, quality, message_locale(pool).await);
     let notification = NotificationPayload::new(
         NotificationType::Error,
         "Download Failed",
-        format!("Failed to download {} Episode {}: {}", title, episode_number, error),
+        format!("Failed to download {} {}: {}", title, episode, error),
     )
     .with_source("download")
+    .with_metadata(serde_json::json!({
+        "title": title,
+        "episode_number": episode_number,
+        "quality": quality,
+        "error": error,
+        "media_id": media_id
+    }));
+
+    emit_notification(app_handle, pool, notification).await
+}
+
+/// Emit a warning that a download didn't start for lack of disk space
+pub async fn notify_low_disk_space(
+    app_handle: &AppHandle,
+    pool: Option<&SqlitePool>,
+    title: &str,
+    episode_number: i32,
+    error: &str,
+    media_id: &str,
+) -> Result<()> {
+    let episode = locale::episode_label(episode_number, message_locale(pool).await);
+    let notification = NotificationPayload::new(
+        NotificationType::Warning,
+        "Not Enough Disk Space",
+        format!("{} {} wasn't downloaded. {}", title, episode, error),
+    )
+    .with_source("download")
+    .with_action("Open Downloads", Some("/downloads".to_string()), None)
     .with_metadata(serde_json::json!({
         "title": title,
         "episode_number": episode_number,
@@ -480,10 +544,11 @@ pub async fn notify_chapter_download_complete(
     chapter_number: f64,
     media_id: &str,
 ) -> Result<()> {
+    let chapter = locale::chapter_label(chapter_number, message_locale(pool).await);
     let notification = NotificationPayload::new(
         NotificationType::Success,
         "Chapter Downloaded",
-        format!("{} Chapter {} downloaded successfully", manga_title, chapter_number),
+        format!("{} {} downloaded successfully", manga_title, chapter),
     )
     .with_source("download")
     .with_action("Open Downloads", Some("/downloads".to_string()), None)
@@ -505,10 +570,11 @@ pub async fn notify_chapter_download_failed(
     error: &str,
     media_id: &str,
 ) -> Result<()> {
+    let chapter = locale::chapter_label(chapter_number, message_locale(pool).await);
     let notification = NotificationPayload::new(
         NotificationType::Error,
         "Chapter Download Failed",
-        format!("Failed to download {} Chapter {}: {}", manga_title, chapter_number, error),
+        format!("Failed to download {} {}: {}", manga_title, chapter, error),
     )
     .with_source("download")
     .with_metadata(serde_json::json!({
@@ -582,7 +648,7 @@ pub async fn notify_app_update_available(
 
 #[cfg(test)]
 mod tests {
-    use super::should_escalate_native;
+    use super::{episode_with_quality, should_escalate_native, Locale};
 
     #[test]
     fn enabled_and_flagged_escalates() {
@@ -608,4 +674,11 @@ mod tests {
     fn both_off_suppresses() {
         assert!(!should_escalate_native(false, false));
     }
+
+    #[test]
+    fn episodes_name_their_quality_when_known() {
+        assert_eq!(episode_with_quality(3, Some("1080p"), Locale::En), "Episode 3 (1080p)");
+        assert_eq!(episode_with_quality(3, Some(""), Locale::En), "Episode 3");
+        assert_eq!(episode_with_quality(3, None, Locale::Ja), "第3話");
+    }
 }
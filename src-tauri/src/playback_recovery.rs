@@ -0,0 +1,255 @@
+// Playback Crash Recovery
+//
+// The player saves watch progress every so often, so if the webview crashes
+// mid-episode the saved position can be minutes old. The player also sends a
+// cheap heartbeat every ~10 seconds. Heartbeats only live in memory, flushed to
+// a small recovery file every 30 seconds. On the next start the file is read
+// back, and get_watch_progress offers the heartbeat position when it is newer
+// than what watch_history has. A graceful shutdown deletes the file.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::database::watch_history::WatchHistory;
+
+const RECOVERY_FILE_NAME: &str = "playback-recovery.json";
+
+/// How often pending heartbeats are written to the recovery file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Heartbeats older than this are dropped when the recovery file is loaded
+const MAX_HEARTBEAT_AGE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Last known playback position of an episode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub media_id: String,
+    pub episode_id: String,
+    pub position_seconds: f64,
+    /// Unix timestamp (ms) of the heartbeat
+    pub at: i64,
+}
+
+#[derive(Default)]
+struct RecoveryState {
+    /// Keyed by episode id
+    heartbeats: HashMap<String, Heartbeat>,
+    /// Heartbeats arrived since the last flush
+    dirty: bool,
+    /// Recovery file, once the task has started
+    file: Option<PathBuf>,
+}
+
+static STATE: LazyLock<Mutex<RecoveryState>> = LazyLock::new(|| Mutex::new(RecoveryState::default()));
+
+/// Record the player's current position
+pub fn report_heartbeat(media_id: &str, episode_id: &str, position_seconds: f64) {
+    if !position_seconds.is_finite() || position_seconds < 0.0 {
+        return;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    state.heartbeats.insert(
+        episode_id.to_string(),
+        Heartbeat {
+            media_id: media_id.to_string(),
+            episode_id: episode_id.to_string(),
+            position_seconds,
+            at: chrono::Utc::now().timestamp_millis(),
+        },
+    );
+    state.dirty = true;
+}
+
+/// Last heartbeat for an episode, from this run or recovered from a crash
+pub fn heartbeat_for(media_id: &str, episode_id: &str) -> Option<Heartbeat> {
+    let state = STATE.lock().unwrap();
+    state
+        .heartbeats
+        .get(episode_id)
+        .filter(|heartbeat| heartbeat.media_id == media_id)
+        .cloned()
+}
+
+/// Parse watch_history's last_watched (SQLite CURRENT_TIMESTAMP, UTC, or
+/// RFC 3339 from imported data) into a unix timestamp in ms
+fn parse_last_watched(last_watched: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(last_watched, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc().timestamp_millis())
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(last_watched).map(|dt| dt.timestamp_millis()))
+        .ok()
+}
+
+/// The position to resume from: the heartbeat's when it is strictly newer
+/// than the saved progress, otherwise None (keep the saved one). Saved
+/// progress with an unreadable timestamp is trusted over the heartbeat.
+pub fn recover_position(last_watched: &str, heartbeat: Option<&Heartbeat>) -> Option<f64> {
+    let heartbeat = heartbeat?;
+    let saved_at = parse_last_watched(last_watched)?;
+
+    (heartbeat.at > saved_at).then_some(heartbeat.position_seconds)
+}
+
+/// Swap in a newer heartbeat position, if there is one
+pub fn apply_recovery(mut history: WatchHistory) -> WatchHistory {
+    let heartbeat = heartbeat_for(&history.media_id, &history.episode_id);
+
+    if let Some(position) = recover_position(&history.last_watched, heartbeat.as_ref()) {
+        log::info!(
+            "Recovered playback position {:.0}s (saved {:.0}s) for episode {}",
+            position, history.progress_seconds, history.episode_id
+        );
+        history.progress_seconds = position;
+    }
+
+    history
+}
+
+fn load_recovery_file(path: &Path, now: i64) -> HashMap<String, Heartbeat> {
+    let heartbeats: Vec<Heartbeat> = match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable playback recovery file: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+
+    heartbeats
+        .into_iter()
+        .filter(|heartbeat| now - heartbeat.at <= MAX_HEARTBEAT_AGE_MS)
+        .map(|heartbeat| (heartbeat.episode_id.clone(), heartbeat))
+        .collect()
+}
+
+/// Write pending heartbeats to the recovery file
+fn flush() {
+    let (path, heartbeats) = {
+        let mut state = STATE.lock().unwrap();
+        let Some(path) = state.file.clone() else { return };
+        if !state.dirty {
+            return;
+        }
+        state.dirty = false;
+        (path, state.heartbeats.values().cloned().collect::<Vec<_>>())
+    };
+
+    let result = serde_json::to_vec(&heartbeats)
+        .map_err(std::io::Error::from)
+        .and_then(|json| {
+            // Write then rename so a crash mid-write doesn't lose the previous file
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, json)?;
+            std::fs::rename(&tmp, &path)
+        });
+
+    if let Err(e) = result {
+        log::warn!("Failed to write playback recovery file: {}", e);
+        STATE.lock().unwrap().dirty = true;
+    }
+}
+
+/// Load heartbeats left behind by a crash and start flushing new ones
+pub fn start_recovery_task(app_dir: &Path) {
+    let path = app_dir.join(RECOVERY_FILE_NAME);
+    let recovered = load_recovery_file(&path, chrono::Utc::now().timestamp_millis());
+    if !recovered.is_empty() {
+        log::info!("Found {} playback position(s) from an unclean shutdown", recovered.len());
+    }
+
+    {
+        let mut state = STATE.lock().unwrap();
+        for (episode_id, heartbeat) in recovered {
+            state.heartbeats.entry(episode_id).or_insert(heartbeat);
+        }
+        state.file = Some(path);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            flush();
+        }
+    });
+}
+
+/// Clean shutdown: the saved progress is current, nothing to recover
+pub fn discard() {
+    let mut state = STATE.lock().unwrap();
+    state.heartbeats.clear();
+    state.dirty = false;
+
+    if let Some(path) = &state.file {
+        if let Err(e) = std::fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove playback recovery file: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(position_seconds: f64, at: i64) -> Heartbeat {
+        Heartbeat {
+            media_id: "m1".to_string(),
+            episode_id: "e1".to_string(),
+            position_seconds,
+            at,
+        }
+    }
+
+    // 2024-06-01 12:00:00 UTC
+    const SAVED_AT: &str = "2024-06-01 12:00:00";
+    const SAVED_AT_MS: i64 = 1_717_243_200_000;
+
+    #[test]
+    fn newer_heartbeat_wins() {
+        let newer = heartbeat(900.0, SAVED_AT_MS + 45_000);
+        assert_eq!(recover_position(SAVED_AT, Some(&newer)), Some(900.0));
+    }
+
+    #[test]
+    fn saved_progress_wins_when_not_older() {
+        assert_eq!(recover_position(SAVED_AT, Some(&heartbeat(900.0, SAVED_AT_MS))), None);
+        assert_eq!(recover_position(SAVED_AT, Some(&heartbeat(900.0, SAVED_AT_MS - 1))), None);
+        assert_eq!(recover_position(SAVED_AT, None), None);
+    }
+
+    #[test]
+    fn unreadable_saved_timestamp_keeps_saved_progress() {
+        let newer = heartbeat(900.0, SAVED_AT_MS + 45_000);
+        assert_eq!(recover_position("yesterday", Some(&newer)), None);
+    }
+
+    #[test]
+    fn rfc3339_timestamps_from_imports_are_compared() {
+        let newer = heartbeat(900.0, SAVED_AT_MS + 1_000);
+        assert_eq!(recover_position("2024-06-01T12:00:00+00:00", Some(&newer)), Some(900.0));
+        assert_eq!(recover_position("2024-06-01T14:00:00+02:00", Some(&newer)), Some(900.0));
+    }
+
+    #[test]
+    fn recovery_file_drops_stale_and_corrupt_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(RECOVERY_FILE_NAME);
+        let now = SAVED_AT_MS;
+
+        let fresh = heartbeat(300.0, now - 60_000);
+        let mut stale = heartbeat(100.0, now - MAX_HEARTBEAT_AGE_MS - 1);
+        stale.episode_id = "e0".to_string();
+        std::fs::write(&path, serde_json::to_vec(&vec![fresh.clone(), stale]).unwrap()).unwrap();
+
+        let loaded = load_recovery_file(&path, now);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.get("e1"), Some(&fresh));
+
+        std::fs::write(&path, b"{\"truncated").unwrap();
+        assert!(load_recovery_file(&path, now).is_empty());
+        assert!(load_recovery_file(&temp_dir.path().join("missing.json"), now).is_empty());
+    }
+}
@@ -0,0 +1,280 @@
+// Playback Sessions
+//
+// The same episode open in two windows (or a webview that never shut down)
+// means two players saving watch progress, and the position jumps back and
+// forth. A player starts a session for the episode it plays and passes the
+// token with every progress save. Starting a session supersedes any older
+// one for that episode, so the newest player wins and the old one gets a
+// StaleSession error and stops writing. Sessions expire when their player
+// goes quiet (no heartbeat or save) for a while.
+//
+// Saves without a token are accepted until playback_sessions_required is
+// turned on, so players that don't start sessions keep working.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+
+/// app_settings key: "true" to reject progress saves that carry no session token
+pub const SESSIONS_REQUIRED_SETTING: &str = "playback_sessions_required";
+
+/// Sessions without a heartbeat or save for this long are dropped
+const SESSION_TIMEOUT_MS: i64 = 2 * 60 * 1000;
+
+/// How long a superseded token is still reported as stale (after that it's
+/// just unknown)
+const SUPERSEDED_RETENTION_MS: i64 = 60 * 60 * 1000;
+
+/// A player currently allowed to save progress for an episode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaybackSession {
+    pub token: String,
+    pub media_id: String,
+    pub episode_id: String,
+    /// Unix timestamp (ms) the session started
+    pub started_at: i64,
+    /// Unix timestamp (ms) of the last heartbeat or save
+    pub last_seen: i64,
+}
+
+/// Why a progress save was rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum PlaybackSessionError {
+    /// A newer session took over the episode; this player should stop saving
+    StaleSession,
+    /// The session timed out or was never started; start a new one
+    UnknownSession,
+    /// The token belongs to a different episode
+    WrongEpisode,
+    /// Sessions are required and the save carried no token
+    MissingToken,
+    /// Saving failed for a reason unrelated to sessions
+    Storage(String),
+}
+
+impl fmt::Display for PlaybackSessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaybackSessionError::StaleSession => {
+                write!(f, "This episode is playing in another window")
+            }
+            PlaybackSessionError::UnknownSession => write!(f, "Playback session expired"),
+            PlaybackSessionError::WrongEpisode => {
+                write!(f, "Playback session belongs to a different episode")
+            }
+            PlaybackSessionError::MissingToken => write!(f, "A playback session is required"),
+            PlaybackSessionError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PlaybackSessionError {}
+
+#[derive(Default)]
+struct Registry {
+    /// Active sessions by token
+    sessions: HashMap<String, PlaybackSession>,
+    /// Tokens superseded by a newer session, with when that happened. Kept so
+    /// the old player is told it lost the episode rather than that it expired.
+    superseded: HashMap<String, i64>,
+}
+
+impl Registry {
+    fn expire(&mut self, now: i64) {
+        let expired: Vec<String> = self
+            .sessions
+            .values()
+            .filter(|session| now - session.last_seen > SESSION_TIMEOUT_MS)
+            .map(|session| session.token.clone())
+            .collect();
+
+        for token in expired {
+            self.sessions.remove(&token);
+        }
+
+        self.superseded.retain(|_, at| now - *at <= SUPERSEDED_RETENTION_MS);
+    }
+
+    fn start(&mut self, media_id: &str, episode_id: &str, token: String, now: i64) -> PlaybackSession {
+        self.expire(now);
+
+        let replaced: Vec<String> = self
+            .sessions
+            .values()
+            .filter(|session| session.episode_id == episode_id)
+            .map(|session| session.token.clone())
+            .collect();
+        for old in replaced {
+            self.sessions.remove(&old);
+            self.superseded.insert(old, now);
+        }
+
+        let session = PlaybackSession {
+            token: token.clone(),
+            media_id: media_id.to_string(),
+            episode_id: episode_id.to_string(),
+            started_at: now,
+            last_seen: now,
+        };
+        self.sessions.insert(token, session.clone());
+        session
+    }
+
+    /// Check a token for an episode and mark the session as alive
+    fn touch(&mut self, token: &str, episode_id: &str, now: i64) -> Result<(), PlaybackSessionError> {
+        self.expire(now);
+
+        if self.superseded.contains_key(token) {
+            return Err(PlaybackSessionError::StaleSession);
+        }
+        let session = self.sessions.get_mut(token).ok_or(PlaybackSessionError::UnknownSession)?;
+        if session.episode_id != episode_id {
+            return Err(PlaybackSessionError::WrongEpisode);
+        }
+        session.last_seen = now;
+        Ok(())
+    }
+
+    fn end(&mut self, token: &str) {
+        self.sessions.remove(token);
+        self.superseded.remove(token);
+    }
+
+    fn active(&mut self, now: i64) -> Vec<PlaybackSession> {
+        self.expire(now);
+        let mut sessions: Vec<PlaybackSession> = self.sessions.values().cloned().collect();
+        sessions.sort_by_key(|session| session.started_at);
+        sessions
+    }
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Start a session for an episode, superseding any other player's session for it
+pub fn start_session(media_id: &str, episode_id: &str) -> PlaybackSession {
+    let token = uuid::Uuid::new_v4().to_string();
+    let session = REGISTRY.lock().unwrap().start(media_id, episode_id, token, now_ms());
+    log::debug!("Started playback session for episode {}", episode_id);
+    session
+}
+
+/// Keep a session alive (called on heartbeats and saves)
+pub fn touch_session(token: &str, episode_id: &str) -> Result<(), PlaybackSessionError> {
+    REGISTRY.lock().unwrap().touch(token, episode_id, now_ms())
+}
+
+/// End a session when its player closes
+pub fn end_session(token: &str) {
+    REGISTRY.lock().unwrap().end(token);
+}
+
+/// Sessions that haven't timed out, oldest first
+pub fn active_sessions() -> Vec<PlaybackSession> {
+    REGISTRY.lock().unwrap().active(now_ms())
+}
+
+async fn sessions_required(pool: &SqlitePool) -> bool {
+    sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+        .bind(SESSIONS_REQUIRED_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true")
+}
+
+/// Whether a progress save for `episode_id` may go ahead
+pub async fn authorize_save(
+    pool: &SqlitePool,
+    token: Option<&str>,
+    episode_id: &str,
+) -> Result<(), PlaybackSessionError> {
+    match token {
+        Some(token) => touch_session(token, episode_id),
+        None if sessions_required(pool).await => Err(PlaybackSessionError::MissingToken),
+        // Legacy player without sessions
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_session_wins_the_episode() {
+        let mut registry = Registry::default();
+        registry.start("m1", "e1", "old".to_string(), 0);
+        registry.start("m1", "e2", "other".to_string(), 0);
+        registry.start("m1", "e1", "new".to_string(), 1_000);
+
+        assert_eq!(registry.touch("old", "e1", 2_000), Err(PlaybackSessionError::StaleSession));
+        assert_eq!(registry.touch("new", "e1", 2_000), Ok(()));
+        // Sessions for other episodes are left alone
+        assert_eq!(registry.touch("other", "e2", 2_000), Ok(()));
+        assert_eq!(registry.touch("new", "e2", 2_000), Err(PlaybackSessionError::WrongEpisode));
+
+        let tokens: Vec<String> = registry.active(2_000).into_iter().map(|s| s.token).collect();
+        assert_eq!(tokens, vec!["other", "new"]);
+    }
+
+    #[test]
+    fn quiet_sessions_expire_and_activity_keeps_them_alive() {
+        let mut registry = Registry::default();
+        registry.start("m1", "e1", "busy".to_string(), 0);
+        registry.start("m1", "e2", "quiet".to_string(), 0);
+
+        registry.touch("busy", "e1", SESSION_TIMEOUT_MS - 1).unwrap();
+        let later = SESSION_TIMEOUT_MS + 1_000;
+
+        assert_eq!(registry.touch("quiet", "e2", later), Err(PlaybackSessionError::UnknownSession));
+        assert_eq!(registry.touch("busy", "e1", later), Ok(()));
+        assert_eq!(registry.active(later).len(), 1);
+    }
+
+    #[test]
+    fn ended_sessions_are_unknown() {
+        let mut registry = Registry::default();
+        registry.start("m1", "e1", "t1".to_string(), 0);
+        registry.end("t1");
+
+        assert_eq!(registry.touch("t1", "e1", 10), Err(PlaybackSessionError::UnknownSession));
+        assert_eq!(registry.touch("never-started", "e1", 10), Err(PlaybackSessionError::UnknownSession));
+        assert!(registry.active(10).is_empty());
+    }
+
+    #[test]
+    fn errors_serialize_with_a_kind() {
+        let json = serde_json::to_value(PlaybackSessionError::StaleSession).unwrap();
+        assert_eq!(json["kind"], "stale_session");
+        let json = serde_json::to_value(PlaybackSessionError::Storage("disk full".to_string())).unwrap();
+        assert_eq!((json["kind"].as_str(), json["detail"].as_str()), (Some("storage"), Some("disk full")));
+    }
+
+    #[tokio::test]
+    async fn tokenless_saves_follow_the_setting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = crate::database::Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(authorize_save(pool, None, "e1").await, Ok(()));
+
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES (?, 'true')")
+            .bind(SESSIONS_REQUIRED_SETTING)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(authorize_save(pool, None, "e1").await, Err(PlaybackSessionError::MissingToken));
+
+        let session = start_session("m1", "authorize-test-episode");
+        assert_eq!(authorize_save(pool, Some(&session.token), "authorize-test-episode").await, Ok(()));
+        end_session(&session.token);
+    }
+}
@@ -9,19 +9,32 @@
 // - Smart scheduling based on media status and activity
 // - Retry mechanism with exponential backoff
 // - Detailed logging for debugging
+//
+// Releases can be checked against the user's preferred source instead of the
+// extension the tracking row was created with (ReleaseSource::Preferred): the
+// media's own mapping, else the first extension of source_priority its id
+// resolves in (through id_mappings between MAL and AllAnime ids). When the
+// preferred source lags, the tracking source confirms the release, unless
+// notify_when_watchable holds the notification until the preferred source
+// has the episode.
 
 use crate::commands::AppState;
-use crate::extensions::{ExtensionRuntime, ExtensionType};
+use crate::events::RELEASE_CHECK_PROGRESS_EVENT;
+use crate::extensions::{background_budget, circuit_breaker};
+use crate::extensions::{adult, ExtensionRuntime, ExtensionType};
 use crate::jikan::anime as jikan_anime;
+use crate::jikan::{numbering, split_cour};
 use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
 use crate::status_normalizer::{normalize_status, NormalizedStatus};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+use std::future::Future;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Manager};
 use tokio::sync::Mutex;
 
 /// Global flag to control the background checker
@@ -37,6 +50,36 @@ static MANUAL_CHECK_STOP_FLAG: AtomicBool = AtomicBool::new(false);
 static CHECK_LOCK: std::sync::LazyLock<Arc<Mutex<()>>> =
     std::sync::LazyLock::new(|| Arc::new(Mutex::new(())));
 
+/// Media currently being checked. A pass that reaches a media another pass is
+/// still checking skips it instead of checking (and notifying) twice.
+static IN_FLIGHT: std::sync::LazyLock<std::sync::Mutex<HashSet<String>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(HashSet::new()));
+
+/// Holds a media's IN_FLIGHT entry, released on drop
+struct InFlightGuard(String);
+
+impl InFlightGuard {
+    /// None when the media is already being checked
+    fn acquire(media_id: &str) -> Option<Self> {
+        IN_FLIGHT
+            .lock()
+            .unwrap()
+            .insert(media_id.to_string())
+            .then(|| Self(media_id.to_string()))
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Whether a release check for this media is running right now
+pub fn is_checking(media_id: &str) -> bool {
+    IN_FLIGHT.lock().unwrap().contains(media_id)
+}
+
 /// Delay between API calls to avoid rate limiting (in milliseconds)
 const API_DELAY_MS: u64 = 2000;
 
@@ -53,6 +96,9 @@ const SINGLE_CHECK_TIMEOUT_SECS: u64 = 60;
 /// Timeout for the entire full release check (seconds)
 const FULL_CHECK_TIMEOUT_SECS: u64 = 30 * 60; // 30 minutes max
 const MANGAKAKALOT_EXTENSION_ID: &str = "com.mangakakalot.source";
+const ALLANIME_EXTENSION_ID: &str = "com.allanime.source";
+/// Stored extension_id of MAL-id anime, which are checked through Jikan
+const JIKAN_SOURCE_ID: &str = "jikan";
 
 fn normalize_manga_extension_id<'a>(extension_id: &'a str, media_type: &'a str) -> &'a str {
     if media_type == "manga" {
@@ -145,6 +191,18 @@ pub struct ReleaseCheckSettings {
     pub retry_delay_minutes: u32,        // Delay after failure (5 min)
     pub max_retries: u32,                // Max retry attempts
     pub last_full_check: Option<i64>,    // Unix timestamp in ms
+    #[serde(default)]
+    pub digest_mode: DigestMode,
+    /// Which source releases are checked against
+    #[serde(default)]
+    pub release_source: ReleaseSource,
+    /// Extension ids, most preferred first, for media without their own
+    /// preferred source ("jikan" stands for MAL)
+    #[serde(default)]
+    pub source_priority: Vec<String>,
+    /// With the preferred source: announce a release only once that source has it
+    #[serde(default)]
+    pub notify_when_watchable: bool,
     // Legacy field for backwards compatibility
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interval_hours: Option<u32>,
@@ -159,11 +217,84 @@ impl Default for ReleaseCheckSettings {
             retry_delay_minutes: 5,
             max_retries: 3,
             last_full_check: None,
+            digest_mode: DigestMode::Immediate,
+            release_source: ReleaseSource::Tracking,
+            source_priority: Vec::new(),
+            notify_when_watchable: false,
             interval_hours: None,
         }
     }
 }
 
+/// How release notifications are delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestMode {
+    /// One notification per title as soon as it's detected
+    #[default]
+    Immediate,
+    /// Queue releases and send one grouped notification per hour
+    Hourly,
+    /// Queue releases and send one grouped notification per day
+    Daily,
+}
+
+impl DigestMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestMode::Immediate => "immediate",
+            DigestMode::Hourly => "hourly",
+            DigestMode::Daily => "daily",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "immediate" => Some(DigestMode::Immediate),
+            "hourly" => Some(DigestMode::Hourly),
+            "daily" => Some(DigestMode::Daily),
+            _ => None,
+        }
+    }
+
+    /// Flush cadence in milliseconds (None for immediate delivery)
+    fn interval_ms(&self) -> Option<i64> {
+        match self {
+            DigestMode::Immediate => None,
+            DigestMode::Hourly => Some(60 * 60 * 1000),
+            DigestMode::Daily => Some(24 * 60 * 60 * 1000),
+        }
+    }
+}
+
+/// Which source a release check asks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseSource {
+    /// The extension stored on the tracking row
+    #[default]
+    Tracking,
+    /// The media's preferred source, falling back to the tracking one
+    Preferred,
+}
+
+impl ReleaseSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReleaseSource::Tracking => "tracking",
+            ReleaseSource::Preferred => "preferred",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "tracking" => Some(ReleaseSource::Tracking),
+            "preferred" => Some(ReleaseSource::Preferred),
+            _ => None,
+        }
+    }
+}
+
 /// Status of the release checker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReleaseCheckStatus {
@@ -190,10 +321,14 @@ pub struct ReleaseCheckResult {
     pub cover_url: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub latest_episode_id: Option<String>,
+    /// Extension whose episode list showed the release ("jikan" for MAL);
+    /// latest_episode_id is an id of this source
+    #[serde(default)]
+    pub confirmed_by: String,
 }
 
 /// Progress update during release checking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ReleaseCheckProgress {
     pub current_index: u32,
     pub total_count: u32,
@@ -213,6 +348,8 @@ pub struct MediaReleaseState {
     pub notified_up_to: Option<f32>,
     pub last_checked: Option<i64>,
     pub normalized_status: String,
+    /// A release check for this media is running right now
+    pub is_checking: bool,
 }
 
 /// Check log entry for debugging
@@ -248,6 +385,8 @@ pub struct TrackingDebugInfo {
     pub next_scheduled_check: Option<i64>,
     pub consecutive_failures: i32,
     pub last_error: Option<String>,
+    /// Set when checks follow a split-cour sequel's MAL entry
+    pub split_cour_link: Option<split_cour::SplitCourLink>,
     pub recent_logs: Vec<CheckLogEntry>,
 }
 
@@ -268,6 +407,9 @@ struct EligibleMedia {
     user_notified_up_to: Option<f32>,
     cover_url: Option<String>,
     auto_download: bool,
+    /// Per-media preferred source (release_tracking_v2), if the user set one
+    preferred_extension_id: Option<String>,
+    preferred_media_id: Option<String>,
 }
 
 /// Extracted episode info for comparison
@@ -319,6 +461,30 @@ pub async fn get_release_settings(pool: &SqlitePool) -> Result<ReleaseCheckSetti
     .fetch_optional(pool)
     .await?;
 
+    let digest_mode: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = 'release_digest_mode'"
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let release_source: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = 'release_check_source'"
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let source_priority: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = 'release_source_priority'"
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let notify_when_watchable: Option<String> = sqlx::query_scalar(
+        "SELECT value FROM app_settings WHERE key = 'release_notify_when_watchable'"
+    )
+    .fetch_optional(pool)
+    .await?;
+
     // Also check legacy interval_hours setting and convert
     let legacy_hours: Option<String> = sqlx::query_scalar(
         "SELECT value FROM app_settings WHERE key = 'release_check_interval_hours'"
@@ -338,6 +504,12 @@ pub async fn get_release_settings(pool: &SqlitePool) -> Result<ReleaseCheckSetti
         retry_delay_minutes: retry_delay.and_then(|v| v.parse().ok()).unwrap_or(5),
         max_retries: max_retries.and_then(|v| v.parse().ok()).unwrap_or(3),
         last_full_check: last_check.and_then(|v| v.parse().ok()),
+        digest_mode: digest_mode.as_deref().and_then(DigestMode::parse).unwrap_or_default(),
+        release_source: release_source.as_deref().and_then(ReleaseSource::parse).unwrap_or_default(),
+        source_priority: source_priority
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default(),
+        notify_when_watchable: notify_when_watchable.map(|v| v == "1").unwrap_or(false),
         interval_hours: None,
     })
 }
@@ -371,6 +543,10 @@ pub async fn update_release_settings(
     upsert_setting(pool, "release_check_fast_interval_minutes", &settings.fast_interval_minutes.to_string(), now).await?;
     upsert_setting(pool, "release_check_retry_delay_minutes", &settings.retry_delay_minutes.to_string(), now).await?;
     upsert_setting(pool, "release_check_max_retries", &settings.max_retries.to_string(), now).await?;
+    upsert_setting(pool, "release_digest_mode", settings.digest_mode.as_str(), now).await?;
+    upsert_setting(pool, "release_check_source", settings.release_source.as_str(), now).await?;
+    upsert_setting(pool, "release_source_priority", &serde_json::to_string(&settings.source_priority)?, now).await?;
+    upsert_setting(pool, "release_notify_when_watchable", if settings.notify_when_watchable { "1" } else { "0" }, now).await?;
 
     if let Some(last_check) = settings.last_full_check {
         upsert_setting(pool, "release_last_full_check", &last_check.to_string(), now).await?;
@@ -403,6 +579,11 @@ pub async fn initialize_tracking_v2(
     latest_id: Option<&str>,
     raw_status: Option<&str>,
 ) -> Result<()> {
+    if is_tracking_disabled(pool, media_id).await? {
+        log::debug!("Release tracking is switched off for {}, not initializing", media_id);
+        return Ok(());
+    }
+
     let now = chrono::Utc::now().timestamp_millis();
     let normalized = raw_status.map(normalize_status).unwrap_or(NormalizedStatus::Unknown);
     let next_check = now + (normalized.recommended_interval_minutes() as i64 * 60 * 1000);
@@ -470,7 +651,95 @@ pub async fn initialize_tracking_v2(
     Ok(())
 }
 
-/// Update tracking after checking
+/// Whether a title added to the library should be tracked when the user
+/// didn't say: only media that may still get new releases (ongoing, or a
+/// status the normalizer can't classify).
+pub fn default_track_releases(raw_status: Option<&str>) -> bool {
+    raw_status
+        .map(normalize_status)
+        .unwrap_or(NormalizedStatus::Unknown)
+        .should_check()
+}
+
+/// Whether the user switched release tracking off for a media item
+pub async fn is_tracking_disabled(pool: &SqlitePool, media_id: &str) -> Result<bool> {
+    let enabled: Option<i32> = sqlx::query_scalar(
+        "SELECT COALESCE(notification_enabled, 1) FROM release_tracking_v2 WHERE media_id = ?"
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(enabled == Some(0))
+}
+
+/// Turn release tracking on for a media item, creating the tracking row from
+/// the cached media if there isn't one yet. A new row has no baseline, so the
+/// next check records the current latest release without notifying.
+pub async fn enable_release_tracking(pool: &SqlitePool, media_id: &str) -> Result<()> {
+    set_release_tracking_enabled(pool, media_id, true).await
+}
+
+/// Turn release tracking off for a media item. The row is kept (with its
+/// baseline) so turning it back on doesn't re-announce old releases.
+pub async fn disable_release_tracking(pool: &SqlitePool, media_id: &str) -> Result<()> {
+    set_release_tracking_enabled(pool, media_id, false).await
+}
+
+async fn set_release_tracking_enabled(pool: &SqlitePool, media_id: &str, enabled: bool) -> Result<()> {
+    let media = sqlx::query("SELECT extension_id, media_type, status FROM media WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Media {} not found", media_id))?;
+
+    let extension_id: String = media.try_get("extension_id")?;
+    let media_type: String = media.try_get("media_type")?;
+    let raw_status: Option<String> = media.try_get("status")?;
+    let normalized = raw_status.as_deref().map(normalize_status).unwrap_or(NormalizedStatus::Unknown);
+    let now = chrono::Utc::now().timestamp_millis();
+
+    // Enabling clears the schedule so the title is picked up by the next run
+    sqlx::query(
+        r#"
+        INSERT INTO release_tracking_v2 (
+            media_id, extension_id, media_type,
+            last_known_count, raw_status, normalized_status,
+            notification_enabled, last_checked_at, next_scheduled_check
+        )
+        VALUES (?, ?, ?, 0, ?, ?, ?, ?, NULL)
+        ON CONFLICT(media_id) DO UPDATE SET
+            notification_enabled = excluded.notification_enabled,
+            next_scheduled_check = CASE
+                WHEN excluded.notification_enabled = 1 THEN NULL
+                ELSE release_tracking_v2.next_scheduled_check
+            END,
+            updated_at = CURRENT_TIMESTAMP
+        "#
+    )
+    .bind(media_id)
+    .bind(normalize_manga_extension_id(&extension_id, &media_type))
+    .bind(&media_type)
+    .bind(raw_status.as_deref())
+    .bind(normalized.as_str())
+    .bind(enabled as i32)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    log::debug!(
+        "Release tracking {} for {}",
+        if enabled { "enabled" } else { "disabled" },
+        media_id
+    );
+
+    Ok(())
+}
+
+/// Update tracking after checking. A check that finds more than last time
+/// stamps last_episode_date with its own time (Unix ms), the closest thing
+/// to a release date the sources give us. The first check only sets the
+/// baseline, taking the media's last aired time when its details had one.
 async fn update_tracking_v2(
     pool: &SqlitePool,
     media_id: &str,
@@ -531,7 +800,7 @@ async fn update_tracking_v2(
                     last_known_count, last_known_latest_number, last_known_latest_id,
                     raw_status, normalized_status,
                     user_notified_up_to, user_acknowledged_at,
-                    last_checked_at, next_scheduled_check,
+                    last_checked_at, next_scheduled_check, last_episode_date,
                     consecutive_failures, last_error
                 )
                 SELECT
@@ -541,7 +810,7 @@ async fn update_tracking_v2(
                     ?, ?, ?,
                     ?, ?,
                     ?, NULL,
-                    ?, ?,
+                    ?, ?, m.last_aired_at,
                     0, NULL
                 FROM media m
                 LEFT JOIN release_tracking_v2 rt ON rt.media_id = m.id
@@ -554,6 +823,12 @@ async fn update_tracking_v2(
                     normalized_status = excluded.normalized_status,
                     user_notified_up_to = excluded.user_notified_up_to,
                     user_acknowledged_at = NULL,
+                    last_episode_date = CASE
+                        WHEN excluded.last_known_latest_number > release_tracking_v2.last_known_latest_number
+                          OR excluded.last_known_count > release_tracking_v2.last_known_count
+                        THEN excluded.last_checked_at
+                        ELSE release_tracking_v2.last_episode_date
+                    END,
                     last_checked_at = excluded.last_checked_at,
                     next_scheduled_check = excluded.next_scheduled_check,
                     consecutive_failures = 0,
@@ -580,7 +855,7 @@ async fn update_tracking_v2(
                     media_id, extension_id, media_type,
                     last_known_count, last_known_latest_number, last_known_latest_id,
                     raw_status, normalized_status,
-                    last_checked_at, next_scheduled_check,
+                    last_checked_at, next_scheduled_check, last_episode_date,
                     consecutive_failures, last_error
                 )
                 SELECT
@@ -589,7 +864,7 @@ async fn update_tracking_v2(
                     m.media_type,
                     ?, ?, ?,
                     ?, ?,
-                    ?, ?,
+                    ?, ?, m.last_aired_at,
                     0, NULL
                 FROM media m
                 LEFT JOIN release_tracking_v2 rt ON rt.media_id = m.id
@@ -600,6 +875,12 @@ async fn update_tracking_v2(
                     last_known_latest_id = excluded.last_known_latest_id,
                     raw_status = excluded.raw_status,
                     normalized_status = excluded.normalized_status,
+                    last_episode_date = CASE
+                        WHEN excluded.last_known_latest_number > release_tracking_v2.last_known_latest_number
+                          OR excluded.last_known_count > release_tracking_v2.last_known_count
+                        THEN excluded.last_checked_at
+                        ELSE release_tracking_v2.last_episode_date
+                    END,
                     last_checked_at = excluded.last_checked_at,
                     next_scheduled_check = excluded.next_scheduled_check,
                     consecutive_failures = 0,
@@ -640,13 +921,16 @@ async fn update_tracking_v2(
         .bind(notified_number.map(|n| n as i32))
         .execute(pool)
         .await?;
+
+        // The latest number may have moved past what the user has seen
+        crate::badges::invalidate();
     }
 
     Ok(())
 }
 
 /// Log a check result for debugging
-async fn log_check_result(
+pub(crate) async fn log_check_result(
     pool: &SqlitePool,
     media_id: &str,
     result_type: &str,
@@ -728,7 +1012,9 @@ async fn get_eligible_media(pool: &SqlitePool, force: bool) -> Result<Vec<Eligib
             COALESCE(rt.consecutive_failures, 0) as consecutive_failures,
             rt.user_notified_up_to,
             m.cover_url,
-            COALESCE(l.auto_download, 0) as auto_download
+            MAX(COALESCE(l.auto_download, 0)) as auto_download,
+            rt.preferred_extension_id,
+            rt.preferred_media_id
         FROM media m
         INNER JOIN library l ON m.id = l.media_id
         LEFT JOIN release_tracking_v2 rt ON m.id = rt.media_id
@@ -754,6 +1040,8 @@ async fn get_eligible_media(pool: &SqlitePool, force: bool) -> Result<Vec<Eligib
             )
             AND COALESCE(rt.notification_enabled, 1) = 1
             {cadence_gate}
+        -- Library is per-profile; check each title once however many profiles track it
+        GROUP BY m.id
         ORDER BY
             CASE WHEN m.media_type = 'anime' THEN 0 ELSE 1 END,
             rt.last_checked_at ASC NULLS FIRST
@@ -782,6 +1070,8 @@ async fn get_eligible_media(pool: &SqlitePool, force: bool) -> Result<Vec<Eligib
             user_notified_up_to: row.try_get("user_notified_up_to")?,
             cover_url: row.try_get("cover_url")?,
             auto_download: row.try_get::<i64, _>("auto_download")? != 0,
+            preferred_extension_id: row.try_get("preferred_extension_id")?,
+            preferred_media_id: row.try_get("preferred_media_id")?,
         });
     }
 
@@ -849,6 +1139,209 @@ fn should_notify(media: &EligibleMedia, current_number: Option<f32>) -> bool {
     }
 }
 
+// ==================== Preferred Source ====================
+
+/// The id `media` has in `extension_id`, when it can be told: an id_mappings
+/// row between its MAL id and its AllAnime id, or its own id in its own
+/// extension
+async fn media_id_in(pool: &SqlitePool, media: &EligibleMedia, extension_id: &str) -> Option<String> {
+    let mapping = match extension_id {
+        ALLANIME_EXTENSION_ID if !uses_extension(media) => Some("SELECT allanime_id FROM id_mappings WHERE mal_id = ?"),
+        JIKAN_SOURCE_ID if media.media_type == "anime" && uses_extension(media) => {
+            Some("SELECT mal_id FROM id_mappings WHERE allanime_id = ?")
+        }
+        _ => None,
+    };
+
+    match mapping {
+        Some(query) => sqlx::query_scalar(query)
+            .bind(&media.media_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to look up the {} id of {}: {}", extension_id, media.media_id, e);
+                None
+            }),
+        None => (extension_id == media.extension_id).then(|| media.media_id.clone()),
+    }
+}
+
+/// The media as it is in the source a ReleaseSource::Preferred check asks
+/// first: its own preferred source, else the first extension of `priority`
+/// its id resolves in. None when that's the tracking source anyway, or no
+/// source resolves.
+async fn preferred_source(pool: &SqlitePool, media: &EligibleMedia, priority: &[String]) -> Option<EligibleMedia> {
+    let (extension_id, media_id) = match &media.preferred_extension_id {
+        Some(extension_id) => {
+            let media_id = match &media.preferred_media_id {
+                Some(media_id) => media_id.clone(),
+                None => media_id_in(pool, media, extension_id).await?,
+            };
+            (extension_id.clone(), media_id)
+        }
+        None => {
+            let mut found = None;
+            for extension_id in priority {
+                if let Some(media_id) = media_id_in(pool, media, extension_id).await {
+                    found = Some((extension_id.clone(), media_id));
+                    break;
+                }
+            }
+            found?
+        }
+    };
+
+    if extension_id == media.extension_id && media_id == media.media_id {
+        return None;
+    }
+    Some(EligibleMedia {
+        extension_id,
+        media_id,
+        preferred_extension_id: None,
+        preferred_media_id: None,
+        ..media.clone()
+    })
+}
+
+/// `media` as `source` last answered for it: the tracking row only holds the
+/// tracking source's answers, and sources number and count episodes
+/// differently. No answer yet (or one under another id) is a first check.
+async fn source_baseline(pool: &SqlitePool, media: &EligibleMedia, source: &EligibleMedia) -> Result<EligibleMedia> {
+    let row = sqlx::query(
+        r#"
+        SELECT last_known_count, last_known_latest_number, last_known_latest_id
+        FROM release_source_baselines
+        WHERE media_id = ? AND extension_id = ? AND source_media_id = ?
+        "#
+    )
+    .bind(&media.media_id)
+    .bind(&source.extension_id)
+    .bind(&source.media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let mut baseline = media.clone();
+    baseline.last_known_count = 0;
+    baseline.last_known_latest_number = None;
+    baseline.last_known_latest_id = None;
+    if let Some(row) = row {
+        baseline.last_known_count = row.try_get("last_known_count")?;
+        baseline.last_known_latest_number = row.try_get("last_known_latest_number")?;
+        baseline.last_known_latest_id = row.try_get("last_known_latest_id")?;
+    }
+    Ok(baseline)
+}
+
+/// Store `info` as what `source` last answered for `media_id`
+async fn update_source_baseline(pool: &SqlitePool, media_id: &str, source: &EligibleMedia, info: &EpisodeInfo) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO release_source_baselines (
+            media_id, extension_id, source_media_id,
+            last_known_count, last_known_latest_number, last_known_latest_id
+        )
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(media_id, extension_id) DO UPDATE SET
+            source_media_id = excluded.source_media_id,
+            last_known_count = excluded.last_known_count,
+            last_known_latest_number = excluded.last_known_latest_number,
+            last_known_latest_id = excluded.last_known_latest_id,
+            updated_at = CURRENT_TIMESTAMP
+        "#
+    )
+    .bind(media_id)
+    .bind(&source.extension_id)
+    .bind(&source.media_id)
+    .bind(info.count)
+    .bind(info.latest_number)
+    .bind(&info.latest_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// `info` from `source` with its latest number in the canonical (MAL)
+/// numbering, the one user_notified_up_to and notifications use
+async fn canonical_episode_info(pool: &SqlitePool, media: &EligibleMedia, source: &EligibleMedia, mut info: EpisodeInfo) -> EpisodeInfo {
+    let offset = numbering::get_numbering_offset(pool, &media.media_id, &source.extension_id)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to read the numbering offset of {} in {}: {}", media.media_id, source.extension_id, e);
+            0
+        });
+    info.latest_number = info
+        .latest_number
+        .map(|number| numbering::to_canonical_number(number as f64, offset) as f32);
+    info
+}
+
+/// Record a successful check. The answer becomes the baseline of the source
+/// that gave it; for a preferred source the tracking row keeps the tracking
+/// source's baseline and only records the check, its status and what was
+/// notified.
+async fn record_answer(
+    pool: &SqlitePool,
+    media: &EligibleMedia,
+    source: Option<&EligibleMedia>,
+    info: &EpisodeInfo,
+    notified_number: Option<f32>,
+    settings: &ReleaseCheckSettings,
+) -> Result<()> {
+    let Some(source) = source else {
+        return update_tracking_v2(pool, &media.media_id, info, notified_number, None, settings).await;
+    };
+
+    update_source_baseline(pool, &media.media_id, source, info).await?;
+    let tracked = EpisodeInfo {
+        count: media.last_known_count,
+        latest_number: media.last_known_latest_number,
+        latest_id: media.last_known_latest_id.clone(),
+        raw_status: info.raw_status.clone(),
+    };
+    update_tracking_v2(pool, &media.media_id, &tracked, notified_number, None, settings).await?;
+
+    // The unchanged tracking baseline doesn't stamp the release
+    if notified_number.is_some() {
+        sqlx::query("UPDATE release_tracking_v2 SET last_episode_date = ? WHERE media_id = ?")
+            .bind(chrono::Utc::now().timestamp_millis())
+            .bind(&media.media_id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Set (or with `extension_id` None, clear) the source a media's releases
+/// are checked against in preferred mode. Without `source_media_id` the id is
+/// resolved through id_mappings at check time.
+pub async fn set_preferred_source(
+    pool: &SqlitePool,
+    media_id: &str,
+    extension_id: Option<&str>,
+    source_media_id: Option<&str>,
+) -> Result<()> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE release_tracking_v2 SET
+            preferred_extension_id = ?,
+            preferred_media_id = ?,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE media_id = ?
+        "#
+    )
+    .bind(extension_id)
+    .bind(extension_id.and(source_media_id))
+    .bind(media_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        anyhow::bail!("Releases of {} aren't tracked", media_id);
+    }
+    Ok(())
+}
+
 // ==================== Release Checking Logic ====================
 
 /// Some errors won't go away by retrying with the same input — e.g., a numeric
@@ -913,19 +1406,30 @@ async fn fetch_episode_info_with_retry(
     Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Unknown error")))
 }
 
-/// Get the NSFW filter setting from database
-/// Returns true if NSFW filter is enabled (hide adult content), false otherwise
-async fn get_nsfw_filter_setting(pool: &SqlitePool) -> bool {
-    let result: Option<String> = sqlx::query_scalar(
-        "SELECT value FROM app_settings WHERE key = 'nsfw_filter'"
-    )
-    .fetch_optional(pool)
+/// Whether a release check for this media goes through its extension rather
+/// than Jikan (MAL-id anime)
+fn uses_extension(media: &EligibleMedia) -> bool {
+    !(media.media_type == "anime" && media.media_id.parse::<i64>().is_ok())
+}
+
+/// Episode info of a MAL entry, with `offset` added to its episode numbers
+async fn fetch_jikan_episode_info(mal_id: i64, offset: i64) -> Result<EpisodeInfo> {
+    let details = tokio::task::spawn_blocking(move || {
+        jikan_anime::anime_details(mal_id)
+    })
     .await
-    .unwrap_or(None);
+    .map_err(|e| anyhow::anyhow!("Jikan task failed: {}", e))?
+    .map_err(|e| anyhow::anyhow!("Jikan API error: {}", e))?;
+
+    let latest_ep = details.episodes.iter()
+        .max_by(|a, b| a.number.partial_cmp(&b.number).unwrap_or(std::cmp::Ordering::Equal));
 
-    // Default to false (allow adult content) if not set
-    // Frontend uses nsfwFilter=true to hide adult, so "1" means hide adult
-    result.map(|v| v == "1").unwrap_or(false)
+    Ok(EpisodeInfo {
+        count: details.episodes.len() as i32 + offset as i32,
+        latest_number: latest_ep.map(|e| numbering::to_source_number(e.number as f64, offset) as f32),
+        latest_id: latest_ep.map(|e| e.id.clone()),
+        raw_status: details.status,
+    })
 }
 
 /// Fetch current episode info from extension (or Jikan for MAL IDs)
@@ -934,48 +1438,35 @@ async fn fetch_episode_info(
     pool: &SqlitePool,
     media: &EligibleMedia,
 ) -> Result<EpisodeInfo> {
-    // If media_id is a MAL ID (integer), use Jikan API directly.
-    // Post-migration anime uses MAL IDs; pre-migration anime uses AllAnime alphanumeric IDs.
     if media.media_type == "anime" {
-        if let Ok(mal_id) = media.media_id.parse::<i64>() {
-            log::debug!("Using Jikan API for anime release check: MAL ID {}", mal_id);
-
-            let details = tokio::task::spawn_blocking(move || {
-                jikan_anime::anime_details(mal_id)
-            })
+        // Linked split-cour media follow the sequel's MAL entry, numbered
+        // after part 1's episodes
+        let link = split_cour::get_split_cour_link(pool, &media.media_id)
             .await
-            .map_err(|e| anyhow::anyhow!("Jikan task failed: {}", e))?
-            .map_err(|e| anyhow::anyhow!("Jikan API error: {}", e))?;
-
-            let latest_ep = details.episodes.iter()
-                .max_by(|a, b| a.number.partial_cmp(&b.number).unwrap_or(std::cmp::Ordering::Equal));
+            .map_err(|e| anyhow::anyhow!(e))?;
+        if let Some(link) = link {
+            log::debug!(
+                "Using split-cour sequel MAL ID {} for {} (offset {})",
+                link.sequel_mal_id, media.media_id, link.episode_offset
+            );
+            return fetch_jikan_episode_info(link.sequel_mal_id, link.episode_offset).await;
+        }
 
-            return Ok(EpisodeInfo {
-                count: details.episodes.len() as i32,
-                latest_number: latest_ep.map(|e| e.number),
-                latest_id: latest_ep.map(|e| e.id.clone()),
-                raw_status: details.status,
-            });
+        // If media_id is a MAL ID (integer), use Jikan API directly.
+        // Post-migration anime uses MAL IDs; pre-migration anime uses AllAnime alphanumeric IDs.
+        if let Ok(mal_id) = media.media_id.parse::<i64>() {
+            log::debug!("Using Jikan API for anime release check: MAL ID {}", mal_id);
+            return fetch_jikan_episode_info(mal_id, 0).await;
         }
     }
 
     // Fallback: use extension system (manga, pre-migration anime with AllAnime IDs)
-    // Get NSFW filter setting BEFORE acquiring lock to avoid holding lock across await
-    // nsfwFilter=true means "hide adult content", so allow_adult should be !nsfwFilter
-    let nsfw_filter = get_nsfw_filter_setting(pool).await;
-    let allow_adult = !nsfw_filter;
+    // Get the adult mode BEFORE acquiring lock to avoid holding lock across await
+    let allow_adult = adult::background_allow_adult(pool).await;
 
-    log::debug!("Creating extension runtime with allow_adult={} (nsfw_filter={})", allow_adult, nsfw_filter);
+    log::debug!("Creating extension runtime with allow_adult={}", allow_adult);
 
-    let extension = {
-        let extensions = app_state.extensions.read()
-            .map_err(|e| anyhow::anyhow!("Failed to lock extensions: {}", e))?;
-
-        extensions.iter()
-            .find(|ext| ext.metadata.id == media.extension_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Extension {} not found", media.extension_id))?
-    }; // MutexGuard dropped here
+    let extension = app_state.extension(&media.extension_id)?;
 
     let runtime = ExtensionRuntime::with_options(extension.clone(), allow_adult)
         .context("Failed to create extension runtime")?;
@@ -1033,22 +1524,201 @@ async fn fetch_episode_info(
     }
 }
 
-/// Check a single media item for new releases
+/// Check a single media item for new releases. Background checks draw from
+/// the extension's background call budget.
 async fn check_single_media(
     app_state: &AppState,
     pool: &SqlitePool,
     media: &EligibleMedia,
     settings: &ReleaseCheckSettings,
+    background: bool,
 ) -> Result<Option<ReleaseCheckResult>> {
+    let max_retries = settings.max_retries;
+    check_media_with(pool, media, settings, background, |media| async move {
+        fetch_episode_info_with_retry(app_state, pool, &media, max_retries).await
+    })
+    .await
+}
+
+/// Re-read the tracked state of a media. The pass's snapshot may predate a
+/// check another pass has finished since, and comparing against it would
+/// report the same release again.
+async fn refresh_tracking_snapshot(pool: &SqlitePool, media: &mut EligibleMedia) -> Result<()> {
+    let row = sqlx::query(
+        r#"
+        SELECT last_known_count, last_known_latest_number, last_known_latest_id, user_notified_up_to
+        FROM release_tracking_v2
+        WHERE media_id = ?
+        "#
+    )
+    .bind(&media.media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(row) = row {
+        media.last_known_count = row.try_get::<Option<i32>, _>("last_known_count")?.unwrap_or(0);
+        media.last_known_latest_number = row.try_get("last_known_latest_number")?;
+        media.last_known_latest_id = row.try_get("last_known_latest_id")?;
+        media.user_notified_up_to = row.try_get("user_notified_up_to")?;
+    }
+
+    Ok(())
+}
+
+/// Fetch `source` (the media as it is in the source asked) behind its
+/// extension's circuit breaker and background budget. None when the fetch
+/// was skipped or deferred.
+async fn fetch_guarded<F, Fut>(
+    pool: &SqlitePool,
+    media: &EligibleMedia,
+    source: &EligibleMedia,
+    background: bool,
+    fetch: &mut F,
+) -> Option<Result<EpisodeInfo>>
+where
+    F: FnMut(EligibleMedia) -> Fut,
+    Fut: Future<Output = Result<EpisodeInfo>>,
+{
+    // Skip media whose extension keeps failing; it's picked up again once
+    // the breaker closes
+    let via_extension = uses_extension(source);
+    if via_extension {
+        if let Err(e) = circuit_breaker::check(&source.extension_id) {
+            log::debug!("Skipping release check for {}: {}", media.media_id, e);
+            return None;
+        }
+        // Deferred to a later pass while the extension's budget is used up
+        if background && background_budget::take(&source.extension_id).is_err() {
+            let _ = log_check_result(
+                pool, &media.media_id, "deferred_budget",
+                Some(media.last_known_count), None,
+                media.last_known_latest_number, None,
+                None, None, None, false
+            ).await;
+            return None;
+        }
+    }
+
     // Fetch with retry
-    let current = match fetch_episode_info_with_retry(app_state, pool, media, settings.max_retries).await {
+    let fetched = fetch(source.clone()).await;
+
+    // Media-specific errors (removed titles, bad ids) say nothing about the extension
+    if via_extension {
+        match &fetched {
+            Ok(_) => circuit_breaker::record_success(&source.extension_id),
+            Err(e) if !is_permanent_error(e) => {
+                circuit_breaker::record_failure(&source.extension_id, &e.to_string())
+            }
+            Err(_) => circuit_breaker::release_probe(&source.extension_id),
+        }
+    }
+
+    Some(fetched)
+}
+
+/// check_single_media with the fetch injected (tests use a fake one). The
+/// fetch gets the media as it is in the source asked.
+async fn check_media_with<F, Fut>(
+    pool: &SqlitePool,
+    media: &EligibleMedia,
+    settings: &ReleaseCheckSettings,
+    background: bool,
+    mut fetch: F,
+) -> Result<Option<ReleaseCheckResult>>
+where
+    F: FnMut(EligibleMedia) -> Fut,
+    Fut: Future<Output = Result<EpisodeInfo>>,
+{
+    let Some(_in_flight) = InFlightGuard::acquire(&media.media_id) else {
+        log::info!("Release check for {} already in progress, skipping", media.media_id);
+        let _ = log_check_result(
+            pool, &media.media_id, "skipped_in_flight",
+            Some(media.last_known_count), None,
+            media.last_known_latest_number, None,
+            None, None, None, false
+        ).await;
+        return Ok(None);
+    };
+
+    let mut media = media.clone();
+    if let Err(e) = refresh_tracking_snapshot(pool, &mut media).await {
+        log::warn!("Failed to refresh tracking state for {}: {}", media.media_id, e);
+    }
+    let media = &media;
+
+    // The preferred source is asked first. A release it doesn't show yet is
+    // confirmed by the tracking source, unless notifications wait until the
+    // episode is watchable there. Each source is compared only against its
+    // own earlier answers.
+    let preferred = match settings.release_source {
+        ReleaseSource::Preferred => preferred_source(pool, media, &settings.source_priority).await,
+        ReleaseSource::Tracking => None,
+    };
+    let preferred = match preferred {
+        Some(source) => match source_baseline(pool, media, &source).await {
+            Ok(baseline) => Some((source, baseline)),
+            Err(e) => {
+                log::warn!("Failed to read the {} baseline of {}: {}", source.extension_id, media.media_id, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let mut answer = None;
+    if let Some((source, baseline)) = &preferred {
+        let watchable_only = settings.notify_when_watchable;
+        let outcome = match fetch_guarded(pool, media, source, background, &mut fetch).await {
+            Some(Ok(info)) => Some(Ok(canonical_episode_info(pool, media, source, info).await)),
+            outcome => outcome,
+        };
+        match outcome {
+            Some(Ok(info)) if watchable_only || detect_new_release(baseline, &info).is_some() => {
+                answer = Some((Ok(info), Some((source, baseline))));
+            }
+            Some(Err(e)) if watchable_only => answer = Some((Err(e), Some((source, baseline)))),
+            None if watchable_only => return Ok(None),
+            outcome => {
+                // Nothing new there still moves the source's own baseline on
+                if let Some(Ok(info)) = &outcome {
+                    if info.count >= baseline.last_known_count {
+                        if let Err(e) = update_source_baseline(pool, &media.media_id, source, info).await {
+                            log::warn!("Failed to record the {} baseline of {}: {}", source.extension_id, media.media_id, e);
+                        }
+                    }
+                }
+                log::debug!(
+                    "Preferred source {} {} for {}, asking {}",
+                    source.extension_id,
+                    match outcome {
+                        Some(Ok(_)) => "has no new release",
+                        Some(Err(_)) => "failed",
+                        None => "was skipped",
+                    },
+                    media.media_id,
+                    media.extension_id
+                );
+            }
+        }
+    }
+    let (fetched, answered_by) = match answer {
+        Some(answer) => answer,
+        None => match fetch_guarded(pool, media, media, background, &mut fetch).await {
+            Some(fetched) => (fetched, None),
+            None => return Ok(None),
+        },
+    };
+    let source = answered_by.map(|(source, _)| source);
+    let baseline = answered_by.map_or(media, |(_, baseline)| baseline);
+    let confirmed_by = source.map_or(&media.extension_id, |source| &source.extension_id).clone();
+
+    let current = match fetched {
         Ok(info) => info,
         Err(e) => {
             // Log error
             let _ = log_check_result(
                 pool, &media.media_id, "api_error",
-                Some(media.last_known_count), None,
-                media.last_known_latest_number, None,
+                Some(baseline.last_known_count), None,
+                baseline.last_known_latest_number, None,
                 None, None,
                 Some(&e.to_string()), false
             ).await;
@@ -1071,11 +1741,12 @@ async fn check_single_media(
         }
     };
 
-    // First-time initialization (no previous data)
-    if media.last_known_count == 0 && media.last_known_latest_number.is_none() {
+    // First-time initialization (no previous data). A preferred source's
+    // first answer leaves what was notified alone.
+    if baseline.last_known_count == 0 && baseline.last_known_latest_number.is_none() {
         log::info!(
-            "First-time tracking for {}: count={}, number={:?}",
-            media.media_id, current.count, current.latest_number
+            "First-time tracking for {} in {}: count={}, number={:?}",
+            media.media_id, confirmed_by, current.count, current.latest_number
         );
 
         let _ = log_check_result(
@@ -1085,22 +1756,23 @@ async fn check_single_media(
             None, None, None, false
         ).await;
 
-        let _ = update_tracking_v2(pool, &media.media_id, &current, current.latest_number, None, settings).await;
+        let notified = if source.is_none() { current.latest_number } else { None };
+        let _ = record_answer(pool, media, source, &current, notified, settings).await;
 
         return Ok(None);
     }
 
     // Check for count decrease (API inconsistency)
-    if current.count < media.last_known_count {
+    if current.count < baseline.last_known_count {
         log::warn!(
-            "Count decreased for {}: {} -> {} (ignoring)",
-            media.media_id, media.last_known_count, current.count
+            "Count decreased for {} in {}: {} -> {} (ignoring)",
+            media.media_id, confirmed_by, baseline.last_known_count, current.count
         );
 
         let _ = log_check_result(
             pool, &media.media_id, "count_decreased",
-            Some(media.last_known_count), Some(current.count),
-            media.last_known_latest_number, current.latest_number,
+            Some(baseline.last_known_count), Some(current.count),
+            baseline.last_known_latest_number, current.latest_number,
             None, None, None, false
         ).await;
 
@@ -1109,27 +1781,27 @@ async fn check_single_media(
     }
 
     // Detect new release using multi-signal
-    if let Some((signal, new_count)) = detect_new_release(media, &current) {
+    if let Some((signal, new_count)) = detect_new_release(baseline, &current) {
         let should_send = should_notify(media, current.latest_number);
 
         log::info!(
-            "New {} detected for {} via {}: {} new (notify={})",
+            "New {} detected for {} via {} in {}: {} new (notify={})",
             if media.media_type == "anime" { "episodes" } else { "chapters" },
-            media.media_id, signal, new_count, should_send
+            media.media_id, signal, confirmed_by, new_count, should_send
         );
 
         let _ = log_check_result(
             pool, &media.media_id, "new_release",
-            Some(media.last_known_count), Some(current.count),
-            media.last_known_latest_number, current.latest_number,
+            Some(baseline.last_known_count), Some(current.count),
+            baseline.last_known_latest_number, current.latest_number,
             Some(&signal), Some(new_count), None, should_send
         ).await;
 
         // Update tracking
-        let _ = update_tracking_v2(
-            pool, &media.media_id, &current,
+        let _ = record_answer(
+            pool, media, source, &current,
             if should_send { current.latest_number } else { None },
-            None, settings
+            settings
         ).await;
 
         if should_send {
@@ -1137,27 +1809,28 @@ async fn check_single_media(
                 media_id: media.media_id.clone(),
                 media_title: media.title.clone(),
                 media_type: media.media_type.clone(),
-                previous_count: media.last_known_count,
+                previous_count: baseline.last_known_count,
                 current_count: current.count,
-                previous_number: media.last_known_latest_number,
+                previous_number: baseline.last_known_latest_number,
                 current_number: current.latest_number,
                 new_releases: new_count,
                 extension_id: media.extension_id.clone(),
                 detection_signal: signal,
                 cover_url: media.cover_url.clone(),
                 latest_episode_id: current.latest_id.clone(),
+                confirmed_by,
             }));
         }
     } else {
         // No change
         let _ = log_check_result(
             pool, &media.media_id, "no_change",
-            Some(media.last_known_count), Some(current.count),
-            media.last_known_latest_number, current.latest_number,
+            Some(baseline.last_known_count), Some(current.count),
+            baseline.last_known_latest_number, current.latest_number,
             None, None, None, false
         ).await;
 
-        let _ = update_tracking_v2(pool, &media.media_id, &current, None, None, settings).await;
+        let _ = record_answer(pool, media, source, &current, None, settings).await;
     }
 
     Ok(None)
@@ -1165,7 +1838,7 @@ async fn check_single_media(
 
 /// Pick the highest-priority anime source for silent auto-download.
 /// Prefers HLS sources with numeric resolution, falling back to the first entry.
-fn pick_auto_download_source(
+pub(crate) fn pick_auto_download_source(
     sources: &crate::extensions::VideoSources,
 ) -> Option<&crate::extensions::VideoSource> {
     let by_resolution = sources
@@ -1176,7 +1849,7 @@ fn pick_auto_download_source(
     by_resolution.or_else(|| sources.sources.first())
 }
 
-fn sanitize_filename(input: &str) -> String {
+pub(crate) fn sanitize_filename(input: &str) -> String {
     input
         .chars()
         .map(|c| if c.is_alphanumeric() { c } else { '_' })
@@ -1204,24 +1877,11 @@ async fn trigger_auto_download(
         return;
     };
 
-    let extension = {
-        let extensions = match app_state.extensions.read() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::warn!("Auto-download: failed to lock extensions: {}", e);
-                return;
-            }
-        };
-        extensions
-            .iter()
-            .find(|ext| ext.metadata.id == media.extension_id)
-            .cloned()
-    };
-
-    let Some(extension) = extension else {
+    // The episode id is one of the source that confirmed the release
+    let Ok(extension) = app_state.extension(&result.confirmed_by) else {
         log::warn!(
             "Auto-download: extension {} not found for {}",
-            media.extension_id,
+            result.confirmed_by,
             media.media_id
         );
         return;
@@ -1232,11 +1892,14 @@ async fn trigger_auto_download(
         .map(|n| n.round() as i32)
         .unwrap_or(result.current_count);
 
+    // Same mode the release check found the episode in
+    let allow_adult = adult::background_allow_adult(app_state.database.pool()).await;
+
     let picked = {
-        let runtime = match ExtensionRuntime::new(extension) {
+        let runtime = match crate::commands::background_runtime(extension, allow_adult) {
             Ok(r) => r,
             Err(e) => {
-                log::warn!("Auto-download: failed to init runtime: {}", e);
+                log::warn!("Auto-download: skipped {} ep {}: {}", media.media_id, episode_id, e);
                 return;
             }
         };
@@ -1253,14 +1916,18 @@ async fn trigger_auto_download(
         pick_auto_download_source(&sources).map(|s| {
             (
                 s.url.clone(),
+                crate::downloads::lazy_source::source_headers(s),
+                crate::downloads::subtitles::source_subtitles(s, &sources),
+                crate::downloads::source_history::alternatives(&sources.sources, &s.url),
                 s.source_type.clone(),
                 s.resolution,
                 s.quality.clone(),
+                s.server.clone(),
             )
         })
     };
 
-    let Some((url, source_type, resolution, quality)) = picked else {
+    let Some((url, headers, subtitles, alternatives, source_type, resolution, quality, server)) = picked else {
         log::warn!(
             "Auto-download: no usable sources for {} ep {}",
             media.media_id, episode_id
@@ -1286,16 +1953,31 @@ async fn trigger_auto_download(
             media.media_id.clone(),
             episode_id.clone(),
             episode_number,
+            Some(media.title.clone()),
             url,
+            headers,
+            subtitles,
+            alternatives,
             filename,
             None,
+            Some(quality_label),
+            Some(server),
+            None,
+            None,
+            false,
         )
         .await
     {
-        log::warn!(
-            "Auto-download: queue_download failed for {}: {}",
-            download_id, e
-        );
+        match crate::downloads::QueueError::from_anyhow(e, "Failed to queue download") {
+            crate::downloads::QueueError::AlreadyDownloaded(existing) => log::debug!(
+                "Auto-download: {} ep {} is already downloaded at {}",
+                media.title, episode_number, existing.file_path
+            ),
+            e => log::warn!(
+                "Auto-download: queue_download failed for {}: {}",
+                download_id, e
+            ),
+        }
     } else {
         log::info!(
             "Auto-download queued for {} ep {} ({})",
@@ -1362,7 +2044,7 @@ async fn run_full_release_check_inner(
 
         if should_stop {
             log::info!("Release check stopped by user (manual={})", is_manual);
-            let _ = app_handle.emit("release_check_progress", ReleaseCheckProgress {
+            RELEASE_CHECK_PROGRESS_EVENT.emit(app_handle, &ReleaseCheckProgress {
                 current_index: index as u32 + 1,
                 total_count,
                 media_title: String::new(),
@@ -1380,7 +2062,7 @@ async fn run_full_release_check_inner(
             break;
         }
 
-        let _ = app_handle.emit("release_check_progress", ReleaseCheckProgress {
+        RELEASE_CHECK_PROGRESS_EVENT.emit(app_handle, &ReleaseCheckProgress {
             current_index: index as u32 + 1,
             total_count,
             media_title: media.title.clone(),
@@ -1390,9 +2072,9 @@ async fn run_full_release_check_inner(
             error_message: None,
         });
 
-        match check_single_media(&app_state, pool, media, &settings).await {
+        match check_single_media(&app_state, pool, media, &settings, !is_manual).await {
             Ok(Some(result)) => {
-                let _ = app_handle.emit("release_check_progress", ReleaseCheckProgress {
+                RELEASE_CHECK_PROGRESS_EVENT.emit(app_handle, &ReleaseCheckProgress {
                     current_index: index as u32 + 1,
                     total_count,
                     media_title: media.title.clone(),
@@ -1402,7 +2084,9 @@ async fn run_full_release_check_inner(
                     error_message: None,
                 });
 
-                if let Err(e) = emit_release_notification(app_handle, pool, &result).await {
+                // Tracking (and with it the NEW badge) was already updated by
+                // check_single_media; only delivery depends on the digest mode
+                if let Err(e) = emit_release_notification(app_handle, pool, &result, settings.digest_mode).await {
                     log::error!("Failed to emit notification for {}: {}", result.media_id, e);
                 }
 
@@ -1417,7 +2101,7 @@ async fn run_full_release_check_inner(
             }
             Err(e) => {
                 log::error!("Failed to check {}: {}", media.media_id, e);
-                let _ = app_handle.emit("release_check_progress", ReleaseCheckProgress {
+                RELEASE_CHECK_PROGRESS_EVENT.emit(app_handle, &ReleaseCheckProgress {
                     current_index: index as u32 + 1,
                     total_count,
                     media_title: media.title.clone(),
@@ -1433,7 +2117,7 @@ async fn run_full_release_check_inner(
     }
 
     // Always emit completion so the frontend overlay can dismiss
-    let _ = app_handle.emit("release_check_progress", ReleaseCheckProgress {
+    RELEASE_CHECK_PROGRESS_EVENT.emit(app_handle, &ReleaseCheckProgress {
         current_index: total_count,
         total_count,
         media_title: String::new(),
@@ -1447,10 +2131,15 @@ async fn run_full_release_check_inner(
     settings.last_full_check = Some(chrono::Utc::now().timestamp_millis());
     update_release_settings(pool, &settings).await?;
 
-    if results.len() > 3 {
+    if settings.digest_mode == DigestMode::Immediate && results.len() > 3 {
         emit_summary_notification(app_handle, pool, &results).await?;
     }
 
+    // Statuses the normalizer couldn't classify, for the tracking debug view
+    if let Err(e) = crate::status_normalizer::flush_unknown_statuses(pool).await {
+        log::warn!("Failed to record unknown release statuses: {}", e);
+    }
+
     log::info!(
         "Release check complete: {} new releases found in {:.1}s",
         results.len(),
@@ -1480,7 +2169,12 @@ async fn emit_release_notification(
     app_handle: &AppHandle,
     pool: &SqlitePool,
     result: &ReleaseCheckResult,
+    digest_mode: DigestMode,
 ) -> Result<()> {
+    if digest_mode != DigestMode::Immediate {
+        return queue_release_digest(pool, result).await;
+    }
+
     let (title, message) = if result.media_type == "anime" {
         (
             "New Episode Available",
@@ -1578,6 +2272,192 @@ async fn emit_summary_notification(
     Ok(())
 }
 
+// ==================== Release Digest ====================
+
+/// A title's queued releases, merged across every check since the last flush
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestEntry {
+    pub media_id: String,
+    pub media_title: String,
+    pub media_type: String,
+    pub extension_id: String,
+    pub new_releases: i32,
+    pub current_number: Option<f32>,
+    pub cover_url: Option<String>,
+}
+
+/// Queue a detected release for the next digest instead of notifying now
+async fn queue_release_digest(pool: &SqlitePool, result: &ReleaseCheckResult) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO pending_release_digest (
+            media_id, media_title, media_type, extension_id,
+            new_releases, current_number, current_count, cover_url, detected_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&result.media_id)
+    .bind(&result.media_title)
+    .bind(&result.media_type)
+    .bind(&result.extension_id)
+    .bind(result.new_releases)
+    .bind(result.current_number)
+    .bind(result.current_count)
+    .bind(&result.cover_url)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove all queued releases and merge them per media, in detection order.
+/// Returns None when nothing is pending.
+async fn take_pending_digest(pool: &SqlitePool) -> Result<Option<Vec<DigestEntry>>> {
+    let mut tx = pool.begin().await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, media_id, media_title, media_type, extension_id,
+               new_releases, current_number, cover_url
+        FROM pending_release_digest
+        ORDER BY detected_at, id
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut entries: Vec<DigestEntry> = Vec::new();
+    let mut max_id: i64 = 0;
+
+    for row in rows {
+        max_id = max_id.max(row.try_get::<i64, _>("id")?);
+        let media_id: String = row.try_get("media_id")?;
+        let new_releases: i32 = row.try_get("new_releases")?;
+        let current_number: Option<f32> = row.try_get("current_number")?;
+        let cover_url: Option<String> = row.try_get("cover_url")?;
+
+        if let Some(entry) = entries.iter_mut().find(|e| e.media_id == media_id) {
+            // Later checks only ever move forward, so the newest number wins
+            entry.new_releases += new_releases;
+            if current_number.is_some() {
+                entry.current_number = current_number;
+            }
+            if cover_url.is_some() {
+                entry.cover_url = cover_url;
+            }
+        } else {
+            entries.push(DigestEntry {
+                media_id,
+                media_title: row.try_get("media_title")?,
+                media_type: row.try_get("media_type")?,
+                extension_id: row.try_get("extension_id")?,
+                new_releases,
+                current_number,
+                cover_url,
+            });
+        }
+    }
+
+    // Only clear what was read; anything queued meanwhile waits for the next flush
+    sqlx::query("DELETE FROM pending_release_digest WHERE id <= ?")
+        .bind(max_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(Some(entries))
+}
+
+/// Build the grouped notification, e.g. "7 new episodes across 5 shows"
+fn digest_notification(entries: &[DigestEntry]) -> NotificationPayload {
+    let total_releases: i32 = entries.iter().map(|e| e.new_releases).sum();
+    let unique_titles = entries.len();
+
+    let all_anime = entries.iter().all(|e| e.media_type == "anime");
+    let all_manga = entries.iter().all(|e| e.media_type == "manga");
+    let (release_noun, title_noun) = match (all_anime, all_manga) {
+        (true, _) => (("episode", "episodes"), ("show", "shows")),
+        (_, true) => (("chapter", "chapters"), ("series", "series")),
+        _ => (("release", "releases"), ("title", "titles")),
+    };
+    let pick = |n: usize, (one, many): (&'static str, &'static str)| if n == 1 { one } else { many };
+
+    let message = format!(
+        "{} new {} across {} {}",
+        total_releases,
+        pick(total_releases.max(0) as usize, release_noun),
+        unique_titles,
+        pick(unique_titles, title_noun),
+    );
+
+    NotificationPayload::new(NotificationType::Info, "New Releases Available", message)
+        .with_source("release")
+        .with_action("View Library", Some("/library".to_string()), None)
+        .with_metadata(serde_json::json!({
+            "total_releases": total_releases,
+            "unique_titles": unique_titles,
+            "is_digest": true,
+            "items": entries,
+        }))
+}
+
+/// Send everything queued as one notification. Returns the number of titles included.
+pub async fn flush_release_digest(app_handle: &AppHandle, pool: &SqlitePool) -> Result<usize> {
+    let Some(entries) = take_pending_digest(pool).await? else {
+        return Ok(0);
+    };
+
+    emit_notification(app_handle, Some(pool), digest_notification(&entries)).await?;
+    log::info!("Flushed release digest with {} titles", entries.len());
+    Ok(entries.len())
+}
+
+/// Flush the digest when its cadence has elapsed. In immediate mode any rows
+/// left over from a previous digest setting are delivered straight away.
+async fn flush_digest_if_due(
+    app_handle: &AppHandle,
+    pool: &SqlitePool,
+    settings: &ReleaseCheckSettings,
+) -> Result<()> {
+    let Some(interval_ms) = settings.digest_mode.interval_ms() else {
+        flush_release_digest(app_handle, pool).await?;
+        return Ok(());
+    };
+
+    let last_flush: Option<i64> = sqlx::query_scalar::<_, String>(
+        "SELECT value FROM app_settings WHERE key = 'release_digest_last_flush'"
+    )
+    .fetch_optional(pool)
+    .await?
+    .and_then(|v| v.parse().ok());
+
+    let now = chrono::Utc::now().timestamp_millis();
+    if last_flush.is_some_and(|last| now - last < interval_ms) {
+        return Ok(());
+    }
+
+    flush_release_digest(app_handle, pool).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO app_settings (key, value, updated_at)
+        VALUES ('release_digest_last_flush', ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(now.to_string())
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 // ==================== Public API for Commands ====================
 
 /// Get release states for multiple media (for NEW badge)
@@ -1629,6 +2509,7 @@ pub async fn get_media_release_states(
         };
 
         states.push(MediaReleaseState {
+            is_checking: is_checking(&media_id),
             media_id,
             has_new_release: has_new,
             latest_number: latest,
@@ -1664,9 +2545,42 @@ pub async fn acknowledge_new_releases(
     .execute(pool)
     .await?;
 
+    crate::badges::invalidate();
     Ok(())
 }
 
+/// Dismiss the NEW badge once the latest known release has been watched.
+/// Watching an older episode leaves it alone. Returns whether anything changed.
+pub async fn acknowledge_watched_release(
+    pool: &SqlitePool,
+    media_id: &str,
+    episode_number: f32,
+) -> Result<bool> {
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let result = sqlx::query(
+        r#"
+        UPDATE release_tracking_v2 SET
+            user_acknowledged_at = ?,
+            user_notified_up_to = MAX(COALESCE(user_notified_up_to, 0), last_known_latest_number),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE media_id = ?
+          AND last_known_latest_number IS NOT NULL
+          AND ? >= last_known_latest_number
+        "#
+    )
+    .bind(now)
+    .bind(media_id)
+    .bind(episode_number)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        crate::badges::invalidate();
+    }
+    Ok(result.rows_affected() > 0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1687,7 +2601,8 @@ mod tests {
                 extension_id TEXT NOT NULL,
                 title TEXT NOT NULL,
                 media_type TEXT CHECK(media_type IN ('anime', 'manga')) NOT NULL,
-                status TEXT
+                status TEXT,
+                last_aired_at INTEGER
             );
             "#
         )
@@ -1715,6 +2630,8 @@ mod tests {
                 next_scheduled_check INTEGER,
                 consecutive_failures INTEGER DEFAULT 0,
                 last_error TEXT,
+                preferred_extension_id TEXT,
+                preferred_media_id TEXT,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (media_id) REFERENCES media(id) ON DELETE CASCADE
@@ -1725,6 +2642,22 @@ mod tests {
         .await
         .expect("create release_tracking_v2 table");
 
+        sqlx::query(
+            r#"
+            CREATE TABLE id_mappings (
+                mal_id TEXT PRIMARY KEY,
+                allanime_id TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                title TEXT NOT NULL,
+                match_score REAL,
+                created_at TEXT DEFAULT (datetime('now'))
+            );
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect("create id_mappings table");
+
         sqlx::query(
             r#"
             CREATE TABLE release_tracking (
@@ -1745,9 +2678,102 @@ mod tests {
         .await
         .expect("create release_tracking table");
 
+        sqlx::query(
+            r#"
+            CREATE TABLE pending_release_digest (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                media_id TEXT NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+                media_title TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                extension_id TEXT NOT NULL,
+                new_releases INTEGER NOT NULL,
+                current_number REAL,
+                current_count INTEGER NOT NULL,
+                cover_url TEXT,
+                detected_at INTEGER NOT NULL
+            );
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect("create pending_release_digest table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE release_check_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                media_id TEXT NOT NULL,
+                check_timestamp INTEGER NOT NULL,
+                result_type TEXT NOT NULL,
+                previous_count INTEGER,
+                previous_latest_number REAL,
+                new_count INTEGER,
+                new_latest_number REAL,
+                detection_signal TEXT,
+                new_releases_count INTEGER,
+                error_message TEXT,
+                notification_sent INTEGER DEFAULT 0
+            );
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect("create release_check_log table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE release_source_baselines (
+                media_id TEXT NOT NULL,
+                extension_id TEXT NOT NULL,
+                source_media_id TEXT NOT NULL,
+                last_known_count INTEGER NOT NULL,
+                last_known_latest_number REAL,
+                last_known_latest_id TEXT,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (media_id, extension_id)
+            );
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect("create release_source_baselines table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE numbering_offsets (
+                media_id TEXT NOT NULL,
+                extension_id TEXT NOT NULL,
+                episode_offset INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (media_id, extension_id)
+            );
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect("create numbering_offsets table");
+
         pool
     }
 
+    fn release(media_id: &str, title: &str, new_releases: i32, number: f32) -> ReleaseCheckResult {
+        ReleaseCheckResult {
+            media_id: media_id.to_string(),
+            media_title: title.to_string(),
+            media_type: "anime".to_string(),
+            previous_count: 0,
+            current_count: number as i32,
+            previous_number: None,
+            current_number: Some(number),
+            new_releases,
+            extension_id: "jikan".to_string(),
+            detection_signal: "number".to_string(),
+            cover_url: None,
+            latest_episode_id: None,
+            confirmed_by: "jikan".to_string(),
+        }
+    }
+
     #[tokio::test]
     async fn update_tracking_inserts_missing_first_check_baseline() {
         let pool = test_pool().await;
@@ -1816,6 +2842,550 @@ mod tests {
         assert_eq!(legacy_count, 1);
     }
 
+    #[tokio::test]
+    async fn a_check_that_finds_more_episodes_records_when() {
+        let pool = test_pool().await;
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('1', 'jikan', 'Frieren', 'anime')")
+            .execute(&pool)
+            .await
+            .expect("insert media");
+
+        let settings = ReleaseCheckSettings::default();
+        let episodes = |n: i32| EpisodeInfo {
+            count: n,
+            latest_number: Some(n as f32),
+            latest_id: None,
+            raw_status: Some("Releasing".to_string()),
+        };
+        let released_at = || async {
+            sqlx::query_scalar::<_, Option<i64>>("SELECT last_episode_date FROM release_tracking_v2 WHERE media_id = '1'")
+                .fetch_one(&pool)
+                .await
+                .expect("fetch last_episode_date")
+        };
+
+        // The first check only sets the baseline; nothing was released yet
+        update_tracking_v2(&pool, "1", &episodes(6), Some(6.0), None, &settings).await.expect("first check");
+        assert_eq!(released_at().await, None);
+
+        // ...unless the details said when the last episode aired
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, last_aired_at) VALUES ('2', 'jikan', 'Dandadan', 'anime', 42)")
+            .execute(&pool)
+            .await
+            .expect("insert aired media");
+        update_tracking_v2(&pool, "2", &episodes(6), Some(6.0), None, &settings).await.expect("first check");
+        let aired: Option<i64> = sqlx::query_scalar("SELECT last_episode_date FROM release_tracking_v2 WHERE media_id = '2'")
+            .fetch_one(&pool)
+            .await
+            .expect("fetch last_episode_date");
+        assert_eq!(aired, Some(42));
+
+        update_tracking_v2(&pool, "1", &episodes(7), None, None, &settings).await.expect("new episode");
+        let stamped = released_at().await.expect("release time recorded");
+        assert!(chrono::Utc::now().timestamp_millis() - stamped < 60_000);
+
+        sqlx::query("UPDATE release_tracking_v2 SET last_episode_date = 1 WHERE media_id = '1'")
+            .execute(&pool)
+            .await
+            .expect("backdate release");
+        update_tracking_v2(&pool, "1", &episodes(7), None, None, &settings).await.expect("no change");
+        assert_eq!(released_at().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn digest_flush_groups_releases_per_media() {
+        let pool = test_pool().await;
+
+        for (id, title) in [("1", "Frieren"), ("2", "Dandadan")] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'jikan', ?, 'anime')")
+                .bind(id)
+                .bind(title)
+                .execute(&pool)
+                .await
+                .expect("insert media");
+        }
+
+        queue_release_digest(&pool, &release("1", "Frieren", 1, 5.0)).await.expect("queue");
+        queue_release_digest(&pool, &release("2", "Dandadan", 2, 8.0)).await.expect("queue");
+        queue_release_digest(&pool, &release("1", "Frieren", 2, 7.0)).await.expect("queue");
+
+        let entries = take_pending_digest(&pool).await.expect("take").expect("digest present");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].media_id, "1");
+        assert_eq!(entries[0].new_releases, 3);
+        assert_eq!(entries[0].current_number, Some(7.0));
+        assert_eq!(entries[1].media_id, "2");
+        assert_eq!(entries[1].new_releases, 2);
+
+        let notification = digest_notification(&entries);
+        assert_eq!(notification.message, "5 new episodes across 2 shows");
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pending_release_digest")
+            .fetch_one(&pool)
+            .await
+            .expect("count pending");
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn digest_flush_with_nothing_pending_is_noop() {
+        let pool = test_pool().await;
+
+        assert!(take_pending_digest(&pool).await.expect("take").is_none());
+    }
+
+    #[tokio::test]
+    async fn watching_the_latest_release_acknowledges_it() {
+        let pool = test_pool().await;
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('1', 'jikan', 'Frieren', 'anime')")
+            .execute(&pool)
+            .await
+            .expect("insert media");
+        sqlx::query(
+            "INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, last_known_latest_number, last_checked_at)
+             VALUES ('1', 'jikan', 'anime', 12, 0)"
+        )
+        .execute(&pool)
+        .await
+        .expect("insert tracking");
+
+        assert!(!acknowledge_watched_release(&pool, "1", 11.0).await.expect("ack"));
+        assert!(acknowledge_watched_release(&pool, "1", 12.0).await.expect("ack"));
+
+        let (notified_up_to, acknowledged_at): (Option<f64>, Option<i64>) = sqlx::query_as(
+            "SELECT user_notified_up_to, user_acknowledged_at FROM release_tracking_v2 WHERE media_id = '1'"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("fetch tracking");
+        assert_eq!(notified_up_to, Some(12.0));
+        assert!(acknowledged_at.is_some());
+
+        assert!(!acknowledge_watched_release(&pool, "missing", 12.0).await.expect("ack"));
+    }
+
+    #[test]
+    fn only_titles_that_may_still_release_are_tracked_by_default() {
+        assert!(default_track_releases(Some("Currently Airing")));
+        assert!(default_track_releases(Some("Not yet aired")));
+        assert!(default_track_releases(Some("SomeRandomStatus")));
+        assert!(default_track_releases(None));
+        assert!(!default_track_releases(Some("Finished Airing")));
+        assert!(!default_track_releases(Some("Completed")));
+        assert!(!default_track_releases(Some("Hiatus")));
+    }
+
+    async fn tracking_row(pool: &SqlitePool, media_id: &str) -> Option<(i32, i64, Option<i64>)> {
+        sqlx::query_as(
+            "SELECT notification_enabled, last_known_count, next_scheduled_check FROM release_tracking_v2 WHERE media_id = ?"
+        )
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await
+        .expect("fetch tracking row")
+    }
+
+    #[tokio::test]
+    async fn disabled_titles_are_not_initialized() {
+        let pool = test_pool().await;
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, status) VALUES ('1', 'jikan', 'Cowboy Bebop', 'anime', 'Finished Airing')")
+            .execute(&pool)
+            .await
+            .expect("insert media");
+
+        disable_release_tracking(&pool, "1").await.expect("disable");
+        assert!(is_tracking_disabled(&pool, "1").await.expect("status"));
+
+        initialize_tracking_v2(&pool, "1", "jikan", "anime", 26, Some(26.0), None, Some("Finished Airing"))
+            .await
+            .expect("init");
+
+        // Still the opted-out row without a baseline, and no legacy row either
+        assert_eq!(tracking_row(&pool, "1").await.map(|row| (row.0, row.1)), Some((0, 0)));
+        let legacy: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM release_tracking")
+            .fetch_one(&pool)
+            .await
+            .expect("count legacy");
+        assert_eq!(legacy, 0);
+    }
+
+    #[tokio::test]
+    async fn enabling_creates_or_reactivates_the_row() {
+        let pool = test_pool().await;
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, status) VALUES ('1', 'jikan', 'Frieren', 'anime', 'Currently Airing')")
+            .execute(&pool)
+            .await
+            .expect("insert media");
+
+        enable_release_tracking(&pool, "1").await.expect("enable");
+        assert_eq!(tracking_row(&pool, "1").await, Some((1, 0, None)));
+
+        // Disabling keeps the baseline the checker recorded
+        sqlx::query("UPDATE release_tracking_v2 SET last_known_count = 12, next_scheduled_check = 99 WHERE media_id = '1'")
+            .execute(&pool)
+            .await
+            .expect("record baseline");
+        disable_release_tracking(&pool, "1").await.expect("disable");
+        assert_eq!(tracking_row(&pool, "1").await, Some((0, 12, Some(99))));
+
+        enable_release_tracking(&pool, "1").await.expect("enable");
+        assert_eq!(tracking_row(&pool, "1").await, Some((1, 12, None)));
+        assert!(!is_tracking_disabled(&pool, "1").await.expect("status"));
+
+        assert!(enable_release_tracking(&pool, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn opt_in_migration_disables_finished_titles_and_status_counts_enabled_rows() {
+        let temp_dir = tempfile::tempdir().expect("tempdir");
+        let db = crate::database::Database::new(temp_dir.path().join("otaku.db"))
+            .await
+            .expect("open database");
+        let pool = db.pool();
+
+        for (id, status, normalized) in [
+            ("1", "Finished Airing", "unknown"),
+            ("2", "Completed", "completed"),
+            ("3", "Currently Airing", "ongoing"),
+            ("4", "", "unknown"),
+        ] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type, status) VALUES (?, 'jikan', ?, 'anime', ?)")
+                .bind(id)
+                .bind(format!("Show {}", id))
+                .bind(status)
+                .execute(pool)
+                .await
+                .expect("insert media");
+            sqlx::query("INSERT INTO library (profile_id, media_id, status) VALUES (1, ?, 'watching')")
+                .bind(id)
+                .execute(pool)
+                .await
+                .expect("insert library");
+            sqlx::query(
+                "INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, normalized_status, last_checked_at)
+                 VALUES (?, 'jikan', 'anime', ?, 0)"
+            )
+            .bind(id)
+            .bind(normalized)
+            .execute(pool)
+            .await
+            .expect("insert tracking");
+        }
+
+        sqlx::raw_sql(include_str!("../migrations/035_release_tracking_opt_in.sql"))
+            .execute(pool)
+            .await
+            .expect("run backfill");
+
+        let enabled: Vec<String> = sqlx::query_scalar(
+            "SELECT media_id FROM release_tracking_v2 WHERE notification_enabled = 1 ORDER BY media_id"
+        )
+        .fetch_all(pool)
+        .await
+        .expect("fetch enabled");
+        assert_eq!(enabled, vec!["3", "4"]);
+
+        assert_eq!(get_release_check_status(pool).await.expect("status").items_checked, 2);
+        disable_release_tracking(pool, "3").await.expect("disable");
+        assert_eq!(get_release_check_status(pool).await.expect("status").items_checked, 1);
+    }
+
+    /// A tracked MAL-id anime (checked via Jikan, so no circuit breaker)
+    /// whose last known episode is 3. Each test uses its own id: IN_FLIGHT is
+    /// shared by every test in the process.
+    async fn tracked_media(pool: &SqlitePool, mal_id: &str) -> EligibleMedia {
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type, status) VALUES (?, 'jikan', 'Frieren', 'anime', 'Currently Airing')")
+            .bind(mal_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO release_tracking_v2
+                (media_id, extension_id, media_type, last_known_count, last_known_latest_number, user_notified_up_to, last_checked_at)
+            VALUES (?, 'jikan', 'anime', 3, 3, 3, 0)
+            "#
+        )
+        .bind(mal_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        EligibleMedia {
+            media_id: mal_id.to_string(),
+            extension_id: "jikan".to_string(),
+            title: "Frieren".to_string(),
+            media_type: "anime".to_string(),
+            last_known_count: 3,
+            last_known_latest_number: Some(3.0),
+            last_known_latest_id: None,
+            normalized_status: NormalizedStatus::Ongoing,
+            consecutive_failures: 0,
+            user_notified_up_to: Some(3.0),
+            cover_url: None,
+            auto_download: false,
+            preferred_extension_id: None,
+            preferred_media_id: None,
+        }
+    }
+
+    /// Slow enough that two passes overlap
+    async fn episode_four(_media: EligibleMedia) -> Result<EpisodeInfo> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(EpisodeInfo {
+            count: 4,
+            latest_number: Some(4.0),
+            latest_id: Some("ep-4".to_string()),
+            raw_status: Some("Currently Airing".to_string()),
+        })
+    }
+
+    #[tokio::test]
+    async fn concurrent_passes_report_a_release_once() {
+        let pool = test_pool().await;
+        let media = tracked_media(&pool, "52101").await;
+        let settings = ReleaseCheckSettings::default();
+
+        let (first, second) = tokio::join!(
+            check_media_with(&pool, &media, &settings, true, episode_four),
+            check_media_with(&pool, &media, &settings, true, episode_four),
+        );
+        let found: Vec<ReleaseCheckResult> = [first.unwrap(), second.unwrap()].into_iter().flatten().collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].current_number, Some(4.0));
+        assert!(!is_checking(&media.media_id));
+
+        let logged: Vec<String> = sqlx::query_scalar("SELECT result_type FROM release_check_log ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(logged.contains(&"skipped_in_flight".to_string()));
+        assert_eq!(logged.iter().filter(|r| *r == "new_release").count(), 1);
+
+        // A later pass still holding the pre-check snapshot doesn't report it again
+        let again = check_media_with(&pool, &media, &settings, true, episode_four).await.unwrap();
+        assert!(again.is_none());
+    }
+
+    #[tokio::test]
+    async fn background_checks_defer_when_the_budget_is_used_up() {
+        let pool = test_pool().await;
+        let settings = ReleaseCheckSettings::default();
+        let media = EligibleMedia {
+            media_id: "budget-manga".to_string(),
+            extension_id: "test.release-checker.budget".to_string(),
+            media_type: "manga".to_string(),
+            ..tracked_media(&pool, "52102").await
+        };
+        while background_budget::take(&media.extension_id).is_ok() {}
+
+        let deferred = check_media_with(&pool, &media, &settings, true, |_| async {
+            Err(anyhow::anyhow!("a deferred check must not call the extension"))
+        })
+        .await
+        .unwrap();
+        assert!(deferred.is_none());
+
+        let logged: Vec<String> = sqlx::query_scalar("SELECT result_type FROM release_check_log WHERE media_id = 'budget-manga'")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, vec!["deferred_budget"]);
+
+        // Manual checks aren't budgeted
+        let manual = check_media_with(&pool, &media, &settings, false, episode_four).await.unwrap();
+        assert_eq!(manual.unwrap().current_number, Some(4.0));
+    }
+
+    /// Episode `latest` as the given source has it
+    fn episodes_up_to(latest: i32) -> EpisodeInfo {
+        EpisodeInfo {
+            count: latest,
+            latest_number: Some(latest as f32),
+            latest_id: Some(format!("ep-{}", latest)),
+            raw_status: Some("Currently Airing".to_string()),
+        }
+    }
+
+    /// Settings asking AllAnime first
+    fn prefer_allanime(notify_when_watchable: bool) -> ReleaseCheckSettings {
+        ReleaseCheckSettings {
+            release_source: ReleaseSource::Preferred,
+            source_priority: vec![ALLANIME_EXTENSION_ID.to_string()],
+            notify_when_watchable,
+            ..ReleaseCheckSettings::default()
+        }
+    }
+
+    async fn map_to_allanime(pool: &SqlitePool, mal_id: &str, allanime_id: &str) {
+        sqlx::query("INSERT INTO id_mappings (mal_id, allanime_id, media_type, title) VALUES (?, ?, 'anime', 'Frieren')")
+            .bind(mal_id)
+            .bind(allanime_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_lagging_preferred_source_falls_back_to_the_tracking_source() {
+        let pool = test_pool().await;
+        let media = tracked_media(&pool, "52201").await;
+        map_to_allanime(&pool, "52201", "aa-frieren").await;
+
+        let asked = std::sync::Mutex::new(Vec::new());
+        let found = check_media_with(&pool, &media, &prefer_allanime(false), false, |source| {
+            asked.lock().unwrap().push(source.media_id.clone());
+            async move {
+                // AllAnime hasn't got episode 4 yet, Jikan lists it
+                Ok(episodes_up_to(if source.extension_id == ALLANIME_EXTENSION_ID { 3 } else { 4 }))
+            }
+        })
+        .await
+        .unwrap()
+        .expect("the tracking source confirms the release");
+
+        assert_eq!(found.confirmed_by, JIKAN_SOURCE_ID);
+        assert_eq!(found.current_number, Some(4.0));
+        assert_eq!(*asked.lock().unwrap(), vec!["aa-frieren", "52201"]);
+    }
+
+    #[tokio::test]
+    async fn watchable_notifications_wait_for_the_preferred_source() {
+        let pool = test_pool().await;
+        let media = tracked_media(&pool, "52202").await;
+        map_to_allanime(&pool, "52202", "aa-frieren").await;
+        let settings = prefer_allanime(true);
+
+        let allanime_has = std::sync::atomic::AtomicI32::new(3);
+        let fetch = |source: EligibleMedia| {
+            let latest = if source.extension_id == ALLANIME_EXTENSION_ID {
+                allanime_has.load(std::sync::atomic::Ordering::SeqCst)
+            } else {
+                4
+            };
+            async move { Ok(episodes_up_to(latest)) }
+        };
+
+        let early = check_media_with(&pool, &media, &settings, false, fetch).await.unwrap();
+        assert!(early.is_none(), "Jikan's episode 4 isn't watchable yet");
+
+        allanime_has.store(4, std::sync::atomic::Ordering::SeqCst);
+        let found = check_media_with(&pool, &media, &settings, false, fetch)
+            .await
+            .unwrap()
+            .expect("released once AllAnime has it");
+        assert_eq!(found.confirmed_by, ALLANIME_EXTENSION_ID);
+        assert_eq!(found.latest_episode_id.as_deref(), Some("ep-4"));
+        assert_eq!(found.extension_id, "jikan", "tracking stays with the tracking source");
+    }
+
+    #[tokio::test]
+    async fn each_source_is_compared_against_its_own_answers() {
+        let pool = test_pool().await;
+        let media = tracked_media(&pool, "52204").await;
+        map_to_allanime(&pool, "52204", "aa-frieren-s2").await;
+        // AllAnime numbers the second season on from the first's 12 episodes
+        numbering::set_numbering_offset(&pool, "52204", ALLANIME_EXTENSION_ID, 12).await.unwrap();
+        let settings = prefer_allanime(false);
+
+        let allanime_has = std::sync::atomic::AtomicI32::new(15);
+        let fetch = |source: EligibleMedia| {
+            let latest = if source.extension_id == ALLANIME_EXTENSION_ID {
+                allanime_has.load(std::sync::atomic::Ordering::SeqCst)
+            } else {
+                3
+            };
+            async move { Ok(episodes_up_to(latest)) }
+        };
+
+        // AllAnime's 15 episodes are its baseline, not 12 new ones
+        assert!(check_media_with(&pool, &media, &settings, false, fetch).await.unwrap().is_none());
+
+        allanime_has.store(16, std::sync::atomic::Ordering::SeqCst);
+        let found = check_media_with(&pool, &media, &settings, false, fetch)
+            .await
+            .unwrap()
+            .expect("AllAnime has a new episode");
+        assert_eq!(found.confirmed_by, ALLANIME_EXTENSION_ID);
+        assert_eq!((found.previous_number, found.current_number), (Some(3.0), Some(4.0)));
+        assert_eq!(found.new_releases, 1);
+
+        let row = sqlx::query(
+            "SELECT last_known_count, last_known_latest_number, user_notified_up_to FROM release_tracking_v2 WHERE media_id = '52204'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row.get::<i32, _>("last_known_count"), 3, "the tracking baseline stays Jikan's");
+        assert_eq!(row.get::<Option<f32>, _>("last_known_latest_number"), Some(3.0));
+        assert_eq!(row.get::<Option<f32>, _>("user_notified_up_to"), Some(4.0));
+
+        // Jikan still lists 3, which isn't a drop from AllAnime's 16
+        let logged: Vec<String> = sqlx::query_scalar("SELECT result_type FROM release_check_log WHERE media_id = '52204' ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, vec!["no_change", "new_release"]);
+        assert!(check_media_with(&pool, &media, &settings, false, fetch).await.unwrap().is_none());
+        let logged: Vec<String> = sqlx::query_scalar("SELECT result_type FROM release_check_log WHERE media_id = '52204' ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, vec!["no_change", "new_release", "no_change"]);
+    }
+
+    #[tokio::test]
+    async fn a_media_preferred_source_overrides_the_priority() {
+        let pool = test_pool().await;
+        let mut media = tracked_media(&pool, "52203").await;
+
+        // No mapping and no per-media source: the priority has nothing to ask
+        assert!(preferred_source(&pool, &media, &[ALLANIME_EXTENSION_ID.to_string()]).await.is_none());
+        map_to_allanime(&pool, "52203", "aa-frieren").await;
+        let preferred = preferred_source(&pool, &media, &[ALLANIME_EXTENSION_ID.to_string()]).await.unwrap();
+        assert_eq!((preferred.extension_id.as_str(), preferred.media_id.as_str()), (ALLANIME_EXTENSION_ID, "aa-frieren"));
+
+        // The tracking source heading the priority leaves nothing to prefer
+        assert!(preferred_source(&pool, &media, &[JIKAN_SOURCE_ID.to_string()]).await.is_none());
+
+        set_preferred_source(&pool, "52203", Some("com.example.source"), Some("frieren-ex")).await.unwrap();
+        assert!(set_preferred_source(&pool, "untracked", None, None).await.is_err());
+        refresh_preferred(&pool, &mut media).await;
+        let preferred = preferred_source(&pool, &media, &[ALLANIME_EXTENSION_ID.to_string()]).await.unwrap();
+        assert_eq!((preferred.extension_id.as_str(), preferred.media_id.as_str()), ("com.example.source", "frieren-ex"));
+
+        set_preferred_source(&pool, "52203", None, Some("ignored")).await.unwrap();
+        refresh_preferred(&pool, &mut media).await;
+        assert_eq!((media.preferred_extension_id, media.preferred_media_id), (None, None));
+    }
+
+    /// Re-read the per-media source the way get_eligible_media does
+    async fn refresh_preferred(pool: &SqlitePool, media: &mut EligibleMedia) {
+        let row = sqlx::query("SELECT preferred_extension_id, preferred_media_id FROM release_tracking_v2 WHERE media_id = ?")
+            .bind(&media.media_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        media.preferred_extension_id = row.get("preferred_extension_id");
+        media.preferred_media_id = row.get("preferred_media_id");
+    }
+
+    #[tokio::test]
+    async fn release_states_flag_media_being_checked() {
+        let pool = test_pool().await;
+        let media = tracked_media(&pool, "52103").await;
+
+        let guard = InFlightGuard::acquire(&media.media_id).unwrap();
+        assert!(InFlightGuard::acquire(&media.media_id).is_none());
+        let states = get_media_release_states(&pool, vec![media.media_id.clone()]).await.unwrap();
+        assert!(states[0].is_checking);
+
+        drop(guard);
+        let states = get_media_release_states(&pool, vec![media.media_id.clone()]).await.unwrap();
+        assert!(!states[0].is_checking);
+    }
+
     #[test]
     fn trim_number_integer_drops_fraction() {
         assert_eq!(trim_number(12.0), "12");
@@ -1896,6 +3466,9 @@ pub async fn get_release_tracking_debug(
     match row {
         Some(row) => {
             let logs = get_release_check_history(pool, media_id, 10).await?;
+            let split_cour_link = split_cour::get_split_cour_link(pool, media_id)
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
 
             Ok(Some(TrackingDebugInfo {
                 media_id: row.try_get("media_id")?,
@@ -1912,6 +3485,7 @@ pub async fn get_release_tracking_debug(
                 next_scheduled_check: row.try_get("next_scheduled_check")?,
                 consecutive_failures: row.try_get("consecutive_failures")?,
                 last_error: row.try_get("last_error")?,
+                split_cour_link,
                 recent_logs: logs,
             }))
         }
@@ -1958,6 +3532,11 @@ pub async fn start_release_checker(app_handle: AppHandle) {
                 }
             };
 
+            // Digest delivery runs on its own cadence, independent of the check interval
+            if let Err(e) = flush_digest_if_due(&app_handle, app_state.database.pool(), &settings).await {
+                log::error!("Failed to flush release digest: {}", e);
+            }
+
             if !settings.enabled {
                 log::debug!("Release check is disabled, sleeping");
                 tokio::time::sleep(Duration::from_secs(60)).await;
@@ -2010,7 +3589,7 @@ pub async fn get_release_check_status(pool: &SqlitePool) -> Result<ReleaseCheckS
 
     let items_count: i64 = sqlx::query_scalar(
         r#"
-        SELECT COUNT(*)
+        SELECT COUNT(DISTINCT m.id)
         FROM media m
         INNER JOIN library l ON m.id = l.media_id
         LEFT JOIN release_tracking_v2 rt ON m.id = rt.media_id
@@ -2022,6 +3601,7 @@ pub async fn get_release_check_status(pool: &SqlitePool) -> Result<ReleaseCheckS
                 l.status IN ('watching', 'reading', 'plan_to_watch', 'plan_to_read')
                 OR l.favorite = 1
             )
+            AND COALESCE(rt.notification_enabled, 1) = 1
         "#
     )
     .fetch_one(pool)
@@ -0,0 +1,444 @@
+// Safe Mode
+//
+// A corrupt database or an extension that takes the loader down used to
+// crash the app on every launch. Setup writes a marker file when it begins,
+// holding how many startups in a row began without finishing, and removes it
+// once the backend is initialized. After two failed startups the next one
+// boots into safe mode:
+// - installed extensions aren't loaded
+// - the release checker doesn't start
+// - database migrations (and the AllAnime migration) don't run
+// - a database that can't be opened is opened read-only, or replaced by an
+//   empty one in memory
+// A notification says what was skipped. Safe mode lasts until the user
+// leaves it, which removes the marker; the recovery actions (integrity
+// check, restoring a backup, clearing extensions) are commands of their own.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+use crate::backup_file;
+use crate::database::export_import::{import_data, ImportOptions, ImportResult, ImportStrategy};
+use crate::database::profiles::DEFAULT_PROFILE_ID;
+use crate::database::Database;
+use crate::notifications::{emit_notification, NotificationPayload, NotificationType};
+
+/// Marker in the app directory; holds the number of startups that began
+/// without finishing
+pub const MARKER_FILE: &str = "startup_attempts";
+
+/// Failed startups in a row after which the next one is in safe mode
+pub const FAILED_STARTUPS_FOR_SAFE_MODE: u32 = 2;
+
+const DATABASE_FILE: &str = "otaku.db";
+
+/// What safe mode leaves out, as shown to the user
+const SKIPPED: [&str; 3] = [
+    "loading installed extensions",
+    "the release checker",
+    "database migrations",
+];
+
+/// How the database was opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseMode {
+    Normal,
+    ReadOnly,
+    /// The file couldn't be opened at all; nothing is kept
+    InMemory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SafeModeState {
+    pub active: bool,
+    /// Startups in a row that didn't finish
+    pub failed_startups: u32,
+    pub skipped: Vec<String>,
+    pub database: DatabaseMode,
+    /// Why the database couldn't be opened normally
+    pub database_error: Option<String>,
+}
+
+/// Managed state: the safe mode of this run and where its marker lives
+pub struct SafeMode {
+    app_dir: PathBuf,
+    state: Mutex<SafeModeState>,
+}
+
+impl SafeMode {
+    pub fn new(app_dir: PathBuf, failed_startups: u32, database: DatabaseMode, database_error: Option<String>) -> Self {
+        let active = should_enter(failed_startups);
+        let skipped = if active {
+            SKIPPED.iter().map(|s| s.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            app_dir,
+            state: Mutex::new(SafeModeState {
+                active,
+                failed_startups,
+                skipped,
+                database,
+                database_error,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> SafeModeState {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.lock().unwrap().active
+    }
+
+    pub fn app_dir(&self) -> &Path {
+        &self.app_dir
+    }
+
+    /// Start normally next launch. This run keeps what it skipped.
+    pub fn leave(&self) -> Result<()> {
+        clear_marker(&self.app_dir)?;
+        self.state.lock().unwrap().failed_startups = 0;
+        Ok(())
+    }
+}
+
+fn marker_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(MARKER_FILE)
+}
+
+/// Startups that began without finishing. A marker that can't be read
+/// counts as one.
+fn read_marker(app_dir: &Path) -> u32 {
+    match std::fs::read_to_string(marker_path(app_dir)) {
+        Ok(contents) => contents.trim().parse().unwrap_or(1),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(_) => 1,
+    }
+}
+
+fn write_marker(app_dir: &Path, count: u32) {
+    if let Err(e) = std::fs::write(marker_path(app_dir), count.to_string()) {
+        log::warn!("Failed to write startup marker: {}", e);
+    }
+}
+
+fn clear_marker(app_dir: &Path) -> Result<()> {
+    match std::fs::remove_file(marker_path(app_dir)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context("Failed to remove startup marker"),
+    }
+}
+
+/// Whether a startup after `failed_startups` failed ones runs in safe mode
+pub fn should_enter(failed_startups: u32) -> bool {
+    failed_startups >= FAILED_STARTUPS_FOR_SAFE_MODE
+}
+
+/// Count this startup as begun, returning how many before it failed
+pub fn begin_startup(app_dir: &Path) -> u32 {
+    let failed_startups = read_marker(app_dir);
+    write_marker(app_dir, failed_startups.saturating_add(1));
+    failed_startups
+}
+
+/// The backend came up. A normal startup removes the marker; in safe mode it
+/// goes back to the failed count, so the next launch is in safe mode too
+/// until the user leaves it.
+pub fn finish_startup(app_dir: &Path, failed_startups: u32) {
+    if should_enter(failed_startups) {
+        write_marker(app_dir, failed_startups);
+    } else if let Err(e) = clear_marker(app_dir) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Whether the database answers queries at all; a file that isn't a
+/// database opens fine and fails on first use
+async fn usable(database: &Database) -> Result<()> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sqlite_master")
+        .fetch_one(database.pool())
+        .await?;
+    Ok(())
+}
+
+/// Open the database for safe mode: as it is without migrations, read-only
+/// if that fails, and in memory if the file can't be opened at all. Also
+/// returns the error that made it fall back.
+pub async fn open_database(db_path: PathBuf) -> Result<(Database, DatabaseMode, Option<String>)> {
+    let error = match Database::open_without_migrations(db_path.clone()).await {
+        Ok(database) => match usable(&database).await {
+            Ok(()) => return Ok((database, DatabaseMode::Normal, None)),
+            Err(e) => {
+                database.pool().close().await;
+                e
+            }
+        },
+        Err(e) => e,
+    };
+    log::error!("Safe mode: failed to open the database: {:#}", error);
+
+    if let Ok(database) = Database::open_read_only(db_path).await {
+        if usable(&database).await.is_ok() {
+            log::warn!("Safe mode: opened the database read-only");
+            return Ok((database, DatabaseMode::ReadOnly, Some(format!("{:#}", error))));
+        }
+        database.pool().close().await;
+    }
+
+    log::warn!("Safe mode: using an empty in-memory database");
+    let database = Database::open_in_memory().await?;
+    Ok((database, DatabaseMode::InMemory, Some(format!("{:#}", error))))
+}
+
+/// Tell the user the app started in safe mode and what it left out
+pub async fn notify(app_handle: &AppHandle, pool: &SqlitePool, state: &SafeModeState) {
+    let mut message = format!(
+        "Otaku failed to start {} times in a row, so it started in safe mode without {}.",
+        state.failed_startups,
+        state.skipped.join(", ")
+    );
+    match state.database {
+        DatabaseMode::Normal => {}
+        DatabaseMode::ReadOnly => message.push_str(" The database could not be opened normally and is read-only."),
+        DatabaseMode::InMemory => {
+            message.push_str(" The database could not be opened; nothing you change will be saved.")
+        }
+    }
+    message.push_str(" Open Settings to check the database, restore a backup or remove extensions.");
+
+    // A database that can't be written to can't store the notification
+    let pool = (state.database == DatabaseMode::Normal).then_some(pool);
+    let _ = emit_notification(
+        app_handle,
+        pool,
+        NotificationPayload::new(NotificationType::Warning, "Started in Safe Mode", message)
+            .with_source("safe_mode")
+            .with_action("Recover", Some("/settings".to_string()), None)
+            .with_native(true),
+    )
+    .await;
+}
+
+/// Remove a database file and its WAL files
+fn remove_database_files(path: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
+
+/// Move a database file and its WAL files to `to`
+fn move_database_files(from: &Path, to: &Path) -> Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        let source = PathBuf::from(format!("{}{}", from.display(), suffix));
+        if source.exists() {
+            std::fs::rename(&source, format!("{}{}", to.display(), suffix))
+                .with_context(|| format!("Failed to move {}", source.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the database with a backup: the backup is imported into a new
+/// database next to it, then the current one is closed and moved aside
+/// (otaku.db.broken-<time>) and the new one takes its place. The app has to
+/// restart afterwards.
+pub async fn restore_backup(app_dir: &Path, current: &SqlitePool, backup: &Path) -> Result<ImportResult> {
+    let data = backup_file::read_backup_file(backup)?;
+
+    let db_path = app_dir.join(DATABASE_FILE);
+    let staging = app_dir.join(format!("{}.restoring", DATABASE_FILE));
+    remove_database_files(&staging);
+
+    let restored = Database::new(staging.clone()).await?;
+    let options = ImportOptions {
+        strategy: ImportStrategy::ReplaceAll,
+        ..ImportOptions::default()
+    };
+    let result = import_data(restored.pool(), DEFAULT_PROFILE_ID, data, options, None).await;
+    restored.pool().close().await;
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            remove_database_files(&staging);
+            return Err(e);
+        }
+    };
+
+    current.close().await;
+    let aside = app_dir.join(format!(
+        "{}.broken-{}",
+        DATABASE_FILE,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    move_database_files(&db_path, &aside)?;
+    move_database_files(&staging, &db_path)?;
+    log::info!("Restored the database from {}; the old one is at {}", backup.display(), aside.display());
+
+    Ok(result)
+}
+
+/// Uninstall every extension and delete the extensions directory. Bundled
+/// extensions come back on the next normal startup.
+pub async fn clear_extensions(pool: &SqlitePool, app_dir: &Path) -> Result<u64> {
+    let removed = sqlx::query("DELETE FROM extensions")
+        .execute(pool)
+        .await
+        .context("Failed to remove installed extensions")?
+        .rows_affected();
+
+    let dir = app_dir.join("extensions");
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).context("Failed to delete the extensions directory")?;
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One launch: begin, and finish unless it crashed. Returns whether it
+    /// was in safe mode.
+    fn launch(app_dir: &Path, crashes: bool) -> bool {
+        let failed = begin_startup(app_dir);
+        if !crashes {
+            finish_startup(app_dir, failed);
+        }
+        should_enter(failed)
+    }
+
+    #[test]
+    fn two_failed_startups_in_a_row_start_safe_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path();
+
+        assert!(!launch(app_dir, false));
+        assert!(!app_dir.join(MARKER_FILE).exists());
+
+        assert!(!launch(app_dir, true));
+        assert!(!launch(app_dir, true));
+        assert_eq!(read_marker(app_dir), 2);
+        assert!(launch(app_dir, false));
+
+        // Safe mode sticks until the user leaves it, counting the safe
+        // startup itself as fine
+        assert_eq!(read_marker(app_dir), 2);
+        assert!(launch(app_dir, false));
+
+        SafeMode::new(app_dir.to_path_buf(), 2, DatabaseMode::Normal, None).leave().unwrap();
+        assert!(!app_dir.join(MARKER_FILE).exists());
+        assert!(!launch(app_dir, false));
+    }
+
+    #[test]
+    fn a_successful_startup_resets_the_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path();
+
+        assert!(!launch(app_dir, true));
+        assert!(!launch(app_dir, false));
+        assert!(!launch(app_dir, true));
+        assert!(!launch(app_dir, false));
+        assert!(!app_dir.join(MARKER_FILE).exists());
+    }
+
+    #[test]
+    fn a_crash_in_safe_mode_keeps_counting() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path();
+        std::fs::write(app_dir.join(MARKER_FILE), "2").unwrap();
+
+        assert!(launch(app_dir, true));
+        assert_eq!(read_marker(app_dir), 3);
+        assert!(launch(app_dir, false));
+        assert_eq!(read_marker(app_dir), 3);
+    }
+
+    #[test]
+    fn an_unreadable_marker_counts_as_one_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path();
+        std::fs::write(app_dir.join(MARKER_FILE), "garbage").unwrap();
+
+        assert_eq!(begin_startup(app_dir), 1);
+        assert_eq!(read_marker(app_dir), 2);
+    }
+
+    #[test]
+    fn the_state_lists_what_was_skipped_only_in_safe_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let normal = SafeMode::new(dir.path().to_path_buf(), 1, DatabaseMode::Normal, None).state();
+        assert!(!normal.active);
+        assert!(normal.skipped.is_empty());
+
+        let safe = SafeMode::new(dir.path().to_path_buf(), 2, DatabaseMode::ReadOnly, Some("corrupt".into())).state();
+        assert!(safe.active);
+        assert_eq!(safe.skipped.len(), SKIPPED.len());
+    }
+
+    #[tokio::test]
+    async fn a_healthy_database_opens_normally_without_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(DATABASE_FILE);
+        Database::new(db_path.clone()).await.unwrap().pool().close().await;
+
+        let (database, mode, error) = open_database(db_path).await.unwrap();
+        assert_eq!(mode, DatabaseMode::Normal);
+        assert!(error.is_none());
+        assert_eq!(database.integrity_check().await.unwrap(), vec!["ok".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn a_file_that_is_not_a_database_falls_back_to_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(DATABASE_FILE);
+        std::fs::write(&db_path, vec![0x5a; 8192]).unwrap();
+
+        let (database, mode, error) = open_database(db_path.clone()).await.unwrap();
+        assert_eq!(mode, DatabaseMode::InMemory);
+        assert!(error.is_some());
+
+        // The in-memory database is migrated and keeps its data
+        sqlx::query("INSERT INTO app_settings (key, value) VALUES ('safe_mode_test', '1')")
+            .execute(database.pool())
+            .await
+            .unwrap();
+        let value: String = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = 'safe_mode_test'")
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_eq!(value, "1");
+
+        // The broken file is left alone for recovery
+        assert_eq!(std::fs::read(&db_path).unwrap(), vec![0x5a; 8192]);
+    }
+
+    #[tokio::test]
+    async fn clearing_extensions_removes_rows_and_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::new(dir.path().join(DATABASE_FILE)).await.unwrap();
+        let icons = dir.path().join("extensions").join("icons");
+        std::fs::create_dir_all(&icons).unwrap();
+        std::fs::write(icons.join("ext.png"), b"png").unwrap();
+
+        clear_extensions(database.pool(), dir.path()).await.unwrap();
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM extensions")
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+        assert!(!dir.path().join("extensions").exists());
+    }
+}
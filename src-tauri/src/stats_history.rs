@@ -0,0 +1,357 @@
+// Developer Stats History
+//
+// The developer panel's stats stream only shows the current values. To see
+// trends ("memory climbed over the last hour") a background task samples the
+// same stats every few seconds, averages them per minute and keeps the
+// averages in stats_history for 48 hours. get_stats_history returns one
+// metric over a period.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+#[cfg(not(target_os = "android"))]
+use std::time::Duration;
+
+use crate::commands::SystemStats;
+
+/// How long per-minute averages are kept
+pub const RETENTION_SECS: i64 = 48 * 60 * 60;
+
+/// Seconds between background samples
+#[cfg(not(target_os = "android"))]
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+const METRIC_COUNT: usize = 7;
+
+/// A stat kept in the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    MemoryUsed,
+    MemoryPercent,
+    CpuUsage,
+    ProcessMemory,
+    ProcessCpu,
+    DiskUsed,
+    DiskPercent,
+}
+
+impl Metric {
+    pub const ALL: [Metric; METRIC_COUNT] = [
+        Metric::MemoryUsed,
+        Metric::MemoryPercent,
+        Metric::CpuUsage,
+        Metric::ProcessMemory,
+        Metric::ProcessCpu,
+        Metric::DiskUsed,
+        Metric::DiskPercent,
+    ];
+
+    /// Name as used by commands, which is also the column name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Metric::MemoryUsed => "memory_used",
+            Metric::MemoryPercent => "memory_percent",
+            Metric::CpuUsage => "cpu_usage",
+            Metric::ProcessMemory => "process_memory",
+            Metric::ProcessCpu => "process_cpu",
+            Metric::DiskUsed => "disk_used",
+            Metric::DiskPercent => "disk_percent",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Metric> {
+        Metric::ALL
+            .into_iter()
+            .find(|metric| metric.as_str() == name)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = Metric::ALL.iter().map(|m| m.as_str()).collect();
+                anyhow!("Unknown metric '{}'. Valid metrics: {}", name, valid.join(", "))
+            })
+    }
+
+    fn value(self, stats: &SystemStats) -> f64 {
+        match self {
+            Metric::MemoryUsed => stats.memory_used as f64,
+            Metric::MemoryPercent => stats.memory_percent as f64,
+            Metric::CpuUsage => stats.cpu_usage as f64,
+            Metric::ProcessMemory => stats.process_memory as f64,
+            Metric::ProcessCpu => stats.process_cpu as f64,
+            Metric::DiskUsed => stats.disk_used as f64,
+            Metric::DiskPercent => stats.disk_percent as f64,
+        }
+    }
+}
+
+/// Averages of the samples taken in one minute, in Metric::ALL order
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinuteAverage {
+    /// Unix seconds at the start of the minute
+    pub minute: i64,
+    pub samples: u32,
+    pub values: [f64; METRIC_COUNT],
+}
+
+/// Collects samples and hands back a minute's averages once a sample from a
+/// later minute arrives
+#[derive(Debug, Default)]
+pub struct Downsampler {
+    minute: Option<i64>,
+    samples: u32,
+    sums: [f64; METRIC_COUNT],
+}
+
+impl Downsampler {
+    /// Add a sample taken at `at_secs` (unix seconds). Returns the previous
+    /// minute's averages when this sample starts a new minute.
+    pub fn push(&mut self, at_secs: i64, values: [f64; METRIC_COUNT]) -> Option<MinuteAverage> {
+        let minute = at_secs - at_secs.rem_euclid(60);
+
+        let finished = match self.minute {
+            Some(current) if current != minute => self.take(),
+            _ => None,
+        };
+
+        self.minute = Some(minute);
+        self.samples += 1;
+        for (sum, value) in self.sums.iter_mut().zip(values) {
+            *sum += value;
+        }
+
+        finished
+    }
+
+    /// Averages of the minute in progress, leaving the downsampler empty
+    pub fn take(&mut self) -> Option<MinuteAverage> {
+        let minute = self.minute.take()?;
+        if self.samples == 0 {
+            return None;
+        }
+
+        let samples = std::mem::take(&mut self.samples);
+        let sums = std::mem::take(&mut self.sums);
+        Some(MinuteAverage {
+            minute,
+            samples,
+            values: sums.map(|sum| sum / samples as f64),
+        })
+    }
+}
+
+/// Values of a stats sample in Metric::ALL order
+pub fn sample_values(stats: &SystemStats) -> [f64; METRIC_COUNT] {
+    Metric::ALL.map(|metric| metric.value(stats))
+}
+
+/// Store a minute's averages and drop rows past the retention window
+pub async fn record(pool: &SqlitePool, average: &MinuteAverage) -> Result<()> {
+    let columns: Vec<&str> = Metric::ALL.iter().map(|m| m.as_str()).collect();
+    let sql = format!(
+        "INSERT OR REPLACE INTO stats_history (minute, samples, {}) VALUES (?, ?, {})",
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let mut query = sqlx::query(&sql).bind(average.minute).bind(average.samples as i64);
+    for value in average.values {
+        query = query.bind(value);
+    }
+    query.execute(pool).await?;
+
+    prune(pool, average.minute).await?;
+    Ok(())
+}
+
+/// Delete rows older than the retention window, returning how many went
+pub async fn prune(pool: &SqlitePool, now_secs: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM stats_history WHERE minute < ?")
+        .bind(now_secs - RETENTION_SECS)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Period length from strings like "30m", "1h" or "48h", capped at the retention
+pub fn parse_period(period: &str) -> Result<i64> {
+    let period = period.trim();
+    let invalid = || anyhow!("Invalid period '{}' (expected e.g. 30m, 1h or 48h)", period);
+
+    let (number, unit_secs) = if let Some(minutes) = period.strip_suffix('m') {
+        (minutes, 60)
+    } else if let Some(hours) = period.strip_suffix('h') {
+        (hours, 60 * 60)
+    } else {
+        return Err(invalid());
+    };
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+
+    let secs = number.saturating_mul(unit_secs);
+    if secs <= 0 {
+        return Err(anyhow!("Period must be positive"));
+    }
+    Ok(secs.min(RETENTION_SECS))
+}
+
+/// One point of a history series
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsPoint {
+    /// Unix timestamp (ms) at the start of the minute
+    pub at: i64,
+    pub value: f64,
+}
+
+/// A metric's per-minute averages over a period, oldest first
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsHistory {
+    pub metric: Metric,
+    pub period_secs: i64,
+    pub points: Vec<StatsPoint>,
+}
+
+async fn history_since(pool: &SqlitePool, metric: Metric, since_secs: i64) -> Result<Vec<StatsPoint>> {
+    // The column name comes from the Metric enum, never from the caller
+    let sql = format!(
+        "SELECT minute, {} AS value FROM stats_history WHERE minute >= ? ORDER BY minute",
+        metric.as_str()
+    );
+    let rows = sqlx::query(&sql).bind(since_secs).fetch_all(pool).await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| StatsPoint {
+            at: row.get::<i64, _>("minute") * 1000,
+            value: row.get("value"),
+        })
+        .collect())
+}
+
+/// A metric over the last `period` (e.g. "1h")
+pub async fn get_stats_history(pool: &SqlitePool, metric: &str, period: &str) -> Result<StatsHistory> {
+    let metric = Metric::parse(metric)?;
+    let period_secs = parse_period(period)?;
+    let now = chrono::Utc::now().timestamp();
+
+    Ok(StatsHistory {
+        metric,
+        period_secs,
+        points: history_since(pool, metric, now - period_secs).await?,
+    })
+}
+
+/// Sample stats in the background and record per-minute averages
+#[cfg(not(target_os = "android"))]
+pub fn start_history_task(pool: std::sync::Arc<SqlitePool>) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = prune(&pool, chrono::Utc::now().timestamp()).await {
+            log::warn!("Failed to prune stats history: {}", e);
+        }
+
+        let mut sys = sysinfo::System::new();
+        let mut downsampler = Downsampler::default();
+        loop {
+            let stats = crate::commands::collect_system_stats(&mut sys);
+            if let Some(average) = downsampler.push(chrono::Utc::now().timestamp(), sample_values(&stats)) {
+                if let Err(e) = record(&pool, &average).await {
+                    log::warn!("Failed to record stats history: {}", e);
+                }
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+/// sysinfo isn't available on Android, so there's nothing to record
+#[cfg(target_os = "android")]
+pub fn start_history_task(_pool: std::sync::Arc<SqlitePool>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    fn values(memory: f64, cpu: f64) -> [f64; METRIC_COUNT] {
+        let mut values = [0.0; METRIC_COUNT];
+        values[0] = memory;
+        values[2] = cpu;
+        values
+    }
+
+    #[test]
+    fn samples_are_averaged_per_minute() {
+        let mut downsampler = Downsampler::default();
+        // 12:00:00 - 12:00:50
+        let start = 1_700_000_040;
+        assert_eq!(start % 60, 0);
+
+        assert_eq!(downsampler.push(start, values(100.0, 10.0)), None);
+        assert_eq!(downsampler.push(start + 20, values(200.0, 20.0)), None);
+        assert_eq!(downsampler.push(start + 59, values(600.0, 30.0)), None);
+
+        // First sample of the next minute closes the previous one
+        let average = downsampler.push(start + 61, values(1000.0, 0.0)).unwrap();
+        assert_eq!(average.minute, start);
+        assert_eq!(average.samples, 3);
+        assert_eq!(average.values[0], 300.0);
+        assert_eq!(average.values[2], 20.0);
+
+        let average = downsampler.take().unwrap();
+        assert_eq!((average.minute, average.samples, average.values[0]), (start + 60, 1, 1000.0));
+        assert_eq!(downsampler.take(), None);
+    }
+
+    #[test]
+    fn a_gap_closes_the_minute_without_filling_in() {
+        let mut downsampler = Downsampler::default();
+        let start = 1_700_000_040;
+        downsampler.push(start + 5, values(50.0, 0.0));
+
+        // Nothing sampled for ten minutes (e.g. the machine slept)
+        let average = downsampler.push(start + 605, values(70.0, 0.0)).unwrap();
+        assert_eq!((average.minute, average.samples, average.values[0]), (start, 1, 50.0));
+        assert_eq!(downsampler.take().unwrap().minute, start + 600);
+    }
+
+    #[test]
+    fn periods_and_metrics_are_validated() {
+        assert_eq!(parse_period("30m").unwrap(), 30 * 60);
+        assert_eq!(parse_period("1h").unwrap(), 60 * 60);
+        assert_eq!(parse_period("72h").unwrap(), RETENTION_SECS);
+        assert!(parse_period("0h").is_err());
+        assert!(parse_period("1d").is_err());
+        assert!(parse_period("").is_err());
+        assert!(parse_period("1時").is_err());
+        assert!(parse_period("é").is_err());
+
+        assert_eq!(Metric::parse("process_memory").unwrap(), Metric::ProcessMemory);
+        assert!(Metric::parse("memory_used; DROP TABLE media").is_err());
+    }
+
+    #[tokio::test]
+    async fn history_keeps_48_hours() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        let now = 1_700_000_040;
+        let old = MinuteAverage { minute: now - RETENTION_SECS - 60, samples: 6, values: values(1.0, 0.0) };
+        let hour_ago = MinuteAverage { minute: now - 3600, samples: 6, values: values(2.0, 0.0) };
+        let latest = MinuteAverage { minute: now, samples: 6, values: values(3.0, 5.0) };
+
+        record(pool, &old).await.unwrap();
+        record(pool, &hour_ago).await.unwrap();
+        // Writing the latest minute prunes the one past the window
+        record(pool, &latest).await.unwrap();
+
+        let all = history_since(pool, Metric::MemoryUsed, 0).await.unwrap();
+        assert_eq!(
+            all,
+            vec![
+                StatsPoint { at: (now - 3600) * 1000, value: 2.0 },
+                StatsPoint { at: now * 1000, value: 3.0 },
+            ]
+        );
+
+        let cpu = history_since(pool, Metric::CpuUsage, now - 60).await.unwrap();
+        assert_eq!(cpu, vec![StatsPoint { at: now * 1000, value: 5.0 }]);
+    }
+}
@@ -3,8 +3,17 @@
 // Normalizes various API status strings to canonical values for consistent
 // release tracking. Different APIs return different status strings (e.g.,
 // "Airing", "Currently Airing", "Releasing", "Ongoing") that all mean the same thing.
+//
+// Statuses the built-in patterns can't classify are counted and recorded in
+// status_mappings, where the user can map them; user mappings are kept in
+// memory (normalize_status is called from sync code) and take precedence
+// over the built-in patterns.
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, RwLock};
 
 /// Canonical status values for normalized status tracking
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,7 +51,6 @@ impl NormalizedStatus {
     }
 
     /// Check if this status should be checked for new releases
-    #[allow(dead_code)]
     pub fn should_check(&self) -> bool {
         matches!(self, NormalizedStatus::Ongoing | NormalizedStatus::Unknown)
     }
@@ -64,15 +72,118 @@ impl std::fmt::Display for NormalizedStatus {
     }
 }
 
+/// Mappings set by the user, by status key
+static USER_MAPPINGS: LazyLock<RwLock<HashMap<String, NormalizedStatus>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Unclassified statuses seen since the last flush: key -> (raw status, times seen)
+static UNKNOWN_SEEN: LazyLock<Mutex<HashMap<String, (String, i64)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Built-in patterns, matched against the status key in order (more specific
+/// first: "Finished Airing" must not match "airing", "Suspended" not "ended")
+const BUILTIN_PATTERNS: &[(&str, NormalizedStatus)] = &[
+    ("finished", NormalizedStatus::Completed),
+    ("finalizado", NormalizedStatus::Completed),
+    ("termine", NormalizedStatus::Completed),
+    ("tamat", NormalizedStatus::Completed),
+    ("完結", NormalizedStatus::Completed),
+    ("on hiatus", NormalizedStatus::Hiatus),
+    // Ongoing
+    ("airing", NormalizedStatus::Ongoing),
+    ("releasing", NormalizedStatus::Ongoing),
+    ("ongoing", NormalizedStatus::Ongoing),
+    ("currently", NormalizedStatus::Ongoing),
+    ("publishing", NormalizedStatus::Ongoing),
+    ("in progress", NormalizedStatus::Ongoing),
+    ("not yet released", NormalizedStatus::Ongoing),
+    ("not yet aired", NormalizedStatus::Ongoing),
+    ("not yet published", NormalizedStatus::Ongoing),
+    ("upcoming", NormalizedStatus::Ongoing),
+    ("emision", NormalizedStatus::Ongoing),
+    ("en curso", NormalizedStatus::Ongoing),
+    ("en cours", NormalizedStatus::Ongoing),
+    ("in corso", NormalizedStatus::Ongoing),
+    ("em andamento", NormalizedStatus::Ongoing),
+    ("em lancamento", NormalizedStatus::Ongoing),
+    ("laufend", NormalizedStatus::Ongoing),
+    ("berlangsung", NormalizedStatus::Ongoing),
+    ("連載中", NormalizedStatus::Ongoing),
+    ("连载中", NormalizedStatus::Ongoing),
+    ("放送中", NormalizedStatus::Ongoing),
+    // Hiatus
+    ("hiatus", NormalizedStatus::Hiatus),
+    ("hiato", NormalizedStatus::Hiatus),
+    ("on hold", NormalizedStatus::Hiatus),
+    ("paused", NormalizedStatus::Hiatus),
+    ("pausado", NormalizedStatus::Hiatus),
+    ("pausiert", NormalizedStatus::Hiatus),
+    ("en pausa", NormalizedStatus::Hiatus),
+    ("en pause", NormalizedStatus::Hiatus),
+    ("in pausa", NormalizedStatus::Hiatus),
+    ("suspended", NormalizedStatus::Hiatus),
+    ("discontinued", NormalizedStatus::Hiatus), // Might still come back
+    ("休載", NormalizedStatus::Hiatus),
+    ("休刊", NormalizedStatus::Hiatus),
+    // Completed
+    ("completed", NormalizedStatus::Completed),
+    ("complete", NormalizedStatus::Completed),
+    ("completo", NormalizedStatus::Completed),
+    ("completado", NormalizedStatus::Completed),
+    ("completato", NormalizedStatus::Completed),
+    ("concluido", NormalizedStatus::Completed),
+    ("concluded", NormalizedStatus::Completed),
+    ("ended", NormalizedStatus::Completed),
+    ("cancelled", NormalizedStatus::Completed),
+    ("canceled", NormalizedStatus::Completed),
+    ("abgeschlossen", NormalizedStatus::Completed),
+    ("selesai", NormalizedStatus::Completed),
+];
+
+/// Lookup key for a raw status: trimmed, lowercase, accents removed and
+/// whitespace collapsed, so "En Emisión" and "en  emision" share one key
+pub fn status_key(raw: &str) -> String {
+    raw.split_whitespace()
+        .map(|word| word.chars().flat_map(char::to_lowercase).map(fold_accent).collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fold_accent(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'ç' => 'c',
+        'ñ' => 'n',
+        _ => c,
+    }
+}
+
+/// Classify a status key with the built-in patterns only
+fn builtin_status(key: &str) -> NormalizedStatus {
+    BUILTIN_PATTERNS
+        .iter()
+        .find(|(pattern, _)| key.contains(pattern))
+        .map(|(_, status)| *status)
+        .unwrap_or(NormalizedStatus::Unknown)
+}
+
 /// Normalize an API status string to a canonical status value
 ///
-/// Handles various status string formats from different APIs:
-/// - "Airing", "Currently Airing" → Ongoing
+/// A mapping set by the user for the status wins; otherwise the built-in
+/// patterns are tried (case, accent and whitespace insensitive):
+/// - "Airing", "Currently Airing", "Publishing", "En emisión" → Ongoing
 /// - "Releasing", "Ongoing", "Not yet released" → Ongoing
-/// - "Finished", "Completed", "Ended" → Completed
-/// - "Hiatus", "On Hold", "Paused" → Hiatus
+/// - "Finished", "Completed", "Ended", "Finalizado" → Completed
+/// - "Hiatus", "On Hold", "Paused", "En pausa" → Hiatus
 /// - Unknown/empty → Unknown
 ///
+/// Unknown non-empty statuses are counted so they show up in
+/// list_unknown_statuses once flushed.
+///
 /// # Examples
 /// ```ignore
 /// use status_normalizer::normalize_status;
@@ -82,45 +193,26 @@ impl std::fmt::Display for NormalizedStatus {
 /// assert_eq!(normalize_status("Finished"), NormalizedStatus::Completed);
 /// ```
 pub fn normalize_status(raw: &str) -> NormalizedStatus {
-    if raw.is_empty() {
+    let key = status_key(raw);
+    if key.is_empty() {
         return NormalizedStatus::Unknown;
     }
 
-    let lower = raw.to_lowercase();
-
-    // Check for ongoing patterns (order matters - check more specific first)
-    if lower.contains("airing")
-        || lower.contains("releasing")
-        || lower.contains("ongoing")
-        || lower.contains("currently")
-        || lower.contains("not yet released")
-        || lower.contains("not yet aired")
-        || lower.contains("upcoming")
-    {
-        return NormalizedStatus::Ongoing;
-    }
-
-    // Check for completed patterns
-    if lower.contains("finished")
-        || lower.contains("completed")
-        || lower.contains("ended")
-        || lower.contains("concluded")
-    {
-        return NormalizedStatus::Completed;
+    let user_mapping = USER_MAPPINGS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+        .copied();
+    if let Some(status) = user_mapping {
+        return status;
     }
 
-    // Check for hiatus patterns
-    if lower.contains("hiatus")
-        || lower.contains("on hold")
-        || lower.contains("paused")
-        || lower.contains("suspended")
-        || lower.contains("discontinued") // Might still come back
-    {
-        return NormalizedStatus::Hiatus;
+    let status = builtin_status(&key);
+    if status == NormalizedStatus::Unknown {
+        let mut seen = UNKNOWN_SEEN.lock().unwrap_or_else(|e| e.into_inner());
+        seen.entry(key).or_insert_with(|| (raw.trim().to_string(), 0)).1 += 1;
     }
-
-    // Default to Unknown for unrecognized status strings
-    NormalizedStatus::Unknown
+    status
 }
 
 /// Status mapping entry for explicit mappings
@@ -131,30 +223,171 @@ pub struct StatusMapping {
     pub normalized: NormalizedStatus,
 }
 
-/// Get all known status mappings for reference/debugging
+/// Get all built-in status patterns for reference/debugging
 #[allow(dead_code)]
 pub fn get_status_mappings() -> Vec<StatusMapping> {
-    vec![
-        // Ongoing patterns
-        StatusMapping { pattern: "Airing", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Currently Airing", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Releasing", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Ongoing", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Not Yet Released", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Not Yet Aired", normalized: NormalizedStatus::Ongoing },
-        StatusMapping { pattern: "Upcoming", normalized: NormalizedStatus::Ongoing },
-        // Completed patterns
-        StatusMapping { pattern: "Finished", normalized: NormalizedStatus::Completed },
-        StatusMapping { pattern: "Completed", normalized: NormalizedStatus::Completed },
-        StatusMapping { pattern: "Ended", normalized: NormalizedStatus::Completed },
-        StatusMapping { pattern: "Concluded", normalized: NormalizedStatus::Completed },
-        // Hiatus patterns
-        StatusMapping { pattern: "Hiatus", normalized: NormalizedStatus::Hiatus },
-        StatusMapping { pattern: "On Hold", normalized: NormalizedStatus::Hiatus },
-        StatusMapping { pattern: "Paused", normalized: NormalizedStatus::Hiatus },
-        StatusMapping { pattern: "Suspended", normalized: NormalizedStatus::Hiatus },
-        StatusMapping { pattern: "Discontinued", normalized: NormalizedStatus::Hiatus },
-    ]
+    BUILTIN_PATTERNS
+        .iter()
+        .map(|(pattern, normalized)| StatusMapping { pattern, normalized: *normalized })
+        .collect()
+}
+
+/// A raw status recorded in status_mappings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LearnedStatus {
+    pub status_key: String,
+    pub raw_status: String,
+    /// None while the user hasn't mapped it
+    pub normalized_status: Option<NormalizedStatus>,
+    pub seen_count: i64,
+    pub last_seen_at: Option<String>,
+}
+
+/// Load the user's mappings into memory. Called at startup.
+pub async fn load_status_mappings(pool: &SqlitePool) -> Result<usize> {
+    let rows = sqlx::query(
+        "SELECT status_key, normalized_status FROM status_mappings WHERE normalized_status IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mappings: HashMap<String, NormalizedStatus> = rows
+        .iter()
+        .map(|row| {
+            let status: String = row.get("normalized_status");
+            (row.get("status_key"), NormalizedStatus::from_str(&status))
+        })
+        .collect();
+    let count = mappings.len();
+    *USER_MAPPINGS.write().unwrap_or_else(|e| e.into_inner()) = mappings;
+
+    Ok(count)
+}
+
+/// Write the unknown statuses counted since the last flush to the database
+pub async fn flush_unknown_statuses(pool: &SqlitePool) -> Result<usize> {
+    let seen: Vec<(String, (String, i64))> = UNKNOWN_SEEN
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain()
+        .collect();
+    if seen.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+    for (key, (raw, count)) in &seen {
+        sqlx::query(
+            r#"
+            INSERT INTO status_mappings (status_key, raw_status, seen_count, last_seen_at)
+            VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(status_key) DO UPDATE SET
+                seen_count = status_mappings.seen_count + excluded.seen_count,
+                last_seen_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(key)
+        .bind(raw)
+        .bind(count)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(seen.len())
+}
+
+/// Recorded statuses, most seen first. Only unmapped ones unless
+/// `include_mapped` is set.
+pub async fn list_unknown_statuses(pool: &SqlitePool, include_mapped: bool) -> Result<Vec<LearnedStatus>> {
+    flush_unknown_statuses(pool).await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT status_key, raw_status, normalized_status, seen_count, last_seen_at
+        FROM status_mappings
+        WHERE ? OR normalized_status IS NULL
+        ORDER BY seen_count DESC, status_key
+        "#,
+    )
+    .bind(include_mapped)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| LearnedStatus {
+            status_key: row.get("status_key"),
+            raw_status: row.get("raw_status"),
+            normalized_status: row
+                .get::<Option<String>, _>("normalized_status")
+                .map(|s| NormalizedStatus::from_str(&s)),
+            seen_count: row.get("seen_count"),
+            last_seen_at: row.get("last_seen_at"),
+        })
+        .collect())
+}
+
+/// Map a raw status (None or Unknown removes the user's mapping, falling back
+/// to the built-in patterns) and re-normalize tracked media with that status.
+/// Returns how many tracked media changed status.
+pub async fn set_status_mapping(pool: &SqlitePool, raw: &str, status: Option<NormalizedStatus>) -> Result<u64> {
+    let key = status_key(raw);
+    if key.is_empty() {
+        anyhow::bail!("Status can't be empty");
+    }
+    let status = status.filter(|s| *s != NormalizedStatus::Unknown);
+
+    sqlx::query(
+        r#"
+        INSERT INTO status_mappings (status_key, raw_status, normalized_status, updated_at)
+        VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(status_key) DO UPDATE SET
+            normalized_status = excluded.normalized_status,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(&key)
+    .bind(raw.trim())
+    .bind(status.map(|s| s.as_str()))
+    .execute(pool)
+    .await?;
+
+    {
+        let mut mappings = USER_MAPPINGS.write().unwrap_or_else(|e| e.into_inner());
+        match status {
+            Some(status) => mappings.insert(key.clone(), status),
+            None => mappings.remove(&key),
+        };
+    }
+
+    // Tracked media keep the raw status, so re-normalizing picks the new
+    // mapping up right away instead of at their next check
+    let rows = sqlx::query(
+        "SELECT media_id, raw_status, normalized_status FROM release_tracking_v2 WHERE raw_status IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut changed = 0;
+    for row in rows {
+        let raw_status: String = row.get("raw_status");
+        if status_key(&raw_status) != key {
+            continue;
+        }
+        let normalized = normalize_status(&raw_status);
+        if row.get::<Option<String>, _>("normalized_status").as_deref() != Some(normalized.as_str()) {
+            sqlx::query("UPDATE release_tracking_v2 SET normalized_status = ?, updated_at = CURRENT_TIMESTAMP WHERE media_id = ?")
+                .bind(normalized.as_str())
+                .bind(row.get::<String, _>("media_id"))
+                .execute(pool)
+                .await?;
+            changed += 1;
+        }
+    }
+
+    log::info!("Status \"{}\" mapped to {:?} ({} tracked media updated)", raw.trim(), status, changed);
+    Ok(changed)
 }
 
 #[cfg(test)]
@@ -177,6 +410,7 @@ mod tests {
         assert_eq!(normalize_status("Completed"), NormalizedStatus::Completed);
         assert_eq!(normalize_status("Ended"), NormalizedStatus::Completed);
         assert_eq!(normalize_status("FINISHED"), NormalizedStatus::Completed);
+        assert_eq!(normalize_status("Finished Airing"), NormalizedStatus::Completed);
     }
 
     #[test]
@@ -200,4 +434,82 @@ mod tests {
         assert!(!NormalizedStatus::Completed.should_check());
         assert!(!NormalizedStatus::Hiatus.should_check());
     }
+
+    #[test]
+    fn test_normalize_localized_variants() {
+        assert_eq!(normalize_status("En emisión"), NormalizedStatus::Ongoing);
+        assert_eq!(normalize_status("EN  CURSO"), NormalizedStatus::Ongoing);
+        assert_eq!(normalize_status("Publishing"), NormalizedStatus::Ongoing);
+        assert_eq!(normalize_status("Finalizado"), NormalizedStatus::Completed);
+        assert_eq!(normalize_status("Terminé"), NormalizedStatus::Completed);
+        assert_eq!(normalize_status("連載中"), NormalizedStatus::Ongoing);
+        assert_eq!(normalize_status("Season finished"), NormalizedStatus::Completed);
+        assert_eq!(normalize_status("Suspended"), NormalizedStatus::Hiatus);
+        assert_eq!(normalize_status("Currently on hiatus"), NormalizedStatus::Hiatus);
+    }
+
+    #[test]
+    fn user_mappings_take_precedence_over_builtins() {
+        // Built-in patterns read this as ongoing ("airing")
+        let raw = "Airing (final cour done)";
+        assert_eq!(normalize_status(raw), NormalizedStatus::Ongoing);
+
+        USER_MAPPINGS
+            .write()
+            .unwrap()
+            .insert(status_key(raw), NormalizedStatus::Completed);
+        assert_eq!(normalize_status(raw), NormalizedStatus::Completed);
+        assert_eq!(normalize_status("  AIRING (Final Cour Done) "), NormalizedStatus::Completed);
+
+        USER_MAPPINGS.write().unwrap().remove(&status_key(raw));
+        assert_eq!(normalize_status(raw), NormalizedStatus::Ongoing);
+    }
+
+    #[tokio::test]
+    async fn unknown_statuses_are_recorded_and_can_be_mapped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = crate::database::Database::new(temp_dir.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES ('m1', 'ext', 'M1', 'anime')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO release_tracking_v2 (media_id, extension_id, media_type, last_known_count, raw_status, normalized_status, last_checked_at) \
+             VALUES ('m1', 'ext', 'anime', 3, 'Saison en attente', 'unknown', 0)",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        assert_eq!(normalize_status("Saison en attente"), NormalizedStatus::Unknown);
+        assert_eq!(normalize_status("saison EN attente"), NormalizedStatus::Unknown);
+
+        let unknown = list_unknown_statuses(pool, false).await.unwrap();
+        let learned = unknown.iter().find(|s| s.status_key == "saison en attente").unwrap();
+        assert_eq!(learned.raw_status, "Saison en attente");
+        assert_eq!(learned.seen_count, 2);
+        assert_eq!(learned.normalized_status, None);
+
+        // Mapping it applies to tracked media right away and takes it off the list
+        assert_eq!(set_status_mapping(pool, "Saison en attente", Some(NormalizedStatus::Hiatus)).await.unwrap(), 1);
+        assert_eq!(normalize_status("Saison en attente"), NormalizedStatus::Hiatus);
+        let status: String = sqlx::query_scalar("SELECT normalized_status FROM release_tracking_v2 WHERE media_id = 'm1'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(status, "hiatus");
+        assert!(!list_unknown_statuses(pool, false).await.unwrap().iter().any(|s| s.status_key == "saison en attente"));
+        assert!(list_unknown_statuses(pool, true).await.unwrap().iter().any(|s| s.status_key == "saison en attente"));
+
+        // Reloading from the database restores it
+        USER_MAPPINGS.write().unwrap().remove("saison en attente");
+        load_status_mappings(pool).await.unwrap();
+        assert_eq!(normalize_status("Saison en attente"), NormalizedStatus::Hiatus);
+
+        // Clearing falls back to the built-in patterns
+        set_status_mapping(pool, "Saison en attente", None).await.unwrap();
+        assert_eq!(normalize_status("Saison en attente"), NormalizedStatus::Unknown);
+    }
 }
@@ -0,0 +1,293 @@
+// Storage Usage Module
+//
+// Computes how much disk space the app uses, split by category, by walking
+// the app data, downloads and log directories. Walks run on a blocking thread
+// and the result is cached briefly so reopening settings doesn't rescan.
+// A storage-usage-changed event is emitted when a category moves noticeably.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::downloads::trash::TRASH_DIR;
+use crate::events::STORAGE_USAGE_CHANGED_EVENT;
+
+/// How long a computed breakdown is reused before walking again
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Minimum change in any category (bytes) that triggers a change event
+const CHANGE_THRESHOLD_BYTES: u64 = 1024 * 1024;
+
+/// How often the background monitor recomputes usage
+const MONITOR_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Subfolder of the downloads directory holding chapter images
+const CHAPTER_DOWNLOADS_DIR: &str = "Manga";
+
+/// Last computed breakdown and when it was taken
+static CACHE: LazyLock<Mutex<Option<(Instant, StorageUsage)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// Directories that make up the app's storage footprint
+#[derive(Debug, Clone)]
+pub struct StoragePaths {
+    pub app_dir: PathBuf,
+    pub downloads_dir: PathBuf,
+    pub log_dir: Option<PathBuf>,
+}
+
+/// Disk usage per category, in bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StorageUsage {
+    /// SQLite database including WAL/SHM files
+    pub database_size: u64,
+    /// Episode downloads (everything in the downloads directory except chapters and trash)
+    pub downloads_size: u64,
+    pub chapter_downloads_size: u64,
+    pub covers_size: u64,
+    pub thumbnails_size: u64,
+    /// Manga reader pages cached on disk
+    pub page_cache_size: u64,
+    pub trash_size: u64,
+    pub logs_size: u64,
+    pub backups_size: u64,
+    /// Anything else in the app data directory
+    pub other_size: u64,
+    pub total_size: u64,
+    /// Covers, thumbnails and cached pages together: what the media cache cap
+    /// (media_cache_max_mb) applies to. Not a separate category.
+    pub media_cache_size: u64,
+    /// Unix timestamp in ms when the walk finished
+    pub computed_at: i64,
+}
+
+impl StorageUsage {
+    fn categories(&self) -> [u64; 10] {
+        [
+            self.database_size,
+            self.downloads_size,
+            self.chapter_downloads_size,
+            self.covers_size,
+            self.thumbnails_size,
+            self.page_cache_size,
+            self.trash_size,
+            self.logs_size,
+            self.backups_size,
+            self.other_size,
+        ]
+    }
+
+    /// True when any category differs from `other` by more than `threshold` bytes
+    fn differs_from(&self, other: &StorageUsage, threshold: u64) -> bool {
+        self.categories()
+            .iter()
+            .zip(other.categories().iter())
+            .any(|(a, b)| a.abs_diff(*b) > threshold)
+    }
+}
+
+/// Total size of all files under `path`. Symlinks are not followed and
+/// unreadable entries are skipped.
+fn dir_size(path: &Path) -> u64 {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(_) => return 0,
+    };
+
+    if metadata.is_file() {
+        return metadata.len();
+    }
+    if !metadata.is_dir() {
+        return 0;
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| dir_size(&entry.path()))
+        .sum()
+}
+
+/// Walk every storage location and split the result into categories
+fn compute_usage(paths: &StoragePaths) -> StorageUsage {
+    let mut usage = StorageUsage::default();
+
+    // Downloads may live outside the app directory, so they're walked on their own
+    let chapters_dir = paths.downloads_dir.join(CHAPTER_DOWNLOADS_DIR);
+    usage.chapter_downloads_size = dir_size(&chapters_dir);
+    usage.trash_size = dir_size(&paths.downloads_dir.join(TRASH_DIR));
+    usage.downloads_size = dir_size(&paths.downloads_dir)
+        .saturating_sub(usage.chapter_downloads_size)
+        .saturating_sub(usage.trash_size);
+
+    if let Some(log_dir) = &paths.log_dir {
+        if !log_dir.starts_with(&paths.app_dir) {
+            usage.logs_size = dir_size(log_dir);
+        }
+    }
+
+    if let Ok(entries) = std::fs::read_dir(&paths.app_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path == paths.downloads_dir {
+                continue;
+            }
+
+            let size = dir_size(&path);
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if paths.log_dir.as_deref() == Some(path.as_path()) {
+                usage.logs_size += size;
+                continue;
+            }
+
+            match name.as_str() {
+                n if n.starts_with("otaku.db") => usage.database_size += size,
+                "covers" => usage.covers_size += size,
+                "thumbnails" => usage.thumbnails_size += size,
+                "page-cache" => usage.page_cache_size += size,
+                "trash" => usage.trash_size += size,
+                "logs" => usage.logs_size += size,
+                "backups" => usage.backups_size += size,
+                _ => usage.other_size += size,
+            }
+        }
+    }
+
+    usage.total_size = usage.categories().iter().sum();
+    usage.media_cache_size = usage.covers_size + usage.thumbnails_size + usage.page_cache_size;
+    usage.computed_at = chrono::Utc::now().timestamp_millis();
+    usage
+}
+
+/// Get the storage breakdown, reusing the cached walk for up to a minute
+/// unless `force_refresh` is set. Emits storage-usage-changed when a fresh
+/// walk differs noticeably from the previous one.
+pub async fn get_storage_usage(
+    app_handle: &AppHandle,
+    paths: StoragePaths,
+    force_refresh: bool,
+) -> Result<StorageUsage> {
+    let previous = CACHE.lock().unwrap().clone();
+
+    if let Some((taken_at, usage)) = &previous {
+        if !force_refresh && taken_at.elapsed() < CACHE_TTL {
+            return Ok(usage.clone());
+        }
+    }
+
+    let usage = tokio::task::spawn_blocking(move || compute_usage(&paths)).await?;
+
+    *CACHE.lock().unwrap() = Some((Instant::now(), usage.clone()));
+
+    let changed = previous
+        .map(|(_, old)| usage.differs_from(&old, CHANGE_THRESHOLD_BYTES))
+        .unwrap_or(false);
+    if changed {
+        STORAGE_USAGE_CHANGED_EVENT.emit(app_handle, &usage);
+    }
+
+    Ok(usage)
+}
+
+/// Periodically recompute usage so open settings pages receive change events
+pub fn start_storage_monitor(app_handle: AppHandle, paths: StoragePaths) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(MONITOR_INTERVAL).await;
+            if let Err(e) = get_storage_usage(&app_handle, paths.clone(), true).await {
+                log::warn!("Failed to refresh storage usage: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(path: &Path, len: usize) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; len]).unwrap();
+    }
+
+    #[test]
+    fn compute_usage_splits_categories() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app_dir = temp_dir.path().to_path_buf();
+        let downloads_dir = app_dir.join("downloads");
+
+        write_file(&app_dir.join("otaku.db"), 4000);
+        write_file(&app_dir.join("otaku.db-wal"), 1000);
+        write_file(&downloads_dir.join("Show/Season 1/Episode_1.otaku"), 3000);
+        write_file(&downloads_dir.join("Manga/Title_Ch1/001.jpg"), 700);
+        write_file(&downloads_dir.join("Manga/Title_Ch1/002.jpg"), 300);
+        write_file(&app_dir.join("covers/1.jpg"), 50);
+        write_file(&app_dir.join("thumbnails/1.jpg"), 60);
+        write_file(&app_dir.join("page-cache/chapter/001.jpg"), 20);
+        write_file(&app_dir.join("trash/old.otaku"), 70);
+        write_file(&downloads_dir.join(".trash/Episode_2.otaku"), 40);
+        write_file(&app_dir.join("logs/otaku.log"), 80);
+        write_file(&app_dir.join("backups/backup.json"), 90);
+        write_file(&app_dir.join("settings.json"), 10);
+
+        let usage = compute_usage(&StoragePaths {
+            app_dir: app_dir.clone(),
+            downloads_dir,
+            log_dir: Some(app_dir.join("logs")),
+        });
+
+        assert_eq!(usage.database_size, 5000);
+        assert_eq!(usage.downloads_size, 3000);
+        assert_eq!(usage.chapter_downloads_size, 1000);
+        assert_eq!(usage.covers_size, 50);
+        assert_eq!(usage.thumbnails_size, 60);
+        assert_eq!(usage.page_cache_size, 20);
+        assert_eq!(usage.media_cache_size, 130);
+        assert_eq!(usage.trash_size, 110);
+        assert_eq!(usage.logs_size, 80);
+        assert_eq!(usage.backups_size, 90);
+        assert_eq!(usage.other_size, 10);
+        assert_eq!(usage.total_size, 9420);
+    }
+
+    #[test]
+    fn compute_usage_walks_external_downloads_and_logs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app_dir = temp_dir.path().join("app");
+        let downloads_dir = temp_dir.path().join("media");
+        let log_dir = temp_dir.path().join("logs");
+
+        write_file(&app_dir.join("otaku.db"), 100);
+        write_file(&downloads_dir.join("Episode_1.otaku"), 2000);
+        write_file(&log_dir.join("otaku.log"), 30);
+
+        let usage = compute_usage(&StoragePaths {
+            app_dir,
+            downloads_dir,
+            log_dir: Some(log_dir),
+        });
+
+        assert_eq!(usage.database_size, 100);
+        assert_eq!(usage.downloads_size, 2000);
+        assert_eq!(usage.chapter_downloads_size, 0);
+        assert_eq!(usage.logs_size, 30);
+        assert_eq!(usage.total_size, 2130);
+    }
+
+    #[test]
+    fn differs_from_respects_threshold() {
+        let base = StorageUsage { downloads_size: 10_000, ..Default::default() };
+        let small = StorageUsage { downloads_size: 10_500, ..Default::default() };
+        let large = StorageUsage { downloads_size: 20_000, ..Default::default() };
+
+        assert!(!small.differs_from(&base, 1000));
+        assert!(large.differs_from(&base, 1000));
+    }
+}
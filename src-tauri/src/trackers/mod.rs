@@ -6,5 +6,7 @@
 // - Progress syncing
 // - Library import/export
 
+pub mod sync_queue;
+
 // Submodules (to be created in Phase 3, Week 9)
 // pub mod anilist;
@@ -0,0 +1,81 @@
+// Tracker Sync Queue
+//
+// Completed episodes of media mapped to a connected tracker are queued here
+// until the tracker client pushes them. One row per tracker and media holds
+// the furthest episode watched, so rewatching an older episode never moves
+// the tracker backwards.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Queue an episode's completion for every connected tracker the media is
+/// mapped to. Returns how many trackers it was queued for.
+pub async fn queue_progress(pool: &SqlitePool, media_id: &str, episode_number: i32) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO tracker_sync_queue (tracker_name, media_id, tracker_media_id, episode_number)
+        SELECT m.tracker_name, m.media_id, m.tracker_media_id, ?
+        FROM tracker_mappings m
+        JOIN tracker_accounts a ON a.tracker_name = m.tracker_name
+        WHERE m.media_id = ?
+        ON CONFLICT(tracker_name, media_id) DO UPDATE SET
+            tracker_media_id = excluded.tracker_media_id,
+            episode_number = MAX(tracker_sync_queue.episode_number, excluded.episode_number),
+            queued_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(episode_number)
+    .bind(media_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    async fn queued(pool: &SqlitePool, media_id: &str) -> Option<i32> {
+        sqlx::query_scalar("SELECT episode_number FROM tracker_sync_queue WHERE media_id = ?")
+            .bind(media_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn queues_furthest_episode_for_connected_trackers_only() {
+        let temp = tempfile::tempdir().unwrap();
+        let db = Database::new(temp.path().join("otaku.db")).await.unwrap();
+        let pool = db.pool();
+
+        for id in ["mapped", "unmapped"] {
+            sqlx::query("INSERT INTO media (id, extension_id, title, media_type) VALUES (?, 'ext', 'Show', 'anime')")
+                .bind(id)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO tracker_mappings (media_id, tracker_name, tracker_media_id) VALUES ('mapped', 'anilist', '42')")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        // No account yet: nothing to push to
+        assert_eq!(queue_progress(pool, "mapped", 3).await.unwrap(), 0);
+
+        sqlx::query("INSERT INTO tracker_accounts (tracker_name, user_id, username, access_token) VALUES ('anilist', '1', 'me', 'token')")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        assert_eq!(queue_progress(pool, "mapped", 5).await.unwrap(), 1);
+        queue_progress(pool, "mapped", 2).await.unwrap();
+        assert_eq!(queued(pool, "mapped").await, Some(5));
+
+        assert_eq!(queue_progress(pool, "unmapped", 1).await.unwrap(), 0);
+        assert_eq!(queued(pool, "unmapped").await, None);
+    }
+}
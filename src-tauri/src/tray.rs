@@ -8,12 +8,14 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     Mutex,
 };
-use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri::{AppHandle, Manager, Wry};
 use tauri::{
     menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
 };
 
+use crate::events::DEEPLINK_EVENT;
+
 /// Process-wide state that the tray, window-close handler, and notification
 /// escalation all read or write.
 #[derive(Default)]
@@ -97,9 +99,7 @@ pub fn restore_and_navigate(app: &AppHandle) {
         .and_then(|s| s.pending_deeplink.lock().ok().and_then(|mut g| g.take()));
 
     if let Some(route) = route_opt {
-        if let Err(e) = app.emit("deeplink", &route) {
-            log::error!("Failed to emit deeplink event: {}", e);
-        }
+        DEEPLINK_EVENT.emit(app, &route);
     }
 }
 
@@ -7,22 +7,29 @@
 // - Proper HTTP Range request handling for seeking (via tower-http ServeDir)
 // - True streaming without buffering entire file in memory
 // - Proxies remote video URLs with streaming
+// - Remuxes MKV downloads to fragmented MP4 on the fly (requires ffmpeg)
 // - Access token authentication for security
+// - Retries port binding with backoff; a fixed port can be configured for
+//   users who firewall per port
 
 use axum::{
     body::Body,
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{header, Request, StatusCode},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use sqlx::SqlitePool;
 use std::{
     net::SocketAddr,
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpListener;
 use tower::ServiceExt;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -30,23 +37,42 @@ use tower_http::{
 };
 
 use crate::downloads::obfuscation;
+use crate::http_retry::{send_with_retry, RetryPolicy};
+use crate::media::remux;
+use crate::notifications::{self, NotificationPayload, NotificationType};
+use crate::VideoServerInfo;
+
+/// app_settings key: fixed port for the video server (unset = pick one)
+pub const VIDEO_SERVER_PORT_SETTING: &str = "video_server_port";
+
+/// Error returned by commands that need the video server while it isn't running
+pub const VIDEO_SERVER_UNAVAILABLE_ERROR: &str =
+    "Video server unavailable: streaming and local playback won't work until it starts. Retry from Settings or restart Otaku.";
+
+/// Ports tried before giving up (a fixed port is retried this many times)
+const BIND_ATTEMPTS: u32 = 5;
+
+/// Wait before the second attempt, doubled for every attempt after
+const BIND_BACKOFF: Duration = Duration::from_millis(250);
 
 #[derive(Clone)]
 pub struct VideoServerState {
     pub access_token: String,
     pub downloads_dir: PathBuf,
+    /// Cached extension icons (see extensions::icons)
+    pub icons_dir: Option<PathBuf>,
+    pub db_pool: Option<Arc<SqlitePool>>,
 }
 
 pub struct VideoServer {
-    port: u16,
     access_token: String,
     downloads_dir: PathBuf,
+    icons_dir: Option<PathBuf>,
+    db_pool: Option<Arc<SqlitePool>>,
 }
 
 impl VideoServer {
     pub fn new(downloads_dir: PathBuf) -> Self {
-        // Generate random port between 10000-60000
-        let port = 10000 + (rand::random::<u16>() % 50000);
         // Generate random access token
         let access_token: String = (0..32)
             .map(|_| {
@@ -60,62 +86,243 @@ impl VideoServer {
             .collect();
 
         Self {
-            port,
             access_token,
             downloads_dir,
+            icons_dir: None,
+            db_pool: None,
         }
     }
 
-    pub fn port(&self) -> u16 {
-        self.port
+    /// Serve cached extension icons from this directory at /extension-icons
+    pub fn with_icons_dir(mut self, icons_dir: PathBuf) -> Self {
+        self.icons_dir = Some(icons_dir);
+        self
+    }
+
+    /// Set the database pool (needed to resolve download ids for /remux)
+    pub fn with_database(mut self, pool: Arc<SqlitePool>) -> Self {
+        self.db_pool = Some(pool);
+        self
     }
 
     pub fn access_token(&self) -> &str {
         &self.access_token
     }
 
-    pub async fn start(self) -> anyhow::Result<()> {
+    /// Serve on a listener from bind_with_retry
+    pub async fn start(self, listener: TcpListener) -> anyhow::Result<()> {
         let state = Arc::new(VideoServerState {
             access_token: self.access_token.clone(),
             downloads_dir: self.downloads_dir.clone(),
+            icons_dir: self.icons_dir.clone(),
+            db_pool: self.db_pool.clone(),
         });
 
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any);
-
-        // Use tower-http's ServeDir for local files - it handles Range requests automatically
-        let serve_dir = ServeDir::new(&self.downloads_dir)
-            .precompressed_gzip()
-            .precompressed_br();
-
-        let app = Router::new()
-            // Local file serving with automatic Range support
-            .nest_service("/files", serve_dir)
-            // Serve files from absolute paths (for custom download locations)
-            .route("/absolute", get(serve_absolute_path))
-            // Legacy local endpoint (redirects to /files)
-            .route("/local/*path", get(serve_local_redirect))
-            // Remote video proxy
-            .route("/proxy", get(proxy_video))
-            // HLS manifest rewriter (rewrites segment URLs to go through /proxy)
-            .route("/hls", get(proxy_hls_manifest))
-            // Add token validation middleware
-            .layer(middleware::from_fn_with_state(state.clone(), validate_token))
-            .layer(cors)
-            .with_state(state);
-
-        let addr = SocketAddr::from(([127, 0, 0, 1], self.port));
-        log::debug!("Video server starting on port {}", self.port);
-
-        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let app = build_router(state);
+
+        log::debug!("Video server starting on {:?}", listener.local_addr());
         axum::serve(listener, app).await?;
 
         Ok(())
     }
 }
 
+/// Ports to try, in order: the fixed port every time, otherwise a small run
+/// of consecutive ports from a random base between 10000-60000
+fn candidate_ports(fixed_port: Option<u16>, random_base: u16) -> Vec<u16> {
+    match fixed_port {
+        Some(port) => vec![port; BIND_ATTEMPTS as usize],
+        None => {
+            let base = 10000 + (random_base % 50000);
+            (0..BIND_ATTEMPTS as u16).map(|offset| base + offset).collect()
+        }
+    }
+}
+
+/// Bind 127.0.0.1, retrying with backoff (another program may hold the port
+/// or release it a moment later)
+pub async fn bind_with_retry(fixed_port: Option<u16>) -> anyhow::Result<TcpListener> {
+    let mut last_error = None;
+
+    for (attempt, port) in candidate_ports(fixed_port, rand::random::<u16>()).into_iter().enumerate() {
+        if attempt > 0 {
+            tokio::time::sleep(BIND_BACKOFF * 2u32.pow(attempt as u32 - 1)).await;
+        }
+
+        match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => {
+                log::warn!("Video server couldn't bind port {} (attempt {}): {}", port, attempt + 1, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "no free port after {} attempts: {}",
+        BIND_ATTEMPTS,
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
+/// Read the fixed port setting. Unset, unparsable or privileged ports mean
+/// "pick one".
+pub async fn configured_port(pool: &SqlitePool) -> Option<u16> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(VIDEO_SERVER_PORT_SETTING)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+
+    value
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .filter(|port| *port >= 1024)
+}
+
+/// Managed state: connection info once the server is listening
+pub struct VideoServerHandle {
+    info: RwLock<Option<VideoServerInfo>>,
+    /// Serializes start attempts (startup vs retry_video_server)
+    starting: tokio::sync::Mutex<()>,
+    downloads_dir: PathBuf,
+    icons_dir: PathBuf,
+    db_pool: Arc<SqlitePool>,
+}
+
+impl VideoServerHandle {
+    pub fn new(downloads_dir: PathBuf, icons_dir: PathBuf, db_pool: Arc<SqlitePool>) -> Self {
+        Self {
+            info: RwLock::new(None),
+            starting: tokio::sync::Mutex::new(()),
+            downloads_dir,
+            icons_dir,
+            db_pool,
+        }
+    }
+
+    /// Connection info, or VIDEO_SERVER_UNAVAILABLE_ERROR when the server
+    /// isn't listening (never hand out URLs to a dead port)
+    pub fn info(&self) -> Result<VideoServerInfo, String> {
+        self.info
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| VIDEO_SERVER_UNAVAILABLE_ERROR.to_string())
+    }
+
+    /// `info` once a launch in progress has finished
+    pub async fn settled_info(&self) -> Result<VideoServerInfo, String> {
+        let _starting = self.starting.lock().await;
+        self.info()
+    }
+
+    fn set_info(&self, info: Option<VideoServerInfo>) {
+        *self.info.write().unwrap() = info;
+    }
+}
+
+/// Start the video server unless it's already running, and publish its
+/// connection info. When no port can be bound the user gets a notification
+/// and commands report VIDEO_SERVER_UNAVAILABLE_ERROR until a retry succeeds.
+pub async fn launch(app: &AppHandle) -> Result<VideoServerInfo, String> {
+    let handle = app.state::<VideoServerHandle>();
+    let _starting = handle.starting.lock().await;
+
+    if let Ok(info) = handle.info() {
+        return Ok(info);
+    }
+
+    let fixed_port = configured_port(&handle.db_pool).await;
+    let listener = match bind_with_retry(fixed_port).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Video server failed to start: {}", e);
+            notify_unavailable(app, &handle.db_pool, fixed_port, &e.to_string()).await;
+            return Err(VIDEO_SERVER_UNAVAILABLE_ERROR.to_string());
+        }
+    };
+
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let server = VideoServer::new(handle.downloads_dir.clone())
+        .with_icons_dir(handle.icons_dir.clone())
+        .with_database(handle.db_pool.clone());
+    let info = VideoServerInfo {
+        port,
+        access_token: server.access_token().to_string(),
+    };
+    handle.set_info(Some(info.clone()));
+    log::info!("Video server listening on port {}", port);
+
+    let app = app.clone();
+    tokio::spawn(async move {
+        if let Err(e) = server.start(listener).await {
+            log::error!("Video server error: {}", e);
+        }
+        // Stopped serving: stop handing out its URLs
+        app.state::<VideoServerHandle>().set_info(None);
+    });
+
+    Ok(info)
+}
+
+async fn notify_unavailable(app: &AppHandle, pool: &SqlitePool, fixed_port: Option<u16>, error: &str) {
+    let message = match fixed_port {
+        Some(port) => format!(
+            "Couldn't open port {} for video playback ({}). Free the port or change it in Settings, then retry.",
+            port, error
+        ),
+        None => format!("Couldn't open a local port for video playback ({}). Retry from Settings.", error),
+    };
+
+    let notification = NotificationPayload::new(NotificationType::Error, "Video playback unavailable", message)
+        .with_source("video_server")
+        .with_action("Open Settings", Some("/settings".to_string()), None)
+        .with_metadata(serde_json::json!({ "port": fixed_port }));
+
+    if let Err(e) = notifications::emit_notification(app, Some(pool), notification).await {
+        log::warn!("Failed to send video server notification: {}", e);
+    }
+}
+
+fn build_router(state: Arc<VideoServerState>) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    // Use tower-http's ServeDir for local files - it handles Range requests automatically
+    let serve_dir = ServeDir::new(&state.downloads_dir)
+        .precompressed_gzip()
+        .precompressed_br();
+
+    let mut router = Router::new()
+        // Local file serving with automatic Range support
+        .nest_service("/files", serve_dir);
+
+    // Cached extension icons, kept apart from downloads so neither route can
+    // reach the other's files
+    if let Some(icons_dir) = &state.icons_dir {
+        router = router.nest_service("/extension-icons", ServeDir::new(icons_dir));
+    }
+
+    router
+        // Serve files from absolute paths (for custom download locations)
+        .route("/absolute", get(serve_absolute_path))
+        // Legacy local endpoint (redirects to /files)
+        .route("/local/*path", get(serve_local_redirect))
+        // MKV downloads remuxed to fragmented MP4 through ffmpeg
+        .route("/remux/:download_id", get(serve_remuxed_download))
+        // Remote video proxy
+        .route("/proxy", get(proxy_video))
+        // HLS manifest rewriter (rewrites segment URLs to go through /proxy)
+        .route("/hls", get(proxy_hls_manifest))
+        // Add token validation middleware
+        .layer(middleware::from_fn_with_state(state.clone(), validate_token))
+        .layer(cors)
+        .with_state(state)
+}
+
 #[derive(serde::Deserialize)]
 struct TokenQuery {
     token: Option<String>,
@@ -151,7 +358,7 @@ async fn validate_token(
 // Redirect /local/* to /files/* for backwards compatibility
 async fn serve_local_redirect(
     State(state): State<Arc<VideoServerState>>,
-    axum::extract::Path(path): axum::extract::Path<String>,
+    Path(path): Path<String>,
     Query(query): Query<TokenQuery>,
     request: Request<Body>,
 ) -> Response {
@@ -376,6 +583,65 @@ fn parse_range_header(range: &str, file_size: u64) -> Option<(u64, u64)> {
     Some((start, end))
 }
 
+// Stream an MKV download as fragmented MP4 by piping it through `ffmpeg -c copy`.
+// Nothing is cached; seeking restarts the stream, so Range requests are ignored.
+async fn serve_remuxed_download(
+    State(state): State<Arc<VideoServerState>>,
+    Path(download_id): Path<String>,
+) -> Response {
+    if !remux::ffmpeg_available() {
+        return (StatusCode::NOT_IMPLEMENTED, remux::FFMPEG_MISSING_ERROR).into_response();
+    }
+
+    let pool = match &state.db_pool {
+        Some(pool) => pool,
+        None => return (StatusCode::SERVICE_UNAVAILABLE, "Database not available").into_response(),
+    };
+
+    let file_path: Option<String> = match sqlx::query_scalar(
+        "SELECT file_path FROM downloads WHERE id = ?"
+    )
+    .bind(&download_id)
+    .fetch_optional(pool.as_ref())
+    .await
+    {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Failed to look up download {}: {}", download_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to look up download").into_response();
+        }
+    };
+
+    let file_path = match file_path {
+        Some(p) => PathBuf::from(p),
+        None => return (StatusCode::NOT_FOUND, "Download not found").into_response(),
+    };
+
+    if !file_path.exists() {
+        log::error!("File not found: {:?}", file_path);
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    }
+
+    log::debug!("Remuxing download {} to MP4", download_id);
+
+    let stream = match remux::remux_to_mp4_stream(&file_path) {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("Failed to start ffmpeg for {}: {}", download_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start remux: {}", e)).into_response();
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "video/mp4")
+        .header(header::ACCEPT_RANGES, "none")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
 #[derive(serde::Deserialize)]
 struct ProxyQuery {
     #[allow(dead_code)]
@@ -417,7 +683,7 @@ async fn proxy_video(
     }
 
     // Make request
-    let response = match remote_request.send().await {
+    let response = match send_with_retry(RetryPolicy::INTERACTIVE, remote_request).await {
         Ok(r) => r,
         Err(e) => {
             log::error!("Proxy request failed: {}", e);
@@ -510,14 +776,12 @@ async fn proxy_hls_manifest(
         .build()
         .unwrap_or_else(|_| reqwest::Client::new());
 
-    let response = match client
+    let manifest_request = client
         .get(&url)
         .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:121.0) Gecko/20100101 Firefox/121.0")
         .header("Referer", "https://allmanga.to")
-        .header("Origin", "https://allmanga.to")
-        .send()
-        .await
-    {
+        .header("Origin", "https://allmanga.to");
+    let response = match send_with_retry(RetryPolicy::INTERACTIVE, manifest_request).await {
         Ok(r) => r,
         Err(e) => {
             log::error!("HLS manifest fetch failed: {}", e);
@@ -603,3 +867,226 @@ fn rewrite_uri_attribute(line: &str, base_url: &str, token: &str) -> String {
     }
     line.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const TOKEN: &str = "test-token";
+
+    async fn setup_state(downloads_dir: PathBuf) -> Arc<VideoServerState> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        sqlx::query("CREATE TABLE downloads (id TEXT PRIMARY KEY, file_path TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        Arc::new(VideoServerState {
+            access_token: TOKEN.to_string(),
+            icons_dir: Some(downloads_dir.with_file_name("icons")),
+            downloads_dir,
+            db_pool: Some(Arc::new(pool)),
+        })
+    }
+
+    /// Generate a one-second MKV with ffmpeg's test source
+    fn make_test_mkv(path: &std::path::Path) -> bool {
+        let ffmpeg = match remux::ffmpeg_path() {
+            Some(f) => f,
+            None => return false,
+        };
+
+        std::process::Command::new(ffmpeg)
+            .args([
+                "-hide_banner", "-loglevel", "error", "-y",
+                "-f", "lavfi", "-i", "testsrc=duration=1:size=64x64:rate=10",
+                "-c:v", "mpeg4",
+            ])
+            .arg(path)
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
+    async fn get(state: Arc<VideoServerState>, uri: &str) -> Response {
+        build_router(state)
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_remux_streams_mkv_as_mp4() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mkv_path = temp_dir.path().join("Episode_1.mkv");
+        if !make_test_mkv(&mkv_path) {
+            eprintln!("ffmpeg not available, skipping remux integration test");
+            return;
+        }
+
+        let state = setup_state(temp_dir.path().to_path_buf()).await;
+        sqlx::query("INSERT INTO downloads (id, file_path) VALUES ('dl-1', ?)")
+            .bind(mkv_path.to_string_lossy().to_string())
+            .execute(state.db_pool.as_ref().unwrap().as_ref())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            remux::detect_container(&mkv_path).await.unwrap(),
+            remux::Container::Matroska
+        );
+
+        let response = get(state, &format!("/remux/dl-1?token={}", TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "video/mp4");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() > 8);
+        assert_eq!(&body[4..8], b"ftyp");
+    }
+
+    #[tokio::test]
+    async fn test_remux_decrypts_obfuscated_mkv() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mkv_path = temp_dir.path().join("source.mkv");
+        if !make_test_mkv(&mkv_path) {
+            eprintln!("ffmpeg not available, skipping remux integration test");
+            return;
+        }
+
+        let mut data = std::fs::read(&mkv_path).unwrap();
+        obfuscation::xor_transform(&mut data, 0);
+        let otaku_path = temp_dir.path().join("Episode_1.otaku");
+        std::fs::write(&otaku_path, &data).unwrap();
+
+        let state = setup_state(temp_dir.path().to_path_buf()).await;
+        sqlx::query("INSERT INTO downloads (id, file_path) VALUES ('dl-1', ?)")
+            .bind(otaku_path.to_string_lossy().to_string())
+            .execute(state.db_pool.as_ref().unwrap().as_ref())
+            .await
+            .unwrap();
+
+        let response = get(state, &format!("/remux/dl-1?token={}", TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[4..8], b"ftyp");
+    }
+
+    #[tokio::test]
+    async fn test_remux_unknown_download() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = setup_state(temp_dir.path().to_path_buf()).await;
+
+        let response = get(state, &format!("/remux/missing?token={}", TOKEN)).await;
+        if remux::ffmpeg_available() {
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        } else {
+            assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_files_serves_nested_paths_without_traversal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let downloads_dir = temp_dir.path().join("downloads");
+        let season_dir = downloads_dir.join("Frieren").join("Season 1");
+        std::fs::create_dir_all(&season_dir).unwrap();
+        std::fs::write(season_dir.join("Episode_1.mp4"), b"video").unwrap();
+        std::fs::write(temp_dir.path().join("secret.txt"), b"secret").unwrap();
+
+        let state = setup_state(downloads_dir).await;
+
+        let response = get(
+            state.clone(),
+            &format!("/files/Frieren/Season%201/Episode_1.mp4?token={}", TOKEN),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for uri in ["/files/..%2Fsecret.txt", "/files/Frieren/%2E%2E/%2E%2E/secret.txt"] {
+            let response = get(state.clone(), &format!("{}?token={}", uri, TOKEN)).await;
+            assert_ne!(response.status(), StatusCode::OK, "{} escaped the downloads dir", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extension_icons_are_served_from_their_own_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let downloads_dir = temp_dir.path().join("downloads");
+        let icons_dir = temp_dir.path().join("icons");
+        std::fs::create_dir_all(&downloads_dir).unwrap();
+        std::fs::create_dir_all(&icons_dir).unwrap();
+        std::fs::write(icons_dir.join("com.example.source.png"), b"png").unwrap();
+        std::fs::write(downloads_dir.join("Episode_1.mp4"), b"video").unwrap();
+
+        let state = setup_state(downloads_dir).await;
+
+        let response = get(state.clone(), &format!("/extension-icons/com.example.source.png?token={}", TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = get(state.clone(), "/extension-icons/com.example.source.png").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        for uri in ["/extension-icons/..%2Fdownloads%2FEpisode_1.mp4", "/files/..%2Ficons%2Fcom.example.source.png"] {
+            let response = get(state.clone(), &format!("{}?token={}", uri, TOKEN)).await;
+            assert_ne!(response.status(), StatusCode::OK, "{} escaped its directory", uri);
+        }
+    }
+
+    #[test]
+    fn test_candidate_ports() {
+        assert_eq!(candidate_ports(Some(48000), 7), vec![48000; BIND_ATTEMPTS as usize]);
+
+        let ports = candidate_ports(None, u16::MAX);
+        assert_eq!(ports.len(), BIND_ATTEMPTS as usize);
+        assert!(ports.iter().all(|port| (10000..60000 + BIND_ATTEMPTS as u16).contains(port)));
+        assert!(ports.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    }
+
+    #[tokio::test]
+    async fn test_fixed_port_is_retried_until_released() {
+        let holder = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = holder.local_addr().unwrap().port();
+
+        // Released between the second and third attempt
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(400));
+            drop(holder);
+        });
+
+        let listener = bind_with_retry(Some(port)).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap().port(), port);
+    }
+
+    #[tokio::test]
+    async fn test_configured_port_setting() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(configured_port(&pool).await, None);
+
+        for (value, expected) in [("48000", Some(48000)), (" 50123 ", Some(50123)), ("80", None), ("70000", None), ("auto", None)] {
+            sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+                .bind(VIDEO_SERVER_PORT_SETTING)
+                .bind(value)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert_eq!(configured_port(&pool).await, expected, "setting {:?}", value);
+        }
+    }
+}
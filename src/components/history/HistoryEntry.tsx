@@ -49,7 +49,10 @@ function relativeTimestamp(iso: string): string {
 
 export function HistoryEntry({ entry, onRemoved }: HistoryEntryProps) {
   const navigate = useNavigate()
-  const { src: coverSrc } = useProxiedImage(entry.media.cover_url || '')
+  const { src: coverSrc } = useProxiedImage(entry.media.cover_url || '', false, {
+    cache: 'covers',
+    mediaId: entry.media.id,
+  })
 
   const isAnime = entry.type === 'watch'
   const isCompleted = entry.completed
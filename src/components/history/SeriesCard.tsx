@@ -46,7 +46,10 @@ function relativeTime(iso: string): string {
 
 export function SeriesCard({ summary, onEntryRemoved }: SeriesCardProps) {
   const navigate = useNavigate()
-  const { src: coverSrc } = useProxiedImage(summary.media.cover_url || '')
+  const { src: coverSrc } = useProxiedImage(summary.media.cover_url || '', false, {
+    cache: 'covers',
+    mediaId: summary.media.id,
+  })
   const [expanded, setExpanded] = useState(false)
   const [entries, setEntries] = useState<HistoryEntryType[]>([])
   const [loadingEntries, setLoadingEntries] = useState(false)
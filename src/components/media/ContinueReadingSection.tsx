@@ -23,7 +23,7 @@ interface SelectedManga {
 }
 
 function ContinueReadingCardImage({ url, title }: { url?: string; title: string }) {
-  const { src } = useProxiedImage(url || '')
+  const { src } = useProxiedImage(url || '', false, { cache: 'covers' })
 
   if (!src) {
     return (
@@ -135,7 +135,7 @@ export function MangaDetailModal({ manga, extensionId = '', onClose }: MangaDeta
   const [resolvedMangaId, setResolvedMangaId] = useState<string | null>(manga?.id || null)
   const [chaptersLoading, setChaptersLoading] = useState(false)
   const coverImageUrl = details?.cover_url || manga?.cover_url || ''
-  const { src: bannerSrc } = useProxiedImage(coverImageUrl)
+  const { src: bannerSrc } = useProxiedImage(coverImageUrl, false, { cache: 'covers' })
 
   // Backward compatibility: old manga entries may still use numeric MAL-era IDs.
   // Resolve those to a Mangakakalot slug using the stored title.
@@ -651,7 +651,7 @@ export function MangaDetailModal({ manga, extensionId = '', onClose }: MangaDeta
     if (!details || !manga) return
 
     try {
-      const chapterImages = await getChapterImages(effectiveExtId, chapter.id)
+      const chapterImages = await getChapterImages(effectiveExtId, chapter.id, !nsfwFilter)
       if (!chapterImages.images || chapterImages.images.length === 0) {
         throw new Error('No images found for this chapter')
       }
@@ -704,7 +704,7 @@ export function MangaDetailModal({ manga, extensionId = '', onClose }: MangaDeta
     if (!details || !manga) return false
 
     try {
-      const chapterImages = await getChapterImages(effectiveExtId, chapter.id)
+      const chapterImages = await getChapterImages(effectiveExtId, chapter.id, !nsfwFilter)
       if (!chapterImages.images || chapterImages.images.length === 0) {
         throw new Error('No images found')
       }
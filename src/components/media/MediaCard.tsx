@@ -56,7 +56,10 @@ interface MediaCardProps {
 }
 
 export function MediaCard({ media, onClick, progress, status, rank }: MediaCardProps) {
-  const { src: coverSrc } = useProxiedImage(media.cover_url || '')
+  const { src: coverSrc } = useProxiedImage(media.cover_url || '', false, {
+    cache: 'covers',
+    mediaId: media.id,
+  })
 
   // Use unified release state hook (V2) for NEW badge
   // Only fetch release state if user is tracking/watching this media
@@ -40,6 +40,7 @@ import {
   jikanSearchAnime,
   loadExtension,
   resolveAllanimeId,
+  getNumberingOffset,
   isInLibrary,
   addToLibrary,
   removeFromLibrary,
@@ -47,6 +48,9 @@ import {
   saveEpisodes,
   getCachedMediaDetails,
   startDownload,
+  sealDownloadBatch,
+  sourceHeaders,
+  sourceSubtitles,
   isEpisodeDownloaded,
   getVideoSources,
   deleteEpisodeDownload,
@@ -82,12 +86,14 @@ import {
   type JikanNews,
   jikanAnimeEpisodeDetail,
   type JikanEpisodeDetail,
+  isAlreadyDownloaded,
 } from '@/utils/tauri-commands'
 import { ALLANIME_EXTENSION } from '@/extensions/allanime-extension'
 import { savePendingReturn } from '@/utils/return-media'
 import { TagSelector, TagChips } from '@/components/library'
 import { useKeyboardShortcut } from '@/hooks/useKeyboardShortcut'
 import { useDownloadEvents } from '@/hooks/useDownloadEvents'
+import { useProxiedImage } from '@/hooks/useProxiedImage'
 import { useMediaStatusContext } from '@/contexts/MediaStatusContext'
 import { useSettingsStore } from '@/store/settingsStore'
 // MediaCard still available for future use
@@ -158,6 +164,19 @@ function isAiring(status?: string): boolean {
   return s === 'releasing' || s === 'ongoing' || s === 'airing' || s === 'currently airing'
 }
 
+/** Episode thumbnail, kept in the thumbnails cache (the original URL if the proxy fails) */
+function EpisodeThumbnail({ url, alt }: { url: string; alt: string }) {
+  const { src, error } = useProxiedImage(url, false, { cache: 'thumbnails' })
+  return (
+    <img
+      src={error ? url : (src ?? undefined)}
+      alt={alt}
+      className="w-full h-full object-cover"
+      loading="lazy"
+    />
+  )
+}
+
 const EPISODES_PER_PAGE = 50
 
 interface MediaDetailModalProps {
@@ -179,6 +198,7 @@ export function MediaDetailModal({
 }: MediaDetailModalProps) {
   const navigate = useNavigate()
   const { getStatus, refresh: refreshMediaStatus } = useMediaStatusContext()
+  const nsfwFilter = useSettingsStore((state) => state.nsfwFilter)
   const customDownloadLocation = useSettingsStore((state) => state.downloadLocation)
   const [details, setDetails] = useState<MediaDetails | null>(null)
   const [loading, setLoading] = useState(true)
@@ -290,6 +310,15 @@ export function MediaDetailModal({
       })
   }, [details, allanimeShowId, media.id])
 
+  // AllAnime may number episodes differently from MAL (e.g. across seasons)
+  const [numberingOffset, setNumberingOffset] = useState(0)
+  useEffect(() => {
+    if (!allanimeShowId || !allanimeExtId) return
+    getNumberingOffset(media.id, allanimeExtId)
+      .then(setNumberingOffset)
+      .catch(() => setNumberingOffset(0))
+  }, [allanimeShowId, allanimeExtId, media.id])
+
   // Tag state
   const [mediaTags, setMediaTags] = useState<LibraryTag[]>([])
   const [showTagSelector, setShowTagSelector] = useState(false)
@@ -449,9 +478,9 @@ export function MediaDetailModal({
 
     try {
       // Build AllAnime-format episode ID: {allanimeShowId}::{episodeNumber}
-      const allanimeEpisodeId = `${allanimeShowId}::${episodeNumber}`
+      const allanimeEpisodeId = `${allanimeShowId}::${episodeNumber + numberingOffset}`
       // Get video sources
-      const videoSources = await getVideoSources(allanimeExtId, allanimeEpisodeId)
+      const videoSources = await getVideoSources(allanimeExtId, allanimeEpisodeId, !nsfwFilter)
       if (!videoSources || !videoSources.sources || videoSources.sources.length === 0) {
         notifyError('Download Failed', `No video sources found for Episode ${episodeNumber}`)
         return
@@ -475,13 +504,26 @@ export function MediaDetailModal({
         episodeNumber,
         videoUrl,
         filename,
-        customDownloadLocation || undefined
+        customDownloadLocation || undefined,
+        source.quality,
+        source.server,
+        undefined,
+        undefined,
+        undefined,
+        details.title,
+        sourceHeaders(source),
+        sourceSubtitles(source, videoSources),
+        videoSources.sources
       )
       notifySuccess(media.title, `Started downloading Episode ${episodeNumber}`, {
         source: 'download',
         metadata: { media_id: media.id },
       })
     } catch (error) {
+      if (isAlreadyDownloaded(error)) {
+        notifyInfo(media.title, `Episode ${episodeNumber} is already downloaded`)
+        return
+      }
       console.error(`Failed to download episode ${episodeNumber}:`, error)
       notifyError('Download Failed', `Failed to download Episode ${episodeNumber}`)
     }
@@ -499,6 +541,8 @@ export function MediaDetailModal({
       let successCount = 0
       let failCount = 0
       let skippedCount = 0
+      // One summary notification once every queued episode has finished
+      const batchId = `batch-${Date.now()}-${Math.random().toString(36).slice(2, 9)}`
 
       for (const episode of details.episodes) {
         try {
@@ -510,9 +554,9 @@ export function MediaDetailModal({
           }
 
           // Build AllAnime-format episode ID: {allanimeShowId}::{episodeNumber}
-          const allanimeEpisodeId = `${allanimeShowId}::${episode.number}`
+          const allanimeEpisodeId = `${allanimeShowId}::${episode.number + numberingOffset}`
           // Get video sources
-          const sources = await getVideoSources(allanimeExtId, allanimeEpisodeId)
+          const sources = await getVideoSources(allanimeExtId, allanimeEpisodeId, !nsfwFilter)
           if (!sources.sources || sources.sources.length === 0) {
             console.error(`No sources found for episode ${episode.number}`)
             failCount++
@@ -520,7 +564,8 @@ export function MediaDetailModal({
           }
 
           // Pick the best quality source (first one is usually highest quality)
-          const videoUrl = sources.sources[0].url
+          const source = sources.sources[0]
+          const videoUrl = source.url
 
           // Generate filename
           const filename = `${details.title.replace(/[^a-z0-9]/gi, '_')}_EP${episode.number}.otaku`
@@ -532,7 +577,16 @@ export function MediaDetailModal({
             episode.number,
             videoUrl,
             filename,
-            customDownloadLocation || undefined
+            customDownloadLocation || undefined,
+            source.quality,
+            source.server,
+            batchId,
+            undefined,
+            undefined,
+            details.title,
+            sourceHeaders(source),
+            sourceSubtitles(source, sources),
+            sources.sources
           )
           successCount++
         } catch (err) {
@@ -540,6 +594,7 @@ export function MediaDetailModal({
           failCount++
         }
       }
+      await sealDownloadBatch(batchId)
 
       if (successCount > 0) {
         notifySuccess(
@@ -590,6 +645,8 @@ export function MediaDetailModal({
       let successCount = 0
       let failCount = 0
       let skippedCount = 0
+      // One summary notification once every queued episode has finished
+      const batchId = `batch-${Date.now()}-${Math.random().toString(36).slice(2, 9)}`
 
       for (const episode of selectedEpisodesList) {
         try {
@@ -601,9 +658,9 @@ export function MediaDetailModal({
           }
 
           // Build AllAnime-format episode ID: {allanimeShowId}::{episodeNumber}
-          const allanimeEpisodeId = `${allanimeShowId}::${episode.number}`
+          const allanimeEpisodeId = `${allanimeShowId}::${episode.number + numberingOffset}`
           // Get video sources
-          const sources = await getVideoSources(allanimeExtId, allanimeEpisodeId)
+          const sources = await getVideoSources(allanimeExtId, allanimeEpisodeId, !nsfwFilter)
           if (!sources.sources || sources.sources.length === 0) {
             console.error(`No sources found for episode ${episode.number}`)
             failCount++
@@ -611,7 +668,8 @@ export function MediaDetailModal({
           }
 
           // Pick the best quality source
-          const videoUrl = sources.sources[0].url
+          const source = sources.sources[0]
+          const videoUrl = source.url
 
           // Generate filename
           const filename = `${details.title.replace(/[^a-z0-9]/gi, '_')}_EP${episode.number}.otaku`
@@ -623,7 +681,16 @@ export function MediaDetailModal({
             episode.number,
             videoUrl,
             filename,
-            customDownloadLocation || undefined
+            customDownloadLocation || undefined,
+            source.quality,
+            source.server,
+            batchId,
+            undefined,
+            undefined,
+            details.title,
+            sourceHeaders(source),
+            sourceSubtitles(source, sources),
+            sources.sources
           )
           successCount++
         } catch (err) {
@@ -631,6 +698,7 @@ export function MediaDetailModal({
           failCount++
         }
       }
+      await sealDownloadBatch(batchId)
 
       if (successCount > 0) {
         notifySuccess(
@@ -1002,7 +1070,7 @@ export function MediaDetailModal({
             created_at: new Date().toISOString(),
             updated_at: new Date().toISOString(),
           }
-          await saveMediaDetails(mediaEntry)
+          await saveMediaDetails(mediaEntry, result.age_rating)
 
           if (result.episodes.length > 0) {
             const episodeEntries: EpisodeEntry[] = result.episodes.map((ep) => ({
@@ -2111,11 +2179,9 @@ export function MediaDetailModal({
                               {/* Thumbnail or placeholder */}
                               <div className="relative aspect-video">
                                 {episode.thumbnail || details.cover_url ? (
-                                  <img
-                                    src={(episode.thumbnail || details.cover_url)!}
+                                  <EpisodeThumbnail
+                                    url={(episode.thumbnail || details.cover_url)!}
                                     alt={episode.title || `Episode ${episode.number}`}
-                                    className="w-full h-full object-cover"
-                                    loading="lazy"
                                   />
                                 ) : (
                                   <div className="w-full h-full flex flex-col items-center justify-center gap-2 bg-gradient-to-br from-[var(--color-surface)] to-[var(--color-card)]">
@@ -10,6 +10,7 @@ import { useState, useEffect, useCallback, useMemo } from 'react'
 import { useNavigate } from '@tanstack/react-router'
 import { Bell, X, CheckCheck, Trash2, RefreshCw, Clock, ChevronDown } from 'lucide-react'
 import { useNotificationStore, type Notification } from '@/store/notificationStore'
+import { runNotificationCallback } from '@/utils/notify'
 import {
   markNotificationRead as markReadBackend,
   markAllNotificationsRead as markAllReadBackend,
@@ -200,6 +201,7 @@ export function NotificationPageContent({ onNavigateAway }: NotificationPageCont
 
         navigate({ to: route })
       }
+      runNotificationCallback(notification)
       markAsRead(notification.id)
       onNavigateAway?.()
     },
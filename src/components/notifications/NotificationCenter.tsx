@@ -3,6 +3,7 @@ import { createPortal } from 'react-dom'
 import { Bell, CheckCheck, X, RefreshCw, Settings, Clock } from 'lucide-react'
 import { useNavigate } from '@tanstack/react-router'
 import { useNotificationStore, type Notification } from '@/store/notificationStore'
+import { runNotificationCallback } from '@/utils/notify'
 import {
   markNotificationRead as markReadBackend,
   markAllNotificationsRead as markAllReadBackend,
@@ -200,6 +201,7 @@ export function NotificationCenter() {
 
         navigate({ to: route })
       }
+      runNotificationCallback(notification)
       markAsRead(notification.id)
     },
     [navigate, markAsRead]
@@ -7,7 +7,7 @@
 import { useEffect, useState } from 'react'
 import { Download, Check, X, Loader2, Star } from 'lucide-react'
 import type { VideoSource } from '@/types/extension'
-import { startDownload } from '@/utils/tauri-commands'
+import { sourceHeaders, sourceSubtitles, startDownload } from '@/utils/tauri-commands'
 import { useSettingsStore } from '@/store/settingsStore'
 import { isAdaptive, qualityLabel, parseQualityPreference } from '@/utils/pickSource'
 import { listAdaptiveVariants, resolveAdaptiveToVariant } from '@/utils/hlsResolve'
@@ -123,7 +123,23 @@ export function DownloadButton({
 
       const safeTitle = animeTitle.replace(/[^a-zA-Z0-9]/g, '_')
       const filename = `${safeTitle}_EP${episodeNumber}_${resolvedLabel}.otaku`
-      await startDownload(mediaId, episodeId, episodeNumber, downloadUrl, filename, customDownloadLocation || undefined)
+      await startDownload(
+        mediaId,
+        episodeId,
+        episodeNumber,
+        downloadUrl,
+        filename,
+        customDownloadLocation || undefined,
+        resolvedLabel,
+        source.server,
+        undefined,
+        undefined,
+        undefined,
+        animeTitle,
+        sourceHeaders(source),
+        sourceSubtitles(source),
+        sources.filter((s) => s !== source && !isAdaptive(s))
+      )
 
       setCompleted(true)
       setTimeout(() => {
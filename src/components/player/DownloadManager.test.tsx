@@ -39,6 +39,7 @@ vi.mock('@/utils/tauri-commands', () => ({
   cancelDownload: vi.fn(),
   pauseDownload: vi.fn(),
   resumeDownload: vi.fn(),
+  startDownloadNow: vi.fn(),
   deleteDownload: mockDeleteDownload,
   getTotalStorageUsed: mockGetTotalStorageUsed,
   clearCompletedDownloads: vi.fn(),
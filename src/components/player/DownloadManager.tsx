@@ -13,7 +13,7 @@ import { useNavigate } from '@tanstack/react-router'
 import { X, Download, Trash2, CheckCircle, Loader2, Folder, BookOpen, Tv, Pause, Play, ChevronDown } from 'lucide-react'
 import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import { ask } from '@tauri-apps/plugin-dialog'
-import { listDownloads, cancelDownload, pauseDownload, resumeDownload, deleteDownload, getTotalStorageUsed, clearCompletedDownloads, clearFailedDownloads, openDownloadsFolder, listAllChapterDownloads, cancelChapterDownload, deleteChapterDownload, clearCompletedChapterDownloads, clearFailedChapterDownloads, getCachedMediaDetails, type DownloadProgress, type ChapterDownloadWithTitle, type ChapterDownloadProgressEvent } from '@/utils/tauri-commands'
+import { listDownloads, cancelDownload, pauseDownload, resumeDownload, redownloadEpisode, startDownloadNow, deleteDownload, getTotalStorageUsed, clearCompletedDownloads, clearFailedDownloads, openDownloadsFolder, listAllChapterDownloads, cancelChapterDownload, deleteChapterDownload, clearCompletedChapterDownloads, clearFailedChapterDownloads, getCachedMediaDetails, type DownloadProgress, type ChapterDownloadWithTitle, type ChapterDownloadProgressEvent } from '@/utils/tauri-commands'
 import { notifySuccess, notifyError } from '@/utils/notify'
 import { useSettingsStore } from '@/store/settingsStore'
 import { isMobile } from '@/utils/platform'
@@ -236,6 +236,29 @@ export function DownloadPageContent({ onNavigateAway }: DownloadPageContentProps
     }
   }
 
+  const handleRedownload = async (downloadId: string) => {
+    const download = downloads.find(d => d.id === downloadId)
+    const displayName = download ? `Episode ${download.episode_number}` : 'Download'
+    try {
+      await redownloadEpisode(downloadId)
+      notifySuccess('Downloading Again', `Downloading ${displayName} again`, mediaMeta(download?.media_id))
+    } catch (error) {
+      console.error('Failed to download again:', error)
+      notifyError('Re-download Failed', `Failed to download ${displayName} again`, mediaMeta(download?.media_id))
+    }
+  }
+
+  const handleStartNow = async (downloadId: string) => {
+    const download = downloads.find(d => d.id === downloadId)
+    const displayName = download ? `Episode ${download.episode_number}` : 'Download'
+    try {
+      await startDownloadNow(downloadId)
+    } catch (error) {
+      console.error('Failed to start download:', error)
+      notifyError('Start Failed', `Failed to start ${displayName}`, mediaMeta(download?.media_id))
+    }
+  }
+
   const handleDelete = async (downloadId: string, filename: string) => {
     const download = downloads.find(d => d.id === downloadId)
     // Extract a cleaner display name from filename (e.g., "Anime_Name_EP1.mp4" -> "Anime Name EP1")
@@ -817,6 +840,8 @@ export function DownloadPageContent({ onNavigateAway }: DownloadPageContentProps
                         onDelete={handleDelete}
                         onPause={handlePause}
                         onResume={handleResume}
+                        onRedownload={handleRedownload}
+                        onStartNow={handleStartNow}
                         onPlay={handlePlayEpisode}
                         extractQuality={extractQuality}
                         formatBytes={formatBytes}
@@ -981,6 +1006,8 @@ function DownloadItem({
   onDelete,
   onPause,
   onResume,
+  onRedownload,
+  onStartNow,
   onPlay,
   extractQuality,
   formatBytes,
@@ -990,6 +1017,8 @@ function DownloadItem({
   onDelete: (id: string, filename: string) => void
   onPause: (id: string) => void
   onResume: (id: string) => void
+  onRedownload: (id: string) => void
+  onStartNow: (id: string) => void
   onPlay: (mediaId: string, episodeId: string) => void
   extractQuality: (filename: string) => string | null
   formatBytes: (bytes: number) => string
@@ -1008,6 +1037,9 @@ function DownloadItem({
 
   const quality = extractQuality(download.filename)
   const isFailed = download.status === 'failed'
+  const fileMissing = download.status === 'completed' && download.file_state === 'missing'
+  const fileCorrupt = download.status === 'completed' && download.file_state === 'corrupt'
+  const needsRedownload = fileMissing || fileCorrupt
 
   return (
     <div className={`group flex items-center gap-3.5 py-3 px-[18px] pl-[92px] bg-white/[0.02] border-t border-white/[0.04] transition-colors hover:bg-white/[0.04] relative ${isFailed ? 'bg-red-400/[0.04]' : ''}`}>
@@ -1036,7 +1068,22 @@ function DownloadItem({
           )}
 
           {/* Status badge */}
-          {download.status === 'completed' && (
+          {download.status === 'completed' && fileMissing && (
+            <span className="inline-flex items-center gap-1 text-[0.75rem] font-semibold text-amber-400">
+              Completed — file missing
+            </span>
+          )}
+          {fileCorrupt && (
+            <span className="inline-flex items-center gap-1 text-[0.75rem] font-semibold text-red-400" title={download.error_message}>
+              Completed — file corrupt
+            </span>
+          )}
+          {download.status === 'completed' && download.file_state === 'trashed' && (
+            <span className="inline-flex items-center gap-1 text-[0.75rem] font-semibold text-[var(--color-text-muted)]">
+              <Trash2 size={11} /> In trash
+            </span>
+          )}
+          {download.status === 'completed' && !needsRedownload && download.file_state !== 'trashed' && (
             <span className="inline-flex items-center gap-1 text-[0.75rem] font-semibold text-green-400">
               <CheckCircle size={12} /> Completed
             </span>
@@ -1058,7 +1105,11 @@ function DownloadItem({
             </span>
           )}
           {download.status === 'queued' && (
-            <span className="text-[0.75rem] font-semibold text-[var(--color-text-muted)]">Queued</span>
+            <span className="text-[0.75rem] font-semibold text-[var(--color-text-muted)]">
+              {download.scheduled_start && !download.start_now && download.scheduled_start > Date.now()
+                ? `Scheduled for ${new Date(download.scheduled_start).toLocaleString()}`
+                : 'Queued'}
+            </span>
           )}
         </div>
 
@@ -1144,24 +1195,46 @@ function DownloadItem({
           </>
         )}
         {download.status === 'queued' && (
-          <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] bg-[var(--color-glass-bg)] border border-[var(--color-glass-border)] text-[var(--color-text-secondary)] hover:bg-red-400/15 hover:text-red-400 hover:border-red-400/30 flex items-center justify-center transition-all" title="Delete">
-            <Trash2 size={13} />
-          </button>
+          <>
+            {!download.start_now && (
+              <button onClick={() => onStartNow(download.id)} className="w-7 h-7 rounded-[var(--radius-md)] bg-[var(--color-glass-bg)] border border-[var(--color-glass-border)] text-green-400 hover:bg-green-500/10 flex items-center justify-center transition-all" title="Start now">
+                <Play size={13} />
+              </button>
+            )}
+            <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] bg-[var(--color-glass-bg)] border border-[var(--color-glass-border)] text-[var(--color-text-secondary)] hover:bg-red-400/15 hover:text-red-400 hover:border-red-400/30 flex items-center justify-center transition-all" title="Delete">
+              <Trash2 size={13} />
+            </button>
+          </>
         )}
-        {download.status === 'completed' && (
+        {download.status === 'completed' && needsRedownload && (
           <>
-            <button onClick={() => onPlay(download.media_id, download.episode_id)} className="w-7 h-7 rounded-[var(--radius-md)] border border-transparent text-[var(--color-text-dim)] opacity-0 group-hover:opacity-100 hover:bg-green-500/15 hover:text-green-400 hover:border-green-400/30 flex items-center justify-center transition-all" title="Play">
-              <Play size={13} />
+            <button onClick={() => onRedownload(download.id)} className="inline-flex items-center gap-1 px-2.5 py-[3px] rounded-[var(--radius-sm)] text-[0.7rem] font-semibold bg-amber-400/[0.12] text-amber-400 border border-amber-400/25 hover:bg-amber-400/[0.22] hover:border-amber-400/40 transition-all cursor-pointer" title="Re-download">
+              Re-download
+            </button>
+            <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] bg-[var(--color-glass-bg)] border border-[var(--color-glass-border)] text-[var(--color-text-secondary)] hover:bg-red-400/15 hover:text-red-400 hover:border-red-400/30 flex items-center justify-center transition-all" title="Delete">
+              <Trash2 size={13} />
             </button>
+          </>
+        )}
+        {download.status === 'completed' && !needsRedownload && (
+          <>
+            {download.file_state !== 'trashed' && <button onClick={() => onPlay(download.media_id, download.episode_id)} className="w-7 h-7 rounded-[var(--radius-md)] border border-transparent text-[var(--color-text-dim)] opacity-0 group-hover:opacity-100 hover:bg-green-500/15 hover:text-green-400 hover:border-green-400/30 flex items-center justify-center transition-all" title="Play">
+              <Play size={13} />
+            </button>}
             <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] border border-transparent text-[var(--color-text-dim)] opacity-0 group-hover:opacity-100 hover:bg-red-400/15 hover:text-red-400 hover:border-red-400/30 flex items-center justify-center transition-all" title="Delete">
               <Trash2 size={13} />
             </button>
           </>
         )}
         {download.status === 'cancelled' && (
-          <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] border border-transparent text-[var(--color-text-dim)] hover:bg-red-400/15 hover:text-red-400 flex items-center justify-center transition-all" title="Remove">
-            <X size={13} />
-          </button>
+          <>
+            <button onClick={() => onResume(download.id)} className="inline-flex items-center gap-1 px-2.5 py-[3px] rounded-[var(--radius-sm)] text-[0.7rem] font-semibold bg-[var(--color-glass-bg)] text-[var(--color-text-secondary)] border border-[var(--color-glass-border)] hover:text-[var(--color-text-primary)] transition-all cursor-pointer" title="Resume">
+              Resume
+            </button>
+            <button onClick={() => onDelete(download.id, download.filename)} className="w-7 h-7 rounded-[var(--radius-md)] border border-transparent text-[var(--color-text-dim)] hover:bg-red-400/15 hover:text-red-400 flex items-center justify-center transition-all" title="Remove">
+              <X size={13} />
+            </button>
+          </>
         )}
       </div>
     </div>
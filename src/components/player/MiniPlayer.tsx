@@ -384,14 +384,12 @@ export function MiniPlayer() {
     if (isInNativePip()) exitNativePip()
     const video = videoRef.current
     if (video && data && video.currentTime > 5) {
-      const percentComplete = (video.currentTime / video.duration) * 100
       saveWatchProgress(
         data.malId,
         data.episodeId,
         data.episodeNumber,
         video.currentTime,
-        video.duration,
-        percentComplete >= 85
+        video.duration
       ).catch(() => {})
     }
     closePip()
@@ -403,14 +401,12 @@ export function MiniPlayer() {
     const time = video ? video.currentTime : (data?.currentTime ?? 0)
 
     if (video && data && video.currentTime > 5) {
-      const percentComplete = (video.currentTime / video.duration) * 100
       saveWatchProgress(
         data.malId,
         data.episodeId,
         data.episodeNumber,
         video.currentTime,
-        video.duration,
-        percentComplete >= 85
+        video.duration
       ).catch(() => {})
     }
 
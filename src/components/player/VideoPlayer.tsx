@@ -21,6 +21,7 @@ import { Loader2, RotateCcw, SkipForward } from 'lucide-react'
 import type { VideoSource } from '@/types/extension'
 import {
   saveWatchProgress,
+  reportPlaybackHeartbeat,
   deleteEpisodeDownload,
   getVideoServerInfo,
   type VideoServerUrls,
@@ -103,7 +104,6 @@ export function VideoPlayer({
 
   // Get settings from stores
   const playerSettings = usePlayerStore((state) => state.settings)
-  const markWatchedThreshold = useSettingsStore((state) => state.markWatchedThreshold)
   const defaultVolume = useSettingsStore((state) => state.defaultVolume)
   const autoDeleteWatched = useSettingsStore((state) => state.autoDeleteWatched)
 
@@ -845,17 +845,13 @@ export function VideoPlayer({
       }
 
       try {
-        // Use the mark-as-watched threshold from settings
-        const percentComplete = (video.currentTime / video.duration) * 100
-        const completed = percentComplete >= markWatchedThreshold
-
-        await saveWatchProgress(
+        // The backend decides completion from the mark-as-watched threshold
+        const { completed } = await saveWatchProgress(
           mediaId,
           episodeId,
           currentEpisode,
           video.currentTime,
-          video.duration,
-          completed
+          video.duration
         )
 
         // Track when episode first reaches completion threshold
@@ -921,6 +917,23 @@ export function VideoPlayer({
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [mediaId, episodeId, currentEpisode])
 
+  // Heartbeat so the position survives a crash between progress saves
+  useEffect(() => {
+    if (!mediaId || !episodeId) {
+      return
+    }
+
+    const interval = setInterval(() => {
+      const video = videoRef.current
+      if (!video || video.paused || video.currentTime < 5) {
+        return
+      }
+      reportPlaybackHeartbeat(mediaId, episodeId, video.currentTime).catch(() => {})
+    }, 10_000)
+
+    return () => clearInterval(interval)
+  }, [mediaId, episodeId])
+
   // Fullscreen handling
   useEffect(() => {
     const handleFullscreenChange = () => {
@@ -13,7 +13,13 @@ import { useState, useEffect, useCallback, useRef } from 'react'
 import { Loader2 } from 'lucide-react'
 import { cn } from '@/lib/utils'
 import { useReaderStore } from '@/store/readerStore'
-import { saveReadingProgress } from '@/utils/tauri-commands'
+import {
+  saveReadingProgress,
+  startReadingSession,
+  recordReadingPageTurn,
+  endReadingSession,
+  getReadingTimeEstimate,
+} from '@/utils/tauri-commands'
 import { useMediaStatusContext } from '@/contexts/MediaStatusContext'
 import type { ChapterImage, Chapter } from '@/types/extension'
 import { useProxiedImage } from '@/hooks/useProxiedImage'
@@ -151,6 +157,8 @@ export function MangaReader({
   const [chapterListOpen, setChapterListOpen] = useState(false)
   const [isLoading, setIsLoading] = useState(true)
   const [isBookmarked, setIsBookmarked] = useState(false)
+  const [minutesLeft, setMinutesLeft] = useState<number | null>(null)
+  const readingSessionRef = useRef<Promise<number | null> | null>(null)
 
   // Check if we can navigate to adjacent chapters (use props passed from parent)
   const canGoToPreviousChapter = !!(onPreviousChapter && hasPreviousChapter)
@@ -206,8 +214,36 @@ export function MangaReader({
   // Reset completion tracking when chapter changes
   useEffect(() => {
     setWasMarkedComplete(false)
+    setMinutesLeft(null)
   }, [chapterId])
 
+  // Time page turns for the reading speed estimate (one session per chapter)
+  const pagesLoaded = totalPages > 0
+  useEffect(() => {
+    if (!mangaId || !chapterId || !pagesLoaded) return
+
+    const session = startReadingSession(mangaId, chapterId, currentPageRef.current).catch((error) => {
+      console.error('Failed to start reading session:', error)
+      return null
+    })
+    readingSessionRef.current = session
+
+    return () => {
+      readingSessionRef.current = null
+      session.then((id) => {
+        if (id !== null) {
+          endReadingSession(id).catch((error) => console.error('Failed to end reading session:', error))
+        }
+      })
+    }
+  }, [mangaId, chapterId, pagesLoaded])
+
+  useEffect(() => {
+    readingSessionRef.current?.then((id) => {
+      if (id !== null) recordReadingPageTurn(id, currentPage).catch(() => {})
+    })
+  }, [currentPage])
+
   // Save reading progress when page changes
   useEffect(() => {
     // Check for undefined/null explicitly (not !currentChapter, which would be true for chapter 0)
@@ -227,6 +263,9 @@ export function MangaReader({
           isCompleted
         )
 
+        const estimate = await getReadingTimeEstimate(mangaId, chapterId)
+        setMinutesLeft(estimate ? estimate.estimated_minutes : null)
+
         // Refresh media status when chapter is marked as completed (only once per chapter)
         if (isCompleted && !wasMarkedComplete) {
           setWasMarkedComplete(true)
@@ -543,13 +582,15 @@ export function MangaReader({
         )
 
       case 'double': {
-        // Show two pages side by side
+        // Show two pages side by side; spreads already span both, so they're shown alone
+        const nextImage = images.find(img => img.page === currentPage + 1)
+        const pairedImage = currentImage?.is_spread || nextImage?.is_spread ? undefined : nextImage
         const leftPage = settings.readingDirection === 'rtl'
-          ? images.find(img => img.page === currentPage + 1)
+          ? pairedImage
           : currentImage
         const rightPage = settings.readingDirection === 'rtl'
           ? currentImage
-          : images.find(img => img.page === currentPage + 1)
+          : pairedImage
 
         return (
           <div className={cn(
@@ -610,6 +651,7 @@ export function MangaReader({
         <ReaderControls
           currentPage={currentPage}
           totalPages={totalPages}
+          minutesLeft={minutesLeft}
           onPageChange={setCurrentPage}
           onPreviousPage={previousPage}
           onNextPage={nextPage}
@@ -36,6 +36,8 @@ interface ReaderControlsProps {
   // Page state
   currentPage: number
   totalPages: number
+  /** Estimated minutes left in the chapter (from reading speed) */
+  minutesLeft?: number | null
   onPageChange: (page: number) => void
   onPreviousPage: () => void
   onNextPage: () => void
@@ -70,6 +72,7 @@ interface ReaderControlsProps {
 export function ReaderControls({
   currentPage,
   totalPages,
+  minutesLeft,
   onPreviousPage,
   onNextPage,
   currentChapterNumber,
@@ -203,6 +206,9 @@ export function ReaderControls({
                   </button>
                   <span className={cn('font-mono-code text-xs whitespace-nowrap px-2', isScrollMode ? 'text-white/30' : 'text-white/50')}>
                     Page {currentPage} of {totalPages}
+                    {minutesLeft != null && minutesLeft > 0 && (
+                      <span className="text-white/30"> · about {minutesLeft} min left</span>
+                    )}
                   </span>
                   <button
                     onClick={onNextPage}
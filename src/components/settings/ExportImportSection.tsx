@@ -1,11 +1,13 @@
-import { useState } from 'react'
+import { useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { save, open } from '@tauri-apps/plugin-dialog'
-import { writeTextFile, readTextFile } from '@tauri-apps/plugin-fs'
+import { readTextFile } from '@tauri-apps/plugin-fs'
 import { Download, Upload, AlertTriangle, Check, Loader2 } from 'lucide-react'
 import { notifySuccess, notifyError, notifyWarning } from '@/utils/notify'
 import { SettingSection } from './SettingSection'
 import { SettingRow } from './SettingRow'
+import { listenEvent, type BackupFileOpened } from '@/types/events'
+import type { ImportResult } from '@/utils/tauri-commands'
 
 interface ExportMetadata {
   library_count: number
@@ -13,6 +15,10 @@ interface ExportMetadata {
   reading_history_count: number
   tag_count: number
   media_cache_count: number
+  id_mapping_count?: number
+  migration_archive_count?: number
+  hidden_media_count?: number
+  extension_count?: number
 }
 
 interface ExportData {
@@ -32,24 +38,18 @@ interface ImportOptions {
   import_settings: boolean
   import_media_cache: boolean
   import_tracker_mappings: boolean
+  import_id_mappings: boolean
+  import_migration_archive: boolean
+  import_hidden_media: boolean
+  import_extensions: boolean
 }
 
-interface ImportResult {
-  success: boolean
-  library_imported: number
-  library_skipped: number
-  watch_history_imported: number
-  watch_history_skipped: number
-  reading_history_imported: number
-  reading_history_skipped: number
-  tags_imported: number
-  tags_skipped: number
-  tag_assignments_imported: number
-  settings_imported: number
-  media_cache_imported: number
-  tracker_mappings_imported: number
-  warnings: string[]
-}
+/** Extension registered with the OS so double-clicking a backup opens Otaku */
+const BACKUP_EXTENSION = 'otakubak'
+
+const BACKUP_FILTERS = [
+  { name: 'Otaku Backup', extensions: [BACKUP_EXTENSION, 'json'] },
+]
 
 type ExportState = 'idle' | 'exporting' | 'success' | 'error'
 type ImportState = 'idle' | 'selecting' | 'preview' | 'importing' | 'success' | 'error'
@@ -57,6 +57,8 @@ type ImportState = 'idle' | 'selecting' | 'preview' | 'importing' | 'success' |
 export function ExportImportSection() {
   // Export state
   const [exportState, setExportState] = useState<ExportState>('idle')
+  // Extension code can make up most of the file, so it's only embedded on request
+  const [includeExtensionCode, setIncludeExtensionCode] = useState(false)
 
   // Import state
   const [importState, setImportState] = useState<ImportState>('idle')
@@ -70,20 +72,54 @@ export function ExportImportSection() {
     import_settings: true,
     import_media_cache: true,
     import_tracker_mappings: true,
+    import_id_mappings: true,
+    import_migration_archive: true,
+    import_hidden_media: true,
+    import_extensions: false,
   })
   const [importResult, setImportResult] = useState<ImportResult | null>(null)
 
+  // Backup files opened with the app (file association). The file may have
+  // been opened before this section mounted, so take the pending one first.
+  useEffect(() => {
+    const showOpenedBackup = async (opened: BackupFileOpened | null) => {
+      if (!opened) return
+      if (opened.error) {
+        notifyError('Import Failed', opened.error)
+        return
+      }
+
+      try {
+        const data = await invoke<ExportData>('read_backup_file', { path: opened.path })
+        setImportResult(null)
+        setImportData(data)
+        setImportState('preview')
+      } catch (error) {
+        notifyError('Import Failed', `${error}`)
+      }
+    }
+
+    const takePending = () =>
+      invoke<BackupFileOpened | null>('take_pending_backup_file')
+        .then(showOpenedBackup)
+        .catch((error) => console.error('[backup-file] failed to take pending file', error))
+
+    takePending()
+    const unlisten = listenEvent('backup-file-opened', () => takePending())
+
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [])
+
   const handleExport = async () => {
     setExportState('exporting')
 
     try {
-      // Get export data from backend
-      const data = await invoke<ExportData>('export_user_data')
-
       // Open save dialog
       const filePath = await save({
-        defaultPath: `otaku-backup-${new Date().toISOString().split('T')[0]}.json`,
-        filters: [{ name: 'JSON', extensions: ['json'] }],
+        defaultPath: `otaku-backup-${new Date().toISOString().split('T')[0]}.${BACKUP_EXTENSION}`,
+        filters: BACKUP_FILTERS,
       })
 
       if (!filePath) {
@@ -91,13 +127,16 @@ export function ExportImportSection() {
         return
       }
 
-      // Write to file
-      await writeTextFile(filePath, JSON.stringify(data, null, 2))
+      // The backend streams the export straight into the file
+      const metadata = await invoke<ExportMetadata>('export_user_data_to_file', {
+        path: filePath,
+        includeExtensionCode,
+      })
 
       setExportState('success')
       notifySuccess(
         'Export Complete',
-        `Exported ${data.metadata.library_count} library items, ${data.metadata.watch_history_count} watch history entries`
+        `Exported ${metadata.library_count} library items, ${metadata.watch_history_count} watch history entries`
       )
 
       // Reset state after a delay
@@ -114,7 +153,7 @@ export function ExportImportSection() {
 
     try {
       const filePath = await open({
-        filters: [{ name: 'JSON', extensions: ['json'] }],
+        filters: BACKUP_FILTERS,
         multiple: false,
       })
 
@@ -206,41 +245,51 @@ export function ExportImportSection() {
         label="Export Data"
         description="Save all your library, watch history, and settings to a JSON file"
       >
-        <button
-            onClick={handleExport}
-            disabled={exportState === 'exporting'}
-            className={`
-              flex items-center gap-2
-              px-4 py-2 rounded-lg
-              font-medium
-              transition-colors
-              ${
-                exportState === 'success'
-                  ? 'bg-green-600 text-white'
-                  : exportState === 'error'
-                    ? 'bg-red-600 text-white'
-                    : 'bg-[var(--color-primary)] hover:bg-[var(--color-primary-hover)] text-white'
-              }
-              disabled:opacity-50 disabled:cursor-not-allowed
-            `}
-          >
-            {exportState === 'exporting' ? (
-            <>
-              <Loader2 size={16} className="animate-spin" />
-              Exporting...
-            </>
-          ) : exportState === 'success' ? (
-            <>
-              <Check size={16} />
-              Exported!
-            </>
-          ) : (
-            <>
-              <Download size={16} />
-              Export to File
-            </>
-          )}
-        </button>
+        <div className="flex items-center gap-3">
+          <label className="flex items-center gap-2 text-sm text-[var(--color-text-secondary)]">
+            <input
+              type="checkbox"
+              checked={includeExtensionCode}
+              onChange={(e) => setIncludeExtensionCode(e.target.checked)}
+            />
+            Include extension code
+          </label>
+          <button
+              onClick={handleExport}
+              disabled={exportState === 'exporting'}
+              className={`
+                flex items-center gap-2
+                px-4 py-2 rounded-lg
+                font-medium
+                transition-colors
+                ${
+                  exportState === 'success'
+                    ? 'bg-green-600 text-white'
+                    : exportState === 'error'
+                      ? 'bg-red-600 text-white'
+                      : 'bg-[var(--color-primary)] hover:bg-[var(--color-primary-hover)] text-white'
+                }
+                disabled:opacity-50 disabled:cursor-not-allowed
+              `}
+            >
+              {exportState === 'exporting' ? (
+              <>
+                <Loader2 size={16} className="animate-spin" />
+                Exporting...
+              </>
+            ) : exportState === 'success' ? (
+              <>
+                <Check size={16} />
+                Exported!
+              </>
+            ) : (
+              <>
+                <Download size={16} />
+                Export to File
+              </>
+            )}
+          </button>
+        </div>
       </SettingRow>
 
       {/* Import Section */}
@@ -315,8 +364,36 @@ export function ExportImportSection() {
                     {importData.metadata.tag_count}
                   </span>
                 </div>
+                {(importData.metadata.extension_count ?? 0) > 0 && (
+                  <div className="flex justify-between">
+                    <span className="text-[var(--color-text-secondary)]">Extensions:</span>
+                    <span className="text-[var(--color-text-primary)] font-medium">
+                      {importData.metadata.extension_count}
+                    </span>
+                  </div>
+                )}
               </div>
 
+              {(importData.metadata.extension_count ?? 0) > 0 && (
+                <label className="flex items-start gap-2 text-sm text-[var(--color-text-primary)]">
+                  <input
+                    type="checkbox"
+                    className="mt-0.5"
+                    checked={importOptions.import_extensions}
+                    onChange={(e) =>
+                      setImportOptions({ ...importOptions, import_extensions: e.target.checked })
+                    }
+                  />
+                  <span>
+                    Reinstall extensions
+                    <span className="block text-xs text-[var(--color-text-tertiary)]">
+                      Bundled extensions are reinstalled from the app; others need their code
+                      included in the export.
+                    </span>
+                  </span>
+                </label>
+              )}
+
               {/* Import strategy */}
               <div className="space-y-2">
                 <label className="text-sm font-medium text-[var(--color-text-primary)]">
@@ -437,6 +514,27 @@ export function ExportImportSection() {
                     Tags: {importResult.tags_imported} imported
                   </div>
                 )}
+                {importResult.id_mappings_imported > 0 && (
+                  <div className="text-[var(--color-text-secondary)]">
+                    Source mappings: {importResult.id_mappings_imported} imported
+                  </div>
+                )}
+                {importResult.migration_archive_imported > 0 && (
+                  <div className="text-[var(--color-text-secondary)]">
+                    Migration archive: {importResult.migration_archive_imported} imported
+                  </div>
+                )}
+                {importResult.hidden_media_imported > 0 && (
+                  <div className="text-[var(--color-text-secondary)]">
+                    Hidden titles: {importResult.hidden_media_imported} imported
+                  </div>
+                )}
+                {importResult.extensions_imported > 0 && (
+                  <div className="text-[var(--color-text-secondary)]">
+                    Extensions: {importResult.extensions_imported} installed
+                    {importResult.extensions_skipped > 0 && `, ${importResult.extensions_skipped} skipped`}
+                  </div>
+                )}
               </div>
 
               {importResult.warnings.length > 0 && (
@@ -19,7 +19,7 @@ function formatTime(seconds: number): string {
 }
 
 function AnimeThumbnail({ url, title }: { url?: string; title: string }) {
-  const { src, loading } = useProxiedImage(url || '')
+  const { src, loading } = useProxiedImage(url || '', false, { cache: 'covers' })
   if (!url || loading || !src) {
     return (
       <div className="w-12 h-16 rounded bg-[var(--color-surface-hover)] shrink-0 flex items-center justify-center text-[var(--color-text-tertiary)]">
@@ -31,7 +31,7 @@ function AnimeThumbnail({ url, title }: { url?: string; title: string }) {
 }
 
 function MangaThumbnail({ url, title }: { url?: string; title: string }) {
-  const { src, loading } = useProxiedImage(url || '')
+  const { src, loading } = useProxiedImage(url || '', false, { cache: 'covers' })
   if (!url || loading || !src) {
     return (
       <div className="w-12 h-16 rounded bg-[var(--color-surface-hover)] shrink-0 flex items-center justify-center text-[var(--color-text-tertiary)]">
@@ -0,0 +1,38 @@
+// Listens for the backend "backup-file-opened" event (a .otakubak file was
+// opened with the app) and brings up Settings → Downloads, where
+// ExportImportSection picks the file up and shows the import preview.
+//
+// Mount once at the router root.
+
+import { useEffect } from 'react';
+import { type UnlistenFn } from '@tauri-apps/api/event';
+import { useNavigate } from '@tanstack/react-router';
+import { listenEvent } from '@/types/events';
+
+export function useBackupFileListener(): void {
+  const navigate = useNavigate();
+
+  useEffect(() => {
+    let unlisten: UnlistenFn | undefined;
+    let cancelled = false;
+
+    listenEvent('backup-file-opened', () => {
+      navigate({ to: '/settings', search: { page: 'downloads' } }).catch((e) => {
+        console.warn('[backup-file] navigate failed', e);
+      });
+    })
+      .then((fn) => {
+        if (cancelled) {
+          fn();
+        } else {
+          unlisten = fn;
+        }
+      })
+      .catch((e) => console.error('[backup-file] listen failed', e));
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+    };
+  }, [navigate]);
+}
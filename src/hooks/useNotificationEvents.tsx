@@ -17,6 +17,7 @@ import {
   type Notification,
   type NotificationType,
 } from '@/store/notificationStore'
+import { runNotificationCallback } from '@/utils/notify'
 import { isMobile } from '@/utils/platform'
 import {
   isPermissionGranted,
@@ -138,6 +139,7 @@ export function useNotificationEvents(): UseNotificationEventsReturn {
       if (notification.action?.route) {
         navigate({ to: notification.action.route })
       }
+      runNotificationCallback(notification)
       // Mark as read when action is clicked
       store.markAsRead(notification.id)
     },
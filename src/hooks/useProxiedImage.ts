@@ -11,10 +11,13 @@
  * Supports a `skip` parameter for lazy loading - when true, the hook
  * defers fetching until skip becomes false. Once fetched, the blob URL
  * persists even if skip goes back to true (avoids re-fetching on scroll).
+ *
+ * `target` picks the media disk cache namespace (reader pages by default).
  */
 
 import { useState, useEffect, useRef } from 'react'
 import { getCachedImageUrl, preloadImage } from '@/utils/proxyImageCache'
+import type { ImageCacheTarget } from '@/utils/tauri-commands'
 
 function isLocalUrl(url: string): boolean {
   return (
@@ -29,6 +32,7 @@ function isLocalUrl(url: string): boolean {
 export function useProxiedImage(
   url: string,
   skip = false,
+  target?: ImageCacheTarget,
 ): {
   src: string | null
   loading: boolean
@@ -96,7 +100,7 @@ export function useProxiedImage(
     setError(false)
 
     // Use shared preloadImage (deduplicates concurrent requests + caches result)
-    preloadImage(url)
+    preloadImage(url, target)
       .then((blobUrl) => {
         if (cancelled) return
         blobUrlRef.current = blobUrl
@@ -113,6 +117,7 @@ export function useProxiedImage(
     return () => {
       cancelled = true
     }
+    // eslint-disable-next-line react-hooks/exhaustive-deps -- target only picks where the bytes are cached
   }, [url, skip])
 
   return { src, loading, error }
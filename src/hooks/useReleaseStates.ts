@@ -49,6 +49,7 @@ async function fetchBatchReleaseStates(mediaIds: string[]): Promise<void> {
           notified_up_to: null,
           last_checked: null,
           normalized_status: 'unknown',
+          is_checking: false,
         })
       }
     }
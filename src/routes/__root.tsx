@@ -6,6 +6,7 @@ import { MediaStatusProvider } from '@/contexts/MediaStatusContext'
 import { useNotificationEvents } from '@/hooks/useNotificationEvents'
 import { useAutoUpdateCheck } from '@/hooks/useAutoUpdateCheck'
 import { useDeeplinkListener } from '@/hooks/useDeeplinkListener'
+import { useBackupFileListener } from '@/hooks/useBackupFileListener'
 import { useSettingsStore } from '@/store/settingsStore'
 import { useReaderStore } from '@/store/readerStore'
 import { usePlayerStore } from '@/store/playerStore'
@@ -112,6 +113,9 @@ function RootComponent() {
   // Listen for backend deeplink events (e.g. from tray icon clicks)
   useDeeplinkListener()
 
+  // Open the import flow when a .otakubak backup is opened with the app
+  useBackupFileListener()
+
   // Show migration screen while migration is needed (blocks all routes)
   if (migrationNeeded) {
     return <MigrationScreen onComplete={() => setMigrationNeeded(false)} />
@@ -27,6 +27,7 @@ import {
 import { convertFileSrc } from '@tauri-apps/api/core'
 import type { MangaDetails, ChapterImages } from '@/types/extension'
 import { toastInfo } from '@/utils/notify'
+import { EVENTS, listenEvent } from '@/types/events'
 import { resolveJikanToMangakakalot } from '@/utils/manga-extensions'
 
 interface ReadSearch {
@@ -312,13 +313,13 @@ function ReadPage() {
 
         if (downloaded) {
           // Load from local storage
-          const localPaths = await getDownloadedChapterImages(trackingId, currentChapterId)
+          const localPages = await getDownloadedChapterImages(trackingId, currentChapterId)
 
-          if (localPaths.length > 0) {
+          if (localPages.length > 0) {
             // Convert local file paths to asset URLs
-            const localImages = localPaths.map((path, index) => ({
-              url: convertFileSrc(path),
-              page: index + 1,
+            const localImages = localPages.map((image) => ({
+              ...image,
+              url: convertFileSrc(image.url),
             }))
 
             setChapterImages({
@@ -333,7 +334,7 @@ function ReadPage() {
         }
 
         // Step 3: Load chapter images from network
-        const images = await getChapterImages(effectiveExtId, currentChapterId)
+        const images = await getChapterImages(effectiveExtId, currentChapterId, !nsfwFilter)
         setChapterImages(images)
       } catch (err) {
         setError(err instanceof Error ? err.message : 'Failed to load chapter images')
@@ -343,7 +344,25 @@ function ReadPage() {
     }
 
     loadChapterData()
-  }, [effectiveExtId, resolvedMangaId, trackingId, currentChapterId, currentChapter])
+  }, [effectiveExtId, resolvedMangaId, trackingId, currentChapterId, currentChapter, nsfwFilter])
+
+  // Page sizes the extension didn't report arrive after the images
+  useEffect(() => {
+    const unlisten = listenEvent(EVENTS.CHAPTER_IMAGE_SIZES, ({ chapter_id, sizes }) => {
+      if (chapter_id !== currentChapterId) return
+      const byPage = new Map(sizes.map((size) => [size.page, size]))
+      setChapterImages((prev) => prev && {
+        ...prev,
+        images: prev.images.map((image) => {
+          const size = byPage.get(image.page)
+          return size ? { ...image, width: size.width, height: size.height, is_spread: size.is_spread } : image
+        }),
+      })
+    })
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [currentChapterId])
 
   const handleNextChapter = () => {
     if (!details || currentChapterIndex === -1) return
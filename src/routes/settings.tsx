@@ -2,6 +2,7 @@ import { createFileRoute } from '@tanstack/react-router'
 import { useSettingsStore } from '../store/settingsStore'
 import { usePlayerStore } from '../store/playerStore'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { getVersion, getTauriVersion } from '@tauri-apps/api/app'
 import { useEffect, useState } from 'react'
 import { notifySuccess, notifyError } from '@/utils/notify'
@@ -45,14 +46,33 @@ const settingsNavItems: { key: SettingsPage; label: string; icon: typeof Palette
   { key: 'about', label: 'About', icon: HelpCircle },
 ]
 
+interface SettingsSearch {
+  page?: SettingsPage
+}
+
 export const Route = createFileRoute('/settings')({
   component: SettingsScreen,
+  validateSearch: (search: Record<string, unknown>): SettingsSearch => {
+    const page = settingsNavItems.find((item) => item.key === search.page)?.key
+    return page ? { page } : {}
+  },
 })
 
 interface StorageUsage {
   database_size: number
   downloads_size: number
+  chapter_downloads_size: number
+  covers_size: number
+  thumbnails_size: number
+  page_cache_size: number
+  trash_size: number
+  logs_size: number
+  backups_size: number
+  other_size: number
   total_size: number
+  /** Covers, thumbnails and cached pages (what the media cache cap applies to) */
+  media_cache_size: number
+  computed_at: number
 }
 
 function SettingsScreen() {
@@ -60,7 +80,13 @@ function SettingsScreen() {
   const playerSettings = usePlayerStore((state) => state.settings)
   const updatePlayerSettings = usePlayerStore((state) => state.updateSettings)
 
-  const [activePage, setActivePage] = useState<SettingsPage>('appearance')
+  const { page: requestedPage } = Route.useSearch()
+  const [activePage, setActivePage] = useState<SettingsPage>(requestedPage ?? 'appearance')
+
+  // Follow navigations that ask for a specific page (e.g. opening a backup file)
+  useEffect(() => {
+    if (requestedPage) setActivePage(requestedPage)
+  }, [requestedPage])
   const [storageUsage, setStorageUsage] = useState<StorageUsage | null>(null)
   const [appVersion, setAppVersion] = useState<string>('')
   const [tauriVersion, setTauriVersion] = useState<string>('')
@@ -80,9 +106,9 @@ function SettingsScreen() {
     }
   }
 
-  const loadStorageUsage = async () => {
+  const loadStorageUsage = async (forceRefresh = false) => {
     try {
-      const usage = await invoke<StorageUsage>('get_storage_usage')
+      const usage = await invoke<StorageUsage>('get_storage_usage', { forceRefresh })
       setStorageUsage(usage)
     } catch (error) {
       console.error('Failed to load storage usage:', error)
@@ -105,6 +131,16 @@ function SettingsScreen() {
     return () => clearTimeout(timeoutId)
   }, [])
 
+  // Live-update when the backend notices usage change
+  useEffect(() => {
+    const unlisten = listen<StorageUsage>('storage-usage-changed', (event) => {
+      setStorageUsage(event.payload)
+    })
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [])
+
   const formatBytes = (bytes: number): string => {
     if (bytes === 0) return '0 B'
     const k = 1024
@@ -117,7 +153,7 @@ function SettingsScreen() {
     try {
       await invoke('clear_all_watch_history')
       notifySuccess('History Cleared', 'Watch history has been cleared')
-      loadStorageUsage()
+      loadStorageUsage(true)
     } catch (error) {
       notifyError('Clear Failed', `Failed to clear watch history: ${error}`)
     }
@@ -127,7 +163,7 @@ function SettingsScreen() {
     try {
       await invoke('clear_library')
       notifySuccess('Library Cleared', 'Library has been cleared')
-      loadStorageUsage()
+      loadStorageUsage(true)
     } catch (error) {
       notifyError('Clear Failed', `Failed to clear library: ${error}`)
     }
@@ -137,7 +173,7 @@ function SettingsScreen() {
     try {
       await invoke('clear_all_data')
       notifySuccess('Data Cleared', 'All data has been cleared')
-      loadStorageUsage()
+      loadStorageUsage(true)
     } catch (error) {
       notifyError('Clear Failed', `Failed to clear data: ${error}`)
     }
@@ -10,12 +10,13 @@ import { Loader2 } from 'lucide-react'
 import { convertFileSrc } from '@tauri-apps/api/core'
 import { VideoPlayer } from '@/components/player/VideoPlayer'
 import { useMobileLayout } from '@/hooks/useMobileLayout'
-import { jikanAnimeDetails, loadExtension, resolveAllanimeId, clearAllanimeMapping, getVideoSources, saveMediaDetails, saveEpisodes, getCachedMediaDetails, getEpisodeFilePath, getWatchProgress, getLocalVideoUrl, getLocalFileSize, getVideoServerInfo, type MediaEntry, type EpisodeEntry, type VideoServerUrls } from '@/utils/tauri-commands'
+import { jikanAnimeDetails, loadExtension, resolveAllanimeId, clearAllanimeMapping, getNumberingOffset, getVideoSources, saveMediaDetails, saveEpisodes, getCachedMediaDetails, getEpisodeFilePath, getWatchProgress, getLocalVideoUrl, getLocalFileSize, getVideoServerInfo, type MediaEntry, type EpisodeEntry, type VideoServerUrls } from '@/utils/tauri-commands'
 import { ALLANIME_EXTENSION } from '@/extensions/allanime-extension'
 import type { MediaDetails, VideoSources } from '@/types/extension'
 import { toastInfo } from '@/utils/notify'
 import { isAndroid } from '@/utils/platform'
 import { usePipStore } from '@/store/pipStore'
+import { useSettingsStore } from '@/store/settingsStore'
 
 interface WatchSearch {
   malId: string
@@ -49,6 +50,7 @@ function WatchPage() {
   const shownOfflineToastRef = useRef<string | null>(null)
   /** When expanding from PiP, skip the DB resume time (pip time takes priority) */
   const pipResumeOverrideRef = useRef(false)
+  const nsfwFilter = useSettingsStore((state) => state.nsfwFilter)
 
   // Check if we're expanding from PiP mini player
   const pipExpandTime = usePipStore((s) => s.expandTime)
@@ -120,7 +122,7 @@ function WatchPage() {
             created_at: new Date().toISOString(),
             updated_at: new Date().toISOString(),
           }
-          await saveMediaDetails(mediaEntry)
+          await saveMediaDetails(mediaEntry, result.age_rating)
 
           // Also cache episodes for offline playback
           if (result.episodes.length > 0) {
@@ -407,9 +409,13 @@ function WatchPage() {
             // Effect will re-run when allanimeId becomes available
             return
           }
-          // Build AllAnime episode ID: {allanimeId}::{episodeNumber}
-          const allanimeEpisodeId = `${allanimeId}::${currentEpisode.number}`
-          let result = await getVideoSources(allanimeExtId, allanimeEpisodeId)
+          // Build AllAnime episode ID: {allanimeId}::{episodeNumber}, in AllAnime's
+          // numbering when it differs from MAL's
+          const numberingOffset = malId
+            ? await getNumberingOffset(malId, allanimeExtId).catch(() => 0)
+            : 0
+          const allanimeEpisodeId = `${allanimeId}::${currentEpisode.number + numberingOffset}`
+          let result = await getVideoSources(allanimeExtId, allanimeEpisodeId, !nsfwFilter)
 
           // If no valid sources, the cached AllAnime ID may be wrong - clear and re-resolve
           const hasValidSources = result.sources.some(s => s.url && s.url.length > 0)
@@ -419,8 +425,8 @@ function WatchPage() {
             const freshId = await resolveAllanimeId(details.title, 'anime', malId, details.english_name, details.year, details.title_synonyms, details.type, details.episode_count, details.native_name, details.season?.quarter)
             if (freshId && freshId !== allanimeId) {
               setAllanimeId(freshId)
-              const freshEpisodeId = `${freshId}::${currentEpisode.number}`
-              result = await getVideoSources(allanimeExtId, freshEpisodeId)
+              const freshEpisodeId = `${freshId}::${currentEpisode.number + numberingOffset}`
+              result = await getVideoSources(allanimeExtId, freshEpisodeId, !nsfwFilter)
             }
           }
 
@@ -449,7 +455,7 @@ function WatchPage() {
     }
 
     loadProgressAndSources()
-  }, [currentEpisodeId, currentEpisode, malId, videoServerInfo, allanimeExtId, allanimeId])
+  }, [currentEpisodeId, currentEpisode, malId, videoServerInfo, allanimeExtId, allanimeId, nsfwFilter])
 
   const handleNextEpisode = () => {
     if (!details || currentEpisodeIndex === -1) return
@@ -81,6 +81,8 @@ const saveToDatabase = async (settings: SettingsData) => {
     // Also save NSFW filter as a separate key for the release checker
     // The release checker needs this to properly fetch episode info for adult content
     await setAppSetting('nsfw_filter', settings.nsfwFilter ? '1' : '0')
+    // Episode completion is decided by the backend from this threshold
+    await setAppSetting('completion_threshold_percent', String(settings.markWatchedThreshold))
   } catch (err) {
     console.error('Failed to save settings to database:', err)
   }
@@ -141,6 +143,7 @@ export const useSettingsStore = create<SettingsState>()((set, get) => ({
         set({ ...mergedSettings, _initialized: true })
         // Sync NSFW filter key for the release checker
         await setAppSetting('nsfw_filter', mergedSettings.nsfwFilter ? '1' : '0')
+        await setAppSetting('completion_threshold_percent', String(mergedSettings.markWatchedThreshold))
       } else {
         set({ _initialized: true })
         // Sync default NSFW filter setting
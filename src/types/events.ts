@@ -0,0 +1,182 @@
+/**
+ * Backend Event Types
+ *
+ * Names and payloads of every event the Rust backend emits.
+ * Keep in sync with src-tauri/src/events.rs — the `generate_event_schema`
+ * command returns the JSON schema of each payload for comparison.
+ */
+
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+import type {
+  BadgeSummary,
+  ChapterDownloadProgressEvent,
+  CoverRefreshProgress,
+  DiscoverResultsEvent,
+  DownloadProgress,
+  DownloadStats,
+  DownloadVerifyProgress,
+  HomeCategoryEvent,
+  LogEntry,
+  MediaHydrationProgress,
+  MigrationProgress,
+  NetworkStatus,
+  NotificationPayload,
+  OfflineReady,
+  SeasonDiscoverResultsEvent,
+  SystemStats,
+} from '@/utils/tauri-commands'
+
+export const EVENTS = {
+  DOWNLOAD_PROGRESS: 'download-progress',
+  DOWNLOAD_STATS: 'download-stats',
+  CHAPTER_DOWNLOAD_PROGRESS: 'chapter-download-progress',
+  CHAPTER_IMAGE_SIZES: 'chapter-image-sizes',
+  DOWNLOAD_VERIFY_PROGRESS: 'download-verify-progress',
+  OFFLINE_READY: 'offline-ready',
+  NETWORK_STATUS: 'network-status',
+  NOTIFICATION: 'notification',
+  BADGE_SUMMARY_CHANGED: 'badge-summary-changed',
+  HOME_CONTENT: 'home-content-category',
+  ANIME_DISCOVER: 'anime-discover-results',
+  MANGA_DISCOVER: 'manga-discover-results',
+  SEASON_ANIME_DISCOVER: 'season-anime-discover-results',
+  SYSTEM_STATS: 'system-stats',
+  APP_LOGS: 'app-logs',
+  DATA_TRANSFER_PROGRESS: 'data-transfer-progress',
+  MIGRATION_PROGRESS: 'migration_progress',
+  RELEASE_CHECK_PROGRESS: 'release_check_progress',
+  COVER_REFRESH: 'cover_refresh_progress',
+  MEDIA_HYDRATION: 'media_hydration_progress',
+  STORAGE_USAGE_CHANGED: 'storage-usage-changed',
+  AUTO_BACKUP_COMPLETED: 'auto-backup-completed',
+  AUTO_BACKUP_FAILED: 'auto-backup-failed',
+  BACKUP_FILE_OPENED: 'backup-file-opened',
+  EPISODE_COMPLETED: 'episode-completed',
+  DEEPLINK: 'deeplink',
+} as const
+
+export type EventName = (typeof EVENTS)[keyof typeof EVENTS]
+
+export interface ReleaseCheckProgress {
+  current_index: number
+  total_count: number
+  media_title: string
+  media_type: string // "anime" or "manga"
+  is_complete: boolean
+  status: 'checking' | 'success' | 'failed' | 'complete'
+  error_message: string | null
+}
+
+export interface DataTransferProgress {
+  phase: 'export' | 'import' | 'complete'
+  /** Table currently being processed (empty once complete) */
+  table: string
+  processed: number
+  total: number
+}
+
+export interface StorageUsage {
+  database_size: number
+  downloads_size: number
+  chapter_downloads_size: number
+  covers_size: number
+  thumbnails_size: number
+  page_cache_size: number
+  trash_size: number
+  logs_size: number
+  backups_size: number
+  other_size: number
+  total_size: number
+  /** Covers, thumbnails and cached pages (what the media cache cap applies to) */
+  media_cache_size: number
+  computed_at: number
+}
+
+export interface BackupResult {
+  success: boolean
+  file_path: string | null
+  timestamp: string
+  error: string | null
+  items_backed_up: {
+    library_count: number
+    watch_history_count: number
+    reading_history_count: number
+  }
+}
+
+export interface AutoBackupFailed {
+  error: string
+}
+
+/** A .otakubak file was opened with the app */
+export interface BackupFileOpened {
+  path: string
+  /** Summary of the backup; null when the file failed to parse */
+  metadata: {
+    library_count: number
+    watch_history_count: number
+    reading_history_count: number
+    tag_count: number
+    media_cache_count: number
+    profile_id?: number | null
+  } | null
+  exported_at: string | null
+  app_version: string | null
+  error: string | null
+}
+
+/** An episode crossed the completion threshold or was marked watched */
+export interface EpisodeCompleted {
+  media_id: string
+  episode_id: string
+  episode_number: number
+}
+
+/** Page sizes of a chapter probed after its images were returned */
+export interface ChapterImageSizes {
+  chapter_id: string
+  sizes: {
+    page: number
+    width: number
+    height: number
+    is_spread: boolean
+  }[]
+}
+
+/** Payload type of each event, keyed by event name */
+export interface EventPayloads {
+  'download-progress': DownloadProgress
+  'download-stats': DownloadStats
+  'chapter-download-progress': ChapterDownloadProgressEvent
+  'chapter-image-sizes': ChapterImageSizes
+  'download-verify-progress': DownloadVerifyProgress
+  'offline-ready': OfflineReady
+  'network-status': NetworkStatus
+  'notification': NotificationPayload
+  'badge-summary-changed': BadgeSummary
+  'home-content-category': HomeCategoryEvent
+  'anime-discover-results': DiscoverResultsEvent
+  'manga-discover-results': DiscoverResultsEvent
+  'season-anime-discover-results': SeasonDiscoverResultsEvent
+  'system-stats': SystemStats
+  'app-logs': LogEntry[]
+  'data-transfer-progress': DataTransferProgress
+  'migration_progress': MigrationProgress
+  'release_check_progress': ReleaseCheckProgress
+  'cover_refresh_progress': CoverRefreshProgress
+  'media_hydration_progress': MediaHydrationProgress
+  'storage-usage-changed': StorageUsage
+  'auto-backup-completed': BackupResult
+  'auto-backup-failed': AutoBackupFailed
+  'backup-file-opened': BackupFileOpened
+  'episode-completed': EpisodeCompleted
+  'deeplink': string
+}
+
+/** Typed wrapper around `listen` for backend events */
+export function listenEvent<E extends EventName>(
+  event: E,
+  handler: (payload: EventPayloads[E]) => void
+): Promise<UnlistenFn> {
+  return listen<EventPayloads[E]>(event, (e) => handler(e.payload))
+}
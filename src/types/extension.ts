@@ -13,7 +13,37 @@ export interface ExtensionMetadata {
   version: string
   type: ExtensionType
   language: string
+  /** Content languages the source serves; falls back to [language] */
+  languages: string[]
   base_url: string
+  /** Remote icon declared by the extension; display `icon` instead */
+  icon_url?: string
+  /** Circuit breaker state (only set by listExtensions) */
+  breaker?: BreakerStatus
+  /** Local URL of the cached icon (only set by listExtensions, null until cached) */
+  icon?: string | null
+}
+
+/**
+ * closed: calls go through; open: failing repeatedly, calls fail fast until
+ * retry_at; half_open: cool-down over, the next call decides
+ */
+export type BreakerState = 'closed' | 'open' | 'half_open'
+
+export interface BreakerStatus {
+  state: BreakerState
+  consecutive_failures: number
+  last_error: string | null
+  /** Unix ms when an open breaker lets calls through again */
+  retry_at: number | null
+}
+
+export interface ExtensionHealth {
+  extension_id: string
+  healthy: boolean
+  error: string | null
+  latency_ms: number
+  breaker: BreakerStatus
 }
 
 export interface SearchResult {
@@ -51,6 +81,12 @@ export interface SearchResult {
   broadcast_time?: string
   /** Broadcast timezone (e.g., "Asia/Tokyo") */
   broadcast_timezone?: string
+  /** Content language tag from the extension (e.g., "en-dub") */
+  language?: string
+  /** Whether `language` matches the preferred content language */
+  language_match?: boolean
+  /** Age rating as the source writes it (e.g., "PG-13 - Teens 13 or older") */
+  age_rating?: string
 }
 
 export interface SearchResults {
@@ -106,6 +142,8 @@ export interface MediaDetails {
   streaming_links?: { name: string; url: string }[]
   /** Related anime/manga */
   relations?: { relation: string; entry: { mal_id: number; type: string; name: string }[] }[]
+  /** Age rating as the source writes it */
+  age_rating?: string
 }
 
 export interface VideoSource {
@@ -121,9 +159,16 @@ export interface VideoSource {
   // Custom Referer required by some CDNs (wixmp, fast4speed). Attached to
   // the HTTP request when fetching/downloading this source.
   referrer?: string
+  // Other HTTP headers the CDN needs (cookies, Origin, ...). Sent with every
+  // request of a download of this source.
+  headers?: Record<string, string>
   // Per-source subtitle sidecars (not the top-level VideoSources.subtitles,
   // which are provider-wide). Rust side mirrors this as Vec<Subtitle>.
   subtitles?: Subtitle[]
+  // Content language tag (e.g. 'en-dub') and whether it matches the
+  // preferred_content_language setting. Set by the backend.
+  language?: string
+  language_match?: boolean
 }
 
 export interface Subtitle {
@@ -152,6 +197,8 @@ export interface ChapterImage {
   page: number
   width?: number
   height?: number
+  /** Landscape page spanning two pages; shown alone in double-page mode */
+  is_spread?: boolean
 }
 
 export interface ChapterImages {
@@ -8,8 +8,8 @@
 
 import toast from 'react-hot-toast'
 import { createElement } from 'react'
-import { useNotificationStore, type NotificationType } from '@/store/notificationStore'
-import { createNotification } from '@/utils/tauri-commands'
+import { useNotificationStore, type Notification, type NotificationType } from '@/store/notificationStore'
+import { createNotification, redownloadEpisode, retryDownloadBatch } from '@/utils/tauri-commands'
 import { isMobile } from '@/utils/platform'
 import {
   isPermissionGranted,
@@ -232,6 +232,30 @@ export function toastWarning(title: string, message: string, duration?: number):
   notify({ title, message, type: 'warning', toastOnly: true, duration })
 }
 
+/**
+ * Run a notification action's backend callback, if it has one.
+ * Called alongside route navigation when the action is clicked.
+ */
+export function runNotificationCallback(notification: Notification): void {
+  const callback = notification.action?.callback
+  if (callback === 'retry_download_batch') {
+    const batchId = notification.metadata?.batch_id
+    if (typeof batchId !== 'string') return
+    retryDownloadBatch(batchId)
+      .then((count) => toastInfo('Retrying Downloads', `Queued ${count} episode${count === 1 ? '' : 's'} again`))
+      .catch((err) => toastError('Retry Failed', String(err)))
+  } else if (callback === 'redownload_corrupt_downloads') {
+    const ids = notification.metadata?.download_ids
+    if (!Array.isArray(ids)) return
+    Promise.allSettled(ids.map((id) => redownloadEpisode(String(id)))).then((results) => {
+      const queued = results.filter((r) => r.status === 'fulfilled').length
+      const failed = results.length - queued
+      if (queued > 0) toastInfo('Downloading Again', `Queued ${queued} episode${queued === 1 ? '' : 's'} again`)
+      if (failed > 0) toastError('Re-download Failed', `${failed} episode${failed === 1 ? '' : 's'} could not be queued`)
+    })
+  }
+}
+
 // Convenience aliases matching common toast patterns
 export const notification = {
   success: notifySuccess,
@@ -13,7 +13,7 @@
  * Lifecycle: cache is cleared when chapter changes (via clearProxyImageCache).
  */
 
-import { proxyImageRequest } from '@/utils/tauri-commands'
+import { proxyImageRequest, type ImageCacheTarget } from '@/utils/tauri-commands'
 
 function guessImageMimeType(url: string): string {
   const clean = url.split('?')[0].split('#')[0].toLowerCase()
@@ -52,7 +52,7 @@ function processQueue() {
  * Deduplicates concurrent requests for the same URL.
  * Respects concurrency limit to avoid bandwidth flooding.
  */
-export async function preloadImage(url: string): Promise<string> {
+export async function preloadImage(url: string, target?: ImageCacheTarget): Promise<string> {
   const cached = cache.get(url)
   if (cached) {
     console.log(`[preload] cache HIT for ${url.slice(-40)}`)
@@ -69,7 +69,7 @@ export async function preloadImage(url: string): Promise<string> {
   const promise = new Promise<string>((resolve, reject) => {
     const execute = () => {
       console.log(`[preload] fetching ${url.slice(-40)} (active: ${activeCount}/${MAX_CONCURRENT}, queued: ${queue.length})`)
-      proxyImageRequest(url)
+      proxyImageRequest(url, target)
         .then((buffer) => {
           const blob = new Blob([buffer], { type: guessImageMimeType(url) })
           const blobUrl = URL.createObjectURL(blob)
@@ -8,11 +8,15 @@
 import { invoke } from '@tauri-apps/api/core'
 import type {
   ExtensionMetadata,
+  ExtensionHealth,
   SearchResult,
   SearchResults,
   MediaDetails,
+  VideoSource,
   VideoSources,
+  Subtitle,
   MangaDetails,
+  ChapterImage,
   ChapterImages,
 } from '@/types/extension'
 
@@ -40,6 +44,41 @@ function reportApiStatus(type: 'anime' | 'manga', success: boolean, resultCount?
   apiStatusListeners.forEach(listener => listener(type, success, resultCount))
 }
 
+// ==================== Extension Errors ====================
+
+/** Kinds of typed extension errors (ExtensionError on the Rust side) */
+export type ExtensionErrorKind = 'not_found'
+
+/**
+ * Typed error of a command that runs an extension, thrown in place of the
+ * `{ kind, detail }` payload so callers can tell a missing extension from a
+ * failed request. Other failures are still plain message strings.
+ */
+export class ExtensionCommandError extends Error {
+  readonly kind: ExtensionErrorKind
+  readonly detail: string
+
+  constructor(kind: ExtensionErrorKind, detail: string) {
+    super(kind === 'not_found' ? `Extension not found: ${detail}` : detail)
+    this.name = 'ExtensionCommandError'
+    this.kind = kind
+    this.detail = detail
+  }
+}
+
+/** invoke() for commands that run an extension */
+async function invokeExtension<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  try {
+    return await invoke<T>(command, args)
+  } catch (err) {
+    if (err && typeof err === 'object' && 'kind' in err) {
+      const { kind, detail } = err as { kind: ExtensionErrorKind; detail: string }
+      throw new ExtensionCommandError(kind, detail)
+    }
+    throw err
+  }
+}
+
 /**
  * Load an extension from JavaScript code
  * @param code - Extension JavaScript code
@@ -64,7 +103,7 @@ export async function searchAnime(
   allowAdult: boolean = false
 ): Promise<SearchResults> {
   try {
-    const result = await invoke<SearchResults>('search_anime', { extensionId, query, page, allowAdult })
+    const result = await invokeExtension<SearchResults>('search_anime', { extensionId, query, page, allowAdult })
     reportApiStatus('anime', true, result.results?.length ?? 0)
     return result
   } catch (err) {
@@ -83,33 +122,37 @@ export async function getRecommendations(
   extensionId: string,
   allowAdult: boolean = false
 ): Promise<SearchResults> {
-  return await invoke('get_recommendations', { extensionId, allowAdult })
+  return await invokeExtension('get_recommendations', { extensionId, allowAdult })
 }
 
 /**
  * Get detailed information about an anime
  * @param extensionId - Extension ID
  * @param animeId - Anime ID from search results
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Detailed anime information with episodes
  */
 export async function getAnimeDetails(
   extensionId: string,
-  animeId: string
+  animeId: string,
+  allowAdult?: boolean
 ): Promise<MediaDetails> {
-  return await invoke('get_anime_details', { extensionId, animeId })
+  return await invokeExtension('get_anime_details', { extensionId, animeId, allowAdult })
 }
 
 /**
  * Get video sources for an episode
  * @param extensionId - Extension ID
  * @param episodeId - Episode ID
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Video sources with quality options and subtitles
  */
 export async function getVideoSources(
   extensionId: string,
-  episodeId: string
+  episodeId: string,
+  allowAdult?: boolean
 ): Promise<VideoSources> {
-  return await invoke('get_video_sources', { extensionId, episodeId })
+  return await invokeExtension('get_video_sources', { extensionId, episodeId, allowAdult })
 }
 
 /**
@@ -129,7 +172,7 @@ export async function discoverAnime(
   allowAdult: boolean = false
 ): Promise<SearchResults> {
   try {
-    const result = await invoke<SearchResults>('discover_anime', { extensionId, page, sortType, genres, allowAdult })
+    const result = await invokeExtension<SearchResults>('discover_anime', { extensionId, page, sortType, genres, allowAdult })
     reportApiStatus('anime', true, result.results?.length ?? 0)
     return result
   } catch (err) {
@@ -159,7 +202,7 @@ export async function getCurrentSeasonAnime(
   page: number = 1,
   allowAdult: boolean = false
 ): Promise<SeasonResults> {
-  return await invoke('get_current_season_anime', { extensionId, page, allowAdult })
+  return await invokeExtension('get_current_season_anime', { extensionId, page, allowAdult })
 }
 
 // Home Content types
@@ -185,7 +228,7 @@ export async function getHomeContent(
   extensionId: string,
   allowAdult: boolean = false
 ): Promise<HomeContent> {
-  return await invoke('get_home_content', { extensionId, allowAdult })
+  return await invokeExtension('get_home_content', { extensionId, allowAdult })
 }
 
 // ==================== Home Content Streaming (SSE) ====================
@@ -212,7 +255,7 @@ export async function streamHomeContent(
   extensionId: string,
   allowAdult: boolean = false
 ): Promise<void> {
-  return await invoke('stream_home_content', { extensionId, allowAdult })
+  return await invokeExtension('stream_home_content', { extensionId, allowAdult })
 }
 
 /**
@@ -261,7 +304,7 @@ export async function streamDiscoverAnime(
   allowAdult: boolean = false,
   pagesToFetch: number = 3
 ): Promise<void> {
-  return await invoke('stream_discover_anime', {
+  return await invokeExtension('stream_discover_anime', {
     extensionId,
     sortType,
     genres,
@@ -299,7 +342,7 @@ export async function streamDiscoverManga(
   allowAdult: boolean = false,
   pagesToFetch: number = 3
 ): Promise<void> {
-  return await invoke('stream_discover_manga', {
+  return await invokeExtension('stream_discover_manga', {
     extensionId,
     sortType,
     genres,
@@ -349,7 +392,7 @@ export async function streamCurrentSeasonAnime(
   allowAdult: boolean = false,
   pagesToFetch: number = 3
 ): Promise<void> {
-  return await invoke('stream_current_season_anime', {
+  return await invokeExtension('stream_current_season_anime', {
     extensionId,
     allowAdult,
     pagesToFetch,
@@ -376,6 +419,8 @@ export interface Tag {
   slug: string
   count: number
   thumbnail?: string
+  /** Slugs of other tags merged into this one by genre normalization */
+  merged_slugs?: string[]
 }
 
 export interface TagsResult {
@@ -388,26 +433,30 @@ export interface TagsResult {
  * Get available tags (genres and studios) for filtering
  * @param extensionId - Extension ID
  * @param page - Page number (1-indexed)
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Tags result with genres and studios
  */
 export async function getTags(
   extensionId: string,
-  page: number = 1
+  page: number = 1,
+  allowAdult?: boolean
 ): Promise<TagsResult> {
-  return await invoke('get_tags', { extensionId, page })
+  return await invokeExtension('get_tags', { extensionId, page, allowAdult })
 }
 
 /**
  * Get detailed media information (alias for getAnimeDetails)
  * @param extensionId - Extension ID
  * @param mediaId - Media ID from search results
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Detailed media information with episodes
  */
 export async function getMediaDetails(
   extensionId: string,
-  mediaId: string
+  mediaId: string,
+  allowAdult?: boolean
 ): Promise<MediaDetails> {
-  return await getAnimeDetails(extensionId, mediaId)
+  return await getAnimeDetails(extensionId, mediaId, allowAdult)
 }
 
 // ==================== Manga Commands ====================
@@ -427,7 +476,7 @@ export async function searchManga(
   allowAdult: boolean = false
 ): Promise<SearchResults> {
   try {
-    const result = await invoke<SearchResults>('search_manga', { extensionId, query, page, allowAdult })
+    const result = await invokeExtension<SearchResults>('search_manga', { extensionId, query, page, allowAdult })
     reportApiStatus('manga', true, result.results?.length ?? 0)
     return result
   } catch (err) {
@@ -448,20 +497,88 @@ export async function getMangaDetails(
   mangaId: string,
   allowAdult: boolean = false
 ): Promise<MangaDetails> {
-  return await invoke('get_manga_details', { extensionId, mangaId, allowAdult })
+  return await invokeExtension('get_manga_details', { extensionId, mangaId, allowAdult })
 }
 
 /**
  * Get chapter images for reading
  * @param extensionId - Extension ID
  * @param chapterId - Chapter ID
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Chapter images with total pages
  */
 export async function getChapterImages(
   extensionId: string,
-  chapterId: string
+  chapterId: string,
+  allowAdult?: boolean
 ): Promise<ChapterImages> {
-  return await invoke('get_chapter_images', { extensionId, chapterId })
+  return await invokeExtension('get_chapter_images', { extensionId, chapterId, allowAdult })
+}
+
+export interface ChapterImageRefresh {
+  page_index: number
+  /** Fresh URL for the failed page, null if the chapter no longer has it */
+  url: string | null
+  images: ChapterImages
+}
+
+/**
+ * Report a chapter page that failed to load (e.g. 403 from an expired URL).
+ * The chapter's images are fetched again; pages failing together share one refresh.
+ * @param extensionId - Extension ID
+ * @param chapterId - Chapter ID
+ * @param pageIndex - Index of the failed page in the chapter's images
+ * @param failedUrl - URL that failed, counted against its host
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
+ * @returns Fresh URL for the failed page plus all refreshed images
+ */
+export async function reportChapterImageFailure(
+  extensionId: string,
+  chapterId: string,
+  pageIndex: number,
+  failedUrl?: string,
+  allowAdult?: boolean
+): Promise<ChapterImageRefresh> {
+  return await invoke('report_chapter_image_failure', { extensionId, chapterId, pageIndex, failedUrl, allowAdult })
+}
+
+export interface ImageHostFailures {
+  host: string
+  failures: number
+  /** Unix timestamp (ms) */
+  last_failure_at: number
+}
+
+/**
+ * Chapter image failures by host since the app started
+ */
+export async function getImageHostFailures(): Promise<ImageHostFailures[]> {
+  return await invoke('get_image_host_failures')
+}
+
+export interface MediaHydrationProgress {
+  total: number
+  processed: number
+  updated: number
+  failed: number
+  current_title: string
+  status: string // "running" | "completed" | "cancelled"
+}
+
+/**
+ * Fill in missing descriptions and covers of library media (anime from Jikan,
+ * manga from their extension). Resumes an interrupted or cancelled run unless
+ * restart is set. Emits "media_hydration_progress" events as it runs.
+ */
+export async function hydrateImportedMedia(restart?: boolean): Promise<MediaHydrationProgress> {
+  return await invoke('hydrate_imported_media', { restart })
+}
+
+/**
+ * Stop a running hydration after the current item; resolves to whether one was running
+ */
+export async function cancelMediaHydration(): Promise<boolean> {
+  return await invoke('cancel_media_hydration')
 }
 
 /**
@@ -481,7 +598,7 @@ export async function discoverManga(
   allowAdult: boolean = false
 ): Promise<SearchResults> {
   try {
-    const result = await invoke<SearchResults>('discover_manga', { extensionId, page, sortType, genres, allowAdult })
+    const result = await invokeExtension<SearchResults>('discover_manga', { extensionId, page, sortType, genres, allowAdult })
     reportApiStatus('manga', true, result.results?.length ?? 0)
     return result
   } catch (err) {
@@ -494,23 +611,44 @@ export async function discoverManga(
  * Get available manga tags (genres)
  * @param extensionId - Extension ID
  * @param page - Page number (1-indexed)
+ * @param allowAdult - Whether to include adult content (from NSFW setting)
  * @returns Tags result with genres
  */
 export async function getMangaTags(
   extensionId: string,
-  page: number = 1
+  page: number = 1,
+  allowAdult?: boolean
 ): Promise<TagsResult> {
-  return await invoke('get_manga_tags', { extensionId, page })
+  return await invokeExtension('get_manga_tags', { extensionId, page, allowAdult })
+}
+
+/** Media disk cache namespace an image is kept in */
+export type ImageCacheNamespace = 'pages' | 'covers' | 'thumbnails'
+
+/** Where a proxied image is cached on disk */
+export interface ImageCacheTarget {
+  cache: ImageCacheNamespace
+  /** Covers of a known media item are cached under its id */
+  mediaId?: string
 }
 
 /**
  * Proxy an image request to avoid CORS issues (for manga pages)
  * Uses tauri::ipc::Response on the Rust side for efficient binary transfer.
+ * Images are served from and kept in the media disk cache.
  * @param url - Image URL to proxy
+ * @param target - Cache namespace (reader pages by default)
  * @returns Image bytes as ArrayBuffer
  */
-export async function proxyImageRequest(url: string): Promise<ArrayBuffer> {
-  return await invoke('proxy_image_request', { url })
+export async function proxyImageRequest(
+  url: string,
+  target?: ImageCacheTarget
+): Promise<ArrayBuffer> {
+  return await invoke('proxy_image_request', {
+    url,
+    cache: target?.cache,
+    mediaId: target?.mediaId,
+  })
 }
 
 /**
@@ -521,6 +659,144 @@ export async function listExtensions(): Promise<ExtensionMetadata[]> {
   return await invoke('list_extensions')
 }
 
+/**
+ * Unload an extension, deleting its cached icon and stored copy. Uninstalled
+ * bundled extensions aren't installed again.
+ * @returns Whether the extension was loaded
+ */
+export async function unloadExtension(extensionId: string): Promise<boolean> {
+  return await invoke('unload_extension', { extensionId })
+}
+
+/**
+ * Install the extensions bundled with the app. Does nothing after the first
+ * run unless a bundled extension was updated.
+ * @returns Extensions that were installed
+ */
+export async function installBundledExtensions(): Promise<ExtensionMetadata[]> {
+  return await invoke('install_bundled_extensions')
+}
+
+export interface OnboardingState {
+  extension_installed: boolean
+  library_non_empty: boolean
+  /** At least one media is mapped to a tracker */
+  tracker_connected: boolean
+  downloads_dir_writable: boolean
+  bundled_extensions_installed: boolean
+}
+
+/**
+ * Which first-run setup steps are complete, for the onboarding checklist
+ */
+export async function getOnboardingState(): Promise<OnboardingState> {
+  return await invoke('get_onboarding_state')
+}
+
+/**
+ * Probe an extension with a small search. Runs even while its circuit breaker
+ * is open; a healthy result closes the breaker.
+ * @param extensionId - Extension ID
+ */
+export async function checkExtensionHealth(extensionId: string): Promise<ExtensionHealth> {
+  return await invokeExtension('check_extension_health', { extensionId })
+}
+
+export interface ExtensionCall {
+  /** Extension method called, e.g. "search" or "getSources" */
+  method: string
+  /** Unix timestamp (ms) when the call finished */
+  at: number
+  duration_ms: number
+  /** Highest runtime heap usage seen around the call */
+  peak_memory_bytes: number
+  error: string | null
+}
+
+/**
+ * Recent calls into an extension (up to 50, newest first), for showing
+ * extension authors their footprint
+ * @param extensionId - Extension ID
+ */
+export async function getExtensionRequestLog(extensionId: string): Promise<ExtensionCall[]> {
+  return await invoke('get_extension_request_log', { extensionId })
+}
+
+export interface ExtensionHookParam {
+  name: string
+  /** TypeScript notation, e.g. 'string' or 'number | null' */
+  type: string
+}
+
+export interface ExtensionHook {
+  name: string
+  params: ExtensionHookParam[]
+  /** Key of `types` in the schema */
+  returns: string
+  /** Kind of extension it's called on; null for both */
+  extension_type: 'anime' | 'manga' | null
+  required: boolean
+  /** What the app does instead when an optional hook is missing */
+  fallback: string | null
+}
+
+export interface ExtensionApiSchema {
+  version: number
+  /** JSON schema of the metadata fields on extensionObject */
+  metadata: Record<string, unknown>
+  hooks: ExtensionHook[]
+  /** JSON schema of each hook's return value, by type name */
+  types: Record<string, Record<string, unknown>>
+}
+
+/**
+ * What the app expects from an extension: the hooks it calls on
+ * extensionObject and a JSON schema of what each returns
+ */
+export async function getExtensionApiSchema(): Promise<ExtensionApiSchema> {
+  return await invoke('get_extension_api_schema')
+}
+
+/**
+ * Get the heap limit of extension runtimes in megabytes (64 by default)
+ */
+export async function getExtensionMemoryLimit(): Promise<number> {
+  return await invoke('get_extension_memory_limit')
+}
+
+/**
+ * Set the heap limit of extension runtimes in megabytes (16-1024)
+ * @returns The limit applied after clamping
+ */
+export async function setExtensionMemoryLimit(megabytes: number): Promise<number> {
+  return await invoke('set_extension_memory_limit', { megabytes })
+}
+
+export interface ExtensionBackgroundBudget {
+  extension_id: string
+  /** Background calls that can be made right now */
+  remaining: number
+  calls_per_hour: number
+  /** Seconds until the next call is available; 0 while some are left */
+  next_call_in_secs: number
+}
+
+/**
+ * Remaining background call budget of every loaded extension. Release checks,
+ * auto-downloads and cache warm-up draw from it; user actions don't.
+ */
+export async function getExtensionBackgroundBudget(): Promise<ExtensionBackgroundBudget[]> {
+  return await invoke('get_extension_background_budget')
+}
+
+/**
+ * Set how many background calls each extension may make per hour (1-3600, 30 by default)
+ * @returns The budget applied after clamping
+ */
+export async function setExtensionBackgroundBudget(callsPerHour: number): Promise<number> {
+  return await invoke('set_extension_background_budget', { callsPerHour })
+}
+
 /**
  * Proxy a video request to avoid CORS issues
  * @param url - URL to proxy
@@ -549,8 +825,21 @@ export async function proxyHlsPlaylist(url: string): Promise<string> {
  * @param episodeId - Episode ID
  * @param episodeNumber - Episode number
  * @param url - Video URL to download
- * @param filename - Filename for the downloaded video
+ * @param filename - Filename for the downloaded video; replaced by the
+ *   'download_filename_template' app setting when one is set
  * @param customPath - Optional custom download location
+ * @param batchId - Shared by episodes queued together; the batch gets one
+ *   summary notification instead of one per episode
+ * @param overwrite - Replace an existing download of the episode
+ * @param scheduledStart - Unix timestamp (ms) to wait for in the queue
+ * @param mediaTitle - Series title stored with the download; defaults to the
+ *   cached media title
+ * @param headers - HTTP headers of the source (see sourceHeaders); defaults
+ *   suit AllAnime's CDNs
+ * @param subtitles - Subtitle tracks to save next to the video once it
+ *   completes (see sourceSubtitles)
+ * @param alternatives - The episode's other sources; the best few are kept
+ *   to download it again if the file goes missing (see redownloadEpisode)
  * @returns Download ID for tracking progress
  */
 export async function startDownload(
@@ -559,9 +848,98 @@ export async function startDownload(
   episodeNumber: number,
   url: string,
   filename: string,
-  customPath?: string
+  customPath?: string,
+  quality?: string,
+  sourceLabel?: string,
+  batchId?: string,
+  overwrite?: boolean,
+  scheduledStart?: number,
+  mediaTitle?: string,
+  headers?: Record<string, string>,
+  subtitles?: Subtitle[],
+  alternatives?: VideoSource[]
 ): Promise<string> {
-  return await invoke('start_download', { mediaId, episodeId, episodeNumber, url, filename, customPath })
+  return await invoke('start_download', {
+    mediaId,
+    episodeId,
+    episodeNumber,
+    url,
+    filename,
+    customPath,
+    quality,
+    sourceLabel,
+    batchId,
+    scheduledStart,
+    overwrite,
+    mediaTitle,
+    headers,
+    subtitles,
+    alternatives,
+  })
+}
+
+/**
+ * HTTP headers to download a source with: its headers plus its referrer as
+ * Referer, or undefined when it has neither
+ */
+export function sourceHeaders(source: VideoSource): Record<string, string> | undefined {
+  const headers = { ...source.headers }
+  const hasReferer = Object.keys(headers).some((name) => name.toLowerCase() === 'referer')
+  if (source.referrer && !hasReferer) {
+    headers.Referer = source.referrer
+  }
+  return Object.keys(headers).length > 0 ? headers : undefined
+}
+
+/**
+ * Subtitle tracks to download with a source: its own, or the provider-wide
+ * ones when it has none
+ */
+export function sourceSubtitles(source: VideoSource, sources?: VideoSources): Subtitle[] {
+  return source.subtitles?.length ? source.subtitles : (sources?.subtitles ?? [])
+}
+
+/** A subtitle file downloaded with an episode */
+export interface DownloadSubtitle {
+  /** Language tag used in the file name */
+  language: string
+  label: string
+  file_path: string
+}
+
+/** What a new download would overwrite */
+export interface ExistingDownload {
+  /** Null for a file no download tracks */
+  download: DownloadProgress | null
+  file_path: string
+}
+
+/**
+ * A download that doesn't fit under the storage quota (app setting
+ * download_storage_quota_gb) even after evicting every watched episode
+ * outside favorites. Sizes are in bytes.
+ */
+export interface QuotaExceeded {
+  quota_bytes: number
+  used_bytes: number
+  /** 0 when the size is unknown */
+  needed_bytes: number
+  evictable_bytes: number
+}
+
+/**
+ * Error returned by startDownload. 'already_downloaded' means the episode is
+ * downloaded (or its file exists); call again with overwrite to replace it,
+ * which moves the old file to the trash first. 'quota_exceeded' means it
+ * wouldn't fit under the download storage quota.
+ */
+export type QueueError =
+  | { kind: 'already_downloaded'; detail: ExistingDownload }
+  | { kind: 'quota_exceeded'; detail: QuotaExceeded }
+  | { kind: 'failed'; detail: string }
+
+export function isAlreadyDownloaded(error: unknown): error is { kind: 'already_downloaded'; detail: ExistingDownload } {
+  return typeof error === 'object' && error !== null && (error as QueueError).kind === 'already_downloaded'
 }
 
 /**
@@ -582,13 +960,23 @@ export async function listDownloads(): Promise<DownloadProgress[]> {
 }
 
 /**
- * Cancel an ongoing download
+ * Cancel an ongoing download, keeping the bytes fetched so far so that
+ * resumeDownload can continue it
  * @param downloadId - Download ID to cancel
  */
 export async function cancelDownload(downloadId: string): Promise<void> {
   return await invoke('cancel_download', { downloadId })
 }
 
+/**
+ * Abort a download
+ * @param downloadId - Download ID to abort
+ * @param deletePartial - Also delete the bytes fetched so far (default true)
+ */
+export async function abortDownload(downloadId: string, deletePartial?: boolean): Promise<void> {
+  return await invoke('abort_download', { downloadId, deletePartial })
+}
+
 /**
  * Pause an ongoing download
  * @param downloadId - Download ID to pause
@@ -598,13 +986,450 @@ export async function pauseDownload(downloadId: string): Promise<void> {
 }
 
 /**
- * Resume a paused download
+ * Resume a paused, cancelled or failed download
  * @param downloadId - Download ID to resume
  */
 export async function resumeDownload(downloadId: string): Promise<void> {
   return await invoke('resume_download', { downloadId })
 }
 
+/**
+ * Resume a download whose source URL has expired, fetching a fresh URL in the
+ * same quality from the extension first. Starts over when the new URL can't
+ * continue the partial file.
+ * @param downloadId - Download ID to resume
+ * @param extensionId - Extension the episode comes from
+ */
+export async function refreshAndResumeDownload(
+  downloadId: string,
+  extensionId: string,
+  allowAdult?: boolean
+): Promise<void> {
+  return await invoke('refresh_and_resume_download', { downloadId, extensionId, allowAdult })
+}
+
+/** Where a re-download got its source from */
+export type RedownloadSource = 'stored' | 'resolved'
+
+/**
+ * Download a completed episode whose file is missing again, under the same
+ * filename. Source URLs stored at download time are tried first; the
+ * extension is only asked for fresh sources when none still works.
+ * @param downloadId - Download whose file is missing
+ */
+export async function redownloadEpisode(
+  downloadId: string,
+  allowAdult?: boolean
+): Promise<RedownloadSource> {
+  return await invoke('redownload_episode', { downloadId, allowAdult })
+}
+
+/**
+ * Get how many downloads may run at the same time
+ */
+export async function getMaxConcurrentDownloads(): Promise<number> {
+  return await invoke('get_max_concurrent_downloads')
+}
+
+/**
+ * Change how many downloads may run at the same time (1-20, out-of-range
+ * values are clamped). Takes effect without a restart.
+ * @returns The value applied
+ */
+export async function setMaxConcurrentDownloads(maxConcurrent: number): Promise<number> {
+  return await invoke('set_max_concurrent_downloads', { maxConcurrent })
+}
+
+/**
+ * Get the global download speed limit in bytes per second (0 = unlimited)
+ */
+export async function getDownloadSpeedLimit(): Promise<number> {
+  return await invoke('get_download_speed_limit')
+}
+
+/**
+ * Set the global download speed limit in bytes per second, shared by all
+ * running downloads (0 = unlimited). Applies immediately.
+ */
+export async function setDownloadSpeedLimit(bytesPerSec: number): Promise<void> {
+  return await invoke('set_download_speed_limit', { bytesPerSec })
+}
+
+/**
+ * Set one download's own speed limit in bytes per second, applied on top of
+ * the global limit (null or 0 = unlimited). A running download slows down or
+ * speeds up within a second or two without restarting.
+ */
+export async function setDownloadSpeedLimitFor(downloadId: string, limit: number | null): Promise<void> {
+  return await invoke('set_download_speed_limit_for', { downloadId, limit })
+}
+
+/** Daily window in local time, as minutes since midnight; may run past midnight */
+export interface OffPeakWindow {
+  start_minute: number
+  end_minute: number
+}
+
+/**
+ * Get the daily window queued downloads start in (null = any time)
+ */
+export async function getDownloadOffPeakWindow(): Promise<OffPeakWindow | null> {
+  return await invoke('get_download_off_peak_window')
+}
+
+/**
+ * Set the daily window queued downloads start in (null = any time).
+ * Downloads with their own scheduled start ignore it.
+ */
+export async function setDownloadOffPeakWindow(window: OffPeakWindow | null): Promise<void> {
+  return await invoke('set_download_off_peak_window', { window })
+}
+
+/**
+ * Set or clear when a queued download may start
+ * @param scheduledStart Unix timestamp (ms), or null to follow the off-peak window
+ */
+export async function scheduleDownload(downloadId: string, scheduledStart: number | null): Promise<void> {
+  return await invoke('schedule_download', { downloadId, scheduledStart })
+}
+
+/**
+ * Start a download without waiting for its scheduled start or the off-peak
+ * window (it still waits for a free slot)
+ */
+export async function startDownloadNow(downloadId: string): Promise<void> {
+  return await invoke('start_download_now', { downloadId })
+}
+
+/**
+ * Pause every downloading or queued download. Downloads queued afterwards
+ * wait (with paused_globally set) until resumeAllDownloads.
+ * @returns Number of downloads paused
+ */
+export async function pauseAllDownloads(): Promise<number> {
+  return await invoke('pause_all_downloads')
+}
+
+/**
+ * Resume every paused download, along with the downloads queued while paused.
+ * Only the max concurrent number start right away; the rest wait in the queue.
+ * @returns Number of downloads resumed
+ */
+export async function resumeAllDownloads(): Promise<number> {
+  return await invoke('resume_all_downloads')
+}
+
+/**
+ * Whether downloads are paused globally by pauseAllDownloads
+ */
+export async function getDownloadsPaused(): Promise<boolean> {
+  return await invoke('get_downloads_paused')
+}
+
+/** Totals for the downloads page header (also the download-stats event) */
+export interface DownloadStats {
+  /** Combined speed of the running downloads, in bytes per second */
+  speed: number
+  active: number
+  queued: number
+  paused: number
+  failed: number
+  completed: number
+  /** Bytes received since local midnight */
+  bytes_today: number
+  /** Size of the completed downloads whose file is still there */
+  completed_bytes: number
+}
+
+export async function getDownloadStats(): Promise<DownloadStats> {
+  return await invoke('get_download_stats')
+}
+
+export interface NetworkStatus {
+  online: boolean
+  /** Unix timestamp (ms) of the last change; null while it never changed */
+  changed_at: number | null
+  /** Downloads paused by the outage, resumed once it's back */
+  paused_downloads: number
+}
+
+/**
+ * Whether the network is up, as last probed. Also emitted as the
+ * `network-status` event whenever it changes.
+ */
+export async function getNetworkStatus(): Promise<NetworkStatus> {
+  return await invoke('get_network_status')
+}
+
+export type IntegrityProblem =
+  | { kind: 'missing' }
+  | { kind: 'size_mismatch'; expected: number; actual: number }
+  | { kind: 'unknown_container' }
+  | { kind: 'checksum_mismatch'; expected: string; actual: string }
+
+export interface VerifyResult {
+  download_id: string
+  ok: boolean
+  problem: IntegrityProblem | null
+  /** Whether the file was hashed this time */
+  checksum_checked: boolean
+  /** Whether this verification stored the file's first checksum */
+  checksum_recorded: boolean
+}
+
+export interface DownloadVerifyProgress {
+  total: number
+  processed: number
+  /** Marked failed for a wrong size or checksum */
+  failed: number
+  missing: number
+  current_title: string
+  status: string // "running" | "completed"
+}
+
+/**
+ * Check that a completed download's file exists and has its recorded size.
+ * With checksum, the file is also hashed and compared with its recorded
+ * SHA-256 (stored on first use). A bad file marks the download failed.
+ */
+export async function verifyDownload(downloadId: string, checksum = false): Promise<VerifyResult> {
+  return await invoke('verify_download', { downloadId, checksum })
+}
+
+/**
+ * Verify every completed download in the background.
+ * Listen for 'download-verify-progress' events for progress.
+ */
+export async function verifyAllDownloads(checksum = false): Promise<void> {
+  return await invoke('verify_all_downloads', { checksum })
+}
+
+/**
+ * Check every completed download of one series now: size, container header
+ * and, with hash, the stored checksum. Bad files are flagged corrupt (or
+ * missing) instead of failing the download.
+ */
+export async function verifyMediaDownloads(mediaId: string, hash = false): Promise<VerifyResult[]> {
+  return await invoke('verify_media_downloads', { mediaId, hash })
+}
+
+export interface VerifySchedule {
+  /** Verify downloads during maintenance (off by default) */
+  enabled: boolean
+  /** Downloads checked per run, least recently verified first */
+  items_per_run: number
+  interval_hours: number
+}
+
+/**
+ * Get the schedule for verifying downloads during maintenance
+ */
+export async function getVerifySchedule(): Promise<VerifySchedule> {
+  return await invoke('get_verify_schedule')
+}
+
+/**
+ * Update the schedule for verifying downloads during maintenance
+ */
+export async function setVerifySchedule(schedule: VerifySchedule): Promise<void> {
+  return await invoke('set_verify_schedule', { schedule })
+}
+
+export interface DownloadEvent {
+  id: number
+  download_id: string
+  media_id: string
+  episode_number: number
+  kind: 'verified' | 'corrupt' | 'missing'
+  /** Why the file was flagged */
+  detail: string | null
+  /** Unix timestamp (ms) */
+  created_at: number
+}
+
+/**
+ * Get integrity events, most recent first, for one download or all of them
+ */
+export async function getDownloadEvents(downloadId?: string, limit?: number): Promise<DownloadEvent[]> {
+  return await invoke('get_download_events', { downloadId, limit })
+}
+
+/**
+ * Retry the failed episodes of a download batch
+ * @returns Number of downloads queued again
+ */
+export async function retryDownloadBatch(batchId: string): Promise<number> {
+  return await invoke('retry_download_batch', { batchId })
+}
+
+/** Why a download failed, from its error message */
+export type FailureCause = 'network' | 'disk' | 'not_found' | 'expired' | 'interrupted' | 'other'
+
+/** Which failed downloads to retry; everything by default */
+export interface RetryFailedFilter {
+  media_id?: string
+  causes?: FailureCause[]
+}
+
+/** What happened to the failed downloads of one cause */
+export interface RetryGroup {
+  cause: FailureCause
+  failed: number
+  /** Queued again (including re-resolved ones) */
+  requeued: number
+  /** Queued again with a fresh source URL from the extension */
+  re_resolved: number
+  /** Left failed: disk errors, or dead URLs with no extension to ask */
+  skipped: number
+  /** Retries that went wrong part-way; the rest carried on */
+  errors: { download_id: string; error: string }[]
+  download_ids: string[]
+}
+
+export interface RetryFailedSummary {
+  groups: RetryGroup[]
+  requeued: number
+  skipped: number
+  /** Retries that errored, listed per group */
+  errored: number
+}
+
+/**
+ * Retry every failed download (or those matching the filter), grouped by why
+ * they failed. Expired source URLs are re-resolved through the extension.
+ */
+export async function retryFailedDownloads(
+  filter?: RetryFailedFilter,
+  allowAdult?: boolean
+): Promise<RetryFailedSummary> {
+  return await invoke<RetryFailedSummary>('retry_failed_downloads', { filter, allowAdult })
+}
+
+export interface ItemSizeEstimate {
+  url: string
+  size: number | null
+  /** Whether the server accepts byte ranges (downloads can resume) */
+  accepts_ranges: boolean
+}
+
+export interface DownloadSizeEstimate {
+  items: ItemSizeEstimate[]
+  total_bytes: number
+  /** Items whose size couldn't be told (not part of total_bytes) */
+  unknown: number
+  available_bytes: number | null
+  reserve_bytes: number
+  fits: boolean
+}
+
+/**
+ * Estimate the size of a set of downloads and whether they fit on disk
+ * (free space minus the download_disk_reserve_mb reserve)
+ * @param headers - HTTP headers of the sources (see sourceHeaders)
+ */
+export async function estimateDownloadSize(
+  urls: string[],
+  customPath?: string,
+  headers?: Record<string, string>
+): Promise<DownloadSizeEstimate> {
+  return await invoke('estimate_download_size', { urls, customPath, headers })
+}
+
+export interface BatchDownloadStarted {
+  batch_id: string
+  queued: number
+  /** Episode numbers skipped because they're downloaded already */
+  already_downloaded: number[]
+  estimated_bytes: number | null
+}
+
+/**
+ * Queue a batch of episodes; each episode's video source is fetched right
+ * before it starts downloading. Episodes already queued are skipped, and so
+ * are downloaded ones (see already_downloaded) unless `overwrite` is set.
+ * Fails with a "Not enough disk space" error when the estimated size doesn't
+ * fit, unless `force` is set.
+ */
+export async function startBatchDownload(
+  mediaId: string,
+  extensionId: string,
+  episodeIds: string[],
+  customPath?: string,
+  force?: boolean,
+  overwrite?: boolean,
+  scheduledStart?: number,
+  allowAdult?: boolean
+): Promise<BatchDownloadStarted> {
+  return await invokeExtension('start_batch_download', {
+    mediaId,
+    extensionId,
+    episodeIds,
+    customPath,
+    force,
+    overwrite,
+    scheduledStart,
+    allowAdult,
+  })
+}
+
+export interface BatchProgress {
+  batch_id: string
+  media_id: string
+  total: number
+  queued: number
+  downloading: number
+  paused: number
+  completed: number
+  failed: number
+  cancelled: number
+  downloaded_bytes: number
+  /** Sum of the known sizes; members that haven't started add nothing */
+  total_bytes: number
+}
+
+/**
+ * Progress of each download batch of a media item
+ */
+export async function getBatchProgress(mediaId: string): Promise<BatchProgress[]> {
+  return await invoke('get_batch_progress', { mediaId })
+}
+
+/** Downloaded episodes of a series watchable in a row from the watch position */
+export interface OfflineReady {
+  media_id: string
+  title: string
+  /** The watch position */
+  from_episode: number
+  /** Last episode before the first one that isn't downloaded */
+  to_episode: number
+}
+
+/**
+ * Episodes ready offline for each series with a batch download in progress
+ * (e.g. "Episodes 5–8 ready offline"); series whose next episode isn't
+ * downloaded yet are left out
+ */
+export async function getOfflineReady(): Promise<OfflineReady[]> {
+  return await invoke('get_offline_ready')
+}
+
+/**
+ * Cancel the episodes of a batch that haven't started yet
+ * @returns Number of downloads cancelled
+ */
+export async function cancelBatchDownload(batchId: string): Promise<number> {
+  return await invoke('cancel_batch_download', { batchId })
+}
+
+/**
+ * Mark a batch queued with startDownload as complete. Call it after the last
+ * episode of the batch is queued; the batch's summary notification waits
+ * for it.
+ */
+export async function sealDownloadBatch(batchId: string): Promise<void> {
+  return await invoke('seal_download_batch', { batchId })
+}
+
 /**
  * Check if an episode is downloaded
  * @param mediaId - Media ID
@@ -623,6 +1448,16 @@ export async function getEpisodeFilePath(mediaId: string, episodeNumber: number)
   return await invoke('get_episode_file_path', { mediaId, episodeNumber })
 }
 
+/**
+ * Subtitle files downloaded with an episode, for offline playback; load them
+ * from the video server like the video file
+ * @param mediaId - Media ID
+ * @param episodeNumber - Episode number
+ */
+export async function getEpisodeSubtitlePaths(mediaId: string, episodeNumber: number): Promise<DownloadSubtitle[]> {
+  return await invoke('get_episode_subtitle_paths', { mediaId, episodeNumber })
+}
+
 /**
  * Get total storage used by downloads in bytes
  */
@@ -637,58 +1472,285 @@ export async function getDownloadsDirectory(): Promise<string> {
   return await invoke('get_downloads_directory')
 }
 
-/**
- * Open the downloads folder in file explorer
- * @param customPath - Optional custom path to open (uses default if not provided)
- */
-export async function openDownloadsFolder(customPath?: string): Promise<void> {
-  return await invoke('open_downloads_folder', { customPath })
+/**
+ * Open the downloads folder in file explorer
+ * @param customPath - Optional custom path to open (uses default if not provided)
+ */
+export async function openDownloadsFolder(customPath?: string): Promise<void> {
+  return await invoke('open_downloads_folder', { customPath })
+}
+
+/**
+ * Remove a download from the list (doesn't delete file)
+ * @param downloadId - Download ID to remove
+ */
+export async function removeDownload(downloadId: string): Promise<void> {
+  return await invoke('remove_download', { downloadId })
+}
+
+/**
+ * Delete a downloaded file
+ * @param downloadId - Download ID to delete
+ */
+export async function deleteDownload(downloadId: string): Promise<void> {
+  return await invoke('delete_download', { downloadId })
+}
+
+/**
+ * Move a completed download's file to the trash; the download stays listed
+ */
+export async function trashDownload(downloadId: string): Promise<void> {
+  return await invoke('trash_download', { downloadId })
+}
+
+/**
+ * Move a trashed download's file back to where it was
+ */
+export async function restoreDownload(downloadId: string): Promise<void> {
+  return await invoke('restore_download', { downloadId })
+}
+
+/**
+ * Delete a downloaded episode by media ID and episode number
+ * @param mediaId - Media ID
+ * @param episodeNumber - Episode number
+ */
+export async function deleteEpisodeDownload(mediaId: string, episodeNumber: number): Promise<void> {
+  return await invoke('delete_episode_download', { mediaId, episodeNumber })
+}
+
+/**
+ * Clear downloads with a finished status from the list, returning how many
+ * were removed. Files stay on disk.
+ */
+export async function clearDownloadsByStatus(
+  status: 'completed' | 'failed' | 'cancelled'
+): Promise<number> {
+  return await invoke('clear_downloads_by_status', { status })
+}
+
+/** A download that completed or was taken off the downloads list */
+export interface DownloadHistoryEntry {
+  id: number
+  download_id: string
+  media_id: string
+  media_title: string | null
+  episode_id: string
+  episode_number: number
+  quality: string | null
+  file_path: string
+  total_bytes: number
+  /** The download's status when the entry was recorded */
+  status: string
+  event: 'completed' | 'deleted' | 'removed' | 'cleared'
+  queued_at: string | null
+  /** Unix ms */
+  recorded_at: number
+}
+
+/**
+ * Get a page of the download history, most recent first. Clearing completed
+ * downloads moves them here.
+ */
+export async function getDownloadHistory(limit = 100, offset = 0): Promise<DownloadHistoryEntry[]> {
+  return await invoke('get_download_history', { limit, offset })
+}
+
+/**
+ * Clear completed downloads from list
+ */
+export async function clearCompletedDownloads(): Promise<void> {
+  return await invoke('clear_completed_downloads')
+}
+
+/**
+ * Clear failed downloads from list
+ */
+export async function clearFailedDownloads(): Promise<void> {
+  return await invoke('clear_failed_downloads')
+}
+
+/**
+ * Clear cancelled downloads from list
+ */
+export async function clearCancelledDownloads(): Promise<void> {
+  return await invoke('clear_cancelled_downloads')
+}
+
+export interface ArchiveResult {
+  moved: number
+  bytes_moved: number
+  failed: string[]
+}
+
+export interface StorageBreakdown {
+  database_size: number
+  local_bytes: number
+  local_count: number
+  archived_bytes: number
+  archived_count: number
+  offline_count: number
+}
+
+export interface RescanResult {
+  checked: number
+  missing: number
+  offline: number
+  restored: number
+}
+
+/**
+ * Move a series' completed downloads to cold storage (e.g. a NAS path)
+ */
+export async function archiveDownloads(mediaId: string, destDir: string): Promise<ArchiveResult> {
+  return await invoke('archive_downloads', { mediaId, destDir })
+}
+
+/**
+ * Move a series' archived downloads back into local storage
+ */
+export async function unarchiveDownloads(mediaId: string): Promise<ArchiveResult> {
+  return await invoke('unarchive_downloads', { mediaId })
+}
+
+/**
+ * Get storage usage split into local and archived downloads
+ */
+export async function getStorageBreakdown(): Promise<StorageBreakdown> {
+  return await invoke('get_storage_breakdown')
+}
+
+/**
+ * Re-check completed downloads against the filesystem.
+ * Archived files on unreachable storage show as 'offline' instead of failing.
+ */
+export async function rescanDownloads(): Promise<RescanResult> {
+  return await invoke('rescan_downloads')
+}
+
+export interface OrganizeMove {
+  download_id: string
+  from: string
+  to: string
+}
+
+export interface ReorganizeResult {
+  dry_run: boolean
+  moves: OrganizeMove[]
+  unchanged: number
+  failed: string[]
+}
+
+export interface UpgradeCandidate {
+  download_id: string
+  media_id: string
+  episode_id: string
+  episode_number: number
+  quality: string | null
+  source_label: string | null
+  preferred_quality: string
+}
+
+/**
+ * List completed downloads below the preferred quality
+ * (defaults to the 'preferred_quality' app setting)
+ */
+export async function findUpgradeableDownloads(preferredQuality?: string): Promise<UpgradeCandidate[]> {
+  return await invoke('find_upgradeable_downloads', { preferredQuality })
+}
+
+/**
+ * Re-download an episode from a better source; the old file is replaced once the new one completes
+ * @returns The upgrade download's ID
+ */
+export async function upgradeDownload(
+  downloadId: string,
+  url: string,
+  filename: string,
+  quality?: string,
+  sourceLabel?: string,
+  headers?: Record<string, string>
+): Promise<string> {
+  return await invoke('upgrade_download', { downloadId, url, filename, quality, sourceLabel, headers })
+}
+
+/**
+ * Move completed downloads into the folder template
+ * (app settings 'organize_downloads' and 'download_folder_template').
+ * With dryRun, only returns the planned moves.
+ */
+export async function reorganizeExistingDownloads(dryRun: boolean): Promise<ReorganizeResult> {
+  return await invoke('reorganize_existing_downloads', { dryRun })
+}
+
+export interface WatchFolderSettings {
+  enabled: boolean
+  path: string | null
+  /** Filename regexes with (?P<title>), (?P<episode>) and optional (?P<season>) groups; empty uses the built-in ones */
+  patterns: string[]
+  /** Search Jikan for titles that aren't in the library */
+  jikan_lookup: boolean
+}
+
+export interface ImportedEpisode {
+  file_name: string
+  media_id: string
+  media_title: string
+  episode_number: number
+}
+
+export interface WatchScanResult {
+  imported: ImportedEpisode[]
+  unmatched: string[]
+  skipped: number
 }
 
-/**
- * Remove a download from the list (doesn't delete file)
- * @param downloadId - Download ID to remove
- */
-export async function removeDownload(downloadId: string): Promise<void> {
-  return await invoke('remove_download', { downloadId })
+export async function getWatchFolderSettings(): Promise<WatchFolderSettings> {
+  return await invoke('get_watch_folder_settings')
 }
 
-/**
- * Delete a downloaded file
- * @param downloadId - Download ID to delete
- */
-export async function deleteDownload(downloadId: string): Promise<void> {
-  return await invoke('delete_download', { downloadId })
+export async function setWatchFolderSettings(settings: WatchFolderSettings): Promise<void> {
+  return await invoke('set_watch_folder_settings', { settings })
 }
 
 /**
- * Delete a downloaded episode by media ID and episode number
- * @param mediaId - Media ID
- * @param episodeNumber - Episode number
+ * Scan the watch folder now. Files are only adopted once their size is
+ * unchanged since the previous scan, so a freshly copied file needs two scans.
  */
-export async function deleteEpisodeDownload(mediaId: string, episodeNumber: number): Promise<void> {
-  return await invoke('delete_episode_download', { mediaId, episodeNumber })
+export async function scanWatchFolder(): Promise<WatchScanResult> {
+  return await invoke('scan_watch_folder')
 }
 
-/**
- * Clear completed downloads from list
- */
-export async function clearCompletedDownloads(): Promise<void> {
-  return await invoke('clear_completed_downloads')
+export interface AutoDeleteSettings {
+  /** Delete downloads of episodes the current profile has watched */
+  enabled: boolean
+  /** Most recently watched episodes of each series to keep */
+  keep_last: number
+  /** Hours after watching before an episode is deleted */
+  grace_hours: number
 }
 
-/**
- * Clear failed downloads from list
- */
-export async function clearFailedDownloads(): Promise<void> {
-  return await invoke('clear_failed_downloads')
+export interface AutoDeletedEpisode {
+  media_id: string
+  title: string
+  episode_number: number
+  bytes: number
+}
+
+export async function getAutoDeleteSettings(): Promise<AutoDeleteSettings> {
+  return await invoke('get_auto_delete_settings')
+}
+
+export async function setAutoDeleteSettings(settings: AutoDeleteSettings): Promise<void> {
+  return await invoke('set_auto_delete_settings', { settings })
 }
 
 /**
- * Clear cancelled downloads from list
+ * Delete watched episodes that are due now instead of waiting for the next
+ * background sweep. Favorite series are never touched.
  */
-export async function clearCancelledDownloads(): Promise<void> {
-  return await invoke('clear_cancelled_downloads')
+export async function sweepWatchedDownloads(): Promise<AutoDeletedEpisode[]> {
+  return await invoke('sweep_watched_downloads')
 }
 
 // Download types
@@ -697,6 +1759,8 @@ export interface DownloadProgress {
   media_id: string
   episode_id: string
   episode_number: number
+  /** Series title (null for downloads queued before titles were stored) */
+  media_title?: string | null
   filename: string
   url: string
   file_path: string
@@ -704,9 +1768,35 @@ export interface DownloadProgress {
   downloaded_bytes: number
   percentage: number
   speed: number
-  status: 'queued' | 'downloading' | 'paused' | 'completed' | 'failed' | 'cancelled'
+  /** Seconds left at the current speed; null when the size is unknown or it isn't running */
+  eta_seconds?: number | null
+  status: 'queued' | 'downloading' | 'paused' | 'completed' | 'failed' | 'cancelled' | 'offline'
   error_message?: string
-}
+  /** Automatic retries since the download last made progress */
+  retry_count?: number
+  /** HLS downloads: segments done out of the total (bytes total isn't known until the end) */
+  segments?: { completed: number; total: number }
+  archived?: boolean
+  quality?: string | null
+  source_label?: string | null
+  replaces_download_id?: string
+  /** Shared by episodes queued together */
+  batch_id?: string
+  /** Set while the source is still to be fetched from this extension */
+  source_extension_id?: string
+  /** Where the completed file is now; status stays 'completed' when it goes missing */
+  file_state?: DownloadFileState
+  /** Unix timestamp (ms) before which it won't start; replaces the off-peak window */
+  scheduled_start?: number | null
+  /** Set by startDownloadNow: ignores the schedule and the off-peak window */
+  start_now?: boolean
+  /** This download's own limit in bytes per second, on top of the global one */
+  speed_limit?: number | null
+  /** Queued while all downloads are paused; starts on resumeAllDownloads */
+  paused_globally?: boolean
+}
+
+export type DownloadFileState = 'present' | 'missing' | 'corrupt' | 'trashed' | 'archived'
 
 // ==================== Watch History Commands ====================
 
@@ -722,8 +1812,17 @@ export interface WatchHistory {
   created_at: string
 }
 
+export interface WatchProgressSaved {
+  completed: boolean
+  newly_completed: boolean // just went from unwatched to watched
+}
+
 /**
- * Save or update watch progress for an episode
+ * Save or update watch progress for an episode.
+ * Pass `completed` only to explicitly mark watched/unwatched; otherwise the
+ * backend decides from the completion threshold setting.
+ * Pass the player's `sessionToken` (see startPlaybackSession); saves from a
+ * superseded session are rejected with a PlaybackSessionError.
  */
 export async function saveWatchProgress(
   mediaId: string,
@@ -731,15 +1830,17 @@ export async function saveWatchProgress(
   episodeNumber: number,
   progressSeconds: number,
   duration?: number,
-  completed: boolean = false
-): Promise<void> {
+  completed?: boolean,
+  sessionToken?: string
+): Promise<WatchProgressSaved> {
   return await invoke('save_watch_progress', {
     mediaId,
     episodeId,
     episodeNumber,
     progressSeconds,
     duration,
-    completed,
+    completed: completed ?? null,
+    sessionToken,
   })
 }
 
@@ -750,6 +1851,57 @@ export async function getWatchProgress(episodeId: string): Promise<WatchHistory
   return await invoke('get_watch_progress', { episodeId })
 }
 
+/**
+ * Report the player's current position. Cheap: kept in memory and flushed to a
+ * crash-recovery file, so getWatchProgress can offer it after a crash.
+ */
+export async function reportPlaybackHeartbeat(
+  mediaId: string,
+  episodeId: string,
+  position: number,
+  sessionToken?: string
+): Promise<void> {
+  return await invoke('report_playback_heartbeat', { mediaId, episodeId, position, sessionToken })
+}
+
+export interface PlaybackSession {
+  token: string
+  media_id: string
+  episode_id: string
+  started_at: number // unix ms
+  last_seen: number // unix ms
+}
+
+/** Error returned by saveWatchProgress / reportPlaybackHeartbeat */
+export interface PlaybackSessionError {
+  kind: 'stale_session' | 'unknown_session' | 'wrong_episode' | 'missing_token' | 'storage'
+  detail?: string
+}
+
+/**
+ * Start a playback session for an episode. Any other window's session for the
+ * same episode is superseded, and its progress saves fail with 'stale_session'.
+ * Sessions expire after ~2 minutes without a heartbeat or save
+ * ('unknown_session'); start a new one then.
+ */
+export async function startPlaybackSession(mediaId: string, episodeId: string): Promise<PlaybackSession> {
+  return await invoke('start_playback_session', { mediaId, episodeId })
+}
+
+/**
+ * End a playback session when the player closes
+ */
+export async function endPlaybackSession(sessionToken: string): Promise<void> {
+  return await invoke('end_playback_session', { sessionToken })
+}
+
+/**
+ * Active playback sessions (debugging)
+ */
+export async function getActivePlaybackSessions(): Promise<PlaybackSession[]> {
+  return await invoke('get_active_playback_sessions')
+}
+
 /**
  * Get watch progress for all episodes of a media (batch).
  * Returns all watch history entries for the given media in one query.
@@ -780,6 +1932,39 @@ export async function removeFromContinueWatching(mediaId: string): Promise<void>
   return await invoke('remove_from_continue_watching', { mediaId })
 }
 
+/**
+ * Delete the watch history of a single episode
+ * @param episodeId - The episode ID
+ * @returns The deleted rows; pass them to restoreWatchHistory to undo
+ */
+export async function deleteEpisodeWatchHistory(episodeId: string): Promise<WatchHistory[]> {
+  return await invoke('delete_episode_watch_history', { episodeId })
+}
+
+/**
+ * Delete the watch history of a range of episodes (inclusive)
+ * @param mediaId - The media ID
+ * @param fromEpisode - First episode number
+ * @param toEpisode - Last episode number
+ * @returns The deleted rows; pass them to restoreWatchHistory to undo
+ */
+export async function deleteWatchHistoryRange(
+  mediaId: string,
+  fromEpisode: number,
+  toEpisode: number
+): Promise<WatchHistory[]> {
+  return await invoke('delete_watch_history_range', { mediaId, fromEpisode, toEpisode })
+}
+
+/**
+ * Undo a watch history deletion. Episodes watched again since keep their newer progress.
+ * @param entries - Rows returned by a delete call
+ * @returns Number of rows restored
+ */
+export async function restoreWatchHistory(entries: WatchHistory[]): Promise<number> {
+  return await invoke('restore_watch_history', { entries })
+}
+
 // ==================== Reading History Commands ====================
 
 export interface ReadingHistory {
@@ -815,6 +2000,64 @@ export async function saveReadingProgress(
   })
 }
 
+/**
+ * What a finished reading session contributed to the speed averages
+ */
+export interface ReadingSessionSummary {
+  media_id: string
+  chapter_id: string
+  pages_timed: number
+  seconds: number
+  seconds_per_page: number | null
+}
+
+/**
+ * Estimated time left in a chapter. `source` says which average was used:
+ * this title's, the global one, or a default before there's enough data.
+ */
+export interface ReadingTimeEstimate {
+  remaining_pages: number
+  seconds_per_page: number
+  source: 'media' | 'global' | 'default'
+  estimated_minutes: number
+}
+
+/**
+ * Start timing page turns for a chapter
+ * @returns Session id for recordReadingPageTurn/endReadingSession
+ */
+export async function startReadingSession(
+  mediaId: string,
+  chapterId: string,
+  page: number
+): Promise<number> {
+  return await invoke('start_reading_session', { mediaId, chapterId, page })
+}
+
+/**
+ * Report a page turn in a reading session
+ */
+export async function recordReadingPageTurn(sessionId: number, page: number): Promise<boolean> {
+  return await invoke('record_reading_page_turn', { sessionId, page })
+}
+
+/**
+ * End a reading session, saving its timings
+ */
+export async function endReadingSession(sessionId: number): Promise<ReadingSessionSummary | null> {
+  return await invoke('end_reading_session', { sessionId })
+}
+
+/**
+ * Estimate minutes left in a chapter (null until its progress has been saved)
+ */
+export async function getReadingTimeEstimate(
+  mediaId: string,
+  chapterId: string
+): Promise<ReadingTimeEstimate | null> {
+  return await invoke('get_reading_time_estimate', { mediaId, chapterId })
+}
+
 /**
  * Get reading progress for a specific chapter
  */
@@ -851,6 +2094,39 @@ export async function removeFromContinueReadingManga(mediaId: string): Promise<v
   return await invoke('remove_from_continue_reading_manga', { mediaId })
 }
 
+/**
+ * Delete the reading history of a single chapter
+ * @param chapterId - The chapter ID
+ * @returns The deleted rows; pass them to restoreReadingHistory to undo
+ */
+export async function deleteChapterReadingHistory(chapterId: string): Promise<ReadingHistory[]> {
+  return await invoke('delete_chapter_reading_history', { chapterId })
+}
+
+/**
+ * Delete the reading history of a range of chapters (inclusive)
+ * @param mediaId - The manga ID
+ * @param fromChapter - First chapter number
+ * @param toChapter - Last chapter number
+ * @returns The deleted rows; pass them to restoreReadingHistory to undo
+ */
+export async function deleteReadingHistoryRange(
+  mediaId: string,
+  fromChapter: number,
+  toChapter: number
+): Promise<ReadingHistory[]> {
+  return await invoke('delete_reading_history_range', { mediaId, fromChapter, toChapter })
+}
+
+/**
+ * Undo a reading history deletion. Chapters read again since keep their newer progress.
+ * @param entries - Rows returned by a delete call
+ * @returns Number of rows restored
+ */
+export async function restoreReadingHistory(entries: ReadingHistory[]): Promise<number> {
+  return await invoke('restore_reading_history', { entries })
+}
+
 // ==================== Library Commands ====================
 
 export type LibraryStatus = 'watching' | 'completed' | 'on_hold' | 'dropped' | 'plan_to_watch' | 'reading' | 'plan_to_read'
@@ -875,14 +2151,26 @@ export interface LibraryEntryWithMedia {
   media: MediaEntry
 }
 
+/**
+ * One page of library entries (newest update first)
+ */
+export interface LibraryPage {
+  entries: LibraryEntryWithMedia[]
+  /** Pass to the next call to continue; null on the last page */
+  next_cursor: string | null
+}
+
 /**
  * Add media to library
+ * @param trackReleases - Opt in/out of new release checks. Omit to let the
+ * backend decide (only ongoing titles or unknown statuses are tracked).
  */
 export async function addToLibrary(
   mediaId: string,
-  status: LibraryStatus = 'plan_to_watch'
+  status: LibraryStatus = 'plan_to_watch',
+  trackReleases?: boolean
 ): Promise<LibraryEntry> {
-  return await invoke('add_to_library', { mediaId, status })
+  return await invoke('add_to_library', { mediaId, status, trackReleases: trackReleases ?? null })
 }
 
 /**
@@ -913,6 +2201,36 @@ export async function getLibraryWithMedia(status?: LibraryStatus): Promise<Libra
   return await invoke('get_library_with_media', { status: status || null })
 }
 
+/**
+ * Library page order. Entries without the data sort last.
+ * - next_airing_at: soonest first (estimated from the last release of ongoing series)
+ * - unwatched_count: most episodes left first
+ * - last_release_at: latest release first
+ */
+export type LibrarySort = 'updated_at' | 'next_airing_at' | 'unwatched_count' | 'last_release_at'
+
+/**
+ * Get a page of library entries with media details.
+ * Use the returned cursor for the next page; it stays stable when entries are
+ * added mid-scroll. `offset` only applies when no cursor is passed. Sorts
+ * other than `updated_at` return no cursor and page by `offset`.
+ */
+export async function getLibraryWithMediaPage(options: {
+  status?: LibraryStatus
+  sort?: LibrarySort
+  limit?: number
+  cursor?: string | null
+  offset?: number
+} = {}): Promise<LibraryPage> {
+  return await invoke('get_library_with_media_page', {
+    status: options.status || null,
+    sort: options.sort ?? null,
+    limit: options.limit ?? null,
+    cursor: options.cursor ?? null,
+    offset: options.offset ?? null,
+  })
+}
+
 /**
  * Toggle favorite status
  */
@@ -934,6 +2252,47 @@ export async function isInLibrary(mediaId: string): Promise<boolean> {
   return await invoke('is_in_library', { mediaId })
 }
 
+// ==================== Genre Normalization ====================
+
+export interface GenreMapping {
+  /** Raw genre key: lowercase, letters and digits only ("Sci-Fi" → "scifi") */
+  raw_key: string
+  canonical: string
+  /** Shipped with the app rather than added by the user */
+  is_default: boolean
+}
+
+/**
+ * List genre mappings (raw genre → canonical genre)
+ */
+export async function getGenreMappings(): Promise<GenreMapping[]> {
+  return await invoke('get_genre_mappings')
+}
+
+/**
+ * Map a raw genre name to a canonical genre. Applies to newly saved media;
+ * call renormalizeMediaGenres to update stored media.
+ */
+export async function setGenreMapping(raw: string, canonical: string): Promise<GenreMapping> {
+  return await invoke('set_genre_mapping', { raw, canonical })
+}
+
+/**
+ * Remove the mapping for a raw genre name
+ * @returns Whether a mapping existed
+ */
+export async function deleteGenreMapping(raw: string): Promise<boolean> {
+  return await invoke('delete_genre_mapping', { raw })
+}
+
+/**
+ * Re-apply genre mappings to all stored media
+ * @returns Number of media whose genres changed
+ */
+export async function renormalizeMediaGenres(): Promise<number> {
+  return await invoke('renormalize_media_genres')
+}
+
 // ==================== Library Tag Commands ====================
 
 export interface LibraryTag {
@@ -1071,6 +2430,43 @@ export async function bulkRemoveFromLibrary(mediaIds: string[]): Promise<void> {
   return await invoke('bulk_remove_from_library', { mediaIds })
 }
 
+/** A watching/reading entry without recent watch or reading history */
+export interface StaleLibraryEntry {
+  media_id: string
+  title: string
+  cover_url: string | null
+  media_type: string
+  status: 'watching' | 'reading'
+  /** Latest watch/read (UTC), or when the entry was added if there's none */
+  last_activity: string
+  days_inactive: number
+  /** Episodes watched or chapters read to the end */
+  progress: number
+  /** Episode or chapter count, when known */
+  total: number | null
+}
+
+/**
+ * Watching/reading entries untouched for a while ("drop suggestions").
+ * Longest inactive first. The monthly reminder is the
+ * 'stale_library_reminder' app setting.
+ * @param thresholdDays - Days without history; defaults to the
+ *   'stale_library_threshold_days' app setting (90)
+ */
+export async function getStaleLibraryEntries(thresholdDays?: number): Promise<StaleLibraryEntry[]> {
+  return await invoke('get_stale_library_entries', { thresholdDays })
+}
+
+/**
+ * Move selected stale entries to on hold or dropped
+ */
+export async function moveStaleLibraryEntries(
+  mediaIds: string[],
+  status: 'on_hold' | 'dropped'
+): Promise<void> {
+  return await invoke('move_stale_library_entries', { mediaIds, status })
+}
+
 // ==================== Media Commands ====================
 
 export interface MediaEntry {
@@ -1322,11 +2718,30 @@ export interface RatingComparisonEntry {
   difference: number
 }
 
+/** What saveMediaDetails did to the stored media */
+export interface MediaSaveReport {
+  /** Whether the media wasn't stored before */
+  created: boolean
+  /** Fields the save changed */
+  updated: string[]
+  /** Stored fields kept because the save had no value for them, or a smaller episode count */
+  preserved: string[]
+}
+
+/**
+ * Save media details to database. Only fields with a value are written, so a
+ * sparse entry doesn't erase what a richer one stored.
+ */
+export async function saveMediaDetails(media: MediaEntry, ageRating?: string): Promise<MediaSaveReport> {
+  return await invoke('save_media_details', { media, ageRating })
+}
+
 /**
- * Save media details to database
+ * Empty fields of a stored media (saving never removes a value). Cleared
+ * fields count as set by the user, so enrichment won't fill them again.
  */
-export async function saveMediaDetails(media: MediaEntry): Promise<void> {
-  return await invoke('save_media_details', { media })
+export async function clearMediaFields(mediaId: string, fields: string[]): Promise<void> {
+  return await invoke('clear_media_fields', { mediaId, fields })
 }
 
 /** Episode entry for caching */
@@ -1394,6 +2809,9 @@ export interface DownloadWithMedia {
   cover_url?: string
   episode_count: number
   total_size: number
+  qualities: string[]
+  /** No media row for media_id: title is a placeholder, there's no cover */
+  media_missing: boolean
 }
 
 /**
@@ -1403,6 +2821,71 @@ export async function getDownloadsWithMedia(): Promise<DownloadWithMedia[]> {
   return await invoke('get_downloads_with_media')
 }
 
+export interface MediaLinkRepair {
+  /** Media ids downloads pointed at without a media row */
+  orphaned: number
+  /** Rows recreated from the extension's or Jikan's details */
+  fetched: number
+  /** Rows recreated from the title the downloads stored ("Unknown series" if none) */
+  placeholders: number
+  /** Details fetches that failed (those got a placeholder) */
+  fetch_failures: number
+}
+
+/**
+ * Recreate missing media rows of downloaded series (after clearing data or
+ * a failed migration) so they show up with a title and cover again
+ */
+export async function repairDownloadMediaLinks(): Promise<MediaLinkRepair> {
+  return await invoke('repair_download_media_links')
+}
+
+export type ReconcileAction = 'import' | 'delete'
+
+/** A file in the downloads directory no download points at */
+export interface OrphanFile {
+  path: string
+  size: number
+  /** Series title and episode parsed from the name, when it could be */
+  title: string | null
+  episode_number: number | null
+  /** Media the title matched */
+  media_id: string | null
+}
+
+/** A completed download whose file isn't there */
+export interface MissingFile {
+  download_id: string
+  media_id: string
+  episode_number: number
+  filename: string
+  file_path: string
+  /** On archive storage, which may just not be mounted */
+  archived: boolean
+}
+
+export interface DownloadDirectoryScan {
+  orphan_files: OrphanFile[]
+  /** Total size of orphan_files */
+  orphan_bytes: number
+  missing_files: MissingFile[]
+  reconcile: ReconcileAction | null
+  /** Paths adopted as downloads */
+  imported: string[]
+  /** Paths deleted */
+  deleted: string[]
+  /** Orphans the reconcile left alone ("path: reason") */
+  failed: string[]
+}
+
+/**
+ * Compare the downloads directory with the downloads list
+ * @param reconcile - Import orphaned files as completed downloads, or delete them
+ */
+export async function scanDownloadsDirectory(reconcile?: ReconcileAction): Promise<DownloadDirectoryScan> {
+  return await invoke('scan_downloads_directory', { reconcile: reconcile ?? null })
+}
+
 // ==================== Video Server Commands ====================
 
 export interface VideoServerUrls {
@@ -1420,6 +2903,14 @@ export async function getVideoServerInfo(): Promise<VideoServerUrls> {
   return await invoke('get_video_server_info')
 }
 
+/**
+ * Start the video server again after it failed to bind a port
+ * (uses the fixed port from the `video_server_port` setting when set)
+ */
+export async function retryVideoServer(): Promise<VideoServerUrls> {
+  return await invoke('retry_video_server')
+}
+
 /**
  * Get streaming URL for a local downloaded file
  * Uses the embedded HTTP server for proper Range request support
@@ -1483,6 +2974,74 @@ export async function getSystemStats(): Promise<SystemStats> {
   return await invoke('get_system_stats')
 }
 
+// ==================== Network Diagnostics ====================
+
+export interface HostReport {
+  host: string
+  dns_ms: number | null
+  addresses: string[]
+  tcp_connect_ms: number | null
+  error: string | null
+}
+
+export interface UrlReport {
+  /** "test" or "source" */
+  label: string
+  /** URL without its query string */
+  url: string
+  host: HostReport
+  /** TCP+TLS setup time */
+  connection_setup_ms: number | null
+  ttfb_ms: number | null
+  status: number | null
+  bytes_sampled: number
+  sample_ms: number
+  throughput_bytes_per_sec: number | null
+  error: string | null
+}
+
+export interface NetworkDiagnostics {
+  hosts: HostReport[]
+  targets: UrlReport[]
+  proxy: {
+    active: boolean
+    /** [variable, value] pairs, credentials removed */
+    variables: [string, string][]
+  }
+  /** Hosts that answered 429/503 this run */
+  retries: HostRetryStats[]
+  started_at: number
+  duration_ms: number
+  /** The results in a few readable lines, formatted for the locale setting */
+  summary: string[]
+}
+
+/**
+ * Run connectivity diagnostics (DNS, connect, TTFB, throughput) against the
+ * test URL and the source hosts. Takes up to ~30 seconds.
+ * @param testUrl - URL for the speed test (defaults to the network_test_url setting)
+ * @param sourceUrl - Source URL to test (defaults to the most recent download)
+ */
+export async function runNetworkDiagnostics(testUrl?: string, sourceUrl?: string): Promise<NetworkDiagnostics> {
+  return await invoke('run_network_diagnostics', { testUrl, sourceUrl })
+}
+
+export interface HostRetryStats {
+  host: string
+  /** Requests retried after a 429/503 */
+  retries: number
+  /** Requests still rate limited when their retries ran out */
+  gave_up: number
+  last_at: number
+}
+
+/**
+ * Rate-limit (429/503) retries by host since the app started
+ */
+export async function getHttpRetryStats(): Promise<HostRetryStats[]> {
+  return await invoke('get_http_retry_stats')
+}
+
 // ==================== Log Commands ====================
 
 export interface LogEntry {
@@ -1508,26 +3067,105 @@ export async function clearAppLogs(): Promise<void> {
 }
 
 /**
- * Get log file path
- * @returns Path to the log file
+ * Get log file path
+ * @returns Path to the log file
+ */
+export async function getLogFilePath(): Promise<string> {
+  return await invoke('get_log_file_path')
+}
+
+// ==================== Streaming Commands ====================
+
+/** Event name for system stats stream */
+export const SYSTEM_STATS_EVENT = 'system-stats'
+
+/** Event name for app logs stream */
+export const APP_LOGS_EVENT = 'app-logs'
+
+/**
+ * Start streaming system stats via events. Emits every `intervalSecs`
+ * (1, 5 or 30; default 1). Calling it while streaming changes the interval.
+ */
+export async function startStatsStream(intervalSecs?: 1 | 5 | 30): Promise<void> {
+  return await invoke('start_stats_stream', { intervalSecs })
+}
+
+export type StatsMetric =
+  | 'memory_used'
+  | 'memory_percent'
+  | 'cpu_usage'
+  | 'process_memory'
+  | 'process_cpu'
+  | 'disk_used'
+  | 'disk_percent'
+
+export interface StatsPoint {
+  /** Unix timestamp (ms) at the start of the minute */
+  at: number
+  value: number
+}
+
+export interface StatsHistory {
+  metric: StatsMetric
+  period_secs: number
+  /** Per-minute averages, oldest first */
+  points: StatsPoint[]
+}
+
+/**
+ * Get per-minute averages of a stat over a period such as '30m', '1h' or
+ * '48h' (history is kept for 48 hours)
  */
-export async function getLogFilePath(): Promise<string> {
-  return await invoke('get_log_file_path')
+export async function getStatsHistory(metric: StatsMetric, period: string): Promise<StatsHistory> {
+  return await invoke('get_stats_history', { metric, period })
 }
 
-// ==================== Streaming Commands ====================
+// ==================== Maintenance ====================
 
-/** Event name for system stats stream */
-export const SYSTEM_STATS_EVENT = 'system-stats'
+export type MaintenanceChore =
+  | 'expired_cache'
+  | 'media_cache'
+  | 'stats_history'
+  | 'optimize_database'
+  | 'verify_downloads'
 
-/** Event name for app logs stream */
-export const APP_LOGS_EVENT = 'app-logs'
+export interface ChoreReport {
+  chore: MaintenanceChore
+  /** Whether the chore runs on its own; verify_downloads is off by default */
+  enabled: boolean
+  interval_secs: number
+  /** Unix timestamp (ms), null if the chore never ran */
+  last_run: number | null
+  duration_ms: number | null
+  last_result: string | null
+  /** Set when the last run failed */
+  last_error: string | null
+  /** Unix timestamp (ms) the chore is due (again) */
+  next_due: number
+}
+
+export interface MaintenanceReport {
+  /** No commands, playback or downloads for 10 minutes */
+  idle: boolean
+  idle_for_secs: number
+  active_downloads: number
+  running: boolean
+  chores: ChoreReport[]
+}
 
 /**
- * Start streaming system stats via events (emits every second)
+ * Whether the app is idle and when each maintenance chore last ran. Chores
+ * run in the background only while the app is idle.
  */
-export async function startStatsStream(): Promise<void> {
-  return await invoke('start_stats_stream')
+export async function getMaintenanceReport(): Promise<MaintenanceReport> {
+  return await invoke('get_maintenance_report')
+}
+
+/**
+ * Run one maintenance chore, or every chore that's on, right away
+ */
+export async function runMaintenanceNow(chore?: MaintenanceChore): Promise<ChoreReport[]> {
+  return await invoke('run_maintenance_now', { chore })
 }
 
 /**
@@ -1618,12 +3256,12 @@ export async function isChapterDownloaded(
  * Get downloaded chapter images (local paths)
  * @param mediaId - Media ID
  * @param chapterId - Chapter ID
- * @returns Array of local file paths for the chapter images
+ * @returns Chapter pages whose url is the local file path, with page dimensions
  */
 export async function getDownloadedChapterImages(
   mediaId: string,
   chapterId: string
-): Promise<string[]> {
+): Promise<ChapterImage[]> {
   return await invoke('get_downloaded_chapter_images', { mediaId, chapterId })
 }
 
@@ -1839,6 +3477,24 @@ export async function getUnreadNotificationCount(): Promise<number> {
   return await invoke('get_unread_notification_count')
 }
 
+/** Counts behind the sidebar badges (also the badge-summary-changed event) */
+export interface BadgeSummary {
+  unread_notifications: number
+  /** Media showing the NEW badge */
+  new_releases: number
+  /** Episode and chapter downloads in progress */
+  active_downloads: number
+  /** Episode and chapter downloads that failed */
+  failed_downloads: number
+}
+
+/**
+ * Get every sidebar badge count in one call
+ */
+export async function getBadgeSummary(): Promise<BadgeSummary> {
+  return await invoke('get_badge_summary')
+}
+
 /**
  * Listen for notification events
  * @param callback - Called when a notification is received
@@ -1891,6 +3547,11 @@ export async function setUpdateCheckInfo(
 // ============================================================================
 
 /** Release check settings (V2 with granular intervals) */
+export type ReleaseDigestMode = 'immediate' | 'hourly' | 'daily'
+
+/** Which source releases are checked against: the tracking source, or the preferred one first */
+export type ReleaseSource = 'tracking' | 'preferred'
+
 export interface ReleaseCheckSettings {
   enabled: boolean
   interval_minutes: number
@@ -1898,6 +3559,14 @@ export interface ReleaseCheckSettings {
   retry_delay_minutes: number
   max_retries: number
   last_full_check: number | null
+  /** How release notifications are delivered */
+  digest_mode: ReleaseDigestMode
+  /** Which source releases are checked against */
+  release_source: ReleaseSource
+  /** Extension ids asked in order in preferred mode, for media without their own preferred source */
+  source_priority: string[]
+  /** In preferred mode, only notify once the episode is available in the preferred source */
+  notify_when_watchable: boolean
   /** @deprecated Use interval_minutes instead */
   interval_hours?: number
 }
@@ -1923,6 +3592,8 @@ export interface ReleaseCheckResult {
   new_releases: number
   extension_id: string
   detection_signal: 'number' | 'id' | 'count'
+  /** Source that confirmed the release (extension id, or 'jikan') */
+  confirmed_by: string
 }
 
 /** Media release state for NEW badges */
@@ -1933,6 +3604,8 @@ export interface MediaReleaseState {
   notified_up_to: number | null
   last_checked: number | null
   normalized_status: 'ongoing' | 'completed' | 'hiatus' | 'unknown'
+  /** A release check for this media is running right now */
+  is_checking: boolean
 }
 
 /** Check log entry for debugging */
@@ -1966,6 +3639,8 @@ export interface TrackingDebugInfo {
   next_scheduled_check: number | null
   consecutive_failures: number
   last_error: string | null
+  /** Set when checks follow a split-cour sequel's MAL entry */
+  split_cour_link: SplitCourLink | null
   recent_logs: CheckLogEntry[]
 }
 
@@ -1982,19 +3657,46 @@ export async function getReleaseCheckSettings(): Promise<ReleaseCheckSettings> {
  * @param enabled - Whether release checking is enabled
  * @param intervalMinutes - Minutes between checks (or use intervalHours for backwards compatibility)
  * @param intervalHours - Hours between checks (legacy, converted to minutes)
+ * @param digestMode - Notify per title immediately, or group into an hourly/daily digest
+ *   (omit to keep the current mode)
+ * @param releaseSource - Check the tracking source, or the preferred source first (omit to keep)
+ * @param sourcePriority - Extension ids asked in order in preferred mode (omit to keep)
+ * @param notifyWhenWatchable - Wait until the preferred source has the episode (omit to keep)
  */
 export async function updateReleaseCheckSettings(
   enabled: boolean,
   intervalMinutes?: number,
-  intervalHours?: number
+  intervalHours?: number,
+  digestMode?: ReleaseDigestMode,
+  releaseSource?: ReleaseSource,
+  sourcePriority?: string[],
+  notifyWhenWatchable?: boolean
 ): Promise<void> {
   return await invoke('update_release_check_settings', {
     enabled,
     intervalMinutes,
     intervalHours,
+    digestMode,
+    releaseSource,
+    sourcePriority,
+    notifyWhenWatchable,
   })
 }
 
+/**
+ * Set the source a media's releases are checked against in preferred mode
+ * @param mediaId - Tracked media ID
+ * @param extensionId - Preferred extension (omit to go back to the source priority)
+ * @param sourceMediaId - The media's ID in that extension (omit to resolve it through ID mappings)
+ */
+export async function setReleaseSource(
+  mediaId: string,
+  extensionId?: string,
+  sourceMediaId?: string
+): Promise<void> {
+  return await invoke('set_release_source', { mediaId, extensionId, sourceMediaId })
+}
+
 /**
  * Manually trigger a release check for all eligible media
  * @returns Array of media items with new releases
@@ -2041,6 +3743,20 @@ export async function initializeReleaseTracking(
   })
 }
 
+/**
+ * Opt a media item into new release checks
+ */
+export async function enableReleaseTracking(mediaId: string): Promise<void> {
+  return await invoke('enable_release_tracking', { mediaId })
+}
+
+/**
+ * Opt a media item out of new release checks (its tracking history is kept)
+ */
+export async function disableReleaseTracking(mediaId: string): Promise<void> {
+  return await invoke('disable_release_tracking', { mediaId })
+}
+
 /**
  * Get release tracking status for multiple media items
  * @param mediaIds - Array of media IDs to check
@@ -2102,6 +3818,36 @@ export async function getReleaseTrackingDebug(
   return await invoke('get_release_tracking_debug', { mediaId })
 }
 
+export type NormalizedStatus = 'ongoing' | 'completed' | 'hiatus' | 'unknown'
+
+export interface LearnedStatus {
+  status_key: string
+  raw_status: string
+  /** null while the user hasn't mapped it */
+  normalized_status: NormalizedStatus | null
+  seen_count: number
+  last_seen_at: string | null
+}
+
+/**
+ * Raw release statuses the app couldn't classify, most seen first
+ * @param includeMapped - Also list statuses already mapped by the user
+ */
+export async function listUnknownStatuses(includeMapped?: boolean): Promise<LearnedStatus[]> {
+  return await invoke('list_unknown_statuses', { includeMapped })
+}
+
+/**
+ * Teach the app what a source's status means; null removes the mapping
+ * @returns How many tracked media changed status
+ */
+export async function setStatusMapping(
+  rawStatus: string,
+  normalizedStatus: NormalizedStatus | null
+): Promise<number> {
+  return await invoke('set_status_mapping', { rawStatus, normalizedStatus })
+}
+
 /**
  * Initialize release tracking with V2 fields
  * Includes episode number, ID, and raw status for better tracking
@@ -2163,6 +3909,20 @@ export async function deleteAppSetting(key: string): Promise<void> {
   return await invoke('delete_app_setting', { key })
 }
 
+export interface LocaleInfo {
+  /** Locale code, saved as the `locale` app setting */
+  code: string
+  /** Name in its own language */
+  name: string
+}
+
+/**
+ * Locales the backend formats notifications, reports and diagnostics in
+ */
+export async function getSupportedLocales(): Promise<LocaleInfo[]> {
+  return await invoke('get_supported_locales')
+}
+
 // ==================== Discover Cache Commands ====================
 
 export interface DiscoverCacheEntry {
@@ -2236,6 +3996,73 @@ export async function saveDiscoverCacheWithTtl(
   return await invoke('save_discover_cache_with_ttl', { cacheKey, data, mediaType, ttlSeconds })
 }
 
+export type CacheName = 'discover' | 'jikan' | 'id_mappings' | 'video_sources'
+
+/**
+ * Clear a single cache, leaving the others intact
+ * @returns Number of entries removed
+ */
+export async function clearCache(name: CacheName): Promise<number> {
+  return await invoke('clear_cache', { name })
+}
+
+export interface CacheWarmupReport {
+  /** Library titles whose details were prefetched on start */
+  warmed: number
+  /** Episodes whose video sources were prefetched on start */
+  sources_warmed: number
+  /** Why warming ended early, e.g. 'user_activity', 'offline' or 'errors' */
+  stopped_reason: string | null
+  finished_at: number | null
+}
+
+export interface CacheStats {
+  caches: { name: CacheName; entries: number }[]
+  warmup: CacheWarmupReport
+}
+
+/**
+ * Entry counts of every cache, plus the startup warm-up report
+ */
+export async function getCacheStats(): Promise<CacheStats> {
+  return await invoke('get_cache_stats')
+}
+
+export type MediaCacheNamespace = 'pages' | 'covers' | 'thumbnails'
+
+export interface MediaCacheUsage {
+  namespaces: { namespace: MediaCacheNamespace; dir: string; size: number; files: number }[]
+  total_size: number
+  /** Cap in bytes; least recently used files are deleted beyond it */
+  max_size: number
+}
+
+export interface MediaCacheCleanup {
+  removed_files: number
+  freed_bytes: number
+}
+
+/**
+ * Disk usage of the media cache (reader pages, covers, thumbnails) per namespace
+ */
+export async function getMediaCacheUsage(): Promise<MediaCacheUsage> {
+  return await invoke('get_media_cache_usage')
+}
+
+/**
+ * Delete the cached files of one namespace, or of all when omitted
+ */
+export async function purgeMediaCache(namespace?: MediaCacheNamespace): Promise<MediaCacheCleanup> {
+  return await invoke('purge_media_cache', { namespace })
+}
+
+/**
+ * Set the media cache cap in megabytes and trim the cache down to it
+ */
+export async function setMediaCacheMaxSize(maxMb: number): Promise<MediaCacheCleanup> {
+  return await invoke('set_media_cache_max_size', { maxMb })
+}
+
 // ==================== Jikan API Commands ====================
 
 /**
@@ -2740,6 +4567,139 @@ export async function clearAllanimeMapping(malId: string): Promise<void> {
   return await invoke('clear_allanime_mapping', { malId })
 }
 
+// ==================== Episode Numbering ====================
+
+/**
+ * Proposed offset between MAL's episode numbers and a source's
+ * (source number = MAL number + offset)
+ */
+export interface OffsetProposal {
+  offset: number
+  /** "first_aired", "first_number", "episode_count" or "aligned" */
+  reason: string
+  canonical_episodes: number
+  source_episodes: number
+}
+
+/**
+ * Compare MAL's episode list with a source's and propose a numbering offset.
+ * Nothing is saved; confirm with setNumberingOffset.
+ * @param mediaId - MAL id
+ * @param extensionId - Source extension
+ * @param sourceMediaId - Source show id (defaults to the cached AllAnime mapping)
+ */
+export async function detectNumberingOffset(
+  mediaId: string,
+  extensionId: string,
+  sourceMediaId?: string,
+): Promise<OffsetProposal> {
+  return await invokeExtension('detect_numbering_offset', { mediaId, extensionId, sourceMediaId })
+}
+
+/**
+ * Stored numbering offset of a MAL entry on a source (0 when unset)
+ */
+export async function getNumberingOffset(mediaId: string, extensionId: string): Promise<number> {
+  return await invoke('get_numbering_offset', { mediaId, extensionId })
+}
+
+/**
+ * Store the numbering offset of a MAL entry on a source; 0 clears it
+ */
+export async function setNumberingOffset(
+  mediaId: string,
+  extensionId: string,
+  offset: number,
+): Promise<void> {
+  return await invoke('set_numbering_offset', { mediaId, extensionId, offset })
+}
+
+// ==================== Split-Cour Links ====================
+
+export interface SplitCourCandidate {
+  media_id: string
+  title: string
+  part_one_mal_id: number
+  sequel_mal_id: number
+  sequel_title: string
+  sequel_status: string | null
+  /** Days between part 1's last and the sequel's first airing */
+  gap_days: number
+  /** Part 1's episode count, added to the sequel's episode numbers */
+  episode_offset: number
+}
+
+export interface SplitCourLink {
+  media_id: string
+  sequel_mal_id: number
+  episode_offset: number
+  linked_at: number
+}
+
+/**
+ * Tracked anime whose finished MAL entry has a split-cour sequel.
+ * Costs a few Jikan requests per entry; nothing is linked.
+ */
+export async function findSplitCourCandidates(): Promise<SplitCourCandidate[]> {
+  return await invoke('find_split_cour_candidates')
+}
+
+/**
+ * Make release tracking of a media follow a split-cour sequel's MAL entry
+ * @param episodeOffset - Part 1's episode count
+ */
+export async function linkSplitCour(
+  mediaId: string,
+  sequelMalId: number,
+  episodeOffset: number,
+): Promise<SplitCourLink> {
+  return await invoke('link_split_cour', { mediaId, sequelMalId, episodeOffset })
+}
+
+/**
+ * Remove a media's split-cour link (false when it had none)
+ */
+export async function unlinkSplitCour(mediaId: string): Promise<boolean> {
+  return await invoke('unlink_split_cour', { mediaId })
+}
+
+// ==================== Metadata Enrichment ====================
+
+export interface EnrichmentResult {
+  media_id: string
+  mal_id: string | null
+  fields_filled: string[]
+  skipped_reason: string | null
+}
+
+export interface FieldProvenance {
+  field: string
+  has_value: boolean
+  source: string | null
+}
+
+export interface MediaProvenance {
+  media_id: string
+  extension_id: string
+  mal_id: string | null
+  enriched_at: string | null
+  fields: FieldProvenance[]
+}
+
+/**
+ * Fill missing metadata for an anime from its MAL entry (never overwrites present values)
+ */
+export async function enrichMediaFromJikan(mediaId: string): Promise<EnrichmentResult> {
+  return await invoke('enrich_media_from_jikan', { mediaId })
+}
+
+/**
+ * Debug: where each metadata field of a media entry came from
+ */
+export async function getMediaProvenance(mediaId: string): Promise<MediaProvenance> {
+  return await invoke('get_media_provenance', { mediaId })
+}
+
 // ==================== Migration (AllAnime → Jikan) ====================
 
 export interface MigrationProgress {
@@ -2774,6 +4734,153 @@ export async function getMigrationProgress(): Promise<MigrationProgress> {
   return await invoke('get_migration_progress')
 }
 
+// ==================== Safe Mode ====================
+
+/** Counts of what an import brought in (also returned by a backup restore) */
+export interface ImportResult {
+  success: boolean
+  library_imported: number
+  library_skipped: number
+  watch_history_imported: number
+  watch_history_skipped: number
+  reading_history_imported: number
+  reading_history_skipped: number
+  tags_imported: number
+  tags_skipped: number
+  tag_assignments_imported: number
+  settings_imported: number
+  media_cache_imported: number
+  tracker_mappings_imported: number
+  id_mappings_imported: number
+  id_mappings_skipped: number
+  migration_archive_imported: number
+  migration_archive_skipped: number
+  hidden_media_imported: number
+  extensions_imported: number
+  extensions_skipped: number
+  /** Extensions the import left disabled */
+  extensions_disabled: string[]
+  warnings: string[]
+}
+
+
+/** How the database was opened; in_memory means nothing is saved */
+export type SafeModeDatabase = 'normal' | 'read_only' | 'in_memory'
+
+export interface SafeModeState {
+  active: boolean
+  /** Startups in a row that didn't finish */
+  failed_startups: number
+  /** What safe mode left out, readable */
+  skipped: string[]
+  database: SafeModeDatabase
+  database_error: string | null
+}
+
+/**
+ * Whether the app started in safe mode (after two failed startups in a row)
+ */
+export async function getSafeModeState(): Promise<SafeModeState> {
+  return await invoke('get_safe_mode_state')
+}
+
+/**
+ * Run SQLite's integrity check; returns the problems found, or ["ok"]
+ */
+export async function runDatabaseIntegrityCheck(): Promise<string[]> {
+  return await invoke('run_database_integrity_check')
+}
+
+/**
+ * Replace the database with a backup file (safe mode only).
+ * The app has to be restarted afterwards.
+ */
+export async function restoreDatabaseBackup(filePath: string): Promise<ImportResult> {
+  return await invoke('restore_database_backup', { filePath })
+}
+
+/**
+ * Uninstall every extension and delete their directory (safe mode only).
+ * Returns how many were removed.
+ */
+export async function clearInstalledExtensions(): Promise<number> {
+  return await invoke('clear_installed_extensions')
+}
+
+/**
+ * Start normally on the next launch
+ */
+export async function leaveSafeMode(): Promise<void> {
+  return await invoke('leave_safe_mode')
+}
+
+export interface CoverRefreshProgress {
+  total: number
+  processed: number
+  fixed: number
+  failed: number
+  current_title: string
+  status: string // "running" | "completed"
+}
+
+/**
+ * Replace dead AllAnime-CDN (or 404ing) anime covers with their MAL images.
+ * Resumes an interrupted run unless restart is set.
+ * Emits "cover_refresh_progress" events as it runs.
+ */
+export async function refreshCoverUrls(restart?: boolean): Promise<CoverRefreshProgress> {
+  return await invoke('refresh_cover_urls', { restart })
+}
+
+/**
+ * Set a media's cover or banner by hand; the cover refresh and enrichment
+ * leave it alone from then on. Without a url the field is handed back to
+ * the refresh.
+ */
+export async function setMediaImage(
+  mediaId: string,
+  field: 'cover_url' | 'banner_url',
+  url?: string
+): Promise<void> {
+  return await invoke('set_media_image', { mediaId, field, url })
+}
+
+// ==================== Season Pass Commands ====================
+
+/** off: disabled; suggest: notify only; auto_add: add as plan to watch with release tracking */
+export type SeasonPassMode = 'off' | 'suggest' | 'auto_add'
+
+export interface SeasonPassSequel {
+  source_media_id: string
+  source_title: string
+  /** MAL id of the sequel (also its media id once added) */
+  sequel_id: string
+  sequel_title: string
+  airing_status: string | null
+  added: boolean
+}
+
+export interface SeasonPassResult {
+  checked: number
+  sequels: SeasonPassSequel[]
+}
+
+export async function getSeasonPassMode(): Promise<SeasonPassMode> {
+  return await invoke('get_season_pass_mode')
+}
+
+export async function setSeasonPassMode(mode: SeasonPassMode): Promise<void> {
+  return await invoke('set_season_pass_mode', { mode })
+}
+
+/**
+ * Check every completed anime for airing or upcoming sequels now.
+ * Suggests only while the season pass is off.
+ */
+export async function runSeasonPass(): Promise<SeasonPassResult> {
+  return await invoke('run_season_pass')
+}
+
 // ==================== History Commands ====================
 
 export async function getAllHistory(
@@ -2918,6 +5025,32 @@ export async function getRatingComparison(): Promise<RatingComparisonEntry[]> {
   return invoke<RatingComparisonEntry[]>('get_rating_comparison')
 }
 
+// ==================== Library Report ====================
+
+export type LibraryReportFormat = 'markdown' | 'html'
+
+export interface LibraryReportOptions {
+  include_notes?: boolean
+  /** Embed covers from the local cover cache (HTML only) */
+  include_covers?: boolean
+}
+
+export interface LibraryReportSummary {
+  entries: number
+  covers_embedded: number
+}
+
+/**
+ * Write a readable library report grouped by status to `path`
+ */
+export async function exportLibraryReport(
+  path: string,
+  format: LibraryReportFormat,
+  options?: LibraryReportOptions
+): Promise<LibraryReportSummary> {
+  return invoke<LibraryReportSummary>('export_library_report', { path, format, options })
+}
+
 // ==================== Recommendation Types ====================
 
 export interface GenrePreference {
@@ -2980,3 +5113,78 @@ export async function getMediaFeedback(mediaId: string): Promise<MediaFeedback |
 export async function removeMediaFeedback(mediaId: string): Promise<void> {
   return invoke('remove_media_feedback', { mediaId })
 }
+
+export interface HiddenMedia {
+  /** Id the listings show: a media id or an extension's source id */
+  media_id: string
+  extension_id: string | null
+  title: string | null
+  /** Unix timestamp (ms) */
+  hidden_at: number
+  reason: string | null
+}
+
+/**
+ * Hide a result from home, discover, search and recommendations ("not interested")
+ */
+export async function hideMedia(
+  mediaId: string,
+  extensionId?: string,
+  title?: string,
+  reason?: string
+): Promise<void> {
+  return invoke('hide_media', { mediaId, extensionId, title, reason })
+}
+
+/**
+ * Show a hidden result again; false if it wasn't hidden
+ */
+export async function unhideMedia(mediaId: string): Promise<boolean> {
+  return invoke<boolean>('unhide_media', { mediaId })
+}
+
+/**
+ * Every hidden result, most recently hidden first
+ */
+export async function listHiddenMedia(): Promise<HiddenMedia[]> {
+  return invoke<HiddenMedia[]>('list_hidden_media')
+}
+
+/** Age ratings, least restricted first (MAL's scale) */
+export type AgeRating = 'g' | 'pg' | 'pg13' | 'r17' | 'r_plus' | 'rx'
+
+/** A profile's age rating limit */
+export interface AgeRatingLimit {
+  /** Highest rating shown; null shows everything */
+  max_rating: AgeRating | null
+  /** Under a limit, also hide media whose rating isn't known */
+  hide_unrated: boolean
+}
+
+/**
+ * Get a profile's age rating limit (the active profile's by default)
+ */
+export async function getAgeRatingLimit(profileId?: number): Promise<AgeRatingLimit> {
+  return invoke<AgeRatingLimit>('get_age_rating_limit', { profileId })
+}
+
+/**
+ * Set a profile's age rating limit (the active profile's by default). Results
+ * rated above it are left out of search, discover, home, recommendations
+ * and the library.
+ */
+export async function setAgeRatingLimit(
+  maxRating: AgeRating | null,
+  hideUnrated: boolean,
+  profileId?: number
+): Promise<void> {
+  return invoke('set_age_rating_limit', { profileId, maxRating, hideUnrated })
+}
+
+/**
+ * JSON schema of every backend event payload, keyed by event name.
+ * See src/types/events.ts for the matching TypeScript types.
+ */
+export async function generateEventSchema(): Promise<{ events: Record<string, unknown> }> {
+  return invoke('generate_event_schema')
+}